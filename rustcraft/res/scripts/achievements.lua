@@ -0,0 +1,38 @@
+-- Built-in achievements, mods could register their own ones
+-- in the same way. The condition receives the statistics of
+-- the player and returns whether the achievement is unlocked.
+
+achievements.register {
+    id = "first_steps",
+    title = "First Steps",
+    description = "Walk 100 blocks",
+    condition = function(stats) return stats.distance_walked >= 100 end,
+}
+
+achievements.register {
+    id = "explorer",
+    title = "Explorer",
+    description = "Walk 10000 blocks",
+    condition = function(stats) return stats.distance_walked >= 10000 end,
+}
+
+achievements.register {
+    id = "builder",
+    title = "Builder",
+    description = "Place 100 blocks",
+    condition = function(stats) return stats.total_blocks_placed >= 100 end,
+}
+
+achievements.register {
+    id = "miner",
+    title = "Miner",
+    description = "Break 100 blocks",
+    condition = function(stats) return stats.total_blocks_broken >= 100 end,
+}
+
+achievements.register {
+    id = "dedicated",
+    title = "Dedicated",
+    description = "Play for an hour",
+    condition = function(stats) return stats.time_played >= 3600 end,
+}
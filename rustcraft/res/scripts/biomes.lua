@@ -0,0 +1,46 @@
+-- Built-in biomes, mods could redefine them or add their own
+-- in the same way. Of the biomes of the same band, e.g. plains
+-- and deserts, the one whose temperature is closest to the
+-- temperature noise is generated.
+
+biomes.define {
+    name = "ocean",
+    band = "ocean",
+    temperature = 0.5,
+    surface = "sand",
+    tree_density = 0,
+}
+
+biomes.define {
+    name = "plains",
+    band = "lowlands",
+    temperature = 0.4,
+    surface = "grass",
+    tree_density = 0.004,
+}
+
+biomes.define {
+    name = "desert",
+    band = "lowlands",
+    temperature = 0.8,
+    surface = "sand",
+    tree_density = 0,
+}
+
+biomes.define {
+    name = "hills",
+    band = "hills",
+    temperature = 0.4,
+    surface = "grass",
+    tree_density = 0.02,
+}
+
+-- Above the snow line, mountains are bare stone
+-- whatever their surface is
+biomes.define {
+    name = "mountains",
+    band = "mountains",
+    temperature = 0.2,
+    surface = "grass",
+    tree_density = 0.006,
+}
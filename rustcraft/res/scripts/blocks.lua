@@ -0,0 +1,27 @@
+-- Built-in block overlays, mods could define their own ones
+-- in the same way. The overlay tiles of the texture atlas are
+-- grey, so that the tint determines their color. The row 12
+-- of the atlas contains a grass fringe, moss and cracks.
+
+blocks.overlay {
+    block = "grass",
+    side = { 0, 12 },
+    tint = { 0.45, 0.75, 0.3 },
+    opacity = 0.6,
+}
+
+-- Grass spreads to dirt which isn't covered by another block
+-- and lies next to grass, up to one block higher or lower
+blocks.onRandomTick("dirt", function(pos, world)
+    local x, y, z = pos[1], pos[2], pos[3]
+    if world.block({ x, y + 1, z }) ~= "air" then
+        return
+    end
+
+    local dx = math.random(-1, 1)
+    local dy = math.random(-1, 1)
+    local dz = math.random(-1, 1)
+    if world.block({ x + dx, y + dy, z + dz }) == "grass" then
+        world.set_block(pos, "grass")
+    end
+end)
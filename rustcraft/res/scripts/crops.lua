@@ -0,0 +1,36 @@
+-- Built-in crops, mods could define their own ones in the
+-- same way. The sprites of the growth stages of wheat are
+-- laid out in the row 10 of the atlas.
+
+crops.define {
+    block = "wheat",
+    soil = "farmland",
+    stages = 8,
+    growth_chance = 0.35,
+    dry_growth_chance = 0.1,
+    min_light = 9,
+    harvest = { item = "wheat", count = 1 },
+}
+
+-- Farmland is hydrated while there is water next to it on
+-- the same level, within this distance in blocks
+local HYDRATION_DISTANCE = 4
+
+local function near_water(pos, world)
+    local x, y, z = pos[1], pos[2], pos[3]
+    for dx = -HYDRATION_DISTANCE, HYDRATION_DISTANCE do
+        for dz = -HYDRATION_DISTANCE, HYDRATION_DISTANCE do
+            if world.block({ x + dx, y, z + dz }) == "water" then
+                return true
+            end
+        end
+    end
+    return false
+end
+
+blocks.onRandomTick("farmland", function(pos, world)
+    local hydrated = near_water(pos, world) and 1 or 0
+    if world.state(pos) ~= hydrated then
+        world.set_state(pos, hydrated)
+    end
+end)
@@ -0,0 +1,13 @@
+-- Built-in flammable blocks, mods could make their own ones
+-- flammable in the same way. Fire spreads to the air next to
+-- these blocks and burns them away, by chances per fire tick.
+
+fire.flammable { block = "tall_grass", spread = 0.6, burn = 0.5 }
+fire.flammable { block = "flower", spread = 0.6, burn = 0.5 }
+fire.flammable { block = "mushroom", spread = 0.3, burn = 0.3 }
+fire.flammable { block = "wheat", spread = 0.6, burn = 0.5 }
+fire.flammable { block = "bed", spread = 0.3, burn = 0.1 }
+fire.flammable { block = "vine", spread = 0.6, burn = 0.4 }
+fire.flammable { block = "ladder", spread = 0.2, burn = 0.2 }
+fire.flammable { block = "log", spread = 0.1, burn = 0.05 }
+fire.flammable { block = "leaves", spread = 0.6, burn = 0.4 }
@@ -0,0 +1,10 @@
+-- Built-in fluid interactions, mods could add their own ones
+-- in the same way. Lava turns into obsidian where water touches
+-- a source and into stone where water touches flowing lava.
+
+fluids.interaction {
+    fluid = "lava",
+    touching = "water",
+    source = "obsidian",
+    flowing = "stone",
+}
@@ -0,0 +1,19 @@
+-- Built-in loot tables, mods could define their own ones
+-- in the same way. The chests of a structure are filled
+-- from the loot table named after it, once they're broken.
+
+loot.register("dungeon", {
+    rolls = 4,
+    entries = {
+        { item = "cobblestone", weight = 6, min = 2, max = 8 },
+        { item = "wheat", weight = 4, min = 1, max = 5 },
+        { item = "glass", weight = 3, min = 1, max = 4 },
+        { item = "glowstone", weight = 2, min = 1, max = 3 },
+    },
+})
+
+-- Spawners break without dropping themselves
+blocks.drops {
+    block = "spawner",
+    drops = {},
+}
@@ -0,0 +1,65 @@
+-- Built-in recipes, mods could define their own ones in the
+-- same way. The player browses them in the recipe book, which
+-- is opened with R, and crafts them from the items they
+-- collected. Ingredients could be given by a tag of tags.lua.
+-- Smelting recipes and fuels are used by furnaces.
+
+recipes.define {
+    result = "polished_stone",
+    count = 4,
+    ingredients = { cobblestone = 4 },
+}
+
+recipes.define {
+    result = "chest",
+    ingredients = { cobblestone = 8 },
+}
+
+recipes.define {
+    result = "grass",
+    ingredients = { dirt = 1, ["#plants"] = 1 },
+}
+
+recipes.define {
+    result = "furnace",
+    ingredients = { cobblestone = 8, glowstone = 1 },
+}
+
+recipes.define {
+    result = "hopper",
+    ingredients = { cobblestone = 5, chest = 1 },
+}
+
+recipes.define {
+    result = "bed",
+    ingredients = { wheat = 3, flower = 1 },
+}
+
+recipes.define {
+    result = "tnt",
+    ingredients = { sand = 5, glowstone = 1 },
+}
+
+recipes.define {
+    result = "ladder",
+    count = 3,
+    ingredients = { wheat = 7 },
+}
+
+recipes.define {
+    result = "vine",
+    count = 2,
+    ingredients = { ["#plants"] = 3 },
+}
+
+recipes.define {
+    result = "bread",
+    ingredients = { wheat = 3 },
+}
+
+smelting.define { input = "cobblestone", result = "stone", time = 10 }
+smelting.define { input = "sand", result = "glass", time = 10 }
+
+smelting.fuel("#plants", 5)
+smelting.fuel("log", 15)
+smelting.fuel("glowstone", 80)
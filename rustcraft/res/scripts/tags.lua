@@ -0,0 +1,11 @@
+-- Built-in tags of blocks and items, mods could add their own
+-- ones or extend these in the same way. Tool speeds, drop
+-- tables and plants defined by the scripts loaded after this
+-- one could refer to them instead of listing every block.
+
+tags.add("#soil", { "dirt", "grass", "farmland" })
+tags.add("#stone", { "stone", "polished_stone" })
+tags.add("#fluids", { "water", "lava" })
+tags.add("#plants", { "tall_grass", "flower", "mushroom", "wheat" })
+tags.add("#furnaces", { "furnace", "lit_furnace" })
+tags.add("#natural", { "#soil", "#stone", "sand", "ice" })
@@ -0,0 +1,111 @@
+-- Built-in tools and drop tables, mods could define their
+-- own ones in the same way. Tools speed up breaking blocks
+-- by the given factors, other blocks are broken as fast as
+-- by hand. Blocks could be given by a tag of tags.lua.
+
+tools.define {
+    name = "pickaxe",
+    durability = 250,
+    speed = { ["#stone"] = 4.0, ["#furnaces"] = 4.0, hopper = 4.0, glowstone = 2.0, obsidian = 4.0 },
+}
+
+tools.define {
+    name = "shovel",
+    durability = 250,
+    speed = { ["#soil"] = 4.0 },
+}
+
+tools.define {
+    name = "axe",
+    durability = 250,
+    speed = { bed = 2.0, log = 4.0 },
+}
+
+-- The wand selects the corners of the region the worldedit
+-- API edits, the first one by breaking a block and the second
+-- one by placing one. It never breaks blocks itself.
+tools.define {
+    name = "wand",
+    durability = 1,
+    speed = {},
+}
+
+-- The brush applies the brush set with worldedit.brush around
+-- the block the player is looking at by placing a block
+tools.define {
+    name = "brush",
+    durability = 1,
+    speed = {},
+}
+
+-- The compass points towards the waypoint set with /waypoint
+-- or the spawn, and the clock shows the time of day, while
+-- they are held
+tools.define {
+    name = "compass",
+    durability = 250,
+    speed = {},
+}
+
+tools.define {
+    name = "clock",
+    durability = 250,
+    speed = {},
+}
+
+-- Flint and steel lights a fire on the face of the block the
+-- player is looking at, instead of placing a block. TNT is lit
+-- and explodes after a few seconds.
+tools.define {
+    name = "flint_and_steel",
+    durability = 64,
+    speed = {},
+}
+
+-- Wolves are tamed by right-clicking them with a bone, which
+-- takes a few tries. Tamed wolves follow their owner, and sit
+-- down or stand up again once their owner right-clicks them.
+-- Summon one with /summon mob ~ ~ ~ { kind = "wolf" }
+tools.define {
+    name = "bone",
+    durability = 16,
+    speed = {},
+}
+
+-- Boats and minecarts are placed instead of a block. Right-click
+-- one to ride it, steer it with WASD and press Y to get off.
+-- Boats float on water, minecarts roll on the ground.
+tools.define {
+    name = "boat",
+    durability = 100,
+    speed = {},
+}
+
+tools.define {
+    name = "minecart",
+    durability = 100,
+    speed = {},
+}
+
+-- Blocks without a drop table drop themselves
+blocks.drops {
+    block = "stone",
+    drops = { { item = "cobblestone", count = 1, tool = "pickaxe" } },
+}
+
+blocks.drops {
+    block = "grass",
+    drops = { { item = "dirt", count = 1 } },
+}
+
+blocks.drops {
+    block = "farmland",
+    drops = { { item = "dirt", count = 1 } },
+}
+
+-- Grown wheat is harvested through its crop definition
+blocks.drops { block = "wheat", drops = {} }
+blocks.drops { block = "glass", drops = {} }
+blocks.drops { block = "lit_furnace", drops = { { item = "furnace", count = 1 } } }
+blocks.drops { block = "#fluids", drops = {} }
+blocks.drops { block = "fire", drops = {} }
@@ -0,0 +1,26 @@
+-- Built-in plants decorating the terrain, mods could define
+-- their own ones in the same way. Each column grows at most
+-- one plant, the first defined one whose chance comes up.
+
+vegetation.define {
+    block = "flower",
+    on = "grass",
+    density = 0.01,
+    biomes = { plains = 0.04, hills = 0.02 },
+}
+
+vegetation.define {
+    block = "tall_grass",
+    on = "grass",
+    density = 0.05,
+    biomes = { plains = 0.25, hills = 0.12, mountains = 0.03 },
+}
+
+-- Mushrooms grow on the floors of caves, where no
+-- sky light reaches them
+vegetation.define {
+    block = "mushroom",
+    on = "stone",
+    density = 0.03,
+    dark = true,
+}
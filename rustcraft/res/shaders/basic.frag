@@ -1,13 +1,103 @@
 #version 330 core
 
 layout (location = 0) out vec4 color;
+// The glow of the surface, which is blurred by the bloom pass
+layout (location = 1) out vec4 emissive;
 
 in vec4 v_Position;
 in vec2 v_TexCoord;
 in vec3 v_Normal;
 in vec2 v_TileCoord;
+in vec2 v_OverlayCoord;
+in vec4 v_OverlayTint;
+in float v_Emission;
+in float v_Reflectivity;
+// The ambient occlusion of the corners of the face, see `VoxelFace::occluded`
+in float v_Occlusion;
 
 uniform sampler2D u_Texture;
+uniform float u_Light;
+// The light levels of the blocks of the chunk, see `LightMap`.
+// The lower bits hold the light of glowing blocks, the upper
+// four bits the light of the sky.
+uniform usampler3D u_LightMap;
+// Whether the light map holds the light of glowing blocks per
+// color channel in sixteen bit texels, see `LightFormat`
+uniform bool u_RgbLight;
+// The opacity of the chunk, which ramps up while it fades in
+uniform float u_Opacity;
+// The surroundings reflective blocks reflect, see `EnvironmentMap`
+uniform samplerCube u_Environment;
+// The position of the camera relative to the chunk
+uniform vec3 u_Eye;
+// The haze in caves, see `CaveFogSettings`
+uniform float u_FogDensity;
+uniform float u_LightShafts;
+uniform vec3 u_FogColor;
+
+// The samples of the sky light along the view ray
+const int FOG_SAMPLES = 12;
+// The distance from the surface in blocks up to which the
+// view ray is sampled, as the light map only covers the chunk
+const float FOG_REACH = 24.0;
+// The share of the light which reaches fully occluded corners
+const float OCCLUDED_LIGHT = 0.5;
+
+// Returns the sky light of a texel in the range [0, 1]
+float skyLevel(uint levels) {
+    return float(levels >> (u_RgbLight ? 12u : 4u)) / 15.0;
+}
+
+// Returns the light of glowing blocks of a texel in the range
+// [0, 1], which scalar light maps have in all color channels
+vec3 blockLevel(uint levels) {
+    if (u_RgbLight) {
+        return vec3(uvec3(levels, levels >> 4u, levels >> 8u) & 15u) / 15.0;
+    }
+    return vec3(float(levels & 15u) / 15.0);
+}
+
+// Returns the sky light of a block in the range [0, 1], or -1.0
+// if the block is out of the chunk. The light map is laid out
+// along x, z and y.
+float skyLight(vec3 pos) {
+    ivec3 texel = ivec3(floor(pos)).xzy;
+    if (any(lessThan(texel, ivec3(0))) || any(greaterThanEqual(texel, textureSize(u_LightMap, 0)))) {
+        return -1.0;
+    }
+    return skyLevel(texelFetch(u_LightMap, texel, 0).r);
+}
+
+// Adds the haze of caves to a color. The sky light is sampled
+// along the view ray back from the surface. Darkness thickens
+// a cool haze, while rays passing the light falling in through
+// an opening brighten the air.
+vec3 caveFog(vec3 color, float surfaceSky) {
+    vec3 toEye = u_Eye - v_Position.xyz;
+    float reach = min(length(toEye), FOG_REACH);
+    vec3 stride = normalize(toEye) * reach / float(FOG_SAMPLES);
+
+    float exposure = 0.0;
+    float brightest = surfaceSky;
+    int samples = 0;
+    for (int i = 1; i <= FOG_SAMPLES; i++) {
+        float sky = skyLight(v_Position.xyz + stride * float(i));
+        if (sky >= 0.0) {
+            exposure += sky;
+            brightest = max(brightest, sky);
+            samples++;
+        }
+    }
+    exposure = samples > 0 ? exposure / float(samples) : surfaceSky;
+
+    float darkness = 1.0 - min(exposure, surfaceSky);
+    float haze = 1.0 - exp(-u_FogDensity * reach * darkness);
+    color = mix(color, u_FogColor, haze);
+
+    // The shafts only show while the sky is lit
+    float shaft = max(brightest - surfaceSky, 0.0) * (1.0 - surfaceSky);
+    return color + vec3(1.0, 0.96, 0.88) * shaft * u_LightShafts * u_Light;
+}
 
 void main() {
 
@@ -17,12 +107,83 @@ void main() {
     vec2 tileUV = vec2(dot(vec3(v_Normal.y-v_Normal.z, 0, v_Normal.x), vec3(v_Position)),
                        dot(vec3(0, abs(v_Normal.x+v_Normal.z), v_Normal.y), vec3(v_Position)));
 
+    // Cross shaped blocks like plants are the only faces with a
+    // diagonal normal, they span a single tile along their quad
+    bool cross = abs(v_Normal.x) > 0.1 && abs(v_Normal.z) > 0.1;
+    if (cross) {
+        tileUV = clamp(v_TexCoord, 0.0, 0.999);
+    }
+
     float tileSize = (1.0/16.0);
     vec2 tileOffset = v_TileCoord / 16.0;
     vec2 texCoord = tileOffset + tileSize * fract(tileUV);
 
     vec4 texColor = texture(u_Texture, texCoord);
-    color = texColor;
+    if (cross && texColor.a < 0.5) {
+        discard;
+    }
+
+    // Blend the tinted overlay tile over the texture,
+    // faces without an overlay have a transparent tint
+    vec4 overlayColor = texture(u_Texture, v_OverlayCoord / 16.0 + tileSize * fract(tileUV));
+    texColor.rgb = mix(texColor.rgb, overlayColor.rgb * v_OverlayTint.rgb, overlayColor.a * v_OverlayTint.a);
+
+    // Reflective blocks mirror the environment map, more so
+    // when they're looked at from a grazing angle
+    if (v_Reflectivity > 0.0 && !cross) {
+        vec3 view = normalize(v_Position.xyz - u_Eye);
+        vec3 reflection = texture(u_Environment, reflect(view, v_Normal)).rgb;
+        float fresnel = pow(1.0 - max(dot(-view, v_Normal), 0.0), 5.0);
+        texColor.rgb = mix(texColor.rgb, reflection, mix(v_Reflectivity, 1.0, fresnel * v_Reflectivity));
+    }
+
+    // The light of glowing blocks is sampled in the block in front
+    // of the face, or in the block itself for cross shaped blocks.
+    // The light map is laid out along x, z and y.
+    ivec3 block = ivec3(floor(v_Position.xyz + (cross ? vec3(0.0) : v_Normal * 0.5)));
+    ivec3 texel = clamp(block.xzy, ivec3(0), textureSize(u_LightMap, 0) - 1);
+    uint levels = texelFetch(u_LightMap, texel, 0).r;
+    vec3 blockLight = blockLevel(levels);
+    float surfaceSky = skyLevel(levels);
+
+    // The debug views show a single input in false color, see
+    // `DebugView`. They aren't lit, fogged or glowing.
+#if defined(DEBUG_OCCLUSION)
+    color = vec4(vec3(v_Occlusion), u_Opacity);
+    emissive = vec4(0.0);
+    return;
+#elif defined(DEBUG_BLOCK_LIGHT)
+    color = vec4(blockLight, u_Opacity);
+    emissive = vec4(0.0);
+    return;
+#elif defined(DEBUG_SKY_LIGHT)
+    color = vec4(vec3(surfaceSky), u_Opacity);
+    emissive = vec4(0.0);
+    return;
+#elif defined(DEBUG_NORMALS)
+    color = vec4(v_Normal * 0.5 + 0.5, u_Opacity);
+    emissive = vec4(0.0);
+    return;
+#elif defined(DEBUG_TILES)
+    // Each tile gets a color of its own, hashed from its coordinates
+    vec3 hash = fract(sin(vec3(dot(v_TileCoord, vec2(12.9898, 78.233)),
+                               dot(v_TileCoord, vec2(39.3468, 11.135)),
+                               dot(v_TileCoord, vec2(73.156, 52.235)))) * 43758.5453);
+    color = vec4(mix(hash, vec3(1.0), 0.2 * float(any(lessThan(fract(tileUV), vec2(0.05))))), u_Opacity);
+    emissive = vec4(0.0);
+    return;
+#endif
+
+    // Emissive blocks aren't darkened at night or in corners, as they
+    // light themselves. Each color channel is lit on its own, so lights
+    // tint the surfaces.
+    vec3 ambient = max(vec3(u_Light), blockLight) * mix(OCCLUDED_LIGHT, 1.0, v_Occlusion);
+    vec3 light = max(ambient, vec3(min(v_Emission, 1.0)));
+    color = vec4(texColor.rgb * light, texColor.a * u_Opacity);
+    if (u_FogDensity > 0.0 || u_LightShafts > 0.0) {
+        color.rgb = caveFog(color.rgb, surfaceSky);
+    }
+    emissive = vec4(texColor.rgb * v_Emission, texColor.a * u_Opacity);
 }
 
 //void main()
@@ -1,14 +1,25 @@
 #version 330 core
 
+// Attribute locations are registered in `graphics::attribute`
 layout (location = 0) in vec4 position;
 layout (location = 1) in vec2 texCoord;
 layout (location = 2) in vec3 normal;
 layout (location = 3) in vec2 tileCoord;
+layout (location = 5) in vec2 overlayCoord;
+layout (location = 6) in vec4 overlayTint;
+layout (location = 7) in float emission;
+layout (location = 8) in float reflectivity;
+layout (location = 9) in float occlusion;
 
 out vec4 v_Position;
 out vec2 v_TexCoord;
 out vec3 v_Normal;
 out vec2 v_TileCoord;
+out vec2 v_OverlayCoord;
+out vec4 v_OverlayTint;
+out float v_Emission;
+out float v_Reflectivity;
+out float v_Occlusion;
 
 uniform mat4 u_MVP;
 
@@ -19,4 +30,9 @@ void main()
     v_TexCoord = texCoord;
     v_Normal = normal;
     v_TileCoord = tileCoord;
+    v_OverlayCoord = overlayCoord;
+    v_OverlayTint = overlayTint;
+    v_Emission = emission;
+    v_Reflectivity = reflectivity;
+    v_Occlusion = occlusion;
 }
@@ -0,0 +1,24 @@
+#version 330 core
+
+layout (location = 0) out vec4 color;
+
+in vec2 v_TexCoord;
+
+uniform sampler2D u_Texture;
+// The direction of the blur, horizontal or vertical
+uniform vec2 u_Direction;
+
+// Weights of a 9-tap gaussian kernel, from the center outwards
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec2 texelSize = 1.0 / vec2(textureSize(u_Texture, 0));
+    vec2 offset = u_Direction * texelSize;
+
+    vec3 result = texture(u_Texture, v_TexCoord).rgb * weights[0];
+    for (int i = 1; i < 5; i++) {
+        result += texture(u_Texture, v_TexCoord + offset * i).rgb * weights[i];
+        result += texture(u_Texture, v_TexCoord - offset * i).rgb * weights[i];
+    }
+    color = vec4(result, 1.0);
+}
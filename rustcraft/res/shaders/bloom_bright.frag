@@ -0,0 +1,17 @@
+#version 330 core
+
+layout (location = 0) out vec4 color;
+
+in vec2 v_TexCoord;
+
+uniform sampler2D u_Scene;
+uniform sampler2D u_Emission;
+uniform float u_Threshold;
+
+void main() {
+    // Everything brighter than the threshold blooms,
+    // emissive surfaces bloom regardless of their brightness
+    vec3 scene = texture(u_Scene, v_TexCoord).rgb;
+    vec3 bright = max(scene - vec3(u_Threshold), vec3(0.0));
+    color = vec4(bright + texture(u_Emission, v_TexCoord).rgb, 1.0);
+}
@@ -0,0 +1,24 @@
+#version 330 core
+
+layout (location = 0) out vec4 color;
+// The glow of the surface, which is blurred by the bloom pass
+layout (location = 1) out vec4 emissive;
+
+in vec3 v_WorldPos;
+
+uniform vec3 u_CameraPos;
+uniform float u_FadeDistance;
+uniform float u_Time;
+
+void main() {
+    // Diagonal stripes moving upwards along the wall
+    float stripe = fract((v_WorldPos.x + v_WorldPos.z + v_WorldPos.y) * 0.25 - u_Time * 0.5);
+    if (stripe > 0.5) {
+        discard;
+    }
+
+    // The wall fades in as the camera gets close to it
+    float fade = 1.0 - clamp(distance(v_WorldPos, u_CameraPos) / u_FadeDistance, 0.0, 1.0);
+    color = vec4(0.35, 0.6, 1.0, 0.6 * fade);
+    emissive = vec4(color.rgb * 0.5, color.a);
+}
@@ -0,0 +1,15 @@
+#version 330 core
+
+// Attribute locations are registered in `graphics::attribute`
+layout (location = 0) in vec4 position;
+
+out vec3 v_WorldPos;
+
+uniform mat4 u_MVP;
+uniform mat4 u_Model;
+
+void main()
+{
+    gl_Position = u_MVP * position;
+    v_WorldPos = vec3(u_Model * position);
+}
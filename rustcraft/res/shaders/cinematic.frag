@@ -0,0 +1,76 @@
+#version 330 core
+
+layout (location = 0) out vec4 color;
+// The glow is passed on to the bloom, blurred like the scene
+layout (location = 1) out vec4 emissive;
+
+in vec2 v_TexCoord;
+
+uniform sampler2D u_Scene;
+uniform sampler2D u_Emission;
+uniform sampler2D u_Depth;
+// The matrices reconstructing positions from the depth
+uniform mat4 u_InvProj;
+uniform mat4 u_InvView;
+// The view projection matrix of the previous frame
+uniform mat4 u_PrevViewProj;
+// The distance in focus, 0 to focus the center of the screen
+uniform float u_FocusDistance;
+// The distance from the focus over which the blur grows to its maximum
+uniform float u_FocusRange;
+// The radius of the strongest blur in pixels, 0 to disable the depth of field
+uniform float u_MaxBlur;
+// The share of the motion since the previous frame which is blurred, 0 to disable it
+uniform float u_MotionBlur;
+
+// The amount of samples per pixel
+const int TAPS = 16;
+// The golden angle, which spreads the samples evenly over a disk
+const float GOLDEN_ANGLE = 2.39996;
+// The longest motion blurred, in screen coordinates
+const float MAX_MOTION = 0.05;
+
+// Returns the position in view space at the given screen coordinates.
+// The sky is moved in front of the far plane, so it isn't infinitely far.
+vec3 viewPos(vec2 texCoord) {
+    float depth = min(texture(u_Depth, texCoord).r, 0.99999);
+    vec4 pos = u_InvProj * vec4(vec3(texCoord, depth) * 2.0 - 1.0, 1.0);
+    return pos.xyz / pos.w;
+}
+
+void main() {
+    vec3 pos = viewPos(v_TexCoord);
+    vec2 texelSize = 1.0 / vec2(textureSize(u_Scene, 0));
+
+    // The radius of the blur grows with the distance to the focus
+    float radius = 0.0;
+    if (u_MaxBlur > 0.0) {
+        float focus = u_FocusDistance > 0.0 ? u_FocusDistance : length(viewPos(vec2(0.5)));
+        radius = clamp(abs(length(pos) - focus) / u_FocusRange, 0.0, 1.0) * u_MaxBlur;
+    }
+
+    // The way the pixel moved on the screen since the previous frame
+    vec2 motion = vec2(0.0);
+    if (u_MotionBlur > 0.0) {
+        vec4 previous = u_PrevViewProj * (u_InvView * vec4(pos, 1.0));
+        motion = (v_TexCoord - (previous.xy / previous.w * 0.5 + 0.5)) * u_MotionBlur;
+        if (length(motion) > MAX_MOTION) {
+            motion = normalize(motion) * MAX_MOTION;
+        }
+    }
+
+    // Each sample lies within the disk of the blur and along
+    // the motion, so both effects are drawn at once
+    vec3 result = vec3(0.0);
+    vec3 glow = vec3(0.0);
+    for (int i = 0; i < TAPS; i++) {
+        float t = (float(i) + 0.5) / float(TAPS);
+        float angle = float(i) * GOLDEN_ANGLE;
+        vec2 disk = vec2(cos(angle), sin(angle)) * sqrt(t) * radius * texelSize;
+        vec2 texCoord = v_TexCoord + disk + motion * (t - 0.5);
+        result += texture(u_Scene, texCoord).rgb;
+        glow += texture(u_Emission, texCoord).rgb;
+    }
+    color = vec4(result / float(TAPS), 1.0);
+    emissive = vec4(glow / float(TAPS), 1.0);
+}
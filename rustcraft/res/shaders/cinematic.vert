@@ -0,0 +1,13 @@
+#version 330 core
+
+// Attribute locations are registered in `graphics::attribute`
+layout (location = 0) in vec4 position;
+
+out vec2 v_TexCoord;
+
+void main()
+{
+    // The quad is given in normalized device coordinates already
+    gl_Position = position;
+    v_TexCoord = position.xy * 0.5 + 0.5;
+}
@@ -0,0 +1,10 @@
+#version 330 core
+
+// Attribute locations are registered in `graphics::attribute`
+layout (location = 0) in vec4 position;
+
+void main()
+{
+    // The quad is given in normalized device coordinates already
+    gl_Position = position;
+}
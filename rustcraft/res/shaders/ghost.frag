@@ -0,0 +1,31 @@
+#version 330 core
+
+layout (location = 0) out vec4 color;
+// The glow of the surface, which is blurred by the bloom pass
+layout (location = 1) out vec4 emissive;
+
+in vec4 v_Position;
+in vec2 v_TexCoord;
+in vec3 v_Normal;
+in vec2 v_TileCoord;
+
+uniform sampler2D u_Texture;
+uniform vec4 u_Tint;
+
+void main() {
+    // Same tiling as `basic.frag`
+    vec2 tileUV = vec2(dot(vec3(v_Normal.y-v_Normal.z, 0, v_Normal.x), vec3(v_Position)),
+                       dot(vec3(0, abs(v_Normal.x+v_Normal.z), v_Normal.y), vec3(v_Position)));
+
+    float tileSize = (1.0/16.0);
+    vec2 tileOffset = v_TileCoord / 16.0;
+    vec2 texCoord = tileOffset + tileSize * fract(tileUV);
+
+    vec4 texColor = texture(u_Texture, texCoord);
+    // Transparent texels are cut out, e.g. around the cracks
+    if (texColor.a < 0.5) {
+        discard;
+    }
+    color = vec4(mix(texColor.rgb, u_Tint.rgb, 0.5), u_Tint.a);
+    emissive = vec4(0.0, 0.0, 0.0, u_Tint.a);
+}
@@ -0,0 +1,20 @@
+#version 330 core
+
+layout (location = 0) out vec4 color;
+// The glow of the surface, which is blurred by the bloom pass
+layout (location = 1) out vec4 emissive;
+
+in vec3 v_Normal;
+
+uniform vec4 u_Color;
+
+void main() {
+    // Simple directional shading, lines have no normal and stay unshaded
+    float shading = 1.0;
+    if (length(v_Normal) > 0.0) {
+        vec3 lightDir = normalize(vec3(0.3, 1.0, 0.5));
+        shading = 0.5 + 0.5 * max(dot(normalize(v_Normal), lightDir), 0.0);
+    }
+    color = vec4(u_Color.rgb * shading, u_Color.a);
+    emissive = vec4(0.0, 0.0, 0.0, u_Color.a);
+}
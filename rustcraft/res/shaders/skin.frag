@@ -0,0 +1,25 @@
+#version 330 core
+
+layout (location = 0) out vec4 color;
+// The glow of the surface, which is blurred by the bloom pass
+layout (location = 1) out vec4 emissive;
+
+in vec2 v_TexCoord;
+in vec3 v_Normal;
+
+uniform sampler2D u_Texture;
+uniform float u_Light;
+
+void main() {
+    // Unused parts of a skin are transparent
+    vec4 texColor = texture(u_Texture, v_TexCoord);
+    if (texColor.a < 0.5) {
+        discard;
+    }
+
+    // Simple directional shading, darkened at night like the chunks
+    vec3 lightDir = normalize(vec3(0.3, 1.0, 0.5));
+    float shading = 0.5 + 0.5 * max(dot(normalize(v_Normal), lightDir), 0.0);
+    color = vec4(texColor.rgb * shading * u_Light, 1.0);
+    emissive = vec4(0.0, 0.0, 0.0, 1.0);
+}
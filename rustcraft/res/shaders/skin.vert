@@ -0,0 +1,19 @@
+#version 330 core
+
+// Attribute locations are registered in `graphics::attribute`
+layout (location = 0) in vec4 position;
+layout (location = 1) in vec2 texCoord;
+layout (location = 2) in vec3 normal;
+
+out vec2 v_TexCoord;
+out vec3 v_Normal;
+
+uniform mat4 u_MVP;
+uniform mat4 u_Model;
+
+void main()
+{
+    gl_Position = u_MVP * position;
+    v_TexCoord = texCoord;
+    v_Normal = mat3(u_Model) * normal;
+}
@@ -0,0 +1,15 @@
+#version 330 core
+
+layout (location = 0) out vec4 color;
+// The glow of the surface, which is blurred by the bloom pass
+layout (location = 1) out vec4 emissive;
+
+in vec2 v_TexCoord;
+
+uniform sampler2D u_Texture;
+uniform vec4 u_Color;
+
+void main() {
+    color = texture(u_Texture, v_TexCoord) * u_Color;
+    emissive = vec4(0.0);
+}
@@ -0,0 +1,427 @@
+//! Types to read the files of ZIP archives, e.g. of zipped
+//! resource packs. Only stored and deflated files are
+//! supported, which is what packers write by default, in
+//! archives of the classic or the Zip64 format.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+/// The signature of the end of the central directory
+const END_SIGNATURE: u32 = 0x0605_4b50;
+/// The signature of the Zip64 end of the central directory
+const ZIP64_END_SIGNATURE: u32 = 0x0606_4b50;
+/// The signature of the locator of the Zip64 end of the
+/// central directory, which is right in front of the end
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+/// The signature of the file headers of the central directory
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+/// The signature of the header in front of the data of a file
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+/// The size of the end of the central directory without its comment
+const END_SIZE: usize = 22;
+/// The size of the locator of the Zip64 end of the central directory
+const ZIP64_LOCATOR_SIZE: usize = 20;
+/// The size of a file header of the central directory without its name
+const CENTRAL_SIZE: usize = 46;
+/// The size of the header in front of the data of a file without its name
+const LOCAL_SIZE: usize = 30;
+/// The id of the extra field holding the sizes and offset
+/// of a file which don't fit into the header
+const ZIP64_EXTRA: u16 = 0x0001;
+/// The value of a size or offset of a header which is
+/// stored in the Zip64 extra field instead
+const ZIP64_MARKER: u32 = 0xFFFF_FFFF;
+/// The compression method of files which are stored as they are
+const STORED: u16 = 0;
+/// The compression method of deflated files
+const DEFLATED: u16 = 8;
+
+/// An entry of the central directory, which locates a file
+#[derive(Copy, Clone, Debug)]
+struct Entry {
+    /// The offset of the local header of the file
+    offset: usize,
+    /// The size of the file within the archive
+    compressed_size: usize,
+    /// The size of the file once it's extracted
+    size: usize,
+    /// The compression method of the file
+    method: u16,
+    /// The CRC-32 of the extracted file
+    crc: u32,
+}
+
+/// ZipArchive
+///
+/// A ZIP archive, which is read into memory once. Its files are
+/// looked up by their path within the archive, e.g. `shaders/basic.frag`.
+/// Extracted files are checked against the size and checksum the
+/// archive lists for them, so a corrupt or crafted archive never
+/// inflates beyond the size it claims.
+#[derive(Debug)]
+pub struct ZipArchive {
+    /// The bytes of the archive
+    data: Vec<u8>,
+    /// The files of the archive by their path
+    entries: HashMap<String, Entry>,
+}
+
+impl ZipArchive {
+    /// Opens the archive at a path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the archive
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    /// Reads the central directory of an archive
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The bytes of the archive
+    pub fn from_bytes(data: Vec<u8>) -> io::Result<Self> {
+        let truncated = || invalid("truncated central directory");
+
+        // The end of the central directory is followed by a comment
+        // of unknown length, so it's searched for from the back
+        let end = (0..=data.len().saturating_sub(END_SIZE)).rev()
+            .find(|&offset| read_u32(&data, offset) == Some(END_SIGNATURE))
+            .ok_or_else(|| invalid("the end of the central directory is missing"))?;
+        let mut count = read_u16(&data, end + 10).ok_or_else(truncated)? as u64;
+        let mut offset = read_u32(&data, end + 16).ok_or_else(truncated)? as u64;
+
+        // Archives too large for the classic format locate their
+        // Zip64 end of the central directory right in front of it
+        let locator = end.checked_sub(ZIP64_LOCATOR_SIZE)
+            .filter(|&locator| read_u32(&data, locator) == Some(ZIP64_LOCATOR_SIGNATURE));
+        if let Some(locator) = locator {
+            let zip64_end = read_u64(&data, locator + 8).ok_or_else(truncated)?;
+            let zip64_end = usize::try_from(zip64_end).map_err(|_| truncated())?;
+            if read_u32(&data, zip64_end) != Some(ZIP64_END_SIGNATURE) {
+                return Err(invalid("malformed Zip64 end of the central directory"));
+            }
+            let field = |at: usize| zip64_end.checked_add(at).and_then(|at| read_u64(&data, at)).ok_or_else(truncated);
+            count = field(32)?;
+            offset = field(48)?;
+        }
+
+        let mut entries = HashMap::new();
+        let mut offset = usize::try_from(offset).map_err(|_| truncated())?;
+        for _ in 0..count {
+            if read_u32(&data, offset) != Some(CENTRAL_SIGNATURE) {
+                return Err(invalid("malformed central directory"));
+            }
+            let field = |at: usize| offset.checked_add(at).and_then(|at| read_u16(&data, at)).map(usize::from).ok_or_else(truncated);
+            let wide_field = |at: usize| offset.checked_add(at).and_then(|at| read_u32(&data, at)).ok_or_else(truncated);
+            let name_len = field(28)?;
+            let extra_len = field(30)?;
+            // The signature is in bounds, so adding the fixed size of the header can't overflow
+            let name_start = offset + CENTRAL_SIZE;
+            let extra_start = name_start.checked_add(name_len).ok_or_else(truncated)?;
+            let extra_end = extra_start.checked_add(extra_len).ok_or_else(truncated)?;
+            let name = data.get(name_start..extra_start).ok_or_else(truncated)?;
+            let name = String::from_utf8_lossy(name).replace('\\', "/");
+            let extra = data.get(extra_start..extra_end).ok_or_else(truncated)?;
+
+            // The sizes and offset which don't fit into the header
+            // follow each other in the Zip64 extra field
+            let mut zip64 = zip64_fields(extra).into_iter();
+            let mut wide = |value: u32| -> io::Result<usize> {
+                let value = match value {
+                    ZIP64_MARKER => zip64.next().ok_or_else(|| invalid("the Zip64 extra field is missing"))?,
+                    value => value as u64,
+                };
+                usize::try_from(value).map_err(|_| invalid("the archive is too large"))
+            };
+            let size = wide(wide_field(24)?)?;
+            let compressed_size = wide(wide_field(20)?)?;
+            let entry = Entry {
+                offset: wide(wide_field(42)?)?,
+                compressed_size,
+                size,
+                method: field(10)? as u16,
+                crc: wide_field(16)?,
+            };
+
+            // Directories are only implied by the paths of their files
+            if !name.ends_with('/') {
+                entries.insert(name, entry);
+            }
+            offset = extra_end.checked_add(field(32)?).ok_or_else(truncated)?;
+        }
+
+        Ok(Self { data, entries })
+    }
+
+    /// Returns whether the archive contains a file
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The path of the file within the archive
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// Returns the paths of all files of the archive
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// Extracts a file of the archive
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The path of the file within the archive
+    pub fn read(&self, name: &str) -> io::Result<Vec<u8>> {
+        let entry = self.entries.get(name)
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, format!("{} isn't in the archive", name)))?;
+        if read_u32(&self.data, entry.offset) != Some(LOCAL_SIGNATURE) {
+            return Err(invalid("malformed local header"));
+        }
+        let truncated = || invalid("truncated local header");
+        let field = |at: usize| entry.offset.checked_add(at).and_then(|at| read_u16(&self.data, at)).ok_or_else(truncated);
+        let name_len = field(26)?;
+        let extra_len = field(28)?;
+        // The signature is in bounds, so adding the fixed size of the header can't overflow
+        let start = entry.offset + LOCAL_SIZE + name_len as usize + extra_len as usize;
+        let compressed = start.checked_add(entry.compressed_size)
+            .and_then(|end| self.data.get(start..end))
+            .ok_or_else(|| invalid("truncated file data"))?;
+
+        let data = match entry.method {
+            STORED => compressed.to_vec(),
+            // Files inflating beyond their size fail
+            // before they take up more memory
+            DEFLATED => miniz_oxide::inflate::decompress_to_vec_with_limit(compressed, entry.size)
+                .map_err(|error| invalid(&format!("failed to inflate {}: {:?}", name, error)))?,
+            method => return Err(invalid(&format!("{} is compressed with the unsupported method {}", name, method))),
+        };
+        if data.len() != entry.size {
+            return Err(invalid(&format!("{} has {} bytes instead of {}", name, data.len(), entry.size)));
+        }
+        if crc32fast::hash(&data) != entry.crc {
+            return Err(invalid(&format!("{} is corrupt, its checksum doesn't match", name)));
+        }
+        Ok(data)
+    }
+}
+
+/// Returns the values of the Zip64 extra field among the
+/// extra fields of a header, if there's one
+///
+/// # Arguments
+///
+/// * `extra` - The extra fields of the header
+fn zip64_fields(extra: &[u8]) -> Vec<u64> {
+    let mut offset = 0;
+    while let (Some(id), Some(len)) = (read_u16(extra, offset), read_u16(extra, offset + 2)) {
+        let start = offset + 4;
+        if id == ZIP64_EXTRA {
+            return (0..len as usize / 8).map_while(|i| read_u64(extra, start + i * 8)).collect();
+        }
+        offset = start + len as usize;
+    }
+    Vec::new()
+}
+
+/// Returns the error of a malformed archive
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+/// Reads a little endian `u16` at an offset, if it's in bounds
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// Reads a little endian `u32` at an offset, if it's in bounds
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Reads a little endian `u64` at an offset, if it's in bounds
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(data.get(offset..offset.checked_add(8)?)?);
+    Some(u64::from_le_bytes(bytes))
+}
+
+/// Writes an archive with stored files, which is
+/// enough to test reading them
+#[cfg(test)]
+pub fn stored_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    write_archive(files, STORED, false)
+}
+
+/// Writes an archive whose files are compressed with a
+/// method, in the Zip64 format or the classic one
+///
+/// # Arguments
+///
+/// * `files` - The paths and contents of the files
+/// * `method` - `STORED` or `DEFLATED`
+/// * `zip64` - Whether the sizes and offsets are stored
+///   in the Zip64 fields
+#[cfg(test)]
+fn write_archive(files: &[(&str, &[u8])], method: u16, zip64: bool) -> Vec<u8> {
+    let wide = |value: usize| if zip64 { ZIP64_MARKER } else { value as u32 };
+    let mut data = Vec::new();
+    let mut central = Vec::new();
+    for (name, content) in files {
+        let offset = data.len();
+        let compressed = match method {
+            DEFLATED => miniz_oxide::deflate::compress_to_vec(content, 6),
+            _ => content.to_vec(),
+        };
+        let mut header = Vec::new();
+        header.extend_from_slice(&method.to_le_bytes());
+        header.extend_from_slice(&[0, 0, 0, 0]);
+        header.extend_from_slice(&crc32fast::hash(content).to_le_bytes());
+        header.extend_from_slice(&wide(compressed.len()).to_le_bytes());
+        header.extend_from_slice(&wide(content.len()).to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        let mut extra = Vec::new();
+        if zip64 {
+            extra.extend_from_slice(&ZIP64_EXTRA.to_le_bytes());
+            extra.extend_from_slice(&24u16.to_le_bytes());
+            for value in [content.len(), compressed.len(), offset].iter() {
+                extra.extend_from_slice(&(*value as u64).to_le_bytes());
+            }
+        }
+
+        data.extend_from_slice(&LOCAL_SIGNATURE.to_le_bytes());
+        data.extend_from_slice(&[45, 0, 0, 0]);
+        data.extend_from_slice(&header);
+        data.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+        data.extend_from_slice(name.as_bytes());
+        data.extend_from_slice(&extra);
+        data.extend_from_slice(&compressed);
+
+        central.extend_from_slice(&CENTRAL_SIGNATURE.to_le_bytes());
+        central.extend_from_slice(&[45, 0, 45, 0, 0, 0]);
+        central.extend_from_slice(&header);
+        central.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&wide(offset).to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+        central.extend_from_slice(&extra);
+    }
+
+    let central_offset = data.len();
+    data.extend_from_slice(&central);
+    if zip64 {
+        let zip64_end = data.len() as u64;
+        data.extend_from_slice(&ZIP64_END_SIGNATURE.to_le_bytes());
+        data.extend_from_slice(&44u64.to_le_bytes());
+        data.extend_from_slice(&[45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        for value in [files.len(), files.len(), central.len(), central_offset].iter() {
+            data.extend_from_slice(&(*value as u64).to_le_bytes());
+        }
+        data.extend_from_slice(&ZIP64_LOCATOR_SIGNATURE.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(&zip64_end.to_le_bytes());
+        data.extend_from_slice(&[1, 0, 0, 0]);
+    }
+    let count = if zip64 { u16::MAX } else { files.len() as u16 };
+    data.extend_from_slice(&END_SIGNATURE.to_le_bytes());
+    data.extend_from_slice(&[0, 0, 0, 0]);
+    data.extend_from_slice(&count.to_le_bytes());
+    data.extend_from_slice(&count.to_le_bytes());
+    data.extend_from_slice(&wide(central.len()).to_le_bytes());
+    data.extend_from_slice(&wide(central_offset).to_le_bytes());
+    data.extend_from_slice(&[0, 0]);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_and_deflated_files_are_extracted() {
+        let archive = ZipArchive::from_bytes(stored_archive(&[
+            ("shaders/basic.frag", b"void main() {}"),
+            ("scripts/", b""),
+        ])).unwrap();
+        assert!(archive.contains("shaders/basic.frag"));
+        assert_eq!(archive.names().collect::<Vec<&str>>(), vec!["shaders/basic.frag"]);
+        assert_eq!(archive.read("shaders/basic.frag").unwrap(), b"void main() {}");
+        assert_eq!(archive.read("scripts/init.lua").unwrap_err().kind(), ErrorKind::NotFound);
+
+        let content = b"local x = 1 local x = 1 local x = 1";
+        let archive = ZipArchive::from_bytes(write_archive(&[("a.lua", content)], DEFLATED, false)).unwrap();
+        assert_eq!(archive.read("a.lua").unwrap(), content.to_vec());
+
+        assert!(ZipArchive::from_bytes(b"not an archive".to_vec()).is_err());
+    }
+
+    #[test]
+    fn zip64_archives_are_read() {
+        let content = b"local x = 1 local x = 1 local x = 1";
+        for &method in [STORED, DEFLATED].iter() {
+            let archive = ZipArchive::from_bytes(write_archive(&[
+                ("scripts/init.lua", content),
+                ("textures/dirt.png", b"png"),
+            ], method, true)).unwrap();
+            assert_eq!(archive.read("scripts/init.lua").unwrap(), content.to_vec());
+            assert_eq!(archive.read("textures/dirt.png").unwrap(), b"png");
+        }
+    }
+
+    #[test]
+    fn corrupt_files_are_rejected() {
+        let content = b"local x = 1 local x = 1 local x = 1";
+        let data = write_archive(&[("a.lua", content)], DEFLATED, false);
+        let central = data.len() - END_SIZE - CENTRAL_SIZE - "a.lua".len();
+
+        // A flipped checksum
+        let mut corrupt = data.clone();
+        corrupt[central + 16] ^= 0xFF;
+        let error = ZipArchive::from_bytes(corrupt).unwrap().read("a.lua").unwrap_err();
+        assert!(error.to_string().contains("checksum"), "{}", error);
+
+        // A file inflating beyond the size it claims isn't inflated any further
+        let mut bomb = data.clone();
+        bomb[central + 24..central + 28].copy_from_slice(&4u32.to_le_bytes());
+        let error = ZipArchive::from_bytes(bomb).unwrap().read("a.lua").unwrap_err();
+        assert!(error.to_string().contains("failed to inflate"), "{}", error);
+
+        // A file inflating to less than its size
+        let mut short = data;
+        short[central + 24..central + 28].copy_from_slice(&1000u32.to_le_bytes());
+        let error = ZipArchive::from_bytes(short).unwrap().read("a.lua").unwrap_err();
+        assert!(error.to_string().contains("instead of 1000"), "{}", error);
+    }
+
+    #[test]
+    fn out_of_range_offsets_are_rejected() {
+        let data = write_archive(&[("a.lua", b"local x = 1")], STORED, true);
+        let locator = data.len() - END_SIZE - ZIP64_LOCATOR_SIZE;
+        let zip64_end = locator - 56;
+        let central = data.len() - END_SIZE - ZIP64_LOCATOR_SIZE - 56 - (CENTRAL_SIZE + "a.lua".len() + 28);
+
+        // The Zip64 end of the central directory
+        let mut corrupt = data.clone();
+        corrupt[locator + 8..locator + 16].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(ZipArchive::from_bytes(corrupt).is_err());
+
+        // The central directory
+        let mut corrupt = data.clone();
+        corrupt[zip64_end + 48..zip64_end + 56].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(ZipArchive::from_bytes(corrupt).is_err());
+
+        // A local header
+        let mut corrupt = data;
+        let offset = central + CENTRAL_SIZE + "a.lua".len() + 20;
+        corrupt[offset..offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        let error = ZipArchive::from_bytes(corrupt).unwrap().read("a.lua").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
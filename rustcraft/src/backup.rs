@@ -0,0 +1,303 @@
+//! Types to back up the directory a world is saved in,
+//! to prune old backups and to restore one of them
+
+use crate::world::save::{SaveDir, EDIT_LOG_PREFIX};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The directory next to the world containing its backups
+const BACKUP_DIR: &str = "backups";
+/// The prefix of the names of the backups
+const BACKUP_PREFIX: &str = "backup-";
+/// The extension of backups which are being written
+const PARTIAL_EXTENSION: &str = "partial";
+/// The command line flag restoring a backup
+pub const RESTORE_FLAG: &str = "--restore-backup";
+/// The command line flag listing the backups
+pub const LIST_FLAG: &str = "--list-backups";
+
+/// Backup
+///
+/// A backup of a world
+#[derive(Clone, Debug, PartialEq)]
+pub struct Backup {
+    /// The name of the backup, e.g. `backup-1700000000`
+    pub name: String,
+    /// The time the backup has been made in seconds
+    /// since the Unix epoch
+    pub time: u64,
+}
+
+/// Backups
+///
+/// The backups of a world, which are snapshots of the
+/// directory the world is saved in. The files of the
+/// snapshots are hard links to the saved files, which are
+/// only ever replaced rather than changed, so a backup
+/// takes hardly any space. Files which are appended to,
+/// like the edit logs, are copied instead.
+///
+/// A snapshot is written to a partial directory first, which
+/// is renamed once it's complete, so an interrupted snapshot
+/// is never mistaken for a backup. Snapshots should be taken
+/// right after the world has been saved by the same thread,
+/// so no file is snapshotted while it's written.
+pub struct Backups {
+    /// The directory the world is saved in
+    world: PathBuf,
+    /// The directory containing the backups
+    dir: PathBuf,
+}
+
+impl Backups {
+    /// Creates the backups of the world saved in the given
+    /// directory, which are kept in a directory next to it
+    ///
+    /// # Arguments
+    ///
+    /// * `save_dir` - The directory the world is saved in
+    pub fn new(save_dir: &SaveDir) -> Self {
+        let world = save_dir.root_path().to_path_buf();
+        let name = world.file_name().and_then(|name| name.to_str()).unwrap_or("world");
+        let dir = world.with_file_name(BACKUP_DIR).join(name);
+        Self {
+            world,
+            dir,
+        }
+    }
+
+    /// Snapshots the world and returns the backup
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The current time in seconds since the Unix epoch
+    pub fn snapshot(&self, time: u64) -> io::Result<Backup> {
+        fs::create_dir_all(&self.dir)?;
+        let mut name = format!("{}{}", BACKUP_PREFIX, time);
+        let mut suffix = 1;
+        while self.dir.join(&name).exists() {
+            suffix += 1;
+            name = format!("{}{}-{}", BACKUP_PREFIX, time, suffix);
+        }
+
+        let partial = self.dir.join(&name).with_extension(PARTIAL_EXTENSION);
+        if partial.exists() {
+            fs::remove_dir_all(&partial)?;
+        }
+        snapshot_dir(&self.world, &partial)?;
+        fs::rename(&partial, self.dir.join(&name))?;
+        Ok(Backup { name, time })
+    }
+
+    /// Returns the complete backups, the oldest one first
+    pub fn list(&self) -> io::Result<Vec<Backup>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut backups = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            if name.ends_with(&format!(".{}", PARTIAL_EXTENSION)) {
+                continue;
+            }
+            let time = name.strip_prefix(BACKUP_PREFIX)
+                .and_then(|rest| rest.split('-').next())
+                .and_then(|time| time.parse().ok());
+            if let Some(time) = time {
+                backups.push(Backup { name, time });
+            }
+        }
+        backups.sort_by(|a, b| (a.time, a.name.len(), &a.name).cmp(&(b.time, b.name.len(), &b.name)));
+        Ok(backups)
+    }
+
+    /// Removes the oldest backups beyond the given amount,
+    /// as well as interrupted snapshots. Returns the amount
+    /// of removed backups.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep` - The amount of backups to keep
+    pub fn prune(&self, keep: usize) -> io::Result<usize> {
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if has_extension(&path, PARTIAL_EXTENSION) {
+                fs::remove_dir_all(path)?;
+            }
+        }
+
+        let backups = self.list()?;
+        let removed = backups.len().saturating_sub(keep);
+        for backup in backups.iter().take(removed) {
+            fs::remove_dir_all(self.dir.join(&backup.name))?;
+        }
+        Ok(removed)
+    }
+
+    /// Replaces the world with a backup. The world must not be
+    /// running. The replaced world is kept as a backup itself,
+    /// so a restore could be undone.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the backup
+    /// * `time` - The current time in seconds since the Unix epoch
+    pub fn restore(&self, name: &str, time: u64) -> io::Result<()> {
+        let backup = self.dir.join(name);
+        if name.is_empty() || !backup.is_dir() || !self.list()?.iter().any(|other| other.name == name) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("there is no backup {}", name)));
+        }
+
+        if self.world.exists() {
+            self.snapshot(time)?;
+            fs::remove_dir_all(&self.world)?;
+        }
+        // The files are copied, so the world doesn't
+        // append to the logs of the backup
+        copy_dir(&backup, &self.world)
+    }
+}
+
+/// Snapshots a directory with all of its files and subdirectories.
+/// Files which are being written are left out.
+///
+/// # Arguments
+///
+/// * `from` - The directory
+/// * `to` - The directory of the snapshot
+fn snapshot_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    if !from.exists() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        let target = to.join(entry.file_name());
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_dir() {
+            snapshot_dir(&path, &target)?;
+        } else if has_extension(&path, "tmp") {
+            continue;
+        } else if name.starts_with(EDIT_LOG_PREFIX) || fs::hard_link(&path, &target).is_err() {
+            // Hard links aren't supported by all file systems
+            fs::copy(&path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether a path has the given extension
+///
+/// # Arguments
+///
+/// * `path` - The path
+/// * `extension` - The extension without the dot
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().and_then(|other| other.to_str()) == Some(extension)
+}
+
+/// Copies a directory with all of its files and subdirectories
+///
+/// # Arguments
+///
+/// * `from` - The directory
+/// * `to` - The copy of the directory
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::save::write_atomic;
+    use std::io::Write;
+
+    fn world(name: &str) -> (SaveDir, Backups) {
+        let root = std::env::temp_dir().join(format!("rustcraft-backup-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let save_dir = SaveDir::new(root.join("world")).unwrap();
+        let backups = Backups::new(&save_dir);
+        (save_dir, backups)
+    }
+
+    fn read(path: PathBuf) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn snapshots_keep_the_saved_state() {
+        let (save_dir, backups) = world("snapshot");
+        let root = save_dir.root_path();
+        let region = save_dir.dimension("overworld").unwrap().root_path().join("r.0.0.toml");
+        let log = save_dir.dimension("overworld").unwrap().root_path().join("log.0.0.toml");
+        write_atomic(&region, b"old").unwrap();
+        fs::write(&log, "old").unwrap();
+        fs::write(root.join("player.tmp"), "half").unwrap();
+
+        let backup = backups.snapshot(100).unwrap();
+        assert_eq!(backup, Backup { name: "backup-100".to_string(), time: 100 });
+        write_atomic(&region, b"new").unwrap();
+        fs::OpenOptions::new().append(true).open(&log).unwrap().write_all(b" appended").unwrap();
+
+        let snapshot = backups.dir.join("backup-100").join("dimensions").join("overworld");
+        assert_eq!(read(snapshot.join("r.0.0.toml")), "old");
+        assert_eq!(read(snapshot.join("log.0.0.toml")), "old");
+        assert!(!backups.dir.join("backup-100").join("player.tmp").exists());
+
+        // Backups of the same second don't replace each other
+        assert_eq!(backups.snapshot(100).unwrap().name, "backup-100-2");
+        let _ = fs::remove_dir_all(root.parent().unwrap());
+    }
+
+    #[test]
+    fn old_backups_are_pruned() {
+        let (save_dir, backups) = world("prune");
+        for time in &[300, 100, 200, 200] {
+            backups.snapshot(*time).unwrap();
+        }
+        fs::create_dir_all(backups.dir.join("backup-400.partial")).unwrap();
+        let names = |backups: &Backups| backups.list().unwrap().into_iter().map(|backup| backup.name).collect::<Vec<_>>();
+        assert_eq!(names(&backups), vec!["backup-100", "backup-200", "backup-200-2", "backup-300"]);
+
+        assert_eq!(backups.prune(2).unwrap(), 2);
+        assert_eq!(names(&backups), vec!["backup-200-2", "backup-300"]);
+        assert!(!backups.dir.join("backup-400.partial").exists());
+        assert_eq!(backups.prune(2).unwrap(), 0);
+        let _ = fs::remove_dir_all(save_dir.root_path().parent().unwrap());
+    }
+
+    #[test]
+    fn backups_are_restored() {
+        let (save_dir, backups) = world("restore");
+        let meta = save_dir.root_path().join("world.toml");
+        write_atomic(&meta, b"old").unwrap();
+        backups.snapshot(100).unwrap();
+        write_atomic(&meta, b"new").unwrap();
+        write_atomic(&save_dir.root_path().join("player.toml"), b"new").unwrap();
+
+        assert!(backups.restore("backup-999", 200).is_err());
+        backups.restore("backup-100", 200).unwrap();
+        assert_eq!(read(meta.clone()), "old");
+        assert!(!save_dir.root_path().join("player.toml").exists());
+
+        // The replaced world has been backed up
+        assert_eq!(read(backups.dir.join("backup-200").join("world.toml")), "new");
+        let _ = fs::remove_dir_all(save_dir.root_path().parent().unwrap());
+    }
+}
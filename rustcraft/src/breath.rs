@@ -0,0 +1,105 @@
+//! Types to show the air the player has left on the HUD
+//! while they hold their breath under water
+
+use crate::graphics::backend::GlBackend;
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use cgmath::Vector4;
+
+/// The amount of bubbles shown with a full breath
+pub const BUBBLES: usize = 10;
+/// The edge length of a bubble
+const BUBBLE_SIZE: f32 = 8.0;
+/// The horizontal distance between two bubbles
+const BUBBLE_GAP: f32 = 2.0;
+/// The distance of the bubbles to the bottom edge of the screen
+const BUBBLE_MARGIN: f32 = 24.0;
+
+/// Returns the amount of bubbles shown for the share of the
+/// air the player has left. A bubble pops as soon as any of
+/// its air is used up.
+///
+/// # Arguments
+///
+/// * `air` - The share of the air in the range `[0, 1]`
+pub fn bubbles(air: f32) -> usize {
+    ((air.clamp(0.0, 1.0) * BUBBLES as f32).ceil() as usize).min(BUBBLES)
+}
+
+/// BreathFrame
+///
+/// A frame the air of the player could be rendered for
+pub trait BreathFrame: Frame {
+    /// Returns the share of the air the player has left
+    /// while they hold their breath, if they do
+    fn air(&self) -> Option<f32>;
+}
+
+/// BreathRenderer
+///
+/// The `BreathRenderer` is the render pass drawing a row of
+/// bubbles at the bottom of the screen while the player holds
+/// their breath, which pop one after another as they run out
+/// of air
+pub struct BreathRenderer {
+    /// The painter drawing the bubbles
+    painter: OverlayPainter,
+}
+
+impl BreathRenderer {
+    /// Creates a new breath renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+impl<F: BreathFrame> RenderPass<F> for BreathRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let count = match ctx.frame.air() {
+            Some(air) => bubbles(air),
+            None => return,
+        };
+
+        let width = OVERLAY_HEIGHT * ctx.frame.camera().aspect_ratio();
+        let row = BUBBLES as f32 * (BUBBLE_SIZE + BUBBLE_GAP) - BUBBLE_GAP;
+        let left = (width - row) / 2.0;
+        let top = OVERLAY_HEIGHT - BUBBLE_MARGIN - BUBBLE_SIZE;
+
+        let mut overlay = OverlayBuilder::default();
+        for i in 0..count {
+            let x = left + i as f32 * (BUBBLE_SIZE + BUBBLE_GAP);
+            overlay.rect([x, top], [x + BUBBLE_SIZE, top + BUBBLE_SIZE], Vector4::new(0.55, 0.8, 1.0, 0.9));
+            overlay.rect([x + 1.0, top + 1.0], [x + BUBBLE_SIZE - 1.0, top + BUBBLE_SIZE - 1.0], Vector4::new(0.15, 0.4, 0.85, 0.9));
+            // The highlight of the bubble
+            overlay.rect([x + 2.0, top + 2.0], [x + 4.0, top + 4.0], Vector4::new(1.0, 1.0, 1.0, 0.9));
+        }
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bubbles_pop_as_air_is_used_up() {
+        assert_eq!(bubbles(1.0), BUBBLES);
+        assert_eq!(bubbles(0.95), BUBBLES);
+        assert_eq!(bubbles(0.9), BUBBLES - 1);
+        assert_eq!(bubbles(0.01), 1);
+        assert_eq!(bubbles(0.0), 0);
+        assert_eq!(bubbles(-1.0), 0);
+    }
+}
@@ -1,10 +1,29 @@
 //! Types and traits representing various of cameras
 
-use cgmath::{Vector3, Matrix4, Zero, InnerSpace, Point3, EuclideanSpace, Rad};
+use cgmath::{Vector3, Matrix4, Zero, InnerSpace, Point3, EuclideanSpace, Rad, ortho};
 use std::ops::{Deref, DerefMut};
 
 const WORLD_UP: Vector3<f32> = Vector3::new(0.0, 1.0, 0.0);
 
+/// Projection
+///
+/// The matrices a scene is rendered with, which are
+/// provided by both the perspective and the orthographic
+/// camera, so render passes work with either of them
+pub trait Projection {
+    /// Returns the position of the camera
+    fn pos(&self) -> &Vector3<f32>;
+
+    /// Returns the view matrix of the camera
+    fn view_matrix(&self) -> &Matrix4<f32>;
+
+    /// Returns the projection matrix of the camera
+    fn proj_matrix(&self) -> &Matrix4<f32>;
+
+    /// Returns the ratio of the width to the height of the view
+    fn aspect_ratio(&self) -> f32;
+}
+
 /// Camera
 ///
 /// The basic structure of a camera
@@ -15,6 +34,7 @@ const WORLD_UP: Vector3<f32> = Vector3::new(0.0, 1.0, 0.0);
 /// matrix is specified in either an
 /// orthographic or a perspective
 /// camera.
+#[derive(Clone)]
 pub struct Camera {
     /// The position of the camera
     pos: Vector3<f32>,
@@ -189,7 +209,24 @@ impl Camera {
         );
         self.yaw += yaw.to_radians();
         self.roll += roll.to_radians();
+        self.calc_orientation();
+    }
 
+    /// Turns the camera to the given yaw and pitch
+    ///
+    /// # Arguments
+    ///
+    /// * `yaw` - The yaw of the camera in radians
+    /// * `pitch` - The pitch of the camera in radians
+    pub fn set_rotation(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw;
+        self.pitch = pitch;
+        self.calc_orientation();
+    }
+
+    /// Calculates the vectors of the camera from its yaw
+    /// and pitch
+    fn calc_orientation(&mut self) {
         self.look.x = self.pitch.cos() * self.yaw.sin();
         self.look.y = self.pitch.sin();
         self.look.z = self.pitch.cos() * self.yaw.cos();
@@ -258,6 +295,7 @@ impl Camera {
 /// of the basic camera and provides a projection
 /// matrix simulating the real world (fov, far and
 /// near plane)
+#[derive(Clone)]
 pub struct PerspectiveCamera {
     /// The embedded basic camera
     camera: Camera,
@@ -390,4 +428,230 @@ impl PerspectiveCamera {
     pub fn calc_proj_matrix(&mut self) {
         self.proj_matrix = cgmath::perspective(Rad(self.fov), self.aspect_ratio, self.near_plane, self.far_plane);
     }
-}
\ No newline at end of file
+}
+
+impl Projection for PerspectiveCamera {
+    fn pos(&self) -> &Vector3<f32> {
+        self.camera.pos()
+    }
+
+    fn view_matrix(&self) -> &Matrix4<f32> {
+        self.camera.view_matrix()
+    }
+
+    fn proj_matrix(&self) -> &Matrix4<f32> {
+        &self.proj_matrix
+    }
+
+    fn aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+}
+
+/// Orthographic Camera
+///
+/// The orthographic camera is an advancement of the
+/// basic camera whose projection matrix keeps parallel
+/// lines parallel, so objects don't shrink with their
+/// distance. Its view is bounded by a box around the
+/// view axis of the camera, e.g. for the 2D user
+/// interface or isometric captures.
+#[derive(Clone)]
+pub struct OrthographicCamera {
+    /// The embedded basic camera
+    camera: Camera,
+    /// The left bound of the view
+    left: f32,
+    /// The right bound of the view
+    right: f32,
+    /// The bottom bound of the view
+    bottom: f32,
+    /// The top bound of the view
+    top: f32,
+    /// The near plane of the camera, which could be
+    /// behind the camera
+    near_plane: f32,
+    /// The far plane of the camera
+    far_plane: f32,
+    /// The projection matrix of the camera
+    proj_matrix: Matrix4<f32>,
+}
+
+impl Deref for OrthographicCamera {
+    type Target = Camera;
+
+    fn deref(&self) -> &Self::Target {
+        &self.camera
+    }
+}
+
+impl DerefMut for OrthographicCamera {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.camera
+    }
+}
+
+impl OrthographicCamera {
+    /// Creates a new camera at the given location, whose
+    /// view is centered around its view axis
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the camera
+    /// * `width` - The width of the view
+    /// * `height` - The height of the view
+    pub fn at_pos(pos: Vector3<f32>, width: f32, height: f32) -> Self {
+        let mut camera = Self {
+            camera: Camera::at_pos(pos),
+            left: -width / 2.0,
+            right: width / 2.0,
+            bottom: -height / 2.0,
+            top: height / 2.0,
+            near_plane: 0.1,
+            far_plane: 100.0,
+            proj_matrix: Matrix4::zero(),
+        };
+        camera.calc_proj_matrix();
+        camera
+    }
+
+    /// Creates a new camera mapping the screen, where the
+    /// origin is the top left corner and y points down
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the screen
+    /// * `height` - The height of the screen
+    pub fn screen(width: f32, height: f32) -> Self {
+        let mut camera = Self {
+            camera: Camera::at_pos(Vector3::zero()),
+            left: 0.0,
+            right: width,
+            bottom: height,
+            top: 0.0,
+            near_plane: -1.0,
+            far_plane: 1.0,
+            proj_matrix: Matrix4::zero(),
+        };
+        camera.look_at(Vector3::new(0.0, 0.0, -1.0));
+        camera.calc_proj_matrix();
+        camera
+    }
+
+    /// Returns the width of the view
+    pub fn width(&self) -> f32 {
+        (self.right - self.left).abs()
+    }
+
+    /// Returns the height of the view
+    pub fn height(&self) -> f32 {
+        (self.top - self.bottom).abs()
+    }
+
+    /// Returns the aspect ratio of the camera
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width() / self.height()
+    }
+
+    /// Returns the near plane of the camera
+    pub fn near_plane(&self) -> f32 {
+        self.near_plane
+    }
+
+    /// Returns the far plane of the camera
+    pub fn far_plane(&self) -> f32 {
+        self.far_plane
+    }
+
+    /// Sets the size of the view, centered around
+    /// the view axis
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The new width of the view
+    /// * `height` - The new height of the view
+    pub fn set_size(&mut self, width: f32, height: f32) {
+        self.left = -width / 2.0;
+        self.right = width / 2.0;
+        self.bottom = -height / 2.0;
+        self.top = height / 2.0;
+        self.calc_proj_matrix();
+    }
+
+    /// Sets the near plane of the camera to a new value
+    ///
+    /// # Arguments
+    ///
+    /// * `near` - The new near plane value
+    pub fn set_near_plane(&mut self, near: f32) {
+        self.near_plane = near;
+        self.calc_proj_matrix();
+    }
+
+    /// Sets the far plane of the camera to a new value
+    ///
+    /// # Arguments
+    ///
+    /// * `far` - The new far plane value
+    pub fn set_far_plane(&mut self, far: f32) {
+        self.far_plane = far;
+        self.calc_proj_matrix();
+    }
+
+    /// Returns the projection matrix of the camera
+    pub fn proj_matrix(&self) -> &Matrix4<f32> {
+        &self.proj_matrix
+    }
+
+    /// Calculates the projection matrix of the camera
+    pub fn calc_proj_matrix(&mut self) {
+        self.proj_matrix = ortho(self.left, self.right, self.bottom, self.top, self.near_plane, self.far_plane);
+    }
+}
+
+impl Projection for OrthographicCamera {
+    fn pos(&self) -> &Vector3<f32> {
+        self.camera.pos()
+    }
+
+    fn view_matrix(&self) -> &Matrix4<f32> {
+        self.camera.view_matrix()
+    }
+
+    fn proj_matrix(&self) -> &Matrix4<f32> {
+        &self.proj_matrix
+    }
+
+    fn aspect_ratio(&self) -> f32 {
+        OrthographicCamera::aspect_ratio(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector2;
+
+    fn project(camera: &dyn Projection, point: Vector3<f32>) -> Vector3<f32> {
+        let clip = camera.proj_matrix() * camera.view_matrix() * point.extend(1.0);
+        clip.truncate() / clip.w
+    }
+
+    #[test]
+    fn screen_camera_maps_the_top_left_corner_to_the_origin() {
+        let camera = OrthographicCamera::screen(200.0, 100.0);
+        assert_eq!(camera.aspect_ratio(), 2.0);
+        assert_eq!(project(&camera, Vector3::new(0.0, 0.0, 0.0)).truncate(), Vector2::new(-1.0, 1.0));
+        assert_eq!(project(&camera, Vector3::new(200.0, 100.0, 0.0)).truncate(), Vector2::new(1.0, -1.0));
+    }
+
+    #[test]
+    fn orthographic_projection_keeps_the_size_with_the_distance() {
+        let mut camera = OrthographicCamera::at_pos(Vector3::new(0.0, 0.0, 0.0), 20.0, 10.0);
+        camera.look_at(Vector3::new(0.0, 0.0, -1.0));
+        let near = project(&camera, Vector3::new(5.0, 5.0, -1.0));
+        let far = project(&camera, Vector3::new(5.0, 5.0, -90.0));
+        assert_eq!(near.truncate(), far.truncate());
+        assert!((near.x - 0.5).abs() < 1e-6 && (near.y - 1.0).abs() < 1e-6);
+    }
+}
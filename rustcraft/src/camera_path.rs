@@ -0,0 +1,377 @@
+//! Types to fly the camera along a path of keyframes, e.g.
+//! for trailers or to show off the world generation
+
+use crate::camera::Camera;
+use crate::scripting::{self, ScriptEngine};
+use cgmath::Vector3;
+use mlua::Table;
+use std::f32::consts::PI;
+use std::sync::{Arc, Mutex};
+
+/// The seconds the camera takes from one keyframe to the
+/// next if no duration is given
+pub const SEGMENT_SECONDS: f32 = 4.0;
+
+/// Keyframe
+///
+/// A position and orientation the camera passes through
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    /// The position of the camera
+    pub pos: Vector3<f32>,
+    /// The yaw of the camera in radians
+    pub yaw: f32,
+    /// The pitch of the camera in radians
+    pub pitch: f32,
+}
+
+impl Keyframe {
+    /// Creates a new keyframe at the current position and
+    /// orientation of a camera
+    ///
+    /// # Arguments
+    ///
+    /// * `camera` - The camera
+    pub fn of_camera(camera: &Camera) -> Self {
+        Self {
+            pos: *camera.pos(),
+            yaw: camera.yaw(),
+            pitch: camera.pitch(),
+        }
+    }
+
+    /// Moves and turns a camera to the keyframe
+    ///
+    /// # Arguments
+    ///
+    /// * `camera` - The camera
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.set_pos(self.pos);
+        camera.set_rotation(self.yaw, self.pitch);
+    }
+}
+
+/// CameraPath
+///
+/// The keyframes the camera flies through in order. The
+/// camera follows a Catmull-Rom spline, which passes
+/// through every keyframe without stopping at them.
+#[derive(Clone, Debug, Default)]
+pub struct CameraPath {
+    /// The keyframes in order
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    /// Appends a keyframe to the path. Its yaw is wrapped
+    /// to the nearest turn of the previous keyframe, so the
+    /// camera takes the shorter way around.
+    ///
+    /// # Arguments
+    ///
+    /// * `keyframe` - The keyframe
+    pub fn add(&mut self, mut keyframe: Keyframe) {
+        if let Some(previous) = self.keyframes.last() {
+            let turns = ((keyframe.yaw - previous.yaw) / (2.0 * PI)).round();
+            keyframe.yaw -= turns * 2.0 * PI;
+        }
+        self.keyframes.push(keyframe);
+    }
+
+    /// Removes all keyframes
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+    }
+
+    /// Returns the amount of keyframes
+    pub fn len(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    /// Returns whether the path has no keyframes
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// Returns the amount of segments between the keyframes
+    pub fn segments(&self) -> usize {
+        self.keyframes.len().saturating_sub(1)
+    }
+
+    /// Returns the point of the path at the given progress,
+    /// or `None` if the path has no keyframes. The segments
+    /// take equally long.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress` - How far along the path the point is in the range `[0, 1]`
+    pub fn sample(&self, progress: f32) -> Option<Keyframe> {
+        let segments = self.segments();
+        if segments == 0 {
+            return self.keyframes.first().copied();
+        }
+
+        let position = progress.clamp(0.0, 1.0) * segments as f32;
+        let index = (position as usize).min(segments - 1);
+        let t = position - index as f32;
+
+        // The keyframes at the ends are repeated, so the
+        // path starts and stops at them
+        let p1 = self.keyframes[index];
+        let p2 = self.keyframes[index + 1];
+        let p0 = if index > 0 { self.keyframes[index - 1] } else { p1 };
+        let p3 = self.keyframes.get(index + 2).copied().unwrap_or(p2);
+
+        Some(Keyframe {
+            pos: Vector3::new(
+                catmull_rom(p0.pos.x, p1.pos.x, p2.pos.x, p3.pos.x, t),
+                catmull_rom(p0.pos.y, p1.pos.y, p2.pos.y, p3.pos.y, t),
+                catmull_rom(p0.pos.z, p1.pos.z, p2.pos.z, p3.pos.z, t),
+            ),
+            yaw: catmull_rom(p0.yaw, p1.yaw, p2.yaw, p3.yaw, t),
+            pitch: catmull_rom(p0.pitch, p1.pitch, p2.pitch, p3.pitch, t),
+        })
+    }
+}
+
+/// Flythrough
+///
+/// The playback of a `CameraPath` over a fixed duration
+pub struct Flythrough {
+    /// The path which is played
+    path: CameraPath,
+    /// The seconds the whole path takes
+    duration: f32,
+    /// The seconds which have been played so far
+    elapsed: f32,
+}
+
+impl Flythrough {
+    /// Creates a new playback of a path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path
+    /// * `duration` - The seconds the whole path takes
+    pub fn new(path: CameraPath, duration: f32) -> Self {
+        Self {
+            path,
+            duration: duration.max(0.0),
+            elapsed: 0.0,
+        }
+    }
+
+    /// Advances the playback and returns where the camera is now
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The seconds passed since the last advance
+    pub fn advance(&mut self, seconds: f32) -> Option<Keyframe> {
+        self.elapsed = (self.elapsed + seconds).min(self.duration);
+        self.path.sample(self.progress())
+    }
+
+    /// Returns how far the playback got in the range `[0, 1]`
+    pub fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            self.elapsed / self.duration
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns whether the end of the path has been reached
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+/// PathCommand
+///
+/// A command scripts issued through the `camerapath` API,
+/// which is applied with the next update
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PathCommand {
+    /// A keyframe should be appended, or the current
+    /// camera if none is given
+    Add(Option<Keyframe>),
+    /// All keyframes should be removed
+    Clear,
+    /// The path should be played in the given seconds,
+    /// or `SEGMENT_SECONDS` per segment
+    Play(Option<f32>),
+    /// The playback should be stopped
+    Stop,
+}
+
+/// CameraPaths
+///
+/// The commands scripts issue through the `camerapath`
+/// API to build and play a camera path, e.g.
+///
+/// ```lua
+/// -- Keyframes at the current camera or at a given
+/// -- position, yaw and pitch in degrees
+/// camerapath.add()
+/// camerapath.add { pos = { 40, 90, -20 }, yaw = 135, pitch = -25 }
+///
+/// -- Flies along the path in 30 seconds
+/// camerapath.play(30)
+/// camerapath.stop()
+/// camerapath.clear()
+/// ```
+///
+/// The user interface is hidden while the path is played.
+pub struct CameraPaths {
+    /// The commands issued since the last update
+    commands: Arc<Mutex<Vec<PathCommand>>>,
+}
+
+impl CameraPaths {
+    /// Creates a new camera path API and registers it
+    /// in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let commands = Arc::new(Mutex::new(Vec::new()));
+
+        let lua = engine.lua();
+        let api = scripting::api_table(lua, "camerapath")?;
+        let added = commands.clone();
+        api.set("add", lua.create_function(move |_, def: Option<Table>| {
+            let keyframe = match def {
+                Some(def) => Some(Keyframe {
+                    pos: scripting::vector_from_table(def.get("pos")?)?,
+                    yaw: def.get::<_, Option<f32>>("yaw")?.unwrap_or(0.0).to_radians(),
+                    pitch: def.get::<_, Option<f32>>("pitch")?.unwrap_or(0.0).to_radians(),
+                }),
+                None => None,
+            };
+            added.lock().unwrap().push(PathCommand::Add(keyframe));
+            Ok(())
+        })?)?;
+        let cleared = commands.clone();
+        api.set("clear", lua.create_function(move |_, ()| {
+            cleared.lock().unwrap().push(PathCommand::Clear);
+            Ok(())
+        })?)?;
+        let played = commands.clone();
+        api.set("play", lua.create_function(move |_, seconds: Option<f32>| {
+            if matches!(seconds, Some(seconds) if seconds <= 0.0) {
+                return Err(mlua::Error::RuntimeError("the duration must be positive".to_string()));
+            }
+            played.lock().unwrap().push(PathCommand::Play(seconds));
+            Ok(())
+        })?)?;
+        let stopped = commands.clone();
+        api.set("stop", lua.create_function(move |_, ()| {
+            stopped.lock().unwrap().push(PathCommand::Stop);
+            Ok(())
+        })?)?;
+
+        Ok(Self { commands })
+    }
+
+    /// Takes the commands scripts issued since the last call
+    pub fn take_commands(&self) -> Vec<PathCommand> {
+        std::mem::take(&mut *self.commands.lock().unwrap())
+    }
+}
+
+/// Interpolates a uniform Catmull-Rom spline between
+/// the two inner points
+///
+/// # Arguments
+///
+/// * `p0` - The point before the segment
+/// * `p1` - The start of the segment
+/// * `p2` - The end of the segment
+/// * `p3` - The point after the segment
+/// * `t` - How far along the segment the point is in the range `[0, 1]`
+fn catmull_rom(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(x: f32, yaw: f32) -> Keyframe {
+        Keyframe { pos: Vector3::new(x, 64.0, 0.0), yaw, pitch: 0.0 }
+    }
+
+    #[test]
+    fn paths_pass_through_their_keyframes() {
+        let mut path = CameraPath::default();
+        assert_eq!(path.sample(0.5), None);
+        path.add(keyframe(0.0, 0.0));
+        assert_eq!(path.sample(0.5), Some(keyframe(0.0, 0.0)));
+
+        path.add(keyframe(10.0, 0.0));
+        path.add(keyframe(30.0, 0.0));
+        assert_eq!(path.segments(), 2);
+        assert_eq!(path.sample(0.0), Some(keyframe(0.0, 0.0)));
+        assert_eq!(path.sample(0.5), Some(keyframe(10.0, 0.0)));
+        assert_eq!(path.sample(1.0), Some(keyframe(30.0, 0.0)));
+
+        // The camera speeds up towards the longer segment
+        let first = path.sample(0.25).unwrap().pos.x;
+        assert!(first > 3.0 && first < 5.0);
+        assert!(path.sample(0.75).unwrap().pos.x > 20.0);
+    }
+
+    #[test]
+    fn yaw_takes_the_shorter_way_around() {
+        let mut path = CameraPath::default();
+        path.add(keyframe(0.0, 0.1));
+        path.add(keyframe(0.0, 2.0 * PI - 0.1));
+        let yaw = path.sample(0.5).unwrap().yaw;
+        assert!(yaw.abs() < 1e-5);
+    }
+
+    #[test]
+    fn flythroughs_stop_at_the_end() {
+        let mut path = CameraPath::default();
+        path.add(keyframe(0.0, 0.0));
+        path.add(keyframe(10.0, 0.0));
+        let mut flythrough = Flythrough::new(path, 2.0);
+        assert_eq!(flythrough.advance(1.0).unwrap().pos.x, 5.0);
+        assert!(!flythrough.is_finished());
+        assert_eq!(flythrough.advance(5.0).unwrap().pos.x, 10.0);
+        assert!(flythrough.is_finished());
+    }
+
+    #[test]
+    fn scripts_issue_commands() {
+        let engine = ScriptEngine::new();
+        let paths = CameraPaths::new(&engine).unwrap();
+        engine.exec("test", r#"
+            camerapath.add()
+            camerapath.add { pos = { 1, 2, 3 }, yaw = 90 }
+            camerapath.play(10)
+            camerapath.stop()
+            camerapath.clear()
+        "#).unwrap();
+        assert!(engine.exec("test", "camerapath.play(0)").is_err());
+
+        let commands = paths.take_commands();
+        assert_eq!(commands.len(), 5);
+        assert_eq!(commands[0], PathCommand::Add(None));
+        match commands[1] {
+            PathCommand::Add(Some(keyframe)) => {
+                assert_eq!(keyframe.pos, Vector3::new(1.0, 2.0, 3.0));
+                assert!((keyframe.yaw - PI / 2.0).abs() < 1e-6);
+            }
+            command => panic!("unexpected command {:?}", command),
+        }
+        assert_eq!(&commands[2..], &[PathCommand::Play(Some(10.0)), PathCommand::Stop, PathCommand::Clear]);
+        assert!(paths.take_commands().is_empty());
+    }
+}
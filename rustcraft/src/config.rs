@@ -0,0 +1,420 @@
+//! Types to load and save the configuration of the game, i.e.
+//! the window, graphics and controls the player chose. Unlike
+//! the settings of the resources, the configuration belongs to
+//! the player and is written back when the game closes.
+
+use crate::input::{Binding, Bound, Control, KeyMap, CONTROLS};
+use crate::world::RENDER_DISTANCE;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// The path of the configuration, relative to the executable
+pub const CONFIG_PATH: &str = "config.toml";
+
+/// The time between two checks whether the file changed
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The smallest width and height of the window in pixels
+const MIN_WINDOW_SIZE: i32 = 320;
+/// The narrowest field of view in degrees
+const MIN_FOV: f32 = 30.0;
+/// The widest field of view in degrees
+const MAX_FOV: f32 = 150.0;
+/// The farthest render distance in chunks
+const MAX_RENDER_DISTANCE: i32 = 32;
+
+/// WindowConfig
+///
+/// The window the game is played in
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    /// The width of the window in pixels, unless it's fullscreen
+    pub width: i32,
+    /// The height of the window in pixels, unless it's fullscreen
+    pub height: i32,
+    /// Whether the window covers the primary monitor
+    pub fullscreen: bool,
+    /// Whether the frames are synchronized with the monitor
+    pub vsync: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 1080,
+            height: 720,
+            fullscreen: false,
+            vsync: true,
+        }
+    }
+}
+
+/// GraphicsConfig
+///
+/// How the world is rendered
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GraphicsConfig {
+    /// The vertical field of view in degrees
+    pub fov: f32,
+    /// The distance in chunks up to which chunks are drawn. The
+    /// adaptive quality could lower it, and chunks beyond the
+    /// view distance of the server aren't loaded to be drawn.
+    pub render_distance: i32,
+    /// Whether lights tint their surroundings. Without it, the
+    /// light maps of the chunks take half the memory. Changes
+    /// apply once the game is started again.
+    pub colored_light: bool,
+}
+
+impl Default for GraphicsConfig {
+    fn default() -> Self {
+        Self {
+            fov: 103.0,
+            render_distance: RENDER_DISTANCE,
+            colored_light: true,
+        }
+    }
+}
+
+/// ControlsConfig
+///
+/// The names of the keys the controls are bound to by the
+/// names of the controls, e.g. `forward = "W"`. Keys are named
+/// like GLFW names them, e.g. `W` or `LeftShift`, and prefixed
+/// with `Ctrl+` for shortcuts, e.g. `undo = "Ctrl+Z"`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(from = "BTreeMap<String, String>", into = "BTreeMap<String, String>")]
+pub struct ControlsConfig {
+    /// The name of the key of each control by its name
+    keys: BTreeMap<String, String>,
+}
+
+impl Default for ControlsConfig {
+    fn default() -> Self {
+        Self::from(BTreeMap::new())
+    }
+}
+
+impl From<BTreeMap<String, String>> for ControlsConfig {
+    /// Takes the keys of a configuration, where the controls
+    /// which are left out are bound to their default keys
+    fn from(mut keys: BTreeMap<String, String>) -> Self {
+        for &control in CONTROLS.iter() {
+            keys.entry(control.name()).or_insert_with(|| control.default_binding().name());
+        }
+        Self { keys }
+    }
+}
+
+impl From<ControlsConfig> for BTreeMap<String, String> {
+    fn from(config: ControlsConfig) -> Self {
+        config.keys
+    }
+}
+
+impl ControlsConfig {
+    /// Returns the name of the key a control is bound to
+    ///
+    /// # Arguments
+    ///
+    /// * `control` - The control
+    pub fn key_name(&self, control: Control) -> &str {
+        &self.keys[&control.name()]
+    }
+
+    /// Binds a control to the key with a name
+    ///
+    /// # Arguments
+    ///
+    /// * `control` - The control
+    /// * `name` - The name of the key
+    pub fn set_key_name(&mut self, control: Control, name: &str) {
+        self.keys.insert(control.name(), name.to_string());
+    }
+
+    /// Returns the keys the controls are bound to, see `apply`
+    pub fn key_map(&self) -> KeyMap {
+        let mut key_map = KeyMap::default();
+        self.apply(&mut key_map);
+        key_map
+    }
+
+    /// Binds the controls of a key map to their keys. Controls
+    /// bound to unknown keys keep their key, as do held controls
+    /// bound to shortcuts. Keys several controls are bound to
+    /// are warned about, see `KeyMap::conflicts`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_map` - The key map
+    pub fn apply(&self, key_map: &mut KeyMap) {
+        for name in self.keys.keys().filter(|name| Control::from_name(name).is_none()) {
+            log::warn!("Can't bind the unknown control {}", name);
+        }
+        for &control in CONTROLS.iter() {
+            let name = self.key_name(control);
+            match Binding::from_name(name) {
+                Some(binding) if binding.shortcut && control.is_held() => {
+                    log::warn!("Can't bind the held control {} to the shortcut {}", control.name(), name)
+                }
+                Some(binding) => key_map.bind(control, binding),
+                None => log::warn!("Can't bind {} to the unknown key {}", control.name(), name),
+            }
+        }
+        for (first, second) in key_map.conflicts() {
+            if let (Bound::Control(first), Bound::Control(second)) = (first, second) {
+                log::warn!("{} and {} are both bound to {}", first.name(), second.name(), key_map.binding(first).name());
+            }
+        }
+    }
+
+    /// Takes the keys the controls are bound to in a key map,
+    /// e.g. once the player rebound them in the key bindings menu
+    ///
+    /// # Arguments
+    ///
+    /// * `key_map` - The key map
+    pub fn update(&mut self, key_map: &KeyMap) {
+        for &control in CONTROLS.iter() {
+            self.set_key_name(control, &key_map.binding(control).name());
+        }
+    }
+}
+
+/// Config
+///
+/// The configuration of the game, e.g.
+///
+/// ```toml
+/// [window]
+/// width = 1080
+/// height = 720
+/// fullscreen = false
+/// vsync = true
+///
+/// [graphics]
+/// fov = 103.0
+/// render_distance = 6
+/// colored_light = true
+///
+/// [controls]
+/// forward = "W"
+/// jump = "Space"
+/// undo = "Ctrl+Z"
+/// ```
+///
+/// Missing values fall back to their defaults and values out
+/// of range are clamped.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// The window the game is played in
+    pub window: WindowConfig,
+    /// How the world is rendered
+    pub graphics: GraphicsConfig,
+    /// The keys the controls are bound to
+    pub controls: ControlsConfig,
+}
+
+impl Config {
+    /// Parses the configuration from its `TOML` declaration
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The declaration of the configuration
+    pub fn from_toml(source: &str) -> Result<Self, toml::de::Error> {
+        let config: Config = toml::from_str(source)?;
+        Ok(config.clamped())
+    }
+
+    /// Returns the `TOML` declaration of the configuration
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("The configuration consists of tables of plain values.")
+    }
+
+    /// Returns the field of view in radians, as the camera takes it
+    pub fn fov_radians(&self) -> f32 {
+        self.graphics.fov.to_radians()
+    }
+
+    /// Returns the configuration with its values within their ranges
+    fn clamped(mut self) -> Self {
+        self.window.width = self.window.width.max(MIN_WINDOW_SIZE);
+        self.window.height = self.window.height.max(MIN_WINDOW_SIZE);
+        self.graphics.fov = self.graphics.fov.clamp(MIN_FOV, MAX_FOV);
+        self.graphics.render_distance = self.graphics.render_distance.clamp(1, MAX_RENDER_DISTANCE);
+        self
+    }
+}
+
+/// ConfigFile
+///
+/// The file the configuration is loaded from and saved to.
+/// It's watched for changes while the game runs, so edits
+/// apply without restarting it.
+pub struct ConfigFile {
+    /// The path of the file
+    path: PathBuf,
+    /// The time the file has been modified at when it's
+    /// been read or written the last time
+    modified: Option<SystemTime>,
+    /// The time the file has been checked for changes
+    last_poll: Instant,
+}
+
+impl ConfigFile {
+    /// Creates the file of the configuration next to the executable
+    pub fn next_to_exe() -> io::Result<Self> {
+        let exe = std::env::current_exe()?;
+        Ok(Self::new(exe.with_file_name(CONFIG_PATH)))
+    }
+
+    /// Creates the file of the configuration at a path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            modified: None,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Loads the configuration. If the file doesn't exist yet, it's
+    /// created with the default configuration. If it can't be read,
+    /// the default configuration is used.
+    pub fn load(&mut self) -> Config {
+        if !self.path.exists() {
+            let config = Config::default();
+            if let Err(error) = self.save(&config) {
+                log::warn!("Failed to create {}: {}", self.path.display(), error);
+            }
+            return config;
+        }
+        self.read().unwrap_or_else(|error| {
+            log::warn!("{}", error);
+            Config::default()
+        })
+    }
+
+    /// Writes the configuration to the file
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration
+    pub fn save(&mut self, config: &Config) -> io::Result<()> {
+        fs::write(&self.path, config.to_toml())?;
+        self.modified = self.modified_time();
+        Ok(())
+    }
+
+    /// Returns the configuration if the file changed since it's
+    /// been read or written the last time. Files which can't be
+    /// read are skipped until they change again.
+    pub fn poll(&mut self) -> Option<Config> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.last_poll = Instant::now();
+        if self.modified_time() == self.modified {
+            return None;
+        }
+        match self.read() {
+            Ok(config) => Some(config),
+            Err(error) => {
+                log::warn!("{}", error);
+                None
+            }
+        }
+    }
+
+    /// Reads and parses the file
+    fn read(&mut self) -> Result<Config, String> {
+        self.modified = self.modified_time();
+        let source = fs::read_to_string(&self.path)
+            .map_err(|error| format!("Failed to read {}: {}", self.path.display(), error))?;
+        Config::from_toml(&source)
+            .map_err(|error| format!("Failed to parse {}: {}", self.path.display(), error))
+    }
+
+    /// Returns the time the file has been modified at
+    fn modified_time(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path).and_then(|metadata| metadata.modified()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glfw::Key;
+
+    #[test]
+    fn missing_values_fall_back_to_their_defaults() {
+        let config = Config::from_toml("[window]\nwidth = 100\nvsync = false\n[graphics]\nfov = 500.0\n[controls]\nforward = \"Up\"\njump = \"Nope\"").unwrap();
+        assert_eq!(config.window.width, MIN_WINDOW_SIZE);
+        assert_eq!(config.window.height, WindowConfig::default().height);
+        assert!(!config.window.vsync);
+        assert_eq!(config.graphics.fov, MAX_FOV);
+        assert_eq!(config.graphics.render_distance, RENDER_DISTANCE);
+        assert!(config.graphics.colored_light);
+
+        let key_map = config.controls.key_map();
+        assert_eq!(key_map.key(Control::Forward), Key::Up);
+        assert_eq!(key_map.key(Control::Jump), Key::Space);
+        assert!(key_map.binds(Key::Up) && !key_map.binds(Key::W));
+
+        assert!(Config::from_toml("[graphics]\nfov = \"wide\"").is_err());
+    }
+
+    #[test]
+    fn shortcuts_and_conflicts_are_bound() {
+        let config = Config::from_toml("[controls]\nundo = \"Ctrl+U\"\nforward = \"Ctrl+W\"\ninteract = \"Q\"\nconsole = \"Q\"").unwrap();
+        let key_map = config.controls.key_map();
+        assert_eq!(key_map.binding(Control::Undo), Binding::shortcut(Key::U));
+        assert_eq!(key_map.binding(Control::Forward), Binding::key(Key::W));
+        assert_eq!(key_map.control(Binding::shortcut(Key::U)), Some(Control::Undo));
+        assert_eq!(key_map.control(Binding::key(Key::U)), None);
+        assert_eq!(key_map.control(Binding::key(Key::W)), None);
+
+        // Only the first control bound to a key is triggered
+        assert_eq!(key_map.control(Binding::key(Key::Q)), Some(Control::Interact));
+        assert_eq!(key_map.conflicts(), vec![(Bound::Control(Control::Interact), Bound::Control(Control::Console))]);
+        assert!(KeyMap::default().conflicts().is_empty());
+        assert_eq!(config.controls.key_name(Control::Hotbar(3)), "Num3");
+
+        let mut controls = ControlsConfig::default();
+        let mut key_map = KeyMap::default();
+        key_map.bind(Control::Undo, Binding::shortcut(Key::U));
+        controls.update(&key_map);
+        assert_eq!(controls.key_name(Control::Undo), "Ctrl+U");
+        assert_eq!(controls.key_map(), key_map);
+    }
+
+    #[test]
+    fn configurations_are_saved_and_reloaded() {
+        let path = std::env::temp_dir().join(format!("rustcraft-config-{}.toml", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut file = ConfigFile::new(path.clone());
+        assert_eq!(file.load(), Config::default());
+        assert!(path.exists());
+
+        let mut config = Config::default();
+        config.window.fullscreen = true;
+        config.controls.set_key_name(Control::Sprint, "RightShift");
+        config.graphics.colored_light = false;
+        file.save(&config).unwrap();
+        file.last_poll -= POLL_INTERVAL;
+        assert_eq!(file.poll(), None);
+        assert_eq!(ConfigFile::new(path.clone()).load(), config);
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,346 @@
+//! Types to type commands into the console, e.g. to
+//! summon entities while testing their behavior
+
+use crate::graphics::backend::GlBackend;
+use crate::graphics::font::GLYPH_SIZE;
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use crate::world::worldgen_overlay::WorldgenLayer;
+use cgmath::{Vector3, Vector4};
+use toml::value::{Table as Components, Value};
+
+/// The maximal amount of characters of a command
+pub const MAX_LINE_LENGTH: usize = 96;
+/// The character commands start with
+const COMMAND_PREFIX: char = '/';
+/// The character coordinates relative to the player start with
+const RELATIVE_PREFIX: char = '~';
+
+/// The distance of the console to the edges of the screen
+const CONSOLE_MARGIN: f32 = 8.0;
+/// The distance of the text to the edges of the console
+const CONSOLE_PADDING: f32 = 4.0;
+
+/// Coordinate
+///
+/// A coordinate of a command, which is either absolute
+/// or relative to the player, e.g. `~2`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Coordinate {
+    Absolute(f32),
+    Relative(f32),
+}
+
+impl Coordinate {
+    /// Parses a coordinate
+    ///
+    /// # Arguments
+    ///
+    /// * `arg` - The argument of the command
+    fn parse(arg: &str) -> Result<Self, String> {
+        let invalid = || format!("{} isn't a coordinate", arg);
+        match arg.strip_prefix(RELATIVE_PREFIX) {
+            Some("") => Ok(Coordinate::Relative(0.0)),
+            Some(offset) => offset.parse().map(Coordinate::Relative).map_err(|_| invalid()),
+            None => arg.parse().map(Coordinate::Absolute).map_err(|_| invalid()),
+        }
+    }
+
+    /// Returns the coordinate relative to the origin
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The coordinate of the player
+    pub fn resolve(&self, origin: f32) -> f32 {
+        match *self {
+            Coordinate::Absolute(value) => value,
+            Coordinate::Relative(offset) => origin + offset,
+        }
+    }
+}
+
+/// Command
+///
+/// A command the player typed into the console:
+/// * `Summon` - `/summon <type> [x y z] [data]` spawns an entity,
+///   at the player unless a position is given. The data is an
+///   inline table of the initial components of the entity, e.g.
+///   `/summon critters:frog ~ ~1 ~ { name = "Fred", health = 2 }`
+/// * `SpawnEgg` - `/egg <type>` lets the player hold the spawn egg
+///   of an entity type, which spawns an entity wherever the player
+///   places a block while holding it
+/// * `Back` - `/back` teleports the player to where they died
+///   the last time
+/// * `Waypoint` - `/waypoint [x y z]` sets the waypoint the compass
+///   points towards instead of the spawn, or clears it
+/// * `Weather` - `/weather <clear|rain>` lets it rain in the
+///   dimension the player is in or stops the rain
+/// * `Worldgen` - `/worldgen <structures|biomes|carvers>` shows or
+///   hides a layer of the worldgen overlay, which outlines what
+///   the terrain generator produced around the player
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Summon {
+        /// The type of the entity
+        type_id: String,
+        /// The position of the bottom center of the entity,
+        /// `None` to spawn it at the player
+        pos: Option<[Coordinate; 3]>,
+        /// The initial components of the entity
+        components: Components,
+    },
+    SpawnEgg(String),
+    Back,
+    Waypoint(Option<[Coordinate; 3]>),
+    Weather(bool),
+    Worldgen(WorldgenLayer),
+}
+
+impl Command {
+    /// Parses a command, which could be typed with or without
+    /// its prefix. The error describes the mistake for the player.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The typed command
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let line = line.strip_prefix(COMMAND_PREFIX).unwrap_or(line);
+        // The data literal could contain spaces, so it's split off first
+        let (args, data) = match line.find('{') {
+            Some(start) => (&line[..start], Some(&line[start..])),
+            None => (line, None),
+        };
+        let args: Vec<&str> = args.split_whitespace().collect();
+
+        match args.as_slice() {
+            ["summon", type_id, coords @ ..] => {
+                let pos = match coords {
+                    [] => None,
+                    [x, y, z] => Some([Coordinate::parse(x)?, Coordinate::parse(y)?, Coordinate::parse(z)?]),
+                    _ => return Err("Usage: /summon <type> [x y z] [data]".to_string()),
+                };
+                let components = data.map(parse_data).transpose()?.unwrap_or_default();
+                Ok(Command::Summon { type_id: type_id.to_string(), pos, components })
+            }
+            ["egg", type_id] if data.is_none() => Ok(Command::SpawnEgg(type_id.to_string())),
+            ["back"] if data.is_none() => Ok(Command::Back),
+            ["waypoint"] if data.is_none() => Ok(Command::Waypoint(None)),
+            ["waypoint", x, y, z] if data.is_none() => {
+                Ok(Command::Waypoint(Some([Coordinate::parse(x)?, Coordinate::parse(y)?, Coordinate::parse(z)?])))
+            }
+            ["weather", "clear"] if data.is_none() => Ok(Command::Weather(false)),
+            ["weather", "rain"] if data.is_none() => Ok(Command::Weather(true)),
+            ["worldgen", layer] if data.is_none() => WorldgenLayer::from_name(layer)
+                .map(Command::Worldgen)
+                .ok_or_else(|| format!("Unknown layer {}, try structures, biomes or carvers", layer)),
+            ["summon", ..] => Err("Usage: /summon <type> [x y z] [data]".to_string()),
+            ["egg", ..] => Err("Usage: /egg <type>".to_string()),
+            ["back", ..] => Err("Usage: /back".to_string()),
+            ["waypoint", ..] => Err("Usage: /waypoint [x y z]".to_string()),
+            ["weather", ..] => Err("Usage: /weather <clear|rain>".to_string()),
+            ["worldgen", ..] => Err("Usage: /worldgen <structures|biomes|carvers>".to_string()),
+            [name, ..] => Err(format!("Unknown command /{}", name)),
+            [] => Err("Type a command, e.g. /summon zombie".to_string()),
+        }
+    }
+}
+
+/// Parses the data literal of a command, which is an inline
+/// table of components like `{ name = "Fred", health = 2 }`
+///
+/// # Arguments
+///
+/// * `data` - The data literal
+fn parse_data(data: &str) -> Result<Components, String> {
+    let mut parsed: Components = toml::from_str(&format!("data = {}", data))
+        .map_err(|_| format!("{} isn't a table of components", data))?;
+    match parsed.remove("data") {
+        Some(Value::Table(components)) => Ok(components),
+        _ => Err(format!("{} isn't a table of components", data)),
+    }
+}
+
+/// Resolves the position of a summoned entity
+///
+/// # Arguments
+///
+/// * `pos` - The coordinates of the command
+/// * `player` - The position of the player's feet
+pub fn resolve_pos(pos: Option<[Coordinate; 3]>, player: Vector3<f32>) -> Vector3<f32> {
+    match pos {
+        Some([x, y, z]) => Vector3::new(x.resolve(player.x), y.resolve(player.y), z.resolve(player.z)),
+        None => player,
+    }
+}
+
+/// Console
+///
+/// The command line the player opens with the prefix key to
+/// type a command, which is run by pressing Enter
+#[derive(Debug, Default)]
+pub struct Console {
+    /// Whether the console is open
+    open: bool,
+    /// The typed command
+    line: String,
+}
+
+impl Console {
+    /// Returns whether the console is open
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Opens or closes the console, which clears the line
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.line.clear();
+    }
+
+    /// Appends a character to the command
+    ///
+    /// # Arguments
+    ///
+    /// * `c` - The typed character
+    pub fn type_char(&mut self, c: char) {
+        if self.open && !c.is_control() && self.line.chars().count() < MAX_LINE_LENGTH {
+            self.line.push(c);
+        }
+    }
+
+    /// Removes the last character of the command
+    pub fn erase(&mut self) {
+        self.line.pop();
+    }
+
+    /// Closes the console and returns the typed command,
+    /// or `None` if it wasn't open
+    pub fn submit(&mut self) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+        self.open = false;
+        Some(std::mem::take(&mut self.line))
+    }
+
+    /// Returns the typed command, or `None` if the console is closed
+    pub fn view(&self) -> Option<String> {
+        Some(self.line.clone()).filter(|_| self.open)
+    }
+}
+
+/// ConsoleFrame
+///
+/// A frame the console could be rendered for
+pub trait ConsoleFrame: Frame {
+    /// Returns the typed command, if the console is open
+    fn console(&self) -> Option<&str>;
+}
+
+/// ConsoleRenderer
+///
+/// The `ConsoleRenderer` is the render pass drawing the
+/// open console along the bottom edge of the screen
+pub struct ConsoleRenderer {
+    /// The painter drawing the console
+    painter: OverlayPainter,
+}
+
+impl ConsoleRenderer {
+    /// Creates a new console renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+impl<F: ConsoleFrame> RenderPass<F> for ConsoleRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let line = match ctx.frame.console() {
+            Some(line) => line,
+            None => return,
+        };
+
+        let width = OVERLAY_HEIGHT * ctx.frame.camera().aspect_ratio();
+        let height = GLYPH_SIZE as f32 + 2.0 * CONSOLE_PADDING;
+        let min = [CONSOLE_MARGIN, OVERLAY_HEIGHT - CONSOLE_MARGIN - height];
+        let mut overlay = OverlayBuilder::default();
+        overlay.rect(min, [width - CONSOLE_MARGIN, min[1] + height], Vector4::new(0.0, 0.0, 0.0, 0.65));
+        let text = format!("{}_", line);
+        overlay.text([min[0] + CONSOLE_PADDING, min[1] + CONSOLE_PADDING], &text, Vector4::new(1.0, 1.0, 1.0, 1.0));
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_parsed() {
+        let summon = Command::parse(r#"/summon critters:frog ~ ~1.5 -3 { name = "Fred", health = 2 }"#).unwrap();
+        let (pos, components) = match summon {
+            Command::Summon { type_id, pos, components } if type_id == "critters:frog" => (pos, components),
+            command => panic!("unexpected {:?}", command),
+        };
+        let player = Vector3::new(1.0, 60.0, 2.0);
+        assert_eq!(resolve_pos(pos, player), Vector3::new(1.0, 61.5, -3.0));
+        assert_eq!(components.get("name").and_then(Value::as_str), Some("Fred"));
+        assert_eq!(components.get("health").and_then(Value::as_integer), Some(2));
+
+        assert_eq!(Command::parse("summon zombie"), Ok(Command::Summon {
+            type_id: "zombie".to_string(),
+            pos: None,
+            components: Components::new(),
+        }));
+        assert_eq!(Command::parse("/egg zombie"), Ok(Command::SpawnEgg("zombie".to_string())));
+        assert_eq!(Command::parse("/back"), Ok(Command::Back));
+        assert!(Command::parse("/back home").is_err());
+        assert_eq!(Command::parse("/waypoint"), Ok(Command::Waypoint(None)));
+        assert_eq!(
+            Command::parse("/waypoint 10 ~ -4"),
+            Ok(Command::Waypoint(Some([Coordinate::Absolute(10.0), Coordinate::Relative(0.0), Coordinate::Absolute(-4.0)]))),
+        );
+        assert!(Command::parse("/waypoint 10 4").is_err());
+        assert_eq!(Command::parse("/weather rain"), Ok(Command::Weather(true)));
+        assert_eq!(Command::parse("weather clear"), Ok(Command::Weather(false)));
+        assert!(Command::parse("/weather snow").is_err());
+        assert_eq!(Command::parse("/worldgen biomes"), Ok(Command::Worldgen(WorldgenLayer::Biomes)));
+        assert!(Command::parse("/worldgen caves").is_err());
+        assert!(Command::parse("/summon zombie 1 2").is_err());
+        assert!(Command::parse("/summon zombie 1 x 2").is_err());
+        assert!(Command::parse("/summon zombie { name = }").is_err());
+        assert!(Command::parse("/teleport 1 2 3").is_err());
+        assert!(Command::parse("/").is_err());
+    }
+
+    #[test]
+    fn typing_fills_the_console() {
+        let mut console = Console::default();
+        console.type_char('/');
+        assert_eq!(console.view(), None);
+        assert_eq!(console.submit(), None);
+
+        console.toggle();
+        for c in "/eggs".chars() {
+            console.type_char(c);
+        }
+        console.erase();
+        console.type_char('\n');
+        assert_eq!(console.view(), Some("/egg".to_string()));
+        assert_eq!(console.submit(), Some("/egg".to_string()));
+        assert!(!console.is_open());
+    }
+}
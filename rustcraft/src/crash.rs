@@ -0,0 +1,255 @@
+//! Types to shut the game down safely, either when the player
+//! interrupts it or when it crashes, and to write crash reports
+//! into the directory the world is saved in
+
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::os::raw::c_int;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// The amount of recent commands listed in crash reports
+const RECENT_COMMANDS: usize = 16;
+/// The prefix of the names of crash reports
+const REPORT_PREFIX: &str = "crash-";
+/// The signal sent by Ctrl+C
+const SIGINT: c_int = 2;
+/// The signal sent to terminate the process
+const SIGTERM: c_int = 15;
+
+extern "C" {
+    /// The `signal` function of the C standard library
+    fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+}
+
+/// Whether the game has been asked to shut down
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+/// What's known about the running game for crash reports
+static CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext::new());
+
+/// CrashContext
+///
+/// What's known about the running game, which is written into
+/// crash reports besides the panic and its backtrace
+#[derive(Clone, Debug, Default)]
+pub struct CrashContext {
+    /// The directory the world is saved in, which crash
+    /// reports are written into
+    world: Option<PathBuf>,
+    /// The capabilities of the graphics driver
+    graphics: Vec<String>,
+    /// The names of the loaded mods
+    mods: Vec<String>,
+    /// The most recent commands, the oldest one first
+    commands: VecDeque<String>,
+}
+
+impl CrashContext {
+    /// Creates a new context without anything known
+    pub const fn new() -> Self {
+        Self {
+            world: None,
+            graphics: Vec::new(),
+            mods: Vec::new(),
+            commands: VecDeque::new(),
+        }
+    }
+
+    /// Remembers a command, forgetting the oldest one
+    /// beyond `RECENT_COMMANDS`
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The name of the command
+    pub fn record(&mut self, command: &str) {
+        if self.commands.len() == RECENT_COMMANDS {
+            self.commands.pop_front();
+        }
+        self.commands.push_back(command.to_string());
+    }
+
+    /// Returns the text of a crash report
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The time of the crash in seconds since the Unix epoch
+    /// * `thread` - The name of the thread which panicked
+    /// * `message` - The message and the location of the panic
+    /// * `backtrace` - The backtrace of the panic
+    pub fn report(&self, time: u64, thread: &str, message: &str, backtrace: &str) -> String {
+        let mut report = String::new();
+        let _ = writeln!(report, "Rustcraft crashed at {} in thread '{}'", time, thread);
+        let _ = writeln!(report, "{}", message);
+        let _ = writeln!(report);
+        let _ = writeln!(report, "Graphics:");
+        for line in &self.graphics {
+            let _ = writeln!(report, "    {}", line);
+        }
+        let _ = writeln!(report, "Mods: {}", if self.mods.is_empty() { "none".to_string() } else { self.mods.join(", ") });
+        let _ = writeln!(report, "Last commands:");
+        for command in &self.commands {
+            let _ = writeln!(report, "    {}", command);
+        }
+        let _ = writeln!(report);
+        let _ = writeln!(report, "Backtrace:");
+        let _ = writeln!(report, "{}", backtrace);
+        report
+    }
+}
+
+/// Sets the directory crash reports are written into
+///
+/// # Arguments
+///
+/// * `world` - The directory the world is saved in
+pub fn set_world_dir(world: &Path) {
+    CONTEXT.lock().unwrap().world = Some(world.to_path_buf());
+}
+
+/// Sets the capabilities of the graphics driver listed
+/// in crash reports
+///
+/// # Arguments
+///
+/// * `graphics` - The lines describing the capabilities
+pub fn set_graphics(graphics: Vec<String>) {
+    CONTEXT.lock().unwrap().graphics = graphics;
+}
+
+/// Sets the names of the loaded mods
+///
+/// # Arguments
+///
+/// * `mods` - The names of the mods
+pub fn set_mods(mods: Vec<String>) {
+    CONTEXT.lock().unwrap().mods = mods;
+}
+
+/// Remembers a command for crash reports
+///
+/// # Arguments
+///
+/// * `command` - The name of the command
+pub fn record_command(command: &str) {
+    CONTEXT.lock().unwrap().record(command);
+}
+
+/// Writes a crash report into a directory and returns its path
+///
+/// # Arguments
+///
+/// * `dir` - The directory
+/// * `time` - The time of the crash in seconds since the Unix epoch
+/// * `report` - The text of the report
+pub fn write_report(dir: &Path, time: u64, report: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let mut path = dir.join(format!("{}{}.txt", REPORT_PREFIX, time));
+    let mut suffix = 1;
+    while path.exists() {
+        suffix += 1;
+        path = dir.join(format!("{}{}-{}.txt", REPORT_PREFIX, time, suffix));
+    }
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+/// Installs a panic hook writing a crash report into the
+/// directory of the world before the default hook runs.
+/// The world itself is saved by the simulation as the
+/// panic unwinds.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let message = match (info.payload().downcast_ref::<&str>(), info.payload().downcast_ref::<String>()) {
+            (Some(message), _) => message.to_string(),
+            (_, Some(message)) => message.clone(),
+            _ => "Box<dyn Any>".to_string(),
+        };
+        let message = match info.location() {
+            Some(location) => format!("{} at {}", message, location),
+            None => message,
+        };
+        let thread = thread::current().name().unwrap_or("<unnamed>").to_string();
+        let time = crate::world::edit_log::unix_time();
+
+        // The context could be locked by the panicking thread
+        let context = CONTEXT.try_lock().map(|context| context.clone()).unwrap_or_default();
+        let report = context.report(time, &thread, &message, &Backtrace::force_capture().to_string());
+        match context.world.as_ref().map(|world| write_report(world, time, &report)) {
+            Some(Ok(path)) => log::error!("The game crashed, the crash report has been written to {}", path.display()),
+            Some(Err(error)) => log::error!("Failed to write the crash report: {}\n{}", error, report),
+            None => log::error!("{}", report),
+        }
+        default_hook(info);
+    }));
+}
+
+/// Installs a handler for Ctrl+C and termination requests,
+/// which asks the game to shut down rather than killing it,
+/// so the world is saved before it exits
+pub fn install_interrupt_handler() {
+    // Safety: The handler only stores an atomic flag, which
+    // is safe to do in a signal handler
+    unsafe {
+        signal(SIGINT, on_interrupt);
+        signal(SIGTERM, on_interrupt);
+    }
+}
+
+/// Asks the game to shut down
+pub fn request_shutdown() {
+    SHUTDOWN.store(true, Ordering::SeqCst);
+}
+
+/// Returns whether the game has been asked to shut down
+pub fn shutdown_requested() -> bool {
+    SHUTDOWN.load(Ordering::SeqCst)
+}
+
+/// Handles Ctrl+C and termination requests
+extern "C" fn on_interrupt(_signal: c_int) {
+    request_shutdown();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_list_the_context() {
+        let mut context = CrashContext::new();
+        context.graphics = vec!["Vendor      Mesa".to_string(), "Extensions  GL_ARB_compute_shader".to_string()];
+        context.mods = vec!["scripts/achievements.lua".to_string(), "scripts/portals.lua".to_string()];
+        for i in 0..RECENT_COMMANDS + 2 {
+            context.record(&format!("command {}", i));
+        }
+        assert_eq!(context.commands.len(), RECENT_COMMANDS);
+        assert_eq!(context.commands[0], "command 2");
+
+        let report = context.report(100, "simulation", "boom at src/main.rs:1:1", "0: main");
+        assert!(report.starts_with("Rustcraft crashed at 100 in thread 'simulation'\nboom at src/main.rs:1:1\n"));
+        assert!(report.contains("Graphics:\n    Vendor      Mesa\n    Extensions  GL_ARB_compute_shader\n"));
+        assert!(report.contains("Mods: scripts/achievements.lua, scripts/portals.lua\n"));
+        assert!(report.contains("    command 17\n"));
+        assert!(!report.contains("    command 1\n"));
+        assert!(report.ends_with("Backtrace:\n0: main\n"));
+    }
+
+    #[test]
+    fn reports_are_written_into_the_world() {
+        let dir = std::env::temp_dir().join(format!("rustcraft-crash-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let first = write_report(&dir, 100, "first").unwrap();
+        let second = write_report(&dir, 100, "second").unwrap();
+        assert_eq!(first, dir.join("crash-100.txt"));
+        assert_eq!(second, dir.join("crash-100-2.txt"));
+        assert_eq!(fs::read_to_string(first).unwrap(), "first");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
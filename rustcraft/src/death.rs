@@ -0,0 +1,168 @@
+//! Types to show the death screen until the player respawns,
+//! and to remember where they died
+
+use crate::graphics::backend::GlBackend;
+use crate::graphics::font::{self, GLYPH_SIZE};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use cgmath::{Vector3, Vector4};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The vertical distance of two lines of text
+const LINE_SPACING: f32 = 8.0;
+/// The distance of the label to the edges of the button
+const BUTTON_PADDING: f32 = 6.0;
+
+/// DeathCause
+///
+/// What the player died of
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DeathCause {
+    /// The player fell below the bottom of the world
+    Void,
+    /// The player burned in lava
+    Lava,
+    /// The player has been caught in an explosion
+    Explosion,
+    /// The player ran out of air under water
+    Drowning,
+}
+
+impl DeathCause {
+    /// Returns the message the death screen shows for the cause
+    pub fn message(&self) -> &'static str {
+        match self {
+            DeathCause::Void => "Fell into the void",
+            DeathCause::Lava => "Tried to swim in lava",
+            DeathCause::Explosion => "Blew up",
+            DeathCause::Drowning => "Forgot to come up for air",
+        }
+    }
+}
+
+/// DeathLocation
+///
+/// Where the player died the last time, which is persisted
+/// with the player data, so they could go back there
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeathLocation {
+    /// The name of the dimension the player died in
+    pub dimension: String,
+    /// The position of the player's feet
+    pub pos: [f32; 3],
+}
+
+impl DeathLocation {
+    /// Returns the position of the player's feet
+    pub fn pos(&self) -> Vector3<f32> {
+        self.pos.into()
+    }
+}
+
+/// DeathScreen
+///
+/// The screen shown while the player is dead
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeathScreen {
+    /// What the player died of
+    pub cause: DeathCause,
+    /// The score the player reached
+    pub score: u64,
+}
+
+/// Returns the score of the player, which is the amount
+/// of items they collected
+///
+/// # Arguments
+///
+/// * `items` - The amount of each collected item
+pub fn score(items: &BTreeMap<String, u64>) -> u64 {
+    items.values().sum()
+}
+
+/// DeathFrame
+///
+/// A frame the death screen could be rendered for
+pub trait DeathFrame: Frame {
+    /// Returns the death screen, if the player is dead
+    fn death(&self) -> Option<&DeathScreen>;
+}
+
+/// DeathScreenRenderer
+///
+/// The `DeathScreenRenderer` is the render pass tinting the
+/// screen red while the player is dead, with the cause of
+/// their death, their score and the respawn button centered
+pub struct DeathScreenRenderer {
+    /// The painter drawing the screen
+    painter: OverlayPainter,
+}
+
+impl DeathScreenRenderer {
+    /// Creates a new death screen renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+impl<F: DeathFrame> RenderPass<F> for DeathScreenRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let death = match ctx.frame.death() {
+            Some(death) => death,
+            None => return,
+        };
+
+        let width = OVERLAY_HEIGHT * ctx.frame.camera().aspect_ratio();
+        let line_height = GLYPH_SIZE as f32 + LINE_SPACING;
+        let score = format!("Score: {}", death.score);
+        let button = "Respawn [Enter]";
+        let lines: [(&str, Vector4<f32>); 3] = [
+            ("You died!", Vector4::new(1.0, 1.0, 1.0, 1.0)),
+            (death.cause.message(), Vector4::new(0.85, 0.85, 0.85, 1.0)),
+            (&score, Vector4::new(1.0, 0.85, 0.3, 1.0)),
+        ];
+
+        let mut overlay = OverlayBuilder::default();
+        overlay.rect([0.0, 0.0], [width, OVERLAY_HEIGHT], Vector4::new(0.5, 0.0, 0.0, 0.45));
+        let mut y = OVERLAY_HEIGHT / 3.0;
+        for (text, color) in lines.iter() {
+            overlay.text([(width - font::text_width(text) as f32) / 2.0, y], text, *color);
+            y += line_height;
+        }
+
+        y += line_height;
+        let button_width = font::text_width(button) as f32 + 2.0 * BUTTON_PADDING;
+        let min = [(width - button_width) / 2.0, y - BUTTON_PADDING];
+        overlay.rect(min, [min[0] + button_width, y + GLYPH_SIZE as f32 + BUTTON_PADDING], Vector4::new(0.2, 0.2, 0.2, 0.9));
+        overlay.text([min[0] + BUTTON_PADDING, y], button, Vector4::new(1.0, 1.0, 1.0, 1.0));
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_counts_collected_items() {
+        let items: BTreeMap<String, u64> = vec![("wheat".to_string(), 3), ("sand".to_string(), 4)].into_iter().collect();
+        assert_eq!(score(&items), 7);
+        assert_eq!(score(&BTreeMap::new()), 0);
+
+        let location: DeathLocation = toml::from_str("dimension = \"caves\"\npos = [1.0, 2.0, 3.0]").unwrap();
+        assert_eq!(location.pos(), Vector3::new(1.0, 2.0, 3.0));
+    }
+}
@@ -0,0 +1,359 @@
+//! Types to show the dialogues scripts open, e.g. when the
+//! player talks to an NPC, and the prompt to interact with
+//! the entity the player is looking at
+
+use crate::graphics::backend::GlBackend;
+use crate::graphics::font::{self, GLYPH_SIZE};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use crate::scripting::{self, ScriptEngine, ScriptError};
+use cgmath::Vector4;
+use mlua::{Function, RegistryKey, Table};
+use std::sync::{Arc, Mutex};
+
+/// The key the player interacts with entities by
+pub const INTERACT_KEY: &str = "E";
+/// The maximal amount of options of a dialogue, which
+/// are chosen by the number keys
+pub const MAX_OPTIONS: usize = 9;
+/// The maximal amount of characters of a line of the
+/// text of a dialogue
+const MAX_LINE_LENGTH: usize = 48;
+
+/// The distance of the dialogue to the bottom edge of the screen
+const DIALOGUE_MARGIN: f32 = 24.0;
+/// The distance of the text to the edges of the dialogue
+const DIALOGUE_PADDING: f32 = 8.0;
+/// The vertical distance of two lines of text
+const LINE_SPACING: f32 = 4.0;
+/// The distance of the prompt below the center of the screen
+const PROMPT_OFFSET: f32 = 24.0;
+/// The distance of the prompt to the edges of its background
+const PROMPT_PADDING: f32 = 3.0;
+
+/// Dialogue
+///
+/// A dialogue as it's shown on the screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dialogue {
+    /// The title, e.g. the name of the NPC talking
+    pub title: String,
+    /// The lines of the text
+    pub lines: Vec<String>,
+    /// The options the player chooses from
+    pub options: Vec<String>,
+}
+
+/// A dialogue which is open, with the callbacks of
+/// its options
+struct OpenDialogue {
+    /// The dialogue
+    dialogue: Dialogue,
+    /// The callbacks by the index of their option, or
+    /// `None` for options which only close the dialogue
+    callbacks: Vec<Option<RegistryKey>>,
+}
+
+/// Dialogues
+///
+/// The dialogue scripts open through the `dialogue` API,
+/// e.g. when the player interacts with an NPC:
+///
+/// ```lua
+/// entities.register {
+///     type = "village:farmer",
+///     prompt = "talk",
+///     onInteract = function(farmer, player)
+///         dialogue.open {
+///             title = farmer.data.name,
+///             text = "Nice weather for the crops, isn't it?",
+///             options = {
+///                 { text = "Do you need any help?", onSelect = function(player)
+///                     dialogue.open { title = farmer.data.name, text = "Bring me some wheat!" }
+///                 end },
+///                 { text = "Goodbye" },
+///             },
+///         }
+///     end,
+/// }
+/// ```
+///
+/// The player chooses an option by its number key, which
+/// closes the dialogue and calls the `onSelect` callback of
+/// the option with the name of the player. The callback
+/// could open the next dialogue to branch the conversation.
+/// The player closes a dialogue by interacting again, while
+/// scripts close it through `dialogue.close()`.
+pub struct Dialogues {
+    /// The open dialogue, shared with the Lua API
+    open: Arc<Mutex<Option<OpenDialogue>>>,
+}
+
+impl Dialogues {
+    /// Creates a new dialogue manager and registers its API
+    /// in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let open = Arc::new(Mutex::new(None));
+
+        let lua = engine.lua();
+        let api = scripting::api_table(lua, "dialogue")?;
+        let shared = open.clone();
+        api.set("open", lua.create_function(move |lua, def: Table| {
+            let text: String = def.get("text")?;
+            let mut dialogue = OpenDialogue {
+                dialogue: Dialogue {
+                    title: def.get::<_, Option<String>>("title")?.unwrap_or_default(),
+                    lines: wrap(&text, MAX_LINE_LENGTH),
+                    options: Vec::new(),
+                },
+                callbacks: Vec::new(),
+            };
+            if let Some(options) = def.get::<_, Option<Table>>("options")? {
+                for option in options.sequence_values::<Table>() {
+                    let option = option?;
+                    let callback = match option.get::<_, Option<Function>>("onSelect")? {
+                        Some(callback) => Some(lua.create_registry_value(callback)?),
+                        None => None,
+                    };
+                    dialogue.dialogue.options.push(option.get("text")?);
+                    dialogue.callbacks.push(callback);
+                }
+            }
+            if dialogue.callbacks.len() > MAX_OPTIONS {
+                return Err(mlua::Error::RuntimeError(format!("a dialogue has {} options at most", MAX_OPTIONS)));
+            }
+            *shared.lock().unwrap() = Some(dialogue);
+            Ok(())
+        })?)?;
+        let shared = open.clone();
+        api.set("close", lua.create_function(move |_, ()| {
+            Ok(shared.lock().unwrap().take().is_some())
+        })?)?;
+
+        Ok(Self { open })
+    }
+
+    /// Returns the open dialogue, if any
+    pub fn current(&self) -> Option<Dialogue> {
+        self.open.lock().unwrap().as_ref().map(|open| open.dialogue.clone())
+    }
+
+    /// Returns whether a dialogue is open
+    pub fn is_open(&self) -> bool {
+        self.open.lock().unwrap().is_some()
+    }
+
+    /// Closes the open dialogue without choosing an option
+    pub fn close(&self) {
+        self.open.lock().unwrap().take();
+    }
+
+    /// Chooses an option of the open dialogue, which closes
+    /// it and calls the callback of the option. Returns
+    /// whether there has been an option with the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `index` - The index of the option
+    /// * `player` - The name of the player
+    pub fn choose(&self, engine: &ScriptEngine, index: usize, player: &str) -> Result<bool, ScriptError> {
+        // The callback is taken out first, so that it
+        // could open the next dialogue
+        let callback: Option<Function> = {
+            let mut open = self.open.lock().unwrap();
+            let callback = match open.as_mut() {
+                Some(dialogue) if index < dialogue.callbacks.len() => dialogue.callbacks[index].take(),
+                _ => return Ok(false),
+            };
+            open.take();
+            match callback {
+                Some(key) => Some(engine.lua().registry_value(&key)
+                    .map_err(|error| ScriptError::new("dialogue option", error))?),
+                None => None,
+            }
+        };
+
+        if let Some(callback) = callback {
+            callback.call::<_, ()>(player)
+                .map_err(|error| ScriptError::new("dialogue option", error))?;
+        }
+        Ok(true)
+    }
+}
+
+/// Splits a text into lines of the given amount of
+/// characters at most, breaking them between words where
+/// possible. Line breaks of the text are kept.
+///
+/// # Arguments
+///
+/// * `text` - The text
+/// * `max_length` - The maximal amount of characters of a line
+fn wrap(text: &str, max_length: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            let length = line.chars().count();
+            if length > 0 && length + 1 + word.len() <= max_length {
+                line.push(' ');
+                line.extend(word.iter());
+                continue;
+            }
+            if length > 0 {
+                lines.push(std::mem::take(&mut line));
+            }
+            // Words longer than a line are split
+            while word.len() > max_length {
+                lines.push(word.drain(..max_length).collect());
+            }
+            line.extend(word.iter());
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// DialogueFrame
+///
+/// A frame dialogues and interaction prompts could be
+/// rendered for
+pub trait DialogueFrame: Frame {
+    /// Returns the open dialogue, if any
+    fn dialogue(&self) -> Option<&Dialogue>;
+
+    /// Returns the action of the entity the player could
+    /// interact with, e.g. `talk`
+    fn prompt(&self) -> Option<&str>;
+}
+
+/// DialogueRenderer
+///
+/// The `DialogueRenderer` is the render pass drawing the open
+/// dialogue at the bottom of the screen, with its numbered
+/// options below the text. While no dialogue is open, the
+/// prompt to interact with an entity is drawn below the
+/// center of the screen.
+pub struct DialogueRenderer {
+    /// The painter drawing the dialogue
+    painter: OverlayPainter,
+}
+
+impl DialogueRenderer {
+    /// Creates a new dialogue renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+impl<F: DialogueFrame> RenderPass<F> for DialogueRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let width = OVERLAY_HEIGHT * ctx.frame.camera().aspect_ratio();
+        let mut overlay = OverlayBuilder::default();
+        match (ctx.frame.dialogue(), ctx.frame.prompt()) {
+            (Some(dialogue), _) => {
+                let options: Vec<String> = dialogue.options.iter().enumerate()
+                    .map(|(i, option)| format!("{}. {}", i + 1, option))
+                    .collect();
+                let content_width = dialogue.lines.iter().chain(options.iter())
+                    .map(|line| font::text_width(line))
+                    .fold(font::text_width(&dialogue.title), u32::max) as f32;
+                let line_height = GLYPH_SIZE as f32 + LINE_SPACING;
+                let lines = 1 + dialogue.lines.len() + options.len();
+                let height = lines as f32 * line_height - LINE_SPACING + 3.0 * DIALOGUE_PADDING;
+                let min = [(width - content_width) / 2.0 - DIALOGUE_PADDING, OVERLAY_HEIGHT - DIALOGUE_MARGIN - height];
+                let max = [min[0] + content_width + 2.0 * DIALOGUE_PADDING, OVERLAY_HEIGHT - DIALOGUE_MARGIN];
+                overlay.rect(min, max, Vector4::new(0.0, 0.0, 0.0, 0.65));
+
+                let x = min[0] + DIALOGUE_PADDING;
+                let mut y = min[1] + DIALOGUE_PADDING;
+                overlay.text([x, y], &dialogue.title, Vector4::new(1.0, 0.85, 0.3, 1.0));
+                for line in dialogue.lines.iter() {
+                    y += line_height;
+                    overlay.text([x, y], line, Vector4::new(1.0, 1.0, 1.0, 1.0));
+                }
+                y += DIALOGUE_PADDING;
+                for option in options.iter() {
+                    y += line_height;
+                    overlay.text([x, y], option, Vector4::new(0.55, 0.8, 1.0, 1.0));
+                }
+            }
+            (None, Some(action)) => {
+                let text = format!("Press {} to {}", INTERACT_KEY, action);
+                let text_width = font::text_width(&text) as f32;
+                let min = [(width - text_width) / 2.0, OVERLAY_HEIGHT / 2.0 + PROMPT_OFFSET];
+                overlay.rect(
+                    [min[0] - PROMPT_PADDING, min[1] - PROMPT_PADDING],
+                    [min[0] + text_width + PROMPT_PADDING, min[1] + GLYPH_SIZE as f32 + PROMPT_PADDING],
+                    Vector4::new(0.0, 0.0, 0.0, 0.4),
+                );
+                overlay.text(min, &text, Vector4::new(1.0, 1.0, 1.0, 1.0));
+            }
+            (None, None) => return,
+        }
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_wrapped_between_words() {
+        assert_eq!(wrap("Nice weather for the crops", 12), vec!["Nice weather", "for the", "crops"]);
+        assert_eq!(wrap("Hello\n\nthere", 12), vec!["Hello", "", "there"]);
+        assert_eq!(wrap("Aaaaaaaaaa b", 4), vec!["Aaaa", "aaaa", "aa b"]);
+    }
+
+    #[test]
+    fn options_branch_the_dialogue() {
+        let engine = ScriptEngine::new();
+        let dialogues = Dialogues::new(&engine).unwrap();
+        assert!(!dialogues.choose(&engine, 0, "steve").unwrap());
+
+        engine.exec("test", r#"
+            dialogue.open {
+                title = "Farmer",
+                text = "Nice weather for the crops, isn't it?",
+                options = {
+                    { text = "Do you need any help?", onSelect = function(player)
+                        dialogue.open { title = "Farmer", text = "Bring me some wheat, " .. player .. "!" }
+                    end },
+                    { text = "Goodbye" },
+                },
+            }
+        "#).unwrap();
+        let dialogue = dialogues.current().unwrap();
+        assert_eq!(dialogue.title, "Farmer");
+        assert_eq!(dialogue.options, vec!["Do you need any help?", "Goodbye"]);
+
+        assert!(!dialogues.choose(&engine, 2, "steve").unwrap());
+        assert!(dialogues.choose(&engine, 0, "steve").unwrap());
+        let dialogue = dialogues.current().unwrap();
+        assert_eq!(dialogue.lines, vec!["Bring me some wheat, steve!"]);
+        assert!(dialogue.options.is_empty());
+
+        dialogues.close();
+        assert!(!dialogues.is_open());
+        assert!(engine.exec("test", r#"dialogue.open { title = "Farmer" }"#).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+//! The components shared by the systems, which any kind
+//! of game object could be made of
+
+use crate::entity::Aabb;
+use cgmath::Vector3;
+
+/// Position
+///
+/// The position of an entity in world coordinates. Entities
+/// with a body stand on it, i.e. it's the bottom center of
+/// their body.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Position(pub Vector3<f32>);
+
+/// Velocity
+///
+/// The velocity of an entity in blocks per second
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Velocity(pub Vector3<f32>);
+
+/// Gravity
+///
+/// Pulls an entity down, until it falls with its terminal velocity
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Gravity {
+    /// The acceleration in blocks per second squared
+    pub acceleration: f32,
+    /// The speed the entity doesn't fall faster than
+    pub terminal_velocity: f32,
+}
+
+/// Body
+///
+/// The box an entity takes up, which collides with solid
+/// blocks. Entities without a body pass through blocks.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Body {
+    /// The width along x and z
+    pub width: f32,
+    /// The height above the position
+    pub height: f32,
+}
+
+impl Body {
+    /// Returns the bounding box of the body at a position
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the entity
+    pub fn aabb(&self, pos: &Position) -> Aabb {
+        Aabb::standing(pos.0, self.width, self.height)
+    }
+}
+
+/// Lifetime
+///
+/// How long an entity lives, it's despawned once it's as old
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Lifetime {
+    /// The time in seconds since the entity has been spawned
+    pub age: f32,
+    /// The time in seconds the entity lives for
+    pub lifetime: f32,
+}
+
+impl Lifetime {
+    /// Creates the lifetime of a newly spawned entity
+    ///
+    /// # Arguments
+    ///
+    /// * `lifetime` - The time in seconds the entity lives for
+    pub fn new(lifetime: f32) -> Self {
+        Self {
+            age: 0.0,
+            lifetime,
+        }
+    }
+
+    /// Returns whether the entity reached the end of its lifetime
+    pub fn is_over(&self) -> bool {
+        self.age >= self.lifetime
+    }
+}
@@ -0,0 +1,334 @@
+//! A small entity component system, so that different kinds of
+//! game objects share the systems moving, colliding and aging
+//! them instead of each bringing their own
+
+pub mod components;
+pub mod systems;
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// EntityId
+///
+/// The handle of an entity of an `Ecs`. The slots of despawned
+/// entities are reused, so each id carries the generation of its
+/// slot and handles of despawned entities never refer to the
+/// entity taking their slot.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct EntityId {
+    /// The slot of the entity
+    index: u32,
+    /// The generation of the slot the entity has been spawned in
+    generation: u32,
+}
+
+/// The components of a single type by the slot of their entity
+struct Storage<T> {
+    components: Vec<Option<T>>,
+}
+
+/// A storage of components whose type has been erased,
+/// so storages of all types could be kept together
+trait AnyStorage: Send {
+    /// Removes the component of the entity in a slot, if any
+    fn remove_slot(&mut self, index: usize);
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Send + 'static> AnyStorage for Storage<T> {
+    fn remove_slot(&mut self, index: usize) {
+        if let Some(component) = self.components.get_mut(index) {
+            *component = None;
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Ecs
+///
+/// The entities of a group of game objects with their components.
+/// An entity is just an id, what it is and how it behaves is up
+/// to the components attached to it, e.g.
+///
+/// ```ignore
+/// let mut ecs = Ecs::default();
+/// let spark = ecs.spawn();
+/// ecs.insert(spark, Position(Vector3::new(0.0, 10.0, 0.0)));
+/// ecs.insert(spark, Velocity(Vector3::new(0.0, 2.0, 0.0)));
+/// ecs.insert(spark, Lifetime::new(0.5));
+/// systems::physics(&mut ecs, seconds, block_at);
+/// systems::age(&mut ecs, seconds);
+/// ```
+///
+/// Any `Send + 'static` type is a component, so entities could
+/// be simulated on their own thread. Systems are functions over
+/// the entities with the components they need, see `systems`.
+#[derive(Default)]
+pub struct Ecs {
+    /// The generation of each slot, which is increased
+    /// whenever the entity in the slot is despawned
+    generations: Vec<u32>,
+    /// Whether an entity lives in each slot
+    alive: Vec<bool>,
+    /// The slots of despawned entities, which are reused first
+    free: Vec<u32>,
+    /// The storages of the components by their type
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+}
+
+impl Ecs {
+    /// Spawns an entity without any components
+    pub fn spawn(&mut self) -> EntityId {
+        match self.free.pop() {
+            Some(index) => {
+                self.alive[index as usize] = true;
+                EntityId { index, generation: self.generations[index as usize] }
+            }
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+                EntityId { index: self.generations.len() as u32 - 1, generation: 0 }
+            }
+        }
+    }
+
+    /// Despawns an entity and drops its components. Returns
+    /// whether the entity has been alive.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the entity
+    pub fn despawn(&mut self, id: EntityId) -> bool {
+        if !self.is_alive(id) {
+            return false;
+        }
+        let index = id.index as usize;
+        for storage in self.storages.values_mut() {
+            storage.remove_slot(index);
+        }
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(id.index);
+        true
+    }
+
+    /// Despawns all entities
+    pub fn clear(&mut self) {
+        for id in self.entities().collect::<Vec<EntityId>>() {
+            self.despawn(id);
+        }
+    }
+
+    /// Returns whether an entity is alive
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the entity
+    pub fn is_alive(&self, id: EntityId) -> bool {
+        let index = id.index as usize;
+        index < self.alive.len() && self.alive[index] && self.generations[index] == id.generation
+    }
+
+    /// Returns the amount of living entities
+    pub fn len(&self) -> usize {
+        self.alive.iter().filter(|&&alive| alive).count()
+    }
+
+    /// Returns whether no entity is alive
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the ids of the living entities in the
+    /// order of their slots
+    pub fn entities(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.alive.iter().enumerate()
+            .filter(|(_, &alive)| alive)
+            .map(move |(index, _)| EntityId { index: index as u32, generation: self.generations[index] })
+    }
+
+    /// Attaches a component to an entity, which replaces the
+    /// component of the same type it had. Returns whether the
+    /// entity is alive, as nothing is attached otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the entity
+    /// * `component` - The component
+    pub fn insert<T: Send + 'static>(&mut self, id: EntityId, component: T) -> bool {
+        if !self.is_alive(id) {
+            return false;
+        }
+        let index = id.index as usize;
+        let components = &mut self.storage_mut::<T>().components;
+        if components.len() <= index {
+            components.resize_with(index + 1, || None);
+        }
+        components[index] = Some(component);
+        true
+    }
+
+    /// Detaches the component of a type from an entity and returns it
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the entity
+    pub fn remove<T: 'static>(&mut self, id: EntityId) -> Option<T> {
+        if !self.is_alive(id) {
+            return None;
+        }
+        self.storages.get_mut(&TypeId::of::<T>())?
+            .as_any_mut().downcast_mut::<Storage<T>>()?
+            .components.get_mut(id.index as usize)?
+            .take()
+    }
+
+    /// Returns the component of a type of an entity
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the entity
+    pub fn get<T: 'static>(&self, id: EntityId) -> Option<&T> {
+        if !self.is_alive(id) {
+            return None;
+        }
+        self.components::<T>()?.get(id.index as usize)?.as_ref()
+    }
+
+    /// Returns the mutable component of a type of an entity
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the entity
+    pub fn get_mut<T: 'static>(&mut self, id: EntityId) -> Option<&mut T> {
+        if !self.is_alive(id) {
+            return None;
+        }
+        self.storages.get_mut(&TypeId::of::<T>())?
+            .as_any_mut().downcast_mut::<Storage<T>>()?
+            .components.get_mut(id.index as usize)?
+            .as_mut()
+    }
+
+    /// Returns the entities with a component of a type
+    /// together with it, in the order of their slots
+    pub fn iter<T: 'static>(&self) -> impl Iterator<Item = (EntityId, &T)> + '_ {
+        self.components::<T>().into_iter()
+            .flat_map(|components| components.iter().enumerate())
+            .filter_map(move |(index, component)| Some((self.id_of(index), component.as_ref()?)))
+    }
+
+    /// Calls a function with each entity with a component of a
+    /// type and the mutable component. The function could read
+    /// all other components of the entities, which is how systems
+    /// join several components, while the components of the type
+    /// itself are hidden from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The function called for each entity
+    pub fn for_each_mut<T: 'static, F>(&mut self, mut f: F)
+        where F: FnMut(&Ecs, EntityId, &mut T)
+    {
+        // The storage is taken out while the function runs, so the
+        // other storages could be borrowed next to its components
+        let mut storage = match self.storages.remove(&TypeId::of::<T>()) {
+            Some(storage) => storage,
+            None => return,
+        };
+        let components = &mut storage.as_any_mut().downcast_mut::<Storage<T>>().unwrap().components;
+        for (index, component) in components.iter_mut().enumerate() {
+            if let Some(component) = component {
+                f(self, self.id_of(index), component);
+            }
+        }
+        self.storages.insert(TypeId::of::<T>(), storage);
+    }
+
+    /// Returns the components of a type by the slot of their entity
+    fn components<T: 'static>(&self) -> Option<&Vec<Option<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?;
+        Some(&storage.as_any().downcast_ref::<Storage<T>>()?.components)
+    }
+
+    /// Returns the storage of the components of a type,
+    /// which is created if there's none yet
+    fn storage_mut<T: Send + 'static>(&mut self) -> &mut Storage<T> {
+        self.storages.entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Storage::<T> { components: Vec::new() }))
+            .as_any_mut().downcast_mut::<Storage<T>>().unwrap()
+    }
+
+    /// Returns the id of the entity living in a slot
+    fn id_of(&self, index: usize) -> EntityId {
+        EntityId { index: index as u32, generation: self.generations[index] }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components_belong_to_living_entities() {
+        let mut ecs = Ecs::default();
+        let first = ecs.spawn();
+        let second = ecs.spawn();
+        assert!(ecs.insert(first, 1u8));
+        assert!(ecs.insert(first, "first"));
+        assert!(ecs.insert(second, 2u8));
+        assert_eq!(ecs.get::<u8>(first), Some(&1));
+        assert_eq!(ecs.get::<&str>(second), None);
+
+        *ecs.get_mut::<u8>(second).unwrap() += 1;
+        let bytes: Vec<(EntityId, u8)> = ecs.iter::<u8>().map(|(id, byte)| (id, *byte)).collect();
+        assert_eq!(bytes, vec![(first, 1), (second, 3)]);
+        assert_eq!(ecs.remove::<&str>(first), Some("first"));
+        assert_eq!(ecs.remove::<&str>(first), None);
+
+        // Despawned entities lose their components, and their
+        // ids don't refer to the entities reusing their slots
+        assert!(ecs.despawn(first));
+        assert!(!ecs.despawn(first));
+        let third = ecs.spawn();
+        assert_ne!(third, first);
+        assert!(!ecs.is_alive(first));
+        assert_eq!(ecs.get::<u8>(third), None);
+        assert!(!ecs.insert(first, 5u8));
+        assert_eq!(ecs.entities().collect::<Vec<EntityId>>(), vec![third, second]);
+        assert_eq!(ecs.len(), 2);
+
+        ecs.clear();
+        assert!(ecs.is_empty());
+        assert_eq!(ecs.iter::<u8>().count(), 0);
+    }
+
+    #[test]
+    fn systems_join_components() {
+        let mut ecs = Ecs::default();
+        for value in 1..=3u32 {
+            let id = ecs.spawn();
+            ecs.insert(id, value);
+            if value != 2 {
+                ecs.insert(id, value as f32 * 10.0);
+            }
+        }
+        ecs.for_each_mut::<u32, _>(|ecs, id, value| {
+            if let Some(bonus) = ecs.get::<f32>(id) {
+                *value += *bonus as u32;
+            }
+        });
+        let values: Vec<u32> = ecs.iter::<u32>().map(|(_, value)| *value).collect();
+        assert_eq!(values, vec![11, 2, 33]);
+    }
+}
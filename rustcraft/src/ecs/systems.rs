@@ -0,0 +1,178 @@
+//! The systems advancing the entities of an `Ecs` by their
+//! components. The steps they are made of are public as well,
+//! so entities ticked one by one, e.g. items taking turns
+//! within a budget, are moved the same way.
+
+use crate::ecs::{Ecs, EntityId};
+use crate::ecs::components::{Body, Gravity, Lifetime, Position, Velocity};
+use crate::entity;
+use crate::world::block::Material;
+use cgmath::Vector3;
+
+/// Accelerates a falling entity by its gravity
+///
+/// # Arguments
+///
+/// * `velocity` - The velocity of the entity
+/// * `gravity` - The gravity of the entity
+/// * `seconds` - The time passed since the last update
+pub fn fall(velocity: &mut Velocity, gravity: &Gravity, seconds: f32) {
+    velocity.0.y = (velocity.0.y - gravity.acceleration * seconds).max(-gravity.terminal_velocity);
+}
+
+/// Moves an entity by its velocity. A body collides with the
+/// solid blocks in its way the same way the player does, and
+/// stops moving along the axes it's blocked on. Returns whether
+/// it landed on a block.
+///
+/// # Arguments
+///
+/// * `pos` - The position of the entity
+/// * `velocity` - The velocity of the entity
+/// * `body` - The body of the entity, if it collides with blocks
+/// * `seconds` - The time passed since the last update
+/// * `block_at` - Returns the material of the block at a position
+pub fn move_body<F>(pos: &mut Position, velocity: &mut Velocity, body: Option<&Body>, seconds: f32, block_at: &F) -> bool
+    where F: Fn(Vector3<i32>) -> Option<Material>
+{
+    let delta = velocity.0 * seconds;
+    let body = match body {
+        Some(body) => body,
+        None => {
+            pos.0 += delta;
+            return false;
+        }
+    };
+
+    // Bodies move along one axis after another,
+    // starting with the vertical one to land first
+    let mut landed = false;
+    for &axis in [1, 0, 2].iter() {
+        let moved = entity::sweep_axis(&body.aabb(pos), axis, delta[axis], block_at);
+        pos.0[axis] += moved;
+        if moved != delta[axis] {
+            landed |= axis == 1 && delta[axis] < 0.0;
+            velocity.0[axis] = 0.0;
+        }
+    }
+    landed
+}
+
+/// Pulls the entities with a gravity down and moves the
+/// ones with a velocity, see `fall` and `move_body`
+///
+/// # Arguments
+///
+/// * `ecs` - The entities
+/// * `seconds` - The time passed since the last update
+/// * `block_at` - Returns the material of the block at a position
+pub fn physics<F>(ecs: &mut Ecs, seconds: f32, block_at: F)
+    where F: Fn(Vector3<i32>) -> Option<Material>
+{
+    let moving: Vec<EntityId> = ecs.iter::<Velocity>().map(|(id, _)| id).collect();
+    for id in moving {
+        physics_of(ecs, id, seconds, &block_at);
+    }
+}
+
+/// Pulls a single entity down if it has a gravity and moves it
+/// if it has a position, see `physics`. Returns whether it
+/// landed on a block.
+///
+/// # Arguments
+///
+/// * `ecs` - The entities
+/// * `id` - The id of the entity
+/// * `seconds` - The time passed since the entity has been moved
+/// * `block_at` - Returns the material of the block at a position
+pub fn physics_of<F>(ecs: &mut Ecs, id: EntityId, seconds: f32, block_at: &F) -> bool
+    where F: Fn(Vector3<i32>) -> Option<Material>
+{
+    let mut velocity = match ecs.get::<Velocity>(id) {
+        Some(&velocity) => velocity,
+        None => return false,
+    };
+    if let Some(gravity) = ecs.get::<Gravity>(id) {
+        fall(&mut velocity, gravity, seconds);
+    }
+    let body = ecs.get::<Body>(id).copied();
+    let landed = match ecs.get_mut::<Position>(id) {
+        Some(pos) => move_body(pos, &mut velocity, body.as_ref(), seconds, block_at),
+        None => false,
+    };
+    ecs.insert(id, velocity);
+    landed
+}
+
+/// Ages the entities with a lifetime and despawns the ones which
+/// reached its end. Returns the ids of the despawned entities.
+///
+/// # Arguments
+///
+/// * `ecs` - The entities
+/// * `seconds` - The time passed since the last update
+pub fn age(ecs: &mut Ecs, seconds: f32) -> Vec<EntityId> {
+    let mut expired = Vec::new();
+    ecs.for_each_mut::<Lifetime, _>(|_, id, lifetime| {
+        lifetime.age += seconds;
+        if lifetime.is_over() {
+            expired.push(id);
+        }
+    });
+    for &id in expired.iter() {
+        ecs.despawn(id);
+    }
+    expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A world whose blocks below `y = 0` are stone
+    fn ground(pos: Vector3<i32>) -> Option<Material> {
+        Some(if pos.y < 0 { Material::Stone } else { Material::Air })
+    }
+
+    #[test]
+    fn bodies_fall_onto_the_ground() {
+        let mut ecs = Ecs::default();
+        let gravity = Gravity { acceleration: 20.0, terminal_velocity: 10.0 };
+        let falling = ecs.spawn();
+        ecs.insert(falling, Position(Vector3::new(0.5, 4.0, 0.5)));
+        ecs.insert(falling, Velocity(Vector3::new(1.0, 0.0, 0.0)));
+        ecs.insert(falling, gravity);
+        ecs.insert(falling, Body { width: 0.5, height: 0.5 });
+
+        // Entities without a body pass through the ground
+        let ghost = ecs.spawn();
+        ecs.insert(ghost, Position(Vector3::new(0.5, 4.0, 0.5)));
+        ecs.insert(ghost, Velocity(Vector3::new(0.0, 0.0, 0.0)));
+        ecs.insert(ghost, gravity);
+
+        for _ in 0..30 {
+            physics(&mut ecs, 1.0 / 30.0, ground);
+        }
+        let pos = ecs.get::<Position>(falling).unwrap().0;
+        assert!(pos.y >= 0.0 && pos.y < 0.01, "{:?}", pos);
+        assert!(pos.x > 1.0);
+        assert_eq!(ecs.get::<Velocity>(falling).unwrap().0.y, 0.0);
+        assert!(ecs.get::<Position>(ghost).unwrap().0.y < -1.0);
+        assert_eq!(ecs.get::<Velocity>(ghost).unwrap().0.y, -gravity.terminal_velocity);
+    }
+
+    #[test]
+    fn entities_despawn_at_the_end_of_their_lifetime() {
+        let mut ecs = Ecs::default();
+        let short = ecs.spawn();
+        ecs.insert(short, Lifetime::new(0.5));
+        let long = ecs.spawn();
+        ecs.insert(long, Lifetime::new(1.0));
+        let endless = ecs.spawn();
+
+        assert!(age(&mut ecs, 0.25).is_empty());
+        assert_eq!(age(&mut ecs, 0.25), vec![short]);
+        assert_eq!(age(&mut ecs, 0.5), vec![long]);
+        assert_eq!(ecs.entities().collect::<Vec<EntityId>>(), vec![endless]);
+    }
+}
@@ -1,6 +1,618 @@
 //! Types and traits representing entities in the game
 
-use cgmath::{Vector3, Zero, Matrix4};
+use crate::world::block::Material;
+use cgmath::{InnerSpace, Vector2, Vector3, Zero, Matrix4};
+use serde::Deserialize;
+
+/// The width of the player's body
+pub const PLAYER_WIDTH: f32 = 0.6;
+/// The height of the player's body
+pub const PLAYER_HEIGHT: f32 = 1.8;
+/// The height of the player's eyes above their feet
+pub const PLAYER_EYE_HEIGHT: f32 = 1.62;
+/// The height of the player's body while they sneak
+pub const SNEAK_HEIGHT: f32 = 1.5;
+/// The height of the player's eyes above their feet while they sneak
+pub const SNEAK_EYE_HEIGHT: f32 = 1.27;
+/// The acceleration of falling entities in blocks per second squared
+pub const GRAVITY: f32 = 28.0;
+/// The speed falling entities don't exceed in blocks per second
+const TERMINAL_VELOCITY: f32 = 60.0;
+/// The share of the gravity the player feels in fluids
+const FLUID_GRAVITY: f32 = 0.2;
+/// The speed the player sinks with in fluids at most
+const SINK_SPEED: f32 = 2.0;
+/// The speed the player swims up and down with
+const SWIM_SPEED: f32 = 3.0;
+/// The share of the walking speed the player has in fluids
+const FLUID_WALK_SPEED: f32 = 0.5;
+/// The speed the player climbs up and down with
+const CLIMB_SPEED: f32 = 2.5;
+/// The share of the walking speed the player has while they sneak
+const SNEAK_SPEED: f32 = 0.3;
+/// The deepest drop a sneaking player walks down, they stop
+/// at the edges of higher ledges
+const EDGE_DROP: f32 = 0.6;
+/// The distance the walk of a sneaking player is shortened by
+/// at a time, until they no longer walk off an edge
+const EDGE_STEP: f32 = 0.05;
+/// The time in seconds the player holds their breath under water
+pub const MAX_AIR: f32 = 15.0;
+/// How much faster the player catches their breath than they lose it
+const AIR_REFILL: f32 = 5.0;
+/// The lowest friction, so that the player always comes to a halt
+const MIN_FRICTION: f32 = 0.1;
+/// The gap kept between colliding boxes, so that boxes
+/// touching a block aren't caught on its edges
+const SKIN: f32 = 1e-3;
+
+/// MovementProfile
+///
+/// How the player moves, declared in the `[movement]` table
+/// of the server resource, so the feel of the movement could be
+/// tuned without rebuilding the game, e.g.
+///
+/// ```toml
+/// [movement]
+/// walk_speed = 4.3
+/// sprint_speed = 5.6
+/// jump_height = 1.25
+/// air_control = 0.2
+/// friction = 12.0
+/// ```
+///
+/// The player speeds up to the speed they walk with and slows
+/// down again by the friction. In the air they keep most of their
+/// momentum, e.g. when they jump while they sprint, and only steer
+/// with the share of the friction given by the air control.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct MovementProfile {
+    /// The speed the player walks with in blocks per second
+    pub walk_speed: f32,
+    /// The speed the player sprints with in blocks per second
+    pub sprint_speed: f32,
+    /// The height in blocks the player jumps up
+    pub jump_height: f32,
+    /// The share of the friction the player steers with in
+    /// the air, from `0` to keep their momentum to `1` to
+    /// steer as on the ground
+    pub air_control: f32,
+    /// How quickly the player reaches the speed they walk with
+    /// and comes to a halt, the higher the snappier
+    pub friction: f32,
+}
+
+impl Default for MovementProfile {
+    fn default() -> Self {
+        Self {
+            walk_speed: 4.3,
+            sprint_speed: 5.6,
+            jump_height: 1.25,
+            air_control: 0.2,
+            friction: 12.0,
+        }
+    }
+}
+
+impl MovementProfile {
+    /// Returns the profile with its values clamped to their
+    /// ranges, e.g. after it has been parsed
+    pub fn clamped(self) -> Self {
+        Self {
+            walk_speed: self.walk_speed.max(0.0),
+            sprint_speed: self.sprint_speed.max(0.0),
+            jump_height: self.jump_height.max(0.0),
+            air_control: self.air_control.clamp(0.0, 1.0),
+            friction: self.friction.max(MIN_FRICTION),
+        }
+    }
+
+    /// Returns the upwards speed in blocks per second
+    /// which lifts the player to the jump height
+    pub fn jump_speed(&self) -> f32 {
+        (2.0 * GRAVITY * self.jump_height).sqrt()
+    }
+}
+
+/// Aabb
+///
+/// An axis aligned bounding box, used to check
+/// whether entities and blocks overlap.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    /// The minimal corner of the box
+    pub min: Vector3<f32>,
+    /// The maximal corner of the box
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    /// Creates a new bounding box from its corners
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - The minimal corner of the box
+    /// * `max` - The maximal corner of the box
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Self {
+            min,
+            max,
+        }
+    }
+
+    /// Creates the bounding box of the block at the given
+    /// position in world coordinates
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block
+    pub fn block(pos: Vector3<i32>) -> Self {
+        let min = Vector3::new(pos.x as f32, pos.y as f32, pos.z as f32);
+        Self::new(min, min + Vector3::new(1.0, 1.0, 1.0))
+    }
+
+    /// Creates the bounding box of the player's body
+    /// from the position of their eyes
+    ///
+    /// # Arguments
+    ///
+    /// * `eye` - The position of the player's eyes
+    pub fn player(eye: Vector3<f32>) -> Self {
+        Self::body(eye - Vector3::new(0.0, PLAYER_EYE_HEIGHT, 0.0), PLAYER_HEIGHT)
+    }
+
+    /// Creates the bounding box of a player's body as wide
+    /// as a player standing on the given position
+    ///
+    /// # Arguments
+    ///
+    /// * `feet` - The position of the player's feet
+    /// * `height` - The height of the player's body
+    fn body(feet: Vector3<f32>, height: f32) -> Self {
+        Self::standing(feet, PLAYER_WIDTH, height)
+    }
+
+    /// Creates the bounding box of a body standing on the
+    /// given position, centered around it horizontally
+    ///
+    /// # Arguments
+    ///
+    /// * `bottom` - The position of the bottom center of the body
+    /// * `width` - The width of the body along x and z
+    /// * `height` - The height of the body
+    pub fn standing(bottom: Vector3<f32>, width: f32, height: f32) -> Self {
+        let half_width = width / 2.0;
+        Self::new(
+            bottom - Vector3::new(half_width, 0.0, half_width),
+            bottom + Vector3::new(half_width, height, half_width),
+        )
+    }
+
+    /// Returns whether this box overlaps with another one.
+    /// Boxes which are just touching don't overlap.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The other bounding box
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x < other.max.x && self.max.x > other.min.x
+            && self.min.y < other.max.y && self.max.y > other.min.y
+            && self.min.z < other.max.z && self.max.z > other.min.z
+    }
+
+    /// Returns the distance along a ray to where it enters the
+    /// box, `0` if it starts inside, or `None` if it misses it
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The origin of the ray
+    /// * `dir` - The direction of the ray, doesn't need to be normalized
+    pub fn ray_distance(&self, origin: Vector3<f32>, dir: Vector3<f32>) -> Option<f32> {
+        if dir.is_zero() {
+            return None;
+        }
+        let dir = dir.normalize();
+
+        let mut near = 0.0f32;
+        let mut far = f32::INFINITY;
+        for axis in 0..3 {
+            if dir[axis] == 0.0 {
+                if origin[axis] < self.min[axis] || origin[axis] > self.max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let a = (self.min[axis] - origin[axis]) / dir[axis];
+            let b = (self.max[axis] - origin[axis]) / dir[axis];
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+        }
+        if near <= far {
+            Some(near)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns whether entities collide with a block. They pass
+/// through air, plants and fluids. Blocks which aren't loaded
+/// yet are solid, so entities don't fall through them.
+///
+/// # Arguments
+///
+/// * `block` - The material of the block, if it's loaded
+pub fn collides(block: Option<Material>) -> bool {
+    match block {
+        Some(material) => material.is_solid() && !material.is_fluid(),
+        None => true,
+    }
+}
+
+/// Moves a bounding box along a single axis until it hits a
+/// block it collides with, and returns how far it moved
+///
+/// # Arguments
+///
+/// * `aabb` - The bounding box, which shouldn't be in a block
+/// * `axis` - The axis it moves along, `0` to `2` for x to z
+/// * `delta` - How far it moves along the axis
+/// * `block_at` - Returns the material of the block at a position
+pub fn sweep_axis<F>(aabb: &Aabb, axis: usize, delta: f32, block_at: &F) -> f32
+    where F: Fn(Vector3<i32>) -> Option<Material>
+{
+    if delta == 0.0 {
+        return 0.0;
+    }
+    // The blocks the box covers across the axis
+    let covered = |other: usize| (aabb.min[other] + SKIN).floor() as i32..=(aabb.max[other] - SKIN).floor() as i32;
+    let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+    let blocked = |layer: i32| covered(a).any(|i| covered(b).any(|j| {
+        let mut pos = Vector3::new(0, 0, 0);
+        pos[axis] = layer;
+        pos[a] = i;
+        pos[b] = j;
+        collides(block_at(pos))
+    }));
+
+    if delta > 0.0 {
+        let first = (aabb.max[axis] - SKIN).ceil() as i32;
+        let last = (aabb.max[axis] + delta).floor() as i32;
+        match (first..=last).find(|&layer| blocked(layer)) {
+            Some(layer) => (layer as f32 - SKIN - aabb.max[axis]).clamp(0.0, delta),
+            None => delta,
+        }
+    } else {
+        let first = (aabb.min[axis] + SKIN).floor() as i32 - 1;
+        let last = (aabb.min[axis] + delta).floor() as i32;
+        match (last..=first).rev().find(|&layer| blocked(layer)) {
+            Some(layer) => ((layer + 1) as f32 + SKIN - aabb.min[axis]).clamp(delta, 0.0),
+            None => delta,
+        }
+    }
+}
+
+/// MovementMode
+///
+/// How the player moves through the world:
+/// * `Walking` - They walk on the ground, fall and jump
+/// * `Swimming` - They are in a fluid, where they sink slowly
+///   and swim up and down
+/// * `Climbing` - They are on a climbable block like a ladder,
+///   which they climb up and down without falling
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MovementMode {
+    Walking,
+    Swimming,
+    Climbing,
+}
+
+/// Controls
+///
+/// How the player wants to move
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Controls {
+    /// The horizontal direction the player walks in,
+    /// which is normalized if it's longer than a block
+    pub walk: Vector2<f32>,
+    /// Whether the player jumps, swims up or climbs up
+    pub up: bool,
+    /// Whether the player dives or climbs down
+    pub down: bool,
+    /// Whether the player sneaks while they walk
+    pub sneak: bool,
+    /// Whether the player sprints while they walk
+    pub sprint: bool,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self {
+            walk: Vector2::zero(),
+            up: false,
+            down: false,
+            sneak: false,
+            sprint: false,
+        }
+    }
+}
+
+/// Player
+///
+/// The body of the player, which falls, walks and jumps
+/// through the world. It collides with the blocks around it
+/// and the camera is attached to its eyes. The player swims
+/// in fluids and climbs climbable blocks, and holds their
+/// breath while their eyes are under water. Sneaking players
+/// duck, walk slowly and don't walk off the edges of ledges.
+/// The speeds, the jump and the friction are tuned by the
+/// movement profile.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Player {
+    /// The position of the player's feet
+    pos: Vector3<f32>,
+    /// The velocity in blocks per second
+    velocity: Vector3<f32>,
+    /// Whether the player stands on a block
+    on_ground: bool,
+    /// How the player moves
+    mode: MovementMode,
+    /// Whether the player sneaks
+    sneaking: bool,
+    /// Whether the player sprints
+    sprinting: bool,
+    /// How the player moves
+    profile: MovementProfile,
+    /// The time in seconds the player could still hold
+    /// their breath for
+    air: f32,
+}
+
+impl Player {
+    /// Creates a standing player with their eyes at the
+    /// given position
+    ///
+    /// # Arguments
+    ///
+    /// * `eye` - The position of the player's eyes
+    pub fn at_eye(eye: Vector3<f32>) -> Self {
+        Self {
+            pos: eye - Vector3::new(0.0, PLAYER_EYE_HEIGHT, 0.0),
+            velocity: Vector3::zero(),
+            on_ground: false,
+            mode: MovementMode::Walking,
+            sneaking: false,
+            sprinting: false,
+            profile: MovementProfile::default(),
+            air: MAX_AIR,
+        }
+    }
+
+    /// Returns the position of the player's feet
+    pub fn pos(&self) -> &Vector3<f32> {
+        &self.pos
+    }
+
+    /// Returns the position of the player's eyes, which
+    /// are lower while they sneak
+    pub fn eye(&self) -> Vector3<f32> {
+        let height = if self.sneaking { SNEAK_EYE_HEIGHT } else { PLAYER_EYE_HEIGHT };
+        self.pos + Vector3::new(0.0, height, 0.0)
+    }
+
+    /// Returns the velocity in blocks per second
+    pub fn velocity(&self) -> &Vector3<f32> {
+        &self.velocity
+    }
+
+    /// Returns whether the player stands on a block
+    pub fn is_on_ground(&self) -> bool {
+        self.on_ground
+    }
+
+    /// Returns how the player moves
+    pub fn mode(&self) -> MovementMode {
+        self.mode
+    }
+
+    /// Returns whether the player sneaks
+    pub fn is_sneaking(&self) -> bool {
+        self.sneaking
+    }
+
+    /// Returns whether the player sprints
+    pub fn is_sprinting(&self) -> bool {
+        self.sprinting
+    }
+
+    /// Changes how the player moves, which takes effect
+    /// with the next update
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - The movement profile
+    pub fn set_profile(&mut self, profile: MovementProfile) {
+        self.profile = profile;
+    }
+
+    /// Returns the time in seconds the player could still
+    /// hold their breath for
+    pub fn air(&self) -> f32 {
+        self.air
+    }
+
+    /// Returns whether the player ran out of air
+    pub fn is_drowning(&self) -> bool {
+        self.air <= 0.0
+    }
+
+    /// Returns the bounding box of the player's body,
+    /// which is lower while they sneak
+    pub fn aabb(&self) -> Aabb {
+        Aabb::body(self.pos, if self.sneaking { SNEAK_HEIGHT } else { PLAYER_HEIGHT })
+    }
+
+    /// Moves the player's eyes to the given position at
+    /// once, which stops their movement, e.g. when they
+    /// are teleported. They keep holding their breath.
+    ///
+    /// # Arguments
+    ///
+    /// * `eye` - The position of the player's eyes
+    pub fn teleport(&mut self, eye: Vector3<f32>) {
+        *self = Self {
+            air: self.air,
+            profile: self.profile,
+            ..Self::at_eye(eye)
+        };
+    }
+
+    /// Lets the player breathe again at once, e.g. when they
+    /// respawn
+    pub fn refill_air(&mut self) {
+        self.air = MAX_AIR;
+    }
+
+    /// Returns how the player moves at their position. Climbable
+    /// blocks at their feet or at their body hold them, otherwise
+    /// they swim while their feet are in a fluid.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_at` - Returns the material of the block at a position
+    fn movement_mode<F>(&self, block_at: &F) -> MovementMode
+        where F: Fn(Vector3<i32>) -> Option<Material>
+    {
+        let feet = self.pos.map(|value| value.floor() as i32);
+        let body = feet + Vector3::new(0, 1, 0);
+        let climbable = |pos| matches!(block_at(pos), Some(material) if material.data().is_climbable());
+        if climbable(feet) || climbable(body) {
+            MovementMode::Climbing
+        } else if matches!(block_at(feet), Some(material) if material.is_fluid()) {
+            MovementMode::Swimming
+        } else {
+            MovementMode::Walking
+        }
+    }
+
+    /// Returns whether a sneaking player could walk their body
+    /// from its bounding box to the given one without dropping
+    /// deeper than `EDGE_DROP`
+    ///
+    /// # Arguments
+    ///
+    /// * `aabb` - The bounding box of the body after the walk
+    /// * `block_at` - Returns the material of the block at a position
+    fn is_supported<F>(aabb: &Aabb, block_at: &F) -> bool
+        where F: Fn(Vector3<i32>) -> Option<Material>
+    {
+        sweep_axis(aabb, 1, -EDGE_DROP, block_at) > -EDGE_DROP
+    }
+
+    /// Shortens the walk of a sneaking player along a horizontal
+    /// axis until they wouldn't drop off an edge, so they stop at
+    /// the edges of ledges. Returns how far they walk.
+    ///
+    /// # Arguments
+    ///
+    /// * `axis` - The axis they walk along, `0` or `2` for x or z
+    /// * `delta` - How far they want to walk along the axis
+    /// * `block_at` - Returns the material of the block at a position
+    fn guard_edge<F>(&self, axis: usize, mut delta: f32, block_at: &F) -> f32
+        where F: Fn(Vector3<i32>) -> Option<Material>
+    {
+        let aabb = self.aabb();
+        let moved = |delta: f32| {
+            let mut offset = Vector3::zero();
+            offset[axis] = delta;
+            Aabb::new(aabb.min + offset, aabb.max + offset)
+        };
+        while delta != 0.0 && !Self::is_supported(&moved(delta), block_at) {
+            delta = if delta.abs() <= EDGE_STEP { 0.0 } else { delta - EDGE_STEP * delta.signum() };
+        }
+        delta
+    }
+
+    /// Advances the player by the given time. They walk in the
+    /// direction of their controls and jump off the ground, swim
+    /// in fluids or climb climbable blocks. Gravity pulls them
+    /// down unless they climb, slowed down in fluids, while the
+    /// blocks around them stop them. They sneak while they walk
+    /// with the sneak control, and keep sneaking as long as there
+    /// is no room to stand up. They sprint while they walk on land
+    /// with the sprint control and don't sneak, and keep sprinting
+    /// in the air. They lose their breath while their eyes are
+    /// under water and catch it again above.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    /// * `controls` - How the player wants to move
+    /// * `block_at` - Returns the material of the block at a position
+    pub fn update<F>(&mut self, seconds: f32, controls: Controls, block_at: F)
+        where F: Fn(Vector3<i32>) -> Option<Material>
+    {
+        self.mode = self.movement_mode(&block_at);
+        let headroom = PLAYER_HEIGHT - SNEAK_HEIGHT;
+        let ducked = sweep_axis(&Aabb::body(self.pos, SNEAK_HEIGHT), 1, headroom, &block_at) < headroom;
+        self.sneaking = (self.mode == MovementMode::Walking && controls.sneak) || (self.sneaking && ducked);
+        let walk = if controls.walk.magnitude2() > 1.0 { controls.walk.normalize() } else { controls.walk };
+        let airborne = self.mode == MovementMode::Walking && !self.on_ground;
+        self.sprinting = self.mode == MovementMode::Walking && controls.sprint && !self.sneaking
+            && walk.magnitude2() > 0.0 && (self.sprinting || !airborne);
+        let profile = self.profile;
+        let speed = if self.sneaking {
+            profile.walk_speed * SNEAK_SPEED
+        } else if self.mode == MovementMode::Swimming {
+            profile.walk_speed * FLUID_WALK_SPEED
+        } else if self.sprinting {
+            profile.sprint_speed
+        } else {
+            profile.walk_speed
+        };
+        // The player approaches the speed they walk with, which
+        // takes longer in the air so that they keep their momentum
+        let control = if airborne { profile.friction * profile.air_control } else { profile.friction };
+        let blend = 1.0 - (-control * seconds).exp();
+        self.velocity.x += (walk.x * speed - self.velocity.x) * blend;
+        self.velocity.z += (walk.y * speed - self.velocity.z) * blend;
+        // Going up wins over going down if both are held
+        let vertical = |speed: f32| if controls.up { Some(speed) } else if controls.down { Some(-speed) } else { None };
+        self.velocity.y = match self.mode {
+            MovementMode::Climbing => vertical(CLIMB_SPEED).unwrap_or(0.0),
+            MovementMode::Swimming => vertical(SWIM_SPEED)
+                .unwrap_or_else(|| (self.velocity.y - GRAVITY * FLUID_GRAVITY * seconds).max(-SINK_SPEED)),
+            MovementMode::Walking => {
+                let velocity = if controls.up && self.on_ground { profile.jump_speed() } else { self.velocity.y };
+                (velocity - GRAVITY * seconds).max(-TERMINAL_VELOCITY)
+            }
+        };
+
+        // The player moves along one axis after another,
+        // starting with the vertical one to land first
+        let delta = self.velocity * seconds;
+        self.on_ground = false;
+        for &axis in [1, 0, 2].iter() {
+            let mut moved = sweep_axis(&self.aabb(), axis, delta[axis], &block_at);
+            if axis != 1 && self.sneaking && self.on_ground {
+                moved = self.guard_edge(axis, moved, &block_at);
+            }
+            self.pos[axis] += moved;
+            if moved != delta[axis] {
+                self.on_ground |= axis == 1 && delta[axis] < 0.0;
+                self.velocity[axis] = 0.0;
+            }
+        }
+
+        let eye = self.eye().map(|value| value.floor() as i32);
+        self.air = if block_at(eye) == Some(Material::Water) {
+            (self.air - seconds).max(0.0)
+        } else {
+            (self.air + seconds * AIR_REFILL).min(MAX_AIR)
+        };
+    }
+}
 
 /// Entity
 ///
@@ -78,4 +690,243 @@ impl Entity {
           0.0, 0.0, 0.0, 1.0,
         )
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_box_spans_one_unit() {
+        let aabb = Aabb::block(Vector3::new(-2, 3, 4));
+        assert_eq!(aabb.min, Vector3::new(-2.0, 3.0, 4.0));
+        assert_eq!(aabb.max, Vector3::new(-1.0, 4.0, 5.0));
+    }
+
+    #[test]
+    fn player_box_is_centered_below_the_eyes() {
+        let aabb = Aabb::player(Vector3::new(0.0, PLAYER_EYE_HEIGHT, 0.0));
+        assert_eq!(aabb.min, Vector3::new(-PLAYER_WIDTH / 2.0, 0.0, -PLAYER_WIDTH / 2.0));
+        assert_eq!(aabb.max, Vector3::new(PLAYER_WIDTH / 2.0, PLAYER_HEIGHT, PLAYER_WIDTH / 2.0));
+    }
+
+    #[test]
+    fn overlapping_boxes_intersect() {
+        let block = Aabb::block(Vector3::new(0, 0, 0));
+        let other = Aabb::new(Vector3::new(0.5, 0.5, 0.5), Vector3::new(1.5, 1.5, 1.5));
+        assert!(block.intersects(&other));
+        assert!(other.intersects(&block));
+
+        let inner = Aabb::new(Vector3::new(0.25, 0.25, 0.25), Vector3::new(0.75, 0.75, 0.75));
+        assert!(block.intersects(&inner));
+    }
+
+    #[test]
+    fn touching_or_separate_boxes_dont_intersect() {
+        let block = Aabb::block(Vector3::new(0, 0, 0));
+        assert!(!block.intersects(&Aabb::block(Vector3::new(1, 0, 0))));
+        assert!(!block.intersects(&Aabb::block(Vector3::new(0, -1, 0))));
+        assert!(!block.intersects(&Aabb::block(Vector3::new(0, 0, 3))));
+    }
+
+    #[test]
+    fn rays_enter_boxes_they_point_at() {
+        let block = Aabb::block(Vector3::new(2, 0, 0));
+        let origin = Vector3::new(0.0, 0.5, 0.5);
+        assert_eq!(block.ray_distance(origin, Vector3::new(3.0, 0.0, 0.0)), Some(2.0));
+        assert_eq!(block.ray_distance(Vector3::new(2.5, 0.5, 0.5), Vector3::new(0.0, 1.0, 0.0)), Some(0.0));
+        assert_eq!(block.ray_distance(origin, Vector3::new(-1.0, 0.0, 0.0)), None);
+        assert_eq!(block.ray_distance(origin, Vector3::new(1.0, 1.0, 0.0)), None);
+        assert_eq!(block.ray_distance(origin, Vector3::zero()), None);
+    }
+
+    /// A world whose blocks below `y = 0` are stone, with a
+    /// wall at `x = 3`, water at `z = -4` and a ladder up a
+    /// wall at `x = -4`
+    fn ground(pos: Vector3<i32>) -> Option<Material> {
+        Some(if pos.y < 0 || (pos.x == 3 && pos.y < 2) || (pos.x == -4 && pos.y < 6) {
+            Material::Stone
+        } else if pos.z == -4 && pos.y < 3 {
+            Material::Water
+        } else if pos.x == -3 && pos.z == 0 && pos.y < 6 {
+            Material::Ladder
+        } else {
+            Material::Air
+        })
+    }
+
+    #[test]
+    fn players_fall_onto_the_ground_and_jump() {
+        let mut player = Player::at_eye(Vector3::new(0.5, 5.0 + PLAYER_EYE_HEIGHT, 0.5));
+        for _ in 0..60 {
+            player.update(1.0 / 30.0, Controls::default(), ground);
+        }
+        assert!(player.is_on_ground());
+        assert!(player.pos().y.abs() < 0.01, "{:?}", player.pos());
+        assert_eq!(player.velocity().y, 0.0);
+
+        // Jumps clear a single block
+        player.update(1.0 / 30.0, Controls { up: true, ..Controls::default() }, ground);
+        assert!(!player.is_on_ground());
+        let mut peak: f32 = 0.0;
+        for _ in 0..60 {
+            player.update(1.0 / 30.0, Controls::default(), ground);
+            peak = peak.max(player.pos().y);
+        }
+        assert!(peak > 1.0 && peak < 1.5, "{}", peak);
+        assert!(player.is_on_ground());
+    }
+
+    #[test]
+    fn players_are_stopped_by_walls() {
+        let mut player = Player::at_eye(Vector3::new(0.5, PLAYER_EYE_HEIGHT, 0.5));
+        for _ in 0..90 {
+            player.update(1.0 / 30.0, Controls { walk: Vector2::new(1.0, 0.0), ..Controls::default() }, ground);
+        }
+        assert!((player.aabb().max.x - 3.0).abs() < 0.01, "{:?}", player.aabb());
+        assert!(player.is_on_ground());
+
+        // Players walk along walls they run into at an angle
+        player.update(0.5, Controls { walk: Vector2::new(1.0, 1.0), ..Controls::default() }, ground);
+        assert!(player.pos().z > 1.5);
+        assert!(player.aabb().max.x <= 3.0);
+    }
+
+    #[test]
+    fn players_sink_slowly_and_swim_up_in_water() {
+        let mut player = Player::at_eye(Vector3::new(0.5, 2.5 + PLAYER_EYE_HEIGHT, -3.5));
+        for _ in 0..10 {
+            player.update(1.0 / 30.0, Controls::default(), ground);
+        }
+        assert!(player.velocity().y >= -SINK_SPEED);
+        let sunken = player.pos().y;
+        player.update(0.5, Controls { up: true, ..Controls::default() }, ground);
+        assert!(player.pos().y > sunken);
+
+        // Unloaded blocks hold the player
+        let mut player = Player::at_eye(Vector3::new(0.5, 2.0 + PLAYER_EYE_HEIGHT, 0.5));
+        let start = *player.pos();
+        player.update(1.0, Controls::default(), |_| None);
+        assert_eq!(*player.pos(), start);
+        assert!(player.is_on_ground());
+    }
+
+    #[test]
+    fn players_climb_ladders() {
+        let mut player = Player::at_eye(Vector3::new(-1.0, PLAYER_EYE_HEIGHT, 0.5));
+        player.update(1.0 / 30.0, Controls::default(), ground);
+        assert_eq!(player.mode(), MovementMode::Walking);
+
+        // Players climb once they are at the ladder, and hold on
+        for _ in 0..45 {
+            player.update(1.0 / 30.0, Controls { walk: Vector2::new(-1.0, 0.0), up: true, ..Controls::default() }, ground);
+        }
+        assert_eq!(player.mode(), MovementMode::Climbing);
+        assert!(player.pos().y > 1.5, "{:?}", player.pos());
+        let height = player.pos().y;
+        for _ in 0..30 {
+            player.update(1.0 / 30.0, Controls::default(), ground);
+        }
+        assert_eq!(player.pos().y, height);
+        player.update(0.5, Controls { down: true, ..Controls::default() }, ground);
+        assert!(player.pos().y < height);
+    }
+
+    #[test]
+    fn sneaking_players_duck_and_stop_at_edges() {
+        let mut player = Player::at_eye(Vector3::new(3.5, 2.0 + PLAYER_EYE_HEIGHT, 0.5));
+        player.update(1.0 / 30.0, Controls::default(), ground);
+        assert!(player.is_on_ground());
+
+        // Sneaking players stop at the edge of the wall
+        let walk = Controls { walk: Vector2::new(1.0, 0.0), ..Controls::default() };
+        for _ in 0..60 {
+            player.update(1.0 / 30.0, Controls { sneak: true, ..walk }, ground);
+        }
+        assert!(player.is_sneaking());
+        assert!(player.is_on_ground());
+        assert!(player.pos().x > 4.0 && player.pos().x < 4.0 + PLAYER_WIDTH / 2.0, "{:?}", player.pos());
+        assert!((player.eye().y - player.pos().y - SNEAK_EYE_HEIGHT).abs() < 1e-5);
+        assert!((player.aabb().max.y - player.pos().y - SNEAK_HEIGHT).abs() < 1e-5);
+
+        // Walking on, they drop off it
+        for _ in 0..30 {
+            player.update(1.0 / 30.0, walk, ground);
+        }
+        assert!(!player.is_sneaking());
+        assert!(player.pos().y < 1.0, "{:?}", player.pos());
+    }
+
+    #[test]
+    fn sprinting_players_keep_their_momentum_in_the_air() {
+        let profile = MovementProfile::default();
+        let mut player = Player::at_eye(Vector3::new(0.5, PLAYER_EYE_HEIGHT, 0.5));
+        let sprint = Controls { walk: Vector2::new(0.0, 1.0), sprint: true, ..Controls::default() };
+        for _ in 0..30 {
+            player.update(1.0 / 30.0, sprint, ground);
+        }
+        assert!(player.is_sprinting());
+        assert!((player.velocity().z - profile.sprint_speed).abs() < 0.01, "{:?}", player.velocity());
+
+        // Letting go in the air barely slows them down, unlike on the ground
+        player.update(1.0 / 30.0, Controls { up: true, ..sprint }, ground);
+        for _ in 0..3 {
+            player.update(1.0 / 30.0, Controls::default(), ground);
+        }
+        assert!(!player.is_on_ground());
+        assert!(player.velocity().z > profile.walk_speed, "{:?}", player.velocity());
+        for _ in 0..60 {
+            player.update(1.0 / 30.0, Controls::default(), ground);
+        }
+        assert!(player.is_on_ground());
+        assert!(player.velocity().z.abs() < 0.01, "{:?}", player.velocity());
+    }
+
+    #[test]
+    fn profiles_tune_the_movement() {
+        let profile = MovementProfile { walk_speed: 2.0, jump_height: 2.0, air_control: 0.0, ..MovementProfile::default() };
+        let mut player = Player::at_eye(Vector3::new(0.5, PLAYER_EYE_HEIGHT, 0.5));
+        player.set_profile(profile);
+        player.update(1.0 / 30.0, Controls::default(), ground);
+        player.update(1.0, Controls { walk: Vector2::new(0.0, 1.0), ..Controls::default() }, ground);
+        assert!((player.velocity().z - profile.walk_speed).abs() < 0.01, "{:?}", player.velocity());
+
+        // Without air control, players can't steer in the air
+        player.update(1.0 / 30.0, Controls { walk: Vector2::new(0.0, 1.0), up: true, ..Controls::default() }, ground);
+        let mut peak: f32 = 0.0;
+        for _ in 0..60 {
+            player.update(1.0 / 30.0, Controls { walk: Vector2::new(1.0, 0.0), ..Controls::default() }, ground);
+            peak = peak.max(player.pos().y);
+            if !player.is_on_ground() {
+                assert_eq!(player.velocity().x, 0.0);
+                assert!(player.velocity().z > 1.9, "{:?}", player.velocity());
+            }
+        }
+        assert!(peak > 1.8 && peak < 2.2, "{}", peak);
+    }
+
+    #[test]
+    fn players_drown_with_their_eyes_under_water() {
+        let mut player = Player::at_eye(Vector3::new(0.5, PLAYER_EYE_HEIGHT, -3.5));
+        player.update(MAX_AIR / 2.0, Controls::default(), ground);
+        assert_eq!(player.mode(), MovementMode::Swimming);
+        assert_eq!(player.air(), MAX_AIR / 2.0);
+        player.update(MAX_AIR, Controls::default(), ground);
+        assert!(player.is_drowning());
+
+        // Players catch their breath above the water
+        player.teleport(Vector3::new(0.5, PLAYER_EYE_HEIGHT, 0.5));
+        assert!(player.is_drowning());
+        player.update(MAX_AIR / AIR_REFILL, Controls::default(), ground);
+        assert_eq!(player.air(), MAX_AIR);
+    }
+
+    #[test]
+    fn player_above_block_doesnt_intersect_it() {
+        let ground = Aabb::block(Vector3::new(0, 0, 0));
+        let player = Aabb::player(Vector3::new(0.5, 1.01 + PLAYER_EYE_HEIGHT, 0.5));
+        assert!(!player.intersects(&ground));
+
+        let sunken = Aabb::player(Vector3::new(0.5, 0.9 + PLAYER_EYE_HEIGHT, 0.5));
+        assert!(sunken.intersects(&ground));
+    }
+}
@@ -0,0 +1,67 @@
+//! The shared registry of vertex attribute locations.
+//!
+//! Shaders declare their inputs with an explicit
+//! `layout (location = ...)`. The very same locations
+//! are declared here once, so that vertex arrays and
+//! shaders can't silently drift apart.
+
+/// VertexAttribute
+///
+/// A `VertexAttribute` ties the name of a shader input
+/// to its explicit attribute location.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct VertexAttribute {
+    /// The name of the attribute in the shader source
+    name: &'static str,
+    /// The location of the attribute
+    location: u32,
+}
+
+impl VertexAttribute {
+    /// Creates a new vertex attribute
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the attribute in the shader source
+    /// * `location` - The location of the attribute
+    pub const fn new(name: &'static str, location: u32) -> Self {
+        Self {
+            name,
+            location,
+        }
+    }
+
+    /// Returns the name of the attribute in the shader source
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the location of the attribute
+    pub fn location(&self) -> u32 {
+        self.location
+    }
+}
+
+/// The vertex position
+pub const POSITION: VertexAttribute = VertexAttribute::new("position", 0);
+/// The texture coordinate of a vertex
+pub const TEX_COORD: VertexAttribute = VertexAttribute::new("texCoord", 1);
+/// The normal of a vertex
+pub const NORMAL: VertexAttribute = VertexAttribute::new("normal", 2);
+/// The tile of a texture atlas a vertex is referring to
+pub const TILE_COORD: VertexAttribute = VertexAttribute::new("tileCoord", 3);
+/// The color of a vertex
+pub const COLOR: VertexAttribute = VertexAttribute::new("color", 4);
+/// The tile of a texture atlas layered over the tile of a vertex
+pub const OVERLAY_COORD: VertexAttribute = VertexAttribute::new("overlayCoord", 5);
+/// The tint and opacity of the overlay tile of a vertex
+pub const OVERLAY_TINT: VertexAttribute = VertexAttribute::new("overlayTint", 6);
+/// How strong a vertex glows
+pub const EMISSION: VertexAttribute = VertexAttribute::new("emission", 7);
+/// How much of its surroundings a vertex reflects
+pub const REFLECTIVITY: VertexAttribute = VertexAttribute::new("reflectivity", 8);
+/// How much of the ambient light reaches a vertex
+pub const OCCLUSION: VertexAttribute = VertexAttribute::new("occlusion", 9);
+
+/// All registered vertex attributes
+pub const ALL: [VertexAttribute; 10] = [POSITION, TEX_COORD, NORMAL, TILE_COORD, COLOR, OVERLAY_COORD, OVERLAY_TINT, EMISSION, REFLECTIVITY, OCCLUSION];
@@ -0,0 +1,220 @@
+//! A trait boundary between the renderer and the
+//! underlying graphics API.
+//!
+//! Every graphics API specific call is supposed to
+//! live behind a `RenderBackend`, so that the remaining
+//! `unsafe` blocks could be audited in one layer and
+//! further backends (e.g. `wgpu` or `Vulkan`) could be
+//! added next to the `OpenGL` one.
+
+pub mod opengl;
+
+use crate::graphics::buffer::Vertex;
+use crate::graphics::capabilities::GpuCapabilities;
+use crate::graphics::texture::TextureError;
+use crate::resources::Resources;
+
+pub use self::opengl::GlBackend;
+
+/// PolygonMode
+///
+/// Determines how polygons are rasterized:
+/// * `Fill` - The polygons are filled
+/// * `Line` - Only the edges of the polygons are drawn
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PolygonMode {
+    Fill,
+    Line,
+}
+
+/// PrimitiveTopology
+///
+/// Determines how indexed vertices are assembled:
+/// * `Triangles` - Each three indices form a triangle
+/// * `Lines` - Each two indices form a line
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PrimitiveTopology {
+    Triangles,
+    Lines,
+}
+
+/// TexelFormat
+///
+/// The size of the unsigned integer texels of a 3D texture:
+/// * `R8` - One byte per texel
+/// * `R16` - Two bytes per texel in native byte order
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TexelFormat {
+    R8,
+    R16,
+}
+
+impl TexelFormat {
+    /// Returns the size of a texel in bytes
+    pub fn size(&self) -> usize {
+        match self {
+            TexelFormat::R8 => 1,
+            TexelFormat::R16 => 2,
+        }
+    }
+}
+
+/// RenderBackend
+///
+/// A trait which can be implemented by different
+/// graphics APIs. It covers the creation of buffers,
+/// textures and pipelines, the global render state
+/// and the submission of draw calls.
+pub trait RenderBackend {
+    /// A buffer storing vertex data
+    type VertexBuffer;
+    /// A buffer storing indices
+    type IndexBuffer;
+    /// A binding of vertex buffers and their layouts
+    type VertexArray;
+    /// A texture living on the GPU
+    type Texture;
+    /// A volume of single channel texels living on the GPU
+    type Texture3d;
+    /// Six square textures sampled by a direction
+    type Cubemap;
+    /// A pipeline describing how vertices are processed,
+    /// e.g. a linked shader program
+    type Pipeline;
+    /// An offscreen target which could be rendered to
+    /// instead of the screen
+    type RenderTarget;
+
+    /// Returns what the graphics driver supports
+    fn capabilities(&self) -> &GpuCapabilities;
+
+    /// Creates a new vertex buffer from the given vertices
+    ///
+    /// # Arguments
+    ///
+    /// * `vertices` - The vertices
+    fn create_vertex_buffer<V: Vertex>(&self, vertices: &[V]) -> Self::VertexBuffer;
+
+    /// Creates a new index buffer from the given indices. The
+    /// backend may choose a narrower index type if the vertex
+    /// count allows it.
+    ///
+    /// # Arguments
+    ///
+    /// * `indices` - The indices
+    /// * `vertex_count` - The amount of vertices the indices refer to
+    fn create_index_buffer(&self, indices: &[u32], vertex_count: usize) -> Self::IndexBuffer;
+
+    /// Creates a new, empty vertex array
+    fn create_vertex_array(&self) -> Self::VertexArray;
+
+    /// Creates a new texture from the given resources
+    ///
+    /// # Arguments
+    ///
+    /// * `res` - A `Resources` instance
+    /// * `file_path` - The file location relative to the
+    /// resources root directory
+    fn create_texture(&self, res: &Resources, file_path: &str) -> Self::Texture;
+
+    /// Creates a new texture from raw `RGBA` pixels, e.g.
+    /// for procedurally generated textures. If the amount of
+    /// pixels doesn't match the size, an error is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the texture
+    /// * `height` - The height of the texture
+    /// * `pixels` - The `RGBA` pixels, row by row starting at the bottom
+    fn create_texture_from_pixels(&self, width: u32, height: u32, pixels: Vec<u8>) -> Result<Self::Texture, TextureError>;
+
+    /// Creates a new 3D texture of single channel texels.
+    /// If the amount of texels doesn't match the size, an
+    /// error is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The width, height and depth of the texture
+    /// * `format` - The size of the texels
+    /// * `texels` - The texels, row by row and layer by layer
+    fn create_texture_3d(&self, size: [u32; 3], format: TexelFormat, texels: &[u8]) -> Result<Self::Texture3d, TextureError>;
+
+    /// Creates a new cubemap from six square faces. If the
+    /// amount of pixels of a face doesn't match the size, an
+    /// error is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The width and height of each face
+    /// * `faces` - The `RGBA` pixels of the faces in the order
+    /// +x, -x, +y, -y, +z, -z
+    fn create_cubemap(&self, size: u32, faces: &[Vec<u8>; 6]) -> Result<Self::Cubemap, TextureError>;
+
+    /// Creates a new pipeline from the given resources.
+    /// If an error occurs, it will return the error
+    /// message.
+    ///
+    /// # Arguments
+    ///
+    /// * `res` - A `Resources` instance
+    /// * `name` - The name of the pipeline, e.g. the shader name
+    fn create_pipeline(&self, res: &Resources, name: &str) -> Result<Self::Pipeline, String>;
+
+    /// Creates a variant of the pipeline of the given name,
+    /// whose shaders are compiled with the given macros
+    /// defined. If an error occurs, it will return the error
+    /// message.
+    ///
+    /// # Arguments
+    ///
+    /// * `res` - A `Resources` instance
+    /// * `name` - The name of the pipeline, e.g. the shader name
+    /// * `defines` - The names of the macros which are defined
+    fn create_pipeline_variant(&self, res: &Resources, name: &str, defines: &[&str]) -> Result<Self::Pipeline, String>;
+
+    /// Creates a new offscreen render target
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the target
+    /// * `height` - The height of the target
+    /// * `color_attachments` - The amount of color buffers,
+    /// which are written by the fragment outputs in order
+    /// * `depth` - Whether the target has a depth buffer
+    fn create_render_target(&self, width: i32, height: i32, color_attachments: usize, depth: bool) -> Self::RenderTarget;
+
+    /// Sets the color the screen is cleared with
+    fn set_clear_color(&self, r: f32, g: f32, b: f32, a: f32);
+
+    /// Sets the viewport to the given size
+    fn set_viewport(&self, width: i32, height: i32);
+
+    /// Returns the width and height of the current viewport
+    fn viewport_size(&self) -> (i32, i32);
+
+    /// Sets the polygon mode
+    fn set_polygon_mode(&self, mode: PolygonMode);
+
+    /// Enables or disables depth testing
+    fn set_depth_test(&self, enabled: bool);
+
+    /// Enables or disables writing to the depth buffer
+    fn set_depth_write(&self, enabled: bool);
+
+    /// Enables or disables alpha blending
+    fn set_alpha_blending(&self, enabled: bool);
+
+    /// Clears the color and the depth buffer
+    fn clear(&self);
+
+    /// Draws the given indexed vertices with the given
+    /// pipeline
+    ///
+    /// # Arguments
+    ///
+    /// * `va` - A vertex array
+    /// * `ib` - An index buffer
+    /// * `pipeline` - A pipeline
+    /// * `topology` - How the indexed vertices are assembled
+    fn draw_indexed(&self, va: &Self::VertexArray, ib: &Self::IndexBuffer, pipeline: &Self::Pipeline, topology: PrimitiveTopology);
+}
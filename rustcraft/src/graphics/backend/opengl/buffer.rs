@@ -0,0 +1,377 @@
+//! The `OpenGL` implementation of vertex buffers,
+//! index buffers and vertex arrays
+
+use crate::graphics::attribute::{self, VertexAttribute};
+use crate::graphics::buffer::{ComponentType, IndexType, Vertex, VertexBufferLayout};
+use crate::graphics::gl::{Gl, gl, types::*};
+use crate::graphics::shader::ShaderProgram;
+use std::cell::Cell;
+use std::ffi::CString;
+use std::mem::size_of;
+
+/// VertexBuffer
+///
+/// A `VertexBuffer` is used to store
+/// any kinds of vertices.
+pub struct VertexBuffer {
+    /// The id of the vertex buffer
+    id: GLuint,
+    /// An `OpenGL` instance
+    gl: Gl,
+    /// The layout of the stored vertices
+    layout: VertexBufferLayout,
+}
+
+impl VertexBuffer {
+    /// Creates a new vertex buffer from the given vertices.
+    /// The buffer keeps the layout of the vertices, so that
+    /// it could be added to a `VertexArray` later on.
+    ///
+    /// # Arguments
+    ///
+    /// * `gl` - An `OpenGL` instance
+    /// * `vertices` - The vertices
+    pub fn new<V: Vertex>(gl: &Gl, vertices: &[V]) -> Self {
+        let mut buffer: GLuint = 0;
+        unsafe {
+            gl.GenBuffers(1, &mut buffer);
+            gl.BindBuffer(gl::ARRAY_BUFFER, buffer);
+            gl.BufferData(
+                gl::ARRAY_BUFFER,
+                (vertices.len() * size_of::<V>()) as isize,
+                vertices.as_ptr() as *const GLvoid,
+                gl::STATIC_DRAW
+            );
+        }
+
+        VertexBuffer {
+            gl: gl.clone(),
+            id: buffer,
+            layout: V::layout(),
+        }
+    }
+
+    /// Binds the buffer
+    pub fn bind(&self) {
+        unsafe { self.gl.BindBuffer(gl::ARRAY_BUFFER, self.id); }
+    }
+
+    /// Unbinds the buffer
+    pub fn unbind(&self) {
+        unsafe { self.gl.BindBuffer(gl::ARRAY_BUFFER, 0); }
+    }
+
+    /// Returns the id of the `VertexBuffer`
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    /// Returns the layout of the stored vertices
+    pub fn layout(&self) -> &VertexBufferLayout {
+        &self.layout
+    }
+}
+
+impl Drop for VertexBuffer {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteBuffers(1, &self.id); }
+    }
+}
+
+/// Returns the `OpenGL` equivalent of an index type
+///
+/// # Arguments
+///
+/// * `index_type` - The index type
+pub(crate) fn gl_index_type(index_type: IndexType) -> GLenum {
+    match index_type {
+        IndexType::U16 => gl::UNSIGNED_SHORT,
+        IndexType::U32 => gl::UNSIGNED_INT,
+    }
+}
+
+/// Returns the `OpenGL` equivalent of a component type
+///
+/// # Arguments
+///
+/// * `component_type` - The component type
+fn gl_component_type(component_type: ComponentType) -> GLenum {
+    match component_type {
+        ComponentType::F32 => gl::FLOAT,
+        ComponentType::I32 => gl::INT,
+        ComponentType::U32 => gl::UNSIGNED_INT,
+        ComponentType::U8 => gl::UNSIGNED_BYTE,
+    }
+}
+
+/// IndexBuffer
+///
+/// A `IndexBuffer` is used to store
+/// a bunch of indices for different
+/// vertices.
+/// The indices are either stored as 16 bit
+/// or 32 bit integers, see `IndexType`.
+pub struct IndexBuffer {
+    /// The id of the vertex buffer
+    id: GLuint,
+    /// An `OpenGL` instance
+    gl: Gl,
+    /// The index count
+    index_count: usize,
+    /// The type of the stored indices
+    index_type: IndexType,
+}
+
+impl IndexBuffer {
+    /// Creates a new `IndexBuffer` from the given indices. The
+    /// index type is selected automatically, so that 16 bit
+    /// indices are used whenever all indices fit into them.
+    ///
+    /// # Arguments
+    ///
+    /// * `gl` - A reference to an `OpenGL` instance
+    /// * `indices` - The indices
+    /// * `vertex_count` - The amount of vertices the indices refer to
+    pub fn from_indices(gl: &Gl, indices: &[u32], vertex_count: usize) -> Self {
+        match IndexType::for_indices(indices, vertex_count) {
+            IndexType::U16 => {
+                let indices: Vec<u16> = indices.iter().map(|&index| index as u16).collect();
+                Self::with_type(gl, indices.as_ptr() as *const GLvoid, indices.len(), IndexType::U16)
+            }
+            IndexType::U32 => {
+                Self::with_type(gl, indices.as_ptr() as *const GLvoid, indices.len(), IndexType::U32)
+            }
+        }
+    }
+
+    /// Creates a new `IndexBuffer` from the given
+    /// raw indices of the given type
+    ///
+    /// # Arguments
+    ///
+    /// * `gl` - A reference to an `OpenGL` instance
+    /// * `indices` - A pointer to the data
+    /// * `index_count` - The index count of the data
+    /// * `index_type` - The type of the indices
+    fn with_type(gl: &Gl, indices: *const GLvoid, index_count: usize, index_type: IndexType) -> Self {
+        let mut buffer: GLuint = 0;
+        unsafe {
+            gl.GenBuffers(1, &mut buffer);
+            gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, buffer);
+            gl.BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                (index_count * index_type.size()) as isize,
+                indices,
+                gl::STATIC_DRAW
+            );
+        }
+
+        IndexBuffer {
+            gl: gl.clone(),
+            id: buffer,
+            index_count,
+            index_type,
+        }
+    }
+
+    /// Binds the buffer
+    pub fn bind(&self) {
+        unsafe { self.gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id); }
+    }
+
+    /// Unbinds the buffer
+    pub fn unbind(&self) {
+        unsafe { self.gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0); }
+    }
+
+    /// Returns the id of the `IndexBuffer`
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    /// Returns the index count of the `IndexBuffer`
+    pub fn index_count(&self) -> usize {
+        self.index_count
+    }
+
+    /// Returns the type of the indices
+    pub fn index_type(&self) -> IndexType {
+        self.index_type
+    }
+}
+
+impl Drop for IndexBuffer {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteBuffers(1, &self.id); }
+    }
+}
+
+/// VertexArray
+///
+/// A vertex array is supposed to tie together a
+/// buffer with an actual layout.
+///
+/// Each element of a layout is bound to the explicit
+/// location of its `VertexAttribute`, which matches the
+/// `layout (location = ...)` declared in the shaders.
+pub struct VertexArray {
+    /// An `OpenGL` instance
+    gl: Gl,
+    /// The id of the `VertexArray`
+    id: GLuint,
+    /// The attributes which are enabled for this vertex array
+    attributes: Vec<VertexAttribute>,
+    /// The id of the last program this vertex array was
+    /// validated against
+    validated_program: Cell<GLuint>,
+}
+
+impl VertexArray {
+    /// Create a new vertex array
+    ///
+    /// # Arguments
+    ///
+    /// * `gl` - An `OpenGL` instance
+    pub fn new(gl: &Gl) -> Self {
+        let mut vao: GLuint = 0;
+        unsafe {
+            gl.GenVertexArrays(1, &mut vao);
+            gl.BindVertexArray(vao);
+        }
+
+        VertexArray {
+            id: vao,
+            gl: gl.clone(),
+            attributes: Vec::new(),
+            validated_program: Cell::new(0),
+        }
+    }
+
+    /// Add a buffer to the vertex array. The buffer is bound
+    /// with the layout of its vertices.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an attribute of the layout has already
+    /// been bound to this vertex array
+    pub fn add_buffer(&mut self, vb: &VertexBuffer) {
+        let layout = vb.layout();
+        self.bind();
+        vb.bind();
+        for element in layout.elements() {
+            assert!(
+                !self.attributes.contains(&element.attribute),
+                "Attribute {} is already bound to the vertex array", element.attribute.name()
+            );
+
+            let index = element.attribute.location();
+            let component_type = gl_component_type(element.component_type);
+            let offset = element.offset as *const GLvoid;
+            unsafe {
+                self.gl.EnableVertexAttribArray(index);
+                // Integers which aren't normalized need to stay integers
+                // in the shader, which requires the `I` variant
+                if element.component_type.is_integer() && !element.normalized {
+                    self.gl.VertexAttribIPointer(index, element.count, component_type, layout.stride(), offset);
+                } else {
+                    let normalized = if element.normalized { gl::TRUE } else { gl::FALSE };
+                    self.gl.VertexAttribPointer(index, element.count, component_type, normalized, layout.stride(), offset);
+                }
+            }
+            self.attributes.push(element.attribute);
+        }
+    }
+
+    /// Validates this vertex array against the given linked
+    /// shader program. Every active attribute of the program
+    /// which isn't provided by this vertex array is reported
+    /// as a warning. Attributes which are provided, but not
+    /// used by the program are fine, since the shader compiler
+    /// may optimize them out. A program is just validated once.
+    ///
+    /// This is only done in debug builds.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - A linked shader program
+    pub fn validate(&self, program: &ShaderProgram) {
+        if !cfg!(debug_assertions) || self.validated_program.get() == program.id() {
+            return;
+        }
+        self.validated_program.set(program.id());
+
+        let mut active_attributes: GLint = 0;
+        let mut max_name_len: GLint = 0;
+        unsafe {
+            self.gl.GetProgramiv(program.id(), gl::ACTIVE_ATTRIBUTES, &mut active_attributes);
+            self.gl.GetProgramiv(program.id(), gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_name_len);
+        }
+
+        for index in 0..active_attributes as GLuint {
+            let mut name_buffer = vec![0u8; max_name_len.max(1) as usize];
+            let mut name_len: GLsizei = 0;
+            let mut size: GLint = 0;
+            let mut attribute_type: GLenum = 0;
+            unsafe {
+                self.gl.GetActiveAttrib(
+                    program.id(),
+                    index,
+                    name_buffer.len() as GLsizei,
+                    &mut name_len,
+                    &mut size,
+                    &mut attribute_type,
+                    name_buffer.as_mut_ptr() as *mut GLchar
+                );
+            }
+            name_buffer.truncate(name_len as usize);
+
+            let name = match CString::new(name_buffer) {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            let location = unsafe { self.gl.GetAttribLocation(program.id(), name.as_ptr() as *const GLchar) };
+
+            // Built-in inputs like `gl_VertexID` don't have a location
+            if location < 0 || self.provides(location as GLuint) {
+                continue;
+            }
+
+            let name = name.to_string_lossy();
+            match attribute::ALL.iter().find(|attribute| attribute.name() == name) {
+                Some(attribute) if attribute.location() != location as GLuint => log::warn!(
+                    "Attribute {} is bound to location {} in program {}, but registered at {}",
+                    name, location, program.id(), attribute.location()
+                ),
+                _ => log::warn!(
+                    "Attribute {} (location {}) is required by program {}, but not provided by the vertex array",
+                    name, location, program.id()
+                ),
+            }
+        }
+    }
+
+    /// Returns whether an attribute is bound to the given location
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - The attribute location
+    fn provides(&self, location: GLuint) -> bool {
+        self.attributes.iter().any(|attribute: &VertexAttribute| attribute.location() == location)
+    }
+
+    /// Binds the vertex array
+    pub fn bind(&self) {
+        unsafe { self.gl.BindVertexArray(self.id); }
+    }
+
+    /// Unbinds the vertex array
+    pub fn unbind(&self) {
+        unsafe { self.gl.BindVertexArray(0); }
+    }
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteVertexArrays(1, &self.id); }
+    }
+}
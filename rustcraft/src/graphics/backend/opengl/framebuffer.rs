@@ -0,0 +1,209 @@
+//! The `OpenGL` implementation of offscreen render targets
+
+use crate::graphics::gl::{Gl, gl, types::*};
+
+/// RenderTarget
+///
+/// A `RenderTarget` is a framebuffer which is rendered
+/// to instead of the screen. Each of its color attachments
+/// is a floating point texture, so that colors brighter
+/// than white are kept, e.g. for bloom. The depth is a
+/// texture as well, so post effects could sample it.
+pub struct RenderTarget {
+    /// The id of the framebuffer
+    id: GLuint,
+    /// An `OpenGL` instance
+    gl: Gl,
+    /// The ids of the color attachment textures
+    color_textures: Vec<GLuint>,
+    /// The id of the depth texture, if the target has one
+    depth_texture: Option<GLuint>,
+    /// The width of the target
+    width: i32,
+    /// The height of the target
+    height: i32,
+}
+
+impl RenderTarget {
+    /// Creates a new render target. If the framebuffer
+    /// is incomplete, an error is logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `gl` - An `OpenGL` instance
+    /// * `width` - The width of the target
+    /// * `height` - The height of the target
+    /// * `color_attachments` - The amount of color attachments
+    /// * `depth` - Whether the target has a depth buffer
+    pub fn new(gl: &Gl, width: i32, height: i32, color_attachments: usize, depth: bool) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
+
+        let mut id = 0;
+        let mut color_textures = vec![0; color_attachments];
+        let mut depth_texture = None;
+        unsafe {
+            gl.GenFramebuffers(1, &mut id);
+            gl.BindFramebuffer(gl::FRAMEBUFFER, id);
+
+            gl.GenTextures(color_attachments as i32, color_textures.as_mut_ptr());
+            let mut draw_buffers = Vec::with_capacity(color_attachments);
+            for (index, texture) in color_textures.iter().enumerate() {
+                gl.BindTexture(gl::TEXTURE_2D, *texture);
+                gl.TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA16F as i32,
+                    width,
+                    height,
+                    0,
+                    gl::RGBA,
+                    gl::FLOAT,
+                    std::ptr::null(),
+                );
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+                let attachment = gl::COLOR_ATTACHMENT0 + index as u32;
+                gl.FramebufferTexture2D(gl::FRAMEBUFFER, attachment, gl::TEXTURE_2D, *texture, 0);
+                draw_buffers.push(attachment);
+            }
+            gl.BindTexture(gl::TEXTURE_2D, 0);
+            gl.DrawBuffers(draw_buffers.len() as i32, draw_buffers.as_ptr());
+
+            if depth {
+                let mut texture = 0;
+                gl.GenTextures(1, &mut texture);
+                gl.BindTexture(gl::TEXTURE_2D, texture);
+                gl.TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::DEPTH_COMPONENT24 as i32,
+                    width,
+                    height,
+                    0,
+                    gl::DEPTH_COMPONENT,
+                    gl::FLOAT,
+                    std::ptr::null(),
+                );
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                gl.FramebufferTexture2D(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::TEXTURE_2D, texture, 0);
+                gl.BindTexture(gl::TEXTURE_2D, 0);
+                depth_texture = Some(texture);
+            }
+
+            let status = gl.CheckFramebufferStatus(gl::FRAMEBUFFER);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                log::error!("Render target of {}x{} is incomplete: {:#x}", width, height, status);
+            }
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+
+        Self {
+            id,
+            gl: gl.clone(),
+            color_textures,
+            depth_texture,
+            width,
+            height,
+        }
+    }
+
+    /// Makes the target the one which is rendered to and
+    /// sets the viewport to its size
+    pub fn bind(&self) {
+        unsafe {
+            self.gl.BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            self.gl.Viewport(0, 0, self.width, self.height);
+        }
+    }
+
+    /// Makes the screen the target which is rendered to
+    /// again. The viewport is left untouched.
+    pub fn unbind(&self) {
+        unsafe { self.gl.BindFramebuffer(gl::FRAMEBUFFER, 0); }
+    }
+
+    /// Binds a color attachment as a texture, so it could
+    /// be sampled by a shader
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the color attachment
+    /// * `slot` - The texture slot the attachment is bound to
+    pub fn bind_color(&self, index: usize, slot: u32) {
+        unsafe {
+            self.gl.ActiveTexture(gl::TEXTURE0 + slot);
+            self.gl.BindTexture(gl::TEXTURE_2D, self.color_textures[index]);
+        }
+    }
+
+    /// Binds the depth as a texture, so it could be sampled
+    /// by a shader. Targets without a depth are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The texture slot the depth is bound to
+    pub fn bind_depth(&self, slot: u32) {
+        if let Some(depth_texture) = self.depth_texture {
+            unsafe {
+                self.gl.ActiveTexture(gl::TEXTURE0 + slot);
+                self.gl.BindTexture(gl::TEXTURE_2D, depth_texture);
+            }
+        }
+    }
+
+    /// Reads the pixels of a color attachment as `RGBA`
+    /// bytes, row by row starting at the bottom. Colors
+    /// brighter than white are clamped.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the color attachment
+    pub fn read_pixels(&self, index: usize) -> Vec<u8> {
+        let mut pixels = vec![0u8; self.width as usize * self.height as usize * 4];
+        unsafe {
+            self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, self.id);
+            self.gl.ReadBuffer(gl::COLOR_ATTACHMENT0 + index as u32);
+            self.gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+            self.gl.ReadPixels(
+                0,
+                0,
+                self.width,
+                self.height,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut std::os::raw::c_void,
+            );
+            self.gl.PixelStorei(gl::PACK_ALIGNMENT, 4);
+            self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        }
+        pixels
+    }
+
+    /// Returns the width of the target
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    /// Returns the height of the target
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteFramebuffers(1, &self.id);
+            self.gl.DeleteTextures(self.color_textures.len() as i32, self.color_textures.as_ptr());
+            if let Some(depth_texture) = self.depth_texture {
+                self.gl.DeleteTextures(1, &depth_texture);
+            }
+        }
+    }
+}
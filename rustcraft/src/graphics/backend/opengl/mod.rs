@@ -0,0 +1,252 @@
+//! The `OpenGL` implementation of a `RenderBackend`.
+//!
+//! This module and its submodules are the only place
+//! where `OpenGL` is called directly.
+
+pub mod buffer;
+pub mod framebuffer;
+pub mod shader;
+pub mod texture;
+
+use crate::graphics::backend::{PolygonMode, PrimitiveTopology, RenderBackend, TexelFormat};
+use crate::graphics::buffer::Vertex;
+use crate::resources::Resources;
+use self::buffer::{gl_index_type, IndexBuffer, VertexArray, VertexBuffer};
+use self::framebuffer::RenderTarget;
+use self::shader::ShaderProgram;
+use self::texture::{Cubemap, Texture, Texture3d, TextureError, MAX_TEXTURE_MAX_ANISOTROPY};
+use crate::graphics::capabilities::{GpuCapabilities, GraphicsSettings};
+use crate::graphics::gl::{Gl, gl};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// GlBackend
+///
+/// The `GlBackend` implements the `RenderBackend` on
+/// top of an `OpenGL` 3.3 core context.
+#[derive(Clone)]
+pub struct GlBackend {
+    /// An `OpenGL` instance
+    gl: Gl,
+    /// What the driver supports
+    capabilities: Arc<GpuCapabilities>,
+    /// The level of the anisotropic filtering of
+    /// textures, if it's enabled
+    anisotropy: Option<f32>,
+}
+
+impl GlBackend {
+    /// Creates a new `OpenGL` backend, which queries the
+    /// capabilities of the driver and enables the optional
+    /// features it supports
+    ///
+    /// # Arguments
+    ///
+    /// * `gl` - An `OpenGL` instance
+    /// * `settings` - The optional features the player asked for
+    pub fn new(gl: &Gl, settings: &GraphicsSettings) -> Self {
+        let capabilities = query_capabilities(gl);
+        let anisotropy = capabilities.anisotropy(settings.anisotropy);
+        if capabilities.window_samples > 0 && capabilities.msaa_samples(settings.msaa) > 0 {
+            unsafe { gl.Enable(gl::MULTISAMPLE); }
+        }
+
+        Self {
+            gl: gl.clone(),
+            capabilities: Arc::new(capabilities),
+            anisotropy,
+        }
+    }
+
+    /// Returns the underlying `OpenGL` instance
+    pub fn gl(&self) -> &Gl {
+        &self.gl
+    }
+}
+
+/// Queries the capabilities of the driver
+///
+/// # Arguments
+///
+/// * `gl` - An `OpenGL` instance
+fn query_capabilities(gl: &Gl) -> GpuCapabilities {
+    let string = |name, index: Option<u32>| unsafe {
+        let string = match index {
+            Some(index) => gl.GetStringi(name, index),
+            None => gl.GetString(name),
+        };
+        if string.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(string as *const c_char).to_string_lossy().to_string()
+        }
+    };
+    let integer = |name| {
+        let mut value = 0;
+        unsafe { gl.GetIntegerv(name, &mut value); }
+        value.max(0) as u32
+    };
+
+    let extensions = (0..integer(gl::NUM_EXTENSIONS))
+        .map(|index| string(gl::EXTENSIONS, Some(index)))
+        .collect();
+    let mut max_anisotropy = 0.0;
+    unsafe {
+        gl.GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+        // The query fails without anisotropic filtering
+        while gl.GetError() != gl::NO_ERROR {}
+    }
+
+    GpuCapabilities {
+        vendor: string(gl::VENDOR, None),
+        renderer: string(gl::RENDERER, None),
+        version: string(gl::VERSION, None),
+        glsl_version: string(gl::SHADING_LANGUAGE_VERSION, None),
+        version_number: (integer(gl::MAJOR_VERSION), integer(gl::MINOR_VERSION)),
+        extensions,
+        max_texture_size: integer(gl::MAX_TEXTURE_SIZE),
+        max_3d_texture_size: integer(gl::MAX_3D_TEXTURE_SIZE),
+        max_texture_units: integer(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS),
+        max_samples: integer(gl::MAX_SAMPLES),
+        window_samples: integer(gl::SAMPLES),
+        max_anisotropy,
+    }
+}
+
+impl RenderBackend for GlBackend {
+    type VertexBuffer = VertexBuffer;
+    type IndexBuffer = IndexBuffer;
+    type VertexArray = VertexArray;
+    type Texture = Texture;
+    type Texture3d = Texture3d;
+    type Cubemap = Cubemap;
+    type Pipeline = ShaderProgram;
+    type RenderTarget = RenderTarget;
+
+    fn create_vertex_buffer<V: Vertex>(&self, vertices: &[V]) -> VertexBuffer {
+        VertexBuffer::new(&self.gl, vertices)
+    }
+
+    fn create_index_buffer(&self, indices: &[u32], vertex_count: usize) -> IndexBuffer {
+        IndexBuffer::from_indices(&self.gl, indices, vertex_count)
+    }
+
+    fn create_vertex_array(&self) -> VertexArray {
+        VertexArray::new(&self.gl)
+    }
+
+    fn capabilities(&self) -> &GpuCapabilities {
+        &self.capabilities
+    }
+
+    fn create_texture(&self, res: &Resources, file_path: &str) -> Texture {
+        let texture = Texture::from_resource(&self.gl, res, file_path);
+        if let Some(level) = self.anisotropy {
+            texture.set_anisotropy(level);
+        }
+        texture
+    }
+
+    fn create_texture_from_pixels(&self, width: u32, height: u32, pixels: Vec<u8>) -> Result<Texture, TextureError> {
+        let texture = Texture::from_pixels(&self.gl, width, height, pixels, PathBuf::new())?;
+        if let Some(level) = self.anisotropy {
+            texture.set_anisotropy(level);
+        }
+        Ok(texture)
+    }
+
+    fn create_texture_3d(&self, size: [u32; 3], format: TexelFormat, texels: &[u8]) -> Result<Texture3d, TextureError> {
+        Texture3d::new(&self.gl, size, format, texels)
+    }
+
+    fn create_cubemap(&self, size: u32, faces: &[Vec<u8>; 6]) -> Result<Cubemap, TextureError> {
+        Cubemap::new(&self.gl, size, faces)
+    }
+
+    fn create_pipeline(&self, res: &Resources, name: &str) -> Result<ShaderProgram, String> {
+        ShaderProgram::from_res(&self.gl, res, name)
+    }
+
+    fn create_pipeline_variant(&self, res: &Resources, name: &str, defines: &[&str]) -> Result<ShaderProgram, String> {
+        ShaderProgram::from_res_with_defines(&self.gl, res, name, defines)
+    }
+
+    fn create_render_target(&self, width: i32, height: i32, color_attachments: usize, depth: bool) -> RenderTarget {
+        RenderTarget::new(&self.gl, width, height, color_attachments, depth)
+    }
+
+    fn set_clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        unsafe { self.gl.ClearColor(r, g, b, a); }
+    }
+
+    fn set_viewport(&self, width: i32, height: i32) {
+        unsafe { self.gl.Viewport(0, 0, width, height); }
+    }
+
+    fn viewport_size(&self) -> (i32, i32) {
+        let mut viewport = [0; 4];
+        unsafe { self.gl.GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()); }
+        (viewport[2], viewport[3])
+    }
+
+    fn set_polygon_mode(&self, mode: PolygonMode) {
+        let gl_mode = match mode {
+            PolygonMode::Fill => gl::FILL,
+            PolygonMode::Line => gl::LINE,
+        };
+        unsafe { self.gl.PolygonMode(gl::FRONT_AND_BACK, gl_mode); }
+    }
+
+    fn set_depth_test(&self, enabled: bool) {
+        unsafe {
+            if enabled {
+                self.gl.Enable(gl::DEPTH_TEST);
+            } else {
+                self.gl.Disable(gl::DEPTH_TEST);
+            }
+        }
+    }
+
+    fn set_depth_write(&self, enabled: bool) {
+        let flag = if enabled { gl::TRUE } else { gl::FALSE };
+        unsafe { self.gl.DepthMask(flag); }
+    }
+
+    fn set_alpha_blending(&self, enabled: bool) {
+        unsafe {
+            if enabled {
+                self.gl.Enable(gl::BLEND);
+                self.gl.BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            } else {
+                self.gl.Disable(gl::BLEND);
+            }
+        }
+    }
+
+    fn clear(&self) {
+        unsafe { self.gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
+    }
+
+    fn draw_indexed(&self, va: &VertexArray, ib: &IndexBuffer, pipeline: &ShaderProgram, topology: PrimitiveTopology) {
+        let mode = match topology {
+            PrimitiveTopology::Triangles => gl::TRIANGLES,
+            PrimitiveTopology::Lines => gl::LINES,
+        };
+
+        pipeline.enable();
+        va.validate(pipeline);
+        va.bind();
+        ib.bind();
+
+        unsafe {
+            self.gl.DrawElements(
+                mode,
+                ib.index_count() as i32,
+                gl_index_type(ib.index_type()),
+                std::ptr::null()
+            );
+        }
+    }
+}
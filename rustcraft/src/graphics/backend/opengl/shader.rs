@@ -57,6 +57,21 @@ impl Shader {
     /// * `res` - A `Resource` instance
     /// * `name` - The name of the shader
     pub fn from_res(gl: &Gl, res: &Resources, name: &str) -> Result<Shader, String> {
+        Shader::from_res_with_defines(gl, res, name, &[])
+    }
+
+    /// Creates a new `Shader` from given `Resources` and its name,
+    /// with the given macros defined at the top of its source, e.g.
+    /// to compile a variant of it.
+    /// If the source is invalid, it will return the error message.
+    ///
+    /// # Arguments
+    ///
+    /// * `gl` - A reference to an `OpenGL` instance
+    /// * `res` - A `Resource` instance
+    /// * `name` - The name of the shader
+    /// * `defines` - The names of the macros which are defined
+    pub fn from_res_with_defines(gl: &Gl, res: &Resources, name: &str, defines: &[&str]) -> Result<Shader, String> {
         const POSSIBLE_EXT: [(&str, ShaderType); 2] = [
             (".vert", ShaderType::Vertex),
             (".frag", ShaderType::Fragment),
@@ -69,7 +84,12 @@ impl Shader {
 
         let source = res.load_cstring(name)
             .map_err(|e| format!("Error loading resource {}: {:?}", name, e))?;
+        if defines.is_empty() {
+            return Shader::from_source(gl, &source, shader_type);
+        }
 
+        let source = CString::new(with_defines(&source.to_string_lossy(), defines))
+            .map_err(|e| format!("Error defining macros in {}: {}", name, e))?;
         Shader::from_source(gl, &source, shader_type)
     }
 
@@ -163,6 +183,23 @@ impl ShaderProgram {
     /// * `res` - A `Resources` instance
     /// * `name` - The name of the shaders
     pub fn from_res(gl: &Gl, res: &Resources, name: &str) -> Result<ShaderProgram, String> {
+        ShaderProgram::from_res_with_defines(gl, res, name, &[])
+    }
+
+    /// Creates a variant of the shader program of the given name,
+    /// see `from_res`, whose shaders are compiled with the given
+    /// macros defined, so they could be checked with `#ifdef`.
+    ///
+    /// If an error occurs, it will return the error
+    /// message.
+    ///
+    /// # Arguments
+    ///
+    /// * `gl` - An `OpenGL` instance
+    /// * `res` - A `Resources` instance
+    /// * `name` - The name of the shaders
+    /// * `defines` - The names of the macros which are defined
+    pub fn from_res_with_defines(gl: &Gl, res: &Resources, name: &str, defines: &[&str]) -> Result<ShaderProgram, String> {
         const POSSIBLE_EXT: [&str; 2] = [
             ".vert",
             ".frag",
@@ -170,7 +207,7 @@ impl ShaderProgram {
 
         let shaders = POSSIBLE_EXT.iter()
             .map(|file_extension| {
-                Shader::from_res(gl, res, &format!("shaders/{}{}", name, file_extension))
+                Shader::from_res_with_defines(gl, res, &format!("shaders/{}{}", name, file_extension), defines)
             })
             .collect::<Result<Vec<Shader>, String>>()?;
 
@@ -253,6 +290,18 @@ impl ShaderProgram {
         unsafe { self.gl.Uniform1f(location, v); }
     }
 
+    /// Sets a uniform of two f32
+    pub fn set_uniform_2f(&self, name: &str, v0: f32, v1: f32) {
+        let location = self.uniform_location(name);
+        unsafe { self.gl.Uniform2f(location, v0, v1); }
+    }
+
+    /// Sets a uniform of three f32
+    pub fn set_uniform_3f(&self, name: &str, v0: f32, v1: f32, v2: f32) {
+        let location = self.uniform_location(name);
+        unsafe { self.gl.Uniform3f(location, v0, v1, v2); }
+    }
+
     /// Sets a uniform of four f32
     pub fn set_uniform_4f(&self, name: &str, v0: f32, v1: f32, v2: f32, v3: f32) {
         let location = self.uniform_location(name);
@@ -278,10 +327,11 @@ impl ShaderProgram {
         }
 
         let location = unsafe { self.gl.GetUniformLocation(self.id, c_name.as_ptr() as *const i8) };
-        uniform_cache.insert(c_name, location);
+        let first_lookup = uniform_cache.insert(c_name, location).is_none();
 
-        if location == -1 {
-            println!("Warning: uniform {} doesn't exist!", name);
+        // Optimized out uniforms are looked up again, but only reported once
+        if location == -1 && first_lookup {
+            log::warn!("Uniform {} doesn't exist in program {}", name, self.id);
         }
 
         location
@@ -313,6 +363,23 @@ fn create_whitespace_cstring_with_len(len: usize) -> CString {
     unsafe { CString::from_vec_unchecked(buffer) }
 }
 
+/// Returns the source of a shader with the given macros defined
+/// right after its `#version` directive, which has to come first
+///
+/// # Arguments
+///
+/// * `source` - The source of the shader
+/// * `defines` - The names of the macros which are defined
+fn with_defines(source: &str, defines: &[&str]) -> String {
+    let defines: String = defines.iter().map(|define| format!("#define {}\n", define)).collect();
+    match source.split_once('\n') {
+        Some((version, rest)) if version.trim_start().starts_with("#version") => {
+            format!("{}\n{}{}", version, defines, rest)
+        }
+        _ => defines + source,
+    }
+}
+
 /// Loads a shader from its source.
 /// Returns the shader id if the source is valid.
 /// Otherwise it will return the `OpenGL` error
@@ -358,4 +425,17 @@ fn shader_from_source(gl: &Gl, source: &CStr, kind: GLenum) -> Result<GLuint, St
     }
 
     Ok(id)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn macros_are_defined_after_the_version() {
+        let source = "#version 330 core\n\nvoid main() {}\n";
+        assert_eq!(with_defines(source, &["DEBUG_NORMALS", "DEBUG_TILES"]),
+                   "#version 330 core\n#define DEBUG_NORMALS\n#define DEBUG_TILES\n\nvoid main() {}\n");
+        assert_eq!(with_defines("void main() {}", &["DEBUG_TILES"]), "#define DEBUG_TILES\nvoid main() {}");
+    }
+}
@@ -0,0 +1,600 @@
+//! Types to represent textures
+
+use crate::graphics::backend::TexelFormat;
+use crate::graphics::gl::{gl, Gl};
+use crate::memory::{MEMORY, Subsystem};
+use crate::resources::Resources;
+use image::GenericImageView;
+use std::os::raw::c_void;
+use std::path::PathBuf;
+use std::ops::{Deref, DerefMut};
+use cgmath::Vector2;
+use std::fmt;
+
+/// The texture parameter of the anisotropic filtering, which
+/// isn't part of the `OpenGL` 4.5 bindings
+const TEXTURE_MAX_ANISOTROPY: gl::types::GLenum = 0x84FE;
+/// The highest supported level of the anisotropic filtering
+pub(crate) const MAX_TEXTURE_MAX_ANISOTROPY: gl::types::GLenum = 0x84FF;
+
+/// TextureError
+///
+/// An error which occurs while creating a texture:
+/// * `InvalidPixelCount` - The amount of pixel bytes doesn't
+/// match the size of the texture
+#[derive(Debug, Eq, PartialEq)]
+pub enum TextureError {
+    InvalidPixelCount {
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::InvalidPixelCount { expected, actual } => {
+                write!(f, "Expected {} bytes of RGBA pixels, but got {}", expected, actual)
+            }
+        }
+    }
+}
+
+/// Texture
+///
+/// A `Texture` is used to represent image data
+/// in our `OpenGL` context. This data gets
+/// processed in the shader.
+pub struct Texture {
+    /// The id of the texture
+    id: u32,
+    /// An `OpenGL` instance
+    gl: Gl,
+    /// The path of the texture file relative to
+    /// the textures (resource) directory
+    file_path: PathBuf,
+    /// The width of the file
+    width: u32,
+    /// The height of the file
+    height: u32,
+    /// The bits per pixel
+    bpp: u16,
+    /// The local buffer of the image
+    local_buffer: Vec<u8>,
+}
+
+impl Texture {
+    /// Creates a new `Texture` from the given `Resources` and its file path
+    ///
+    /// # Arguments
+    ///
+    /// * `gl` - An `OpenGL` instance
+    /// * `res` - A `Resource` instance
+    /// * `file_path` - The file location relative to the
+    /// resources root directory.
+    pub fn from_resource(gl: &Gl, res: &Resources, file_path: &str) -> Self {
+        // Load image from resources
+        let mut image = res.load_image(file_path).unwrap();
+
+        // Flip image vertically for `OpenGL` use
+        image = image.flipv();
+
+        Self::upload(
+            gl,
+            image.width(),
+            image.height(),
+            image.color().bits_per_pixel(),
+            image.into_rgba().into_raw(),
+            PathBuf::from(file_path),
+        )
+    }
+
+    /// Creates a new `Texture` from raw `RGBA` pixels, e.g.
+    /// for procedurally generated textures
+    ///
+    /// # Arguments
+    ///
+    /// * `gl` - An `OpenGL` instance
+    /// * `width` - The width of the texture
+    /// * `height` - The height of the texture
+    /// * `pixels` - The `RGBA` pixels, row by row starting at the bottom
+    /// * `file_path` - The file the pixels originate from, if any
+    pub fn from_pixels(gl: &Gl, width: u32, height: u32, pixels: Vec<u8>, file_path: PathBuf) -> Result<Self, TextureError> {
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() != expected {
+            return Err(TextureError::InvalidPixelCount {
+                expected,
+                actual: pixels.len(),
+            });
+        }
+
+        Ok(Self::upload(gl, width, height, 32, pixels, file_path))
+    }
+
+    /// Uploads the given `RGBA` pixels to a new texture
+    ///
+    /// # Arguments
+    ///
+    /// * `gl` - An `OpenGL` instance
+    /// * `width` - The width of the texture
+    /// * `height` - The height of the texture
+    /// * `bpp` - The bits per pixel of the source image
+    /// * `pixels` - The `RGBA` pixels, row by row starting at the bottom
+    /// * `file_path` - The file the pixels originate from, if any
+    fn upload(gl: &Gl, width: u32, height: u32, bpp: u16, pixels: Vec<u8>, file_path: PathBuf) -> Self {
+        // Setup `OpenGL`
+        let mut id = 0;
+        unsafe {
+            gl.GenTextures(1, &mut id);
+        }
+
+        // Return a `Texture` instance
+        MEMORY.allocate(Subsystem::Textures, pixels.len());
+        let texture = Self {
+            id,
+            gl: gl.clone(),
+            file_path,
+            width,
+            height,
+            bpp,
+            local_buffer: pixels,
+        };
+
+        // Setup `OpenGL` texture parameters and image data
+        unsafe {
+            gl.BindTexture(gl::TEXTURE_2D, id);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+            gl.TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as i32,
+                texture.width() as i32,
+                texture.height() as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                texture.local_buffer.as_ptr() as *const c_void,
+            );
+            gl.BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        texture
+    }
+
+    /// Binds the texture in the current `OpenGL` context
+    ///
+    /// # Arguments
+    ///
+    /// * `slot_op` - A optional slot the texture should bound to,
+    /// default: 0
+    pub fn bind(&self, slot_op: Option<u32>) {
+        let slot = slot_op.unwrap_or(0);
+        unsafe {
+            self.gl.ActiveTexture(gl::TEXTURE0 + slot);
+            self.gl.BindTexture(gl::TEXTURE_2D, self.id);
+        }
+    }
+
+    /// Unbinds the texture from the current `OpenGL` context
+    pub fn unbind(&self) {
+        unsafe { self.gl.BindTexture(gl::TEXTURE_2D, 0); }
+    }
+
+    /// Sets the level of the anisotropic filtering of the texture.
+    /// The level must be supported by the driver.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - The level, e.g. `16.0`
+    pub fn set_anisotropy(&self, level: f32) {
+        unsafe {
+            self.gl.BindTexture(gl::TEXTURE_2D, self.id);
+            self.gl.TexParameterf(gl::TEXTURE_2D, TEXTURE_MAX_ANISOTROPY, level);
+            self.gl.BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    /// Returns the width of the texture
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the texture
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the bits per pixel of the texture
+    pub fn bpp(&self) -> u16 {
+        self.bpp
+    }
+
+    /// Returns the file path of the texture
+    pub fn file_path(&self) -> &PathBuf {
+        &self.file_path
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        MEMORY.release(Subsystem::Textures, self.local_buffer.len());
+        unsafe { self.gl.DeleteTextures(1, &self.id); }
+    }
+}
+
+/// Texture3d
+///
+/// A `Texture3d` is a volume of single channel texels,
+/// e.g. the light levels of a chunk. Its texels are
+/// fetched by their integer coordinates, so they are
+/// neither filtered nor normalized by the size.
+pub struct Texture3d {
+    /// The id of the texture
+    id: u32,
+    /// An `OpenGL` instance
+    gl: Gl,
+    /// The width, height and depth of the texture
+    size: [u32; 3],
+    /// The size of the texels
+    format: TexelFormat,
+}
+
+impl Texture3d {
+    /// Creates a new 3D texture from the given texels. If the
+    /// amount of texels doesn't match the size, an error is
+    /// returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `gl` - An `OpenGL` instance
+    /// * `size` - The width, height and depth of the texture
+    /// * `format` - The size of the texels
+    /// * `texels` - The texels, row by row and layer by layer
+    pub fn new(gl: &Gl, size: [u32; 3], format: TexelFormat, texels: &[u8]) -> Result<Self, TextureError> {
+        let mut id = 0;
+        unsafe { gl.GenTextures(1, &mut id); }
+
+        let texture = Self {
+            id,
+            gl: gl.clone(),
+            size,
+            format,
+        };
+
+        unsafe {
+            gl.BindTexture(gl::TEXTURE_3D, id);
+            gl.TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl.TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl.TexImage3D(
+                gl::TEXTURE_3D,
+                0,
+                texture.internal_format() as i32,
+                size[0] as i32,
+                size[1] as i32,
+                size[2] as i32,
+                0,
+                gl::RED_INTEGER,
+                texture.texel_type(),
+                std::ptr::null(),
+            );
+            gl.BindTexture(gl::TEXTURE_3D, 0);
+        }
+
+        texture.upload(texels)?;
+        Ok(texture)
+    }
+
+    /// Replaces all texels of the texture
+    ///
+    /// # Arguments
+    ///
+    /// * `texels` - The texels, row by row and layer by layer
+    pub fn upload(&self, texels: &[u8]) -> Result<(), TextureError> {
+        let expected = self.size.iter().map(|size| *size as usize).product::<usize>() * self.format.size();
+        if texels.len() != expected {
+            return Err(TextureError::InvalidPixelCount {
+                expected,
+                actual: texels.len(),
+            });
+        }
+
+        unsafe {
+            self.gl.BindTexture(gl::TEXTURE_3D, self.id);
+            self.gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            self.gl.TexSubImage3D(
+                gl::TEXTURE_3D,
+                0,
+                0,
+                0,
+                0,
+                self.size[0] as i32,
+                self.size[1] as i32,
+                self.size[2] as i32,
+                gl::RED_INTEGER,
+                self.texel_type(),
+                texels.as_ptr() as *const c_void,
+            );
+            self.gl.PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            self.gl.BindTexture(gl::TEXTURE_3D, 0);
+        }
+        Ok(())
+    }
+
+    /// Binds the texture in the current `OpenGL` context
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The slot the texture should be bound to
+    pub fn bind(&self, slot: u32) {
+        unsafe {
+            self.gl.ActiveTexture(gl::TEXTURE0 + slot);
+            self.gl.BindTexture(gl::TEXTURE_3D, self.id);
+        }
+    }
+
+    /// Returns the width, height and depth of the texture
+    pub fn size(&self) -> [u32; 3] {
+        self.size
+    }
+
+    /// Returns the size of the texels
+    pub fn format(&self) -> TexelFormat {
+        self.format
+    }
+
+    /// Returns the `OpenGL` format the texels are stored in
+    fn internal_format(&self) -> gl::types::GLenum {
+        match self.format {
+            TexelFormat::R8 => gl::R8UI,
+            TexelFormat::R16 => gl::R16UI,
+        }
+    }
+
+    /// Returns the `OpenGL` type the texels are uploaded as
+    fn texel_type(&self) -> gl::types::GLenum {
+        match self.format {
+            TexelFormat::R8 => gl::UNSIGNED_BYTE,
+            TexelFormat::R16 => gl::UNSIGNED_SHORT,
+        }
+    }
+}
+
+impl Drop for Texture3d {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteTextures(1, &self.id); }
+    }
+}
+
+/// Cubemap
+///
+/// A `Cubemap` is made of six square `RGBA` faces, which
+/// are sampled by a direction rather than a coordinate,
+/// e.g. to reflect the surroundings. Its texels are
+/// filtered linearly and don't wrap around the edges.
+pub struct Cubemap {
+    /// The id of the texture
+    id: u32,
+    /// An `OpenGL` instance
+    gl: Gl,
+    /// The width and height of each face
+    size: u32,
+}
+
+impl Cubemap {
+    /// Creates a new cubemap from the given faces. If the amount
+    /// of pixels of a face doesn't match the size, an error is
+    /// returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `gl` - An `OpenGL` instance
+    /// * `size` - The width and height of each face
+    /// * `faces` - The `RGBA` pixels of the faces in the order
+    /// +x, -x, +y, -y, +z, -z
+    pub fn new(gl: &Gl, size: u32, faces: &[Vec<u8>; 6]) -> Result<Self, TextureError> {
+        let mut id = 0;
+        unsafe { gl.GenTextures(1, &mut id); }
+
+        MEMORY.allocate(Subsystem::Textures, Self::byte_size(size));
+        let cubemap = Self {
+            id,
+            gl: gl.clone(),
+            size,
+        };
+
+        unsafe {
+            gl.BindTexture(gl::TEXTURE_CUBE_MAP, id);
+            gl.TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl.TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl.TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl.TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl.TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as i32);
+            for face in 0..6 {
+                gl.TexImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                    0,
+                    gl::RGBA8 as i32,
+                    size as i32,
+                    size as i32,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    std::ptr::null(),
+                );
+            }
+            gl.BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+
+        cubemap.upload(faces)?;
+        Ok(cubemap)
+    }
+
+    /// Replaces the pixels of all faces
+    ///
+    /// # Arguments
+    ///
+    /// * `faces` - The `RGBA` pixels of the faces in the order
+    /// +x, -x, +y, -y, +z, -z
+    pub fn upload(&self, faces: &[Vec<u8>; 6]) -> Result<(), TextureError> {
+        let expected = self.size as usize * self.size as usize * 4;
+        if let Some(face) = faces.iter().find(|face| face.len() != expected) {
+            return Err(TextureError::InvalidPixelCount {
+                expected,
+                actual: face.len(),
+            });
+        }
+
+        unsafe {
+            self.gl.BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+            for (index, face) in faces.iter().enumerate() {
+                self.gl.TexSubImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + index as u32,
+                    0,
+                    0,
+                    0,
+                    self.size as i32,
+                    self.size as i32,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    face.as_ptr() as *const c_void,
+                );
+            }
+            self.gl.BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+        Ok(())
+    }
+
+    /// Binds the cubemap in the current `OpenGL` context
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The slot the cubemap should be bound to
+    pub fn bind(&self, slot: u32) {
+        unsafe {
+            self.gl.ActiveTexture(gl::TEXTURE0 + slot);
+            self.gl.BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+        }
+    }
+
+    /// Returns the width and height of each face
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the size of the pixels of all faces in bytes
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The width and height of each face
+    fn byte_size(size: u32) -> usize {
+        size as usize * size as usize * 4 * 6
+    }
+}
+
+impl Drop for Cubemap {
+    fn drop(&mut self) {
+        MEMORY.release(Subsystem::Textures, Self::byte_size(self.size));
+        unsafe { self.gl.DeleteTextures(1, &self.id); }
+    }
+}
+
+/// SubTexture
+///
+/// A `SubTexture` represents one sprite of a texture atlas
+pub struct SubTexture<'a> {
+    /// The texture atlas this sub texture is referring
+    tex_atlas: &'a TextureAtlas,
+    /// The texture coordinates of this sub texture
+    tex_coords: [f32; 8]
+}
+
+impl<'a> SubTexture<'a> {
+
+    /// Creates a new sub texture from min and max coordinates
+    ///
+    /// # Arguments
+    ///
+    /// * `tex_atlas` - A reference to a texture atlas
+    /// * `min` - The min coordinate of the sub texture
+    /// * `max` - The max coordinate of the sub texture
+    fn new(tex_atlas: &'a TextureAtlas, min: Vector2<f32>, max: Vector2<f32>) -> Self {
+        let tex_coords= [
+            min.x, min.y,
+            max.x, min.y,
+            max.x, max.y,
+            min.x, max.y,
+        ];
+        Self {
+            tex_atlas,
+            tex_coords,
+        }
+    }
+
+    /// Returns the texture coords as a `[f32; 8]`
+    pub fn coords(&self) -> &[f32; 8] {
+       &self.tex_coords
+    }
+}
+
+/// TextureAtlas
+///
+/// A `TextureAtlas` combines multiple textures in just one file.
+/// Therefore, only one texture needs to be load with `OpenGL`.
+/// With this in place, the texture coordinates for each sprite
+/// could be calculated using the `total width/height` and `sprite
+/// width/length`
+pub struct TextureAtlas {
+    /// The underlying texture
+    texture: Texture,
+    /// The size of each sprite in the texture atlas
+    sprite_size: Vector2<f32>,
+}
+
+impl Deref for TextureAtlas {
+    type Target = Texture;
+
+    fn deref(&self) -> &Self::Target {
+        &self.texture
+    }
+}
+
+impl DerefMut for TextureAtlas {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.texture
+    }
+}
+
+impl TextureAtlas {
+    /// Creates a new texture atlas from a given texture
+    ///
+    /// # Arguments
+    ///
+    /// * `texture` - The underlying texture
+    /// * `sprite_size` - The size of each sprite
+    pub fn from_texture(texture: Texture, sprite_size: Vector2<f32>) -> Self {
+        return Self {
+            texture,
+            sprite_size,
+        }
+    }
+
+    /// Returns the sub texture within the given coords
+    ///
+    /// # Argument
+    ///
+    /// * `coords` - The relative coordinates to a sub texture of the atlas
+    pub fn sub_texture(&self, coords: Vector2<f32>) -> SubTexture {
+        let min: Vector2<f32> = Vector2::new(
+            (coords.x * self.sprite_size.x) / self.width as f32,
+            (coords.y * self.sprite_size.y) / self.height as f32,
+        );
+        let max: Vector2<f32> = Vector2::new(
+            ((coords.x + 1.0) * self.sprite_size.x) / self.width as f32,
+            ((coords.y + 1.0) * self.sprite_size.y) / self.height as f32,
+        );
+        SubTexture::new(&self, min, max)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,326 @@
+//! Types rendering a bloom around bright and emissive
+//! surfaces, e.g. lava and glowstone
+
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::framebuffer::RenderTarget;
+use crate::graphics::mesh::{Mesh, MeshBuilder, Model};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::shader::ShaderProgram;
+use crate::resources::Resources;
+use cgmath::Matrix4;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The color attachment of the scene target the colors
+/// of the scene are written to
+const SCENE_COLOR: usize = 0;
+/// The color attachment of the scene target the glow
+/// of emissive surfaces is written to
+const SCENE_EMISSION: usize = 1;
+
+/// BloomSettings
+///
+/// The settings of the bloom post effect
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BloomSettings {
+    /// Whether the bloom is rendered at all
+    pub enabled: bool,
+    /// The brightness above which colors of the
+    /// scene bloom, apart from emissive surfaces
+    pub threshold: f32,
+    /// How strong the bloom is added to the scene
+    pub intensity: f32,
+    /// The amount of blur iterations, each blurring
+    /// horizontally and vertically once
+    pub blur_passes: u32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 1.0,
+            intensity: 0.8,
+            blur_passes: 4,
+        }
+    }
+}
+
+/// Returns the size of the targets the bloom is blurred
+/// in, which have half the size of the screen
+///
+/// # Arguments
+///
+/// * `width` - The width of the screen
+/// * `height` - The height of the screen
+pub fn blur_size(width: i32, height: i32) -> (i32, i32) {
+    ((width / 2).max(1), (height / 2).max(1))
+}
+
+/// The offscreen targets of the bloom, which are
+/// shared by the capture and the bloom pass
+struct BloomTargets {
+    /// The target the scene is rendered to, with the
+    /// colors and the glow of the scene
+    scene: RenderTarget,
+    /// The targets the bloom is blurred in, alternately
+    blur: [RenderTarget; 2],
+    /// The size of the screen the targets were created for
+    size: (i32, i32),
+}
+
+impl BloomTargets {
+    /// Creates the targets for a screen of the given size
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `size` - The width and height of the screen
+    fn new(backend: &GlBackend, size: (i32, i32)) -> Self {
+        let (width, height) = blur_size(size.0, size.1);
+        Self {
+            scene: backend.create_render_target(size.0, size.1, 2, true),
+            blur: [
+                backend.create_render_target(width, height, 1, false),
+                backend.create_render_target(width, height, 1, false),
+            ],
+            size,
+        }
+    }
+}
+
+/// Creates the passes rendering the bloom. The capture
+/// pass redirects the scene into an offscreen target
+/// before the sky is drawn, while the bloom pass blurs
+/// its glow and draws the scene to the screen again.
+/// Both passes need to be added to the render passes.
+///
+/// # Arguments
+///
+/// * `backend` - The render backend
+/// * `resources` - A resource instance
+/// * `settings` - The settings of the bloom
+pub fn bloom_passes(backend: &GlBackend, resources: &Resources, settings: BloomSettings) -> (BloomCapture, BloomRenderer) {
+    let targets = Rc::new(RefCell::new(None));
+    let capture = BloomCapture {
+        backend: backend.clone(),
+        targets: targets.clone(),
+        settings,
+    };
+    (capture, BloomRenderer::new(backend, resources, targets, settings))
+}
+
+/// BloomScene
+///
+/// A handle to the offscreen target the bloom reads the
+/// scene from, so post effects drawn before the bloom
+/// could draw the scene into it
+#[derive(Clone)]
+pub struct BloomScene {
+    /// The offscreen targets, if the bloom is enabled
+    targets: Rc<RefCell<Option<BloomTargets>>>,
+}
+
+impl BloomScene {
+    /// Makes the scene target of the bloom the one which is
+    /// rendered to. Returns `false` if the bloom isn't drawn
+    /// with the current frame.
+    pub fn bind(&self) -> bool {
+        match self.targets.borrow().as_ref() {
+            Some(targets) => {
+                targets.scene.bind();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// BloomCapture
+///
+/// The pass redirecting the scene into the offscreen
+/// target of the bloom. It's the very first pass of
+/// a frame, the targets are recreated whenever the
+/// size of the screen changes. While the quality
+/// doesn't allow the bloom, its targets are released.
+pub struct BloomCapture {
+    /// The render backend
+    backend: GlBackend,
+    /// The offscreen targets, if the bloom is enabled
+    targets: Rc<RefCell<Option<BloomTargets>>>,
+    /// The settings of the bloom
+    settings: BloomSettings,
+}
+
+impl BloomCapture {
+    /// Returns a handle to the target the scene is
+    /// rendered to while the bloom is drawn
+    pub fn scene(&self) -> BloomScene {
+        BloomScene {
+            targets: self.targets.clone(),
+        }
+    }
+}
+
+impl<F: Frame> RenderPass<F> for BloomCapture {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Sky
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        if !self.settings.enabled {
+            return;
+        }
+        if !ctx.quality.bloom {
+            self.targets.borrow_mut().take();
+            return;
+        }
+
+        let size = ctx.renderer.viewport_size();
+        let mut targets = self.targets.borrow_mut();
+        if !matches!(targets.as_ref(), Some(targets) if targets.size == size) {
+            *targets = Some(BloomTargets::new(&self.backend, size));
+        }
+
+        if let Some(targets) = targets.as_ref() {
+            targets.scene.bind();
+            ctx.renderer.clear();
+        }
+    }
+}
+
+/// BloomRenderer
+///
+/// The post processing pass adding the bloom to the
+/// scene. It extracts the bright and emissive parts of
+/// the scene, blurs them and draws them on top of the
+/// scene to the screen. It's drawn before any other
+/// post processing pass, which draws to the screen.
+pub struct BloomRenderer {
+    /// The shader program extracting the bright parts
+    bright_shader: ShaderProgram,
+    /// The shader program blurring in one direction
+    blur_shader: ShaderProgram,
+    /// The shader program drawing the scene and the bloom
+    composite_shader: ShaderProgram,
+    /// A quad covering the whole screen
+    quad: Model,
+    /// The offscreen targets, if the bloom is enabled
+    targets: Rc<RefCell<Option<BloomTargets>>>,
+    /// The settings of the bloom
+    settings: BloomSettings,
+}
+
+impl BloomRenderer {
+    /// Creates a new bloom renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    /// * `targets` - The targets shared with the capture pass
+    /// * `settings` - The settings of the bloom
+    fn new(backend: &GlBackend, resources: &Resources, targets: Rc<RefCell<Option<BloomTargets>>>, settings: BloomSettings) -> Self {
+        let bright_shader = backend.create_pipeline(resources, "bloom_bright").unwrap();
+        bright_shader.disable();
+        let blur_shader = backend.create_pipeline(resources, "bloom_blur").unwrap();
+        blur_shader.disable();
+        let composite_shader = backend.create_pipeline(resources, "bloom_composite").unwrap();
+        composite_shader.disable();
+
+        // `Mesh::quad` spans from -0.5 to 0.5, while normalized
+        // device coordinates span from -1.0 to 1.0
+        let mut quad = MeshBuilder::new();
+        quad.add(&Mesh::quad(), &Matrix4::from_scale(2.0));
+
+        Self {
+            bright_shader,
+            blur_shader,
+            composite_shader,
+            quad: Model::from_mesh(backend, &quad.build()),
+            targets,
+            settings,
+        }
+    }
+
+    /// Draws the fullscreen quad with the given shader program
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The render context of the current frame
+    /// * `shader_program` - The enabled shader program
+    fn draw_quad<F>(&self, ctx: &RenderContext<F>, shader_program: &ShaderProgram) {
+        self.quad.bind();
+        ctx.renderer.draw_with(self.quad.va(), self.quad.ib(), shader_program, self.quad.topology());
+        self.quad.unbind();
+    }
+}
+
+impl<F: Frame> RenderPass<F> for BloomRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::PostProcess
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let targets = self.targets.borrow();
+        let targets = match targets.as_ref() {
+            Some(targets) => targets,
+            None => return,
+        };
+
+        ctx.renderer.set_depth_test(false);
+        ctx.renderer.set_alpha_blending(false);
+
+        // Extract the bright and emissive parts of the scene
+        let [first, second] = &targets.blur;
+        first.bind();
+        targets.scene.bind_color(SCENE_COLOR, 0);
+        targets.scene.bind_color(SCENE_EMISSION, 1);
+        self.bright_shader.enable();
+        self.bright_shader.set_uniform_1i("u_Scene", 0);
+        self.bright_shader.set_uniform_1i("u_Emission", 1);
+        self.bright_shader.set_uniform_1f("u_Threshold", self.settings.threshold);
+        self.draw_quad(ctx, &self.bright_shader);
+
+        // Blur them horizontally and vertically in turns
+        self.blur_shader.enable();
+        self.blur_shader.set_uniform_1i("u_Texture", 0);
+        for _ in 0..self.settings.blur_passes {
+            second.bind();
+            first.bind_color(0, 0);
+            self.blur_shader.set_uniform_2f("u_Direction", 1.0, 0.0);
+            self.draw_quad(ctx, &self.blur_shader);
+
+            first.bind();
+            second.bind_color(0, 0);
+            self.blur_shader.set_uniform_2f("u_Direction", 0.0, 1.0);
+            self.draw_quad(ctx, &self.blur_shader);
+        }
+
+        // Draw the scene with the bloom to the screen
+        first.unbind();
+        ctx.renderer.set_viewport(targets.size.0, targets.size.1);
+        first.bind_color(0, 1);
+        targets.scene.bind_color(SCENE_COLOR, 0);
+        self.composite_shader.enable();
+        self.composite_shader.set_uniform_1i("u_Scene", 0);
+        self.composite_shader.set_uniform_1i("u_Bloom", 1);
+        self.composite_shader.set_uniform_1f("u_Intensity", self.settings.intensity);
+        self.draw_quad(ctx, &self.composite_shader);
+        self.composite_shader.disable();
+
+        ctx.renderer.set_alpha_blending(true);
+        ctx.renderer.set_depth_test(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bloom_is_blurred_at_half_size() {
+        assert_eq!(blur_size(1080, 720), (540, 360));
+        assert_eq!(blur_size(1, 0), (1, 1));
+    }
+}
@@ -1,167 +1,209 @@
-//! Types and traits representing graphics buffers
-//! like `VertexBuffer`s, `IndexBuffer`s and
-//! `VertexArray`s
+//! Types and traits describing the content of graphics
+//! buffers, independent of the graphics API.
+//!
+//! The buffers themselves are created by a `RenderBackend`,
+//! the `OpenGL` ones are re-exported here.
 
-use crate::graphics::gl::{Gl, gl, types::*};
+use crate::graphics::attribute::VertexAttribute;
 use std::mem::size_of;
-use std::any::type_name;
-use std::os::raw::c_uchar;
 use std::slice::Iter;
+use cgmath::{Vector2, Vector3, Vector4};
 
-/// VertexBuffer
+pub use crate::graphics::backend::opengl::buffer::{IndexBuffer, VertexArray, VertexBuffer};
+
+/// IndexType
 ///
-/// A `VertexBuffer` is used to store
-/// any kinds of vertices.
-pub struct VertexBuffer {
-    /// The id of the vertex buffer
-    id: GLuint,
-    /// An `OpenGL` instance
-    gl: Gl,
+/// The width of the indices stored in an `IndexBuffer`:
+/// * `U16` - 16 bit unsigned integers
+/// * `U32` - 32 bit unsigned integers
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum IndexType {
+    U16,
+    U32,
 }
 
-impl VertexBuffer {
-    /// Creates a new vertex buffer
+impl IndexType {
+    /// Returns the smallest index type which is able
+    /// to address the given amount of vertices
     ///
     /// # Arguments
     ///
-    /// * `gl` - An `OpenGL` instance
-    /// * `data` - A pointer to the data
-    /// * `size` - The size of the data
-    pub fn new(gl: &Gl, data: *const GLvoid, size: isize) -> Self {
-        let mut buffer: GLuint = 0;
-        unsafe {
-            gl.GenBuffers(1, &mut buffer);
-            gl.BindBuffer(gl::ARRAY_BUFFER, buffer);
-            gl.BufferData(gl::ARRAY_BUFFER, size, data, gl::STATIC_DRAW);
-        }
-
-        VertexBuffer {
-            gl: gl.clone(),
-            id: buffer,
+    /// * `vertex_count` - The amount of vertices which
+    /// should be addressed
+    pub fn for_vertex_count(vertex_count: usize) -> Self {
+        if vertex_count <= u16::MAX as usize + 1 {
+            IndexType::U16
+        } else {
+            IndexType::U32
         }
     }
 
-    /// Binds the buffer
-    pub fn bind(&self) {
-        unsafe { self.gl.BindBuffer(gl::ARRAY_BUFFER, self.id); }
+    /// Returns the smallest index type which is able to store
+    /// all of the given indices. Indices referring to vertices
+    /// beyond the vertex count are a bug in the caller, but they
+    /// are never truncated.
+    ///
+    /// # Arguments
+    ///
+    /// * `indices` - The indices
+    /// * `vertex_count` - The amount of vertices the indices refer to
+    pub fn for_indices(indices: &[u32], vertex_count: usize) -> Self {
+        let max_index = indices.iter().max().map_or(0, |&index| index as usize + 1);
+        debug_assert!(
+            max_index <= vertex_count,
+            "Index {} is out of bounds for {} vertices", max_index - 1, vertex_count
+        );
+        Self::for_vertex_count(vertex_count.max(max_index))
     }
 
-    /// Unbinds the buffer
-    pub fn unbind(&self) {
-        unsafe { self.gl.BindBuffer(gl::ARRAY_BUFFER, 0); }
+    /// Returns the size of a single index in bytes
+    pub fn size(&self) -> usize {
+        match self {
+            IndexType::U16 => size_of::<u16>(),
+            IndexType::U32 => size_of::<u32>(),
+        }
     }
+}
 
-    /// Returns the id of the `VertexBuffer`
-    pub fn id(&self) -> GLuint {
-        self.id
-    }
+/// ComponentType
+///
+/// The type of a single component of a vertex attribute:
+/// * `F32` - 32 bit floats
+/// * `I32` - 32 bit signed integers
+/// * `U32` - 32 bit unsigned integers
+/// * `U8` - 8 bit unsigned integers
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ComponentType {
+    F32,
+    I32,
+    U32,
+    U8,
 }
 
-impl Drop for VertexBuffer {
-    fn drop(&mut self) {
-        unsafe { self.gl.DeleteBuffers(1, &self.id); }
+impl ComponentType {
+    /// Returns whether the component is an integer type
+    pub fn is_integer(&self) -> bool {
+        *self != ComponentType::F32
     }
 }
 
-/// IndexBuffer
+/// VertexAttrib
 ///
-/// A `IndexBuffer` is used to store
-/// a bunch of indices for different
-/// vertices.
-/// At the moment, just 32 bit integers
-/// are allowed.
-pub struct IndexBuffer {
-    /// The id of the vertex buffer
-    id: GLuint,
-    /// An `OpenGL` instance
-    gl: Gl,
-    /// The index count
-    index_count: usize,
+/// A type which could be stored as a single vertex
+/// attribute. It describes the type of its components
+/// and the component count, e.g. a `Vector3<f32>`
+/// consists of three `F32`s.
+pub trait VertexAttrib: Copy {
+    /// The type of each component
+    const COMPONENT_TYPE: ComponentType;
+    /// The count of components
+    const COMPONENTS: i32;
+    /// Whether integer components should be normalized
+    /// to `[0.0, 1.0]` when accessed in a shader
+    const NORMALIZED: bool;
 }
 
-impl IndexBuffer {
-    /// Creates a new `IndexBuffer` from the
-    /// given indices and stores its length.
-    ///
-    /// # Arguments
-    ///
-    /// * `gl` - A reference to an `OpenGL` instance
-    /// * `indices` - A pointer to the data
-    /// * `index_count` - The index count of the data
-    pub fn new(gl: &Gl, indices: *const u32, index_count: usize) -> Self {
-        let mut buffer: GLuint = 0;
-        unsafe {
-            gl.GenBuffers(1, &mut buffer);
-            gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, buffer);
-            gl.BufferData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                (index_count * size_of::<u32>()) as isize,
-                indices as *const GLvoid,
-                gl::STATIC_DRAW
-            );
+/// Implements `VertexAttrib` for scalar types, arrays
+/// and `cgmath` vectors of a given component type
+macro_rules! impl_vertex_attrib {
+    ($component:ty, $component_type:expr, $normalized:expr) => {
+        impl VertexAttrib for $component {
+            const COMPONENT_TYPE: ComponentType = $component_type;
+            const COMPONENTS: i32 = 1;
+            const NORMALIZED: bool = $normalized;
         }
 
-        IndexBuffer {
-            gl: gl.clone(),
-            id: buffer,
-            index_count
+        impl_vertex_attrib!(@vector $component, $component_type, $normalized, [$component; 2], 2);
+        impl_vertex_attrib!(@vector $component, $component_type, $normalized, [$component; 3], 3);
+        impl_vertex_attrib!(@vector $component, $component_type, $normalized, [$component; 4], 4);
+        impl_vertex_attrib!(@vector $component, $component_type, $normalized, Vector2<$component>, 2);
+        impl_vertex_attrib!(@vector $component, $component_type, $normalized, Vector3<$component>, 3);
+        impl_vertex_attrib!(@vector $component, $component_type, $normalized, Vector4<$component>, 4);
+    };
+    (@vector $component:ty, $component_type:expr, $normalized:expr, $vector:ty, $count:expr) => {
+        impl VertexAttrib for $vector {
+            const COMPONENT_TYPE: ComponentType = $component_type;
+            const COMPONENTS: i32 = $count;
+            const NORMALIZED: bool = $normalized;
         }
-    }
-
-    /// Binds the buffer
-    pub fn bind(&self) {
-        unsafe { self.gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id); }
-    }
-
-    /// Unbinds the buffer
-    pub fn unbind(&self) {
-        unsafe { self.gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0); }
-    }
+    };
+}
 
-    /// Returns the id of the `IndexBuffer`
-    pub fn id(&self) -> GLuint {
-        self.id
-    }
+impl_vertex_attrib!(f32, ComponentType::F32, false);
+impl_vertex_attrib!(i32, ComponentType::I32, false);
+impl_vertex_attrib!(u32, ComponentType::U32, false);
+impl_vertex_attrib!(u8, ComponentType::U8, true);
 
-    /// Returns the index count of the `IndexBuffer`
-    pub fn index_count(&self) -> usize {
-        self.index_count
-    }
+/// Vertex
+///
+/// A `Vertex` is a struct of multiple vertex attributes
+/// which are stored interleaved in a single vertex buffer.
+/// Usually, this trait is implemented with the `impl_vertex!`
+/// macro.
+pub trait Vertex: Copy {
+    /// Returns the layout of the vertex
+    fn layout() -> VertexBufferLayout;
 }
 
-impl Drop for IndexBuffer {
-    fn drop(&mut self) {
-        unsafe { self.gl.DeleteBuffers(1, &self.id); }
-    }
+/// Implements `Vertex` for a struct by binding each listed
+/// field to a registered `VertexAttribute`. The type of each
+/// field needs to implement `VertexAttrib`.
+///
+/// # Example
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Copy, Clone)]
+/// struct SimpleVertex {
+///     pos: Vector3<f32>,
+///     uv: Vector2<f32>,
+/// }
+///
+/// impl_vertex!(SimpleVertex {
+///     pos => attribute::POSITION,
+///     uv => attribute::TEX_COORD,
+/// });
+/// ```
+#[macro_export]
+macro_rules! impl_vertex {
+    ($vertex:ty { $($field:ident => $attribute:expr),* $(,)? }) => {
+        impl $crate::graphics::buffer::Vertex for $vertex {
+            fn layout() -> $crate::graphics::buffer::VertexBufferLayout {
+                let mut layout = $crate::graphics::buffer::VertexBufferLayout::new();
+                $(
+                    layout.push_field(
+                        $attribute,
+                        std::mem::offset_of!($vertex, $field),
+                        |vertex: &$vertex| &vertex.$field,
+                    );
+                )*
+                layout
+            }
+        }
+    };
 }
 
 /// VertexBufferElement
 ///
-struct VertexBufferElement {
-    count: i32,
-    element_type: u32,
-    normalized: u8,
-}
-
-impl VertexBufferElement {
-    /// Returns the `OpenGL` type as
-    /// `GLenum`
-    ///
-    /// # Arguments
-    ///
-    /// * `opengl_type` - An `OpenGL` type
-    fn size_of_opengl_type(opengl_type: u32) -> i32 {
-        match opengl_type {
-            gl::FLOAT => 4,
-            gl::UNSIGNED_INT => 4,
-            gl::UNSIGNED_BYTE => 4,
-            _ => panic!("Unsupported type!"),
-        }
-    }
+/// A single attribute within a `VertexBufferLayout`
+#[derive(Copy, Clone, Debug)]
+pub struct VertexBufferElement {
+    /// The attribute the element is bound to
+    pub attribute: VertexAttribute,
+    /// The count of components
+    pub count: i32,
+    /// The type of each component
+    pub component_type: ComponentType,
+    /// Whether integer components are normalized
+    pub normalized: bool,
+    /// The offset of the element within a vertex in bytes
+    pub offset: i32,
 }
 
 /// VertexBufferLayout
 ///
+/// The layout of the vertices stored in a vertex buffer
+#[derive(Clone, Debug)]
 pub struct VertexBufferLayout {
     /// A vector of vertex buffer elements
     elements: Vec<VertexBufferElement>,
@@ -178,118 +220,140 @@ impl VertexBufferLayout {
         }
     }
 
-    /// Pushes a new element to the layout
+    /// Pushes a new element to the end of the layout
     ///
-    /// This method uses the type name of the give type to
-    /// transform the type in an `OpenGL`-known type.
-    /// Actually, this isn't best practice and should be replaced
-    /// by an own `TypeWrapper` with the `OpenGL` types associated.
-    pub fn push<T: ?Sized>(&mut self, count: i32, normalized: u8) {
-        let element_type: GLenum;
-
-        match type_name::<T>() {
-            "f32" => element_type = gl::FLOAT,
-            "u32" => element_type = gl::UNSIGNED_INT,
-            "os::raw::c_uchar" => element_type = gl::UNSIGNED_BYTE,
-            _ => panic!("Unsupported type!"),
-        }
-
-        self.stride += VertexBufferElement::size_of_opengl_type(element_type) * count;
+    /// # Arguments
+    ///
+    /// * `attribute` - The attribute the element is bound to
+    pub fn push<T: VertexAttrib>(&mut self, attribute: VertexAttribute) {
         self.elements.push(VertexBufferElement {
-            count,
-            element_type,
-            normalized,
+            attribute,
+            count: T::COMPONENTS,
+            component_type: T::COMPONENT_TYPE,
+            normalized: T::NORMALIZED,
+            offset: self.stride,
         });
+        self.stride += size_of::<T>() as i32;
     }
 
-    /// Push a new f32 element to the layout
-    pub fn push_f32(&mut self, count: i32) {
-        self.push::<f32>(count, gl::FALSE);
-    }
-
-    /// Push a new u32 element to the layout
-    pub fn push_u32(&mut self, count: i32) {
-        self.push::<u32>(count, gl::FALSE);
-    }
-
-    /// Push a new f32 element to the layout
-    pub fn push_uchar(&mut self, count: i32) {
-        self.push::<c_uchar>(count, gl::TRUE);
+    /// Pushes a field of the vertex struct `V` to the layout.
+    /// The stride of the layout is set to the size of `V`.
+    /// This method is used by the `impl_vertex!` macro.
+    ///
+    /// # Arguments
+    ///
+    /// * `attribute` - The attribute the field is bound to
+    /// * `offset` - The offset of the field within `V`
+    /// * `_field` - An accessor of the field, used to infer its type
+    pub fn push_field<V: Copy, T: VertexAttrib>(&mut self, attribute: VertexAttribute, offset: usize, _field: fn(&V) -> &T) {
+        self.elements.push(VertexBufferElement {
+            attribute,
+            count: T::COMPONENTS,
+            component_type: T::COMPONENT_TYPE,
+            normalized: T::NORMALIZED,
+            offset: offset as i32,
+        });
+        self.stride = size_of::<V>() as i32;
     }
 
     /// Returns the elements of the layout as
     /// an iterator
-    fn elements(&self) -> Iter<'_, VertexBufferElement> {
+    pub fn elements(&self) -> Iter<'_, VertexBufferElement> {
         self.elements.iter()
     }
 
     /// Returns the stride of the layout
-    fn stride(&self) -> i32 {
+    pub fn stride(&self) -> i32 {
         self.stride
     }
 }
 
-/// VertexArray
-///
-/// A vertex array is supposed to tie together a
-/// buffer with an actual layout
-pub struct VertexArray {
-    /// An `OpenGL` instance
-    gl: Gl,
-    /// The id of the `VertexArray`
-    id: GLuint,
-    /// The buffer count
-    buffer_count: u8,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::attribute;
+
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct TestVertex {
+        position: Vector3<f32>,
+        color: [u8; 4],
+        tile_coord: [i32; 2],
+    }
 
-impl VertexArray {
-    /// Create a new vertex array
-    ///
-    /// # Arguments
-    ///
-    /// * `gl` - An `OpenGL` instance
-    pub fn new(gl: &Gl) -> Self {
-        let mut vao: GLuint = 0;
-        unsafe {
-            gl.GenVertexArrays(1, &mut vao);
-            gl.BindVertexArray(vao);
-        }
+    impl_vertex!(TestVertex {
+        position => attribute::POSITION,
+        color => attribute::TEX_COORD,
+        tile_coord => attribute::TILE_COORD,
+    });
+
+    #[test]
+    fn layout_is_derived_from_repr_c_struct() {
+        let layout = TestVertex::layout();
+        assert_eq!(layout.stride(), size_of::<TestVertex>() as i32);
+
+        let elements: Vec<&VertexBufferElement> = layout.elements().collect();
+        assert_eq!(elements.len(), 3);
+
+        assert_eq!(elements[0].attribute, attribute::POSITION);
+        assert_eq!(elements[0].count, 3);
+        assert_eq!(elements[0].component_type, ComponentType::F32);
+        assert!(!elements[0].normalized);
+        assert_eq!(elements[0].offset, 0);
+
+        assert_eq!(elements[1].attribute, attribute::TEX_COORD);
+        assert_eq!(elements[1].count, 4);
+        assert_eq!(elements[1].component_type, ComponentType::U8);
+        assert!(elements[1].normalized);
+        assert_eq!(elements[1].offset, 12);
+
+        assert_eq!(elements[2].attribute, attribute::TILE_COORD);
+        assert_eq!(elements[2].count, 2);
+        assert_eq!(elements[2].component_type, ComponentType::I32);
+        assert!(!elements[2].normalized);
+        assert_eq!(elements[2].offset, 16);
+    }
 
-        VertexArray {
-            id: vao,
-            gl: gl.clone(),
-            buffer_count: 0,
-        }
+    #[test]
+    fn pushed_layout_accumulates_stride() {
+        let mut layout = VertexBufferLayout::new();
+        layout.push::<[f32; 3]>(attribute::POSITION);
+        layout.push::<Vector2<f32>>(attribute::TEX_COORD);
+        layout.push::<u32>(attribute::TILE_COORD);
+
+        let offsets: Vec<i32> = layout.elements().map(|element| element.offset).collect();
+        assert_eq!(offsets, vec![0, 12, 20]);
+        assert_eq!(layout.stride(), 24);
     }
 
-    /// Add a buffer to the vertex array
-    pub fn add_buffer(&mut self, vb: &VertexBuffer, layout: &VertexBufferLayout) {
-        let mut offset = 0;
-
-        self.bind();
-        vb.bind();
-        layout.elements().for_each(|element | unsafe {
-            let index = self.buffer_count as u32;
-            self.gl.EnableVertexAttribArray(index);
-            self.gl.VertexAttribPointer(index, element.count, element.element_type, element.normalized, layout.stride(), offset as *const gl::types::GLvoid);
-            offset += element.count * VertexBufferElement::size_of_opengl_type(element.element_type);
-            self.buffer_count += 1;
-        });
+    #[test]
+    fn index_type_for_vertex_count() {
+        assert_eq!(IndexType::for_vertex_count(0), IndexType::U16);
+        assert_eq!(IndexType::for_vertex_count(65535), IndexType::U16);
+        // The indices of 65536 vertices range from 0 to 65535
+        assert_eq!(IndexType::for_vertex_count(65536), IndexType::U16);
+        assert_eq!(IndexType::for_vertex_count(65537), IndexType::U32);
     }
 
-    /// Binds the vertex array
-    pub fn bind(&self) {
-        unsafe { self.gl.BindVertexArray(self.id); }
+    #[test]
+    fn index_type_for_indices() {
+        assert_eq!(IndexType::for_indices(&[], 0), IndexType::U16);
+        assert_eq!(IndexType::for_indices(&[0, 1, 65535], 65536), IndexType::U16);
+        assert_eq!(IndexType::for_indices(&[0, 65536], 65537), IndexType::U32);
     }
 
-    /// Unbinds the vertex array
-    pub fn unbind(&self) {
-        unsafe { self.gl.BindVertexArray(self.id); }
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    #[cfg(debug_assertions)]
+    fn index_type_for_out_of_bounds_indices() {
+        IndexType::for_indices(&[0, 3], 3);
     }
-}
 
-impl Drop for VertexArray {
-    fn drop(&mut self) {
-        unsafe { self.gl.DeleteVertexArrays(1, &self.id); }
+    #[test]
+    fn integer_component_types() {
+        assert!(!ComponentType::F32.is_integer());
+        assert!(ComponentType::I32.is_integer());
+        assert!(ComponentType::U32.is_integer());
+        assert!(ComponentType::U8.is_integer());
     }
-}
\ No newline at end of file
+}
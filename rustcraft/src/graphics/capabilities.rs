@@ -0,0 +1,323 @@
+//! Types describing what the graphics driver supports, which
+//! gate optional features, and the debug panel showing them
+
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::quality::SETTINGS_FILE;
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use serde::Deserialize;
+
+/// The distance of the panel to the edges of the screen
+const PANEL_MARGIN: f32 = 8.0;
+/// The extensions which allow anisotropic filtering
+/// before it became part of `OpenGL` 4.6
+const ANISOTROPY_EXTENSIONS: [&str; 2] = ["GL_EXT_texture_filter_anisotropic", "GL_ARB_texture_filter_anisotropic"];
+/// The extension which allows compute shaders before
+/// they became part of `OpenGL` 4.3
+const COMPUTE_EXTENSION: &str = "GL_ARB_compute_shader";
+
+/// GraphicsSettings
+///
+/// The optional graphics features the player asked for,
+/// declared in the `[graphics]` table of the settings
+/// resource, e.g.
+///
+/// ```toml
+/// [graphics]
+/// msaa = 4
+/// anisotropy = 16.0
+/// ```
+///
+/// Features the driver doesn't support are left out.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    /// The samples per pixel of the window, 0 to
+    /// disable multisampling
+    pub msaa: u32,
+    /// The level of the anisotropic filtering of
+    /// textures, 1 to disable it
+    pub anisotropy: f32,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            msaa: 4,
+            anisotropy: 16.0,
+        }
+    }
+}
+
+/// The declaration of the settings resource
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct SettingsDef {
+    /// The settings of the graphics features
+    graphics: GraphicsSettings,
+}
+
+impl GraphicsSettings {
+    /// Loads the settings declared by the resources. If they
+    /// can't be loaded, the default settings are used.
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - A resource instance
+    pub fn load(resources: &Resources) -> Self {
+        let source = match resources.load_string(SETTINGS_FILE) {
+            Ok(source) => source,
+            Err(error) => {
+                log::warn!("Failed to load {}: {:?}", SETTINGS_FILE, error);
+                return Self::default();
+            }
+        };
+
+        Self::from_toml(&source).unwrap_or_else(|error| {
+            log::warn!("Failed to parse {}: {}", SETTINGS_FILE, error);
+            Self::default()
+        })
+    }
+
+    /// Parses the settings from the `TOML` declaration
+    /// of the settings resource
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The declaration of the settings
+    pub fn from_toml(source: &str) -> Result<Self, toml::de::Error> {
+        let def: SettingsDef = toml::from_str(source)?;
+        Ok(def.graphics)
+    }
+}
+
+/// GpuCapabilities
+///
+/// What the graphics driver supports, which is queried
+/// once at startup. It decides which optional features
+/// are used and is shown on the capabilities panel and
+/// in crash reports.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GpuCapabilities {
+    /// The company responsible for the driver
+    pub vendor: String,
+    /// The name of the graphics card or renderer
+    pub renderer: String,
+    /// The version string of the driver
+    pub version: String,
+    /// The version string of the shading language
+    pub glsl_version: String,
+    /// The major and minor version of the context
+    pub version_number: (u32, u32),
+    /// The names of the supported extensions
+    pub extensions: Vec<String>,
+    /// The largest width and height of 2D textures
+    pub max_texture_size: u32,
+    /// The largest width, height and depth of 3D textures
+    pub max_3d_texture_size: u32,
+    /// The amount of textures which could be bound at once
+    pub max_texture_units: u32,
+    /// The most samples per pixel of multisampling
+    pub max_samples: u32,
+    /// The samples per pixel of the window
+    pub window_samples: u32,
+    /// The highest level of anisotropic filtering, or 0
+    /// if it isn't supported
+    pub max_anisotropy: f32,
+}
+
+impl GpuCapabilities {
+    /// Returns whether the driver supports an extension
+    ///
+    /// # Arguments
+    ///
+    /// * `extension` - The name of the extension, e.g. `GL_ARB_compute_shader`
+    pub fn supports(&self, extension: &str) -> bool {
+        self.extensions.iter().any(|other| other == extension)
+    }
+
+    /// Returns whether the context has at least the given version
+    ///
+    /// # Arguments
+    ///
+    /// * `major` - The major version
+    /// * `minor` - The minor version
+    pub fn has_version(&self, major: u32, minor: u32) -> bool {
+        self.version_number >= (major, minor)
+    }
+
+    /// Returns whether anisotropic filtering is supported
+    pub fn supports_anisotropy(&self) -> bool {
+        (self.has_version(4, 6) || ANISOTROPY_EXTENSIONS.iter().any(|extension| self.supports(extension)))
+            && self.max_anisotropy > 1.0
+    }
+
+    /// Returns the level of anisotropic filtering textures are
+    /// filtered with, or `None` if it's disabled or unsupported
+    ///
+    /// # Arguments
+    ///
+    /// * `requested` - The level the player asked for
+    pub fn anisotropy(&self, requested: f32) -> Option<f32> {
+        if requested > 1.0 && self.supports_anisotropy() {
+            Some(requested.min(self.max_anisotropy))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the supported samples per pixel of multisampling,
+    /// the lowest amount first
+    pub fn msaa_levels(&self) -> Vec<u32> {
+        (1..).map(|exponent| 1 << exponent)
+            .take_while(|samples| *samples <= self.max_samples)
+            .collect()
+    }
+
+    /// Returns the most samples per pixel of multisampling up to
+    /// the given amount, or 0 if multisampling is disabled
+    ///
+    /// # Arguments
+    ///
+    /// * `requested` - The samples per pixel the player asked for
+    pub fn msaa_samples(&self, requested: u32) -> u32 {
+        self.msaa_levels().into_iter().filter(|samples| *samples <= requested).max().unwrap_or(0)
+    }
+
+    /// Returns whether compute shaders are supported
+    pub fn supports_compute(&self) -> bool {
+        self.has_version(4, 3) || self.supports(COMPUTE_EXTENSION)
+    }
+
+    /// Returns the lines describing the capabilities, as they
+    /// are shown on the capabilities panel
+    pub fn lines(&self) -> Vec<String> {
+        let supported = |supported: bool| if supported { "yes" } else { "no" };
+        let levels: Vec<String> = self.msaa_levels().iter().map(|samples| format!("{}x", samples)).collect();
+        vec![
+            format!("Vendor      {}", self.vendor),
+            format!("Renderer    {}", self.renderer),
+            format!("OpenGL      {}", self.version),
+            format!("GLSL        {}", self.glsl_version),
+            format!("Extensions  {}", self.extensions.len()),
+            format!("Texture     {0}x{0}", self.max_texture_size),
+            format!("3D texture  {0}x{0}x{0}", self.max_3d_texture_size),
+            format!("Tex units   {}", self.max_texture_units),
+            format!("MSAA        {} (window {}x)", if levels.is_empty() { "none".to_string() } else { levels.join(" ") }, self.window_samples),
+            format!("Anisotropy  {}", if self.supports_anisotropy() { format!("{}x", self.max_anisotropy) } else { "no".to_string() }),
+            format!("Compute     {}", supported(self.supports_compute())),
+        ]
+    }
+
+    /// Returns the lines describing the capabilities followed by
+    /// the names of all extensions, e.g. for crash reports
+    pub fn report(&self) -> Vec<String> {
+        let mut lines = self.lines();
+        lines.extend(self.extensions.iter().map(|extension| format!("            {}", extension)));
+        lines
+    }
+}
+
+/// CapabilitiesFrame
+///
+/// A frame the capabilities panel could be rendered for
+pub trait CapabilitiesFrame: Frame {
+    /// Returns whether the capabilities panel is shown
+    fn show_capabilities(&self) -> bool;
+}
+
+/// CapabilitiesPanel
+///
+/// The debug panel drawing the capabilities of the
+/// graphics driver into the bottom left corner of the
+/// screen
+pub struct CapabilitiesPanel {
+    /// The painter drawing the panel
+    painter: OverlayPainter,
+    /// The lines shown on the panel
+    lines: Vec<String>,
+}
+
+impl CapabilitiesPanel {
+    /// Creates a new capabilities panel
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+            lines: backend.capabilities().lines(),
+        }
+    }
+}
+
+impl<F: CapabilitiesFrame> RenderPass<F> for CapabilitiesPanel {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        if !ctx.frame.show_capabilities() {
+            return;
+        }
+
+        let height = OverlayBuilder::panel_height(self.lines.len());
+        let mut overlay = OverlayBuilder::default();
+        overlay.panel([PANEL_MARGIN, OVERLAY_HEIGHT - PANEL_MARGIN - height], &self.lines);
+
+        let width = OVERLAY_HEIGHT * ctx.frame.camera().aspect_ratio();
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capabilities(version_number: (u32, u32), extensions: &[&str]) -> GpuCapabilities {
+        GpuCapabilities {
+            version_number,
+            extensions: extensions.iter().map(|extension| extension.to_string()).collect(),
+            max_samples: 8,
+            max_anisotropy: 16.0,
+            ..GpuCapabilities::default()
+        }
+    }
+
+    #[test]
+    fn features_are_gated_by_the_driver() {
+        let old = capabilities((3, 3), &[]);
+        assert_eq!(old.anisotropy(16.0), None);
+        assert!(!old.supports_compute());
+
+        let extended = capabilities((3, 3), &["GL_EXT_texture_filter_anisotropic", COMPUTE_EXTENSION]);
+        assert_eq!(extended.anisotropy(32.0), Some(16.0));
+        assert_eq!(extended.anisotropy(4.0), Some(4.0));
+        assert_eq!(extended.anisotropy(1.0), None);
+        assert!(extended.supports_compute());
+
+        let modern = capabilities((4, 6), &[]);
+        assert_eq!(modern.anisotropy(8.0), Some(8.0));
+        assert!(modern.supports_compute());
+    }
+
+    #[test]
+    fn msaa_levels_are_limited_by_the_driver() {
+        let capabilities = capabilities((3, 3), &[]);
+        assert_eq!(capabilities.msaa_levels(), vec![2, 4, 8]);
+        assert_eq!(capabilities.msaa_samples(16), 8);
+        assert_eq!(capabilities.msaa_samples(6), 4);
+        assert_eq!(capabilities.msaa_samples(1), 0);
+        assert_eq!(GpuCapabilities::default().msaa_samples(4), 0);
+    }
+
+    #[test]
+    fn settings_default_without_graphics_table() {
+        assert_eq!(GraphicsSettings::from_toml("[quality]\nadaptive = true").unwrap(), GraphicsSettings::default());
+        let settings = GraphicsSettings::from_toml("[graphics]\nmsaa = 0").unwrap();
+        assert_eq!(settings, GraphicsSettings { msaa: 0, anisotropy: 16.0 });
+    }
+}
@@ -0,0 +1,136 @@
+//! Types configuring the haze the chunk shader draws in
+//! caves, which is marched through the sky light of the
+//! light maps
+
+use crate::graphics::quality::SETTINGS_FILE;
+use crate::graphics::shader::ShaderProgram;
+use crate::resources::Resources;
+use serde::Deserialize;
+
+/// CaveFogSettings
+///
+/// The settings of the cave fog, declared in the
+/// `[cave_fog]` table of the settings resource, e.g.
+///
+/// ```toml
+/// [cave_fog]
+/// enabled = true
+/// density = 0.05
+/// color = [0.16, 0.2, 0.28]
+/// light_shafts = 0.4
+/// ```
+///
+/// The chunk shader samples the sky light along the view
+/// ray in front of each surface. Where the sky doesn't reach,
+/// a cool haze thickens with the distance, while light falling
+/// in through an opening brightens the air it passes.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CaveFogSettings {
+    /// Whether the fog is drawn
+    pub enabled: bool,
+    /// How much of the haze is added per block the view
+    /// ray passes through darkness
+    pub density: f32,
+    /// The color of the haze
+    pub color: [f32; 3],
+    /// How much the light falling in through openings
+    /// brightens the air, or 0 to disable it
+    pub light_shafts: f32,
+}
+
+impl Default for CaveFogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            density: 0.05,
+            color: [0.16, 0.2, 0.28],
+            light_shafts: 0.4,
+        }
+    }
+}
+
+/// The declaration of the settings resource
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct SettingsDef {
+    /// The settings of the cave fog
+    cave_fog: CaveFogSettings,
+}
+
+impl CaveFogSettings {
+    /// Loads the settings declared by the resources. If they
+    /// can't be loaded, the default settings are used.
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - A resource instance
+    pub fn load(resources: &Resources) -> Self {
+        let source = match resources.load_string(SETTINGS_FILE) {
+            Ok(source) => source,
+            Err(error) => {
+                log::warn!("Failed to load {}: {:?}", SETTINGS_FILE, error);
+                return Self::default();
+            }
+        };
+
+        Self::from_toml(&source).unwrap_or_else(|error| {
+            log::warn!("Failed to parse {}: {}", SETTINGS_FILE, error);
+            Self::default()
+        })
+    }
+
+    /// Parses the settings from the `TOML` declaration
+    /// of the settings resource
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The declaration of the settings
+    pub fn from_toml(source: &str) -> Result<Self, toml::de::Error> {
+        let def: SettingsDef = toml::from_str(source)?;
+        Ok(def.cave_fog)
+    }
+
+    /// Returns the density of the haze and the strength of
+    /// the light shafts a frame is drawn with, which are both
+    /// 0 if the fog isn't drawn
+    ///
+    /// # Arguments
+    ///
+    /// * `allowed` - Whether the quality of the frame allows the fog
+    pub fn strength(&self, allowed: bool) -> (f32, f32) {
+        if !self.enabled || !allowed {
+            return (0.0, 0.0);
+        }
+        (self.density.max(0.0), self.light_shafts.max(0.0))
+    }
+
+    /// Sets the uniforms of the fog in the chunk shader
+    ///
+    /// # Arguments
+    ///
+    /// * `shader_program` - The enabled chunk shader
+    /// * `allowed` - Whether the quality of the frame allows the fog
+    pub fn apply(&self, shader_program: &ShaderProgram, allowed: bool) {
+        let (density, light_shafts) = self.strength(allowed);
+        shader_program.set_uniform_1f("u_FogDensity", density);
+        shader_program.set_uniform_1f("u_LightShafts", light_shafts);
+        shader_program.set_uniform_3f("u_FogColor", self.color[0], self.color[1], self.color[2]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fog_is_dropped_with_the_quality() {
+        let settings = CaveFogSettings::default();
+        assert_eq!(settings.strength(true), (0.05, 0.4));
+        assert_eq!(settings.strength(false), (0.0, 0.0));
+
+        let disabled = CaveFogSettings::from_toml("[cave_fog]\nenabled = false").unwrap();
+        assert_eq!(disabled.strength(true), (0.0, 0.0));
+        assert_eq!(CaveFogSettings::from_toml("[graphics]\nmsaa = 0").unwrap(), settings);
+    }
+}
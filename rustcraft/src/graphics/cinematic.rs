@@ -0,0 +1,344 @@
+//! Types rendering the cinematic post effects, a depth of
+//! field and a motion blur, e.g. for flythroughs of the
+//! camera path
+
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::bloom::BloomScene;
+use crate::graphics::framebuffer::RenderTarget;
+use crate::graphics::mesh::{Mesh, MeshBuilder, Model};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::quality::SETTINGS_FILE;
+use crate::graphics::shader::ShaderProgram;
+use crate::resources::Resources;
+use cgmath::{Matrix4, SquareMatrix};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The color attachment of the cinematic target the
+/// colors of the scene are written to
+const SCENE_COLOR: usize = 0;
+/// The color attachment of the cinematic target the glow
+/// of emissive surfaces is written to, which is passed on
+/// to the bloom
+const SCENE_EMISSION: usize = 1;
+
+/// CinematicSettings
+///
+/// The settings of the cinematic post effects, declared
+/// in the `[cinematic]` table of the settings resource, e.g.
+///
+/// ```toml
+/// [cinematic]
+/// depth_of_field = true
+/// focus_distance = 0.0
+/// motion_blur = true
+/// always = false
+/// ```
+///
+/// The effects are only drawn while the camera path is
+/// played, unless they should always be drawn.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CinematicSettings {
+    /// Whether surfaces out of focus are blurred
+    pub depth_of_field: bool,
+    /// The distance in blocks which is in focus, or 0 to
+    /// focus whatever is in the center of the screen
+    pub focus_distance: f32,
+    /// The distance from the focus in blocks over which
+    /// the blur grows to its maximum
+    pub focus_range: f32,
+    /// The radius of the strongest blur in pixels
+    pub max_blur: f32,
+    /// Whether the screen is blurred along the motion of
+    /// the camera between two frames
+    pub motion_blur: bool,
+    /// The share of the motion between two frames which
+    /// is blurred
+    pub motion_blur_strength: f32,
+    /// Whether the effects are drawn outside of flythroughs
+    pub always: bool,
+}
+
+impl Default for CinematicSettings {
+    fn default() -> Self {
+        Self {
+            depth_of_field: true,
+            focus_distance: 0.0,
+            focus_range: 24.0,
+            max_blur: 6.0,
+            motion_blur: true,
+            motion_blur_strength: 0.5,
+            always: false,
+        }
+    }
+}
+
+/// The declaration of the settings resource
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct SettingsDef {
+    /// The settings of the cinematic post effects
+    cinematic: CinematicSettings,
+}
+
+impl CinematicSettings {
+    /// Loads the settings declared by the resources. If they
+    /// can't be loaded, the default settings are used.
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - A resource instance
+    pub fn load(resources: &Resources) -> Self {
+        let source = match resources.load_string(SETTINGS_FILE) {
+            Ok(source) => source,
+            Err(error) => {
+                log::warn!("Failed to load {}: {:?}", SETTINGS_FILE, error);
+                return Self::default();
+            }
+        };
+
+        Self::from_toml(&source).unwrap_or_else(|error| {
+            log::warn!("Failed to parse {}: {}", SETTINGS_FILE, error);
+            Self::default()
+        })
+    }
+
+    /// Parses the settings from the `TOML` declaration
+    /// of the settings resource
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The declaration of the settings
+    pub fn from_toml(source: &str) -> Result<Self, toml::de::Error> {
+        let def: SettingsDef = toml::from_str(source)?;
+        Ok(def.cinematic)
+    }
+
+    /// Returns whether any effect is drawn with a frame
+    ///
+    /// # Arguments
+    ///
+    /// * `cinematic` - Whether the frame is part of a flythrough
+    pub fn applies(&self, cinematic: bool) -> bool {
+        (self.depth_of_field || self.motion_blur) && (self.always || cinematic)
+    }
+}
+
+/// CinematicFrame
+///
+/// A frame the cinematic post effects could be rendered for
+pub trait CinematicFrame: Frame {
+    /// Returns whether the frame is part of a flythrough,
+    /// e.g. of the camera path
+    fn cinematic(&self) -> bool;
+}
+
+/// The offscreen target of the cinematic effects, which
+/// is shared by the capture and the cinematic pass
+struct CinematicTarget {
+    /// The target the scene is rendered to, with the
+    /// colors, the glow and the depth of the scene
+    scene: RenderTarget,
+    /// The size of the screen the target was created for
+    size: (i32, i32),
+}
+
+/// Creates the passes rendering the cinematic effects. The
+/// capture pass redirects the scene into an offscreen target
+/// after the bloom did, while the cinematic pass draws the
+/// blurred scene into the target of the bloom, or the screen
+/// without bloom. The capture needs to be added after the one
+/// of the bloom, the cinematic pass before the bloom pass.
+///
+/// # Arguments
+///
+/// * `backend` - The render backend
+/// * `resources` - A resource instance
+/// * `settings` - The settings of the effects
+/// * `bloom` - The target the bloom reads the scene from
+pub fn cinematic_passes(backend: &GlBackend, resources: &Resources, settings: CinematicSettings, bloom: BloomScene) -> (CinematicCapture, CinematicRenderer) {
+    let target = Rc::new(RefCell::new(None));
+    let capture = CinematicCapture {
+        backend: backend.clone(),
+        target: target.clone(),
+        settings,
+    };
+    (capture, CinematicRenderer::new(backend, resources, target, settings, bloom))
+}
+
+/// CinematicCapture
+///
+/// The pass redirecting the scene into the offscreen target
+/// of the cinematic effects. The target is only kept while
+/// the effects are drawn.
+pub struct CinematicCapture {
+    /// The render backend
+    backend: GlBackend,
+    /// The offscreen target, if the effects are drawn
+    target: Rc<RefCell<Option<CinematicTarget>>>,
+    /// The settings of the effects
+    settings: CinematicSettings,
+}
+
+impl<F: CinematicFrame> RenderPass<F> for CinematicCapture {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Sky
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let mut target = self.target.borrow_mut();
+        if !ctx.quality.cinematic || !self.settings.applies(ctx.frame.cinematic()) {
+            target.take();
+            return;
+        }
+
+        let size = ctx.renderer.viewport_size();
+        if !matches!(target.as_ref(), Some(target) if target.size == size) {
+            *target = Some(CinematicTarget {
+                scene: self.backend.create_render_target(size.0, size.1, 2, true),
+                size,
+            });
+        }
+
+        if let Some(target) = target.as_ref() {
+            target.scene.bind();
+            ctx.renderer.clear();
+        }
+    }
+}
+
+/// CinematicRenderer
+///
+/// The post processing pass drawing the scene with the
+/// cinematic effects. The depth of field blurs each pixel
+/// by how far it's away from the focus. The motion blur
+/// reprojects each pixel with the camera of the previous
+/// frame and blurs it along the way it moved on the screen.
+pub struct CinematicRenderer {
+    /// The shader program drawing the effects
+    shader_program: ShaderProgram,
+    /// A quad covering the whole screen
+    quad: Model,
+    /// The offscreen target, if the effects are drawn
+    target: Rc<RefCell<Option<CinematicTarget>>>,
+    /// The settings of the effects
+    settings: CinematicSettings,
+    /// The target the bloom reads the scene from
+    bloom: BloomScene,
+    /// The view projection matrix of the previous frame
+    /// with the effects
+    previous: Option<Matrix4<f32>>,
+}
+
+impl CinematicRenderer {
+    /// Creates a new cinematic renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    /// * `target` - The target shared with the capture pass
+    /// * `settings` - The settings of the effects
+    /// * `bloom` - The target the bloom reads the scene from
+    fn new(backend: &GlBackend, resources: &Resources, target: Rc<RefCell<Option<CinematicTarget>>>, settings: CinematicSettings, bloom: BloomScene) -> Self {
+        let shader_program = backend.create_pipeline(resources, "cinematic").unwrap();
+        shader_program.disable();
+
+        // `Mesh::quad` spans from -0.5 to 0.5, while normalized
+        // device coordinates span from -1.0 to 1.0
+        let mut quad = MeshBuilder::new();
+        quad.add(&Mesh::quad(), &Matrix4::from_scale(2.0));
+
+        Self {
+            shader_program,
+            quad: Model::from_mesh(backend, &quad.build()),
+            target,
+            settings,
+            bloom,
+            previous: None,
+        }
+    }
+}
+
+impl<F: CinematicFrame> RenderPass<F> for CinematicRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::PostProcess
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let target = self.target.borrow();
+        let target = match target.as_ref() {
+            Some(target) => target,
+            None => {
+                // The motion starts over with the next effects
+                self.previous = None;
+                return;
+            }
+        };
+
+        let camera = ctx.frame.camera();
+        let view_proj = camera.proj_matrix() * camera.view_matrix();
+        let previous = self.previous.replace(view_proj).unwrap_or(view_proj);
+
+        ctx.renderer.set_depth_test(false);
+        ctx.renderer.set_alpha_blending(false);
+        if !self.bloom.bind() {
+            target.scene.unbind();
+            ctx.renderer.set_viewport(target.size.0, target.size.1);
+        }
+
+        target.scene.bind_color(SCENE_COLOR, 0);
+        target.scene.bind_color(SCENE_EMISSION, 1);
+        target.scene.bind_depth(2);
+        let invert = |matrix: &Matrix4<f32>| matrix.invert().unwrap_or_else(Matrix4::identity);
+        let settings = &self.settings;
+        self.shader_program.enable();
+        self.shader_program.set_uniform_1i("u_Scene", 0);
+        self.shader_program.set_uniform_1i("u_Emission", 1);
+        self.shader_program.set_uniform_1i("u_Depth", 2);
+        self.shader_program.set_uniform_mat4f("u_InvProj", &invert(camera.proj_matrix()));
+        self.shader_program.set_uniform_mat4f("u_InvView", &invert(camera.view_matrix()));
+        self.shader_program.set_uniform_mat4f("u_PrevViewProj", &previous);
+        self.shader_program.set_uniform_1f("u_FocusDistance", settings.focus_distance);
+        self.shader_program.set_uniform_1f("u_FocusRange", settings.focus_range.max(1.0));
+        self.shader_program.set_uniform_1f("u_MaxBlur", if settings.depth_of_field { settings.max_blur } else { 0.0 });
+        self.shader_program.set_uniform_1f("u_MotionBlur", if settings.motion_blur { settings.motion_blur_strength } else { 0.0 });
+
+        self.quad.bind();
+        ctx.renderer.draw_with(self.quad.va(), self.quad.ib(), &self.shader_program, self.quad.topology());
+        self.quad.unbind();
+        self.shader_program.disable();
+
+        ctx.renderer.set_alpha_blending(true);
+        ctx.renderer.set_depth_test(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effects_are_only_drawn_in_flythroughs() {
+        let settings = CinematicSettings::default();
+        assert!(settings.applies(true));
+        assert!(!settings.applies(false));
+
+        let always = CinematicSettings { always: true, ..settings };
+        assert!(always.applies(false));
+
+        let disabled = CinematicSettings { depth_of_field: false, motion_blur: false, always: true, ..settings };
+        assert!(!disabled.applies(true));
+    }
+
+    #[test]
+    fn settings_default_without_cinematic_table() {
+        assert_eq!(CinematicSettings::from_toml("[graphics]\nmsaa = 0").unwrap(), CinematicSettings::default());
+        let settings = CinematicSettings::from_toml("[cinematic]\nmotion_blur = false\nfocus_distance = 12.0").unwrap();
+        assert!(!settings.motion_blur);
+        assert_eq!(settings.focus_distance, 12.0);
+        assert!(settings.depth_of_field);
+    }
+}
@@ -0,0 +1,96 @@
+//! Types to render the chunks with a single input of their
+//! shading in false color, e.g. while working on the lighting
+
+/// DebugView
+///
+/// What the chunks show instead of their shaded textures. Each
+/// view is a variant of the chunk shader compiled with its
+/// define, e.g. `DEBUG_BLOCK_LIGHT`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum DebugView {
+    /// The shaded textures, as they are played with
+    #[default]
+    Off,
+    /// The ambient occlusion of the corners of the faces
+    /// from black to white
+    Occlusion,
+    /// The light of glowing blocks from black to white
+    BlockLight,
+    /// The light of the sky from black to white
+    SkyLight,
+    /// The normals of the faces, mapped from `[-1, 1]` to `[0, 1]`
+    Normals,
+    /// The tiles of the texture atlas the faces show, each
+    /// one in its own color
+    Tiles,
+}
+
+/// The views with a shader variant, in the order they're cycled through
+pub const DEBUG_VIEWS: [DebugView; 5] = [
+    DebugView::Occlusion,
+    DebugView::BlockLight,
+    DebugView::SkyLight,
+    DebugView::Normals,
+    DebugView::Tiles,
+];
+
+impl DebugView {
+    /// Returns the view after this one, which is the
+    /// shaded textures again after the last one
+    pub fn next(self) -> Self {
+        match self {
+            DebugView::Off => DebugView::Occlusion,
+            DebugView::Occlusion => DebugView::BlockLight,
+            DebugView::BlockLight => DebugView::SkyLight,
+            DebugView::SkyLight => DebugView::Normals,
+            DebugView::Normals => DebugView::Tiles,
+            DebugView::Tiles => DebugView::Off,
+        }
+    }
+
+    /// Returns the define the chunk shader is compiled
+    /// with for this view, if it needs a variant
+    pub fn define(self) -> Option<&'static str> {
+        match self {
+            DebugView::Off => None,
+            DebugView::Occlusion => Some("DEBUG_OCCLUSION"),
+            DebugView::BlockLight => Some("DEBUG_BLOCK_LIGHT"),
+            DebugView::SkyLight => Some("DEBUG_SKY_LIGHT"),
+            DebugView::Normals => Some("DEBUG_NORMALS"),
+            DebugView::Tiles => Some("DEBUG_TILES"),
+        }
+    }
+
+    /// Returns the name of the view shown to the player
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugView::Off => "off",
+            DebugView::Occlusion => "ambient occlusion",
+            DebugView::BlockLight => "block light",
+            DebugView::SkyLight => "sky light",
+            DebugView::Normals => "normals",
+            DebugView::Tiles => "tiles",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn views_cycle_back_to_the_shaded_textures() {
+        let mut view = DebugView::default();
+        let mut seen = Vec::new();
+        loop {
+            view = view.next();
+            if view == DebugView::Off {
+                break;
+            }
+            seen.push(view);
+        }
+        assert_eq!(seen, DEBUG_VIEWS.to_vec());
+        assert!(DEBUG_VIEWS.iter().all(|view| view.define().is_some()));
+        assert_eq!(DebugView::Off.define(), None);
+    }
+}
@@ -0,0 +1,238 @@
+//! Types providing the surroundings which are mirrored
+//! by reflective blocks like ice and polished stone
+
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::texture::Cubemap;
+use crate::resources::Resources;
+use cgmath::{InnerSpace, Vector3, VectorSpace};
+
+/// The resource of a static environment map, whose six square
+/// faces are laid out side by side in the order +x, -x, +y,
+/// -y, +z, -z
+pub const ENVIRONMENT_FILE: &str = "textures/environment.png";
+/// The width and height of the faces of a map generated
+/// from the sky, which is blurry anyway
+const SKY_MAP_SIZE: u32 = 16;
+/// The change of a channel of the sky color beyond which
+/// the generated map is regenerated
+const SKY_COLOR_TOLERANCE: f32 = 1.0 / 128.0;
+
+/// EnvironmentMap
+///
+/// The cubemap reflective blocks sample in the direction
+/// the view is reflected to. It's loaded from the
+/// `ENVIRONMENT_FILE` resource if there is one. Otherwise
+/// it's generated from the color of the sky, fading from
+/// the zenith to a bright horizon and a dark ground, and
+/// regenerated as the sky changes with the time of day.
+pub struct EnvironmentMap {
+    /// The cubemap
+    cubemap: Cubemap,
+    /// The color of the sky the map has been generated
+    /// from, or `None` if it has been loaded
+    sky_color: Option<Vector3<f32>>,
+}
+
+impl EnvironmentMap {
+    /// Creates a new environment map, either from the
+    /// resources or from the default sky color
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        if resources.exists(ENVIRONMENT_FILE) {
+            match load_faces(resources) {
+                Ok((size, faces)) => match backend.create_cubemap(size, &faces) {
+                    Ok(cubemap) => return Self { cubemap, sky_color: None },
+                    Err(error) => log::warn!("Failed to upload {}: {}", ENVIRONMENT_FILE, error),
+                },
+                Err(error) => log::warn!("{}", error),
+            }
+        }
+
+        let sky_color = Vector3::new(0.23, 0.38, 0.47);
+        Self {
+            cubemap: backend.create_cubemap(SKY_MAP_SIZE, &sky_faces(SKY_MAP_SIZE, sky_color))
+                .expect("The faces have been generated with a matching size."),
+            sky_color: Some(sky_color),
+        }
+    }
+
+    /// Regenerates a map generated from the sky if the color
+    /// of the sky changed noticeably
+    ///
+    /// # Arguments
+    ///
+    /// * `sky_color` - The current color of the sky
+    pub fn update(&mut self, sky_color: Vector3<f32>) {
+        let previous = match self.sky_color {
+            Some(previous) => previous,
+            None => return,
+        };
+        let difference = sky_color - previous;
+        if difference.x.abs().max(difference.y.abs()).max(difference.z.abs()) < SKY_COLOR_TOLERANCE {
+            return;
+        }
+
+        match self.cubemap.upload(&sky_faces(SKY_MAP_SIZE, sky_color)) {
+            Ok(()) => self.sky_color = Some(sky_color),
+            Err(error) => log::error!("Failed to regenerate the environment map: {}", error),
+        }
+    }
+
+    /// Binds the cubemap in the current `OpenGL` context
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The slot the cubemap should be bound to
+    pub fn bind(&self, slot: u32) {
+        self.cubemap.bind(slot);
+    }
+}
+
+/// Loads the faces of the static environment map and
+/// returns their size
+///
+/// # Arguments
+///
+/// * `resources` - A resource instance
+fn load_faces(resources: &Resources) -> Result<(u32, [Vec<u8>; 6]), String> {
+    let image = resources.load_image(ENVIRONMENT_FILE)
+        .map_err(|error| format!("Failed to load {}: {:?}", ENVIRONMENT_FILE, error))?
+        .into_rgba8();
+    let (width, height) = image.dimensions();
+    split_faces(width, height, &image.into_raw())
+        .map(|faces| (height, faces))
+        .map_err(|error| format!("Failed to load {}: {}", ENVIRONMENT_FILE, error))
+}
+
+/// Splits a strip of six square faces laid out side by side
+/// into the pixels of each face
+///
+/// # Arguments
+///
+/// * `width` - The width of the strip
+/// * `height` - The height of the strip, which is the size of each face
+/// * `pixels` - The `RGBA` pixels of the strip, row by row
+fn split_faces(width: u32, height: u32, pixels: &[u8]) -> Result<[Vec<u8>; 6], String> {
+    if height == 0 || width != height * 6 {
+        return Err(format!("Expected six square faces side by side, but the image is {}x{}", width, height));
+    }
+
+    let row = height as usize * 4;
+    let mut faces: [Vec<u8>; 6] = Default::default();
+    for (index, face) in faces.iter_mut().enumerate() {
+        for y in 0..height as usize {
+            let start = y * width as usize * 4 + index * row;
+            face.extend_from_slice(&pixels[start..start + row]);
+        }
+    }
+    Ok(faces)
+}
+
+/// Generates the `RGBA` pixels of the faces of a cubemap
+/// showing a sky of the given color
+///
+/// # Arguments
+///
+/// * `size` - The width and height of each face
+/// * `sky_color` - The color of the sky
+fn sky_faces(size: u32, sky_color: Vector3<f32>) -> [Vec<u8>; 6] {
+    let mut faces: [Vec<u8>; 6] = Default::default();
+    for (index, face) in faces.iter_mut().enumerate() {
+        face.reserve((size * size * 4) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let s = (x as f32 + 0.5) / size as f32;
+                let t = (y as f32 + 0.5) / size as f32;
+                let color = sky_gradient(face_direction(index, s, t), sky_color);
+                face.extend_from_slice(&[
+                    (color.x.clamp(0.0, 1.0) * 255.0) as u8,
+                    (color.y.clamp(0.0, 1.0) * 255.0) as u8,
+                    (color.z.clamp(0.0, 1.0) * 255.0) as u8,
+                    255,
+                ]);
+            }
+        }
+    }
+    faces
+}
+
+/// Returns the direction a texel of a cubemap face is
+/// sampled with, following the face layout of `OpenGL`
+///
+/// # Arguments
+///
+/// * `face` - The index of the face in the order +x, -x, +y, -y, +z, -z
+/// * `s` - The horizontal coordinate within the face in the range `[0, 1]`
+/// * `t` - The vertical coordinate within the face in the range `[0, 1]`
+fn face_direction(face: usize, s: f32, t: f32) -> Vector3<f32> {
+    let sc = s * 2.0 - 1.0;
+    let tc = t * 2.0 - 1.0;
+    let direction = match face {
+        0 => Vector3::new(1.0, -tc, -sc),
+        1 => Vector3::new(-1.0, -tc, sc),
+        2 => Vector3::new(sc, 1.0, tc),
+        3 => Vector3::new(sc, -1.0, -tc),
+        4 => Vector3::new(sc, -tc, 1.0),
+        _ => Vector3::new(-sc, -tc, -1.0),
+    };
+    direction.normalize()
+}
+
+/// Returns the color of the sky in the given direction
+///
+/// # Arguments
+///
+/// * `direction` - The normalized direction
+/// * `sky_color` - The color of the sky
+fn sky_gradient(direction: Vector3<f32>, sky_color: Vector3<f32>) -> Vector3<f32> {
+    let horizon = sky_color.lerp(Vector3::new(1.0, 1.0, 1.0), 0.25);
+    if direction.y >= 0.0 {
+        horizon.lerp(sky_color * 0.85, direction.y.sqrt())
+    } else {
+        horizon.lerp(sky_color * 0.3, (-direction.y * 4.0).min(1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faces_point_along_their_axes() {
+        let axes = [
+            Vector3::unit_x(), -Vector3::unit_x(),
+            Vector3::unit_y(), -Vector3::unit_y(),
+            Vector3::unit_z(), -Vector3::unit_z(),
+        ];
+        for (face, axis) in axes.iter().enumerate() {
+            assert!((face_direction(face, 0.5, 0.5) - axis).magnitude() < 1e-6);
+        }
+        // The top face's first row looks towards -z
+        assert!(face_direction(2, 0.5, 0.0).z < 0.0);
+    }
+
+    #[test]
+    fn sky_is_darker_below_the_horizon() {
+        let sky_color = Vector3::new(0.4, 0.6, 0.8);
+        let faces = sky_faces(5, sky_color);
+        assert!(faces.iter().all(|face| face.len() == 5 * 5 * 4));
+
+        // The blue channel of the texel in the center of a face
+        let center = |face: &Vec<u8>| face[(2 * 5 + 2) * 4 + 2];
+        let (up, down, side) = (center(&faces[2]), center(&faces[3]), center(&faces[0]));
+        assert!(down < up && up < side);
+    }
+
+    #[test]
+    fn strips_are_split_into_faces() {
+        let pixels: Vec<u8> = (0..6 * 2 * 2).flat_map(|index| vec![index as u8; 4]).collect();
+        let faces = split_faces(12, 2, &pixels).unwrap();
+        assert_eq!(faces[0], [0, 0, 0, 0, 1, 1, 1, 1, 12, 12, 12, 12, 13, 13, 13, 13]);
+        assert_eq!(faces[5][..4], [10, 10, 10, 10]);
+        assert!(split_faces(12, 3, &pixels).is_err());
+    }
+}
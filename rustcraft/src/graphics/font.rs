@@ -0,0 +1,270 @@
+//! A bitmap font to draw text in the 2D user interface
+//!
+//! The font covers the printable `ASCII` characters with
+//! glyphs of 8x8 pixels. All glyphs are packed into a single
+//! atlas texture, which also contains a solid glyph to draw
+//! filled rectangles with the same texture.
+
+/// The width and height of a glyph in pixels
+pub const GLYPH_SIZE: u32 = 8;
+/// The amount of glyphs in a row of the atlas
+const ATLAS_COLUMNS: u32 = 16;
+/// The amount of rows of the atlas
+const ATLAS_ROWS: u32 = GLYPHS.len() as u32 / ATLAS_COLUMNS;
+/// The first character contained in the font
+const FIRST_CHAR: u32 = 0x20;
+/// The index of the glyph replacing unknown characters
+const FALLBACK_GLYPH: u32 = '?' as u32 - FIRST_CHAR;
+/// The index of the solid glyph
+const SOLID_GLYPH: u32 = 0x7F - FIRST_CHAR;
+
+/// The glyphs of the characters from `0x20` to `0x7E` followed
+/// by the solid glyph. Each byte is a row from top to bottom,
+/// the lowest bit is the leftmost pixel.
+const GLYPHS: [[u8; 8]; 96] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x18, 0x3C, 0x3C, 0x18, 0x18, 0x00, 0x18, 0x00], // '!'
+    [0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x36, 0x36, 0x7F, 0x36, 0x7F, 0x36, 0x36, 0x00], // '#'
+    [0x0C, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x0C, 0x00], // '$'
+    [0x00, 0x63, 0x33, 0x18, 0x0C, 0x66, 0x63, 0x00], // '%'
+    [0x1C, 0x36, 0x1C, 0x6E, 0x3B, 0x33, 0x6E, 0x00], // '&'
+    [0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00], // "'"
+    [0x18, 0x0C, 0x06, 0x06, 0x06, 0x0C, 0x18, 0x00], // '('
+    [0x06, 0x0C, 0x18, 0x18, 0x18, 0x0C, 0x06, 0x00], // ')'
+    [0x00, 0x66, 0x3C, 0xFF, 0x3C, 0x66, 0x00, 0x00], // '*'
+    [0x00, 0x0C, 0x0C, 0x3F, 0x0C, 0x0C, 0x00, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ','
+    [0x00, 0x00, 0x00, 0x3F, 0x00, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x00], // '.'
+    [0x60, 0x30, 0x18, 0x0C, 0x06, 0x03, 0x01, 0x00], // '/'
+    [0x3E, 0x63, 0x73, 0x7B, 0x6F, 0x67, 0x3E, 0x00], // '0'
+    [0x0C, 0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x3F, 0x00], // '1'
+    [0x1E, 0x33, 0x30, 0x1C, 0x06, 0x33, 0x3F, 0x00], // '2'
+    [0x1E, 0x33, 0x30, 0x1C, 0x30, 0x33, 0x1E, 0x00], // '3'
+    [0x38, 0x3C, 0x36, 0x33, 0x7F, 0x30, 0x78, 0x00], // '4'
+    [0x3F, 0x03, 0x1F, 0x30, 0x30, 0x33, 0x1E, 0x00], // '5'
+    [0x1C, 0x06, 0x03, 0x1F, 0x33, 0x33, 0x1E, 0x00], // '6'
+    [0x3F, 0x33, 0x30, 0x18, 0x0C, 0x0C, 0x0C, 0x00], // '7'
+    [0x1E, 0x33, 0x33, 0x1E, 0x33, 0x33, 0x1E, 0x00], // '8'
+    [0x1E, 0x33, 0x33, 0x3E, 0x30, 0x18, 0x0E, 0x00], // '9'
+    [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x00], // ':'
+    [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ';'
+    [0x18, 0x0C, 0x06, 0x03, 0x06, 0x0C, 0x18, 0x00], // '<'
+    [0x00, 0x00, 0x3F, 0x00, 0x00, 0x3F, 0x00, 0x00], // '='
+    [0x06, 0x0C, 0x18, 0x30, 0x18, 0x0C, 0x06, 0x00], // '>'
+    [0x1E, 0x33, 0x30, 0x18, 0x0C, 0x00, 0x0C, 0x00], // '?'
+    [0x3E, 0x63, 0x7B, 0x7B, 0x7B, 0x03, 0x1E, 0x00], // '@'
+    [0x0C, 0x1E, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x00], // 'A'
+    [0x3F, 0x66, 0x66, 0x3E, 0x66, 0x66, 0x3F, 0x00], // 'B'
+    [0x3C, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3C, 0x00], // 'C'
+    [0x1F, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1F, 0x00], // 'D'
+    [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x46, 0x7F, 0x00], // 'E'
+    [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x06, 0x0F, 0x00], // 'F'
+    [0x3C, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7C, 0x00], // 'G'
+    [0x33, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x33, 0x00], // 'H'
+    [0x1E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'I'
+    [0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E, 0x00], // 'J'
+    [0x67, 0x66, 0x36, 0x1E, 0x36, 0x66, 0x67, 0x00], // 'K'
+    [0x0F, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7F, 0x00], // 'L'
+    [0x63, 0x77, 0x7F, 0x7F, 0x6B, 0x63, 0x63, 0x00], // 'M'
+    [0x63, 0x67, 0x6F, 0x7B, 0x73, 0x63, 0x63, 0x00], // 'N'
+    [0x1C, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1C, 0x00], // 'O'
+    [0x3F, 0x66, 0x66, 0x3E, 0x06, 0x06, 0x0F, 0x00], // 'P'
+    [0x1E, 0x33, 0x33, 0x33, 0x3B, 0x1E, 0x38, 0x00], // 'Q'
+    [0x3F, 0x66, 0x66, 0x3E, 0x36, 0x66, 0x67, 0x00], // 'R'
+    [0x1E, 0x33, 0x07, 0x0E, 0x38, 0x33, 0x1E, 0x00], // 'S'
+    [0x3F, 0x2D, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'T'
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3F, 0x00], // 'U'
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // 'V'
+    [0x63, 0x63, 0x63, 0x6B, 0x7F, 0x77, 0x63, 0x00], // 'W'
+    [0x63, 0x63, 0x36, 0x1C, 0x1C, 0x36, 0x63, 0x00], // 'X'
+    [0x33, 0x33, 0x33, 0x1E, 0x0C, 0x0C, 0x1E, 0x00], // 'Y'
+    [0x7F, 0x63, 0x31, 0x18, 0x4C, 0x66, 0x7F, 0x00], // 'Z'
+    [0x1E, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1E, 0x00], // '['
+    [0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x40, 0x00], // '\'
+    [0x1E, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1E, 0x00], // ']'
+    [0x08, 0x1C, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF], // '_'
+    [0x0C, 0x0C, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x1E, 0x30, 0x3E, 0x33, 0x6E, 0x00], // 'a'
+    [0x07, 0x06, 0x06, 0x3E, 0x66, 0x66, 0x3B, 0x00], // 'b'
+    [0x00, 0x00, 0x1E, 0x33, 0x03, 0x33, 0x1E, 0x00], // 'c'
+    [0x38, 0x30, 0x30, 0x3E, 0x33, 0x33, 0x6E, 0x00], // 'd'
+    [0x00, 0x00, 0x1E, 0x33, 0x3F, 0x03, 0x1E, 0x00], // 'e'
+    [0x1C, 0x36, 0x06, 0x0F, 0x06, 0x06, 0x0F, 0x00], // 'f'
+    [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x1F], // 'g'
+    [0x07, 0x06, 0x36, 0x6E, 0x66, 0x66, 0x67, 0x00], // 'h'
+    [0x0C, 0x00, 0x0E, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'i'
+    [0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E], // 'j'
+    [0x07, 0x06, 0x66, 0x36, 0x1E, 0x36, 0x67, 0x00], // 'k'
+    [0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'l'
+    [0x00, 0x00, 0x33, 0x7F, 0x7F, 0x6B, 0x63, 0x00], // 'm'
+    [0x00, 0x00, 0x1F, 0x33, 0x33, 0x33, 0x33, 0x00], // 'n'
+    [0x00, 0x00, 0x1E, 0x33, 0x33, 0x33, 0x1E, 0x00], // 'o'
+    [0x00, 0x00, 0x3B, 0x66, 0x66, 0x3E, 0x06, 0x0F], // 'p'
+    [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x78], // 'q'
+    [0x00, 0x00, 0x3B, 0x6E, 0x66, 0x06, 0x0F, 0x00], // 'r'
+    [0x00, 0x00, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x00], // 's'
+    [0x08, 0x0C, 0x3E, 0x0C, 0x0C, 0x2C, 0x18, 0x00], // 't'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6E, 0x00], // 'u'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // 'v'
+    [0x00, 0x00, 0x63, 0x6B, 0x7F, 0x7F, 0x36, 0x00], // 'w'
+    [0x00, 0x00, 0x63, 0x36, 0x1C, 0x36, 0x63, 0x00], // 'x'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x3E, 0x30, 0x1F], // 'y'
+    [0x00, 0x00, 0x3F, 0x19, 0x0C, 0x26, 0x3F, 0x00], // 'z'
+    [0x38, 0x0C, 0x0C, 0x07, 0x0C, 0x0C, 0x38, 0x00], // '{'
+    [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00], // '|'
+    [0x07, 0x0C, 0x0C, 0x38, 0x0C, 0x0C, 0x07, 0x00], // '}'
+    [0x6E, 0x3B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '~'
+    [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF], // solid
+];
+
+/// Returns the width and height of the atlas in pixels
+pub fn atlas_size() -> (u32, u32) {
+    (ATLAS_COLUMNS * GLYPH_SIZE, ATLAS_ROWS * GLYPH_SIZE)
+}
+
+/// Returns the `RGBA` pixels of the atlas, row by row starting
+/// at the bottom. Set pixels are opaque white, so the glyphs
+/// could be tinted by multiplying them with a color.
+pub fn atlas_pixels() -> Vec<u8> {
+    let (width, height) = atlas_size();
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+
+    for (index, glyph) in GLYPHS.iter().enumerate() {
+        let (cell_x, cell_y) = glyph_cell(index as u32);
+        for (row, bits) in glyph.iter().enumerate() {
+            // The rows of a glyph are stored top to bottom
+            let y = height - 1 - (cell_y * GLYPH_SIZE + row as u32);
+            for column in 0..GLYPH_SIZE {
+                if bits & (1 << column) != 0 {
+                    let x = cell_x * GLYPH_SIZE + column;
+                    let offset = ((y * width + x) * 4) as usize;
+                    pixels[offset..offset + 4].copy_from_slice(&[255, 255, 255, 255]);
+                }
+            }
+        }
+    }
+
+    pixels
+}
+
+/// Returns the minimal and maximal texture coordinates of
+/// the glyph of a character. Unknown characters are drawn
+/// as a question mark.
+///
+/// # Arguments
+///
+/// * `c` - The character
+pub fn glyph_tex_coords(c: char) -> ([f32; 2], [f32; 2]) {
+    tex_coords(glyph_index(c))
+}
+
+/// Returns a texture coordinate within the solid glyph
+pub fn solid_tex_coord() -> [f32; 2] {
+    let (min, max) = tex_coords(SOLID_GLYPH);
+    [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0]
+}
+
+/// Returns the width of a text in pixels
+///
+/// # Arguments
+///
+/// * `text` - The text
+pub fn text_width(text: &str) -> u32 {
+    text.chars().count() as u32 * GLYPH_SIZE
+}
+
+/// Returns the index of the glyph of a character
+fn glyph_index(c: char) -> u32 {
+    match c as u32 {
+        code @ 0x20..=0x7E => code - FIRST_CHAR,
+        _ => FALLBACK_GLYPH,
+    }
+}
+
+/// Returns the column and the row of a glyph in the atlas,
+/// the first row being the top one
+fn glyph_cell(index: u32) -> (u32, u32) {
+    (index % ATLAS_COLUMNS, index / ATLAS_COLUMNS)
+}
+
+/// Returns the minimal and maximal texture coordinates of a glyph
+fn tex_coords(index: u32) -> ([f32; 2], [f32; 2]) {
+    let (width, height) = atlas_size();
+    let (cell_x, cell_y) = glyph_cell(index);
+
+    let min_x = (cell_x * GLYPH_SIZE) as f32 / width as f32;
+    let max_x = ((cell_x + 1) * GLYPH_SIZE) as f32 / width as f32;
+    // The texture coordinates start at the bottom of the atlas
+    let max_y = 1.0 - (cell_y * GLYPH_SIZE) as f32 / height as f32;
+    let min_y = 1.0 - ((cell_y + 1) * GLYPH_SIZE) as f32 / height as f32;
+
+    ([min_x, min_y], [max_x, max_y])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the alpha of a pixel of the atlas at the
+    /// given texture coordinate
+    fn alpha_at(pixels: &[u8], uv: [f32; 2]) -> u8 {
+        let (width, height) = atlas_size();
+        let x = (uv[0] * width as f32) as u32;
+        let y = (uv[1] * height as f32) as u32;
+        pixels[((y * width + x) * 4 + 3) as usize]
+    }
+
+    #[test]
+    fn atlas_contains_all_glyphs() {
+        let (width, height) = atlas_size();
+        assert_eq!((width, height), (128, 48));
+        assert_eq!(atlas_pixels().len(), (width * height * 4) as usize);
+    }
+
+    #[test]
+    fn glyph_tex_coords_cover_one_cell() {
+        for c in (0x20u8..0x7F).map(char::from) {
+            let (min, max) = glyph_tex_coords(c);
+            assert!(min[0] >= 0.0 && min[1] >= 0.0 && max[0] <= 1.0 && max[1] <= 1.0);
+            assert!((max[0] - min[0] - 1.0 / 16.0).abs() < 1e-6);
+            assert!((max[1] - min[1] - 1.0 / 6.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn unknown_characters_fall_back() {
+        assert_eq!(glyph_tex_coords('\u{e9}'), glyph_tex_coords('?'));
+        assert_eq!(glyph_tex_coords('\n'), glyph_tex_coords('?'));
+        assert_ne!(glyph_tex_coords('A'), glyph_tex_coords('?'));
+    }
+
+    #[test]
+    fn solid_glyph_is_opaque() {
+        let pixels = atlas_pixels();
+        assert_eq!(alpha_at(&pixels, solid_tex_coord()), 255);
+    }
+
+    #[test]
+    fn glyph_rows_start_at_the_top() {
+        let pixels = atlas_pixels();
+
+        // The underscore only has its bottom row set
+        let (min, max) = glyph_tex_coords('_');
+        let texel = 1.0 / 48.0;
+        assert_eq!(alpha_at(&pixels, [min[0] + 0.001, min[1] + texel / 2.0]), 255);
+        assert_eq!(alpha_at(&pixels, [min[0] + 0.001, max[1] - texel / 2.0]), 0);
+
+        // The space is empty
+        let (min, max) = glyph_tex_coords(' ');
+        assert_eq!(alpha_at(&pixels, [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0]), 0);
+    }
+
+    #[test]
+    fn text_width_counts_characters() {
+        assert_eq!(text_width(""), 0);
+        assert_eq!(text_width("Saved"), 5 * GLYPH_SIZE);
+    }
+}
@@ -0,0 +1,134 @@
+//! Types to capture the area around the player from a
+//! fixed dimetric angle into a large image, e.g. to show
+//! off a build
+
+use crate::camera::OrthographicCamera;
+use crate::graphics::capabilities::GpuCapabilities;
+use crate::world::chunk::CHUNK_HEIGHT;
+use cgmath::Vector3;
+use std::f32::consts::SQRT_2;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The directory next to the executable the captures
+/// are written into
+pub const CAPTURE_PATH: &str = "screenshots";
+/// The prefix of the names of the captures
+const CAPTURE_PREFIX: &str = "isometric-";
+/// The width and height of a capture in pixels, unless
+/// the driver doesn't support textures as large
+const CAPTURE_SIZE: u32 = 4096;
+/// The angle the view looks down at in degrees, which
+/// halves the depth of the ground, so blocks have the
+/// 2:1 look of pixel art
+const DIMETRIC_PITCH: f32 = -26.565;
+/// The angle the view is turned around the vertical axis
+/// in degrees, so two sides of each block are visible
+const DIMETRIC_YAW: f32 = 45.0;
+
+/// Returns the width and height of a capture, which is
+/// limited by the largest texture the driver supports
+///
+/// # Arguments
+///
+/// * `capabilities` - What the driver supports
+pub fn capture_size(capabilities: &GpuCapabilities) -> u32 {
+    CAPTURE_SIZE.min(capabilities.max_texture_size).max(1)
+}
+
+/// Returns the camera looking at the area around the given
+/// center from the dimetric angle. The camera is placed at
+/// the center, so the chunks around it are drawn, and its
+/// near plane lies behind it.
+///
+/// # Arguments
+///
+/// * `center` - The center of the captured area, e.g. the player
+/// * `radius` - The distance from the center to the edges of the area in blocks
+pub fn dimetric_camera(center: Vector3<f32>, radius: f32) -> OrthographicCamera {
+    // The diagonal of the area spans the width of the view
+    let size = radius * 2.0 * SQRT_2;
+    let depth = size + CHUNK_HEIGHT as f32;
+    let mut camera = OrthographicCamera::at_pos(center, size, size);
+    camera.set_near_plane(-depth);
+    camera.set_far_plane(depth);
+    camera.rotate(DIMETRIC_YAW, DIMETRIC_PITCH, 0.0);
+    camera
+}
+
+/// Writes the pixels of a capture as a `PNG` image into a
+/// directory and returns its path
+///
+/// # Arguments
+///
+/// * `dir` - The directory
+/// * `time` - The time of the capture in seconds since the Unix epoch
+/// * `size` - The width and height of the capture
+/// * `pixels` - The `RGBA` pixels, row by row starting at the bottom
+pub fn write_capture(dir: &Path, time: u64, size: u32, pixels: &[u8]) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|error| format!("Failed to create {}: {}", dir.display(), error))?;
+    let mut path = dir.join(format!("{}{}.png", CAPTURE_PREFIX, time));
+    let mut suffix = 1;
+    while path.exists() {
+        suffix += 1;
+        path = dir.join(format!("{}{}-{}.png", CAPTURE_PREFIX, time, suffix));
+    }
+
+    let pixels = flip_rows(pixels, size);
+    image::save_buffer(&path, &pixels, size, size, image::ColorType::Rgba8)
+        .map_err(|error| format!("Failed to write {}: {}", path.display(), error))?;
+    Ok(path)
+}
+
+/// Returns the rows of `RGBA` pixels in reverse order, as
+/// `OpenGL` starts at the bottom while images start at the top
+///
+/// # Arguments
+///
+/// * `pixels` - The pixels, row by row
+/// * `width` - The width of a row
+fn flip_rows(pixels: &[u8], width: u32) -> Vec<u8> {
+    pixels.chunks(width as usize * 4).rev().flatten().copied().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Projection;
+
+    #[test]
+    fn dimetric_camera_covers_the_area() {
+        let center = Vector3::new(100.0, 64.0, -50.0);
+        let camera = dimetric_camera(center, 32.0);
+        assert_eq!(camera.pos(), &center);
+
+        let view_proj = camera.proj_matrix() * camera.view_matrix();
+        for (x, z) in &[(-32.0, -32.0), (32.0, -32.0), (-32.0, 32.0), (32.0, 32.0)] {
+            let clip = view_proj * (center + Vector3::new(*x, 0.0, *z)).extend(1.0);
+            assert!(clip.x.abs() <= 1.0 + 1e-4 && clip.y.abs() <= 1.0 && clip.z.abs() <= 1.0);
+        }
+
+        // Two opposite corners of the area line up with the
+        // center, as the view looks along their diagonal
+        let corner = view_proj * (center + Vector3::new(32.0, 0.0, 32.0)).extend(1.0);
+        assert!(corner.x.abs() < 1e-4);
+    }
+
+    #[test]
+    fn captures_are_written_top_down() {
+        assert_eq!(flip_rows(&[1, 1, 1, 1, 2, 2, 2, 2], 1), vec![2, 2, 2, 2, 1, 1, 1, 1]);
+
+        let dir = std::env::temp_dir().join(format!("rustcraft-isometric-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let pixels = [255, 0, 0, 255, 255, 0, 0, 255, 0, 0, 255, 255, 0, 0, 255, 255];
+        let first = write_capture(&dir, 100, 2, &pixels).unwrap();
+        let second = write_capture(&dir, 100, 2, &pixels).unwrap();
+        assert_eq!(first, dir.join("isometric-100.png"));
+        assert_eq!(second, dir.join("isometric-100-2.png"));
+
+        let image = image::open(&first).unwrap().into_rgba8();
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(0, 1).0, [255, 0, 0, 255]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
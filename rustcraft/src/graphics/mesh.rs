@@ -1,9 +1,13 @@
 //! Types to represent meshes and models
 
-use crate::graphics::buffer::{VertexArray, VertexBuffer, VertexBufferLayout, IndexBuffer};
-use crate::graphics::gl::Gl;
-use crate::graphics::bindings::types::GLvoid;
-use std::mem::size_of;
+use crate::graphics::attribute;
+use crate::graphics::backend::{GlBackend, PrimitiveTopology, RenderBackend};
+use crate::graphics::buffer::{VertexArray, VertexBuffer, IndexBuffer};
+use crate::impl_vertex;
+use cgmath::{InnerSpace, Matrix, Matrix4, SquareMatrix, Vector2, Vector3, Vector4, Zero};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Mesh
 ///
@@ -14,6 +18,7 @@ pub struct Mesh {
     pub tex_coords: Vec<f32>,
     pub indices: Vec<u32>,
     pub normals: Vec<f32>,
+    pub topology: PrimitiveTopology,
 }
 
 impl Default for Mesh {
@@ -23,24 +28,375 @@ impl Default for Mesh {
             tex_coords: Vec::new(),
             indices: Vec::new(),
             normals: Vec::new(),
+            topology: PrimitiveTopology::Triangles,
         }
     }
 }
 
+/// MeshVertex
+///
+/// A single vertex of a mesh as it is uploaded to
+/// the vertex buffer of a model
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MeshVertex {
+    /// The position of the vertex
+    pub position: [f32; 3],
+    /// The texture coordinate of the vertex
+    pub tex_coord: [f32; 2],
+    /// The normal of the vertex
+    pub normal: [f32; 3],
+}
+
+impl_vertex!(MeshVertex {
+    position => attribute::POSITION,
+    tex_coord => attribute::TEX_COORD,
+    normal => attribute::NORMAL,
+});
+
+impl Mesh {
+    /// Returns the amount of vertices of the mesh
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_positions.len() / 3
+    }
+
+    /// Returns the interleaved vertices of the mesh. Missing
+    /// texture coordinates and normals are filled with zeros.
+    pub fn vertices(&self) -> Vec<MeshVertex> {
+        let component = |values: &[f32], index: usize| values.get(index).cloned().unwrap_or(0.0);
+
+        (0..self.vertex_count())
+            .map(|i| MeshVertex {
+                position: [
+                    self.vertex_positions[i * 3],
+                    self.vertex_positions[i * 3 + 1],
+                    self.vertex_positions[i * 3 + 2],
+                ],
+                tex_coord: [
+                    component(&self.tex_coords, i * 2),
+                    component(&self.tex_coords, i * 2 + 1),
+                ],
+                normal: [
+                    component(&self.normals, i * 3),
+                    component(&self.normals, i * 3 + 1),
+                    component(&self.normals, i * 3 + 2),
+                ],
+            })
+            .collect()
+    }
+
+    /// Creates a unit quad in the xy plane which is centered
+    /// around the origin and faces towards the positive z axis
+    pub fn quad() -> Self {
+        let mut builder = MeshBuilder::new();
+        builder.quad(
+            [
+                Vector3::new(-0.5, -0.5, 0.0),
+                Vector3::new(0.5, -0.5, 0.0),
+                Vector3::new(0.5, 0.5, 0.0),
+                Vector3::new(-0.5, 0.5, 0.0),
+            ],
+            Vector3::unit_z(),
+        );
+        builder.build()
+    }
+
+    /// Creates a unit cube spanning from `(0, 0, 0)` to `(1, 1, 1)`.
+    /// Each face has its own vertices, so that normals and
+    /// texture coordinates are not shared between faces.
+    pub fn cube() -> Self {
+        let mut builder = MeshBuilder::new();
+        let v = |x: f32, y: f32, z: f32| Vector3::new(x, y, z);
+
+        // Front, back, left, right, top, bottom
+        builder.quad([v(0.0, 0.0, 1.0), v(1.0, 0.0, 1.0), v(1.0, 1.0, 1.0), v(0.0, 1.0, 1.0)], Vector3::unit_z());
+        builder.quad([v(1.0, 0.0, 0.0), v(0.0, 0.0, 0.0), v(0.0, 1.0, 0.0), v(1.0, 1.0, 0.0)], -Vector3::unit_z());
+        builder.quad([v(0.0, 0.0, 0.0), v(0.0, 0.0, 1.0), v(0.0, 1.0, 1.0), v(0.0, 1.0, 0.0)], -Vector3::unit_x());
+        builder.quad([v(1.0, 0.0, 1.0), v(1.0, 0.0, 0.0), v(1.0, 1.0, 0.0), v(1.0, 1.0, 1.0)], Vector3::unit_x());
+        builder.quad([v(0.0, 1.0, 1.0), v(1.0, 1.0, 1.0), v(1.0, 1.0, 0.0), v(0.0, 1.0, 0.0)], Vector3::unit_y());
+        builder.quad([v(0.0, 0.0, 0.0), v(1.0, 0.0, 0.0), v(1.0, 0.0, 1.0), v(0.0, 0.0, 1.0)], -Vector3::unit_y());
+
+        builder.build()
+    }
+
+    /// Creates a uv sphere with a radius of one which is
+    /// centered around the origin
+    ///
+    /// # Arguments
+    ///
+    /// * `rings` - The amount of horizontal rings, at least 2
+    /// * `segments` - The amount of vertical segments, at least 3
+    pub fn sphere(rings: u32, segments: u32) -> Self {
+        let rings = rings.max(2);
+        let segments = segments.max(3);
+        let mut builder = MeshBuilder::new();
+
+        for ring in 0..=rings {
+            let v = ring as f32 / rings as f32;
+            let theta = v * std::f32::consts::PI;
+
+            for segment in 0..=segments {
+                let u = segment as f32 / segments as f32;
+                let phi = u * 2.0 * std::f32::consts::PI;
+
+                let normal = Vector3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                builder.vertex(normal, Vector2::new(u, 1.0 - v), normal);
+            }
+        }
+
+        let stride = segments + 1;
+        for ring in 0..rings {
+            for segment in 0..segments {
+                let top_left = ring * stride + segment;
+                let bottom_left = top_left + stride;
+                builder.triangle(top_left, top_left + 1, bottom_left);
+                builder.triangle(top_left + 1, bottom_left + 1, bottom_left);
+            }
+        }
+
+        builder.build()
+    }
+
+    /// Creates a line list from the given pairs of points.
+    /// The resulting mesh needs to be drawn with
+    /// `PrimitiveTopology::Lines`.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - The start and end points of each line
+    pub fn lines(lines: &[(Vector3<f32>, Vector3<f32>)]) -> Self {
+        let mut builder = MeshBuilder::with_topology(PrimitiveTopology::Lines);
+
+        for (start, end) in lines {
+            let start = builder.vertex(*start, Vector2::new(0.0, 0.0), Vector3::zero());
+            let end = builder.vertex(*end, Vector2::new(1.0, 0.0), Vector3::zero());
+            builder.index(start);
+            builder.index(end);
+        }
+
+        builder.build()
+    }
+
+    /// Creates a quad at the given position which faces
+    /// the camera spanned by the given right and up vectors
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - The center of the billboard
+    /// * `size` - The width and height of the billboard
+    /// * `camera_right` - The right vector of the camera
+    /// * `camera_up` - The up vector of the camera
+    pub fn billboard(center: Vector3<f32>, size: Vector2<f32>, camera_right: Vector3<f32>, camera_up: Vector3<f32>) -> Self {
+        let right = camera_right.normalize() * size.x / 2.0;
+        let up = camera_up.normalize() * size.y / 2.0;
+        let normal = camera_right.cross(camera_up).normalize();
+
+        let mut builder = MeshBuilder::new();
+        builder.quad(
+            [
+                center - right - up,
+                center + right - up,
+                center + right + up,
+                center - right + up,
+            ],
+            normal,
+        );
+        builder.build()
+    }
+}
+
+/// The id of the next mesh handle
+static NEXT_MESH_ID: AtomicU64 = AtomicU64::new(0);
+
+/// MeshHandle
+///
+/// A shared, immutable mesh with an id. The id is unique
+/// for the lifetime of the process, so it could be used
+/// as a stable key for the models uploaded from the mesh.
+/// Clones of a handle share the mesh and the id.
+#[derive(Clone)]
+pub struct MeshHandle {
+    /// The unique id of the mesh
+    id: u64,
+    /// The shared mesh
+    mesh: Arc<Mesh>,
+}
+
+impl MeshHandle {
+    /// Creates a new handle to the given mesh
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh
+    pub fn new(mesh: Mesh) -> Self {
+        Self {
+            id: NEXT_MESH_ID.fetch_add(1, Ordering::Relaxed),
+            mesh: Arc::new(mesh),
+        }
+    }
+
+    /// Returns the unique id of the mesh
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Deref for MeshHandle {
+    type Target = Mesh;
+
+    fn deref(&self) -> &Self::Target {
+        &self.mesh
+    }
+}
+
+/// MeshBuilder
+///
+/// A `MeshBuilder` accumulates vertices, primitives and
+/// whole sub meshes into a single mesh. Sub meshes could
+/// be transformed while they are added, e.g. to place
+/// multiple cubes next to each other.
+pub struct MeshBuilder {
+    /// The mesh which is built up
+    mesh: Mesh,
+}
+
+impl Default for MeshBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MeshBuilder {
+    /// Creates a new mesh builder for triangles
+    pub fn new() -> Self {
+        Self::with_topology(PrimitiveTopology::Triangles)
+    }
+
+    /// Creates a new mesh builder with the given
+    /// primitive topology
+    ///
+    /// # Arguments
+    ///
+    /// * `topology` - How the indexed vertices are assembled
+    pub fn with_topology(topology: PrimitiveTopology) -> Self {
+        Self {
+            mesh: Mesh {
+                topology,
+                ..Mesh::default()
+            },
+        }
+    }
+
+    /// Adds a vertex and returns its index
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the vertex
+    /// * `tex_coord` - The texture coordinate of the vertex
+    /// * `normal` - The normal of the vertex
+    pub fn vertex(&mut self, pos: Vector3<f32>, tex_coord: Vector2<f32>, normal: Vector3<f32>) -> u32 {
+        let index = self.mesh.vertex_count() as u32;
+        self.mesh.vertex_positions.extend_from_slice(&[pos.x, pos.y, pos.z]);
+        self.mesh.tex_coords.extend_from_slice(&[tex_coord.x, tex_coord.y]);
+        self.mesh.normals.extend_from_slice(&[normal.x, normal.y, normal.z]);
+        index
+    }
+
+    /// Adds a single index
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of a vertex
+    pub fn index(&mut self, index: u32) {
+        self.mesh.indices.push(index);
+    }
+
+    /// Adds a triangle of the given vertex indices in
+    /// counter clockwise order
+    pub fn triangle(&mut self, a: u32, b: u32, c: u32) {
+        self.mesh.indices.extend_from_slice(&[a, b, c]);
+    }
+
+    /// Adds a quad of the given corners in counter clockwise
+    /// order, starting at the bottom left corner. The texture
+    /// coordinates span the whole quad.
+    ///
+    /// # Arguments
+    ///
+    /// * `corners` - The corners of the quad
+    /// * `normal` - The normal of the quad
+    pub fn quad(&mut self, corners: [Vector3<f32>; 4], normal: Vector3<f32>) {
+        let bottom_left = self.vertex(corners[0], Vector2::new(0.0, 0.0), normal);
+        let bottom_right = self.vertex(corners[1], Vector2::new(1.0, 0.0), normal);
+        let top_right = self.vertex(corners[2], Vector2::new(1.0, 1.0), normal);
+        let top_left = self.vertex(corners[3], Vector2::new(0.0, 1.0), normal);
+
+        self.triangle(bottom_left, bottom_right, top_right);
+        self.triangle(top_right, top_left, bottom_left);
+    }
+
+    /// Adds a sub mesh which is transformed by the given matrix.
+    /// Normals are transformed by the inverse transpose of the
+    /// matrix, so non-uniform scaling is handled correctly.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The sub mesh
+    /// * `transform` - The transformation of the sub mesh
+    pub fn add(&mut self, mesh: &Mesh, transform: &Matrix4<f32>) {
+        let normal_matrix = transform.invert()
+            .map(|inverse| inverse.transpose())
+            .unwrap_or_else(Matrix4::identity);
+        let offset = self.mesh.vertex_count() as u32;
+
+        for i in 0..mesh.vertex_count() {
+            let pos = Vector4::new(
+                mesh.vertex_positions[i * 3],
+                mesh.vertex_positions[i * 3 + 1],
+                mesh.vertex_positions[i * 3 + 2],
+                1.0,
+            );
+            let normal = Vector4::new(
+                mesh.normals.get(i * 3).cloned().unwrap_or(0.0),
+                mesh.normals.get(i * 3 + 1).cloned().unwrap_or(0.0),
+                mesh.normals.get(i * 3 + 2).cloned().unwrap_or(0.0),
+                0.0,
+            );
+            let tex_coord = Vector2::new(
+                mesh.tex_coords.get(i * 2).cloned().unwrap_or(0.0),
+                mesh.tex_coords.get(i * 2 + 1).cloned().unwrap_or(0.0),
+            );
+
+            let pos = transform * pos;
+            let normal = (normal_matrix * normal).truncate();
+            let normal = if normal.is_zero() { normal } else { normal.normalize() };
+
+            self.vertex(pos.truncate(), tex_coord, normal);
+        }
+
+        self.mesh.indices.extend(mesh.indices.iter().map(|index| index + offset));
+    }
+
+    /// Returns the built mesh
+    pub fn build(self) -> Mesh {
+        self.mesh
+    }
+}
+
 /// Model
 ///
 /// A model is built up by a mesh and it is generating the
-/// required buffers for an `OpenGL` render call
+/// required buffers for a render call
 pub struct Model {
-    /// An `OpenGL` instance
-    gl: Gl,
     /// The vertex array of the model
     va: VertexArray,
     /// The index buffer of the model
     ib: IndexBuffer,
-    /// All additional buffers for the model.
-    /// At the moment, only vertex buffers are supported to be stored here.
-    /// This might change in the future.
+    /// How the indexed vertices of the model are assembled
+    topology: PrimitiveTopology,
+    /// All vertex buffers of the model. The first one stores
+    /// the vertices of the mesh, additional ones could be added
+    /// with `add_buffer`.
     buffers: Vec<VertexBuffer>,
 }
 
@@ -49,37 +405,34 @@ impl Model {
     ///
     /// # Arguments
     ///
+    /// * `backend` - The render backend the buffers are created with
     /// * `mesh` - A mesh instance
-    pub fn from_mesh(gl: &Gl, mesh: &Mesh) -> Self {
-        let mut va = VertexArray::new(gl);
-        let vb_vertex_positions = VertexBuffer::new(gl, mesh.vertex_positions.as_ptr() as *const GLvoid, mesh.vertex_positions.len() as isize * size_of::<f32>() as isize);
-        let vb_tex_coords = VertexBuffer::new(gl, mesh.tex_coords.as_ptr() as *const GLvoid, mesh.tex_coords.len() as isize * size_of::<f32>() as isize);
-        let vb_normals = VertexBuffer::new(gl, mesh.normals.as_ptr() as *const GLvoid, mesh.normals.len() as isize * size_of::<f32>() as isize);
-
-        let mut buffer_layout = VertexBufferLayout::new();
-        buffer_layout.push_f32(3);
-        va.add_buffer(&vb_vertex_positions, &buffer_layout);
-
-        let mut buffer_layout = VertexBufferLayout::new();
-        buffer_layout.push_f32(2);
-        va.add_buffer(&vb_tex_coords, &buffer_layout);
-
-        let mut buffer_layout = VertexBufferLayout::new();
-        buffer_layout.push_f32(3);
-        va.add_buffer(&vb_normals, &buffer_layout);
+    pub fn from_mesh(backend: &GlBackend, mesh: &Mesh) -> Self {
+        let mut va = backend.create_vertex_array();
+        let vb = backend.create_vertex_buffer(&mesh.vertices());
+        va.add_buffer(&vb);
 
-        let ib = IndexBuffer::new(gl, mesh.indices.as_ptr(), mesh.indices.len());
-
-        let buffers = vec![vb_vertex_positions, vb_tex_coords, vb_normals];
+        let ib = backend.create_index_buffer(&mesh.indices, mesh.vertex_count());
 
         Self {
             va,
             ib,
-            buffers,
-            gl: gl.clone(),
+            topology: mesh.topology,
+            buffers: vec![vb],
         }
     }
 
+    /// Adds an additional vertex buffer to the model, e.g.
+    /// for per vertex data which isn't part of the mesh
+    ///
+    /// # Arguments
+    ///
+    /// * `vb` - The vertex buffer
+    pub fn add_buffer(&mut self, vb: VertexBuffer) {
+        self.va.add_buffer(&vb);
+        self.buffers.push(vb);
+    }
+
     /// Binds the model
     pub fn bind(&self) {
         self.va.bind();
@@ -97,27 +450,150 @@ impl Model {
        &self.va
     }
 
-    /// Returns the mutable vertex array of the model
-    pub fn va_mut(&mut self) -> &mut VertexArray {
-        &mut self.va
-    }
-
     /// Returns the index buffer of the model
     pub fn ib(&self) -> &IndexBuffer {
         &self.ib
     }
 
-    /// Returns all additional buffers.
-    /// At the moment, only vertex buffers are supported to be stored here.
-    /// This might change in the future.
-    pub fn buffers(&self) -> &Vec<VertexBuffer> {
+    /// Returns how the indexed vertices of the model are assembled
+    pub fn topology(&self) -> PrimitiveTopology {
+        self.topology
+    }
+
+    /// Returns all vertex buffers of the model
+    pub fn buffers(&self) -> &[VertexBuffer] {
         &self.buffers
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts that all indices of the mesh refer to one of its vertices
+    fn assert_indices_in_range(mesh: &Mesh) {
+        let vertex_count = mesh.vertex_count() as u32;
+        assert!(mesh.indices.iter().all(|&index| index < vertex_count));
+    }
+
+    /// Asserts that all attributes have an entry per vertex
+    fn assert_consistent(mesh: &Mesh) {
+        assert_eq!(mesh.vertex_positions.len() % 3, 0);
+        assert_eq!(mesh.tex_coords.len(), mesh.vertex_count() * 2);
+        assert_eq!(mesh.normals.len(), mesh.vertex_count() * 3);
+        assert_indices_in_range(mesh);
+    }
+
+    #[test]
+    fn quad_counts() {
+        let mesh = Mesh::quad();
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.indices.len(), 6);
+        assert_eq!(mesh.topology, PrimitiveTopology::Triangles);
+        assert_consistent(&mesh);
+    }
+
+    #[test]
+    fn cube_counts() {
+        let mesh = Mesh::cube();
+        assert_eq!(mesh.vertex_count(), 24);
+        assert_eq!(mesh.indices.len(), 36);
+        assert_consistent(&mesh);
+    }
+
+    #[test]
+    fn sphere_counts() {
+        let mesh = Mesh::sphere(16, 32);
+        assert_eq!(mesh.vertex_count(), 17 * 33);
+        assert_eq!(mesh.indices.len(), 16 * 32 * 6);
+        assert_consistent(&mesh);
+
+        // Degenerate arguments are clamped
+        let mesh = Mesh::sphere(0, 0);
+        assert_eq!(mesh.vertex_count(), 3 * 4);
+        assert_eq!(mesh.indices.len(), 2 * 3 * 6);
+        assert_consistent(&mesh);
+    }
 
-    /// Returns all additional buffers mutably.
-    /// At the moment, only vertex buffers are supported to be stored here.
-    /// This might change in the future.
-    pub fn buffers_mut(&mut self) -> &mut Vec<VertexBuffer> {
-        &mut self.buffers
+    #[test]
+    fn sphere_vertices_are_on_unit_sphere() {
+        let mesh = Mesh::sphere(8, 8);
+        for vertex in mesh.vertices() {
+            let length = Vector3::from(vertex.position).magnitude();
+            assert!((length - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn lines_counts() {
+        let mesh = Mesh::lines(&[
+            (Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0)),
+            (Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)),
+        ]);
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 3]);
+        assert_eq!(mesh.topology, PrimitiveTopology::Lines);
+        assert_consistent(&mesh);
+    }
+
+    #[test]
+    fn billboard_faces_along_cross_product() {
+        let mesh = Mesh::billboard(Vector3::new(5.0, 0.0, 0.0), Vector2::new(2.0, 4.0), Vector3::unit_z(), Vector3::unit_y());
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.indices.len(), 6);
+        assert_consistent(&mesh);
+
+        for vertex in mesh.vertices() {
+            assert_eq!(vertex.position[0], 5.0);
+            assert_eq!(vertex.position[1].abs(), 2.0);
+            assert_eq!(vertex.position[2].abs(), 1.0);
+            assert_eq!(vertex.normal, [-1.0, 0.0, 0.0]);
+        }
+    }
+
+    #[test]
+    fn builder_add_offsets_indices() {
+        let mut builder = MeshBuilder::new();
+        builder.add(&Mesh::cube(), &Matrix4::identity());
+        builder.add(&Mesh::cube(), &Matrix4::from_translation(Vector3::new(1.0, 0.0, 0.0)));
+        let mesh = builder.build();
+
+        assert_eq!(mesh.vertex_count(), 48);
+        assert_eq!(mesh.indices.len(), 72);
+        assert_eq!(mesh.indices[36], mesh.indices[0] + 24);
+        assert_consistent(&mesh);
+        assert_eq!(mesh.vertices()[24].position, [1.0, 0.0, 1.0]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn builder_add_transforms_normals() {
+        let mut builder = MeshBuilder::new();
+        builder.add(&Mesh::quad(), &Matrix4::from_nonuniform_scale(2.0, 1.0, 3.0));
+        let mesh = builder.build();
+
+        for vertex in mesh.vertices() {
+            assert_eq!(vertex.normal, [0.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn mesh_handles_have_unique_ids() {
+        let a = MeshHandle::new(Mesh::quad());
+        let b = MeshHandle::new(Mesh::quad());
+        assert_ne!(a.id(), b.id());
+        assert_eq!(a.clone().id(), a.id());
+    }
+
+    #[test]
+    fn vertices_fill_missing_attributes() {
+        let mesh = Mesh {
+            vertex_positions: vec![1.0, 2.0, 3.0],
+            ..Mesh::default()
+        };
+        assert_eq!(mesh.vertices(), vec![MeshVertex {
+            position: [1.0, 2.0, 3.0],
+            tex_coord: [0.0, 0.0],
+            normal: [0.0, 0.0, 0.0],
+        }]);
+    }
+}
@@ -1,8 +1,24 @@
 #[doc(hidden)]
 pub mod bindings;
+pub mod attribute;
+pub mod backend;
+pub mod bloom;
 pub mod buffer;
+pub mod capabilities;
+pub mod cave_fog;
+pub mod cinematic;
+pub mod debug_view;
+pub mod environment;
+pub mod font;
 pub mod gl;
+pub mod isometric;
 pub mod mesh;
+pub mod pass;
+pub mod quality;
 pub mod renderer;
-pub mod shader;
-pub mod texture;
\ No newline at end of file
+pub mod scene;
+pub mod sky;
+pub mod submerged;
+pub mod transition;
+
+pub use self::backend::opengl::{framebuffer, shader, texture};
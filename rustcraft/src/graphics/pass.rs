@@ -0,0 +1,136 @@
+//! Types to split the rendering of a frame into
+//! multiple ordered passes
+
+use crate::camera::Projection;
+use crate::graphics::debug_view::DebugView;
+use crate::graphics::quality::Quality;
+use crate::graphics::renderer::Renderer;
+use crate::world::texture_pack::TexturePack;
+
+/// RenderStage
+///
+/// The stage of a frame a `RenderPass` belongs to.
+/// Stages are rendered in the order of declaration:
+/// * `Sky` - The sky and celestial bodies
+/// * `Opaque` - Opaque geometry like chunks and props
+/// * `Transparent` - Translucent geometry, rendered after
+/// all opaque geometry
+/// * `Debug` - Debug geometry like lines and outlines
+/// * `PostProcess` - Effects applied to the rendered scene
+/// * `Overlay` - The 2D user interface
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum RenderStage {
+    Sky,
+    Opaque,
+    Transparent,
+    Debug,
+    PostProcess,
+    Overlay,
+}
+
+/// Frame
+///
+/// The state of a frame which is rendered. The graphics
+/// module doesn't know the concrete frame type, every pass
+/// rather describes what it needs from a frame with a trait
+/// extending this one.
+pub trait Frame {
+    /// Returns the camera the frame is rendered with
+    fn camera(&self) -> &dyn Projection;
+}
+
+/// RenderContext
+///
+/// The `RenderContext` is handed to each pass and
+/// provides everything needed to draw a frame.
+pub struct RenderContext<'a, F> {
+    /// The renderer submitting the draw calls
+    pub renderer: &'a Renderer,
+    /// The state of the frame which is rendered
+    pub frame: &'a F,
+    /// The quality the frame is rendered with
+    pub quality: Quality,
+    /// The texture pack of the blocks
+    pub textures: &'a TexturePack,
+    /// What the chunks show instead of their shaded textures
+    pub debug_view: DebugView,
+}
+
+/// RenderPass
+///
+/// A trait which is implemented by everything that
+/// draws a part of a frame of the type `F`.
+pub trait RenderPass<F> {
+    /// Returns the stage this pass belongs to
+    fn stage(&self) -> RenderStage;
+
+    /// Renders the pass
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The render context of the current frame
+    fn render(&mut self, ctx: &RenderContext<F>);
+}
+
+/// RenderPasses
+///
+/// An ordered list of render passes. Passes of the same
+/// stage are rendered in the order they were added.
+pub struct RenderPasses<F> {
+    /// The passes ordered by their stage
+    passes: Vec<Box<dyn RenderPass<F>>>,
+}
+
+impl<F> Default for RenderPasses<F> {
+    fn default() -> Self {
+        Self {
+            passes: Vec::new(),
+        }
+    }
+}
+
+impl<F> RenderPasses<F> {
+    /// Creates a new, empty list of render passes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a render pass after all passes of the same
+    /// or an earlier stage
+    ///
+    /// # Arguments
+    ///
+    /// * `pass` - The render pass
+    pub fn add(&mut self, pass: Box<dyn RenderPass<F>>) {
+        let stage = pass.stage();
+        let index = self.passes.iter()
+            .position(|other| other.stage() > stage)
+            .unwrap_or(self.passes.len());
+        self.passes.insert(index, pass);
+    }
+
+    /// Renders all passes in order
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The render context of the current frame
+    pub fn render(&mut self, ctx: &RenderContext<F>) {
+        for pass in self.passes.iter_mut() {
+            pass.render(ctx);
+        }
+    }
+
+    /// Renders the passes of the stages before the given
+    /// one in order, e.g. to capture the scene without
+    /// post processing and the user interface
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The render context of the current frame
+    /// * `until` - The first stage which isn't rendered
+    pub fn render_until(&mut self, ctx: &RenderContext<F>, until: RenderStage) {
+        for pass in self.passes.iter_mut().take_while(|pass| pass.stage() < until) {
+            pass.render(ctx);
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! Types scaling the quality of the rendering down when
+//! the target frame rate can't be held, and up again once
+//! there is headroom
+
+use crate::resources::Resources;
+use crate::world::RENDER_DISTANCE;
+use serde::Deserialize;
+
+/// The resource declaring the settings of the game
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+/// The amount of frames whose average frame time
+/// decides whether the quality is changed
+const FRAME_WINDOW: usize = 60;
+
+/// The share of the frame budget above which the
+/// quality is scaled down
+const DEGRADE_RATIO: f32 = 1.1;
+
+/// The share of the frame budget below which the
+/// quality is scaled up again. It's well below the
+/// budget, so that the quality doesn't flip back and
+/// forth between two levels.
+const RESTORE_RATIO: f32 = 0.6;
+
+/// Quality
+///
+/// The effective quality a frame is rendered with
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quality {
+    /// The distance in chunks up to which chunks are drawn
+    pub render_distance: i32,
+    /// Whether the bloom post effect is drawn
+    pub bloom: bool,
+    /// Whether the cinematic post effects are drawn,
+    /// if they are enabled
+    pub cinematic: bool,
+    /// Whether the fog in caves is drawn, if it's enabled
+    pub cave_fog: bool,
+}
+
+impl Default for Quality {
+    fn default() -> Self {
+        QUALITY_LEVELS[0]
+    }
+}
+
+/// The quality levels from the full quality to the
+/// lowest one. The post effects are dropped first, as
+/// they are the cheapest to lose, before the render
+/// distance shrinks.
+const QUALITY_LEVELS: [Quality; 4] = [
+    Quality { render_distance: RENDER_DISTANCE, bloom: true, cinematic: true, cave_fog: true },
+    Quality { render_distance: RENDER_DISTANCE, bloom: false, cinematic: false, cave_fog: false },
+    Quality { render_distance: 4, bloom: false, cinematic: false, cave_fog: false },
+    Quality { render_distance: 2, bloom: false, cinematic: false, cave_fog: false },
+];
+
+/// QualitySettings
+///
+/// The settings of the adaptive quality, declared in the
+/// `[quality]` table of the settings resource, e.g.
+///
+/// ```toml
+/// [quality]
+/// adaptive = true
+/// target_fps = 60.0
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct QualitySettings {
+    /// Whether the quality is scaled to hold the target
+    /// frame rate. Otherwise, the full quality is always used.
+    pub adaptive: bool,
+    /// The frame rate the quality is scaled for
+    pub target_fps: f32,
+}
+
+impl Default for QualitySettings {
+    fn default() -> Self {
+        Self {
+            adaptive: true,
+            target_fps: 60.0,
+        }
+    }
+}
+
+/// The declaration of the settings resource
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct SettingsDef {
+    /// The settings of the adaptive quality
+    quality: QualitySettings,
+}
+
+impl QualitySettings {
+    /// Loads the settings declared by the resources. If they
+    /// can't be loaded, the default settings are used.
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - A resource instance
+    pub fn load(resources: &Resources) -> Self {
+        let source = match resources.load_string(SETTINGS_FILE) {
+            Ok(source) => source,
+            Err(error) => {
+                log::warn!("Failed to load {}: {:?}", SETTINGS_FILE, error);
+                return Self::default();
+            }
+        };
+
+        Self::from_toml(&source).unwrap_or_else(|error| {
+            log::warn!("Failed to parse {}: {}", SETTINGS_FILE, error);
+            Self::default()
+        })
+    }
+
+    /// Parses the settings from the `TOML` declaration
+    /// of the settings resource
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The declaration of the settings
+    pub fn from_toml(source: &str) -> Result<Self, toml::de::Error> {
+        let def: SettingsDef = toml::from_str(source)?;
+        Ok(def.quality)
+    }
+}
+
+/// QualityGovernor
+///
+/// The `QualityGovernor` watches the recent frame times and
+/// steps the quality down one level whenever their average
+/// exceeds the frame budget of the target frame rate. Once
+/// the frames take well below the budget again, the quality
+/// is stepped up one level at a time.
+pub struct QualityGovernor {
+    /// The settings of the adaptive quality
+    settings: QualitySettings,
+    /// The frame times of the current window in seconds
+    frame_times: Vec<f32>,
+    /// The index of the current quality level
+    level: usize,
+    /// The render distance the player chose
+    render_distance: i32,
+}
+
+impl QualityGovernor {
+    /// Creates a new governor starting at the full quality
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The settings of the adaptive quality
+    pub fn new(settings: QualitySettings) -> Self {
+        Self {
+            settings,
+            frame_times: Vec::with_capacity(FRAME_WINDOW),
+            level: 0,
+            render_distance: RENDER_DISTANCE,
+        }
+    }
+
+    /// Sets the render distance the player chose. The levels at
+    /// the full render distance draw up to it, the reduced ones
+    /// never farther than it.
+    ///
+    /// # Arguments
+    ///
+    /// * `render_distance` - The distance in chunks
+    pub fn set_render_distance(&mut self, render_distance: i32) {
+        self.render_distance = render_distance;
+    }
+
+    /// Records the time a frame took and adjusts the quality
+    /// at the end of each window of frames
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_time` - The time the frame took in seconds
+    pub fn record_frame(&mut self, frame_time: f32) {
+        if !self.settings.adaptive || self.settings.target_fps <= 0.0 {
+            return;
+        }
+
+        self.frame_times.push(frame_time);
+        if self.frame_times.len() < FRAME_WINDOW {
+            return;
+        }
+
+        let average = self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32;
+        self.frame_times.clear();
+
+        let budget = 1.0 / self.settings.target_fps;
+        if average > budget * DEGRADE_RATIO && self.level + 1 < QUALITY_LEVELS.len() {
+            self.level += 1;
+            log::info!("Frames take {:.1}ms, lowering the quality to {:?}", average * 1000.0, self.quality());
+        } else if average < budget * RESTORE_RATIO && self.level > 0 {
+            self.level -= 1;
+            log::info!("Frames take {:.1}ms, raising the quality to {:?}", average * 1000.0, self.quality());
+        }
+    }
+
+    /// Returns the quality the next frame is rendered with
+    pub fn quality(&self) -> Quality {
+        let quality = QUALITY_LEVELS[self.level];
+        let render_distance = if quality.render_distance == RENDER_DISTANCE {
+            self.render_distance
+        } else {
+            quality.render_distance.min(self.render_distance)
+        };
+        Quality { render_distance, ..quality }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records a full window of frames taking the given time
+    fn record_window(governor: &mut QualityGovernor, frame_time: f32) {
+        for _ in 0..FRAME_WINDOW {
+            governor.record_frame(frame_time);
+        }
+    }
+
+    #[test]
+    fn quality_follows_frame_times() {
+        let mut governor = QualityGovernor::new(QualitySettings::default());
+        assert_eq!(governor.quality(), Quality::default());
+
+        // A slow window drops the post effects, then the render distance
+        record_window(&mut governor, 0.025);
+        assert_eq!(governor.quality(), QUALITY_LEVELS[1]);
+        record_window(&mut governor, 0.025);
+        assert_eq!(governor.quality().render_distance, 4);
+
+        // Frames within the budget keep the current quality
+        record_window(&mut governor, 0.015);
+        assert_eq!(governor.quality().render_distance, 4);
+
+        // Headroom restores the quality one level at a time
+        record_window(&mut governor, 0.005);
+        assert_eq!(governor.quality(), QUALITY_LEVELS[1]);
+        record_window(&mut governor, 0.005);
+        record_window(&mut governor, 0.005);
+        assert_eq!(governor.quality(), Quality::default());
+    }
+
+    #[test]
+    fn quality_stays_within_levels() {
+        let mut governor = QualityGovernor::new(QualitySettings::default());
+        for _ in 0..10 {
+            record_window(&mut governor, 1.0);
+        }
+        assert_eq!(governor.quality(), QUALITY_LEVELS[QUALITY_LEVELS.len() - 1]);
+    }
+
+    #[test]
+    fn levels_follow_the_chosen_render_distance() {
+        let mut governor = QualityGovernor::new(QualitySettings::default());
+        governor.set_render_distance(12);
+        assert_eq!(governor.quality().render_distance, 12);
+        record_window(&mut governor, 0.025);
+        record_window(&mut governor, 0.025);
+        assert_eq!(governor.quality().render_distance, 4);
+
+        governor.set_render_distance(3);
+        assert_eq!(governor.quality().render_distance, 3);
+    }
+
+    #[test]
+    fn disabled_governor_keeps_full_quality() {
+        let settings = QualitySettings::from_toml("[quality]\nadaptive = false").unwrap();
+        assert_eq!(settings.target_fps, 60.0);
+
+        let mut governor = QualityGovernor::new(settings);
+        record_window(&mut governor, 1.0);
+        assert_eq!(governor.quality(), Quality::default());
+    }
+
+    #[test]
+    fn settings_default_without_quality_table() {
+        assert_eq!(QualitySettings::from_toml("").unwrap(), QualitySettings::default());
+        assert!(QualitySettings::from_toml("[quality]\ntarget_fps = \"fast\"").is_err());
+    }
+}
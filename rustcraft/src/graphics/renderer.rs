@@ -1,55 +1,117 @@
-use crate::graphics::buffer::{VertexArray, IndexBuffer};
-use crate::graphics::shader::{ShaderProgram};
-use crate::gl;
+use crate::graphics::backend::{GlBackend, PolygonMode, PrimitiveTopology, RenderBackend};
+use crate::graphics::capabilities::GraphicsSettings;
 use crate::graphics::gl::Gl;
 
 /// A `Renderer` somehow links the whole
 /// graphics context together. It combines
 /// the given buffers, vertex arrays, shaders and cameras
 /// to draw a scene on the screen.
-pub struct Renderer {
-    /// An `OpenGL` instance
-    gl: Gl,
+///
+/// All graphics API specific work is delegated to
+/// the underlying `RenderBackend`.
+pub struct Renderer<B: RenderBackend = GlBackend> {
+    /// The render backend
+    backend: B,
 }
 
-impl Renderer {
-    /// Creates a new `Renderer`
+impl Renderer<GlBackend> {
+    /// Creates a new `Renderer` using the `OpenGL` backend
     ///
     /// # Arguments
     ///
     /// * `gl` - An `OpenGL` instance
-    pub fn new(gl: &Gl) -> Self {
+    /// * `settings` - The optional graphics features the player asked for
+    pub fn new(gl: &Gl, settings: &GraphicsSettings) -> Self {
         Renderer {
-            gl: gl.clone(),
+            backend: GlBackend::new(gl, settings),
         }
     }
-    /// Clears the `OpenGL` rendered context
-    pub fn clear(&self) {
-        unsafe {
-            self.gl.Clear(gl::COLOR_BUFFER_BIT);
-            self.gl.Clear(gl::DEPTH_BUFFER_BIT);
+}
+
+impl<B: RenderBackend> Renderer<B> {
+    /// Creates a new `Renderer` from a given backend
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - A render backend
+    pub fn from_backend(backend: B) -> Self {
+        Renderer {
+            backend,
         }
     }
 
+    /// Returns the render backend
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Clears the rendered context
+    pub fn clear(&self) {
+        self.backend.clear();
+    }
+
+    /// Sets the color the screen is cleared with
+    pub fn set_clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.backend.set_clear_color(r, g, b, a);
+    }
+
+    /// Sets the viewport to the given size
+    pub fn set_viewport(&self, width: i32, height: i32) {
+        self.backend.set_viewport(width, height);
+    }
+
+    /// Sets the polygon mode
+    pub fn set_polygon_mode(&self, mode: PolygonMode) {
+        self.backend.set_polygon_mode(mode);
+    }
+
+    /// Enables or disables depth testing
+    pub fn set_depth_test(&self, enabled: bool) {
+        self.backend.set_depth_test(enabled);
+    }
+
+    /// Enables or disables writing to the depth buffer
+    pub fn set_depth_write(&self, enabled: bool) {
+        self.backend.set_depth_write(enabled);
+    }
+
+    /// Enables or disables alpha blending
+    pub fn set_alpha_blending(&self, enabled: bool) {
+        self.backend.set_alpha_blending(enabled);
+    }
+
+    /// Returns the width and height of the current viewport
+    pub fn viewport_size(&self) -> (i32, i32) {
+        self.backend.viewport_size()
+    }
+
+    /// Enables depth testing and alpha blending, which are
+    /// required for rendering the world
+    pub fn enable_default_state(&self) {
+        self.backend.set_alpha_blending(true);
+        self.backend.set_depth_test(true);
+    }
+
     /// Draws the given buffers vertex arrays, shaders and cameras
     ///
     /// # Arguments
     ///
     /// * `va` - A vertex array
     /// * `ib` - An index buffer
-    /// * `shader_program` - A shader program
-    pub fn draw(&self, va: &VertexArray, ib: &IndexBuffer, shader_program: &mut ShaderProgram) {
-        shader_program.enable();
-        va.bind();
-        ib.bind();
-
-        unsafe {
-            self.gl.DrawElements(
-                gl::TRIANGLES,
-                ib.index_count() as i32,
-                gl::UNSIGNED_INT,
-                std::ptr::null()
-            );
-        }
+    /// * `pipeline` - A pipeline, e.g. a shader program
+    pub fn draw(&self, va: &B::VertexArray, ib: &B::IndexBuffer, pipeline: &B::Pipeline) {
+        self.backend.draw_indexed(va, ib, pipeline, PrimitiveTopology::Triangles);
     }
-}
\ No newline at end of file
+
+    /// Draws the given buffers with the given primitive topology
+    ///
+    /// # Arguments
+    ///
+    /// * `va` - A vertex array
+    /// * `ib` - An index buffer
+    /// * `pipeline` - A pipeline, e.g. a shader program
+    /// * `topology` - How the indexed vertices are assembled
+    pub fn draw_with(&self, va: &B::VertexArray, ib: &B::IndexBuffer, pipeline: &B::Pipeline, topology: PrimitiveTopology) {
+        self.backend.draw_indexed(va, ib, pipeline, topology);
+    }
+}
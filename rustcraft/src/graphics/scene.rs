@@ -0,0 +1,466 @@
+//! Types representing a scene graph of non-voxel props
+//! like decorative models, waypoint beacons or particle
+//! emitters
+
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::mesh::{MeshHandle, Model};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::shader::ShaderProgram;
+use crate::resources::Resources;
+use cgmath::{Matrix4, SquareMatrix, Vector4};
+use std::collections::{HashMap, HashSet};
+
+/// NodeId
+///
+/// A handle to a node of a `Scene`. Handles of removed
+/// nodes are never valid again, even if their slot is
+/// reused by another node.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct NodeId {
+    /// The index of the node slot
+    index: usize,
+    /// The generation of the node slot
+    generation: u32,
+}
+
+/// SceneNode
+///
+/// A node of the scene graph. Each node has a transform
+/// relative to its parent and optionally a mesh which is
+/// rendered with a flat color.
+pub struct SceneNode {
+    /// The name of the node
+    name: String,
+    /// The transform relative to the parent node
+    transform: Matrix4<f32>,
+    /// Whether the node and its children are visible
+    visible: bool,
+    /// The mesh of the node
+    mesh: Option<MeshHandle>,
+    /// The color the mesh is rendered with
+    color: Vector4<f32>,
+    /// The parent of the node
+    parent: Option<NodeId>,
+    /// The children of the node
+    children: Vec<NodeId>,
+}
+
+impl SceneNode {
+    /// Creates a new node without a mesh, e.g. to group
+    /// other nodes
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the node
+    /// * `transform` - The transform relative to the parent node
+    pub fn new(name: &str, transform: Matrix4<f32>) -> Self {
+        Self {
+            name: name.to_string(),
+            transform,
+            visible: true,
+            mesh: None,
+            color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            parent: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// Creates a new node with a mesh
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the node
+    /// * `transform` - The transform relative to the parent node
+    /// * `mesh` - The mesh of the node
+    /// * `color` - The color the mesh is rendered with
+    pub fn with_mesh(name: &str, transform: Matrix4<f32>, mesh: MeshHandle, color: Vector4<f32>) -> Self {
+        Self {
+            mesh: Some(mesh),
+            color,
+            ..Self::new(name, transform)
+        }
+    }
+
+    /// Returns the name of the node
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the transform relative to the parent node
+    pub fn transform(&self) -> &Matrix4<f32> {
+        &self.transform
+    }
+
+    /// Sets the transform relative to the parent node
+    pub fn set_transform(&mut self, transform: Matrix4<f32>) {
+        self.transform = transform;
+    }
+
+    /// Returns whether the node and its children are visible
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Sets whether the node and its children are visible
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Returns the mesh of the node
+    pub fn mesh(&self) -> Option<&MeshHandle> {
+        self.mesh.as_ref()
+    }
+
+    /// Returns the color the mesh is rendered with
+    pub fn color(&self) -> Vector4<f32> {
+        self.color
+    }
+
+    /// Sets the color the mesh is rendered with
+    pub fn set_color(&mut self, color: Vector4<f32>) {
+        self.color = color;
+    }
+
+    /// Returns the parent of the node
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    /// Returns the children of the node
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+/// SceneInstance
+///
+/// A flattened, visible node of a scene with its
+/// transform resolved to world space.
+#[derive(Clone)]
+pub struct SceneInstance {
+    /// The mesh of the node
+    pub mesh: MeshHandle,
+    /// The transform in world space
+    pub transform: Matrix4<f32>,
+    /// The color the mesh is rendered with
+    pub color: Vector4<f32>,
+}
+
+/// Scene
+///
+/// A scene is a light scene graph. The nodes are stored
+/// in slots, the handles of the nodes are generational.
+#[derive(Default)]
+pub struct Scene {
+    /// The node slots and their generation
+    slots: Vec<(u32, Option<SceneNode>)>,
+    /// The indices of free slots
+    free: Vec<usize>,
+    /// The nodes without a parent
+    roots: Vec<NodeId>,
+}
+
+impl Scene {
+    /// Creates a new, empty scene
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node to the scene and returns its handle.
+    /// If the given parent doesn't exist, the node is
+    /// added as a root node.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The parent of the node
+    /// * `node` - The node
+    pub fn add(&mut self, parent: Option<NodeId>, mut node: SceneNode) -> NodeId {
+        let parent = parent.filter(|&parent| self.node(parent).is_some());
+        node.parent = parent;
+        node.children.clear();
+
+        let id = if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index];
+            slot.1 = Some(node);
+            NodeId { index, generation: slot.0 }
+        } else {
+            self.slots.push((0, Some(node)));
+            NodeId { index: self.slots.len() - 1, generation: 0 }
+        };
+
+        match parent.and_then(|parent| self.node_mut(parent)) {
+            Some(parent) => parent.children.push(id),
+            None => self.roots.push(id),
+        }
+
+        id
+    }
+
+    /// Removes a node and all of its children from the scene
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The handle of the node
+    pub fn remove(&mut self, id: NodeId) {
+        let node = match self.take(id) {
+            Some(node) => node,
+            None => return,
+        };
+
+        match node.parent.and_then(|parent| self.node_mut(parent)) {
+            Some(parent) => parent.children.retain(|&child| child != id),
+            None => self.roots.retain(|&root| root != id),
+        }
+
+        let mut stack = node.children;
+        while let Some(child) = stack.pop() {
+            if let Some(child) = self.take(child) {
+                stack.extend(child.children);
+            }
+        }
+    }
+
+    /// Takes a node out of its slot and frees the slot
+    fn take(&mut self, id: NodeId) -> Option<SceneNode> {
+        let slot = self.slots.get_mut(id.index).filter(|slot| slot.0 == id.generation)?;
+        let node = slot.1.take()?;
+        slot.0 = slot.0.wrapping_add(1);
+        self.free.push(id.index);
+        Some(node)
+    }
+
+    /// Returns the node of the given handle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The handle of the node
+    pub fn node(&self, id: NodeId) -> Option<&SceneNode> {
+        self.slots.get(id.index)
+            .filter(|slot| slot.0 == id.generation)
+            .and_then(|slot| slot.1.as_ref())
+    }
+
+    /// Returns the mutable node of the given handle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The handle of the node
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut SceneNode> {
+        self.slots.get_mut(id.index)
+            .filter(|slot| slot.0 == id.generation)
+            .and_then(|slot| slot.1.as_mut())
+    }
+
+    /// Returns the transform of a node in world space
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The handle of the node
+    pub fn world_transform(&self, id: NodeId) -> Option<Matrix4<f32>> {
+        let mut node = self.node(id)?;
+        let mut transform = node.transform;
+        while let Some(parent) = node.parent.and_then(|parent| self.node(parent)) {
+            transform = parent.transform * transform;
+            node = parent;
+        }
+        Some(transform)
+    }
+
+    /// Returns all visible nodes with a mesh, flattened into
+    /// instances with their transforms in world space
+    pub fn instances(&self) -> Vec<SceneInstance> {
+        let mut instances = Vec::new();
+        let mut stack: Vec<(NodeId, Matrix4<f32>)> = self.roots.iter()
+            .map(|&root| (root, Matrix4::identity()))
+            .collect();
+
+        while let Some((id, parent_transform)) = stack.pop() {
+            let node = match self.node(id) {
+                Some(node) if node.visible => node,
+                _ => continue,
+            };
+
+            let transform = parent_transform * node.transform;
+            if let Some(mesh) = &node.mesh {
+                instances.push(SceneInstance {
+                    mesh: mesh.clone(),
+                    transform,
+                    color: node.color,
+                });
+            }
+
+            stack.extend(node.children.iter().map(|&child| (child, transform)));
+        }
+
+        instances
+    }
+}
+
+/// SceneFrame
+///
+/// A frame scene instances could be rendered for
+pub trait SceneFrame: Frame {
+    /// Returns the scene instances which should be rendered
+    fn props(&self) -> &[SceneInstance];
+}
+
+/// SceneRenderer
+///
+/// The `SceneRenderer` is the render pass drawing the
+/// scene instances of a frame. Models are uploaded lazily
+/// and released once their mesh isn't rendered anymore.
+pub struct SceneRenderer {
+    /// The render backend
+    backend: GlBackend,
+    /// A shader program
+    shader_program: ShaderProgram,
+    /// The uploaded models keyed by the id of their mesh
+    models: HashMap<u64, Model>,
+}
+
+impl SceneRenderer {
+    /// Creates a new scene renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        let shader_program = backend.create_pipeline(resources, "prop").unwrap();
+        shader_program.disable();
+
+        Self {
+            backend: backend.clone(),
+            shader_program,
+            models: HashMap::new(),
+        }
+    }
+}
+
+impl<F: SceneFrame> RenderPass<F> for SceneRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Opaque
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let instances = ctx.frame.props();
+        let ids: HashSet<u64> = instances.iter().map(|instance| instance.mesh.id()).collect();
+        self.models.retain(|id, _| ids.contains(id));
+
+        if instances.is_empty() {
+            return;
+        }
+
+        let camera = ctx.frame.camera();
+        let view_proj = camera.proj_matrix() * camera.view_matrix();
+
+        self.shader_program.enable();
+        for instance in instances {
+            let backend = &self.backend;
+            let model = self.models.entry(instance.mesh.id())
+                .or_insert_with(|| Model::from_mesh(backend, &instance.mesh));
+
+            let mvp = view_proj * instance.transform;
+            self.shader_program.set_uniform_mat4f("u_MVP", &mvp);
+            self.shader_program.set_uniform_mat4f("u_Model", &instance.transform);
+            self.shader_program.set_uniform_4f("u_Color", instance.color.x, instance.color.y, instance.color.z, instance.color.w);
+
+            ctx.renderer.draw_with(model.va(), model.ib(), &self.shader_program, model.topology());
+            model.unbind();
+        }
+        self.shader_program.disable();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::mesh::Mesh;
+    use cgmath::Vector3;
+
+    fn translation(x: f32, y: f32, z: f32) -> Matrix4<f32> {
+        Matrix4::from_translation(Vector3::new(x, y, z))
+    }
+
+    fn prop(name: &str, transform: Matrix4<f32>) -> SceneNode {
+        SceneNode::with_mesh(name, transform, MeshHandle::new(Mesh::cube()), Vector4::new(1.0, 1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn removed_handles_stay_invalid_when_slot_is_reused() {
+        let mut scene = Scene::new();
+        let first = scene.add(None, SceneNode::new("first", Matrix4::identity()));
+        scene.remove(first);
+        assert!(scene.node(first).is_none());
+
+        let second = scene.add(None, SceneNode::new("second", Matrix4::identity()));
+        assert_eq!(second.index, first.index);
+        assert_ne!(second.generation, first.generation);
+        assert!(scene.node(first).is_none());
+        assert_eq!(scene.node(second).unwrap().name(), "second");
+
+        // Removing the stale handle must not remove the new node
+        scene.remove(first);
+        assert!(scene.node(second).is_some());
+    }
+
+    #[test]
+    fn remove_removes_children_recursively() {
+        let mut scene = Scene::new();
+        let root = scene.add(None, SceneNode::new("root", Matrix4::identity()));
+        let child = scene.add(Some(root), SceneNode::new("child", Matrix4::identity()));
+        let grandchild = scene.add(Some(child), prop("grandchild", Matrix4::identity()));
+        let sibling = scene.add(Some(root), prop("sibling", Matrix4::identity()));
+
+        scene.remove(child);
+        assert!(scene.node(child).is_none());
+        assert!(scene.node(grandchild).is_none());
+        assert_eq!(scene.node(root).unwrap().children(), &[sibling]);
+        assert_eq!(scene.instances().len(), 1);
+
+        scene.remove(root);
+        assert!(scene.node(sibling).is_none());
+        assert!(scene.instances().is_empty());
+        assert!(scene.roots.is_empty());
+    }
+
+    #[test]
+    fn add_with_missing_parent_adds_root() {
+        let mut scene = Scene::new();
+        let parent = scene.add(None, SceneNode::new("parent", Matrix4::identity()));
+        scene.remove(parent);
+
+        let node = scene.add(Some(parent), SceneNode::new("node", Matrix4::identity()));
+        assert_eq!(scene.node(node).unwrap().parent(), None);
+        assert_eq!(scene.roots, vec![node]);
+    }
+
+    #[test]
+    fn world_transform_combines_parent_transforms() {
+        let mut scene = Scene::new();
+        let root = scene.add(None, SceneNode::new("root", translation(1.0, 0.0, 0.0)));
+        let child = scene.add(Some(root), SceneNode::new("child", Matrix4::from_scale(2.0)));
+        let grandchild = scene.add(Some(child), SceneNode::new("grandchild", translation(0.0, 1.0, 0.0)));
+
+        let transform = scene.world_transform(grandchild).unwrap();
+        assert_eq!(transform, translation(1.0, 0.0, 0.0) * Matrix4::from_scale(2.0) * translation(0.0, 1.0, 0.0));
+        assert_eq!(transform.w.truncate(), Vector3::new(1.0, 2.0, 0.0));
+
+        scene.remove(root);
+        assert!(scene.world_transform(grandchild).is_none());
+    }
+
+    #[test]
+    fn instances_skip_invisible_subtrees() {
+        let mut scene = Scene::new();
+        let root = scene.add(None, prop("root", translation(1.0, 0.0, 0.0)));
+        let hidden = scene.add(Some(root), prop("hidden", Matrix4::identity()));
+        scene.add(Some(hidden), prop("hidden_child", Matrix4::identity()));
+        scene.add(Some(root), prop("visible", translation(0.0, 0.0, 1.0)));
+        scene.node_mut(hidden).unwrap().set_visible(false);
+
+        let instances = scene.instances();
+        assert_eq!(instances.len(), 2);
+        assert!(instances.iter().any(|instance| instance.transform == translation(1.0, 0.0, 1.0)));
+    }
+}
@@ -0,0 +1,211 @@
+//! Types rendering the sky, like the sun, the moon
+//! and the stars
+
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::mesh::{Mesh, Model};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::shader::ShaderProgram;
+use crate::graphics::texture::Texture;
+use crate::resources::Resources;
+use cgmath::{Matrix4, Rad, Vector2, Vector3, Vector4};
+use std::f32::consts::PI;
+
+/// The distance of the sun and the moon to the camera
+const CELESTIAL_DISTANCE: f32 = 60.0;
+/// The size of the sun quad
+const SUN_SIZE: f32 = 12.0;
+/// The size of the moon quad
+const MOON_SIZE: f32 = 8.0;
+/// The radius of the star dome
+const STAR_DOME_RADIUS: f32 = 50.0;
+
+/// SkyFrame
+///
+/// A frame the sky could be rendered for
+pub trait SkyFrame: Frame {
+    /// Returns the angle of the sun above the eastern
+    /// horizon in radians
+    fn sun_angle(&self) -> f32;
+
+    /// Returns how dark the sky is, from `0.0` at day
+    /// to `1.0` at night
+    fn darkness(&self) -> f32;
+
+    /// Returns whether the sun, the moon and the stars
+    /// should be drawn
+    fn celestial_bodies(&self) -> bool;
+}
+
+/// SkyRenderer
+///
+/// The `SkyRenderer` is the render pass drawing the
+/// sky behind everything else. It draws a star dome
+/// which fades in with the darkness, as well as a sun
+/// and a moon which rotate with the world time.
+///
+/// The sky is always centered around the camera and
+/// doesn't write any depth.
+pub struct SkyRenderer {
+    /// A shader program
+    shader_program: ShaderProgram,
+    /// The billboard of the sun, facing the camera at sunrise
+    sun: Model,
+    /// The billboard of the moon, facing the camera at sunrise
+    moon: Model,
+    /// The dome the stars are mapped onto
+    dome: Model,
+    /// The texture of the sun
+    sun_texture: Texture,
+    /// The texture of the moon
+    moon_texture: Texture,
+    /// The texture of the stars
+    star_texture: Texture,
+}
+
+impl SkyRenderer {
+    /// Creates a new sky renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        let shader_program = backend.create_pipeline(resources, "sky").unwrap();
+        shader_program.disable();
+
+        Self {
+            shader_program,
+            sun: Model::from_mesh(backend, &celestial_mesh(SUN_SIZE)),
+            moon: Model::from_mesh(backend, &celestial_mesh(MOON_SIZE)),
+            dome: Model::from_mesh(backend, &Mesh::sphere(16, 32)),
+            sun_texture: backend.create_texture_from_pixels(32, 32, disc_pixels(32, 0.6)).unwrap(),
+            moon_texture: backend.create_texture_from_pixels(32, 32, disc_pixels(32, 0.9)).unwrap(),
+            star_texture: backend.create_texture_from_pixels(512, 256, star_pixels(512, 256, 0x5EED)).unwrap(),
+        }
+    }
+
+    /// Draws a model with the given texture, color and transform
+    fn draw<F>(&self, ctx: &RenderContext<F>, model: &Model, texture: &Texture, color: Vector4<f32>, mvp: &Matrix4<f32>) {
+        texture.bind(None);
+        model.bind();
+        self.shader_program.set_uniform_mat4f("u_MVP", mvp);
+        self.shader_program.set_uniform_4f("u_Color", color.x, color.y, color.z, color.w);
+
+        ctx.renderer.draw_with(model.va(), model.ib(), &self.shader_program, model.topology());
+
+        model.unbind();
+        texture.unbind();
+    }
+}
+
+impl<F: SkyFrame> RenderPass<F> for SkyRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Sky
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        if !ctx.frame.celestial_bodies() {
+            return;
+        }
+        let camera = ctx.frame.camera();
+
+        // Center the sky around the camera by dropping the translation
+        let mut view = *camera.view_matrix();
+        view.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+        let view_proj = camera.proj_matrix() * view;
+
+        let sun_angle = ctx.frame.sun_angle();
+        let darkness = ctx.frame.darkness();
+
+        ctx.renderer.set_depth_test(false);
+        self.shader_program.enable();
+        self.shader_program.set_uniform_1i("u_Texture", 0);
+
+        if darkness > 0.0 {
+            let model = Matrix4::from_angle_z(Rad(sun_angle)) * Matrix4::from_scale(STAR_DOME_RADIUS);
+            self.draw(ctx, &self.dome, &self.star_texture, Vector4::new(1.0, 1.0, 1.0, darkness), &(view_proj * model));
+        }
+
+        // The sun and the moon are opposite of each other, so
+        // only bodies above the horizon need to be drawn
+        let sun_elevation = sun_angle.sin();
+        if sun_elevation < 0.0 {
+            let moon = Matrix4::from_angle_z(Rad(sun_angle + PI));
+            self.draw(ctx, &self.moon, &self.moon_texture, Vector4::new(0.85, 0.88, 1.0, 1.0), &(view_proj * moon));
+        } else {
+            let sun = Matrix4::from_angle_z(Rad(sun_angle));
+            self.draw(ctx, &self.sun, &self.sun_texture, Vector4::new(1.0, 0.95, 0.7, 1.0), &(view_proj * sun));
+        }
+
+        self.shader_program.disable();
+        ctx.renderer.set_depth_test(true);
+    }
+}
+
+/// Returns a billboard of the given size above the eastern
+/// horizon, facing the camera in the origin. It's rotated
+/// around the z axis with the time of day.
+///
+/// # Arguments
+///
+/// * `size` - The width and height of the billboard
+fn celestial_mesh(size: f32) -> Mesh {
+    Mesh::billboard(
+        Vector3::new(CELESTIAL_DISTANCE, 0.0, 0.0),
+        Vector2::new(size, size),
+        Vector3::unit_z(),
+        Vector3::unit_y(),
+    )
+}
+
+/// Generates the `RGBA` pixels of a white disc with a soft edge
+///
+/// # Arguments
+///
+/// * `size` - The width and height of the texture
+/// * `core` - The relative radius of the opaque core of the disc
+fn disc_pixels(size: u32, core: f32) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+    let half = size as f32 / 2.0;
+
+    for y in 0..size {
+        for x in 0..size {
+            let dx = (x as f32 + 0.5 - half) / half;
+            let dy = (y as f32 + 0.5 - half) / half;
+            let dist = (dx * dx + dy * dy).sqrt();
+            let alpha = (1.0 - (dist - core) / (1.0 - core)).clamp(0.0, 1.0);
+            pixels.extend_from_slice(&[255, 255, 255, (alpha * 255.0) as u8]);
+        }
+    }
+
+    pixels
+}
+
+/// Generates the `RGBA` pixels of a transparent texture with
+/// randomly scattered stars
+///
+/// # Arguments
+///
+/// * `width` - The width of the texture
+/// * `height` - The height of the texture
+/// * `seed` - The seed the stars are scattered with
+fn star_pixels(width: u32, height: u32, seed: u32) -> Vec<u8> {
+    let mut pixels = vec![0; (width * height * 4) as usize];
+    let mut state = seed.max(1);
+    let mut next = || {
+        // Xorshift, good enough to scatter some stars
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+
+    let star_count = width * height / 200;
+    for _ in 0..star_count {
+        let index = ((next() % (width * height)) * 4) as usize;
+        let brightness = 128 + (next() % 128) as u8;
+        pixels[index..index + 4].copy_from_slice(&[brightness, brightness, brightness, 255]);
+    }
+
+    pixels
+}
@@ -0,0 +1,91 @@
+//! Types tinting the screen while the camera is
+//! submerged in a fluid
+
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::mesh::{Mesh, MeshBuilder, Model};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::shader::ShaderProgram;
+use crate::resources::Resources;
+use crate::world::block::Material;
+use cgmath::{Matrix4, Vector4};
+
+/// Returns the color the screen is tinted with while the
+/// camera is submerged in a fluid, where the alpha component
+/// is the opacity of the tint
+///
+/// # Arguments
+///
+/// * `fluid` - The fluid the camera is in
+pub fn tint(fluid: Material) -> Option<Vector4<f32>> {
+    match fluid {
+        Material::Lava => Some(Vector4::new(1.0, 0.35, 0.0, 0.75)),
+        Material::Water => Some(Vector4::new(0.1, 0.25, 0.8, 0.35)),
+        _ => None,
+    }
+}
+
+/// SubmergedFrame
+///
+/// A frame the tint of a fluid could be rendered for
+pub trait SubmergedFrame: Frame {
+    /// Returns the fluid the camera is in, if any
+    fn submerged(&self) -> Option<Material>;
+}
+
+/// SubmergedRenderer
+///
+/// The `SubmergedRenderer` is the post processing pass
+/// covering the rendered scene with the tint of the fluid
+/// the camera is in, e.g. orange in lava
+pub struct SubmergedRenderer {
+    /// A shader program
+    shader_program: ShaderProgram,
+    /// A quad covering the whole screen
+    quad: Model,
+}
+
+impl SubmergedRenderer {
+    /// Creates a new submerged renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        // The tint is drawn like the fade of a transition
+        let shader_program = backend.create_pipeline(resources, "fade").unwrap();
+        shader_program.disable();
+
+        let mut quad = MeshBuilder::new();
+        quad.add(&Mesh::quad(), &Matrix4::from_scale(2.0));
+
+        Self {
+            shader_program,
+            quad: Model::from_mesh(backend, &quad.build()),
+        }
+    }
+}
+
+impl<F: SubmergedFrame> RenderPass<F> for SubmergedRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::PostProcess
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let color = match ctx.frame.submerged().and_then(tint) {
+            Some(color) => color,
+            None => return,
+        };
+
+        ctx.renderer.set_depth_test(false);
+        self.shader_program.enable();
+        self.shader_program.set_uniform_4f("u_Color", color.x, color.y, color.z, color.w);
+        self.quad.bind();
+
+        ctx.renderer.draw_with(self.quad.va(), self.quad.ib(), &self.shader_program, self.quad.topology());
+
+        self.quad.unbind();
+        self.shader_program.disable();
+        ctx.renderer.set_depth_test(true);
+    }
+}
@@ -0,0 +1,82 @@
+//! Types rendering screen transitions, like the
+//! fade of a teleport
+
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::mesh::{Mesh, MeshBuilder, Model};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::shader::ShaderProgram;
+use crate::resources::Resources;
+use cgmath::{Matrix4, Vector3};
+
+/// TransitionFrame
+///
+/// A frame a screen transition could be rendered for
+pub trait TransitionFrame: Frame {
+    /// Returns how far the screen is faded out, from
+    /// `0.0` for no transition to `1.0` for a fully
+    /// covered screen
+    fn fade(&self) -> f32;
+}
+
+/// TransitionRenderer
+///
+/// The `TransitionRenderer` is the post processing pass
+/// covering the rendered scene with a colored quad while
+/// a transition is running. It's drawn before the overlay,
+/// so notifications stay readable.
+pub struct TransitionRenderer {
+    /// A shader program
+    shader_program: ShaderProgram,
+    /// A quad covering the whole screen
+    quad: Model,
+    /// The color the screen is faded to
+    color: Vector3<f32>,
+}
+
+impl TransitionRenderer {
+    /// Creates a new transition renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        let shader_program = backend.create_pipeline(resources, "fade").unwrap();
+        shader_program.disable();
+
+        // `Mesh::quad` spans from -0.5 to 0.5, while normalized
+        // device coordinates span from -1.0 to 1.0
+        let mut quad = MeshBuilder::new();
+        quad.add(&Mesh::quad(), &Matrix4::from_scale(2.0));
+
+        Self {
+            shader_program,
+            quad: Model::from_mesh(backend, &quad.build()),
+            color: Vector3::new(0.3, 0.1, 0.45),
+        }
+    }
+}
+
+impl<F: TransitionFrame> RenderPass<F> for TransitionRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::PostProcess
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let fade = ctx.frame.fade();
+        if fade <= 0.0 {
+            return;
+        }
+
+        ctx.renderer.set_depth_test(false);
+        self.shader_program.enable();
+        self.shader_program.set_uniform_4f("u_Color", self.color.x, self.color.y, self.color.z, fade.min(1.0));
+        self.quad.bind();
+
+        ctx.renderer.draw_with(self.quad.va(), self.quad.ib(), &self.shader_program, self.quad.topology());
+
+        self.quad.unbind();
+        self.shader_program.disable();
+        ctx.renderer.set_depth_test(true);
+    }
+}
@@ -0,0 +1,210 @@
+//! Types to show the item the player holds on the HUD,
+//! whose icon is drawn each frame for items like the
+//! compass and the clock
+
+use crate::graphics::backend::GlBackend;
+use crate::graphics::font::{self, GLYPH_SIZE};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use crate::world::items;
+use cgmath::{InnerSpace, Vector2, Vector3, Vector4};
+use std::f32::consts::PI;
+
+/// The name of the compass, which points towards
+/// the waypoint of the player or the spawn
+pub const COMPASS: &str = "compass";
+/// The name of the clock, which shows the time of day
+pub const CLOCK: &str = "clock";
+/// The speed in radians per second the needle of the compass
+/// spins with, where it has nothing to point at
+pub const COMPASS_SPIN_SPEED: f32 = 6.0;
+
+/// The edge length of the slot of the held item
+const SLOT_SIZE: f32 = 40.0;
+/// The distance of the slot to the edges of the screen
+const SLOT_MARGIN: f32 = 8.0;
+/// The edge length of the dots drawn icons consist of
+const DOT_SIZE: f32 = 2.0;
+/// The vertical distance of the name to the slot
+const LABEL_GAP: f32 = 4.0;
+
+/// ItemIcon
+///
+/// The icon of the held item:
+/// * `Static` - The colored square of the item
+/// * `Compass` - A needle at the given angle in radians,
+///   clockwise from the direction the player looks in
+/// * `Clock` - A dial with the sun or the moon at the
+///   given time of day, as a `WorldTime` counts it
+#[derive(Clone, Debug, PartialEq)]
+pub enum ItemIcon {
+    Static,
+    Compass(f32),
+    Clock(f32),
+}
+
+/// HeldItem
+///
+/// The item the player holds, as it's shown on the HUD
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeldItem {
+    /// The name of the item
+    pub name: String,
+    /// The icon of the item
+    pub icon: ItemIcon,
+}
+
+/// Returns the angle in radians a compass needle points at,
+/// clockwise from the direction the player looks in
+///
+/// # Arguments
+///
+/// * `pos` - The position of the player
+/// * `look` - The direction the player looks in
+/// * `target` - The position the compass points towards
+pub fn compass_angle(pos: Vector3<f32>, look: Vector3<f32>, target: Vector3<f32>) -> f32 {
+    let forward = Vector2::new(look.x, look.z);
+    let to_target = Vector2::new(target.x - pos.x, target.z - pos.z);
+    if forward.magnitude2() == 0.0 || to_target.magnitude2() == 0.0 {
+        return 0.0;
+    }
+    // Right of the player is clockwise of the direction they look in
+    let right = Vector2::new(-forward.y, forward.x);
+    to_target.dot(right).atan2(to_target.dot(forward))
+}
+
+/// Returns the time a clock shows, where sunrise is at 06:00
+///
+/// # Arguments
+///
+/// * `time_of_day` - The time of day in the range `[0, 1)`
+pub fn clock_text(time_of_day: f32) -> String {
+    let minutes = ((time_of_day * 24.0 + 6.0) * 60.0).round() as u32 % (24 * 60);
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Adds a line of dots to an overlay
+///
+/// # Arguments
+///
+/// * `overlay` - The overlay
+/// * `from` - The start of the line
+/// * `to` - The end of the line
+/// * `color` - The color of the dots
+fn dotted_line(overlay: &mut OverlayBuilder, from: Vector2<f32>, to: Vector2<f32>, color: Vector4<f32>) {
+    let steps = ((to - from).magnitude() / (DOT_SIZE / 2.0)).ceil().max(1.0) as usize;
+    for i in 0..=steps {
+        let dot = from + (to - from) * (i as f32 / steps as f32);
+        overlay.rect([dot.x - DOT_SIZE / 2.0, dot.y - DOT_SIZE / 2.0], [dot.x + DOT_SIZE / 2.0, dot.y + DOT_SIZE / 2.0], color);
+    }
+}
+
+/// HeldItemFrame
+///
+/// A frame the held item could be rendered for
+pub trait HeldItemFrame: Frame {
+    /// Returns the item the player holds, if any
+    fn held_item(&self) -> Option<&HeldItem>;
+}
+
+/// HeldItemRenderer
+///
+/// The `HeldItemRenderer` is the render pass drawing the slot
+/// of the held item in the bottom right corner of the screen,
+/// with its name above. The icons of the compass and the
+/// clock are drawn from their current state.
+pub struct HeldItemRenderer {
+    /// The painter drawing the slot
+    painter: OverlayPainter,
+}
+
+impl HeldItemRenderer {
+    /// Creates a new held item renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+impl<F: HeldItemFrame> RenderPass<F> for HeldItemRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let item = match ctx.frame.held_item() {
+            Some(item) => item,
+            None => return,
+        };
+
+        let width = OVERLAY_HEIGHT * ctx.frame.camera().aspect_ratio();
+        let min = [width - SLOT_MARGIN - SLOT_SIZE, OVERLAY_HEIGHT - SLOT_MARGIN - SLOT_SIZE];
+        let max = [min[0] + SLOT_SIZE, min[1] + SLOT_SIZE];
+        let center = Vector2::new(min[0] + SLOT_SIZE / 2.0, min[1] + SLOT_SIZE / 2.0);
+        let radius = SLOT_SIZE / 2.0 - 5.0;
+
+        let mut overlay = OverlayBuilder::default();
+        overlay.rect(min, max, Vector4::new(0.0, 0.0, 0.0, 0.5));
+        let label = match item.icon {
+            ItemIcon::Static => {
+                overlay.rect([min[0] + 8.0, min[1] + 8.0], [max[0] - 8.0, max[1] - 8.0], items::item_color(&item.name));
+                item.name.clone()
+            }
+            ItemIcon::Compass(angle) => {
+                overlay.rect([min[0] + 3.0, min[1] + 3.0], [max[0] - 3.0, max[1] - 3.0], Vector4::new(0.75, 0.7, 0.6, 1.0));
+                // The top of the screen is ahead of the player
+                let direction = Vector2::new(angle.sin(), -angle.cos());
+                dotted_line(&mut overlay, center, center + direction * radius, Vector4::new(0.85, 0.1, 0.1, 1.0));
+                dotted_line(&mut overlay, center, center - direction * radius * 0.6, Vector4::new(0.95, 0.95, 0.95, 1.0));
+                item.name.clone()
+            }
+            ItemIcon::Clock(time_of_day) => {
+                // The upper half of the dial is the sky above the horizon
+                overlay.rect([min[0] + 3.0, min[1] + 3.0], [max[0] - 3.0, center.y], Vector4::new(0.35, 0.6, 0.9, 1.0));
+                overlay.rect([min[0] + 3.0, center.y], [max[0] - 3.0, max[1] - 3.0], Vector4::new(0.05, 0.07, 0.2, 1.0));
+                // The sun rises on the left and sets on the right,
+                // the moon is always on the opposite side
+                let angle = time_of_day * 2.0 * PI;
+                let sun = Vector2::new(-angle.cos(), -angle.sin()) * radius;
+                for (pos, color) in [(center + sun, Vector4::new(1.0, 0.85, 0.3, 1.0)), (center - sun, Vector4::new(0.9, 0.9, 0.95, 1.0))].iter() {
+                    overlay.rect([pos.x - 3.0, pos.y - 3.0], [pos.x + 3.0, pos.y + 3.0], *color);
+                }
+                clock_text(time_of_day)
+            }
+        };
+        // The name is aligned with the right edge of the slot
+        let label_x = max[0] - font::text_width(&label) as f32;
+        overlay.text([label_x, min[1] - LABEL_GAP - GLYPH_SIZE as f32], &label, Vector4::new(1.0, 1.0, 1.0, 1.0));
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compass_points_towards_target() {
+        let pos = Vector3::new(0.0, 60.0, 0.0);
+        let north = Vector3::new(0.0, 0.0, -1.0);
+        assert!(compass_angle(pos, north, Vector3::new(0.0, 0.0, -10.0)).abs() < 1e-5);
+        assert!((compass_angle(pos, north, Vector3::new(10.0, 0.0, 0.0)) - PI / 2.0).abs() < 1e-5);
+        assert!((compass_angle(pos, north, Vector3::new(-10.0, 0.0, 0.0)) + PI / 2.0).abs() < 1e-5);
+        assert!((compass_angle(pos, north, Vector3::new(0.0, 0.0, 10.0)).abs() - PI).abs() < 1e-5);
+    }
+
+    #[test]
+    fn clock_shows_time_of_day() {
+        assert_eq!(clock_text(0.0), "06:00");
+        assert_eq!(clock_text(0.25), "12:00");
+        assert_eq!(clock_text(0.75), "00:00");
+        assert_eq!(clock_text(0.9), "03:36");
+    }
+}
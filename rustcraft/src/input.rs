@@ -1,9 +1,15 @@
 //! Module handling the player's key and mouse input
 
 use crate::camera::PerspectiveCamera;
+use crate::entity::Controls;
 use crate::timestep::TimeStep;
-use glfw::{Key, Action, Window};
+use crate::world::vehicles::Steering;
+use glfw::{Key, Action, MouseButton, Window};
+use cgmath::{InnerSpace, Vector2, Zero};
 use cgmath::num_traits::FromPrimitive;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
 
 /// The default mouse speed
 const MOVE_SPEED: f32 = 4.0;
@@ -14,8 +20,596 @@ const MOUSE_SENSITIVITY: f32 = 0.25;
 /// The default zoom sensitivity
 const _ZOOM_SENSITIVITY: f32 = -3.0;
 
+/// The keys holding down the shortcuts, which aren't
+/// bound to the controls, e.g. Ctrl+Z
+const SHORTCUT_KEYS: [Key; 2] = [Key::LeftControl, Key::RightControl];
 
-pub fn handle_key_input(timestep: TimeStep, window: &Window, camera: &mut PerspectiveCamera) {
+/// The keys selecting the slots of the hotbar by default
+const HOTBAR_KEYS: [Key; 9] = [
+    Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+];
+
+/// The prefix of the names of shortcuts, e.g. `Ctrl+Z`
+const SHORTCUT_PREFIX: &str = "Ctrl+";
+
+/// The keys the controls could be bound to. They are named
+/// like GLFW names them, e.g. `W`, `LeftShift` or `Kp8`.
+const BINDABLE_KEYS: [Key; 90] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+    Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::Num0, Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+    Key::Kp0, Key::Kp1, Key::Kp2, Key::Kp3, Key::Kp4, Key::Kp5, Key::Kp6, Key::Kp7, Key::Kp8, Key::Kp9,
+    Key::Space, Key::Tab, Key::CapsLock, Key::Enter, Key::Backspace, Key::Insert, Key::Delete,
+    Key::Home, Key::End, Key::PageUp, Key::PageDown, Key::Up, Key::Down, Key::Left, Key::Right,
+    Key::LeftShift, Key::RightShift, Key::LeftAlt, Key::RightAlt, Key::LeftSuper, Key::RightSuper,
+    Key::Comma, Key::Period, Key::Minus, Key::Equal, Key::Semicolon, Key::Apostrophe,
+    Key::LeftBracket, Key::RightBracket, Key::Backslash, Key::GraveAccent, Key::Slash,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+];
+
+/// Binding
+///
+/// A key a control is bound to, which is either pressed on
+/// its own or as a shortcut while Ctrl is held, e.g. Ctrl+Z
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Binding {
+    /// The key
+    pub key: Key,
+    /// Whether Ctrl has to be held while the key is pressed
+    pub shortcut: bool,
+}
+
+impl Binding {
+    /// Creates a binding to a key pressed on its own
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key
+    pub const fn key(key: Key) -> Self {
+        Self { key, shortcut: false }
+    }
+
+    /// Creates a binding to a key pressed while Ctrl is held
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key
+    pub const fn shortcut(key: Key) -> Self {
+        Self { key, shortcut: true }
+    }
+
+    /// Returns the binding with a name, e.g. `LeftShift`
+    /// or `Ctrl+Z`, if controls could be bound to its key
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the key, like GLFW names it,
+    ///   prefixed with `Ctrl+` for shortcuts
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.strip_prefix(SHORTCUT_PREFIX) {
+            Some(key) => key_by_name(key).map(Self::shortcut),
+            None => key_by_name(name).map(Self::key),
+        }
+    }
+
+    /// Returns the name of the binding, see `from_name`
+    pub fn name(self) -> String {
+        if self.shortcut {
+            format!("{}{:?}", SHORTCUT_PREFIX, self.key)
+        } else {
+            format!("{:?}", self.key)
+        }
+    }
+}
+
+/// Control
+///
+/// A control of the player, which is either held down, e.g.
+/// to walk forward, or pressed once, e.g. to open the console.
+/// Each control is bound to a key, see `KeyMap`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Control {
+    /// Walks or flies in the direction the camera looks in
+    Forward,
+    /// Walks or flies backwards
+    Back,
+    /// Walks or flies to the left
+    Left,
+    /// Walks or flies to the right
+    Right,
+    /// Flies up with the free camera
+    Ascend,
+    /// Flies down with the free camera and gets off vehicles
+    Descend,
+    /// Jumps, swims or climbs up
+    Jump,
+    /// Sneaks, dives or climbs down
+    Sneak,
+    /// Sprints while walking
+    Sprint,
+    /// Shows the player list while it's held
+    PlayerList,
+    /// Interacts with the block the player looks at
+    Interact,
+    /// Detaches the camera from the player or attaches it again
+    Spectate,
+    /// Cycles through the tools of the player
+    CycleTool,
+    /// Opens the recipe book
+    RecipeBook,
+    /// Shows the details of the block the player looks at
+    InspectBlock,
+    /// Opens the console
+    Console,
+    /// Opens the key bindings menu
+    KeyBindings,
+    /// Selects a slot of the hotbar, from 1 to 9
+    Hotbar(u8),
+    /// Undoes the last edit of the player
+    Undo,
+    /// Redoes the last undone edit of the player
+    Redo,
+    /// Adds a keyframe to the camera path
+    AddKeyframe,
+    /// Removes all keyframes of the camera path
+    ClearCameraPath,
+    /// Flies along the camera path or stops flying along it
+    Flythrough,
+    /// Starts or stops recording a replay
+    Record,
+    /// Plays the last replay or stops playing it
+    Replay,
+    /// Pauses or resumes the replay
+    PauseReplay,
+    /// Skips the replay forward
+    ScrubReplay,
+    /// Backs up the world
+    Backup,
+    /// Shows or hides the light levels of the blocks
+    LightOverlay,
+    /// Shows or hides the capabilities of the graphics card
+    Capabilities,
+    /// Shows or hides the memory the world takes
+    MemoryPanel,
+    /// Shows the ticks per second of the simulation
+    ShowTps,
+    /// Draws the edges of the triangles instead of filling them
+    Wireframe,
+    /// Reloads the textures of the blocks
+    ReloadTextures,
+    /// Captures the next frame for debugging
+    Capture,
+    /// Cycles through the debug views of the renderer
+    DebugView,
+    /// Switches the window to fullscreen or back
+    Fullscreen,
+}
+
+/// All controls, in the order they're listed in
+pub const CONTROLS: [Control; 45] = [
+    Control::Forward, Control::Back, Control::Left, Control::Right, Control::Ascend,
+    Control::Descend, Control::Jump, Control::Sneak, Control::Sprint, Control::PlayerList,
+    Control::Interact, Control::Spectate, Control::CycleTool, Control::RecipeBook, Control::InspectBlock,
+    Control::Console, Control::KeyBindings,
+    Control::Hotbar(1), Control::Hotbar(2), Control::Hotbar(3), Control::Hotbar(4), Control::Hotbar(5),
+    Control::Hotbar(6), Control::Hotbar(7), Control::Hotbar(8), Control::Hotbar(9),
+    Control::Undo, Control::Redo, Control::AddKeyframe, Control::ClearCameraPath, Control::Flythrough,
+    Control::Record, Control::Replay, Control::PauseReplay, Control::ScrubReplay, Control::Backup,
+    Control::LightOverlay, Control::Capabilities, Control::MemoryPanel, Control::ShowTps,
+    Control::Wireframe, Control::ReloadTextures, Control::Capture, Control::DebugView, Control::Fullscreen,
+];
+
+impl Control {
+    /// Returns the key the control is bound to by default
+    pub fn default_binding(self) -> Binding {
+        match self {
+            Control::Forward => Binding::key(Key::W),
+            Control::Back => Binding::key(Key::S),
+            Control::Left => Binding::key(Key::A),
+            Control::Right => Binding::key(Key::D),
+            Control::Ascend => Binding::key(Key::Z),
+            Control::Descend => Binding::key(Key::Y),
+            Control::Jump => Binding::key(Key::Space),
+            Control::Sneak => Binding::key(Key::LeftShift),
+            Control::Sprint => Binding::key(Key::LeftAlt),
+            Control::PlayerList => Binding::key(Key::Tab),
+            Control::Interact => Binding::key(Key::E),
+            Control::Spectate => Binding::key(Key::C),
+            Control::CycleTool => Binding::key(Key::T),
+            Control::RecipeBook => Binding::key(Key::R),
+            Control::InspectBlock => Binding::key(Key::I),
+            Control::Console => Binding::key(Key::Slash),
+            Control::KeyBindings => Binding::key(Key::F1),
+            Control::Hotbar(slot) => Binding::key(HOTBAR_KEYS[usize::from(slot) - 1]),
+            Control::Undo => Binding::shortcut(Key::Z),
+            Control::Redo => Binding::shortcut(Key::Y),
+            Control::AddKeyframe => Binding::key(Key::K),
+            Control::ClearCameraPath => Binding::shortcut(Key::K),
+            Control::Flythrough => Binding::key(Key::L),
+            Control::Record => Binding::key(Key::F9),
+            Control::Replay => Binding::key(Key::F10),
+            Control::PauseReplay => Binding::key(Key::P),
+            Control::ScrubReplay => Binding::key(Key::Right),
+            Control::Backup => Binding::key(Key::F6),
+            Control::LightOverlay => Binding::shortcut(Key::L),
+            Control::Capabilities => Binding::key(Key::F2),
+            Control::MemoryPanel => Binding::key(Key::F3),
+            Control::ShowTps => Binding::key(Key::F4),
+            Control::Wireframe => Binding::key(Key::F5),
+            Control::ReloadTextures => Binding::key(Key::F7),
+            Control::Capture => Binding::key(Key::F8),
+            Control::DebugView => Binding::key(Key::F11),
+            Control::Fullscreen => Binding::key(Key::F12),
+        }
+    }
+
+    /// Returns whether the control is held down rather than
+    /// pressed once. Held controls are polled each frame, so
+    /// they can't be bound to shortcuts.
+    pub fn is_held(self) -> bool {
+        matches!(self,
+            Control::Forward | Control::Back | Control::Left | Control::Right | Control::Ascend
+            | Control::Descend | Control::Jump | Control::Sneak | Control::Sprint | Control::PlayerList)
+    }
+
+    /// Returns the name of the control in the configuration,
+    /// e.g. `player_list` or `hotbar_1`
+    pub fn name(self) -> String {
+        match self {
+            Control::Hotbar(slot) => format!("hotbar_{}", slot),
+            control => {
+                let mut name = String::new();
+                for c in format!("{:?}", control).chars() {
+                    if c.is_uppercase() && !name.is_empty() {
+                        name.push('_');
+                    }
+                    name.push(c.to_ascii_lowercase());
+                }
+                name
+            }
+        }
+    }
+
+    /// Returns what the control does, as it's listed in
+    /// the key bindings menu
+    pub fn description(self) -> String {
+        let description = match self {
+            Control::Forward => "Walk forward",
+            Control::Back => "Walk backwards",
+            Control::Left => "Walk to the left",
+            Control::Right => "Walk to the right",
+            Control::Ascend => "Fly up",
+            Control::Descend => "Fly down or get off",
+            Control::Jump => "Jump, swim or climb up",
+            Control::Sneak => "Sneak, dive or climb down",
+            Control::Sprint => "Sprint",
+            Control::PlayerList => "Show the player list",
+            Control::Interact => "Interact with the block",
+            Control::Spectate => "Spectate",
+            Control::CycleTool => "Cycle the tools",
+            Control::RecipeBook => "Open the recipe book",
+            Control::InspectBlock => "Inspect the block",
+            Control::Console => "Open the console",
+            Control::KeyBindings => "Open the key bindings",
+            Control::Hotbar(slot) => return format!("Select hotbar slot {}", slot),
+            Control::Undo => "Undo",
+            Control::Redo => "Redo",
+            Control::AddKeyframe => "Add a camera keyframe",
+            Control::ClearCameraPath => "Clear the camera path",
+            Control::Flythrough => "Fly along the camera path",
+            Control::Record => "Record a replay",
+            Control::Replay => "Play the replay",
+            Control::PauseReplay => "Pause the replay",
+            Control::ScrubReplay => "Skip the replay forward",
+            Control::Backup => "Back up the world",
+            Control::LightOverlay => "Show the light levels",
+            Control::Capabilities => "Show the graphics capabilities",
+            Control::MemoryPanel => "Show the memory usage",
+            Control::ShowTps => "Show the ticks per second",
+            Control::Wireframe => "Toggle wireframes",
+            Control::ReloadTextures => "Reload the textures",
+            Control::Capture => "Capture a frame",
+            Control::DebugView => "Cycle the debug views",
+            Control::Fullscreen => "Toggle fullscreen",
+        };
+        description.to_string()
+    }
+
+    /// Returns the control with a name, see `name`
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the control
+    pub fn from_name(name: &str) -> Option<Self> {
+        CONTROLS.iter().copied().find(|control| control.name() == name)
+    }
+}
+
+/// Returns the key with a name, e.g. `LeftShift`, if
+/// controls could be bound to it
+///
+/// # Arguments
+///
+/// * `name` - The name of the key, like GLFW names it
+pub fn key_by_name(name: &str) -> Option<Key> {
+    BINDABLE_KEYS.iter().copied().find(|key| format!("{:?}", key) == name)
+}
+
+/// Bound
+///
+/// What a key is bound to in the `KeyMap`
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Bound {
+    /// A built-in control
+    Control(Control),
+    /// An action a script registered, by its name
+    Action(String),
+}
+
+/// The key map shared by the render thread, which triggers the
+/// controls, and the simulation, which triggers the actions of
+/// scripts and rebinds both in the key bindings menu
+pub type SharedKeyMap = Arc<RwLock<KeyMap>>;
+
+/// KeyMap
+///
+/// The keys the built-in controls and the actions of
+/// scripts are bound to, in a single table so that keys
+/// bound twice are found and shown to the player
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyMap {
+    /// The binding of each control
+    bindings: HashMap<Control, Binding>,
+    /// The binding of each action of the scripts by its name
+    actions: BTreeMap<String, Binding>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self {
+            bindings: CONTROLS.iter().map(|&control| (control, control.default_binding())).collect(),
+            actions: BTreeMap::new(),
+        }
+    }
+}
+
+impl KeyMap {
+    /// Returns the key a control is bound to
+    ///
+    /// # Arguments
+    ///
+    /// * `control` - The control
+    pub fn key(&self, control: Control) -> Key {
+        self.bindings[&control].key
+    }
+
+    /// Returns the binding of a control
+    ///
+    /// # Arguments
+    ///
+    /// * `control` - The control
+    pub fn binding(&self, control: Control) -> Binding {
+        self.bindings[&control]
+    }
+
+    /// Binds a control to a key, see `conflicts`
+    ///
+    /// # Arguments
+    ///
+    /// * `control` - The control
+    /// * `binding` - The key
+    pub fn bind(&mut self, control: Control, binding: Binding) {
+        self.bindings.insert(control, binding);
+    }
+
+    /// Returns whether any control is bound to a key, so
+    /// it's polled each frame and forwarded to the simulation
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key
+    pub fn binds(&self, key: Key) -> bool {
+        self.bindings.values().any(|bound| bound.key == key) || SHORTCUT_KEYS.contains(&key)
+    }
+
+    /// Returns the control pressed once with a key, which is
+    /// the first listed one if several controls share it
+    ///
+    /// # Arguments
+    ///
+    /// * `binding` - The pressed key
+    pub fn control(&self, binding: Binding) -> Option<Control> {
+        CONTROLS.iter().copied().find(|&control| !control.is_held() && self.bindings[&control] == binding)
+    }
+
+    /// Binds an action of a script to a key, or leaves it
+    /// unbound, see `conflicts`
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the action
+    /// * `binding` - The key, if any
+    pub fn bind_action(&mut self, name: &str, binding: Option<Binding>) {
+        match binding {
+            Some(binding) => { self.actions.insert(name.to_string(), binding); }
+            None => { self.actions.remove(name); }
+        }
+    }
+
+    /// Returns the action of a script pressed with a key. Keys
+    /// bound to a control never trigger actions, and of several
+    /// actions sharing a key only the first by name is triggered.
+    ///
+    /// # Arguments
+    ///
+    /// * `binding` - The pressed key
+    pub fn action(&self, binding: Binding) -> Option<&str> {
+        if self.bindings.values().any(|&bound| bound == binding) {
+            return None;
+        }
+        self.actions.iter()
+            .find(|(_, &bound)| bound == binding)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the pairs of controls and actions bound to the
+    /// same key, in the order they're triggered. Held controls
+    /// could share a key, e.g. to sneak while sprinting, but a
+    /// key pressed once only triggers one control or action.
+    pub fn conflicts(&self) -> Vec<(Bound, Bound)> {
+        let held = |bound: &Bound| matches!(bound, Bound::Control(control) if control.is_held());
+        let entries: Vec<(Bound, Binding)> = CONTROLS.iter()
+            .map(|&control| (Bound::Control(control), self.bindings[&control]))
+            .chain(self.actions.iter().map(|(name, &binding)| (Bound::Action(name.clone()), binding)))
+            .collect();
+
+        let mut conflicts = Vec::new();
+        for (i, (first, binding)) in entries.iter().enumerate() {
+            for (second, other) in entries[i + 1..].iter() {
+                if binding == other && !(held(first) && held(second)) {
+                    conflicts.push((first.clone(), second.clone()));
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Returns the first other control or action bound to
+    /// the same key as a control or action, if any
+    ///
+    /// # Arguments
+    ///
+    /// * `bound` - The control or action
+    pub fn conflict(&self, bound: &Bound) -> Option<Bound> {
+        self.conflicts().into_iter().find_map(|(first, second)| {
+            if &first == bound {
+                Some(second)
+            } else if &second == bound {
+                Some(first)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// InputState
+///
+/// An `InputState` is a snapshot of the player's
+/// input. It is captured on the render thread, which
+/// owns the window, and applied on the simulation thread.
+#[derive(Clone, Default, Debug)]
+pub struct InputState {
+    /// The controls whose keys are currently pressed
+    pressed: HashSet<Control>,
+    /// Whether either key holding down the shortcuts is pressed
+    shortcut: bool,
+    /// The mouse movement since the last snapshot
+    mouse_delta: (f32, f32),
+    /// Whether the mouse button breaking blocks is held
+    breaking: bool,
+    /// When the snapshot has been captured, which is
+    /// `None` for snapshots which weren't captured
+    captured: Option<Instant>,
+}
+
+impl InputState {
+    /// Captures the current input state of the given window.
+    /// The cursor is moved back to the center of the window
+    /// afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The window the input should be captured from
+    /// * `key_map` - The keys the controls are bound to
+    pub fn capture(window: &mut Window, key_map: &KeyMap) -> Self {
+        let pressed = CONTROLS.iter()
+            .filter(|&&control| control.is_held() && window.get_key(key_map.key(control)) == Action::Press)
+            .cloned()
+            .collect();
+        let shortcut = SHORTCUT_KEYS.iter().any(|&key| window.get_key(key) == Action::Press);
+
+        let (width, height) = window.get_size();
+        let (mouse_x, mouse_y) = window.get_cursor_pos();
+        let mouse_delta = (
+            f32::from(width as i16) / 2.0 - f32::from_f64(mouse_x).unwrap(),
+            f32::from(height as i16) / 2.0 - f32::from_f64(mouse_y).unwrap(),
+        );
+        window.set_cursor_pos( width as f64 / 2.0, height as f64 / 2.0);
+
+        Self {
+            pressed,
+            shortcut,
+            mouse_delta,
+            breaking: window.get_mouse_button(MouseButton::Button1) == Action::Press,
+            captured: Some(Instant::now()),
+        }
+    }
+
+    /// Merges a newer snapshot into this one. The pressed controls
+    /// and buttons are replaced while mouse movements are
+    /// accumulated.
+    ///
+    /// # Arguments
+    ///
+    /// * `newer` - The newer input snapshot
+    pub fn merge(&mut self, newer: InputState) {
+        self.pressed = newer.pressed;
+        self.shortcut = newer.shortcut;
+        self.breaking = newer.breaking;
+        self.captured = newer.captured.or(self.captured);
+        self.mouse_delta.0 += newer.mouse_delta.0;
+        self.mouse_delta.1 += newer.mouse_delta.1;
+    }
+
+    /// Resets the accumulated mouse movement
+    pub fn reset_mouse_delta(&mut self) {
+        self.mouse_delta = (0.0, 0.0);
+    }
+
+    /// Returns whether the key of the given control is pressed
+    ///
+    /// # Arguments
+    ///
+    /// * `control` - The control which should be checked
+    pub fn is_pressed(&self, control: Control) -> bool {
+        self.pressed.contains(&control)
+    }
+
+    /// Returns whether either control key is pressed
+    pub fn is_control_pressed(&self) -> bool {
+        self.shortcut
+    }
+
+    /// Returns whether the player holds the key showing
+    /// the player list
+    pub fn is_player_list_shown(&self) -> bool {
+        self.is_pressed(Control::PlayerList)
+    }
+
+    /// Returns when the snapshot has been captured
+    pub fn captured(&self) -> Option<Instant> {
+        self.captured
+    }
+
+    /// Returns the mouse movement since the last snapshot
+    pub fn mouse_delta(&self) -> (f32, f32) {
+        self.mouse_delta
+    }
+
+    /// Returns whether the player holds the mouse
+    /// button breaking blocks
+    pub fn is_breaking(&self) -> bool {
+        self.breaking
+    }
+}
+
+pub fn handle_key_input(timestep: TimeStep, input: &InputState, camera: &mut PerspectiveCamera) {
+    // Control is used for shortcuts like Ctrl+Z, which
+    // would otherwise move the camera as well
+    if input.is_control_pressed() {
+        return;
+    }
 
     // Camera Movement
     let look = camera.look();
@@ -23,34 +617,104 @@ pub fn handle_key_input(timestep: TimeStep, window: &Window, camera: &mut Perspe
     let up = camera.up();
 
     // Forward / Backward
-    if window.get_key(Key::W) == Action::Press {
+    if input.is_pressed(Control::Forward) {
         camera.set_offset(MOVE_SPEED * timestep.seconds() * look);
-    } else if window.get_key(Key::S) == Action::Press {
+    } else if input.is_pressed(Control::Back) {
         camera.set_offset(MOVE_SPEED * timestep.seconds() * -look);
     }
 
     // LEFT / RIGHT
-    if window.get_key(Key::A) == Action::Press {
+    if input.is_pressed(Control::Left) {
         camera.set_offset(MOVE_SPEED * timestep.seconds() * -right);
-    } else if window.get_key(Key::D) == Action::Press {
+    } else if input.is_pressed(Control::Right) {
         camera.set_offset(MOVE_SPEED * timestep.seconds() * right);
     }
 
     // Up / Down
-    if window.get_key(Key::Z) == Action::Press {
+    if input.is_pressed(Control::Ascend) {
         camera.set_offset(MOVE_SPEED * timestep.seconds() * up);
-    } else if window.get_key(Key::Y) == Action::Press {
+    } else if input.is_pressed(Control::Descend) {
         camera.set_offset(MOVE_SPEED * timestep.seconds() * -up);
     }
 }
 
-pub fn handle_mouse_input(window: &mut Window, camera: &mut PerspectiveCamera) {
-    let (width, height) = window.get_size();
-    let (mouse_x, mouse_y) = window.get_cursor_pos();
+/// Returns how the player wants to move their body. They
+/// walk relative to the direction the camera looks in, jump,
+/// swim or climb up with the jump control, sneak, dive or
+/// climb down with the sneak control and sprint, see `Control`.
+///
+/// # Arguments
+///
+/// * `input` - The input of the player
+/// * `camera` - The camera attached to the player
+pub fn controls(input: &InputState, camera: &PerspectiveCamera) -> Controls {
+    if input.is_control_pressed() {
+        return Controls::default();
+    }
+    let (look, right) = (camera.look(), camera.right());
+    let forward = Vector2::new(look.x, look.z);
+    let right = Vector2::new(right.x, right.z);
+
+    let mut walk = Vector2::zero();
+    if input.is_pressed(Control::Forward) {
+        walk += forward;
+    } else if input.is_pressed(Control::Back) {
+        walk -= forward;
+    }
+    if input.is_pressed(Control::Left) {
+        walk -= right;
+    } else if input.is_pressed(Control::Right) {
+        walk += right;
+    }
+    Controls {
+        walk: if walk.magnitude2() > 0.0 { walk.normalize() } else { walk },
+        up: input.is_pressed(Control::Jump),
+        down: input.is_pressed(Control::Sneak),
+        sneak: input.is_pressed(Control::Sneak),
+        sprint: input.is_pressed(Control::Sprint),
+    }
+}
+
+/// Returns how the player steers the vehicle they ride,
+/// where the keys moving the camera steer instead
+///
+/// # Arguments
+///
+/// * `input` - The input of the player
+pub fn steering(input: &InputState) -> Steering {
+    if input.is_control_pressed() {
+        return Steering::default();
+    }
+    let axis = |positive: Control, negative: Control| {
+        if input.is_pressed(positive) {
+            1.0
+        } else if input.is_pressed(negative) {
+            -1.0
+        } else {
+            0.0
+        }
+    };
+    Steering {
+        forward: axis(Control::Forward, Control::Back),
+        turn: axis(Control::Right, Control::Left),
+    }
+}
+
+/// Returns whether the player wants to get off the
+/// vehicle they ride, which they do by descending
+///
+/// # Arguments
+///
+/// * `input` - The input of the player
+pub fn is_dismounting(input: &InputState) -> bool {
+    !input.is_control_pressed() && input.is_pressed(Control::Descend)
+}
+
+pub fn handle_mouse_input(input: &InputState, camera: &mut PerspectiveCamera) {
+    let (delta_x, delta_y) = input.mouse_delta();
     camera.rotate(
-        (f32::from(width as i16) / 2.0 - f32::from_f64(mouse_x).unwrap()) * MOUSE_SENSITIVITY,
-        (f32::from(height as i16) / 2.0 - f32::from_f64(mouse_y).unwrap()) * MOUSE_SENSITIVITY,
+        delta_x * MOUSE_SENSITIVITY,
+        delta_y * MOUSE_SENSITIVITY,
         0.0
     );
-    window.set_cursor_pos( width as f64 / 2.0, height as f64 / 2.0);
 }
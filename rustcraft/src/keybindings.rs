@@ -0,0 +1,556 @@
+//! Types to let scripts register actions triggered by keys,
+//! which the player could bind to other keys along with the
+//! built-in controls
+
+use crate::graphics::backend::GlBackend;
+use crate::graphics::font::{self, GLYPH_SIZE};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::input::{Binding, Bound, Control, KeyMap, SharedKeyMap, CONTROLS};
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use crate::scripting::{self, ScriptEngine, ScriptError};
+use cgmath::Vector4;
+use mlua::{Function, RegistryKey, Table};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// The amount of actions shown at once, the list
+/// scrolls with the selected one
+const VISIBLE_ACTIONS: usize = 10;
+
+/// The distance of the menu to the top edge of the screen
+const MENU_MARGIN: f32 = 32.0;
+/// The distance of the text to the edges of the menu
+const MENU_PADDING: f32 = 8.0;
+/// The smallest width of the content of the menu
+const MIN_CONTENT_WIDTH: f32 = 160.0;
+/// The vertical distance of two lines of text
+const LINE_SPACING: f32 = 6.0;
+/// The horizontal distance of the key to the description
+const KEY_GAP: f32 = 12.0;
+
+/// A key bound by the player, by the name of the action
+pub type KeyOverrides = BTreeMap<String, String>;
+
+/// Action
+///
+/// An action a script registered
+struct Action {
+    /// The unique name of the action, e.g. `minimap:toggle`
+    name: String,
+    /// The description shown in the key bindings menu
+    description: String,
+    /// The key the action is bound to, unless the
+    /// player bound it to another one
+    default_key: String,
+    /// The callback called when the key is pressed
+    callback: RegistryKey,
+}
+
+/// KeyBinding
+///
+/// A control or an action with the key it's bound to
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBinding {
+    /// The name of the control or action
+    pub name: String,
+    /// The description of the control or action
+    pub description: String,
+    /// The key the control or action is bound to
+    pub key: String,
+    /// The description of another control or action
+    /// bound to the same key, if any
+    pub conflict: Option<String>,
+}
+
+/// Actions
+///
+/// The actions scripts registered through the `keybindings`
+/// API, e.g.
+///
+/// ```lua
+/// keybindings.register {
+///     name = "minimap:toggle",
+///     description = "Toggle the minimap",
+///     key = "M",
+///     callback = function(player)
+///         minimap_shown = not minimap_shown
+///     end,
+/// }
+/// local key = keybindings.key("minimap:toggle")
+/// ```
+///
+/// Keys are named like GLFW names them, e.g. `M`, `Num1` or
+/// `F11`, and prefixed with `Ctrl+` for shortcuts. The actions
+/// are bound in the same key map as the built-in controls. A
+/// key bound to both triggers the control, and the key bindings
+/// menu shows the conflict so the player could rebind either.
+/// An action registered again replaces the previous one.
+pub struct Actions {
+    /// The actions in the order they've been registered,
+    /// shared with the Lua API
+    actions: Arc<Mutex<Vec<Action>>>,
+    /// The keys bound by the player, shared with the Lua API
+    overrides: Arc<Mutex<KeyOverrides>>,
+    /// The keys of the controls and actions, shared with
+    /// the Lua API and the render thread
+    key_map: SharedKeyMap,
+}
+
+impl Actions {
+    /// Creates a new action registry and registers its API
+    /// in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `key_map` - The key map the actions are bound in
+    pub fn new(engine: &ScriptEngine, key_map: SharedKeyMap) -> mlua::Result<Self> {
+        let actions = Arc::new(Mutex::new(Vec::<Action>::new()));
+        let overrides = Arc::new(Mutex::new(KeyOverrides::new()));
+
+        let lua = engine.lua();
+        let api = scripting::api_table(lua, "keybindings")?;
+        let shared = actions.clone();
+        let bound = overrides.clone();
+        let keys = key_map.clone();
+        api.set("register", lua.create_function(move |lua, def: Table| {
+            let action = Action {
+                name: def.get("name")?,
+                description: def.get::<_, Option<String>>("description")?.unwrap_or_default(),
+                default_key: def.get("key")?,
+                callback: lua.create_registry_value(def.get::<_, Function>("callback")?)?,
+            };
+            let key = bound.lock().unwrap().get(&action.name).unwrap_or(&action.default_key).clone();
+            bind_action(&mut keys.write().unwrap(), &action.name, &key);
+            let mut actions = shared.lock().unwrap();
+            match actions.iter_mut().find(|existing| existing.name == action.name) {
+                Some(existing) => *existing = action,
+                None => actions.push(action),
+            }
+            Ok(())
+        })?)?;
+        let shared = actions.clone();
+        let bound = overrides.clone();
+        api.set("key", lua.create_function(move |_, name: String| {
+            Ok(shared.lock().unwrap().iter()
+                .find(|action| action.name == name)
+                .map(|action| bound.lock().unwrap().get(&name).unwrap_or(&action.default_key).clone()))
+        })?)?;
+
+        Ok(Self { actions, overrides, key_map })
+    }
+
+    /// Returns the key map the actions are bound in
+    pub fn key_map(&self) -> SharedKeyMap {
+        self.key_map.clone()
+    }
+
+    /// Returns the keys bound by the player
+    pub fn overrides(&self) -> KeyOverrides {
+        self.overrides.lock().unwrap().clone()
+    }
+
+    /// Replaces the keys bound by the player, e.g.
+    /// with the ones loaded from the player data
+    ///
+    /// # Arguments
+    ///
+    /// * `overrides` - The keys bound by the player
+    pub fn set_overrides(&self, overrides: KeyOverrides) {
+        let actions = self.actions.lock().unwrap();
+        let mut key_map = self.key_map.write().unwrap();
+        for action in actions.iter() {
+            bind_action(&mut key_map, &action.name, overrides.get(&action.name).unwrap_or(&action.default_key));
+        }
+        *self.overrides.lock().unwrap() = overrides;
+    }
+
+    /// Returns the built-in controls followed by the registered
+    /// actions, with the keys they are bound to
+    pub fn bindings(&self) -> Vec<KeyBinding> {
+        let actions = self.actions.lock().unwrap();
+        let overrides = self.overrides.lock().unwrap();
+        let key_map = self.key_map.read().unwrap();
+        let describe = |bound: Bound| match bound {
+            Bound::Control(control) => control.description(),
+            Bound::Action(name) => actions.iter()
+                .find(|action| action.name == name && !action.description.is_empty())
+                .map_or(name, |action| action.description.clone()),
+        };
+
+        let controls = CONTROLS.iter().map(|&control| KeyBinding {
+            name: control.name(),
+            description: control.description(),
+            key: key_map.binding(control).name(),
+            conflict: key_map.conflict(&Bound::Control(control)).map(describe),
+        });
+        let scripted = actions.iter().map(|action| KeyBinding {
+            name: action.name.clone(),
+            description: action.description.clone(),
+            key: overrides.get(&action.name).unwrap_or(&action.default_key).clone(),
+            conflict: key_map.conflict(&Bound::Action(action.name.clone())).map(describe),
+        });
+        controls.chain(scripted).collect()
+    }
+
+    /// Binds a control or an action to a key. Keys bound to
+    /// actions are forgotten again once they're the default key
+    /// of the action. Unknown keys, and shortcuts for held
+    /// controls, are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the control or action
+    /// * `key` - The name of the key
+    pub fn bind(&self, name: &str, key: &str) {
+        let binding = match Binding::from_name(key) {
+            Some(binding) => binding,
+            None => return,
+        };
+        if let Some(control) = Control::from_name(name) {
+            if !(binding.shortcut && control.is_held()) {
+                self.key_map.write().unwrap().bind(control, binding);
+            }
+            return;
+        }
+
+        let actions = self.actions.lock().unwrap();
+        let mut overrides = self.overrides.lock().unwrap();
+        match actions.iter().find(|action| action.name == name) {
+            Some(action) if action.default_key == key => { overrides.remove(name); }
+            Some(_) => { overrides.insert(name.to_string(), key.to_string()); }
+            None => return,
+        }
+        self.key_map.write().unwrap().bind_action(name, Some(binding));
+    }
+
+    /// Calls the callback of the action a key triggers, see
+    /// `KeyMap::action`, and returns the errors of failing
+    /// callbacks
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine the callbacks are registered in
+    /// * `key` - The name of the pressed key
+    /// * `player` - The name of the player pressing the key
+    pub fn press(&self, engine: &ScriptEngine, key: &str, player: &str) -> Vec<ScriptError> {
+        let lua = engine.lua();
+        let name = match Binding::from_name(key).and_then(|binding| self.key_map.read().unwrap().action(binding).map(str::to_string)) {
+            Some(name) => name,
+            None => return Vec::new(),
+        };
+
+        // The callback is taken out of the registry first,
+        // so that it could register actions itself
+        let callbacks: Vec<(String, mlua::Result<Function>)> = self.actions.lock().unwrap().iter()
+            .filter(|action| action.name == name)
+            .map(|action| (action.name.clone(), lua.registry_value(&action.callback)))
+            .collect();
+
+        callbacks.into_iter()
+            .filter_map(|(name, callback)| {
+                callback.and_then(|callback| callback.call::<_, ()>(player))
+                    .err()
+                    .map(|error| ScriptError::new(&format!("action {}", name), error))
+            })
+            .collect()
+    }
+}
+
+/// Binds an action in a key map to the key with a name,
+/// or leaves it unbound if the key is unknown
+///
+/// # Arguments
+///
+/// * `key_map` - The key map
+/// * `name` - The name of the action
+/// * `key` - The name of the key
+fn bind_action(key_map: &mut KeyMap, name: &str, key: &str) {
+    let binding = Binding::from_name(key);
+    if binding.is_none() {
+        log::warn!("Can't bind the action {} to the unknown key {}", name, key);
+    }
+    key_map.bind_action(name, binding);
+}
+
+/// KeyBindingsView
+///
+/// The key bindings menu as it's shown on the screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBindingsView {
+    /// The actions with their keys
+    pub bindings: Vec<KeyBinding>,
+    /// The index of the selected action
+    pub selected: usize,
+    /// Whether the next pressed key is bound
+    /// to the selected action
+    pub rebinding: bool,
+}
+
+/// KeyBindingsMenu
+///
+/// The state of the menu listing the built-in controls and
+/// the actions of scripts. The arrow keys select one of them
+/// and Enter waits for the key it's bound to next.
+#[derive(Debug, Default)]
+pub struct KeyBindingsMenu {
+    /// Whether the menu is open
+    open: bool,
+    /// The index of the selected action
+    selected: usize,
+    /// Whether the next pressed key is bound
+    /// to the selected action
+    rebinding: bool,
+}
+
+impl KeyBindingsMenu {
+    /// Returns whether the menu is open
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Opens or closes the menu
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.selected = 0;
+        self.rebinding = false;
+    }
+
+    /// Moves the selection by a number of actions, which
+    /// stops at the first and the last one
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - The number of actions to move by
+    /// * `count` - The amount of actions
+    pub fn select(&mut self, delta: i32, count: usize) {
+        if !self.rebinding {
+            self.selected = (self.selected as i32 + delta).min(count as i32 - 1).max(0) as usize;
+        }
+    }
+
+    /// Waits for the key the selected action is bound
+    /// to, or stops waiting for it
+    pub fn toggle_rebinding(&mut self) {
+        self.rebinding = self.open && !self.rebinding;
+    }
+
+    /// Returns the name of the action the pressed key should
+    /// be bound to, if the menu waits for a key, and stops
+    /// waiting for it
+    ///
+    /// # Arguments
+    ///
+    /// * `bindings` - The actions with their keys
+    pub fn take_rebinding<'a>(&mut self, bindings: &'a [KeyBinding]) -> Option<&'a str> {
+        if !std::mem::take(&mut self.rebinding) {
+            return None;
+        }
+        bindings.get(self.selected).map(|binding| binding.name.as_str())
+    }
+
+    /// Returns the menu as it's shown, or `None` if it's closed
+    ///
+    /// # Arguments
+    ///
+    /// * `bindings` - The actions with their keys
+    pub fn view(&self, bindings: Vec<KeyBinding>) -> Option<KeyBindingsView> {
+        if !self.open {
+            return None;
+        }
+        Some(KeyBindingsView {
+            bindings,
+            selected: self.selected,
+            rebinding: self.rebinding,
+        })
+    }
+}
+
+/// KeyBindingsFrame
+///
+/// A frame the key bindings menu could be rendered for
+pub trait KeyBindingsFrame: Frame {
+    /// Returns the key bindings menu, if it's open
+    fn key_bindings(&self) -> Option<&KeyBindingsView>;
+}
+
+/// KeyBindingsRenderer
+///
+/// The `KeyBindingsRenderer` is the render pass drawing the
+/// open key bindings menu centered at the top of the screen,
+/// with a row per control and action showing its key and
+/// description. Keys bound twice are highlighted along with
+/// the other control or action bound to them.
+pub struct KeyBindingsRenderer {
+    /// The painter drawing the menu
+    painter: OverlayPainter,
+}
+
+impl KeyBindingsRenderer {
+    /// Creates a new key bindings renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+impl<F: KeyBindingsFrame> RenderPass<F> for KeyBindingsRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let menu = match ctx.frame.key_bindings() {
+            Some(menu) => menu,
+            None => return,
+        };
+
+        let title = "Key bindings";
+        let first = (menu.selected + 1).saturating_sub(VISIBLE_ACTIONS);
+        let rows: Vec<(usize, String, String, bool)> = menu.bindings.iter()
+            .enumerate()
+            .skip(first)
+            .take(VISIBLE_ACTIONS)
+            .map(|(i, binding)| {
+                let key = if menu.rebinding && i == menu.selected { "...".to_string() } else { format!("[{}]", binding.key) };
+                let description = if binding.description.is_empty() { &binding.name } else { &binding.description };
+                match &binding.conflict {
+                    Some(other) => (i, key, format!("{} (also {})", description, other), true),
+                    None => (i, key, description.clone(), false),
+                }
+            })
+            .collect();
+
+        let key_width = rows.iter().map(|(_, key, _, _)| font::text_width(key) as f32).fold(0.0, f32::max);
+        let content_width = rows.iter()
+            .map(|(_, _, description, _)| key_width + KEY_GAP + font::text_width(description) as f32)
+            .fold(font::text_width(title) as f32, f32::max)
+            .max(MIN_CONTENT_WIDTH);
+        let line_height = GLYPH_SIZE as f32 + LINE_SPACING;
+        let lines = 1 + rows.len().max(1);
+        let height = lines as f32 * line_height - LINE_SPACING + 3.0 * MENU_PADDING;
+        let width = OVERLAY_HEIGHT * ctx.frame.camera().aspect_ratio();
+        let min = [(width - content_width) / 2.0 - MENU_PADDING, MENU_MARGIN];
+        let max_x = min[0] + content_width + 2.0 * MENU_PADDING;
+
+        let mut overlay = OverlayBuilder::default();
+        overlay.rect(min, [max_x, min[1] + height], Vector4::new(0.0, 0.0, 0.0, 0.65));
+        let x = min[0] + MENU_PADDING;
+        let mut y = min[1] + MENU_PADDING;
+        overlay.text([x, y], title, Vector4::new(1.0, 0.85, 0.3, 1.0));
+        y += line_height + MENU_PADDING;
+
+        for (i, key, description, conflict) in rows.iter() {
+            if *i == menu.selected {
+                overlay.rect(
+                    [x - 2.0, y - LINE_SPACING / 2.0],
+                    [max_x - MENU_PADDING + 2.0, y + GLYPH_SIZE as f32 + LINE_SPACING / 2.0],
+                    Vector4::new(0.55, 0.8, 1.0, 0.3),
+                );
+            }
+            let key_color = if *conflict { Vector4::new(1.0, 0.35, 0.3, 1.0) } else { Vector4::new(0.55, 0.8, 1.0, 1.0) };
+            overlay.text([x, y], key, key_color);
+            overlay.text([x + key_width + KEY_GAP, y], description, Vector4::new(1.0, 1.0, 1.0, 1.0));
+            y += line_height;
+        }
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pressed_keys_trigger_their_actions() {
+        let engine = ScriptEngine::new();
+        let actions = Actions::new(&engine, SharedKeyMap::default()).unwrap();
+        engine.exec("test", r#"
+            presses = {}
+            keybindings.register {
+                name = "minimap:toggle",
+                description = "Toggle the minimap",
+                key = "M",
+                callback = function(player) table.insert(presses, player) end,
+            }
+            keybindings.register { name = "broken", key = "B", callback = function() error("oops") end }
+        "#).unwrap();
+
+        assert!(actions.press(&engine, "M", "Player").is_empty());
+        assert_eq!(actions.press(&engine, "B", "Player").len(), 1);
+
+        // The action is only triggered by the key it's bound to
+        actions.bind("minimap:toggle", "N");
+        assert!(actions.press(&engine, "M", "Player").is_empty());
+        assert!(actions.press(&engine, "N", "Other").is_empty());
+        let presses: Vec<String> = engine.lua().globals().get("presses").unwrap();
+        assert_eq!(presses, vec!["Player", "Other"]);
+        assert_eq!(actions.bindings()[CONTROLS.len()].key, "N");
+        engine.exec("test", r#"assert(keybindings.key("minimap:toggle") == "N")"#).unwrap();
+
+        actions.bind("minimap:toggle", "M");
+        assert!(actions.overrides().is_empty());
+        engine.exec("test", r#"assert(keybindings.key("minimap:toggle") == "M")"#).unwrap();
+    }
+
+    #[test]
+    fn actions_share_the_keys_of_the_controls() {
+        let engine = ScriptEngine::new();
+        let key_map = SharedKeyMap::default();
+        let actions = Actions::new(&engine, key_map.clone()).unwrap();
+        engine.exec("test", r#"
+            presses = 0
+            keybindings.register {
+                name = "wave",
+                description = "Wave",
+                key = "E",
+                callback = function() presses = presses + 1 end,
+            }
+        "#).unwrap();
+
+        // The control bound to the key wins, and both show the conflict
+        assert!(actions.press(&engine, "E", "Player").is_empty());
+        assert_eq!(engine.lua().globals().get::<_, i32>("presses").unwrap(), 0);
+        let bindings = actions.bindings();
+        let binding = |name: &str| bindings.iter().find(|binding| binding.name == name).unwrap().clone();
+        assert_eq!(binding("wave").conflict.as_deref(), Some("Interact with the block"));
+        assert_eq!(binding("interact").conflict.as_deref(), Some("Wave"));
+        assert_eq!(binding("forward").conflict, None);
+
+        // Moving the control to another key frees it for the action
+        actions.bind("interact", "Q");
+        assert_eq!(key_map.read().unwrap().binding(Control::Interact), Binding::from_name("Q").unwrap());
+        assert!(actions.press(&engine, "E", "Player").is_empty());
+        assert_eq!(engine.lua().globals().get::<_, i32>("presses").unwrap(), 1);
+        assert!(actions.bindings().iter().all(|binding| binding.conflict.is_none()));
+
+        // Held controls can't be bound to shortcuts
+        actions.bind("forward", "Ctrl+W");
+        assert_eq!(key_map.read().unwrap().binding(Control::Forward), Control::Forward.default_binding());
+    }
+
+    #[test]
+    fn menu_rebinds_the_selected_action() {
+        let bindings = vec![
+            KeyBinding { name: "a".to_string(), description: String::new(), key: "A".to_string(), conflict: None },
+            KeyBinding { name: "b".to_string(), description: String::new(), key: "B".to_string(), conflict: None },
+        ];
+        let mut menu = KeyBindingsMenu::default();
+        menu.toggle_rebinding();
+        assert_eq!(menu.take_rebinding(&bindings), None);
+
+        menu.toggle();
+        menu.select(5, bindings.len());
+        menu.toggle_rebinding();
+        assert!(menu.view(bindings.clone()).unwrap().rebinding);
+        assert_eq!(menu.take_rebinding(&bindings), Some("b"));
+        assert_eq!(menu.take_rebinding(&bindings), None);
+    }
+}
@@ -0,0 +1,41 @@
+//! The game, without the window it's played in. The
+//! executable opens the window and drives the game, while
+//! tests simulate worlds headless through `world::sim`.
+
+#![feature(clamp)]
+
+pub mod archive;
+pub mod backup;
+pub mod breath;
+pub mod camera;
+pub mod camera_path;
+pub mod config;
+pub mod console;
+pub mod crash;
+pub mod death;
+pub mod dialogue;
+pub mod ecs;
+pub mod entity;
+pub mod held_item;
+pub mod input;
+pub mod keybindings;
+pub mod memory;
+pub mod metrics;
+pub mod graphics;
+pub mod network;
+pub mod notification;
+pub mod player;
+pub mod player_list;
+pub mod recipe_book;
+pub mod replay;
+pub mod resources;
+pub mod scoreboard;
+pub mod scripting;
+pub mod server;
+pub mod simulation;
+pub mod sound;
+pub mod stats;
+pub mod task;
+pub mod timestep;
+pub mod triple_buffer;
+pub mod world;
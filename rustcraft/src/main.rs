@@ -1,38 +1,86 @@
 //! Entry point and types/trait representing the
-//! application/game.
-
-#![feature(clamp)]
-
-use crate::camera::PerspectiveCamera;
-use crate::graphics::gl::{Gl, gl};
-use crate::resources::Resources;
-use crate::timestep::TimeStep;
-use crate::world::World;
-
-use cgmath::{Vector3};
-use cgmath::num_traits::FromPrimitive;
-
-use glfw::{Action, Context, Key, Glfw, Window, WindowEvent, SwapInterval, OpenGlProfileHint, CursorMode};
-
-use std::path::Path;
-use std::sync::mpsc::Receiver;
-
-pub mod camera;
-pub mod entity;
-pub mod input;
-pub mod graphics;
-pub mod resources;
-pub mod timestep;
-pub mod world;
-
-struct WindowProps {
-    height: i32,
-    width: i32,
-    fullscreen: bool,
-    vsync: bool,
-    polygon_mode: bool,
-    title: &'static str,
-}
+//! application/game. The game itself lives in the
+//! library, see `lib.rs`.
+
+use rustcraft::archive::ZipArchive;
+use rustcraft::backup::{self, Backups};
+use rustcraft::breath::BreathRenderer;
+use rustcraft::camera::PerspectiveCamera;
+use rustcraft::config::{Config, ConfigFile, WindowConfig};
+use rustcraft::console::ConsoleRenderer;
+use rustcraft::crash;
+use rustcraft::death::DeathScreenRenderer;
+use rustcraft::dialogue::DialogueRenderer;
+use rustcraft::graphics::backend::PolygonMode;
+use rustcraft::graphics::backend::RenderBackend;
+use rustcraft::graphics::bloom::{self, BloomSettings};
+use rustcraft::graphics::capabilities::{CapabilitiesPanel, GraphicsSettings};
+use rustcraft::graphics::cave_fog::CaveFogSettings;
+use rustcraft::graphics::cinematic::{self, CinematicSettings};
+use rustcraft::graphics::debug_view::DebugView;
+use rustcraft::graphics::gl::Gl;
+use rustcraft::graphics::isometric;
+use rustcraft::graphics::pass::{RenderContext, RenderPasses, RenderStage};
+use rustcraft::graphics::quality::{self, Quality, QualityGovernor, QualitySettings};
+use rustcraft::graphics::renderer::Renderer;
+use rustcraft::graphics::scene::SceneRenderer;
+use rustcraft::graphics::sky::SkyRenderer;
+use rustcraft::graphics::submerged::SubmergedRenderer;
+use rustcraft::graphics::transition::TransitionRenderer;
+use rustcraft::held_item::HeldItemRenderer;
+use rustcraft::input::{Binding, Control, InputState, SharedKeyMap};
+use rustcraft::keybindings::KeyBindingsRenderer;
+use rustcraft::memory::MemoryPanel;
+use rustcraft::notification::ToastRenderer;
+use rustcraft::player::{NameTagRenderer, PlayerRenderer};
+use rustcraft::player_list::PlayerListRenderer;
+use rustcraft::recipe_book::RecipeBookRenderer;
+use rustcraft::resources::Resources;
+use rustcraft::scoreboard::SidebarRenderer;
+use rustcraft::simulation::{FrameState, SimEvent, Simulation};
+use rustcraft::triple_buffer::triple_buffer;
+use rustcraft::world::{self, World};
+use rustcraft::world::dimension::GeneratorPreset;
+use rustcraft::world::generators::{BoxedTerrainGen, TerrainGenRegistry, DEFAULT_GENERATOR};
+use rustcraft::world::overlay::BlockOverlays;
+use rustcraft::world::pregen;
+use rustcraft::world::preview::{self, PreviewOptions};
+use rustcraft::world::time::SkySettings;
+use rustcraft::world::universe::{Universe, CAVES, OVERWORLD};
+use rustcraft::world::save::SaveDir;
+use rustcraft::world::structure::{StructureDef, StructureGen};
+use rustcraft::world::biome::{BiomeRegistry, BiomeTerrainGen};
+use rustcraft::world::texture_pack::TexturePack;
+use rustcraft::world::placement::PlacementGhostRenderer;
+use rustcraft::world::light::LightFormat;
+use rustcraft::world::light_overlay::LightOverlayRenderer;
+use rustcraft::world::worldgen_overlay::WorldgenOverlayRenderer;
+use rustcraft::world::particles::ParticleRenderer;
+use rustcraft::world::furnace::FurnaceRenderer;
+use rustcraft::world::selection::SelectionRenderer;
+use rustcraft::world::border::WorldBorderRenderer;
+use rustcraft::world::time::WorldTime;
+use rustcraft::world::chunk::{ChunkRenderer, CHUNK_SIZE};
+
+use cgmath::Vector3;
+
+use glfw::{Action, Context, Key, Modifiers, MouseButton, Glfw, Window, WindowEvent, SwapInterval, OpenGlProfileHint, CursorMode};
+
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Instant;
+
+/// The seconds a replay is skipped forward by at once
+const SCRUB_SECONDS: u32 = 5;
+/// The path of the directory the world is saved in,
+/// relative to the executable
+const SAVE_PATH: &str = "saves/world";
+
+/// The title of the window
+const WINDOW_TITLE: &str = "Rustcraft v0.1.0";
 
 /// Rustcraft
 ///
@@ -40,19 +88,30 @@ struct WindowProps {
 /// application. It provides all game related
 /// functionality like `window creation`, `game loop`
 /// and `rendering`.
+///
+/// The thread running `Rustcraft` is the render thread.
+/// It owns the window and the `OpenGL` context, while
+/// the world is simulated on a separate thread.
 struct Rustcraft {
-    /// A `OpenGL` 'instance'
-    gl: Gl,
+    /// The renderer owning the global render state
+    renderer: Renderer,
     /// A `GLFW` instance
     glfw: Glfw,
     /// An `GLFW` event receiver
     events: Receiver<(f64, WindowEvent)>,
     /// A `GLFW` window,
     window: Window,
-    /// The window properties
-    window_props: WindowProps,
-    /// The last frame time
-    last_frame_time: f32,
+    /// The configuration the player chose
+    config: Config,
+    /// The file the configuration is loaded from and saved to
+    config_file: ConfigFile,
+    /// The keys the controls are bound to, shared with the
+    /// simulation once it's created
+    key_map: SharedKeyMap,
+    /// Whether the faces are drawn as wireframes
+    polygon_mode: bool,
+    /// What the chunks show instead of their shaded textures
+    debug_view: DebugView,
 }
 
 impl Rustcraft {
@@ -65,15 +124,17 @@ impl Rustcraft {
         glfw.window_hint(glfw::WindowHint::ContextVersionMinor(3));
         glfw.window_hint(glfw::WindowHint::OpenGlProfile(OpenGlProfileHint::Core));
 
-        let window_props = WindowProps {
-            width: 1080,
-            height: 720,
-            fullscreen: false,
-            vsync: false,
-            polygon_mode: false,
-            title: "Rustcraft v0.1.0"
-        };
-        let (mut window, events) = Self::create_window(&glfw, &window_props);
+        let mut config_file = ConfigFile::next_to_exe().expect("Failed to locate the executable.");
+        let config = config_file.load();
+        // Multisampling is requested before the driver is known,
+        // the backend enables it only if the window supports it
+        let resources = Resources::from_relative_exe_path(Path::new("res")).unwrap();
+        let graphics = GraphicsSettings::load(&resources);
+        if graphics.msaa > 0 {
+            glfw.window_hint(glfw::WindowHint::Samples(Some(graphics.msaa)));
+        }
+
+        let (mut window, events) = Self::create_window(&glfw, &config.window);
 
         let (width, height) = window.get_size();
 
@@ -82,25 +143,35 @@ impl Rustcraft {
 
         let gl = Gl::load_with(|s| window.get_proc_address(s) as *const std::os::raw::c_void);
 
-        unsafe {
-            gl.ClearColor(0.23, 0.38, 0.47, 1.0);
-            gl.Viewport(0, 0, width, height);
+        let renderer = Renderer::new(&gl, &graphics);
+        let capabilities = renderer.backend().capabilities();
+        for line in capabilities.lines() {
+            log::info!("{}", line);
         }
+        crash::set_graphics(capabilities.report());
+        renderer.set_clear_color(0.23, 0.38, 0.47, 1.0);
+        renderer.set_viewport(width, height);
 
-
-        Self {
+        let mut rustcraft = Self {
             glfw,
-            gl,
+            renderer,
             events,
             window,
-            window_props,
-            last_frame_time: 0.0,
+            key_map: SharedKeyMap::default(),
+            config,
+            config_file,
+            polygon_mode: false,
+            debug_view: DebugView::Off,
+        };
+        if rustcraft.config.window.fullscreen {
+            Self::apply_fullscreen(&mut rustcraft.window, &rustcraft.config.window);
         }
+        rustcraft
     }
 
     /// Create a new `GLFW` window with a title
-    fn create_window(glfw: &Glfw, props: &WindowProps) -> (Window, Receiver<(f64, WindowEvent)>) {
-        let (mut window, events) = glfw.create_window(props.width as u32, props.height as u32, props.title, glfw::WindowMode::Windowed)
+    fn create_window(glfw: &Glfw, config: &WindowConfig) -> (Window, Receiver<(f64, WindowEvent)>) {
+        let (mut window, events) = glfw.create_window(config.width as u32, config.height as u32, WINDOW_TITLE, glfw::WindowMode::Windowed)
             .expect("Failed to create window.");
 
         window.make_current();
@@ -109,93 +180,664 @@ impl Rustcraft {
         (window, events)
     }
 
-    /// Run the main game loop of `Rustcraft`
-    fn run(&mut self) {
-        self.glfw.set_swap_interval(SwapInterval::Sync(1));
+    /// Renders the area around the player from the dimetric
+    /// angle into a large offscreen image and writes it next
+    /// to the executable. Only the world is captured, without
+    /// the sun, the moon, post processing and the user interface.
+    ///
+    /// # Arguments
+    ///
+    /// * `passes` - The render passes
+    /// * `frame` - The frame state the capture is rendered from
+    /// * `quality` - The quality the frames are rendered with
+    /// * `textures` - The texture pack of the blocks
+    fn capture_isometric(&self, passes: &mut RenderPasses<FrameState>, frame: &FrameState, quality: Quality, textures: &TexturePack) -> Result<PathBuf, String> {
+        let dir = std::env::current_exe()
+            .map_err(|error| format!("Failed to get the path of the executable: {}", error))?
+            .with_file_name(isometric::CAPTURE_PATH);
+
+        let radius = (quality.render_distance * CHUNK_SIZE as i32) as f32;
+        let mut frame = frame.clone();
+        frame.capture = Some(isometric::dimetric_camera(*frame.camera.pos(), radius));
+        frame.sky.celestial_bodies = false;
+        frame.placement = None;
+
+        let size = isometric::capture_size(self.renderer.backend().capabilities());
+        let target = self.renderer.backend().create_render_target(size as i32, size as i32, 1, true);
+        target.bind();
+        self.renderer.clear();
+        passes.render_until(&RenderContext {
+            renderer: &self.renderer,
+            frame: &frame,
+            quality: Quality { bloom: false, cinematic: false, cave_fog: false, ..quality },
+            textures,
+            debug_view: DebugView::Off,
+        }, RenderStage::PostProcess);
+        let pixels = target.read_pixels(0);
+        target.unbind();
+        let (width, height) = self.window.get_framebuffer_size();
+        self.renderer.set_viewport(width, height);
+
+        isometric::write_capture(&dir, world::edit_log::unix_time(), size, &pixels)
+    }
 
-        unsafe {
-            self.gl.Enable(gl::BLEND);
-            self.gl.Enable(gl::DEPTH_TEST);
-            self.gl.BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+    /// Moves the window onto the primary monitor if the configuration
+    /// wants it fullscreen, or back into its configured size otherwise
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The window of the game
+    /// * `config` - The configuration of the window
+    fn apply_fullscreen(window: &mut Window, config: &WindowConfig) {
+        let (pos_x, pos_y) = window.get_pos();
+        if config.fullscreen {
+            unsafe {
+                let monitor = glfw::ffi::glfwGetPrimaryMonitor();
+                let vid_mode = glfw::ffi::glfwGetVideoMode(monitor);
+                glfw::ffi::glfwSetWindowMonitor(window.window_ptr(), monitor, pos_x, pos_y, (*vid_mode).width, (*vid_mode).height, (*vid_mode).refreshRate);
+            }
+        } else {
+            unsafe {
+                glfw::ffi::glfwSetWindowMonitor(window.window_ptr(), std::ptr::null_mut(), pos_x, pos_y, config.width, config.height, 60);
+            }
         }
+    }
 
-        let resources = Resources::from_relative_exe_path(Path::new("res")).unwrap();
-        // let mut camera = PerspectiveCamera::at_pos(Vector3::new(0.0, 34.0,  0.0));
-        let mut camera = PerspectiveCamera::at_pos(Vector3::new(0.0, 10.0,  0.0));
-        camera.rotate(45.0, -30.0, 0.0);
+    /// Applies an edited configuration to the running game
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The edited configuration
+    /// * `sim_sender` - The sender of the events of the simulation
+    /// * `governor` - The governor of the quality of the frames
+    fn apply_config(&mut self, config: Config, sim_sender: &Sender<SimEvent>, governor: &mut QualityGovernor) {
+        let old = mem::replace(&mut self.config, config);
+        let window = &self.config.window;
+        if window.vsync != old.window.vsync {
+            self.glfw.set_swap_interval(swap_interval(window.vsync));
+        }
+        if window.fullscreen != old.window.fullscreen {
+            Self::apply_fullscreen(&mut self.window, window);
+        } else if !window.fullscreen && (window.width, window.height) != (old.window.width, old.window.height) {
+            self.window.set_size(window.width, window.height);
+        }
+        if self.config.graphics.fov != old.graphics.fov {
+            let _ = sim_sender.send(SimEvent::SetFov(self.config.fov_radians()));
+        }
+        governor.set_render_distance(self.config.graphics.render_distance);
+        self.config.controls.apply(&mut self.key_map.write().unwrap());
+    }
 
-        let mut world = World::new(&self.gl, &resources);
-        // world.load_chunk(Vector2::new(0, 0));
-        // world.load_chunk(Vector2::new(0, 1));
-        // world.load_chunk(Vector2::new(1, 0));
-        // world.load_chunk(Vector2::new(1, 1));
+    /// Run the main game loop of `Rustcraft`
+    ///
+    /// # Arguments
+    ///
+    /// * `pregen` - The radius of the chunks which should be
+    ///   pre-generated around the player, if any
+    fn run(&mut self, pregen: Option<i32>) {
+        self.glfw.set_swap_interval(swap_interval(self.config.window.vsync));
+
+        self.renderer.enable_default_state();
+
+        // The world's resource pack and shader options only apply to
+        // the resources of this session, the game's ones stay as they are
+        let save_dir = SaveDir::from_relative_exe_path(Path::new(SAVE_PATH)).unwrap();
+        let resources = world_resources(Resources::from_relative_exe_path(Path::new("res")).unwrap(), &save_dir);
+        let structures = Arc::new(StructureDef::load_all(&resources));
+        let biomes = BiomeRegistry::default();
+        let generator = match overworld_generator(&save_dir, &biomes) {
+            Ok(generator) => generator,
+            Err(error) => {
+                log::error!("{}", error);
+                return;
+            }
+        };
+        let overworld = StructureGen::new(generator, structures, 0);
 
-        // let mut chunk_renderer: ChunkRenderer = ChunkRenderer::new(&self.gl, &resources);
+        // The simulation lifts the camera above the surface once
+        // the scripts defined the biomes
+        let mut camera = PerspectiveCamera::at_pos(Vector3::new(0.0, 0.0, 0.0));
+        camera.rotate(45.0, -30.0, 0.0);
+        camera.set_fov(self.config.fov_radians());
+
+        let (bloom_capture, bloom) = bloom::bloom_passes(self.renderer.backend(), &resources, BloomSettings::default());
+        let cinematic_settings = CinematicSettings::load(&resources);
+        let (cinematic_capture, cinematic) = cinematic::cinematic_passes(self.renderer.backend(), &resources, cinematic_settings, bloom_capture.scene());
+        let mut textures = TexturePack::load(&resources).expect("Failed to load the texture pack.");
+        let mut passes = RenderPasses::new();
+        passes.add(Box::new(bloom_capture));
+        passes.add(Box::new(cinematic_capture));
+        passes.add(Box::new(SkyRenderer::new(self.renderer.backend(), &resources)));
+        let light_format = LightFormat::from_colored(self.config.graphics.colored_light);
+        passes.add(Box::new(ChunkRenderer::new(self.renderer.backend(), &resources, &textures, CaveFogSettings::load(&resources))
+            .with_light_format(light_format)));
+        passes.add(Box::new(SceneRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(PlayerRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(PlacementGhostRenderer::new(self.renderer.backend(), &resources, &textures)));
+        passes.add(Box::new(SelectionRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(LightOverlayRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(WorldgenOverlayRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(WorldBorderRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(cinematic));
+        passes.add(Box::new(bloom));
+        passes.add(Box::new(SubmergedRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(TransitionRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(NameTagRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(ParticleRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(SidebarRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(HeldItemRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(BreathRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(DialogueRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(PlayerListRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(RecipeBookRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(FurnaceRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(ConsoleRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(KeyBindingsRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(DeathScreenRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(ToastRenderer::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(MemoryPanel::new(self.renderer.backend(), &resources)));
+        passes.add(Box::new(CapabilitiesPanel::new(self.renderer.backend(), &resources)));
+        let mut governor = QualityGovernor::new(QualitySettings::load(&resources));
+        governor.set_render_distance(self.config.graphics.render_distance);
+
+        // Spawn the simulation thread
+        let (frame_writer, mut frame_reader) = triple_buffer(FrameState {
+            camera: camera.clone(),
+            chunks: Vec::new(),
+            props: Vec::new(),
+            time: WorldTime::default(),
+            sky: SkySettings::default(),
+            placement: None,
+            selection: None,
+            toasts: Vec::new(),
+            fade: 0.0,
+            submerged: None,
+            air: None,
+            overlays: Arc::new(BlockOverlays::default()),
+            memory: None,
+            capabilities: false,
+            lit_surfaces: None,
+            worldgen_lines: None,
+            border: None,
+            players: Vec::new(),
+            name_tags: Vec::new(),
+            particles: Vec::new(),
+            dialogue: None,
+            prompt: None,
+            player_list: None,
+            recipe_book: None,
+            furnace: None,
+            console: None,
+            death: None,
+            key_bindings: None,
+            held_item: None,
+            sidebar: None,
+            capture: None,
+            hud: true,
+        });
+        let (sim_sender, sim_receiver) = channel();
+        let (sound_sender, sound_receiver) = channel();
+        let mut universe = Universe::new(OVERWORLD, World::with_generator(overworld, SkySettings::default()));
+        let caves = GeneratorPreset::Caves;
+        universe.add(CAVES, caves.create_world(caves.default_sky(), &biomes));
+        universe.set_biomes(biomes);
+        let simulation = match Simulation::new(camera, universe, &resources, save_dir, sim_receiver, frame_writer, sound_sender) {
+            Ok(simulation) => simulation,
+            Err(error) => {
+                log::error!("Failed to start the simulation: {}", error);
+                return;
+            }
+        };
+        self.key_map = simulation.key_map();
+        self.config.controls.apply(&mut self.key_map.write().unwrap());
+        let simulation_handle = simulation.spawn();
+        if let Some(radius) = pregen {
+            let _ = sim_sender.send(SimEvent::Pregenerate(radius));
+        }
+        let mut capture_requested = false;
+        // While the recipe book is open, the keys type into its
+        // search box instead of triggering their commands
+        let mut recipe_book_open = false;
+        // Likewise while the console is open
+        let mut console_open = false;
+        // While the key bindings menu is open, the keys
+        // are bound to the controls and actions instead
+        let mut key_bindings_open = false;
+
+        // The simulation saves the world as it stops,
+        // even if the render thread panicked
+        let rendered = panic::catch_unwind(AssertUnwindSafe(|| {
+            while !self.window.should_close() {
+                // Ctrl+C closes the game like the window, and the game
+                // can't go on without the simulation if it crashed
+                if crash::shutdown_requested() || simulation_handle.is_finished() {
+                    self.window.set_should_close(true);
+                }
 
-        while !self.window.should_close() {
-            let time = f32::from_f64(self.glfw.get_time()).unwrap();
+                // Render the most recent frame state
+                let frame_start = Instant::now();
+                let frame = frame_reader.read();
+                let sky_color = frame.sky.color(&frame.time);
+                self.renderer.set_clear_color(sky_color.x, sky_color.y, sky_color.z, 1.0);
+                self.renderer.clear();
+                let ctx = RenderContext {
+                    renderer: &self.renderer,
+                    frame,
+                    quality: governor.quality(),
+                    textures: &textures,
+                    debug_view: self.debug_view,
+                };
+                if frame.hud {
+                    passes.render(&ctx);
+                } else {
+                    passes.render_until(&ctx, RenderStage::Overlay);
+                }
 
-            let time_step = TimeStep(time - self.last_frame_time);
-            self.last_frame_time = time;
+                // The capture is rendered after the frame, so
+                // it doesn't end up on the screen
+                if capture_requested {
+                    capture_requested = false;
+                    match self.capture_isometric(&mut passes, frame, governor.quality(), &textures) {
+                        Ok(path) => log::info!("Captured the isometric view to {}", path.display()),
+                        Err(error) => log::error!("Failed to capture the isometric view: {}", error),
+                    }
+                }
 
-            world.clear_renderer();
-            world.render(&camera);
+                // There is no audio output yet, so the sounds
+                // are only traced
+                for cue in sound_receiver.try_iter() {
+                    log::trace!("Playing {} at {:?}", cue.resource(), cue.pos);
+                }
 
-            // Swap front and back buffers
-            self.window.swap_buffers();
+                // The time spent waiting for the vertical sync
+                // isn't part of the frame time
+                governor.record_frame(frame_start.elapsed().as_secs_f32());
 
-            // Poll for and process events
-            self.glfw.poll_events();
+                // The keys don't trigger their commands while the
+                // death screen is shown, except for respawning
+                let dead = frame.death.is_some();
 
-            // Handle player input
-            input::handle_mouse_input(&mut self.window, &mut camera);
-            input::handle_key_input(time_step, &self.window, &mut camera);
+                // Swap front and back buffers
+                self.window.swap_buffers();
 
-            for (_, event) in glfw::flush_messages(&self.events) {
+                // Poll for and process events
+                self.glfw.poll_events();
 
-                if let glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) = event {
-                    self.window.set_should_close(true);
+                // Edited textures are swapped in with the next frame
+                textures.poll(&resources);
+
+                // Edits of the configuration apply right away
+                if let Some(config) = self.config_file.poll() {
+                    log::info!("Applying the edited configuration");
+                    self.apply_config(config, &sim_sender, &mut governor);
                 }
 
-                if let glfw::WindowEvent::Key(Key::F5, _, Action::Press, _) = event {
-                    self.window_props.polygon_mode = !self.window_props.polygon_mode;
-                    if self.window_props.polygon_mode {
-                        unsafe { self.gl.PolygonMode(gl::FRONT_AND_BACK, gl::LINE); }
-                    } else {
-                        unsafe { self.gl.PolygonMode(gl::FRONT_AND_BACK, gl::FILL); }
+                // Forward player input to the simulation
+                let input = InputState::capture(&mut self.window, &self.key_map.read().unwrap());
+                let _ = sim_sender.send(SimEvent::Input(input));
+
+                // The character of the key opening the recipe
+                // book isn't typed into its search box
+                let mut book_opened = false;
+                for (_, event) in glfw::flush_messages(&self.events) {
+
+                    if recipe_book_open {
+                        match event {
+                            glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
+                                recipe_book_open = false;
+                                let _ = sim_sender.send(SimEvent::ToggleRecipeBook);
+                            }
+                            glfw::WindowEvent::Key(key, _, Action::Press, _) | glfw::WindowEvent::Key(key, _, Action::Repeat, _) => match key {
+                                Key::Backspace => { let _ = sim_sender.send(SimEvent::EraseSearch); }
+                                Key::Up => { let _ = sim_sender.send(SimEvent::SelectRecipe(-1)); }
+                                Key::Down => { let _ = sim_sender.send(SimEvent::SelectRecipe(1)); }
+                                Key::Enter => { let _ = sim_sender.send(SimEvent::CraftRecipe); }
+                                _ => {}
+                            },
+                            glfw::WindowEvent::Char(c) if !book_opened => { let _ = sim_sender.send(SimEvent::SearchRecipes(c)); }
+                            _ => {}
+                        }
+                        if let glfw::WindowEvent::Key(..) | glfw::WindowEvent::Char(_) | glfw::WindowEvent::MouseButton(..) = event {
+                            continue;
+                        }
                     }
-                }
 
-                if let glfw::WindowEvent::Key(Key::F12, _, Action::Press, _) = event {
-                    self.window_props.fullscreen = !self.window_props.fullscreen;
-                    if self.window_props.fullscreen {
-                        unsafe {
-                            let monitor = glfw::ffi::glfwGetPrimaryMonitor();
-                            let vid_mode = glfw::ffi::glfwGetVideoMode(monitor);
-                            let (pos_x, pos_y) = self.window.get_pos();
-                            glfw::ffi::glfwSetWindowMonitor(self.window.window_ptr(), monitor, pos_x, pos_y, (*vid_mode).width, (*vid_mode).height, (*vid_mode).refreshRate);
+                    // The slash opening the console is typed as its first character
+                    if console_open {
+                        match event {
+                            glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
+                                console_open = false;
+                                let _ = sim_sender.send(SimEvent::ToggleConsole);
+                            }
+                            glfw::WindowEvent::Key(Key::Enter, _, Action::Press, _) => {
+                                console_open = false;
+                                let _ = sim_sender.send(SimEvent::RunCommand);
+                            }
+                            glfw::WindowEvent::Key(Key::Backspace, _, Action::Press, _)
+                            | glfw::WindowEvent::Key(Key::Backspace, _, Action::Repeat, _) => {
+                                let _ = sim_sender.send(SimEvent::EraseCommand);
+                            }
+                            glfw::WindowEvent::Char(c) => { let _ = sim_sender.send(SimEvent::TypeCommand(c)); }
+                            _ => {}
                         }
-                    } else {
-                        unsafe {
-                            let (pos_x, pos_y) = self.window.get_pos();
-                            glfw::ffi::glfwSetWindowMonitor(self.window.window_ptr(), std::ptr::null_mut(), pos_x, pos_y, 1028, 720, 60);
+                        if let glfw::WindowEvent::Key(..) | glfw::WindowEvent::Char(_) | glfw::WindowEvent::MouseButton(..) = event {
+                            continue;
                         }
                     }
-                }
 
-                if let glfw::WindowEvent::FramebufferSize(width, height) = event {
-                    self.window_props.width = width;
-                    self.window_props.height = height;
-                    unsafe { self.gl.Viewport(0, 0, width, height); }
-                    camera.set_aspect_ratio((width / height) as f32);
+                    if key_bindings_open {
+                        match event {
+                            glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
+                                key_bindings_open = false;
+                                let _ = sim_sender.send(SimEvent::ToggleKeyBindings);
+                            }
+                            glfw::WindowEvent::Key(key, _, Action::Press, modifiers)
+                                if self.key_map.read().unwrap().control(Binding { key, shortcut: modifiers.contains(Modifiers::Control) }) == Some(Control::KeyBindings) =>
+                            {
+                                key_bindings_open = false;
+                                let _ = sim_sender.send(SimEvent::ToggleKeyBindings);
+                            }
+                            glfw::WindowEvent::Key(key, _, Action::Press, modifiers) => match key {
+                                Key::Up => { let _ = sim_sender.send(SimEvent::SelectKeyBinding(-1)); }
+                                Key::Down => { let _ = sim_sender.send(SimEvent::SelectKeyBinding(1)); }
+                                Key::Enter => { let _ = sim_sender.send(SimEvent::RebindKey); }
+                                key => {
+                                    let binding = Binding { key, shortcut: modifiers.contains(Modifiers::Control) };
+                                    let _ = sim_sender.send(SimEvent::PressKey(binding.name()));
+                                }
+                            },
+                            _ => {}
+                        }
+                        if let glfw::WindowEvent::Key(..) | glfw::WindowEvent::Char(_) | glfw::WindowEvent::MouseButton(..) = event {
+                            continue;
+                        }
+                    }
+
+                    if dead {
+                        match event {
+                            glfw::WindowEvent::Key(Key::Enter, _, Action::Press, _) => {
+                                let _ = sim_sender.send(SimEvent::Respawn);
+                                continue;
+                            }
+                            glfw::WindowEvent::Key(Key::Escape, ..) => {}
+                            glfw::WindowEvent::Key(..) | glfw::WindowEvent::Char(_) | glfw::WindowEvent::MouseButton(..) => continue,
+                            _ => {}
+                        }
+                    }
+
+                    if let glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) = event {
+                        self.window.set_should_close(true);
+                    }
+
+                    if let glfw::WindowEvent::Key(key, _, Action::Press, modifiers) = event {
+                        let binding = Binding { key, shortcut: modifiers.contains(Modifiers::Control) };
+                        let control = self.key_map.read().unwrap().control(binding);
+                        match control {
+                            Some(Control::ReloadTextures) => { textures.reload(&resources); }
+                            Some(Control::Capture) => { capture_requested = true; }
+                            Some(Control::Wireframe) => {
+                                self.polygon_mode = !self.polygon_mode;
+                                if self.polygon_mode {
+                                    self.renderer.set_polygon_mode(PolygonMode::Line);
+                                } else {
+                                    self.renderer.set_polygon_mode(PolygonMode::Fill);
+                                }
+                            }
+                            Some(Control::DebugView) => {
+                                self.debug_view = self.debug_view.next();
+                                log::info!("Debug view: {}", self.debug_view.name());
+                            }
+                            Some(Control::Fullscreen) => {
+                                self.config.window.fullscreen = !self.config.window.fullscreen;
+                                Self::apply_fullscreen(&mut self.window, &self.config.window);
+                            }
+                            Some(Control::RecipeBook) => {
+                                recipe_book_open = true;
+                                book_opened = true;
+                                let _ = sim_sender.send(SimEvent::ToggleRecipeBook);
+                            }
+                            Some(Control::Console) => {
+                                console_open = true;
+                                let _ = sim_sender.send(SimEvent::ToggleConsole);
+                            }
+                            Some(Control::KeyBindings) => {
+                                key_bindings_open = true;
+                                let _ = sim_sender.send(SimEvent::ToggleKeyBindings);
+                            }
+                            Some(control) => {
+                                if let Some(event) = control_event(control) {
+                                    let _ = sim_sender.send(event);
+                                }
+                            }
+                            // The other keys trigger the actions of scripts, unless
+                            // they're bound to held controls
+                            None if key == Key::Escape => {}
+                            None => { let _ = sim_sender.send(SimEvent::PressKey(binding.name())); }
+                        }
+                    }
+
+                    if let glfw::WindowEvent::MouseButton(MouseButton::Button2, Action::Press, _) = event {
+                        let _ = sim_sender.send(SimEvent::PlaceBlock);
+                    }
+
+                    // The size of the window is kept for the next
+                    // time, unless it covers the monitor
+                    if let glfw::WindowEvent::Size(width, height) = event {
+                        if !self.config.window.fullscreen {
+                            self.config.window.width = width;
+                            self.config.window.height = height;
+                        }
+                    }
+
+                    if let glfw::WindowEvent::FramebufferSize(width, height) = event {
+                        self.renderer.set_viewport(width, height);
+                        let _ = sim_sender.send(SimEvent::Resize(width, height));
+                    }
                 }
             }
+        }));
+
+        // Controls rebound in the key bindings menu are kept
+        self.config.controls.update(&self.key_map.read().unwrap());
+        if let Err(error) = self.config_file.save(&self.config) {
+            log::warn!("Failed to save the configuration: {}", error);
+        }
+
+        let _ = sim_sender.send(SimEvent::Shutdown);
+        if simulation_handle.join().is_err() {
+            log::error!("The simulation thread crashed");
+        }
+        if let Err(panic) = rendered {
+            panic::resume_unwind(panic);
         }
     }
 }
 
 /// The entry function of this binary
 fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    crash::install_panic_hook();
+    crash::install_interrupt_handler();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match PreviewOptions::from_args(&args) {
+        Ok(Some(options)) => {
+            log::info!("Generating the map of {:?} with seed {} in a radius of {} chunks", options.preset, options.seed, options.radius);
+            match preview::export(&options) {
+                Ok(()) => log::info!("Exported the map to {}", options.output.display()),
+                Err(error) => log::error!("Failed to export the map: {}", error),
+            }
+            return;
+        }
+        Ok(None) => {}
+        Err(error) => {
+            log::error!("Invalid arguments of {}: {}", preview::PREVIEW_FLAG, error);
+            return;
+        }
+    }
+
+    if args.iter().any(|arg| arg == backup::LIST_FLAG || arg == backup::RESTORE_FLAG) {
+        manage_backups(&args);
+        return;
+    }
+
+    let pregen = match pregen::radius_from_args(&args) {
+        Ok(pregen) => pregen,
+        Err(error) => {
+            log::error!("Invalid arguments of {}: {}", pregen::PREGEN_FLAG, error);
+            return;
+        }
+    };
+
     let mut rustcraft = Rustcraft::new();
-    rustcraft.run();
+    rustcraft.run(pregen);
+}
+
+/// Returns the event a control pressed once sends to the
+/// simulation, unless it's handled on the render thread
+///
+/// # Arguments
+///
+/// * `control` - The pressed control
+fn control_event(control: Control) -> Option<SimEvent> {
+    match control {
+        Control::Interact => Some(SimEvent::Interact),
+        Control::Spectate => Some(SimEvent::ToggleSpectating),
+        Control::CycleTool => Some(SimEvent::CycleTool),
+        Control::InspectBlock => Some(SimEvent::InspectBlock),
+        Control::Hotbar(slot) => Some(SimEvent::SelectBlock(slot.into())),
+        Control::Undo => Some(SimEvent::Undo),
+        Control::Redo => Some(SimEvent::Redo),
+        Control::AddKeyframe => Some(SimEvent::AddKeyframe),
+        Control::ClearCameraPath => Some(SimEvent::ClearCameraPath),
+        Control::Flythrough => Some(SimEvent::ToggleFlythrough),
+        Control::Record => Some(SimEvent::ToggleRecording),
+        Control::Replay => Some(SimEvent::ToggleReplay),
+        Control::PauseReplay => Some(SimEvent::PauseReplay),
+        Control::ScrubReplay => Some(SimEvent::ScrubReplay(SCRUB_SECONDS)),
+        Control::Backup => Some(SimEvent::Backup),
+        Control::LightOverlay => Some(SimEvent::ToggleLightOverlay),
+        Control::Capabilities => Some(SimEvent::ToggleCapabilities),
+        Control::MemoryPanel => Some(SimEvent::ToggleMemoryPanel),
+        Control::ShowTps => Some(SimEvent::ShowTps),
+        _ => None,
+    }
+}
+
+/// Returns the swap interval of the buffers of the window
+///
+/// # Arguments
+///
+/// * `vsync` - Whether the frames are synchronized with the monitor
+fn swap_interval(vsync: bool) -> SwapInterval {
+    if vsync { SwapInterval::Sync(1) } else { SwapInterval::None }
+}
+
+/// Layers the resource pack and the shader options the world
+/// declares in its metadata over the resources of the game
+///
+/// # Arguments
+///
+/// * `resources` - The resources of the game
+/// * `save_dir` - The save directory of the world
+fn world_resources(resources: Resources, save_dir: &SaveDir) -> Resources {
+    let meta = match save_dir.load_meta() {
+        Ok(meta) => meta,
+        Err(error) => {
+            log::warn!("Failed to load the world metadata: {:?}", error);
+            return resources;
+        }
+    };
+
+    let mut resources = resources;
+    if !meta.resource_pack.is_empty() {
+        match save_dir.resource_pack(&meta.resource_pack) {
+            Some(path) if path.is_dir() => {
+                log::info!("Using the resource pack {} of the world", meta.resource_pack);
+                resources = resources.with_layer(path);
+            }
+            Some(path) => match ZipArchive::open(&path) {
+                Ok(archive) => {
+                    log::info!("Using the zipped resource pack {} of the world", meta.resource_pack);
+                    resources = resources.with_archive(archive);
+                }
+                Err(error) => log::warn!("Failed to open the resource pack {}: {}", meta.resource_pack, error),
+            },
+            None => log::warn!("The resource pack {} of the world doesn't exist", meta.resource_pack),
+        }
+    }
+    if !meta.shader_options.is_empty() {
+        log::info!("Using the shader options of the world");
+        resources = resources.with_overrides(quality::SETTINGS_FILE, meta.shader_options);
+    }
+    resources
+}
+
+/// Creates the terrain generator of the overworld the world's
+/// metadata chose. A new world falls back to the default biomes
+/// if it failed, but a world which already has chunks fails to
+/// start, since its terrain would change at the edge of them.
+///
+/// # Arguments
+///
+/// * `save_dir` - The directory the world is saved in
+/// * `biomes` - The definitions of the biomes
+fn overworld_generator(save_dir: &SaveDir, biomes: &BiomeRegistry) -> Result<BoxedTerrainGen, String> {
+    let generated = save_dir.dimension(OVERWORLD).is_ok_and(|dir| dir.has_chunks());
+    let generator = save_dir.load_meta()
+        .map_err(|error| format!("failed to read the world metadata: {:?}", error))
+        .and_then(|meta| {
+            let name = if meta.generator.is_empty() { DEFAULT_GENERATOR } else { &meta.generator };
+            let registry = TerrainGenRegistry::new(save_dir.root_path().to_path_buf()).with_biomes(biomes.clone());
+            registry.create(name, &meta.generator_options)
+        });
+    match generator {
+        Ok(generator) => Ok(generator),
+        Err(error) if generated => Err(format!("Can't generate the world: {}", error)),
+        Err(error) => {
+            log::error!("{}, generating the default terrain instead", error);
+            Ok(Box::new(BiomeTerrainGen::default().with_biomes(biomes.clone())))
+        }
+    }
+}
+
+/// Lists the backups of the world or restores one of
+/// them, instead of starting the game
+///
+/// # Arguments
+///
+/// * `args` - The command line arguments
+fn manage_backups(args: &[String]) {
+    let backups = match SaveDir::from_relative_exe_path(Path::new(SAVE_PATH)) {
+        Ok(save_dir) => Backups::new(&save_dir),
+        Err(error) => {
+            log::error!("Failed to open the world: {:?}", error);
+            return;
+        }
+    };
+
+    match args.iter().position(|arg| arg == backup::RESTORE_FLAG) {
+        Some(index) => {
+            let name = match args.get(index + 1) {
+                Some(name) => name,
+                None => {
+                    log::error!("Missing the name of the backup to restore");
+                    return;
+                }
+            };
+            match backups.restore(name, world::edit_log::unix_time()) {
+                Ok(()) => log::info!("Restored the world from {}", name),
+                Err(error) => log::error!("Failed to restore {}: {}", name, error),
+            }
+        }
+        None => match backups.list() {
+            Ok(list) if list.is_empty() => log::info!("There are no backups"),
+            Ok(list) => {
+                for backup in list {
+                    log::info!("{}", backup.name);
+                }
+            }
+            Err(error) => log::error!("Failed to list the backups: {}", error),
+        },
+    }
 }
\ No newline at end of file
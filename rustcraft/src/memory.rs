@@ -0,0 +1,254 @@
+//! Types accounting the memory allocated by the subsystems
+//! of the game, e.g. to catch chunks which are retained
+//! after they have been unloaded
+
+use crate::graphics::backend::GlBackend;
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The distance of the panel to the edges of the screen
+const PANEL_MARGIN: f32 = 8.0;
+
+/// Subsystem
+///
+/// A part of the game whose allocations are accounted:
+/// * `Blocks` - The block arrays of all chunks which are alive
+/// * `PendingMeshes` - Computed chunk meshes and light maps, not uploaded yet
+/// * `Lua` - The memory used by the script engine
+/// * `Textures` - The pixels textures keep a copy of
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Subsystem {
+    Blocks,
+    PendingMeshes,
+    Lua,
+    Textures,
+}
+
+impl Subsystem {
+    /// All subsystems in the order they are reported
+    pub const ALL: [Subsystem; 4] = [Subsystem::Blocks, Subsystem::PendingMeshes, Subsystem::Lua, Subsystem::Textures];
+
+    /// Returns the name of the subsystem as it's reported
+    pub fn name(&self) -> &'static str {
+        match self {
+            Subsystem::Blocks => "Blocks",
+            Subsystem::PendingMeshes => "Pending meshes",
+            Subsystem::Lua => "Lua",
+            Subsystem::Textures => "Textures",
+        }
+    }
+
+    /// Returns the index of the counter of the subsystem
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// MemoryCounters
+///
+/// The amount of bytes allocated by each subsystem. The
+/// counters are updated from any thread, whenever memory
+/// is allocated or released.
+pub struct MemoryCounters {
+    /// The allocated bytes by the index of the subsystem
+    bytes: [AtomicUsize; 4],
+}
+
+/// The counters of the whole game
+pub static MEMORY: MemoryCounters = MemoryCounters::new();
+
+impl MemoryCounters {
+    /// Creates new counters without any allocations
+    pub const fn new() -> Self {
+        Self {
+            bytes: [AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0)],
+        }
+    }
+
+    /// Records memory allocated by a subsystem
+    ///
+    /// # Arguments
+    ///
+    /// * `subsystem` - The subsystem
+    /// * `bytes` - The amount of allocated bytes
+    pub fn allocate(&self, subsystem: Subsystem, bytes: usize) {
+        self.bytes[subsystem.index()].fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Records memory released by a subsystem
+    ///
+    /// # Arguments
+    ///
+    /// * `subsystem` - The subsystem
+    /// * `bytes` - The amount of released bytes
+    pub fn release(&self, subsystem: Subsystem, bytes: usize) {
+        let _ = self.bytes[subsystem.index()].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+            Some(current.saturating_sub(bytes))
+        });
+    }
+
+    /// Replaces the memory of a subsystem, which is measured
+    /// as a whole rather than by single allocations
+    ///
+    /// # Arguments
+    ///
+    /// * `subsystem` - The subsystem
+    /// * `bytes` - The amount of bytes the subsystem uses
+    pub fn set(&self, subsystem: Subsystem, bytes: usize) {
+        self.bytes[subsystem.index()].store(bytes, Ordering::Relaxed);
+    }
+
+    /// Returns the amount of bytes allocated by a subsystem
+    ///
+    /// # Arguments
+    ///
+    /// * `subsystem` - The subsystem
+    pub fn bytes(&self, subsystem: Subsystem) -> usize {
+        self.bytes[subsystem.index()].load(Ordering::Relaxed)
+    }
+
+    /// Returns the current memory of all subsystems
+    pub fn report(&self) -> MemoryReport {
+        MemoryReport {
+            usage: Subsystem::ALL.iter().map(|subsystem| (*subsystem, self.bytes(*subsystem))).collect(),
+        }
+    }
+}
+
+impl Default for MemoryCounters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// MemoryReport
+///
+/// A snapshot of the memory of all subsystems
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MemoryReport {
+    /// The allocated bytes of each subsystem
+    pub usage: Vec<(Subsystem, usize)>,
+}
+
+impl MemoryReport {
+    /// Returns the bytes allocated by all subsystems
+    pub fn total(&self) -> usize {
+        self.usage.iter().map(|(_, bytes)| bytes).sum()
+    }
+
+    /// Returns the report as lines of text, one per
+    /// subsystem and the total in the last line
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.usage.iter()
+            .map(|(subsystem, bytes)| format!("{:<15}{:>10}", subsystem.name(), format_bytes(*bytes)))
+            .collect();
+        lines.push(format!("{:<15}{:>10}", "Total", format_bytes(self.total())));
+        lines
+    }
+}
+
+/// Formats an amount of bytes with a binary unit
+///
+/// # Arguments
+///
+/// * `bytes` - The amount of bytes
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// MemoryFrame
+///
+/// A frame the memory panel could be rendered for
+pub trait MemoryFrame: Frame {
+    /// Returns the memory report, if the panel is shown
+    fn memory(&self) -> Option<&MemoryReport>;
+}
+
+/// MemoryPanel
+///
+/// The debug panel drawing the memory report into the
+/// top left corner of the screen
+pub struct MemoryPanel {
+    /// The painter drawing the panel
+    painter: OverlayPainter,
+}
+
+impl MemoryPanel {
+    /// Creates a new memory panel
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+impl<F: MemoryFrame> RenderPass<F> for MemoryPanel {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let report = match ctx.frame.memory() {
+            Some(report) => report,
+            None => return,
+        };
+
+        let mut overlay = OverlayBuilder::default();
+        overlay.panel([PANEL_MARGIN, PANEL_MARGIN], &report.lines());
+
+        let width = OVERLAY_HEIGHT * ctx.frame.camera().aspect_ratio();
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_track_allocations_per_subsystem() {
+        let counters = MemoryCounters::new();
+        counters.allocate(Subsystem::Blocks, 2048);
+        counters.allocate(Subsystem::Blocks, 1024);
+        counters.release(Subsystem::Blocks, 2048);
+        counters.allocate(Subsystem::PendingMeshes, 10);
+        counters.release(Subsystem::PendingMeshes, 20);
+        counters.set(Subsystem::Lua, 512);
+        counters.set(Subsystem::Lua, 256);
+
+        assert_eq!(counters.bytes(Subsystem::Blocks), 1024);
+        assert_eq!(counters.bytes(Subsystem::PendingMeshes), 0);
+        assert_eq!(counters.bytes(Subsystem::Lua), 256);
+
+        let report = counters.report();
+        assert_eq!(report.usage.len(), Subsystem::ALL.len());
+        assert_eq!(report.total(), 1280);
+        assert_eq!(report.lines().last().unwrap(), &format!("{:<15}{:>10}", "Total", "1.2 KiB"));
+    }
+
+    #[test]
+    fn bytes_are_formatted_with_units() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(65536), "64.0 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 + 512 * 1024), "3.5 MiB");
+    }
+}
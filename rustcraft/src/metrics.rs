@@ -0,0 +1,290 @@
+//! Types to monitor the runtime of the simulation, e.g. the
+//! ticks per second, and to expose them to operators in the
+//! text format of Prometheus
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// The amount of ticks the ticks per second are averaged over
+const TICK_SAMPLES: usize = 100;
+/// The time in seconds a client of the metrics endpoint
+/// has to send its request
+const REQUEST_TIMEOUT: u64 = 5;
+
+/// Metric
+///
+/// A value of the simulation which is monitored:
+/// * `Ticks` - The amount of ticks since the start
+/// * `Tps` - The ticks per second
+/// * `TickDuration` - The average time the work of a tick takes
+/// * `LoadedChunks` - The chunks which are loaded
+/// * `Entities` - The entities in loaded chunks, e.g. dropped items and mobs
+/// * `Players` - The players in the world
+/// * `GenerationQueue` - The loaded chunks which aren't generated yet
+/// * `MeshQueue` - The chunks which are meshed or lit in the background
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Metric {
+    Ticks,
+    Tps,
+    TickDuration,
+    LoadedChunks,
+    Entities,
+    Players,
+    GenerationQueue,
+    MeshQueue,
+}
+
+impl Metric {
+    /// All metrics in the order they are exposed
+    pub const ALL: [Metric; 8] = [
+        Metric::Ticks,
+        Metric::Tps,
+        Metric::TickDuration,
+        Metric::LoadedChunks,
+        Metric::Entities,
+        Metric::Players,
+        Metric::GenerationQueue,
+        Metric::MeshQueue,
+    ];
+
+    /// Returns the name of the metric as it's exposed
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Ticks => "rustcraft_ticks_total",
+            Metric::Tps => "rustcraft_tps",
+            Metric::TickDuration => "rustcraft_tick_duration_seconds",
+            Metric::LoadedChunks => "rustcraft_loaded_chunks",
+            Metric::Entities => "rustcraft_entities",
+            Metric::Players => "rustcraft_players",
+            Metric::GenerationQueue => "rustcraft_generation_queue",
+            Metric::MeshQueue => "rustcraft_mesh_queue",
+        }
+    }
+
+    /// Returns the description of the metric
+    pub fn help(&self) -> &'static str {
+        match self {
+            Metric::Ticks => "Simulation ticks since the start",
+            Metric::Tps => "Simulation ticks per second",
+            Metric::TickDuration => "Average time the work of a tick takes",
+            Metric::LoadedChunks => "Chunks which are loaded",
+            Metric::Entities => "Entities in loaded chunks",
+            Metric::Players => "Players in the world",
+            Metric::GenerationQueue => "Loaded chunks whose terrain isn't generated yet",
+            Metric::MeshQueue => "Chunks which are meshed or lit in the background",
+        }
+    }
+
+    /// Returns whether the metric only ever increases
+    fn is_counter(&self) -> bool {
+        *self == Metric::Ticks
+    }
+
+    /// Returns the index of the value of the metric
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Metrics
+///
+/// The current value of each metric. The values are
+/// updated from any thread, e.g. the mesh queue by the
+/// render thread.
+pub struct Metrics {
+    /// The bits of the values by the index of the metric
+    values: [AtomicU64; 8],
+}
+
+/// The metrics of the whole game
+pub static METRICS: Metrics = Metrics::new();
+
+impl Metrics {
+    /// Creates new metrics with all values being zero
+    pub const fn new() -> Self {
+        Self {
+            values: [
+                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
+                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
+            ],
+        }
+    }
+
+    /// Replaces the value of a metric
+    ///
+    /// # Arguments
+    ///
+    /// * `metric` - The metric
+    /// * `value` - The current value
+    pub fn set(&self, metric: Metric, value: f64) {
+        self.values[metric.index()].store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Returns the value of a metric
+    ///
+    /// # Arguments
+    ///
+    /// * `metric` - The metric
+    pub fn get(&self, metric: Metric) -> f64 {
+        f64::from_bits(self.values[metric.index()].load(Ordering::Relaxed))
+    }
+
+    /// Returns all metrics in the text format of Prometheus
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for metric in Metric::ALL.iter() {
+            let kind = if metric.is_counter() { "counter" } else { "gauge" };
+            let _ = writeln!(text, "# HELP {} {}", metric.name(), metric.help());
+            let _ = writeln!(text, "# TYPE {} {}", metric.name(), kind);
+            let _ = writeln!(text, "{} {}", metric.name(), self.get(*metric));
+        }
+        text
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// TickTimer
+///
+/// Measures the ticks of the simulation over the most
+/// recent `TICK_SAMPLES` ticks
+#[derive(Default)]
+pub struct TickTimer {
+    /// The time since the previous tick and the time the
+    /// work of the tick took of each sample in seconds
+    samples: VecDeque<(f32, f32)>,
+}
+
+impl TickTimer {
+    /// Records a tick
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The time since the previous tick in seconds
+    /// * `work` - The time the work of the tick took in seconds
+    pub fn record(&mut self, interval: f32, work: f32) {
+        if self.samples.len() >= TICK_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((interval, work));
+    }
+
+    /// Returns the ticks per second, or `0` if no
+    /// tick has been recorded
+    pub fn tps(&self) -> f32 {
+        let elapsed: f32 = self.samples.iter().map(|(interval, _)| interval).sum();
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        self.samples.len() as f32 / elapsed
+    }
+
+    /// Returns the average time the work of a tick takes
+    /// in seconds, or `0` if no tick has been recorded
+    pub fn tick_duration(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().map(|(_, work)| work).sum::<f32>() / self.samples.len() as f32
+    }
+}
+
+/// Starts the metrics endpoint on a new thread, which answers
+/// `GET /metrics` requests with the current metrics
+///
+/// # Arguments
+///
+/// * `address` - The address the endpoint listens on, e.g. `127.0.0.1:9225`
+pub fn serve(address: &str) -> io::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(address)?;
+    log::info!("Serving metrics on http://{}/metrics", listener.local_addr()?);
+
+    thread::Builder::new()
+        .name("metrics".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream.and_then(handle_request);
+                if let Err(error) = result {
+                    log::warn!("Failed to answer metrics request: {}", error);
+                }
+            }
+        })
+}
+
+/// Answers a single request to the metrics endpoint
+///
+/// # Arguments
+///
+/// * `stream` - The connection of the client
+fn handle_request(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(REQUEST_TIMEOUT)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    stream.write_all(response(&request_line).as_bytes())
+}
+
+/// Returns the `HTTP` response to a request
+///
+/// # Arguments
+///
+/// * `request_line` - The first line of the request, e.g. `GET /metrics HTTP/1.1`
+fn response(request_line: &str) -> String {
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", METRICS.render()),
+        (Some("GET"), _) => ("404 Not Found", String::from("Not found\n")),
+        _ => ("405 Method Not Allowed", String::from("Method not allowed\n")),
+    };
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_are_averaged() {
+        let mut timer = TickTimer::default();
+        assert_eq!(timer.tps(), 0.0);
+        assert_eq!(timer.tick_duration(), 0.0);
+
+        for _ in 0..TICK_SAMPLES * 2 {
+            timer.record(0.05, 0.01);
+        }
+        assert!((timer.tps() - 20.0).abs() < 1e-3);
+        assert!((timer.tick_duration() - 0.01).abs() < 1e-6);
+    }
+
+    #[test]
+    fn metrics_are_rendered_as_text() {
+        let metrics = Metrics::new();
+        metrics.set(Metric::Tps, 59.5);
+        metrics.set(Metric::Ticks, 120.0);
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE rustcraft_ticks_total counter\nrustcraft_ticks_total 120\n"));
+        assert!(text.contains("# TYPE rustcraft_tps gauge\nrustcraft_tps 59.5\n"));
+        assert!(text.contains("rustcraft_mesh_queue 0\n"));
+    }
+
+    #[test]
+    fn only_the_metrics_path_is_served() {
+        assert!(response("GET /metrics HTTP/1.1\r\n").starts_with("HTTP/1.1 200 OK"));
+        assert!(response("GET / HTTP/1.1\r\n").starts_with("HTTP/1.1 404 Not Found"));
+        assert!(response("POST /metrics HTTP/1.1\r\n").starts_with("HTTP/1.1 405"));
+    }
+}
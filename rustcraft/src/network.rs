@@ -0,0 +1,1417 @@
+//! Types to host the world for other players over the network.
+//! The simulation acts as a listen server, which streams the
+//! chunks around the host, the blocks which change and the
+//! positions of the players to the clients connected over `TCP`.
+//!
+//! Each message is sent as a frame of its kind, the length of
+//! its payload as a big endian `u32` and the payload, which is
+//! the message encoded as `TOML`.
+//!
+//! A client offers the compressions and encryptions it supports
+//! in its `Hello`, of which the server picks one each in its
+//! `Accepted`. Bulk frames are then compressed with `zstd`, which
+//! sets the highest bit of their kind, and the connection is
+//! encrypted after a `Noise_NN` handshake. `NN` doesn't authenticate
+//! either side, so it keeps the traffic from being read along but
+//! doesn't stop a man in the middle.
+
+use crate::world::chunk::Chunk;
+use crate::world::history::BlockEdit;
+use crate::world::pregen::PregenChunk;
+use cgmath::Vector3;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use snow::{HandshakeState, StatelessTransportState};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+/// The version of the protocol, which clients have to speak
+pub const PROTOCOL_VERSION: u32 = 1;
+/// The compression of bulk frames the game supports
+pub const COMPRESSION: &str = "zstd";
+/// The noise protocol the game can encrypt connections with
+pub const ENCRYPTION: &str = "Noise_NN_25519_ChaChaPoly_BLAKE2s";
+/// The largest payload of a frame in bytes
+const MAX_PAYLOAD: usize = 16 * 1024 * 1024;
+/// The length of the header of a frame in bytes
+const FRAME_HEADER: usize = 5;
+/// The bit of the kind of a frame which is set if its payload
+/// has been compressed
+const COMPRESSED: u8 = 0x80;
+/// The smallest payload in bytes which is worth compressing
+const COMPRESSION_THRESHOLD: usize = 1024;
+/// The level of `zstd` bulk frames are compressed with
+const COMPRESSION_LEVEL: i32 = 3;
+/// The largest noise message in bytes
+const NOISE_MESSAGE: usize = 65535;
+/// The length of the tag authenticating a noise message in bytes
+const NOISE_TAG: usize = 16;
+/// The largest amount of chunks sent to a client per update
+const CHUNKS_PER_UPDATE: usize = 2;
+/// The time in seconds a client has to introduce itself
+const HANDSHAKE_TIMEOUT: u64 = 5;
+/// The largest amount of frames queued for a client, which
+/// is kicked once it falls that far behind
+const CLIENT_QUEUE: usize = 256;
+
+/// Hello
+///
+/// The first message of a client, introducing its player
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Hello {
+    /// The name of the player
+    pub name: String,
+    /// The version of the protocol the client speaks
+    pub version: u32,
+    /// The compressions of bulk frames the client supports
+    #[serde(default)]
+    pub compression: Vec<String>,
+    /// The noise protocols the client can encrypt the connection with
+    #[serde(default)]
+    pub encryption: Vec<String>,
+}
+
+impl Hello {
+    /// Creates the introduction of a player, which offers all
+    /// the compressions and encryptions the game supports
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the player
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: PROTOCOL_VERSION,
+            compression: vec![COMPRESSION.to_string()],
+            encryption: vec![ENCRYPTION.to_string()],
+        }
+    }
+}
+
+/// Accepted
+///
+/// The answer of the server to the `Hello` of a client speaking
+/// its version of the protocol, which tells how the rest of the
+/// connection is compressed and encrypted. If an encryption has
+/// been picked, its handshake follows right away.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Accepted {
+    /// The compression of bulk frames, if any
+    #[serde(default)]
+    pub compression: Option<String>,
+    /// The noise protocol the connection is encrypted with, if any
+    #[serde(default)]
+    pub encryption: Option<String>,
+}
+
+/// Rejected
+///
+/// The answer of the server to a client it turns away, after
+/// which the connection is closed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rejected {
+    /// Why the client has been turned away
+    pub reason: String,
+    /// The version of the protocol the server speaks
+    pub server_version: u32,
+}
+
+impl Rejected {
+    /// Creates the rejection of a client
+    ///
+    /// # Arguments
+    ///
+    /// * `reason` - Why the client is turned away
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+            server_version: PROTOCOL_VERSION,
+        }
+    }
+}
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.server_version > PROTOCOL_VERSION {
+            write!(f, "The server runs a newer version of the game (protocol {}, yours is {}), update the game to join it",
+                   self.server_version, PROTOCOL_VERSION)
+        } else if self.server_version < PROTOCOL_VERSION {
+            write!(f, "The server runs an older version of the game (protocol {}, yours is {}), it has to be updated before you can join it",
+                   self.server_version, PROTOCOL_VERSION)
+        } else {
+            write!(f, "The server turned you away: {}", self.reason)
+        }
+    }
+}
+
+/// Welcome
+///
+/// The answer of the server to the `Hello` of a client. It's sent
+/// again whenever the host enters another dimension, after which
+/// the client should drop all chunks it received.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Welcome {
+    /// The name of the player hosting the world
+    pub host: String,
+    /// The name of the dimension the chunks are streamed of
+    pub dimension: String,
+}
+
+/// UnloadChunk
+///
+/// A chunk the server unloaded, which is sent again once
+/// it's loaded again
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UnloadChunk {
+    /// The location of the chunk in chunks
+    pub loc: [i32; 2],
+}
+
+/// BlockChange
+///
+/// A block which has been changed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BlockChange {
+    /// The position of the block in world coordinates
+    pub pos: [i32; 3],
+    /// The name of the material after the change
+    pub material: String,
+}
+
+/// PlayerMoved
+///
+/// The position of a player, sent by clients for their own
+/// player and by the server for all the other players
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerMoved {
+    /// The name of the player, which the server ignores for
+    /// the messages of clients
+    pub name: String,
+    /// The position of the player's eyes
+    pub eye: [f32; 3],
+    /// The direction the player looks in
+    pub look: [f32; 3],
+    /// Whether the player sneaks, which hides their name tag
+    #[serde(default)]
+    pub sneaking: bool,
+}
+
+/// PlayerLeft
+///
+/// A player who left the game
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerLeft {
+    /// The name of the player
+    pub name: String,
+}
+
+/// Message
+///
+/// A message of the protocol:
+/// * `Hello` - A client introduces its player
+/// * `Accepted` - The server accepted the protocol the client speaks
+/// * `Rejected` - The server turned the client away
+/// * `Welcome` - The server accepted a client or the host entered another dimension
+/// * `Chunk` - The blocks of a chunk around the host
+/// * `UnloadChunk` - A chunk has been unloaded
+/// * `BlockChange` - A block has been changed
+/// * `PlayerMoved` - A player moved
+/// * `PlayerLeft` - A player left the game
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Hello(Hello),
+    Accepted(Accepted),
+    Rejected(Rejected),
+    Welcome(Welcome),
+    Chunk(PregenChunk),
+    UnloadChunk(UnloadChunk),
+    BlockChange(BlockChange),
+    PlayerMoved(PlayerMoved),
+    PlayerLeft(PlayerLeft),
+}
+
+impl Message {
+    /// Returns the kind of the message, which precedes its
+    /// payload in a frame
+    fn kind(&self) -> u8 {
+        match self {
+            Message::Hello(_) => 0,
+            Message::Accepted(_) => 1,
+            Message::Rejected(_) => 2,
+            Message::Welcome(_) => 3,
+            Message::Chunk(_) => 4,
+            Message::UnloadChunk(_) => 5,
+            Message::BlockChange(_) => 6,
+            Message::PlayerMoved(_) => 7,
+            Message::PlayerLeft(_) => 8,
+        }
+    }
+
+    /// Encodes the message as a frame
+    pub fn encode(&self) -> io::Result<Vec<u8>> {
+        let payload = match self {
+            Message::Hello(hello) => toml::to_string(hello),
+            Message::Accepted(accepted) => toml::to_string(accepted),
+            Message::Rejected(rejected) => toml::to_string(rejected),
+            Message::Welcome(welcome) => toml::to_string(welcome),
+            Message::Chunk(chunk) => toml::to_string(chunk),
+            Message::UnloadChunk(unload) => toml::to_string(unload),
+            Message::BlockChange(change) => toml::to_string(change),
+            Message::PlayerMoved(moved) => toml::to_string(moved),
+            Message::PlayerLeft(left) => toml::to_string(left),
+        }.map_err(invalid_data)?;
+        if payload.len() > MAX_PAYLOAD {
+            return Err(invalid_data("message too large"));
+        }
+
+        Ok(encode_frame(self.kind(), payload.as_bytes()))
+    }
+
+    /// Reads the next message from a stream, blocking until
+    /// its whole frame has been received
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The stream the frame is read from
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let (kind, payload) = read_frame(reader)?;
+        Self::decode(kind, payload)
+    }
+
+    /// Decodes the payload of a frame
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of the message
+    /// * `payload` - The message encoded as `TOML`
+    fn decode(kind: u8, payload: Vec<u8>) -> io::Result<Self> {
+        let payload = String::from_utf8(payload).map_err(invalid_data)?;
+        match kind {
+            0 => toml::from_str(&payload).map(Message::Hello),
+            1 => toml::from_str(&payload).map(Message::Accepted),
+            2 => toml::from_str(&payload).map(Message::Rejected),
+            3 => toml::from_str(&payload).map(Message::Welcome),
+            4 => toml::from_str(&payload).map(Message::Chunk),
+            5 => toml::from_str(&payload).map(Message::UnloadChunk),
+            6 => toml::from_str(&payload).map(Message::BlockChange),
+            7 => toml::from_str(&payload).map(Message::PlayerMoved),
+            8 => toml::from_str(&payload).map(Message::PlayerLeft),
+            kind => return Err(invalid_data(format!("unknown message kind {}", kind))),
+        }.map_err(invalid_data)
+    }
+
+    /// Writes the message to a stream
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The stream the frame is written to
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.encode()?)
+    }
+}
+
+/// Returns a frame of the given kind and payload
+///
+/// # Arguments
+///
+/// * `kind` - The kind of the message
+/// * `payload` - The encoded message
+fn encode_frame(kind: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + FRAME_HEADER);
+    frame.push(kind);
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Reads the next frame from a stream and returns its kind
+/// and payload
+///
+/// # Arguments
+///
+/// * `reader` - The stream the frame is read from
+fn read_frame<R: Read>(reader: &mut R) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0; FRAME_HEADER];
+    reader.read_exact(&mut header)?;
+    let length = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    if length > MAX_PAYLOAD {
+        return Err(invalid_data("message too large"));
+    }
+    let mut payload = vec![0; length];
+    reader.read_exact(&mut payload)?;
+    Ok((header[0], payload))
+}
+
+/// Returns an error for data which doesn't follow the protocol
+///
+/// # Arguments
+///
+/// * `error` - The description of the error
+fn invalid_data<E: ToString>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+/// Transport
+///
+/// One direction of a connection, which is either sent in the
+/// clear or encrypted as noise messages, each preceded by its
+/// length as a big endian `u16`
+enum Transport {
+    Plain(TcpStream),
+    Encrypted {
+        stream: TcpStream,
+        /// The keys negotiated by the handshake
+        cipher: Arc<StatelessTransportState>,
+        /// The nonce of the next noise message
+        nonce: u64,
+        /// The decrypted data of the last noise message
+        buffer: Vec<u8>,
+        /// How much of the buffer has been read
+        position: usize,
+    },
+}
+
+impl Transport {
+    /// Creates a direction of a connection
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - The connection
+    /// * `cipher` - The keys of the encryption, if any
+    fn new(stream: TcpStream, cipher: Option<Arc<StatelessTransportState>>) -> Self {
+        match cipher {
+            Some(cipher) => Transport::Encrypted { stream, cipher, nonce: 0, buffer: Vec::new(), position: 0 },
+            None => Transport::Plain(stream),
+        }
+    }
+
+    /// Returns the underlying connection
+    fn stream(&self) -> &TcpStream {
+        match self {
+            Transport::Plain(stream) | Transport::Encrypted { stream, .. } => stream,
+        }
+    }
+
+    /// Shuts the connection down in both directions
+    fn shutdown(&self) {
+        let _ = self.stream().shutdown(Shutdown::Both);
+    }
+}
+
+impl Read for Transport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Transport::Plain(stream) => stream.read(buf),
+            Transport::Encrypted { stream, cipher, nonce, buffer, position } => {
+                if *position == buffer.len() {
+                    let record = read_record(stream)?;
+                    buffer.resize(record.len(), 0);
+                    let length = cipher.read_message(*nonce, &record, buffer).map_err(invalid_data)?;
+                    buffer.truncate(length);
+                    *nonce += 1;
+                    *position = 0;
+                }
+                let length = buf.len().min(buffer.len() - *position);
+                buf[..length].copy_from_slice(&buffer[*position..*position + length]);
+                *position += length;
+                Ok(length)
+            }
+        }
+    }
+}
+
+impl Write for Transport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Transport::Plain(stream) => stream.write(buf),
+            Transport::Encrypted { stream, cipher, nonce, .. } => {
+                let length = buf.len().min(NOISE_MESSAGE - NOISE_TAG);
+                if length == 0 {
+                    return Ok(0);
+                }
+                let mut record = vec![0; length + NOISE_TAG];
+                let sealed = cipher.write_message(*nonce, &buf[..length], &mut record).map_err(invalid_data)?;
+                write_record(stream, &record[..sealed])?;
+                *nonce += 1;
+                Ok(length)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Transport::Plain(stream) | Transport::Encrypted { stream, .. } => stream.flush(),
+        }
+    }
+}
+
+/// Reads a noise message preceded by its length
+///
+/// # Arguments
+///
+/// * `stream` - The connection
+fn read_record(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let mut length = [0; 2];
+    stream.read_exact(&mut length)?;
+    let mut record = vec![0; u16::from_be_bytes(length) as usize];
+    stream.read_exact(&mut record)?;
+    Ok(record)
+}
+
+/// Writes a noise message preceded by its length
+///
+/// # Arguments
+///
+/// * `stream` - The connection
+/// * `record` - The noise message
+fn write_record(stream: &mut TcpStream, record: &[u8]) -> io::Result<()> {
+    let mut framed = Vec::with_capacity(record.len() + 2);
+    framed.extend_from_slice(&(record.len() as u16).to_be_bytes());
+    framed.extend_from_slice(record);
+    stream.write_all(&framed)
+}
+
+/// Runs a noise handshake to its end and returns the keys of
+/// the connection
+///
+/// # Arguments
+///
+/// * `stream` - The connection
+/// * `noise` - The initiator or responder of the handshake
+fn handshake(stream: &mut TcpStream, mut noise: HandshakeState) -> io::Result<StatelessTransportState> {
+    let mut message = vec![0; NOISE_MESSAGE];
+    while !noise.is_handshake_finished() {
+        if noise.is_my_turn() {
+            let length = noise.write_message(&[], &mut message).map_err(invalid_data)?;
+            write_record(stream, &message[..length])?;
+        } else {
+            let record = read_record(stream)?;
+            noise.read_message(&record, &mut message).map_err(invalid_data)?;
+        }
+    }
+    noise.into_stateless_transport_mode().map_err(invalid_data)
+}
+
+/// Creates one side of a noise handshake
+///
+/// # Arguments
+///
+/// * `protocol` - The name of the noise protocol
+/// * `initiator` - Whether this side starts the handshake
+fn noise(protocol: &str, initiator: bool) -> io::Result<HandshakeState> {
+    let builder = snow::Builder::new(protocol.parse().map_err(invalid_data)?);
+    if initiator {
+        builder.build_initiator()
+    } else {
+        builder.build_responder()
+    }.map_err(invalid_data)
+}
+
+/// FrameReader
+///
+/// The receiving direction of a connection, which decrypts and
+/// decompresses the frames as the handshake negotiated
+pub struct FrameReader {
+    transport: Transport,
+    /// Whether bulk frames may be compressed
+    compression: bool,
+}
+
+impl FrameReader {
+    /// Reads the next message, blocking until its whole frame
+    /// has been received
+    pub fn read(&mut self) -> io::Result<Message> {
+        let (kind, payload) = read_frame(&mut self.transport)?;
+        if kind & COMPRESSED == 0 {
+            return Message::decode(kind, payload);
+        }
+        if !self.compression {
+            return Err(invalid_data("compressed frame without negotiated compression"));
+        }
+        Message::decode(kind & !COMPRESSED, zstd::bulk::decompress(&payload, MAX_PAYLOAD)?)
+    }
+
+    /// Shuts the connection down in both directions
+    pub fn shutdown(&self) {
+        self.transport.shutdown();
+    }
+}
+
+/// FrameWriter
+///
+/// The sending direction of a connection, which compresses and
+/// encrypts the frames as the handshake negotiated
+pub struct FrameWriter {
+    transport: Transport,
+    /// Whether bulk frames are compressed
+    compression: bool,
+}
+
+impl FrameWriter {
+    /// Writes an encoded message, compressing its payload if
+    /// it's large enough to be worth it
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The encoded message
+    pub fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        if self.compression && frame.len() >= FRAME_HEADER + COMPRESSION_THRESHOLD {
+            let payload = &frame[FRAME_HEADER..];
+            let compressed = zstd::bulk::compress(payload, COMPRESSION_LEVEL)?;
+            if compressed.len() < payload.len() {
+                return self.transport.write_all(&encode_frame(frame[0] | COMPRESSED, &compressed));
+            }
+        }
+        self.transport.write_all(frame)
+    }
+
+    /// Writes a message
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        self.write_frame(&message.encode()?)
+    }
+
+    /// Shuts the connection down in both directions
+    pub fn shutdown(&self) {
+        self.transport.shutdown();
+    }
+}
+
+/// Connection
+///
+/// A connection between a client and the server whose handshake
+/// succeeded
+pub struct Connection {
+    reader: FrameReader,
+    writer: FrameWriter,
+}
+
+impl Connection {
+    /// Connects to a server and introduces the player, offering
+    /// all the compressions and encryptions the game supports.
+    /// If the server turns the player away, e.g. as it runs
+    /// another version of the game, the error tells why.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the server
+    /// * `name` - The name of the player
+    pub fn connect<A: ToSocketAddrs>(address: A, name: &str) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_read_timeout(Some(Duration::from_secs(HANDSHAKE_TIMEOUT)))?;
+        let hello = Hello::new(name);
+        Message::Hello(hello.clone()).write(&mut stream)?;
+
+        let accepted = match Message::read(&mut stream) {
+            Ok(Message::Accepted(accepted)) => accepted,
+            Ok(Message::Rejected(rejected)) => {
+                return Err(io::Error::new(io::ErrorKind::ConnectionRefused, rejected.to_string()));
+            }
+            Ok(message) => return Err(invalid_data(format!("expected to be accepted, got {:?}", message))),
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                return Err(io::Error::new(io::ErrorKind::ConnectionRefused,
+                    "The server closed the connection, it might run another version of the game"));
+            }
+            Err(error) => return Err(error),
+        };
+        let offered = |offers: &[String], picked: &Option<String>| picked.iter().all(|picked| offers.contains(picked));
+        if !offered(&hello.compression, &accepted.compression) || !offered(&hello.encryption, &accepted.encryption) {
+            return Err(invalid_data("the server picked a compression or encryption which hasn't been offered"));
+        }
+
+        let cipher = match &accepted.encryption {
+            Some(protocol) => Some(handshake(&mut stream, noise(protocol, true)?)?),
+            None => None,
+        };
+        stream.set_read_timeout(None)?;
+        Self::new(stream, accepted.compression.is_some(), cipher)
+    }
+
+    /// Accepts a client connecting to the server. The client is
+    /// turned away if it doesn't introduce its player first or
+    /// speaks another version of the protocol, which is returned
+    /// as an error of the kind `ConnectionRefused`.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - The connection of the client
+    pub fn accept(mut stream: TcpStream) -> io::Result<(Self, Hello)> {
+        stream.set_read_timeout(Some(Duration::from_secs(HANDSHAKE_TIMEOUT)))?;
+        let hello = match Message::read(&mut stream)? {
+            Message::Hello(hello) => hello,
+            _ => return Err(reject(&mut stream, "the client has to introduce its player first")),
+        };
+        if hello.version != PROTOCOL_VERSION {
+            let reason = format!("the server speaks version {} of the protocol, not {}", PROTOCOL_VERSION, hello.version);
+            return Err(reject(&mut stream, reason));
+        }
+        if hello.name.trim().is_empty() {
+            return Err(reject(&mut stream, "the player needs a name"));
+        }
+
+        let accepted = Accepted {
+            compression: hello.compression.iter().find(|offer| *offer == COMPRESSION).cloned(),
+            encryption: hello.encryption.iter().find(|offer| *offer == ENCRYPTION).cloned(),
+        };
+        Message::Accepted(accepted.clone()).write(&mut stream)?;
+        let cipher = match &accepted.encryption {
+            Some(protocol) => Some(handshake(&mut stream, noise(protocol, false)?)?),
+            None => None,
+        };
+        stream.set_read_timeout(None)?;
+        Ok((Self::new(stream, accepted.compression.is_some(), cipher)?, hello))
+    }
+
+    /// Creates a connection whose handshake succeeded
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - The connection
+    /// * `compression` - Whether bulk frames are compressed
+    /// * `cipher` - The keys of the encryption, if any
+    fn new(stream: TcpStream, compression: bool, cipher: Option<StatelessTransportState>) -> io::Result<Self> {
+        let cipher = cipher.map(Arc::new);
+        Ok(Self {
+            reader: FrameReader {
+                transport: Transport::new(stream.try_clone()?, cipher.clone()),
+                compression,
+            },
+            writer: FrameWriter {
+                transport: Transport::new(stream, cipher),
+                compression,
+            },
+        })
+    }
+
+    /// Reads the next message, blocking until its whole frame
+    /// has been received
+    pub fn read(&mut self) -> io::Result<Message> {
+        self.reader.read()
+    }
+
+    /// Writes a message
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        self.writer.send(message)
+    }
+
+    /// Sets how long reading a message may block, forever if
+    /// no timeout is given
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The timeout of reads
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.reader.transport.stream().set_read_timeout(timeout)
+    }
+
+    /// Splits the connection into its directions, which can be
+    /// used on threads of their own
+    pub fn split(self) -> (FrameReader, FrameWriter) {
+        (self.reader, self.writer)
+    }
+}
+
+/// Tells a client why it's turned away and returns the error
+/// the handshake fails with
+///
+/// # Arguments
+///
+/// * `stream` - The connection of the client
+/// * `reason` - Why the client is turned away
+fn reject(stream: &mut TcpStream, reason: impl Into<String>) -> io::Error {
+    let rejected = Rejected::new(reason);
+    if let Err(error) = Message::Rejected(rejected.clone()).write(stream) {
+        log::debug!("Failed to reject client: {}", error);
+    }
+    io::Error::new(io::ErrorKind::ConnectionRefused, rejected.reason)
+}
+
+/// ServerEvent
+///
+/// A change of the players connected to the server:
+/// * `Joined` - A player joined the game
+/// * `Left` - A player left the game
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ServerEvent {
+    Joined(String),
+    Left(String),
+}
+
+/// ConnectionEvent
+///
+/// What the thread reading from a client received:
+/// * `Joined` - The client introduced its player
+/// * `Moved` - The player of the client moved
+/// * `Closed` - The connection has been closed
+enum ConnectionEvent {
+    Joined {
+        id: u32,
+        name: String,
+        frames: SyncSender<Arc<Vec<u8>>>,
+        connection: TcpStream,
+    },
+    Moved {
+        id: u32,
+        moved: PlayerMoved,
+    },
+    Closed(u32),
+}
+
+/// RemoteClient
+///
+/// A client connected to the server
+struct RemoteClient {
+    /// A sender for the frames the client should receive,
+    /// which are written by the thread of the connection
+    frames: SyncSender<Arc<Vec<u8>>>,
+    /// The connection of the client, which is shut down when
+    /// the client is kicked
+    connection: TcpStream,
+    /// The player of the client where they moved last
+    player: PlayerMoved,
+    /// The locations of the chunks the client received
+    chunks: HashSet<[i32; 2]>,
+}
+
+impl RemoteClient {
+    /// Queues a frame to be sent to the client. Returns
+    /// `false` if the connection has been closed or the
+    /// client fell too far behind to queue another frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The encoded message
+    fn send(&self, frame: &Arc<Vec<u8>>) -> bool {
+        self.frames.try_send(frame.clone()).is_ok()
+    }
+}
+
+/// ListenServer
+///
+/// The server which hosts the world of the simulation for
+/// the clients connecting to it. The connections are handled
+/// on their own threads, while the simulation decides what
+/// is sent to the clients.
+pub struct ListenServer {
+    /// The address the server listens on
+    address: SocketAddr,
+    /// The name of the player hosting the world
+    host: String,
+    /// The dimension the chunks are streamed of
+    dimension: String,
+    /// The position of the host last sent to the clients
+    host_position: Option<PlayerMoved>,
+    /// A receiver for the events of the connections
+    events: Receiver<ConnectionEvent>,
+    /// The clients which introduced their player by their id
+    clients: HashMap<u32, RemoteClient>,
+    /// The clients whose frames couldn't be queued, which
+    /// are dropped by the next poll
+    stalled: HashSet<u32>,
+}
+
+impl ListenServer {
+    /// Starts listening for clients on a new thread
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address the server listens on, e.g. `0.0.0.0:25565`
+    /// * `host` - The name of the player hosting the world
+    /// * `dimension` - The dimension the host is in
+    pub fn bind(address: &str, host: &str, dimension: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        log::info!("Listening for players on {}", address);
+
+        let (sender, events) = channel();
+        thread::Builder::new()
+            .name("listen-server".into())
+            .spawn(move || {
+                for (id, stream) in (0..).zip(listener.incoming()) {
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(error) => {
+                            log::warn!("Failed to accept client: {}", error);
+                            continue;
+                        }
+                    };
+                    let events = sender.clone();
+                    let spawned = thread::Builder::new()
+                        .name(format!("client-{}", id))
+                        .spawn(move || handle_connection(id, stream, events));
+                    if let Err(error) = spawned {
+                        log::warn!("Failed to spawn thread of client: {}", error);
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            address,
+            host: host.to_string(),
+            dimension: dimension.to_string(),
+            host_position: None,
+            events,
+            clients: HashMap::new(),
+            stalled: HashSet::new(),
+        })
+    }
+
+    /// Returns the address the server listens on
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Returns the amount of connected clients
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Returns whether no client is connected
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Returns the players of the connected clients where
+    /// they moved last
+    pub fn players(&self) -> impl Iterator<Item = &PlayerMoved> {
+        self.clients.values().map(|client| &client.player)
+    }
+
+    /// Handles what the clients sent since the last call and
+    /// returns which players joined or left. Clients whose name
+    /// is already taken are turned away and clients which fell
+    /// too far behind are kicked.
+    pub fn poll(&mut self) -> Vec<ServerEvent> {
+        let mut events = Vec::new();
+        loop {
+            match self.events.try_recv() {
+                Ok(ConnectionEvent::Joined { id, name, frames, connection }) => {
+                    if name == self.host || self.players().any(|player| player.name == name) {
+                        log::info!("Turned away client {} as {} is already playing", id, name);
+                        let rejected = Rejected::new(format!("{} is already playing", name));
+                        let _ = frames.try_send(frame(&Message::Rejected(rejected)));
+                        continue;
+                    }
+                    let welcome = Welcome {
+                        host: self.host.clone(),
+                        dimension: self.dimension.clone(),
+                    };
+                    let client = RemoteClient {
+                        frames,
+                        connection,
+                        player: PlayerMoved {
+                            name: name.clone(),
+                            eye: [0.0; 3],
+                            look: [0.0, 0.0, -1.0],
+                            sneaking: false,
+                        },
+                        chunks: HashSet::new(),
+                    };
+                    let mut sent = client.send(&frame(&Message::Welcome(welcome)));
+                    for player in self.host_position.iter().chain(self.players()) {
+                        sent &= client.send(&frame(&Message::PlayerMoved(player.clone())));
+                    }
+                    if !sent {
+                        self.stalled.insert(id);
+                    }
+                    self.clients.insert(id, client);
+                    events.push(ServerEvent::Joined(name));
+                }
+                Ok(ConnectionEvent::Moved { id, moved }) => {
+                    let player = match self.clients.get_mut(&id) {
+                        Some(client) => {
+                            client.player = PlayerMoved { name: client.player.name.clone(), ..moved };
+                            client.player.clone()
+                        }
+                        None => continue,
+                    };
+                    let moved = frame(&Message::PlayerMoved(player));
+                    for (other, client) in self.clients.iter().filter(|(other, _)| **other != id) {
+                        if !client.send(&moved) {
+                            self.stalled.insert(*other);
+                        }
+                    }
+                }
+                Ok(ConnectionEvent::Closed(id)) => {
+                    if let Some(name) = self.remove_client(id) {
+                        events.push(ServerEvent::Left(name));
+                    }
+                }
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => break,
+            }
+        }
+
+        while let Some(&id) = self.stalled.iter().next() {
+            self.stalled.remove(&id);
+            if let Some(name) = self.remove_client(id) {
+                log::info!("Kicked {} as they fell too far behind", name);
+                events.push(ServerEvent::Left(name));
+            }
+        }
+        events
+    }
+
+    /// Drops a client, closes its connection and tells the other
+    /// clients its player left. Returns the name of the player,
+    /// if the client had joined.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the client
+    fn remove_client(&mut self, id: u32) -> Option<String> {
+        let client = self.clients.remove(&id)?;
+        let _ = client.connection.shutdown(Shutdown::Both);
+        let name = client.player.name;
+        self.broadcast(&Message::PlayerLeft(PlayerLeft { name: name.clone() }));
+        Some(name)
+    }
+
+    /// Sends a message to all clients
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message
+    pub fn broadcast(&mut self, message: &Message) {
+        self.broadcast_frame(&frame(message));
+    }
+
+    /// Queues an encoded message for all clients and marks the
+    /// clients which can't take it to be dropped
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The encoded message
+    fn broadcast_frame(&mut self, frame: &Arc<Vec<u8>>) {
+        for (id, client) in self.clients.iter() {
+            if !client.send(frame) {
+                self.stalled.insert(*id);
+            }
+        }
+    }
+
+    /// Makes the dimension the chunks are streamed of the given
+    /// one. The clients are welcomed to the dimension again and
+    /// receive its chunks from scratch.
+    ///
+    /// # Arguments
+    ///
+    /// * `dimension` - The name of the dimension the host is in
+    pub fn set_dimension(&mut self, dimension: &str) {
+        if self.dimension == dimension {
+            return;
+        }
+        self.dimension = dimension.to_string();
+        for client in self.clients.values_mut() {
+            client.chunks.clear();
+        }
+        self.broadcast(&Message::Welcome(Welcome {
+            host: self.host.clone(),
+            dimension: self.dimension.clone(),
+        }));
+    }
+
+    /// Sends the changed blocks to all clients
+    ///
+    /// # Arguments
+    ///
+    /// * `changes` - The blocks changed since the last call
+    pub fn send_changes(&mut self, changes: &[BlockEdit]) {
+        for change in changes {
+            self.broadcast(&Message::BlockChange(BlockChange {
+                pos: change.pos.into(),
+                material: change.new.name().to_string(),
+            }));
+        }
+    }
+
+    /// Sends the position of the host to all clients, if it
+    /// changed since it has been sent the last time
+    ///
+    /// # Arguments
+    ///
+    /// * `eye` - The position of the host's eyes
+    /// * `look` - The direction the host looks in
+    /// * `sneaking` - Whether the host sneaks
+    pub fn send_host_position(&mut self, eye: Vector3<f32>, look: Vector3<f32>, sneaking: bool) {
+        let position = PlayerMoved {
+            name: self.host.clone(),
+            eye: eye.into(),
+            look: look.into(),
+            sneaking,
+        };
+        if self.host_position.as_ref() == Some(&position) {
+            return;
+        }
+        self.broadcast(&Message::PlayerMoved(position.clone()));
+        self.host_position = Some(position);
+    }
+
+    /// Streams the loaded chunks to the clients. Each client
+    /// receives the generated chunks it's missing in the order
+    /// they are given, a few per call, and is told about the
+    /// chunks which have been unloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunks` - The loaded chunks, the nearest ones to the host first
+    pub fn send_chunks(&mut self, chunks: &[Chunk]) {
+        if self.clients.is_empty() {
+            return;
+        }
+        let loaded: HashSet<[i32; 2]> = chunks.iter().map(chunk_loc).collect();
+        let mut encoded: HashMap<[i32; 2], Option<Arc<Vec<u8>>>> = HashMap::new();
+
+        for (id, client) in self.clients.iter_mut() {
+            if self.stalled.contains(id) {
+                continue;
+            }
+            let unloaded: Vec<[i32; 2]> = client.chunks.iter().filter(|loc| !loaded.contains(*loc)).copied().collect();
+            for loc in unloaded {
+                client.chunks.remove(&loc);
+                if !client.send(&frame(&Message::UnloadChunk(UnloadChunk { loc }))) {
+                    self.stalled.insert(*id);
+                }
+            }
+
+            let missing: Vec<&Chunk> = chunks.iter()
+                .filter(|chunk| chunk.is_generated() && !client.chunks.contains(&chunk_loc(chunk)))
+                .take(CHUNKS_PER_UPDATE)
+                .collect();
+            for chunk in missing {
+                let loc = chunk_loc(chunk);
+                let frame = encoded.entry(loc).or_insert_with(|| match PregenChunk::encode(chunk) {
+                    Ok(encoded) => Some(frame(&Message::Chunk(encoded))),
+                    Err(error) => {
+                        log::warn!("Failed to send chunk {:?}: {}", loc, error);
+                        None
+                    }
+                });
+                let frame = match frame {
+                    Some(frame) => frame,
+                    None => continue,
+                };
+                if !client.send(frame) {
+                    self.stalled.insert(*id);
+                    break;
+                }
+                client.chunks.insert(loc);
+            }
+        }
+    }
+}
+
+/// Returns the location of a chunk as it's sent
+///
+/// # Arguments
+///
+/// * `chunk` - The chunk
+fn chunk_loc(chunk: &Chunk) -> [i32; 2] {
+    let loc = chunk.loc();
+    [loc.x, loc.y]
+}
+
+/// Encodes a message as a frame which could be shared by the
+/// connections. Messages which can't be encoded are sent as
+/// empty frames, which are skipped.
+///
+/// # Arguments
+///
+/// * `message` - The message
+fn frame(message: &Message) -> Arc<Vec<u8>> {
+    Arc::new(message.encode().unwrap_or_else(|error| {
+        log::error!("Failed to encode message: {}", error);
+        Vec::new()
+    }))
+}
+
+/// Handles the connection of a client until it's closed. The
+/// client has to introduce its player first, afterwards the
+/// frames queued for the client are written on a thread of
+/// their own while its messages are read.
+///
+/// # Arguments
+///
+/// * `id` - The id of the connection
+/// * `stream` - The connection of the client
+/// * `events` - A sender for the events of the connection
+fn handle_connection(id: u32, stream: TcpStream, events: Sender<ConnectionEvent>) {
+    let peer = stream.peer_addr();
+    let connection = match stream.try_clone() {
+        Ok(connection) => connection,
+        Err(error) => {
+            log::warn!("Failed to set up connection of client {}: {}", id, error);
+            return;
+        }
+    };
+    let (mut reader, writer, hello) = match Connection::accept(stream) {
+        Ok((accepted, hello)) => {
+            let (reader, writer) = accepted.split();
+            (reader, writer, hello)
+        }
+        Err(error) if error.kind() == io::ErrorKind::ConnectionRefused => {
+            log::info!("Turned away client {}: {}", id, error);
+            let _ = connection.shutdown(Shutdown::Both);
+            return;
+        }
+        Err(error) => {
+            log::warn!("Failed to read handshake of client {}: {}", id, error);
+            let _ = connection.shutdown(Shutdown::Both);
+            return;
+        }
+    };
+
+    let (frames, queued) = sync_channel::<Arc<Vec<u8>>>(CLIENT_QUEUE);
+    let spawned = thread::Builder::new()
+        .name(format!("client-{}-writer", id))
+        .spawn(move || write_frames(writer, queued));
+    if let Err(error) = spawned {
+        log::warn!("Failed to spawn writer of client {}: {}", id, error);
+        return;
+    }
+    log::info!("{} connected from {:?}", hello.name, peer);
+    if events.send(ConnectionEvent::Joined { id, name: hello.name, frames, connection }).is_err() {
+        return;
+    }
+
+    loop {
+        match reader.read() {
+            Ok(Message::PlayerMoved(moved)) => {
+                if events.send(ConnectionEvent::Moved { id, moved }).is_err() {
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(error) => {
+                log::debug!("Closed connection of client {}: {}", id, error);
+                break;
+            }
+        }
+    }
+    reader.shutdown();
+    let _ = events.send(ConnectionEvent::Closed(id));
+}
+
+/// Writes the frames queued for a client until the server
+/// drops the client or the connection fails, which closes
+/// the connection
+///
+/// # Arguments
+///
+/// * `writer` - The sending direction of the connection
+/// * `queued` - A receiver for the frames to write
+fn write_frames(mut writer: FrameWriter, queued: Receiver<Arc<Vec<u8>>>) {
+    for frame in queued.iter() {
+        if let Err(error) = writer.write_frame(&frame) {
+            log::debug!("Failed to write to client: {}", error);
+            break;
+        }
+    }
+    writer.shutdown();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::Material;
+    use std::time::Instant;
+
+    /// Polls the server until an event happens
+    fn wait_for_event(server: &mut ListenServer) -> ServerEvent {
+        let start = Instant::now();
+        loop {
+            if let Some(event) = server.poll().pop() {
+                return event;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "no event");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn messages_survive_a_round_trip() {
+        let messages = [
+            Message::Hello(Hello::new("steve")),
+            Message::Accepted(Accepted { compression: Some(COMPRESSION.into()), encryption: None }),
+            Message::Rejected(Rejected::new("full")),
+            Message::BlockChange(BlockChange { pos: [1, -2, 3], material: "stone".into() }),
+            Message::PlayerMoved(PlayerMoved { name: "alex".into(), eye: [0.5, 64.0, -3.25], look: [0.0, 0.0, -1.0], sneaking: true }),
+            Message::UnloadChunk(UnloadChunk { loc: [-1, 7] }),
+        ];
+        let mut stream = Vec::new();
+        for message in messages.iter() {
+            message.write(&mut stream).unwrap();
+        }
+
+        let mut reader = stream.as_slice();
+        for message in messages.iter() {
+            assert_eq!(&Message::read(&mut reader).unwrap(), message);
+        }
+        assert!(Message::read(&mut reader).is_err());
+
+        let mut unknown: &[u8] = &[42, 0, 0, 0, 0];
+        assert_eq!(Message::read(&mut unknown).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn clients_receive_the_world_from_the_server() {
+        let mut server = ListenServer::bind("127.0.0.1:0", "host", "overworld").unwrap();
+        let address = server.address();
+        let connecting = thread::spawn(move || Connection::connect(address, "guest"));
+
+        assert_eq!(wait_for_event(&mut server), ServerEvent::Joined("guest".into()));
+        let mut client = connecting.join().unwrap().unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(client.read().unwrap(), Message::Welcome(Welcome {
+            host: "host".into(),
+            dimension: "overworld".into(),
+        }));
+
+        // Enough different blocks for the chunk to be compressed
+        let chunk = Chunk::new(cgmath::Vector2::new(2, -1));
+        for x in 0..16 {
+            for z in 0..16 {
+                let material = if (x + z) % 2 == 0 { Material::Stone } else { Material::Dirt };
+                chunk.set_block(Vector3::new(x, 2, z), material);
+            }
+        }
+        chunk.mark_generated();
+        server.send_chunks(&[chunk]);
+        server.send_changes(&[BlockEdit { pos: Vector3::new(33, 2, -13), old: Material::Stone, new: Material::Air }]);
+        server.send_host_position(Vector3::new(1.0, 2.0, 3.0), Vector3::new(0.0, 0.0, -1.0), false);
+
+        match client.read().unwrap() {
+            Message::Chunk(received) => {
+                let restored = Chunk::new(cgmath::Vector2::new(2, -1));
+                assert!(received.restore(&restored));
+                assert_eq!(restored.block(Vector3::new(1, 2, 2)), Some(Material::Dirt));
+                assert_eq!(restored.block(Vector3::new(1, 2, 1)), Some(Material::Stone));
+            }
+            message => panic!("unexpected message {:?}", message),
+        }
+        assert_eq!(client.read().unwrap(), Message::BlockChange(BlockChange {
+            pos: [33, 2, -13],
+            material: "air".into(),
+        }));
+        assert!(matches!(client.read().unwrap(), Message::PlayerMoved(moved) if moved.name == "host"));
+
+        client.send(&Message::PlayerMoved(PlayerMoved { name: "anyone".into(), eye: [4.0, 5.0, 6.0], look: [1.0, 0.0, 0.0], sneaking: true }))
+            .unwrap();
+        let start = Instant::now();
+        while !server.players().any(|player| player.eye == [4.0, 5.0, 6.0]) {
+            assert!(start.elapsed() < Duration::from_secs(5), "no movement");
+            server.poll();
+            thread::sleep(Duration::from_millis(10));
+        }
+        let player = server.players().next().unwrap();
+        assert_eq!(player.name, "guest");
+        assert!(player.sneaking);
+
+        drop(client);
+        assert_eq!(wait_for_event(&mut server), ServerEvent::Left("guest".into()));
+        assert!(server.is_empty());
+    }
+
+    #[test]
+    fn frames_are_compressed_and_encrypted_as_negotiated() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let accepting = thread::spawn(move || Connection::accept(listener.accept().unwrap().0).unwrap());
+        let mut client = Connection::connect(address, "guest").unwrap();
+        let (mut server, hello) = accepting.join().unwrap();
+        assert_eq!(hello, Hello::new("guest"));
+        assert!(matches!(server.writer.transport, Transport::Encrypted { .. }));
+        assert!(server.writer.compression);
+
+        let bulk = Message::Rejected(Rejected::new("full ".repeat(2048)));
+        let small = Message::Accepted(Accepted { compression: None, encryption: None });
+        server.send(&bulk).unwrap();
+        server.send(&small).unwrap();
+        assert_eq!(client.read().unwrap(), bulk);
+        assert_eq!(client.read().unwrap(), small);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let sending = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut receiving = listener.accept().unwrap().0;
+        let mut writer = FrameWriter { transport: Transport::Plain(sending), compression: true };
+        writer.send(&bulk).unwrap();
+        writer.send(&bulk).unwrap();
+        let (kind, payload) = read_frame(&mut receiving).unwrap();
+        assert_eq!(kind, 2 | COMPRESSED);
+        assert!(payload.len() < bulk.encode().unwrap().len() / 10);
+        let mut uncompressing = FrameReader { transport: Transport::Plain(receiving), compression: false };
+        assert_eq!(uncompressing.read().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn clients_are_told_why_they_are_turned_away() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let accepting = thread::spawn(move || {
+            for _ in 0..2 {
+                let refused = Connection::accept(listener.accept().unwrap().0).err().unwrap();
+                assert_eq!(refused.kind(), io::ErrorKind::ConnectionRefused);
+            }
+        });
+
+        let mut client = TcpStream::connect(address).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        Message::Hello(Hello { version: PROTOCOL_VERSION + 1, ..Hello::new("guest") }).write(&mut client).unwrap();
+        match Message::read(&mut client).unwrap() {
+            Message::Rejected(rejected) => assert_eq!(rejected.server_version, PROTOCOL_VERSION),
+            message => panic!("unexpected message {:?}", message),
+        }
+
+        let unnamed = Connection::connect(address, " ").err().unwrap();
+        assert_eq!(unnamed.kind(), io::ErrorKind::ConnectionRefused);
+        assert!(unnamed.to_string().contains("needs a name"));
+        accepting.join().unwrap();
+
+        let newer = Rejected { reason: String::new(), server_version: PROTOCOL_VERSION + 1 };
+        assert!(newer.to_string().contains("newer version of the game"));
+    }
+
+    #[test]
+    fn clients_whose_name_is_taken_are_turned_away() {
+        let mut server = ListenServer::bind("127.0.0.1:0", "host", "overworld").unwrap();
+        let address = server.address();
+        let impostor = thread::spawn(move || Connection::connect(address, "host").and_then(|mut client| client.read()));
+        let start = Instant::now();
+        while !impostor.is_finished() {
+            assert!(start.elapsed() < Duration::from_secs(5), "no rejection");
+            server.poll();
+            thread::sleep(Duration::from_millis(10));
+        }
+        let rejected = impostor.join().unwrap().unwrap();
+        assert!(matches!(rejected, Message::Rejected(rejected) if rejected.reason.contains("host")));
+        assert!(server.is_empty());
+    }
+
+    #[test]
+    fn clients_falling_behind_are_kicked() {
+        let mut server = ListenServer::bind("127.0.0.1:0", "host", "overworld").unwrap();
+        let mut client = TcpStream::connect(server.address()).unwrap();
+        Message::Hello(Hello { compression: Vec::new(), encryption: Vec::new(), ..Hello::new("guest") }).write(&mut client).unwrap();
+        assert_eq!(wait_for_event(&mut server), ServerEvent::Joined("guest".into()));
+
+        // The client never reads, so the socket fills up and the queue after it
+        let bulk = Arc::new(vec![0; 1024 * 1024]);
+        let start = Instant::now();
+        while !server.is_empty() {
+            assert!(start.elapsed() < Duration::from_secs(10), "not kicked");
+            server.broadcast_frame(&bulk);
+            server.poll();
+        }
+    }
+}
@@ -0,0 +1,675 @@
+//! Types to show transient notifications, so called
+//! toasts, on top of the rendered world
+
+use crate::camera::OrthographicCamera;
+use crate::graphics::attribute;
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::font::{self, GLYPH_SIZE};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::shader::ShaderProgram;
+use crate::graphics::texture::Texture;
+use crate::impl_vertex;
+use crate::resources::Resources;
+use crate::scripting::ScriptEngine;
+use crate::timestep::TimeStep;
+use cgmath::Vector4;
+use mlua::Table;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// The default time a notification is shown in seconds
+pub const DEFAULT_DURATION: f32 = 4.0;
+/// The time a toast takes to fade in and out in seconds
+const FADE_DURATION: f32 = 0.4;
+/// The maximal amount of toasts shown at once, further
+/// notifications are queued
+const MAX_VISIBLE_TOASTS: usize = 4;
+/// The maximal amount of characters of a single line
+const MAX_LINE_LENGTH: usize = 40;
+
+/// The height of the overlay in virtual pixels, the
+/// width depends on the aspect ratio
+pub(crate) const OVERLAY_HEIGHT: f32 = 360.0;
+/// The distance of the toasts to the edges of the screen
+const TOAST_MARGIN: f32 = 8.0;
+/// The distance of the text to the edges of a toast
+const TOAST_PADDING: f32 = 6.0;
+/// The minimal width of a toast
+const TOAST_MIN_WIDTH: f32 = 120.0;
+/// The width of the colored bar marking the kind of a toast
+const ACCENT_WIDTH: f32 = 3.0;
+/// The vertical distance of two lines of text
+const LINE_SPACING: f32 = 4.0;
+/// The distance of the text of debug panels to their edges
+const PANEL_PADDING: f32 = 6.0;
+
+/// NotificationKind
+///
+/// The kind of a notification, which decides on
+/// the color of its toast:
+/// * `Info` - General information, e.g. the world has been saved
+/// * `Achievement` - An achievement has been unlocked
+/// * `Warning` - Something didn't work as expected
+/// * `Error` - Something failed, e.g. a mod
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum NotificationKind {
+    Info,
+    Achievement,
+    Warning,
+    Error,
+}
+
+impl NotificationKind {
+    /// Returns the kind with the given name as used by scripts,
+    /// or `None` if there is no such kind
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the kind
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "info" => Some(NotificationKind::Info),
+            "achievement" => Some(NotificationKind::Achievement),
+            "warning" => Some(NotificationKind::Warning),
+            "error" => Some(NotificationKind::Error),
+            _ => None,
+        }
+    }
+
+    /// Returns the accent color of the kind
+    pub fn color(&self) -> Vector4<f32> {
+        match self {
+            NotificationKind::Info => Vector4::new(0.4, 0.7, 1.0, 1.0),
+            NotificationKind::Achievement => Vector4::new(1.0, 0.8, 0.2, 1.0),
+            NotificationKind::Warning => Vector4::new(1.0, 0.55, 0.1, 1.0),
+            NotificationKind::Error => Vector4::new(1.0, 0.25, 0.25, 1.0),
+        }
+    }
+}
+
+/// Notification
+///
+/// A notification which should be shown to the player
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+    /// The kind of the notification
+    pub kind: NotificationKind,
+    /// The title, shown in the first line
+    pub title: String,
+    /// The message, shown below the title. It could be empty.
+    pub message: String,
+    /// The time the notification is shown in seconds,
+    /// including fading in and out
+    pub duration: f32,
+}
+
+impl Notification {
+    /// Creates a new notification which is shown for
+    /// the default duration
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of the notification
+    /// * `title` - The title of the notification
+    /// * `message` - The message of the notification
+    pub fn new(kind: NotificationKind, title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            title: title.into(),
+            message: message.into(),
+            duration: DEFAULT_DURATION,
+        }
+    }
+}
+
+/// Toast
+///
+/// A notification as it's currently shown on the screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct Toast {
+    /// The kind of the notification
+    pub kind: NotificationKind,
+    /// The title of the notification
+    pub title: String,
+    /// The message of the notification
+    pub message: String,
+    /// The opacity of the toast, from `0.0` to `1.0`
+    pub opacity: f32,
+}
+
+/// A notification which is currently shown
+struct ActiveNotification {
+    /// The notification
+    notification: Notification,
+    /// The time the notification has been shown in seconds
+    age: f32,
+}
+
+impl ActiveNotification {
+    /// Returns the opacity of the notification, which fades
+    /// in after it's shown and out before it expires
+    fn opacity(&self) -> f32 {
+        let fade = FADE_DURATION.min(self.notification.duration / 2.0);
+        if fade <= 0.0 {
+            return 0.0;
+        }
+        let fade_in = self.age / fade;
+        let fade_out = (self.notification.duration - self.age) / fade;
+        fade_in.min(fade_out).clamp(0.0, 1.0)
+    }
+
+    /// Returns whether the notification has been shown long enough
+    fn is_expired(&self) -> bool {
+        self.age >= self.notification.duration
+    }
+}
+
+/// NotificationManager
+///
+/// The `NotificationManager` queues notifications and decides
+/// which of them are currently shown as toasts. It's owned by
+/// the simulation thread. Scripts push notifications through
+/// the `notifications.push` function, e.g.
+///
+/// ```lua
+/// notifications.push {
+///     title = "Hello",
+///     message = "My mod has been loaded",
+///     kind = "info",
+///     duration = 5,
+/// }
+/// ```
+///
+/// Only the title is required, the kind defaults to `info`.
+pub struct NotificationManager {
+    /// The notifications pushed by scripts since the last update
+    pushed: Arc<Mutex<Vec<Notification>>>,
+    /// The notifications waiting to be shown
+    queue: VecDeque<Notification>,
+    /// The notifications which are currently shown, the
+    /// oldest one first
+    active: Vec<ActiveNotification>,
+}
+
+impl NotificationManager {
+    /// Creates a new notification manager and registers its
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let pushed = Arc::new(Mutex::new(Vec::new()));
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let sender = pushed.clone();
+        api.set("push", lua.create_function(move |_, def: Table| {
+            let kind = match def.get::<_, Option<String>>("kind")? {
+                Some(name) => NotificationKind::from_name(&name).ok_or_else(|| {
+                    mlua::Error::RuntimeError(format!("unknown notification kind '{}'", name))
+                })?,
+                None => NotificationKind::Info,
+            };
+
+            let mut notification = Notification::new(
+                kind,
+                def.get::<_, String>("title")?,
+                def.get::<_, Option<String>>("message")?.unwrap_or_default(),
+            );
+            if let Some(duration) = def.get::<_, Option<f32>>("duration")? {
+                notification.duration = duration.max(0.0);
+            }
+
+            sender.lock().unwrap().push(notification);
+            Ok(())
+        })?)?;
+        lua.globals().set("notifications", api)?;
+
+        Ok(Self {
+            pushed,
+            queue: VecDeque::new(),
+            active: Vec::new(),
+        })
+    }
+
+    /// Queues a notification. It's shown as soon as there
+    /// is space for another toast.
+    ///
+    /// # Arguments
+    ///
+    /// * `notification` - The notification
+    pub fn push(&mut self, notification: Notification) {
+        self.queue.push_back(notification);
+    }
+
+    /// Advances the shown notifications by the given time step,
+    /// removes the expired ones and shows queued ones instead
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    pub fn update(&mut self, time_step: TimeStep) {
+        let pushed: Vec<Notification> = self.pushed.lock().unwrap().drain(..).collect();
+        self.queue.extend(pushed);
+
+        for active in self.active.iter_mut() {
+            active.age += time_step.seconds();
+        }
+        self.active.retain(|active| !active.is_expired());
+
+        while self.active.len() < MAX_VISIBLE_TOASTS {
+            match self.queue.pop_front() {
+                Some(notification) => self.active.push(ActiveNotification {
+                    notification,
+                    age: 0.0,
+                }),
+                None => break,
+            }
+        }
+    }
+
+    /// Returns the toasts which are currently shown,
+    /// the oldest one first
+    pub fn toasts(&self) -> Vec<Toast> {
+        self.active.iter()
+            .map(|active| Toast {
+                kind: active.notification.kind,
+                title: truncate(&active.notification.title),
+                message: truncate(&active.notification.message),
+                opacity: active.opacity(),
+            })
+            .collect()
+    }
+}
+
+/// Shortens a text which doesn't fit into a single line
+/// of a toast
+///
+/// # Arguments
+///
+/// * `text` - The text
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= MAX_LINE_LENGTH {
+        return text.to_string();
+    }
+
+    let mut truncated: String = text.chars().take(MAX_LINE_LENGTH - 3).collect();
+    truncated.push_str("...");
+    truncated
+}
+
+/// ToastFrame
+///
+/// A frame toasts could be rendered for
+pub trait ToastFrame: Frame {
+    /// Returns the toasts which are currently shown
+    fn toasts(&self) -> &[Toast];
+}
+
+/// A vertex of the 2D overlay
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct OverlayVertex {
+    /// The position in virtual pixels
+    position: [f32; 2],
+    /// The texture coordinate within the font atlas
+    tex_coord: [f32; 2],
+    /// The color the font atlas is tinted with
+    color: [f32; 4],
+}
+
+impl_vertex!(OverlayVertex {
+    position => attribute::POSITION,
+    tex_coord => attribute::TEX_COORD,
+    color => attribute::COLOR,
+});
+
+/// Collects the quads of the overlay, which are all
+/// textured with the font atlas
+#[derive(Default)]
+pub(crate) struct OverlayBuilder {
+    /// The vertices of all quads
+    vertices: Vec<OverlayVertex>,
+    /// The indices of all quads
+    indices: Vec<u32>,
+}
+
+impl OverlayBuilder {
+    /// Adds a quad
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - The top left corner in virtual pixels
+    /// * `max` - The bottom right corner in virtual pixels
+    /// * `uv_min` - The bottom left texture coordinate
+    /// * `uv_max` - The top right texture coordinate
+    /// * `color` - The color of the quad
+    fn quad(&mut self, min: [f32; 2], max: [f32; 2], uv_min: [f32; 2], uv_max: [f32; 2], color: Vector4<f32>) {
+        let base = self.vertices.len() as u32;
+        let color = [color.x, color.y, color.z, color.w];
+        let corners = [
+            ([min[0], max[1]], [uv_min[0], uv_min[1]]),
+            ([max[0], max[1]], [uv_max[0], uv_min[1]]),
+            ([max[0], min[1]], [uv_max[0], uv_max[1]]),
+            ([min[0], min[1]], [uv_min[0], uv_max[1]]),
+        ];
+        self.vertices.extend(corners.iter().map(|&(position, tex_coord)| OverlayVertex {
+            position,
+            tex_coord,
+            color,
+        }));
+        self.indices.extend([0, 1, 2, 2, 3, 0].iter().map(|index| base + index));
+    }
+
+    /// Adds a filled rectangle
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - The top left corner in virtual pixels
+    /// * `max` - The bottom right corner in virtual pixels
+    /// * `color` - The color of the rectangle
+    pub(crate) fn rect(&mut self, min: [f32; 2], max: [f32; 2], color: Vector4<f32>) {
+        let solid = font::solid_tex_coord();
+        self.quad(min, max, solid, solid, color);
+    }
+
+    /// Adds a single line of text
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The top left corner of the text in virtual pixels
+    /// * `text` - The text
+    /// * `color` - The color of the text
+    pub(crate) fn text(&mut self, pos: [f32; 2], text: &str, color: Vector4<f32>) {
+        let size = GLYPH_SIZE as f32;
+        for (i, c) in text.chars().enumerate() {
+            if c == ' ' {
+                continue;
+            }
+            let x = pos[0] + i as f32 * size;
+            let (uv_min, uv_max) = font::glyph_tex_coords(c);
+            self.quad([x, pos[1]], [x + size, pos[1] + size], uv_min, uv_max, color);
+        }
+    }
+
+    /// Adds a debug panel with a line of text per entry
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - The top left corner in virtual pixels
+    /// * `lines` - The lines of text
+    pub(crate) fn panel(&mut self, min: [f32; 2], lines: &[String]) {
+        let line_height = GLYPH_SIZE as f32 + LINE_SPACING;
+        let text_width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0) as f32 * GLYPH_SIZE as f32;
+        let max = [
+            min[0] + text_width + 2.0 * PANEL_PADDING,
+            min[1] + Self::panel_height(lines.len()),
+        ];
+
+        self.rect(min, max, Vector4::new(0.0, 0.0, 0.0, 0.65));
+        for (i, line) in lines.iter().enumerate() {
+            let pos = [min[0] + PANEL_PADDING, min[1] + PANEL_PADDING + i as f32 * line_height];
+            self.text(pos, line, Vector4::new(1.0, 1.0, 1.0, 1.0));
+        }
+    }
+
+    /// Returns the height of a debug panel in virtual pixels
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - The amount of lines of the panel
+    pub(crate) fn panel_height(lines: usize) -> f32 {
+        lines as f32 * (GLYPH_SIZE as f32 + LINE_SPACING) - LINE_SPACING + 2.0 * PANEL_PADDING
+    }
+
+    /// Adds a toast with its top right corner at the given position.
+    /// Returns the height of the toast.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The top right corner in virtual pixels
+    /// * `toast` - The toast
+    fn toast(&mut self, pos: [f32; 2], toast: &Toast) -> f32 {
+        let line_height = GLYPH_SIZE as f32;
+        let text_width = font::text_width(&toast.title).max(font::text_width(&toast.message)) as f32;
+        let width = (text_width + 2.0 * TOAST_PADDING + ACCENT_WIDTH).max(TOAST_MIN_WIDTH);
+        let height = if toast.message.is_empty() {
+            line_height + 2.0 * TOAST_PADDING
+        } else {
+            2.0 * line_height + LINE_SPACING + 2.0 * TOAST_PADDING
+        };
+
+        let min = [pos[0] - width, pos[1]];
+        let max = [pos[0], pos[1] + height];
+        let opacity = toast.opacity;
+
+        let mut accent = toast.kind.color();
+        accent.w *= opacity;
+        self.rect(min, max, Vector4::new(0.0, 0.0, 0.0, 0.65 * opacity));
+        self.rect(min, [min[0] + ACCENT_WIDTH, max[1]], accent);
+
+        let text_x = min[0] + ACCENT_WIDTH + TOAST_PADDING;
+        let text_y = min[1] + TOAST_PADDING;
+        self.text([text_x, text_y], &toast.title, Vector4::new(1.0, 1.0, 1.0, opacity));
+        self.text([text_x, text_y + line_height + LINE_SPACING], &toast.message, Vector4::new(0.8, 0.8, 0.8, opacity));
+
+        height
+    }
+}
+
+/// OverlayPainter
+///
+/// Draws the quads of an `OverlayBuilder`, which are all
+/// textured with the atlas of the bitmap font
+pub(crate) struct OverlayPainter {
+    /// The render backend creating the buffers of each frame
+    backend: GlBackend,
+    /// A shader program
+    shader_program: ShaderProgram,
+    /// The atlas of the bitmap font
+    font: Texture,
+}
+
+impl OverlayPainter {
+    /// Creates a new overlay painter
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub(crate) fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        let shader_program = backend.create_pipeline(resources, "ui").unwrap();
+        shader_program.disable();
+
+        let (width, height) = font::atlas_size();
+        let font = backend.create_texture_from_pixels(width, height, font::atlas_pixels()).unwrap();
+
+        Self {
+            backend: backend.clone(),
+            shader_program,
+            font,
+        }
+    }
+
+    /// Draws the quads of an overlay on top of the screen
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The render context of the current frame
+    /// * `overlay` - The overlay
+    /// * `width` - The width of the screen in virtual pixels
+    pub(crate) fn draw<F>(&self, ctx: &RenderContext<F>, overlay: &OverlayBuilder, width: f32) {
+        // The overlay changes every frame, so its buffers are
+        // recreated instead of being cached
+        let mut va = self.backend.create_vertex_array();
+        let vb = self.backend.create_vertex_buffer(&overlay.vertices);
+        va.add_buffer(&vb);
+        let ib = self.backend.create_index_buffer(&overlay.indices, overlay.vertices.len());
+
+        let camera = OrthographicCamera::screen(width, OVERLAY_HEIGHT);
+        let projection = camera.proj_matrix() * camera.view_matrix();
+
+        ctx.renderer.set_depth_test(false);
+        self.shader_program.enable();
+        self.shader_program.set_uniform_1i("u_Texture", 0);
+        self.shader_program.set_uniform_mat4f("u_Projection", &projection);
+        self.font.bind(None);
+
+        ctx.renderer.draw(&va, &ib, &self.shader_program);
+
+        va.unbind();
+        ib.unbind();
+        self.font.unbind();
+        self.shader_program.disable();
+        ctx.renderer.set_depth_test(true);
+    }
+}
+
+/// ToastRenderer
+///
+/// The `ToastRenderer` is the render pass drawing the
+/// toasts into the top right corner of the screen. The
+/// overlay uses virtual pixels with a fixed height, so
+/// the toasts scale with the window.
+pub struct ToastRenderer {
+    /// The painter drawing the toasts
+    painter: OverlayPainter,
+}
+
+impl ToastRenderer {
+    /// Creates a new toast renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+impl<F: ToastFrame> RenderPass<F> for ToastRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let toasts = ctx.frame.toasts();
+        if toasts.is_empty() {
+            return;
+        }
+
+        let width = OVERLAY_HEIGHT * ctx.frame.camera().aspect_ratio();
+        let mut overlay = OverlayBuilder::default();
+        let mut y = TOAST_MARGIN;
+        for toast in toasts {
+            y += overlay.toast([width - TOAST_MARGIN, y], toast) + TOAST_MARGIN;
+        }
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager() -> (ScriptEngine, NotificationManager) {
+        let engine = ScriptEngine::new();
+        let manager = NotificationManager::new(&engine).unwrap();
+        (engine, manager)
+    }
+
+    fn titles(manager: &NotificationManager) -> Vec<String> {
+        manager.toasts().into_iter().map(|toast| toast.title).collect()
+    }
+
+    #[test]
+    fn toasts_fade_in_and_out() {
+        let (_engine, mut manager) = manager();
+        manager.push(Notification::new(NotificationKind::Info, "Saved world", ""));
+
+        manager.update(TimeStep(0.0));
+        assert_eq!(manager.toasts()[0].opacity, 0.0);
+
+        manager.update(TimeStep(FADE_DURATION / 2.0));
+        assert!((manager.toasts()[0].opacity - 0.5).abs() < 1e-5);
+
+        manager.update(TimeStep(1.0));
+        assert_eq!(manager.toasts()[0].opacity, 1.0);
+
+        manager.update(TimeStep(DEFAULT_DURATION - 1.0 - FADE_DURATION));
+        assert!((manager.toasts()[0].opacity - 0.5).abs() < 1e-4);
+
+        manager.update(TimeStep(FADE_DURATION));
+        assert!(manager.toasts().is_empty());
+    }
+
+    #[test]
+    fn notifications_are_queued_until_there_is_space() {
+        let (_engine, mut manager) = manager();
+        for i in 0..MAX_VISIBLE_TOASTS + 2 {
+            manager.push(Notification::new(NotificationKind::Info, format!("{}", i), ""));
+        }
+
+        manager.update(TimeStep(0.0));
+        assert_eq!(titles(&manager), vec!["0", "1", "2", "3"]);
+
+        manager.update(TimeStep(DEFAULT_DURATION));
+        assert_eq!(titles(&manager), vec!["4", "5"]);
+    }
+
+    #[test]
+    fn long_lines_are_truncated() {
+        let (_engine, mut manager) = manager();
+        manager.push(Notification::new(NotificationKind::Error, "x".repeat(100), "short"));
+        manager.update(TimeStep(0.0));
+
+        let toast = &manager.toasts()[0];
+        assert_eq!(toast.title.chars().count(), MAX_LINE_LENGTH);
+        assert!(toast.title.ends_with("..."));
+        assert_eq!(toast.message, "short");
+    }
+
+    #[test]
+    fn scripts_push_notifications() {
+        let (engine, mut manager) = manager();
+        engine.exec("test", r#"
+            notifications.push { title = "Hello" }
+            notifications.push { title = "Oops", message = "Failed", kind = "error", duration = 1 }
+        "#).unwrap();
+        manager.update(TimeStep(0.0));
+
+        let toasts = manager.toasts();
+        assert_eq!(toasts.len(), 2);
+        assert_eq!(toasts[0].kind, NotificationKind::Info);
+        assert_eq!(toasts[0].message, "");
+        assert_eq!(toasts[1].kind, NotificationKind::Error);
+        assert_eq!(toasts[1].message, "Failed");
+
+        manager.update(TimeStep(1.0));
+        assert_eq!(titles(&manager), vec!["Hello"]);
+    }
+
+    #[test]
+    fn scripts_fail_on_invalid_notifications() {
+        let (engine, mut manager) = manager();
+        assert!(engine.exec("test", r#"notifications.push { title = "Hi", kind = "loud" }"#).is_err());
+        assert!(engine.exec("test", r#"notifications.push { message = "No title" }"#).is_err());
+
+        manager.update(TimeStep(0.0));
+        assert!(manager.toasts().is_empty());
+    }
+
+    #[test]
+    fn overlay_skips_spaces() {
+        let mut overlay = OverlayBuilder::default();
+        overlay.text([0.0, 0.0], "a b", Vector4::new(1.0, 1.0, 1.0, 1.0));
+
+        assert_eq!(overlay.vertices.len(), 8);
+        assert_eq!(overlay.indices.len(), 12);
+        assert_eq!(overlay.vertices[4].position, [2.0 * GLYPH_SIZE as f32, GLYPH_SIZE as f32]);
+    }
+}
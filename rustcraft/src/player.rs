@@ -0,0 +1,464 @@
+//! Types to show other players in the world, with their
+//! skins applied to a box model and their names floating
+//! above their heads
+
+use crate::entity::{PLAYER_EYE_HEIGHT, PLAYER_HEIGHT, SNEAK_EYE_HEIGHT, SNEAK_HEIGHT};
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::font::{self, GLYPH_SIZE};
+use crate::graphics::mesh::{MeshBuilder, Model};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::shader::ShaderProgram;
+use crate::graphics::texture::Texture;
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use crate::world::save::SaveDir;
+use cgmath::{InnerSpace, Matrix4, Rad, Vector2, Vector3, Vector4};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The width and height of a skin in pixels
+pub const SKIN_SIZE: u32 = 64;
+/// The resource of the skin of players without their own skin
+const DEFAULT_SKIN: &str = "textures/skin.png";
+/// The size of a skin pixel on the box model in blocks
+const PIXEL_SIZE: f32 = PLAYER_HEIGHT / 32.0;
+/// The height of the neck above the feet in skin pixels
+const NECK_HEIGHT: f32 = 24.0;
+/// The distance of a name tag above the eyes of a player,
+/// or the top of another entity, in blocks
+const NAME_TAG_OFFSET: f32 = 0.6;
+/// The distance to the camera up to which name tags are fully opaque
+const NAME_TAG_FADE_START: f32 = 16.0;
+/// The distance to the camera beyond which name tags are hidden
+const NAME_TAG_RANGE: f32 = 32.0;
+/// The distance of the name to the edges of its tag
+const NAME_TAG_PADDING: f32 = 2.0;
+
+/// Skin
+///
+/// The texture of a player's box model, laid out like the
+/// skins of Minecraft with `SKIN_SIZE` by `SKIN_SIZE` pixels
+#[derive(Debug, PartialEq)]
+pub struct Skin {
+    /// The `RGBA` pixels, row by row starting at the bottom
+    pixels: Vec<u8>,
+}
+
+impl Skin {
+    /// Creates a new skin. Returns `None` if the skin
+    /// doesn't have the size of a skin.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the skin in pixels
+    /// * `height` - The height of the skin in pixels
+    /// * `pixels` - The `RGBA` pixels, row by row starting at the bottom
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Option<Self> {
+        let expected = SKIN_SIZE as usize * SKIN_SIZE as usize * 4;
+        if width != SKIN_SIZE || height != SKIN_SIZE || pixels.len() != expected {
+            return None;
+        }
+        Some(Self {
+            pixels,
+        })
+    }
+
+    /// Returns the `RGBA` pixels, row by row starting at the bottom
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+/// Skins
+///
+/// The skins of the players by their name. Skins are loaded
+/// from the save directory the first time they're needed.
+#[derive(Default)]
+pub struct Skins {
+    /// The loaded skins, `None` for players without a skin
+    skins: HashMap<String, Option<Arc<Skin>>>,
+}
+
+impl Skins {
+    /// Returns the skin of a player, or `None` if the player
+    /// doesn't have a skin
+    ///
+    /// # Arguments
+    ///
+    /// * `save_dir` - The directory the skins are saved in
+    /// * `name` - The name of the player
+    pub fn get(&mut self, save_dir: &SaveDir, name: &str) -> Option<Arc<Skin>> {
+        self.skins.entry(name.to_string())
+            .or_insert_with(|| match save_dir.load_skin(name) {
+                Ok(skin) => skin.map(Arc::new),
+                Err(error) => {
+                    log::warn!("Failed to load skin of {}: {:?}", name, error);
+                    None
+                }
+            })
+            .clone()
+    }
+}
+
+/// RemotePlayer
+///
+/// A player other than the one the camera belongs to,
+/// as far as it's needed to render them
+#[derive(Clone, Debug)]
+pub struct RemotePlayer {
+    /// The name of the player
+    pub name: String,
+    /// The position of the player's eyes
+    pub eye: Vector3<f32>,
+    /// The direction the player looks in
+    pub look: Vector3<f32>,
+    /// Whether the player sneaks, which ducks their model
+    /// and hides their name tag
+    pub sneaking: bool,
+    /// The skin of the player, `None` for the default skin
+    pub skin: Option<Arc<Skin>>,
+}
+
+/// NameTag
+///
+/// A name floating above an entity other than a player,
+/// e.g. an NPC a script named
+#[derive(Clone, Debug, PartialEq)]
+pub struct NameTag {
+    /// The name
+    pub name: String,
+    /// The position of the top of the entity
+    pub pos: Vector3<f32>,
+}
+
+/// PlayerFrame
+///
+/// A frame other players could be rendered for
+pub trait PlayerFrame: Frame {
+    /// Returns the players which should be rendered
+    fn players(&self) -> &[RemotePlayer];
+
+    /// Returns the name tags of other entities
+    fn name_tags(&self) -> &[NameTag];
+
+    /// Returns the light of the sky in the range `0..=1`
+    fn light(&self) -> f32;
+}
+
+/// PlayerRenderer
+///
+/// The `PlayerRenderer` is the render pass drawing other
+/// players as box models with their skins applied. The
+/// body turns with the direction the player looks in,
+/// while the head pitches up and down as well.
+pub struct PlayerRenderer {
+    /// The render backend creating the textures of the skins
+    backend: GlBackend,
+    /// A shader program
+    shader_program: ShaderProgram,
+    /// The head, pivoting around the neck
+    head: Model,
+    /// The body, arms and legs, standing on the origin
+    body: Model,
+    /// The skin of players without their own skin
+    default_skin: Texture,
+    /// The textures of the skins by the name of the player
+    skins: HashMap<String, (Arc<Skin>, Texture)>,
+}
+
+impl PlayerRenderer {
+    /// Creates a new player renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        let shader_program = backend.create_pipeline(resources, "skin").unwrap();
+        shader_program.disable();
+
+        let mut head = MeshBuilder::new();
+        skin_box(&mut head, [-4.0, 0.0, -4.0], [8.0, 8.0, 8.0], [0.0, 0.0]);
+
+        let mut body = MeshBuilder::new();
+        skin_box(&mut body, [-4.0, 12.0, -2.0], [8.0, 12.0, 4.0], [16.0, 16.0]);
+        // The right side of a player facing along `+z` is at `-x`
+        skin_box(&mut body, [-8.0, 12.0, -2.0], [4.0, 12.0, 4.0], [40.0, 16.0]);
+        skin_box(&mut body, [4.0, 12.0, -2.0], [4.0, 12.0, 4.0], [32.0, 48.0]);
+        skin_box(&mut body, [-4.0, 0.0, -2.0], [4.0, 12.0, 4.0], [0.0, 16.0]);
+        skin_box(&mut body, [0.0, 0.0, -2.0], [4.0, 12.0, 4.0], [16.0, 48.0]);
+
+        Self {
+            backend: backend.clone(),
+            shader_program,
+            head: Model::from_mesh(backend, &head.build()),
+            body: Model::from_mesh(backend, &body.build()),
+            default_skin: backend.create_texture(resources, DEFAULT_SKIN),
+            skins: HashMap::new(),
+        }
+    }
+
+    /// Uploads the skin of a player, unless it has been
+    /// uploaded already
+    ///
+    /// # Arguments
+    ///
+    /// * `player` - The player
+    fn upload_skin(&mut self, player: &RemotePlayer) {
+        let skin = match player.skin.as_ref() {
+            Some(skin) => skin,
+            None => return,
+        };
+        if matches!(self.skins.get(&player.name), Some((uploaded, _)) if Arc::ptr_eq(uploaded, skin)) {
+            return;
+        }
+
+        match self.backend.create_texture_from_pixels(SKIN_SIZE, SKIN_SIZE, skin.pixels().to_vec()) {
+            Ok(texture) => {
+                self.skins.insert(player.name.clone(), (skin.clone(), texture));
+            }
+            Err(error) => log::warn!("Failed to upload skin of {}: {:?}", player.name, error),
+        }
+    }
+
+    /// Draws a part of the box model
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The render context of the current frame
+    /// * `model` - The part of the box model
+    /// * `transform` - The model matrix of the part
+    fn draw_part<F: Frame>(&self, ctx: &RenderContext<F>, model: &Model, transform: Matrix4<f32>) {
+        let camera = ctx.frame.camera();
+        let mvp = camera.proj_matrix() * camera.view_matrix() * transform;
+        self.shader_program.set_uniform_mat4f("u_MVP", &mvp);
+        self.shader_program.set_uniform_mat4f("u_Model", &transform);
+        model.bind();
+        ctx.renderer.draw_with(model.va(), model.ib(), &self.shader_program, model.topology());
+        model.unbind();
+    }
+}
+
+impl<F: PlayerFrame> RenderPass<F> for PlayerRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Opaque
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let players = ctx.frame.players();
+        if players.is_empty() {
+            return;
+        }
+        for player in players {
+            self.upload_skin(player);
+        }
+
+        self.shader_program.enable();
+        self.shader_program.set_uniform_1i("u_Texture", 0);
+        self.shader_program.set_uniform_1f("u_Light", ctx.frame.light());
+        for player in players {
+            let texture = match self.skins.get(&player.name) {
+                Some((_, texture)) if player.skin.is_some() => texture,
+                _ => &self.default_skin,
+            };
+            texture.bind(None);
+
+            let (yaw, pitch) = body_angles(player.look);
+            let (eye_height, height) = if player.sneaking {
+                (SNEAK_EYE_HEIGHT, SNEAK_HEIGHT)
+            } else {
+                (PLAYER_EYE_HEIGHT, PLAYER_HEIGHT)
+            };
+            let feet = player.eye - Vector3::new(0.0, eye_height, 0.0);
+            let body = Matrix4::from_translation(feet)
+                * Matrix4::from_angle_y(Rad(yaw))
+                * Matrix4::from_nonuniform_scale(PIXEL_SIZE, PIXEL_SIZE * height / PLAYER_HEIGHT, PIXEL_SIZE);
+            let head = body
+                * Matrix4::from_translation(Vector3::new(0.0, NECK_HEIGHT, 0.0))
+                * Matrix4::from_angle_x(Rad(-pitch));
+            self.draw_part(ctx, &self.body, body);
+            self.draw_part(ctx, &self.head, head);
+
+            texture.unbind();
+        }
+        self.shader_program.disable();
+    }
+}
+
+/// NameTagRenderer
+///
+/// The `NameTagRenderer` is the render pass drawing the names
+/// of other players above their heads, as well as the name
+/// tags of other entities. The tags always face the screen
+/// and fade out with the distance to the camera.
+pub struct NameTagRenderer {
+    /// The painter drawing the name tags
+    painter: OverlayPainter,
+}
+
+impl NameTagRenderer {
+    /// Creates a new name tag renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+impl<F: PlayerFrame> RenderPass<F> for NameTagRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let players = ctx.frame.players();
+        let name_tags = ctx.frame.name_tags();
+        if players.is_empty() && name_tags.is_empty() {
+            return;
+        }
+
+        let camera = ctx.frame.camera();
+        let view_proj = camera.proj_matrix() * camera.view_matrix();
+        let width = OVERLAY_HEIGHT * camera.aspect_ratio();
+        let mut overlay = OverlayBuilder::default();
+        // Sneaking players hide their name tags
+        let tags = players.iter()
+            .filter(|player| !player.sneaking)
+            .map(|player| (player.eye, player.name.as_str()))
+            .chain(name_tags.iter().map(|tag| (tag.pos, tag.name.as_str())));
+        for (top, name) in tags {
+            let pos = top + Vector3::new(0.0, NAME_TAG_OFFSET, 0.0);
+            let opacity = name_tag_opacity((pos - camera.pos()).magnitude());
+            if opacity <= 0.0 {
+                continue;
+            }
+
+            // Tags behind the camera aren't shown
+            let clip = view_proj * pos.extend(1.0);
+            if clip.w <= 0.0 {
+                continue;
+            }
+            let x = (clip.x / clip.w + 1.0) / 2.0 * width;
+            let y = (1.0 - clip.y / clip.w) / 2.0 * OVERLAY_HEIGHT;
+
+            let text_width = font::text_width(name) as f32;
+            let min = [x - text_width / 2.0, y - GLYPH_SIZE as f32];
+            overlay.rect(
+                [min[0] - NAME_TAG_PADDING, min[1] - NAME_TAG_PADDING],
+                [min[0] + text_width + NAME_TAG_PADDING, y + NAME_TAG_PADDING],
+                Vector4::new(0.0, 0.0, 0.0, 0.4 * opacity),
+            );
+            overlay.text(min, name, Vector4::new(1.0, 1.0, 1.0, opacity));
+        }
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+/// Returns the opacity of a name tag at the given distance
+/// to the camera
+///
+/// # Arguments
+///
+/// * `distance` - The distance of the name tag to the camera
+fn name_tag_opacity(distance: f32) -> f32 {
+    let fade = (distance - NAME_TAG_FADE_START) / (NAME_TAG_RANGE - NAME_TAG_FADE_START);
+    1.0 - fade.clamp(0.0, 1.0)
+}
+
+/// Returns the angle the body of a player is turned by around
+/// the `y` axis and the angle their head is pitched up by, so
+/// that a model facing along `+z` looks in the given direction
+///
+/// # Arguments
+///
+/// * `look` - The direction the player looks in
+fn body_angles(look: Vector3<f32>) -> (f32, f32) {
+    let look = look.normalize();
+    (look.x.atan2(look.z), look.y.clamp(-1.0, 1.0).asin())
+}
+
+/// Adds a box textured with the unwrapped layout of a skin,
+/// in which the top and bottom are followed by the right,
+/// front, left and back side of the box
+///
+/// # Arguments
+///
+/// * `builder` - The builder of the mesh
+/// * `min` - The corner of the box with the smallest coordinates in pixels
+/// * `size` - The width, height and depth of the box in pixels
+/// * `offset` - The top left corner of the layout on the skin in pixels
+fn skin_box(builder: &mut MeshBuilder, min: [f32; 3], size: [f32; 3], offset: [f32; 2]) {
+    let [x0, y0, z0] = min;
+    let [w, h, d] = size;
+    let (x1, y1, z1) = (x0 + w, y0 + h, z0 + d);
+    let [u, v] = offset;
+
+    // The corners of each side as seen from outside, starting
+    // at the bottom left corner, with the area of the skin
+    let faces = [
+        ([[x0, y1, z1], [x1, y1, z1], [x1, y1, z0], [x0, y1, z0]], [0.0, 1.0, 0.0], [u + d, v, w, d]),
+        ([[x0, y0, z0], [x1, y0, z0], [x1, y0, z1], [x0, y0, z1]], [0.0, -1.0, 0.0], [u + d + w, v, w, d]),
+        ([[x0, y0, z0], [x0, y0, z1], [x0, y1, z1], [x0, y1, z0]], [-1.0, 0.0, 0.0], [u, v + d, d, h]),
+        ([[x0, y0, z1], [x1, y0, z1], [x1, y1, z1], [x0, y1, z1]], [0.0, 0.0, 1.0], [u + d, v + d, w, h]),
+        ([[x1, y0, z1], [x1, y0, z0], [x1, y1, z0], [x1, y1, z1]], [1.0, 0.0, 0.0], [u + d + w, v + d, d, h]),
+        ([[x1, y0, z0], [x0, y0, z0], [x0, y1, z0], [x1, y1, z0]], [0.0, 0.0, -1.0], [u + 2.0 * d + w, v + d, w, h]),
+    ];
+
+    for (corners, normal, [u, v, w, h]) in faces.iter() {
+        // Skins are flipped vertically for `OpenGL`
+        let size = SKIN_SIZE as f32;
+        let (left, right) = (u / size, (u + w) / size);
+        let (top, bottom) = (1.0 - v / size, 1.0 - (v + h) / size);
+        let tex_coords = [
+            Vector2::new(left, bottom),
+            Vector2::new(right, bottom),
+            Vector2::new(right, top),
+            Vector2::new(left, top),
+        ];
+
+        let normal = Vector3::from(*normal);
+        let mut indices = [0; 4];
+        for i in 0..4 {
+            indices[i] = builder.vertex(Vector3::from(corners[i]), tex_coords[i], normal);
+        }
+        builder.triangle(indices[0], indices[1], indices[2]);
+        builder.triangle(indices[2], indices[3], indices[0]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skins_must_have_the_size_of_a_skin() {
+        let pixels = vec![255; (SKIN_SIZE * SKIN_SIZE * 4) as usize];
+        assert!(Skin::new(SKIN_SIZE, SKIN_SIZE, pixels.clone()).is_some());
+        assert!(Skin::new(SKIN_SIZE, SKIN_SIZE / 2, pixels.clone()).is_none());
+        assert!(Skin::new(SKIN_SIZE, SKIN_SIZE, pixels[4..].to_vec()).is_none());
+    }
+
+    #[test]
+    fn name_tags_fade_with_the_distance() {
+        assert_eq!(name_tag_opacity(4.0), 1.0);
+        assert_eq!(name_tag_opacity(NAME_TAG_FADE_START), 1.0);
+        assert_eq!(name_tag_opacity((NAME_TAG_FADE_START + NAME_TAG_RANGE) / 2.0), 0.5);
+        assert_eq!(name_tag_opacity(NAME_TAG_RANGE + 1.0), 0.0);
+    }
+
+    #[test]
+    fn body_turns_towards_the_look_direction() {
+        let (yaw, pitch) = body_angles(Vector3::new(1.0, 0.0, 0.0));
+        assert!((yaw - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+        assert_eq!(pitch, 0.0);
+
+        let (yaw, pitch) = body_angles(Vector3::new(0.0, 1.0, 1.0));
+        assert_eq!(yaw, 0.0);
+        assert!((pitch - std::f32::consts::FRAC_PI_4).abs() < 1e-6);
+    }
+}
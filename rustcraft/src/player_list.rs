@@ -0,0 +1,380 @@
+//! Types to list the players the server hosts, with the
+//! columns scripts add, while the player holds Tab
+
+use crate::graphics::backend::GlBackend;
+use crate::graphics::font::{self, GLYPH_SIZE};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use crate::scripting::{self, ScriptEngine, ScriptError};
+use cgmath::Vector4;
+use mlua::{Function, RegistryKey};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The time between two status updates of the player
+/// list in seconds
+pub const STATUS_INTERVAL: f32 = 1.0;
+/// The weight of a new measurement in the average latency
+const LATENCY_SMOOTHING: f32 = 0.1;
+/// The latency in milliseconds up to which the ping
+/// is shown as good
+const GOOD_PING: u32 = 150;
+/// The latency in milliseconds up to which the ping
+/// is shown as fair
+const FAIR_PING: u32 = 300;
+
+/// The distance of the list to the top edge of the screen
+const LIST_MARGIN: f32 = 16.0;
+/// The distance of the text to the edges of the list
+const LIST_PADDING: f32 = 6.0;
+/// The horizontal distance of two columns
+const COLUMN_GAP: f32 = 16.0;
+/// The vertical distance of two lines of text
+const LINE_SPACING: f32 = 4.0;
+
+/// GameMode
+///
+/// How a player takes part in the game
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GameMode {
+    /// The player breaks and places blocks
+    Survival,
+    /// The player only watches, e.g. a replay or a
+    /// flythrough of the camera path
+    Spectator,
+}
+
+impl GameMode {
+    /// Returns the name of the game mode
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Survival => "survival",
+            GameMode::Spectator => "spectator",
+        }
+    }
+}
+
+/// PlayerEntry
+///
+/// A row of the player list
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayerEntry {
+    /// The name of the player
+    pub name: String,
+    /// The latency of the player in milliseconds
+    pub ping: u32,
+    /// How the player takes part in the game
+    pub game_mode: GameMode,
+    /// The name of the dimension the player is in
+    pub dimension: String,
+    /// The values of the columns scripts added
+    pub columns: Vec<String>,
+}
+
+/// PlayerList
+///
+/// The status of the players the server sends to the
+/// client every `STATUS_INTERVAL` seconds
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerList {
+    /// The titles of the columns scripts added
+    pub columns: Vec<String>,
+    /// The players, ordered by their name
+    pub players: Vec<PlayerEntry>,
+}
+
+impl PlayerList {
+    /// Returns the header and the rows of the list as text
+    pub fn rows(&self) -> Vec<Vec<String>> {
+        let header = ["Player", "Ping", "Mode", "Dimension"].iter()
+            .map(|title| title.to_string())
+            .chain(self.columns.iter().cloned())
+            .collect();
+        let players = self.players.iter().map(|player| {
+            vec![player.name.clone(), format!("{}ms", player.ping), player.game_mode.name().to_string(), player.dimension.clone()]
+                .into_iter()
+                .chain(player.columns.iter().cloned())
+                .collect()
+        });
+        std::iter::once(header).chain(players).collect()
+    }
+}
+
+/// Latency
+///
+/// The average time the input of a player takes to reach
+/// the server
+#[derive(Debug, Default)]
+pub struct Latency {
+    /// The average latency in milliseconds, `None` until
+    /// it has been measured
+    average: Option<f32>,
+}
+
+impl Latency {
+    /// Adds a measurement to the average latency
+    ///
+    /// # Arguments
+    ///
+    /// * `latency` - The time the input took to reach the server
+    pub fn record(&mut self, latency: Duration) {
+        let millis = latency.as_secs_f32() * 1000.0;
+        self.average = Some(match self.average {
+            Some(average) => average + (millis - average) * LATENCY_SMOOTHING,
+            None => millis,
+        });
+    }
+
+    /// Returns the average latency in milliseconds
+    pub fn millis(&self) -> u32 {
+        self.average.unwrap_or(0.0).round() as u32
+    }
+}
+
+/// PlayerListColumns
+///
+/// The columns scripts add to the player list through the
+/// `playerlist` API, e.g.
+///
+/// ```lua
+/// playerlist.column("Mined", function(player)
+///     return scoreboard.get("mined", player)
+/// end)
+/// playerlist.remove("Mined")
+/// ```
+///
+/// The callback of a column is called with the name of each
+/// player whenever the list is updated. It returns the value
+/// shown for the player, or `nil` to leave it empty. A column
+/// added again replaces the callback but keeps its place.
+pub struct PlayerListColumns {
+    /// The titles of the columns with their callbacks in the
+    /// order they've been added, shared with the Lua API
+    columns: Arc<Mutex<Vec<(String, RegistryKey)>>>,
+}
+
+impl PlayerListColumns {
+    /// Creates a new column registry and registers its API
+    /// in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let columns = Arc::new(Mutex::new(Vec::new()));
+
+        let lua = engine.lua();
+        let api = scripting::api_table(lua, "playerlist")?;
+        let shared = columns.clone();
+        api.set("column", lua.create_function(move |lua, (title, callback): (String, Function)| {
+            let key = lua.create_registry_value(callback)?;
+            let mut columns = shared.lock().unwrap();
+            match columns.iter_mut().find(|(existing, _)| *existing == title) {
+                Some(column) => column.1 = key,
+                None => columns.push((title, key)),
+            }
+            Ok(())
+        })?)?;
+        let shared = columns.clone();
+        api.set("remove", lua.create_function(move |_, title: String| {
+            let mut columns = shared.lock().unwrap();
+            let len = columns.len();
+            columns.retain(|(existing, _)| *existing != title);
+            Ok(columns.len() < len)
+        })?)?;
+
+        Ok(Self { columns })
+    }
+
+    /// Returns the titles of the columns
+    pub fn titles(&self) -> Vec<String> {
+        self.columns.lock().unwrap().iter().map(|(title, _)| title.clone()).collect()
+    }
+
+    /// Returns the values of the columns for a player. The
+    /// value of a column whose callback failed is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `player` - The name of the player
+    pub fn values(&self, engine: &ScriptEngine, player: &str) -> (Vec<String>, Vec<ScriptError>) {
+        let lua = engine.lua();
+
+        // The callbacks are taken out of the registry first,
+        // so that they could add columns themselves
+        let callbacks: Vec<(String, mlua::Result<Function>)> = self.columns.lock().unwrap().iter()
+            .map(|(title, key)| (title.clone(), lua.registry_value(key)))
+            .collect();
+
+        let mut errors = Vec::new();
+        let values = callbacks.into_iter()
+            .map(|(title, callback)| {
+                let value = callback.and_then(|callback| callback.call::<_, mlua::Value>(player))
+                    .and_then(display_value);
+                value.unwrap_or_else(|error| {
+                    errors.push(ScriptError::new(&format!("player list column {}", title), error));
+                    String::new()
+                })
+            })
+            .collect();
+        (values, errors)
+    }
+}
+
+/// Returns how a value returned by a column callback is shown
+///
+/// # Arguments
+///
+/// * `value` - The Lua value
+fn display_value(value: mlua::Value) -> mlua::Result<String> {
+    Ok(match value {
+        mlua::Value::Nil => String::new(),
+        mlua::Value::Boolean(value) => value.to_string(),
+        mlua::Value::Integer(value) => value.to_string(),
+        mlua::Value::Number(value) => value.to_string(),
+        mlua::Value::String(value) => value.to_str()?.to_string(),
+        value => return Err(mlua::Error::RuntimeError(format!("a column can't show a {}", value.type_name()))),
+    })
+}
+
+/// PlayerListFrame
+///
+/// A frame the player list could be rendered for
+pub trait PlayerListFrame: Frame {
+    /// Returns the player list, if it's shown
+    fn player_list(&self) -> Option<&PlayerList>;
+}
+
+/// PlayerListRenderer
+///
+/// The `PlayerListRenderer` is the render pass drawing the
+/// player list centered at the top of the screen, with a
+/// row per player below the titles of the columns. The ping
+/// is colored by how good it is.
+pub struct PlayerListRenderer {
+    /// The painter drawing the list
+    painter: OverlayPainter,
+}
+
+impl PlayerListRenderer {
+    /// Creates a new player list renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+impl<F: PlayerListFrame> RenderPass<F> for PlayerListRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let list = match ctx.frame.player_list() {
+            Some(list) => list,
+            None => return,
+        };
+
+        let rows = list.rows();
+        let mut widths = vec![0.0f32; rows[0].len()];
+        for row in rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = width.max(font::text_width(cell) as f32);
+            }
+        }
+        let content_width = widths.iter().sum::<f32>() + COLUMN_GAP * (widths.len() - 1) as f32;
+        let line_height = GLYPH_SIZE as f32 + LINE_SPACING;
+        let height = rows.len() as f32 * line_height - LINE_SPACING + 2.0 * LIST_PADDING;
+        let width = OVERLAY_HEIGHT * ctx.frame.camera().aspect_ratio();
+        let min = [(width - content_width) / 2.0 - LIST_PADDING, LIST_MARGIN];
+
+        let mut overlay = OverlayBuilder::default();
+        overlay.rect(min, [min[0] + content_width + 2.0 * LIST_PADDING, min[1] + height], Vector4::new(0.0, 0.0, 0.0, 0.5));
+        overlay.rect(min, [min[0] + content_width + 2.0 * LIST_PADDING, min[1] + line_height + LIST_PADDING / 2.0], Vector4::new(0.0, 0.0, 0.0, 0.2));
+        for (i, row) in rows.iter().enumerate() {
+            let y = min[1] + LIST_PADDING + i as f32 * line_height;
+            let mut x = min[0] + LIST_PADDING;
+            for (column, (cell, width)) in row.iter().zip(widths.iter()).enumerate() {
+                let color = match (i, column) {
+                    (0, _) => Vector4::new(1.0, 0.85, 0.3, 1.0),
+                    (_, 1) => ping_color(list.players[i - 1].ping),
+                    _ => Vector4::new(1.0, 1.0, 1.0, 1.0),
+                };
+                overlay.text([x, y], cell, color);
+                x += width + COLUMN_GAP;
+            }
+        }
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+/// Returns the color of a ping by how good it is
+///
+/// # Arguments
+///
+/// * `ping` - The latency in milliseconds
+fn ping_color(ping: u32) -> Vector4<f32> {
+    if ping <= GOOD_PING {
+        Vector4::new(0.33, 1.0, 0.33, 1.0)
+    } else if ping <= FAIR_PING {
+        Vector4::new(1.0, 1.0, 0.33, 1.0)
+    } else {
+        Vector4::new(1.0, 0.33, 0.33, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_is_averaged() {
+        let mut latency = Latency::default();
+        assert_eq!(latency.millis(), 0);
+        latency.record(Duration::from_millis(20));
+        assert_eq!(latency.millis(), 20);
+        latency.record(Duration::from_millis(120));
+        assert_eq!(latency.millis(), 30);
+    }
+
+    #[test]
+    fn scripts_add_columns() {
+        let engine = ScriptEngine::new();
+        let columns = PlayerListColumns::new(&engine).unwrap();
+        engine.exec("test", r#"
+            playerlist.column("Team", function(player) return "red" end)
+            playerlist.column("Score", function(player) return #player end)
+            playerlist.column("Team", function(player) return nil end)
+            playerlist.column("Broken", function(player) return {} end)
+        "#).unwrap();
+        assert_eq!(columns.titles(), vec!["Team", "Score", "Broken"]);
+
+        let (values, errors) = columns.values(&engine, "steve");
+        assert_eq!(values, vec!["", "5", ""]);
+        assert_eq!(errors.len(), 1);
+
+        engine.exec("test", r#"assert(playerlist.remove("Broken"))"#).unwrap();
+        let list = PlayerList {
+            columns: columns.titles(),
+            players: vec![PlayerEntry {
+                name: "steve".to_string(),
+                ping: 12,
+                game_mode: GameMode::Survival,
+                dimension: "overworld".to_string(),
+                columns: columns.values(&engine, "steve").0,
+            }],
+        };
+        assert_eq!(list.rows(), vec![
+            vec!["Player", "Ping", "Mode", "Dimension", "Team", "Score"],
+            vec!["steve", "12ms", "survival", "overworld", "", "5"],
+        ]);
+    }
+}
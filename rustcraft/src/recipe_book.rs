@@ -0,0 +1,289 @@
+//! Types to browse the recipes scripts defined and to craft
+//! them from the items the player collected
+
+use crate::graphics::backend::GlBackend;
+use crate::graphics::font::{self, GLYPH_SIZE};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use crate::world::items;
+use crate::world::recipes::{Recipe, Recipes};
+use cgmath::Vector4;
+use std::collections::BTreeMap;
+
+/// The maximal amount of characters of the search query
+pub const MAX_QUERY_LENGTH: usize = 24;
+/// The amount of recipes shown at once, the list
+/// scrolls with the selected one
+const VISIBLE_RECIPES: usize = 10;
+
+/// The distance of the book to the top edge of the screen
+const BOOK_MARGIN: f32 = 32.0;
+/// The distance of the text to the edges of the book
+const BOOK_PADDING: f32 = 8.0;
+/// The smallest width of the content of the book
+const MIN_CONTENT_WIDTH: f32 = 160.0;
+/// The vertical distance of two lines of text
+const LINE_SPACING: f32 = 6.0;
+/// The horizontal distance of the icon to the text
+const ICON_GAP: f32 = 6.0;
+
+/// RecipeEntry
+///
+/// A row of the recipe book
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecipeEntry {
+    /// The recipe
+    pub recipe: Recipe,
+    /// Whether the player collected the ingredients
+    pub craftable: bool,
+}
+
+/// RecipeBookView
+///
+/// The recipe book as it's shown on the screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecipeBookView {
+    /// The search query
+    pub query: String,
+    /// The recipes matching the query
+    pub entries: Vec<RecipeEntry>,
+    /// The index of the selected recipe
+    pub selected: usize,
+}
+
+/// RecipeBook
+///
+/// The state of the recipe book, which the player opens to
+/// search the recipes by typing. The arrow keys select a
+/// recipe and Enter crafts it, if the player collected
+/// its ingredients.
+#[derive(Debug, Default)]
+pub struct RecipeBook {
+    /// Whether the book is open
+    open: bool,
+    /// The search query
+    query: String,
+    /// The index of the selected recipe among the
+    /// ones matching the query
+    selected: usize,
+}
+
+impl RecipeBook {
+    /// Returns whether the book is open
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Opens or closes the book, which clears the search
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// Appends a character to the search query
+    ///
+    /// # Arguments
+    ///
+    /// * `c` - The typed character
+    pub fn type_char(&mut self, c: char) {
+        if self.open && !c.is_control() && self.query.chars().count() < MAX_QUERY_LENGTH {
+            self.query.push(c);
+            self.selected = 0;
+        }
+    }
+
+    /// Removes the last character of the search query
+    pub fn erase(&mut self) {
+        if self.query.pop().is_some() {
+            self.selected = 0;
+        }
+    }
+
+    /// Moves the selection by a number of recipes, which
+    /// stops at the first and the last one
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - The number of recipes to move by
+    /// * `recipes` - The recipes
+    pub fn select(&mut self, delta: i32, recipes: &Recipes) {
+        let count = recipes.search(&self.query).len() as i32;
+        self.selected = (self.selected as i32 + delta).min(count - 1).max(0) as usize;
+    }
+
+    /// Returns the selected recipe, if any matches the query
+    ///
+    /// # Arguments
+    ///
+    /// * `recipes` - The recipes
+    pub fn selected(&self, recipes: &Recipes) -> Option<Recipe> {
+        recipes.search(&self.query).into_iter().nth(self.selected)
+    }
+
+    /// Returns the book as it's shown, or `None` if it's closed
+    ///
+    /// # Arguments
+    ///
+    /// * `recipes` - The recipes
+    /// * `items` - The amount of each collected item
+    pub fn view(&self, recipes: &Recipes, items: &BTreeMap<String, u64>) -> Option<RecipeBookView> {
+        if !self.open {
+            return None;
+        }
+        let entries = recipes.search(&self.query).into_iter()
+            .map(|recipe| RecipeEntry { craftable: recipes.can_craft(&recipe, items), recipe })
+            .collect();
+        Some(RecipeBookView {
+            query: self.query.clone(),
+            entries,
+            selected: self.selected,
+        })
+    }
+}
+
+/// RecipeBookFrame
+///
+/// A frame the recipe book could be rendered for
+pub trait RecipeBookFrame: Frame {
+    /// Returns the recipe book, if it's open
+    fn recipe_book(&self) -> Option<&RecipeBookView>;
+}
+
+/// RecipeBookRenderer
+///
+/// The `RecipeBookRenderer` is the render pass drawing the open
+/// recipe book centered at the top of the screen, with the
+/// search box above the matching recipes. Each recipe is shown
+/// with the icon of its result, and the ones the player lacks
+/// the ingredients of are grayed out.
+pub struct RecipeBookRenderer {
+    /// The painter drawing the book
+    painter: OverlayPainter,
+}
+
+impl RecipeBookRenderer {
+    /// Creates a new recipe book renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+impl<F: RecipeBookFrame> RenderPass<F> for RecipeBookRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let book = match ctx.frame.recipe_book() {
+            Some(book) => book,
+            None => return,
+        };
+
+        let search = format!("Search: {}_", book.query);
+        let first = (book.selected + 1).saturating_sub(VISIBLE_RECIPES);
+        let rows: Vec<(&RecipeEntry, String)> = book.entries.iter()
+            .skip(first)
+            .take(VISIBLE_RECIPES)
+            .map(|entry| {
+                let recipe = &entry.recipe;
+                (entry, format!("{}x {} <- {}", recipe.count, recipe.result, recipe.ingredients_text()))
+            })
+            .collect();
+
+        let icon_size = GLYPH_SIZE as f32;
+        let content_width = rows.iter()
+            .map(|(_, text)| icon_size + ICON_GAP + font::text_width(text) as f32)
+            .fold(font::text_width(&search) as f32, f32::max)
+            .max(MIN_CONTENT_WIDTH);
+        let line_height = GLYPH_SIZE as f32 + LINE_SPACING;
+        let lines = 1 + rows.len().max(1);
+        let height = lines as f32 * line_height - LINE_SPACING + 3.0 * BOOK_PADDING;
+        let width = OVERLAY_HEIGHT * ctx.frame.camera().aspect_ratio();
+        let min = [(width - content_width) / 2.0 - BOOK_PADDING, BOOK_MARGIN];
+        let max_x = min[0] + content_width + 2.0 * BOOK_PADDING;
+
+        let mut overlay = OverlayBuilder::default();
+        overlay.rect(min, [max_x, min[1] + height], Vector4::new(0.0, 0.0, 0.0, 0.65));
+        let x = min[0] + BOOK_PADDING;
+        let mut y = min[1] + BOOK_PADDING;
+        overlay.rect(
+            [x - 2.0, y - 2.0],
+            [max_x - BOOK_PADDING + 2.0, y + GLYPH_SIZE as f32 + 2.0],
+            Vector4::new(1.0, 1.0, 1.0, 0.15),
+        );
+        overlay.text([x, y], &search, Vector4::new(1.0, 0.85, 0.3, 1.0));
+        y += line_height + BOOK_PADDING;
+
+        if rows.is_empty() {
+            overlay.text([x, y], "No recipes found", Vector4::new(0.6, 0.6, 0.6, 1.0));
+        }
+        for (i, (entry, text)) in rows.iter().enumerate() {
+            if first + i == book.selected {
+                overlay.rect(
+                    [x - 2.0, y - LINE_SPACING / 2.0],
+                    [max_x - BOOK_PADDING + 2.0, y + GLYPH_SIZE as f32 + LINE_SPACING / 2.0],
+                    Vector4::new(0.55, 0.8, 1.0, 0.3),
+                );
+            }
+            let mut icon = items::item_color(&entry.recipe.result);
+            let color = if entry.craftable {
+                Vector4::new(1.0, 1.0, 1.0, 1.0)
+            } else {
+                icon.w = 0.4;
+                Vector4::new(0.55, 0.55, 0.55, 1.0)
+            };
+            overlay.rect([x, y], [x + icon_size, y + icon_size], icon);
+            overlay.text([x + icon_size + ICON_GAP, y], text, color);
+            y += line_height;
+        }
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scripting::ScriptEngine;
+    use crate::world::tags::Tags;
+
+    #[test]
+    fn typing_searches_the_recipes() {
+        let engine = ScriptEngine::new();
+        let recipes = Recipes::new(&engine, &Tags::new(&engine).unwrap()).unwrap();
+        engine.exec("test", r#"
+            recipes.define { result = "polished_stone", count = 4, ingredients = { cobblestone = 4 } }
+            recipes.define { result = "bread", ingredients = { wheat = 3 } }
+            recipes.define { result = "chest", ingredients = { cobblestone = 8 } }
+        "#).unwrap();
+        let items: BTreeMap<String, u64> = vec![("cobblestone".to_string(), 5)].into_iter().collect();
+
+        let mut book = RecipeBook::default();
+        book.type_char('c');
+        assert_eq!(book.view(&recipes, &items), None);
+
+        book.toggle();
+        for c in "COBBLE".chars() {
+            book.type_char(c);
+        }
+        book.select(5, &recipes);
+        let view = book.view(&recipes, &items).unwrap();
+        assert_eq!(view.entries.iter().map(|entry| entry.craftable).collect::<Vec<_>>(), vec![true, false]);
+        assert_eq!(book.selected(&recipes).unwrap().result, "chest");
+
+        for _ in 0..6 {
+            book.erase();
+        }
+        book.type_char('\n');
+        assert_eq!(book.view(&recipes, &items).unwrap().entries.len(), 3);
+        assert_eq!(book.selected(&recipes).unwrap().result, "polished_stone");
+    }
+}
@@ -0,0 +1,252 @@
+//! Types to record play sessions and replay them,
+//! e.g. to debug the world or for cinematic captures
+
+use crate::camera::Camera;
+use crate::world::history::BlockEdit;
+
+/// The maximal amount of ticks of a recording, which
+/// are ten minutes at 60 updates per second
+pub const MAX_RECORDED_TICKS: usize = 60 * 60 * 10;
+
+/// ReplayTick
+///
+/// The state of a single recorded simulation update
+#[derive(Clone)]
+pub struct ReplayTick {
+    /// The camera of the player after the update
+    pub camera: Camera,
+    /// The block changes during the update
+    pub edits: Vec<BlockEdit>,
+}
+
+/// Recording
+///
+/// A recorded session, tick by tick
+#[derive(Clone, Default)]
+pub struct Recording {
+    /// The recorded ticks in order
+    ticks: Vec<ReplayTick>,
+}
+
+impl Recording {
+    /// Creates a new, empty recording
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a tick to the recording. Returns `false` if the
+    /// recording is full and the tick has been dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `camera` - The camera of the player after the update
+    /// * `edits` - The block changes during the update
+    pub fn record(&mut self, camera: &Camera, edits: Vec<BlockEdit>) -> bool {
+        if self.ticks.len() >= MAX_RECORDED_TICKS {
+            return false;
+        }
+
+        self.ticks.push(ReplayTick {
+            camera: camera.clone(),
+            edits,
+        });
+        true
+    }
+
+    /// Returns the amount of recorded ticks
+    pub fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    /// Returns whether no tick has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+
+    /// Returns the block changes of all ticks in order
+    pub fn edits(&self) -> impl DoubleEndedIterator<Item = &BlockEdit> {
+        self.ticks.iter().flat_map(|tick| tick.edits.iter())
+    }
+}
+
+/// Replay
+///
+/// The playback of a `Recording`. The replay only moves
+/// forward, but could skip ticks. While replaying, the
+/// camera could be detached from the recorded camera to
+/// spectate the session from anywhere.
+pub struct Replay {
+    /// The recording which is replayed
+    recording: Recording,
+    /// The index of the next tick
+    cursor: usize,
+    /// Whether the playback is paused
+    paused: bool,
+    /// Whether the camera is detached from the recorded camera
+    spectating: bool,
+}
+
+impl Replay {
+    /// Creates a new replay starting at the first tick
+    ///
+    /// # Arguments
+    ///
+    /// * `recording` - The recording which should be replayed
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            cursor: 0,
+            paused: false,
+            spectating: false,
+        }
+    }
+
+    /// Advances the replay by a single tick, unless it's paused.
+    /// Returns the replayed tick, or `None` if the replay is
+    /// paused or finished.
+    pub fn advance(&mut self) -> Option<&ReplayTick> {
+        if self.paused {
+            return None;
+        }
+        self.skip(1).last()
+    }
+
+    /// Skips the given amount of ticks and returns them in
+    /// order, so that their block changes could be applied.
+    /// The replay can't skip beyond its end.
+    ///
+    /// # Arguments
+    ///
+    /// * `ticks` - The amount of ticks to skip
+    pub fn skip(&mut self, ticks: usize) -> &[ReplayTick] {
+        let start = self.cursor;
+        self.cursor = (self.cursor + ticks).min(self.recording.len());
+        &self.recording.ticks[start..self.cursor]
+    }
+
+    /// Returns the ticks which haven't been replayed yet
+    pub fn remaining(&self) -> &[ReplayTick] {
+        &self.recording.ticks[self.cursor..]
+    }
+
+    /// Returns the most recently replayed tick
+    pub fn current(&self) -> Option<&ReplayTick> {
+        self.cursor.checked_sub(1).map(|index| &self.recording.ticks[index])
+    }
+
+    /// Returns whether all ticks have been replayed
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.recording.len()
+    }
+
+    /// Returns the index of the next tick
+    pub fn position(&self) -> usize {
+        self.cursor
+    }
+
+    /// Returns the recording which is replayed
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    /// Stops the replay and returns its recording
+    pub fn into_recording(self) -> Recording {
+        self.recording
+    }
+
+    /// Pauses or resumes the playback. Returns whether
+    /// the playback is paused now.
+    pub fn toggle_paused(&mut self) -> bool {
+        self.paused = !self.paused;
+        self.paused
+    }
+
+    /// Detaches the camera from or attaches it to the recorded
+    /// camera. Returns whether the camera is detached now.
+    pub fn toggle_spectating(&mut self) -> bool {
+        self.spectating = !self.spectating;
+        self.spectating
+    }
+
+    /// Returns whether the camera is detached from the
+    /// recorded camera
+    pub fn is_spectating(&self) -> bool {
+        self.spectating
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::Material;
+    use cgmath::Vector3;
+
+    /// Creates a recording of the given amount of ticks with
+    /// a single edit per tick at the x coordinate of the tick
+    fn recording(ticks: usize) -> Recording {
+        let mut recording = Recording::new();
+        for i in 0..ticks {
+            let mut camera = Camera::default();
+            camera.set_pos(Vector3::new(i as f32, 0.0, 0.0));
+            recording.record(&camera, vec![BlockEdit {
+                pos: Vector3::new(i as i32, 0, 0),
+                old: Material::Air,
+                new: Material::Dirt,
+            }]);
+        }
+        recording
+    }
+
+    fn xs(ticks: &[ReplayTick]) -> Vec<i32> {
+        ticks.iter().flat_map(|tick| tick.edits.iter()).map(|edit| edit.pos.x).collect()
+    }
+
+    #[test]
+    fn recording_is_bounded() {
+        let mut recording = recording(MAX_RECORDED_TICKS);
+        assert!(!recording.record(&Camera::default(), Vec::new()));
+        assert_eq!(recording.len(), MAX_RECORDED_TICKS);
+    }
+
+    #[test]
+    fn replay_advances_tick_by_tick() {
+        let mut replay = Replay::new(recording(2));
+        assert!(replay.current().is_none());
+
+        assert_eq!(replay.advance().unwrap().camera.pos().x, 0.0);
+        assert_eq!(replay.advance().unwrap().camera.pos().x, 1.0);
+        assert!(replay.advance().is_none());
+        assert!(replay.is_finished());
+        assert_eq!(replay.current().unwrap().camera.pos().x, 1.0);
+    }
+
+    #[test]
+    fn paused_replay_could_be_scrubbed() {
+        let mut replay = Replay::new(recording(10));
+        replay.advance();
+        assert!(replay.toggle_paused());
+        assert!(replay.advance().is_none());
+        assert_eq!(replay.position(), 1);
+
+        assert_eq!(xs(replay.skip(3)), vec![1, 2, 3]);
+        assert_eq!(replay.position(), 4);
+        assert_eq!(xs(replay.remaining()).len(), 6);
+    }
+
+    #[test]
+    fn skipping_stops_at_the_end() {
+        let mut replay = Replay::new(recording(3));
+        assert_eq!(xs(replay.skip(100)), vec![0, 1, 2]);
+        assert!(replay.skip(1).is_empty());
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    fn spectating_is_toggled() {
+        let mut replay = Replay::new(Recording::new());
+        assert!(!replay.is_spectating());
+        assert!(replay.toggle_spectating());
+        assert!(replay.is_spectating());
+        assert!(replay.is_finished());
+    }
+}
@@ -1,10 +1,12 @@
 //! Types implementing a simple resources system
 
+use std::collections::HashMap;
 use std::ffi;
 use std::fs;
-use std::io::{self, Read, Error};
+use std::io::{self, Error};
 use std::path::{Path, PathBuf};
 use image::{ImageError, DynamicImage};
+use crate::archive::ZipArchive;
 
 #[derive(Debug)]
 pub enum ResourceError {
@@ -12,6 +14,7 @@ pub enum ResourceError {
     FileContainsNil,
     Io(io::Error),
     Image(image::ImageError),
+    Toml(String),
 }
 
 impl From<io::Error> for ResourceError {
@@ -26,9 +29,46 @@ impl From<image::ImageError> for ResourceError {
     }
 }
 
+/// A pack of files layered over the resource directory
+enum Layer {
+    /// A directory of files
+    Dir(PathBuf),
+    /// A ZIP archive of files
+    Zip(ZipArchive),
+}
+
+impl Layer {
+    /// Returns whether the pack contains a file
+    ///
+    /// # Arguments
+    ///
+    /// * `resource_name` - The resource name of the file
+    fn contains(&self, resource_name: &str) -> bool {
+        match self {
+            Layer::Dir(path) => resource_name_to_path(path, resource_name).is_file(),
+            Layer::Zip(archive) => archive.contains(resource_name),
+        }
+    }
+}
+
+/// Resources
+///
+/// The files of the resource directory, which could be
+/// replaced by layers of other packs, e.g. by the resource
+/// pack of a world. Packs are either directories or ZIP
+/// archives. A file is loaded from the topmost layer
+/// containing it, which is the pack layered last. The
+/// tables of `TOML` files could be overridden key by key
+/// as well.
 pub struct Resources {
     /// The root path of the resource directory
     root_path: PathBuf,
+    /// The packs layered over the resource
+    /// directory, the topmost one first
+    layers: Vec<Layer>,
+    /// The tables merged into `TOML` files by their
+    /// resource name
+    overrides: HashMap<String, toml::value::Table>,
 }
 
 impl Resources {
@@ -48,9 +88,101 @@ impl Resources {
         let exe_path = exe_file_name.parent()
             .ok_or(ResourceError::FailedToGetExePath)?;
 
-        Ok(Resources {
-            root_path: exe_path.join(rel_path)
-        })
+        Ok(Resources::from_path(exe_path.join(rel_path)))
+    }
+
+    /// Creates a new `Resources` instance from the path of
+    /// the resource directory
+    ///
+    /// # Arguments
+    ///
+    /// * `root_path` - The path of the resource directory
+    pub fn from_path(root_path: PathBuf) -> Resources {
+        Resources {
+            root_path,
+            layers: Vec::new(),
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Layers a directory over the resources, whose
+    /// files replace the ones with the same name
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the directory
+    pub fn with_layer(mut self, path: PathBuf) -> Self {
+        self.layers.insert(0, Layer::Dir(path));
+        self
+    }
+
+    /// Layers a ZIP archive over the resources, whose
+    /// files replace the ones with the same name
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - The archive
+    pub fn with_archive(mut self, archive: ZipArchive) -> Self {
+        self.layers.insert(0, Layer::Zip(archive));
+        self
+    }
+
+    /// Overrides values of a `TOML` file, which are merged
+    /// into the file's tables whenever it's loaded
+    ///
+    /// # Arguments
+    ///
+    /// * `resource_name` - The resource name of the file
+    /// * `table` - The values which should be overridden
+    pub fn with_overrides(mut self, resource_name: &str, table: toml::value::Table) -> Self {
+        let overrides = self.overrides.entry(resource_name.to_string()).or_default();
+        merge_tables(overrides, table);
+        self
+    }
+
+    /// Returns the path of a file located in a resource directory,
+    /// e.g. to watch it for changes. This is the file of the topmost
+    /// directory containing it, or the one of the resource directory.
+    /// Files of archives have no path of their own, so they are
+    /// skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource_name` - The resource name of the file
+    pub fn path(&self, resource_name: &str) -> PathBuf {
+        self.layers.iter()
+            .filter_map(|layer| match layer {
+                Layer::Dir(path) => Some(resource_name_to_path(path, resource_name)),
+                Layer::Zip(_) => None,
+            })
+            .find(|path| path.exists())
+            .unwrap_or_else(|| resource_name_to_path(&self.root_path, resource_name))
+    }
+
+    /// Returns whether a file exists in any layer
+    /// or the resource directory
+    ///
+    /// # Arguments
+    ///
+    /// * `resource_name` - The resource name of the file
+    pub fn exists(&self, resource_name: &str) -> bool {
+        self.layers.iter().any(|layer| layer.contains(resource_name))
+            || resource_name_to_path(&self.root_path, resource_name).exists()
+    }
+
+    /// Reads the bytes of a file of the topmost layer
+    /// containing it, or the one of the resource directory
+    ///
+    /// # Arguments
+    ///
+    /// * `resource_name` - The resource name of the file
+    fn read(&self, resource_name: &str) -> Result<Vec<u8>, ResourceError> {
+        let bytes = match self.layers.iter().find(|layer| layer.contains(resource_name)) {
+            Some(Layer::Dir(path)) => fs::read(resource_name_to_path(path, resource_name))?,
+            Some(Layer::Zip(archive)) => archive.read(resource_name)?,
+            None => fs::read(resource_name_to_path(&self.root_path, resource_name))?,
+        };
+        Ok(bytes)
     }
 
     /// Loads a cstring out of an file located in a resource directory.
@@ -61,11 +193,8 @@ impl Resources {
     ///
     /// * `resource_name` - The resource name the cstring should be read.
     pub fn load_cstring(&self, resource_name: &str) -> Result<ffi::CString, ResourceError> {
-        let mut file = fs::File::open(resource_name_to_path(&self.root_path, resource_name))?;
-
-        // allocate buffer of the same size as file
-        let mut buffer: Vec<u8> = Vec::with_capacity(file.metadata()?.len() as usize + 1);
-        file.read_to_end(&mut buffer)?;
+        let mut buffer = self.read(resource_name)?;
+        buffer.reserve_exact(1);
 
         // check for nil byte
         if buffer.iter().find(|i| **i == 0).is_some() {
@@ -75,18 +204,112 @@ impl Resources {
         Ok(unsafe { ffi::CString::from_vec_unchecked(buffer)})
     }
 
+    /// Loads a string out of a file located in a resource directory.
+    /// This function might end in a `ResourceError` if the file could
+    /// somehow not be read correctly or isn't valid UTF-8. Overridden
+    /// values are merged into `TOML` files, which are formatted anew.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource_name` - The resource name the string should be read.
+    pub fn load_string(&self, resource_name: &str) -> Result<String, ResourceError> {
+        let string = String::from_utf8(self.read(resource_name)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        let overrides = match self.overrides.get(resource_name) {
+            Some(overrides) => overrides,
+            None => return Ok(string),
+        };
+
+        let mut table: toml::value::Table = toml::from_str(&string)
+            .map_err(|error| ResourceError::Toml(error.to_string()))?;
+        merge_tables(&mut table, overrides.clone());
+        toml::to_string(&table).map_err(|error| ResourceError::Toml(error.to_string()))
+    }
+
+    /// Returns the names of all files within a resource directory
+    /// which have the given extension, sorted by name. The returned
+    /// names could be passed to the other load functions.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource_dir` - The resource directory which should be listed
+    /// * `extension` - The extension of the files, e.g. `lua`
+    pub fn list_files(&self, resource_dir: &str, extension: &str) -> Result<Vec<String>, ResourceError> {
+        let mut names = Vec::new();
+        // Layers could add files, but don't need to have the directory
+        for layer in &self.layers {
+            match layer {
+                Layer::Dir(path) => {
+                    let dir = resource_name_to_path(path, resource_dir);
+                    if dir.is_dir() {
+                        list_dir(&dir, resource_dir, extension, &mut names)?;
+                    }
+                }
+                Layer::Zip(archive) => {
+                    let prefix = format!("{}/", resource_dir);
+                    names.extend(archive.names()
+                        .filter(|name| name.strip_prefix(&prefix).is_some_and(|file_name| !file_name.contains('/')))
+                        .filter(|name| Path::new(name).extension().is_some_and(|ext| ext == extension))
+                        .map(str::to_string));
+                }
+            }
+        }
+        list_dir(&resource_name_to_path(&self.root_path, resource_dir), resource_dir, extension, &mut names)?;
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
     /// Loads a image from a resource directory.
     ///
     /// # Arguments
     ///
     /// * `resource_name` - The resource name the image should be read.
     pub fn load_image(&self, resource_name: &str) -> Result<DynamicImage, ResourceError> {
-        let path = resource_name_to_path(&self.root_path, resource_name);
-        let image = image::open(path)?;
+        let image = image::load_from_memory(&self.read(resource_name)?)?;
         Ok(image)
     }
 }
 
+/// Adds the resource names of the files within a directory
+/// which have the given extension
+///
+/// # Arguments
+///
+/// * `dir` - The path of the directory
+/// * `resource_dir` - The resource name of the directory
+/// * `extension` - The extension of the files
+/// * `names` - The resource names
+fn list_dir(dir: &Path, resource_dir: &str, extension: &str, names: &mut Vec<String>) -> Result<(), ResourceError> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().map_or(false, |ext| ext == extension) {
+            if let Some(file_name) = path.file_name().and_then(|name| name.to_str()) {
+                names.push(format!("{}/{}", resource_dir, file_name));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Merges a table into another one. Nested tables are merged
+/// key by key, while other values are replaced.
+///
+/// # Arguments
+///
+/// * `table` - The table which is changed
+/// * `overrides` - The table which is merged into it
+fn merge_tables(table: &mut toml::value::Table, overrides: toml::value::Table) {
+    for (key, value) in overrides {
+        match (table.get_mut(&key), value) {
+            (Some(toml::Value::Table(nested)), toml::Value::Table(value)) => merge_tables(nested, value),
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
+
 /// Helper function which takes a root directory and a path location
 /// to create a platform independent path by splitting over all `/` and
 /// adding them to the path with the correct separator internally.
@@ -103,4 +326,65 @@ fn resource_name_to_path(root_dir: &Path, location: &str) -> PathBuf {
     }
 
     path
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive;
+
+    #[test]
+    fn layers_replace_files_and_overrides_are_merged() {
+        let root = std::env::temp_dir().join(format!("rustcraft-resources-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("base/shaders")).unwrap();
+        fs::create_dir_all(root.join("pack/shaders")).unwrap();
+        fs::write(root.join("base/shaders/sky.frag"), "base").unwrap();
+        fs::write(root.join("base/shaders/ui.frag"), "base").unwrap();
+        fs::write(root.join("pack/shaders/sky.frag"), "pack").unwrap();
+        fs::write(root.join("pack/shaders/fog.frag"), "pack").unwrap();
+        fs::write(root.join("base/settings.toml"), "[cave_fog]\nenabled = true\ndensity = 0.05\n").unwrap();
+
+        let mut overrides = toml::value::Table::new();
+        overrides.insert("cave_fog".to_string(), toml::Value::Table(toml::from_str("density = 0.2").unwrap()));
+        let resources = Resources::from_path(root.join("base"))
+            .with_layer(root.join("pack"))
+            .with_overrides("settings.toml", overrides);
+
+        assert_eq!(resources.load_string("shaders/sky.frag").unwrap(), "pack");
+        assert_eq!(resources.load_string("shaders/ui.frag").unwrap(), "base");
+        assert_eq!(resources.list_files("shaders", "frag").unwrap(), vec!["shaders/fog.frag", "shaders/sky.frag", "shaders/ui.frag"]);
+
+        let settings: toml::Value = toml::from_str(&resources.load_string("settings.toml").unwrap()).unwrap();
+        assert_eq!(settings["cave_fog"]["enabled"].as_bool(), Some(true));
+        assert_eq!(settings["cave_fog"]["density"].as_float(), Some(0.2));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn zipped_packs_are_searched_from_the_last_one() {
+        let root = std::env::temp_dir().join(format!("rustcraft-packs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("base/scripts")).unwrap();
+        fs::write(root.join("base/scripts/init.lua"), "base").unwrap();
+        fs::write(root.join("base/scripts/mobs.lua"), "base").unwrap();
+        fs::write(root.join("first.zip"), archive::stored_archive(&[
+            ("scripts/init.lua", b"first"),
+            ("scripts/mobs.lua", b"first"),
+            ("scripts/lib/util.lua", b"first"),
+        ])).unwrap();
+        fs::write(root.join("second.zip"), archive::stored_archive(&[("scripts/init.lua", b"second")])).unwrap();
+
+        let resources = Resources::from_path(root.join("base"))
+            .with_archive(ZipArchive::open(&root.join("first.zip")).unwrap())
+            .with_archive(ZipArchive::open(&root.join("second.zip")).unwrap());
+        assert_eq!(resources.load_string("scripts/init.lua").unwrap(), "second");
+        assert_eq!(resources.load_string("scripts/mobs.lua").unwrap(), "first");
+        assert_eq!(resources.list_files("scripts", "lua").unwrap(), vec!["scripts/init.lua", "scripts/mobs.lua"]);
+        assert!(resources.exists("scripts/lib/util.lua"));
+        assert!(!resources.exists("scripts/missing.lua"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,345 @@
+//! Types to keep scores of the players, e.g. for minigames
+//! defined by scripts, and to show them on the screen
+
+use crate::graphics::backend::GlBackend;
+use crate::graphics::font::{self, GLYPH_SIZE};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use crate::scripting::ScriptEngine;
+use cgmath::Vector4;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// The maximal amount of scores shown on the sidebar
+pub const MAX_SIDEBAR_SCORES: usize = 15;
+/// The name of the only display slot, the sidebar
+const SIDEBAR_SLOT: &str = "sidebar";
+
+/// The distance of the sidebar to the right edge of the screen
+const SIDEBAR_MARGIN: f32 = 8.0;
+/// The distance of the text to the edges of the sidebar
+const SIDEBAR_PADDING: f32 = 4.0;
+/// The minimal distance of a name to its score
+const SCORE_GAP: f32 = 16.0;
+/// The vertical distance of two lines of text
+const LINE_SPACING: f32 = 2.0;
+
+/// Objective
+///
+/// A named set of scores, one per player
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Objective {
+    /// The title of the objective shown on the screen
+    pub title: String,
+    /// The scores by the name of the player
+    pub scores: BTreeMap<String, i64>,
+}
+
+/// ScoreboardData
+///
+/// Everything which is persisted about the scoreboard
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoreboardData {
+    /// The name of the objective shown on the sidebar
+    pub sidebar: Option<String>,
+    /// The objectives by their name
+    pub objectives: BTreeMap<String, Objective>,
+}
+
+impl ScoreboardData {
+    /// Returns the objective with the given name, or an
+    /// error for the scripts if there is none
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the objective
+    fn objective_mut(&mut self, name: &str) -> mlua::Result<&mut Objective> {
+        self.objectives.get_mut(name).ok_or_else(|| {
+            mlua::Error::RuntimeError(format!("unknown objective '{}'", name))
+        })
+    }
+}
+
+/// Sidebar
+///
+/// The highest scores of an objective as they're shown
+/// on the side of the screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sidebar {
+    /// The title of the objective
+    pub title: String,
+    /// The names of the players with their score, the
+    /// highest score first
+    pub scores: Vec<(String, i64)>,
+}
+
+/// Scoreboard
+///
+/// The objectives scripts keep scores in through the
+/// `scoreboard` API, e.g. a minigame counting the blocks
+/// each player broke:
+///
+/// ```lua
+/// scoreboard.add("mined", "Blocks mined")
+/// scoreboard.display("sidebar", "mined")
+/// events.on("block_broken", function(player, block)
+///     scoreboard.increase("mined", player, 1)
+/// end)
+/// ```
+///
+/// Besides `add`, `display` and `increase`, the API offers
+/// `remove` to remove an objective, `get` and `set` to read
+/// and change a score, and `reset` to remove a score. An
+/// objective added again keeps its scores, so that scripts
+/// could add their objectives whenever they're loaded.
+pub struct Scoreboard {
+    /// The objectives, shared with the Lua API
+    data: Arc<Mutex<ScoreboardData>>,
+}
+
+impl Scoreboard {
+    /// Creates a new scoreboard and registers its API in the
+    /// given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `data` - The persisted objectives
+    pub fn new(engine: &ScriptEngine, data: ScoreboardData) -> mlua::Result<Self> {
+        let data = Arc::new(Mutex::new(data));
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let shared = data.clone();
+        api.set("add", lua.create_function(move |_, (name, title): (String, Option<String>)| {
+            let title = title.unwrap_or_else(|| name.clone());
+            shared.lock().unwrap().objectives.entry(name).or_default().title = title;
+            Ok(())
+        })?)?;
+        let shared = data.clone();
+        api.set("remove", lua.create_function(move |_, name: String| {
+            let mut data = shared.lock().unwrap();
+            if data.sidebar.as_ref() == Some(&name) {
+                data.sidebar = None;
+            }
+            Ok(data.objectives.remove(&name).is_some())
+        })?)?;
+        let shared = data.clone();
+        api.set("get", lua.create_function(move |_, (name, player): (String, String)| {
+            let mut data = shared.lock().unwrap();
+            Ok(data.objective_mut(&name)?.scores.get(&player).cloned().unwrap_or(0))
+        })?)?;
+        let shared = data.clone();
+        api.set("set", lua.create_function(move |_, (name, player, score): (String, String, i64)| {
+            shared.lock().unwrap().objective_mut(&name)?.scores.insert(player, score);
+            Ok(())
+        })?)?;
+        let shared = data.clone();
+        api.set("increase", lua.create_function(move |_, (name, player, amount): (String, String, i64)| {
+            let mut data = shared.lock().unwrap();
+            let score = data.objective_mut(&name)?.scores.entry(player).or_insert(0);
+            *score = score.saturating_add(amount);
+            Ok(*score)
+        })?)?;
+        let shared = data.clone();
+        api.set("reset", lua.create_function(move |_, (name, player): (String, String)| {
+            shared.lock().unwrap().objective_mut(&name)?.scores.remove(&player);
+            Ok(())
+        })?)?;
+        let shared = data.clone();
+        api.set("display", lua.create_function(move |_, (slot, name): (String, Option<String>)| {
+            if slot != SIDEBAR_SLOT {
+                return Err(mlua::Error::RuntimeError(format!("unknown display slot '{}'", slot)));
+            }
+            let mut data = shared.lock().unwrap();
+            if let Some(name) = name.as_ref() {
+                data.objective_mut(name)?;
+            }
+            data.sidebar = name;
+            Ok(())
+        })?)?;
+        lua.globals().set("scoreboard", api)?;
+
+        Ok(Self {
+            data,
+        })
+    }
+
+    /// Returns the objectives, e.g. to persist them
+    pub fn data(&self) -> ScoreboardData {
+        self.data.lock().unwrap().clone()
+    }
+
+    /// Returns the highest scores of the objective shown on
+    /// the sidebar, or `None` if no objective is shown
+    pub fn sidebar(&self) -> Option<Sidebar> {
+        let data = self.data.lock().unwrap();
+        let objective = data.objectives.get(data.sidebar.as_ref()?)?;
+
+        let mut scores: Vec<(String, i64)> = objective.scores.iter()
+            .map(|(player, score)| (player.clone(), *score))
+            .collect();
+        scores.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scores.truncate(MAX_SIDEBAR_SCORES);
+
+        Some(Sidebar {
+            title: objective.title.clone(),
+            scores,
+        })
+    }
+}
+
+/// ScoreboardFrame
+///
+/// A frame the scoreboard could be rendered for
+pub trait ScoreboardFrame: Frame {
+    /// Returns the sidebar, if an objective is shown on it
+    fn sidebar(&self) -> Option<&Sidebar>;
+}
+
+/// SidebarRenderer
+///
+/// The `SidebarRenderer` is the render pass drawing the
+/// sidebar centered at the right edge of the screen, with
+/// the title of the objective above the scores.
+pub struct SidebarRenderer {
+    /// The painter drawing the sidebar
+    painter: OverlayPainter,
+}
+
+impl SidebarRenderer {
+    /// Creates a new sidebar renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+impl<F: ScoreboardFrame> RenderPass<F> for SidebarRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let sidebar = match ctx.frame.sidebar() {
+            Some(sidebar) => sidebar,
+            None => return,
+        };
+
+        let scores: Vec<(String, String)> = sidebar.scores.iter()
+            .map(|(player, score)| (player.clone(), score.to_string()))
+            .collect();
+        let title_width = font::text_width(&sidebar.title) as f32;
+        let content_width = scores.iter()
+            .map(|(player, score)| (font::text_width(player) + font::text_width(score)) as f32 + SCORE_GAP)
+            .fold(title_width, f32::max);
+
+        let line_height = GLYPH_SIZE as f32 + LINE_SPACING;
+        let height = (scores.len() + 1) as f32 * line_height - LINE_SPACING + 2.0 * SIDEBAR_PADDING;
+        let width = OVERLAY_HEIGHT * ctx.frame.camera().aspect_ratio();
+        let max_x = width - SIDEBAR_MARGIN;
+        let min = [max_x - content_width - 2.0 * SIDEBAR_PADDING, (OVERLAY_HEIGHT - height) / 2.0];
+
+        let mut overlay = OverlayBuilder::default();
+        overlay.rect(min, [max_x, min[1] + height], Vector4::new(0.0, 0.0, 0.0, 0.4));
+        overlay.rect(min, [max_x, min[1] + line_height + SIDEBAR_PADDING], Vector4::new(0.0, 0.0, 0.0, 0.2));
+
+        let left = min[0] + SIDEBAR_PADDING;
+        let right = max_x - SIDEBAR_PADDING;
+        let mut y = min[1] + SIDEBAR_PADDING;
+        overlay.text([(left + right - title_width) / 2.0, y], &sidebar.title, Vector4::new(1.0, 1.0, 1.0, 1.0));
+        for (player, score) in scores.iter() {
+            y += line_height;
+            overlay.text([left, y], player, Vector4::new(0.8, 0.8, 0.8, 1.0));
+            overlay.text([right - font::text_width(score) as f32, y], score, Vector4::new(1.0, 0.33, 0.33, 1.0));
+        }
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scoreboard(source: &str) -> (ScriptEngine, Scoreboard) {
+        let engine = ScriptEngine::new();
+        let scoreboard = Scoreboard::new(&engine, ScoreboardData::default()).unwrap();
+        engine.exec("test", source).unwrap();
+        (engine, scoreboard)
+    }
+
+    #[test]
+    fn scripts_keep_scores() {
+        let (engine, scoreboard) = scoreboard(r#"
+            scoreboard.add("mined", "Blocks mined")
+            scoreboard.set("mined", "alice", 3)
+            scoreboard.increase("mined", "bob", 2)
+            scoreboard.increase("mined", "alice", 1)
+            scoreboard.reset("mined", "nobody")
+        "#);
+        let objective = &scoreboard.data().objectives["mined"];
+        assert_eq!(objective.title, "Blocks mined");
+        assert_eq!(objective.scores["alice"], 4);
+        assert_eq!(objective.scores["bob"], 2);
+
+        engine.exec("test", r#"
+            assert(scoreboard.get("mined", "carol") == 0)
+            scoreboard.add("mined")
+        "#).unwrap();
+        let objective = &scoreboard.data().objectives["mined"];
+        assert_eq!(objective.title, "mined");
+        assert_eq!(objective.scores.len(), 2);
+    }
+
+    #[test]
+    fn unknown_objectives_and_slots_are_rejected() {
+        let (engine, scoreboard) = scoreboard(r#"scoreboard.add("kills")"#);
+        assert!(engine.exec("test", r#"scoreboard.set("deaths", "alice", 1)"#).is_err());
+        assert!(engine.exec("test", r#"scoreboard.display("sidebar", "deaths")"#).is_err());
+        assert!(engine.exec("test", r#"scoreboard.display("belowName", "kills")"#).is_err());
+        assert_eq!(scoreboard.data().sidebar, None);
+    }
+
+    #[test]
+    fn sidebar_shows_the_highest_scores() {
+        let (engine, scoreboard) = scoreboard(r#"
+            scoreboard.add("mined", "Blocks mined")
+            for i = 1, 20 do
+                scoreboard.set("mined", "player" .. i, i % 10)
+            end
+        "#);
+        assert_eq!(scoreboard.sidebar(), None);
+
+        engine.exec("test", r#"scoreboard.display("sidebar", "mined")"#).unwrap();
+        let sidebar = scoreboard.sidebar().unwrap();
+        assert_eq!(sidebar.title, "Blocks mined");
+        assert_eq!(sidebar.scores.len(), MAX_SIDEBAR_SCORES);
+        assert_eq!(sidebar.scores[0], ("player19".to_string(), 9));
+        assert_eq!(sidebar.scores[1], ("player9".to_string(), 9));
+
+        engine.exec("test", r#"scoreboard.remove("mined")"#).unwrap();
+        assert_eq!(scoreboard.sidebar(), None);
+    }
+
+    #[test]
+    fn scoreboard_is_persisted() {
+        let (_engine, scoreboard) = scoreboard(r#"
+            scoreboard.add("mined", "Blocks mined")
+            scoreboard.set("mined", "alice", 3)
+            scoreboard.display("sidebar", "mined")
+        "#);
+        let data = scoreboard.data();
+        let restored: ScoreboardData = toml::from_str(&toml::to_string(&data).unwrap()).unwrap();
+        assert_eq!(restored, data);
+    }
+}
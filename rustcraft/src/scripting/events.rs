@@ -0,0 +1,127 @@
+//! Types to notify scripts about things happening in the
+//! game, e.g. a block the player broke
+
+use crate::scripting::{ScriptEngine, ScriptError};
+use mlua::{Function, IntoLuaMulti, RegistryKey};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// A block has been broken, with the name of the player
+/// and the name of the block
+pub const BLOCK_BROKEN: &str = "block_broken";
+/// A block has been placed, with the name of the player
+/// and the name of the block
+pub const BLOCK_PLACED: &str = "block_placed";
+/// Items have been picked up, with the name of the player,
+/// the name of the item and the amount of items
+pub const ITEM_PICKED_UP: &str = "item_picked_up";
+
+/// All events callbacks could be registered for
+const EVENTS: [&str; 3] = [BLOCK_BROKEN, BLOCK_PLACED, ITEM_PICKED_UP];
+
+/// Events
+///
+/// The callbacks scripts registered for events through the
+/// `events.on` function, e.g.
+///
+/// ```lua
+/// events.on("block_broken", function(player, block)
+///     scoreboard.increase("mined", player, 1)
+/// end)
+/// ```
+///
+/// All callbacks of an event are called in the order they
+/// have been registered.
+pub struct Events {
+    /// The callbacks by the name of the event, stored
+    /// in the Lua registry
+    callbacks: Arc<Mutex<HashMap<String, Vec<RegistryKey>>>>,
+}
+
+impl Events {
+    /// Creates a new event registry and registers its
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let callbacks = Arc::new(Mutex::new(HashMap::new()));
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let registered = callbacks.clone();
+        api.set("on", lua.create_function(move |lua, (event, callback): (String, Function)| {
+            if !EVENTS.contains(&event.as_str()) {
+                return Err(mlua::Error::RuntimeError(format!("unknown event '{}'", event)));
+            }
+            let key = lua.create_registry_value(callback)?;
+            registered.lock().unwrap().entry(event).or_insert_with(Vec::new).push(key);
+            Ok(())
+        })?)?;
+        lua.globals().set("events", api)?;
+
+        Ok(Self {
+            callbacks,
+        })
+    }
+
+    /// Calls all callbacks of an event and returns the
+    /// errors of failing callbacks
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine the callbacks are registered in
+    /// * `event` - The name of the event
+    /// * `args` - The arguments the callbacks are called with
+    pub fn fire<'lua, A>(&self, engine: &'lua ScriptEngine, event: &str, args: A) -> Vec<ScriptError>
+        where A: IntoLuaMulti<'lua> + Clone
+    {
+        let lua = engine.lua();
+
+        // The callbacks are taken out of the registry first, so
+        // that they could register callbacks themselves
+        let callbacks: Vec<mlua::Result<Function>> = match self.callbacks.lock().unwrap().get(event) {
+            Some(keys) => keys.iter().map(|key| lua.registry_value(key)).collect(),
+            None => return Vec::new(),
+        };
+
+        callbacks.into_iter()
+            .filter_map(|callback| callback.and_then(|callback| callback.call::<_, ()>(args.clone())).err())
+            .map(|error| ScriptError::new(&format!("event '{}'", event), error))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn callbacks_are_called_in_order() {
+        let engine = ScriptEngine::new();
+        let events = Events::new(&engine).unwrap();
+        engine.exec("test", r#"
+            calls = {}
+            events.on("block_broken", function(player, block) table.insert(calls, player .. ":" .. block) end)
+            events.on("block_broken", function(player, block) table.insert(calls, "second") end)
+            events.on("block_placed", function() error("oops") end)
+        "#).unwrap();
+
+        assert!(events.fire(&engine, BLOCK_BROKEN, ("Player", "stone")).is_empty());
+        let calls: Vec<String> = engine.lua().globals().get("calls").unwrap();
+        assert_eq!(calls, vec!["Player:stone", "second"]);
+
+        let errors = events.fire(&engine, BLOCK_PLACED, ("Player", "dirt"));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].source(), "event 'block_placed'");
+        assert!(events.fire(&engine, ITEM_PICKED_UP, ("Player", "dirt", 1)).is_empty());
+    }
+
+    #[test]
+    fn unknown_events_are_rejected() {
+        let engine = ScriptEngine::new();
+        let _events = Events::new(&engine).unwrap();
+        assert!(engine.exec("test", r#"events.on("block_brokne", function() end)"#).is_err());
+    }
+}
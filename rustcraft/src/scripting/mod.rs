@@ -0,0 +1,168 @@
+//! Types to run Lua scripts, e.g. the mods of the game
+//!
+//! Each subsystem exposing an API to Lua registers its
+//! globals on the `ScriptEngine` before the scripts are
+//! loaded, so the scripts could use them right away.
+
+pub mod events;
+
+use crate::resources::{ResourceError, Resources};
+use cgmath::Vector3;
+use mlua::{Lua, Table};
+use std::fmt;
+
+/// The resource directory containing the scripts
+const SCRIPT_DIR: &str = "scripts";
+
+/// ScriptError
+///
+/// An error which occurred while a script was loaded
+/// or one of its callbacks was executed.
+#[derive(Debug, Clone)]
+pub struct ScriptError {
+    /// The name of the script or callback
+    source: String,
+    /// The error message
+    message: String,
+}
+
+impl ScriptError {
+    /// Creates a new script error
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The name of the script or callback
+    /// * `message` - The error message
+    pub fn new(source: &str, message: impl ToString) -> Self {
+        Self {
+            source: source.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Returns the name of the script or callback
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the error message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.source, self.message)
+    }
+}
+
+/// Returns the resource names of all scripts, e.g. the mods
+/// of the game, in the order they are executed in
+///
+/// # Arguments
+///
+/// * `resources` - A resource instance
+pub fn script_names(resources: &Resources) -> Result<Vec<String>, ResourceError> {
+    resources.list_files(SCRIPT_DIR, "lua")
+}
+
+/// ScriptEngine
+///
+/// The `ScriptEngine` owns the Lua state all scripts
+/// are executed in. It's owned by the simulation thread.
+pub struct ScriptEngine {
+    /// The Lua state
+    lua: Lua,
+}
+
+impl Default for ScriptEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptEngine {
+    /// Creates a new script engine without any loaded scripts
+    pub fn new() -> Self {
+        Self {
+            lua: Lua::new(),
+        }
+    }
+
+    /// Returns the Lua state, e.g. to register an API
+    pub fn lua(&self) -> &Lua {
+        &self.lua
+    }
+
+    /// Executes all scripts of the resource directory in the
+    /// order of their names. A failing script doesn't prevent
+    /// the other scripts from being executed, all errors are
+    /// returned instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - A resource instance
+    pub fn load_scripts(&self, resources: &Resources) -> Vec<ScriptError> {
+        let names = match script_names(resources) {
+            Ok(names) => names,
+            Err(error) => return vec![ScriptError::new(SCRIPT_DIR, format!("{:?}", error))],
+        };
+
+        names.iter()
+            .filter_map(|name| self.exec_resource(resources, name).err())
+            .collect()
+    }
+
+    /// Executes a single script of the resources
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - A resource instance
+    /// * `name` - The resource name of the script
+    pub fn exec_resource(&self, resources: &Resources, name: &str) -> Result<(), ScriptError> {
+        let source = resources.load_string(name)
+            .map_err(|error| ScriptError::new(name, format!("{:?}", error)))?;
+        self.exec(name, &source)
+    }
+
+    /// Executes a chunk of Lua source code
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the chunk shown in error messages
+    /// * `source` - The Lua source code
+    pub fn exec(&self, name: &str, source: &str) -> Result<(), ScriptError> {
+        self.lua.load(source)
+            .set_name(name)
+            .exec()
+            .map_err(|error| ScriptError::new(name, error))
+    }
+}
+
+/// Returns the global table of an API, which is created if
+/// it doesn't exist yet. Thereby, multiple subsystems could
+/// add their functions to the same API, e.g. `blocks`.
+///
+/// # Arguments
+///
+/// * `lua` - The Lua state
+/// * `name` - The name of the global table
+pub fn api_table<'lua>(lua: &'lua Lua, name: &str) -> mlua::Result<Table<'lua>> {
+    if let Some(api) = lua.globals().get::<_, Option<Table>>(name)? {
+        return Ok(api);
+    }
+    let api = lua.create_table()?;
+    lua.globals().set(name, api.clone())?;
+    Ok(api)
+}
+
+/// Reads a vector from a Lua table of three numbers,
+/// e.g. a position `{ x, y, z }` or a color `{ r, g, b }`
+///
+/// # Arguments
+///
+/// * `table` - The Lua table
+pub fn vector_from_table(table: Table) -> mlua::Result<Vector3<f32>> {
+    Ok(Vector3::new(table.get(1)?, table.get(2)?, table.get(3)?))
+}
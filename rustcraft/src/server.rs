@@ -0,0 +1,225 @@
+//! Types configuring the simulation, which acts as the
+//! server of the game, apart from the settings of the
+//! client rendering it
+
+use crate::entity::MovementProfile;
+use crate::resources::Resources;
+use crate::world::time::DEFAULT_DAY_LENGTH;
+use crate::world::RENDER_DISTANCE;
+use serde::Deserialize;
+
+/// The resource declaring the server settings
+const SERVER_FILE: &str = "server.toml";
+
+/// The smallest distance in chunks up to which chunks are loaded
+const MIN_VIEW_DISTANCE: i32 = 2;
+/// The largest distance in chunks up to which chunks are loaded
+const MAX_VIEW_DISTANCE: i32 = 32;
+/// The shortest length of a full day in seconds
+const MIN_DAY_LENGTH: f32 = 10.0;
+
+/// ServerSettings
+///
+/// The settings of the simulation, declared in the
+/// `[server]` table of the server resource, e.g.
+///
+/// ```toml
+/// [server]
+/// view_distance = 6
+/// entity_tick_budget = 256
+/// entity_activation_radius = 4
+/// autosave_interval = 300.0
+/// edit_log = false
+/// backup_interval = 3600.0
+/// backups_kept = 5
+/// metrics_address = "127.0.0.1:9225"
+/// listen_address = "0.0.0.0:25565"
+/// pregen_tasks = 2
+/// pregen_min_tps = 50.0
+/// compress_after = 30.0
+/// evict_after = 300.0
+/// day_length = 1200.0
+/// ```
+///
+/// The view distance decides which chunks are loaded around
+/// the player, no matter how far the client draws them. Only
+/// the entities within the activation radius are ticked, the
+/// ones in further loaded chunks are frozen. How the player
+/// moves is declared in the `[movement]` table next to it, see
+/// `MovementProfile`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ServerSettings {
+    /// The distance in chunks up to which chunks
+    /// are loaded around the player
+    pub view_distance: i32,
+    /// The largest amount of entities ticked per update.
+    /// Further entities are ticked in the next updates.
+    pub entity_tick_budget: usize,
+    /// The distance in chunks up to which entities
+    /// are ticked around the player
+    pub entity_activation_radius: i32,
+    /// The time in seconds between two saves of the
+    /// world, `0` to only save when the game is closed
+    pub autosave_interval: f32,
+    /// Whether the blocks players change are logged,
+    /// e.g. to roll back the edits of a griefer
+    pub edit_log: bool,
+    /// The time in seconds between two backups of
+    /// the world, `0` to only back it up on demand
+    pub backup_interval: f32,
+    /// The amount of backups which are kept, the
+    /// oldest ones are removed beyond it
+    pub backups_kept: usize,
+    /// The address the metrics endpoint listens on,
+    /// `None` to not serve any metrics
+    pub metrics_address: Option<String>,
+    /// The address other players connect to, `None` to
+    /// not host the world for anyone else
+    pub listen_address: Option<String>,
+    /// The largest amount of chunks pre-generated at the
+    /// same time
+    pub pregen_tasks: usize,
+    /// The ticks per second below which no further chunks
+    /// are pre-generated until the simulation caught up
+    pub pregen_min_tps: f32,
+    /// The time in seconds a loaded chunk has to be idle
+    /// before its blocks are compressed, `0` to never
+    /// compress them
+    pub compress_after: f32,
+    /// The time in seconds a loaded chunk has to be idle
+    /// before its compressed blocks are evicted to disk,
+    /// `0` to keep them in memory
+    pub evict_after: f32,
+    /// The length of a full day and night in seconds
+    pub day_length: f32,
+    /// How the player moves, declared in its own table
+    #[serde(skip)]
+    pub movement: MovementProfile,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            view_distance: RENDER_DISTANCE,
+            entity_tick_budget: 256,
+            entity_activation_radius: 4,
+            autosave_interval: 300.0,
+            edit_log: false,
+            backup_interval: 3600.0,
+            backups_kept: 5,
+            metrics_address: None,
+            listen_address: None,
+            pregen_tasks: 2,
+            pregen_min_tps: 50.0,
+            compress_after: 30.0,
+            evict_after: 300.0,
+            day_length: DEFAULT_DAY_LENGTH,
+            movement: MovementProfile::default(),
+        }
+    }
+}
+
+/// The declaration of the server resource
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ServerDef {
+    /// The settings of the simulation
+    server: ServerSettings,
+    /// How the player moves
+    movement: MovementProfile,
+}
+
+impl ServerSettings {
+    /// Loads the settings declared by the resources. If they
+    /// can't be loaded, the default settings are used.
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - A resource instance
+    pub fn load(resources: &Resources) -> Self {
+        let source = match resources.load_string(SERVER_FILE) {
+            Ok(source) => source,
+            Err(error) => {
+                log::warn!("Failed to load {}: {:?}", SERVER_FILE, error);
+                return Self::default();
+            }
+        };
+
+        Self::from_toml(&source).unwrap_or_else(|error| {
+            log::warn!("Failed to parse {}: {}", SERVER_FILE, error);
+            Self::default()
+        })
+    }
+
+    /// Parses the settings from the `TOML` declaration of the
+    /// server resource. Values out of range are clamped.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The declaration of the settings
+    pub fn from_toml(source: &str) -> Result<Self, toml::de::Error> {
+        let def: ServerDef = toml::from_str(source)?;
+        let settings = def.server;
+        Ok(Self {
+            view_distance: settings.view_distance.clamp(MIN_VIEW_DISTANCE, MAX_VIEW_DISTANCE),
+            entity_tick_budget: settings.entity_tick_budget.max(1),
+            entity_activation_radius: settings.entity_activation_radius.clamp(1, MAX_VIEW_DISTANCE),
+            autosave_interval: settings.autosave_interval.max(0.0),
+            edit_log: settings.edit_log,
+            backup_interval: settings.backup_interval.max(0.0),
+            backups_kept: settings.backups_kept.max(1),
+            metrics_address: settings.metrics_address,
+            listen_address: settings.listen_address,
+            pregen_tasks: settings.pregen_tasks.max(1),
+            pregen_min_tps: settings.pregen_min_tps.max(0.0),
+            compress_after: settings.compress_after.max(0.0),
+            evict_after: settings.evict_after.max(0.0),
+            day_length: settings.day_length.max(MIN_DAY_LENGTH),
+            movement: def.movement.clamped(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_are_parsed() {
+        let settings = ServerSettings::from_toml("[server]\nview_distance = 10\nentity_activation_radius = 3\nautosave_interval = 60.0\nedit_log = true").unwrap();
+        assert_eq!(settings.view_distance, 10);
+        assert_eq!(settings.entity_activation_radius, 3);
+        assert_eq!(settings.autosave_interval, 60.0);
+        assert!(settings.edit_log);
+        assert_eq!(settings.entity_tick_budget, ServerSettings::default().entity_tick_budget);
+        assert_eq!(settings.metrics_address, None);
+        assert_eq!(settings.listen_address, None);
+
+        assert_eq!(ServerSettings::from_toml("").unwrap(), ServerSettings::default());
+        assert!(ServerSettings::from_toml("[server]\nview_distance = \"far\"").is_err());
+    }
+
+    #[test]
+    fn settings_out_of_range_are_clamped() {
+        let settings = ServerSettings::from_toml("[server]\nview_distance = 100\nentity_tick_budget = 0\nentity_activation_radius = 0\nautosave_interval = -5.0\nbackups_kept = 0\npregen_tasks = 0\nevict_after = -1.0\nday_length = 0.0").unwrap();
+        assert_eq!(settings.view_distance, MAX_VIEW_DISTANCE);
+        assert_eq!(settings.entity_tick_budget, 1);
+        assert_eq!(settings.entity_activation_radius, 1);
+        assert_eq!(settings.autosave_interval, 0.0);
+        assert_eq!(settings.backups_kept, 1);
+        assert_eq!(settings.pregen_tasks, 1);
+        assert_eq!(settings.evict_after, 0.0);
+        assert_eq!(settings.day_length, MIN_DAY_LENGTH);
+    }
+
+    #[test]
+    fn movement_is_parsed_from_its_own_table() {
+        let settings = ServerSettings::from_toml("[server]\nview_distance = 10\n[movement]\nsprint_speed = 7.5\nair_control = 2.0\nfriction = -1.0").unwrap();
+        assert_eq!(settings.view_distance, 10);
+        assert_eq!(settings.movement.sprint_speed, 7.5);
+        assert_eq!(settings.movement.walk_speed, MovementProfile::default().walk_speed);
+        assert_eq!(settings.movement.air_control, 1.0);
+        assert!(settings.movement.friction > 0.0);
+    }
+}
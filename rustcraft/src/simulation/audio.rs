@@ -0,0 +1,114 @@
+//! The subsystem of the simulation picking the sounds the
+//! render thread plays, e.g. the footsteps of the player
+
+use crate::scripting::ScriptEngine;
+use crate::sound::{Footsteps, SoundCue, SoundGroups, SoundKind};
+use crate::world::block::Material;
+
+use cgmath::Vector3;
+use std::sync::mpsc::Sender;
+
+/// Audio
+///
+/// Picks the sounds of the blocks by the sound groups scripts
+/// assigned them and sends them to the render thread, which
+/// plays them
+pub struct Audio {
+    /// The sound groups of the blocks
+    sound_groups: SoundGroups,
+    /// Counts the player's movement towards the next footstep
+    footsteps: Footsteps,
+    /// A sender for the sounds the render thread should play
+    sounds: Sender<SoundCue>,
+}
+
+impl Audio {
+    /// Creates a new audio subsystem and registers the sound
+    /// group API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `sounds` - A sender for the sounds the render thread should play
+    pub fn new(engine: &ScriptEngine, sounds: Sender<SoundCue>) -> mlua::Result<Self> {
+        Ok(Self {
+            sound_groups: SoundGroups::new(engine)?,
+            footsteps: Footsteps::default(),
+            sounds,
+        })
+    }
+
+    /// Plays a footstep or a stroke once the player walked or
+    /// swam far enough since the last one
+    ///
+    /// # Arguments
+    ///
+    /// * `moved` - The distance the player moved with the update
+    /// * `feet` - The position of the player's feet
+    /// * `in_feet` - The block the player's feet are in
+    /// * `ground` - The block the player stands on, if they
+    ///   are on the ground
+    pub fn update(&mut self, moved: f32, feet: Vector3<f32>, in_feet: Option<Material>, ground: Option<Material>) {
+        if let Some((kind, material)) = self.footsteps.advance(moved, in_feet, ground) {
+            let cue = match kind {
+                SoundKind::Swim => SoundCue { kind, group: None, pos: feet },
+                _ => self.sound_groups.cue(kind, material, feet),
+            };
+            self.play(cue);
+        }
+    }
+
+    /// Asks the render thread to play a sound
+    ///
+    /// # Arguments
+    ///
+    /// * `cue` - The sound
+    pub fn play(&self, cue: SoundCue) {
+        let _ = self.sounds.send(cue);
+    }
+
+    /// Plays a sound of a block at a position
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - What the sound is played for
+    /// * `material` - The material of the block
+    /// * `pos` - The position the sound comes from
+    pub fn play_at(&self, kind: SoundKind, material: Material, pos: Vector3<f32>) {
+        self.play(self.sound_groups.cue(kind, material, pos));
+    }
+
+    /// Plays a sound of a block from its center
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - What the sound is played for
+    /// * `pos` - The position of the block in world coordinates
+    /// * `material` - The material of the block
+    pub fn play_block(&self, kind: SoundKind, pos: Vector3<i32>, material: Material) {
+        self.play_at(kind, material, pos.map(|value| value as f32 + 0.5));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn footsteps_are_sent_to_the_render_thread() {
+        let (sender, receiver) = channel();
+        let mut audio = Audio::new(&ScriptEngine::new(), sender).unwrap();
+        let feet = Vector3::new(1.0, 5.0, 2.0);
+
+        audio.update(1.0, feet, Some(Material::Air), Some(Material::Sand));
+        assert!(receiver.try_recv().is_err());
+        audio.update(1.0, feet, Some(Material::Air), Some(Material::Sand));
+        let cue = receiver.try_recv().unwrap();
+        assert_eq!(cue.kind, SoundKind::Footstep);
+        assert_eq!(cue.pos, feet);
+
+        audio.play_block(SoundKind::Break, Vector3::new(1, 2, 3), Material::Stone);
+        assert_eq!(receiver.try_recv().unwrap().pos, Vector3::new(1.5, 2.5, 3.5));
+    }
+}
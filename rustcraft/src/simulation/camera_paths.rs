@@ -0,0 +1,219 @@
+//! The subsystem of the simulation flying the camera
+//! along the camera path
+
+use crate::camera::Camera;
+use crate::camera_path::{CameraPath, CameraPaths, Flythrough, Keyframe, PathCommand, SEGMENT_SECONDS};
+use crate::input::InputState;
+use crate::notification::{Notification, NotificationKind, NotificationManager};
+use crate::scripting::ScriptEngine;
+use crate::timestep::TimeStep;
+
+/// Flythroughs
+///
+/// Keeps the keyframes the player and the scripts added
+/// to the camera path and flies the camera along it. The
+/// player's input is ignored during a flythrough.
+pub struct Flythroughs {
+    /// The commands of the camera path API
+    api: CameraPaths,
+    /// The keyframes the camera flies through
+    path: CameraPath,
+    /// The playback of the camera path which is currently running
+    flythrough: Option<Flythrough>,
+    /// The camera of the player before the flythrough started
+    player_camera: Option<Camera>,
+}
+
+impl Flythroughs {
+    /// Creates a new camera path subsystem and registers
+    /// the camera path API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        Ok(Self {
+            api: CameraPaths::new(engine)?,
+            path: CameraPath::default(),
+            flythrough: None,
+            player_camera: None,
+        })
+    }
+
+    /// Returns whether a flythrough is running
+    pub fn is_running(&self) -> bool {
+        self.flythrough.is_some()
+    }
+
+    /// Applies the commands scripts issued through the
+    /// camera path API
+    ///
+    /// # Arguments
+    ///
+    /// * `replaying` - Whether a replay is running
+    /// * `camera` - The camera of the player
+    /// * `notifications` - The notifications shown to the player
+    pub fn apply_commands(&mut self, replaying: bool, camera: &mut Camera, notifications: &mut NotificationManager) {
+        for command in self.api.take_commands() {
+            match command {
+                PathCommand::Add(keyframe) => self.add_keyframe(keyframe, camera, notifications),
+                PathCommand::Clear => self.clear(notifications),
+                PathCommand::Play(seconds) => self.play(seconds, replaying, camera, notifications),
+                PathCommand::Stop => self.stop(camera),
+            }
+        }
+    }
+
+    /// Advances the running flythrough, which moves the camera
+    /// while the player's input is ignored
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    /// * `camera` - The camera of the player
+    /// * `input` - The accumulated input of the player
+    pub fn update(&mut self, time_step: TimeStep, camera: &mut Camera, input: &mut InputState) {
+        let flythrough = match self.flythrough.as_mut() {
+            Some(flythrough) => flythrough,
+            None => return,
+        };
+
+        if let Some(keyframe) = flythrough.advance(time_step.0) {
+            keyframe.apply(camera);
+        }
+        input.reset_mouse_delta();
+
+        if flythrough.is_finished() {
+            self.stop(camera);
+        }
+    }
+
+    /// Appends a keyframe to the camera path
+    ///
+    /// # Arguments
+    ///
+    /// * `keyframe` - The keyframe, or `None` for the current camera
+    /// * `camera` - The camera of the player
+    /// * `notifications` - The notifications shown to the player
+    pub fn add_keyframe(&mut self, keyframe: Option<Keyframe>, camera: &Camera, notifications: &mut NotificationManager) {
+        self.path.add(keyframe.unwrap_or_else(|| Keyframe::of_camera(camera)));
+        notifications.push(Notification::new(
+            NotificationKind::Info,
+            format!("Keyframe {} added", self.path.len()),
+            "Press L to play the camera path",
+        ));
+    }
+
+    /// Removes all keyframes of the camera path
+    ///
+    /// # Arguments
+    ///
+    /// * `notifications` - The notifications shown to the player
+    pub fn clear(&mut self, notifications: &mut NotificationManager) {
+        self.path.clear();
+        notifications.push(Notification::new(NotificationKind::Info, "Camera path cleared", ""));
+    }
+
+    /// Flies the camera along the camera path. A running
+    /// flythrough is restarted.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The seconds the whole path takes, or `None`
+    ///   for `SEGMENT_SECONDS` per segment
+    /// * `replaying` - Whether a replay is running, which keeps
+    ///   the camera until it's stopped
+    /// * `camera` - The camera of the player
+    /// * `notifications` - The notifications shown to the player
+    pub fn play(&mut self, seconds: Option<f32>, replaying: bool, camera: &Camera, notifications: &mut NotificationManager) {
+        if replaying {
+            notifications.push(Notification::new(NotificationKind::Warning, "Can't play the camera path", "Stop the replay first"));
+            return;
+        }
+        let segments = self.path.segments();
+        if segments == 0 {
+            notifications.push(Notification::new(NotificationKind::Warning, "Nothing to play", "Press K to add keyframes"));
+            return;
+        }
+
+        let duration = seconds.unwrap_or(SEGMENT_SECONDS * segments as f32);
+        log::info!("Playing the camera path of {} keyframes in {} seconds", self.path.len(), duration);
+        if self.flythrough.is_none() {
+            self.player_camera = Some(camera.clone());
+        }
+        self.flythrough = Some(Flythrough::new(self.path.clone(), duration));
+    }
+
+    /// Plays the camera path, or stops the running flythrough
+    ///
+    /// # Arguments
+    ///
+    /// * `replaying` - Whether a replay is running
+    /// * `camera` - The camera of the player
+    /// * `notifications` - The notifications shown to the player
+    pub fn toggle(&mut self, replaying: bool, camera: &mut Camera, notifications: &mut NotificationManager) {
+        if self.flythrough.is_some() {
+            self.stop(camera);
+        } else {
+            self.play(None, replaying, camera, notifications);
+        }
+    }
+
+    /// Stops the running flythrough and gives the camera
+    /// back to the player
+    ///
+    /// # Arguments
+    ///
+    /// * `camera` - The camera of the player
+    pub fn stop(&mut self, camera: &mut Camera) {
+        if self.flythrough.take().is_none() {
+            return;
+        }
+        if let Some(player_camera) = self.player_camera.take() {
+            *camera = player_camera;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector3;
+
+    fn keyframe(x: f32) -> Keyframe {
+        Keyframe {
+            pos: Vector3::new(x, 64.0, 0.0),
+            yaw: 0.0,
+            pitch: 0.0,
+        }
+    }
+
+    #[test]
+    fn a_flythrough_gives_the_camera_back() {
+        let engine = ScriptEngine::new();
+        let mut notifications = NotificationManager::new(&engine).unwrap();
+        let mut flythroughs = Flythroughs::new(&engine).unwrap();
+        let mut camera = Camera::default();
+        camera.set_pos(Vector3::new(1.0, 2.0, 3.0));
+
+        // There is nothing to play without keyframes
+        flythroughs.play(None, false, &camera, &mut notifications);
+        assert!(!flythroughs.is_running());
+
+        flythroughs.add_keyframe(Some(keyframe(0.0)), &camera, &mut notifications);
+        flythroughs.add_keyframe(Some(keyframe(10.0)), &camera, &mut notifications);
+        flythroughs.play(None, true, &camera, &mut notifications);
+        assert!(!flythroughs.is_running());
+
+        flythroughs.play(Some(1.0), false, &camera, &mut notifications);
+        assert!(flythroughs.is_running());
+        let mut input = InputState::default();
+        flythroughs.update(TimeStep(0.5), &mut camera, &mut input);
+        assert!(flythroughs.is_running());
+        assert_ne!(*camera.pos(), Vector3::new(1.0, 2.0, 3.0));
+
+        flythroughs.update(TimeStep(0.5), &mut camera, &mut input);
+        assert!(!flythroughs.is_running());
+        assert_eq!(*camera.pos(), Vector3::new(1.0, 2.0, 3.0));
+    }
+}
@@ -0,0 +1,98 @@
+//! The commands of the console, which the simulation
+//! runs against the world and the player
+
+use crate::console::{self, Command};
+use crate::notification::{Notification, NotificationKind};
+use crate::simulation::Simulation;
+use crate::world::chunk_entities::{EntityUuid, SavedEntity};
+use crate::world::entity_types;
+use crate::world::mining::HeldTool;
+use crate::world::portal::{Destination, Teleport, PRELOAD_RADIUS};
+
+use cgmath::Vector3;
+use toml::value::Table as Components;
+
+impl Simulation {
+    /// Runs the command typed into the console. The player
+    /// is notified of commands which can't be run.
+    pub(crate) fn run_command(&mut self) {
+        let line = match self.console.submit() {
+            Some(line) => line,
+            None => return,
+        };
+        let result = Command::parse(&line).and_then(|command| match command {
+            Command::Summon { type_id, pos, components } => {
+                let player = *self.camera.pos();
+                let feet = Vector3::new(player.x, self.body().min.y, player.z);
+                self.summon(&type_id, console::resolve_pos(pos, feet), components)
+            }
+            Command::SpawnEgg(type_id) => {
+                self.entity_types.registry().spawner(&type_id)
+                    .ok_or_else(|| format!("Unknown entity type {}", type_id))?;
+                let egg = entity_types::spawn_egg(&type_id);
+                self.hud.notifications().push(Notification::new(NotificationKind::Info, format!("Holding the {}", egg), ""));
+                self.player_data.tool = Some(HeldTool::new(&egg));
+                Ok(())
+            }
+            Command::Back => self.back(),
+            Command::Waypoint(pos) => {
+                let waypoint = pos.map(|pos| console::resolve_pos(Some(pos), *self.camera.pos()));
+                self.player_data.waypoint = waypoint.map(Vector3::into);
+                let title = if waypoint.is_some() { "Waypoint set" } else { "Waypoint cleared" };
+                self.hud.notifications().push(Notification::new(NotificationKind::Info, title, ""));
+                Ok(())
+            }
+            Command::Weather(raining) => {
+                self.universe.set_raining(raining);
+                let title = if raining { "It started to rain" } else { "The rain stopped" };
+                self.hud.notifications().push(Notification::new(NotificationKind::Info, title, ""));
+                Ok(())
+            }
+            Command::Worldgen(layer) => {
+                let title = if self.hud.toggle_worldgen_layer(layer) { "Outlining" } else { "Stopped outlining" };
+                self.hud.notifications().push(Notification::new(NotificationKind::Info, format!("{} the {}", title, layer.name()), ""));
+                Ok(())
+            }
+        });
+        if let Err(message) = result {
+            self.hud.notifications().push(Notification::new(NotificationKind::Warning, "Failed to run the command", message));
+        }
+    }
+
+    /// Spawns an entity of a registered type into the
+    /// dimension the player is in
+    ///
+    /// # Arguments
+    ///
+    /// * `type_id` - The type of the entity
+    /// * `pos` - The position of the bottom center of the entity
+    /// * `components` - The initial components of the entity
+    pub(crate) fn summon(&mut self, type_id: &str, pos: Vector3<f32>, components: Components) -> Result<(), String> {
+        if self.entity_types.registry().spawner(type_id).is_none() {
+            return Err(format!("Unknown entity type {}", type_id));
+        }
+        let entity = SavedEntity {
+            components,
+            ..SavedEntity::new(type_id, EntityUuid::generate(), pos)
+        };
+        self.universe.spawn_entity(&entity)?;
+        if let Err(error) = self.entity_types.spawned(&self.scripts, &mut self.universe, entity.uuid) {
+            log::warn!("Failed to run {}", error);
+        }
+        self.hud.notifications().push(Notification::new(NotificationKind::Info, format!("Summoned a {}", type_id), ""));
+        Ok(())
+    }
+
+    /// Teleports the player to where they died the last time
+    fn back(&mut self) -> Result<(), String> {
+        let location = self.player_data.last_death.clone().ok_or_else(|| "You haven't died yet".to_string())?;
+        if !self.universe.contains(&location.dimension) {
+            return Err(format!("The dimension {} doesn't exist anymore", location.dimension));
+        }
+        if let Some(world) = self.universe.dimension_mut(&location.dimension) {
+            world.load_area(&location.pos(), PRELOAD_RADIUS);
+        }
+        self.teleport = Some(Teleport::new(Destination::in_dimension(&location.dimension, location.pos())));
+        Ok(())
+    }
+}
@@ -0,0 +1,316 @@
+//! The state of a frame the simulation publishes for
+//! the render thread
+
+use crate::breath::BreathFrame;
+use crate::camera::{OrthographicCamera, PerspectiveCamera, Projection};
+use crate::console::ConsoleFrame;
+use crate::death::{DeathFrame, DeathScreen};
+use crate::dialogue::{Dialogue, DialogueFrame};
+use crate::graphics::capabilities::CapabilitiesFrame;
+use crate::graphics::cinematic::CinematicFrame;
+use crate::graphics::pass::Frame;
+use crate::graphics::scene::{SceneFrame, SceneInstance};
+use crate::graphics::sky::SkyFrame;
+use crate::graphics::submerged::SubmergedFrame;
+use crate::graphics::transition::TransitionFrame;
+use crate::held_item::{HeldItem, HeldItemFrame};
+use crate::keybindings::{KeyBindingsFrame, KeyBindingsView};
+use crate::memory::{MemoryFrame, MemoryReport};
+use crate::notification::{Toast, ToastFrame};
+use crate::player::{NameTag, PlayerFrame, RemotePlayer};
+use crate::player_list::{PlayerList, PlayerListFrame};
+use crate::recipe_book::{RecipeBookFrame, RecipeBookView};
+use crate::scoreboard::{ScoreboardFrame, Sidebar};
+use crate::world::block::Material;
+use crate::world::border::{BorderFrame, WorldBorder};
+use crate::world::chunk::{Chunk, ChunkFrame};
+use crate::world::furnace::{Furnace, FurnaceFrame};
+use crate::world::light_overlay::{LightOverlayFrame, LitSurface};
+use crate::world::overlay::BlockOverlays;
+use crate::world::particles::{Particle, ParticleFrame};
+use crate::world::placement::{PlacementFrame, PlacementPreview};
+use crate::world::selection::SelectionFrame;
+use crate::world::time::{SkySettings, WorldTime};
+use crate::world::worldgen_overlay::{WorldgenLine, WorldgenOverlayFrame};
+
+use cgmath::Vector3;
+use std::sync::Arc;
+
+/// FrameState
+///
+/// The `FrameState` contains everything the render
+/// thread needs to know to draw a single frame. It is
+/// published by the simulation thread after each update.
+#[derive(Clone)]
+pub struct FrameState {
+    /// The camera the frame should be rendered with
+    pub camera: PerspectiveCamera,
+    /// The chunks which should be rendered, ordered from
+    /// the inside to the outside
+    pub chunks: Vec<Chunk>,
+    /// The visible props of the world's scene
+    pub props: Vec<SceneInstance>,
+    /// The time of the world
+    pub time: WorldTime,
+    /// The look of the sky of the dimension the player is in
+    pub sky: SkySettings,
+    /// The block which would be placed at the targeted face
+    pub placement: Option<PlacementPreview>,
+    /// The lowest and the highest block of the outlined
+    /// selection, if any
+    pub selection: Option<(Vector3<i32>, Vector3<i32>)>,
+    /// The toasts which are currently shown
+    pub toasts: Vec<Toast>,
+    /// How far the screen is faded out by a transition
+    pub fade: f32,
+    /// The fluid the camera is in, if any
+    pub submerged: Option<Material>,
+    /// The share of the air the player has left while
+    /// they hold their breath, if they do
+    pub air: Option<f32>,
+    /// The overlays of the block faces
+    pub overlays: Arc<BlockOverlays>,
+    /// The memory of the subsystems, if the memory
+    /// panel is shown
+    pub memory: Option<MemoryReport>,
+    /// Whether the capabilities panel is shown
+    pub capabilities: bool,
+    /// The surfaces tinted by their light level,
+    /// if the light overlay is shown
+    pub lit_surfaces: Option<Arc<Vec<LitSurface>>>,
+    /// The lines outlining the generated features,
+    /// if a layer of the worldgen overlay is shown
+    pub worldgen_lines: Option<Arc<Vec<WorldgenLine>>>,
+    /// The world border of the dimension the player is in
+    pub border: Option<WorldBorder>,
+    /// The other players which are shown in the world
+    pub players: Vec<RemotePlayer>,
+    /// The name tags of other entities
+    pub name_tags: Vec<NameTag>,
+    /// The particles in the dimension the player is in
+    pub particles: Vec<Particle>,
+    /// The open dialogue, if any
+    pub dialogue: Option<Dialogue>,
+    /// The action of the entity the player could interact
+    /// with, if they are looking at one
+    pub prompt: Option<String>,
+    /// The player list, while the player holds Tab
+    pub player_list: Option<Arc<PlayerList>>,
+    /// The recipe book, if it's open
+    pub recipe_book: Option<RecipeBookView>,
+    /// The furnace the player opened, if any
+    pub furnace: Option<Furnace>,
+    /// The command typed into the console, if it's open
+    pub console: Option<String>,
+    /// The death screen, while the player is dead
+    pub death: Option<DeathScreen>,
+    /// The key bindings menu, if it's open
+    pub key_bindings: Option<KeyBindingsView>,
+    /// The item the player holds, if any
+    pub held_item: Option<HeldItem>,
+    /// The scores shown on the sidebar, if any
+    pub sidebar: Option<Sidebar>,
+    /// The camera of an isometric capture, which replaces
+    /// the camera of the player while the capture is rendered
+    pub capture: Option<OrthographicCamera>,
+    /// Whether the user interface is drawn, which is
+    /// hidden while the camera path is played
+    pub hud: bool,
+}
+
+impl Frame for FrameState {
+    fn camera(&self) -> &dyn Projection {
+        match self.capture.as_ref() {
+            Some(capture) => capture,
+            None => &self.camera,
+        }
+    }
+}
+
+impl CinematicFrame for FrameState {
+    fn cinematic(&self) -> bool {
+        !self.hud
+    }
+}
+
+impl SkyFrame for FrameState {
+    fn sun_angle(&self) -> f32 {
+        self.time.sun_angle()
+    }
+
+    fn darkness(&self) -> f32 {
+        self.sky.darkness(&self.time)
+    }
+
+    fn celestial_bodies(&self) -> bool {
+        self.sky.celestial_bodies
+    }
+}
+
+impl ChunkFrame for FrameState {
+    fn chunks(&self) -> &[Chunk] {
+        &self.chunks
+    }
+
+    fn light(&self) -> f32 {
+        self.sky.light(&self.time)
+    }
+
+    fn overlays(&self) -> &Arc<BlockOverlays> {
+        &self.overlays
+    }
+
+    fn sky_color(&self) -> Vector3<f32> {
+        self.sky.color(&self.time)
+    }
+}
+
+impl SceneFrame for FrameState {
+    fn props(&self) -> &[SceneInstance] {
+        &self.props
+    }
+}
+
+impl PlacementFrame for FrameState {
+    fn placement(&self) -> Option<&PlacementPreview> {
+        self.placement.as_ref()
+    }
+}
+
+impl LightOverlayFrame for FrameState {
+    fn lit_surfaces(&self) -> Option<&Arc<Vec<LitSurface>>> {
+        self.lit_surfaces.as_ref()
+    }
+}
+
+impl WorldgenOverlayFrame for FrameState {
+    fn worldgen_lines(&self) -> Option<&Arc<Vec<WorldgenLine>>> {
+        self.worldgen_lines.as_ref()
+    }
+}
+
+impl SelectionFrame for FrameState {
+    fn selection(&self) -> Option<(Vector3<i32>, Vector3<i32>)> {
+        self.selection
+    }
+}
+
+impl ToastFrame for FrameState {
+    fn toasts(&self) -> &[Toast] {
+        &self.toasts
+    }
+}
+
+impl MemoryFrame for FrameState {
+    fn memory(&self) -> Option<&MemoryReport> {
+        self.memory.as_ref()
+    }
+}
+
+impl CapabilitiesFrame for FrameState {
+    fn show_capabilities(&self) -> bool {
+        self.capabilities
+    }
+}
+
+impl BorderFrame for FrameState {
+    fn border(&self) -> Option<&WorldBorder> {
+        self.border.as_ref()
+    }
+}
+
+impl PlayerFrame for FrameState {
+    fn players(&self) -> &[RemotePlayer] {
+        &self.players
+    }
+
+    fn name_tags(&self) -> &[NameTag] {
+        &self.name_tags
+    }
+
+    fn light(&self) -> f32 {
+        self.sky.light(&self.time)
+    }
+}
+
+impl ScoreboardFrame for FrameState {
+    fn sidebar(&self) -> Option<&Sidebar> {
+        self.sidebar.as_ref()
+    }
+}
+
+impl DialogueFrame for FrameState {
+    fn dialogue(&self) -> Option<&Dialogue> {
+        self.dialogue.as_ref()
+    }
+
+    fn prompt(&self) -> Option<&str> {
+        self.prompt.as_deref()
+    }
+}
+
+impl PlayerListFrame for FrameState {
+    fn player_list(&self) -> Option<&PlayerList> {
+        self.player_list.as_deref()
+    }
+}
+
+impl ParticleFrame for FrameState {
+    fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+}
+
+impl RecipeBookFrame for FrameState {
+    fn recipe_book(&self) -> Option<&RecipeBookView> {
+        self.recipe_book.as_ref()
+    }
+}
+
+impl FurnaceFrame for FrameState {
+    fn furnace(&self) -> Option<&Furnace> {
+        self.furnace.as_ref()
+    }
+}
+
+impl ConsoleFrame for FrameState {
+    fn console(&self) -> Option<&str> {
+        self.console.as_deref()
+    }
+}
+
+impl BreathFrame for FrameState {
+    fn air(&self) -> Option<f32> {
+        self.air
+    }
+}
+
+impl HeldItemFrame for FrameState {
+    fn held_item(&self) -> Option<&HeldItem> {
+        self.held_item.as_ref()
+    }
+}
+
+impl KeyBindingsFrame for FrameState {
+    fn key_bindings(&self) -> Option<&KeyBindingsView> {
+        self.key_bindings.as_ref()
+    }
+}
+
+impl DeathFrame for FrameState {
+    fn death(&self) -> Option<&DeathScreen> {
+        self.death.as_ref()
+    }
+}
+
+impl TransitionFrame for FrameState {
+    fn fade(&self) -> f32 {
+        self.fade
+    }
+}
+
+impl SubmergedFrame for FrameState {
+    fn submerged(&self) -> Option<Material> {
+        self.submerged
+    }
+}
@@ -0,0 +1,162 @@
+//! The subsystem of the simulation keeping the history
+//! of the blocks the player changed
+
+use crate::camera::Camera;
+use crate::notification::{Notification, NotificationKind, NotificationManager};
+use crate::scripting::ScriptEngine;
+use crate::world::edit_log::{self, EditLog};
+use crate::world::placement::REACH_DISTANCE;
+use crate::world::raycast;
+use crate::world::save::{SaveDir, SaveError};
+use crate::world::universe::Universe;
+
+/// The amount of logged edits shown when a block is inspected
+const INSPECTED_EDITS: usize = 3;
+
+/// History
+///
+/// Undoes and redoes the block edits of the player and logs
+/// them per region, if the edit log is enabled, so that the
+/// scripts could look up who changed a block and roll back
+/// the edits of a player
+pub struct History {
+    /// The log of the blocks the player changed
+    edit_log: EditLog,
+    /// Whether the edits are logged
+    logging: bool,
+}
+
+impl History {
+    /// Creates a new history and registers the edit log
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `save_dir` - The directory the world is saved in
+    /// * `logging` - Whether the edits are logged
+    pub fn new(engine: &ScriptEngine, save_dir: SaveDir, logging: bool) -> mlua::Result<Self> {
+        Ok(Self {
+            edit_log: EditLog::new(engine, save_dir)?,
+            logging,
+        })
+    }
+
+    /// Logs the blocks the player changed, if the edit log
+    /// is enabled
+    ///
+    /// # Arguments
+    ///
+    /// * `universe` - The universe the blocks changed in
+    /// * `player` - The name of the player
+    /// * `since` - The amount of block changes of the dimension
+    ///   before the edit
+    pub fn log(&self, universe: &Universe, player: &str, since: usize) {
+        if !self.logging {
+            return;
+        }
+        let edits = &universe.changes()[since..];
+        self.edit_log.record(universe.active_name(), player, edits, edit_log::unix_time());
+    }
+
+    /// Reverts the most recent block edit of the dimension
+    /// the player is in, e.g. of a world edit command
+    ///
+    /// # Arguments
+    ///
+    /// * `universe` - The universe
+    /// * `player` - The name of the player
+    pub fn undo(&self, universe: &mut Universe, player: &str) {
+        let since = universe.changes().len();
+        if let Err(error) = universe.undo() {
+            log::warn!("Failed to undo: {}", error);
+        }
+        self.log(universe, player, since);
+    }
+
+    /// Reapplies the most recently reverted block edit of
+    /// the dimension the player is in
+    ///
+    /// # Arguments
+    ///
+    /// * `universe` - The universe
+    /// * `player` - The name of the player
+    pub fn redo(&self, universe: &mut Universe, player: &str) {
+        let since = universe.changes().len();
+        if let Err(error) = universe.redo() {
+            log::warn!("Failed to redo: {}", error);
+        }
+        self.log(universe, player, since);
+    }
+
+    /// Rolls back the edits of players the scripts requested
+    ///
+    /// # Arguments
+    ///
+    /// * `universe` - The universe
+    /// * `notifications` - The notifications shown to the player
+    pub fn update(&self, universe: &mut Universe, notifications: &mut NotificationManager) {
+        for rollback in self.edit_log.take_rollbacks() {
+            let world = match universe.dimension_mut(&rollback.dimension) {
+                Some(world) => world,
+                None => {
+                    log::warn!("Skipping rollback in unknown dimension {}", rollback.dimension);
+                    continue;
+                }
+            };
+            match self.edit_log.roll_back(world, &rollback, edit_log::unix_time()) {
+                Ok((reverted, skipped)) => {
+                    let title = format!("Rolled back {} blocks of {}", reverted, rollback.player);
+                    let message = format!("{} blocks have been changed since or aren't loaded", skipped);
+                    log::info!("{}, {}", title, message);
+                    notifications.push(Notification::new(NotificationKind::Info, title, message));
+                }
+                Err(error) => log::error!("Failed to roll back the edits of {}: {:?}", rollback.player, error),
+            }
+        }
+    }
+
+    /// Shows the most recent logged edits of the block the
+    /// player is looking at, or of the air in front of it,
+    /// where broken blocks have been
+    ///
+    /// # Arguments
+    ///
+    /// * `universe` - The universe
+    /// * `camera` - The camera of the player
+    /// * `notifications` - The notifications shown to the player
+    pub fn inspect(&self, universe: &Universe, camera: &Camera, notifications: &mut NotificationManager) {
+        let hit = match raycast::raycast(universe, *camera.pos(), camera.look(), REACH_DISTANCE) {
+            Some(hit) => hit,
+            None => return,
+        };
+        let dimension = universe.active_name();
+        let edits = self.edit_log.inspect(dimension, hit.block).and_then(|mut edits| {
+            if hit.adjacent() != hit.block {
+                edits.extend(self.edit_log.inspect(dimension, hit.adjacent())?);
+            }
+            Ok(edits)
+        });
+        let mut edits = match edits {
+            Ok(edits) => edits,
+            Err(error) => {
+                log::error!("Failed to read edit log: {:?}", error);
+                return;
+            }
+        };
+        edits.sort_by_key(|edit| edit.time);
+
+        let now = edit_log::unix_time();
+        let title = format!("{} edits at {} {} {}", edits.len(), hit.block.x, hit.block.y, hit.block.z);
+        let message = edits.iter().rev().take(INSPECTED_EDITS)
+            .map(|edit| format!("{}s ago {}: {} -> {}", now.saturating_sub(edit.time), edit.player, edit.old, edit.new))
+            .collect::<Vec<_>>()
+            .join(", ");
+        notifications.push(Notification::new(NotificationKind::Info, title, message));
+    }
+
+    /// Writes the logged edits to disk
+    pub fn flush(&self) -> Result<(), SaveError> {
+        self.edit_log.flush()
+    }
+}
@@ -0,0 +1,169 @@
+//! The subsystem of the simulation keeping what the HUD shows
+//! besides the world, e.g. the notifications and the overlays
+
+use crate::notification::{NotificationManager, Toast};
+use crate::scripting::ScriptEngine;
+use crate::timestep::TimeStep;
+use crate::world::chunk::CHUNK_SIZE;
+use crate::world::history::BlockEdit;
+use crate::world::light_overlay::{LightOverlay, LitSurface};
+use crate::world::worldgen_overlay::{WorldgenLayer, WorldgenLine, WorldgenOverlay};
+use crate::world::World;
+
+use cgmath::{Vector2, Vector3};
+use std::sync::Arc;
+
+/// Hud
+///
+/// Keeps the notifications shown to the player, which panels
+/// they opened and the overlays outlining the world around them
+pub struct Hud {
+    /// The notifications shown to the player
+    notifications: NotificationManager,
+    /// Whether the memory panel is shown
+    memory_panel: bool,
+    /// Whether the capabilities panel is shown
+    capabilities_panel: bool,
+    /// The light levels of the surfaces around the
+    /// player, if the light overlay is shown
+    light_overlay: Option<LightOverlay>,
+    /// The outlines of the generated features around
+    /// the player, shown layer by layer
+    worldgen_overlay: WorldgenOverlay,
+}
+
+impl Hud {
+    /// Creates a new HUD and registers the notification API
+    /// in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        Ok(Self {
+            notifications: NotificationManager::new(engine)?,
+            memory_panel: false,
+            capabilities_panel: false,
+            light_overlay: None,
+            worldgen_overlay: WorldgenOverlay::default(),
+        })
+    }
+
+    /// Returns the notifications shown to the player
+    pub fn notifications(&mut self) -> &mut NotificationManager {
+        &mut self.notifications
+    }
+
+    /// Advances the notifications and updates the overlays
+    /// around the player
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    /// * `world` - The dimension the player is in
+    /// * `changes` - The blocks changed during the update
+    /// * `pos` - The position of the player's eyes
+    pub fn update(&mut self, time_step: TimeStep, world: &World, changes: &[BlockEdit], pos: Vector3<f32>) {
+        self.notifications.update(time_step);
+        if let Some(light_overlay) = self.light_overlay.as_mut() {
+            let center = Vector3::new(pos.x.floor() as i32, pos.y.floor() as i32, pos.z.floor() as i32);
+            light_overlay.invalidate(changes);
+            light_overlay.update(world, center, time_step.0);
+        }
+        let size = CHUNK_SIZE as f32;
+        let chunk = Vector2::new((pos.x / size).floor() as i32, (pos.z / size).floor() as i32);
+        self.worldgen_overlay.update(world, chunk, time_step.0);
+    }
+
+    /// Shows or hides the memory panel. Returns whether
+    /// it's shown now.
+    pub fn toggle_memory_panel(&mut self) -> bool {
+        self.memory_panel = !self.memory_panel;
+        self.memory_panel
+    }
+
+    /// Shows or hides the capabilities panel
+    pub fn toggle_capabilities_panel(&mut self) {
+        self.capabilities_panel = !self.capabilities_panel;
+    }
+
+    /// Shows or hides the light levels of the surfaces
+    /// around the player
+    pub fn toggle_light_overlay(&mut self) {
+        self.light_overlay = match self.light_overlay {
+            Some(_) => None,
+            None => Some(LightOverlay::default()),
+        };
+    }
+
+    /// Outlines a layer of the generated features or stops
+    /// outlining it. Returns whether it's outlined now.
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - The layer of the features
+    pub fn toggle_worldgen_layer(&mut self, layer: WorldgenLayer) -> bool {
+        self.worldgen_overlay.toggle(layer)
+    }
+
+    /// Forgets the surfaces and outlines of the overlays, e.g.
+    /// as the player entered another dimension. The overlays
+    /// which are shown stay shown.
+    pub fn clear_overlays(&mut self) {
+        if self.light_overlay.is_some() {
+            self.light_overlay = Some(LightOverlay::default());
+        }
+        self.worldgen_overlay.clear();
+    }
+
+    /// Returns whether the memory panel is shown
+    pub fn is_memory_panel_shown(&self) -> bool {
+        self.memory_panel
+    }
+
+    /// Returns whether the capabilities panel is shown
+    pub fn is_capabilities_panel_shown(&self) -> bool {
+        self.capabilities_panel
+    }
+
+    /// Returns the toasts which are currently shown
+    pub fn toasts(&self) -> Vec<Toast> {
+        self.notifications.toasts()
+    }
+
+    /// Returns the lit surfaces, if the light overlay is shown
+    pub fn lit_surfaces(&self) -> Option<Arc<Vec<LitSurface>>> {
+        self.light_overlay.as_ref().map(|light_overlay| light_overlay.surfaces().clone())
+    }
+
+    /// Returns the outlines of the generated features, if
+    /// a layer is outlined
+    pub fn worldgen_lines(&self) -> Option<Arc<Vec<WorldgenLine>>> {
+        self.worldgen_overlay.lines().cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::terrain_generator::FlatTerrainGen;
+    use crate::world::time::SkySettings;
+
+    #[test]
+    fn overlays_stay_shown_in_other_dimensions() {
+        let world = World::with_generator(FlatTerrainGen {}, SkySettings::default());
+        let mut hud = Hud::new(&ScriptEngine::new()).unwrap();
+        assert!(hud.lit_surfaces().is_none());
+
+        hud.toggle_light_overlay();
+        hud.update(TimeStep(1.0), &world, &[], Vector3::new(0.5, 10.0, 0.5));
+        assert!(hud.lit_surfaces().is_some());
+        hud.clear_overlays();
+        assert!(hud.lit_surfaces().is_some());
+
+        hud.toggle_light_overlay();
+        assert!(hud.lit_surfaces().is_none());
+        assert!(hud.toggle_memory_panel());
+        assert!(!hud.toggle_memory_panel());
+    }
+}
@@ -0,0 +1,2303 @@
+//! Types running the game simulation on a dedicated
+//! thread, apart from the render thread
+
+use crate::camera::PerspectiveCamera;
+use crate::console::Console;
+use crate::crash;
+use crate::death::{self, DeathCause, DeathLocation, DeathScreen};
+use crate::dialogue::Dialogues;
+use crate::entity::{self, Aabb, Controls, Player, PLAYER_EYE_HEIGHT};
+use crate::graphics::mesh::{Mesh, MeshBuilder, MeshHandle};
+use crate::graphics::scene::{NodeId, Scene, SceneNode};
+use crate::held_item::{self, HeldItem, ItemIcon};
+use crate::input::{self, InputState, SharedKeyMap};
+use crate::memory::{MemoryReport, MEMORY, Subsystem};
+use crate::metrics::{self, Metric, TickTimer, METRICS};
+use crate::notification::{Notification, NotificationKind};
+use crate::player_list::GameMode;
+use crate::recipe_book::RecipeBook;
+use crate::resources::Resources;
+use crate::scoreboard::Scoreboard;
+use crate::scripting::{self, ScriptEngine};
+use crate::scripting::events::{self, Events};
+use crate::server::ServerSettings;
+use crate::sound::{SoundCue, SoundKind};
+use crate::stats::Achievements;
+use crate::timestep::TimeStep;
+use crate::triple_buffer::TripleBufferWriter;
+use crate::world::bed::{self, NightSkip, Sleepers};
+use crate::world::block::Material;
+use crate::world::block_entity::BlockEntity;
+use crate::world::border::{SpawnProtection, WorldBorder};
+use crate::world::crops::Crops;
+use crate::world::dimension::Dimensions;
+use crate::world::chunk_entities::EntityUuid;
+use crate::world::entity_types::{self, EntityTypes};
+use crate::world::explosion::{self, FUSE_TIME};
+use crate::world::fire::{self, Fire};
+use crate::world::fluids::Fluids;
+use crate::world::furnace::{self, Furnace, FurnaceSlot, Smelting};
+use crate::world::hopper;
+use crate::world::loot::LootTables;
+use crate::world::mining::{BreakProgress, HeldTool, Mining};
+use crate::world::mobs;
+use crate::world::noise_source;
+use crate::world::overlay::Overlays;
+use crate::world::particles::Particles;
+use crate::world::placement::{PlacementPreview, REACH_DISTANCE};
+use crate::world::portal::{Destination, Portals, Teleport, PRELOAD_RADIUS};
+use crate::world::raycast;
+use crate::world::recipes::Recipes;
+use crate::world::rules::Rules;
+use crate::world::save::{PlayerData, SaveDir, WorldMeta};
+use crate::world::brush::BRUSH;
+use crate::world::structure::Facing;
+use crate::world::selection::{EditCommand, WAND};
+use crate::world::tags::Tags;
+use crate::world::ticks::{self, BlockBehaviors, RandomTicks, ScheduledTick, TickAction};
+use crate::world::universe::{Universe, OVERWORLD};
+use crate::world::vehicles::{self, VehicleKind};
+use crate::world::vegetation::Vegetation;
+
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector2, Vector3, Vector4};
+use mlua::ErrorContext;
+use toml::value::Table as Components;
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+pub mod audio;
+pub mod camera_paths;
+pub mod console;
+pub mod frame;
+pub mod history;
+pub mod hud;
+pub mod network;
+pub mod player_input;
+pub mod replay;
+pub mod storage;
+pub mod world_edit;
+
+pub use self::frame::FrameState;
+
+use self::audio::Audio;
+use self::camera_paths::Flythroughs;
+use self::history::History;
+use self::hud::Hud;
+use self::network::{LocalPlayer, Network};
+use self::player_input::{Movement, PlayerInput};
+use self::replay::Replays;
+use self::storage::Storage;
+use self::world_edit::WorldEditor;
+
+/// The amount of simulation updates per second
+const UPDATES_PER_SECOND: u32 = 60;
+/// The amount of updates between two checks of
+/// the achievement conditions
+const ACHIEVEMENT_INTERVAL: u64 = UPDATES_PER_SECOND as u64;
+/// The height of the beacons marking the spawn and
+/// where the player died
+const BEACON_HEIGHT: f32 = 24.0;
+/// The height in blocks above the surface the player spawns at
+const SPAWN_HEIGHT: f32 = 2.0;
+/// The height below which the player dies in the void
+const VOID_DEPTH: f32 = -64.0;
+/// The maximal distance of the player's feet above a solid
+/// block, at which their movement counts as walking
+const GROUND_TOLERANCE: f32 = 0.25;
+/// The damage the player deals to scripted entities per hit
+const ATTACK_DAMAGE: f32 = 1.0;
+/// The time in seconds between two hits of the player
+/// while they hold the mouse button breaking blocks
+const ATTACK_COOLDOWN: f32 = 0.5;
+/// The distance in blocks scripted entities are knocked
+/// back by per hit
+const KNOCKBACK_DISTANCE: f32 = 0.4;
+/// The damage lava deals to scripted entities per burn
+const LAVA_DAMAGE: f32 = 2.0;
+/// The time in seconds between two burns of the
+/// entities in lava
+const LAVA_BURN_INTERVAL: f32 = 0.5;
+/// The time in seconds the player survives in lava
+const LAVA_SURVIVAL_TIME: f32 = 2.0;
+/// The damage an explosion deals to scripted entities
+/// at its center
+const EXPLOSION_DAMAGE: f32 = 8.0;
+/// The damage of an explosion the player doesn't survive,
+/// which they take within half of its radius
+const EXPLOSION_LETHAL_DAMAGE: f32 = EXPLOSION_DAMAGE / 2.0;
+
+/// SimEvent
+///
+/// A `SimEvent` is sent from the render thread to
+/// the simulation thread.
+pub enum SimEvent {
+    /// A new snapshot of the player's input
+    Input(InputState),
+    /// The framebuffer has been resized to the given
+    /// width and height
+    Resize(i32, i32),
+    /// The field of view of the camera has been
+    /// configured, in radians
+    SetFov(f32),
+    /// The selected block should be placed at the face
+    /// the player is looking at
+    PlaceBlock,
+    /// The most recent block edit should be reverted
+    Undo,
+    /// The most recently reverted block edit should be
+    /// reapplied
+    Redo,
+    /// The session should be recorded, or the recording
+    /// should be stopped
+    ToggleRecording,
+    /// The most recent recording should be replayed, or
+    /// the replay should be stopped
+    ToggleReplay,
+    /// The camera should be detached from or attached to
+    /// the recorded camera of the replay
+    ToggleSpectating,
+    /// The replay should be paused or resumed
+    PauseReplay,
+    /// The replay should skip the given amount of seconds
+    ScrubReplay(u32),
+    /// The current camera should be appended to the
+    /// camera path
+    AddKeyframe,
+    /// All keyframes of the camera path should be removed
+    ClearCameraPath,
+    /// The camera path should be played, or the playback
+    /// should be stopped
+    ToggleFlythrough,
+    /// The memory panel should be shown or hidden
+    ToggleMemoryPanel,
+    /// The capabilities panel should be shown or hidden
+    ToggleCapabilities,
+    /// The light overlay should be shown or hidden
+    ToggleLightOverlay,
+    /// The ticks per second should be shown
+    ShowTps,
+    /// The logged edits of the block the player is
+    /// looking at should be shown
+    InspectBlock,
+    /// The world should be saved and backed up
+    Backup,
+    /// The block with the given index in `Material::ALL`
+    /// should be selected for placing
+    SelectBlock(usize),
+    /// The player should hold the next tool, or their
+    /// bare hand after the last one
+    CycleTool,
+    /// The player should interact with the entity they
+    /// are looking at, or close the open dialogue
+    Interact,
+    /// The recipe book should be opened or closed
+    ToggleRecipeBook,
+    /// The character should be typed into the search
+    /// box of the recipe book
+    SearchRecipes(char),
+    /// The last character of the search box of the
+    /// recipe book should be erased
+    EraseSearch,
+    /// The selection of the recipe book should move
+    /// by the given amount of recipes
+    SelectRecipe(i32),
+    /// The selected recipe should be crafted
+    CraftRecipe,
+    /// The console should be opened or closed
+    ToggleConsole,
+    /// The character should be typed into the console
+    TypeCommand(char),
+    /// The last character of the console should be erased
+    EraseCommand,
+    /// The command typed into the console should be run
+    RunCommand,
+    /// The dead player should respawn
+    Respawn,
+    /// The key bindings menu should be opened or closed
+    ToggleKeyBindings,
+    /// The selection of the key bindings menu should
+    /// move by the given amount of controls and actions
+    SelectKeyBinding(i32),
+    /// The key bindings menu should wait for the key the selected
+    /// control or action is bound to, or stop waiting for it
+    RebindKey,
+    /// The key with the given name, e.g. `M` or `Ctrl+M`, has
+    /// been pressed without triggering a built-in control, which
+    /// triggers the action of a script bound to it
+    PressKey(String),
+    /// The chunks within the given radius around the
+    /// player should be pre-generated
+    Pregenerate(i32),
+    /// The simulation should stop
+    Shutdown,
+}
+
+impl SimEvent {
+    /// Returns the name of the command the event represents,
+    /// as it's listed in crash reports. Continuous events like
+    /// the player's input aren't commands.
+    pub fn command(&self) -> Option<&'static str> {
+        match self {
+            SimEvent::Input(_) | SimEvent::Resize(_, _) | SimEvent::SetFov(_) => None,
+            SimEvent::PlaceBlock => Some("place block"),
+            SimEvent::Undo => Some("undo"),
+            SimEvent::Redo => Some("redo"),
+            SimEvent::ToggleRecording => Some("toggle recording"),
+            SimEvent::ToggleReplay => Some("toggle replay"),
+            SimEvent::ToggleSpectating => Some("toggle spectating"),
+            SimEvent::PauseReplay => Some("pause replay"),
+            SimEvent::ScrubReplay(_) => Some("scrub replay"),
+            SimEvent::AddKeyframe => Some("add keyframe"),
+            SimEvent::ClearCameraPath => Some("clear camera path"),
+            SimEvent::ToggleFlythrough => Some("toggle flythrough"),
+            SimEvent::ToggleMemoryPanel => Some("toggle memory panel"),
+            SimEvent::ToggleCapabilities => Some("toggle capabilities"),
+            SimEvent::ToggleLightOverlay => Some("toggle light overlay"),
+            SimEvent::ShowTps => Some("show tps"),
+            SimEvent::InspectBlock => Some("inspect block"),
+            SimEvent::Backup => Some("backup"),
+            SimEvent::SelectBlock(_) => Some("select block"),
+            SimEvent::CycleTool => Some("cycle tool"),
+            SimEvent::Interact => Some("interact"),
+            SimEvent::ToggleRecipeBook => Some("toggle recipe book"),
+            SimEvent::SearchRecipes(_) => Some("search recipes"),
+            SimEvent::EraseSearch => Some("erase search"),
+            SimEvent::SelectRecipe(_) => Some("select recipe"),
+            SimEvent::CraftRecipe => Some("craft recipe"),
+            SimEvent::ToggleConsole => Some("toggle console"),
+            SimEvent::TypeCommand(_) => Some("type command"),
+            SimEvent::EraseCommand => Some("erase command"),
+            SimEvent::RunCommand => Some("run command"),
+            SimEvent::Respawn => Some("respawn"),
+            SimEvent::ToggleKeyBindings => Some("toggle key bindings"),
+            SimEvent::SelectKeyBinding(_) => Some("select key binding"),
+            SimEvent::RebindKey => Some("rebind key"),
+            SimEvent::PressKey(_) => Some("press key"),
+            SimEvent::Pregenerate(_) => Some("pregen"),
+            SimEvent::Shutdown => Some("shutdown"),
+        }
+    }
+}
+
+/// Simulation
+///
+/// The `Simulation` owns the universe and the camera. It
+/// applies the player's input, manages the loaded chunks
+/// and publishes a `FrameState` for the render thread. As
+/// it doesn't own an `OpenGL` context, heavy simulation
+/// work can't cause any frame hitches.
+pub struct Simulation {
+    /// The camera of the player
+    camera: PerspectiveCamera,
+    /// The dimensions which are simulated
+    universe: Universe,
+    /// A receiver for events from the render thread
+    events: Receiver<SimEvent>,
+    /// The writer to publish the frame states
+    frame_writer: TripleBufferWriter<FrameState>,
+    /// Applies the input of the player
+    input: PlayerInput,
+    /// The script engine running the mods
+    scripts: ScriptEngine,
+    /// The achievements defined by the scripts
+    achievements: Achievements,
+    /// Shows the notifications, panels and overlays
+    hud: Hud,
+    /// The callbacks scripts registered for events
+    script_events: Events,
+    /// The objectives scripts keep scores in
+    scoreboard: Scoreboard,
+    /// The dialogue scripts opened
+    dialogues: Dialogues,
+    /// Hosts the world for other players and keeps the
+    /// list of the players
+    network: Network,
+    /// The portals defined by the scripts
+    portals: Portals,
+    /// The dimensions defined by the scripts
+    dimensions: Dimensions,
+    /// The block overlays defined by the scripts
+    overlays: Overlays,
+    /// The game rules, which could be changed by the scripts
+    rules: Rules,
+    /// The random tick callbacks defined by the scripts
+    random_ticks: RandomTicks,
+    /// The fluid interactions defined by the scripts
+    fluids: Fluids,
+    /// The flammability of the blocks defined by the scripts
+    fire: Fire,
+    /// The crops defined by the scripts
+    crops: Crops,
+    /// The tools and drop tables defined by the scripts
+    mining: Mining,
+    /// The loot tables of chests defined by the scripts
+    loot: LootTables,
+    /// The recipes defined by the scripts
+    recipes: Recipes,
+    /// The recipe book the player searches the recipes with
+    recipe_book: RecipeBook,
+    /// The smelting recipes and fuels defined by the scripts
+    smelting: Smelting,
+    /// The position of the furnace the player opened, if any
+    open_furnace: Option<Vector3<i32>>,
+    /// The console the player types commands into
+    console: Console,
+    /// The history of the blocks the player changed
+    history: History,
+    /// How far the player broke the block they are looking at,
+    /// while they hold the mouse button breaking blocks
+    breaking: Option<BreakProgress>,
+    /// The teleport which is currently running
+    teleport: Option<Teleport>,
+    /// The night skip which is currently running
+    night_skip: Option<NightSkip>,
+    /// The players sleeping in a bed
+    sleepers: Sleepers,
+    /// The portal the player stood in during the last update,
+    /// which doesn't teleport them until they left it
+    in_portal: Option<u32>,
+    /// The settings of the simulation
+    settings: ServerSettings,
+    /// Keeps the world on disk and the blocks of idle
+    /// chunks in their storage tiers
+    storage: Storage,
+    /// Measures the ticks per second
+    tick_timer: TickTimer,
+    /// The directory the world is saved in
+    save_dir: SaveDir,
+    /// The persisted data of the player
+    player_data: PlayerData,
+    /// The settings of the world
+    meta: WorldMeta,
+    /// The position the player respawns at
+    spawn: Vector3<f32>,
+    /// The death screen, while the player is dead
+    death: Option<DeathScreen>,
+    /// The beacon marking where the player died the last
+    /// time with the dimension it's in, if they ever died
+    death_beacon: Option<(String, NodeId)>,
+    /// The position of the player's feet when they last
+    /// stood on a solid block
+    last_grounded: Vector3<f32>,
+    /// The time until the entities in lava burn again
+    lava_burn: f32,
+    /// The time the player has been in lava in a row
+    burning: f32,
+    /// The vehicle the player rides, if any
+    riding: Option<EntityUuid>,
+    /// The body of the player, which the camera is attached to
+    player: Player,
+    /// The area around the spawn only operators could edit
+    spawn_protection: SpawnProtection,
+    /// Records the session and replays it
+    replays: Replays,
+    /// Flies the camera along the camera path
+    flythroughs: Flythroughs,
+    /// Edits the selected region of blocks at once
+    world_editor: WorldEditor,
+    /// The entity types registered by scripts
+    entity_types: EntityTypes,
+    /// The time in seconds until the player could hit
+    /// an entity again
+    attack_cooldown: f32,
+    /// Picks the sounds the render thread plays
+    audio: Audio,
+    /// The amount of updates so far
+    ticks: u64,
+    /// The particles in the dimension the player is in
+    particles: Particles,
+}
+
+impl Simulation {
+    /// Creates a new simulation. The APIs of the subsystems are
+    /// registered in the script engine before the scripts are
+    /// loaded, an error is returned if one of them couldn't be.
+    ///
+    /// # Arguments
+    ///
+    /// * `camera` - The camera of the player, which is moved
+    ///   above the surface of the overworld at its origin
+    /// * `universe` - The dimensions which should be simulated
+    /// * `resources` - A resource instance the scripts are loaded from
+    /// * `save_dir` - The directory the world is saved in
+    /// * `events` - A receiver for events from the render thread
+    /// * `frame_writer` - The writer to publish the frame states
+    /// * `sounds` - A sender for the sounds the render thread should play
+    pub fn new(
+        mut camera: PerspectiveCamera,
+        mut universe: Universe,
+        resources: &Resources,
+        save_dir: SaveDir,
+        events: Receiver<SimEvent>,
+        frame_writer: TripleBufferWriter<FrameState>,
+        sounds: Sender<SoundCue>,
+    ) -> mlua::Result<Self> {
+        let settings = ServerSettings::load(resources);
+
+        // The APIs need to be registered before the scripts are loaded
+        let scripts = ScriptEngine::new();
+        let achievements = Achievements::new(&scripts).context("Failed to register achievement API")?;
+        let mut hud = Hud::new(&scripts).context("Failed to register notification API")?;
+        let portals = Portals::new(&scripts).context("Failed to register portal API")?;
+        let dimensions = Dimensions::new(&scripts).context("Failed to register dimension API")?;
+        let overlays = Overlays::new(&scripts).context("Failed to register block overlay API")?;
+        let rules = Rules::new(&scripts).context("Failed to register game rule API")?;
+        let random_ticks = RandomTicks::new(&scripts).context("Failed to register random tick API")?;
+        let fluids = Fluids::new(&scripts).context("Failed to register fluid API")?;
+        let crops = Crops::new(&scripts).context("Failed to register crop API")?;
+        let tags = Tags::new(&scripts).context("Failed to register tag API")?;
+        let mining = Mining::new(&scripts, &tags).context("Failed to register mining API")?;
+        let fire = Fire::new(&scripts, &tags).context("Failed to register fire API")?;
+        let loot = LootTables::new(&scripts).context("Failed to register loot API")?;
+        let recipes = Recipes::new(&scripts, &tags).context("Failed to register recipe API")?;
+        let smelting = Smelting::new(&scripts, &tags).context("Failed to register smelting API")?;
+        let vegetation = Vegetation::new(&scripts, &tags).context("Failed to register vegetation API")?;
+        universe.biomes().register_api(&scripts).context("Failed to register biome API")?;
+        let script_events = Events::new(&scripts).context("Failed to register event API")?;
+        let history = History::new(&scripts, save_dir.clone(), settings.edit_log).context("Failed to register edit log API")?;
+        let flythroughs = Flythroughs::new(&scripts).context("Failed to register camera path API")?;
+        let world_editor = WorldEditor::new(&scripts).context("Failed to register world edit API")?;
+        let audio = Audio::new(&scripts, sounds).context("Failed to register sound group API")?;
+        let entity_types = EntityTypes::new(&scripts).context("Failed to register entity API")?;
+        noise_source::register_api(&scripts).context("Failed to register noise API")?;
+        let scoreboard_data = save_dir.load_scoreboard().unwrap_or_else(|error| {
+            log::warn!("Failed to load scoreboard: {:?}", error);
+            Default::default()
+        });
+        let scoreboard = Scoreboard::new(&scripts, scoreboard_data).context("Failed to register scoreboard API")?;
+        let dialogues = Dialogues::new(&scripts).context("Failed to register dialogue API")?;
+        let mut network = Network::new(&scripts).context("Failed to register player list API")?;
+        let input = PlayerInput::new(&scripts).context("Failed to register key bindings API")?;
+        crash::set_world_dir(save_dir.root_path());
+        crash::set_mods(scripting::script_names(resources).unwrap_or_default());
+        for error in scripts.load_scripts(resources) {
+            log::error!("Failed to load script {}", error);
+            hud.notifications().push(Notification::new(NotificationKind::Error, "Failed to load mod", error.source()));
+        }
+
+        if let Some(address) = settings.metrics_address.as_ref() {
+            if let Err(error) = metrics::serve(address) {
+                log::error!("Failed to serve metrics on {}: {}", address, error);
+            }
+        }
+        let player_data = save_dir.load_player_data().unwrap_or_else(|error| {
+            log::warn!("Failed to load player data: {:?}", error);
+            PlayerData::default()
+        });
+        input.set_key_overrides(player_data.key_bindings.clone());
+        if let Some(address) = settings.listen_address.as_ref() {
+            network.listen(address, &player_data.name, universe.active_name());
+        }
+
+        let meta = save_dir.load_meta().unwrap_or_else(|error| {
+            log::warn!("Failed to load world metadata: {:?}", error);
+            WorldMeta::default()
+        });
+        if meta.border_radius > 0 {
+            universe.set_border(Some(WorldBorder::new(meta.border_radius)));
+        }
+
+        universe.set_plants(vegetation.plants());
+        universe.set_entity_types(entity_types.registry());
+        universe.set_day_length(settings.day_length);
+        dimensions.register_all(&mut universe);
+        if let Err(error) = universe.load(&save_dir) {
+            log::warn!("Failed to load world: {:?}", error);
+        }
+
+        // The player spawns above the surface, wherever the biomes
+        // put it, which the scripts could only define by now
+        if let Some(overworld) = universe.dimension(OVERWORLD) {
+            let height = overworld.terrain_gen().gen_heightmap(&Vector2::new(0, 0))[0] as f32;
+            let pos = *camera.pos();
+            camera.set_pos(Vector3::new(pos.x, height + SPAWN_HEIGHT, pos.z));
+        }
+        let spawn = *camera.pos();
+        if let Some(overworld) = universe.dimension_mut(OVERWORLD) {
+            add_beacon(overworld.scene_mut(), "spawn_beacon", spawn, Vector4::new(0.9, 0.8, 0.4, 1.0));
+        }
+        let death_beacon = player_data.last_death.as_ref().and_then(|death| add_death_beacon(&mut universe, death));
+        let mut player = Player::at_eye(spawn);
+        player.set_profile(settings.movement);
+
+        Ok(Self {
+            camera,
+            universe,
+            events,
+            frame_writer,
+            input,
+            scripts,
+            achievements,
+            hud,
+            script_events,
+            scoreboard,
+            dialogues,
+            network,
+            portals,
+            dimensions,
+            overlays,
+            rules,
+            random_ticks,
+            fluids,
+            fire,
+            crops,
+            mining,
+            loot,
+            recipes,
+            recipe_book: RecipeBook::default(),
+            smelting,
+            open_furnace: None,
+            console: Console::default(),
+            history,
+            breaking: None,
+            teleport: None,
+            night_skip: None,
+            sleepers: Sleepers::default(),
+            in_portal: None,
+            settings,
+            storage: Storage::new(&save_dir),
+            tick_timer: TickTimer::default(),
+            save_dir,
+            player_data,
+            spawn_protection: SpawnProtection::new(spawn, meta.spawn_protection),
+            meta,
+            spawn,
+            death: None,
+            death_beacon,
+            last_grounded: spawn - Vector3::new(0.0, PLAYER_EYE_HEIGHT, 0.0),
+            lava_burn: LAVA_BURN_INTERVAL,
+            burning: 0.0,
+            riding: None,
+            player,
+            replays: Replays::default(),
+            flythroughs,
+            world_editor,
+            entity_types,
+            attack_cooldown: 0.0,
+            audio,
+            ticks: 0,
+            particles: Particles::default(),
+        })
+    }
+
+    /// Returns the keys the controls and the actions of scripts
+    /// are bound to, which the render thread shares to trigger
+    /// the controls
+    pub fn key_map(&self) -> SharedKeyMap {
+        self.input.key_map()
+    }
+
+    /// Spawns the simulation on a new thread
+    pub fn spawn(mut self) -> JoinHandle<()> {
+        thread::Builder::new()
+            .name("simulation".into())
+            .spawn(move || self.run())
+            .expect("Failed to spawn simulation thread.")
+    }
+
+    /// Runs the simulation loop until a `SimEvent::Shutdown`
+    /// is received or the render thread hung up. The world is
+    /// saved before the simulation stops, even if an update
+    /// panicked, in which case the panic is resumed afterwards.
+    fn run(&mut self) {
+        let update_duration = Duration::from_secs(1) / UPDATES_PER_SECOND;
+        let mut last_update = Instant::now();
+
+        loop {
+            if !self.handle_events() {
+                break;
+            }
+
+            let now = Instant::now();
+            let time_step = TimeStep((now - last_update).as_secs_f32());
+            last_update = now;
+
+            if let Err(panic) = panic::catch_unwind(AssertUnwindSafe(|| self.update(time_step))) {
+                log::error!("The simulation crashed, saving the world");
+                self.save();
+                panic::resume_unwind(panic);
+            }
+
+            let elapsed = now.elapsed();
+            self.tick_timer.record(time_step.0, elapsed.as_secs_f32());
+            self.update_metrics();
+            if elapsed < update_duration {
+                thread::sleep(update_duration - elapsed);
+            }
+        }
+
+        self.save();
+    }
+
+    /// Saves the player data, the world metadata and the
+    /// regions which have been edited since the last save
+    fn save(&mut self) {
+        if let Err(error) = self.save_dir.save_player_data(&self.player_data) {
+            log::error!("Failed to save player data: {:?}", error);
+        }
+        if let Err(error) = self.save_dir.save_meta(&self.meta) {
+            log::error!("Failed to save world metadata: {:?}", error);
+        }
+        if let Err(error) = self.save_dir.save_scoreboard(&self.scoreboard.data()) {
+            log::error!("Failed to save scoreboard: {:?}", error);
+        }
+        if let Err(error) = self.universe.save(&self.save_dir) {
+            log::error!("Failed to save world: {:?}", error);
+        }
+        if let Err(error) = self.history.flush() {
+            log::error!("Failed to save edit log: {:?}", error);
+        }
+        self.storage.saved();
+    }
+
+    /// Saves the world and backs it up
+    fn backup(&mut self) {
+        self.save();
+        self.storage.backup(self.settings.backups_kept, self.hud.notifications());
+    }
+
+    /// Handles all pending events from the render thread.
+    /// Returns `false` if the simulation should stop.
+    fn handle_events(&mut self) -> bool {
+        loop {
+            let event = self.events.try_recv();
+            if let Some(command) = event.as_ref().ok().and_then(SimEvent::command) {
+                crash::record_command(command);
+            }
+            match event {
+                Ok(SimEvent::Input(input)) => {
+                    if let Some(captured) = input.captured() {
+                        self.network.record_latency(captured.elapsed());
+                    }
+                    self.input.merge(input);
+                }
+                Ok(SimEvent::Resize(width, height)) => {
+                    if height > 0 {
+                        self.camera.set_aspect_ratio(width as f32 / height as f32);
+                    }
+                }
+                Ok(SimEvent::SetFov(fov)) => self.camera.set_fov(fov),
+                // The world is driven by the replay, so the player
+                // can't edit it until the replay is stopped, nor
+                // while the camera isn't theirs during a flythrough
+                Ok(SimEvent::PlaceBlock) | Ok(SimEvent::Undo) | Ok(SimEvent::Redo) | Ok(SimEvent::Interact)
+                    if self.is_watching() => {}
+                Ok(SimEvent::PlaceBlock) => self.place_block(),
+                Ok(SimEvent::Interact) => self.interact(),
+                Ok(SimEvent::Undo) => self.history.undo(&mut self.universe, &self.player_data.name),
+                Ok(SimEvent::Redo) => self.history.redo(&mut self.universe, &self.player_data.name),
+                Ok(SimEvent::ToggleRecording) => self.replays.toggle_recording(self.hud.notifications()),
+                Ok(SimEvent::ToggleReplay) => self.toggle_replay(),
+                Ok(SimEvent::ToggleSpectating) => self.replays.toggle_spectating(self.hud.notifications()),
+                Ok(SimEvent::PauseReplay) => self.replays.toggle_paused(),
+                Ok(SimEvent::ScrubReplay(seconds)) => self.replays.scrub(seconds, &mut self.universe),
+                Ok(SimEvent::AddKeyframe) => self.flythroughs.add_keyframe(None, &self.camera, self.hud.notifications()),
+                Ok(SimEvent::ClearCameraPath) => self.flythroughs.clear(self.hud.notifications()),
+                Ok(SimEvent::ToggleFlythrough) => {
+                    let replaying = self.replays.is_running();
+                    self.flythroughs.toggle(replaying, &mut self.camera, self.hud.notifications());
+                }
+                Ok(SimEvent::ToggleMemoryPanel) => self.toggle_memory_panel(),
+                Ok(SimEvent::ToggleCapabilities) => self.hud.toggle_capabilities_panel(),
+                Ok(SimEvent::ToggleLightOverlay) => self.hud.toggle_light_overlay(),
+                Ok(SimEvent::ShowTps) => self.show_tps(),
+                Ok(SimEvent::InspectBlock) => self.history.inspect(&self.universe, &self.camera, self.hud.notifications()),
+                Ok(SimEvent::Backup) => self.backup(),
+                Ok(SimEvent::Pregenerate(radius)) => {
+                    self.storage.pregenerate(radius, &self.universe, *self.camera.pos(), self.hud.notifications());
+                }
+                // The number keys choose the options of dialogues
+                Ok(SimEvent::SelectBlock(number)) if self.dialogues.is_open() => self.choose_option(number),
+                // The number keys use the slots of the open furnace
+                Ok(SimEvent::SelectBlock(number)) if self.open_furnace.is_some() => self.use_furnace_slot(number),
+                Ok(SimEvent::SelectBlock(index)) => self.select_block(index),
+                Ok(SimEvent::CycleTool) => self.cycle_tool(),
+                Ok(SimEvent::ToggleRecipeBook) => self.recipe_book.toggle(),
+                Ok(SimEvent::SearchRecipes(c)) => self.recipe_book.type_char(c),
+                Ok(SimEvent::EraseSearch) => self.recipe_book.erase(),
+                Ok(SimEvent::SelectRecipe(delta)) => self.recipe_book.select(delta, &self.recipes),
+                Ok(SimEvent::CraftRecipe) => self.craft_recipe(),
+                Ok(SimEvent::ToggleConsole) => self.console.toggle(),
+                Ok(SimEvent::TypeCommand(c)) => self.console.type_char(c),
+                Ok(SimEvent::EraseCommand) => self.console.erase(),
+                Ok(SimEvent::RunCommand) => self.run_command(),
+                Ok(SimEvent::Respawn) => self.respawn(),
+                Ok(SimEvent::ToggleKeyBindings) => self.input.toggle_key_bindings(),
+                Ok(SimEvent::SelectKeyBinding(delta)) => self.input.select_key_binding(delta),
+                Ok(SimEvent::RebindKey) => self.input.toggle_rebinding(),
+                Ok(SimEvent::PressKey(key)) => self.press_key(&key),
+                Ok(SimEvent::Shutdown) | Err(TryRecvError::Disconnected) => return false,
+                Err(TryRecvError::Empty) => return true,
+            }
+        }
+    }
+
+    /// Updates the simulation by a single step and publishes
+    /// the resulting frame state
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    fn update(&mut self, time_step: TimeStep) {
+        let replaying = self.replays.is_running();
+        self.flythroughs.apply_commands(replaying, &mut self.camera, self.hud.notifications());
+        self.apply_edit_commands();
+        self.spawn_scripted_entities();
+        if self.replays.is_running() {
+            self.replays.update(time_step, &mut self.universe, &mut self.camera, self.input.state_mut(), self.hud.notifications());
+        } else if self.flythroughs.is_running() {
+            self.flythroughs.update(time_step, &mut self.camera, self.input.state_mut());
+        } else {
+            self.update_player(time_step);
+        }
+
+        // The world is driven by the replay, so its blocks
+        // aren't ticked until the replay is stopped
+        if !self.replays.is_running() {
+            self.tick_blocks();
+            self.history.update(&mut self.universe, self.hud.notifications());
+            let pos = *self.camera.pos();
+            let feet = Vector3::new(pos.x, self.body().min.y, pos.z);
+            self.universe.update_furnaces(time_step.0, feet, self.settings.entity_activation_radius, &self.smelting);
+            self.universe.update_hoppers(time_step.0, feet, self.settings.entity_activation_radius, &self.smelting);
+        }
+
+        // Advance the time of day in all dimensions
+        for (_, world) in self.universe.dimensions_mut() {
+            world.time_mut().advance(time_step);
+        }
+
+        let tps = self.tick_timer.tps();
+        let upkeep = self.storage.update(time_step, &mut self.universe, &self.save_dir, &self.settings, tps, self.hud.notifications());
+        if upkeep.save {
+            log::info!("Saving the world");
+            self.save();
+        }
+        if upkeep.backup {
+            self.backup();
+        }
+        let changes = self.universe.take_changes();
+
+        // Dimensions could be registered by callbacks as well
+        self.dimensions.register_all(&mut self.universe);
+
+        // Load and unload chunks around the player
+        let chunks = self.universe.update(self.camera.pos(), self.settings.view_distance);
+        let local = LocalPlayer {
+            name: &self.player_data.name,
+            game_mode: if self.is_watching() { GameMode::Spectator } else { GameMode::Survival },
+            dimension: self.universe.active_name(),
+            camera: &self.camera,
+            sneaking: self.player.is_sneaking(),
+        };
+        self.network.update(time_step, &self.scripts, &local, &changes, &chunks, self.hud.notifications());
+        self.hud.update(time_step, &self.universe, &changes, *self.camera.pos());
+
+        // Record the session after the update
+        self.replays.record(&self.camera, changes, self.hud.notifications());
+        self.portals.sync_scene(&mut self.universe);
+        let props = self.universe.scene().instances();
+        let placement = if self.is_watching() {
+            None
+        } else {
+            PlacementPreview::new(&self.universe, &self.camera, &self.body(), self.input.selected_material()).map(|mut preview| {
+                preview.progress = match self.breaking {
+                    Some(breaking) if breaking.block == preview.target => breaking.progress,
+                    _ => 0.0,
+                };
+                preview.placeable &= self.may_edit(preview.pos);
+                preview
+            })
+        };
+        let players = self.network.remote_players(&self.save_dir, &self.player_data.name, self.replays.spectated_camera());
+        let prompt = self.interaction_prompt();
+        let furnace = self.furnace();
+
+        self.frame_writer.publish(FrameState {
+            camera: self.camera.clone(),
+            chunks,
+            props,
+            time: *self.universe.time(),
+            sky: *self.universe.sky(),
+            placement,
+            selection: self.world_editor.outline(),
+            toasts: self.hud.toasts(),
+            fade: self.teleport.as_ref().map_or(0.0, |teleport| teleport.fade())
+                .max(self.night_skip.as_ref().map_or(0.0, NightSkip::fade)),
+            submerged: self.submerged(),
+            air: self.air(),
+            overlays: self.overlays.snapshot(),
+            memory: if self.hud.is_memory_panel_shown() { Some(self.memory_report()) } else { None },
+            capabilities: self.hud.is_capabilities_panel_shown(),
+            lit_surfaces: self.hud.lit_surfaces(),
+            worldgen_lines: self.hud.worldgen_lines(),
+            border: self.universe.border().copied(),
+            players,
+            name_tags: self.universe.scripted_entities().name_tags(),
+            particles: self.particles.particles(),
+            dialogue: self.dialogues.current(),
+            prompt,
+            player_list: if self.input.state().is_player_list_shown() { Some(self.network.player_list().clone()) } else { None },
+            recipe_book: self.recipe_book.view(&self.recipes, &self.player_data.items),
+            furnace,
+            console: self.console.view(),
+            death: self.death.clone(),
+            key_bindings: self.input.key_bindings_view(),
+            held_item: self.held_item(),
+            sidebar: self.scoreboard.sidebar(),
+            capture: None,
+            hud: !self.flythroughs.is_running(),
+        });
+    }
+
+    /// Returns whether the player watches a replay or a
+    /// flythrough, so that the camera isn't theirs and they
+    /// can't edit the world
+    fn is_watching(&self) -> bool {
+        self.replays.is_running() || self.flythroughs.is_running()
+    }
+
+    /// Replays the most recent recording, or stops the current
+    /// replay. A running flythrough is stopped before the
+    /// replay starts.
+    fn toggle_replay(&mut self) {
+        if !self.replays.is_running() {
+            self.flythroughs.stop(&mut self.camera);
+        }
+        self.replays.toggle(&mut self.universe, &mut self.camera, self.hud.notifications());
+    }
+
+    /// Shows or hides the memory panel. The report is
+    /// logged as well whenever the panel is shown.
+    fn toggle_memory_panel(&mut self) {
+        if self.hud.toggle_memory_panel() {
+            for line in self.memory_report().lines() {
+                log::info!("{}", line);
+            }
+        }
+    }
+
+    /// Publishes the metrics of the simulation, which
+    /// are served by the metrics endpoint
+    fn update_metrics(&self) {
+        let chunks = self.universe.chunks();
+        let pending = chunks.iter().filter(|chunk| !chunk.is_generated()).count();
+        METRICS.set(Metric::Ticks, self.ticks as f64);
+        METRICS.set(Metric::Tps, self.tick_timer.tps() as f64);
+        METRICS.set(Metric::TickDuration, self.tick_timer.tick_duration() as f64);
+        METRICS.set(Metric::LoadedChunks, chunks.len() as f64);
+        let entities = self.universe.items().items().len() + self.universe.mobs().mobs().len();
+        METRICS.set(Metric::Entities, entities as f64);
+        METRICS.set(Metric::Players, self.network.player_count() as f64);
+        METRICS.set(Metric::GenerationQueue, pending as f64);
+    }
+
+    /// Shows the ticks per second and the time the work
+    /// of a tick takes, like the `/tps` command of servers
+    fn show_tps(&mut self) {
+        let title = format!("TPS: {:.1}", self.tick_timer.tps());
+        let message = format!("{:.1} ms per tick", self.tick_timer.tick_duration() * 1000.0);
+        log::info!("{}, {}", title, message);
+        self.hud.notifications().push(Notification::new(NotificationKind::Info, title, message));
+    }
+
+    /// Logs the blocks the player changed, if the edit log is
+    /// enabled in the server settings
+    ///
+    /// # Arguments
+    ///
+    /// * `since` - The amount of block changes of the dimension
+    ///   before the edit
+    fn log_edits(&self, since: usize) {
+        self.history.log(&self.universe, &self.player_data.name, since);
+    }
+
+    /// Returns the current memory of all subsystems
+    fn memory_report(&self) -> MemoryReport {
+        MEMORY.set(Subsystem::Lua, self.scripts.lua().used_memory());
+        MEMORY.report()
+    }
+
+    /// Applies the player's input and tracks their statistics
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    fn update_player(&mut self, time_step: TimeStep) {
+        // Apply player input
+        let last_pos = *self.camera.pos();
+        // The player doesn't move while typing into the recipe book
+        // or the console, or while they are dead or the night is skipped
+        let moving = self.death.is_none() && self.night_skip.is_none();
+        let steering = moving && !self.recipe_book.is_open() && !self.console.is_open();
+        let controls = match self.input.update(&mut self.camera, steering, self.riding.is_some()) {
+            Movement::Walk(controls) => controls,
+            Movement::Ride => Controls::default(),
+            Movement::Dismount => {
+                self.dismount();
+                Controls::default()
+            }
+        };
+        if moving && self.riding.is_none() {
+            self.move_player(time_step, controls);
+        }
+        if let Some(border) = self.universe.border() {
+            let pos = border.clamp_player(*self.camera.pos());
+            self.camera.set_pos(pos);
+        }
+        if self.death.is_none() {
+            self.update_breaking(time_step);
+        }
+        self.pick_up_items(time_step);
+        let pos = *self.camera.pos();
+        let feet = Vector3::new(pos.x, self.body().min.y, pos.z);
+        self.universe.update_mobs(time_step.0, feet, &self.player_data.name, self.settings.entity_activation_radius);
+        self.update_vehicles(time_step);
+        self.universe.update_hurt_flashes(time_step.0);
+        self.particles.update(time_step.0);
+        for error in self.entity_types.tick(&self.scripts, &mut self.universe, time_step.0, feet, self.settings.entity_activation_radius) {
+            log::warn!("Failed to run {}", error);
+        }
+
+        // Track statistics and achievements
+        let moved = self.camera.pos() - last_pos;
+        // Players waiting for the others to sleep leave their bed by moving
+        if moved.magnitude2() > 0.0 {
+            self.sleepers.wake(&self.player_data.name);
+        }
+        let grounded = self.is_grounded();
+        let feet = self.body().min;
+        let in_feet = self.universe.block(feet.map(|value| value.floor() as i32));
+        self.audio.update(moved.magnitude(), feet, in_feet, self.ground());
+        let stats = &mut self.player_data.statistics;
+        if grounded {
+            self.last_grounded = Vector3::new(self.camera.pos().x, feet.y, self.camera.pos().z);
+            stats.add_distance_walked(Vector2::new(moved.x, moved.z).magnitude());
+        } else {
+            stats.add_distance_flown(moved.magnitude());
+        }
+        stats.add_time_played(time_step);
+
+        if self.camera.pos().y < VOID_DEPTH && self.death.is_none() {
+            self.die(DeathCause::Void);
+        }
+        self.burn_in_lava(time_step);
+        self.update_explosions(time_step);
+        self.update_teleport(time_step);
+        self.update_night_skip(time_step);
+
+        self.ticks += 1;
+        if self.ticks % ACHIEVEMENT_INTERVAL == 0 {
+            self.update_achievements();
+        }
+    }
+
+    /// Spawns the entities scripts spawned through the
+    /// entity API in the current dimension
+    fn spawn_scripted_entities(&mut self) {
+        for entity in self.entity_types.take_spawned() {
+            if let Err(error) = self.universe.spawn_entity(&entity) {
+                log::warn!("Failed to spawn the {} entity {}: {}", entity.type_id, entity.uuid, error);
+                continue;
+            }
+            if let Err(error) = self.entity_types.spawned(&self.scripts, &mut self.universe, entity.uuid) {
+                log::warn!("Failed to run {}", error);
+            }
+        }
+    }
+
+    /// Applies the commands scripts issued through the
+    /// `worldedit` API
+    fn apply_edit_commands(&mut self) {
+        for command in self.world_editor.take_commands() {
+            match command {
+                EditCommand::Pos1(pos) => self.select_corner(pos, true),
+                EditCommand::Pos2(pos) => self.select_corner(pos, false),
+                EditCommand::Clear => self.world_editor.clear_selection(),
+                // The world is driven by the replay, so it can't be
+                // edited until the replay is stopped
+                EditCommand::Fill(_) | EditCommand::Paste(_) | EditCommand::Undo if self.is_watching() => {
+                    log::warn!("Ignoring {:?} during a replay or flythrough", command);
+                }
+                EditCommand::Fill(material) => self.fill_selection(material),
+                EditCommand::Copy => self.world_editor.copy(&self.universe, self.hud.notifications()),
+                EditCommand::Paste(pos) => self.paste_clipboard(pos),
+                EditCommand::Undo => self.history.undo(&mut self.universe, &self.player_data.name),
+                EditCommand::Brush(brush) => self.world_editor.set_brush(brush, self.hud.notifications()),
+            }
+        }
+    }
+
+    /// Returns whether the player holds a tool, e.g. the
+    /// wand selecting the corners of the region to edit
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the tool
+    fn holds(&self, name: &str) -> bool {
+        matches!(self.player_data.tool.as_ref(), Some(tool) if tool.name == name)
+    }
+
+    /// Selects a corner of the region the world edit commands edit
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the corner, or `None` for the
+    ///   block the player is looking at
+    /// * `first` - Whether the first or the second corner is selected
+    fn select_corner(&mut self, pos: Option<Vector3<i32>>, first: bool) {
+        self.world_editor.select_corner(pos, first, &self.universe, &self.camera, self.hud.notifications());
+    }
+
+    /// Replaces all selected blocks
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The new material of the blocks
+    fn fill_selection(&mut self, material: Material) {
+        let edits = match self.world_editor.fill(material, self.hud.notifications()) {
+            Some(edits) => edits,
+            None => return,
+        };
+        if let Some(changed) = self.edit_region(&edits) {
+            self.hud.notifications().push(Notification::new(NotificationKind::Info, format!("Filled {} blocks", changed), "Press Ctrl+Z to undo"));
+        }
+    }
+
+    /// Pastes the copied blocks
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the lowest pasted block, or `None`
+    ///   for the position in front of the block the player is looking at
+    fn paste_clipboard(&mut self, pos: Option<Vector3<i32>>) {
+        let edits = match self.world_editor.paste(pos, &self.universe, &self.camera, self.hud.notifications()) {
+            Some(edits) => edits,
+            None => return,
+        };
+        if let Some(changed) = self.edit_region(&edits) {
+            self.hud.notifications().push(Notification::new(NotificationKind::Info, format!("Pasted {} blocks", changed), "Press Ctrl+Z to undo"));
+        }
+    }
+
+    /// Applies the brush around the block the player is looking at
+    fn apply_brush(&mut self) {
+        if let Some(edits) = self.world_editor.brush_stroke(&self.universe, &self.camera, self.hud.notifications()) {
+            self.edit_region(&edits);
+        }
+    }
+
+    /// Changes the blocks of a world edit command, which are
+    /// recorded as a single operation in the edit history, and
+    /// returns the amount of changed blocks. No block is changed
+    /// if the player may not edit one of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `edits` - The positions and the new materials of the blocks
+    fn edit_region(&mut self, edits: &[(Vector3<i32>, Material)]) -> Option<usize> {
+        if edits.iter().any(|&(pos, _)| !self.may_edit(pos)) {
+            self.hud.notifications().push(Notification::new(NotificationKind::Warning, "This area is protected", ""));
+            return None;
+        }
+        let since = self.universe.changes().len();
+        let changed = self.universe.edit_blocks(edits);
+        self.log_edits(since);
+        Some(changed)
+    }
+
+    /// Advances the running teleport, or starts a new one if the
+    /// player entered a portal. The chunks around the destination
+    /// are loaded as soon as the teleport starts, so that they are
+    /// generated by the time the player arrives.
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    fn update_teleport(&mut self, time_step: TimeStep) {
+        if let Some(teleport) = self.teleport.as_mut() {
+            if let Some(destination) = teleport.advance(time_step.0).cloned() {
+                self.arrive(destination);
+            }
+            if matches!(&self.teleport, Some(teleport) if teleport.is_finished()) {
+                self.teleport = None;
+            }
+        }
+
+        // Portals only teleport the player when they enter them,
+        // which avoids teleporting them back and forth between
+        // portals whose destinations are other portals
+        let entered = self.portals.portal_at(self.universe.active_name(), &self.body());
+        let previous = std::mem::replace(&mut self.in_portal, entered.as_ref().map(|(id, _)| *id));
+        let (id, destination) = match entered {
+            Some((id, _)) if self.teleport.is_some() || previous == Some(id) => return,
+            Some(portal) => portal,
+            None => return,
+        };
+
+        let dimension = destination.dimension.as_deref().unwrap_or_else(|| self.universe.active_name()).to_string();
+        if !self.universe.contains(&dimension) {
+            log::warn!("Portal {} leads to the unknown dimension {}", id, dimension);
+            return;
+        }
+
+        match self.portals.enter(&self.scripts, id) {
+            Ok(true) => {
+                if let Some(world) = self.universe.dimension_mut(&dimension) {
+                    world.load_area(&destination.pos, PRELOAD_RADIUS);
+                }
+                self.teleport = Some(Teleport::new(destination));
+            }
+            Ok(false) => {}
+            Err(error) => log::warn!("Failed to enter {}", error),
+        }
+    }
+
+    /// Moves the player to the destination of a teleport
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - The location the player is moved to
+    fn arrive(&mut self, destination: Destination) {
+        self.riding = None;
+        if let Some(dimension) = destination.dimension {
+            self.switch_dimension(&dimension);
+        }
+        self.camera.set_pos(destination.pos + Vector3::new(0.0, PLAYER_EYE_HEIGHT, 0.0));
+    }
+
+    /// Moves the player into another dimension. A running
+    /// recording is stopped, as recordings don't span
+    /// multiple dimensions, and the selection is cleared.
+    ///
+    /// # Arguments
+    ///
+    /// * `dimension` - The name of the dimension
+    fn switch_dimension(&mut self, dimension: &str) {
+        if self.universe.active_name() == dimension {
+            return;
+        }
+        self.replays.stop_recording(self.hud.notifications());
+        if !self.universe.switch_to(dimension) {
+            log::warn!("Failed to enter the unknown dimension {}", dimension);
+            return;
+        }
+        // The corners and particles are positions of the previous dimension
+        self.world_editor.clear_selection();
+        self.particles.clear();
+        self.hud.clear_overlays();
+    }
+
+    /// Moves the player's body by their input and gravity, and
+    /// attaches the camera to their eyes. The body follows the
+    /// camera first if something else moved it, e.g. a teleport.
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    /// * `controls` - How the player wants to move
+    fn move_player(&mut self, time_step: TimeStep, controls: Controls) {
+        if *self.camera.pos() != self.player.eye() {
+            self.player.teleport(*self.camera.pos());
+        }
+        let universe = &self.universe;
+        self.player.update(time_step.0, controls, |pos| universe.block(pos));
+        self.camera.set_pos(self.player.eye());
+        if self.player.is_drowning() {
+            self.die(DeathCause::Drowning);
+        }
+    }
+
+    /// Returns whether the player stands on a solid block, so
+    /// that their movement counts as walking rather than flying
+    fn is_grounded(&self) -> bool {
+        self.ground().is_some()
+    }
+
+    /// Returns the bounding box of the player's body. While the
+    /// camera isn't attached to the body, e.g. while they ride a
+    /// vehicle, it's the box of a player standing at the camera.
+    fn body(&self) -> Aabb {
+        if *self.camera.pos() == self.player.eye() {
+            self.player.aabb()
+        } else {
+            Aabb::player(*self.camera.pos())
+        }
+    }
+
+    /// Returns the solid block the player stands on, if any
+    fn ground(&self) -> Option<Material> {
+        let pos = *self.camera.pos();
+        let feet = self.body().min.y;
+        let below = Vector3::new(pos.x.floor() as i32, (feet - GROUND_TOLERANCE).floor() as i32, pos.z.floor() as i32);
+        self.universe.block(below).filter(|material| material.is_solid())
+    }
+
+    /// Returns the share of the air the player has left while
+    /// they hold their breath, which is shown on the HUD
+    fn air(&self) -> Option<f32> {
+        let air = self.player.air();
+        if air < entity::MAX_AIR && self.death.is_none() {
+            Some(air / entity::MAX_AIR)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the fluid the camera is in, if any
+    fn submerged(&self) -> Option<Material> {
+        self.universe.block(self.camera.pos().map(|value| value.floor() as i32))
+            .filter(Material::is_fluid)
+    }
+
+    /// Burns the player and the active scripted entities which are
+    /// in lava or stand on it. The entities take damage with every
+    /// burn, while the player dies after a while in lava.
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    fn burn_in_lava(&mut self, time_step: TimeStep) {
+        let pos = *self.camera.pos();
+        let feet = Vector3::new(pos.x, self.body().min.y, pos.z);
+        let in_lava = self.death.is_none() && (is_in_lava(&self.universe, pos) || is_in_lava(&self.universe, feet));
+        self.burning = if in_lava { self.burning + time_step.0 } else { 0.0 };
+
+        self.lava_burn -= time_step.0;
+        if self.lava_burn <= 0.0 {
+            self.lava_burn = LAVA_BURN_INTERVAL;
+            if in_lava {
+                self.audio.play(SoundCue {
+                    kind: SoundKind::Hurt,
+                    group: None,
+                    pos: feet,
+                });
+            }
+
+            let radius = self.settings.entity_activation_radius;
+            for uuid in self.universe.scripted_entities().active(feet, radius) {
+                let state = match self.universe.scripted_entities().state(uuid) {
+                    Some(state) if is_in_lava(&self.universe, state.pos) => state,
+                    _ => continue,
+                };
+                if let Err(error) = self.entity_types.hurt(&self.scripts, &mut self.universe, uuid, LAVA_DAMAGE, Vector3::new(0.0, 0.0, 0.0)) {
+                    log::warn!("Failed to run {}", error);
+                }
+                let height = self.entity_types.registry().definition(&state.type_id).size.y;
+                self.particles.spawn_damage_number(state.pos + Vector3::new(0.0, height, 0.0), LAVA_DAMAGE);
+            }
+        }
+
+        if self.burning >= LAVA_SURVIVAL_TIME {
+            self.burning = 0.0;
+            self.die(DeathCause::Lava);
+        }
+    }
+
+    /// Advances the primed TNT of the dimension the player is in.
+    /// Explosions hurt the scripted entities around them and kill
+    /// the player close to them. The blocks of the spawn are spared
+    /// from explosions unless the player is an operator.
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    fn update_explosions(&mut self, time_step: TimeStep) {
+        let since = self.universe.changes().len();
+        let protection = &self.spawn_protection;
+        let protected = !self.player_data.operator && self.universe.active_name() == OVERWORLD;
+        let explosions = self.universe.update_tnt(time_step.0, |pos| protected && protection.protects(pos));
+        if explosions.is_empty() {
+            return;
+        }
+        self.log_edits(since);
+
+        let pos = *self.camera.pos();
+        let feet = Vector3::new(pos.x, self.body().min.y, pos.z);
+        for explosion in explosions {
+            self.particles.spawn_explosion(explosion.center);
+            let material = explosion.destroyed.first().map_or(Material::Tnt, |(_, material)| *material);
+            self.audio.play_at(SoundKind::Break, material, explosion.center);
+
+            let radius = self.settings.entity_activation_radius;
+            for uuid in self.universe.scripted_entities().active(feet, radius) {
+                let state = match self.universe.scripted_entities().state(uuid) {
+                    Some(state) => state,
+                    None => continue,
+                };
+                let damage = explosion::damage_at(&explosion, state.pos, EXPLOSION_DAMAGE);
+                if damage <= 0.0 {
+                    continue;
+                }
+                let away = state.pos - explosion.center;
+                let knockback = if away.magnitude2() > 0.0 { away.normalize() * KNOCKBACK_DISTANCE } else { away };
+                if let Err(error) = self.entity_types.hurt(&self.scripts, &mut self.universe, uuid, damage, knockback) {
+                    log::warn!("Failed to run {}", error);
+                }
+                let height = self.entity_types.registry().definition(&state.type_id).size.y;
+                self.particles.spawn_damage_number(state.pos + Vector3::new(0.0, height, 0.0), damage);
+            }
+
+            if self.death.is_none() && explosion::damage_at(&explosion, pos, EXPLOSION_DAMAGE) >= EXPLOSION_LETHAL_DAMAGE {
+                self.die(DeathCause::Explosion);
+            }
+        }
+    }
+
+    /// Records a death of the player and shows the death screen
+    /// until they respawn. Unless the `keepInventory` rule is set,
+    /// their items are dropped where they died, which is marked by
+    /// a beacon and could be returned to with `/back`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cause` - What the player died of
+    fn die(&mut self, cause: DeathCause) {
+        self.riding = None;
+        self.player.refill_air();
+        self.player_data.statistics.record_death();
+        self.death = Some(DeathScreen {
+            cause,
+            score: death::score(&self.player_data.items),
+        });
+
+        // The player falls endlessly in the void, so they
+        // died where they last stood
+        let pos = match cause {
+            DeathCause::Void => self.last_grounded,
+            DeathCause::Lava | DeathCause::Explosion | DeathCause::Drowning => {
+                Vector3::new(self.camera.pos().x, self.body().min.y, self.camera.pos().z)
+            }
+        };
+        if !self.rules.current().keep_inventory {
+            for (item, count) in std::mem::take(&mut self.player_data.items) {
+                self.universe.spawn_item(&item, count, pos);
+            }
+        }
+
+        let location = DeathLocation {
+            dimension: self.universe.active_name().to_string(),
+            pos: pos.into(),
+        };
+        if let Some((dimension, beacon)) = self.death_beacon.take() {
+            if let Some(world) = self.universe.dimension_mut(&dimension) {
+                world.scene_mut().remove(beacon);
+            }
+        }
+        self.death_beacon = add_death_beacon(&mut self.universe, &location);
+        self.player_data.last_death = Some(location);
+    }
+
+    /// Teleports the dead player to their spawn point
+    /// and closes the death screen
+    fn respawn(&mut self) {
+        if self.death.take().is_none() {
+            return;
+        }
+        let destination = Destination::in_dimension(OVERWORLD, self.respawn_point());
+        if let Some(world) = self.universe.dimension_mut(OVERWORLD) {
+            world.load_area(&destination.pos, PRELOAD_RADIUS);
+        }
+        self.teleport = Some(Teleport::new(destination));
+    }
+
+    /// Returns the position of the player's feet when they
+    /// respawn, which is on top of their bed if they slept
+    /// in one that still exists
+    fn respawn_point(&mut self) -> Vector3<f32> {
+        if let Some(foot) = self.player_data.bed.map(Vector3::from) {
+            let world = self.universe.dimension(OVERWORLD);
+            // Beds in chunks which aren't loaded are assumed to exist
+            match world.map(|world| world.block(foot)) {
+                Some(Some(Material::Bed)) | Some(None) => {
+                    return foot.cast::<f32>().unwrap() + Vector3::new(0.5, 1.0, 0.5);
+                }
+                _ => {
+                    self.player_data.bed = None;
+                    self.hud.notifications().push(Notification::new(NotificationKind::Warning, "Your bed is missing", "You respawn at the world spawn"));
+                }
+            }
+        }
+        self.spawn - Vector3::new(0.0, PLAYER_EYE_HEIGHT, 0.0)
+    }
+
+    /// Hits a scripted entity, which takes damage and is knocked
+    /// back. The hit is shown by the entity flashing red, the
+    /// damage rising above it and a hurt sound.
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The id of the entity
+    fn attack(&mut self, uuid: EntityUuid) {
+        let state = match self.universe.scripted_entities().state(uuid) {
+            Some(state) => state,
+            None => return,
+        };
+        let look = self.camera.look();
+        let knockback = Vector3::new(look.x, 0.0, look.z);
+        let knockback = if knockback.magnitude2() > 0.0 { knockback.normalize() * KNOCKBACK_DISTANCE } else { knockback };
+        if let Err(error) = self.entity_types.hurt(&self.scripts, &mut self.universe, uuid, ATTACK_DAMAGE, knockback) {
+            log::warn!("Failed to run {}", error);
+        }
+
+        let height = self.entity_types.registry().definition(&state.type_id).size.y;
+        self.particles.spawn_damage_number(state.pos + Vector3::new(0.0, height, 0.0), ATTACK_DAMAGE);
+        self.audio.play(SoundCue {
+            kind: SoundKind::Hurt,
+            group: None,
+            pos: state.pos,
+        });
+    }
+
+    /// Advances breaking the block the player is looking at while
+    /// they hold the mouse button breaking blocks. The progress is
+    /// lost as soon as they release it or look at another block.
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    fn update_breaking(&mut self, time_step: TimeStep) {
+        // Entities in front of the block are hit instead
+        self.attack_cooldown = (self.attack_cooldown - time_step.0).max(0.0);
+        let vehicle = if self.input.state().is_breaking() { self.target_vehicle() } else { None };
+        if let Some(uuid) = vehicle {
+            self.breaking = None;
+            if self.attack_cooldown <= 0.0 {
+                self.attack_cooldown = ATTACK_COOLDOWN;
+                self.universe.remove_vehicle(uuid);
+            }
+            return;
+        }
+        let target = if self.input.state().is_breaking() { self.target_entity() } else { None };
+        if let Some(uuid) = target {
+            self.breaking = None;
+            if self.attack_cooldown <= 0.0 {
+                self.attack_cooldown = ATTACK_COOLDOWN;
+                self.attack(uuid);
+            }
+            return;
+        }
+
+        let hit = match raycast::raycast(&self.universe, *self.camera.pos(), self.camera.look(), REACH_DISTANCE) {
+            Some(hit) if self.input.state().is_breaking() => hit,
+            _ => {
+                self.breaking = None;
+                return;
+            }
+        };
+
+        // The wand selects the first corner instead of breaking blocks
+        if self.holds(WAND) {
+            self.breaking = None;
+            self.select_corner(Some(hit.block), true);
+            return;
+        }
+
+        let mut progress = match self.breaking {
+            Some(progress) if progress.block == hit.block && progress.material == hit.material => progress,
+            _ => {
+                if !self.may_edit(hit.block) {
+                    self.hud.notifications().push(Notification::new(NotificationKind::Warning, "This area is protected", ""));
+                }
+                BreakProgress::new(hit.block, hit.material)
+            }
+        };
+        // Protected blocks are never broken, but the progress is
+        // kept so that the player is only notified once
+        if !self.may_edit(hit.block) {
+            self.breaking = Some(progress);
+            return;
+        }
+        let tool = self.player_data.tool.as_ref().map(|tool| tool.name.clone());
+        let break_time = self.mining.break_time(hit.material, tool.as_deref());
+        if progress.advance(time_step.0, break_time) {
+            self.breaking = None;
+            self.break_block(hit.block, hit.material);
+        } else {
+            self.breaking = Some(progress);
+        }
+    }
+
+    /// Breaks a block and drops the items of its drop table,
+    /// as well as the harvest if it's a grown crop, the loot
+    /// if it's a chest and the items of a furnace
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    /// * `material` - The material of the block
+    fn break_block(&mut self, pos: Vector3<i32>, material: Material) {
+        let state = self.universe.block_state(pos).unwrap_or(0);
+        let generated = self.universe.is_generated(pos);
+        let entity = self.universe.take_block_entity(pos);
+        // Both halves of a bed are broken together
+        let mut edits = vec![(pos, Material::Air)];
+        if material == Material::Bed {
+            let other = bed::other_half(pos, state);
+            if self.universe.block(other) == Some(Material::Bed) {
+                edits.push((other, Material::Air));
+            }
+        }
+        let since = self.universe.changes().len();
+        if self.universe.edit_blocks(&edits) == 0 {
+            return;
+        }
+        self.log_edits(since);
+        self.audio.play_block(SoundKind::Break, pos, material);
+        let delay = self.rules.current().regeneration_delay;
+        if generated && delay > 0 {
+            self.universe.scheduled_mut().schedule(delay as u64 * UPDATES_PER_SECOND as u64, ScheduledTick {
+                pos,
+                action: TickAction::Regenerate { material, state },
+            });
+        }
+        self.player_data.statistics.record_block_edit(material, Material::Air);
+        self.fire_event(events::BLOCK_BROKEN, material.name(), 1);
+
+        let tool = self.player_data.tool.as_ref().map(|tool| tool.name.clone());
+        let mut drops = self.mining.drops(material, tool.as_deref());
+        if let Some(harvest) = self.crops.harvest(material, state) {
+            drops.push((harvest.item, harvest.count));
+        }
+        match entity {
+            Some(BlockEntity::Chest { loot_table, items }) => {
+                if !loot_table.is_empty() {
+                    drops.extend(self.loot.roll(&loot_table));
+                }
+                drops.extend(items);
+            }
+            Some(BlockEntity::Furnace(mut furnace)) => drops.extend(furnace.take_all()),
+            Some(BlockEntity::Hopper(hopper)) => drops.extend(hopper.items().cloned()),
+            _ => {}
+        }
+        let center = Vector3::new(pos.x as f32 + 0.5, pos.y as f32, pos.z as f32 + 0.5);
+        for (item, count) in drops {
+            self.universe.spawn_item(&item, count, center);
+        }
+
+        self.wear_tool();
+    }
+
+    /// Wears down the tool the player holds by a use, e.g. a
+    /// broken block, which breaks the tool once its durability
+    /// is used up
+    fn wear_tool(&mut self) {
+        let tool = match self.player_data.tool.as_mut() {
+            Some(tool) => tool,
+            None => return,
+        };
+        let durability = match self.mining.tool(&tool.name) {
+            Some(def) => def.durability(),
+            None => return,
+        };
+        if tool.wear_down(durability) {
+            let title = format!("Your {} broke", tool.name);
+            self.player_data.tool = None;
+            self.hud.notifications().push(Notification::new(NotificationKind::Warning, title, ""));
+        }
+    }
+
+    /// Lets the player hold the next tool defined by the scripts.
+    /// There is no inventory of tools yet, so the tool is new.
+    fn cycle_tool(&mut self) {
+        let current = self.player_data.tool.as_ref().map(|tool| tool.name.clone());
+        let next = self.mining.next_tool(current.as_deref());
+        let title = next.clone().unwrap_or_else(|| "Hand".to_string());
+        self.player_data.tool = next.map(|name| HeldTool::new(&name));
+        self.breaking = None;
+        self.hud.notifications().push(Notification::new(NotificationKind::Info, title, ""));
+    }
+
+    /// Crafts the recipe selected in the recipe book from the
+    /// items the player collected, if they suffice
+    fn craft_recipe(&mut self) {
+        let recipe = match self.recipe_book.selected(&self.recipes) {
+            Some(recipe) => recipe,
+            None => return,
+        };
+        if self.recipes.craft(&recipe, &mut self.player_data.items) {
+            let title = format!("+{} {}", recipe.count, recipe.result);
+            self.hud.notifications().push(Notification::new(NotificationKind::Info, title, "Crafted"));
+        } else {
+            let message = format!("Crafting {} takes {}", recipe.result, recipe.ingredients_text());
+            self.hud.notifications().push(Notification::new(NotificationKind::Warning, "Missing ingredients", message));
+        }
+    }
+
+    /// Returns the item the player holds as it's shown
+    /// on the HUD, with the icon of the compass and the
+    /// clock following the world
+    fn held_item(&self) -> Option<HeldItem> {
+        let name = self.player_data.tool.as_ref()?.name.clone();
+        let icon = match name.as_str() {
+            held_item::COMPASS => ItemIcon::Compass(match self.compass_target() {
+                Some(target) => held_item::compass_angle(*self.camera.pos(), self.camera.look(), target),
+                None => self.ticks as f32 / UPDATES_PER_SECOND as f32 * held_item::COMPASS_SPIN_SPEED,
+            }),
+            held_item::CLOCK => ItemIcon::Clock(self.universe.time().time_of_day()),
+            _ => ItemIcon::Static,
+        };
+        Some(HeldItem { name, icon })
+    }
+
+    /// Returns the position the compass points towards, which
+    /// is the waypoint of the player or the spawn. The compass
+    /// spins outside of the overworld.
+    fn compass_target(&self) -> Option<Vector3<f32>> {
+        if self.universe.active_name() != OVERWORLD {
+            return None;
+        }
+        Some(self.player_data.waypoint.map_or(self.spawn, Vector3::from))
+    }
+
+    /// Binds the control or action selected in the key bindings
+    /// menu to the pressed key, if the menu waits for one, or
+    /// triggers the action bound to the key otherwise
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the pressed key
+    fn press_key(&mut self, key: &str) {
+        if let Some(overrides) = self.input.press_key(&self.scripts, key, &self.player_data.name) {
+            self.player_data.key_bindings = overrides;
+        }
+    }
+
+    /// Advances the items lying in the dimension the player is
+    /// in and collects the ones the player picked up
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    fn pick_up_items(&mut self, time_step: TimeStep) {
+        let pos = *self.camera.pos();
+        let feet = Vector3::new(pos.x, self.body().min.y, pos.z);
+        for (item, count) in self.universe.update_items(time_step.0, feet, self.settings.entity_activation_radius,
+                                                             self.settings.entity_tick_budget) {
+            *self.player_data.items.entry(item.clone()).or_insert(0) += count;
+            self.fire_event(events::ITEM_PICKED_UP, &item, count);
+            let title = format!("+{} {}", count, item);
+            self.hud.notifications().push(Notification::new(NotificationKind::Info, title, ""));
+        }
+    }
+
+    /// Selects the block the player places
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the material in `Material::ALL`
+    fn select_block(&mut self, index: usize) {
+        if let Some(material) = self.input.select_material(index) {
+            self.hud.notifications().push(Notification::new(NotificationKind::Info, material.name(), ""));
+        }
+    }
+
+    /// Returns the scripted entity the player is looking at,
+    /// if it's within reach and not behind a block
+    fn target_entity(&self) -> Option<EntityUuid> {
+        let (origin, look) = (*self.camera.pos(), self.camera.look());
+        let (uuid, distance) = self.universe.scripted_entities().hit(origin, look, REACH_DISTANCE)?;
+        match raycast::raycast(&self.universe, origin, look, REACH_DISTANCE) {
+            Some(hit) if hit.distance < distance => None,
+            _ => Some(uuid),
+        }
+    }
+
+    /// Places the selected block at the face the player is
+    /// looking at, if it's within reach, not blocked and
+    /// not protected. The player interacts with the scripted
+    /// entity they are looking at instead, if there's one.
+    fn place_block(&mut self) {
+        if let Some(uuid) = self.target_entity() {
+            self.interact_with(uuid);
+            return;
+        }
+        if let Some(uuid) = self.target_vehicle() {
+            self.mount(uuid);
+            return;
+        }
+        if let Some(uuid) = self.target_mob() {
+            if self.interact_with_mob(uuid) {
+                return;
+            }
+        }
+        if let Some(pos) = self.target_bed() {
+            self.use_bed(pos);
+            return;
+        }
+        // The wand selects the second corner instead of placing blocks
+        if self.holds(WAND) {
+            self.select_corner(None, false);
+            return;
+        }
+        // The brush sculpts the terrain instead of placing blocks
+        if self.holds(BRUSH) {
+            self.apply_brush();
+            return;
+        }
+        if self.holds(fire::FLINT_AND_STEEL) {
+            self.light_fire();
+            return;
+        }
+        let vehicle = self.player_data.tool.as_ref().and_then(|tool| VehicleKind::from_name(&tool.name));
+        if let Some(kind) = vehicle {
+            self.place_vehicle(kind);
+            return;
+        }
+        let preview = PlacementPreview::new(&self.universe, &self.camera, &self.body(), self.input.selected_material())
+            .filter(|preview| preview.placeable);
+        // Spawn eggs spawn their entity on the targeted face instead of placing blocks
+        let egg = self.player_data.tool.as_ref().and_then(|tool| entity_types::egg_type(&tool.name)).map(str::to_string);
+        if let (Some(type_id), Some(preview)) = (egg.as_ref(), preview.as_ref()) {
+            let pos = preview.pos.cast::<f32>().unwrap() + Vector3::new(0.5, 0.0, 0.5);
+            if let Err(message) = self.summon(type_id, pos, Components::new()) {
+                self.hud.notifications().push(Notification::new(NotificationKind::Warning, "Failed to use the spawn egg", message));
+            }
+            return;
+        }
+        if let Some(preview) = preview {
+            if !self.may_edit(preview.pos) {
+                self.hud.notifications().push(Notification::new(NotificationKind::Warning, "This area is protected", ""));
+                return;
+            }
+            if preview.material == Material::Bed {
+                self.place_bed(preview.pos);
+                return;
+            }
+            let since = self.universe.changes().len();
+            if self.universe.edit_block(preview.pos, preview.material) {
+                self.log_edits(since);
+                if let Some(entity) = BlockEntity::container(preview.material) {
+                    self.universe.set_block_entity(preview.pos, entity);
+                }
+                // Hoppers face the block they're placed against
+                if preview.material == Material::Hopper {
+                    let facing = Facing::from_offset(preview.target - preview.pos);
+                    self.universe.set_block_state(preview.pos, hopper::facing_state(facing));
+                }
+                self.audio.play_block(SoundKind::Place, preview.pos, preview.material);
+                self.player_data.statistics.record_block_edit(Material::Air, preview.material);
+                self.fire_event(events::BLOCK_PLACED, preview.material.name(), 1);
+            }
+        }
+    }
+
+    /// Lights a fire on the face the player is looking at
+    /// with flint and steel, which wears it down. TNT the
+    /// player is looking at is lit instead.
+    fn light_fire(&mut self) {
+        if let Some(hit) = raycast::raycast(&self.universe, *self.camera.pos(), self.camera.look(), REACH_DISTANCE) {
+            if hit.material == Material::Tnt {
+                if !self.may_edit(hit.block) {
+                    self.hud.notifications().push(Notification::new(NotificationKind::Warning, "This area is protected", ""));
+                    return;
+                }
+                let since = self.universe.changes().len();
+                if explosion::prime(&mut self.universe, hit.block, FUSE_TIME) {
+                    self.log_edits(since);
+                    self.audio.play_block(SoundKind::Place, hit.block, Material::Fire);
+                    self.wear_tool();
+                }
+                return;
+            }
+        }
+        let preview = match PlacementPreview::new(&self.universe, &self.camera, &self.body(), Material::Fire) {
+            Some(preview) => preview,
+            None => return,
+        };
+        if !self.may_edit(preview.pos) {
+            self.hud.notifications().push(Notification::new(NotificationKind::Warning, "This area is protected", ""));
+            return;
+        }
+        let since = self.universe.changes().len();
+        if fire::ignite(&mut self.universe, preview.pos) {
+            self.log_edits(since);
+            self.audio.play_block(SoundKind::Place, preview.pos, Material::Fire);
+            self.wear_tool();
+        }
+    }
+
+    /// Places a bed with its foot at the given position and
+    /// its head in the horizontal direction the player looks
+    ///
+    /// # Arguments
+    ///
+    /// * `foot` - The position of the foot of the bed
+    fn place_bed(&mut self, foot: Vector3<i32>) {
+        let look = self.camera.look();
+        let facing = if look.x.abs() > look.z.abs() {
+            if look.x > 0.0 { Facing::East } else { Facing::West }
+        } else if look.z > 0.0 {
+            Facing::South
+        } else {
+            Facing::North
+        };
+        let head = foot + facing.offset();
+        if self.universe.block(head) != Some(Material::Air) || !self.may_edit(head) {
+            self.hud.notifications().push(Notification::new(NotificationKind::Warning, "There is no room for the bed", ""));
+            return;
+        }
+
+        let since = self.universe.changes().len();
+        if self.universe.edit_blocks(&[(foot, Material::Bed), (head, Material::Bed)]) < 2 {
+            return;
+        }
+        self.log_edits(since);
+        self.universe.set_block_state(foot, bed::state(facing, false));
+        self.universe.set_block_state(head, bed::state(facing, true));
+        self.audio.play_block(SoundKind::Place, foot, Material::Bed);
+        self.player_data.statistics.record_block_edit(Material::Air, Material::Bed);
+        self.fire_event(events::BLOCK_PLACED, Material::Bed.name(), 1);
+    }
+
+    /// Returns the mob the player is looking at, if it's
+    /// within reach
+    fn target_mob(&self) -> Option<EntityUuid> {
+        let (origin, look) = (*self.camera.pos(), self.camera.look());
+        let (uuid, distance) = self.universe.mobs().hit(origin, look, REACH_DISTANCE)?;
+        match raycast::raycast(&self.universe, origin, look, REACH_DISTANCE) {
+            Some(hit) if hit.distance < distance => None,
+            _ => Some(uuid),
+        }
+    }
+
+    /// Tries to tame a mob while the player holds a bone, or
+    /// tells a mob the player tamed to sit or to stand up.
+    /// Returns whether the player interacted with the mob.
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The id of the mob
+    fn interact_with_mob(&mut self, uuid: EntityUuid) -> bool {
+        let kind = match self.universe.mobs().get(uuid) {
+            Some(mob) => mob.kind().to_string(),
+            None => return false,
+        };
+        let player = self.player_data.name.clone();
+        if self.holds(mobs::TAMING_ITEM) {
+            if let Some(tamed) = self.universe.tame_mob(uuid, &player) {
+                if tamed {
+                    self.hud.notifications().push(Notification::new(NotificationKind::Info, format!("Tamed the {}", kind), "It follows you now"));
+                }
+                self.wear_tool();
+                return true;
+            }
+        }
+        match self.universe.toggle_mob_sitting(uuid, &player) {
+            Some(sitting) => {
+                let title = if sitting { format!("The {} sits", kind) } else { format!("The {} follows you", kind) };
+                self.hud.notifications().push(Notification::new(NotificationKind::Info, title, ""));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the vehicle the player is looking at, if it's
+    /// within reach and not the one they ride
+    fn target_vehicle(&self) -> Option<EntityUuid> {
+        let (origin, look) = (*self.camera.pos(), self.camera.look());
+        let (uuid, distance) = self.universe.vehicles().hit(origin, look, REACH_DISTANCE)?;
+        match raycast::raycast(&self.universe, origin, look, REACH_DISTANCE) {
+            Some(hit) if hit.distance < distance => None,
+            _ if self.riding == Some(uuid) => None,
+            _ => Some(uuid),
+        }
+    }
+
+    /// Places a vehicle on the face the player is looking at,
+    /// which faces the direction they look in
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of the vehicle
+    fn place_vehicle(&mut self, kind: VehicleKind) {
+        let hit = match raycast::raycast(&self.universe, *self.camera.pos(), self.camera.look(), REACH_DISTANCE) {
+            Some(hit) => hit,
+            None => return,
+        };
+        let pos = hit.adjacent();
+        if !matches!(self.universe.block(pos), Some(material) if !material.is_solid()) {
+            return;
+        }
+        let look = self.camera.look();
+        self.universe.spawn_vehicle(kind, pos.cast::<f32>().unwrap() + Vector3::new(0.5, 0.0, 0.5), look.z.atan2(look.x));
+    }
+
+    /// Lets the player ride a vehicle
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The id of the vehicle
+    fn mount(&mut self, uuid: EntityUuid) {
+        self.riding = Some(uuid);
+        self.breaking = None;
+        self.sleepers.wake(&self.player_data.name);
+    }
+
+    /// Lets the player get off the vehicle they ride onto
+    /// the safe ground next to it
+    fn dismount(&mut self) {
+        let uuid = match self.riding.take() {
+            Some(uuid) => uuid,
+            None => return,
+        };
+        if let Some(vehicle) = self.universe.vehicles().get(uuid) {
+            let feet = vehicles::dismount_pos(*vehicle.pos(), |pos| self.universe.block(pos));
+            self.camera.set_pos(feet + Vector3::new(0.0, PLAYER_EYE_HEIGHT, 0.0));
+        }
+    }
+
+    /// Advances the vehicles of the dimension the player is in,
+    /// and moves the player along with the one they ride. They
+    /// get off once it's gone.
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    fn update_vehicles(&mut self, time_step: TimeStep) {
+        let pos = *self.camera.pos();
+        let feet = Vector3::new(pos.x, self.body().min.y, pos.z);
+        let steering = if self.console.is_open() || self.recipe_book.is_open() || self.death.is_some() {
+            Default::default()
+        } else {
+            input::steering(self.input.state())
+        };
+        let ridden = self.riding.map(|uuid| (uuid, steering));
+        self.universe.update_vehicles(time_step.0, feet, self.settings.entity_activation_radius, ridden);
+
+        if let Some(uuid) = self.riding {
+            match self.universe.vehicles().get(uuid) {
+                Some(vehicle) => {
+                    let seat = vehicle.seat();
+                    self.camera.set_pos(seat + Vector3::new(0.0, PLAYER_EYE_HEIGHT, 0.0));
+                }
+                None => self.riding = None,
+            }
+        }
+    }
+
+    /// Returns the position of the bed the player is looking
+    /// at, if it's within reach
+    fn target_bed(&self) -> Option<Vector3<i32>> {
+        let hit = raycast::raycast(&self.universe, *self.camera.pos(), self.camera.look(), REACH_DISTANCE)?;
+        Some(hit.block).filter(|_| hit.material == Material::Bed)
+    }
+
+    /// Sets the spawn point of the player to a bed and lets
+    /// them sleep, if it's night. The night is skipped once
+    /// all players in the world sleep.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of either half of the bed
+    fn use_bed(&mut self, pos: Vector3<i32>) {
+        if self.universe.active_name() != OVERWORLD {
+            self.hud.notifications().push(Notification::new(NotificationKind::Warning, "You can't sleep here", "Beds only work in the overworld"));
+            return;
+        }
+        let foot: [i32; 3] = bed::foot(pos, self.universe.block_state(pos).unwrap_or(0)).into();
+        if self.player_data.bed != Some(foot) {
+            self.player_data.bed = Some(foot);
+            self.hud.notifications().push(Notification::new(NotificationKind::Info, "Spawn point set", ""));
+        }
+        if !bed::is_night(self.universe.time()) {
+            self.hud.notifications().push(Notification::new(NotificationKind::Info, "You can only sleep at night", ""));
+            return;
+        }
+        if self.night_skip.is_some() {
+            return;
+        }
+
+        // The local player is in the world even before
+        // the player list has been updated the first time
+        let name = self.player_data.name.clone();
+        self.sleepers.sleep(&name);
+        let players = std::iter::once(name.as_str())
+            .chain(self.network.player_list().players.iter().map(|player| player.name.as_str()));
+        if self.sleepers.all_asleep(players) {
+            self.night_skip = Some(NightSkip::default());
+        } else {
+            self.hud.notifications().push(Notification::new(NotificationKind::Info, "Sleeping", "Waiting for the other players"));
+        }
+    }
+
+    /// Advances the running night skip, which sets the time
+    /// of the overworld to the morning once the screen faded
+    /// out and wakes all players up
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    fn update_night_skip(&mut self, time_step: TimeStep) {
+        let night_skip = match self.night_skip.as_mut() {
+            Some(night_skip) => night_skip,
+            None => return,
+        };
+        if night_skip.advance(time_step.0) {
+            if let Some(world) = self.universe.dimension_mut(OVERWORLD) {
+                world.time_mut().skip_to(bed::WAKE_UP_TIME);
+            }
+            self.sleepers.wake_all();
+        }
+        if night_skip.is_finished() {
+            self.night_skip = None;
+        }
+    }
+
+    /// Closes the open dialogue, or lets the player interact
+    /// with the scripted entity they are looking at
+    fn interact(&mut self) {
+        if self.dialogues.is_open() {
+            self.dialogues.close();
+        } else if self.open_furnace.is_some() {
+            self.open_furnace = None;
+        } else if let Some(uuid) = self.target_entity() {
+            self.interact_with(uuid);
+        } else if let Some(pos) = self.target_furnace() {
+            if !matches!(self.universe.block_entity(pos), Some(BlockEntity::Furnace(_))) {
+                self.universe.set_block_entity(pos, BlockEntity::Furnace(Furnace::default()));
+            }
+            self.open_furnace = Some(pos);
+        }
+    }
+
+    /// Returns the position of the furnace the player is
+    /// looking at, if it's within reach
+    fn target_furnace(&self) -> Option<Vector3<i32>> {
+        let hit = raycast::raycast(&self.universe, *self.camera.pos(), self.camera.look(), REACH_DISTANCE)?;
+        Some(hit.block).filter(|_| matches!(hit.material, Material::Furnace | Material::LitFurnace))
+    }
+
+    /// Returns the furnace the player opened. The furnace is
+    /// closed once its block is gone.
+    fn furnace(&mut self) -> Option<Furnace> {
+        match self.universe.block_entity(self.open_furnace?) {
+            Some(BlockEntity::Furnace(furnace)) => Some(furnace),
+            _ => {
+                self.open_furnace = None;
+                None
+            }
+        }
+    }
+
+    /// Puts the collected items into the input or the fuel slot
+    /// of the open furnace, or takes its output
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The number of the slot, starting at 1
+    fn use_furnace_slot(&mut self, number: usize) {
+        let (pos, slot) = match (self.open_furnace, FurnaceSlot::from_number(number)) {
+            (Some(pos), Some(slot)) => (pos, slot),
+            _ => return,
+        };
+        let mut furnace = match self.furnace() {
+            Some(furnace) => furnace,
+            None => return,
+        };
+
+        if slot == FurnaceSlot::Output {
+            if let Some((item, count)) = furnace.take(slot) {
+                *self.player_data.items.entry(item.clone()).or_insert(0) += count;
+                self.fire_event(events::ITEM_PICKED_UP, &item, count);
+                self.hud.notifications().push(Notification::new(NotificationKind::Info, format!("+{} {}", count, item), ""));
+            }
+        } else if let Some(item) = furnace::item_for_slot(&furnace, slot, &self.player_data.items, &self.smelting) {
+            let held = self.player_data.items[&item];
+            let added = furnace.insert(slot, &item, held, &self.smelting);
+            if added == held {
+                self.player_data.items.remove(&item);
+            } else {
+                self.player_data.items.insert(item, held - added);
+            }
+        } else {
+            let message = if slot == FurnaceSlot::Input { "Nothing to smelt" } else { "No fuel" };
+            self.hud.notifications().push(Notification::new(NotificationKind::Warning, message, ""));
+        }
+        self.universe.set_block_entity(pos, BlockEntity::Furnace(furnace));
+    }
+
+    /// Calls the `onInteract` callback of a scripted entity,
+    /// e.g. to open a dialogue
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The id of the entity
+    fn interact_with(&mut self, uuid: EntityUuid) {
+        let player = self.player_data.name.clone();
+        if let Err(error) = self.entity_types.interact(&self.scripts, &mut self.universe, uuid, &player) {
+            log::warn!("Failed to run {}", error);
+        }
+    }
+
+    /// Returns the action of the scripted entity or the furnace
+    /// the player could interact with, unless a dialogue or a
+    /// furnace is open or the camera isn't theirs
+    fn interaction_prompt(&self) -> Option<String> {
+        if self.dialogues.is_open() || self.is_watching() {
+            return None;
+        }
+        match self.target_entity() {
+            Some(uuid) => self.entity_types.prompt(&self.universe, uuid),
+            None if self.open_furnace.is_none() => self.target_furnace().map(|_| "open the furnace".to_string()),
+            None => None,
+        }
+    }
+
+    /// Chooses an option of the open dialogue
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The number of the option, starting at 1
+    fn choose_option(&mut self, number: usize) {
+        let index = match number.checked_sub(1) {
+            Some(index) => index,
+            None => return,
+        };
+        let player = self.player_data.name.clone();
+        if let Err(error) = self.dialogues.choose(&self.scripts, index, &player) {
+            log::warn!("Failed to run {}", error);
+        }
+    }
+
+    /// Calls the callbacks scripts registered for an event of
+    /// the player, with the name of the player, the name of the
+    /// block or item and, for items, the amount of items
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The name of the event
+    /// * `name` - The name of the block or item
+    /// * `count` - The amount of items
+    fn fire_event(&self, event: &str, name: &str, count: u64) {
+        let player = self.player_data.name.as_str();
+        let errors = if event == events::ITEM_PICKED_UP {
+            self.script_events.fire(&self.scripts, event, (player, name, count))
+        } else {
+            self.script_events.fire(&self.scripts, event, (player, name))
+        };
+        for error in errors {
+            log::warn!("Failed to run {}", error);
+        }
+    }
+
+    /// Returns whether the player may break or place the block at
+    /// the given position, which isn't the case within the spawn
+    /// protection unless they are an operator
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    fn may_edit(&self, pos: Vector3<i32>) -> bool {
+        self.player_data.operator
+            || self.universe.active_name() != OVERWORLD
+            || !self.spawn_protection.protects(pos)
+    }
+
+    /// Ticks random blocks of the dimension the player is in
+    /// and runs the scheduled ticks of all dimensions
+    fn tick_blocks(&mut self) {
+        let rules = self.rules.current();
+        let behaviors = BlockBehaviors {
+            interactions: self.fluids.interactions(),
+            flammable: self.fire.flammable(),
+        };
+        for error in self.random_ticks.tick(&self.scripts, &mut self.universe, &rules) {
+            log::warn!("Failed to run {}", error);
+        }
+        let active = self.universe.active_name().to_string();
+        for (name, world) in self.universe.dimensions_mut() {
+            let report = ticks::run_scheduled(world, &behaviors);
+            if report.regenerated > 0 {
+                log::debug!("Regenerated {} blocks in {}", report.regenerated, name);
+            }
+            if name == active {
+                for pos in report.burning {
+                    self.particles.spawn_flame(pos.cast::<f32>().unwrap() + Vector3::new(0.5, 0.3, 0.5));
+                }
+            }
+        }
+    }
+
+    /// Unlocks all achievements whose conditions are fulfilled
+    fn update_achievements(&mut self) {
+        let (unlocked, errors) = self.achievements.update(&self.scripts, &mut self.player_data.statistics);
+        for achievement in unlocked {
+            log::info!("Achievement unlocked: {} - {}", achievement.title, achievement.description);
+            self.hud.notifications().push(Notification::new(
+                NotificationKind::Achievement,
+                format!("Achievement: {}", achievement.title),
+                achievement.description,
+            ));
+        }
+        for error in errors {
+            log::warn!("Failed to check {}", error);
+        }
+    }
+}
+
+/// Adds a beacon to the scene which marks a position, e.g.
+/// the spawn, so that the player could find back to it
+///
+/// # Arguments
+///
+/// * `scene` - The scene of the world
+/// * `name` - The name of the beacon's node
+/// * `pos` - The marked position
+/// * `color` - The color of the beacon
+fn add_beacon(scene: &mut Scene, name: &str, pos: Vector3<f32>, color: Vector4<f32>) -> NodeId {
+    let beacon = scene.add(None, SceneNode::new(
+        name,
+        Matrix4::from_translation(Vector3::new(pos.x, 0.0, pos.z)),
+    ));
+
+    // A thin pillar with its base centered around the origin
+    let mut pillar = MeshBuilder::new();
+    pillar.add(&Mesh::cube(), &(Matrix4::from_nonuniform_scale(0.3, BEACON_HEIGHT, 0.3)
+        * Matrix4::from_translation(Vector3::new(-0.5, 0.0, -0.5))));
+    scene.add(Some(beacon), SceneNode::with_mesh(
+        "pillar",
+        Matrix4::identity(),
+        MeshHandle::new(pillar.build()),
+        color,
+    ));
+
+    // The light is slightly brighter than the pillar
+    let light = color.truncate().map(|value| (value + 0.15).min(1.0));
+    scene.add(Some(beacon), SceneNode::with_mesh(
+        "light",
+        Matrix4::from_translation(Vector3::new(0.0, BEACON_HEIGHT, 0.0)) * Matrix4::from_scale(0.6),
+        MeshHandle::new(Mesh::sphere(8, 16)),
+        light.extend(color.w),
+    ));
+    beacon
+}
+
+/// Adds a red beacon marking where the player died to the
+/// dimension they died in. Returns the dimension and the node
+/// of the beacon, or `None` if the dimension doesn't exist.
+///
+/// # Arguments
+///
+/// * `universe` - The universe
+/// * `location` - Where the player died
+fn add_death_beacon(universe: &mut Universe, location: &DeathLocation) -> Option<(String, NodeId)> {
+    let world = universe.dimension_mut(&location.dimension)?;
+    let beacon = add_beacon(world.scene_mut(), "death_beacon", location.pos(), Vector4::new(0.8, 0.15, 0.1, 1.0));
+    Some((location.dimension.clone(), beacon))
+}
+
+/// Returns whether an entity is in lava or stands on it
+///
+/// # Arguments
+///
+/// * `universe` - The universe
+/// * `feet` - The position of the entity's feet
+fn is_in_lava(universe: &Universe, feet: Vector3<f32>) -> bool {
+    [feet, feet - Vector3::new(0.0, GROUND_TOLERANCE, 0.0)].iter()
+        .any(|pos| universe.block(pos.map(|value| value.floor() as i32)) == Some(Material::Lava))
+}
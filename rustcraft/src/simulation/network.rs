@@ -0,0 +1,263 @@
+//! The subsystem of the simulation hosting the world for
+//! other players and keeping the list of the players
+
+use crate::camera::Camera;
+use crate::network::{ListenServer, ServerEvent};
+use crate::notification::{Notification, NotificationKind, NotificationManager};
+use crate::player::{RemotePlayer, Skins};
+use crate::player_list::{GameMode, Latency, PlayerEntry, PlayerList, PlayerListColumns, STATUS_INTERVAL};
+use crate::scripting::ScriptEngine;
+use crate::timestep::TimeStep;
+use crate::world::chunk::Chunk;
+use crate::world::history::BlockEdit;
+use crate::world::save::SaveDir;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/// LocalPlayer
+///
+/// The player playing on this machine, as the other
+/// players see them
+pub struct LocalPlayer<'a> {
+    /// The name of the player
+    pub name: &'a str,
+    /// The game mode of the player
+    pub game_mode: GameMode,
+    /// The dimension the player is in
+    pub dimension: &'a str,
+    /// The camera of the player
+    pub camera: &'a Camera,
+    /// Whether the player sneaks
+    pub sneaking: bool,
+}
+
+/// Network
+///
+/// Hosts the world for other players, if a listen address
+/// is set, and keeps the status of the players with the
+/// columns scripts added to the player list
+pub struct Network {
+    /// The server hosting the world for other players, if
+    /// a listen address is set
+    server: Option<ListenServer>,
+    /// The columns scripts added to the player list
+    columns: PlayerListColumns,
+    /// The most recent status of the players
+    player_list: Arc<PlayerList>,
+    /// The time since the status of the players has been
+    /// updated the last time in seconds
+    since_status: f32,
+    /// The time the player's input takes to reach the simulation
+    latency: Latency,
+    /// The skins of the players shown in the world
+    skins: Skins,
+}
+
+impl Network {
+    /// Creates a new network subsystem and registers the
+    /// player list API in the given script engine. The world
+    /// isn't hosted until the server listens.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        Ok(Self {
+            server: None,
+            columns: PlayerListColumns::new(engine)?,
+            player_list: Arc::new(PlayerList::default()),
+            // The status is sent with the first update
+            since_status: STATUS_INTERVAL,
+            latency: Latency::default(),
+            skins: Skins::default(),
+        })
+    }
+
+    /// Hosts the world for other players. An error is
+    /// logged if the address can't be listened on.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address other players connect to
+    /// * `host` - The name of the local player
+    /// * `dimension` - The dimension the local player is in
+    pub fn listen(&mut self, address: &str, host: &str, dimension: &str) {
+        self.server = ListenServer::bind(address, host, dimension)
+            .map_err(|error| log::error!("Failed to listen for players on {}: {}", address, error))
+            .ok();
+    }
+
+    /// Records the time the player's input took to reach
+    /// the simulation
+    ///
+    /// # Arguments
+    ///
+    /// * `latency` - The time since the input has been captured
+    pub fn record_latency(&mut self, latency: Duration) {
+        self.latency.record(latency);
+    }
+
+    /// Updates the status of the players, handles the players
+    /// joining and leaving the server, if the world is hosted,
+    /// and sends them the changed blocks, the chunks around the
+    /// local player and where they are
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    /// * `engine` - The script engine running the mods
+    /// * `local` - The local player
+    /// * `changes` - The blocks changed during the update
+    /// * `chunks` - The chunks loaded around the local player
+    /// * `notifications` - The notifications shown to the player
+    pub fn update(
+        &mut self,
+        time_step: TimeStep,
+        engine: &ScriptEngine,
+        local: &LocalPlayer,
+        changes: &[BlockEdit],
+        chunks: &[Chunk],
+        notifications: &mut NotificationManager,
+    ) {
+        self.update_status(time_step, engine, local.name, local.game_mode, local.dimension);
+        let server = match self.server.as_mut() {
+            Some(server) => server,
+            None => return,
+        };
+        for event in server.poll() {
+            let title = match event {
+                ServerEvent::Joined(name) => format!("{} joined the game", name),
+                ServerEvent::Left(name) => format!("{} left the game", name),
+            };
+            log::info!("{}", title);
+            notifications.push(Notification::new(NotificationKind::Info, title, ""));
+        }
+        server.set_dimension(local.dimension);
+        server.send_changes(changes);
+        server.send_chunks(chunks);
+        server.send_host_position(*local.camera.pos(), local.camera.look(), local.sneaking);
+    }
+
+    /// Updates the status of the players the simulation hosts,
+    /// the local player and the ones connected to the server,
+    /// with the values of the columns scripts added, once the
+    /// status interval passed
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    /// * `engine` - The script engine running the mods
+    /// * `host` - The name of the local player
+    /// * `game_mode` - The game mode of the local player
+    /// * `dimension` - The dimension the local player is in
+    fn update_status(&mut self, time_step: TimeStep, engine: &ScriptEngine, host: &str, game_mode: GameMode, dimension: &str) {
+        self.since_status += time_step.0;
+        if self.since_status < STATUS_INTERVAL {
+            return;
+        }
+        self.since_status = 0.0;
+
+        let (columns, errors) = self.columns.values(engine, host);
+        for error in errors {
+            log::warn!("Failed to run {}", error);
+        }
+        let mut players = vec![PlayerEntry {
+            name: host.to_string(),
+            ping: self.latency.millis(),
+            game_mode,
+            dimension: dimension.to_string(),
+            columns,
+        }];
+        for player in self.server.iter().flat_map(ListenServer::players) {
+            let (columns, errors) = self.columns.values(engine, &player.name);
+            for error in errors {
+                log::warn!("Failed to run {}", error);
+            }
+            players.push(PlayerEntry {
+                name: player.name.clone(),
+                ping: 0,
+                game_mode: GameMode::Survival,
+                dimension: dimension.to_string(),
+                columns,
+            });
+        }
+        self.player_list = Arc::new(PlayerList {
+            columns: self.columns.titles(),
+            players,
+        });
+    }
+
+    /// Returns the other players which are shown in the world,
+    /// which are the players connected to the server. While
+    /// spectating a replay, the recorded player is shown where
+    /// the recorded camera is.
+    ///
+    /// # Arguments
+    ///
+    /// * `save_dir` - The directory the skins are loaded from
+    /// * `host` - The name of the local player
+    /// * `spectated` - The recorded camera of the replay the
+    ///   local player spectates, if any
+    pub fn remote_players(&mut self, save_dir: &SaveDir, host: &str, spectated: Option<&Camera>) -> Vec<RemotePlayer> {
+        let mut players = Vec::new();
+        for player in self.server.iter().flat_map(ListenServer::players) {
+            players.push(RemotePlayer {
+                name: player.name.clone(),
+                eye: player.eye.into(),
+                look: player.look.into(),
+                sneaking: player.sneaking,
+                skin: self.skins.get(save_dir, &player.name),
+            });
+        }
+
+        if let Some(camera) = spectated {
+            players.push(RemotePlayer {
+                name: host.to_string(),
+                eye: *camera.pos(),
+                look: camera.look(),
+                sneaking: false,
+                skin: self.skins.get(save_dir, host),
+            });
+        }
+        players
+    }
+
+    /// Returns the most recent status of the players
+    pub fn player_list(&self) -> &Arc<PlayerList> {
+        &self.player_list
+    }
+
+    /// Returns the amount of players in the world, the local
+    /// player and the ones connected to the server
+    pub fn player_count(&self) -> usize {
+        1 + self.server.as_ref().map_or(0, ListenServer::len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(network: &Network) -> Vec<&str> {
+        network.player_list().players.iter().map(|player| player.name.as_str()).collect()
+    }
+
+    #[test]
+    fn the_status_is_updated_once_the_interval_passed() {
+        let engine = ScriptEngine::new();
+        let mut network = Network::new(&engine).unwrap();
+        assert_eq!(network.player_count(), 1);
+
+        // The status is updated with the first update
+        network.update_status(TimeStep(0.0), &engine, "host", GameMode::Survival, "overworld");
+        assert_eq!(names(&network), vec!["host"]);
+
+        network.update_status(TimeStep(STATUS_INTERVAL / 2.0), &engine, "renamed", GameMode::Survival, "overworld");
+        assert_eq!(names(&network), vec!["host"]);
+
+        network.update_status(TimeStep(STATUS_INTERVAL / 2.0), &engine, "renamed", GameMode::Spectator, "overworld");
+        assert_eq!(names(&network), vec!["renamed"]);
+        assert_eq!(network.player_list().players[0].game_mode, GameMode::Spectator);
+    }
+}
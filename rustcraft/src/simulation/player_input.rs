@@ -0,0 +1,201 @@
+//! The subsystem of the simulation applying the input
+//! of the player, e.g. their controls and key bindings
+
+use crate::camera::PerspectiveCamera;
+use crate::entity::Controls;
+use crate::input::{self, InputState, SharedKeyMap};
+use crate::keybindings::{Actions, KeyBindingsMenu, KeyBindingsView, KeyOverrides};
+use crate::scripting::ScriptEngine;
+use crate::world::block::Material;
+
+/// Movement
+///
+/// How the player's input moves them with an update
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Movement {
+    /// The player walks, swims or flies as the controls say
+    Walk(Controls),
+    /// The player rides a vehicle, which they steer
+    Ride,
+    /// The player gets off the vehicle they ride
+    Dismount,
+}
+
+/// PlayerInput
+///
+/// Accumulates the input of the player between the updates
+/// and keeps the block they place and the keys the actions
+/// of scripts are bound to
+pub struct PlayerInput {
+    /// The accumulated input since the last update
+    state: InputState,
+    /// The material of the blocks the player places
+    selected_material: Material,
+    /// The actions scripts bound to keys
+    actions: Actions,
+    /// The menu listing the actions of scripts
+    key_bindings: KeyBindingsMenu,
+}
+
+impl PlayerInput {
+    /// Creates a new player input and registers the key bindings
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        Ok(Self {
+            state: InputState::default(),
+            selected_material: Material::Grass,
+            actions: Actions::new(engine, SharedKeyMap::default())?,
+            key_bindings: KeyBindingsMenu::default(),
+        })
+    }
+
+    /// Binds the actions to the keys the player chose
+    ///
+    /// # Arguments
+    ///
+    /// * `overrides` - The keys the player bound the actions to
+    pub fn set_key_overrides(&self, overrides: KeyOverrides) {
+        self.actions.set_overrides(overrides);
+    }
+
+    /// Returns the keys the controls and the actions of
+    /// scripts are bound to
+    pub fn key_map(&self) -> SharedKeyMap {
+        self.actions.key_map()
+    }
+
+    /// Adds the input captured by the render thread
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The newer input
+    pub fn merge(&mut self, input: InputState) {
+        self.state.merge(input);
+    }
+
+    /// Returns the accumulated input
+    pub fn state(&self) -> &InputState {
+        &self.state
+    }
+
+    /// Returns the accumulated input, e.g. for a replay to
+    /// replace it with the recorded one
+    pub fn state_mut(&mut self) -> &mut InputState {
+        &mut self.state
+    }
+
+    /// Turns the camera as the mouse moved and returns how the
+    /// player moves. The mouse movement is consumed either way.
+    ///
+    /// # Arguments
+    ///
+    /// * `camera` - The camera of the player
+    /// * `steering` - Whether the input controls the player,
+    ///   which it doesn't while they type or can't move
+    /// * `riding` - Whether the player rides a vehicle
+    pub fn update(&mut self, camera: &mut PerspectiveCamera, steering: bool, riding: bool) -> Movement {
+        let mut movement = if riding { Movement::Ride } else { Movement::Walk(Controls::default()) };
+        if steering {
+            input::handle_mouse_input(&self.state, camera);
+            // The keys moving the player steer the vehicle they ride
+            if !riding {
+                movement = Movement::Walk(input::controls(&self.state, camera));
+            } else if input::is_dismounting(&self.state) {
+                movement = Movement::Dismount;
+            }
+        }
+        self.state.reset_mouse_delta();
+        movement
+    }
+
+    /// Returns the material of the blocks the player places
+    pub fn selected_material(&self) -> Material {
+        self.selected_material
+    }
+
+    /// Selects the block the player places. Returns the
+    /// material, unless there's none to place at the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the material in `Material::ALL`
+    pub fn select_material(&mut self, index: usize) -> Option<Material> {
+        let material = Material::ALL.get(index).copied().filter(|&material| material != Material::Air)?;
+        self.selected_material = material;
+        Some(material)
+    }
+
+    /// Opens or closes the key bindings menu
+    pub fn toggle_key_bindings(&mut self) {
+        self.key_bindings.toggle();
+    }
+
+    /// Moves the selection of the key bindings menu
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - The number of actions to move by
+    pub fn select_key_binding(&mut self, delta: i32) {
+        self.key_bindings.select(delta, self.actions.bindings().len());
+    }
+
+    /// Waits for the key the selected action is bound
+    /// to, or stops waiting for it
+    pub fn toggle_rebinding(&mut self) {
+        self.key_bindings.toggle_rebinding();
+    }
+
+    /// Binds the control or action selected in the key bindings
+    /// menu to the pressed key, if the menu waits for one, or
+    /// triggers the action bound to the key otherwise. Returns
+    /// the keys the player bound the actions to, if they
+    /// changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine running the mods
+    /// * `key` - The name of the pressed key
+    /// * `player` - The name of the player
+    pub fn press_key(&mut self, engine: &ScriptEngine, key: &str, player: &str) -> Option<KeyOverrides> {
+        if self.key_bindings.is_open() {
+            let bindings = self.actions.bindings();
+            let name = self.key_bindings.take_rebinding(&bindings)?;
+            self.actions.bind(name, key);
+            return Some(self.actions.overrides());
+        }
+        for error in self.actions.press(engine, key, player) {
+            log::warn!("Failed to run {}", error);
+        }
+        None
+    }
+
+    /// Returns the key bindings menu as it's shown,
+    /// or `None` if it's closed
+    pub fn key_bindings_view(&self) -> Option<KeyBindingsView> {
+        self.key_bindings.view(self.actions.bindings())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_placeable_materials_are_selected() {
+        let mut input = PlayerInput::new(&ScriptEngine::new()).unwrap();
+        assert_eq!(input.selected_material(), Material::Grass);
+
+        let air = Material::ALL.iter().position(|&material| material == Material::Air).unwrap();
+        assert_eq!(input.select_material(air), None);
+        assert_eq!(input.select_material(Material::ALL.len()), None);
+        assert_eq!(input.selected_material(), Material::Grass);
+
+        let stone = Material::ALL.iter().position(|&material| material == Material::Stone).unwrap();
+        assert_eq!(input.select_material(stone), Some(Material::Stone));
+        assert_eq!(input.selected_material(), Material::Stone);
+    }
+}
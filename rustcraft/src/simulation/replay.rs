@@ -0,0 +1,273 @@
+//! The subsystem of the simulation recording the session
+//! of the player and replaying it
+
+use crate::camera::{Camera, PerspectiveCamera};
+use crate::input::{self, InputState};
+use crate::notification::{Notification, NotificationKind, NotificationManager};
+use crate::replay::{Recording, Replay};
+use crate::simulation::UPDATES_PER_SECOND;
+use crate::timestep::TimeStep;
+use crate::world::history::BlockEdit;
+use crate::world::universe::Universe;
+
+/// Replays
+///
+/// Records the session of the player and replays the most
+/// recent recording. While a replay runs, the world is driven
+/// by the recorded block changes.
+#[derive(Default)]
+pub struct Replays {
+    /// The session which is currently recorded
+    recording: Option<Recording>,
+    /// The most recently finished recording
+    last_recording: Option<Recording>,
+    /// The replay which is currently running
+    replay: Option<Replay>,
+    /// The camera of the player before the replay started
+    player_camera: Option<Camera>,
+}
+
+impl Replays {
+    /// Returns whether a replay is running
+    pub fn is_running(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Returns the recorded camera of the running replay while
+    /// the player spectates it, which is where the recorded
+    /// player is shown
+    pub fn spectated_camera(&self) -> Option<&Camera> {
+        let replay = self.replay.as_ref().filter(|replay| replay.is_spectating())?;
+        replay.current().map(|tick| &tick.camera)
+    }
+
+    /// Advances the running replay by a single tick. The camera
+    /// either follows the recorded camera or, while spectating,
+    /// is moved by the player's input. The replay is stopped
+    /// once it's finished.
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    /// * `universe` - The universe the recorded changes are applied to
+    /// * `camera` - The camera of the player
+    /// * `input` - The accumulated input of the player
+    /// * `notifications` - The notifications shown to the player
+    pub fn update(
+        &mut self,
+        time_step: TimeStep,
+        universe: &mut Universe,
+        camera: &mut PerspectiveCamera,
+        input: &mut InputState,
+        notifications: &mut NotificationManager,
+    ) {
+        let replay = match self.replay.as_mut() {
+            Some(replay) => replay,
+            None => return,
+        };
+
+        if let Some(tick) = replay.advance() {
+            for edit in tick.edits.iter() {
+                universe.set_block(edit.pos, edit.new);
+            }
+        }
+
+        if replay.is_spectating() {
+            input::handle_mouse_input(input, camera);
+            input::handle_key_input(time_step, input, camera);
+        } else if let Some(tick) = replay.current() {
+            **camera = tick.camera.clone();
+        }
+        input.reset_mouse_delta();
+
+        if replay.is_finished() {
+            self.stop(universe, camera, notifications);
+        }
+    }
+
+    /// Records the camera and the block changes of an update,
+    /// if the session is recorded. The recording is stopped
+    /// once it's full.
+    ///
+    /// # Arguments
+    ///
+    /// * `camera` - The camera of the player
+    /// * `changes` - The blocks changed during the update
+    /// * `notifications` - The notifications shown to the player
+    pub fn record(&mut self, camera: &Camera, changes: Vec<BlockEdit>, notifications: &mut NotificationManager) {
+        if let Some(recording) = self.recording.as_mut() {
+            if !recording.record(camera, changes) {
+                self.toggle_recording(notifications);
+            }
+        }
+    }
+
+    /// Starts recording the session, or stops the current
+    /// recording and keeps it for the next replay
+    ///
+    /// # Arguments
+    ///
+    /// * `notifications` - The notifications shown to the player
+    pub fn toggle_recording(&mut self, notifications: &mut NotificationManager) {
+        match self.recording.take() {
+            Some(recording) => {
+                let seconds = recording.len() as u32 / UPDATES_PER_SECOND;
+                notifications.push(Notification::new(
+                    NotificationKind::Info,
+                    "Recording stopped",
+                    format!("Recorded {} seconds", seconds),
+                ));
+                self.last_recording = Some(recording);
+            }
+            None if self.replay.is_none() => {
+                self.recording = Some(Recording::new());
+                notifications.push(Notification::new(NotificationKind::Info, "Recording", ""));
+            }
+            None => {}
+        }
+    }
+
+    /// Stops the current recording, if the session is recorded
+    ///
+    /// # Arguments
+    ///
+    /// * `notifications` - The notifications shown to the player
+    pub fn stop_recording(&mut self, notifications: &mut NotificationManager) {
+        if self.recording.is_some() {
+            self.toggle_recording(notifications);
+        }
+    }
+
+    /// Replays the most recent recording, or stops the current
+    /// replay. The block changes of the recording are reverted
+    /// before the replay starts, and all of them are applied
+    /// again when it stops, no matter how far it got.
+    ///
+    /// # Arguments
+    ///
+    /// * `universe` - The universe the recorded changes are applied to
+    /// * `camera` - The camera of the player
+    /// * `notifications` - The notifications shown to the player
+    pub fn toggle(&mut self, universe: &mut Universe, camera: &mut Camera, notifications: &mut NotificationManager) {
+        if self.replay.is_some() {
+            self.stop(universe, camera, notifications);
+            return;
+        }
+
+        self.stop_recording(notifications);
+        let recording = match self.last_recording.take() {
+            Some(recording) if !recording.is_empty() => recording,
+            _ => {
+                notifications.push(Notification::new(NotificationKind::Warning, "Nothing to replay", "Press F9 to record"));
+                return;
+            }
+        };
+
+        for edit in recording.edits().rev() {
+            universe.set_block(edit.pos, edit.old);
+        }
+        self.player_camera = Some(camera.clone());
+        self.replay = Some(Replay::new(recording));
+        notifications.push(Notification::new(NotificationKind::Info, "Replaying", "Press C to spectate"));
+    }
+
+    /// Stops the running replay, applies the remaining block
+    /// changes and gives the camera back to the player
+    ///
+    /// # Arguments
+    ///
+    /// * `universe` - The universe the recorded changes are applied to
+    /// * `camera` - The camera of the player
+    /// * `notifications` - The notifications shown to the player
+    fn stop(&mut self, universe: &mut Universe, camera: &mut Camera, notifications: &mut NotificationManager) {
+        let replay = match self.replay.take() {
+            Some(replay) => replay,
+            None => return,
+        };
+        for tick in replay.remaining() {
+            for edit in tick.edits.iter() {
+                universe.set_block(edit.pos, edit.new);
+            }
+        }
+        if let Some(player_camera) = self.player_camera.take() {
+            *camera = player_camera;
+        }
+        self.last_recording = Some(replay.into_recording());
+        notifications.push(Notification::new(NotificationKind::Info, "Replay stopped", ""));
+    }
+
+    /// Detaches the camera from the recorded camera of the
+    /// running replay, or attaches it again
+    ///
+    /// # Arguments
+    ///
+    /// * `notifications` - The notifications shown to the player
+    pub fn toggle_spectating(&mut self, notifications: &mut NotificationManager) {
+        if let Some(replay) = self.replay.as_mut() {
+            let title = if replay.toggle_spectating() { "Spectating" } else { "Following the recording" };
+            notifications.push(Notification::new(NotificationKind::Info, title, ""));
+        }
+    }
+
+    /// Pauses or resumes the running replay
+    pub fn toggle_paused(&mut self) {
+        if let Some(replay) = self.replay.as_mut() {
+            replay.toggle_paused();
+        }
+    }
+
+    /// Skips the running replay forward
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The seconds to skip
+    /// * `universe` - The universe the recorded changes are applied to
+    pub fn scrub(&mut self, seconds: u32, universe: &mut Universe) {
+        if let Some(replay) = self.replay.as_mut() {
+            for tick in replay.skip((seconds * UPDATES_PER_SECOND) as usize) {
+                for edit in tick.edits.iter() {
+                    universe.set_block(edit.pos, edit.new);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scripting::ScriptEngine;
+    use crate::world::universe::OVERWORLD;
+    use crate::world::World;
+    use cgmath::Vector3;
+
+    #[test]
+    fn a_replay_takes_the_camera_and_gives_it_back() {
+        let engine = ScriptEngine::new();
+        let mut notifications = NotificationManager::new(&engine).unwrap();
+        let mut universe = Universe::new(OVERWORLD, World::new());
+        let mut camera = Camera::default();
+        let mut replays = Replays::default();
+
+        // Nothing has been recorded yet
+        replays.toggle(&mut universe, &mut camera, &mut notifications);
+        assert!(!replays.is_running());
+
+        replays.toggle_recording(&mut notifications);
+        replays.record(&camera, Vec::new(), &mut notifications);
+        camera.set_pos(Vector3::new(5.0, 0.0, 0.0));
+        replays.toggle(&mut universe, &mut camera, &mut notifications);
+        assert!(replays.is_running());
+        assert!(replays.recording.is_none());
+
+        // No session is recorded while it's replayed
+        replays.toggle_recording(&mut notifications);
+        assert!(replays.recording.is_none());
+
+        camera.set_pos(Vector3::new(0.0, 0.0, 0.0));
+        replays.toggle(&mut universe, &mut camera, &mut notifications);
+        assert!(!replays.is_running());
+        assert_eq!(*camera.pos(), Vector3::new(5.0, 0.0, 0.0));
+        assert_eq!(replays.last_recording.as_ref().map(Recording::len), Some(1));
+    }
+}
@@ -0,0 +1,274 @@
+//! The subsystem of the simulation keeping the world on disk
+//! and the blocks of idle chunks in their storage tiers
+
+use crate::backup::Backups;
+use crate::notification::{Notification, NotificationKind, NotificationManager};
+use crate::server::ServerSettings;
+use crate::timestep::TimeStep;
+use crate::world::chunk::CHUNK_SIZE;
+use crate::world::edit_log;
+use crate::world::pregen::{self, PregenJob};
+use crate::world::save::SaveDir;
+use crate::world::tiers::TierSettings;
+use crate::world::universe::Universe;
+
+use cgmath::{Vector2, Vector3};
+
+/// Upkeep
+///
+/// What the simulation is due to do after an update of
+/// the storage, as it owns everything which is saved
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Upkeep {
+    /// Whether the world should be saved
+    pub save: bool,
+    /// Whether the world should be backed up
+    pub backup: bool,
+}
+
+/// Storage
+///
+/// Compresses and evicts the blocks of idle chunks, keeps
+/// track of when the world is due to be saved and backed up,
+/// and drives the pre-generation of chunks
+pub struct Storage {
+    /// The backups of the world
+    backups: Backups,
+    /// The time since the world has been saved the last time
+    /// in seconds
+    since_save: f32,
+    /// The time since the world has been backed up the last
+    /// time in seconds
+    since_backup: f32,
+    /// The chunks which are currently pre-generated
+    pregen: Option<PregenJob>,
+    /// The time since the progress of the pre-generation
+    /// has been reported the last time in seconds
+    since_pregen_report: f32,
+}
+
+impl Storage {
+    /// Creates a new storage of a world
+    ///
+    /// # Arguments
+    ///
+    /// * `save_dir` - The directory the world is saved in
+    pub fn new(save_dir: &SaveDir) -> Self {
+        Self {
+            backups: Backups::new(save_dir),
+            since_save: 0.0,
+            since_backup: 0.0,
+            pregen: None,
+            since_pregen_report: 0.0,
+        }
+    }
+
+    /// Moves the blocks of idle chunks of all dimensions to
+    /// their next tier, drives the pre-generation of chunks and
+    /// returns whether the world is due to be saved or backed up
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    /// * `universe` - The universe
+    /// * `save_dir` - The directory the world is saved in
+    /// * `settings` - The settings of the simulation
+    /// * `tps` - The current ticks per second
+    /// * `notifications` - The notifications shown to the player
+    pub fn update(
+        &mut self,
+        time_step: TimeStep,
+        universe: &mut Universe,
+        save_dir: &SaveDir,
+        settings: &ServerSettings,
+        tps: f32,
+        notifications: &mut NotificationManager,
+    ) -> Upkeep {
+        let tiers = TierSettings {
+            compress_after: settings.compress_after,
+            evict_after: settings.evict_after,
+        };
+        for (name, world) in universe.dimensions_mut() {
+            let (compressed, evicted) = world.update_tiers(time_step.0, tiers);
+            if compressed + evicted > 0 {
+                log::debug!("Compressed {} and evicted {} idle chunks of {}", compressed, evicted, name);
+            }
+        }
+
+        self.update_pregen(time_step, universe, save_dir, settings, tps, notifications);
+
+        self.since_save += time_step.0;
+        self.since_backup += time_step.0;
+        Upkeep {
+            save: settings.autosave_interval > 0.0 && self.since_save >= settings.autosave_interval,
+            backup: settings.backup_interval > 0.0 && self.since_backup >= settings.backup_interval,
+        }
+    }
+
+    /// Records that the world has been saved
+    pub fn saved(&mut self) {
+        self.since_save = 0.0;
+    }
+
+    /// Snapshots the saved world, so the backup never contains
+    /// a file which is written in the meantime. The oldest
+    /// backups beyond the retention are removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `kept` - The amount of backups which are kept
+    /// * `notifications` - The notifications shown to the player
+    pub fn backup(&mut self, kept: usize, notifications: &mut NotificationManager) {
+        self.since_backup = 0.0;
+        match self.backups.snapshot(edit_log::unix_time()) {
+            Ok(backup) => {
+                log::info!("Backed up the world to {}", backup.name);
+                notifications.push(Notification::new(NotificationKind::Info, "World backed up", &backup.name));
+            }
+            Err(error) => {
+                log::error!("Failed to back up the world: {}", error);
+                notifications.push(Notification::new(NotificationKind::Error, "Failed to back up the world", ""));
+                return;
+            }
+        }
+        match self.backups.prune(kept) {
+            Ok(0) => {}
+            Ok(removed) => log::info!("Removed {} old backups", removed),
+            Err(error) => log::error!("Failed to remove old backups: {}", error),
+        }
+    }
+
+    /// Starts pre-generating the chunks around the player in
+    /// the dimension they are in
+    ///
+    /// # Arguments
+    ///
+    /// * `radius` - The amount of chunks in each direction of the player
+    /// * `universe` - The universe
+    /// * `pos` - The position of the player
+    /// * `notifications` - The notifications shown to the player
+    pub fn pregenerate(&mut self, radius: i32, universe: &Universe, pos: Vector3<f32>, notifications: &mut NotificationManager) {
+        if self.pregen.is_some() {
+            notifications.push(Notification::new(NotificationKind::Warning, "Chunks are already pre-generated", ""));
+            return;
+        }
+
+        let size = CHUNK_SIZE as f32;
+        let center = Vector2::new((pos.x / size).floor() as i32, (pos.z / size).floor() as i32);
+        let radius = radius.clamp(0, pregen::MAX_RADIUS);
+        let job = PregenJob::new(universe.active_name(), center, radius, universe.border().copied());
+        let (_, total) = job.progress();
+        log::info!("Pre-generating {} chunks of {} around chunk {}, {}", total, job.dimension(), center.x, center.y);
+        notifications.push(Notification::new(NotificationKind::Info, "Pre-generating chunks", format!("{} chunks", total)));
+        self.pregen = Some(job);
+        self.since_pregen_report = 0.0;
+    }
+
+    /// Drives the pre-generation of chunks and reports its
+    /// progress. No further chunks are started while the
+    /// ticks per second are below the minimum of the settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    /// * `universe` - The universe
+    /// * `save_dir` - The directory the world is saved in
+    /// * `settings` - The settings of the simulation
+    /// * `tps` - The current ticks per second
+    /// * `notifications` - The notifications shown to the player
+    fn update_pregen(
+        &mut self,
+        time_step: TimeStep,
+        universe: &Universe,
+        save_dir: &SaveDir,
+        settings: &ServerSettings,
+        tps: f32,
+        notifications: &mut NotificationManager,
+    ) {
+        let job = match self.pregen.as_mut() {
+            Some(job) => job,
+            None => return,
+        };
+        let world = match universe.dimension(job.dimension()) {
+            Some(world) => world,
+            None => {
+                self.pregen = None;
+                return;
+            }
+        };
+
+        let tasks = if tps > 0.0 && tps < settings.pregen_min_tps {
+            0
+        } else {
+            settings.pregen_tasks
+        };
+        let result = save_dir.dimension(job.dimension())
+            .and_then(|dir| job.update(world, &dir, tasks));
+        let (done, total) = job.progress();
+        if let Err(error) = result {
+            log::error!("Failed to pre-generate chunks: {:?}", error);
+            notifications.push(Notification::new(NotificationKind::Error, "Failed to pre-generate chunks", ""));
+            self.pregen = None;
+            return;
+        }
+        if job.is_finished() {
+            let seconds = job.elapsed().as_secs_f32();
+            log::info!("Pre-generated {} chunks in {:.1} s", total, seconds);
+            notifications.push(Notification::new(NotificationKind::Info, "Chunks pre-generated", format!("{} chunks in {:.0} s", total, seconds)));
+            self.pregen = None;
+            return;
+        }
+
+        self.since_pregen_report += time_step.0;
+        if self.since_pregen_report >= pregen::REPORT_INTERVAL {
+            self.since_pregen_report = 0.0;
+            let percent = done * 100 / total.max(1);
+            log::info!("Pre-generated {} of {} chunks ({}%)", done, total, percent);
+            notifications.push(Notification::new(NotificationKind::Info, "Pre-generating chunks", format!("{} of {} chunks ({}%)", done, total, percent)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scripting::ScriptEngine;
+    use crate::world::universe::OVERWORLD;
+    use crate::world::World;
+    use std::fs;
+
+    #[test]
+    fn the_world_is_due_to_be_saved_and_backed_up() {
+        let engine = ScriptEngine::new();
+        let mut notifications = NotificationManager::new(&engine).unwrap();
+        let mut universe = Universe::new(OVERWORLD, World::new());
+        let path = std::env::temp_dir().join(format!("rustcraft-storage-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        let save_dir = SaveDir::new(path.clone()).unwrap();
+        let settings = ServerSettings {
+            autosave_interval: 10.0,
+            backup_interval: 20.0,
+            ..ServerSettings::default()
+        };
+        let mut storage = Storage::new(&save_dir);
+        let mut update = |storage: &mut Storage, seconds| {
+            storage.update(TimeStep(seconds), &mut universe, &save_dir, &settings, 60.0, &mut notifications)
+        };
+
+        assert_eq!(update(&mut storage, 6.0), Upkeep { save: false, backup: false });
+        assert_eq!(update(&mut storage, 6.0), Upkeep { save: true, backup: false });
+        storage.saved();
+        assert_eq!(update(&mut storage, 6.0), Upkeep { save: false, backup: false });
+        assert_eq!(update(&mut storage, 6.0), Upkeep { save: true, backup: true });
+
+        // The world is never due with an interval of 0
+        let settings = ServerSettings {
+            autosave_interval: 0.0,
+            backup_interval: 0.0,
+            ..ServerSettings::default()
+        };
+        let upkeep = storage.update(TimeStep(60.0), &mut universe, &save_dir, &settings, 60.0, &mut notifications);
+        assert_eq!(upkeep, Upkeep::default());
+        let _ = fs::remove_dir_all(&path);
+    }
+}
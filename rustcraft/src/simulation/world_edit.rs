@@ -0,0 +1,272 @@
+//! The subsystem of the simulation editing a selected
+//! region of blocks at once
+
+use crate::camera::Camera;
+use crate::notification::{Notification, NotificationKind, NotificationManager};
+use crate::scripting::ScriptEngine;
+use crate::world::block::Material;
+use crate::world::brush::{Brush, BRUSH_REACH};
+use crate::world::placement::REACH_DISTANCE;
+use crate::world::raycast;
+use crate::world::selection::{Clipboard, EditCommand, Selection, WorldEdit, MAX_VOLUME};
+use crate::world::universe::Universe;
+
+use cgmath::Vector3;
+
+/// WorldEditor
+///
+/// Keeps the region the world edit commands edit, the blocks
+/// copied last and the brush of the brush tool. The editor
+/// only works out which blocks change, the simulation changes
+/// them so that protected blocks are spared.
+pub struct WorldEditor {
+    /// The commands of the world edit API
+    api: WorldEdit,
+    /// The region the world edit commands edit
+    selection: Selection,
+    /// The blocks which have been copied last
+    clipboard: Option<Clipboard>,
+    /// The brush the brush tool applies
+    brush: Option<Brush>,
+}
+
+impl WorldEditor {
+    /// Creates a new world editor and registers the world
+    /// edit API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        Ok(Self {
+            api: WorldEdit::new(engine)?,
+            selection: Selection::default(),
+            clipboard: None,
+            brush: None,
+        })
+    }
+
+    /// Takes the commands scripts issued through the
+    /// `worldedit` API since the last call
+    pub fn take_commands(&self) -> Vec<EditCommand> {
+        self.api.take_commands()
+    }
+
+    /// Returns the lowest and the highest block of the
+    /// selection as it's outlined, if any
+    pub fn outline(&self) -> Option<(Vector3<i32>, Vector3<i32>)> {
+        self.selection.outline()
+    }
+
+    /// Removes both corners of the selection
+    pub fn clear_selection(&mut self) {
+        self.selection.clear();
+    }
+
+    /// Selects a corner of the region the world edit commands
+    /// edit. The player is notified unless it's selected already.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the corner, or `None` for the
+    ///   block the player is looking at
+    /// * `first` - Whether the first or the second corner is selected
+    /// * `universe` - The universe
+    /// * `camera` - The camera of the player
+    /// * `notifications` - The notifications shown to the player
+    pub fn select_corner(
+        &mut self,
+        pos: Option<Vector3<i32>>,
+        first: bool,
+        universe: &Universe,
+        camera: &Camera,
+        notifications: &mut NotificationManager,
+    ) {
+        let target = || raycast::raycast(universe, *camera.pos(), camera.look(), REACH_DISTANCE).map(|hit| hit.block);
+        let pos = match pos.or_else(target) {
+            Some(pos) => pos,
+            None => {
+                notifications.push(Notification::new(NotificationKind::Warning, "No block selected", "Look at a block within reach"));
+                return;
+            }
+        };
+        let corner = if first { &mut self.selection.first } else { &mut self.selection.second };
+        if *corner == Some(pos) {
+            return;
+        }
+        *corner = Some(pos);
+
+        let title = format!("{} corner set to {}, {}, {}", if first { "First" } else { "Second" }, pos.x, pos.y, pos.z);
+        let message = match self.selection.volume() {
+            0 => String::new(),
+            volume => format!("{} blocks selected", volume),
+        };
+        notifications.push(Notification::new(NotificationKind::Info, title, message));
+    }
+
+    /// Returns whether the selection could be edited at once,
+    /// otherwise the player is notified why it can't
+    ///
+    /// # Arguments
+    ///
+    /// * `notifications` - The notifications shown to the player
+    fn check_selection(&self, notifications: &mut NotificationManager) -> bool {
+        let volume = self.selection.volume();
+        if volume == 0 {
+            notifications.push(Notification::new(NotificationKind::Warning, "Nothing selected", "Select two corners with the wand first"));
+            return false;
+        }
+        if volume > MAX_VOLUME {
+            let message = format!("{} blocks are selected, at most {} could be edited", volume, MAX_VOLUME);
+            notifications.push(Notification::new(NotificationKind::Warning, "The selection is too large", message));
+            return false;
+        }
+        true
+    }
+
+    /// Returns the edits replacing all selected blocks, unless
+    /// the selection can't be edited at once
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The new material of the blocks
+    /// * `notifications` - The notifications shown to the player
+    pub fn fill(&self, material: Material, notifications: &mut NotificationManager) -> Option<Vec<(Vector3<i32>, Material)>> {
+        if !self.check_selection(notifications) {
+            return None;
+        }
+        Some(self.selection.positions().into_iter().map(|pos| (pos, material)).collect())
+    }
+
+    /// Copies the selected blocks, so they could be pasted
+    ///
+    /// # Arguments
+    ///
+    /// * `universe` - The universe the blocks are copied from
+    /// * `notifications` - The notifications shown to the player
+    pub fn copy(&mut self, universe: &Universe, notifications: &mut NotificationManager) {
+        if !self.check_selection(notifications) {
+            return;
+        }
+        match Clipboard::copy(universe, &self.selection) {
+            Ok(clipboard) => {
+                let title = format!("Copied {} blocks", clipboard.len());
+                notifications.push(Notification::new(NotificationKind::Info, title, ""));
+                self.clipboard = Some(clipboard);
+            }
+            Err(pos) => {
+                let message = format!("The block at {}, {}, {} isn't loaded", pos.x, pos.y, pos.z);
+                notifications.push(Notification::new(NotificationKind::Warning, "Failed to copy the selection", message));
+            }
+        }
+    }
+
+    /// Returns the edits pasting the copied blocks, if any
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the lowest pasted block, or `None`
+    ///   for the position in front of the block the player is looking at
+    /// * `universe` - The universe
+    /// * `camera` - The camera of the player
+    /// * `notifications` - The notifications shown to the player
+    pub fn paste(
+        &self,
+        pos: Option<Vector3<i32>>,
+        universe: &Universe,
+        camera: &Camera,
+        notifications: &mut NotificationManager,
+    ) -> Option<Vec<(Vector3<i32>, Material)>> {
+        let clipboard = match self.clipboard.as_ref() {
+            Some(clipboard) => clipboard,
+            None => {
+                notifications.push(Notification::new(NotificationKind::Warning, "Nothing copied", "Copy a selection first"));
+                return None;
+            }
+        };
+        let target = || raycast::raycast(universe, *camera.pos(), camera.look(), REACH_DISTANCE).map(|hit| hit.adjacent());
+        match pos.or_else(target) {
+            Some(origin) => Some(clipboard.paste(origin)),
+            None => {
+                notifications.push(Notification::new(NotificationKind::Warning, "No block selected", "Look at a block within reach"));
+                None
+            }
+        }
+    }
+
+    /// Changes the brush the brush tool applies
+    ///
+    /// # Arguments
+    ///
+    /// * `brush` - The new brush, or `None` to remove it
+    /// * `notifications` - The notifications shown to the player
+    pub fn set_brush(&mut self, brush: Option<Brush>, notifications: &mut NotificationManager) {
+        self.brush = brush;
+        let title = match brush {
+            Some(Brush::Sphere { material, radius }) => format!("Sphere brush of {} with radius {}", material.name(), radius),
+            Some(Brush::Cylinder { material, radius, height }) => {
+                format!("Cylinder brush of {} with radius {} and height {}", material.name(), radius, height)
+            }
+            Some(Brush::Smooth { radius }) => format!("Smooth brush with radius {}", radius),
+            None => "Brush removed".to_string(),
+        };
+        notifications.push(Notification::new(NotificationKind::Info, title, ""));
+    }
+
+    /// Returns the edits of a stroke of the brush around the
+    /// block the player is looking at. All blocks of a stroke
+    /// are changed at once, so the chunks are remeshed once and
+    /// the stroke is undone at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `universe` - The universe
+    /// * `camera` - The camera of the player
+    /// * `notifications` - The notifications shown to the player
+    pub fn brush_stroke(
+        &self,
+        universe: &Universe,
+        camera: &Camera,
+        notifications: &mut NotificationManager,
+    ) -> Option<Vec<(Vector3<i32>, Material)>> {
+        let brush = match self.brush {
+            Some(brush) => brush,
+            None => {
+                notifications.push(Notification::new(NotificationKind::Warning, "No brush set", "Set one with worldedit.brush first"));
+                return None;
+            }
+        };
+        let hit = raycast::raycast(universe, *camera.pos(), camera.look(), BRUSH_REACH)?;
+        Some(brush.edits(universe, hit.block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::universe::OVERWORLD;
+    use crate::world::World;
+
+    #[test]
+    fn only_a_complete_selection_is_filled() {
+        let engine = ScriptEngine::new();
+        let mut notifications = NotificationManager::new(&engine).unwrap();
+        let mut editor = WorldEditor::new(&engine).unwrap();
+        let universe = Universe::new(OVERWORLD, World::new());
+        let camera = Camera::default();
+
+        assert!(editor.fill(Material::Stone, &mut notifications).is_none());
+        editor.select_corner(Some(Vector3::new(0, 0, 0)), true, &universe, &camera, &mut notifications);
+        assert!(editor.fill(Material::Stone, &mut notifications).is_none());
+
+        editor.select_corner(Some(Vector3::new(1, 1, 1)), false, &universe, &camera, &mut notifications);
+        let edits = editor.fill(Material::Stone, &mut notifications).unwrap();
+        assert_eq!(edits.len(), 8);
+        assert!(edits.iter().all(|&(_, material)| material == Material::Stone));
+
+        editor.clear_selection();
+        assert_eq!(editor.outline(), None);
+        // Nothing has been copied, so nothing is pasted
+        assert!(editor.paste(Some(Vector3::new(0, 0, 0)), &universe, &camera, &mut notifications).is_none());
+    }
+}
@@ -0,0 +1,215 @@
+//! Types picking the sounds of blocks which are stepped on,
+//! swum through, broken or placed
+
+use crate::scripting::{self, ScriptEngine};
+use crate::world::block::{Material, SoundGroup};
+use cgmath::Vector3;
+use mlua::Table;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The distance in blocks the player walks per footstep
+const STEP_DISTANCE: f32 = 1.6;
+/// The distance in blocks the player swims per stroke
+const SWIM_DISTANCE: f32 = 2.5;
+/// The directory of the sounds within the resources
+const SOUNDS_PATH: &str = "sounds";
+
+/// SoundKind
+///
+/// What a sound is played for
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SoundKind {
+    /// The player stepped on a block
+    Footstep,
+    /// The player swam a stroke through water
+    Swim,
+    /// A block has been broken
+    Break,
+    /// A block has been placed
+    Place,
+    /// An entity took damage
+    Hurt,
+}
+
+impl SoundKind {
+    /// Returns the name of the sound file of the kind,
+    /// without its extension
+    pub fn name(&self) -> &'static str {
+        match self {
+            SoundKind::Footstep => "step",
+            SoundKind::Swim => "swim",
+            SoundKind::Break => "break",
+            SoundKind::Place => "place",
+            SoundKind::Hurt => "hurt",
+        }
+    }
+}
+
+/// SoundCue
+///
+/// A sound the simulation asks the audio to play
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SoundCue {
+    /// What the sound is played for
+    pub kind: SoundKind,
+    /// The group of the block the sound belongs to, or
+    /// `None` for the sounds of water and entities
+    pub group: Option<SoundGroup>,
+    /// The position the sound comes from
+    pub pos: Vector3<f32>,
+}
+
+impl SoundCue {
+    /// Returns the resource of the sound, e.g.
+    /// `sounds/grass/step.ogg`
+    pub fn resource(&self) -> String {
+        let group = match (self.kind, self.group) {
+            (SoundKind::Hurt, _) => "entity",
+            (_, Some(group)) => group.name(),
+            (_, None) => "water",
+        };
+        format!("{}/{}/{}.ogg", SOUNDS_PATH, group, self.kind.name())
+    }
+}
+
+/// SoundGroups
+///
+/// The sound groups of the blocks, which scripts could
+/// replace through the `blocks` API, e.g.
+///
+/// ```lua
+/// -- Glass sounds like stone unless it's replaced
+/// blocks.sounds { block = "glass", group = "sand" }
+/// ```
+///
+/// The groups are `stone`, `wood`, `grass` and `sand`.
+pub struct SoundGroups {
+    /// The groups replaced by scripts, shared with the Lua API
+    replaced: Arc<Mutex<HashMap<Material, SoundGroup>>>,
+}
+
+impl SoundGroups {
+    /// Creates a new sound group registry and registers
+    /// its API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let replaced = Arc::new(Mutex::new(HashMap::new()));
+
+        let lua = engine.lua();
+        let api = scripting::api_table(lua, "blocks")?;
+        let groups = replaced.clone();
+        api.set("sounds", lua.create_function(move |_, def: Table| {
+            let name: String = def.get("block")?;
+            let material = Material::from_name(&name)
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("unknown block {}", name)))?;
+            let name: String = def.get("group")?;
+            let group = SoundGroup::from_name(&name)
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("unknown sound group {}", name)))?;
+            groups.lock().unwrap().insert(material, group);
+            Ok(())
+        })?)?;
+
+        Ok(Self { replaced })
+    }
+
+    /// Returns the sound group of a block
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    pub fn group(&self, material: Material) -> SoundGroup {
+        self.replaced.lock().unwrap().get(&material).copied().unwrap_or_else(|| material.data().sound_group())
+    }
+
+    /// Returns the cue of a sound of a block
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - What the sound is played for
+    /// * `material` - The material of the block
+    /// * `pos` - The position the sound comes from
+    pub fn cue(&self, kind: SoundKind, material: Material, pos: Vector3<f32>) -> SoundCue {
+        SoundCue {
+            kind,
+            group: Some(self.group(material)),
+            pos,
+        }
+    }
+}
+
+/// Footsteps
+///
+/// Counts the distance the player moved to play a footstep
+/// every few blocks they walk, or a stroke every few blocks
+/// they swim. Nothing is played while they fly or fall.
+#[derive(Debug, Default)]
+pub struct Footsteps {
+    /// The distance moved since the last sound
+    distance: f32,
+}
+
+impl Footsteps {
+    /// Advances by the player's movement of an update and
+    /// returns the sound which is due, with the material of
+    /// the block the sound belongs to
+    ///
+    /// # Arguments
+    ///
+    /// * `moved` - The distance the player moved
+    /// * `feet` - The block the player's feet are in
+    /// * `ground` - The block the player stands on, if they
+    ///   are on the ground
+    pub fn advance(&mut self, moved: f32, feet: Option<Material>, ground: Option<Material>) -> Option<(SoundKind, Material)> {
+        let (kind, material, interval) = match (feet, ground) {
+            (Some(Material::Water), _) => (SoundKind::Swim, Material::Water, SWIM_DISTANCE),
+            (_, Some(ground)) => (SoundKind::Footstep, ground, STEP_DISTANCE),
+            _ => return None,
+        };
+
+        self.distance += moved;
+        if self.distance < interval {
+            return None;
+        }
+        self.distance %= interval;
+        Some((kind, material))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn footsteps_follow_the_ground() {
+        let mut footsteps = Footsteps::default();
+        assert_eq!(footsteps.advance(1.0, Some(Material::Air), Some(Material::Grass)), None);
+        assert_eq!(footsteps.advance(1.0, Some(Material::Air), Some(Material::Sand)), Some((SoundKind::Footstep, Material::Sand)));
+
+        // Falling doesn't count, swimming takes longer strokes
+        assert_eq!(footsteps.advance(10.0, Some(Material::Air), None), None);
+        assert_eq!(footsteps.advance(2.0, Some(Material::Water), Some(Material::Sand)), None);
+        assert_eq!(footsteps.advance(1.0, Some(Material::Water), None), Some((SoundKind::Swim, Material::Water)));
+    }
+
+    #[test]
+    fn scripts_replace_sound_groups() {
+        let engine = ScriptEngine::new();
+        let groups = SoundGroups::new(&engine).unwrap();
+        assert_eq!(groups.group(Material::Chest), SoundGroup::Wood);
+        assert_eq!(groups.group(Material::Glass), SoundGroup::Stone);
+
+        engine.exec("test", r#"blocks.sounds { block = "glass", group = "sand" }"#).unwrap();
+        assert_eq!(groups.group(Material::Glass), SoundGroup::Sand);
+        assert!(engine.exec("test", r#"blocks.sounds { block = "glass", group = "cheese" }"#).is_err());
+        assert!(engine.exec("test", r#"blocks.sounds { block = "cheese", group = "wood" }"#).is_err());
+
+        let cue = groups.cue(SoundKind::Break, Material::Glass, Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(cue.resource(), "sounds/sand/break.ogg");
+        assert_eq!(SoundCue { kind: SoundKind::Swim, group: None, ..cue }.resource(), "sounds/water/swim.ogg");
+        assert_eq!(SoundCue { kind: SoundKind::Hurt, group: None, ..cue }.resource(), "sounds/entity/hurt.ogg");
+    }
+}
@@ -0,0 +1,423 @@
+//! Types tracking the statistics and achievements
+//! of a player
+
+use crate::scripting::{ScriptEngine, ScriptError};
+use crate::timestep::TimeStep;
+use crate::world::block::Material;
+use mlua::{Function, Lua, RegistryKey, Table};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+/// Statistics
+///
+/// The `Statistics` of a player in a world. They are
+/// persisted with the player data.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Statistics {
+    /// The distance the player walked in blocks
+    distance_walked: f64,
+    /// The distance the player flew in blocks
+    distance_flown: f64,
+    /// The time the player played in seconds
+    time_played: f64,
+    /// The amount of deaths of the player
+    deaths: u64,
+    /// The ids of the unlocked achievements
+    achievements: BTreeSet<String>,
+    /// The amount of placed blocks per material
+    blocks_placed: BTreeMap<String, u64>,
+    /// The amount of broken blocks per material
+    blocks_broken: BTreeMap<String, u64>,
+}
+
+impl Statistics {
+    /// Records a block the player placed
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    pub fn record_block_placed(&mut self, material: Material) {
+        *self.blocks_placed.entry(material.name().to_string()).or_insert(0) += 1;
+    }
+
+    /// Records a block the player broke
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    pub fn record_block_broken(&mut self, material: Material) {
+        *self.blocks_broken.entry(material.name().to_string()).or_insert(0) += 1;
+    }
+
+    /// Records a block the player changed, which counts as
+    /// broken and/or placed block depending on the materials
+    ///
+    /// # Arguments
+    ///
+    /// * `old` - The material before the edit
+    /// * `new` - The material after the edit
+    pub fn record_block_edit(&mut self, old: Material, new: Material) {
+        if old == new {
+            return;
+        }
+        if old != Material::Air {
+            self.record_block_broken(old);
+        }
+        if new != Material::Air {
+            self.record_block_placed(new);
+        }
+    }
+
+    /// Adds a walked distance
+    ///
+    /// # Arguments
+    ///
+    /// * `distance` - The distance in blocks
+    pub fn add_distance_walked(&mut self, distance: f32) {
+        self.distance_walked += distance as f64;
+    }
+
+    /// Adds a distance the player flew
+    ///
+    /// # Arguments
+    ///
+    /// * `distance` - The distance in blocks
+    pub fn add_distance_flown(&mut self, distance: f32) {
+        self.distance_flown += distance as f64;
+    }
+
+    /// Adds played time
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time which has been played
+    pub fn add_time_played(&mut self, time_step: TimeStep) {
+        self.time_played += time_step.seconds() as f64;
+    }
+
+    /// Records a death of the player
+    pub fn record_death(&mut self) {
+        self.deaths += 1;
+    }
+
+    /// Returns the amount of placed blocks of a material
+    pub fn blocks_placed(&self, material: Material) -> u64 {
+        self.blocks_placed.get(material.name()).cloned().unwrap_or(0)
+    }
+
+    /// Returns the amount of broken blocks of a material
+    pub fn blocks_broken(&self, material: Material) -> u64 {
+        self.blocks_broken.get(material.name()).cloned().unwrap_or(0)
+    }
+
+    /// Returns the distance the player walked in blocks
+    pub fn distance_walked(&self) -> f64 {
+        self.distance_walked
+    }
+
+    /// Returns the distance the player flew in blocks
+    pub fn distance_flown(&self) -> f64 {
+        self.distance_flown
+    }
+
+    /// Returns the time the player played in seconds
+    pub fn time_played(&self) -> f64 {
+        self.time_played
+    }
+
+    /// Returns the amount of deaths of the player
+    pub fn deaths(&self) -> u64 {
+        self.deaths
+    }
+
+    /// Returns whether the achievement with the given id
+    /// is unlocked
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.achievements.contains(id)
+    }
+
+    /// Unlocks the achievement with the given id. Returns
+    /// `false` if the achievement was already unlocked.
+    pub fn unlock(&mut self, id: &str) -> bool {
+        self.achievements.insert(id.to_string())
+    }
+
+    /// Converts the statistics into a Lua table
+    ///
+    /// # Arguments
+    ///
+    /// * `lua` - The Lua state
+    fn to_lua_table<'lua>(&self, lua: &'lua Lua) -> mlua::Result<Table<'lua>> {
+        let table = lua.create_table()?;
+        table.set("distance_walked", self.distance_walked)?;
+        table.set("distance_flown", self.distance_flown)?;
+        table.set("time_played", self.time_played)?;
+        table.set("deaths", self.deaths)?;
+        table.set("blocks_placed", lua.create_table_from(self.blocks_placed.clone())?)?;
+        table.set("blocks_broken", lua.create_table_from(self.blocks_broken.clone())?)?;
+        table.set("total_blocks_placed", self.blocks_placed.values().sum::<u64>())?;
+        table.set("total_blocks_broken", self.blocks_broken.values().sum::<u64>())?;
+        Ok(table)
+    }
+}
+
+/// Achievement
+///
+/// An achievement defined by a script
+#[derive(Clone, Debug)]
+pub struct Achievement {
+    /// The unique id of the achievement
+    pub id: String,
+    /// The title of the achievement
+    pub title: String,
+    /// The description of the achievement
+    pub description: String,
+}
+
+/// The definition of an achievement with its unlock condition
+struct AchievementDef {
+    /// The achievement
+    achievement: Achievement,
+    /// The Lua function deciding whether the achievement
+    /// is unlocked, stored in the Lua registry
+    condition: RegistryKey,
+}
+
+/// Achievements
+///
+/// The registry of all achievements. Scripts register
+/// achievements through the `achievements.register`
+/// function, e.g.
+///
+/// ```lua
+/// achievements.register {
+///     id = "builder",
+///     title = "Builder",
+///     description = "Place 100 blocks",
+///     condition = function(stats) return stats.total_blocks_placed >= 100 end,
+/// }
+/// ```
+pub struct Achievements {
+    /// The registered achievements
+    defs: Arc<Mutex<Vec<AchievementDef>>>,
+}
+
+impl Achievements {
+    /// Creates a new achievement registry and registers its
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let defs = Arc::new(Mutex::new(Vec::new()));
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let registered = defs.clone();
+        api.set("register", lua.create_function(move |lua, def: Table| {
+            let achievement = Achievement {
+                id: def.get("id")?,
+                title: def.get("title")?,
+                description: def.get::<_, Option<String>>("description")?.unwrap_or_default(),
+            };
+            let condition: Function = def.get("condition")?;
+
+            let mut defs = registered.lock().unwrap();
+            defs.retain(|def: &AchievementDef| def.achievement.id != achievement.id);
+            defs.push(AchievementDef {
+                achievement,
+                condition: lua.create_registry_value(condition)?,
+            });
+            Ok(())
+        })?)?;
+        lua.globals().set("achievements", api)?;
+
+        Ok(Self {
+            defs,
+        })
+    }
+
+    /// Returns all registered achievements
+    pub fn all(&self) -> Vec<Achievement> {
+        self.defs.lock().unwrap().iter()
+            .map(|def| def.achievement.clone())
+            .collect()
+    }
+
+    /// Evaluates the conditions of all locked achievements and
+    /// unlocks the fulfilled ones. Returns the newly unlocked
+    /// achievements and the errors of failing conditions.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine the achievements are registered in
+    /// * `stats` - The statistics of the player
+    pub fn update(&self, engine: &ScriptEngine, stats: &mut Statistics) -> (Vec<Achievement>, Vec<ScriptError>) {
+        let lua = engine.lua();
+        let mut unlocked = Vec::new();
+        let mut errors = Vec::new();
+
+        // The conditions are collected first, so that they could
+        // register achievements themselves without a deadlock
+        let locked: Vec<(Achievement, mlua::Result<Function>)> = self.defs.lock().unwrap().iter()
+            .filter(|def| !stats.is_unlocked(&def.achievement.id))
+            .map(|def| (def.achievement.clone(), lua.registry_value::<Function>(&def.condition)))
+            .collect();
+        if locked.is_empty() {
+            return (unlocked, errors);
+        }
+
+        let table = match stats.to_lua_table(lua) {
+            Ok(table) => table,
+            Err(error) => return (unlocked, vec![ScriptError::new("achievements", error)]),
+        };
+
+        for (achievement, condition) in locked {
+            let result = condition.and_then(|condition| condition.call::<_, bool>(table.clone()));
+            match result {
+                Ok(true) => {
+                    if stats.unlock(&achievement.id) {
+                        unlocked.push(achievement);
+                    }
+                }
+                Ok(false) => {}
+                Err(error) => errors.push(ScriptError::new(&format!("achievement '{}'", achievement.id), error)),
+            }
+        }
+
+        (unlocked, errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a script engine with the given achievements script
+    fn achievements(source: &str) -> (ScriptEngine, Achievements) {
+        let engine = ScriptEngine::new();
+        let achievements = Achievements::new(&engine).unwrap();
+        engine.exec("test", source).unwrap();
+        (engine, achievements)
+    }
+
+    fn ids(achievements: &[Achievement]) -> Vec<&str> {
+        achievements.iter().map(|achievement| achievement.id.as_str()).collect()
+    }
+
+    #[test]
+    fn block_edits_count_as_broken_and_placed() {
+        let mut stats = Statistics::default();
+        stats.record_block_edit(Material::Air, Material::Dirt);
+        stats.record_block_edit(Material::Grass, Material::Air);
+        stats.record_block_edit(Material::Stone, Material::Dirt);
+        stats.record_block_edit(Material::Dirt, Material::Dirt);
+
+        assert_eq!(stats.blocks_placed(Material::Dirt), 2);
+        assert_eq!(stats.blocks_placed(Material::Air), 0);
+        assert_eq!(stats.blocks_broken(Material::Grass), 1);
+        assert_eq!(stats.blocks_broken(Material::Stone), 1);
+        assert_eq!(stats.blocks_broken(Material::Dirt), 0);
+    }
+
+    #[test]
+    fn statistics_are_exposed_to_lua() {
+        let engine = ScriptEngine::new();
+        let mut stats = Statistics::default();
+        stats.add_distance_walked(2.5);
+        stats.add_distance_flown(4.0);
+        stats.add_time_played(TimeStep(1.5));
+        stats.record_death();
+        stats.record_block_placed(Material::Dirt);
+        stats.record_block_placed(Material::Stone);
+        stats.record_block_broken(Material::Grass);
+
+        let table = stats.to_lua_table(engine.lua()).unwrap();
+        assert_eq!(table.get::<_, f64>("distance_walked").unwrap(), 2.5);
+        assert_eq!(table.get::<_, f64>("distance_flown").unwrap(), 4.0);
+        assert_eq!(table.get::<_, f64>("time_played").unwrap(), 1.5);
+        assert_eq!(table.get::<_, u64>("deaths").unwrap(), 1);
+        assert_eq!(table.get::<_, u64>("total_blocks_placed").unwrap(), 2);
+        assert_eq!(table.get::<_, u64>("total_blocks_broken").unwrap(), 1);
+
+        let placed: Table = table.get("blocks_placed").unwrap();
+        assert_eq!(placed.get::<_, u64>("stone").unwrap(), 1);
+    }
+
+    #[test]
+    fn statistics_are_persisted() {
+        let mut stats = Statistics::default();
+        stats.add_distance_walked(12.0);
+        stats.record_block_placed(Material::Grass);
+        stats.unlock("builder");
+
+        let restored: Statistics = toml::from_str(&toml::to_string(&stats).unwrap()).unwrap();
+        assert_eq!(restored.distance_walked(), 12.0);
+        assert_eq!(restored.blocks_placed(Material::Grass), 1);
+        assert!(restored.is_unlocked("builder"));
+
+        // Fields missing in older saves fall back to their defaults
+        let old: Statistics = toml::from_str("deaths = 3").unwrap();
+        assert_eq!(old.deaths(), 3);
+        assert_eq!(old.distance_flown(), 0.0);
+    }
+
+    #[test]
+    fn achievements_are_unlocked_once() {
+        let (engine, achievements) = achievements(r#"
+            achievements.register {
+                id = "builder",
+                title = "Builder",
+                condition = function(stats) return stats.total_blocks_placed >= 2 end,
+            }
+        "#);
+        let mut stats = Statistics::default();
+
+        stats.record_block_placed(Material::Dirt);
+        assert!(achievements.update(&engine, &mut stats).0.is_empty());
+
+        stats.record_block_placed(Material::Dirt);
+        let (unlocked, errors) = achievements.update(&engine, &mut stats);
+        assert_eq!(ids(&unlocked), vec!["builder"]);
+        assert!(errors.is_empty());
+        assert!(stats.is_unlocked("builder"));
+
+        assert!(achievements.update(&engine, &mut stats).0.is_empty());
+    }
+
+    #[test]
+    fn failing_conditions_are_reported() {
+        let (engine, achievements) = achievements(r#"
+            achievements.register { id = "broken", title = "Broken", condition = function(stats) error("oops") end }
+            achievements.register { id = "always", title = "Always", condition = function(stats) return true end }
+        "#);
+        let mut stats = Statistics::default();
+
+        let (unlocked, errors) = achievements.update(&engine, &mut stats);
+        assert_eq!(ids(&unlocked), vec!["always"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].source(), "achievement 'broken'");
+    }
+
+    #[test]
+    fn conditions_could_register_achievements() {
+        let (engine, achievements) = achievements(r#"
+            achievements.register {
+                id = "first",
+                title = "First",
+                condition = function(stats)
+                    achievements.register { id = "second", title = "Second", condition = function() return true end }
+                    return true
+                end,
+            }
+        "#);
+        let mut stats = Statistics::default();
+
+        assert_eq!(ids(&achievements.update(&engine, &mut stats).0), vec!["first"]);
+        assert_eq!(ids(&achievements.update(&engine, &mut stats).0), vec!["second"]);
+        assert_eq!(achievements.all().len(), 2);
+    }
+}
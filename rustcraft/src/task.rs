@@ -0,0 +1,284 @@
+//! Types to run background work which could be cancelled,
+//! e.g. meshing a chunk which has been unloaded meanwhile
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+/// CancelToken
+///
+/// The `CancelToken` is handed to a task, which checks it
+/// between its steps and stops as soon as it's cancelled.
+/// Results of a task carry its token, so the receiver
+/// could drop results of cancelled tasks as well.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    /// Whether the task has been cancelled
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Returns whether the task has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// TaskHandle
+///
+/// The handle of a running task, which is kept by the owner
+/// of the task. The task is cancelled when the handle is
+/// dropped, e.g. because a newer task replaced it.
+#[derive(Debug)]
+pub struct TaskHandle {
+    /// The token shared with the task
+    token: CancelToken,
+}
+
+impl TaskHandle {
+    /// Cancels the task. A task which already finished
+    /// isn't affected.
+    pub fn cancel(&self) {
+        self.token.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the handle belongs to the task
+    /// with the given token
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The token of a task
+    pub fn owns(&self, token: &CancelToken) -> bool {
+        Arc::ptr_eq(&self.token.cancelled, &token.cancelled)
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Spawns a task on a new thread and returns its handle
+///
+/// # Arguments
+///
+/// * `task` - The work of the task, which receives its token
+pub fn spawn<F: FnOnce(CancelToken) + Send + 'static>(task: F) -> TaskHandle {
+    let token = CancelToken::default();
+    let task_token = token.clone();
+    thread::spawn(move || {
+        if !task_token.is_cancelled() {
+            task(task_token);
+        }
+    });
+    TaskHandle {
+        token,
+    }
+}
+
+/// The work of a task queued in a pool
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The tasks queued in a pool, which the workers take in
+/// the order they have been submitted
+struct Queue<K> {
+    /// The keys of the queued tasks in their order
+    order: VecDeque<K>,
+    /// The queued tasks by their key
+    jobs: HashMap<K, Job>,
+    /// Whether the workers should stop
+    shutdown: bool,
+}
+
+/// TaskPool
+///
+/// A fixed amount of worker threads running tasks from a
+/// bounded queue. Each task has a key, e.g. the location of
+/// the chunk it meshes, and a task replaces the queued one
+/// with the same key, which would be outdated anyway. A full
+/// queue refuses tasks, so the owner retries them later
+/// instead of piling them up.
+pub struct TaskPool<K> {
+    /// The queue shared with the workers
+    queue: Arc<(Mutex<Queue<K>>, Condvar)>,
+    /// The worker threads
+    workers: Vec<JoinHandle<()>>,
+    /// The largest amount of queued tasks
+    capacity: usize,
+}
+
+impl<K: Clone + Eq + Hash + Send + 'static> TaskPool<K> {
+    /// Creates a new pool and starts its workers
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the worker threads
+    /// * `workers` - The amount of worker threads, at least one
+    /// * `capacity` - The largest amount of queued tasks
+    pub fn new(name: &str, workers: usize, capacity: usize) -> Self {
+        let queue = Arc::new((Mutex::new(Queue {
+            order: VecDeque::new(),
+            jobs: HashMap::new(),
+            shutdown: false,
+        }), Condvar::new()));
+        let workers = (0..workers.max(1))
+            .map(|index| {
+                let queue = queue.clone();
+                thread::Builder::new()
+                    .name(format!("{}-{}", name, index))
+                    .spawn(move || work(&queue))
+                    .expect("Failed to spawn a worker thread.")
+            })
+            .collect();
+        Self {
+            queue,
+            workers,
+            capacity,
+        }
+    }
+
+    /// Queues a task and returns its handle, or `None` if the
+    /// queue is full. A queued task with the same key is
+    /// replaced and keeps its place in the queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the task
+    /// * `task` - The work of the task, which receives its token
+    pub fn submit<F: FnOnce(CancelToken) + Send + 'static>(&self, key: K, task: F) -> Option<TaskHandle> {
+        let (queue, available) = &*self.queue;
+        let mut queue = queue.lock().unwrap();
+        let replaces = queue.jobs.contains_key(&key);
+        if !replaces && queue.order.len() >= self.capacity {
+            return None;
+        }
+
+        let token = CancelToken::default();
+        let task_token = token.clone();
+        queue.jobs.insert(key.clone(), Box::new(move || {
+            if !task_token.is_cancelled() {
+                task(task_token);
+            }
+        }));
+        if !replaces {
+            queue.order.push_back(key);
+            available.notify_one();
+        }
+        Some(TaskHandle {
+            token,
+        })
+    }
+
+    /// Returns the amount of queued tasks, which
+    /// haven't been taken by a worker yet
+    pub fn queued(&self) -> usize {
+        self.queue.0.lock().unwrap().order.len()
+    }
+}
+
+impl<K> Drop for TaskPool<K> {
+    fn drop(&mut self) {
+        {
+            let (queue, available) = &*self.queue;
+            let mut queue = queue.lock().unwrap();
+            queue.shutdown = true;
+            queue.order.clear();
+            queue.jobs.clear();
+            available.notify_all();
+        }
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Runs the tasks of a pool's queue until the pool is dropped
+///
+/// # Arguments
+///
+/// * `queue` - The queue shared with the pool
+fn work<K: Eq + Hash>(queue: &(Mutex<Queue<K>>, Condvar)) {
+    let (queue, available) = queue;
+    loop {
+        let job = {
+            let mut queue = queue.lock().unwrap();
+            loop {
+                if queue.shutdown {
+                    return;
+                }
+                if let Some(key) = queue.order.pop_front() {
+                    break queue.jobs.remove(&key);
+                }
+                queue = available.wait(queue).unwrap();
+            }
+        };
+        if let Some(job) = job {
+            job();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn dropped_handles_cancel_their_task() {
+        let (sender, receiver) = channel();
+        let (started_sender, started) = channel();
+        let handle = spawn(move |token| {
+            started_sender.send(()).unwrap();
+            while !token.is_cancelled() {
+                thread::yield_now();
+            }
+            sender.send(token).unwrap();
+        });
+
+        started.recv().unwrap();
+        drop(handle);
+        assert!(receiver.recv().unwrap().is_cancelled());
+    }
+
+    #[test]
+    fn handles_own_the_token_of_their_task() {
+        let (sender, receiver) = channel();
+        let handle = spawn(move |token| sender.send(token).unwrap());
+        let token = receiver.recv().unwrap();
+        assert!(handle.owns(&token));
+        assert!(!handle.owns(&CancelToken::default()));
+        assert!(!token.is_cancelled());
+
+        handle.cancel();
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn pools_replace_queued_tasks_and_refuse_them_when_full() {
+        let pool = TaskPool::new("test", 1, 2);
+        let (sender, receiver) = channel();
+
+        // The worker is blocked until the test lets it go on
+        let (release, blocked) = channel::<()>();
+        let _busy = pool.submit(0, move |_| blocked.recv().unwrap()).unwrap();
+        while pool.queued() > 0 {
+            thread::yield_now();
+        }
+
+        let outdated = { let sender = sender.clone(); pool.submit(1, move |_| sender.send("outdated").unwrap()) };
+        let _first = { let sender = sender.clone(); pool.submit(1, move |_| sender.send("first").unwrap()) };
+        let _second = { let sender = sender.clone(); pool.submit(2, move |_| sender.send("second").unwrap()) };
+        assert!(outdated.is_some());
+        assert_eq!(pool.queued(), 2);
+        assert!(pool.submit(3, |_| {}).is_none());
+
+        release.send(()).unwrap();
+        assert_eq!(receiver.recv().unwrap(), "first");
+        assert_eq!(receiver.recv().unwrap(), "second");
+        drop(pool);
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! A lock-light triple buffer to hand over values
+//! between exactly one writer and one reader thread
+
+use std::sync::{Arc, Mutex};
+
+/// The slot shared between the writer and the reader
+struct SharedSlot<T> {
+    /// The value which is currently exchanged
+    value: T,
+    /// Whether the value has been published but not
+    /// taken by the reader so far
+    fresh: bool,
+}
+
+/// TripleBufferWriter
+///
+/// The writing end of a triple buffer. Publishing
+/// a value never blocks on the reader for longer than
+/// a pointer swap.
+pub struct TripleBufferWriter<T> {
+    /// The slot the writer is currently owning
+    back: T,
+    /// The slot shared with the reader
+    shared: Arc<Mutex<SharedSlot<T>>>,
+}
+
+/// TripleBufferReader
+///
+/// The reading end of a triple buffer. The reader
+/// always sees the most recently published value
+/// and skips values which have been overwritten in
+/// the meantime.
+pub struct TripleBufferReader<T> {
+    /// The slot the reader is currently owning
+    front: T,
+    /// The slot shared with the writer
+    shared: Arc<Mutex<SharedSlot<T>>>,
+}
+
+/// Creates a new triple buffer. All three slots are
+/// initialized with the given value.
+///
+/// # Arguments
+///
+/// * `initial` - The initial value of all slots
+pub fn triple_buffer<T: Clone>(initial: T) -> (TripleBufferWriter<T>, TripleBufferReader<T>) {
+    let shared = Arc::new(Mutex::new(SharedSlot {
+        value: initial.clone(),
+        fresh: false,
+    }));
+
+    let writer = TripleBufferWriter {
+        back: initial.clone(),
+        shared: shared.clone(),
+    };
+
+    let reader = TripleBufferReader {
+        front: initial,
+        shared,
+    };
+
+    (writer, reader)
+}
+
+impl<T> TripleBufferWriter<T> {
+    /// Publishes a new value to the reader
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value which should be published
+    pub fn publish(&mut self, value: T) {
+        self.back = value;
+        let mut guard = self.shared.lock().unwrap();
+        std::mem::swap(&mut self.back, &mut guard.value);
+        guard.fresh = true;
+    }
+}
+
+impl<T> TripleBufferReader<T> {
+    /// Returns the most recently published value. If
+    /// nothing new has been published since the last
+    /// call, the previous value is returned again.
+    pub fn read(&mut self) -> &T {
+        {
+            let mut guard = self.shared.lock().unwrap();
+            if guard.fresh {
+                std::mem::swap(&mut self.front, &mut guard.value);
+                guard.fresh = false;
+            }
+        }
+        &self.front
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn read_returns_initial_value_before_publish() {
+        let (_writer, mut reader) = triple_buffer(1);
+        assert_eq!(*reader.read(), 1);
+        assert_eq!(*reader.read(), 1);
+    }
+
+    #[test]
+    fn read_returns_published_value() {
+        let (mut writer, mut reader) = triple_buffer(0);
+        writer.publish(1);
+        assert_eq!(*reader.read(), 1);
+        // Reading again without a new publish keeps the value
+        assert_eq!(*reader.read(), 1);
+    }
+
+    #[test]
+    fn read_skips_overwritten_values() {
+        let (mut writer, mut reader) = triple_buffer(0);
+        writer.publish(1);
+        writer.publish(2);
+        writer.publish(3);
+        assert_eq!(*reader.read(), 3);
+    }
+
+    #[test]
+    fn publish_after_read_is_seen() {
+        let (mut writer, mut reader) = triple_buffer(0);
+        writer.publish(1);
+        assert_eq!(*reader.read(), 1);
+        writer.publish(2);
+        assert_eq!(*reader.read(), 2);
+        writer.publish(3);
+        writer.publish(4);
+        assert_eq!(*reader.read(), 4);
+    }
+
+    #[test]
+    fn reader_never_sees_older_values_across_threads() {
+        let (mut writer, mut reader) = triple_buffer(0u32);
+        let handle = thread::spawn(move || {
+            for value in 1..=10_000 {
+                writer.publish(value);
+            }
+        });
+
+        let mut last = 0;
+        while last < 10_000 {
+            let value = *reader.read();
+            assert!(value >= last);
+            last = value;
+        }
+        handle.join().unwrap();
+    }
+}
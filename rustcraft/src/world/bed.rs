@@ -0,0 +1,225 @@
+//! Types to sleep in beds, which set the spawn point of
+//! the player and skip the night once all players sleep
+
+use crate::world::structure::Facing;
+use crate::world::time::WorldTime;
+use cgmath::Vector3;
+use std::collections::BTreeSet;
+
+/// The time in seconds the screen fades out and in
+/// while the night is skipped
+pub const NIGHT_SKIP_DURATION: f32 = 3.0;
+/// The time of day the players wake up at
+pub const WAKE_UP_TIME: f32 = 0.02;
+/// The darkness above which players could sleep
+const MIN_SLEEP_DARKNESS: f32 = 0.5;
+/// The bit of the block state set for the head of a bed
+const HEAD_BIT: u8 = 0b100;
+
+/// Returns the block state of one half of a bed. The foot
+/// of a bed is the half it's placed at, the head is the
+/// adjacent block in the direction the bed faces.
+///
+/// # Arguments
+///
+/// * `facing` - The direction from the foot to the head
+/// * `head` - Whether the block is the head of the bed
+pub fn state(facing: Facing, head: bool) -> u8 {
+    let facing = match facing {
+        Facing::North => 0,
+        Facing::East => 1,
+        Facing::South => 2,
+        Facing::West => 3,
+    };
+    if head { facing | HEAD_BIT } else { facing }
+}
+
+/// Returns the direction from the foot to the head
+/// of a bed by the block state of one of its halves
+///
+/// # Arguments
+///
+/// * `state` - The block state
+pub fn facing(state: u8) -> Facing {
+    match state & 0b11 {
+        0 => Facing::North,
+        1 => Facing::East,
+        2 => Facing::South,
+        _ => Facing::West,
+    }
+}
+
+/// Returns whether a block state is the head of a bed
+///
+/// # Arguments
+///
+/// * `state` - The block state
+pub fn is_head(state: u8) -> bool {
+    state & HEAD_BIT != 0
+}
+
+/// Returns the position of the foot of a bed
+///
+/// # Arguments
+///
+/// * `pos` - The position of one half of the bed
+/// * `state` - The block state of that half
+pub fn foot(pos: Vector3<i32>, state: u8) -> Vector3<i32> {
+    if is_head(state) { pos - facing(state).offset() } else { pos }
+}
+
+/// Returns the position of the other half of a bed
+///
+/// # Arguments
+///
+/// * `pos` - The position of one half of the bed
+/// * `state` - The block state of that half
+pub fn other_half(pos: Vector3<i32>, state: u8) -> Vector3<i32> {
+    if is_head(state) { pos - facing(state).offset() } else { pos + facing(state).offset() }
+}
+
+/// Returns whether it's dark enough to sleep
+///
+/// # Arguments
+///
+/// * `time` - The time of the world
+pub fn is_night(time: &WorldTime) -> bool {
+    time.darkness() > MIN_SLEEP_DARKNESS
+}
+
+/// Sleepers
+///
+/// The names of the players sleeping in a bed. The night is
+/// only skipped once every player in the world sleeps.
+#[derive(Debug, Default)]
+pub struct Sleepers {
+    /// The names of the sleeping players
+    sleeping: BTreeSet<String>,
+}
+
+impl Sleepers {
+    /// Lets a player fall asleep
+    ///
+    /// # Arguments
+    ///
+    /// * `player` - The name of the player
+    pub fn sleep(&mut self, player: &str) {
+        self.sleeping.insert(player.to_string());
+    }
+
+    /// Wakes a player up, e.g. once they left their bed
+    ///
+    /// # Arguments
+    ///
+    /// * `player` - The name of the player
+    pub fn wake(&mut self, player: &str) {
+        self.sleeping.remove(player);
+    }
+
+    /// Returns whether a player sleeps
+    ///
+    /// # Arguments
+    ///
+    /// * `player` - The name of the player
+    pub fn is_sleeping(&self, player: &str) -> bool {
+        self.sleeping.contains(player)
+    }
+
+    /// Returns whether all of the given players sleep,
+    /// which is never the case without any players
+    ///
+    /// # Arguments
+    ///
+    /// * `players` - The names of the players in the world
+    pub fn all_asleep<'a>(&self, players: impl IntoIterator<Item = &'a str>) -> bool {
+        let mut players = players.into_iter().peekable();
+        players.peek().is_some() && players.all(|player| self.is_sleeping(player))
+    }
+
+    /// Wakes all players up
+    pub fn wake_all(&mut self) {
+        self.sleeping.clear();
+    }
+}
+
+/// NightSkip
+///
+/// The transition while the night is skipped. The screen fades
+/// out, the time is set to the morning and the screen fades in.
+#[derive(Debug, Default)]
+pub struct NightSkip {
+    /// The time since the night skip started in seconds
+    elapsed: f32,
+    /// Whether the time has been set to the morning already
+    skipped: bool,
+}
+
+impl NightSkip {
+    /// Advances the night skip. Returns `true` once, when
+    /// the time should be set to the morning.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    pub fn advance(&mut self, seconds: f32) -> bool {
+        self.elapsed += seconds;
+        if !self.skipped && self.elapsed >= NIGHT_SKIP_DURATION / 2.0 {
+            self.skipped = true;
+            return true;
+        }
+        false
+    }
+
+    /// Returns whether the night skip is over
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= NIGHT_SKIP_DURATION
+    }
+
+    /// Returns how far the screen is faded out, from `0.0`
+    /// to `1.0` at the moment the time is set
+    pub fn fade(&self) -> f32 {
+        let half = NIGHT_SKIP_DURATION / 2.0;
+        (1.0 - (self.elapsed - half).abs() / half).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bed_halves_find_each_other() {
+        let foot_pos = Vector3::new(3, 10, -2);
+        let head_pos = foot_pos + Facing::West.offset();
+        let (foot_state, head_state) = (state(Facing::West, false), state(Facing::West, true));
+        assert_eq!(facing(head_state), Facing::West);
+        assert!(is_head(head_state) && !is_head(foot_state));
+        assert_eq!(other_half(foot_pos, foot_state), head_pos);
+        assert_eq!(other_half(head_pos, head_state), foot_pos);
+        assert_eq!(foot(head_pos, head_state), foot_pos);
+    }
+
+    #[test]
+    fn night_is_skipped_once_all_players_sleep() {
+        let mut sleepers = Sleepers::default();
+        assert!(!sleepers.all_asleep(Vec::new()));
+        sleepers.sleep("alex");
+        assert!(sleepers.all_asleep(vec!["alex"]));
+        assert!(!sleepers.all_asleep(vec!["alex", "steve"]));
+        sleepers.sleep("steve");
+        sleepers.wake("alex");
+        assert!(!sleepers.all_asleep(vec!["alex", "steve"]));
+        sleepers.wake_all();
+        assert!(!sleepers.is_sleeping("alex"));
+
+        assert!(is_night(&WorldTime::new(0.75, 100.0)));
+        assert!(!is_night(&WorldTime::new(0.25, 100.0)));
+
+        let mut skip = NightSkip::default();
+        assert!(!skip.advance(NIGHT_SKIP_DURATION / 4.0));
+        assert!(skip.advance(NIGHT_SKIP_DURATION / 4.0));
+        assert_eq!(skip.fade(), 1.0);
+        assert!(!skip.advance(NIGHT_SKIP_DURATION / 2.0));
+        assert!(skip.is_finished());
+    }
+}
@@ -0,0 +1,762 @@
+//! Types to generate terrain made up of biomes, which
+//! blend into each other at their borders, and to define
+//! the biomes from scripts
+
+use crate::scripting::ScriptEngine;
+use crate::world::block::Material;
+use crate::world::chunk::{Chunk, CHUNK_AREA, CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::world::noise_source::{Noise, NoiseAlgorithm, NoiseSource};
+use crate::world::terrain_generator::{CaveCarver, TerrainGen};
+use crate::world::vegetation;
+use cgmath::{Vector2, Vector3};
+use mlua::Table;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// The height up to which the oceans are filled with water
+pub const SEA_LEVEL: i32 = 9;
+/// The height from which on the surface is bare stone
+pub const SNOW_LINE: i32 = 38;
+/// The height above the sea level up to which the
+/// shores are covered with sand
+const BEACH_HEIGHT: i32 = 1;
+/// The depth of the layer of soil below the surface,
+/// beneath which the terrain is stone
+const SOIL_DEPTH: i32 = 3;
+/// The frequency of the biomes
+const BIOME_FREQUENCY: f64 = 1.0 / 256.0;
+/// The frequency of the hills within a biome
+const DETAIL_FREQUENCY: f64 = 1.0 / 24.0;
+/// The distance in blocks the noise of the biomes is sampled
+/// apart from the hills, so that they don't follow each other
+const BIOME_NOISE_OFFSET: f64 = 1024.5 / BIOME_FREQUENCY;
+/// The frequency of the temperature
+const TEMPERATURE_FREQUENCY: f64 = 1.0 / 384.0;
+/// The distance in blocks the noise of the temperature is
+/// sampled apart from the other noises
+const TEMPERATURE_NOISE_OFFSET: f64 = 2048.5 / TEMPERATURE_FREQUENCY;
+/// The amount of logs of the trunk of a tree
+const TRUNK_HEIGHT: i16 = 5;
+/// The distance of the leaves to the trunk of a tree
+const CANOPY_RADIUS: i16 = 2;
+/// A number distinguishing the rolls of the trees of a
+/// column from the rolls of its plants
+const TREE_SALT: u64 = 0x7EE5;
+/// The distance in blocks around a column whose biomes are
+/// blended into its height
+const BLEND_RADIUS: i32 = 12;
+/// The distance in blocks between the samples of the biomes
+/// which are blended
+const BLEND_STEP: i32 = 4;
+
+/// Biome
+///
+/// The name of a kind of landscape the terrain is made up of,
+/// e.g. `plains`. Scripts could add biomes to the built-in
+/// ones, so biomes are told apart by their name alone.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Biome(Arc<str>);
+
+impl Biome {
+    /// Creates the biome of the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the biome, e.g. `"plains"`
+    pub fn new(name: &str) -> Self {
+        Self(Arc::from(name))
+    }
+
+    /// Returns the name of the biome
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Band
+///
+/// The heights of the landscape the noise of the biomes
+/// chooses from, ordered by their height, so that only
+/// biomes of similar heights border each other. The biomes
+/// of the same band, e.g. deserts next to plains, are told
+/// apart by their temperature.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Band {
+    /// A sea floor below the sea level
+    Ocean,
+    /// Low land just above the sea level
+    Lowlands,
+    /// Rolling hills
+    Hills,
+    /// Steep peaks reaching above the snow line
+    Mountains,
+}
+
+impl Band {
+    /// All bands, ordered by their height
+    pub const ALL: [Band; 4] = [Band::Ocean, Band::Lowlands, Band::Hills, Band::Mountains];
+
+    /// Returns the band with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the band, e.g. `"lowlands"`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ocean" => Some(Band::Ocean),
+            "lowlands" => Some(Band::Lowlands),
+            "hills" => Some(Band::Hills),
+            "mountains" => Some(Band::Mountains),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the band
+    pub fn name(&self) -> &'static str {
+        match self {
+            Band::Ocean => "ocean",
+            Band::Lowlands => "lowlands",
+            Band::Hills => "hills",
+            Band::Mountains => "mountains",
+        }
+    }
+
+    /// Returns the band of the given value of the biome noise
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value of the noise in the range `[-1, 1]`
+    pub fn from_noise(value: f64) -> Self {
+        if value < -0.2 {
+            Band::Ocean
+        } else if value < 0.1 {
+            Band::Lowlands
+        } else if value < 0.3 {
+            Band::Hills
+        } else {
+            Band::Mountains
+        }
+    }
+
+    /// Returns the average height of the surface of the
+    /// biomes of the band which don't define their own
+    pub fn base_height(&self) -> f64 {
+        match self {
+            Band::Ocean => 4.0,
+            Band::Lowlands => 12.0,
+            Band::Hills => 20.0,
+            Band::Mountains => 36.0,
+        }
+    }
+
+    /// Returns how far the surface of the biomes of the band
+    /// rises above and sinks below its average height, unless
+    /// they define it themselves
+    pub fn variation(&self) -> f64 {
+        match self {
+            Band::Ocean => 2.0,
+            Band::Lowlands => 2.0,
+            Band::Hills => 6.0,
+            Band::Mountains => 14.0,
+        }
+    }
+}
+
+/// BiomeDef
+///
+/// The properties of a biome which scripts define
+#[derive(Clone, Debug, PartialEq)]
+pub struct BiomeDef {
+    /// The biome
+    pub biome: Biome,
+    /// The band of heights the biome is generated in
+    pub band: Band,
+    /// How warm the biome is, from `0` to `1`
+    pub temperature: f64,
+    /// The block covering the surface between the
+    /// shores and the snow line
+    pub surface: Material,
+    /// The chance of a column of the surface to grow a tree
+    pub tree_density: f64,
+    /// The average height of the surface
+    pub base_height: f64,
+    /// How far the surface rises above and sinks
+    /// below its average height
+    pub variation: f64,
+}
+
+impl BiomeDef {
+    /// Creates the definition of a biome of grass,
+    /// as high as the other biomes of its band
+    ///
+    /// # Arguments
+    ///
+    /// * `biome` - The biome
+    /// * `band` - The band of heights the biome is generated in
+    pub fn new(biome: Biome, band: Band) -> Self {
+        Self {
+            biome,
+            band,
+            temperature: 0.5,
+            surface: Material::Grass,
+            tree_density: 0.0,
+            base_height: band.base_height(),
+            variation: band.variation(),
+        }
+    }
+
+    /// Returns the built-in definitions of the biomes, which
+    /// apply until a script defines the biomes
+    pub fn builtin() -> Vec<Self> {
+        let def = |name: &str, band: Band, temperature: f64, surface: Material, tree_density: f64| Self {
+            temperature,
+            surface,
+            tree_density,
+            ..Self::new(Biome::new(name), band)
+        };
+        vec![
+            def("ocean", Band::Ocean, 0.5, Material::Sand, 0.0),
+            def("plains", Band::Lowlands, 0.4, Material::Grass, 0.004),
+            // Flat dunes as low as the plains, where it's warmer
+            Self { variation: 1.0, ..def("desert", Band::Lowlands, 0.8, Material::Sand, 0.0) },
+            def("hills", Band::Hills, 0.4, Material::Grass, 0.02),
+            def("mountains", Band::Mountains, 0.2, Material::Grass, 0.006),
+        ]
+    }
+}
+
+/// BiomeRegistry
+///
+/// The definitions of the biomes, shared by the generators
+/// of the biomes with the `biomes` API of the scripts, e.g.
+///
+/// ```lua
+/// biomes.define {
+///     name = "tundra",
+///     -- The band of heights the biome is generated in, one
+///     -- of ocean, lowlands, hills and mountains
+///     band = "lowlands",
+///     -- Of the biomes of the same band, the one whose
+///     -- temperature is closest to the temperature noise,
+///     -- from 0 to 1, is generated
+///     temperature = 0.05,
+///     surface = "dirt",
+///     -- The chance of a column to grow a tree
+///     tree_density = 0.001,
+///     -- The average height of the surface and how far it
+///     -- rises above and sinks below it, which default to
+///     -- the ones of the band
+///     height = 14,
+///     variation = 3,
+/// }
+/// ```
+///
+/// All fields but the name are optional and keep their
+/// previous value, only new biomes need a band. Chunks
+/// generated after a definition follow it.
+#[derive(Clone)]
+pub struct BiomeRegistry {
+    /// The definitions in the order of their definition
+    defined: Arc<RwLock<Vec<BiomeDef>>>,
+}
+
+impl Default for BiomeRegistry {
+    fn default() -> Self {
+        Self {
+            defined: Arc::new(RwLock::new(BiomeDef::builtin())),
+        }
+    }
+}
+
+impl BiomeRegistry {
+    /// Registers the `biomes` API in the given script engine,
+    /// which defines the biomes of this registry
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn register_api(&self, engine: &ScriptEngine) -> mlua::Result<()> {
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let registry = self.clone();
+        api.set("define", lua.create_function(move |_, def: Table| {
+            let def = biome_from_table(def, &registry)?;
+            registry.define(def);
+            Ok(())
+        })?)?;
+        lua.globals().set("biomes", api)?;
+        Ok(())
+    }
+
+    /// Adds a biome, or replaces its definition
+    /// if it's already defined
+    ///
+    /// # Arguments
+    ///
+    /// * `def` - The definition
+    pub fn define(&self, def: BiomeDef) {
+        let mut defined = self.defined.write().unwrap();
+        match defined.iter_mut().find(|other| other.biome == def.biome) {
+            Some(other) => *other = def,
+            None => defined.push(def),
+        }
+    }
+
+    /// Returns the definition of a biome, if it's defined
+    ///
+    /// # Arguments
+    ///
+    /// * `biome` - The biome
+    pub fn get(&self, biome: &Biome) -> Option<BiomeDef> {
+        self.defined.read().unwrap().iter()
+            .find(|def| def.biome == *biome)
+            .cloned()
+    }
+
+    /// Returns the biome of a band whose temperature is
+    /// closest to the given one
+    ///
+    /// # Arguments
+    ///
+    /// * `band` - The band the noise of the biomes chose
+    /// * `temperature` - The temperature from `0` to `1`
+    pub fn select(&self, band: Band, temperature: f64) -> BiomeDef {
+        self.defined.read().unwrap().iter()
+            .filter(|def| def.band == band)
+            .min_by(|a, b| (a.temperature - temperature).abs().total_cmp(&(b.temperature - temperature).abs()))
+            .cloned()
+            .unwrap_or_else(|| BiomeDef::new(Biome::new(band.name()), band))
+    }
+}
+
+/// Reads the definition of a biome from a Lua table
+///
+/// # Arguments
+///
+/// * `def` - The Lua table
+/// * `registry` - The registry whose definitions are kept
+///   for the missing fields
+fn biome_from_table(def: Table, registry: &BiomeRegistry) -> mlua::Result<BiomeDef> {
+    let name: String = def.get("name")?;
+    let biome = Biome::new(&name);
+    let band = match def.get::<_, Option<String>>("band")? {
+        Some(band) => Some(Band::from_name(&band).ok_or_else(|| {
+            mlua::Error::RuntimeError(format!("unknown band {}", band))
+        })?),
+        None => None,
+    };
+    // Biomes moving to another band take its heights,
+    // unless the definition sets them as well
+    let previous = match (registry.get(&biome), band) {
+        (Some(previous), Some(band)) if previous.band != band => BiomeDef {
+            band,
+            base_height: band.base_height(),
+            variation: band.variation(),
+            ..previous
+        },
+        (Some(previous), _) => previous,
+        (None, Some(band)) => BiomeDef::new(biome, band),
+        (None, None) => {
+            return Err(mlua::Error::RuntimeError(format!("the new biome {} needs a band", name)));
+        }
+    };
+
+    let surface = match def.get::<_, Option<String>>("surface")? {
+        Some(name) => Material::from_name(&name).ok_or_else(|| {
+            mlua::Error::RuntimeError(format!("unknown block {}", name))
+        })?,
+        None => previous.surface,
+    };
+    Ok(BiomeDef {
+        temperature: def.get::<_, Option<f64>>("temperature")?.unwrap_or(previous.temperature).clamp(0.0, 1.0),
+        surface,
+        tree_density: def.get::<_, Option<f64>>("tree_density")?.unwrap_or(previous.tree_density).clamp(0.0, 1.0),
+        base_height: def.get::<_, Option<f64>>("height")?.unwrap_or(previous.base_height).clamp(1.0, (CHUNK_HEIGHT - 1) as f64),
+        variation: def.get::<_, Option<f64>>("variation")?.unwrap_or(previous.variation).max(0.0),
+        ..previous
+    })
+}
+
+/// Returns the block covering the surface of a column. Sand
+/// lines the shores and bare stone the peaks wherever they
+/// are, even across the borders of biomes, while the biome
+/// covers the surface in between.
+///
+/// # Arguments
+///
+/// * `height` - The height of the surface of the column
+/// * `surface` - The surface block of the biome of the column
+pub fn surface_block(height: i32, surface: Material) -> Material {
+    if height <= SEA_LEVEL + BEACH_HEIGHT {
+        Material::Sand
+    } else if height >= SNOW_LINE {
+        Material::Stone
+    } else {
+        surface
+    }
+}
+
+/// Grows a tree of a trunk of logs with a canopy of leaves,
+/// which only replace air
+///
+/// # Arguments
+///
+/// * `chunk` - The chunk the tree grows in
+/// * `base` - The position of the lowest log in the chunk
+fn grow_tree(chunk: &Chunk, base: Vector3<i16>) {
+    for dy in TRUNK_HEIGHT - 2..=TRUNK_HEIGHT + 1 {
+        // The canopy narrows above the trunk
+        let radius = if dy < TRUNK_HEIGHT { CANOPY_RADIUS } else { CANOPY_RADIUS - 1 };
+        for dz in -radius..=radius {
+            for dx in -radius..=radius {
+                let corner = dx.abs() == radius && dz.abs() == radius;
+                if corner && (radius == CANOPY_RADIUS || dy > TRUNK_HEIGHT) {
+                    continue;
+                }
+                let pos = base + Vector3::new(dx, dy, dz);
+                if chunk.block(pos) == Some(Material::Air) {
+                    chunk.set_block(pos, Material::Leaves);
+                }
+            }
+        }
+    }
+    for dy in 0..TRUNK_HEIGHT {
+        chunk.set_block(base + Vector3::new(0, dy, 0), Material::Log);
+    }
+}
+
+/// BiomeTerrainGen
+///
+/// A terrain generator for the biomes of a registry, e.g.
+/// oceans, plains, deserts, hills and mountains. The noise
+/// of the biomes chooses the band of heights, while the noise
+/// of the temperature chooses among the biomes of the band. The height of each
+/// column blends the biomes around it, so that the terrain
+/// slopes from one biome into the next rather than dropping
+/// off a cliff at their border.
+///
+/// Tunnels are carved into the ground beneath the surface,
+/// see `CaveCarver`. Trees only grow where their canopy fits
+/// into the chunk of their trunk, so they're never cut off
+/// at its border.
+pub struct BiomeTerrainGen {
+    /// The noise the biomes follow
+    biomes: Noise,
+    /// The noise the temperature follows
+    temperature: Noise,
+    /// The noise the hills within the biomes follow
+    detail: Noise,
+    /// The definitions of the biomes
+    registry: BiomeRegistry,
+    /// The pass carving the tunnels
+    caves: CaveCarver,
+}
+
+impl Default for BiomeTerrainGen {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl BiomeTerrainGen {
+    /// Creates a new generator of biomes
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed of the noise, `0` for the default terrain
+    pub fn new(seed: u32) -> Self {
+        Self {
+            biomes: Noise::new(NoiseAlgorithm::Perlin, seed, BIOME_FREQUENCY),
+            temperature: Noise::new(NoiseAlgorithm::Perlin, seed, TEMPERATURE_FREQUENCY),
+            detail: Noise::new(NoiseAlgorithm::Perlin, seed, DETAIL_FREQUENCY),
+            registry: BiomeRegistry::default(),
+            caves: CaveCarver::new(seed),
+        }
+    }
+
+    /// Sets the definitions of the biomes, e.g. the ones
+    /// scripts define, instead of the built-in ones
+    ///
+    /// # Arguments
+    ///
+    /// * `registry` - The definitions of the biomes
+    pub fn with_biomes(mut self, registry: BiomeRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Returns the biome of the column at the given position
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The x coordinate of the column
+    /// * `z` - The z coordinate of the column
+    pub fn biome_at(&self, x: i32, z: i32) -> Biome {
+        self.def_at(x, z).biome
+    }
+
+    /// Returns the definition of the biome of the
+    /// column at the given position
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The x coordinate of the column
+    /// * `z` - The z coordinate of the column
+    fn def_at(&self, x: i32, z: i32) -> BiomeDef {
+        let band = Band::from_noise(self.biomes.get(x as f64 + BIOME_NOISE_OFFSET, z as f64 + BIOME_NOISE_OFFSET));
+        let temperature = self.temperature.get(x as f64 + TEMPERATURE_NOISE_OFFSET, z as f64 + TEMPERATURE_NOISE_OFFSET);
+        self.registry.select(band, (temperature + 1.0) / 2.0)
+    }
+}
+
+impl TerrainGen for BiomeTerrainGen {
+    fn gen_heightmap(&self, loc: &Vector2<i32>) -> [i32; CHUNK_AREA] {
+        let origin = Vector2::new(loc.x * CHUNK_SIZE as i32, loc.y * CHUNK_SIZE as i32);
+
+        // The biomes are sampled on a grid, so neighbouring
+        // columns share their samples
+        let mut samples = HashMap::new();
+        let mut height_at = |x: i32, z: i32| *samples.entry((x, z)).or_insert_with(|| {
+            let def = self.def_at(x, z);
+            (def.base_height, def.variation)
+        });
+
+        let mut height_map = [0i32; CHUNK_AREA];
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let block_x = origin.x + x as i32;
+                let block_z = origin.y + z as i32;
+
+                // Each sample is weighted by its distance to the
+                // column, which blends the heights of the biomes
+                // smoothly across their borders
+                let (mut base, mut variation, mut total) = (0.0, 0.0, 0.0);
+                let first_x = (block_x - BLEND_RADIUS).div_euclid(BLEND_STEP) * BLEND_STEP;
+                let first_z = (block_z - BLEND_RADIUS).div_euclid(BLEND_STEP) * BLEND_STEP;
+                for sample_z in (first_z..=block_z + BLEND_RADIUS).step_by(BLEND_STEP as usize) {
+                    for sample_x in (first_x..=block_x + BLEND_RADIUS).step_by(BLEND_STEP as usize) {
+                        let weight = (BLEND_RADIUS + BLEND_STEP - (sample_x - block_x).abs()).max(0)
+                            * (BLEND_RADIUS + BLEND_STEP - (sample_z - block_z).abs()).max(0);
+                        let weight = weight as f64;
+                        let (sample_base, sample_variation) = height_at(sample_x, sample_z);
+                        base += sample_base * weight;
+                        variation += sample_variation * weight;
+                        total += weight;
+                    }
+                }
+
+                let detail = self.detail.get(block_x as f64, block_z as f64);
+                let height = (base + variation * detail) / total;
+                height_map[z * CHUNK_SIZE + x] = height.round().max(1.0) as i32;
+            }
+        }
+
+        height_map
+    }
+
+    fn gen_smooth_terrain(&self, chunk: &Chunk, height_map: &[i32; CHUNK_AREA]) {
+        let loc = chunk.loc();
+        let mut columns = Vec::with_capacity(CHUNK_AREA);
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let block_x = loc.x * CHUNK_SIZE as i32 + x as i32;
+                let block_z = loc.y * CHUNK_SIZE as i32 + z as i32;
+                columns.push((block_x, block_z, self.def_at(block_x, block_z)));
+            }
+        }
+
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let height = height_map[z * CHUNK_SIZE + x];
+                let surface = surface_block(height, columns[z * CHUNK_SIZE + x].2.surface);
+                let soil = match surface {
+                    Material::Grass => Material::Dirt,
+                    material => material,
+                };
+
+                for y in 0..=height.max(SEA_LEVEL) {
+                    let material = if y > height {
+                        Material::Water
+                    } else if y == height {
+                        surface
+                    } else if y > height - SOIL_DEPTH {
+                        soil
+                    } else {
+                        Material::Stone
+                    };
+                    chunk.set_block(Vector3::new(x as i16, y as i16, z as i16), material);
+                }
+            }
+        }
+
+        self.caves.carve(chunk, height_map);
+
+        let margin = CANOPY_RADIUS as usize;
+        for z in margin..CHUNK_SIZE - margin {
+            for x in margin..CHUNK_SIZE - margin {
+                let (block_x, block_z, def) = &columns[z * CHUNK_SIZE + x];
+                let height = height_map[z * CHUNK_SIZE + x];
+                let ground = Vector3::new(x as i16, height as i16, z as i16);
+                let fits = height > 0 && height + (TRUNK_HEIGHT as i32 + 2) < CHUNK_HEIGHT as i32;
+                if def.tree_density <= 0.0 || !fits || chunk.block(ground) != Some(Material::Grass) {
+                    continue;
+                }
+                if vegetation::column_rng(*block_x, *block_z, TREE_SALT).unit() < def.tree_density {
+                    grow_tree(chunk, ground + Vector3::new(0, 1, 0));
+                }
+            }
+        }
+    }
+
+    fn biome(&self, x: i32, z: i32) -> Option<Biome> {
+        Some(self.biome_at(x, z))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the heights of a line of columns along the x axis
+    fn heights_along_x(generator: &BiomeTerrainGen, chunks: i32) -> Vec<i32> {
+        (0..chunks)
+            .flat_map(|x| {
+                let height_map = generator.gen_heightmap(&Vector2::new(x, 0));
+                (0..CHUNK_SIZE).map(move |column| height_map[column]).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn biomes_are_ordered_by_height() {
+        let bands = [-1.0, -0.2, 0.1, 0.3].iter().map(|value| Band::from_noise(*value)).collect::<Vec<_>>();
+        assert_eq!(bands, Band::ALL.to_vec());
+        for pair in bands.windows(2) {
+            assert!(pair[0].base_height() < pair[1].base_height());
+        }
+        assert!(Band::Ocean.base_height() + Band::Ocean.variation() < SEA_LEVEL as f64);
+        assert!(Band::Mountains.base_height() + Band::Mountains.variation() > SNOW_LINE as f64);
+    }
+
+    #[test]
+    fn biome_borders_slope_without_cliffs() {
+        let generator = BiomeTerrainGen::default();
+        let heights = heights_along_x(&generator, 48);
+        let biomes: Vec<Biome> = (0..heights.len() as i32).map(|x| generator.biome_at(x, 0)).collect();
+        assert!(biomes.windows(2).any(|pair| pair[0] != pair[1]), "the line crosses no border");
+
+        for (x, pair) in heights.windows(2).enumerate() {
+            assert!((pair[0] - pair[1]).abs() <= 3, "cliff at x = {}: {:?}", x, pair);
+        }
+    }
+
+    #[test]
+    fn surface_blocks_follow_the_height() {
+        assert_eq!(surface_block(2, Material::Grass), Material::Sand);
+        assert_eq!(surface_block(SEA_LEVEL + BEACH_HEIGHT, Material::Grass), Material::Sand);
+        assert_eq!(surface_block(SEA_LEVEL + BEACH_HEIGHT + 1, Material::Grass), Material::Grass);
+        assert_eq!(surface_block(SEA_LEVEL + BEACH_HEIGHT + 1, Material::Sand), Material::Sand);
+        assert_eq!(surface_block(SNOW_LINE, Material::Grass), Material::Stone);
+
+        let generator = BiomeTerrainGen::default();
+        let chunk = Chunk::new(Vector2::new(0, 0));
+        let mut height_map = [20; CHUNK_AREA];
+        height_map[0] = 4;
+        height_map[1] = SEA_LEVEL;
+        height_map[2] = SNOW_LINE + 2;
+        generator.gen_smooth_terrain(&chunk, &height_map);
+
+        // The sea floor is covered with sand and water
+        assert_eq!(chunk.block(Vector3::new(0, 4, 0)), Some(Material::Sand));
+        assert_eq!(chunk.block(Vector3::new(0, SEA_LEVEL as i16, 0)), Some(Material::Water));
+        assert_eq!(chunk.block(Vector3::new(0, SEA_LEVEL as i16 + 1, 0)), Some(Material::Air));
+        // A beach right at the water
+        assert_eq!(chunk.block(Vector3::new(1, SEA_LEVEL as i16, 0)), Some(Material::Sand));
+        assert_eq!(chunk.block(Vector3::new(1, SEA_LEVEL as i16 + 1, 0)), Some(Material::Air));
+        // Bare stone above the snow line
+        assert_eq!(chunk.block(Vector3::new(2, SNOW_LINE as i16 + 2, 0)), Some(Material::Stone));
+        // Grass on dirt on stone everywhere else
+        assert_eq!(chunk.block(Vector3::new(3, 20, 0)), Some(Material::Grass));
+        assert_eq!(chunk.block(Vector3::new(3, 19, 0)), Some(Material::Dirt));
+        assert_eq!(chunk.block(Vector3::new(3, 10, 0)), Some(Material::Stone));
+    }
+
+    #[test]
+    fn temperature_tells_biomes_of_the_same_height_apart() {
+        let registry = BiomeRegistry::default();
+        assert_eq!(registry.select(Band::Lowlands, 0.1).biome, Biome::new("plains"));
+        assert_eq!(registry.select(Band::Lowlands, 0.9).biome, Biome::new("desert"));
+        assert_eq!(registry.select(Band::Hills, 0.9).biome, Biome::new("hills"));
+        for pair in BiomeDef::builtin().windows(2) {
+            assert!(pair[0].base_height <= pair[1].base_height);
+        }
+
+        // Deserts are covered with sand, the plains with grass
+        let generator = BiomeTerrainGen::default();
+        let surface_of = |biome: &str| (0..64).flat_map(|x| (0..64).map(move |z| (x, z)))
+            .map(|(x, z)| (x * 8, z * 8))
+            .find(|&(x, z)| generator.biome_at(x, z).name() == biome)
+            .map(|(x, z)| {
+                let loc = Vector2::new(x.div_euclid(CHUNK_SIZE as i32), z.div_euclid(CHUNK_SIZE as i32));
+                let chunk = Chunk::new(loc);
+                let mut height_map = [SEA_LEVEL + 4; CHUNK_AREA];
+                height_map[0] = SEA_LEVEL + 4;
+                generator.gen_smooth_terrain(&chunk, &height_map);
+                let local = Vector3::new(x.rem_euclid(CHUNK_SIZE as i32) as i16, (SEA_LEVEL + 4) as i16, z.rem_euclid(CHUNK_SIZE as i32) as i16);
+                chunk.block(local)
+            });
+        assert_eq!(surface_of("desert"), Some(Some(Material::Sand)));
+        assert_eq!(surface_of("plains"), Some(Some(Material::Grass)));
+    }
+
+    #[test]
+    fn scripts_define_the_biomes() {
+        let engine = ScriptEngine::new();
+        let registry = BiomeRegistry::default();
+        registry.register_api(&engine).unwrap();
+        engine.exec("test", r#"
+            biomes.define { name = "plains", tree_density = 1 }
+            biomes.define { name = "desert", temperature = 0, surface = "glass" }
+        "#).unwrap();
+        let plains = Biome::new("plains");
+        let desert = Biome::new("desert");
+        assert_eq!(registry.get(&plains).unwrap().surface, Material::Grass);
+        assert_eq!(registry.get(&desert), Some(BiomeDef {
+            biome: desert.clone(),
+            band: Band::Lowlands,
+            temperature: 0.0,
+            surface: Material::Glass,
+            tree_density: 0.0,
+            base_height: 12.0,
+            variation: 1.0,
+        }));
+        assert_eq!(registry.select(Band::Lowlands, 0.1).biome, desert);
+        assert!(engine.exec("test", r#"biomes.define { name = "plains", surface = "cheese" }"#).is_err());
+
+        // New biomes join the others of their band
+        assert!(engine.exec("test", r#"biomes.define { name = "tundra" }"#).is_err());
+        assert!(engine.exec("test", r#"biomes.define { name = "tundra", band = "valleys" }"#).is_err());
+        engine.exec("test", r#"biomes.define { name = "tundra", band = "hills", temperature = 0, surface = "ice", height = 18 }"#).unwrap();
+        let tundra = registry.get(&Biome::new("tundra")).unwrap();
+        assert_eq!((tundra.band, tundra.surface, tundra.base_height, tundra.variation), (Band::Hills, Material::Ice, 18.0, 6.0));
+        assert_eq!(registry.select(Band::Hills, 0.1).biome, tundra.biome);
+        assert_eq!(registry.select(Band::Hills, 0.6).biome, Biome::new("hills"));
+        // Moving to another band takes its heights
+        engine.exec("test", r#"biomes.define { name = "tundra", band = "mountains" }"#).unwrap();
+        assert_eq!(registry.get(&tundra.biome).unwrap().base_height, Band::Mountains.base_height());
+        assert_eq!(registry.select(Band::Hills, 0.1).biome, Biome::new("hills"));
+
+        // Every column of grass whose tree fits into the chunk grows one
+        engine.exec("test", r#"biomes.define { name = "desert", temperature = 0.8, surface = "sand" }"#).unwrap();
+        let generator = BiomeTerrainGen::default().with_biomes(registry);
+        let loc = (0..64).map(|x| Vector2::new(x, 0))
+            .find(|loc| (0..CHUNK_SIZE as i32).all(|i| generator.biome_at(loc.x * CHUNK_SIZE as i32 + i, i) == plains))
+            .expect("no chunk of plains");
+        let chunk = Chunk::new(loc);
+        generator.gen_smooth_terrain(&chunk, &[SEA_LEVEL + 4; CHUNK_AREA]);
+        let trunk = Vector3::new(8, SEA_LEVEL as i16 + 5, 8);
+        assert_eq!(chunk.block(trunk), Some(Material::Log));
+        assert_eq!(chunk.block(trunk + Vector3::new(0, TRUNK_HEIGHT, 0)), Some(Material::Leaves));
+        assert_eq!(chunk.block(Vector3::new(0, SEA_LEVEL as i16 + 5, 8)), Some(Material::Air));
+    }
+}
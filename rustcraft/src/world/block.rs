@@ -5,12 +5,732 @@ use cgmath::{Vector2};
 /// A `Material` represents the 'type' of a block
 /// as just one u8
 #[repr(u8)]
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum Material {
     Air = 0,
     Grass = 1,
     Dirt = 2,
     Stone = 3,
+    Glass = 4,
+    Lava = 5,
+    Glowstone = 6,
+    Farmland = 7,
+    Wheat = 8,
+    Water = 9,
+    Chest = 10,
+    Spawner = 11,
+    Sand = 12,
+    TallGrass = 13,
+    Flower = 14,
+    Mushroom = 15,
+    Ice = 16,
+    PolishedStone = 17,
+    Furnace = 18,
+    LitFurnace = 19,
+    Hopper = 20,
+    Bed = 21,
+    Obsidian = 22,
+    Fire = 23,
+    Tnt = 24,
+    Ladder = 25,
+    Vine = 26,
+    Log = 27,
+    Leaves = 28,
+}
+
+impl Material {
+    /// All materials, ordered by their id
+    pub const ALL: [Material; 29] = [
+        Material::Air,
+        Material::Grass,
+        Material::Dirt,
+        Material::Stone,
+        Material::Glass,
+        Material::Lava,
+        Material::Glowstone,
+        Material::Farmland,
+        Material::Wheat,
+        Material::Water,
+        Material::Chest,
+        Material::Spawner,
+        Material::Sand,
+        Material::TallGrass,
+        Material::Flower,
+        Material::Mushroom,
+        Material::Ice,
+        Material::PolishedStone,
+        Material::Furnace,
+        Material::LitFurnace,
+        Material::Hopper,
+        Material::Bed,
+        Material::Obsidian,
+        Material::Fire,
+        Material::Tnt,
+        Material::Ladder,
+        Material::Vine,
+        Material::Log,
+        Material::Leaves,
+    ];
+
+    /// Returns the material with the given name, e.g. read
+    /// from a save file
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the material
+    pub fn from_name(name: &str) -> Option<Material> {
+        Self::ALL.iter().copied().find(|material| material.name() == name)
+    }
+
+    /// Returns the name of the material, e.g. used
+    /// in save files and scripts
+    pub fn name(&self) -> &'static str {
+        self.data().name()
+    }
+
+    /// Returns the `BlockData` describing the material
+    pub fn data(&self) -> &'static BlockData {
+        match self {
+            Material::Air => &AIR,
+            Material::Grass => &GRASS,
+            Material::Dirt => &DIRT,
+            Material::Stone => &STONE,
+            Material::Glass => &GLASS,
+            Material::Lava => &LAVA,
+            Material::Glowstone => &GLOWSTONE,
+            Material::Farmland => &FARMLAND,
+            Material::Wheat => &WHEAT,
+            Material::Water => &WATER,
+            Material::Chest => &CHEST,
+            Material::Spawner => &SPAWNER,
+            Material::Sand => &SAND,
+            Material::TallGrass => &TALL_GRASS,
+            Material::Flower => &FLOWER,
+            Material::Mushroom => &MUSHROOM,
+            Material::Ice => &ICE,
+            Material::PolishedStone => &POLISHED_STONE,
+            Material::Furnace => &FURNACE,
+            Material::LitFurnace => &LIT_FURNACE,
+            Material::Hopper => &HOPPER,
+            Material::Bed => &BED,
+            Material::Obsidian => &OBSIDIAN,
+            Material::Fire => &FIRE,
+            Material::Tnt => &TNT,
+            Material::Ladder => &LADDER,
+            Material::Vine => &VINE,
+            Material::Log => &LOG,
+            Material::Leaves => &LEAVES,
+        }
+    }
+
+    /// Returns whether entities collide with the block, which
+    /// they don't with air and cross-shaped blocks like plants
+    pub fn is_solid(&self) -> bool {
+        *self != Material::Air && self.data().shape() == BlockShape::Cube
+    }
+
+    /// Returns whether the block is a fluid like water or lava
+    pub fn is_fluid(&self) -> bool {
+        matches!(self, Material::Water | Material::Lava)
+    }
+}
+
+/// The color of the light of blocks which don't tint it
+const WHITE_LIGHT: [f32; 3] = [1.0, 1.0, 1.0];
+
+/// Creates the coordinates of a sprite in the texture atlas
+const fn sprite(x: f32, y: f32) -> Vector2<f32> {
+    Vector2 { x, y }
+}
+
+static AIR: BlockData = BlockData {
+    name: "air",
+    tex_coords: BlockTextureCoords {
+        top: sprite(1.0, 15.0),
+        bottom: sprite(2.0, 15.0),
+        side: sprite(0.0, 15.0),
+    },
+    opaque: false,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.0,
+    sound_group: SoundGroup::Stone,
+};
+
+static GRASS: BlockData = BlockData {
+    name: "grass",
+    tex_coords: BlockTextureCoords {
+        top: sprite(1.0, 15.0),
+        bottom: sprite(2.0, 15.0),
+        side: sprite(0.0, 15.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.6,
+    sound_group: SoundGroup::Grass,
+};
+
+static DIRT: BlockData = BlockData {
+    name: "dirt",
+    tex_coords: BlockTextureCoords {
+        top: sprite(2.0, 15.0),
+        bottom: sprite(2.0, 15.0),
+        side: sprite(2.0, 15.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.5,
+    sound_group: SoundGroup::Grass,
+};
+
+// The atlas doesn't contain a stone sprite yet,
+// so stone uses the dirt sprite
+static STONE: BlockData = BlockData {
+    name: "stone",
+    tex_coords: BlockTextureCoords {
+        top: sprite(2.0, 15.0),
+        bottom: sprite(2.0, 15.0),
+        side: sprite(2.0, 15.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 1.5,
+    sound_group: SoundGroup::Stone,
+};
+
+// The sprite of a glass block without any connected
+// neighbours, see `ConnectedTextures`
+static GLASS: BlockData = BlockData {
+    name: "glass",
+    tex_coords: BlockTextureCoords {
+        top: sprite(0.0, 13.0),
+        bottom: sprite(0.0, 13.0),
+        side: sprite(0.0, 13.0),
+    },
+    opaque: false,
+    connects: true,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.3,
+    sound_group: SoundGroup::Stone,
+};
+
+static LAVA: BlockData = BlockData {
+    name: "lava",
+    tex_coords: BlockTextureCoords {
+        top: sprite(0.0, 11.0),
+        bottom: sprite(0.0, 11.0),
+        side: sprite(0.0, 11.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 1.5,
+    light_color: [1.0, 0.6, 0.25],
+    reflectivity: 0.0,
+    hardness: 0.0,
+    sound_group: SoundGroup::Stone,
+};
+
+static GLOWSTONE: BlockData = BlockData {
+    name: "glowstone",
+    tex_coords: BlockTextureCoords {
+        top: sprite(1.0, 11.0),
+        bottom: sprite(1.0, 11.0),
+        side: sprite(1.0, 11.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 1.0,
+    light_color: [1.0, 0.9, 0.7],
+    reflectivity: 0.0,
+    hardness: 0.3,
+    sound_group: SoundGroup::Stone,
+};
+
+static FARMLAND: BlockData = BlockData {
+    name: "farmland",
+    tex_coords: BlockTextureCoords {
+        top: sprite(2.0, 11.0),
+        bottom: sprite(2.0, 15.0),
+        side: sprite(2.0, 15.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.6,
+    sound_group: SoundGroup::Grass,
+};
+
+// The sprites of the growth stages follow the side
+// sprite in the same row of the atlas, see `BlockShape`
+static WHEAT: BlockData = BlockData {
+    name: "wheat",
+    tex_coords: BlockTextureCoords {
+        top: sprite(0.0, 10.0),
+        bottom: sprite(0.0, 10.0),
+        side: sprite(0.0, 10.0),
+    },
+    opaque: false,
+    connects: false,
+    shape: BlockShape::Cross,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.0,
+    sound_group: SoundGroup::Grass,
+};
+
+static WATER: BlockData = BlockData {
+    name: "water",
+    tex_coords: BlockTextureCoords {
+        top: sprite(3.0, 11.0),
+        bottom: sprite(3.0, 11.0),
+        side: sprite(3.0, 11.0),
+    },
+    opaque: false,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.0,
+    sound_group: SoundGroup::Stone,
+};
+
+// Chests keep their loot in a block entity, see `BlockEntity`
+static CHEST: BlockData = BlockData {
+    name: "chest",
+    tex_coords: BlockTextureCoords {
+        top: sprite(5.0, 11.0),
+        bottom: sprite(5.0, 11.0),
+        side: sprite(4.0, 11.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 2.5,
+    sound_group: SoundGroup::Wood,
+};
+
+// A cage the mobs of its block entity are spawned
+// from, see `BlockEntity`
+static SPAWNER: BlockData = BlockData {
+    name: "spawner",
+    tex_coords: BlockTextureCoords {
+        top: sprite(6.0, 11.0),
+        bottom: sprite(6.0, 11.0),
+        side: sprite(6.0, 11.0),
+    },
+    opaque: false,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 5.0,
+    sound_group: SoundGroup::Stone,
+};
+
+static SAND: BlockData = BlockData {
+    name: "sand",
+    tex_coords: BlockTextureCoords {
+        top: sprite(7.0, 11.0),
+        bottom: sprite(7.0, 11.0),
+        side: sprite(7.0, 11.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.5,
+    sound_group: SoundGroup::Sand,
+};
+
+// The plants decorating the terrain, see `Vegetation`
+static TALL_GRASS: BlockData = BlockData {
+    name: "tall_grass",
+    tex_coords: BlockTextureCoords {
+        top: sprite(8.0, 11.0),
+        bottom: sprite(8.0, 11.0),
+        side: sprite(8.0, 11.0),
+    },
+    opaque: false,
+    connects: false,
+    shape: BlockShape::Cross,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.0,
+    sound_group: SoundGroup::Grass,
+};
+
+static FLOWER: BlockData = BlockData {
+    name: "flower",
+    tex_coords: BlockTextureCoords {
+        top: sprite(9.0, 11.0),
+        bottom: sprite(9.0, 11.0),
+        side: sprite(9.0, 11.0),
+    },
+    opaque: false,
+    connects: false,
+    shape: BlockShape::Cross,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.0,
+    sound_group: SoundGroup::Grass,
+};
+
+static MUSHROOM: BlockData = BlockData {
+    name: "mushroom",
+    tex_coords: BlockTextureCoords {
+        top: sprite(10.0, 11.0),
+        bottom: sprite(10.0, 11.0),
+        side: sprite(10.0, 11.0),
+    },
+    opaque: false,
+    connects: false,
+    shape: BlockShape::Cross,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.0,
+    sound_group: SoundGroup::Grass,
+};
+
+// Reflective blocks mirror their surroundings, see `EnvironmentMap`
+static ICE: BlockData = BlockData {
+    name: "ice",
+    tex_coords: BlockTextureCoords {
+        top: sprite(11.0, 11.0),
+        bottom: sprite(11.0, 11.0),
+        side: sprite(11.0, 11.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.45,
+    hardness: 0.5,
+    sound_group: SoundGroup::Stone,
+};
+
+static POLISHED_STONE: BlockData = BlockData {
+    name: "polished_stone",
+    tex_coords: BlockTextureCoords {
+        top: sprite(12.0, 11.0),
+        bottom: sprite(12.0, 11.0),
+        side: sprite(12.0, 11.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.2,
+    hardness: 1.5,
+    sound_group: SoundGroup::Stone,
+};
+
+// A furnace smelting the items of its block entity, see
+// `Smelting`, which is swapped with the lit one while it burns fuel
+static FURNACE: BlockData = BlockData {
+    name: "furnace",
+    tex_coords: BlockTextureCoords {
+        top: sprite(12.0, 11.0),
+        bottom: sprite(12.0, 11.0),
+        side: sprite(13.0, 11.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 3.5,
+    sound_group: SoundGroup::Stone,
+};
+
+static LIT_FURNACE: BlockData = BlockData {
+    name: "lit_furnace",
+    tex_coords: BlockTextureCoords {
+        top: sprite(12.0, 11.0),
+        bottom: sprite(12.0, 11.0),
+        side: sprite(14.0, 11.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.85,
+    light_color: [1.0, 0.7, 0.4],
+    reflectivity: 0.0,
+    hardness: 3.5,
+    sound_group: SoundGroup::Stone,
+};
+
+// Moves items between containers, facing the way its
+// block state tells, see `hopper`
+static HOPPER: BlockData = BlockData {
+    name: "hopper",
+    tex_coords: BlockTextureCoords {
+        top: sprite(12.0, 11.0),
+        bottom: sprite(12.0, 11.0),
+        side: sprite(15.0, 11.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 3.0,
+    sound_group: SoundGroup::Stone,
+};
+
+// Spans two blocks, whose block states tell which half
+// they are and the way the bed faces, see `bed`
+static BED: BlockData = BlockData {
+    name: "bed",
+    tex_coords: BlockTextureCoords {
+        top: sprite(8.0, 10.0),
+        bottom: sprite(10.0, 10.0),
+        side: sprite(9.0, 10.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.5,
+    sound_group: SoundGroup::Wood,
+};
+
+// Forms where water touches still lava, see `fluids`
+static OBSIDIAN: BlockData = BlockData {
+    name: "obsidian",
+    tex_coords: BlockTextureCoords {
+        top: sprite(11.0, 10.0),
+        bottom: sprite(11.0, 10.0),
+        side: sprite(11.0, 10.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 25.0,
+    sound_group: SoundGroup::Stone,
+};
+
+// Spreads to flammable blocks and burns them away, see `fire`
+static FIRE: BlockData = BlockData {
+    name: "fire",
+    tex_coords: BlockTextureCoords {
+        top: sprite(12.0, 10.0),
+        bottom: sprite(12.0, 10.0),
+        side: sprite(12.0, 10.0),
+    },
+    opaque: false,
+    connects: false,
+    shape: BlockShape::Cross,
+    climbable: false,
+    emission: 1.0,
+    light_color: [1.0, 0.7, 0.35],
+    reflectivity: 0.0,
+    hardness: 0.0,
+    sound_group: SoundGroup::Grass,
+};
+
+static TNT: BlockData = BlockData {
+    name: "tnt",
+    tex_coords: BlockTextureCoords {
+        top: sprite(14.0, 10.0),
+        bottom: sprite(14.0, 10.0),
+        side: sprite(13.0, 10.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.0,
+    sound_group: SoundGroup::Grass,
+};
+
+static LADDER: BlockData = BlockData {
+    name: "ladder",
+    tex_coords: BlockTextureCoords {
+        top: sprite(15.0, 10.0),
+        bottom: sprite(15.0, 10.0),
+        side: sprite(15.0, 10.0),
+    },
+    opaque: false,
+    connects: false,
+    shape: BlockShape::Cross,
+    climbable: true,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.4,
+    sound_group: SoundGroup::Wood,
+};
+
+static VINE: BlockData = BlockData {
+    name: "vine",
+    tex_coords: BlockTextureCoords {
+        top: sprite(0.0, 9.0),
+        bottom: sprite(0.0, 9.0),
+        side: sprite(0.0, 9.0),
+    },
+    opaque: false,
+    connects: false,
+    shape: BlockShape::Cross,
+    climbable: true,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.2,
+    sound_group: SoundGroup::Grass,
+};
+
+static LOG: BlockData = BlockData {
+    name: "log",
+    tex_coords: BlockTextureCoords {
+        top: sprite(2.0, 9.0),
+        bottom: sprite(2.0, 9.0),
+        side: sprite(1.0, 9.0),
+    },
+    opaque: true,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 2.0,
+    sound_group: SoundGroup::Wood,
+};
+
+static LEAVES: BlockData = BlockData {
+    name: "leaves",
+    tex_coords: BlockTextureCoords {
+        top: sprite(3.0, 9.0),
+        bottom: sprite(3.0, 9.0),
+        side: sprite(3.0, 9.0),
+    },
+    opaque: false,
+    connects: false,
+    shape: BlockShape::Cube,
+    climbable: false,
+    emission: 0.0,
+    light_color: WHITE_LIGHT,
+    reflectivity: 0.0,
+    hardness: 0.2,
+    sound_group: SoundGroup::Grass,
+};
+
+/// BlockShape
+///
+/// The shape of the model of a block:
+/// * `Cube` - A full cube, whose faces are merged with the faces of adjacent blocks
+/// * `Cross` - Two diagonal quads crossing each other, e.g. for plants. The sprite is picked by the state of the block, counted from the side sprite to the right.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BlockShape {
+    Cube,
+    Cross,
+}
+
+/// SoundGroup
+///
+/// The sounds which are played when a block is stepped on,
+/// broken or placed
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum SoundGroup {
+    Stone,
+    Wood,
+    Grass,
+    Sand,
+}
+
+impl SoundGroup {
+    /// All sound groups
+    pub const ALL: [SoundGroup; 4] = [SoundGroup::Stone, SoundGroup::Wood, SoundGroup::Grass, SoundGroup::Sand];
+
+    /// Returns the sound group with the given name, e.g.
+    /// given by a script
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the sound group
+    pub fn from_name(name: &str) -> Option<SoundGroup> {
+        Self::ALL.iter().copied().find(|group| group.name() == name)
+    }
+
+    /// Returns the name of the sound group, which is the
+    /// directory of its sounds as well
+    pub fn name(&self) -> &'static str {
+        match self {
+            SoundGroup::Stone => "stone",
+            SoundGroup::Wood => "wood",
+            SoundGroup::Grass => "grass",
+            SoundGroup::Sand => "sand",
+        }
+    }
 }
 
 /// BlockTextureCoords
@@ -26,6 +746,23 @@ pub struct BlockTextureCoords {
     side: Vector2<f32>,
 }
 
+impl BlockTextureCoords {
+    /// Returns the coordinates of the top view
+    pub fn top(&self) -> Vector2<f32> {
+        self.top
+    }
+
+    /// Returns the coordinates of the bottom view
+    pub fn bottom(&self) -> Vector2<f32> {
+        self.bottom
+    }
+
+    /// Returns the coordinates of the side view
+    pub fn side(&self) -> Vector2<f32> {
+        self.side
+    }
+}
+
 /// BlockData
 ///
 /// The `BlockData` stores the nature, character and texture
@@ -38,6 +775,29 @@ pub struct BlockData {
     tex_coords: BlockTextureCoords,
     /// A block could either be `opaque` (true) or transparent (false)
     opaque: bool,
+    /// Whether the faces of the block connect with the
+    /// faces of adjacent blocks of the same type
+    connects: bool,
+    /// The shape of the model of the block
+    shape: BlockShape,
+    /// Whether the player climbs up and down the block
+    /// instead of falling through it, e.g. ladders
+    climbable: bool,
+    /// How strong the block glows, where `0.0` doesn't glow
+    /// at all. Glowing blocks are drawn with their own sprite
+    /// and contribute to the bloom.
+    emission: f32,
+    /// The color of the light the block emits, whose channels
+    /// its emission is multiplied with
+    light_color: [f32; 3],
+    /// How much of its surroundings the block reflects,
+    /// where `0.0` doesn't reflect anything
+    reflectivity: f32,
+    /// The time in seconds it takes to break the block by hand,
+    /// where `0.0` breaks it at once
+    hardness: f32,
+    /// The sounds of the block, unless a script replaced them
+    sound_group: SoundGroup,
 }
 
 impl BlockData {
@@ -51,4 +811,57 @@ impl BlockData {
     pub fn tex_coords(&self) -> &BlockTextureCoords {
         &self.tex_coords
     }
+
+    /// Returns whether the block is opaque
+    pub fn is_opaque(&self) -> bool {
+        self.opaque
+    }
+
+    /// Returns whether the faces of the block connect with
+    /// the faces of adjacent blocks of the same type
+    pub fn connects(&self) -> bool {
+        self.connects
+    }
+
+    /// Returns the shape of the model of the block
+    pub fn shape(&self) -> BlockShape {
+        self.shape
+    }
+
+    /// Returns whether the player climbs the block
+    pub fn is_climbable(&self) -> bool {
+        self.climbable
+    }
+
+    /// Returns how strong the block glows
+    pub fn emission(&self) -> f32 {
+        self.emission
+    }
+
+    /// Returns the color of the light the block emits
+    pub fn light_color(&self) -> [f32; 3] {
+        self.light_color
+    }
+
+    /// Returns whether the block glows
+    pub fn is_emissive(&self) -> bool {
+        self.emission > 0.0
+    }
+
+    /// Returns how much of its surroundings the block reflects
+    pub fn reflectivity(&self) -> f32 {
+        self.reflectivity
+    }
+
+    /// Returns the time in seconds it takes to break
+    /// the block by hand
+    pub fn hardness(&self) -> f32 {
+        self.hardness
+    }
+
+    /// Returns the sounds of the block, unless a script
+    /// replaced them
+    pub fn sound_group(&self) -> SoundGroup {
+        self.sound_group
+    }
 }
\ No newline at end of file
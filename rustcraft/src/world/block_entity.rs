@@ -0,0 +1,150 @@
+//! Types storing the data of blocks which doesn't fit
+//! into their state, e.g. the loot of a chest
+
+use crate::world::block::Material;
+use crate::world::furnace::{Furnace, FurnaceSlot, Smelting, MAX_STACK};
+use crate::world::hopper::Hopper;
+use serde::{Deserialize, Serialize};
+
+/// The mob spawners spawn if their structure doesn't declare one
+pub const DEFAULT_MOB: &str = "zombie";
+/// The amount of stacks a chest holds
+pub const CHEST_SLOTS: usize = 27;
+
+/// BlockEntity
+///
+/// The data of a single block, which is stored with its
+/// chunk and removed once the block is replaced:
+/// * `Chest` - A chest whose loot is rolled from a loot table
+///   once it's broken, and the items hoppers put into it
+/// * `Spawner` - A spawner of mobs, which spawns them once
+///   its cooldown is over while a player is close
+/// * `Furnace` - A furnace with the items it smelts, which
+///   is created once the player opens it
+/// * `Hopper` - A hopper with the items it moves
+///
+/// Chests, furnaces and hoppers are containers, which
+/// hoppers pull items from and push items into.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BlockEntity {
+    Chest {
+        /// The name of the loot table, empty for
+        /// chests without loot
+        loot_table: String,
+        /// The stacks of items put into the chest
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        items: Vec<(String, u64)>,
+    },
+    Spawner {
+        /// The kind of the spawned mobs
+        mob: String,
+        /// The time in seconds until the next mobs are spawned
+        cooldown: f32,
+    },
+    Furnace(Furnace),
+    Hopper(Hopper),
+}
+
+impl BlockEntity {
+    /// Creates a new chest
+    ///
+    /// # Arguments
+    ///
+    /// * `loot_table` - The name of the loot table
+    pub fn chest(loot_table: &str) -> Self {
+        BlockEntity::Chest {
+            loot_table: loot_table.to_string(),
+            items: Vec::new(),
+        }
+    }
+
+    /// Creates a new spawner, which spawns its first
+    /// mobs as soon as a player comes close
+    ///
+    /// # Arguments
+    ///
+    /// * `mob` - The kind of the spawned mobs
+    pub fn spawner(mob: &str) -> Self {
+        BlockEntity::Spawner {
+            mob: mob.to_string(),
+            cooldown: 0.0,
+        }
+    }
+
+    /// Creates the empty container of a block, e.g. for a
+    /// chest the player placed, or `None` if the block
+    /// isn't a container
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    pub fn container(material: Material) -> Option<Self> {
+        match material {
+            Material::Chest => Some(Self::chest("")),
+            Material::Furnace | Material::LitFurnace => Some(BlockEntity::Furnace(Furnace::default())),
+            Material::Hopper => Some(BlockEntity::Hopper(Hopper::default())),
+            _ => None,
+        }
+    }
+
+    /// Returns the item a hopper would pull out of the container
+    /// next, which is the first stack of a chest, the output of
+    /// a furnace and the items of a hopper
+    pub fn peek(&self) -> Option<&str> {
+        let stack = match self {
+            BlockEntity::Chest { items, .. } => items.first(),
+            BlockEntity::Furnace(furnace) => furnace.slot(FurnaceSlot::Output),
+            BlockEntity::Hopper(hopper) => hopper.items(),
+            BlockEntity::Spawner { .. } => None,
+        };
+        stack.map(|(item, _)| item.as_str())
+    }
+
+    /// Pulls a single item out of the container, see `peek`
+    pub fn extract(&mut self) -> Option<String> {
+        match self {
+            BlockEntity::Chest { items, .. } => {
+                let (item, count) = items.first_mut()?;
+                *count -= 1;
+                let item = item.clone();
+                if *count == 0 {
+                    items.remove(0);
+                }
+                Some(item)
+            }
+            BlockEntity::Furnace(furnace) => furnace.take_one(FurnaceSlot::Output),
+            BlockEntity::Hopper(hopper) => hopper.take_one(),
+            BlockEntity::Spawner { .. } => None,
+        }
+    }
+
+    /// Puts a single item into the container and returns
+    /// whether it fit. Items put into a furnace from above
+    /// are smelted, while the ones from its sides are fuel.
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - The name of the item
+    /// * `from_above` - Whether the item comes from above
+    /// * `smelting` - The smelting recipes and fuels
+    pub fn accept(&mut self, item: &str, from_above: bool, smelting: &Smelting) -> bool {
+        match self {
+            BlockEntity::Chest { items, .. } => {
+                let full = items.len() >= CHEST_SLOTS;
+                match items.iter_mut().find(|(name, count)| name == item && *count < MAX_STACK) {
+                    Some((_, count)) => *count += 1,
+                    None if !full => items.push((item.to_string(), 1)),
+                    None => return false,
+                }
+                true
+            }
+            BlockEntity::Furnace(furnace) => {
+                let slot = if from_above { FurnaceSlot::Input } else { FurnaceSlot::Fuel };
+                furnace.insert(slot, item, 1, smelting) == 1
+            }
+            BlockEntity::Hopper(hopper) => hopper.insert(item),
+            BlockEntity::Spawner { .. } => false,
+        }
+    }
+}
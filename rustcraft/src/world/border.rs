@@ -0,0 +1,259 @@
+//! Types to limit where the player could go and edit
+//! blocks, like the world border and the spawn protection
+
+use crate::entity::PLAYER_WIDTH;
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::mesh::{Mesh, MeshBuilder, Model};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::shader::ShaderProgram;
+use crate::resources::Resources;
+use crate::world::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
+use cgmath::{Matrix4, Vector2, Vector3};
+use std::time::Instant;
+
+/// The distance to the camera within which the wall of
+/// the world border fades in
+pub const BORDER_FADE_DISTANCE: f32 = 24.0;
+
+/// WorldBorder
+///
+/// A square around the origin of the world. The player can't
+/// leave it, and chunks outside of it aren't loaded.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WorldBorder {
+    /// The distance from the origin to each side in blocks
+    radius: f32,
+}
+
+impl WorldBorder {
+    /// Creates a new world border
+    ///
+    /// # Arguments
+    ///
+    /// * `radius` - The distance from the origin to each side in blocks
+    pub fn new(radius: u32) -> Self {
+        Self {
+            radius: radius as f32,
+        }
+    }
+
+    /// Returns the distance from the origin to each side in blocks
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Returns whether a position is within the border
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position in world coordinates
+    pub fn contains(&self, pos: Vector3<f32>) -> bool {
+        pos.x.abs() <= self.radius && pos.z.abs() <= self.radius
+    }
+
+    /// Returns whether a chunk is at least partly within
+    /// the border, so that it should be loaded
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the chunk
+    pub fn contains_chunk(&self, loc: &Vector2<i32>) -> bool {
+        let size = CHUNK_SIZE as f32;
+        let overlaps = |start: i32| {
+            let min = start as f32 * size;
+            min < self.radius && min + size > -self.radius
+        };
+        overlaps(loc.x) && overlaps(loc.y)
+    }
+
+    /// Moves the player's eyes back within the border, so
+    /// that their body doesn't reach beyond it
+    ///
+    /// # Arguments
+    ///
+    /// * `eye` - The position of the player's eyes
+    pub fn clamp_player(&self, eye: Vector3<f32>) -> Vector3<f32> {
+        let limit = (self.radius - PLAYER_WIDTH / 2.0).max(0.0);
+        Vector3::new(eye.x.clamp(-limit, limit), eye.y, eye.z.clamp(-limit, limit))
+    }
+
+    /// Returns the horizontal distance of a position
+    /// within the border to the closest side
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position in world coordinates
+    pub fn distance(&self, pos: Vector3<f32>) -> f32 {
+        (self.radius - pos.x.abs()).min(self.radius - pos.z.abs())
+    }
+}
+
+/// SpawnProtection
+///
+/// A square around the spawn in which only operators
+/// could break and place blocks
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpawnProtection {
+    /// The position the player spawns at
+    spawn: Vector3<f32>,
+    /// The distance from the spawn to each side in
+    /// blocks, `0` for no protection
+    radius: u32,
+}
+
+impl SpawnProtection {
+    /// Creates a new spawn protection
+    ///
+    /// # Arguments
+    ///
+    /// * `spawn` - The position the player spawns at
+    /// * `radius` - The distance from the spawn to each side in blocks, `0` for no protection
+    pub fn new(spawn: Vector3<f32>, radius: u32) -> Self {
+        Self {
+            spawn,
+            radius,
+        }
+    }
+
+    /// Returns whether the block at the given position is protected
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    pub fn protects(&self, pos: Vector3<i32>) -> bool {
+        if self.radius == 0 {
+            return false;
+        }
+        let spawn = Vector2::new(self.spawn.x.floor() as i32, self.spawn.z.floor() as i32);
+        let radius = self.radius as i32;
+        (pos.x - spawn.x).abs() <= radius && (pos.z - spawn.y).abs() <= radius
+    }
+}
+
+/// BorderFrame
+///
+/// A frame the world border could be rendered for
+pub trait BorderFrame: Frame {
+    /// Returns the world border of the dimension the player is in
+    fn border(&self) -> Option<&WorldBorder>;
+}
+
+/// WorldBorderRenderer
+///
+/// The `WorldBorderRenderer` is the render pass drawing the
+/// world border as translucent walls with moving stripes.
+/// The walls fade in as the camera gets close to them.
+pub struct WorldBorderRenderer {
+    /// A shader program
+    shader_program: ShaderProgram,
+    /// The walls of a border with the radius `1`
+    walls: Model,
+    /// The time the renderer has been created, which
+    /// animates the stripes
+    start: Instant,
+}
+
+impl WorldBorderRenderer {
+    /// Creates a new world border renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        let shader_program = backend.create_pipeline(resources, "border").unwrap();
+        shader_program.disable();
+
+        Self {
+            shader_program,
+            walls: Model::from_mesh(backend, &walls_mesh()),
+            start: Instant::now(),
+        }
+    }
+}
+
+impl<F: BorderFrame> RenderPass<F> for WorldBorderRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Transparent
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let border = match ctx.frame.border() {
+            Some(border) => border,
+            None => return,
+        };
+        let camera = ctx.frame.camera();
+        if border.distance(*camera.pos()) > BORDER_FADE_DISTANCE {
+            return;
+        }
+
+        let model = Matrix4::from_nonuniform_scale(border.radius(), CHUNK_HEIGHT as f32, border.radius());
+        let mvp = camera.proj_matrix() * camera.view_matrix() * model;
+
+        self.shader_program.enable();
+        self.shader_program.set_uniform_mat4f("u_MVP", &mvp);
+        self.shader_program.set_uniform_mat4f("u_Model", &model);
+        self.shader_program.set_uniform_3f("u_CameraPos", camera.pos().x, camera.pos().y, camera.pos().z);
+        self.shader_program.set_uniform_1f("u_FadeDistance", BORDER_FADE_DISTANCE);
+        self.shader_program.set_uniform_1f("u_Time", self.start.elapsed().as_secs_f32());
+        self.walls.bind();
+
+        // The walls are translucent, so they must not occlude
+        // anything rendered after them
+        ctx.renderer.set_depth_write(false);
+        ctx.renderer.draw_with(self.walls.va(), self.walls.ib(), &self.shader_program, self.walls.topology());
+        ctx.renderer.set_depth_write(true);
+
+        self.walls.unbind();
+        self.shader_program.disable();
+    }
+}
+
+/// Returns the four walls of a border with the radius `1` and
+/// the height `1`, facing both inwards and outwards
+fn walls_mesh() -> Mesh {
+    let mut builder = MeshBuilder::new();
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+    for i in 0..corners.len() {
+        let (x0, z0) = corners[i];
+        let (x1, z1) = corners[(i + 1) % corners.len()];
+        let bottom = (Vector3::new(x0, 0.0, z0), Vector3::new(x1, 0.0, z1));
+        let top = (Vector3::new(x0, 1.0, z0), Vector3::new(x1, 1.0, z1));
+        let inwards = Vector3::new(z0 - z1, 0.0, x1 - x0) / 2.0;
+        builder.quad([bottom.0, bottom.1, top.1, top.0], inwards);
+        builder.quad([bottom.1, bottom.0, top.0, top.1], -inwards);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_within_the_border_are_loaded() {
+        let border = WorldBorder::new(20);
+        assert!(border.contains_chunk(&Vector2::new(0, 0)));
+        assert!(border.contains_chunk(&Vector2::new(-2, 1)));
+        assert!(!border.contains_chunk(&Vector2::new(2, 0)));
+        assert!(!border.contains_chunk(&Vector2::new(0, -3)));
+    }
+
+    #[test]
+    fn player_is_kept_within_the_border() {
+        let border = WorldBorder::new(10);
+        let eye = border.clamp_player(Vector3::new(12.0, 5.0, -3.0));
+        assert_eq!(eye, Vector3::new(10.0 - PLAYER_WIDTH / 2.0, 5.0, -3.0));
+        assert!(border.contains(eye));
+        assert!(!border.contains(Vector3::new(0.0, 0.0, -10.5)));
+        assert_eq!(border.distance(Vector3::new(7.0, 0.0, -1.0)), 3.0);
+    }
+
+    #[test]
+    fn blocks_around_the_spawn_are_protected() {
+        let protection = SpawnProtection::new(Vector3::new(0.5, 10.0, 0.5), 4);
+        assert!(protection.protects(Vector3::new(4, 0, -4)));
+        assert!(!protection.protects(Vector3::new(5, 0, 0)));
+        assert!(!SpawnProtection::new(Vector3::new(0.0, 0.0, 0.0), 0).protects(Vector3::new(0, 0, 0)));
+    }
+}
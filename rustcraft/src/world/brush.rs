@@ -0,0 +1,240 @@
+//! Brushes sculpting the terrain around the block the
+//! player is looking at, e.g. to place spheres of stone
+//! or to smooth hills
+
+use crate::world::World;
+use crate::world::block::Material;
+use cgmath::Vector3;
+use mlua::{FromLua, Lua, Value, Variadic};
+use std::collections::HashMap;
+
+/// The name of the tool applying the brush by placing a block
+pub const BRUSH: &str = "brush";
+/// The distance to the farthest block a brush could be applied
+/// to, which is farther than blocks could be placed
+pub const BRUSH_REACH: f32 = 64.0;
+/// The largest radius of a brush, so that a stroke is
+/// within the edit history and could be undone
+pub const MAX_RADIUS: i32 = 8;
+/// The amount of times the heightfield is blurred by
+/// the smooth brush
+const SMOOTH_PASSES: usize = 2;
+
+/// Brush
+///
+/// The shape a brush changes around the targeted block
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Brush {
+    /// A sphere of a material centered at the block
+    Sphere { material: Material, radius: i32 },
+    /// A cylinder of a material standing on the block
+    Cylinder { material: Material, radius: i32, height: i32 },
+    /// The surface around the block is blurred, which
+    /// flattens bumps and fills holes
+    Smooth { radius: i32 },
+}
+
+impl Brush {
+    /// Creates a brush from the arguments of `worldedit.brush`,
+    /// which are a material and a radius for spheres, an additional
+    /// height for cylinders and only a radius to smooth
+    ///
+    /// # Arguments
+    ///
+    /// * `lua` - The Lua state
+    /// * `shape` - The name of the shape, e.g. `sphere`
+    /// * `args` - The remaining arguments
+    pub fn from_args<'lua>(lua: &'lua Lua, shape: &str, args: Variadic<Value<'lua>>) -> mlua::Result<Self> {
+        let mut args = args.into_iter();
+        let mut next = |name: &str| args.next().ok_or_else(|| {
+            mlua::Error::RuntimeError(format!("the {} brush is missing its {}", shape, name))
+        });
+        let brush = match shape {
+            "sphere" | "cylinder" => {
+                let name = String::from_lua(next("block")?, lua)?;
+                let material = Material::from_name(&name).ok_or_else(|| {
+                    mlua::Error::RuntimeError(format!("unknown block {}", name))
+                })?;
+                let radius = i32::from_lua(next("radius")?, lua)?;
+                if shape == "sphere" {
+                    Brush::Sphere { material, radius }
+                } else {
+                    let height = next("height").map_or(Ok(1), |height| i32::from_lua(height, lua))?;
+                    if !(1..=2 * MAX_RADIUS + 1).contains(&height) {
+                        return Err(mlua::Error::RuntimeError(format!("the height must be within 1 and {}", 2 * MAX_RADIUS + 1)));
+                    }
+                    Brush::Cylinder { material, radius, height }
+                }
+            }
+            "smooth" => Brush::Smooth { radius: i32::from_lua(next("radius")?, lua)? },
+            _ => return Err(mlua::Error::RuntimeError(format!("unknown brush {}", shape))),
+        };
+        if !(1..=MAX_RADIUS).contains(&brush.radius()) {
+            return Err(mlua::Error::RuntimeError(format!("the radius must be within 1 and {}", MAX_RADIUS)));
+        }
+        Ok(brush)
+    }
+
+    /// Returns the radius of the brush
+    pub fn radius(&self) -> i32 {
+        match *self {
+            Brush::Sphere { radius, .. } | Brush::Cylinder { radius, .. } | Brush::Smooth { radius } => radius,
+        }
+    }
+
+    /// Returns the edits of a stroke of the brush, which are
+    /// applied at once
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world
+    /// * `center` - The position of the targeted block
+    pub fn edits(&self, world: &World, center: Vector3<i32>) -> Vec<(Vector3<i32>, Material)> {
+        match *self {
+            Brush::Sphere { material, radius } => (-radius..=radius)
+                .flat_map(|y| disc(radius).map(move |(x, z)| Vector3::new(x, y, z)))
+                .filter(|offset| within(offset.x * offset.x + offset.y * offset.y + offset.z * offset.z, radius))
+                .map(|offset| (center + offset, material))
+                .collect(),
+            Brush::Cylinder { material, radius, height } => (0..height)
+                .flat_map(|y| disc(radius).map(move |(x, z)| (center + Vector3::new(x, y, z), material)))
+                .collect(),
+            Brush::Smooth { radius } => smooth(world, center, radius),
+        }
+    }
+}
+
+/// Returns whether a squared distance is within a radius, which
+/// includes half a block more to round the shapes off
+///
+/// # Arguments
+///
+/// * `distance2` - The squared distance
+/// * `radius` - The radius
+fn within(distance2: i32, radius: i32) -> bool {
+    distance2 <= radius * radius + radius
+}
+
+/// Returns the horizontal offsets of the columns within a radius
+///
+/// # Arguments
+///
+/// * `radius` - The radius
+fn disc(radius: i32) -> impl Iterator<Item = (i32, i32)> {
+    (-radius..=radius)
+        .flat_map(move |z| (-radius..=radius).map(move |x| (x, z)))
+        .filter(move |&(x, z)| within(x * x + z * z, radius))
+}
+
+/// Returns the edits blurring the surface around a block. The
+/// height of every column is averaged with its neighbours, then
+/// columns are raised with the blocks below their surface or
+/// lowered by removing their top blocks. Only the surface within
+/// the radius above and below the block is changed.
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `center` - The position of the targeted block
+/// * `radius` - The radius
+fn smooth(world: &World, center: Vector3<i32>, radius: i32) -> Vec<(Vector3<i32>, Material)> {
+    let (bottom, top) = (center.y - radius, center.y + radius);
+    let solid = |x: i32, y: i32, z: i32| world.block(Vector3::new(x, y, z)).filter(Material::is_solid);
+
+    let heights: HashMap<(i32, i32), i32> = disc(radius)
+        .map(|(x, z)| (center.x + x, center.z + z))
+        .filter_map(|(x, z)| (bottom..=top).rev().find(|&y| solid(x, y, z).is_some()).map(|y| ((x, z), y)))
+        .collect();
+
+    let mut blurred: HashMap<(i32, i32), f32> = heights.iter().map(|(&column, &y)| (column, y as f32)).collect();
+    for _ in 0..SMOOTH_PASSES {
+        blurred = blurred.iter()
+            .map(|(&(x, z), _)| {
+                let neighbours: Vec<f32> = (-1..=1)
+                    .flat_map(|dz| (-1..=1).map(move |dx| (x + dx, z + dz)))
+                    .filter_map(|column| blurred.get(&column).copied())
+                    .collect();
+                ((x, z), neighbours.iter().sum::<f32>() / neighbours.len() as f32)
+            })
+            .collect();
+    }
+
+    let mut edits = Vec::new();
+    for (&(x, z), &old) in &heights {
+        let new = (blurred[&(x, z)].round() as i32).clamp(bottom, top);
+        let surface = match solid(x, old, z) {
+            Some(surface) => surface,
+            None => continue,
+        };
+        if new > old {
+            let filler = solid(x, old - 1, z).unwrap_or(surface);
+            edits.extend((old..new).map(|y| (Vector3::new(x, y, z), filler)));
+            edits.push((Vector3::new(x, new, z), surface));
+        } else if new < old {
+            edits.extend((new + 1..=old).map(|y| (Vector3::new(x, y, z), Material::Air)));
+            edits.push((Vector3::new(x, new, z), surface));
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::terrain_generator::FlatTerrainGen;
+    use crate::world::time::SkySettings;
+    use cgmath::Vector2;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn shapes_are_rounded_off() {
+        let world = World::with_generator(FlatTerrainGen {}, SkySettings::default());
+        let center = Vector3::new(10, 20, 10);
+
+        let sphere = Brush::Sphere { material: Material::Stone, radius: 1 }.edits(&world, center);
+        assert_eq!(sphere.len(), 19);
+        assert!(sphere.iter().all(|&(_, material)| material == Material::Stone));
+
+        let cylinder = Brush::Cylinder { material: Material::Stone, radius: 2, height: 3 }.edits(&world, center);
+        assert_eq!(cylinder.len(), 3 * 21);
+        assert!(cylinder.iter().all(|&(pos, _)| pos.y >= 20 && pos.y < 23));
+    }
+
+    #[test]
+    fn smoothing_flattens_bumps() {
+        let mut world = World::with_generator(FlatTerrainGen {}, SkySettings::default());
+        world.load_chunk(&Vector2::new(0, 0));
+        let start = Instant::now();
+        while !world.chunks()[0].is_generated() {
+            assert!(start.elapsed() < Duration::from_secs(10), "the chunk hasn't been generated");
+            thread::yield_now();
+        }
+        for y in 5..=7 {
+            world.set_block(Vector3::new(8, y, 8), Material::Stone);
+        }
+
+        let edits = Brush::Smooth { radius: 3 }.edits(&world, Vector3::new(8, 4, 8));
+        world.edit_blocks(&edits);
+        assert!((5..=7).all(|y| world.block(Vector3::new(8, y, 8)) == Some(Material::Air)));
+        assert_eq!(world.block(Vector3::new(8, 4, 8)), Some(Material::Stone));
+
+        // A flat surface stays as it is
+        assert!(Brush::Smooth { radius: 3 }.edits(&world, Vector3::new(3, 4, 3)).is_empty());
+    }
+
+    #[test]
+    fn brushes_are_created_from_script_arguments() {
+        let lua = Lua::new();
+        let brush = |shape: &str, code: &str| {
+            let args: Variadic<Value> = lua.load(code).eval().unwrap();
+            Brush::from_args(&lua, shape, args)
+        };
+        assert_eq!(brush("sphere", r#"return "stone", 5"#).unwrap(), Brush::Sphere { material: Material::Stone, radius: 5 });
+        assert_eq!(brush("cylinder", r#"return "dirt", 2"#).unwrap(), Brush::Cylinder { material: Material::Dirt, radius: 2, height: 1 });
+        assert_eq!(brush("smooth", "return 4").unwrap(), Brush::Smooth { radius: 4 });
+        assert!(brush("sphere", r#"return "stone""#).is_err());
+        assert!(brush("sphere", r#"return "stone", 20"#).is_err());
+        assert!(brush("cube", "return 4").is_err());
+    }
+}
@@ -1,22 +1,40 @@
 use cgmath::{Vector3, Vector2};
-use crate::world::block::{Material};
+use crate::world::block::{BlockShape, Material};
+use crate::world::block_entity::BlockEntity;
+use crate::world::connected::{self, ConnectedTextures};
+use crate::world::light::{self, LightFormat, LightMap, SKY_LIT_TEXEL};
+use crate::world::overlay::{BlockOverlays, Overlay};
+use crate::world::texture_pack::TexturePack;
 use crate::resources::Resources;
-use crate::camera::PerspectiveCamera;
+use crate::camera::Projection;
 use crate::entity::Entity;
-use crate::gl;
-use crate::graphics::gl::Gl;
+use crate::graphics::attribute;
+use crate::graphics::backend::{GlBackend, RenderBackend, TexelFormat};
+use crate::graphics::cave_fog::CaveFogSettings;
+use crate::graphics::debug_view::{DebugView, DEBUG_VIEWS};
+use crate::graphics::environment::EnvironmentMap;
 use crate::graphics::mesh::{Mesh, Model};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::renderer::Renderer;
 use crate::graphics::shader::ShaderProgram;
-use crate::graphics::texture::{TextureAtlas, Texture};
+use crate::graphics::texture::{Texture3d, TextureAtlas};
+use crate::impl_vertex;
+use crate::memory::{MEMORY, Subsystem};
+use crate::metrics::{METRICS, Metric};
+use crate::task::{CancelToken, TaskHandle, TaskPool};
 use std::borrow::{BorrowMut, Borrow};
+use std::fs;
+use std::io;
+use std::mem;
 use std::ops::{Deref};
-use crate::graphics::buffer::{VertexBufferLayout, VertexBuffer};
-use std::mem::size_of;
-use crate::graphics::gl::types::GLvoid;
-use std::sync::{Arc, Mutex};
-use std::thread;
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::FRAC_1_SQRT_2;
 use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread;
+use std::time::Instant;
 
 /// The size of each chunk
 pub const CHUNK_SIZE:usize = 16;
@@ -27,6 +45,26 @@ pub const CHUNK_HEIGHT:usize = 256;
 pub const CHUNK_AREA:usize = CHUNK_SIZE * CHUNK_SIZE;
 /// The volume of each chunk
 pub const CHUNK_VOLUME:usize = CHUNK_AREA * CHUNK_HEIGHT;
+/// The size of the light map texture of each chunk,
+/// with the height of the chunk as its depth
+const LIGHT_MAP_SIZE: [u32; 3] = [CHUNK_SIZE as u32, CHUNK_SIZE as u32, CHUNK_HEIGHT as u32];
+/// The time in seconds a chunk takes to fade in after
+/// its first mesh arrived, `0.0` to show it at once
+const CHUNK_FADE_IN: f32 = 0.4;
+/// The largest amount of chunks queued to be meshed or lit.
+/// Further chunks are queued once the workers caught up.
+const CHUNK_QUEUE_CAPACITY: usize = 64;
+/// The size of the blocks of each chunk in bytes
+const BLOCKS_SIZE: usize = mem::size_of::<[Material; CHUNK_VOLUME]>();
+/// The size of a run of compressed blocks in bytes
+const RUN_SIZE: usize = mem::size_of::<(u32, Material)>();
+/// The size of a run of compressed blocks in a swap file in bytes
+const SWAP_RUN_SIZE: usize = 5;
+/// The level of `zstd` the swap files are compressed with
+const SWAP_COMPRESSION_LEVEL: i32 = 3;
+/// The amount of swap files written so far, which
+/// tells the files of chunks apart
+static SWAP_FILES: AtomicUsize = AtomicUsize::new(0);
 
 /// Chunk
 ///
@@ -46,16 +84,182 @@ pub struct Chunk {
 }
 
 pub struct ChunkInner {
-    /// An `OpenGL` instance
-    gl: Gl,
     /// The location of the chunk
     loc: Vector2<i32>,
     /// The blocks stored in the chunk
-    blocks: Mutex<Box<[Material; CHUNK_VOLUME]>>,
-    /// The current chunk model
-    model: Arc<Mutex<Option<ChunkModel>>>,
+    blocks: Mutex<BlockStorage>,
+    /// Whether the blocks have been accessed since the
+    /// tiers of the chunks have been updated the last time
+    accessed: AtomicBool,
+    /// The states of the blocks by their index, e.g. the growth
+    /// stage of a crop. Blocks without an entry have the state `0`.
+    states: Mutex<HashMap<usize, u8>>,
+    /// The block entities by the index of their block,
+    /// e.g. the loot table of a chest
+    block_entities: Mutex<HashMap<usize, BlockEntity>>,
     /// A boolean determining whether the chunk model should be recalculated
     recalculate: Arc<Mutex<bool>>,
+    /// A boolean determining whether the light map should be recalculated
+    relight: Arc<Mutex<bool>>,
+    /// A boolean determining whether the terrain of the chunk has been generated
+    generated: Arc<Mutex<bool>>,
+}
+
+impl Drop for ChunkInner {
+    fn drop(&mut self) {
+        let blocks = self.blocks.get_mut().unwrap();
+        MEMORY.release(Subsystem::Blocks, blocks.size());
+        if let BlockStorage::Evicted(path) = blocks {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// BlockTier
+///
+/// How the blocks of a chunk are stored:
+/// * `Decoded` - Every block is stored, e.g. while the chunk
+///   is edited or meshed
+/// * `Compressed` - Runs of blocks with the same material are
+///   stored, which blocks are still read from
+/// * `Evicted` - The runs are written to a swap file compressed
+///   with `zstd`, which they're read from again once the chunk
+///   is accessed
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BlockTier {
+    Decoded,
+    Compressed,
+    Evicted,
+}
+
+/// BlockStorage
+///
+/// The blocks of a chunk in their current tier. The runs
+/// of compressed blocks are ordered by their index, each
+/// storing the index after its last block and its material.
+enum BlockStorage {
+    Decoded(Box<[Material; CHUNK_VOLUME]>),
+    Compressed(Vec<(u32, Material)>),
+    /// The path of the swap file
+    Evicted(PathBuf),
+}
+
+impl BlockStorage {
+    /// Returns the size of the blocks in memory in bytes
+    fn size(&self) -> usize {
+        match self {
+            BlockStorage::Decoded(_) => BLOCKS_SIZE,
+            BlockStorage::Compressed(runs) => runs.len() * RUN_SIZE,
+            BlockStorage::Evicted(_) => 0,
+        }
+    }
+
+    /// Returns the tier of the blocks
+    fn tier(&self) -> BlockTier {
+        match self {
+            BlockStorage::Decoded(_) => BlockTier::Decoded,
+            BlockStorage::Compressed(_) => BlockTier::Compressed,
+            BlockStorage::Evicted(_) => BlockTier::Evicted,
+        }
+    }
+
+    /// Reads the runs of evicted blocks back from their swap
+    /// file, which is removed afterwards. If the file can't
+    /// be read, the blocks stay evicted and the file is kept.
+    fn rehydrate(&mut self) -> io::Result<()> {
+        if let BlockStorage::Evicted(path) = self {
+            let runs = read_swap(path).map_err(|error| {
+                io::Error::new(error.kind(), format!("failed to read {}: {}", path.display(), error))
+            })?;
+            let _ = fs::remove_file(&path);
+            *self = BlockStorage::Compressed(runs);
+            MEMORY.allocate(Subsystem::Blocks, self.size());
+        }
+        Ok(())
+    }
+
+    /// Decodes the blocks, so they could be changed
+    fn decode(&mut self) -> io::Result<&mut [Material; CHUNK_VOLUME]> {
+        self.rehydrate()?;
+        if let BlockStorage::Compressed(runs) = self {
+            let mut blocks = Box::new([Material::Air; CHUNK_VOLUME]);
+            let mut start = 0;
+            for (end, material) in runs.iter() {
+                blocks[start..*end as usize].fill(*material);
+                start = *end as usize;
+            }
+            MEMORY.release(Subsystem::Blocks, runs.len() * RUN_SIZE);
+            MEMORY.allocate(Subsystem::Blocks, BLOCKS_SIZE);
+            *self = BlockStorage::Decoded(blocks);
+        }
+        match self {
+            BlockStorage::Decoded(blocks) => Ok(&mut **blocks),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the block with the given index, or `None`
+    /// if the blocks are evicted
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the block
+    fn get(&self, index: usize) -> Option<Material> {
+        match self {
+            BlockStorage::Decoded(blocks) => Some(blocks[index]),
+            BlockStorage::Compressed(runs) => {
+                let run = runs.partition_point(|(end, _)| *end as usize <= index);
+                Some(runs[run].1)
+            }
+            BlockStorage::Evicted(_) => None,
+        }
+    }
+}
+
+/// Writes the runs of compressed blocks to a swap file,
+/// with five bytes per run compressed with `zstd`
+///
+/// # Arguments
+///
+/// * `path` - The path of the swap file
+/// * `runs` - The runs of compressed blocks
+fn write_swap(path: &Path, runs: &[(u32, Material)]) -> io::Result<()> {
+    let mut content = Vec::with_capacity(runs.len() * SWAP_RUN_SIZE);
+    for (end, material) in runs.iter() {
+        content.extend_from_slice(&end.to_le_bytes());
+        content.push(*material as u8);
+    }
+    fs::write(path, zstd::bulk::compress(&content, SWAP_COMPRESSION_LEVEL)?)
+}
+
+/// Reads the runs of compressed blocks from a swap file
+///
+/// # Arguments
+///
+/// * `path` - The path of the swap file
+fn read_swap(path: &Path) -> io::Result<Vec<(u32, Material)>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid swap file");
+    let content = zstd::bulk::decompress(&fs::read(path)?, CHUNK_VOLUME * SWAP_RUN_SIZE)?;
+    let mut runs = Vec::with_capacity(content.len() / SWAP_RUN_SIZE);
+    let mut start = 0;
+    for run in content.chunks(SWAP_RUN_SIZE) {
+        if run.len() < SWAP_RUN_SIZE {
+            return Err(invalid());
+        }
+        // Each run ends after the one before, the blocks are
+        // filled from the end of one run to the next
+        let end = u32::from_le_bytes([run[0], run[1], run[2], run[3]]);
+        if end <= start {
+            return Err(invalid());
+        }
+        start = end;
+        let material = Material::ALL.get(run[4] as usize).copied().ok_or_else(invalid)?;
+        runs.push((end, material));
+    }
+    if runs.last().map(|(end, _)| *end as usize) != Some(CHUNK_VOLUME) {
+        return Err(invalid());
+    }
+    Ok(runs)
 }
 
 impl Deref for Chunk {
@@ -78,39 +282,26 @@ impl Chunk {
     ///
     /// # Arguments
     ///
-    /// * `gl` - An `OpenGl` instance
     /// * `loc` - The location of the chunk
-    pub fn new(gl: &Gl, loc: Vector2<i32>) -> Self {
+    pub fn new(loc: Vector2<i32>) -> Self {
+        MEMORY.allocate(Subsystem::Blocks, BLOCKS_SIZE);
         Self {
             inner: Arc::new(ChunkInner {
                 loc,
-                gl: gl.clone(),
-                blocks: Mutex::new(Box::new([Material::Air; CHUNK_VOLUME])),
-                model: Arc::new(Mutex::new(None)),
+                blocks: Mutex::new(BlockStorage::Decoded(Box::new([Material::Air; CHUNK_VOLUME]))),
+                accessed: AtomicBool::new(true),
+                states: Mutex::new(HashMap::new()),
+                block_entities: Mutex::new(HashMap::new()),
                 recalculate: Arc::new(Mutex::new(true)),
+                relight: Arc::new(Mutex::new(true)),
+                generated: Arc::new(Mutex::new(false)),
             }),
         }
     }
 
-    /// Recalculates the chunk mesh and model
-    pub fn recalculate_model(&self) {
-        // let chunk = self.clone();
-        // thread::spawn(move || {
-        //     let mesh = make_greedy_chunk_mesh(&chunk);
-        //     let model = ChunkModel::from_chunk_mesh(&chunk.gl, &mesh);
-        //
-        //     {
-        //         let mut guard = chunk.model.lock().unwrap();
-        //         *guard = Some(model);
-        //     }
-        //     {
-        //         let mut guard = chunk.recalculate.lock().unwrap();
-        //         *guard = false;
-        //     }
-        // });
-    }
-
-    /// Places a block to the given location
+    /// Places a block to the given location. The state and
+    /// the block entity are reset if the block has a different
+    /// material than the previous one.
     ///
     /// # Argument
     ///
@@ -119,23 +310,136 @@ impl Chunk {
     ///
     /// # Safety
     ///
-    /// If the location is out of bounds, the block won't be placed
+    /// If the location is out of bounds or the blocks have been
+    /// evicted and can't be read back, the block won't be placed
     pub fn set_block(&self, loc: Vector3<i16>, material: Material) {
         if let Some(index) = self.index_of(loc) {
-            {
-                let mut guard = self.blocks.lock().unwrap();
-                (*guard)[index] = material;
+            let old = match self.with_blocks(|blocks| mem::replace(&mut blocks[index], material)) {
+                Some(old) => old,
+                None => return,
+            };
+            if old != material {
+                self.states.lock().unwrap().remove(&index);
+                self.block_entities.lock().unwrap().remove(&index);
             }
+            self.mark_recalculate();
+            self.mark_relight();
+        }
+    }
+
+    /// Returns the state of the block at the given location,
+    /// or `None` if the location is out of bounds
+    ///
+    /// # Argument
+    ///
+    /// * `loc` - The location of the block in the chunk
+    pub fn state(&self, loc: Vector3<i16>) -> Option<u8> {
+        let index = self.index_of(loc)?;
+        Some(self.states.lock().unwrap().get(&index).copied().unwrap_or(0))
+    }
+
+    /// Changes the state of the block at the given location.
+    /// Only the model of the chunk is recalculated, as the
+    /// state doesn't change how light spreads.
+    ///
+    /// # Argument
+    ///
+    /// * `loc` - The location of the block in the chunk
+    /// * `state` - The new state of the block
+    pub fn set_state(&self, loc: Vector3<i16>, state: u8) {
+        if let Some(index) = self.index_of(loc) {
             {
-                let mut guard = self.recalculate.lock().unwrap();
-                *guard = true;
+                let mut states = self.states.lock().unwrap();
+                if state == 0 {
+                    states.remove(&index);
+                } else {
+                    states.insert(index, state);
+                }
             }
+            self.mark_recalculate();
+        }
+    }
+
+    /// Returns the block entity of the block at the given location
+    ///
+    /// # Argument
+    ///
+    /// * `loc` - The location of the block in the chunk
+    pub fn block_entity(&self, loc: Vector3<i16>) -> Option<BlockEntity> {
+        let index = self.index_of(loc)?;
+        self.block_entities.lock().unwrap().get(&index).cloned()
+    }
+
+    /// Attaches a block entity to the block at the given location
+    ///
+    /// # Argument
+    ///
+    /// * `loc` - The location of the block in the chunk
+    /// * `entity` - The block entity
+    pub fn set_block_entity(&self, loc: Vector3<i16>, entity: BlockEntity) {
+        if let Some(index) = self.index_of(loc) {
+            self.block_entities.lock().unwrap().insert(index, entity);
+        }
+    }
+
+    /// Removes the block entity of the block at the given
+    /// location and returns it
+    ///
+    /// # Argument
+    ///
+    /// * `loc` - The location of the block in the chunk
+    pub fn take_block_entity(&self, loc: Vector3<i16>) -> Option<BlockEntity> {
+        let index = self.index_of(loc)?;
+        self.block_entities.lock().unwrap().remove(&index)
+    }
+
+    /// Calls a function with each block entity of the chunk
+    /// and the location of its block, which could change the
+    /// block entity
+    ///
+    /// # Argument
+    ///
+    /// * `update` - The function
+    pub fn update_block_entities<F: FnMut(Vector3<i16>, &mut BlockEntity)>(&self, mut update: F) {
+        for (index, entity) in self.block_entities.lock().unwrap().iter_mut() {
+            let loc = Vector3::new(
+                (index % CHUNK_SIZE) as i16,
+                (index / CHUNK_AREA) as i16,
+                (index / CHUNK_SIZE % CHUNK_SIZE) as i16,
+            );
+            update(loc, entity);
+        }
+    }
+
+    /// Marks the chunk, so that its model is recalculated
+    /// before it's rendered the next time
+    pub fn mark_recalculate(&self) {
+        let mut guard = self.recalculate.lock().unwrap();
+        *guard = true;
+    }
+
+    /// Marks the chunk, so that its light map is recalculated
+    /// before it's rendered the next time. The model of the
+    /// chunk is kept.
+    pub fn mark_relight(&self) {
+        let mut guard = self.relight.lock().unwrap();
+        *guard = true;
+    }
+
+    /// Marks the terrain of the chunk as generated. Until
+    /// then, the chunk is neither meshed nor rendered.
+    pub fn mark_generated(&self) {
+        {
+            let mut guard = self.generated.lock().unwrap();
+            *guard = true;
         }
+        self.mark_recalculate();
+        self.mark_relight();
     }
 
-    /// Returns the model of the chunk
-    pub fn model(&self) -> Arc<Mutex<Option<ChunkModel>>> {
-        self.model.clone()
+    /// Returns whether the terrain of the chunk has been generated
+    pub fn is_generated(&self) -> bool {
+        *self.generated.lock().unwrap()
     }
 
     /// Returns the location of the chunk
@@ -158,17 +462,116 @@ impl Chunk {
     ///
     /// If the location is out of bounds, a `None` will be
     /// returned
+    /// If the blocks have been evicted and can't be read back,
+    /// `None` is returned as well.
     pub fn block(&self, loc: Vector3<i16>) -> Option<Material> {
         // println!("X: {}, Y: {}, Z: {}", loc.x, loc.y, loc.z);
         if let Some(index) = self.index_of(loc) {
-            let guard = self.blocks.lock().unwrap();
-            let blocks = &*guard;
             // println!("Index: {}, Material: {:?}", index, blocks[index]);
-            return Some(blocks[index]);
+            return self.accessed_blocks()?.get(index);
         }
         None
     }
 
+    /// Returns the material of a block without counting as an
+    /// access, e.g. for random ticks which sample all chunks.
+    /// Blocks of evicted chunks aren't read back, so `None`
+    /// is returned for them.
+    ///
+    /// # Argument
+    ///
+    /// * `loc` - The location of the block in the chunk
+    pub fn peek_block(&self, loc: Vector3<i16>) -> Option<Material> {
+        let index = self.index_of(loc)?;
+        self.blocks.lock().unwrap().get(index)
+    }
+
+    /// Locks the blocks of the chunk as they're accessed, which
+    /// reads them back if they've been evicted. Returns `None`
+    /// if they can't be read back.
+    fn accessed_blocks(&self) -> Option<MutexGuard<'_, BlockStorage>> {
+        let mut guard = self.blocks.lock().unwrap();
+        if let Err(error) = guard.rehydrate() {
+            log::error!("Blocks of chunk {:?} are unavailable: {}", self.loc, error);
+            return None;
+        }
+        self.accessed.store(true, Ordering::Relaxed);
+        Some(guard)
+    }
+
+    /// Calls a function with the decoded blocks of the chunk,
+    /// which could change them. Returns `None` without calling
+    /// it if the blocks have been evicted and can't be read back.
+    ///
+    /// # Argument
+    ///
+    /// * `f` - The function
+    fn with_blocks<R, F: FnOnce(&mut [Material; CHUNK_VOLUME]) -> R>(&self, f: F) -> Option<R> {
+        let mut guard = self.accessed_blocks()?;
+        Some(f(guard.decode().ok()?))
+    }
+
+    /// Returns how the blocks of the chunk are currently stored
+    pub fn tier(&self) -> BlockTier {
+        self.blocks.lock().unwrap().tier()
+    }
+
+    /// Returns whether the blocks have been accessed since
+    /// the last call and resets it
+    pub fn take_accessed(&self) -> bool {
+        self.accessed.swap(false, Ordering::Relaxed)
+    }
+
+    /// Compresses the decoded blocks of a generated chunk into
+    /// runs of blocks with the same material. Returns whether
+    /// the blocks have been compressed.
+    pub fn compress(&self) -> bool {
+        if !self.is_generated() {
+            return false;
+        }
+        let mut guard = self.blocks.lock().unwrap();
+        let runs = match &*guard {
+            BlockStorage::Decoded(blocks) => {
+                let mut runs: Vec<(u32, Material)> = Vec::new();
+                for (index, material) in blocks.iter().enumerate() {
+                    match runs.last_mut() {
+                        Some((end, last)) if last == material => *end = index as u32 + 1,
+                        _ => runs.push((index as u32 + 1, *material)),
+                    }
+                }
+                runs.shrink_to_fit();
+                runs
+            }
+            _ => return false,
+        };
+        MEMORY.release(Subsystem::Blocks, BLOCKS_SIZE);
+        *guard = BlockStorage::Compressed(runs);
+        MEMORY.allocate(Subsystem::Blocks, guard.size());
+        true
+    }
+
+    /// Writes the compressed blocks of the chunk to a swap file
+    /// in the given directory and drops them from memory. Returns
+    /// whether the blocks have been evicted.
+    ///
+    /// # Argument
+    ///
+    /// * `dir` - The directory of the swap files
+    pub fn evict(&self, dir: &Path) -> io::Result<bool> {
+        let mut guard = self.blocks.lock().unwrap();
+        let runs = match &*guard {
+            BlockStorage::Compressed(runs) => runs,
+            _ => return Ok(false),
+        };
+        fs::create_dir_all(dir)?;
+        let number = SWAP_FILES.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!("{}.{}.{}.swap", self.loc.x, self.loc.y, number));
+        write_swap(&path, runs)?;
+        MEMORY.release(Subsystem::Blocks, guard.size());
+        *guard = BlockStorage::Evicted(path);
+        Ok(true)
+    }
+
     /// Returns the index of a given location
     ///
     /// # Argument
@@ -197,6 +600,83 @@ impl Chunk {
     }
 }
 
+/// TileVertex
+///
+/// The tile of the texture atlas a vertex is textured with
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TileVertex {
+    /// The coordinate of the tile within the texture atlas
+    pub tile_coord: [f32; 2],
+}
+
+impl_vertex!(TileVertex {
+    tile_coord => attribute::TILE_COORD,
+});
+
+/// OverlayVertex
+///
+/// The tile of the texture atlas which is layered
+/// over the tile a vertex is textured with
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OverlayVertex {
+    /// The coordinate of the overlay tile within the texture atlas
+    pub overlay_coord: [f32; 2],
+    /// The tint and opacity of the overlay, which is fully
+    /// transparent if the vertex has no overlay
+    pub overlay_tint: [f32; 4],
+}
+
+impl_vertex!(OverlayVertex {
+    overlay_coord => attribute::OVERLAY_COORD,
+    overlay_tint => attribute::OVERLAY_TINT,
+});
+
+/// EmissionVertex
+///
+/// How strong a vertex glows, which is written into
+/// the emissive buffer of the bloom
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EmissionVertex {
+    /// The emission of the block the vertex belongs to
+    pub emission: f32,
+}
+
+impl_vertex!(EmissionVertex {
+    emission => attribute::EMISSION,
+});
+
+/// ReflectionVertex
+///
+/// How much of the environment map a vertex reflects
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ReflectionVertex {
+    /// The reflectivity of the block the vertex belongs to
+    pub reflectivity: f32,
+}
+
+impl_vertex!(ReflectionVertex {
+    reflectivity => attribute::REFLECTIVITY,
+});
+
+/// OcclusionVertex
+///
+/// How much of the ambient light reaches a vertex
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OcclusionVertex {
+    /// The ambient occlusion of the vertex, from fully
+    /// occluded at `0.0` to unoccluded at `1.0`
+    pub occlusion: f32,
+}
+
+impl_vertex!(OcclusionVertex {
+    occlusion => attribute::OCCLUSION,
+});
+
 /// ChunkModel
 ///
 /// A chunk model is built up by a chunk mesh and it is generating the
@@ -205,6 +685,8 @@ impl Chunk {
 pub struct ChunkModel {
     /// The underlying model
     model: Model,
+    /// The time the first model of the chunk has been shown
+    shown_at: Instant,
 }
 
 impl Deref for ChunkModel {
@@ -220,20 +702,59 @@ impl ChunkModel {
     ///
     /// # Arguments
     ///
+    /// * `backend` - The render backend the buffers are created with
     /// * `mesh` - A chunk mesh instance
-    pub fn from_chunk_mesh(gl: &Gl, mesh: &ChunkMesh) -> Self {
-        let mut model = Model::from_mesh(gl, &mesh.mesh);
-        let vb_tile_coords = VertexBuffer::new(gl, mesh.tile_offsets.as_ptr() as *const GLvoid, mesh.tile_offsets.len() as isize * size_of::<f32>() as isize);
-
-        let mut buffer_layout = VertexBufferLayout::new();
-        buffer_layout.push_f32(2);
-        model.va_mut().add_buffer(&vb_tile_coords, &buffer_layout);
-        model.buffers_mut().push(vb_tile_coords);
+    pub fn from_chunk_mesh(backend: &GlBackend, mesh: &ChunkMesh) -> Self {
+        let mut model = Model::from_mesh(backend, &mesh.mesh);
+        let tile_coords: Vec<TileVertex> = mesh.tile_offsets.chunks(2)
+            .map(|offset| TileVertex { tile_coord: [offset[0], offset[1]] })
+            .collect();
+        model.add_buffer(backend.create_vertex_buffer(&tile_coords));
+        let overlays: Vec<OverlayVertex> = mesh.overlay_offsets.chunks(2)
+            .zip(mesh.overlay_tints.chunks(4))
+            .map(|(offset, tint)| OverlayVertex {
+                overlay_coord: [offset[0], offset[1]],
+                overlay_tint: [tint[0], tint[1], tint[2], tint[3]],
+            })
+            .collect();
+        model.add_buffer(backend.create_vertex_buffer(&overlays));
+        let emissions: Vec<EmissionVertex> = mesh.emissions.iter()
+            .map(|emission| EmissionVertex { emission: *emission })
+            .collect();
+        model.add_buffer(backend.create_vertex_buffer(&emissions));
+        let reflections: Vec<ReflectionVertex> = mesh.reflectivities.iter()
+            .map(|reflectivity| ReflectionVertex { reflectivity: *reflectivity })
+            .collect();
+        model.add_buffer(backend.create_vertex_buffer(&reflections));
+        let occlusions: Vec<OcclusionVertex> = mesh.occlusions.iter()
+            .map(|occlusion| OcclusionVertex { occlusion: *occlusion })
+            .collect();
+        model.add_buffer(backend.create_vertex_buffer(&occlusions));
 
         Self {
             model,
+            shown_at: Instant::now(),
         }
     }
+
+    /// Returns the opacity the model is rendered with, which
+    /// ramps up while the chunk fades in
+    pub fn opacity(&self) -> f32 {
+        fade_in_opacity(self.shown_at.elapsed().as_secs_f32())
+    }
+}
+
+/// Returns the opacity of a chunk which has been shown
+/// for the given time
+///
+/// # Arguments
+///
+/// * `seconds` - The time since the first model of the chunk has been shown
+fn fade_in_opacity(seconds: f32) -> f32 {
+    if CHUNK_FADE_IN <= 0.0 {
+        return 1.0;
+    }
+    (seconds / CHUNK_FADE_IN).clamp(0.0, 1.0)
 }
 
 /// ChunkMesh
@@ -246,6 +767,16 @@ pub struct ChunkMesh {
     mesh: Mesh,
     /// The tile offsets of the mesh
     tile_offsets: Vec<f32>,
+    /// The overlay tile offsets of the mesh
+    overlay_offsets: Vec<f32>,
+    /// The overlay tints of the mesh
+    overlay_tints: Vec<f32>,
+    /// The emissions of the mesh
+    emissions: Vec<f32>,
+    /// The reflectivities of the mesh
+    reflectivities: Vec<f32>,
+    /// The ambient occlusions of the mesh
+    occlusions: Vec<f32>,
     /// The current index,
     current_index: u32,
 }
@@ -255,12 +786,25 @@ impl Default for ChunkMesh {
         Self {
             mesh: Mesh::default(),
             tile_offsets: Vec::new(),
+            overlay_offsets: Vec::new(),
+            overlay_tints: Vec::new(),
+            emissions: Vec::new(),
+            reflectivities: Vec::new(),
+            occlusions: Vec::new(),
             current_index: 0
         }
     }
 }
 
 impl ChunkMesh {
+    /// Returns the size of the vertex and index data in bytes
+    pub fn byte_size(&self) -> usize {
+        let floats = self.mesh.vertex_positions.len() + self.mesh.tex_coords.len() + self.mesh.normals.len()
+            + self.tile_offsets.len() + self.overlay_offsets.len() + self.overlay_tints.len() + self.emissions.len()
+            + self.reflectivities.len() + self.occlusions.len();
+        floats * mem::size_of::<f32>() + self.mesh.indices.len() * mem::size_of::<u32>()
+    }
+
     pub fn add_quad(&mut self,
         bottom_left: Vector3<f32>,
         top_left: Vector3<f32>,
@@ -338,29 +882,149 @@ impl ChunkMesh {
             }
         };
 
-        match face.side {
-            Side::TOP => push_tile_offset(&mut self.tile_offsets, [1.0, 15.0]),
-            Side::BOTTOM => push_tile_offset(&mut self.tile_offsets, [2.0, 15.0]),
-            _ => push_tile_offset(&mut self.tile_offsets, [0.0, 15.0]),
+        match (face.tile, face.side) {
+            (Some(tile), _) => push_tile_offset(&mut self.tile_offsets, [tile.x, tile.y]),
+            (None, Side::TOP) => push_tile_offset(&mut self.tile_offsets, [1.0, 15.0]),
+            (None, Side::BOTTOM) => push_tile_offset(&mut self.tile_offsets, [2.0, 15.0]),
+            (None, _) => push_tile_offset(&mut self.tile_offsets, [0.0, 15.0]),
+        }
+
+        // Add overlay tiles
+        self.overlay_offsets.reserve(8);
+        self.overlay_tints.reserve(16);
+        let (overlay_offset, overlay_tint) = match face.overlay {
+            Some(overlay) => ([overlay.tile.x, overlay.tile.y], overlay.tint.into()),
+            None => ([0.0; 2], [0.0; 4]),
+        };
+        for _ in 0..4 {
+            self.overlay_offsets.extend_from_slice(&overlay_offset);
+            self.overlay_tints.extend_from_slice(&overlay_tint);
+        }
+
+        // Add emissions
+        self.emissions.extend_from_slice(&[face.material.data().emission(); 4]);
+
+        // Add reflectivities
+        self.reflectivities.extend_from_slice(&[face.material.data().reflectivity(); 4]);
+
+        // Add ambient occlusions
+        self.occlusions.extend(face.occlusion.iter().map(|&level| level as f32 / 3.0));
+    }
+
+    /// Adds a block shaped like a cross, i.e. two quads along the
+    /// diagonals of the block, which are textured with the tile of
+    /// the face
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the block in the chunk
+    /// * `face` - The face both quads are textured with
+    pub fn add_cross(&mut self, loc: Vector3<f32>, face: &VoxelFace) {
+        let up = Vector3::new(0.0, 1.0, 0.0);
+        let diagonals = [
+            (Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 1.0), [FRAC_1_SQRT_2, 0.0, -FRAC_1_SQRT_2]),
+            (Vector3::new(0.0, 0.0, 1.0), Vector3::new(1.0, 0.0, 0.0), [FRAC_1_SQRT_2, 0.0, FRAC_1_SQRT_2]),
+        ];
+        for &(start, end, normal) in diagonals.iter() {
+            self.add_quad(loc + start, loc + start + up, loc + end + up, loc + end, 1, 1, face, false);
+
+            // The quads don't face any side of the block, so the
+            // normals of the side are replaced by the diagonal ones
+            let normals = &mut self.mesh.normals;
+            let first = normals.len() - 12;
+            for vertex in normals[first..].chunks_mut(3) {
+                vertex.copy_from_slice(&normal);
+            }
         }
     }
 }
 
+/// ChunkFrame
+///
+/// A frame chunks could be rendered for
+pub trait ChunkFrame: Frame {
+    /// Returns the chunks which should be rendered
+    fn chunks(&self) -> &[Chunk];
+
+    /// Returns the brightness of the chunks in the range `[0, 1]`
+    fn light(&self) -> f32;
+
+    /// Returns the overlays of the block faces. The chunks
+    /// are remeshed whenever a different instance is returned.
+    fn overlays(&self) -> &Arc<BlockOverlays>;
+
+    /// Returns the color of the sky, which reflective
+    /// blocks reflect
+    fn sky_color(&self) -> Vector3<f32>;
+}
+
+/// The kinds of background work of the chunk renderer,
+/// which are queued once per chunk each
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+enum ChunkJob {
+    /// Meshing a chunk
+    Mesh(Vector2<i32>),
+    /// Computing the light map of a chunk
+    Light(Vector2<i32>),
+}
+
 /// ChunkRenderer
 ///
 /// This is a renderer which renders
 /// `Minecraft-like` chunks
 pub struct ChunkRenderer {
-    /// An `OpenGL` instance
-    gl: Gl,
+    /// The render backend
+    backend: GlBackend,
+    /// The renderer which submits the draw calls
+    renderer: Renderer,
     /// A texture atlas
     tex_atlas: TextureAtlas,
+    /// The generation of the texture pack the atlas belongs to
+    textures: u64,
     /// A shader program
     shader_program: ShaderProgram,
+    /// The variants of the shader program showing the debug
+    /// views, which are left out if they failed to compile
+    debug_programs: Vec<(DebugView, ShaderProgram)>,
+    /// The debug view the chunks are rendered with
+    debug_view: DebugView,
+    /// The brightness the chunks are rendered with
+    light: f32,
+    /// The settings of the haze in caves
+    cave_fog: CaveFogSettings,
+    /// Whether the quality of the current frame allows the fog
+    fog_allowed: bool,
+    /// The tile sets of blocks with connected textures
+    connected_textures: Arc<ConnectedTextures>,
+    /// The overlays of the block faces the meshes are built with
+    overlays: Arc<BlockOverlays>,
     /// A map which internally stores the chunk models
     chunk_map: HashMap<Vector2<i32>, Option<ChunkModel>>,
+    /// The workers meshing and lighting the chunks
+    workers: TaskPool<ChunkJob>,
     /// A channel to send/receive chunk mesh updates
-    chunk_update_channel: (Sender<(Vector2<i32>, ChunkMesh)>, Receiver<(Vector2<i32>, ChunkMesh)>)
+    chunk_update_channel: (Sender<(Vector2<i32>, CancelToken, ChunkMesh)>, Receiver<(Vector2<i32>, CancelToken, ChunkMesh)>),
+    /// The mesh tasks which are still running, by the location
+    /// of their chunk
+    mesh_tasks: HashMap<Vector2<i32>, TaskHandle>,
+    /// A map which internally stores the light maps of the chunks
+    light_maps: HashMap<Vector2<i32>, Texture3d>,
+    /// The light map of chunks whose light map isn't computed
+    /// yet, which are lit by the sky alone
+    unlit: Texture3d,
+    /// How the light of glowing blocks is stored in the light maps
+    light_format: LightFormat,
+    /// The surroundings reflective blocks reflect
+    environment: EnvironmentMap,
+    /// A channel to send/receive light map updates
+    light_update_channel: (Sender<(Vector2<i32>, CancelToken, LightMap)>, Receiver<(Vector2<i32>, CancelToken, LightMap)>),
+    /// The light map tasks which are still running, by the
+    /// location of their chunk
+    light_tasks: HashMap<Vector2<i32>, TaskHandle>,
+    /// The amount of generated adjacent chunks each chunk has
+    /// last been lit with, so it's lit again once light could
+    /// spread in from further ones
+    lit_neighbours: HashMap<Vector2<i32>, usize>,
 }
 
 impl ChunkRenderer {
@@ -369,67 +1033,189 @@ impl ChunkRenderer {
     ///
     /// # Arguments
     ///
-    /// * `gl` - An `OpenGL` instance
+    /// * `backend` - The render backend
     /// * `resources` - A resource instance
-    pub fn new(gl: &Gl, resources: &Resources) -> Self {
+    /// * `textures` - The texture pack of the blocks
+    /// * `cave_fog` - The settings of the haze in caves
+    pub fn new(backend: &GlBackend, resources: &Resources, textures: &TexturePack, cave_fog: CaveFogSettings) -> Self {
         // Create shader program
-        let shader_program = ShaderProgram::from_res(gl, resources, "basic").unwrap();
+        let shader_program = backend.create_pipeline(resources, "basic").unwrap();
         shader_program.disable();
-
-        // Create default texture atlas
-        let texture = Texture::from_resource(gl, resources, "textures/textures.png");
-        let tex_atlas = TextureAtlas::from_texture(texture, Vector2::new(16.0, 16.0));
-        tex_atlas.unbind();
+        let debug_programs = DEBUG_VIEWS.iter()
+            .filter_map(|&view| {
+                let define = view.define()?;
+                match backend.create_pipeline_variant(resources, "basic", &[define]) {
+                    Ok(program) => Some((view, program)),
+                    Err(error) => {
+                        log::warn!("Failed to compile the {} debug view: {}", view.name(), error);
+                        None
+                    }
+                }
+            })
+            .collect();
 
         Self {
             shader_program,
-            tex_atlas,
-            gl: gl.clone(),
+            debug_programs,
+            debug_view: DebugView::Off,
+            tex_atlas: textures.atlas(backend),
+            textures: textures.generation(),
+            backend: backend.clone(),
+            renderer: Renderer::from_backend(backend.clone()),
+            light: 1.0,
+            cave_fog,
+            fog_allowed: true,
+            connected_textures: textures.connected().clone(),
+            overlays: Arc::new(BlockOverlays::default()),
             chunk_map: HashMap::new(),
+            workers: TaskPool::new("chunk-worker", chunk_workers(), CHUNK_QUEUE_CAPACITY),
             chunk_update_channel: channel(),
+            mesh_tasks: HashMap::new(),
+            light_maps: HashMap::new(),
+            unlit: backend.create_texture_3d([1, 1, 1], TexelFormat::R8, &[SKY_LIT_TEXEL]).unwrap(),
+            light_format: LightFormat::Rgb,
+            environment: EnvironmentMap::new(backend, resources),
+            light_update_channel: channel(),
+            light_tasks: HashMap::new(),
+            lit_neighbours: HashMap::new(),
         }
     }
 
-    /// Add a chunk
-    pub fn add_chunk(&mut self, loc: &Vector2<i32>) {
-        if !self.chunk_map.contains_key(loc) {
-            self.chunk_map.insert(loc.clone(), None);
-        }
+    /// Sets how the light of glowing blocks is stored in the
+    /// light maps. Scalar light takes half the memory, but
+    /// lights don't tint their surroundings.
+    ///
+    /// # Arguments
+    ///
+    /// * `light_format` - The layout of the light maps
+    pub fn with_light_format(mut self, light_format: LightFormat) -> Self {
+        self.light_format = light_format;
+        self
     }
 
-    /// Remove a chunk
-    pub fn remove_chunk(&mut self, loc: &Vector2<i32>) {
-        self.chunk_map.remove(loc);
+    /// Removes all chunk models which don't belong to one of
+    /// the given chunks and cancels the tasks of those chunks
+    ///
+    /// # Arguments
+    ///
+    /// * `chunks` - The chunks whose models should be kept
+    pub fn retain_chunks(&mut self, chunks: &[Chunk]) {
+        let locs: HashSet<&Vector2<i32>> = chunks.iter().map(|chunk| chunk.loc()).collect();
+        self.chunk_map.retain(|loc, _| locs.contains(loc));
+        self.light_maps.retain(|loc, _| locs.contains(loc));
+        self.mesh_tasks.retain(|loc, _| locs.contains(loc));
+        self.light_tasks.retain(|loc, _| locs.contains(loc));
+        self.lit_neighbours.retain(|loc, _| locs.contains(loc));
     }
 
-    /// Recalculates a chunk
+    /// Queues a chunk to be recalculated. A task still meshing
+    /// the chunk is cancelled, as its mesh is outdated. Returns
+    /// `false` and leaves the chunk marked if the queue is full,
+    /// so it's queued again the next frame.
     ///
     /// # Arguments
     ///
     /// * `chunk` - The chunk which should be recalculated
-    pub fn recalculate_chunk(&self, chunk: &Chunk) {
+    pub fn recalculate_chunk(&mut self, chunk: &Chunk) -> bool {
+        let loc = *chunk.loc();
+        let task_chunk = chunk.clone();
+        let connected_textures = self.connected_textures.clone();
+        let overlays = self.overlays.clone();
+        let (tx, _) = &self.chunk_update_channel;
+        let sender = tx.clone();
+        let handle = self.workers.submit(ChunkJob::Mesh(loc), move |token| {
+            let mesh = make_greedy_chunk_mesh(&task_chunk, &connected_textures, &overlays);
+            if token.is_cancelled() {
+                return;
+            }
+            MEMORY.allocate(Subsystem::PendingMeshes, mesh.byte_size());
+            let _ = sender.send((loc, token, mesh));
+        });
+        let handle = match handle {
+            Some(handle) => handle,
+            None => return false,
+        };
         {
             let mut guard = chunk.recalculate.lock().unwrap();
             *guard = false;
         }
-        let chunk = chunk.clone();
-        let (tx, _) = &self.chunk_update_channel;
+        self.mesh_tasks.insert(loc, handle);
+        true
+    }
+
+    /// Queues the light map of a chunk to be recalculated. A task
+    /// still lighting the chunk is cancelled. Returns `false` and
+    /// leaves the chunk marked if the queue is full.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The chunk whose light map should be recalculated
+    /// * `neighbours` - The generated chunks adjacent to the chunk
+    pub fn relight_chunk(&mut self, chunk: &Chunk, neighbours: Vec<Chunk>) -> bool {
+        let loc = *chunk.loc();
+        let lit_neighbours = neighbours.len();
+        let task_chunk = chunk.clone();
+        let light_format = self.light_format;
+        let (tx, _) = &self.light_update_channel;
         let sender = tx.clone();
-        thread::spawn(move || {
-            let mesh = make_greedy_chunk_mesh(&chunk);
-            sender.send((chunk.loc.clone(), mesh)).unwrap();
+        let handle = self.workers.submit(ChunkJob::Light(loc), move |token| {
+            let light_map = LightMap::compute(&task_chunk, &neighbours, light_format);
+            if token.is_cancelled() {
+                return;
+            }
+            MEMORY.allocate(Subsystem::PendingMeshes, light_map.texels().len());
+            let _ = sender.send((loc, token, light_map));
         });
-
+        let handle = match handle {
+            Some(handle) => handle,
+            None => return false,
+        };
+        {
+            let mut guard = chunk.relight.lock().unwrap();
+            *guard = false;
+        }
+        self.lit_neighbours.insert(loc, lit_neighbours);
+        self.light_tasks.insert(loc, handle);
+        true
     }
 
     /// Prepares the rendering process by reading in some mesh updates
-    /// and inserting them into the chunk map
+    /// and inserting them into the chunk map. Results of cancelled
+    /// tasks are dropped, e.g. of chunks which have been unloaded.
     pub fn prepare(&mut self) {
         let (_, rx) = &self.chunk_update_channel;
-        for (loc, mesh) in rx.try_iter() {
-            let model = ChunkModel::from_chunk_mesh(&self.gl, &mesh);
+        for (loc, token, mesh) in rx.try_iter() {
+            MEMORY.release(Subsystem::PendingMeshes, mesh.byte_size());
+            if !finish_task(&mut self.mesh_tasks, &loc, &token) {
+                continue;
+            }
+            // Remeshed chunks don't fade in again
+            let mut model = ChunkModel::from_chunk_mesh(&self.backend, &mesh);
+            if let Some(Some(previous)) = self.chunk_map.get(&loc) {
+                model.shown_at = previous.shown_at;
+            }
             self.chunk_map.insert(loc, Some(model));
         }
+
+        // Light maps of known chunks are re-uploaded in place
+        let (_, rx) = &self.light_update_channel;
+        let light_maps = &mut self.light_maps;
+        for (loc, token, light_map) in rx.try_iter() {
+            MEMORY.release(Subsystem::PendingMeshes, light_map.texels().len());
+            if !finish_task(&mut self.light_tasks, &loc, &token) {
+                continue;
+            }
+            let result = match light_maps.get(&loc) {
+                Some(texture) => texture.upload(light_map.texels()),
+                None => self.backend.create_texture_3d(LIGHT_MAP_SIZE, texel_format(light_map.format()), light_map.texels())
+                    .map(|texture| { light_maps.insert(loc, texture); }),
+            };
+            if let Err(error) = result {
+                log::error!("Failed to upload the light map of chunk {:?}: {}", loc, error);
+            }
+        }
+
+        METRICS.set(Metric::MeshQueue, (self.mesh_tasks.len() + self.light_tasks.len()) as f64);
     }
 
     /// Returns the model at a given location or `None`
@@ -462,7 +1248,7 @@ impl ChunkRenderer {
     //         let chunk = Chunk::new(&self.gl, Vector2::new(0, 0));
     //         let mesh = make_greedy_chunk_mesh(&chunk);
     //
-    //         let chunk_model = ChunkModel::from_chunk_mesh(&self.gl, &mesh);
+    //         let chunk_model = ChunkModel::from_chunk_mesh(&self.backend, &mesh);
     //         chunk_model.bind();
     //
     //         // Create a new entity
@@ -493,12 +1279,53 @@ impl ChunkRenderer {
     //     self.chunk_positions.clear();
     // }
 
-    /// Renders a given chunk
+    /// Renders the given chunks within the render distance around
+    /// the camera. Models of chunks which aren't part of the given
+    /// chunks anymore are released, while the models of chunks
+    /// beyond the render distance are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunks` - The chunks which should be rendered to the screen
+    /// * `camera` - A perspective camera
+    /// * `render_distance` - The distance in chunks up to which chunks are drawn
+    pub fn render(&mut self, chunks: &[Chunk], camera: &dyn Projection, render_distance: i32) {
+        self.prepare();
+        self.retain_chunks(chunks);
+
+        let center = Vector2::new(
+            (camera.pos().x / CHUNK_SIZE as f32).floor() as i32,
+            (camera.pos().z / CHUNK_SIZE as f32).floor() as i32,
+        );
+        let generated: HashMap<&Vector2<i32>, &Chunk> = chunks.iter()
+            .filter(|chunk| chunk.is_generated())
+            .map(|chunk| (chunk.loc(), chunk))
+            .collect();
+        for chunk in chunks {
+            let offset = chunk.loc() - center;
+            if offset.x.abs() <= render_distance && offset.y.abs() <= render_distance {
+                let neighbours: Vec<Chunk> = light::ADJACENT.iter()
+                    .filter_map(|offset| generated.get(&(chunk.loc() + offset)))
+                    .map(|&neighbour| neighbour.clone())
+                    .collect();
+                self.render_chunk(chunk, neighbours, camera);
+            }
+        }
+    }
+
+    /// Renders a given chunk. Chunks whose terrain is still
+    /// generated are skipped, so they aren't meshed in vain.
     ///
     /// # Arguments
     ///
     /// * `chunk` - The chunk which should be rendered to the screen
-    pub fn render_chunk(&self, chunk: &Chunk, camera: &PerspectiveCamera) {
+    /// * `neighbours` - The generated chunks adjacent to the chunk,
+    ///   whose light spreads into it
+    pub fn render_chunk(&mut self, chunk: &Chunk, neighbours: Vec<Chunk>, camera: &dyn Projection) {
+        if !chunk.is_generated() {
+            return;
+        }
+
         let recalculate;
         {
             let guard = chunk.recalculate.lock().unwrap();
@@ -507,14 +1334,29 @@ impl ChunkRenderer {
 
         if recalculate {
             self.recalculate_chunk(&chunk);
-            // chunk.recalculate_model();
         }
 
-        // if let Some(chunk_model) = chunk.model.lock().unwrap().as_ref() {
+        let relight = *chunk.relight.lock().unwrap()
+            || self.lit_neighbours.get(chunk.loc()) != Some(&neighbours.len());
+        if relight {
+            self.relight_chunk(chunk, neighbours);
+        }
+
         if let Some(chunk_model) = self.model(chunk.loc()) {
-            let shader_program = self.shader_program.borrow();
+            let shader_program = self.debug_programs.iter()
+                .find(|(view, _)| *view == self.debug_view)
+                .map_or(&self.shader_program, |(_, program)| program);
             shader_program.enable();
             shader_program.set_uniform_1i("u_Texture", 0);
+            shader_program.set_uniform_1f("u_Light", self.light);
+            let light_map = self.light_maps.get(chunk.loc()).unwrap_or(&self.unlit);
+            shader_program.set_uniform_1i("u_LightMap", 1);
+            shader_program.set_uniform_1i("u_RgbLight", (light_map.format() == TexelFormat::R16) as i32);
+            shader_program.set_uniform_1f("u_Opacity", chunk_model.opacity());
+            shader_program.set_uniform_1i("u_Environment", 2);
+            self.cave_fog.apply(shader_program, self.fog_allowed);
+            light_map.bind(1);
+            self.environment.bind(2);
             self.tex_atlas.bind(None);
             chunk_model.bind();
 
@@ -532,15 +1374,11 @@ impl ChunkRenderer {
             let mvp = proj * view * model;
             shader_program.set_uniform_mat4f("u_MVP", &mvp);
 
-            // `OpenGL` draw call
-            unsafe {
-                self.gl.DrawElements(
-                    gl::TRIANGLES,
-                    chunk_model.ib().index_count() as i32,
-                    gl::UNSIGNED_INT,
-                    std::ptr::null(),
-                );
-            }
+            // The vertices are relative to the chunk, so is the eye
+            let eye = camera.pos() - ent.pos();
+            shader_program.set_uniform_3f("u_Eye", eye.x, eye.y, eye.z);
+
+            self.renderer.draw(chunk_model.va(), chunk_model.ib(), shader_program);
 
             chunk_model.unbind();
             self.tex_atlas.unbind();
@@ -548,14 +1386,80 @@ impl ChunkRenderer {
         }
     }
 
-    /// Clears the `OpenGL` rendered context
+    /// Clears the rendered context
     pub fn clear(&self) {
-        unsafe {
-            self.gl.Clear(gl::COLOR_BUFFER_BIT);
-            self.gl.Clear(gl::DEPTH_BUFFER_BIT);
+        self.renderer.clear();
+    }
+
+}
+
+/// Returns the amount of workers of a pool working on chunks, e.g.
+/// meshing or generating them, which leaves a core each to the
+/// render and simulation thread
+pub(crate) fn chunk_workers() -> usize {
+    thread::available_parallelism().map_or(1, |cores| cores.get().saturating_sub(2).max(1))
+}
+
+/// Returns the format of the texture a light map is uploaded to
+///
+/// # Arguments
+///
+/// * `format` - The layout of the light map
+fn texel_format(format: LightFormat) -> TexelFormat {
+    match format {
+        LightFormat::Rgb => TexelFormat::R16,
+        LightFormat::Scalar => TexelFormat::R8,
+    }
+}
+
+/// Removes the handle of a task whose result has been received.
+/// Returns whether the task is still the current one of its
+/// chunk, so that its result should be used.
+///
+/// # Arguments
+///
+/// * `tasks` - The running tasks by the location of their chunk
+/// * `loc` - The location of the chunk of the task
+/// * `token` - The token of the task
+fn finish_task(tasks: &mut HashMap<Vector2<i32>, TaskHandle>, loc: &Vector2<i32>, token: &CancelToken) -> bool {
+    if token.is_cancelled() {
+        return false;
+    }
+    match tasks.get(loc) {
+        Some(handle) if handle.owns(token) => {
+            tasks.remove(loc);
+            true
         }
+        _ => false,
     }
+}
 
+impl<F: ChunkFrame> RenderPass<F> for ChunkRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Opaque
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        self.light = ctx.frame.light();
+        self.fog_allowed = ctx.quality.cave_fog;
+        self.debug_view = ctx.debug_view;
+        self.environment.update(ctx.frame.sky_color());
+        if self.textures != ctx.textures.generation() {
+            self.tex_atlas = ctx.textures.atlas(&self.backend);
+            self.textures = ctx.textures.generation();
+        }
+        // The meshes refer to the tiles of the blocks, so they're
+        // only rebuilt if the tiles changed with the texture pack
+        let tiles_changed = !Arc::ptr_eq(&self.connected_textures, ctx.textures.connected());
+        if tiles_changed || !Arc::ptr_eq(&self.overlays, ctx.frame.overlays()) {
+            self.connected_textures = ctx.textures.connected().clone();
+            self.overlays = ctx.frame.overlays().clone();
+            for chunk in ctx.frame.chunks() {
+                chunk.mark_recalculate();
+            }
+        }
+        ChunkRenderer::render(self, ctx.frame.chunks(), ctx.frame.camera(), ctx.quality.render_distance);
+    }
 }
 
 /*
@@ -591,14 +1495,102 @@ impl Side {
 pub struct VoxelFace {
     side: Side,
     material: Material,
+    /// The tile of a connected texture the face is textured with
+    tile: Option<Vector2<f32>>,
+    /// The tile layered over the texture of the face
+    overlay: Option<Overlay>,
+    /// The ambient occlusion at the corners of the face from
+    /// fully occluded at `0` to unoccluded at `3`, see `occluded`
+    occlusion: [u8; 4],
 }
 
 impl VoxelFace {
     fn new(chunk: &Chunk, loc: Vector3<i16>, side: Side) -> Self {
+        // Blocks which aren't cubes don't hide the faces of their
+        // neighbours, they are added to the mesh on their own
+        let material = match chunk.block(loc) {
+            Some(material) if material.data().shape() == BlockShape::Cube => material,
+            _ => Material::Air,
+        };
+
+        // Blocks are drawn with the sprites of their material, only
+        // grass falls back to the default tiles of the mesh
+        let data = material.data();
+        let tile = match side {
+            _ if material == Material::Grass => None,
+            Side::TOP => Some(data.tex_coords().top()),
+            Side::BOTTOM => Some(data.tex_coords().bottom()),
+            _ => Some(data.tex_coords().side()),
+        };
+
         Self {
             side,
-            material: chunk.block(loc).unwrap_or(Material::Air),
+            material,
+            tile,
+            overlay: None,
+            occlusion: [3; 4],
+        }
+    }
+
+    /// Selects the overlay of the face by its material and side
+    fn overlaid(mut self, overlays: &BlockOverlays) -> Self {
+        self.overlay = overlays.overlay(self.material, self.side.normal());
+        self
+    }
+
+    /// Selects the tile of a connected texture for the face of
+    /// the block at the given location, if its material connects.
+    /// Blocks of adjacent chunks aren't taken into account.
+    fn connect(mut self, chunk: &Chunk, loc: Vector3<i16>, connected_textures: &ConnectedTextures) -> Self {
+        if self.material.data().connects() {
+            let connections = connected::connections(|loc| chunk.block(loc), loc, self.side.normal());
+            self.tile = connected_textures.tile(self.material, connections);
+        }
+        self
+    }
+
+    /// Computes the ambient occlusion at the corners of the face of
+    /// the block at the given location. Each corner is occluded by
+    /// the opaque blocks in front of the face which touch it. The
+    /// face spans the axes `u` and `v`, its corners are in the order
+    /// the quads add their vertices. Blocks of adjacent chunks aren't
+    /// taken into account.
+    fn occluded(mut self, chunk: &Chunk, loc: Vector3<i16>, u: usize, v: usize) -> Self {
+        let normal = self.side.normal();
+        let front = loc + Vector3::new(normal[0] as i16, normal[1] as i16, normal[2] as i16);
+        let occludes = |du: i16, dv: i16| {
+            let mut offset = Vector3::new(0, 0, 0);
+            offset[u] = du;
+            offset[v] = dv;
+            matches!(chunk.block(front + offset), Some(material)
+                if material != Material::Air && material.data().shape() == BlockShape::Cube && material.data().is_opaque())
+        };
+        for (corner, &(du, dv)) in [(-1, -1), (-1, 1), (1, -1), (1, 1)].iter().enumerate() {
+            let (side1, side2) = (occludes(du, 0), occludes(0, dv));
+            // A corner between two blocks is fully occluded, no
+            // matter whether the block across the corner is there
+            self.occlusion[corner] = if side1 && side2 {
+                0
+            } else {
+                3 - side1 as u8 - side2 as u8 - occludes(du, dv) as u8
+            };
         }
+        self
+    }
+
+    /// Returns whether the block of the face is opaque, so it
+    /// hides the faces of its neighbours pointing towards it
+    fn hides(&self) -> bool {
+        self.material != Material::Air && self.material.data().is_opaque()
+    }
+
+    /// Returns whether the face could be merged with another face
+    /// into a single quad. Faces with connected textures have a
+    /// tile of their own, so they are never merged. Faces which are
+    /// occluded differently are neither, as the occlusion is only
+    /// interpolated between the corners of a quad.
+    fn merges_with(&self, other: &VoxelFace) -> bool {
+        self == other && self.occlusion == other.occlusion && !self.material.data().connects()
     }
 }
 
@@ -619,7 +1611,10 @@ impl PartialEq for VoxelFace {
 ///
 /// * `chunk`- The chunk for which a mesh
 /// should be generated
-fn make_greedy_chunk_mesh(chunk: &Chunk) -> ChunkMesh {
+/// * `connected_textures` - The tile sets of blocks with
+/// connected textures
+/// * `overlays` - The overlays of the block faces
+fn make_greedy_chunk_mesh(chunk: &Chunk, connected_textures: &ConnectedTextures, overlays: &BlockOverlays) -> ChunkMesh {
     let mut mesh = ChunkMesh::default();
 
     /*
@@ -630,6 +1625,10 @@ fn make_greedy_chunk_mesh(chunk: &Chunk) -> ChunkMesh {
     let (mut i, mut j, mut k, mut l, mut w, mut h, mut u, mut v, mut n, mut side);
     side = Side::SOUTH;
 
+    // The size of the chunk along each axis, the mask spans
+    // the two axes of the faces of the current direction
+    let dims = [CHUNK_SIZE, CHUNK_HEIGHT, CHUNK_SIZE];
+
     let mut x = [0i16; 3];
     let mut q = [0i16; 3];
     let mut du = [0i16; 3];
@@ -687,7 +1686,7 @@ fn make_greedy_chunk_mesh(chunk: &Chunk) -> ChunkMesh {
              * Here we're keeping track of the side that we're meshing.
              */
             if d == 0 {
-                side = if back_face { Side::WEST } else { Side::EAST };
+                side = if back_face { Side::EAST } else { Side::WEST };
             } else if d == 1 {
                 side = if back_face { Side::BOTTOM } else { Side::TOP };
             } else if d == 2 {
@@ -698,16 +1697,16 @@ fn make_greedy_chunk_mesh(chunk: &Chunk) -> ChunkMesh {
              * We move through the dimensions from front to back
              */
             x[d] = -1;
-            while x[d] < CHUNK_SIZE as i16 {
+            while x[d] < dims[d] as i16 {
                 /*
                  * We compute the mask
                  */
                 n = 0;
 
                 x[v] = 0;
-                while x[v] < CHUNK_HEIGHT as i16 {
+                while x[v] < dims[v] as i16 {
                     x[u] = 0;
-                    while x[u] < CHUNK_SIZE as i16 {
+                    while x[u] < dims[u] as i16 {
                         /*
                          * Here we retrieve two voxel faces for comparison.
                          */
@@ -715,7 +1714,7 @@ fn make_greedy_chunk_mesh(chunk: &Chunk) -> ChunkMesh {
                             let vface = VoxelFace::new(&chunk, Vector3::new(x[0], x[1], x[2]), side);
                             Some(vface)
                         } else { None };
-                        face1_op = if x[d] < (CHUNK_SIZE as i16 - 1) {
+                        face1_op = if x[d] < (dims[d] as i16 - 1) {
                             Some(VoxelFace::new(&chunk, Vector3::new(x[0] + q[0], x[1] + q[1], x[2] + q[2]), side))
                         } else { None };
 
@@ -729,7 +1728,17 @@ fn make_greedy_chunk_mesh(chunk: &Chunk) -> ChunkMesh {
                          */
                         mask[n] = match (face_op, face1_op) {
                             (Some(face), Some(face1)) if face == face1 => None,
-                            _ => if back_face { face1_op } else { face_op }
+                            // Faces against opaque blocks are never seen, e.g.
+                            // the ones between stone and dirt under the ground
+                            (Some(face), _) if back_face && face.hides() => None,
+                            (_, Some(face1)) if !back_face && face1.hides() => None,
+                            _ => if back_face {
+                                let loc = Vector3::new(x[0] + q[0], x[1] + q[1], x[2] + q[2]);
+                                face1_op.map(|face| face.connect(chunk, loc, connected_textures).overlaid(overlays).occluded(chunk, loc, u, v))
+                            } else {
+                                let loc = Vector3::new(x[0], x[1], x[2]);
+                                face_op.map(|face| face.connect(chunk, loc, connected_textures).overlaid(overlays).occluded(chunk, loc, u, v))
+                            }
                         };
 
                         n+=1;
@@ -748,21 +1757,21 @@ fn make_greedy_chunk_mesh(chunk: &Chunk) -> ChunkMesh {
                 n = 0;
 
                 j = 0;
-                while j < CHUNK_HEIGHT {
+                while j < dims[v] {
                     i = 0;
-                    while i < CHUNK_SIZE {
+                    while i < dims[u] {
 
                         if let Some(_) = mask[n] {
                             /*
                              * We compute the width
                              */
-                            let compute_width = |i, w, mask: &[Option<VoxelFace>; CHUNK_SIZE * CHUNK_HEIGHT]| {
+                            let compute_width = |i: usize, w: usize, mask: &[Option<VoxelFace>; CHUNK_SIZE * CHUNK_HEIGHT]| {
                                 if n + w >= mask.len() {
                                     return false;
                                 }
 
                                 match mask[n + w] {
-                                    Some(face) if i + w < CHUNK_SIZE && face == mask[n].unwrap() => true,
+                                    Some(face) if i + w < dims[u] && face.merges_with(&mask[n].unwrap()) => true,
                                     _ => false,
                                 }
 
@@ -779,13 +1788,13 @@ fn make_greedy_chunk_mesh(chunk: &Chunk) -> ChunkMesh {
                             let mut done = false;
 
                             h = 1;
-                            while j + h < CHUNK_HEIGHT {
+                            while j + h < dims[v] {
                                 k=0;
                                 while k < w {
 
                                     let compute_height = |h: usize, k: usize, n: usize, mask: &[Option<VoxelFace>; CHUNK_SIZE * CHUNK_HEIGHT]| {
-                                        match mask[n + k + h * CHUNK_SIZE] {
-                                            Some(face) => face != mask[n].unwrap(),
+                                        match mask[n + k + h * dims[u]] {
+                                            Some(face) => !face.merges_with(&mask[n].unwrap()),
                                             _ => true,
                                         }
                                     };
@@ -856,7 +1865,7 @@ fn make_greedy_chunk_mesh(chunk: &Chunk) -> ChunkMesh {
                             while l < h {
                                 k = 0;
                                 while k < w {
-                                    mask[n + k + l * CHUNK_SIZE] = None;
+                                    mask[n + k + l * dims[u]] = None;
                                     k += 1;
                                 }
                                 l += 1;
@@ -883,5 +1892,287 @@ fn make_greedy_chunk_mesh(chunk: &Chunk) -> ChunkMesh {
         b = !b;
     }
 
+    add_crosses(&mut mesh, chunk);
     mesh
-}
\ No newline at end of file
+}
+
+/// Adds all blocks of a chunk shaped like a cross to its mesh.
+/// The state of a block selects its sprite, counted from the
+/// side sprite of its material to the right.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh of the chunk
+/// * `chunk` - The chunk
+fn add_crosses(mesh: &mut ChunkMesh, chunk: &Chunk) {
+    chunk.with_blocks(|blocks| add_crosses_of(mesh, blocks, &chunk.states.lock().unwrap()));
+}
+
+/// Adds the blocks shaped like a cross to the mesh of a chunk
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh of the chunk
+/// * `blocks` - The decoded blocks of the chunk
+/// * `states` - The states of the blocks by their index
+fn add_crosses_of(mesh: &mut ChunkMesh, blocks: &[Material; CHUNK_VOLUME], states: &HashMap<usize, u8>) {
+    for (index, material) in blocks.iter().enumerate() {
+        let data = material.data();
+        if data.shape() != BlockShape::Cross {
+            continue;
+        }
+
+        let state = states.get(&index).copied().unwrap_or(0);
+        let face = VoxelFace {
+            side: Side::NORTH,
+            material: *material,
+            tile: Some(data.tex_coords().side() + Vector2::new(state as f32, 0.0)),
+            overlay: None,
+            occlusion: [3; 4],
+        };
+        let loc = Vector3::new(index % CHUNK_SIZE, index / CHUNK_AREA, index / CHUNK_SIZE % CHUNK_SIZE);
+        mesh.add_cross(loc.cast::<f32>().unwrap(), &face);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::InnerSpace;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// The directory of the golden meshes, relative to the manifest
+    const GOLDEN_DIR: &str = "tests/meshes";
+
+    /// Builds a chunk with blocks at the given locations
+    fn chunk_of(blocks: &[(Vector3<i16>, Material)]) -> Chunk {
+        let chunk = Chunk::new(Vector2::new(0, 0));
+        for &(loc, material) in blocks {
+            chunk.set_block(loc, material);
+        }
+        chunk
+    }
+
+    fn mesh_of(chunk: &Chunk) -> ChunkMesh {
+        make_greedy_chunk_mesh(chunk, &ConnectedTextures::default(), &BlockOverlays::default())
+    }
+
+    /// The corners of a triangle in the order they're wound
+    type Triangle = [Vector3<f32>; 3];
+
+    /// Returns the normal and the two triangles of each quad
+    fn quads(mesh: &ChunkMesh) -> Vec<(Vector3<f32>, [Triangle; 2])> {
+        let vertex = |index: u32| {
+            let i = index as usize * 3;
+            let positions = &mesh.mesh.vertex_positions;
+            Vector3::new(positions[i], positions[i + 1], positions[i + 2])
+        };
+        mesh.mesh.indices.chunks(6).enumerate()
+            .map(|(quad, indices)| {
+                let normals = &mesh.mesh.normals[quad * 12..quad * 12 + 3];
+                let triangles = [
+                    [vertex(indices[0]), vertex(indices[1]), vertex(indices[2])],
+                    [vertex(indices[3]), vertex(indices[4]), vertex(indices[5])],
+                ];
+                (Vector3::new(normals[0], normals[1], normals[2]), triangles)
+            })
+            .collect()
+    }
+
+    /// Returns whether a block hides the faces next to it, which
+    /// is how the mesher sees the blocks of adjacent chunks as well
+    fn hides(material: Option<Material>) -> bool {
+        matches!(material, Some(material)
+            if material != Material::Air && material.data().shape() == BlockShape::Cube && material.data().is_opaque())
+    }
+
+    /// Asserts the properties every chunk mesh has. The triangles are
+    /// wound counter-clockwise around the normals of their quads, the
+    /// quads cover exactly the faces of blocks which are visible, and
+    /// no quad lies between two blocks hiding each other.
+    fn assert_well_formed(chunk: &Chunk, mesh: &ChunkMesh) {
+        let mut area = 0.0;
+        for (normal, triangles) in quads(mesh) {
+            for [a, b, c] in triangles.iter() {
+                let winding = (b - a).cross(c - a);
+                assert!(winding.dot(normal) > 0.0, "{:?} is wound against its normal {:?}", [a, b, c], normal);
+                area += winding.magnitude() / 2.0;
+            }
+
+            // Each block the quad covers is behind it, while
+            // the block in front of it doesn't hide it
+            let corners = [triangles[0][0], triangles[0][1], triangles[0][2], triangles[1][0], triangles[1][1], triangles[1][2]];
+            let min = corners.iter().fold(corners[0], |min, c| Vector3::new(min.x.min(c.x), min.y.min(c.y), min.z.min(c.z)));
+            let max = corners.iter().fold(corners[0], |max, c| Vector3::new(max.x.max(c.x), max.y.max(c.y), max.z.max(c.z)));
+            let cells = |from: f32, to: f32| (from as i16..(to as i16).max(from as i16 + 1)).map(|c| c as f32 + 0.5);
+            for x in cells(min.x, max.x) {
+                for y in cells(min.y, max.y) {
+                    for z in cells(min.z, max.z) {
+                        let center = Vector3::new(x, y, z) - normal.map(|n| n.abs() * 0.5);
+                        let front = (center + normal * 0.5).map(|c| c.floor() as i16);
+                        let back = (center - normal * 0.5).map(|c| c.floor() as i16);
+                        let material = chunk.block(back).unwrap_or(Material::Air);
+                        assert!(material != Material::Air, "the quad at {:?} covers no block", center);
+                        assert!(!hides(chunk.block(front)) && chunk.block(front) != Some(material),
+                                "the quad at {:?} lies between two blocks", center);
+                    }
+                }
+            }
+        }
+
+        // The visible faces are counted one by one
+        let mut faces = 0;
+        for x in 0..CHUNK_SIZE as i16 {
+            for y in 0..CHUNK_HEIGHT as i16 {
+                for z in 0..CHUNK_SIZE as i16 {
+                    let loc = Vector3::new(x, y, z);
+                    let material = match chunk.block(loc) {
+                        Some(material) if material != Material::Air && material.data().shape() == BlockShape::Cube => material,
+                        _ => continue,
+                    };
+                    for side in [Side::SOUTH, Side::NORTH, Side::EAST, Side::WEST, Side::TOP, Side::BOTTOM].iter() {
+                        let normal = side.normal();
+                        let neighbour = chunk.block(loc + Vector3::new(normal[0] as i16, normal[1] as i16, normal[2] as i16));
+                        if !hides(neighbour) && neighbour != Some(material) {
+                            faces += 1;
+                        }
+                    }
+                }
+            }
+        }
+        assert_eq!(area, faces as f32, "the quads don't cover the visible faces");
+    }
+
+    /// Compares a mesh with its golden file, which lists each triangle
+    /// with its normal and tile. The lines are sorted, so meshes only
+    /// differ if their geometry does, not if their quads are added in
+    /// another order. Running the tests with `UPDATE_GOLDEN_MESHES`
+    /// set writes the golden files instead.
+    fn assert_golden(name: &str, mesh: &ChunkMesh) {
+        let mut lines: Vec<String> = quads(mesh).iter().enumerate()
+            .flat_map(|(quad, (normal, triangles))| {
+                let tile = &mesh.tile_offsets[quad * 8..quad * 8 + 2];
+                triangles.iter()
+                    .map(|triangle| {
+                        let corners: Vec<String> = triangle.iter().map(|c| format!("{} {} {}", c.x, c.y, c.z)).collect();
+                        format!("normal {} {} {} | tile {} {} | {}", normal.x, normal.y, normal.z, tile[0], tile[1], corners.join(", "))
+                    })
+                    .collect::<Vec<String>>()
+            })
+            .collect();
+        lines.sort();
+        let actual = lines.join("\n") + "\n";
+
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(GOLDEN_DIR).join(format!("{}.txt", name));
+        if env::var_os("UPDATE_GOLDEN_MESHES").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("failed to read {}, run with UPDATE_GOLDEN_MESHES set to create it: {}", path.display(), err));
+        assert!(actual == expected, "the mesh differs from {}, run with UPDATE_GOLDEN_MESHES set if that's intended:\n{}", path.display(), actual);
+    }
+
+    #[test]
+    fn single_blocks_have_six_faces() {
+        let chunk = chunk_of(&[(Vector3::new(3, 5, 5), Material::Stone), (Vector3::new(10, 100, 7), Material::Dirt)]);
+        let mesh = mesh_of(&chunk);
+        assert_eq!(quads(&mesh).len(), 12);
+        assert_well_formed(&chunk, &mesh);
+        assert_golden("single_blocks", &mesh);
+    }
+
+    #[test]
+    fn l_shapes_merge_into_rectangles() {
+        let mut blocks = Vec::new();
+        for i in 0..4 {
+            blocks.push((Vector3::new(2 + i, 8, 2), Material::Stone));
+            blocks.push((Vector3::new(2, 8, 3 + i), Material::Stone));
+        }
+        let chunk = chunk_of(&blocks);
+        let mesh = mesh_of(&chunk);
+
+        // Two quads on top and bottom, six around the sides and two more
+        // where the walls of the inner corner are occluded by the other arm
+        assert_eq!(quads(&mesh).len(), 12);
+        assert_well_formed(&chunk, &mesh);
+        assert_golden("l_shape", &mesh);
+    }
+
+    #[test]
+    fn checkerboards_keep_their_materials_apart() {
+        let mut blocks = Vec::new();
+        for x in 0..4 {
+            for z in 0..4 {
+                let material = if (x + z) % 2 == 0 { Material::Stone } else { Material::Dirt };
+                blocks.push((Vector3::new(4 + x, 20, 4 + z), material));
+                if (x + z) % 2 == 0 {
+                    blocks.push((Vector3::new(4 + x, 30, 4 + z), Material::Stone));
+                }
+            }
+        }
+        let chunk = chunk_of(&blocks);
+        let mesh = mesh_of(&chunk);
+
+        // The layer of stone and dirt has no faces between its blocks,
+        // while the blocks touching by their edges have all six faces
+        assert_eq!(quads(&mesh).len(), 16 * 2 + 16 + 8 * 6);
+        assert_well_formed(&chunk, &mesh);
+        assert_golden("checkerboard", &mesh);
+    }
+
+    #[test]
+    fn chunk_borders_close_the_mesh() {
+        let mut blocks = vec![
+            (Vector3::new(0, 0, 0), Material::Stone),
+            (Vector3::new(15, 255, 15), Material::Stone),
+        ];
+        for y in 0..CHUNK_HEIGHT as i16 {
+            blocks.push((Vector3::new(15, y, 0), Material::Stone));
+        }
+        let chunk = chunk_of(&blocks);
+        let mesh = mesh_of(&chunk);
+
+        // The blocks of adjacent chunks are unknown, so the faces
+        // along the borders are kept and the column is a single box
+        assert_eq!(quads(&mesh).len(), 6 * 3);
+        assert_well_formed(&chunk, &mesh);
+        assert_golden("chunk_borders", &mesh);
+    }
+
+    #[test]
+    fn transparent_blocks_show_what_is_behind_them() {
+        let chunk = chunk_of(&[
+            (Vector3::new(5, 40, 5), Material::Stone),
+            (Vector3::new(6, 40, 5), Material::Glass),
+            (Vector3::new(7, 40, 5), Material::Glass),
+        ]);
+        let mesh = mesh_of(&chunk);
+
+        // The stone shows its face behind the glass, while the glass
+        // hides none. Glass connects, so its faces aren't merged.
+        assert_eq!(quads(&mesh).len(), 6 + 2 * 4 + 1);
+        assert_well_formed(&chunk, &mesh);
+        assert_golden("transparent", &mesh);
+    }
+
+    #[test]
+    fn corners_next_to_blocks_are_occluded() {
+        let chunk = chunk_of(&[(Vector3::new(5, 10, 5), Material::Stone), (Vector3::new(6, 11, 5), Material::Stone)]);
+        let mesh = mesh_of(&chunk);
+        assert_well_formed(&chunk, &mesh);
+
+        // The top of the lower block is darkened towards the block next to it
+        let positions = &mesh.mesh.vertex_positions;
+        let corners: Vec<(f32, f32)> = mesh.mesh.normals.chunks(3).enumerate()
+            .filter(|(vertex, normal)| normal[1] == 1.0 && positions[vertex * 3 + 1] == 11.0)
+            .map(|(vertex, _)| (positions[vertex * 3], mesh.occlusions[vertex]))
+            .collect();
+        assert_eq!(corners.len(), 4);
+        for (x, occlusion) in corners {
+            assert_eq!(occlusion, if x == 6.0 { 2.0 / 3.0 } else { 1.0 }, "the corner at x {}", x);
+        }
+    }
+}
@@ -0,0 +1,505 @@
+//! Types to store entities in the chunks they are in,
+//! so that only the ones near the player are ticked
+
+use crate::world::chunk::CHUNK_SIZE;
+use crate::world::ticks::TickRng;
+use cgmath::{Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use toml::value::{Table, Value};
+
+/// The type id of dropped items
+pub const ITEM_TYPE: &str = "item";
+/// The type id of mobs
+pub const MOB_TYPE: &str = "mob";
+/// The type id of vehicles
+pub const VEHICLE_TYPE: &str = "vehicle";
+
+/// The amount of ids generated so far, which tells
+/// apart the ids generated at the same time
+static GENERATED_IDS: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the location of the chunk containing a position
+///
+/// # Arguments
+///
+/// * `pos` - The position in world coordinates
+pub fn chunk_of(pos: Vector3<f32>) -> Vector2<i32> {
+    let size = CHUNK_SIZE as f32;
+    Vector2::new((pos.x / size).floor() as i32, (pos.z / size).floor() as i32)
+}
+
+/// Returns whether a chunk is within the activation
+/// radius around another chunk
+///
+/// # Arguments
+///
+/// * `loc` - The location of the chunk
+/// * `center` - The location of the chunk in the center
+/// * `radius` - The activation radius in chunks
+pub fn is_active(loc: Vector2<i32>, center: Vector2<i32>, radius: i32) -> bool {
+    (loc.x - center.x).abs() <= radius && (loc.y - center.y).abs() <= radius
+}
+
+/// ChunkEntity
+///
+/// An entity which is stored in the chunk it's in
+pub trait ChunkEntity {
+    /// Returns the id of the entity, which stays the
+    /// same as the entity is saved and loaded
+    fn uuid(&self) -> EntityUuid;
+
+    /// Returns the position of the entity in world coordinates
+    fn pos(&self) -> Vector3<f32>;
+}
+
+/// ChunkEntities
+///
+/// Entities in lists by the chunk they are in. Entities
+/// are moved to the list of another chunk once they enter it.
+pub struct ChunkEntities<T: ChunkEntity> {
+    /// The lists of entities by the location of their chunk,
+    /// ordered, so that entities are ticked in the same order
+    lists: BTreeMap<(i32, i32), Vec<T>>,
+}
+
+impl<T: ChunkEntity> Default for ChunkEntities<T> {
+    fn default() -> Self {
+        Self {
+            lists: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: ChunkEntity> ChunkEntities<T> {
+    /// Adds an entity to the list of the chunk it's in
+    ///
+    /// # Arguments
+    ///
+    /// * `entity` - The entity
+    pub fn insert(&mut self, entity: T) {
+        let loc = chunk_of(entity.pos());
+        self.lists.entry((loc.x, loc.y)).or_default().push(entity);
+    }
+
+    /// Returns the amount of entities in all chunks
+    pub fn len(&self) -> usize {
+        self.lists.values().map(Vec::len).sum()
+    }
+
+    /// Returns whether there are no entities at all
+    pub fn is_empty(&self) -> bool {
+        self.lists.is_empty()
+    }
+
+    /// Returns all entities, ordered by their chunk
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.lists.values().flatten()
+    }
+
+    /// Returns all entities mutably, ordered by their chunk.
+    /// Entities moved this way stay in the list of their chunk.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.lists.values_mut().flatten()
+    }
+
+    /// Returns the entities in a chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the chunk
+    pub fn in_chunk(&self, loc: Vector2<i32>) -> &[T] {
+        self.lists.get(&(loc.x, loc.y)).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the locations of the chunks containing entities
+    pub fn chunks(&self) -> Vec<Vector2<i32>> {
+        self.lists.keys().map(|&(x, y)| Vector2::new(x, y)).collect()
+    }
+
+    /// Returns the keys of the entities in the chunks within the
+    /// activation radius, ordered by their chunk. The keys stay
+    /// valid until entities are removed or relocated.
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - The location of the chunk in the center
+    /// * `radius` - The activation radius in chunks
+    pub fn active(&self, center: Vector2<i32>, radius: i32) -> Vec<EntityKey> {
+        self.lists.iter()
+            .filter(|(&(x, y), _)| is_active(Vector2::new(x, y), center, radius))
+            .flat_map(|(&chunk, list)| (0..list.len()).map(move |index| EntityKey { chunk, index }))
+            .collect()
+    }
+
+    /// Returns the key of the entity with the given id,
+    /// if it's in any chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The id of the entity
+    pub fn find_key(&self, uuid: EntityUuid) -> Option<EntityKey> {
+        self.lists.iter().find_map(|(&chunk, list)| {
+            let index = list.iter().position(|entity| entity.uuid() == uuid)?;
+            Some(EntityKey { chunk, index })
+        })
+    }
+
+    /// Returns the entity with the given key
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the entity
+    pub fn get_mut(&mut self, key: EntityKey) -> &mut T {
+        &mut self.lists.get_mut(&key.chunk).expect("the key of a removed chunk")[key.index]
+    }
+
+    /// Removes the entities with the given keys and moves the
+    /// other entities which left their chunk to the list of the
+    /// chunk they entered. Returns the removed entities.
+    ///
+    /// # Arguments
+    ///
+    /// * `removed` - The keys of the removed entities
+    pub fn remove_and_relocate(&mut self, mut removed: Vec<EntityKey>) -> Vec<T> {
+        // The entities in other chunks are removed like the
+        // removed ones, and inserted again afterwards
+        for (&chunk, list) in self.lists.iter() {
+            for (index, entity) in list.iter().enumerate() {
+                let loc = chunk_of(entity.pos());
+                if (loc.x, loc.y) != chunk {
+                    removed.push(EntityKey { chunk, index });
+                }
+            }
+        }
+        removed.sort_by(|a, b| b.cmp(a));
+        removed.dedup();
+
+        let mut taken = Vec::new();
+        let mut moved = Vec::new();
+        for key in removed {
+            let list = self.lists.get_mut(&key.chunk).expect("the key of a removed chunk");
+            let entity = list.swap_remove(key.index);
+            let loc = chunk_of(entity.pos());
+            if (loc.x, loc.y) == key.chunk {
+                taken.push(entity);
+            } else {
+                moved.push(entity);
+            }
+        }
+        self.lists.retain(|_, list| !list.is_empty());
+        for entity in moved {
+            self.insert(entity);
+        }
+        taken
+    }
+
+    /// Removes all entities of a chunk and returns them
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the chunk
+    pub fn take_chunk(&mut self, loc: Vector2<i32>) -> Vec<T> {
+        self.lists.remove(&(loc.x, loc.y)).unwrap_or_default()
+    }
+
+    /// Removes all entities and returns them
+    pub fn take_all(&mut self) -> Vec<T> {
+        std::mem::take(&mut self.lists).into_values().flatten().collect()
+    }
+}
+
+/// EntityKey
+///
+/// The key of an entity in its chunk list
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct EntityKey {
+    /// The location of the chunk
+    chunk: (i32, i32),
+    /// The index of the entity in the list of the chunk
+    index: usize,
+}
+
+/// EntityUuid
+///
+/// The id of an entity, which is a random version 4 `UUID`
+/// written as `8-4-4-4-12` hexadecimal digits in saves
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct EntityUuid(u128);
+
+impl EntityUuid {
+    /// Generates a new random id
+    pub fn generate() -> Self {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+        let count = GENERATED_IDS.fetch_add(1, Ordering::Relaxed);
+        let mut rng = TickRng::new(time ^ count.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        // The first numbers of seeds which are alike are alike too
+        for _ in 0..4 {
+            rng.next();
+        }
+        let bits = (rng.next() as u128) << 64 | rng.next() as u128;
+
+        // The version and variant bits of a random `UUID`
+        let bits = bits & !(0xF << 76) | 0x4 << 76;
+        Self(bits & !(0x3 << 62) | 0x2 << 62)
+    }
+}
+
+impl fmt::Display for EntityUuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = format!("{:032x}", self.0);
+        write!(f, "{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+    }
+}
+
+impl FromStr for EntityUuid {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let groups: Vec<&str> = source.split('-').collect();
+        let lengths: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+        if lengths != [8, 4, 4, 4, 12] {
+            return Err(format!("malformed entity id {}", source));
+        }
+        u128::from_str_radix(&groups.concat(), 16)
+            .map(Self)
+            .map_err(|_| format!("malformed entity id {}", source))
+    }
+}
+
+impl TryFrom<String> for EntityUuid {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        source.parse()
+    }
+}
+
+impl From<EntityUuid> for String {
+    fn from(uuid: EntityUuid) -> Self {
+        uuid.to_string()
+    }
+}
+
+/// SavedEntity
+///
+/// An entity saved with the chunk it's in. The type id tells
+/// which spawn function of the `EntityRegistry` restores the
+/// entity, while its components hold whatever the type needs,
+/// e.g. the name and amount of dropped items.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "EntityDef")]
+pub struct SavedEntity {
+    /// The id of the entity
+    pub uuid: EntityUuid,
+    /// The type of the entity, e.g. `item` or a type
+    /// registered by a script
+    #[serde(rename = "type")]
+    pub type_id: String,
+    /// The position of the bottom center of the entity
+    pub pos: [f32; 3],
+    /// The components of the entity
+    #[serde(skip_serializing_if = "Table::is_empty")]
+    pub components: Table,
+}
+
+impl SavedEntity {
+    /// Creates a new saved entity without components
+    ///
+    /// # Arguments
+    ///
+    /// * `type_id` - The type of the entity
+    /// * `uuid` - The id of the entity
+    /// * `pos` - The position of the bottom center of the entity
+    pub fn new(type_id: &str, uuid: EntityUuid, pos: Vector3<f32>) -> Self {
+        Self {
+            uuid,
+            type_id: type_id.to_string(),
+            pos: pos.into(),
+            components: Table::new(),
+        }
+    }
+
+    /// Adds a component to the entity
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the component
+    /// * `value` - The value of the component
+    pub fn with<V: Into<Value>>(mut self, key: &str, value: V) -> Self {
+        self.components.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Returns a text component of the entity
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the component
+    pub fn text(&self, key: &str) -> Result<&str, String> {
+        self.components.get(key).and_then(Value::as_str)
+            .ok_or_else(|| format!("the {} entity {} has no {}", self.type_id, self.uuid, key))
+    }
+
+    /// Returns a number component of the entity, if it has one
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the component
+    pub fn number(&self, key: &str) -> Option<f64> {
+        match self.components.get(key)? {
+            Value::Integer(value) => Some(*value as f64),
+            Value::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns whether a boolean component of the entity
+    /// is set, which it isn't if it's missing
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the component
+    pub fn flag(&self, key: &str) -> bool {
+        self.components.get(key).and_then(Value::as_bool).unwrap_or(false)
+    }
+}
+
+/// The declaration of a saved entity, which also accepts
+/// entities saved before they had types and ids. These
+/// declared their kind and name instead.
+#[derive(Deserialize)]
+struct EntityDef {
+    /// The id of the entity
+    uuid: Option<EntityUuid>,
+    /// The type of the entity
+    #[serde(rename = "type")]
+    type_id: Option<String>,
+    /// The kind of an old entity, `item` or `mob`
+    kind: Option<String>,
+    /// The name of the item or the kind of the mob of an old entity
+    name: Option<String>,
+    /// The amount of items of an old entity
+    count: Option<u64>,
+    /// The time since an old entity has been spawned in seconds
+    age: Option<f32>,
+    /// The position of the bottom center of the entity
+    pos: [f32; 3],
+    /// The components of the entity
+    #[serde(default)]
+    components: Table,
+}
+
+impl From<EntityDef> for SavedEntity {
+    fn from(def: EntityDef) -> Self {
+        let legacy_mob = def.kind.as_deref() == Some(MOB_TYPE);
+        let mut entity = Self {
+            uuid: def.uuid.unwrap_or_else(EntityUuid::generate),
+            type_id: def.type_id.or(def.kind).unwrap_or_default(),
+            pos: def.pos,
+            components: def.components,
+        };
+        if let Some(name) = def.name {
+            let key = if legacy_mob { "kind" } else { "item" };
+            entity = entity.with(key, name);
+        }
+        if let Some(count) = def.count {
+            entity = entity.with("count", count as i64);
+        }
+        if let Some(age) = def.age {
+            entity = entity.with("age", age as f64);
+        }
+        entity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Dummy(Vector3<f32>);
+
+    impl ChunkEntity for Dummy {
+        fn uuid(&self) -> EntityUuid {
+            EntityUuid(0)
+        }
+
+        fn pos(&self) -> Vector3<f32> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn entities_are_listed_by_chunk() {
+        let mut entities = ChunkEntities::default();
+        entities.insert(Dummy(Vector3::new(1.0, 0.0, 1.0)));
+        entities.insert(Dummy(Vector3::new(-1.0, 0.0, 1.0)));
+        entities.insert(Dummy(Vector3::new(40.0, 0.0, 1.0)));
+        assert_eq!(entities.len(), 3);
+        assert_eq!(entities.in_chunk(Vector2::new(0, 0)).len(), 1);
+        assert_eq!(entities.in_chunk(Vector2::new(-1, 0)).len(), 1);
+        assert_eq!(entities.active(Vector2::new(0, 0), 1).len(), 2);
+        assert_eq!(entities.active(Vector2::new(0, 0), 2).len(), 3);
+
+        // Entities which leave their chunk enter the next one
+        let key = entities.active(Vector2::new(0, 0), 0)[0];
+        entities.get_mut(key).0.x = 20.0;
+        assert!(entities.remove_and_relocate(Vec::new()).is_empty());
+        assert!(entities.in_chunk(Vector2::new(0, 0)).is_empty());
+        assert_eq!(entities.in_chunk(Vector2::new(1, 0)).len(), 1);
+        assert_eq!(entities.chunks(), vec![Vector2::new(-1, 0), Vector2::new(1, 0), Vector2::new(2, 0)]);
+
+        let key = entities.active(Vector2::new(2, 0), 0)[0];
+        assert_eq!(entities.remove_and_relocate(vec![key]).len(), 1);
+        assert_eq!(entities.take_chunk(Vector2::new(-1, 0)).len(), 1);
+        assert_eq!(entities.take_all().len(), 1);
+        assert!(entities.is_empty());
+    }
+
+    #[test]
+    fn saved_entities_are_serialized() {
+        let uuid = EntityUuid::generate();
+        let entity = SavedEntity::new("mymod:balloon", uuid, Vector3::new(0.5, 3.0, -2.5))
+            .with("color", "red")
+            .with("lift", 2);
+        let toml = toml::to_string(&entity).unwrap();
+        assert!(toml.contains(&format!("uuid = \"{}\"", uuid)));
+        assert!(toml.contains("type = \"mymod:balloon\""));
+        let loaded = toml::from_str::<SavedEntity>(&toml).unwrap();
+        assert_eq!(loaded, entity);
+        assert_eq!(loaded.text("color"), Ok("red"));
+        assert_eq!(loaded.number("lift"), Some(2.0));
+        assert!(loaded.text("lift").is_err());
+    }
+
+    #[test]
+    fn old_entities_are_converted() {
+        let entity: SavedEntity = toml::from_str("kind = \"mob\"\nname = \"zombie\"\npos = [1.0, 2.0, 3.0]").unwrap();
+        assert_eq!(entity.type_id, MOB_TYPE);
+        assert_eq!(entity.text("kind"), Ok("zombie"));
+
+        let entity: SavedEntity = toml::from_str("kind = \"item\"\nname = \"dirt\"\ncount = 3\npos = [1.0, 2.0, 3.0]").unwrap();
+        assert_eq!(entity.type_id, ITEM_TYPE);
+        assert_eq!(entity.text("item"), Ok("dirt"));
+        assert_eq!(entity.number("count"), Some(3.0));
+        assert_eq!(entity.number("age"), None);
+    }
+
+    #[test]
+    fn uuids_are_random_and_parsed() {
+        let uuid = EntityUuid::generate();
+        assert_ne!(uuid, EntityUuid::generate());
+        let text = uuid.to_string();
+        assert_eq!(text.len(), 36);
+        assert_eq!(&text[14..15], "4");
+        assert_eq!(text.parse(), Ok(uuid));
+        assert!("not-a-uuid".parse::<EntityUuid>().is_err());
+        assert!("0000000g-0000-4000-8000-000000000000".parse::<EntityUuid>().is_err());
+    }
+}
@@ -0,0 +1,195 @@
+//! Types to select the tiles of connected textures,
+//! e.g. to draw adjacent glass blocks as one pane
+
+use crate::resources::Resources;
+use crate::world::block::Material;
+use cgmath::{Vector2, Vector3};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// The resource declaring the connected texture tile sets
+pub(crate) const CONNECTED_TEXTURES_FILE: &str = "textures/connected.toml";
+
+/// The upper edge of a face connects
+pub const CONNECT_UP: u8 = 1;
+/// The right edge of a face connects
+pub const CONNECT_RIGHT: u8 = 2;
+/// The lower edge of a face connects
+pub const CONNECT_DOWN: u8 = 4;
+/// The left edge of a face connects
+pub const CONNECT_LEFT: u8 = 8;
+
+/// The declaration of a single tile set
+#[derive(Deserialize)]
+struct TileSetDef {
+    /// The name of the material of the block
+    block: String,
+    /// The row of the tiles in the texture atlas
+    row: u32,
+}
+
+/// The declaration of all tile sets of a resource pack
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ConnectedTexturesDef {
+    /// The declared tile sets
+    tile_sets: Vec<TileSetDef>,
+}
+
+/// ConnectedTextures
+///
+/// The tile sets of all blocks with connected textures.
+/// Each tile set is a row of 16 tiles in the texture atlas,
+/// one for each combination of connected edges.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConnectedTextures {
+    /// The row of the tile set by the material of the block
+    rows: HashMap<Material, u32>,
+}
+
+impl ConnectedTextures {
+    /// Loads the tile sets declared by the resources. If they
+    /// can't be loaded, the error message is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - A resource instance
+    pub fn load(resources: &Resources) -> Result<Self, String> {
+        let source = resources.load_string(CONNECTED_TEXTURES_FILE)
+            .map_err(|error| format!("Failed to load {}: {:?}", CONNECTED_TEXTURES_FILE, error))?;
+        Self::from_toml(&source).map_err(|error| format!("Failed to parse {}: {}", CONNECTED_TEXTURES_FILE, error))
+    }
+
+    /// Parses the tile sets from their `TOML` declaration.
+    /// Tile sets of unknown blocks are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The declaration of the tile sets
+    pub fn from_toml(source: &str) -> Result<Self, toml::de::Error> {
+        let def: ConnectedTexturesDef = toml::from_str(source)?;
+
+        let mut rows = HashMap::new();
+        for tile_set in def.tile_sets {
+            match Material::from_name(&tile_set.block) {
+                Some(material) => { rows.insert(material, tile_set.row); }
+                None => log::warn!("Skipping tile set of unknown block {}", tile_set.block),
+            }
+        }
+
+        Ok(Self {
+            rows,
+        })
+    }
+
+    /// Returns the tile a face is textured with, or `None` if the
+    /// material doesn't connect or has no tile set
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    /// * `connections` - The connected edges of the face
+    pub fn tile(&self, material: Material, connections: u8) -> Option<Vector2<f32>> {
+        if !material.data().connects() {
+            return None;
+        }
+        let row = self.rows.get(&material)?;
+        Some(Vector2::new((connections & 0xF) as f32, *row as f32))
+    }
+}
+
+/// Returns the edges of a face which connect with the faces of
+/// adjacent blocks of the same material. The edges are relative
+/// to the texture of the face, as it's mapped by the chunk shader.
+///
+/// # Arguments
+///
+/// * `block_at` - Returns the material of the block at a position
+/// * `pos` - The position of the block
+/// * `normal` - The normal of the face
+pub fn connections<F: Fn(Vector3<i16>) -> Option<Material>>(block_at: F, pos: Vector3<i16>, normal: [f32; 3]) -> u8 {
+    let material = match block_at(pos) {
+        Some(material) if material.data().connects() => material,
+        _ => return 0,
+    };
+
+    let (u, v) = tile_axes(normal);
+    let connects = |offset: Vector3<i16>| block_at(pos + offset) == Some(material);
+
+    let mut connections = 0;
+    if connects(v) { connections |= CONNECT_UP; }
+    if connects(u) { connections |= CONNECT_RIGHT; }
+    if connects(-v) { connections |= CONNECT_DOWN; }
+    if connects(-u) { connections |= CONNECT_LEFT; }
+    connections
+}
+
+/// Returns the directions the texture of a face runs along in
+/// world space, to the right and upwards. They match the tile
+/// coordinates the chunk shader derives from the normal.
+///
+/// # Arguments
+///
+/// * `normal` - The normal of the face
+fn tile_axes(normal: [f32; 3]) -> (Vector3<i16>, Vector3<i16>) {
+    let [x, y, z] = [normal[0] as i16, normal[1] as i16, normal[2] as i16];
+    if x != 0 {
+        (Vector3::new(0, 0, x), Vector3::new(0, 1, 0))
+    } else if y != 0 {
+        (Vector3::new(y, 0, 0), Vector3::new(0, 0, y))
+    } else {
+        (Vector3::new(-z, 0, 0), Vector3::new(0, 1, 0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE_SETS: &str = r#"
+        [[tile_sets]]
+        block = "glass"
+        row = 13
+
+        [[tile_sets]]
+        block = "obsidian"
+        row = 12
+    "#;
+
+    /// A wall of glass in the xy plane spanning from
+    /// `(0, 0, 0)` to `(2, 2, 0)`
+    fn wall(pos: Vector3<i16>) -> Option<Material> {
+        if (0..3).contains(&pos.x) && (0..3).contains(&pos.y) && pos.z == 0 {
+            Some(Material::Glass)
+        } else {
+            Some(Material::Air)
+        }
+    }
+
+    #[test]
+    fn tiles_are_selected_for_connecting_blocks() {
+        let textures = ConnectedTextures::from_toml(TILE_SETS).unwrap();
+        assert_eq!(textures.tile(Material::Glass, 0), Some(Vector2::new(0.0, 13.0)));
+        assert_eq!(textures.tile(Material::Glass, CONNECT_UP | CONNECT_LEFT), Some(Vector2::new(9.0, 13.0)));
+        assert_eq!(textures.tile(Material::Dirt, 0), None);
+
+        assert_eq!(ConnectedTextures::default().tile(Material::Glass, 0), None);
+        assert!(ConnectedTextures::from_toml("tile_sets = 1").is_err());
+    }
+
+    #[test]
+    fn faces_connect_within_their_plane() {
+        let front = [0.0, 0.0, 1.0];
+        assert_eq!(connections(wall, Vector3::new(1, 1, 0), front), CONNECT_UP | CONNECT_RIGHT | CONNECT_DOWN | CONNECT_LEFT);
+
+        // The texture of the front face runs towards negative x
+        assert_eq!(connections(wall, Vector3::new(0, 0, 0), front), CONNECT_UP | CONNECT_LEFT);
+        assert_eq!(connections(wall, Vector3::new(0, 0, 0), [0.0, 0.0, -1.0]), CONNECT_UP | CONNECT_RIGHT);
+
+        // The top of the wall only connects along the wall
+        assert_eq!(connections(wall, Vector3::new(1, 2, 0), [0.0, 1.0, 0.0]), CONNECT_RIGHT | CONNECT_LEFT);
+        assert_eq!(connections(wall, Vector3::new(1, 2, 0), [1.0, 0.0, 0.0]), CONNECT_DOWN);
+
+        assert_eq!(connections(wall, Vector3::new(5, 5, 5), front), 0);
+    }
+}
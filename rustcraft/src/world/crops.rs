@@ -0,0 +1,337 @@
+//! Types to grow crops on farmland, whose growth
+//! and harvest are defined by scripts
+
+use crate::scripting::{self, ScriptEngine};
+use crate::world::block::Material;
+use mlua::{Function, Lua, Table};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The largest amount of growth stages, as the sprites of
+/// the stages are laid out in a single row of the atlas
+const MAX_STAGES: u8 = 16;
+
+/// Harvest
+///
+/// The items a mature crop yields when it's broken
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Harvest {
+    /// The name of the item, e.g. `wheat`
+    pub item: String,
+    /// The amount of items
+    pub count: u64,
+}
+
+/// CropDef
+///
+/// How a crop grows. The state of a crop block is its growth
+/// stage, starting at `0`. Each random tick, a crop which
+/// stands on its soil and gets enough light advances a stage
+/// with the growth chance, which is lower while the soil isn't
+/// hydrated. The soil counts as hydrated if its state isn't `0`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CropDef {
+    /// The material of the crop block
+    block: Material,
+    /// The material of the block the crop grows on
+    soil: Material,
+    /// The amount of growth stages, the last one is mature
+    stages: u8,
+    /// The chance to grow on hydrated soil per random tick
+    growth_chance: f64,
+    /// The chance to grow on dry soil per random tick
+    dry_growth_chance: f64,
+    /// The light level of the sky the crop needs to grow
+    min_light: u8,
+    /// The items the mature crop yields
+    harvest: Option<Harvest>,
+}
+
+impl CropDef {
+    /// Returns the stage a crop reaches within a random tick,
+    /// or `None` if it doesn't grow
+    ///
+    /// # Arguments
+    ///
+    /// * `stage` - The current stage of the crop
+    /// * `soil` - The material and state of the block below the crop
+    /// * `light` - The light level of the sky at the crop
+    /// * `roll` - A random number in the range `[0, 1)`
+    pub fn grow(&self, stage: u8, soil: Option<(Material, u8)>, light: u8, roll: f64) -> Option<u8> {
+        let hydrated = match soil {
+            Some((material, state)) if material == self.soil => state > 0,
+            _ => return None,
+        };
+        if stage >= self.stages - 1 || light < self.min_light {
+            return None;
+        }
+
+        let chance = if hydrated { self.growth_chance } else { self.dry_growth_chance };
+        if roll < chance {
+            Some(stage + 1)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the items a crop yields at the given stage.
+    /// Crops only yield items once they are mature.
+    ///
+    /// # Arguments
+    ///
+    /// * `stage` - The stage of the crop
+    pub fn harvest(&self, stage: u8) -> Option<&Harvest> {
+        if stage >= self.stages - 1 {
+            self.harvest.as_ref()
+        } else {
+            None
+        }
+    }
+}
+
+/// Crops
+///
+/// The crops defined by scripts through the `crops` API.
+/// The growth is driven by random ticks, so the callback
+/// is registered through `blocks.onRandomTick`, e.g.
+///
+/// ```lua
+/// crops.define {
+///     block = "wheat",
+///     soil = "farmland",
+///     stages = 8,
+///     growth_chance = 0.3,
+///     dry_growth_chance = 0.1,
+///     min_light = 9,
+///     harvest = { item = "wheat", count = 1 },
+/// }
+/// ```
+///
+/// A crop defined again replaces its previous definition.
+pub struct Crops {
+    /// The defined crops by the material of their block,
+    /// shared with the Lua API
+    defined: Arc<Mutex<HashMap<Material, CropDef>>>,
+}
+
+impl Crops {
+    /// Creates a new crop registry and registers its API in
+    /// the given script engine. The random tick API needs to
+    /// be registered before.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let defined = Arc::new(Mutex::new(HashMap::new()));
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let crops = defined.clone();
+        api.set("define", lua.create_function(move |lua, def: Table| {
+            let def = crop_from_table(def)?;
+            let on_random_tick: Function = scripting::api_table(lua, "blocks")?.get("onRandomTick")?;
+            let grow_def = def.clone();
+            let grow = lua.create_function(move |lua, (pos, world): (Table, Table)| {
+                grow_crop(lua, &grow_def, pos, world)
+            })?;
+            on_random_tick.call::<_, ()>((def.block.name(), grow))?;
+            crops.lock().unwrap().insert(def.block, def);
+            Ok(())
+        })?)?;
+        lua.globals().set("crops", api)?;
+
+        Ok(Self {
+            defined,
+        })
+    }
+
+    /// Returns the items a crop block yields when it's broken,
+    /// if it's a mature crop
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    /// * `state` - The state of the block
+    pub fn harvest(&self, material: Material, state: u8) -> Option<Harvest> {
+        self.defined.lock().unwrap().get(&material)?.harvest(state).cloned()
+    }
+}
+
+/// Grows a crop within a random tick
+///
+/// # Arguments
+///
+/// * `lua` - The Lua state
+/// * `def` - The definition of the crop
+/// * `pos` - The position of the crop as Lua table
+/// * `world` - The world API of the random tick
+fn grow_crop(lua: &Lua, def: &CropDef, pos: Table, world: Table) -> mlua::Result<()> {
+    let block: Function = world.get("block")?;
+    let state: Function = world.get("state")?;
+    let below = lua.create_sequence_from(vec![pos.get::<_, i32>(1)?, pos.get::<_, i32>(2)? - 1, pos.get::<_, i32>(3)?])?;
+
+    let soil = match block.call::<_, Option<String>>(below.clone())?.and_then(|name| Material::from_name(&name)) {
+        Some(material) => Some((material, state.call::<_, Option<u8>>(below)?.unwrap_or(0))),
+        None => None,
+    };
+    let stage = state.call::<_, Option<u8>>(pos.clone())?.unwrap_or(0);
+    let light = world.get::<_, Function>("light")?.call::<_, Option<u8>>(pos.clone())?.unwrap_or(0);
+    let random: Function = lua.globals().get::<_, Table>("math")?.get("random")?;
+
+    if let Some(stage) = def.grow(stage, soil, light, random.call(())?) {
+        world.get::<_, Function>("set_state")?.call::<_, bool>((pos, stage))?;
+    }
+    Ok(())
+}
+
+/// Reads the definition of a crop from a Lua table
+///
+/// # Arguments
+///
+/// * `def` - The Lua table
+fn crop_from_table(def: Table) -> mlua::Result<CropDef> {
+    let material = |key: &str| -> mlua::Result<Material> {
+        let name: String = def.get(key)?;
+        Material::from_name(&name).ok_or_else(|| {
+            mlua::Error::RuntimeError(format!("unknown block {}", name))
+        })
+    };
+
+    let stages: u8 = def.get("stages")?;
+    if stages == 0 || stages > MAX_STAGES {
+        return Err(mlua::Error::RuntimeError(format!("a crop needs 1 to {} stages", MAX_STAGES)));
+    }
+    let growth_chance = def.get::<_, f64>("growth_chance")?.clamp(0.0, 1.0);
+    let dry_growth_chance = def.get::<_, Option<f64>>("dry_growth_chance")?.unwrap_or(growth_chance).clamp(0.0, 1.0);
+
+    let harvest = match def.get::<_, Option<Table>>("harvest")? {
+        Some(harvest) => Some(Harvest {
+            item: harvest.get("item")?,
+            count: harvest.get::<_, Option<u64>>("count")?.unwrap_or(1),
+        }),
+        None => None,
+    };
+
+    Ok(CropDef {
+        block: material("block")?,
+        soil: material("soil")?,
+        stages,
+        growth_chance,
+        dry_growth_chance,
+        min_light: def.get::<_, Option<u8>>("min_light")?.unwrap_or(0),
+        harvest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::World;
+    use crate::world::chunk::CHUNK_SIZE;
+    use crate::world::rules::GameRules;
+    use crate::world::terrain_generator::FlatTerrainGen;
+    use crate::world::ticks::RandomTicks;
+    use crate::world::time::SkySettings;
+    use cgmath::{Vector2, Vector3};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    const WHEAT: &str = r#"
+        crops.define {
+            block = "wheat",
+            soil = "farmland",
+            stages = 4,
+            growth_chance = 1.0,
+            dry_growth_chance = 0.0,
+            min_light = 5,
+            harvest = { item = "wheat", count = 2 },
+        }
+    "#;
+
+    fn wheat() -> CropDef {
+        CropDef {
+            block: Material::Wheat,
+            soil: Material::Farmland,
+            stages: 4,
+            growth_chance: 0.5,
+            dry_growth_chance: 0.1,
+            min_light: 9,
+            harvest: Some(Harvest { item: "wheat".to_string(), count: 1 }),
+        }
+    }
+
+    #[test]
+    fn crops_grow_on_lit_soil() {
+        let wheat = wheat();
+        let wet = Some((Material::Farmland, 1));
+        let dry = Some((Material::Farmland, 0));
+        assert_eq!(wheat.grow(0, wet, 15, 0.3), Some(1));
+        assert_eq!(wheat.grow(0, dry, 15, 0.3), None);
+        assert_eq!(wheat.grow(0, dry, 15, 0.05), Some(1));
+        assert_eq!(wheat.grow(0, wet, 8, 0.0), None);
+        assert_eq!(wheat.grow(0, Some((Material::Dirt, 1)), 15, 0.0), None);
+        assert_eq!(wheat.grow(0, None, 15, 0.0), None);
+        assert_eq!(wheat.grow(3, wet, 15, 0.0), None);
+    }
+
+    #[test]
+    fn only_mature_crops_are_harvested() {
+        let wheat = wheat();
+        assert_eq!(wheat.harvest(2), None);
+        assert_eq!(wheat.harvest(3).unwrap().item, "wheat");
+    }
+
+    #[test]
+    fn scripts_define_crops() {
+        let engine = ScriptEngine::new();
+        let mut ticks = RandomTicks::new(&engine).unwrap();
+        let crops = Crops::new(&engine).unwrap();
+        engine.exec("test", WHEAT).unwrap();
+        assert_eq!(crops.harvest(Material::Wheat, 3), Some(Harvest { item: "wheat".to_string(), count: 2 }));
+        assert_eq!(crops.harvest(Material::Wheat, 2), None);
+        assert_eq!(crops.harvest(Material::Dirt, 3), None);
+
+        let mut world = World::with_generator(FlatTerrainGen {}, SkySettings::default());
+        world.load_chunk(&Vector2::new(0, 0));
+        let start = Instant::now();
+        while !world.chunks()[0].is_generated() {
+            assert!(start.elapsed() < Duration::from_secs(10), "the chunk hasn't been generated");
+            thread::yield_now();
+        }
+
+        // The grass of the flat terrain is turned into farmland,
+        // of which only the western half is hydrated
+        world.time_mut().set_time_of_day(0.25);
+        let y = 5;
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                world.set_block(Vector3::new(x, y - 1, z), Material::Farmland);
+                world.set_block_state(Vector3::new(x, y - 1, z), if x < 8 { 1 } else { 0 });
+                world.set_block(Vector3::new(x, y, z), Material::Wheat);
+            }
+        }
+        let rules = GameRules { random_tick_speed: 4096, ..GameRules::default() };
+        for _ in 0..8 {
+            assert!(ticks.tick(&engine, &mut world, &rules).is_empty());
+        }
+
+        let stages: Vec<(i32, u8)> = (0..CHUNK_SIZE as i32)
+            .flat_map(|x| (0..CHUNK_SIZE as i32).map(move |z| (x, z)))
+            .map(|(x, z)| (x, world.block_state(Vector3::new(x, y, z)).unwrap()))
+            .collect();
+        assert!(stages.iter().any(|&(x, stage)| x < 8 && stage > 0));
+        assert!(stages.iter().all(|&(x, stage)| stage < 4 && (x < 8 || stage == 0)));
+    }
+
+    #[test]
+    fn invalid_crops_are_rejected() {
+        let engine = ScriptEngine::new();
+        let _ticks = RandomTicks::new(&engine).unwrap();
+        let crops = Crops::new(&engine).unwrap();
+        assert!(engine.exec("test", r#"crops.define { block = "cheese", soil = "farmland", stages = 4, growth_chance = 1 }"#).is_err());
+        assert!(engine.exec("test", r#"crops.define { block = "wheat", soil = "farmland", stages = 0, growth_chance = 1 }"#).is_err());
+        assert!(engine.exec("test", r#"crops.define { block = "wheat", soil = "farmland", stages = 4 }"#).is_err());
+        assert_eq!(crops.harvest(Material::Wheat, 3), None);
+    }
+}
@@ -0,0 +1,301 @@
+//! Types to define dimensions, e.g. from scripts
+
+use crate::scripting::{self, ScriptEngine};
+use crate::world::World;
+use crate::world::biome::{BiomeRegistry, BiomeTerrainGen};
+use crate::world::noise_source::{LayeredNoise, NoiseSettings};
+use crate::world::terrain_generator::{CaveTerrainGen, FlatTerrainGen, SimpleTerrainGen, TerrainGen, VoidTerrainGen};
+use crate::world::time::SkySettings;
+use crate::world::universe::Universe;
+use mlua::Table;
+use std::sync::{Arc, Mutex};
+
+/// GeneratorPreset
+///
+/// The terrain generators a dimension could be created with
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GeneratorPreset {
+    /// Oceans, plains, hills and mountains
+    Overworld,
+    /// A stone floor and ceiling with a cave in between
+    Caves,
+    /// A flat layer of dirt covered with grass
+    Flat,
+    /// No terrain at all
+    Void,
+}
+
+impl GeneratorPreset {
+    /// Returns the preset with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the preset, e.g. `"caves"`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "overworld" => Some(GeneratorPreset::Overworld),
+            "caves" => Some(GeneratorPreset::Caves),
+            "flat" => Some(GeneratorPreset::Flat),
+            "void" => Some(GeneratorPreset::Void),
+            _ => None,
+        }
+    }
+
+    /// Returns the sky a dimension of this preset has by default
+    pub fn default_sky(&self) -> SkySettings {
+        match self {
+            GeneratorPreset::Caves => SkySettings::underground(),
+            _ => SkySettings::default(),
+        }
+    }
+
+    /// Creates the terrain generator of this preset
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed of the noise, `0` for the default terrain
+    /// * `biomes` - The definitions of the biomes
+    pub fn generator(&self, seed: u32, biomes: &BiomeRegistry) -> Box<dyn TerrainGen + Send + Sync> {
+        match self {
+            GeneratorPreset::Overworld => Box::new(BiomeTerrainGen::new(seed).with_biomes(biomes.clone())),
+            GeneratorPreset::Caves => Box::new(CaveTerrainGen::new(seed)),
+            GeneratorPreset::Flat => Box::new(FlatTerrainGen::default()),
+            GeneratorPreset::Void => Box::new(VoidTerrainGen::default()),
+        }
+    }
+
+    /// Creates a new world generated by this preset
+    ///
+    /// # Arguments
+    ///
+    /// * `sky` - The look of the sky of the world
+    /// * `biomes` - The definitions of the biomes
+    pub fn create_world(&self, sky: SkySettings, biomes: &BiomeRegistry) -> World {
+        World::with_generator(self.generator(0, biomes), sky)
+    }
+}
+
+impl DimensionDef {
+    /// Creates the world of this dimension
+    ///
+    /// # Arguments
+    ///
+    /// * `biomes` - The definitions of the biomes
+    pub fn create_world(&self, biomes: &BiomeRegistry) -> World {
+        if self.noise.is_empty() {
+            return self.generator.create_world(self.sky, biomes);
+        }
+        let noise = LayeredNoise::from_settings(&self.noise);
+        World::with_generator(SimpleTerrainGen::with_noise(Box::new(noise)), self.sky)
+    }
+}
+
+/// DimensionDef
+///
+/// The definition of a dimension which has been
+/// registered, but not added to the universe yet
+#[derive(Clone, Debug)]
+pub struct DimensionDef {
+    /// The name of the dimension
+    pub name: String,
+    /// The terrain generator of the dimension
+    pub generator: GeneratorPreset,
+    /// The noise layers the hills of an overworld follow,
+    /// or none for the default biomes
+    pub noise: Vec<NoiseSettings>,
+    /// The look of the sky of the dimension
+    pub sky: SkySettings,
+}
+
+/// Dimensions
+///
+/// The dimensions registered by scripts through the
+/// `dimensions` API. All fields but the name are
+/// optional, e.g.
+///
+/// ```lua
+/// dimensions.register {
+///     name = "nether",
+///     -- "overworld", "caves", "flat" or "void"
+///     generator = "caves",
+///     -- The noise layers the hills of an "overworld" follow instead
+///     -- of its biomes, see `NoiseSettings` for their fields
+///     noise = { { algorithm = "super_simplex", frequency = 1 / 32 }, { amplitude = 0.25 } },
+///     -- The colors of the sky at day, at night and at twilight
+///     sky = { day = { 0.3, 0.05, 0.02 } },
+///     celestial_bodies = false,
+///     ambient_light = 0.8,
+///     daylight = false,
+///     weather = false,
+/// }
+/// ```
+///
+/// The registered dimensions are added to the universe
+/// with `register_all`.
+pub struct Dimensions {
+    /// The registered dimensions, shared with the Lua API
+    registered: Arc<Mutex<Vec<DimensionDef>>>,
+}
+
+impl Dimensions {
+    /// Creates a new dimension registry and registers its
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let registered = Arc::new(Mutex::new(Vec::new()));
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let dimensions = registered.clone();
+        api.set("register", lua.create_function(move |_, def: Table| {
+            let def = dimension_from_table(def)?;
+            dimensions.lock().unwrap().push(def);
+            Ok(())
+        })?)?;
+        lua.globals().set("dimensions", api)?;
+
+        Ok(Self {
+            registered,
+        })
+    }
+
+    /// Adds all dimensions registered since the last call
+    /// to the universe. Dimensions whose names are taken
+    /// already are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `universe` - The universe the dimensions are added to
+    pub fn register_all(&self, universe: &mut Universe) {
+        let registered: Vec<DimensionDef> = self.registered.lock().unwrap().drain(..).collect();
+        let biomes = universe.biomes().clone();
+        for def in registered {
+            if !universe.add(&def.name, def.create_world(&biomes)) {
+                log::warn!("Skipping dimension {}, the name is taken already", def.name);
+            }
+        }
+    }
+}
+
+/// Reads the definition of a dimension from a Lua table
+///
+/// # Arguments
+///
+/// * `def` - The Lua table
+fn dimension_from_table(def: Table) -> mlua::Result<DimensionDef> {
+    let name: String = def.get("name")?;
+    if name.is_empty() {
+        return Err(mlua::Error::RuntimeError("the name of a dimension must not be empty".into()));
+    }
+
+    let generator = match def.get::<_, Option<String>>("generator")? {
+        Some(generator) => GeneratorPreset::from_name(&generator).ok_or_else(|| {
+            mlua::Error::RuntimeError(format!("unknown generator {}", generator))
+        })?,
+        None => GeneratorPreset::Overworld,
+    };
+
+    let noise = match def.get::<_, Option<Table>>("noise")? {
+        Some(layers) => NoiseSettings::layers_from_table(&layers)?,
+        None => Vec::new(),
+    };
+    if !noise.is_empty() && generator != GeneratorPreset::Overworld {
+        return Err(mlua::Error::RuntimeError("only the overworld generator follows noise".into()));
+    }
+
+    let mut sky = generator.default_sky();
+    if let Some(colors) = def.get::<_, Option<Table>>("sky")? {
+        if let Some(color) = colors.get::<_, Option<Table>>("day")? {
+            sky.day_color = scripting::vector_from_table(color)?;
+        }
+        if let Some(color) = colors.get::<_, Option<Table>>("night")? {
+            sky.night_color = scripting::vector_from_table(color)?;
+        }
+        if let Some(color) = colors.get::<_, Option<Table>>("twilight")? {
+            sky.twilight_color = scripting::vector_from_table(color)?;
+        }
+    }
+    if let Some(celestial_bodies) = def.get("celestial_bodies")? {
+        sky.celestial_bodies = celestial_bodies;
+    }
+    if let Some(ambient_light) = def.get::<_, Option<f32>>("ambient_light")? {
+        sky.ambient_light = ambient_light.clamp(0.0, 1.0);
+    }
+    if let Some(daylight) = def.get("daylight")? {
+        sky.daylight = daylight;
+    }
+    if let Some(weather) = def.get("weather")? {
+        sky.weather = weather;
+    }
+
+    Ok(DimensionDef {
+        name,
+        generator,
+        noise,
+        sky,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::universe::{CAVES, OVERWORLD};
+    use cgmath::Vector3;
+
+    fn dimensions() -> (ScriptEngine, Dimensions, Universe) {
+        let engine = ScriptEngine::new();
+        let dimensions = Dimensions::new(&engine).unwrap();
+        let mut universe = Universe::new(OVERWORLD, World::new());
+        universe.add(CAVES, World::new());
+        (engine, dimensions, universe)
+    }
+
+    #[test]
+    fn scripts_register_dimensions() {
+        let (engine, dimensions, mut universe) = dimensions();
+        engine.exec("test", r#"
+            dimensions.register {
+                name = "nether",
+                generator = "caves",
+                sky = { day = { 0.3, 0.05, 0.02 } },
+                ambient_light = 2.0,
+                daylight = false,
+            }
+            dimensions.register { name = "creative", generator = "void", weather = false }
+            dimensions.register { name = "highlands", noise = { { algorithm = "value", seed = 3 }, { amplitude = 0.5 } } }
+        "#).unwrap();
+        dimensions.register_all(&mut universe);
+
+        let nether = universe.dimension("nether").unwrap().sky();
+        assert_eq!(nether.day_color, Vector3::new(0.3, 0.05, 0.02));
+        assert_eq!(nether.night_color, SkySettings::underground().night_color);
+        assert!(!nether.celestial_bodies);
+        assert_eq!(nether.ambient_light, 1.0);
+        assert!(!nether.daylight);
+
+        let creative = universe.dimension("creative").unwrap().sky();
+        assert!(creative.celestial_bodies);
+        assert!(creative.daylight);
+        assert!(!creative.weather);
+        assert!(universe.dimension("highlands").is_some());
+    }
+
+    #[test]
+    fn invalid_dimensions_are_rejected() {
+        let (engine, dimensions, mut universe) = dimensions();
+        assert!(engine.exec("test", r#"dimensions.register { name = "" }"#).is_err());
+        assert!(engine.exec("test", r#"dimensions.register { name = "moon", generator = "cheese" }"#).is_err());
+        assert!(engine.exec("test", r#"dimensions.register { generator = "flat" }"#).is_err());
+        assert!(engine.exec("test", r#"dimensions.register { name = "moon", generator = "flat", noise = { {} } }"#).is_err());
+        assert!(engine.exec("test", r#"dimensions.register { name = "moon", noise = { { algorithm = "simplex" } } }"#).is_err());
+
+        // Existing dimensions are kept
+        engine.exec("test", r#"dimensions.register { name = "caves", generator = "void" }"#).unwrap();
+        dimensions.register_all(&mut universe);
+        assert!(universe.dimension("moon").is_none());
+        assert!(universe.dimension(CAVES).unwrap().sky().daylight);
+    }
+}
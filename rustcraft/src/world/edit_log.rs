@@ -0,0 +1,359 @@
+//! Types to log the blocks players changed to an append-only
+//! log per region, to look up who changed a block and to roll
+//! back the edits of a player, e.g. on a multiplayer server
+
+use crate::scripting::ScriptEngine;
+use crate::world::block::Material;
+use crate::world::chunk::CHUNK_SIZE;
+use crate::world::history::BlockEdit;
+use crate::world::save::{SaveDir, SaveError, REGION_SIZE};
+use crate::world::World;
+use cgmath::Vector3;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The name the edits of rollbacks are logged with
+pub const ROLLBACK_PLAYER: &str = "#rollback";
+
+/// LoggedEdit
+///
+/// A block a player changed, as it's logged
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LoggedEdit {
+    /// The time of the edit in seconds since the Unix epoch
+    pub time: u64,
+    /// The name of the player who changed the block
+    pub player: String,
+    /// The position of the block in world coordinates
+    pub pos: [i32; 3],
+    /// The name of the material before the edit
+    pub old: String,
+    /// The name of the material after the edit
+    pub new: String,
+}
+
+/// Rollback
+///
+/// A rollback of the edits of a player requested by a script,
+/// which is applied to the world with the next update
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rollback {
+    /// The name of the dimension the edits are rolled back in
+    pub dimension: String,
+    /// The name of the player whose edits are rolled back
+    pub player: String,
+    /// The time in seconds since the Unix epoch from which
+    /// on the edits are rolled back
+    pub since: u64,
+}
+
+/// EditLog
+///
+/// The log of the blocks players changed, which is queried by
+/// scripts through the `editlog` API, e.g.
+///
+/// ```lua
+/// -- The edits of a block of a dimension, the oldest one first
+/// for _, edit in ipairs(editlog.inspect("overworld", 12, 20, -4)) do
+///     print(edit.time, edit.player, edit.old, edit.new)
+/// end
+///
+/// -- Reverts the edits a player made in the last ten minutes
+/// editlog.rollback("overworld", "Griefer", 600)
+/// ```
+///
+/// New edits are kept in memory and appended to the log of
+/// their region whenever the world is saved. The logs are
+/// never rewritten, so they could be kept for auditing.
+#[derive(Clone)]
+pub struct EditLog {
+    /// The directory the world is saved in
+    save_dir: SaveDir,
+    /// The edits which haven't been appended to the logs
+    /// yet with the name of their dimension
+    pending: Arc<Mutex<Vec<(String, LoggedEdit)>>>,
+    /// The rollbacks requested by scripts
+    rollbacks: Arc<Mutex<Vec<Rollback>>>,
+}
+
+impl EditLog {
+    /// Creates a new edit log and registers its API
+    /// in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `save_dir` - The directory the world is saved in
+    pub fn new(engine: &ScriptEngine, save_dir: SaveDir) -> mlua::Result<Self> {
+        let log = Self {
+            save_dir,
+            pending: Arc::new(Mutex::new(Vec::new())),
+            rollbacks: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let inspected = log.clone();
+        api.set("inspect", lua.create_function(move |lua, (dimension, x, y, z): (String, i32, i32, i32)| {
+            let edits = inspected.inspect(&dimension, Vector3::new(x, y, z))
+                .map_err(|error| mlua::Error::RuntimeError(format!("failed to read the edit log: {:?}", error)))?;
+            let result = lua.create_table()?;
+            for (i, edit) in edits.into_iter().enumerate() {
+                let entry = lua.create_table()?;
+                entry.set("time", edit.time)?;
+                entry.set("player", edit.player)?;
+                entry.set("old", edit.old)?;
+                entry.set("new", edit.new)?;
+                result.set(i + 1, entry)?;
+            }
+            Ok(result)
+        })?)?;
+        let rollbacks = log.rollbacks.clone();
+        api.set("rollback", lua.create_function(move |_, (dimension, player, seconds): (String, String, u64)| {
+            let since = unix_time().saturating_sub(seconds);
+            rollbacks.lock().unwrap().push(Rollback { dimension, player, since });
+            Ok(())
+        })?)?;
+        lua.globals().set("editlog", api)?;
+
+        Ok(log)
+    }
+
+    /// Logs the blocks a player changed
+    ///
+    /// # Arguments
+    ///
+    /// * `dimension` - The name of the dimension the blocks are in
+    /// * `player` - The name of the player
+    /// * `edits` - The changed blocks
+    /// * `time` - The time of the edits in seconds since the Unix epoch
+    pub fn record(&self, dimension: &str, player: &str, edits: &[BlockEdit], time: u64) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.extend(edits.iter().map(|edit| (dimension.to_string(), LoggedEdit {
+            time,
+            player: player.to_string(),
+            pos: edit.pos.into(),
+            old: edit.old.name().to_string(),
+            new: edit.new.name().to_string(),
+        })));
+    }
+
+    /// Appends the new edits to the logs of their regions
+    pub fn flush(&self) -> Result<(), SaveError> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        let mut regions: BTreeMap<(String, [i32; 2]), Vec<LoggedEdit>> = BTreeMap::new();
+        for (dimension, edit) in pending {
+            regions.entry((dimension, region_of(edit.pos))).or_default().push(edit);
+        }
+        for ((dimension, region), edits) in regions {
+            self.save_dir.dimension(&dimension)?.append_edit_log(region, &edits)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the logged edits of a block, the oldest one first
+    ///
+    /// # Arguments
+    ///
+    /// * `dimension` - The name of the dimension the block is in
+    /// * `pos` - The position of the block in world coordinates
+    pub fn inspect(&self, dimension: &str, pos: Vector3<i32>) -> Result<Vec<LoggedEdit>, SaveError> {
+        let pos: [i32; 3] = pos.into();
+        let logged = self.save_dir.dimension(dimension)?.load_edit_log(region_of(pos))?;
+        Ok(self.with_pending(dimension, logged, |edit| edit.pos == pos))
+    }
+
+    /// Returns the logged edits of a player since the given
+    /// time, the most recent one first
+    ///
+    /// # Arguments
+    ///
+    /// * `dimension` - The name of the dimension
+    /// * `player` - The name of the player
+    /// * `since` - The time in seconds since the Unix epoch
+    pub fn edits_of(&self, dimension: &str, player: &str, since: u64) -> Result<Vec<LoggedEdit>, SaveError> {
+        let logged = self.save_dir.dimension(dimension)?.load_edit_logs()?;
+        let mut edits = self.with_pending(dimension, logged, |edit| edit.player == player && edit.time >= since);
+        edits.reverse();
+        Ok(edits)
+    }
+
+    /// Returns the logged and the pending edits of a dimension
+    /// matching the filter, ordered by their time
+    ///
+    /// # Arguments
+    ///
+    /// * `dimension` - The name of the dimension
+    /// * `logged` - The edits read from the logs
+    /// * `filter` - Returns whether an edit is included
+    fn with_pending<F>(&self, dimension: &str, logged: Vec<LoggedEdit>, filter: F) -> Vec<LoggedEdit>
+        where F: Fn(&LoggedEdit) -> bool
+    {
+        let pending = self.pending.lock().unwrap();
+        let pending = pending.iter()
+            .filter(|(name, _)| name == dimension)
+            .map(|(_, edit)| edit.clone());
+        // The sort is stable, so edits of the same second
+        // keep the order they have been logged in
+        let mut edits: Vec<LoggedEdit> = logged.into_iter().chain(pending).filter(|edit| filter(edit)).collect();
+        edits.sort_by_key(|edit| edit.time);
+        edits
+    }
+
+    /// Takes the rollbacks scripts requested since the last call
+    pub fn take_rollbacks(&self) -> Vec<Rollback> {
+        std::mem::take(&mut *self.rollbacks.lock().unwrap())
+    }
+
+    /// Reverts the edits of a player in a world, the most recent
+    /// one first. Blocks which have been changed by someone else
+    /// in the meantime or which aren't loaded are skipped. The
+    /// reverted blocks are logged as edits of the rollback.
+    /// Returns the amount of reverted and skipped blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world of the dimension
+    /// * `rollback` - The rollback
+    /// * `time` - The time in seconds since the Unix epoch
+    pub fn roll_back(&self, world: &mut World, rollback: &Rollback, time: u64) -> Result<(usize, usize), SaveError> {
+        let edits = self.edits_of(&rollback.dimension, &rollback.player, rollback.since)?;
+        let mut reverted = Vec::new();
+        let mut skipped = 0;
+        for edit in edits {
+            let pos = Vector3::from(edit.pos);
+            match (Material::from_name(&edit.old), Material::from_name(&edit.new)) {
+                (Some(old), Some(new)) if world.block(pos) == Some(new) => {
+                    world.set_block(pos, old);
+                    reverted.push(BlockEdit { pos, old: new, new: old });
+                }
+                _ => skipped += 1,
+            }
+        }
+        self.record(&rollback.dimension, ROLLBACK_PLAYER, &reverted, time);
+        Ok((reverted.len(), skipped))
+    }
+}
+
+/// Returns the current time in seconds since the Unix epoch
+pub fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs())
+}
+
+/// Returns the location of the region containing a block
+///
+/// # Arguments
+///
+/// * `pos` - The position of the block in world coordinates
+fn region_of(pos: [i32; 3]) -> [i32; 2] {
+    let size = CHUNK_SIZE as i32 * REGION_SIZE;
+    [pos[0].div_euclid(size), pos[2].div_euclid(size)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::terrain_generator::FlatTerrainGen;
+    use crate::world::time::SkySettings;
+    use cgmath::Vector2;
+    use std::fs;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn edit_log(name: &str) -> (ScriptEngine, EditLog) {
+        let path = std::env::temp_dir().join(format!("rustcraft-edit-log-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        let engine = ScriptEngine::new();
+        let log = EditLog::new(&engine, SaveDir::new(path).unwrap()).unwrap();
+        (engine, log)
+    }
+
+    fn edit(pos: [i32; 3], old: Material, new: Material) -> BlockEdit {
+        BlockEdit { pos: Vector3::from(pos), old, new }
+    }
+
+    /// Creates a world whose chunk at the origin has been generated
+    fn generated_world() -> World {
+        let mut world = World::with_generator(FlatTerrainGen::default(), SkySettings::default());
+        world.load_chunk(&Vector2::new(0, 0));
+        let start = Instant::now();
+        while !world.chunk(&Vector2::new(0, 0)).unwrap().is_generated() {
+            assert!(start.elapsed() < Duration::from_secs(10), "the chunk hasn't been generated");
+            thread::yield_now();
+        }
+        world
+    }
+
+    #[test]
+    fn edits_are_appended_to_the_log_of_their_region() {
+        let (_engine, log) = edit_log("append");
+        log.record("overworld", "Alice", &[edit([1, 20, 1], Material::Air, Material::Dirt)], 100);
+        log.record("overworld", "Bob", &[edit([1, 20, 1], Material::Dirt, Material::Air)], 200);
+        log.record("overworld", "Bob", &[edit([-1, 20, 1], Material::Air, Material::Stone)], 200);
+        log.record("caves", "Bob", &[edit([1, 20, 1], Material::Air, Material::Stone)], 200);
+
+        // Pending edits are looked up as well
+        assert_eq!(log.inspect("overworld", Vector3::new(1, 20, 1)).unwrap().len(), 2);
+        log.flush().unwrap();
+        log.record("overworld", "Alice", &[edit([1, 20, 1], Material::Air, Material::Grass)], 300);
+        log.flush().unwrap();
+
+        let edits = log.inspect("overworld", Vector3::new(1, 20, 1)).unwrap();
+        let players: Vec<&str> = edits.iter().map(|edit| edit.player.as_str()).collect();
+        assert_eq!(players, vec!["Alice", "Bob", "Alice"]);
+        assert_eq!(edits[2].new, "grass");
+
+        let dir = log.save_dir.dimension("overworld").unwrap();
+        assert_eq!(dir.load_edit_log([0, 0]).unwrap().len(), 3);
+        assert_eq!(dir.load_edit_log([-1, 0]).unwrap().len(), 1);
+        assert_eq!(log.edits_of("caves", "Bob", 0).unwrap().len(), 1);
+        let _ = fs::remove_dir_all(log.save_dir.root_path());
+    }
+
+    #[test]
+    fn edits_of_a_player_are_rolled_back() {
+        let (engine, log) = edit_log("rollback");
+        let mut world = generated_world();
+        let (placed, broken, other) = (Vector3::new(2, 40, 2), Vector3::new(3, 40, 3), Vector3::new(4, 40, 4));
+
+        // An old edit, a block placed and broken again,
+        // and a block someone else changed afterwards
+        world.set_block(placed, Material::Stone);
+        log.record("overworld", "Griefer", &[edit(placed.into(), Material::Air, Material::Stone)], 10);
+        world.set_block(broken, Material::Dirt);
+        log.record("overworld", "Griefer", &[edit(broken.into(), Material::Air, Material::Dirt)], 1000);
+        world.set_block(broken, Material::Air);
+        log.record("overworld", "Griefer", &[edit(broken.into(), Material::Dirt, Material::Air)], 1001);
+        world.set_block(other, Material::Sand);
+        log.record("overworld", "Griefer", &[edit(other.into(), Material::Air, Material::Sand)], 1002);
+        world.set_block(other, Material::Grass);
+        log.record("overworld", "Builder", &[edit(other.into(), Material::Sand, Material::Grass)], 1003);
+        log.flush().unwrap();
+
+        let rollback = Rollback { dimension: "overworld".to_string(), player: "Griefer".to_string(), since: 1000 };
+        assert_eq!(log.roll_back(&mut world, &rollback, 2000).unwrap(), (2, 1));
+        assert_eq!(world.block(placed), Some(Material::Stone));
+        assert_eq!(world.block(broken), Some(Material::Air));
+        assert_eq!(world.block(other), Some(Material::Grass));
+
+        // The rollback is logged as well
+        let edits = log.inspect("overworld", broken).unwrap();
+        assert_eq!(edits.len(), 4);
+        assert_eq!(edits[3].player, ROLLBACK_PLAYER);
+
+        // Scripts request rollbacks and inspect blocks
+        engine.exec("test", r#"
+            editlog.rollback("overworld", "Griefer", 60)
+            local edits = editlog.inspect("overworld", 4, 40, 4)
+            assert(#edits == 2 and edits[2].player == "Builder" and edits[2].new == "grass")
+        "#).unwrap();
+        let rollbacks = log.take_rollbacks();
+        assert_eq!(rollbacks.len(), 1);
+        assert_eq!(rollbacks[0].player, "Griefer");
+        assert!(rollbacks[0].since > 2000);
+        assert!(log.take_rollbacks().is_empty());
+        let _ = fs::remove_dir_all(log.save_dir.root_path());
+    }
+}
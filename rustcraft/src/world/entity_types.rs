@@ -0,0 +1,1025 @@
+//! Types to spawn saved entities by their type, including
+//! the entity types scripts register and their behavior
+
+use crate::graphics::mesh::{Mesh, MeshHandle};
+use crate::graphics::scene::{NodeId, Scene, SceneNode};
+use crate::entity::Aabb;
+use crate::player::NameTag;
+use crate::scripting::{self, ScriptEngine, ScriptError};
+use crate::world::World;
+use crate::world::chunk_entities::{self, ChunkEntities, ChunkEntity, EntityUuid, SavedEntity, ITEM_TYPE, MOB_TYPE, VEHICLE_TYPE};
+use crate::world::items;
+use crate::world::script_api;
+use cgmath::{InnerSpace, Matrix4, Vector2, Vector3, Vector4};
+use mlua::{Function, IntoLua, Lua, RegistryKey, Table};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use toml::value::{Table as Components, Value};
+
+/// The edge length of the cube scripted entities are shown
+/// as, unless their type declares a model
+const SCRIPTED_SIZE: f32 = 0.8;
+/// The health of scripted entities whose type doesn't
+/// declare it
+const SCRIPTED_HEALTH: f32 = 10.0;
+/// The speed of scripted entities in blocks per second
+/// whose type doesn't declare it
+const SCRIPTED_SPEED: f32 = 1.0;
+/// The component the health of a scripted entity is saved as
+const HEALTH: &str = "health";
+/// The component the name of a scripted entity is shown from
+const NAME: &str = "name";
+/// The action interaction prompts show, unless the entity
+/// type declares its own
+const DEFAULT_PROMPT: &str = "interact";
+/// The time in seconds entities flash red after taking damage
+const HURT_FLASH: f32 = 0.3;
+/// The color hurt entities flash in
+const HURT_COLOR: Vector4<f32> = Vector4 { x: 1.0, y: 0.15, z: 0.15, w: 1.0 };
+
+/// The suffix of the names of spawn eggs, which the player
+/// holds like tools to spawn entities of their type
+const SPAWN_EGG_SUFFIX: &str = "_spawn_egg";
+
+/// A function spawning a saved entity into a world
+pub type SpawnFn = fn(&mut World, &SavedEntity) -> Result<(), String>;
+
+/// Returns the name of the spawn egg of an entity type,
+/// e.g. `critters:frog_spawn_egg`
+///
+/// # Arguments
+///
+/// * `type_id` - The type id
+pub fn spawn_egg(type_id: &str) -> String {
+    format!("{}{}", type_id, SPAWN_EGG_SUFFIX)
+}
+
+/// Returns the entity type a spawn egg spawns, or `None`
+/// if the item isn't a spawn egg
+///
+/// # Arguments
+///
+/// * `item` - The name of the item
+pub fn egg_type(item: &str) -> Option<&str> {
+    item.strip_suffix(SPAWN_EGG_SUFFIX).filter(|type_id| !type_id.is_empty())
+}
+
+/// EntityRegistry
+///
+/// The spawn functions of the entity types by their type id.
+/// Saved entities are spawned by the function of their type
+/// once their chunk is loaded. Entities of unknown types, e.g.
+/// of a mod which isn't installed anymore, are kept as they
+/// are saved. The registry is shared by all its clones.
+#[derive(Clone)]
+pub struct EntityRegistry {
+    /// The spawn functions by their type id
+    spawners: Arc<Mutex<HashMap<String, SpawnFn>>>,
+    /// The definitions of the types scripts registered
+    definitions: Arc<Mutex<HashMap<String, EntityDefinition>>>,
+}
+
+impl Default for EntityRegistry {
+    fn default() -> Self {
+        let registry = Self {
+            spawners: Arc::new(Mutex::new(HashMap::new())),
+            definitions: Arc::new(Mutex::new(HashMap::new())),
+        };
+        registry.register(ITEM_TYPE, World::restore_item);
+        registry.register(MOB_TYPE, World::restore_mob);
+        registry.register(VEHICLE_TYPE, World::restore_vehicle);
+        registry
+    }
+}
+
+impl EntityRegistry {
+    /// Registers the spawn function of an entity type,
+    /// replacing the previous function of the type
+    ///
+    /// # Arguments
+    ///
+    /// * `type_id` - The type id
+    /// * `spawn` - The spawn function
+    pub fn register(&self, type_id: &str, spawn: SpawnFn) {
+        self.spawners.lock().unwrap().insert(type_id.to_string(), spawn);
+    }
+
+    /// Returns the spawn function of an entity type, if
+    /// it's registered
+    ///
+    /// # Arguments
+    ///
+    /// * `type_id` - The type id
+    pub fn spawner(&self, type_id: &str) -> Option<SpawnFn> {
+        self.spawners.lock().unwrap().get(type_id).copied()
+    }
+
+    /// Registers an entity type a script defined, which
+    /// is spawned as a scripted entity
+    ///
+    /// # Arguments
+    ///
+    /// * `type_id` - The type id
+    /// * `definition` - The model and stats of the type
+    pub fn define(&self, type_id: &str, definition: EntityDefinition) {
+        self.definitions.lock().unwrap().insert(type_id.to_string(), definition);
+        self.register(type_id, World::restore_scripted);
+    }
+
+    /// Returns the model and stats of an entity type, which
+    /// are the default ones if the type didn't declare them
+    ///
+    /// # Arguments
+    ///
+    /// * `type_id` - The type id
+    pub fn definition(&self, type_id: &str) -> EntityDefinition {
+        self.definitions.lock().unwrap().get(type_id).copied().unwrap_or_default()
+    }
+}
+
+/// EntityDefinition
+///
+/// The box model and the stats of an entity type a
+/// script registered
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EntityDefinition {
+    /// The width, height and depth of the box
+    pub size: Vector3<f32>,
+    /// The color of the box, or `None` to color it
+    /// by the type id
+    pub color: Option<Vector4<f32>>,
+    /// The health entities are spawned with
+    pub health: f32,
+    /// The speed in blocks per second entities move
+    /// with at most
+    pub speed: f32,
+}
+
+impl Default for EntityDefinition {
+    fn default() -> Self {
+        Self {
+            size: Vector3::new(SCRIPTED_SIZE, SCRIPTED_SIZE, SCRIPTED_SIZE),
+            color: None,
+            health: SCRIPTED_HEALTH,
+            speed: SCRIPTED_SPEED,
+        }
+    }
+}
+
+impl EntityDefinition {
+    /// Reads the model and stats of an entity type from
+    /// the table it's registered with
+    ///
+    /// # Arguments
+    ///
+    /// * `def` - The Lua table
+    fn from_table(def: &Table) -> mlua::Result<Self> {
+        let mut definition = Self::default();
+        if let Some(model) = def.get::<_, Option<Table>>("model")? {
+            if let Some(size) = model.get::<_, Option<Table>>("size")? {
+                definition.size = scripting::vector_from_table(size)?;
+            }
+            if let Some(color) = model.get::<_, Option<Table>>("color")? {
+                let color = scripting::vector_from_table(color)?;
+                definition.color = Some(color.extend(1.0));
+            }
+        }
+        if let Some(stats) = def.get::<_, Option<Table>>("stats")? {
+            definition.health = stats.get::<_, Option<f32>>("health")?.unwrap_or(definition.health);
+            definition.speed = stats.get::<_, Option<f32>>("speed")?.unwrap_or(definition.speed);
+        }
+        if definition.size.x <= 0.0 || definition.size.y <= 0.0 || definition.size.z <= 0.0 {
+            return Err(mlua::Error::RuntimeError("the size of a model must be positive".to_string()));
+        }
+        Ok(definition)
+    }
+}
+
+/// ScriptedEntity
+///
+/// An entity of a type a script registered. It keeps the
+/// components it has been spawned with, which its behavior
+/// could change.
+pub struct ScriptedEntity {
+    /// The id of the entity
+    uuid: EntityUuid,
+    /// The type of the entity
+    type_id: String,
+    /// The position of the bottom center of the entity
+    pos: Vector3<f32>,
+    /// The components of the entity, without its health
+    components: Components,
+    /// The health of the entity
+    health: f32,
+    /// The model and stats of the entity's type
+    definition: EntityDefinition,
+    /// The node the entity is shown with
+    node: NodeId,
+    /// The time in seconds the entity still flashes red for
+    flash: f32,
+}
+
+impl ScriptedEntity {
+    /// Returns the type of the entity
+    pub fn type_id(&self) -> &str {
+        &self.type_id
+    }
+
+    /// Returns the position of the bottom center of the entity
+    pub fn pos(&self) -> &Vector3<f32> {
+        &self.pos
+    }
+
+    /// Returns the components of the entity
+    pub fn components(&self) -> &Components {
+        &self.components
+    }
+
+    /// Returns the health of the entity
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+
+    /// Returns the color of the entity's model, which is
+    /// tinted red while it flashes after taking damage
+    fn color(&self) -> Vector4<f32> {
+        let color = self.definition.color.unwrap_or_else(|| items::item_color(&self.type_id));
+        color + (HURT_COLOR - color) * (self.flash / HURT_FLASH).clamp(0.0, 1.0)
+    }
+
+    /// Returns the bounding box of the entity
+    pub fn aabb(&self) -> Aabb {
+        let half = Vector3::new(self.definition.size.x / 2.0, 0.0, self.definition.size.z / 2.0);
+        Aabb::new(self.pos - half, self.pos + half + Vector3::new(0.0, self.definition.size.y, 0.0))
+    }
+
+    /// Returns the entity as it's saved with its chunk
+    fn saved(&self) -> SavedEntity {
+        SavedEntity {
+            components: self.components.clone(),
+            ..SavedEntity::new(&self.type_id, self.uuid, self.pos)
+        }.with(HEALTH, self.health as f64)
+    }
+
+    /// Returns the state of the entity its behavior changes
+    fn state(&self) -> EntityState {
+        EntityState {
+            uuid: self.uuid,
+            type_id: self.type_id.clone(),
+            pos: self.pos,
+            health: self.health,
+            speed: self.definition.speed,
+            components: self.components.clone(),
+            removed: false,
+        }
+    }
+}
+
+/// EntityState
+///
+/// The state of a scripted entity, which its behavior
+/// callbacks read and change
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityState {
+    /// The id of the entity
+    pub uuid: EntityUuid,
+    /// The type of the entity
+    pub type_id: String,
+    /// The position of the bottom center of the entity
+    pub pos: Vector3<f32>,
+    /// The health of the entity
+    pub health: f32,
+    /// The speed of the entity in blocks per second
+    pub speed: f32,
+    /// The components of the entity, without its health
+    pub components: Components,
+    /// Whether the entity should be removed
+    pub removed: bool,
+}
+
+impl ChunkEntity for ScriptedEntity {
+    fn uuid(&self) -> EntityUuid {
+        self.uuid
+    }
+
+    fn pos(&self) -> Vector3<f32> {
+        self.pos
+    }
+}
+
+/// ScriptedEntities
+///
+/// The entities of types scripts registered in a world. They
+/// are shown as the box model of their type and are moved by
+/// their behavior callbacks.
+pub struct ScriptedEntities {
+    /// The entities by their chunk
+    entities: ChunkEntities<ScriptedEntity>,
+    /// The mesh all entities are shown with
+    mesh: MeshHandle,
+}
+
+impl Default for ScriptedEntities {
+    fn default() -> Self {
+        Self {
+            entities: ChunkEntities::default(),
+            mesh: MeshHandle::new(Mesh::cube()),
+        }
+    }
+}
+
+impl ScriptedEntities {
+    /// Returns the entities in the world
+    pub fn entities(&self) -> &ChunkEntities<ScriptedEntity> {
+        &self.entities
+    }
+
+    /// Spawns a saved entity with its id and components. It
+    /// has the health of its type unless its health is saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the entity is shown in
+    /// * `saved` - The saved entity
+    /// * `definition` - The model and stats of the entity's type
+    pub fn spawn(&mut self, scene: &mut Scene, saved: &SavedEntity, definition: EntityDefinition) {
+        let mut components = saved.components.clone();
+        let health = components.remove(HEALTH)
+            .and_then(|health| health.as_float().or_else(|| health.as_integer().map(|health| health as f64)))
+            .map_or(definition.health, |health| health as f32);
+        let pos = saved.pos.into();
+        let node = scene.add(None, SceneNode::with_mesh(
+            "entity",
+            model_transform(pos, definition.size),
+            self.mesh.clone(),
+            definition.color.unwrap_or_else(|| items::item_color(&saved.type_id)),
+        ));
+        self.entities.insert(ScriptedEntity {
+            uuid: saved.uuid,
+            type_id: saved.type_id.clone(),
+            pos,
+            components,
+            health,
+            definition,
+            node,
+            flash: 0.0,
+        });
+    }
+
+    /// Returns the state of an entity, if it's in the world
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The id of the entity
+    pub fn state(&self, uuid: EntityUuid) -> Option<EntityState> {
+        self.entities.iter().find(|entity| entity.uuid == uuid).map(ScriptedEntity::state)
+    }
+
+    /// Applies the state of an entity its behavior changed.
+    /// Entities which are removed or out of health are
+    /// removed from the world.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the entity is shown in
+    /// * `state` - The changed state of the entity
+    pub fn apply(&mut self, scene: &mut Scene, state: EntityState) {
+        let key = match self.entities.find_key(state.uuid) {
+            Some(key) => key,
+            None => return,
+        };
+        let entity = self.entities.get_mut(key);
+        // Entities flash red as they take damage
+        if state.health < entity.health {
+            entity.flash = HURT_FLASH;
+        }
+        entity.pos = state.pos;
+        entity.health = state.health;
+        entity.components = state.components;
+
+        let mut removed = Vec::new();
+        if state.removed || state.health <= 0.0 {
+            scene.remove(entity.node);
+            removed.push(key);
+        } else if let Some(node) = scene.node_mut(entity.node) {
+            node.set_transform(model_transform(entity.pos, entity.definition.size));
+            node.set_color(entity.color());
+        }
+        self.entities.remove_and_relocate(removed);
+    }
+
+    /// Returns the ids of the entities in the chunks within
+    /// the activation radius around the player
+    ///
+    /// # Arguments
+    ///
+    /// * `player` - The position of the player's feet
+    /// * `radius` - The activation radius in chunks
+    pub fn active(&self, player: Vector3<f32>, radius: i32) -> Vec<EntityUuid> {
+        let center = chunk_entities::chunk_of(player);
+        self.entities.iter()
+            .filter(|entity| chunk_entities::is_active(chunk_entities::chunk_of(entity.pos), center, radius))
+            .map(|entity| entity.uuid)
+            .collect()
+    }
+
+    /// Fades the red flash of the entities which took damage
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the entities are shown in
+    /// * `seconds` - The time passed since the last update
+    pub fn update_flashes(&mut self, scene: &mut Scene, seconds: f32) {
+        for entity in self.entities.iter_mut().filter(|entity| entity.flash > 0.0) {
+            entity.flash = (entity.flash - seconds).max(0.0);
+            if let Some(node) = scene.node_mut(entity.node) {
+                node.set_color(entity.color());
+            }
+        }
+    }
+
+    /// Returns the name tags of the entities with a
+    /// `name` component
+    pub fn name_tags(&self) -> Vec<NameTag> {
+        self.entities.iter()
+            .filter_map(|entity| Some(NameTag {
+                name: entity.components.get(NAME)?.as_str()?.to_string(),
+                pos: entity.pos + Vector3::new(0.0, entity.definition.size.y, 0.0),
+            }))
+            .collect()
+    }
+
+    /// Returns the closest entity a ray hits within the
+    /// given distance, with the distance to it
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The origin of the ray in world coordinates
+    /// * `dir` - The direction of the ray, doesn't need to be normalized
+    /// * `max_distance` - The maximal distance of the ray
+    pub fn hit(&self, origin: Vector3<f32>, dir: Vector3<f32>, max_distance: f32) -> Option<(EntityUuid, f32)> {
+        self.entities.iter()
+            .filter_map(|entity| Some((entity.uuid, entity.aabb().ray_distance(origin, dir)?)))
+            .filter(|&(_, distance)| distance <= max_distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Removes the entities of a chunk from the world and
+    /// returns them, so they can be saved with the chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the entities are shown in
+    /// * `loc` - The location of the chunk
+    pub fn unload_chunk(&mut self, scene: &mut Scene, loc: Vector2<i32>) -> Vec<SavedEntity> {
+        self.entities.take_chunk(loc).into_iter()
+            .map(|entity| {
+                scene.remove(entity.node);
+                entity.saved()
+            })
+            .collect()
+    }
+
+    /// Returns the entities of a chunk as they're saved
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the chunk
+    pub fn saved(&self, loc: Vector2<i32>) -> Vec<SavedEntity> {
+        self.entities.in_chunk(loc).iter().map(ScriptedEntity::saved).collect()
+    }
+}
+
+/// EntityHook
+///
+/// A behavior callback of a scripted entity type
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum EntityHook {
+    /// A script spawned the entity
+    Spawn,
+    /// The entity is updated, with the time passed since
+    /// the last update in seconds
+    Tick,
+    /// The player hit the entity, with the damage it took
+    Hurt,
+    /// The player interacted with the entity, with the
+    /// name of the player
+    Interact,
+}
+
+/// The behavior callbacks of an entity type, and the
+/// action the interaction prompt shows
+#[derive(Default)]
+struct Behavior {
+    /// The callbacks
+    hooks: HashMap<EntityHook, RegistryKey>,
+    /// The action the interaction prompt shows, e.g. `talk`
+    prompt: Option<String>,
+}
+
+impl EntityHook {
+    /// All behavior callbacks
+    pub const ALL: [EntityHook; 4] = [EntityHook::Spawn, EntityHook::Tick, EntityHook::Hurt, EntityHook::Interact];
+
+    /// Returns the name of the callback in the table
+    /// the entity type is registered with
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntityHook::Spawn => "onSpawn",
+            EntityHook::Tick => "onTick",
+            EntityHook::Hurt => "onHurt",
+            EntityHook::Interact => "onInteract",
+        }
+    }
+}
+
+/// EntityTypes
+///
+/// The entity types scripts register through the `entities`
+/// API, and the entities they spawn, e.g.
+///
+/// ```lua
+/// entities.register("balloons:balloon")
+/// -- Returns the id of the entity
+/// local id = entities.spawn("balloons:balloon", { 10, 80, 4 }, { color = "red", lift = 2 })
+/// ```
+///
+/// The components of an entity are saved with it and could be
+/// booleans, numbers, strings or tables of these. The built-in
+/// types `item` and `mob` could be spawned too, e.g. with the
+/// components `item` and `count` of dropped items.
+///
+/// Types could be registered with a box model, stats and
+/// behavior callbacks as well, e.g.
+///
+/// ```lua
+/// entities.register {
+///     type = "critters:frog",
+///     model = { size = { 0.5, 0.4, 0.5 }, color = { 0.3, 0.7, 0.2 } },
+///     stats = { health = 4, speed = 2 },
+///     onTick = function(frog, seconds)
+///         frog.pos[1] = frog.pos[1] + seconds
+///     end,
+///     onHurt = function(frog, damage)
+///         frog.data.scared = true
+///     end,
+///     prompt = "pet",
+///     onInteract = function(frog, player)
+///         frog.removed = true
+///     end,
+/// }
+/// ```
+///
+/// The player is prompted to interact with entities whose
+/// type has an `onInteract` callback, showing the `prompt`
+/// of the type. Entities with a `name` component have their
+/// name floating above them.
+///
+/// The callbacks get a table of the entity with its `id`,
+/// `type`, `pos`, `health` and the components as `data`.
+/// Changes to its `pos`, `health` and `data` are applied
+/// afterwards, while entities are removed once they are out
+/// of health or `removed` is set. An update moves an entity
+/// by its speed at most. The `world` table random ticks get is
+/// passed last, e.g. to cast rays with `world.raycast`.
+pub struct EntityTypes {
+    /// The spawn functions of the entity types
+    registry: EntityRegistry,
+    /// The behavior callbacks by the type id
+    behaviors: Arc<Mutex<HashMap<String, Behavior>>>,
+    /// The entities spawned since the last update
+    spawned: Arc<Mutex<Vec<SavedEntity>>>,
+}
+
+impl EntityTypes {
+    /// Creates a new entity type registry and registers
+    /// its API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let registry = EntityRegistry::default();
+        let behaviors = Arc::new(Mutex::new(HashMap::new()));
+        let spawned = Arc::new(Mutex::new(Vec::new()));
+
+        let lua = engine.lua();
+        let api = scripting::api_table(lua, "entities")?;
+        let types = registry.clone();
+        let callbacks = behaviors.clone();
+        api.set("register", lua.create_function(move |lua, def: mlua::Value| {
+            let (type_id, definition, behavior): (String, _, _) = match def {
+                mlua::Value::String(type_id) => (type_id.to_str()?.to_string(), EntityDefinition::default(), Behavior::default()),
+                mlua::Value::Table(def) => {
+                    let mut behavior = Behavior {
+                        prompt: def.get("prompt")?,
+                        ..Behavior::default()
+                    };
+                    for &hook in EntityHook::ALL.iter() {
+                        if let Some(callback) = def.get::<_, Option<Function>>(hook.name())? {
+                            behavior.hooks.insert(hook, lua.create_registry_value(callback)?);
+                        }
+                    }
+                    (def.get("type")?, EntityDefinition::from_table(&def)?, behavior)
+                }
+                value => return Err(mlua::Error::RuntimeError(format!("an entity type can't be a {}", value.type_name()))),
+            };
+            if type_id == ITEM_TYPE || type_id == MOB_TYPE || type_id == VEHICLE_TYPE {
+                return Err(mlua::Error::RuntimeError(format!("{} is a built-in entity type", type_id)));
+            }
+            types.define(&type_id, definition);
+            callbacks.lock().unwrap().insert(type_id, behavior);
+            Ok(())
+        })?)?;
+        let types = registry.clone();
+        let queue = spawned.clone();
+        api.set("spawn", lua.create_function(move |_, (type_id, pos, components): (String, Table, Option<Table>)| {
+            if types.spawner(&type_id).is_none() {
+                return Err(mlua::Error::RuntimeError(format!("unknown entity type {}", type_id)));
+            }
+            let mut entity = SavedEntity::new(&type_id, EntityUuid::generate(), scripting::vector_from_table(pos)?);
+            if let Some(components) = components {
+                entity.components = components_from_table(components)?;
+            }
+            let uuid = entity.uuid;
+            queue.lock().unwrap().push(entity);
+            Ok(uuid.to_string())
+        })?)?;
+
+        Ok(Self {
+            registry,
+            behaviors,
+            spawned,
+        })
+    }
+
+    /// Returns the registry, which is shared with the Lua API
+    pub fn registry(&self) -> EntityRegistry {
+        self.registry.clone()
+    }
+
+    /// Takes the entities scripts spawned since the last call
+    pub fn take_spawned(&self) -> Vec<SavedEntity> {
+        std::mem::take(&mut *self.spawned.lock().unwrap())
+    }
+
+    /// Calls the `onSpawn` callback of an entity a script
+    /// spawned, once it's in the world
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `world` - The world the entity is in
+    /// * `uuid` - The id of the entity
+    pub fn spawned(&self, engine: &ScriptEngine, world: &mut World, uuid: EntityUuid) -> Result<(), ScriptError> {
+        match world.scripted_entities().state(uuid) {
+            Some(state) if self.has_hook(&state.type_id, EntityHook::Spawn) => {
+                self.run(engine.lua(), world, state, EntityHook::Spawn, mlua::Nil, None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Calls the `onTick` callbacks of the entities in the
+    /// chunks within the activation radius around the player
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `world` - The world the entities are in
+    /// * `seconds` - The time passed since the last update
+    /// * `player` - The position of the player's feet
+    /// * `radius` - The activation radius in chunks
+    pub fn tick(&self, engine: &ScriptEngine, world: &mut World, seconds: f32, player: Vector3<f32>, radius: i32) -> Vec<ScriptError> {
+        let mut errors = Vec::new();
+        for uuid in world.scripted_entities().active(player, radius) {
+            // A previous callback could have removed the entity
+            let state = match world.scripted_entities().state(uuid) {
+                Some(state) if self.has_hook(&state.type_id, EntityHook::Tick) => state,
+                _ => continue,
+            };
+            let max_move = state.speed.max(0.0) * seconds;
+            if let Err(error) = self.run(engine.lua(), world, state, EntityHook::Tick, seconds, Some(max_move)) {
+                errors.push(error);
+            }
+        }
+        errors
+    }
+
+    /// Deals damage to an entity and calls its `onHurt`
+    /// callback, which could still heal it before it's
+    /// removed for being out of health. The entity is
+    /// knocked back unless it would end up in a block.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `world` - The world the entity is in
+    /// * `uuid` - The id of the entity
+    /// * `damage` - The damage the entity takes
+    /// * `knockback` - The offset the entity is knocked back by
+    pub fn hurt(&self, engine: &ScriptEngine, world: &mut World, uuid: EntityUuid, damage: f32,
+                knockback: Vector3<f32>) -> Result<(), ScriptError> {
+        let mut state = match world.scripted_entities().state(uuid) {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        state.health -= damage;
+        let pushed = state.pos + knockback;
+        if matches!(world.block(pushed.map(|value| value.floor() as i32)), Some(material) if !material.is_solid()) {
+            state.pos = pushed;
+        }
+        self.run(engine.lua(), world, state, EntityHook::Hurt, damage, None)
+    }
+
+    /// Calls the `onInteract` callback of an entity the
+    /// player interacted with
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `world` - The world the entity is in
+    /// * `uuid` - The id of the entity
+    /// * `player` - The name of the player
+    pub fn interact(&self, engine: &ScriptEngine, world: &mut World, uuid: EntityUuid, player: &str) -> Result<(), ScriptError> {
+        match world.scripted_entities().state(uuid) {
+            Some(state) if self.has_hook(&state.type_id, EntityHook::Interact) => {
+                self.run(engine.lua(), world, state, EntityHook::Interact, player, None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the action the interaction prompt of an entity
+    /// shows, e.g. `talk`, or `None` if the player can't
+    /// interact with it
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world the entity is in
+    /// * `uuid` - The id of the entity
+    pub fn prompt(&self, world: &World, uuid: EntityUuid) -> Option<String> {
+        let state = world.scripted_entities().state(uuid)?;
+        let behaviors = self.behaviors.lock().unwrap();
+        let behavior = behaviors.get(&state.type_id).filter(|behavior| behavior.hooks.contains_key(&EntityHook::Interact))?;
+        Some(behavior.prompt.clone().unwrap_or_else(|| DEFAULT_PROMPT.to_string()))
+    }
+
+    /// Returns whether an entity type has a behavior callback
+    ///
+    /// # Arguments
+    ///
+    /// * `type_id` - The type id
+    /// * `hook` - The callback
+    fn has_hook(&self, type_id: &str, hook: EntityHook) -> bool {
+        matches!(self.behaviors.lock().unwrap().get(type_id), Some(behavior) if behavior.hooks.contains_key(&hook))
+    }
+
+    /// Calls a behavior callback of an entity and applies the
+    /// state it changed. The state is applied as it was passed
+    /// if the callback fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `lua` - The Lua state of the script engine
+    /// * `world` - The world the entity is in
+    /// * `state` - The state of the entity
+    /// * `hook` - The callback
+    /// * `arg` - The argument passed after the entity
+    /// * `max_move` - How far the callback may move the entity,
+    ///   or `None` if it may move it anywhere
+    fn run<'lua, A: IntoLua<'lua>>(&self, lua: &'lua Lua, world: &mut World, mut state: EntityState,
+                                   hook: EntityHook, arg: A, max_move: Option<f32>) -> Result<(), ScriptError> {
+        let source = format!("{} of {}", hook.name(), state.type_id);
+        let result = {
+            let world = RefCell::new(&mut *world);
+            lua.scope(|scope| {
+                let api = script_api::world_api(lua, scope, &world)?;
+                self.call(lua, &mut state, hook, arg, api, max_move)
+            })
+        };
+        world.apply_entity_state(state);
+        result.map_err(|error| ScriptError::new(&source, error))
+    }
+
+    /// Calls a behavior callback of an entity with a table of
+    /// its state and reads the changed state back
+    ///
+    /// # Arguments
+    ///
+    /// * `lua` - The Lua state of the script engine
+    /// * `state` - The state of the entity
+    /// * `hook` - The callback
+    /// * `arg` - The argument passed after the entity
+    /// * `world` - The `world` table passed last
+    /// * `max_move` - How far the callback may move the entity
+    fn call<'lua, A: IntoLua<'lua>>(&self, lua: &'lua Lua, state: &mut EntityState, hook: EntityHook,
+                                    arg: A, world: Table<'lua>, max_move: Option<f32>) -> mlua::Result<()> {
+        // The callback is taken out of the registry first, so
+        // that it could register entity types itself
+        let callback: Function = {
+            let behaviors = self.behaviors.lock().unwrap();
+            match behaviors.get(&state.type_id).and_then(|behavior| behavior.hooks.get(&hook)) {
+                Some(key) => lua.registry_value(key)?,
+                None => return Ok(()),
+            }
+        };
+
+        let entity = lua.create_table()?;
+        entity.set("id", state.uuid.to_string())?;
+        entity.set("type", state.type_id.as_str())?;
+        entity.set("pos", lua.create_sequence_from(vec![state.pos.x, state.pos.y, state.pos.z])?)?;
+        entity.set("health", state.health)?;
+        entity.set("data", components_to_table(lua, &state.components)?)?;
+        callback.call::<_, ()>((entity.clone(), arg, world))?;
+
+        let pos = scripting::vector_from_table(entity.get("pos")?)?;
+        let health: f32 = entity.get("health")?;
+        let components = components_from_table(entity.get("data")?)?;
+        let removed = entity.get::<_, Option<bool>>("removed")?.unwrap_or(false);
+
+        let moved = pos - state.pos;
+        state.pos = match max_move {
+            Some(max_move) if moved.magnitude() > max_move => state.pos + moved.normalize_to(max_move),
+            _ => pos,
+        };
+        state.health = health;
+        state.components = components;
+        state.removed = removed;
+        Ok(())
+    }
+}
+
+/// Converts the components of an entity to a Lua table
+///
+/// # Arguments
+///
+/// * `lua` - The Lua state
+/// * `components` - The components
+fn components_to_table<'lua>(lua: &'lua Lua, components: &Components) -> mlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    for (key, value) in components {
+        table.set(key.as_str(), value_to_lua(lua, value)?)?;
+    }
+    Ok(table)
+}
+
+/// Converts a component value to a Lua value. Arrays
+/// become sequences and dates become strings.
+///
+/// # Arguments
+///
+/// * `lua` - The Lua state
+/// * `value` - The component value
+fn value_to_lua<'lua>(lua: &'lua Lua, value: &Value) -> mlua::Result<mlua::Value<'lua>> {
+    Ok(match value {
+        Value::Boolean(value) => mlua::Value::Boolean(*value),
+        Value::Integer(value) => mlua::Value::Integer(*value),
+        Value::Float(value) => mlua::Value::Number(*value),
+        Value::String(value) => value.as_str().into_lua(lua)?,
+        Value::Datetime(value) => value.to_string().into_lua(lua)?,
+        Value::Array(values) => mlua::Value::Table(lua.create_sequence_from(
+            values.iter().map(|value| value_to_lua(lua, value)).collect::<mlua::Result<Vec<_>>>()?
+        )?),
+        Value::Table(table) => mlua::Value::Table(components_to_table(lua, table)?),
+    })
+}
+
+/// Returns the transformation of the box model of an entity
+///
+/// # Arguments
+///
+/// * `pos` - The position of the bottom center of the entity
+/// * `size` - The width, height and depth of the box
+fn model_transform(pos: Vector3<f32>, size: Vector3<f32>) -> Matrix4<f32> {
+    Matrix4::from_translation(pos + Vector3::new(0.0, size.y / 2.0, 0.0))
+        * Matrix4::from_nonuniform_scale(size.x, size.y, size.z)
+}
+
+/// Reads the components of an entity from a Lua table
+///
+/// # Arguments
+///
+/// * `table` - The Lua table
+fn components_from_table(table: Table) -> mlua::Result<Components> {
+    let mut components = Components::new();
+    for pair in table.pairs::<String, mlua::Value>() {
+        let (key, value) = pair?;
+        components.insert(key, value_from_lua(value)?);
+    }
+    Ok(components)
+}
+
+/// Converts a Lua value to a component value. Tables
+/// with a sequence become arrays.
+///
+/// # Arguments
+///
+/// * `value` - The Lua value
+fn value_from_lua(value: mlua::Value) -> mlua::Result<Value> {
+    Ok(match value {
+        mlua::Value::Boolean(value) => Value::Boolean(value),
+        mlua::Value::Integer(value) => Value::Integer(value),
+        mlua::Value::Number(value) => Value::Float(value),
+        mlua::Value::String(value) => Value::String(value.to_str()?.to_string()),
+        mlua::Value::Table(table) if table.raw_len() > 0 => Value::Array(
+            table.sequence_values::<mlua::Value>()
+                .map(|value| value_from_lua(value?))
+                .collect::<mlua::Result<_>>()?
+        ),
+        mlua::Value::Table(table) => Value::Table(components_from_table(table)?),
+        value => return Err(mlua::Error::RuntimeError(format!("a component can't be a {}", value.type_name()))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_spawn_registered_types() {
+        let engine = ScriptEngine::new();
+        let types = EntityTypes::new(&engine).unwrap();
+        assert!(engine.exec("test", r#"entities.spawn("balloons:balloon", { 0, 0, 0 })"#).is_err());
+        assert!(engine.exec("test", r#"entities.register("item")"#).is_err());
+
+        engine.exec("test", r#"
+            entities.register("balloons:balloon")
+            local id = entities.spawn("balloons:balloon", { 1, 2, 3 }, { color = "red", lift = 2, path = { 1.5, 2 }, tags = { floating = true } })
+            assert(#id == 36)
+            entities.spawn("item", { 0, 64, 0 }, { item = "dirt", count = 4 })
+        "#).unwrap();
+        assert!(engine.exec("test", r#"entities.spawn("balloons:balloon", { 0, 0, 0 }, { broken = print })"#).is_err());
+
+        let spawned = types.take_spawned();
+        assert_eq!(spawned.len(), 2);
+        let balloon = &spawned[0];
+        assert_eq!(balloon.type_id, "balloons:balloon");
+        assert_eq!(balloon.pos, [1.0, 2.0, 3.0]);
+        assert_eq!(balloon.text("color"), Ok("red"));
+        assert_eq!(balloon.components["path"], Value::Array(vec![Value::Float(1.5), Value::Integer(2)]));
+        assert_eq!(balloon.components["tags"].get("floating"), Some(&Value::Boolean(true)));
+        assert_ne!(balloon.uuid, spawned[1].uuid);
+        assert!(types.take_spawned().is_empty());
+        assert!(types.registry().spawner("balloons:balloon").is_some());
+    }
+
+    #[test]
+    fn spawn_eggs_name_their_type() {
+        assert_eq!(spawn_egg("critters:frog"), "critters:frog_spawn_egg");
+        assert_eq!(egg_type(&spawn_egg("zombie")), Some("zombie"));
+        assert_eq!(egg_type("_spawn_egg"), None);
+        assert_eq!(egg_type("pickaxe"), None);
+    }
+
+    #[test]
+    fn behavior_callbacks_change_entities() {
+        let engine = ScriptEngine::new();
+        let types = EntityTypes::new(&engine).unwrap();
+        engine.exec("test", r#"
+            entities.register {
+                type = "critters:frog",
+                model = { size = { 0.5, 0.4, 0.5 }, color = { 0.3, 0.7, 0.2 } },
+                stats = { health = 4, speed = 2 },
+                prompt = "pet",
+                onSpawn = function(frog) frog.data.name = "Fred" end,
+                onTick = function(frog, seconds) frog.pos[1] = frog.pos[1] + 10 end,
+                onHurt = function(frog, damage) frog.data.hurt = damage end,
+                onInteract = function(frog, player) frog.data.friend = player end,
+            }
+        "#).unwrap();
+        assert!(engine.exec("test", r#"entities.register { type = "mob" }"#).is_err());
+        assert!(engine.exec("test", r#"entities.register { type = "critters:flat", model = { size = { 1, 0, 1 } } }"#).is_err());
+
+        let mut world = World::new();
+        world.load_chunk(&Vector2::new(0, 0));
+        world.set_entity_types(types.registry());
+        let uuid = EntityUuid::generate();
+        world.spawn_entity(&SavedEntity::new("critters:frog", uuid, Vector3::new(1.0, 64.0, 1.0))).unwrap();
+        types.spawned(&engine, &mut world, uuid).unwrap();
+
+        // The frog only moves by its speed
+        assert!(types.tick(&engine, &mut world, 0.5, Vector3::new(0.0, 64.0, 0.0), 1).is_empty());
+        let frog = world.scripted_entities().state(uuid).unwrap();
+        assert_eq!(frog.pos, Vector3::new(2.0, 64.0, 1.0));
+        assert_eq!(frog.health, 4.0);
+        assert_eq!(world.scripted_entities().name_tags(), vec![NameTag { name: "Fred".to_string(), pos: Vector3::new(2.0, 64.4, 1.0) }]);
+        assert_eq!(types.prompt(&world, uuid), Some("pet".to_string()));
+        assert_eq!(world.scripted_entities().hit(Vector3::new(2.0, 64.2, 5.0), Vector3::new(0.0, 0.0, -1.0), 5.0), Some((uuid, 3.75)));
+
+        types.interact(&engine, &mut world, uuid, "steve").unwrap();
+        types.hurt(&engine, &mut world, uuid, 1.5, Vector3::new(0.0, 0.0, 0.5)).unwrap();
+        let frog = world.scripted_entities().state(uuid).unwrap();
+        assert_eq!(frog.health, 2.5);
+        assert_eq!(frog.pos, Vector3::new(2.0, 64.0, 1.5));
+        assert_eq!(frog.components["hurt"], Value::Float(1.5));
+        assert_eq!(frog.components["friend"], Value::String("steve".to_string()));
+        assert_eq!(world.scripted_entities().saved(Vector2::new(0, 0))[0].number(HEALTH), Some(2.5));
+
+        // Frogs out of health are removed
+        types.hurt(&engine, &mut world, uuid, 3.0, Vector3::new(0.0, 0.0, 0.0)).unwrap();
+        assert!(world.scripted_entities().state(uuid).is_none());
+        assert!(world.scripted_entities().entities().is_empty());
+    }
+}
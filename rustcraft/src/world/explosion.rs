@@ -0,0 +1,355 @@
+//! Types to let primed TNT fall and blow up the blocks
+//! around it, setting off the TNT caught in the blast
+
+use crate::graphics::mesh::{Mesh, MeshHandle};
+use crate::graphics::scene::{NodeId, Scene, SceneNode};
+use crate::world::block::Material;
+use crate::world::World;
+use cgmath::{InnerSpace, Matrix4, Vector3, Vector4};
+
+/// The time in seconds until TNT lit by the player or by
+/// fire explodes
+pub const FUSE_TIME: f32 = 4.0;
+/// The time in seconds until TNT set off by another
+/// explosion explodes, so chains go off one after another
+pub const CHAINED_FUSE_TIME: f32 = 0.5;
+/// The radius in blocks of the explosion of TNT
+pub const TNT_RADIUS: f32 = 4.0;
+/// The hardness from which on blocks withstand explosions,
+/// e.g. obsidian
+const BLAST_RESISTANCE: f32 = 10.0;
+/// The speed primed TNT falls with in blocks per second
+const FALL_SPEED: f32 = 8.0;
+/// The time in seconds the fuse flashes white for, and
+/// the time between two flashes
+const FLASH_INTERVAL: f32 = 0.25;
+/// The colors primed TNT is shown with
+const TNT_COLOR: Vector4<f32> = Vector4 { x: 0.8, y: 0.15, z: 0.1, w: 1.0 };
+const FLASH_COLOR: Vector4<f32> = Vector4 { x: 1.0, y: 1.0, z: 1.0, w: 1.0 };
+
+/// PrimedTnt
+///
+/// A TNT block which has been lit, and falls like an
+/// entity until its fuse burned down
+pub struct PrimedTnt {
+    /// The position of the bottom center of the TNT
+    pos: Vector3<f32>,
+    /// The time in seconds until the TNT explodes
+    fuse: f32,
+    /// The node the TNT is shown with
+    node: NodeId,
+}
+
+impl PrimedTnt {
+    /// Returns the position of the bottom center of the TNT
+    pub fn pos(&self) -> &Vector3<f32> {
+        &self.pos
+    }
+
+    /// Returns the time in seconds until the TNT explodes
+    pub fn fuse(&self) -> f32 {
+        self.fuse
+    }
+
+    /// Returns whether the fuse is flashing white
+    fn is_flashing(&self) -> bool {
+        (self.fuse / (2.0 * FLASH_INTERVAL)).fract() < 0.5
+    }
+
+    /// Returns the center of the explosion of the TNT
+    fn center(&self) -> Vector3<f32> {
+        self.pos + Vector3::new(0.0, 0.5, 0.0)
+    }
+}
+
+/// PrimedTntEntities
+///
+/// The primed TNT in a world, shown as flashing cubes in
+/// the scene of the world. Primed TNT isn't saved with
+/// its chunk, it explodes within a few seconds anyway.
+pub struct PrimedTntEntities {
+    /// The primed TNT
+    primed: Vec<PrimedTnt>,
+    /// The mesh the TNT is shown with
+    mesh: MeshHandle,
+}
+
+impl Default for PrimedTntEntities {
+    fn default() -> Self {
+        Self {
+            primed: Vec::new(),
+            mesh: MeshHandle::new(Mesh::cube()),
+        }
+    }
+}
+
+impl PrimedTntEntities {
+    /// Returns the primed TNT
+    pub fn primed(&self) -> &[PrimedTnt] {
+        &self.primed
+    }
+
+    /// Spawns primed TNT at the given position
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the TNT is shown in
+    /// * `pos` - The position of the bottom center of the TNT
+    /// * `fuse` - The time in seconds until the TNT explodes
+    pub fn spawn(&mut self, scene: &mut Scene, pos: Vector3<f32>, fuse: f32) {
+        let node = scene.add(None, SceneNode::with_mesh("tnt", tnt_transform(pos, fuse), self.mesh.clone(), TNT_COLOR));
+        self.primed.push(PrimedTnt { pos, fuse, node });
+    }
+
+    /// Advances the primed TNT and returns the centers of the
+    /// explosions of the TNT whose fuse burned down
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the TNT is shown in
+    /// * `seconds` - The time passed since the last update
+    /// * `block_at` - Returns the material of the block at a position
+    pub fn update<F>(&mut self, scene: &mut Scene, seconds: f32, block_at: F) -> Vec<Vector3<f32>>
+        where F: Fn(Vector3<i32>) -> Option<Material>
+    {
+        let mut exploded = Vec::new();
+        for tnt in self.primed.iter_mut() {
+            tnt.fuse -= seconds;
+            if tnt.fuse <= 0.0 {
+                exploded.push(tnt.center());
+                scene.remove(tnt.node);
+                continue;
+            }
+
+            // TNT only falls through air, plants and fluids of loaded chunks
+            let below = tnt.pos - Vector3::new(0.0, 0.01, 0.0);
+            let below = Vector3::new(below.x.floor() as i32, below.y.floor() as i32, below.z.floor() as i32);
+            if matches!(block_at(below), Some(material) if !material.is_solid()) {
+                let floor = below.y as f32;
+                tnt.pos.y = (tnt.pos.y - FALL_SPEED * seconds).max(floor);
+            }
+
+            if let Some(node) = scene.node_mut(tnt.node) {
+                node.set_transform(tnt_transform(tnt.pos, tnt.fuse));
+                node.set_color(if tnt.is_flashing() { FLASH_COLOR } else { TNT_COLOR });
+            }
+        }
+        self.primed.retain(|tnt| tnt.fuse > 0.0);
+        exploded
+    }
+
+    /// Removes all primed TNT from the world
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the TNT is shown in
+    pub fn clear(&mut self, scene: &mut Scene) {
+        for tnt in self.primed.drain(..) {
+            scene.remove(tnt.node);
+        }
+    }
+}
+
+/// Returns the transform of the node primed TNT is shown
+/// with, which swells shortly before it explodes
+///
+/// # Arguments
+///
+/// * `pos` - The position of the bottom center of the TNT
+/// * `fuse` - The time in seconds until the TNT explodes
+fn tnt_transform(pos: Vector3<f32>, fuse: f32) -> Matrix4<f32> {
+    let swell = 1.0 + 0.15 * (1.0 - fuse / FLASH_INTERVAL).max(0.0);
+    Matrix4::from_translation(pos)
+        * Matrix4::from_scale(swell)
+        * Matrix4::from_translation(Vector3::new(-0.5, 0.0, -0.5))
+}
+
+/// Explosion
+///
+/// The blocks an explosion blew up
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explosion {
+    /// The center of the explosion
+    pub center: Vector3<f32>,
+    /// The radius of the explosion in blocks
+    pub radius: f32,
+    /// The blocks which have been destroyed
+    pub destroyed: Vec<(Vector3<i32>, Material)>,
+    /// The TNT blocks which have been set off
+    pub primed: Vec<Vector3<i32>>,
+}
+
+/// Lights the TNT block at the given position, which
+/// turns into primed TNT. Returns whether it has been lit.
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `pos` - The position of the TNT block
+/// * `fuse` - The time in seconds until the TNT explodes
+pub fn prime(world: &mut World, pos: Vector3<i32>, fuse: f32) -> bool {
+    if world.block(pos) != Some(Material::Tnt) || world.set_block(pos, Material::Air).is_none() {
+        return false;
+    }
+    let pos = pos.cast::<f32>().unwrap() + Vector3::new(0.5, 0.0, 0.5);
+    world.spawn_primed_tnt(pos, fuse);
+    true
+}
+
+/// Blows up the blocks within the radius around the center,
+/// except for the ones harder than `BLAST_RESISTANCE`, fluids
+/// and the spared ones. TNT caught in the blast is primed with
+/// a short fuse. Blown up blocks don't drop items.
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `center` - The center of the explosion
+/// * `radius` - The radius of the explosion in blocks
+/// * `spared` - Returns whether a block is protected from explosions
+pub fn explode<F>(world: &mut World, center: Vector3<f32>, radius: f32, spared: F) -> Explosion
+    where F: Fn(Vector3<i32>) -> bool
+{
+    let mut explosion = Explosion {
+        center,
+        radius,
+        destroyed: Vec::new(),
+        primed: Vec::new(),
+    };
+    let reach = radius.ceil() as i32;
+    let origin = Vector3::new(center.x.floor() as i32, center.y.floor() as i32, center.z.floor() as i32);
+    for x in -reach..=reach {
+        for y in -reach..=reach {
+            for z in -reach..=reach {
+                let pos = origin + Vector3::new(x, y, z);
+                let block_center = pos.cast::<f32>().unwrap() + Vector3::new(0.5, 0.5, 0.5);
+                if (block_center - center).magnitude() > radius || spared(pos) {
+                    continue;
+                }
+                match world.block(pos) {
+                    Some(Material::Tnt) if prime(world, pos, CHAINED_FUSE_TIME) => explosion.primed.push(pos),
+                    Some(block) if !matches!(block, Material::Air | Material::Tnt) && !block.is_fluid()
+                        && block.data().hardness() < BLAST_RESISTANCE => {
+                        world.set_block(pos, Material::Air);
+                        explosion.destroyed.push((pos, block));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    explosion
+}
+
+/// Returns the damage an explosion deals at a position, which
+/// falls off towards the edge of the explosion
+///
+/// # Arguments
+///
+/// * `explosion` - The explosion
+/// * `pos` - The position of the entity
+/// * `max_damage` - The damage at the center of the explosion
+pub fn damage_at(explosion: &Explosion, pos: Vector3<f32>, max_damage: f32) -> f32 {
+    let distance = (pos - explosion.center).magnitude();
+    (max_damage * (1.0 - distance / explosion.radius)).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::terrain_generator::FlatTerrainGen;
+    use crate::world::time::SkySettings;
+    use cgmath::Vector2;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// A world whose blocks below `y = 0` are stone
+    fn ground(pos: Vector3<i32>) -> Option<Material> {
+        Some(if pos.y < 0 { Material::Stone } else { Material::Air })
+    }
+
+    #[test]
+    fn primed_tnt_falls_and_explodes_once_its_fuse_burned_down() {
+        let mut scene = Scene::new();
+        let mut tnt = PrimedTntEntities::default();
+        tnt.spawn(&mut scene, Vector3::new(0.5, 3.0, 0.5), 1.0);
+        assert_eq!(scene.instances().len(), 1);
+
+        for _ in 0..29 {
+            assert!(tnt.update(&mut scene, 1.0 / 30.0, ground).is_empty());
+        }
+        assert_eq!(tnt.primed()[0].pos().y, 0.0);
+
+        let exploded = tnt.update(&mut scene, 1.0 / 30.0 + 0.001, ground);
+        assert_eq!(exploded, vec![Vector3::new(0.5, 0.5, 0.5)]);
+        assert!(tnt.primed().is_empty());
+        assert!(scene.instances().is_empty());
+    }
+
+    #[test]
+    fn fuse_flashes() {
+        let mut scene = Scene::new();
+        let mut tnt = PrimedTntEntities::default();
+        tnt.spawn(&mut scene, Vector3::new(0.5, 0.0, 0.5), 1.0);
+
+        let mut colors = Vec::new();
+        for _ in 0..4 {
+            tnt.update(&mut scene, FLASH_INTERVAL / 2.0, ground);
+            colors.push(scene.instances()[0].color);
+        }
+        assert!(colors.contains(&TNT_COLOR));
+        assert!(colors.contains(&FLASH_COLOR));
+    }
+
+    #[test]
+    fn explosions_set_off_the_tnt_around_them() {
+        let mut world = World::with_generator(FlatTerrainGen {}, SkySettings::default());
+        world.load_chunk(&Vector2::new(0, 0));
+        let start = Instant::now();
+        while !world.chunks()[0].is_generated() {
+            assert!(start.elapsed() < Duration::from_secs(10), "the chunk hasn't been generated");
+            thread::yield_now();
+        }
+        let (first, second) = (Vector3::new(8, 5, 8), Vector3::new(11, 5, 8));
+        let obsidian = Vector3::new(8, 5, 10);
+        world.set_block(first, Material::Tnt);
+        world.set_block(second, Material::Tnt);
+        world.set_block(obsidian, Material::Obsidian);
+        // The blocks in front of the TNT are protected
+        let spared = |pos: Vector3<i32>| pos.z < 6;
+
+        assert!(prime(&mut world, first, 1.0));
+        assert!(!prime(&mut world, first, 1.0));
+        assert_eq!(world.block(first), Some(Material::Air));
+        assert!(world.update_tnt(0.5, spared).is_empty());
+
+        let explosions = world.update_tnt(0.6, spared);
+        assert_eq!(explosions.len(), 1);
+        assert_eq!(explosions[0].primed, vec![second]);
+        assert!(explosions[0].destroyed.contains(&(Vector3::new(8, 4, 8), Material::Grass)));
+        assert_eq!(world.block(Vector3::new(8, 4, 8)), Some(Material::Air));
+        assert_eq!(world.block(obsidian), Some(Material::Obsidian));
+        assert_eq!(world.block(Vector3::new(8, 4, 5)), Some(Material::Grass));
+        assert_eq!(world.primed_tnt().primed().len(), 1);
+
+        // The TNT set off falls into the crater before it explodes
+        assert!(world.update_tnt(CHAINED_FUSE_TIME / 2.0, spared).is_empty());
+        let explosions = world.update_tnt(CHAINED_FUSE_TIME / 2.0, spared);
+        assert_eq!(explosions.len(), 1);
+        assert!(explosions[0].center.y < 5.5);
+        assert!(world.primed_tnt().primed().is_empty());
+    }
+
+    #[test]
+    fn damage_falls_off_with_distance() {
+        let explosion = Explosion {
+            center: Vector3::new(0.0, 0.0, 0.0),
+            radius: 4.0,
+            destroyed: Vec::new(),
+            primed: Vec::new(),
+        };
+        assert_eq!(damage_at(&explosion, Vector3::new(0.0, 0.0, 0.0), 10.0), 10.0);
+        assert_eq!(damage_at(&explosion, Vector3::new(2.0, 0.0, 0.0), 10.0), 5.0);
+        assert_eq!(damage_at(&explosion, Vector3::new(0.0, 6.0, 0.0), 10.0), 0.0);
+    }
+}
@@ -0,0 +1,259 @@
+//! Types to let fire spread to flammable blocks and burn them
+//! away, e.g. fire lit by flint and steel or by lava
+
+use crate::scripting::{self, ScriptEngine};
+use crate::world::block::Material;
+use crate::world::chunk::CHUNK_HEIGHT;
+use crate::world::explosion::{self, FUSE_TIME};
+use crate::world::tags::Tags;
+use crate::world::ticks::{ScheduledTick, TickAction, TickRng};
+use crate::world::World;
+use cgmath::Vector3;
+use mlua::Table;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The name of flint and steel, which lights fires
+pub const FLINT_AND_STEEL: &str = "flint_and_steel";
+/// The updates between two ticks of a fire block
+pub const FIRE_TICK_DELAY: u64 = 20;
+/// The chance a fire without flammable blocks next
+/// to it burns out per tick
+const BURN_OUT_CHANCE: f64 = 0.3;
+
+/// The offsets of the blocks a block touches
+const NEIGHBOURS: [Vector3<i32>; 6] = [
+    Vector3::new(1, 0, 0),
+    Vector3::new(-1, 0, 0),
+    Vector3::new(0, 1, 0),
+    Vector3::new(0, -1, 0),
+    Vector3::new(0, 0, 1),
+    Vector3::new(0, 0, -1),
+];
+
+/// Flammability
+///
+/// How easily a block catches fire, as chances per tick
+/// of a fire next to it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Flammability {
+    /// The chance fire spreads to the air next to the block
+    pub spread: f64,
+    /// The chance the block burns away, which leaves fire
+    pub burn: f64,
+}
+
+/// FlammableBlocks
+///
+/// The flammability of the blocks which catch fire.
+/// Other blocks never burn.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FlammableBlocks {
+    /// The flammability by the material of the block
+    blocks: HashMap<Material, Flammability>,
+}
+
+impl FlammableBlocks {
+    /// Returns the flammability of a block, if it's flammable
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    pub fn get(&self, material: Material) -> Option<Flammability> {
+        self.blocks.get(&material).copied()
+    }
+
+    /// Sets the flammability of a block
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    /// * `flammability` - The flammability of the block
+    pub fn set(&mut self, material: Material, flammability: Flammability) {
+        self.blocks.insert(material, flammability);
+    }
+
+    /// Returns the chance fire spreads to a block, which is
+    /// the highest one of the flammable blocks next to it
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world
+    /// * `pos` - The position of the block
+    fn spread_chance(&self, world: &World, pos: Vector3<i32>) -> f64 {
+        NEIGHBOURS.iter()
+            .filter_map(|offset| self.get(world.block(pos + offset)?))
+            .map(|flammability| flammability.spread)
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Fire
+///
+/// The flammability of the blocks defined by scripts through
+/// the `fire` API. Blocks could be given by a tag, e.g.
+///
+/// ```lua
+/// fire.flammable {
+///     block = "#plants",
+///     -- The chance fire spreads next to the block per tick
+///     spread = 0.6,
+///     -- The chance the block burns away per tick
+///     burn = 0.3,
+/// }
+/// ```
+pub struct Fire {
+    /// The defined flammability, shared with the Lua API
+    defined: Arc<Mutex<FlammableBlocks>>,
+}
+
+impl Fire {
+    /// Creates a new flammability registry and registers
+    /// its API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `tags` - The tags definitions could refer to
+    pub fn new(engine: &ScriptEngine, tags: &Tags) -> mlua::Result<Self> {
+        let defined = Arc::new(Mutex::new(FlammableBlocks::default()));
+
+        let lua = engine.lua();
+        let api = scripting::api_table(lua, "fire")?;
+        let flammable = defined.clone();
+        let tags = tags.clone();
+        api.set("flammable", lua.create_function(move |_, def: Table| {
+            let flammability = Flammability {
+                spread: def.get::<_, Option<f64>>("spread")?.unwrap_or(0.0).clamp(0.0, 1.0),
+                burn: def.get::<_, Option<f64>>("burn")?.unwrap_or(0.0).clamp(0.0, 1.0),
+            };
+            let mut blocks = flammable.lock().unwrap();
+            for material in tags.materials(&def.get::<_, String>("block")?)? {
+                blocks.set(material, flammability);
+            }
+            Ok(())
+        })?)?;
+
+        Ok(Self {
+            defined,
+        })
+    }
+
+    /// Returns the flammable blocks
+    pub fn flammable(&self) -> FlammableBlocks {
+        self.defined.lock().unwrap().clone()
+    }
+}
+
+/// Lights a fire in the air at the given position.
+/// Returns whether the fire has been lit.
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `pos` - The position of the fire
+pub fn ignite(world: &mut World, pos: Vector3<i32>) -> bool {
+    world.block(pos) == Some(Material::Air) && world.set_block(pos, Material::Fire).is_some()
+}
+
+/// Schedules the next tick of a fire block
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `pos` - The position of the fire
+pub fn schedule_tick(world: &mut World, pos: Vector3<i32>) {
+    world.scheduled_mut().schedule(FIRE_TICK_DELAY, ScheduledTick {
+        pos,
+        action: TickAction::FireTick,
+    });
+}
+
+/// Returns whether no solid block is above a block,
+/// so rain falls onto it
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `pos` - The position of the block
+fn is_exposed(world: &World, pos: Vector3<i32>) -> bool {
+    (pos.y + 1..CHUNK_HEIGHT as i32)
+        .all(|y| !matches!(world.block(Vector3::new(pos.x, y, pos.z)), Some(block) if block.is_solid()))
+}
+
+/// Ticks a fire block. Rain puts out fire under the open sky.
+/// Otherwise, the flammable blocks next to the fire could burn
+/// away and the fire could spread to the air next to flammable
+/// blocks, and TNT next to it is lit. Fire without flammable
+/// blocks next to it burns out after a while. Returns whether
+/// the block is still burning.
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `pos` - The position of the fire
+/// * `flammable` - The flammable blocks
+/// * `rng` - The generator rolling the chances
+pub(crate) fn tick(world: &mut World, pos: Vector3<i32>, flammable: &FlammableBlocks, rng: &mut TickRng) -> bool {
+    match world.block(pos) {
+        Some(Material::Fire) => {}
+        // The fire burns on once its chunk is loaded again
+        None => {
+            schedule_tick(world, pos);
+            return false;
+        }
+        Some(_) => return false,
+    }
+    if world.is_raining() && is_exposed(world, pos) {
+        world.set_block(pos, Material::Air);
+        return false;
+    }
+
+    let mut fuelled = false;
+    for offset in NEIGHBOURS.iter() {
+        let neighbour = pos + offset;
+        match world.block(neighbour) {
+            Some(Material::Air) if rng.unit() < flammable.spread_chance(world, neighbour) => {
+                ignite(world, neighbour);
+            }
+            Some(Material::Tnt) => {
+                explosion::prime(world, neighbour, FUSE_TIME);
+            }
+            Some(block) => if let Some(flammability) = flammable.get(block) {
+                fuelled = true;
+                if rng.unit() < flammability.burn {
+                    world.set_block(neighbour, Material::Fire);
+                }
+            },
+            None => {}
+        }
+    }
+
+    if !fuelled && rng.unit() < BURN_OUT_CHANCE {
+        world.set_block(pos, Material::Air);
+        return false;
+    }
+    schedule_tick(world, pos);
+    true
+}
+
+/// Sets fire to the air next to a lava block which
+/// touches flammable blocks
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `pos` - The position of the lava
+/// * `flammable` - The flammable blocks
+/// * `rng` - The generator rolling the chances
+pub(crate) fn ignite_near_lava(world: &mut World, pos: Vector3<i32>, flammable: &FlammableBlocks, rng: &mut TickRng) {
+    if world.block(pos) != Some(Material::Lava) {
+        return;
+    }
+    for offset in NEIGHBOURS.iter() {
+        let neighbour = pos + offset;
+        if world.block(neighbour) == Some(Material::Air) && rng.unit() < flammable.spread_chance(world, neighbour) {
+            ignite(world, neighbour);
+        }
+    }
+}
@@ -0,0 +1,350 @@
+//! Types to let fluids flow from their sources and react
+//! with the blocks they touch, e.g. lava turning into
+//! obsidian where water touches it
+
+use crate::scripting::{self, ScriptEngine};
+use crate::world::block::Material;
+use crate::world::ticks::{ScheduledTick, TickAction};
+use crate::world::World;
+use cgmath::Vector3;
+use mlua::Table;
+use std::sync::{Arc, Mutex};
+
+/// The block state of a fluid block which is a source. Flowing
+/// fluid blocks have a state above, which is their distance to
+/// the source they flow from.
+pub const SOURCE_STATE: u8 = 0;
+
+/// The offsets of the blocks a block touches
+const NEIGHBOURS: [Vector3<i32>; 6] = [
+    Vector3::new(1, 0, 0),
+    Vector3::new(-1, 0, 0),
+    Vector3::new(0, 1, 0),
+    Vector3::new(0, -1, 0),
+    Vector3::new(0, 0, 1),
+    Vector3::new(0, 0, -1),
+];
+/// The offsets of the blocks a fluid spreads to sideways
+const SIDES: [Vector3<i32>; 4] = [
+    Vector3::new(1, 0, 0),
+    Vector3::new(-1, 0, 0),
+    Vector3::new(0, 0, 1),
+    Vector3::new(0, 0, -1),
+];
+/// The offset of the block above another one
+const UP: Vector3<i32> = Vector3::new(0, 1, 0);
+
+/// FluidFlow
+///
+/// How fast and how far a fluid flows from its source
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FluidFlow {
+    /// The updates after a change next to a fluid
+    /// block until it flows on or reacts
+    pub delay: u64,
+    /// The distance in blocks the fluid spreads
+    /// sideways from its source
+    pub range: u8,
+}
+
+/// Returns how a fluid flows. Lava flows a lot slower
+/// than water and doesn't spread as far.
+///
+/// # Arguments
+///
+/// * `fluid` - The material of the fluid
+pub fn flow(fluid: Material) -> FluidFlow {
+    match fluid {
+        Material::Lava => FluidFlow { delay: 30, range: 3 },
+        _ => FluidFlow { delay: 5, range: 7 },
+    }
+}
+
+/// Returns whether the block state of a fluid block
+/// is the one of a source
+///
+/// # Arguments
+///
+/// * `state` - The block state
+pub fn is_source(state: u8) -> bool {
+    state == SOURCE_STATE
+}
+
+/// FluidInteraction
+///
+/// The block a fluid block turns into when it touches
+/// another block, depending on whether it's a source
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FluidInteraction {
+    /// The fluid which reacts
+    pub fluid: Material,
+    /// The block the fluid reacts with
+    pub touching: Material,
+    /// The block a source of the fluid turns into
+    pub source: Material,
+    /// The block a flowing fluid block turns into
+    pub flowing: Material,
+}
+
+/// FluidInteractions
+///
+/// The interactions of all fluids. The first interaction
+/// defined for a fluid whose block touches it wins.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FluidInteractions {
+    /// The interactions in the order they have been defined
+    interactions: Vec<FluidInteraction>,
+}
+
+impl FluidInteractions {
+    /// Adds an interaction, replacing the one defined for
+    /// the same fluid and block before
+    ///
+    /// # Arguments
+    ///
+    /// * `interaction` - The interaction
+    pub fn add(&mut self, interaction: FluidInteraction) {
+        match self.interactions.iter_mut().find(|defined| {
+            defined.fluid == interaction.fluid && defined.touching == interaction.touching
+        }) {
+            Some(defined) => *defined = interaction,
+            None => self.interactions.push(interaction),
+        }
+    }
+
+    /// Returns the block a fluid block turns into, if it
+    /// touches a block it reacts with
+    ///
+    /// # Arguments
+    ///
+    /// * `fluid` - The material of the fluid block
+    /// * `state` - The block state of the fluid block
+    /// * `touching` - The materials of the blocks it touches
+    pub fn reaction(&self, fluid: Material, state: u8, touching: &[Material]) -> Option<Material> {
+        let interaction = self.interactions.iter()
+            .find(|interaction| interaction.fluid == fluid && touching.contains(&interaction.touching))?;
+        Some(if is_source(state) { interaction.source } else { interaction.flowing })
+    }
+}
+
+/// Fluids
+///
+/// The fluid interactions defined by scripts through the
+/// `fluids` API. The block a fluid turns into could differ
+/// between sources and flowing fluid blocks, e.g.
+///
+/// ```lua
+/// fluids.interaction {
+///     fluid = "lava",
+///     touching = "water",
+///     -- The block still lava turns into
+///     source = "obsidian",
+///     -- The block flowing lava turns into, which is
+///     -- the one of the source if it's left out
+///     flowing = "stone",
+/// }
+/// ```
+///
+/// A fluid reacts a few updates after a block next to it
+/// changed, before it flows on, see `update`.
+pub struct Fluids {
+    /// The defined interactions, shared with the Lua API
+    defined: Arc<Mutex<FluidInteractions>>,
+}
+
+impl Fluids {
+    /// Creates a new fluid interaction registry and registers
+    /// its API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let defined = Arc::new(Mutex::new(FluidInteractions::default()));
+
+        let lua = engine.lua();
+        let api = scripting::api_table(lua, "fluids")?;
+        let interactions = defined.clone();
+        api.set("interaction", lua.create_function(move |_, def: Table| {
+            let interaction = interaction_from_table(def)?;
+            interactions.lock().unwrap().add(interaction);
+            Ok(())
+        })?)?;
+
+        Ok(Self {
+            defined,
+        })
+    }
+
+    /// Returns the defined interactions
+    pub fn interactions(&self) -> FluidInteractions {
+        self.defined.lock().unwrap().clone()
+    }
+}
+
+/// Reads a fluid interaction from a Lua table
+///
+/// # Arguments
+///
+/// * `def` - The Lua table
+fn interaction_from_table(def: Table) -> mlua::Result<FluidInteraction> {
+    let material = |key: &str, name: String| Material::from_name(&name).ok_or_else(|| {
+        mlua::Error::RuntimeError(format!("unknown block {} of {}", name, key))
+    });
+
+    let fluid = material("fluid", def.get("fluid")?)?;
+    if !fluid.is_fluid() {
+        return Err(mlua::Error::RuntimeError(format!("{} isn't a fluid", fluid.name())));
+    }
+    let touching = material("touching", def.get("touching")?)?;
+    let source = material("source", def.get("source")?)?;
+    let flowing = match def.get::<_, Option<String>>("flowing")? {
+        Some(name) => material("flowing", name)?,
+        None => source,
+    };
+    Ok(FluidInteraction { fluid, touching, source, flowing })
+}
+
+/// Schedules updates of the fluid blocks at and next to a
+/// changed block, so they react with their new neighbours
+/// and flow on once their fluid's delay passed
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `pos` - The position of the changed block
+pub fn schedule_updates(world: &mut World, pos: Vector3<i32>) {
+    for pos in std::iter::once(pos).chain(NEIGHBOURS.iter().map(|offset| pos + offset)) {
+        match world.block(pos) {
+            Some(block) if block.is_fluid() => world.scheduled_mut().schedule(flow(block).delay, ScheduledTick {
+                pos,
+                action: TickAction::FluidUpdate,
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// Updates a fluid block. It reacts with the blocks it touches
+/// first. Otherwise, a flowing block takes the distance of the
+/// closest block it's fed by, or dries up without one. Fluids
+/// fall into the air below them, or spread sideways into the air
+/// next to them up to the range of the fluid.
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `pos` - The position of the fluid block
+/// * `interactions` - The fluid interactions
+pub fn update(world: &mut World, pos: Vector3<i32>, interactions: &FluidInteractions) {
+    if react(world, pos, interactions) {
+        return;
+    }
+    let fluid = match world.block(pos) {
+        Some(block) if block.is_fluid() => block,
+        _ => return,
+    };
+    let range = flow(fluid).range;
+
+    let mut state = world.block_state(pos).unwrap_or(SOURCE_STATE);
+    if !is_source(state) {
+        // Falling fluid spreads as if it were next to a source
+        let fed = if world.block(pos + UP) == Some(fluid) {
+            Some(1)
+        } else {
+            SIDES.iter()
+                .filter(|offset| world.block(pos + *offset) == Some(fluid))
+                .filter_map(|offset| world.block_state(pos + offset))
+                .min()
+                .map(|distance| distance.saturating_add(1))
+        };
+        match fed {
+            Some(distance) if distance <= range => {
+                if distance != state {
+                    world.set_block_state(pos, distance);
+                    // The blocks fed by this one need another distance
+                    schedule_updates(world, pos);
+                    state = distance;
+                }
+            }
+            _ => {
+                world.set_block(pos, Material::Air);
+                return;
+            }
+        }
+    }
+
+    let below = world.block(pos - UP);
+    if below == Some(Material::Air) {
+        place(world, pos - UP, fluid, 1);
+    } else if below.is_some() && below != Some(fluid) && state < range {
+        for offset in SIDES.iter() {
+            if world.block(pos + offset) == Some(Material::Air) {
+                place(world, pos + offset, fluid, state + 1);
+            }
+        }
+    }
+}
+
+/// Places a flowing fluid block
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `pos` - The position of the block
+/// * `fluid` - The material of the fluid
+/// * `distance` - The distance to the source it flows from
+fn place(world: &mut World, pos: Vector3<i32>, fluid: Material, distance: u8) {
+    if world.set_block(pos, fluid).is_some() {
+        world.set_block_state(pos, distance);
+    }
+}
+
+/// Lets a fluid block react with the blocks it touches.
+/// Returns whether the block has been replaced.
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `pos` - The position of the fluid block
+/// * `interactions` - The fluid interactions
+fn react(world: &mut World, pos: Vector3<i32>, interactions: &FluidInteractions) -> bool {
+    let fluid = match world.block(pos) {
+        Some(block) if block.is_fluid() => block,
+        _ => return false,
+    };
+    let state = world.block_state(pos).unwrap_or(SOURCE_STATE);
+    let touching: Vec<Material> = NEIGHBOURS.iter().filter_map(|offset| world.block(pos + offset)).collect();
+    match interactions.reaction(fluid, state, &touching) {
+        Some(result) => world.set_block(pos, result).is_some(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fluids_react_by_their_flow_state() {
+        let engine = ScriptEngine::new();
+        let fluids = Fluids::new(&engine).unwrap();
+        engine.exec("test", r#"
+            fluids.interaction { fluid = "lava", touching = "water", source = "obsidian", flowing = "stone" }
+            fluids.interaction { fluid = "water", touching = "ice", source = "ice" }
+        "#).unwrap();
+        assert!(engine.exec("test", r#"fluids.interaction { fluid = "stone", touching = "water", source = "sand" }"#).is_err());
+        assert!(engine.exec("test", r#"fluids.interaction { fluid = "lava", touching = "cheese", source = "sand" }"#).is_err());
+
+        let interactions = fluids.interactions();
+        let touching = [Material::Stone, Material::Water];
+        assert_eq!(interactions.reaction(Material::Lava, SOURCE_STATE, &touching), Some(Material::Obsidian));
+        assert_eq!(interactions.reaction(Material::Lava, 3, &touching), Some(Material::Stone));
+        assert_eq!(interactions.reaction(Material::Lava, SOURCE_STATE, &[Material::Stone]), None);
+        assert_eq!(interactions.reaction(Material::Water, 2, &[Material::Ice]), Some(Material::Ice));
+        assert_eq!(interactions.reaction(Material::Water, SOURCE_STATE, &[Material::Lava]), None);
+
+        engine.exec("test", r#"fluids.interaction { fluid = "lava", touching = "water", source = "glass" }"#).unwrap();
+        assert_eq!(fluids.interactions().reaction(Material::Lava, 3, &touching), Some(Material::Glass));
+    }
+}
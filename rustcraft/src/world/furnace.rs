@@ -0,0 +1,557 @@
+//! Types to smelt items in furnaces, which burn fuel
+//! and glow while they're smelting
+
+use crate::graphics::backend::GlBackend;
+use crate::graphics::font::{self, GLYPH_SIZE};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use crate::scripting::ScriptEngine;
+use crate::world::block::Material;
+use crate::world::items;
+use crate::world::tags::Tags;
+use cgmath::Vector4;
+use mlua::Table;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// The largest amount of items a slot of a furnace holds
+pub const MAX_STACK: u64 = 64;
+
+/// The width of the furnace screen
+const SCREEN_WIDTH: f32 = 150.0;
+/// The distance of the contents to the edges of the screen
+const SCREEN_PADDING: f32 = 8.0;
+/// The edge length of a slot
+const SLOT_SIZE: f32 = 22.0;
+/// The length of the progress arrow
+const ARROW_LENGTH: f32 = 40.0;
+
+/// SmeltingRecipe
+///
+/// The item an item is smelted into
+#[derive(Clone, Debug, PartialEq)]
+pub struct SmeltingRecipe {
+    /// The name of the resulting item
+    pub result: String,
+    /// The amount of resulting items per smelted item
+    pub count: u64,
+    /// The time in seconds it takes to smelt an item
+    pub time: f32,
+}
+
+/// Smelting
+///
+/// The smelting recipes and fuels scripts define through
+/// the `smelting` API, e.g.
+///
+/// ```lua
+/// smelting.define { input = "cobblestone", result = "stone", time = 10 }
+/// smelting.define { input = "#ores", result = "ingot", count = 2, time = 20 }
+/// smelting.fuel("#plants", 5)
+/// ```
+///
+/// The count defaults to a single item. Fuels burn for the
+/// given time in seconds. Inputs and fuels given by a tag are
+/// the ones of all of its items, so the tag needs to be added
+/// by a script loaded before. Recipes and fuels defined again
+/// replace their previous definition.
+#[derive(Clone, Default)]
+pub struct Smelting {
+    /// The recipes by the name of their input, shared
+    /// with the Lua API
+    recipes: Arc<Mutex<HashMap<String, SmeltingRecipe>>>,
+    /// The burn times of the fuels in seconds by their
+    /// name, shared with the Lua API
+    fuels: Arc<Mutex<HashMap<String, f32>>>,
+}
+
+impl Smelting {
+    /// Creates a new smelting registry and registers its
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `tags` - The tags definitions could refer to
+    pub fn new(engine: &ScriptEngine, tags: &Tags) -> mlua::Result<Self> {
+        let registry = Self::default();
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let recipes = registry.recipes.clone();
+        let recipe_tags = tags.clone();
+        api.set("define", lua.create_function(move |_, def: Table| {
+            let input: String = def.get("input")?;
+            let recipe = SmeltingRecipe {
+                result: def.get("result")?,
+                count: def.get::<_, Option<u64>>("count")?.unwrap_or(1),
+                time: def.get("time")?,
+            };
+            if recipe.count == 0 || recipe.time <= 0.0 {
+                return Err(mlua::Error::RuntimeError(format!("smelting {} needs a count and a time", input)));
+            }
+            let mut recipes = recipes.lock().unwrap();
+            for name in recipe_tags.items(&input)? {
+                recipes.insert(name, recipe.clone());
+            }
+            Ok(())
+        })?)?;
+        let fuels = registry.fuels.clone();
+        let tags = tags.clone();
+        api.set("fuel", lua.create_function(move |_, (name, time): (String, f32)| {
+            if time <= 0.0 {
+                return Err(mlua::Error::RuntimeError(format!("the fuel {} needs a burn time", name)));
+            }
+            let mut fuels = fuels.lock().unwrap();
+            for name in tags.items(&name)? {
+                fuels.insert(name, time);
+            }
+            Ok(())
+        })?)?;
+        lua.globals().set("smelting", api)?;
+
+        Ok(registry)
+    }
+
+    /// Returns the recipe smelting an item
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - The name of the item
+    pub fn recipe(&self, item: &str) -> Option<SmeltingRecipe> {
+        self.recipes.lock().unwrap().get(item).cloned()
+    }
+
+    /// Returns the time in seconds an item burns for, or
+    /// `None` if it isn't a fuel
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - The name of the item
+    pub fn burn_time(&self, item: &str) -> Option<f32> {
+        self.fuels.lock().unwrap().get(item).copied()
+    }
+}
+
+/// FurnaceSlot
+///
+/// The slots of a furnace
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FurnaceSlot {
+    /// The items which are smelted
+    Input,
+    /// The items which are burnt
+    Fuel,
+    /// The smelted items
+    Output,
+}
+
+impl FurnaceSlot {
+    /// Returns the slot chosen by a number key on the
+    /// furnace screen, counted from one
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The number
+    pub fn from_number(number: usize) -> Option<Self> {
+        match number {
+            1 => Some(FurnaceSlot::Input),
+            2 => Some(FurnaceSlot::Fuel),
+            3 => Some(FurnaceSlot::Output),
+            _ => None,
+        }
+    }
+}
+
+/// Furnace
+///
+/// The block entity of a furnace. A furnace smelts one item
+/// of its input at a time while it burns fuel, which it only
+/// takes once there's an item it could smelt. Smelting is
+/// paused while the output is full and loses its progress
+/// while the furnace runs out of fuel.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Furnace {
+    /// The items which are smelted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    input: Option<(String, u64)>,
+    /// The items which are burnt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fuel: Option<(String, u64)>,
+    /// The smelted items
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output: Option<(String, u64)>,
+    /// How far the current item has been smelted, in
+    /// the range `[0, 1]`
+    progress: f32,
+    /// The time in seconds the burning fuel burns for
+    burn_time: f32,
+    /// The time in seconds until the burning fuel burnt out
+    burning: f32,
+}
+
+impl Furnace {
+    /// Returns whether the furnace burns fuel
+    pub fn is_lit(&self) -> bool {
+        self.burning > 0.0
+    }
+
+    /// Returns the items of a slot
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The slot
+    pub fn slot(&self, slot: FurnaceSlot) -> Option<&(String, u64)> {
+        match slot {
+            FurnaceSlot::Input => self.input.as_ref(),
+            FurnaceSlot::Fuel => self.fuel.as_ref(),
+            FurnaceSlot::Output => self.output.as_ref(),
+        }
+    }
+
+    /// Returns how far the current item has been smelted,
+    /// in the range `[0, 1]`
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+
+    /// Returns how much of the burning fuel is left,
+    /// in the range `[0, 1]`
+    pub fn flame(&self) -> f32 {
+        if self.burn_time > 0.0 { (self.burning / self.burn_time).max(0.0) } else { 0.0 }
+    }
+
+    /// Adds items to the input or the fuel slot and returns
+    /// the amount which has been added, which is none if the
+    /// slot holds other items or they can't go into the slot
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The input or the fuel slot
+    /// * `item` - The name of the items
+    /// * `count` - The amount of items
+    /// * `smelting` - The smelting recipes and fuels
+    pub fn insert(&mut self, slot: FurnaceSlot, item: &str, count: u64, smelting: &Smelting) -> u64 {
+        let (stack, fits) = match slot {
+            FurnaceSlot::Input => (&mut self.input, smelting.recipe(item).is_some()),
+            FurnaceSlot::Fuel => (&mut self.fuel, smelting.burn_time(item).is_some()),
+            FurnaceSlot::Output => return 0,
+        };
+        if !fits || count == 0 {
+            return 0;
+        }
+        let (name, held) = stack.get_or_insert_with(|| (item.to_string(), 0));
+        if name != item {
+            return 0;
+        }
+        let added = count.min(MAX_STACK - *held);
+        *held += added;
+        added
+    }
+
+    /// Takes all items out of a slot
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The slot
+    pub fn take(&mut self, slot: FurnaceSlot) -> Option<(String, u64)> {
+        match slot {
+            FurnaceSlot::Input => {
+                self.progress = 0.0;
+                self.input.take()
+            }
+            FurnaceSlot::Fuel => self.fuel.take(),
+            FurnaceSlot::Output => self.output.take(),
+        }
+    }
+
+    /// Takes a single item out of a slot
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The slot
+    pub fn take_one(&mut self, slot: FurnaceSlot) -> Option<String> {
+        let stack = match slot {
+            FurnaceSlot::Input => &mut self.input,
+            FurnaceSlot::Fuel => &mut self.fuel,
+            FurnaceSlot::Output => &mut self.output,
+        };
+        let item = stack.as_ref()?.0.clone();
+        take_one(stack);
+        Some(item)
+    }
+
+    /// Takes all items out of the furnace, e.g. once it's broken
+    pub fn take_all(&mut self) -> Vec<(String, u64)> {
+        [FurnaceSlot::Input, FurnaceSlot::Fuel, FurnaceSlot::Output].iter()
+            .filter_map(|&slot| self.take(slot))
+            .collect()
+    }
+
+    /// Advances smelting and burning the fuel
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    /// * `smelting` - The smelting recipes and fuels
+    pub fn tick(&mut self, seconds: f32, smelting: &Smelting) {
+        let recipe = self.input.as_ref()
+            .and_then(|(item, _)| smelting.recipe(item))
+            .filter(|recipe| match &self.output {
+                Some((item, count)) => *item == recipe.result && count + recipe.count <= MAX_STACK,
+                None => true,
+            });
+
+        if !self.is_lit() && recipe.is_some() {
+            let burn_time = self.fuel.as_ref().and_then(|(item, _)| smelting.burn_time(item));
+            if let Some(burn_time) = burn_time {
+                take_one(&mut self.fuel);
+                self.burn_time = burn_time;
+                self.burning = burn_time;
+            }
+        }
+
+        if !self.is_lit() {
+            self.progress = 0.0;
+            return;
+        }
+        self.burning -= seconds;
+        let recipe = match recipe {
+            Some(recipe) => recipe,
+            None => {
+                self.progress = 0.0;
+                return;
+            }
+        };
+        self.progress += seconds / recipe.time;
+        if self.progress >= 1.0 {
+            self.progress = 0.0;
+            take_one(&mut self.input);
+            self.output.get_or_insert_with(|| (recipe.result.clone(), 0)).1 += recipe.count;
+        }
+    }
+}
+
+/// Takes a single item out of a slot, which
+/// is emptied with its last item
+///
+/// # Arguments
+///
+/// * `stack` - The items of the slot
+fn take_one(stack: &mut Option<(String, u64)>) {
+    if let Some((_, count)) = stack.as_mut() {
+        *count -= 1;
+        if *count == 0 {
+            *stack = None;
+        }
+    }
+}
+
+/// Returns the material a furnace is shown as
+///
+/// # Arguments
+///
+/// * `lit` - Whether the furnace burns fuel
+pub fn furnace_material(lit: bool) -> Material {
+    if lit { Material::LitFurnace } else { Material::Furnace }
+}
+
+/// Returns the collected item the player puts into a slot of
+/// a furnace, which is the one the slot already holds or else
+/// the one the player collected the most of that fits
+///
+/// # Arguments
+///
+/// * `furnace` - The furnace
+/// * `slot` - The input or the fuel slot
+/// * `items` - The amount of each collected item
+/// * `smelting` - The smelting recipes and fuels
+pub fn item_for_slot(furnace: &Furnace, slot: FurnaceSlot, items: &BTreeMap<String, u64>, smelting: &Smelting) -> Option<String> {
+    let fits = |item: &str| match slot {
+        FurnaceSlot::Input => smelting.recipe(item).is_some(),
+        FurnaceSlot::Fuel => smelting.burn_time(item).is_some(),
+        FurnaceSlot::Output => false,
+    };
+    if let Some((item, _)) = furnace.slot(slot) {
+        return Some(item.clone()).filter(|item| items.contains_key(item) && fits(item));
+    }
+    items.iter()
+        .filter(|(item, &count)| count > 0 && fits(item))
+        .max_by_key(|(_, &count)| count)
+        .map(|(item, _)| item.clone())
+}
+
+/// FurnaceFrame
+///
+/// A frame the furnace screen could be rendered for
+pub trait FurnaceFrame: Frame {
+    /// Returns the furnace the player opened, if any
+    fn furnace(&self) -> Option<&Furnace>;
+}
+
+/// FurnaceRenderer
+///
+/// The `FurnaceRenderer` is the render pass drawing the screen
+/// of the furnace the player opened, centered on the screen.
+/// The input slot is above the fuel slot, with a flame between
+/// them showing the fuel left, and an arrow points to the output
+/// slot, filling up while an item is smelted. The number keys
+/// of the slots are shown below them.
+pub struct FurnaceRenderer {
+    /// The painter drawing the screen
+    painter: OverlayPainter,
+}
+
+impl FurnaceRenderer {
+    /// Creates a new furnace renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+/// Adds a slot with the icon and the amount of its items
+///
+/// # Arguments
+///
+/// * `overlay` - The overlay
+/// * `min` - The upper left corner of the slot
+/// * `stack` - The items of the slot
+fn slot(overlay: &mut OverlayBuilder, min: [f32; 2], stack: Option<&(String, u64)>) {
+    let max = [min[0] + SLOT_SIZE, min[1] + SLOT_SIZE];
+    overlay.rect(min, max, Vector4::new(0.25, 0.25, 0.25, 0.9));
+    if let Some((item, count)) = stack {
+        overlay.rect([min[0] + 3.0, min[1] + 3.0], [max[0] - 3.0, max[1] - 3.0], items::item_color(item));
+        let text = count.to_string();
+        let pos = [max[0] - font::text_width(&text) as f32, max[1] - GLYPH_SIZE as f32];
+        overlay.text(pos, &text, Vector4::new(1.0, 1.0, 1.0, 1.0));
+    }
+}
+
+impl<F: FurnaceFrame> RenderPass<F> for FurnaceRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let furnace = match ctx.frame.furnace() {
+            Some(furnace) => furnace,
+            None => return,
+        };
+
+        let width = OVERLAY_HEIGHT * ctx.frame.camera().aspect_ratio();
+        let line_height = GLYPH_SIZE as f32 + SCREEN_PADDING;
+        let height = 2.0 * line_height + 3.0 * SLOT_SIZE + 2.0 * SCREEN_PADDING;
+        let min = [(width - SCREEN_WIDTH) / 2.0, (OVERLAY_HEIGHT - height) / 2.0];
+        let white = Vector4::new(1.0, 1.0, 1.0, 1.0);
+
+        let mut overlay = OverlayBuilder::default();
+        overlay.rect(min, [min[0] + SCREEN_WIDTH, min[1] + height], Vector4::new(0.0, 0.0, 0.0, 0.65));
+        let x = min[0] + SCREEN_PADDING;
+        let top = min[1] + SCREEN_PADDING;
+        overlay.text([x, top], "Furnace", Vector4::new(1.0, 0.85, 0.3, 1.0));
+
+        // The input above the flame above the fuel
+        let input = [x + SCREEN_PADDING, top + line_height];
+        let fuel = [input[0], input[1] + 2.0 * SLOT_SIZE];
+        slot(&mut overlay, input, furnace.slot(FurnaceSlot::Input));
+        slot(&mut overlay, fuel, furnace.slot(FurnaceSlot::Fuel));
+        let flame_min = [input[0] + SLOT_SIZE / 4.0, input[1] + SLOT_SIZE + 3.0];
+        let flame_max = [input[0] + 3.0 * SLOT_SIZE / 4.0, fuel[1] - 3.0];
+        overlay.rect(flame_min, flame_max, Vector4::new(0.15, 0.15, 0.15, 1.0));
+        let flame_top = flame_max[1] - (flame_max[1] - flame_min[1]) * furnace.flame();
+        overlay.rect([flame_min[0], flame_top], flame_max, Vector4::new(1.0, 0.55, 0.1, 1.0));
+
+        // The arrow filling up towards the output
+        let arrow_y = input[1] + SLOT_SIZE;
+        let arrow_x = input[0] + SLOT_SIZE + SCREEN_PADDING;
+        let shaft = [arrow_x, arrow_y - 2.0];
+        overlay.rect(shaft, [arrow_x + ARROW_LENGTH, arrow_y + 2.0], Vector4::new(0.35, 0.35, 0.35, 1.0));
+        overlay.rect(shaft, [arrow_x + ARROW_LENGTH * furnace.progress(), arrow_y + 2.0], white);
+        for i in 0..4 {
+            let step = i as f32 * 1.5;
+            let head = [arrow_x + ARROW_LENGTH + step, arrow_y - 6.0 + step];
+            let color = if furnace.progress() >= 1.0 { white } else { Vector4::new(0.35, 0.35, 0.35, 1.0) };
+            overlay.rect(head, [head[0] + 1.5, arrow_y + 6.0 - step], color);
+        }
+        let output = [arrow_x + ARROW_LENGTH + 2.0 * SCREEN_PADDING, arrow_y - SLOT_SIZE / 2.0];
+        slot(&mut overlay, output, furnace.slot(FurnaceSlot::Output));
+
+        let hint = "1 Smelt  2 Fuel  3 Take";
+        overlay.text([x, min[1] + height - line_height], hint, Vector4::new(0.55, 0.8, 1.0, 1.0));
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn smelting() -> Smelting {
+        let engine = ScriptEngine::new();
+        let tags = Tags::new(&engine).unwrap();
+        let smelting = Smelting::new(&engine, &tags).unwrap();
+        engine.exec("test", r##"
+            tags.add("#plants", { "tall_grass", "flower" })
+            smelting.define { input = "cobblestone", result = "stone", time = 2 }
+            smelting.define { input = "sand", result = "glass", count = 2, time = 1 }
+            smelting.fuel("#plants", 3)
+        "##).unwrap();
+        assert!(engine.exec("test", r#"smelting.define { input = "sand", result = "glass", time = 0 }"#).is_err());
+        assert!(engine.exec("test", r##"smelting.fuel("#unknown", 3)"##).is_err());
+        smelting
+    }
+
+    #[test]
+    fn furnaces_smelt_while_burning_fuel() {
+        let smelting = smelting();
+        let mut furnace = Furnace::default();
+        assert_eq!(furnace.insert(FurnaceSlot::Input, "dirt", 1, &smelting), 0);
+        assert_eq!(furnace.insert(FurnaceSlot::Input, "cobblestone", 3, &smelting), 3);
+        assert_eq!(furnace.insert(FurnaceSlot::Input, "sand", 1, &smelting), 0);
+        assert_eq!(furnace.insert(FurnaceSlot::Fuel, "cobblestone", 1, &smelting), 0);
+
+        // There's no fuel yet
+        furnace.tick(1.0, &smelting);
+        assert!(!furnace.is_lit());
+
+        assert_eq!(furnace.insert(FurnaceSlot::Fuel, "flower", 1, &smelting), 1);
+        for _ in 0..4 {
+            furnace.tick(0.5, &smelting);
+        }
+        assert!(furnace.is_lit());
+        assert_eq!(furnace.slot(FurnaceSlot::Output), Some(&("stone".to_string(), 1)));
+        assert_eq!(furnace.slot(FurnaceSlot::Fuel), None);
+
+        // The fuel burns out before the next item is smelted
+        for _ in 0..4 {
+            furnace.tick(0.5, &smelting);
+        }
+        assert!(!furnace.is_lit());
+        assert_eq!(furnace.progress(), 0.0);
+        assert_eq!(furnace.slot(FurnaceSlot::Input), Some(&("cobblestone".to_string(), 2)));
+
+        assert_eq!(furnace.take_all(), vec![("cobblestone".to_string(), 2), ("stone".to_string(), 1)]);
+    }
+
+    #[test]
+    fn items_are_picked_for_slots() {
+        let smelting = smelting();
+        let items: BTreeMap<String, u64> = vec![("sand".to_string(), 2), ("cobblestone".to_string(), 5), ("flower".to_string(), 1)]
+            .into_iter()
+            .collect();
+        let mut furnace = Furnace::default();
+        assert_eq!(item_for_slot(&furnace, FurnaceSlot::Input, &items, &smelting), Some("cobblestone".to_string()));
+        assert_eq!(item_for_slot(&furnace, FurnaceSlot::Fuel, &items, &smelting), Some("flower".to_string()));
+        furnace.insert(FurnaceSlot::Input, "sand", 1, &smelting);
+        assert_eq!(item_for_slot(&furnace, FurnaceSlot::Input, &items, &smelting), Some("sand".to_string()));
+    }
+}
@@ -0,0 +1,284 @@
+//! Types to create terrain generators by their name, e.g.
+//! the generator a world's metadata chose, with parameters
+//! which are read from `TOML`
+
+use crate::world::biome::{BiomeRegistry, BiomeTerrainGen};
+use crate::world::block::Material;
+use crate::world::terrain_generator::{
+    CaveTerrainGen, FlatTerrainGen, HeightmapTerrainGen, ScriptedTerrainGen, SimpleTerrainGen, TerrainGen, VoidTerrainGen,
+};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// The generator of the overworld if the world doesn't choose one
+pub const DEFAULT_GENERATOR: &str = "overworld";
+
+/// A terrain generator which could be shared by the
+/// threads generating chunks
+pub type BoxedTerrainGen = Box<dyn TerrainGen + Send + Sync>;
+
+/// Creates a generator from its parameters and the directory
+/// the paths among them are relative to
+type Factory = Box<dyn Fn(toml::Value, &Path) -> Result<BoxedTerrainGen, String>>;
+
+/// The parameters of generators which only take a seed
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeedParams {
+    /// The seed of the noise, `0` for the default terrain
+    pub seed: u32,
+}
+
+/// The parameters of generators without any
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoParams {}
+
+/// The parameters of the `heightmap` generator
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeightmapParams {
+    /// The path of the grayscale image, relative to the world.
+    /// Absolute paths and paths leaving the world are rejected.
+    pub image: String,
+    /// The height of black pixels
+    pub min_height: i32,
+    /// The height of white pixels
+    pub max_height: i32,
+    /// The name of the block at the top of each column
+    pub surface: String,
+    /// The name of the blocks below the surface
+    pub filler: String,
+}
+
+impl Default for HeightmapParams {
+    fn default() -> Self {
+        Self {
+            image: "heightmap.png".to_string(),
+            min_height: 1,
+            max_height: 64,
+            surface: "grass".to_string(),
+            filler: "dirt".to_string(),
+        }
+    }
+}
+
+/// The parameters of the `scripted` generator
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScriptedParams {
+    /// The Lua source code defining the `height` function,
+    /// see `ScriptedTerrainGen`
+    pub source: String,
+    /// The name of the block at the top of each column
+    pub surface: String,
+    /// The name of the blocks below the surface
+    pub filler: String,
+}
+
+impl Default for ScriptedParams {
+    fn default() -> Self {
+        Self {
+            source: String::new(),
+            surface: "grass".to_string(),
+            filler: "dirt".to_string(),
+        }
+    }
+}
+
+/// TerrainGenRegistry
+///
+/// The terrain generators by their name. Each generator reads
+/// its parameters from a table, e.g. the options of the world
+/// metadata:
+///
+/// ```toml
+/// generator = "heightmap"
+///
+/// [generator_options]
+/// image = "island.png"
+/// max_height = 80
+/// ```
+///
+/// Missing parameters fall back to their defaults, unknown
+/// ones are rejected. The built-in generators are `overworld`,
+/// `simple`, `caves`, `flat`, `void`, `heightmap` and `scripted`.
+pub struct TerrainGenRegistry {
+    /// The factories of the generators by their name
+    factories: HashMap<String, Factory>,
+    /// The directory paths among the parameters are relative to
+    dir: PathBuf,
+}
+
+impl Default for TerrainGenRegistry {
+    fn default() -> Self {
+        Self::new(PathBuf::from("."))
+    }
+}
+
+impl TerrainGenRegistry {
+    /// Creates a new registry of the built-in generators
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory paths among the parameters are
+    ///   relative to, e.g. the directory of the world
+    pub fn new(dir: PathBuf) -> Self {
+        let mut registry = Self {
+            factories: HashMap::new(),
+            dir,
+        };
+        registry.register("overworld", |params: SeedParams, _| Ok(Box::new(BiomeTerrainGen::new(params.seed))));
+        registry.register("simple", |params: SeedParams, _| Ok(Box::new(SimpleTerrainGen::new(params.seed))));
+        registry.register("caves", |params: SeedParams, _| Ok(Box::new(CaveTerrainGen::new(params.seed))));
+        registry.register("flat", |_: NoParams, _| Ok(Box::new(FlatTerrainGen::default())));
+        registry.register("void", |_: NoParams, _| Ok(Box::new(VoidTerrainGen::default())));
+        registry.register("heightmap", |params: HeightmapParams, dir| {
+            let path = dir.join(relative_path(&params.image)?);
+            let generator = HeightmapTerrainGen::open(&path, params.min_height, params.max_height,
+                                                      material(&params.surface)?, material(&params.filler)?)
+                .map_err(|error| format!("failed to open {}: {}", path.display(), error))?;
+            Ok(Box::new(generator))
+        });
+        registry.register("scripted", |params: ScriptedParams, _| {
+            let generator = ScriptedTerrainGen::new(&params.source, material(&params.surface)?, material(&params.filler)?)
+                .map_err(|error| format!("failed to run the script: {}", error))?;
+            Ok(Box::new(generator))
+        });
+        registry
+    }
+
+    /// Lets the `overworld` generator follow the given
+    /// definitions of the biomes instead of the built-in ones
+    ///
+    /// # Arguments
+    ///
+    /// * `biomes` - The definitions of the biomes, e.g. the
+    ///   ones scripts define
+    pub fn with_biomes(mut self, biomes: BiomeRegistry) -> Self {
+        self.register("overworld", move |params: SeedParams, _| {
+            Ok(Box::new(BiomeTerrainGen::new(params.seed).with_biomes(biomes.clone())))
+        });
+        self
+    }
+
+    /// Registers a generator, replacing the one with the same name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the generator
+    /// * `factory` - Creates the generator from its parameters and
+    ///   the directory the paths among them are relative to
+    pub fn register<P, F>(&mut self, name: &str, factory: F)
+        where P: DeserializeOwned, F: Fn(P, &Path) -> Result<BoxedTerrainGen, String> + 'static
+    {
+        self.factories.insert(name.to_string(), Box::new(move |options, dir| {
+            let params = options.try_into().map_err(|error| format!("invalid options: {}", error))?;
+            factory(params, dir)
+        }));
+    }
+
+    /// Returns whether a generator with the given name is registered
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the generator
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    /// Creates a generator
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the generator
+    /// * `options` - The parameters of the generator
+    pub fn create(&self, name: &str, options: &toml::value::Table) -> Result<BoxedTerrainGen, String> {
+        let factory = self.factories.get(name).ok_or_else(|| format!("unknown generator {}", name))?;
+        factory(toml::Value::Table(options.clone()), &self.dir)
+            .map_err(|error| format!("failed to create the {} generator: {}", name, error))
+    }
+}
+
+/// Returns a path among the parameters, if it's relative and
+/// stays within the directory it's relative to, so that a world
+/// can't read files outside of it
+///
+/// # Arguments
+///
+/// * `path` - The path
+fn relative_path(path: &str) -> Result<&Path, String> {
+    let path = Path::new(path);
+    let within = path.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !within {
+        return Err(format!("{} isn't a path within the world", path.display()));
+    }
+    Ok(path)
+}
+
+/// Returns the block with the given name
+///
+/// # Arguments
+///
+/// * `name` - The name of the block
+fn material(name: &str) -> Result<Material, String> {
+    Material::from_name(name).ok_or_else(|| format!("unknown block {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector2;
+    use image::{GrayImage, Luma};
+
+    fn options(source: &str) -> toml::value::Table {
+        toml::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn generators_are_created_with_their_options() {
+        let registry = TerrainGenRegistry::default();
+        let origin = Vector2::new(0, 0);
+        let seeded = registry.create("simple", &options("seed = 7")).unwrap();
+        assert_eq!(seeded.gen_heightmap(&origin)[..], SimpleTerrainGen::new(7).gen_heightmap(&origin)[..]);
+        assert_eq!(registry.create("void", &options("")).unwrap().gen_heightmap(&origin)[0], -1);
+
+        let scripted = registry.create("scripted", &options("source = 'function height(x, z) return x + z / 16 end'")).unwrap();
+        let heights = scripted.gen_heightmap(&Vector2::new(1, 0));
+        assert_eq!((heights[0], heights[15], heights[16 * 15]), (16, 31, 16));
+
+        assert!(registry.create("moon", &options("")).err().unwrap().contains("unknown generator"));
+        assert!(registry.create("simple", &options("seed = 'seven'")).is_err());
+        assert!(registry.create("flat", &options("height = 3")).is_err());
+        assert!(registry.create("scripted", &options("source = 'x = 1'")).is_err());
+        assert!(registry.create("scripted", &options("source = 'function height() end'\nsurface = 'cheese'")).is_err());
+    }
+
+    #[test]
+    fn heightmaps_follow_the_brightness_of_their_image() {
+        let root = std::env::temp_dir().join(format!("rustcraft-heightmap-{}", std::process::id()));
+        let dir = root.join("world");
+        std::fs::create_dir_all(&dir).unwrap();
+        let image = GrayImage::from_fn(20, 2, |x, _| Luma([if x < 10 { 0 } else { 255 }]));
+        image.save(dir.join("island.png")).unwrap();
+        image.save(root.join("outside.png")).unwrap();
+
+        let registry = TerrainGenRegistry::new(dir.clone());
+        let island = registry.create("heightmap", &options("image = 'island.png'\nmin_height = 2\nmax_height = 40")).unwrap();
+        let heights = island.gen_heightmap(&Vector2::new(0, 0));
+        assert_eq!((heights[0], heights[9], heights[10], heights[16]), (2, 2, 40, 2));
+        assert_eq!(island.gen_heightmap(&Vector2::new(1, 0))[0], 40);
+        assert_eq!(island.gen_heightmap(&Vector2::new(-1, 0))[0], 2);
+        assert!(registry.create("heightmap", &options("image = 'missing.png'")).is_err());
+
+        // Images outside of the world aren't read, even if they exist
+        assert!(registry.create("heightmap", &options("image = '../outside.png'")).is_err());
+        assert!(registry.create("heightmap", &options("image = 'maps/../../outside.png'")).is_err());
+        let absolute = format!("image = '{}'", root.join("outside.png").display());
+        assert!(registry.create("heightmap", &options(&absolute)).is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
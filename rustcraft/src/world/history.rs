@@ -0,0 +1,323 @@
+//! Types to keep track of block edits, so that
+//! they could be reverted and reapplied
+
+use crate::world::block::Material;
+use cgmath::Vector3;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// The default amount of block edits an edit history keeps
+pub const DEFAULT_HISTORY_CAPACITY: usize = 4096;
+
+/// EditError
+///
+/// The reasons why an edit operation couldn't be
+/// reverted or reapplied
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EditError {
+    /// There is no operation which could be reverted
+    NothingToUndo,
+    /// There is no operation which could be reapplied
+    NothingToRedo,
+    /// The block at the position isn't loaded
+    NotLoaded(Vector3<i32>),
+    /// The block at the position has been changed
+    /// by something else in the meantime
+    Conflict(Vector3<i32>),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::NothingToUndo => write!(f, "nothing to undo"),
+            EditError::NothingToRedo => write!(f, "nothing to redo"),
+            EditError::NotLoaded(pos) => write!(f, "block at {:?} isn't loaded", pos),
+            EditError::Conflict(pos) => write!(f, "block at {:?} has been changed in the meantime", pos),
+        }
+    }
+}
+
+/// BlockEdit
+///
+/// A single block which has been changed
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BlockEdit {
+    /// The position of the block in world coordinates
+    pub pos: Vector3<i32>,
+    /// The material before the edit
+    pub old: Material,
+    /// The material after the edit
+    pub new: Material,
+}
+
+/// EditOperation
+///
+/// A group of block edits which are reverted and
+/// reapplied at once, e.g. all blocks changed by
+/// a single bulk command.
+#[derive(Clone, Debug, Default)]
+pub struct EditOperation {
+    /// The edits in the order they have been applied
+    edits: Vec<BlockEdit>,
+}
+
+impl EditOperation {
+    /// Creates a new, empty operation
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an edit to the operation
+    ///
+    /// # Arguments
+    ///
+    /// * `edit` - The block edit
+    pub fn push(&mut self, edit: BlockEdit) {
+        self.edits.push(edit);
+    }
+
+    /// Returns the edits in the order they have been applied
+    pub fn edits(&self) -> &[BlockEdit] {
+        &self.edits
+    }
+
+    /// Returns whether the operation doesn't contain any edits
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+}
+
+/// EditHistory
+///
+/// A bounded history of edit operations. If the history
+/// exceeds its capacity of block edits, the oldest operations
+/// are dropped. Recording a new operation discards all
+/// operations which could have been reapplied.
+///
+/// Reverting and reapplying is split into peeking at an
+/// operation and committing it, so an operation stays on its
+/// stack if it couldn't be applied to the world.
+pub struct EditHistory {
+    /// The operations which could be reverted, the most
+    /// recent one at the back
+    undo: VecDeque<EditOperation>,
+    /// The operations which could be reapplied, the most
+    /// recently reverted one at the back
+    redo: Vec<EditOperation>,
+    /// The amount of block edits in both stacks
+    len: usize,
+    /// The maximal amount of block edits to keep
+    capacity: usize,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_HISTORY_CAPACITY)
+    }
+}
+
+impl EditHistory {
+    /// Creates a new, empty edit history
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximal amount of block edits to keep
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            len: 0,
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Records an applied operation. Empty operations are
+    /// ignored. An operation with more edits than the capacity
+    /// can't be reverted, so it clears the whole history.
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - The operation which has been applied
+    pub fn record(&mut self, operation: EditOperation) {
+        if operation.is_empty() {
+            return;
+        }
+
+        let redo_len: usize = self.redo.drain(..).map(|operation| operation.edits().len()).sum();
+        self.len -= redo_len;
+
+        if operation.edits().len() > self.capacity {
+            self.clear();
+            return;
+        }
+
+        self.len += operation.edits().len();
+        self.undo.push_back(operation);
+        while self.len > self.capacity {
+            let oldest = self.undo.pop_front().unwrap();
+            self.len -= oldest.edits().len();
+        }
+    }
+
+    /// Returns the most recent operation which could be reverted
+    pub fn peek_undo(&self) -> Option<&EditOperation> {
+        self.undo.back()
+    }
+
+    /// Returns the most recently reverted operation which
+    /// could be reapplied
+    pub fn peek_redo(&self) -> Option<&EditOperation> {
+        self.redo.last()
+    }
+
+    /// Moves the operation returned by `peek_undo` to the redo
+    /// stack, after it has been reverted. Returns whether there
+    /// was an operation to move.
+    pub fn commit_undo(&mut self) -> bool {
+        match self.undo.pop_back() {
+            Some(operation) => {
+                self.redo.push(operation);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the operation returned by `peek_redo` back to the
+    /// undo stack, after it has been reapplied. Returns whether
+    /// there was an operation to move.
+    pub fn commit_redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(operation) => {
+                self.undo.push_back(operation);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the amount of block edits in the history
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the history doesn't contain any edits
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all recorded operations
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an operation with a single edit at the given x coordinate
+    fn single(x: i32) -> EditOperation {
+        operation(x, 1)
+    }
+
+    /// Creates an operation with `count` edits starting at the given x coordinate
+    fn operation(x: i32, count: i32) -> EditOperation {
+        let mut operation = EditOperation::new();
+        for i in 0..count {
+            operation.push(BlockEdit {
+                pos: Vector3::new(x + i, 0, 0),
+                old: Material::Air,
+                new: Material::Dirt,
+            });
+        }
+        operation
+    }
+
+    /// Returns the x coordinate of the first edit of an operation
+    fn first_x(operation: Option<&EditOperation>) -> Option<i32> {
+        operation.map(|operation| operation.edits()[0].pos.x)
+    }
+
+    #[test]
+    fn undo_redo_round_trip() {
+        let mut history = EditHistory::with_capacity(8);
+        history.record(single(1));
+        history.record(single(2));
+
+        assert_eq!(first_x(history.peek_undo()), Some(2));
+        assert!(history.commit_undo());
+        assert_eq!(first_x(history.peek_undo()), Some(1));
+        assert_eq!(first_x(history.peek_redo()), Some(2));
+
+        assert!(history.commit_redo());
+        assert_eq!(first_x(history.peek_undo()), Some(2));
+        assert!(history.peek_redo().is_none());
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn peeking_doesnt_move_operations() {
+        let mut history = EditHistory::with_capacity(8);
+        history.record(single(1));
+
+        assert_eq!(first_x(history.peek_undo()), Some(1));
+        assert_eq!(first_x(history.peek_undo()), Some(1));
+        assert!(history.peek_redo().is_none());
+        assert!(!history.commit_redo());
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut history = EditHistory::with_capacity(8);
+        history.record(operation(1, 3));
+        history.record(single(10));
+        assert!(history.commit_undo());
+        assert_eq!(history.len(), 4);
+
+        history.record(single(20));
+        assert!(history.peek_redo().is_none());
+        assert_eq!(first_x(history.peek_undo()), Some(20));
+        assert_eq!(history.len(), 4);
+    }
+
+    #[test]
+    fn empty_operations_are_ignored() {
+        let mut history = EditHistory::with_capacity(8);
+        history.record(single(1));
+        history.commit_undo();
+
+        history.record(EditOperation::new());
+        assert_eq!(first_x(history.peek_redo()), Some(1));
+        assert!(history.peek_undo().is_none());
+    }
+
+    #[test]
+    fn capacity_bounds_block_edits() {
+        let mut history = EditHistory::with_capacity(5);
+        history.record(operation(0, 2));
+        history.record(operation(10, 2));
+        assert_eq!(history.len(), 4);
+
+        // The oldest operation is evicted as a whole
+        history.record(operation(20, 2));
+        assert_eq!(history.len(), 4);
+        assert!(history.commit_undo());
+        assert!(history.commit_undo());
+        assert!(!history.commit_undo());
+        assert_eq!(first_x(history.peek_redo()), Some(10));
+    }
+
+    #[test]
+    fn oversized_operation_clears_history() {
+        let mut history = EditHistory::with_capacity(4);
+        history.record(operation(0, 2));
+        history.record(operation(10, 5));
+
+        assert!(history.is_empty());
+        assert!(history.peek_undo().is_none());
+        assert!(history.peek_redo().is_none());
+    }
+}
@@ -0,0 +1,202 @@
+//! Types to move items between containers with hoppers,
+//! which chain chests and furnaces together
+
+use crate::world::block_entity::BlockEntity;
+use crate::world::furnace::{Smelting, MAX_STACK};
+use crate::world::structure::Facing;
+use cgmath::Vector3;
+use serde::{Deserialize, Serialize};
+
+/// The time in seconds between two items a hopper moves
+pub const TRANSFER_INTERVAL: f32 = 0.4;
+
+/// Hopper
+///
+/// The block entity of a hopper. Each time its cooldown is
+/// over, a hopper pushes one of its items into the container
+/// it faces and pulls one item out of the container above.
+/// It faces down or a horizontal direction, which is stored
+/// in its block state, see `facing`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Hopper {
+    /// The items the hopper moves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    items: Option<(String, u64)>,
+    /// The time in seconds until the next item is moved
+    cooldown: f32,
+}
+
+impl Hopper {
+    /// Returns the items the hopper moves
+    pub fn items(&self) -> Option<&(String, u64)> {
+        self.items.as_ref()
+    }
+
+    /// Adds a single item and returns whether it fit, which it
+    /// doesn't if the hopper is full or holds other items
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - The name of the item
+    pub fn insert(&mut self, item: &str) -> bool {
+        let (name, count) = self.items.get_or_insert_with(|| (item.to_string(), 0));
+        if name != item || *count >= MAX_STACK {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+
+    /// Takes a single item out of the hopper
+    pub fn take_one(&mut self) -> Option<String> {
+        let (item, count) = self.items.as_mut()?;
+        *count -= 1;
+        let item = item.clone();
+        if *count == 0 {
+            self.items = None;
+        }
+        Some(item)
+    }
+
+    /// Advances the cooldown and returns whether the
+    /// hopper moves the next item
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    pub fn tick(&mut self, seconds: f32) -> bool {
+        self.cooldown -= seconds;
+        if self.cooldown > 0.0 {
+            return false;
+        }
+        self.cooldown += TRANSFER_INTERVAL;
+        true
+    }
+}
+
+/// Returns the block state of a hopper facing a direction,
+/// or facing down for `None`
+///
+/// # Arguments
+///
+/// * `facing` - The horizontal direction
+pub fn facing_state(facing: Option<Facing>) -> u8 {
+    match facing {
+        None => 0,
+        Some(Facing::North) => 1,
+        Some(Facing::East) => 2,
+        Some(Facing::South) => 3,
+        Some(Facing::West) => 4,
+    }
+}
+
+/// Returns the horizontal direction a hopper faces
+/// by its block state, or `None` if it faces down
+///
+/// # Arguments
+///
+/// * `state` - The block state
+pub fn facing(state: u8) -> Option<Facing> {
+    match state {
+        1 => Some(Facing::North),
+        2 => Some(Facing::East),
+        3 => Some(Facing::South),
+        4 => Some(Facing::West),
+        _ => None,
+    }
+}
+
+/// Returns the offset to the block a hopper pushes its
+/// items into by its block state
+///
+/// # Arguments
+///
+/// * `state` - The block state
+pub fn target_offset(state: u8) -> Vector3<i32> {
+    facing(state).map_or(Vector3::new(0, -1, 0), |facing| facing.offset())
+}
+
+/// Moves a single item from one container into another and
+/// returns whether an item has been moved. An item which
+/// doesn't fit stays in its container.
+///
+/// # Arguments
+///
+/// * `from` - The container the item is pulled out of
+/// * `to` - The container the item is pushed into
+/// * `from_above` - Whether `to` is below `from`
+/// * `smelting` - The smelting recipes and fuels
+pub fn move_item(from: &mut BlockEntity, to: &mut BlockEntity, from_above: bool, smelting: &Smelting) -> bool {
+    let item = match from.peek() {
+        Some(item) => item.to_string(),
+        None => return false,
+    };
+    if !to.accept(&item, from_above, smelting) {
+        return false;
+    }
+    from.extract();
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scripting::ScriptEngine;
+    use crate::world::block::Material;
+    use crate::world::furnace::FurnaceSlot;
+    use crate::world::tags::Tags;
+
+    #[test]
+    fn items_move_between_containers() {
+        let engine = ScriptEngine::new();
+        let tags = Tags::new(&engine).unwrap();
+        let smelting = Smelting::new(&engine, &tags).unwrap();
+        engine.exec("test", r#"
+            smelting.define { input = "sand", result = "glass", time = 1 }
+            smelting.fuel("flower", 3)
+        "#).unwrap();
+
+        let mut chest = BlockEntity::Chest {
+            loot_table: String::new(),
+            items: vec![("sand".to_string(), 2), ("flower".to_string(), 1)],
+        };
+        let mut hopper = BlockEntity::Hopper(Hopper::default());
+        let mut furnace = BlockEntity::container(Material::Furnace).unwrap();
+
+        // The hopper only holds one kind of item at a time
+        assert!(move_item(&mut chest, &mut hopper, true, &smelting));
+        assert!(move_item(&mut chest, &mut hopper, true, &smelting));
+        assert!(!move_item(&mut chest, &mut hopper, true, &smelting));
+        assert_eq!(chest.peek(), Some("flower"));
+
+        // Sand isn't a fuel, so it only goes into a furnace from above
+        assert!(!move_item(&mut hopper, &mut furnace, false, &smelting));
+        assert!(move_item(&mut hopper, &mut furnace, true, &smelting));
+        assert!(move_item(&mut hopper, &mut furnace, true, &smelting));
+        assert!(move_item(&mut chest, &mut hopper, true, &smelting));
+        assert!(move_item(&mut hopper, &mut furnace, false, &smelting));
+        assert_eq!(chest.peek(), None);
+
+        match &furnace {
+            BlockEntity::Furnace(furnace) => {
+                assert_eq!(furnace.slot(FurnaceSlot::Input), Some(&("sand".to_string(), 2)));
+                assert_eq!(furnace.slot(FurnaceSlot::Fuel), Some(&("flower".to_string(), 1)));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn hoppers_face_by_their_state() {
+        for facing in [None, Some(Facing::North), Some(Facing::East), Some(Facing::South), Some(Facing::West)].iter() {
+            assert_eq!(super::facing(facing_state(*facing)), *facing);
+        }
+        assert_eq!(target_offset(0), Vector3::new(0, -1, 0));
+        assert_eq!(target_offset(facing_state(Some(Facing::East))), Facing::East.offset());
+
+        let mut hopper = Hopper::default();
+        assert!(hopper.tick(0.1));
+        assert!(!hopper.tick(0.2));
+        assert!(hopper.tick(0.2));
+    }
+}
@@ -0,0 +1,479 @@
+//! Types representing dropped items lying in the world,
+//! until the player picks them up
+
+use crate::ecs::{Ecs, EntityId};
+use crate::ecs::components::{Body, Gravity, Lifetime, Position, Velocity};
+use crate::ecs::systems;
+use crate::graphics::mesh::{Mesh, MeshHandle};
+use crate::graphics::scene::{NodeId, Scene, SceneNode};
+use crate::world::block::Material;
+use crate::world::chunk_entities::{self, ChunkEntities, ChunkEntity, EntityUuid, SavedEntity, ITEM_TYPE};
+use cgmath::{InnerSpace, Matrix4, Rad, Vector2, Vector3, Vector4};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// The edge length of the cube items are shown as
+const ITEM_SIZE: f32 = 0.25;
+/// The distance up to which the player picks up items
+pub const PICKUP_DISTANCE: f32 = 1.5;
+/// The time in seconds before a dropped item could be picked up
+const PICKUP_DELAY: f32 = 0.5;
+/// The time in seconds after which items despawn
+const DESPAWN_TIME: f32 = 300.0;
+/// The speed items fall with at most in blocks per second
+const FALL_SPEED: f32 = 6.0;
+/// The gravity pulling items down
+const ITEM_GRAVITY: Gravity = Gravity { acceleration: 20.0, terminal_velocity: FALL_SPEED };
+/// The box items take up, which lands on solid blocks
+const ITEM_BODY: Body = Body { width: ITEM_SIZE, height: ITEM_SIZE };
+/// The speed items spin with in radians per second
+const SPIN_SPEED: f32 = 1.5;
+/// How far items bob up and down in blocks
+const BOB_HEIGHT: f32 = 0.08;
+/// The longest time in seconds an item catches up with once it's
+/// ticked again, so that items outside the activation radius
+/// don't age while the player is away
+const MAX_CATCH_UP: f32 = 5.0;
+
+/// ItemStack
+///
+/// The component of the items an entity is made of
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ItemStack {
+    /// The name of the item
+    pub item: String,
+    /// The amount of items
+    pub count: u64,
+}
+
+/// Ticked
+///
+/// The component of the time of the clock of the items
+/// when the item has been ticked the last time
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Ticked(f32);
+
+/// ItemEntity
+///
+/// The entry of a stack of items lying in the world in the list
+/// of its chunk. The item itself is an entity of the `Ecs` of the
+/// items, made up of a position, velocity, gravity, body, lifetime,
+/// stack and the node it's shown with.
+pub struct ItemEntity {
+    /// The id of the entity as it's saved
+    uuid: EntityUuid,
+    /// The entity of the item
+    id: EntityId,
+    /// The position of the bottom center of the item when it has
+    /// been ticked the last time, which files it under its chunk
+    pos: Vector3<f32>,
+}
+
+impl ItemEntity {
+    /// Returns the position of the bottom center of the item
+    pub fn pos(&self) -> &Vector3<f32> {
+        &self.pos
+    }
+}
+
+impl ChunkEntity for ItemEntity {
+    fn uuid(&self) -> EntityUuid {
+        self.uuid
+    }
+
+    fn pos(&self) -> Vector3<f32> {
+        self.pos
+    }
+}
+
+/// ItemEntities
+///
+/// The items lying in a world. Items fall down until they land
+/// on a block, moved by the physics of the entity component
+/// system, and are shown as small spinning cubes colored by
+/// their name in the scene of the world.
+///
+/// Items are stored in the chunk they lie in, and only the ones
+/// within the activation radius around the player are ticked.
+/// Only a limited amount of these is ticked per update. The
+/// items take turns, and each one is advanced by the time since
+/// it has been ticked the last time.
+pub struct ItemEntities {
+    /// The components of the items
+    ecs: Ecs,
+    /// The items lying in the world by their chunk
+    items: ChunkEntities<ItemEntity>,
+    /// The mesh all items are shown with
+    mesh: MeshHandle,
+    /// The time the items have been updated for in seconds
+    clock: f32,
+    /// The index of the item which is ticked next
+    next: usize,
+}
+
+impl Default for ItemEntities {
+    fn default() -> Self {
+        Self {
+            ecs: Ecs::default(),
+            items: ChunkEntities::default(),
+            mesh: MeshHandle::new(Mesh::cube()),
+            clock: 0.0,
+            next: 0,
+        }
+    }
+}
+
+impl ItemEntities {
+    /// Returns the items lying in the world
+    pub fn items(&self) -> &ChunkEntities<ItemEntity> {
+        &self.items
+    }
+
+    /// Returns the stack of items an item lying in the world is made of
+    ///
+    /// # Arguments
+    ///
+    /// * `entity` - The item lying in the world
+    pub fn stack(&self, entity: &ItemEntity) -> Option<&ItemStack> {
+        self.ecs.get::<ItemStack>(entity.id)
+    }
+
+    /// Drops items at the given position
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the item is shown in
+    /// * `item` - The name of the item
+    /// * `count` - The amount of items
+    /// * `pos` - The position of the bottom center of the item
+    pub fn spawn(&mut self, scene: &mut Scene, item: &str, count: u64, pos: Vector3<f32>) {
+        self.spawn_aged(scene, EntityUuid::generate(), item, count, pos, 0.0);
+    }
+
+    /// Drops items of the given age at the given position
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the item is shown in
+    /// * `uuid` - The id of the entity
+    /// * `item` - The name of the item
+    /// * `count` - The amount of items
+    /// * `pos` - The position of the bottom center of the item
+    /// * `age` - The time since the item has been dropped in seconds
+    fn spawn_aged(&mut self, scene: &mut Scene, uuid: EntityUuid, item: &str, count: u64, pos: Vector3<f32>, age: f32) {
+        let node = scene.add(None, SceneNode::with_mesh(
+            "item",
+            item_transform(pos, age),
+            self.mesh.clone(),
+            item_color(item),
+        ));
+        let id = self.ecs.spawn();
+        self.ecs.insert(id, Position(pos));
+        self.ecs.insert(id, Velocity(Vector3::new(0.0, 0.0, 0.0)));
+        self.ecs.insert(id, ITEM_GRAVITY);
+        self.ecs.insert(id, ITEM_BODY);
+        self.ecs.insert(id, Lifetime { age, lifetime: DESPAWN_TIME });
+        self.ecs.insert(id, ItemStack { item: item.to_string(), count });
+        self.ecs.insert(id, Ticked(self.clock));
+        self.ecs.insert(id, node);
+        self.items.insert(ItemEntity { uuid, id, pos });
+    }
+
+    /// Advances the items within the activation radius around the
+    /// player and returns the ones the player picked up with their
+    /// amount. Items despawn once they're too old.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the items are shown in
+    /// * `seconds` - The time passed since the last update
+    /// * `player` - The position of the player's feet
+    /// * `radius` - The activation radius in chunks
+    /// * `budget` - The largest amount of items ticked in this update
+    /// * `block_at` - Returns the material of the block at a position
+    pub fn update<F>(&mut self, scene: &mut Scene, seconds: f32, player: Vector3<f32>, radius: i32, budget: usize,
+                     block_at: F) -> Vec<(String, u64)>
+        where F: Fn(Vector3<i32>) -> Option<Material>
+    {
+        self.clock += seconds;
+
+        let active = self.items.active(chunk_entities::chunk_of(player), radius);
+        if active.is_empty() {
+            return Vec::new();
+        }
+        let start = self.next % active.len();
+        let count = budget.min(active.len());
+
+        let mut removed = Vec::new();
+        let mut picked_up = Vec::new();
+        for &key in active.iter().cycle().skip(start).take(count) {
+            let entity = self.items.get_mut(key);
+            let id = entity.id;
+            let ticked = self.ecs.get_mut::<Ticked>(id).expect("an item without its components");
+            let seconds = (self.clock - ticked.0).min(MAX_CATCH_UP);
+            ticked.0 = self.clock;
+
+            // Items only fall through the blocks of loaded chunks
+            // which don't collide, e.g. air and plants
+            systems::physics_of(&mut self.ecs, id, seconds, &block_at);
+            let lifetime = self.ecs.get_mut::<Lifetime>(id).expect("an item without its components");
+            lifetime.age += seconds;
+            let (age, expired) = (lifetime.age, lifetime.is_over());
+            entity.pos = self.ecs.get::<Position>(id).expect("an item without its components").0;
+
+            let reached = (entity.pos - player).magnitude() <= PICKUP_DISTANCE;
+            if (reached && age >= PICKUP_DELAY) || expired {
+                if reached {
+                    let stack = self.ecs.get::<ItemStack>(id).expect("an item without its components");
+                    picked_up.push((stack.item.clone(), stack.count));
+                }
+                removed.push(key);
+                continue;
+            }
+
+            if let Some(node) = self.ecs.get::<NodeId>(id).and_then(|&node| scene.node_mut(node)) {
+                node.set_transform(item_transform(entity.pos, age));
+            }
+        }
+        // The items after the removed ones move up,
+        // so they're ticked next
+        self.next = start + count - removed.len();
+
+        for entity in self.items.remove_and_relocate(removed) {
+            self.despawn(scene, &entity);
+        }
+        picked_up
+    }
+
+    /// Removes the items of a chunk from the world and
+    /// returns them, so they can be saved with the chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the items are shown in
+    /// * `loc` - The location of the chunk
+    pub fn unload_chunk(&mut self, scene: &mut Scene, loc: Vector2<i32>) -> Vec<SavedEntity> {
+        self.items.take_chunk(loc).into_iter()
+            .filter_map(|entity| {
+                let saved = self.saved_entity(&entity);
+                self.despawn(scene, &entity);
+                saved
+            })
+            .collect()
+    }
+
+    /// Returns the items of a chunk as they're saved
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the chunk
+    pub fn saved(&self, loc: Vector2<i32>) -> Vec<SavedEntity> {
+        self.items.in_chunk(loc).iter().filter_map(|entity| self.saved_entity(entity)).collect()
+    }
+
+    /// Drops saved items into the world again with their id
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the items are shown in
+    /// * `saved` - The saved items
+    pub fn restore(&mut self, scene: &mut Scene, saved: &SavedEntity) -> Result<(), String> {
+        let item = saved.text("item")?;
+        let count = saved.number("count").unwrap_or(1.0).max(1.0) as u64;
+        let age = saved.number("age").unwrap_or(0.0) as f32;
+        self.spawn_aged(scene, saved.uuid, item, count, saved.pos.into(), age);
+        Ok(())
+    }
+
+    /// Removes all items from the world
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the items are shown in
+    pub fn clear(&mut self, scene: &mut Scene) {
+        for entity in self.items.take_all() {
+            self.despawn(scene, &entity);
+        }
+    }
+
+    /// Returns an item as it's saved with its chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `entity` - The item lying in the world
+    fn saved_entity(&self, entity: &ItemEntity) -> Option<SavedEntity> {
+        let pos = self.ecs.get::<Position>(entity.id)?;
+        let stack = self.ecs.get::<ItemStack>(entity.id)?;
+        let lifetime = self.ecs.get::<Lifetime>(entity.id)?;
+        Some(SavedEntity::new(ITEM_TYPE, entity.uuid, pos.0)
+            .with("item", stack.item.as_str())
+            .with("count", stack.count as i64)
+            .with("age", lifetime.age as f64))
+    }
+
+    /// Despawns an item which has been taken out of the
+    /// list of its chunk and removes its node from the scene
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the item is shown in
+    /// * `entity` - The item
+    fn despawn(&mut self, scene: &mut Scene, entity: &ItemEntity) {
+        if let Some(&node) = self.ecs.get::<NodeId>(entity.id) {
+            scene.remove(node);
+        }
+        self.ecs.despawn(entity.id);
+    }
+}
+
+/// Returns the transform of the node an item is shown with,
+/// which bobs and spins the item as it ages
+///
+/// # Arguments
+///
+/// * `pos` - The position of the bottom center of the item
+/// * `age` - The time since the item has been dropped in seconds
+fn item_transform(pos: Vector3<f32>, age: f32) -> Matrix4<f32> {
+    let bob = BOB_HEIGHT * (1.0 + (age * 2.0).sin());
+    Matrix4::from_translation(pos + Vector3::new(0.0, bob, 0.0))
+        * Matrix4::from_angle_y(Rad(age * SPIN_SPEED))
+        * Matrix4::from_scale(ITEM_SIZE)
+        * Matrix4::from_translation(Vector3::new(-0.5, 0.0, -0.5))
+}
+
+/// Returns the color an item is shown with, which is
+/// derived from its name, so that items are told apart
+///
+/// # Arguments
+///
+/// * `item` - The name of the item
+pub(crate) fn item_color(item: &str) -> Vector4<f32> {
+    let mut hasher = DefaultHasher::new();
+    item.hash(&mut hasher);
+    let hash = hasher.finish();
+    let channel = |shift: u64| 0.3 + 0.7 * ((hash >> shift) & 0xff) as f32 / 255.0;
+    Vector4::new(channel(0), channel(8), channel(16), 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A world whose blocks below `y = 0` are stone
+    fn ground(pos: Vector3<i32>) -> Option<Material> {
+        Some(if pos.y < 0 { Material::Stone } else { Material::Air })
+    }
+
+    /// Returns the ages of the items, ordered by their chunk
+    fn ages(items: &ItemEntities) -> Vec<f32> {
+        items.items().iter().map(|item| items.ecs.get::<Lifetime>(item.id).unwrap().age).collect()
+    }
+
+    #[test]
+    fn items_fall_until_they_land() {
+        let mut scene = Scene::new();
+        let mut items = ItemEntities::default();
+        items.spawn(&mut scene, "dirt", 1, Vector3::new(0.5, 3.5, 0.5));
+        assert_eq!(scene.instances().len(), 1);
+
+        let far = Vector3::new(100.0, 0.0, 100.0);
+        for _ in 0..60 {
+            assert!(items.update(&mut scene, 1.0 / 30.0, far, 8, 16, ground).is_empty());
+        }
+        let landed = items.items().iter().next().unwrap().pos().y;
+        assert!((0.0..0.01).contains(&landed), "{}", landed);
+        assert_eq!(items.ecs.len(), 1);
+    }
+
+    #[test]
+    fn items_are_picked_up_after_a_delay() {
+        let mut scene = Scene::new();
+        let mut items = ItemEntities::default();
+        items.spawn(&mut scene, "cobblestone", 2, Vector3::new(0.5, 0.0, 0.5));
+
+        let player = Vector3::new(1.0, 0.0, 1.0);
+        assert!(items.update(&mut scene, 0.1, player, 8, 16, ground).is_empty());
+        assert_eq!(items.update(&mut scene, PICKUP_DELAY, player, 8, 16, ground), vec![("cobblestone".to_string(), 2)]);
+        assert!(items.items().is_empty());
+        assert!(items.ecs.is_empty());
+        assert!(scene.instances().is_empty());
+    }
+
+    #[test]
+    fn old_items_despawn() {
+        let mut scene = Scene::new();
+        let mut items = ItemEntities::default();
+        items.spawn(&mut scene, "dirt", 1, Vector3::new(0.5, 0.0, 0.5));
+
+        let far = Vector3::new(100.0, 0.0, 100.0);
+        for _ in 0..DESPAWN_TIME as usize {
+            assert!(items.update(&mut scene, 1.0, far, 8, 16, ground).is_empty());
+        }
+        assert!(items.items().is_empty());
+        assert!(scene.instances().is_empty());
+    }
+
+    #[test]
+    fn items_take_turns_within_the_budget() {
+        let mut scene = Scene::new();
+        let mut items = ItemEntities::default();
+        for x in 0..3 {
+            items.spawn(&mut scene, "dirt", 1, Vector3::new(x as f32 * 10.0, 0.0, 0.0));
+        }
+
+        let far = Vector3::new(100.0, 0.0, 100.0);
+        items.update(&mut scene, 1.0, far, 8, 2, ground);
+        assert_eq!(ages(&items), vec![1.0, 1.0, 0.0]);
+
+        // The skipped item catches up with the time it missed
+        items.update(&mut scene, 1.0, far, 8, 2, ground);
+        assert_eq!(ages(&items), vec![2.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn only_items_near_the_player_are_ticked() {
+        let mut scene = Scene::new();
+        let mut items = ItemEntities::default();
+        items.spawn(&mut scene, "dirt", 1, Vector3::new(0.5, 3.0, 0.5));
+        items.spawn(&mut scene, "dirt", 1, Vector3::new(100.5, 3.0, 0.5));
+
+        let player = Vector3::new(8.0, 0.0, 40.0);
+        items.update(&mut scene, 10.0, player, 2, 16, ground);
+        assert_eq!(ages(&items), vec![MAX_CATCH_UP, 0.0]);
+
+        // Items far away don't age while the player is away
+        let player = Vector3::new(100.0, 0.0, 40.0);
+        items.update(&mut scene, 100.0, player, 2, 16, ground);
+        assert_eq!(ages(&items), vec![MAX_CATCH_UP, MAX_CATCH_UP]);
+    }
+
+    #[test]
+    fn items_are_saved_and_restored() {
+        let mut scene = Scene::new();
+        let mut items = ItemEntities::default();
+        items.spawn(&mut scene, "cobblestone", 3, Vector3::new(20.5, 0.0, -4.5));
+        items.update(&mut scene, 2.0, Vector3::new(100.0, 0.0, 0.0), 8, 16, ground);
+
+        let loc = Vector2::new(1, -1);
+        assert_eq!(items.saved(loc).len(), 1);
+        let uuid = items.items().iter().next().unwrap().uuid;
+        let saved = items.unload_chunk(&mut scene, loc);
+        assert_eq!(saved, vec![SavedEntity::new(ITEM_TYPE, uuid, Vector3::new(20.5, 0.0, -4.5))
+            .with("item", "cobblestone")
+            .with("count", 3)
+            .with("age", 2.0)]);
+        assert!(items.items().is_empty());
+        assert!(scene.instances().is_empty());
+
+        items.restore(&mut scene, &saved[0]).unwrap();
+        assert_eq!(items.saved(loc), saved);
+        assert!(items.restore(&mut scene, &SavedEntity::new(ITEM_TYPE, uuid, Vector3::new(0.0, 0.0, 0.0))).is_err());
+        assert_eq!(scene.instances().len(), 1);
+    }
+
+    #[test]
+    fn items_are_colored_by_name() {
+        assert_eq!(item_color("dirt"), item_color("dirt"));
+        assert_ne!(item_color("dirt"), item_color("cobblestone"));
+    }
+}
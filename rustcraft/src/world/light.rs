@@ -0,0 +1,497 @@
+//! Types to compute the light emitted by glowing
+//! blocks and the light of the sky within a chunk,
+//! including the light spreading in from adjacent chunks
+
+use crate::world::block::Material;
+use crate::world::chunk::{Chunk, CHUNK_AREA, CHUNK_HEIGHT, CHUNK_SIZE, CHUNK_VOLUME};
+use cgmath::{Vector2, Vector3};
+use std::collections::VecDeque;
+
+/// The light level of the brightest light source
+pub const MAX_LIGHT: u8 = 15;
+/// The bits of a scalar texel the sky light is stored in,
+/// above the light of glowing blocks
+const SKY_SHIFT: u8 = 4;
+/// The bits of an `RGB` texel the sky light is stored in,
+/// above the red, green and blue light of glowing blocks
+const RGB_SKY_SHIFT: u16 = 12;
+/// The texel of a block lit by the sky but no glowing
+/// block, e.g. for chunks without a light map yet
+pub const SKY_LIT_TEXEL: u8 = MAX_LIGHT << SKY_SHIFT;
+/// The distance in blocks the brightest light reaches
+const REACH: i16 = MAX_LIGHT as i16 - 1;
+/// The width of the area light is computed in, which covers
+/// a chunk and the blocks of the adjacent chunks whose light
+/// could reach it
+const AREA_SIZE: usize = CHUNK_SIZE + 2 * REACH as usize;
+/// The amount of blocks of the area light is computed in
+const AREA_VOLUME: usize = AREA_SIZE * AREA_SIZE * CHUNK_HEIGHT;
+/// The offsets of the chunks adjacent to a chunk,
+/// including the diagonal ones
+pub const ADJACENT: [Vector2<i32>; 8] = [
+    Vector2::new(-1, -1), Vector2::new(0, -1), Vector2::new(1, -1),
+    Vector2::new(-1, 0), Vector2::new(1, 0),
+    Vector2::new(-1, 1), Vector2::new(0, 1), Vector2::new(1, 1),
+];
+
+/// LightFormat
+///
+/// How the light of glowing blocks is stored in a light map:
+/// * `Rgb` - A level per color channel, so that lights tint
+///   their surroundings. Each texel takes two bytes, the red,
+///   green and blue levels in the lower twelve bits and the
+///   sky light in the upper four bits.
+/// * `Scalar` - The level of the brightest channel alone,
+///   which takes half the memory. The lower four bits of each
+///   byte hold the light of glowing blocks, the upper four
+///   bits the light of the sky.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LightFormat {
+    Rgb,
+    Scalar,
+}
+
+impl LightFormat {
+    /// Returns the format of the given choice of the player
+    ///
+    /// # Arguments
+    ///
+    /// * `colored` - Whether lights should tint their surroundings
+    pub fn from_colored(colored: bool) -> Self {
+        if colored { LightFormat::Rgb } else { LightFormat::Scalar }
+    }
+
+    /// Returns the size of a texel in bytes
+    pub fn texel_size(&self) -> usize {
+        match self {
+            LightFormat::Rgb => 2,
+            LightFormat::Scalar => 1,
+        }
+    }
+}
+
+/// LightMap
+///
+/// The light level of each block of a chunk, in the
+/// same order as the blocks of the chunk. The light map
+/// is uploaded as a 3D texture of `16*16*256` texels,
+/// which is sampled by the chunk shader, so that light
+/// updates don't require the chunk to be remeshed. The
+/// texels hold the light of glowing blocks and the light
+/// of the sky, see `LightFormat`.
+pub struct LightMap {
+    /// The layout of the texels
+    format: LightFormat,
+    /// The light levels in the range `[0, MAX_LIGHT]`,
+    /// packed with the sky light levels
+    texels: Box<[u8]>,
+}
+
+impl LightMap {
+    /// Computes the light map of a chunk. The light of glowing
+    /// blocks spreads through all blocks which aren't opaque and
+    /// loses one level per block. The sky fully lights the blocks
+    /// which have no opaque block above them, and its light spreads
+    /// from there the same way, e.g. into the mouth of a cave.
+    /// The blocks of the given adjacent chunks which are close
+    /// enough to the border are lit the same way, so light spreads
+    /// across it. Adjacent chunks which aren't given, e.g. as they
+    /// aren't generated yet, don't let any light through.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The chunk
+    /// * `neighbours` - The chunks adjacent to the chunk, including
+    ///   diagonal ones. Other chunks are ignored.
+    /// * `format` - How the light of glowing blocks is stored
+    pub fn compute(chunk: &Chunk, neighbours: &[Chunk], format: LightFormat) -> Self {
+        let size = CHUNK_SIZE as i16;
+        let neighbours: Vec<(Vector2<i32>, &Chunk)> = neighbours.iter()
+            .map(|neighbour| (neighbour.loc() - chunk.loc(), neighbour))
+            .filter(|(offset, _)| ADJACENT.contains(offset))
+            .collect();
+        Self::from_blocks(format, |loc| {
+            let offset = Vector2::new(loc.x.div_euclid(size) as i32, loc.z.div_euclid(size) as i32);
+            let local = Vector3::new(loc.x.rem_euclid(size), loc.y, loc.z.rem_euclid(size));
+            if offset == Vector2::new(0, 0) {
+                return chunk.block(local);
+            }
+            neighbours.iter()
+                .find(|(neighbour, _)| *neighbour == offset)
+                .and_then(|(_, neighbour)| neighbour.block(local))
+        })
+    }
+
+    /// Computes the light map from the blocks returned by the
+    /// given function, see `compute`. The function is called for
+    /// the blocks of the chunk and the ones up to `REACH` blocks
+    /// beyond its sides.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - How the light of glowing blocks is stored
+    /// * `block_at` - Returns the material of the block at a location
+    ///   relative to the chunk
+    fn from_blocks<F: Fn(Vector3<i16>) -> Option<Material>>(format: LightFormat, block_at: F) -> Self {
+        // The blocks are looked up once, as light passes most of them several times.
+        // Each color channel spreads on its own, scalar light as a single channel.
+        let channels = match format {
+            LightFormat::Rgb => 3,
+            LightFormat::Scalar => 1,
+        };
+        let mut transparent = vec![false; AREA_VOLUME];
+        let mut light = vec![vec![0u8; AREA_VOLUME]; channels];
+        let mut queue = VecDeque::new();
+        for index in 0..AREA_VOLUME {
+            if let Some(material) = block_at(area_location_of(index)) {
+                transparent[index] = !material.data().is_opaque();
+                let emission = material.data().emission();
+                if emission > 0.0 {
+                    let levels = material.data().light_color().map(|channel| light_level(emission * channel));
+                    match format {
+                        LightFormat::Rgb => {
+                            for (channel, level) in light.iter_mut().zip(levels.iter()) {
+                                channel[index] = *level;
+                            }
+                        }
+                        LightFormat::Scalar => light[0][index] = levels.iter().copied().max().unwrap_or(0),
+                    }
+                    queue.push_back(index);
+                }
+            }
+        }
+        for channel in light.iter_mut() {
+            spread(&transparent, channel, queue.clone());
+        }
+
+        // The sky shines down each column until it hits an opaque block
+        let mut sky = vec![0u8; AREA_VOLUME];
+        let mut queue = VecDeque::new();
+        for column in 0..AREA_SIZE * AREA_SIZE {
+            for y in (0..CHUNK_HEIGHT).rev() {
+                let index = y * AREA_SIZE * AREA_SIZE + column;
+                if !transparent[index] {
+                    break;
+                }
+                sky[index] = MAX_LIGHT;
+                queue.push_back(index);
+            }
+        }
+        spread(&transparent, &mut sky, queue);
+
+        let mut texels = vec![0u8; CHUNK_VOLUME * format.texel_size()].into_boxed_slice();
+        for (index, texel) in texels.chunks_exact_mut(format.texel_size()).enumerate() {
+            let area_index = area_index_of(location_of(index)).unwrap();
+            match format {
+                LightFormat::Rgb => {
+                    let packed = light[0][area_index] as u16 | (light[1][area_index] as u16) << 4
+                        | (light[2][area_index] as u16) << 8 | (sky[area_index] as u16) << RGB_SKY_SHIFT;
+                    texel.copy_from_slice(&packed.to_ne_bytes());
+                }
+                LightFormat::Scalar => texel[0] = light[0][area_index] | sky[area_index] << SKY_SHIFT,
+            }
+        }
+        Self {
+            format,
+            texels,
+        }
+    }
+
+    /// Returns the light level of the block at a location
+    /// within the chunk, which is the level of the brightest
+    /// color channel, or `0` if it's out of bounds
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the block in the chunk
+    pub fn level(&self, loc: Vector3<i16>) -> u8 {
+        self.color(loc).iter().copied().max().unwrap_or(0)
+    }
+
+    /// Returns the red, green and blue light levels of the
+    /// block at a location within the chunk, or `0` if it's
+    /// out of bounds. Scalar light maps have the same level
+    /// in all channels.
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the block in the chunk
+    pub fn color(&self, loc: Vector3<i16>) -> [u8; 3] {
+        if !in_bounds(loc) {
+            return [0; 3];
+        }
+        let texel = self.texel(index_of(loc));
+        match self.format {
+            LightFormat::Rgb => [0, 4, 8].map(|shift| (texel >> shift) as u8 & MAX_LIGHT),
+            LightFormat::Scalar => [texel as u8 & MAX_LIGHT; 3],
+        }
+    }
+
+    /// Returns the light level of the sky of the block at a
+    /// location within the chunk, or `0` if it's out of bounds
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the block in the chunk
+    pub fn sky_level(&self, loc: Vector3<i16>) -> u8 {
+        if !in_bounds(loc) {
+            return 0;
+        }
+        let texel = self.texel(index_of(loc));
+        match self.format {
+            LightFormat::Rgb => (texel >> RGB_SKY_SHIFT) as u8,
+            LightFormat::Scalar => texel as u8 >> SKY_SHIFT,
+        }
+    }
+
+    /// Returns the layout of the texels
+    pub fn format(&self) -> LightFormat {
+        self.format
+    }
+
+    /// Returns the light levels as texels of a 3D texture. The
+    /// texture is `16` texels wide along `x`, `16` texels high
+    /// along `z` and `256` texels deep along `y`. `RGB` texels
+    /// take two bytes in native byte order.
+    pub fn texels(&self) -> &[u8] {
+        &self.texels[..]
+    }
+
+    /// Returns the texel of a block
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the block in the chunk
+    fn texel(&self, index: usize) -> u16 {
+        match self.format {
+            LightFormat::Rgb => u16::from_ne_bytes([self.texels[2 * index], self.texels[2 * index + 1]]),
+            LightFormat::Scalar => self.texels[index] as u16,
+        }
+    }
+}
+
+/// Spreads the light from the queued blocks through all blocks
+/// which aren't opaque, losing one level per block
+///
+/// # Arguments
+///
+/// * `transparent` - Whether light passes through each block of the area
+/// * `levels` - The light levels, including the ones of the queued blocks
+/// * `queue` - The indices of the blocks the light spreads from
+fn spread(transparent: &[bool], levels: &mut [u8], mut queue: VecDeque<usize>) {
+    let offsets = [
+        Vector3::new(1, 0, 0), Vector3::new(-1, 0, 0),
+        Vector3::new(0, 1, 0), Vector3::new(0, -1, 0),
+        Vector3::new(0, 0, 1), Vector3::new(0, 0, -1),
+    ];
+    while let Some(index) = queue.pop_front() {
+        let level = levels[index];
+        if level <= 1 {
+            continue;
+        }
+
+        let loc = area_location_of(index);
+        for offset in offsets.iter() {
+            let neighbour = match area_index_of(loc + offset) {
+                Some(neighbour) if transparent[neighbour] => neighbour,
+                _ => continue,
+            };
+            if levels[neighbour] < level - 1 {
+                levels[neighbour] = level - 1;
+                queue.push_back(neighbour);
+            }
+        }
+    }
+}
+
+/// Returns the locations of the chunks adjacent to a chunk
+/// whose light could change with the block at a location,
+/// i.e. the ones it's at most `REACH` blocks away from
+///
+/// # Arguments
+///
+/// * `chunk` - The location of the chunk
+/// * `loc` - The location of the block in the chunk
+pub fn neighbours_in_reach(chunk: Vector2<i32>, loc: Vector3<i16>) -> impl Iterator<Item = Vector2<i32>> {
+    let distance = |coord: i16, offset: i32| match offset {
+        -1 => coord + 1,
+        1 => CHUNK_SIZE as i16 - coord,
+        _ => 0,
+    };
+    ADJACENT.iter()
+        .filter(move |offset| distance(loc.x, offset.x) + distance(loc.z, offset.y) <= REACH)
+        .map(move |offset| chunk + offset)
+}
+
+/// Converts the emission of a block to its light level
+///
+/// # Arguments
+///
+/// * `emission` - The emission of the block
+fn light_level(emission: f32) -> u8 {
+    (emission * MAX_LIGHT as f32).round().clamp(1.0, MAX_LIGHT as f32) as u8
+}
+
+/// Returns whether a location is within the bounds of a chunk
+fn in_bounds(loc: Vector3<i16>) -> bool {
+    loc.x >= 0 && loc.y >= 0 && loc.z >= 0 &&
+        loc.x < CHUNK_SIZE as i16 && loc.y < CHUNK_HEIGHT as i16 && loc.z < CHUNK_SIZE as i16
+}
+
+/// Returns the index of a location within the bounds of a chunk
+fn index_of(loc: Vector3<i16>) -> usize {
+    CHUNK_AREA * loc.y as usize + CHUNK_SIZE * loc.z as usize + loc.x as usize
+}
+
+/// Returns the location of an index of a chunk
+fn location_of(index: usize) -> Vector3<i16> {
+    Vector3::new(
+        (index % CHUNK_SIZE) as i16,
+        (index / CHUNK_AREA) as i16,
+        (index % CHUNK_AREA / CHUNK_SIZE) as i16,
+    )
+}
+
+/// Returns the index of a location relative to a chunk within
+/// the area light is computed in, or `None` if it's outside
+fn area_index_of(loc: Vector3<i16>) -> Option<usize> {
+    let (x, z) = (loc.x + REACH, loc.z + REACH);
+    if x < 0 || loc.y < 0 || z < 0 ||
+        x >= AREA_SIZE as i16 || loc.y >= CHUNK_HEIGHT as i16 || z >= AREA_SIZE as i16 {
+        return None;
+    }
+    Some(AREA_SIZE * AREA_SIZE * loc.y as usize + AREA_SIZE * z as usize + x as usize)
+}
+
+/// Returns the location relative to a chunk of an index
+/// of the area light is computed in
+fn area_location_of(index: usize) -> Vector3<i16> {
+    Vector3::new(
+        (index % AREA_SIZE) as i16 - REACH,
+        (index / (AREA_SIZE * AREA_SIZE)) as i16,
+        (index % (AREA_SIZE * AREA_SIZE) / AREA_SIZE) as i16 - REACH,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A chunk filled with air, with a glowstone block at
+    /// `(8, 8, 8)` and a stone wall at `x = 10`
+    fn blocks(loc: Vector3<i16>) -> Option<Material> {
+        if !in_bounds(loc) {
+            None
+        } else if loc == Vector3::new(8, 8, 8) {
+            Some(Material::Glowstone)
+        } else if loc.x == 10 {
+            Some(Material::Stone)
+        } else {
+            Some(Material::Air)
+        }
+    }
+
+    #[test]
+    fn indices_and_locations_match() {
+        for loc in [Vector3::new(0, 0, 0), Vector3::new(3, 200, 15), Vector3::new(15, 255, 15)].iter() {
+            assert_eq!(location_of(index_of(*loc)), *loc);
+        }
+    }
+
+    #[test]
+    fn light_spreads_around_glowing_blocks() {
+        let light_map = LightMap::from_blocks(LightFormat::Rgb, blocks);
+        assert_eq!(light_map.level(Vector3::new(8, 8, 8)), MAX_LIGHT);
+        assert_eq!(light_map.level(Vector3::new(8, 9, 8)), MAX_LIGHT - 1);
+        assert_eq!(light_map.level(Vector3::new(6, 7, 8)), MAX_LIGHT - 3);
+        assert_eq!(light_map.level(Vector3::new(8, 8 + MAX_LIGHT as i16, 8)), 0);
+
+        // Opaque blocks block the light
+        assert_eq!(light_map.level(Vector3::new(9, 8, 8)), MAX_LIGHT - 1);
+        assert_eq!(light_map.level(Vector3::new(10, 8, 8)), 0);
+        assert_eq!(light_map.level(Vector3::new(11, 8, 8)), 0);
+
+        assert_eq!(light_map.level(Vector3::new(-1, 8, 8)), 0);
+        assert_eq!(light_map.texels().len(), LightFormat::Rgb.texel_size() * CHUNK_VOLUME);
+    }
+
+    #[test]
+    fn sky_light_falls_into_caves() {
+        // A stone roof at `y = 100` with a hole at `(8, 8)`
+        let light_map = LightMap::from_blocks(LightFormat::Rgb, |loc| {
+            if !in_bounds(loc) {
+                None
+            } else if loc.y == 100 && (loc.x, loc.z) != (8, 8) {
+                Some(Material::Stone)
+            } else {
+                Some(Material::Air)
+            }
+        });
+        assert_eq!(light_map.sky_level(Vector3::new(2, 120, 2)), MAX_LIGHT);
+        assert_eq!(light_map.sky_level(Vector3::new(8, 50, 8)), MAX_LIGHT);
+        assert_eq!(light_map.sky_level(Vector3::new(5, 99, 8)), MAX_LIGHT - 3);
+        assert_eq!(light_map.sky_level(Vector3::new(0, 99, 1)), 0);
+        assert_eq!(light_map.level(Vector3::new(8, 50, 8)), 0);
+
+        // Both levels share the texels, in either format
+        let glowing = LightMap::from_blocks(LightFormat::Scalar, blocks);
+        let texel = glowing.texels()[index_of(Vector3::new(8, 9, 8))];
+        assert_eq!(texel, (MAX_LIGHT - 1) | SKY_LIT_TEXEL);
+        let colored = LightMap::from_blocks(LightFormat::Rgb, blocks);
+        assert_eq!(colored.sky_level(Vector3::new(8, 9, 8)), MAX_LIGHT);
+    }
+
+    #[test]
+    fn lights_tint_their_surroundings() {
+        let rgb = LightMap::from_blocks(LightFormat::Rgb, blocks);
+        let scalar = LightMap::from_blocks(LightFormat::Scalar, blocks);
+        let [red, green, blue] = rgb.color(Vector3::new(8, 8, 8));
+        assert_eq!(red, MAX_LIGHT);
+        assert!(red > green && green > blue);
+
+        // Each channel fades on its own, the brightest one like scalar light
+        let [red, green, blue] = rgb.color(Vector3::new(8, 11, 8));
+        assert_eq!((red, green), (MAX_LIGHT - 3, rgb.color(Vector3::new(8, 8, 8))[1] - 3));
+        assert_eq!(blue, rgb.color(Vector3::new(8, 8, 8))[2] - 3);
+        assert_eq!(rgb.level(Vector3::new(8, 11, 8)), scalar.level(Vector3::new(8, 11, 8)));
+        assert_eq!(scalar.color(Vector3::new(8, 11, 8)), [MAX_LIGHT - 3; 3]);
+        assert_eq!(rgb.color(Vector3::new(11, 8, 8)), [0; 3]);
+    }
+
+    #[test]
+    fn light_spreads_across_chunk_borders() {
+        // A roofed chunk next to a chunk with a glowstone block at its border
+        let chunk = Chunk::new(Vector2::new(0, 0));
+        let neighbour = Chunk::new(Vector2::new(1, 0));
+        for x in 0..CHUNK_SIZE as i16 {
+            for z in 0..CHUNK_SIZE as i16 {
+                chunk.set_block(Vector3::new(x, 100, z), Material::Stone);
+                neighbour.set_block(Vector3::new(x, 100, z), Material::Stone);
+            }
+        }
+        neighbour.set_block(Vector3::new(0, 8, 8), Material::Glowstone);
+
+        let light_map = LightMap::compute(&chunk, &[neighbour.clone(), Chunk::new(Vector2::new(3, 0))], LightFormat::Rgb);
+        assert_eq!(light_map.level(Vector3::new(15, 8, 8)), MAX_LIGHT - 1);
+        assert_eq!(light_map.level(Vector3::new(10, 8, 8)), MAX_LIGHT - 6);
+        assert_eq!(light_map.sky_level(Vector3::new(15, 99, 8)), 0);
+
+        // Missing neighbours don't let any light through
+        let alone = LightMap::compute(&chunk, &[], LightFormat::Rgb);
+        assert_eq!(alone.level(Vector3::new(15, 8, 8)), 0);
+        assert_eq!(alone.sky_level(Vector3::new(15, 120, 8)), MAX_LIGHT);
+    }
+
+    #[test]
+    fn blocks_near_the_border_reach_adjacent_chunks() {
+        let chunk = Vector2::new(2, -1);
+        let center: Vec<_> = neighbours_in_reach(chunk, Vector3::new(7, 50, 7)).collect();
+        assert_eq!(center, vec![Vector2::new(2, -2), Vector2::new(1, -1), Vector2::new(3, -1), Vector2::new(2, 0)]);
+
+        let corner: Vec<_> = neighbours_in_reach(chunk, Vector3::new(15, 50, 0)).collect();
+        assert_eq!(corner, vec![Vector2::new(2, -2), Vector2::new(3, -2), Vector2::new(3, -1)]);
+
+        let edge: Vec<_> = neighbours_in_reach(chunk, Vector3::new(0, 50, 3)).collect();
+        assert_eq!(edge, vec![Vector2::new(1, -2), Vector2::new(2, -2), Vector2::new(1, -1), Vector2::new(1, 0), Vector2::new(2, 0)]);
+    }
+}
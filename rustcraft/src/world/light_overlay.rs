@@ -0,0 +1,312 @@
+//! A debug overlay tinting the surfaces mobs could stand on
+//! by their block light level, which helps to light up an
+//! area until nothing could spawn in it anymore
+
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::mesh::{MeshBuilder, Model};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::shader::ShaderProgram;
+use crate::resources::Resources;
+use crate::world::World;
+use crate::world::chunk::Chunk;
+use crate::world::history::BlockEdit;
+use crate::world::light::{self, LightFormat, LightMap, MAX_LIGHT};
+use cgmath::{Matrix4, SquareMatrix, Vector2, Vector3};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The horizontal distance in blocks up to which
+/// surfaces around the player are tinted
+pub const OVERLAY_RADIUS: i32 = 12;
+/// The vertical distance in blocks up to which
+/// surfaces above and below the player are tinted
+pub const OVERLAY_HEIGHT: i32 = 6;
+/// The lowest block light level at which no mob spawns
+pub const SAFE_LIGHT: u8 = 8;
+/// The time in seconds between two refreshes of the overlay
+const REFRESH_INTERVAL: f32 = 0.5;
+
+/// LitSurface
+///
+/// The top face of a block mobs could stand on
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LitSurface {
+    /// The position of the block in world coordinates
+    pub pos: Vector3<i32>,
+    /// The block light level of the space above the block
+    pub level: u8,
+}
+
+/// LightOverlay
+///
+/// The surfaces around the player with their light levels,
+/// which are refreshed periodically while the overlay is shown.
+/// The light maps of the chunks are kept until one of their
+/// blocks changes.
+#[derive(Default)]
+pub struct LightOverlay {
+    /// The light maps of the chunks around the player
+    light_maps: HashMap<Vector2<i32>, LightMap>,
+    /// The surfaces of the last refresh
+    surfaces: Arc<Vec<LitSurface>>,
+    /// The time in seconds since the last refresh
+    since_refresh: f32,
+}
+
+impl LightOverlay {
+    /// Discards the light maps of the chunks whose
+    /// blocks have changed
+    ///
+    /// # Arguments
+    ///
+    /// * `changes` - The changed blocks
+    pub fn invalidate(&mut self, changes: &[BlockEdit]) {
+        for change in changes {
+            let (loc, local) = World::split_pos(change.pos);
+            for loc in std::iter::once(loc).chain(light::neighbours_in_reach(loc, local)) {
+                if self.light_maps.remove(&loc).is_some() {
+                    // Refresh with the next update, so the
+                    // overlay follows torches immediately
+                    self.since_refresh = REFRESH_INTERVAL;
+                }
+            }
+        }
+    }
+
+    /// Refreshes the surfaces around a position once the
+    /// refresh interval has passed
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world
+    /// * `center` - The position of the player in world coordinates
+    /// * `dt` - The time passed since the last update
+    pub fn update(&mut self, world: &World, center: Vector3<i32>, dt: f32) {
+        self.since_refresh += dt;
+        if self.since_refresh < REFRESH_INTERVAL {
+            return;
+        }
+        self.since_refresh = 0.0;
+
+        let (min, _) = World::split_pos(center - Vector3::new(OVERLAY_RADIUS, 0, OVERLAY_RADIUS));
+        let (max, _) = World::split_pos(center + Vector3::new(OVERLAY_RADIUS, 0, OVERLAY_RADIUS));
+        self.light_maps.retain(|loc, _| loc.x >= min.x && loc.x <= max.x && loc.y >= min.y && loc.y <= max.y);
+        for x in min.x..=max.x {
+            for z in min.y..=max.y {
+                let loc = Vector2::new(x, z);
+                if self.light_maps.contains_key(&loc) {
+                    continue;
+                }
+                if let Some(chunk) = world.chunk(&loc).filter(|chunk| chunk.is_generated()) {
+                    let neighbours: Vec<Chunk> = light::ADJACENT.iter()
+                        .filter_map(|offset| world.chunk(&(loc + offset)))
+                        .filter(|neighbour| neighbour.is_generated())
+                        .cloned()
+                        .collect();
+                    // The overlay shows the levels alone, which scalar light has as well
+                    self.light_maps.insert(loc, LightMap::compute(chunk, &neighbours, LightFormat::Scalar));
+                }
+            }
+        }
+        self.surfaces = Arc::new(lit_surfaces(world, &self.light_maps, center));
+    }
+
+    /// Returns the surfaces of the last refresh
+    pub fn surfaces(&self) -> &Arc<Vec<LitSurface>> {
+        &self.surfaces
+    }
+}
+
+/// Returns the surfaces around a position with the light levels
+/// of the given light maps. Mobs could stand on solid blocks
+/// other than fluids whose space above is neither solid nor
+/// a fluid.
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `light_maps` - The light maps of the chunks around the position
+/// * `center` - The position in world coordinates
+fn lit_surfaces(world: &World, light_maps: &HashMap<Vector2<i32>, LightMap>, center: Vector3<i32>) -> Vec<LitSurface> {
+    let mut surfaces = Vec::new();
+    for x in center.x - OVERLAY_RADIUS..=center.x + OVERLAY_RADIUS {
+        for z in center.z - OVERLAY_RADIUS..=center.z + OVERLAY_RADIUS {
+            for y in center.y - OVERLAY_HEIGHT..=center.y + OVERLAY_HEIGHT {
+                let pos = Vector3::new(x, y, z);
+                let above = pos + Vector3::unit_y();
+                let standable = matches!(world.block(pos), Some(block) if block.is_solid() && !block.is_fluid())
+                    && matches!(world.block(above), Some(block) if !block.is_solid() && !block.is_fluid());
+                if !standable {
+                    continue;
+                }
+                let (loc, local) = World::split_pos(above);
+                if let Some(light_map) = light_maps.get(&loc) {
+                    surfaces.push(LitSurface { pos, level: light_map.level(local) });
+                }
+            }
+        }
+    }
+    surfaces
+}
+
+/// Returns the color surfaces of a light level are tinted
+/// with, from red in the dark over yellow to green once
+/// mobs can't spawn anymore
+///
+/// # Arguments
+///
+/// * `level` - The block light level
+fn tint(level: u8) -> [f32; 4] {
+    if level >= SAFE_LIGHT {
+        [0.2, 0.9, 0.2, 0.35]
+    } else {
+        let t = level as f32 / (SAFE_LIGHT - 1) as f32;
+        [1.0, 0.15 + 0.75 * t, 0.1, 0.45]
+    }
+}
+
+/// LightOverlayFrame
+///
+/// A frame the light overlay could be rendered for
+pub trait LightOverlayFrame: Frame {
+    /// Returns the surfaces which should be tinted,
+    /// or `None` if the overlay is hidden
+    fn lit_surfaces(&self) -> Option<&Arc<Vec<LitSurface>>>;
+}
+
+/// LightOverlayRenderer
+///
+/// The `LightOverlayRenderer` is the debug render pass
+/// tinting the top faces of the surfaces by their light
+/// level. The surfaces of each level are batched into a
+/// single model, which is rebuilt whenever the overlay
+/// has been refreshed.
+pub struct LightOverlayRenderer {
+    /// The render backend
+    backend: GlBackend,
+    /// The shader program the surfaces are drawn with
+    shader_program: ShaderProgram,
+    /// The surfaces the models have been built from
+    surfaces: Option<Arc<Vec<LitSurface>>>,
+    /// The models of the surfaces by their light level
+    models: Vec<(u8, Model)>,
+}
+
+impl LightOverlayRenderer {
+    /// Creates a new light overlay renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        let shader_program = backend.create_pipeline(resources, "prop").unwrap();
+        shader_program.disable();
+
+        Self {
+            backend: backend.clone(),
+            shader_program,
+            surfaces: None,
+            models: Vec::new(),
+        }
+    }
+
+    /// Rebuilds the models of the surfaces
+    ///
+    /// # Arguments
+    ///
+    /// * `surfaces` - The surfaces
+    fn rebuild(&mut self, surfaces: &[LitSurface]) {
+        let mut builders: Vec<Option<MeshBuilder>> = (0..=MAX_LIGHT).map(|_| None).collect();
+        for surface in surfaces {
+            // Float a little above the face, so it doesn't flicker
+            let min = surface.pos.cast::<f32>().unwrap() + Vector3::new(0.05, 1.01, 0.05);
+            let corners = [
+                min + Vector3::new(0.0, 0.0, 0.9),
+                min + Vector3::new(0.9, 0.0, 0.9),
+                min + Vector3::new(0.9, 0.0, 0.0),
+                min,
+            ];
+            builders[surface.level.min(MAX_LIGHT) as usize]
+                .get_or_insert_with(MeshBuilder::new)
+                .quad(corners, Vector3::unit_y());
+        }
+        self.models = builders.into_iter()
+            .enumerate()
+            .filter_map(|(level, builder)| Some((level as u8, Model::from_mesh(&self.backend, &builder?.build()))))
+            .collect();
+    }
+}
+
+impl<F: LightOverlayFrame> RenderPass<F> for LightOverlayRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Transparent
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let surfaces = match ctx.frame.lit_surfaces() {
+            Some(surfaces) => surfaces,
+            None => {
+                self.surfaces = None;
+                self.models.clear();
+                return;
+            }
+        };
+        if !matches!(&self.surfaces, Some(built) if Arc::ptr_eq(built, surfaces)) {
+            self.rebuild(surfaces);
+            self.surfaces = Some(surfaces.clone());
+        }
+
+        let camera = ctx.frame.camera();
+        self.shader_program.enable();
+        self.shader_program.set_uniform_mat4f("u_MVP", &(camera.proj_matrix() * camera.view_matrix()));
+        self.shader_program.set_uniform_mat4f("u_Model", &Matrix4::identity());
+        for (level, model) in &self.models {
+            let [r, g, b, a] = tint(*level);
+            self.shader_program.set_uniform_4f("u_Color", r, g, b, a);
+            model.bind();
+            ctx.renderer.draw_with(model.va(), model.ib(), &self.shader_program, model.topology());
+            model.unbind();
+        }
+        self.shader_program.disable();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::Material;
+    use crate::world::terrain_generator::FlatTerrainGen;
+    use crate::world::time::SkySettings;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn surfaces_are_lit_by_glowing_blocks() {
+        let mut world = World::with_generator(FlatTerrainGen {}, SkySettings::default());
+        world.load_chunk(&Vector2::new(0, 0));
+        let start = Instant::now();
+        while !world.chunks()[0].is_generated() {
+            assert!(start.elapsed() < Duration::from_secs(10), "the chunk hasn't been generated");
+            thread::yield_now();
+        }
+
+        let mut overlay = LightOverlay::default();
+        let center = Vector3::new(8, 5, 8);
+        overlay.update(&world, center, REFRESH_INTERVAL);
+        let level_at = |overlay: &LightOverlay, x: i32, z: i32| overlay.surfaces().iter()
+            .find(|surface| surface.pos == Vector3::new(x, 4, z))
+            .map(|surface| surface.level);
+        assert_eq!(level_at(&overlay, 8, 8), Some(0));
+        // Chunks which aren't loaded have no surfaces
+        assert_eq!(level_at(&overlay, -1, 8), None);
+
+        let since = world.changes().len();
+        world.set_block(Vector3::new(8, 5, 8), Material::Lava);
+        overlay.invalidate(&world.changes()[since..]);
+        overlay.update(&world, center, 0.0);
+        assert_eq!(level_at(&overlay, 8, 8), None, "mobs don't stand in fluids");
+        assert!(level_at(&overlay, 9, 8).unwrap() >= SAFE_LIGHT);
+        assert!(level_at(&overlay, 15, 15).unwrap() < SAFE_LIGHT);
+    }
+}
@@ -0,0 +1,214 @@
+//! Types to define the loot of chests, e.g. of dungeons
+
+use crate::scripting::ScriptEngine;
+use crate::world::ticks::TickRng;
+use mlua::Table;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// LootEntry
+///
+/// An entry of a loot table, which is picked by
+/// its weight among the other entries
+#[derive(Clone, Debug, PartialEq)]
+pub struct LootEntry {
+    /// The name of the item
+    pub item: String,
+    /// The weight of the entry relative to the others
+    pub weight: u32,
+    /// The least amount of items the entry gives
+    pub min: u64,
+    /// The largest amount of items the entry gives
+    pub max: u64,
+}
+
+/// LootTable
+///
+/// The items a chest could contain
+#[derive(Clone, Debug, PartialEq)]
+pub struct LootTable {
+    /// The amount of entries which are picked
+    pub rolls: u32,
+    /// The entries the picked ones are chosen from
+    pub entries: Vec<LootEntry>,
+}
+
+/// LootTables
+///
+/// The loot tables defined by scripts through the
+/// `loot` API, e.g.
+///
+/// ```lua
+/// loot.register("dungeon", {
+///     -- The amount of entries which are picked
+///     rolls = 3,
+///     entries = {
+///         { item = "glowstone", weight = 2, min = 1, max = 4 },
+///         { item = "wheat", weight = 5, min = 2, max = 8 },
+///     },
+/// })
+/// ```
+///
+/// The same entry could be picked several times. Loot
+/// tables registered again replace their previous
+/// definition.
+pub struct LootTables {
+    /// The loot tables by their name, shared with the Lua API
+    tables: Arc<Mutex<HashMap<String, LootTable>>>,
+    /// The generator picking the entries
+    rng: TickRng,
+}
+
+impl LootTables {
+    /// Creates a new loot table registry and registers
+    /// its API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let tables = Arc::new(Mutex::new(HashMap::new()));
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let registered = tables.clone();
+        api.set("register", lua.create_function(move |_, (name, def): (String, Table)| {
+            let table = loot_table_from_table(def)?;
+            registered.lock().unwrap().insert(name, table);
+            Ok(())
+        })?)?;
+        lua.globals().set("loot", api)?;
+
+        Ok(Self {
+            tables,
+            rng: TickRng::from_time(),
+        })
+    }
+
+    /// Returns the loot table with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the loot table
+    pub fn table(&self, name: &str) -> Option<LootTable> {
+        self.tables.lock().unwrap().get(name).cloned()
+    }
+
+    /// Rolls a loot table and returns the picked items with
+    /// their amount. Unknown loot tables give nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the loot table
+    pub fn roll(&mut self, name: &str) -> Vec<(String, u64)> {
+        let table = match self.table(name) {
+            Some(table) => table,
+            None => {
+                log::warn!("Unknown loot table {}", name);
+                return Vec::new();
+            }
+        };
+
+        let total: u32 = table.entries.iter().map(|entry| entry.weight).sum();
+        if total == 0 {
+            return Vec::new();
+        }
+
+        let mut items = Vec::new();
+        for _ in 0..table.rolls {
+            let mut pick = self.rng.below(total as usize) as u32;
+            let entry = table.entries.iter()
+                .find(|entry| {
+                    if pick < entry.weight {
+                        return true;
+                    }
+                    pick -= entry.weight;
+                    false
+                })
+                .expect("the pick is below the total weight");
+
+            let count = entry.min + self.rng.below((entry.max - entry.min + 1) as usize) as u64;
+            if count > 0 {
+                items.push((entry.item.clone(), count));
+            }
+        }
+        items
+    }
+}
+
+/// Reads a loot table from a Lua table
+///
+/// # Arguments
+///
+/// * `def` - The Lua table
+fn loot_table_from_table(def: Table) -> mlua::Result<LootTable> {
+    let mut entries = Vec::new();
+    for entry in def.get::<_, Table>("entries")?.sequence_values::<Table>() {
+        let entry = entry?;
+        let min = entry.get::<_, Option<u64>>("min")?.unwrap_or(1);
+        let max = entry.get::<_, Option<u64>>("max")?.unwrap_or(min);
+        if max < min {
+            return Err(mlua::Error::RuntimeError("the max of a loot entry is below its min".to_string()));
+        }
+        entries.push(LootEntry {
+            item: entry.get("item")?,
+            weight: entry.get::<_, Option<u32>>("weight")?.unwrap_or(1),
+            min,
+            max,
+        });
+    }
+
+    Ok(LootTable {
+        rolls: def.get::<_, Option<u32>>("rolls")?.unwrap_or(1),
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loot() -> (ScriptEngine, LootTables) {
+        let engine = ScriptEngine::new();
+        let loot = LootTables::new(&engine).unwrap();
+        engine.exec("test", r#"
+            loot.register("dungeon", {
+                rolls = 20,
+                entries = {
+                    { item = "glowstone", weight = 1, min = 1, max = 3 },
+                    { item = "wheat", weight = 3, min = 2 },
+                    { item = "dust", weight = 0 },
+                },
+            })
+            loot.register("empty", { entries = {} })
+        "#).unwrap();
+        (engine, loot)
+    }
+
+    #[test]
+    fn loot_tables_are_rolled() {
+        let (_engine, mut loot) = loot();
+        let items = loot.roll("dungeon");
+        assert_eq!(items.len(), 20);
+        for (item, count) in items {
+            match item.as_str() {
+                "glowstone" => assert!((1..=3).contains(&count)),
+                "wheat" => assert_eq!(count, 2),
+                _ => panic!("unexpected item {}", item),
+            }
+        }
+
+        assert!(loot.roll("empty").is_empty());
+        assert!(loot.roll("treasure").is_empty());
+    }
+
+    #[test]
+    fn invalid_loot_tables_are_rejected() {
+        let (engine, loot) = loot();
+        assert!(engine.exec("test", r#"loot.register("treasure", {})"#).is_err());
+        assert!(engine.exec("test", r#"loot.register("treasure", { entries = { { weight = 1 } } })"#).is_err());
+        assert!(engine.exec("test", r#"loot.register("treasure", { entries = { { item = "dirt", min = 3, max = 2 } } })"#).is_err());
+        assert!(loot.table("treasure").is_none());
+        assert_eq!(loot.table("dungeon").unwrap().rolls, 20);
+    }
+}
@@ -0,0 +1,435 @@
+//! Types to break blocks over time with tools, and to
+//! define the items broken blocks drop
+
+use crate::scripting::{self, ScriptEngine};
+use crate::world::block::Material;
+use crate::world::tags::Tags;
+use crate::world::ticks::TickRng;
+use cgmath::Vector3;
+use mlua::Table;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// ToolDef
+///
+/// A tool the player could hold to break blocks faster.
+/// Each broken block wears the tool down by one, until it
+/// breaks once its durability is used up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolDef {
+    /// The name of the tool, e.g. `pickaxe`
+    name: String,
+    /// The amount of blocks the tool breaks before it breaks itself
+    durability: u32,
+    /// The factors the tool speeds up breaking blocks with,
+    /// by their material. Other blocks are broken as fast
+    /// as by hand.
+    speeds: HashMap<Material, f32>,
+}
+
+impl ToolDef {
+    /// Returns the name of the tool
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the amount of blocks the tool breaks
+    /// before it breaks itself
+    pub fn durability(&self) -> u32 {
+        self.durability
+    }
+
+    /// Returns the factor the tool speeds up breaking
+    /// blocks of the given material with
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    pub fn speed(&self, material: Material) -> f32 {
+        self.speeds.get(&material).copied().unwrap_or(1.0)
+    }
+}
+
+/// HeldTool
+///
+/// The tool the player holds and how much it's worn,
+/// which is persisted with the player data
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeldTool {
+    /// The name of the tool
+    pub name: String,
+    /// The amount of blocks the tool broke so far
+    pub wear: u32,
+}
+
+impl HeldTool {
+    /// Creates a new tool which isn't worn at all
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the tool
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            wear: 0,
+        }
+    }
+
+    /// Wears the tool down by a broken block. Returns whether
+    /// the tool broke, as its durability is used up.
+    ///
+    /// # Arguments
+    ///
+    /// * `durability` - The durability of the tool
+    pub fn wear_down(&mut self, durability: u32) -> bool {
+        self.wear += 1;
+        self.wear >= durability
+    }
+}
+
+/// BlockDrop
+///
+/// An entry of the drop table of a block
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockDrop {
+    /// The name of the dropped item
+    pub item: String,
+    /// The amount of dropped items
+    pub count: u64,
+    /// The chance the items are dropped in the range `[0, 1]`
+    pub chance: f64,
+    /// The tool the block needs to be broken with to drop
+    /// the items, or `None` if they are dropped anyway
+    pub tool: Option<String>,
+}
+
+/// BreakProgress
+///
+/// How far the player broke the block they are looking at.
+/// The progress is lost as soon as they look at another block.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BreakProgress {
+    /// The position of the block in world coordinates
+    pub block: Vector3<i32>,
+    /// The material of the block
+    pub material: Material,
+    /// The progress in the range `[0, 1]`, where `1` is broken
+    pub progress: f32,
+}
+
+impl BreakProgress {
+    /// Starts breaking a block
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The position of the block in world coordinates
+    /// * `material` - The material of the block
+    pub fn new(block: Vector3<i32>, material: Material) -> Self {
+        Self {
+            block,
+            material,
+            progress: 0.0,
+        }
+    }
+
+    /// Advances the progress and returns whether the block is broken
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time the block has been broken for since the last advance
+    /// * `break_time` - The time in seconds it takes to break the block
+    pub fn advance(&mut self, seconds: f32, break_time: f32) -> bool {
+        self.progress = if break_time <= 0.0 {
+            1.0
+        } else {
+            (self.progress + seconds / break_time).min(1.0)
+        };
+        self.progress >= 1.0
+    }
+}
+
+/// Mining
+///
+/// The tools and drop tables defined by scripts through the
+/// `tools` and `blocks` API, e.g.
+///
+/// ```lua
+/// tools.define {
+///     name = "pickaxe",
+///     durability = 250,
+///     -- The factors breaking blocks is sped up with,
+///     -- by block or by tag
+///     speed = { stone = 4.0, ["#ores"] = 2.0 },
+/// }
+///
+/// blocks.drops {
+///     block = "stone",
+///     drops = {
+///         -- Stone only drops cobblestone if it's broken with a pickaxe
+///         { item = "cobblestone", count = 1, chance = 1.0, tool = "pickaxe" },
+///     },
+/// }
+/// ```
+///
+/// Drop tables of a tag are the ones of all of its blocks.
+/// Blocks without a drop table drop themselves. Tools and drop
+/// tables defined again replace their previous definition.
+pub struct Mining {
+    /// The tools by their name, shared with the Lua API
+    tools: Arc<Mutex<BTreeMap<String, ToolDef>>>,
+    /// The drop tables by the material of the block, shared
+    /// with the Lua API
+    drops: Arc<Mutex<HashMap<Material, Vec<BlockDrop>>>>,
+    /// The generator rolling the chances of the drops
+    rng: TickRng,
+}
+
+impl Mining {
+    /// Creates a new tool and drop table registry and
+    /// registers their API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `tags` - The tags definitions could refer to
+    pub fn new(engine: &ScriptEngine, tags: &Tags) -> mlua::Result<Self> {
+        let tools = Arc::new(Mutex::new(BTreeMap::new()));
+        let drops = Arc::new(Mutex::new(HashMap::new()));
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let defined = tools.clone();
+        let tool_tags = tags.clone();
+        api.set("define", lua.create_function(move |_, def: Table| {
+            let tool = tool_from_table(def, &tool_tags)?;
+            defined.lock().unwrap().insert(tool.name.clone(), tool);
+            Ok(())
+        })?)?;
+        lua.globals().set("tools", api)?;
+
+        let api = scripting::api_table(lua, "blocks")?;
+        let tables = drops.clone();
+        let tags = tags.clone();
+        api.set("drops", lua.create_function(move |_, def: Table| {
+            let (materials, drops) = drops_from_table(def, &tags)?;
+            let mut tables = tables.lock().unwrap();
+            for material in materials {
+                tables.insert(material, drops.clone());
+            }
+            Ok(())
+        })?)?;
+
+        Ok(Self {
+            tools,
+            drops,
+            rng: TickRng::from_time(),
+        })
+    }
+
+    /// Returns the tool with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the tool
+    pub fn tool(&self, name: &str) -> Option<ToolDef> {
+        self.tools.lock().unwrap().get(name).cloned()
+    }
+
+    /// Returns the tool following the given one in the order of
+    /// their names, or `None` for the bare hand after the last one
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - The name of the current tool, `None` for the bare hand
+    pub fn next_tool(&self, current: Option<&str>) -> Option<String> {
+        let tools = self.tools.lock().unwrap();
+        match current {
+            Some(current) => tools.keys().find(|name| name.as_str() > current).cloned(),
+            None => tools.keys().next().cloned(),
+        }
+    }
+
+    /// Returns the time in seconds it takes to break a block
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    /// * `tool` - The name of the tool the block is broken with
+    pub fn break_time(&self, material: Material, tool: Option<&str>) -> f32 {
+        let speed = tool.and_then(|tool| self.tool(tool)).map_or(1.0, |tool| tool.speed(material));
+        material.data().hardness() / speed.max(f32::EPSILON)
+    }
+
+    /// Rolls the drop table of a broken block and returns the
+    /// dropped items with their amount
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    /// * `tool` - The name of the tool the block has been broken with
+    pub fn drops(&mut self, material: Material, tool: Option<&str>) -> Vec<(String, u64)> {
+        if material == Material::Air {
+            return Vec::new();
+        }
+
+        let drops = self.drops.lock().unwrap();
+        let table = match drops.get(&material) {
+            Some(table) => table,
+            None => return vec![(material.name().to_string(), 1)],
+        };
+
+        let rng = &mut self.rng;
+        table.iter()
+            .filter(|drop| drop.tool.is_none() || drop.tool.as_deref() == tool)
+            .filter(|drop| drop.count > 0 && rng.unit() < drop.chance)
+            .map(|drop| (drop.item.clone(), drop.count))
+            .collect()
+    }
+}
+
+/// Reads the definition of a tool from a Lua table
+///
+/// # Arguments
+///
+/// * `def` - The Lua table
+/// * `tags` - The tags the speeds could refer to
+fn tool_from_table(def: Table, tags: &Tags) -> mlua::Result<ToolDef> {
+    let durability: u32 = def.get("durability")?;
+    if durability == 0 {
+        return Err(mlua::Error::RuntimeError("a tool needs a durability".to_string()));
+    }
+
+    let mut speeds = HashMap::new();
+    if let Some(table) = def.get::<_, Option<Table>>("speed")? {
+        for pair in table.pairs::<String, f32>() {
+            let (name, speed) = pair?;
+            if speed <= 0.0 {
+                return Err(mlua::Error::RuntimeError(format!("the speed for {} needs to be positive", name)));
+            }
+            for material in tags.materials(&name)? {
+                speeds.insert(material, speed);
+            }
+        }
+    }
+
+    Ok(ToolDef {
+        name: def.get("name")?,
+        durability,
+        speeds,
+    })
+}
+
+/// Reads the drop table of a block or the blocks of a tag
+/// from a Lua table
+///
+/// # Arguments
+///
+/// * `def` - The Lua table
+/// * `tags` - The tags the block could refer to
+fn drops_from_table(def: Table, tags: &Tags) -> mlua::Result<(Vec<Material>, Vec<BlockDrop>)> {
+    let materials = tags.materials(&def.get::<_, String>("block")?)?;
+
+    let mut drops = Vec::new();
+    for drop in def.get::<_, Table>("drops")?.sequence_values::<Table>() {
+        let drop = drop?;
+        drops.push(BlockDrop {
+            item: drop.get("item")?,
+            count: drop.get::<_, Option<u64>>("count")?.unwrap_or(1),
+            chance: drop.get::<_, Option<f64>>("chance")?.unwrap_or(1.0).clamp(0.0, 1.0),
+            tool: drop.get("tool")?,
+        });
+    }
+    Ok((materials, drops))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mining() -> (ScriptEngine, Mining) {
+        let engine = ScriptEngine::new();
+        let tags = Tags::new(&engine).unwrap();
+        let mining = Mining::new(&engine, &tags).unwrap();
+        engine.exec("test", r##"
+            tags.add("#liquids", { "water", "lava" })
+            tools.define { name = "shovel", durability = 2, speed = { dirt = 5.0 } }
+            tools.define { name = "pickaxe", durability = 100, speed = { stone = 3.0 } }
+            blocks.drops {
+                block = "stone",
+                drops = {
+                    { item = "cobblestone", tool = "pickaxe" },
+                    { item = "pebble", count = 3, chance = 0.0 },
+                },
+            }
+            blocks.drops { block = "grass", drops = { { item = "dirt" } } }
+            blocks.drops { block = "#liquids", drops = {} }
+        "##).unwrap();
+        (engine, mining)
+    }
+
+    #[test]
+    fn tools_speed_up_breaking() {
+        let (_engine, mining) = mining();
+        assert_eq!(mining.break_time(Material::Stone, None), Material::Stone.data().hardness());
+        assert_eq!(mining.break_time(Material::Stone, Some("pickaxe")), Material::Stone.data().hardness() / 3.0);
+        assert_eq!(mining.break_time(Material::Stone, Some("shovel")), Material::Stone.data().hardness());
+        assert_eq!(mining.break_time(Material::Dirt, Some("shovel")), Material::Dirt.data().hardness() / 5.0);
+        assert_eq!(mining.break_time(Material::Dirt, Some("spoon")), Material::Dirt.data().hardness());
+    }
+
+    #[test]
+    fn drop_tables_are_rolled() {
+        let (_engine, mut mining) = mining();
+        assert_eq!(mining.drops(Material::Stone, Some("pickaxe")), vec![("cobblestone".to_string(), 1)]);
+        assert!(mining.drops(Material::Stone, None).is_empty());
+        assert_eq!(mining.drops(Material::Grass, None), vec![("dirt".to_string(), 1)]);
+        assert!(mining.drops(Material::Water, None).is_empty());
+        assert!(mining.drops(Material::Lava, None).is_empty());
+        assert!(mining.drops(Material::Air, None).is_empty());
+
+        // Blocks without a drop table drop themselves
+        assert_eq!(mining.drops(Material::Glowstone, None), vec![("glowstone".to_string(), 1)]);
+    }
+
+    #[test]
+    fn tools_are_cycled_by_name() {
+        let (_engine, mining) = mining();
+        assert_eq!(mining.next_tool(None).as_deref(), Some("pickaxe"));
+        assert_eq!(mining.next_tool(Some("pickaxe")).as_deref(), Some("shovel"));
+        assert_eq!(mining.next_tool(Some("shovel")), None);
+        assert_eq!(mining.tool("shovel").unwrap().durability(), 2);
+    }
+
+    #[test]
+    fn tools_wear_down() {
+        let mut tool = HeldTool::new("shovel");
+        assert!(!tool.wear_down(2));
+        assert!(tool.wear_down(2));
+    }
+
+    #[test]
+    fn blocks_break_over_time() {
+        let mut progress = BreakProgress::new(Vector3::new(0, 0, 0), Material::Stone);
+        assert!(!progress.advance(0.5, 1.5));
+        assert!(!progress.advance(0.5, 1.5));
+        assert!(progress.advance(0.5, 1.5));
+        assert_eq!(progress.progress, 1.0);
+
+        assert!(BreakProgress::new(Vector3::new(0, 0, 0), Material::Wheat).advance(0.0, 0.0));
+    }
+
+    #[test]
+    fn invalid_definitions_are_rejected() {
+        let (engine, mut mining) = mining();
+        assert!(engine.exec("test", r#"tools.define { name = "axe", durability = 0 }"#).is_err());
+        assert!(engine.exec("test", r#"tools.define { name = "axe", durability = 10, speed = { cheese = 2 } }"#).is_err());
+        assert!(engine.exec("test", r#"tools.define { name = "axe", durability = 10, speed = { dirt = 0 } }"#).is_err());
+        assert!(engine.exec("test", r#"blocks.drops { block = "cheese", drops = {} }"#).is_err());
+        assert!(engine.exec("test", r##"blocks.drops { block = "#cheeses", drops = {} }"##).is_err());
+        assert!(engine.exec("test", r#"blocks.drops { block = "dirt", drops = { { count = 1 } } }"#).is_err());
+        assert!(mining.tool("axe").is_none());
+        assert_eq!(mining.drops(Material::Dirt, None), vec![("dirt".to_string(), 1)]);
+    }
+}
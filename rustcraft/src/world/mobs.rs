@@ -0,0 +1,694 @@
+//! Types representing mobs walking around in the world,
+//! e.g. the ones spawned by the spawners of dungeons or
+//! the wolves players tame
+
+use crate::entity::Aabb;
+use crate::graphics::mesh::{Mesh, MeshHandle};
+use crate::graphics::scene::{NodeId, Scene, SceneNode};
+use crate::world::block::Material;
+use crate::world::block_entity::BlockEntity;
+use crate::world::chunk_entities::{self, ChunkEntities, ChunkEntity, EntityUuid, SavedEntity, MOB_TYPE};
+use crate::world::items;
+use crate::world::ticks::TickRng;
+use cgmath::{InnerSpace, Matrix4, Vector2, Vector3};
+
+/// The width and depth of mobs in blocks
+const MOB_WIDTH: f32 = 0.6;
+/// The height of mobs in blocks
+const MOB_HEIGHT: f32 = 1.8;
+/// The height of sitting mobs in blocks
+const SITTING_HEIGHT: f32 = 1.0;
+/// The distance up to which mobs chase the player
+const CHASE_DISTANCE: f32 = 16.0;
+/// The distance mobs chasing the player stop at
+const STOP_DISTANCE: f32 = 1.5;
+/// The distance beyond which mobs despawn
+const DESPAWN_DISTANCE: f32 = 64.0;
+/// The speed mobs chase the player with in blocks per second
+const CHASE_SPEED: f32 = 2.5;
+/// The speed mobs wander around with in blocks per second
+const WANDER_SPEED: f32 = 1.0;
+/// The longest time in seconds mobs wander in one direction
+const WANDER_TIME: f32 = 4.0;
+/// The speed mobs fall with in blocks per second
+const FALL_SPEED: f32 = 8.0;
+/// The kind of the mobs players could tame, which
+/// never chase them
+pub const TAMEABLE_KIND: &str = "wolf";
+/// The name of the tool players tame mobs with
+pub const TAMING_ITEM: &str = "bone";
+/// The chance a mob is tamed per try
+const TAME_CHANCE: f64 = 0.35;
+/// The distance beyond which tamed mobs follow their owner
+const FOLLOW_DISTANCE: f32 = 3.0;
+/// The speed tamed mobs follow their owner with in
+/// blocks per second
+const FOLLOW_SPEED: f32 = 4.0;
+/// The distance beyond which tamed mobs teleport to their owner
+const TELEPORT_DISTANCE: f32 = 12.0;
+/// The distance up to which spawners are active
+pub const SPAWNER_RANGE: f32 = 16.0;
+/// The distance in blocks mobs are spawned from their spawner
+const SPAWN_RADIUS: i32 = 4;
+/// The largest amount of mobs spawned at once
+const SPAWN_COUNT: usize = 2;
+/// The amount of positions a spawner tries to spawn mobs at
+const SPAWN_ATTEMPTS: usize = 8;
+/// The largest amount of mobs near a spawner which still spawns
+const MAX_NEARBY: usize = 6;
+/// The shortest time in seconds between two spawns of a spawner
+const MIN_COOLDOWN: f32 = 10.0;
+/// The longest time in seconds between two spawns of a spawner
+const MAX_COOLDOWN: f32 = 40.0;
+
+/// Mob
+///
+/// A mob walking around in the world
+pub struct Mob {
+    /// The id of the entity
+    uuid: EntityUuid,
+    /// The kind of the mob, e.g. `zombie`
+    kind: String,
+    /// The position of the bottom center of the mob
+    pos: Vector3<f32>,
+    /// The direction the mob wanders in, which is
+    /// zero while it stands still
+    wander: Vector2<f32>,
+    /// The time in seconds until the mob picks
+    /// another direction to wander in
+    wander_time: f32,
+    /// The name of the player who tamed the mob, if any
+    owner: Option<String>,
+    /// Whether the mob sits instead of following its owner
+    sitting: bool,
+    /// The node the mob is shown with
+    node: NodeId,
+}
+
+impl Mob {
+    /// Returns the kind of the mob
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Returns the position of the bottom center of the mob
+    pub fn pos(&self) -> &Vector3<f32> {
+        &self.pos
+    }
+
+    /// Returns the name of the player who tamed the mob, if any
+    pub fn owner(&self) -> Option<&str> {
+        self.owner.as_deref()
+    }
+
+    /// Returns whether the mob sits instead of following its owner
+    pub fn is_sitting(&self) -> bool {
+        self.sitting
+    }
+
+    /// Returns the bounding box of the mob
+    pub fn aabb(&self) -> Aabb {
+        let half = Vector3::new(MOB_WIDTH / 2.0, 0.0, MOB_WIDTH / 2.0);
+        Aabb::new(self.pos - half, self.pos + half + Vector3::new(0.0, mob_height(self.sitting), 0.0))
+    }
+
+    /// Returns the mob as it's saved with its chunk
+    fn saved(&self) -> SavedEntity {
+        let saved = SavedEntity::new(MOB_TYPE, self.uuid, self.pos).with("kind", self.kind.as_str());
+        match &self.owner {
+            Some(owner) => saved.with("owner", owner.as_str()).with("sitting", self.sitting),
+            None => saved,
+        }
+    }
+}
+
+impl ChunkEntity for Mob {
+    fn uuid(&self) -> EntityUuid {
+        self.uuid
+    }
+
+    fn pos(&self) -> Vector3<f32> {
+        self.pos
+    }
+}
+
+/// Mobs
+///
+/// The mobs in a world. Mobs chase the player once they come
+/// close, and wander around otherwise. They step up single
+/// blocks, fall down until they land on a block, and despawn
+/// once the player is far away. Mobs are shown as boxes
+/// colored by their kind in the scene of the world.
+///
+/// Wolves never chase the player, who could tame them instead.
+/// Tamed wolves follow their owner and teleport next to them
+/// once they fall behind, unless they were told to sit. They
+/// never despawn.
+///
+/// Mobs are stored in the chunk they are in, and move to the
+/// list of the next chunk as they walk into it. Only the mobs
+/// within the activation radius around the player are ticked.
+pub struct Mobs {
+    /// The mobs in the world by their chunk
+    mobs: ChunkEntities<Mob>,
+    /// The mesh all mobs are shown with
+    mesh: MeshHandle,
+    /// The generator of the wandering and spawning of the mobs
+    rng: TickRng,
+}
+
+impl Default for Mobs {
+    fn default() -> Self {
+        Self {
+            mobs: ChunkEntities::default(),
+            mesh: MeshHandle::new(Mesh::cube()),
+            rng: TickRng::from_time(),
+        }
+    }
+}
+
+impl Mobs {
+    /// Returns the mobs in the world
+    pub fn mobs(&self) -> &ChunkEntities<Mob> {
+        &self.mobs
+    }
+
+    /// Spawns a mob at the given position
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the mob is shown in
+    /// * `kind` - The kind of the mob
+    /// * `pos` - The position of the bottom center of the mob
+    pub fn spawn(&mut self, scene: &mut Scene, kind: &str, pos: Vector3<f32>) {
+        self.spawn_with_id(scene, EntityUuid::generate(), kind, pos, None, false);
+    }
+
+    /// Spawns a mob with the given id at the given position
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the mob is shown in
+    /// * `uuid` - The id of the entity
+    /// * `kind` - The kind of the mob
+    /// * `pos` - The position of the bottom center of the mob
+    /// * `owner` - The name of the player who tamed the mob
+    /// * `sitting` - Whether the mob sits
+    fn spawn_with_id(&mut self, scene: &mut Scene, uuid: EntityUuid, kind: &str, pos: Vector3<f32>,
+                     owner: Option<String>, sitting: bool) {
+        let node = scene.add(None, SceneNode::with_mesh(
+            "mob",
+            mob_transform(pos, sitting),
+            self.mesh.clone(),
+            items::item_color(kind),
+        ));
+        self.mobs.insert(Mob {
+            uuid,
+            kind: kind.to_string(),
+            pos,
+            wander: Vector2::new(0.0, 0.0),
+            wander_time: 0.0,
+            owner,
+            sitting,
+            node,
+        });
+    }
+
+    /// Returns the closest mob a ray hits within the given
+    /// distance, with the distance to it
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The origin of the ray in world coordinates
+    /// * `dir` - The direction of the ray, doesn't need to be normalized
+    /// * `max_distance` - The maximal distance of the ray
+    pub fn hit(&self, origin: Vector3<f32>, dir: Vector3<f32>, max_distance: f32) -> Option<(EntityUuid, f32)> {
+        self.mobs.iter()
+            .filter_map(|mob| Some((mob.uuid, mob.aabb().ray_distance(origin, dir)?)))
+            .filter(|&(_, distance)| distance <= max_distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Returns a mob, if it's in the world
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The id of the mob
+    pub fn get(&self, uuid: EntityUuid) -> Option<&Mob> {
+        self.mobs.iter().find(|mob| mob.uuid == uuid)
+    }
+
+    /// Tries to tame a mob for a player, which succeeds by
+    /// chance. Returns whether the mob has been tamed, or
+    /// `None` if it can't be tamed, e.g. as it's tamed already.
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The id of the mob
+    /// * `owner` - The name of the player
+    pub fn tame(&mut self, uuid: EntityUuid, owner: &str) -> Option<bool> {
+        let key = self.mobs.find_key(uuid)?;
+        let mob = self.mobs.get_mut(key);
+        if mob.kind != TAMEABLE_KIND || mob.owner.is_some() {
+            return None;
+        }
+        if self.rng.unit() >= TAME_CHANCE {
+            return Some(false);
+        }
+        mob.owner = Some(owner.to_string());
+        Some(true)
+    }
+
+    /// Tells a tamed mob to sit down or to stand up again.
+    /// Returns whether it sits now, or `None` if it isn't
+    /// tamed by the player.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the mob is shown in
+    /// * `uuid` - The id of the mob
+    /// * `owner` - The name of the player
+    pub fn toggle_sitting(&mut self, scene: &mut Scene, uuid: EntityUuid, owner: &str) -> Option<bool> {
+        let key = self.mobs.find_key(uuid)?;
+        let mob = self.mobs.get_mut(key);
+        if mob.owner.as_deref() != Some(owner) {
+            return None;
+        }
+        mob.sitting = !mob.sitting;
+        if let Some(node) = scene.node_mut(mob.node) {
+            node.set_transform(mob_transform(mob.pos, mob.sitting));
+        }
+        Some(mob.sitting)
+    }
+
+    /// Advances a spawner, which spawns mobs around itself once
+    /// its cooldown is over while the player is close. Mobs are
+    /// spawned on air or plants above solid blocks, and not while
+    /// there are too many mobs near the spawner already. Other
+    /// block entities are left as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the mobs are shown in
+    /// * `spawner` - The position of the spawner in world coordinates
+    /// * `entity` - The block entity of the spawner
+    /// * `seconds` - The time passed since the last update
+    /// * `player` - The position of the player's feet
+    /// * `block_at` - Returns the material of the block at a position
+    pub fn tick_spawner<F>(&mut self, scene: &mut Scene, spawner: Vector3<i32>, entity: &mut BlockEntity,
+                           seconds: f32, player: Vector3<f32>, block_at: &F)
+        where F: Fn(Vector3<i32>) -> Option<Material>
+    {
+        let (kind, cooldown) = match entity {
+            BlockEntity::Spawner { mob, cooldown } => (mob.as_str(), cooldown),
+            _ => return,
+        };
+        let center = spawner.cast::<f32>().unwrap() + Vector3::new(0.5, 0.5, 0.5);
+        if (center - player).magnitude() > SPAWNER_RANGE {
+            return;
+        }
+        *cooldown -= seconds;
+        if *cooldown > 0.0 {
+            return;
+        }
+        *cooldown = MIN_COOLDOWN + self.rng.unit() as f32 * (MAX_COOLDOWN - MIN_COOLDOWN);
+
+        let nearby = self.mobs.iter().filter(|mob| (mob.pos - center).magnitude() <= SPAWNER_RANGE).count();
+        let mut left = SPAWN_COUNT.min(MAX_NEARBY.saturating_sub(nearby));
+        for _ in 0..SPAWN_ATTEMPTS {
+            if left == 0 {
+                break;
+            }
+            let diameter = 2 * SPAWN_RADIUS as usize + 1;
+            let pos = spawner + Vector3::new(
+                self.rng.below(diameter) as i32 - SPAWN_RADIUS,
+                self.rng.below(3) as i32 - 1,
+                self.rng.below(diameter) as i32 - SPAWN_RADIUS,
+            );
+            let fits = matches!(block_at(pos), Some(material) if !material.is_solid())
+                && matches!(block_at(pos + Vector3::new(0, 1, 0)), Some(material) if !material.is_solid())
+                && matches!(block_at(pos - Vector3::new(0, 1, 0)), Some(material) if material.is_solid());
+            if fits {
+                self.spawn(scene, kind, pos.cast::<f32>().unwrap() + Vector3::new(0.5, 0.0, 0.5));
+                left -= 1;
+            }
+        }
+    }
+
+    /// Advances the mobs within the activation radius around
+    /// the player. Mobs despawn once they're too far away from
+    /// the player, unless they're tamed.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the mobs are shown in
+    /// * `seconds` - The time passed since the last update
+    /// * `player` - The position of the player's feet
+    /// * `name` - The name of the player
+    /// * `radius` - The activation radius in chunks
+    /// * `block_at` - Returns the material of the block at a position
+    pub fn update<F>(&mut self, scene: &mut Scene, seconds: f32, player: Vector3<f32>, name: &str, radius: i32, block_at: F)
+        where F: Fn(Vector3<i32>) -> Option<Material>
+    {
+        let block_pos = |pos: Vector3<f32>| Vector3::new(pos.x.floor() as i32, pos.y.floor() as i32, pos.z.floor() as i32);
+        let passable = |pos: Vector3<i32>| matches!(block_at(pos), Some(material) if !material.is_solid());
+
+        let mut removed = Vec::new();
+        for key in self.mobs.active(chunk_entities::chunk_of(player), radius) {
+            let mob = self.mobs.get_mut(key);
+            let to_player = player - mob.pos;
+            let horizontal = Vector2::new(to_player.x, to_player.z);
+            if horizontal.magnitude() > DESPAWN_DISTANCE && mob.owner.is_none() {
+                removed.push(key);
+                continue;
+            }
+
+            let following = mob.owner.as_deref() == Some(name) && !mob.sitting;
+            if following && to_player.magnitude() > TELEPORT_DISTANCE {
+                if let Some(pos) = landing_spot(player, &block_at) {
+                    mob.pos = pos;
+                    if let Some(node) = scene.node_mut(mob.node) {
+                        node.set_transform(mob_transform(mob.pos, mob.sitting));
+                    }
+                    continue;
+                }
+            }
+
+            let velocity = if mob.sitting {
+                Vector2::new(0.0, 0.0)
+            } else if following {
+                if horizontal.magnitude() <= FOLLOW_DISTANCE {
+                    Vector2::new(0.0, 0.0)
+                } else {
+                    horizontal.normalize() * FOLLOW_SPEED
+                }
+            } else if mob.kind != TAMEABLE_KIND && horizontal.magnitude() <= STOP_DISTANCE {
+                Vector2::new(0.0, 0.0)
+            } else if mob.kind != TAMEABLE_KIND && horizontal.magnitude() <= CHASE_DISTANCE {
+                horizontal.normalize() * CHASE_SPEED
+            } else {
+                mob.wander_time -= seconds;
+                if mob.wander_time <= 0.0 {
+                    mob.wander_time = self.rng.unit() as f32 * WANDER_TIME;
+                    // Mobs stand still for every other while
+                    mob.wander = if self.rng.below(2) == 0 {
+                        Vector2::new(0.0, 0.0)
+                    } else {
+                        let angle = self.rng.unit() as f32 * std::f32::consts::PI * 2.0;
+                        Vector2::new(angle.cos(), angle.sin())
+                    };
+                }
+                mob.wander * WANDER_SPEED
+            };
+
+            let feet = block_pos(mob.pos + Vector3::new(0.0, 0.01, 0.0));
+            let below = block_pos(mob.pos - Vector3::new(0.0, 0.01, 0.0));
+            let grounded = matches!(block_at(below), Some(material) if material.is_solid());
+
+            // Mobs walk through air and plants only, and step up a block
+            // while they stand on the ground
+            let target = mob.pos + Vector3::new(velocity.x, 0.0, velocity.y) * seconds;
+            let ahead = block_pos(target + Vector3::new(0.0, 0.01, 0.0));
+            if ahead == feet || (passable(ahead) && passable(ahead + Vector3::new(0, 1, 0))) {
+                mob.pos = target;
+            } else if grounded && passable(ahead + Vector3::new(0, 1, 0)) && passable(ahead + Vector3::new(0, 2, 0))
+                && passable(feet + Vector3::new(0, 2, 0)) {
+                mob.pos = target + Vector3::new(0.0, 1.0, 0.0);
+                mob.pos.y = mob.pos.y.floor();
+            }
+
+            // Mobs only fall through air and plants of loaded chunks
+            let below = block_pos(mob.pos - Vector3::new(0.0, 0.01, 0.0));
+            if passable(below) {
+                let floor = below.y as f32;
+                mob.pos.y = (mob.pos.y - FALL_SPEED * seconds).max(floor);
+            }
+
+            if let Some(node) = scene.node_mut(mob.node) {
+                node.set_transform(mob_transform(mob.pos, mob.sitting));
+            }
+        }
+
+        for mob in self.mobs.remove_and_relocate(removed) {
+            scene.remove(mob.node);
+        }
+    }
+
+    /// Removes the mobs of a chunk from the world and
+    /// returns them, so they can be saved with the chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the mobs are shown in
+    /// * `loc` - The location of the chunk
+    pub fn unload_chunk(&mut self, scene: &mut Scene, loc: Vector2<i32>) -> Vec<SavedEntity> {
+        self.mobs.take_chunk(loc).into_iter()
+            .map(|mob| {
+                scene.remove(mob.node);
+                mob.saved()
+            })
+            .collect()
+    }
+
+    /// Returns the mobs of a chunk as they're saved
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the chunk
+    pub fn saved(&self, loc: Vector2<i32>) -> Vec<SavedEntity> {
+        self.mobs.in_chunk(loc).iter().map(Mob::saved).collect()
+    }
+
+    /// Spawns a saved mob again with its id
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the mobs are shown in
+    /// * `saved` - The saved mob
+    pub fn restore(&mut self, scene: &mut Scene, saved: &SavedEntity) -> Result<(), String> {
+        let owner = saved.text("owner").ok().map(str::to_string);
+        self.spawn_with_id(scene, saved.uuid, saved.text("kind")?, saved.pos.into(), owner, saved.flag("sitting"));
+        Ok(())
+    }
+
+    /// Removes all mobs from the world
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the mobs are shown in
+    pub fn clear(&mut self, scene: &mut Scene) {
+        for mob in self.mobs.take_all() {
+            scene.remove(mob.node);
+        }
+    }
+}
+
+/// Returns the height of a mob
+///
+/// # Arguments
+///
+/// * `sitting` - Whether the mob sits
+fn mob_height(sitting: bool) -> f32 {
+    if sitting { SITTING_HEIGHT } else { MOB_HEIGHT }
+}
+
+/// Returns the transform of the node a mob is shown with
+///
+/// # Arguments
+///
+/// * `pos` - The position of the bottom center of the mob
+/// * `sitting` - Whether the mob sits
+fn mob_transform(pos: Vector3<f32>, sitting: bool) -> Matrix4<f32> {
+    Matrix4::from_translation(pos)
+        * Matrix4::from_nonuniform_scale(MOB_WIDTH, mob_height(sitting), MOB_WIDTH)
+        * Matrix4::from_translation(Vector3::new(-0.5, 0.0, -0.5))
+}
+
+/// Returns a free spot on the ground next to the player a
+/// tamed mob could teleport to, or `None` if there's none
+///
+/// # Arguments
+///
+/// * `player` - The position of the player's feet
+/// * `block_at` - Returns the material of the block at a position
+fn landing_spot<F>(player: Vector3<f32>, block_at: &F) -> Option<Vector3<f32>>
+    where F: Fn(Vector3<i32>) -> Option<Material>
+{
+    let passable = |pos: Vector3<i32>| matches!(block_at(pos), Some(material) if !material.is_solid());
+    let ground = |pos: Vector3<i32>| matches!(block_at(pos), Some(material) if material.is_solid() && !material.is_fluid());
+    let feet = Vector3::new(player.x.floor() as i32, player.y.floor() as i32, player.z.floor() as i32);
+    const OFFSETS: [(i32, i32); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (-1, 1), (1, -1), (-1, -1)];
+    OFFSETS.iter()
+        .flat_map(|&(x, z)| [0, 1, -1].iter().map(move |&y| feet + Vector3::new(x, y, z)))
+        .find(|&pos| passable(pos) && passable(pos + Vector3::new(0, 1, 0)) && ground(pos - Vector3::new(0, 1, 0)))
+        .map(|pos| pos.cast::<f32>().unwrap() + Vector3::new(0.5, 0.0, 0.5))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A world whose blocks below `y = 0` are stone, with
+    /// a single step at `x = 6`
+    fn ground(pos: Vector3<i32>) -> Option<Material> {
+        let solid = pos.y < 0 || (pos.y == 0 && pos.x == 6);
+        Some(if solid { Material::Stone } else { Material::Air })
+    }
+
+    #[test]
+    fn mobs_chase_the_player_up_steps() {
+        let mut scene = Scene::new();
+        let mut mobs = Mobs::default();
+        mobs.spawn(&mut scene, "zombie", Vector3::new(0.5, 0.0, 0.5));
+        assert_eq!(scene.instances().len(), 1);
+
+        let player = Vector3::new(8.5, 0.0, 0.5);
+        for _ in 0..90 {
+            mobs.update(&mut scene, 1.0 / 30.0, player, "steve", 4, ground);
+        }
+        let pos = *mobs.mobs().iter().next().unwrap().pos();
+        assert!(pos.x > 6.0 && pos.x < player.x - STOP_DISTANCE + 0.1, "{:?}", pos);
+
+        // The mob stepped onto the block and down again
+        for _ in 0..90 {
+            mobs.update(&mut scene, 1.0 / 30.0, player, "steve", 4, ground);
+        }
+        let pos = *mobs.mobs().iter().next().unwrap().pos();
+        assert_eq!(pos.y, 0.0);
+        assert!((player - pos).magnitude() <= STOP_DISTANCE + 0.1);
+    }
+
+    #[test]
+    fn mobs_fall_and_despawn() {
+        let mut scene = Scene::new();
+        let mut mobs = Mobs::default();
+        mobs.spawn(&mut scene, "zombie", Vector3::new(0.5, 3.0, 0.5));
+
+        let player = Vector3::new(1.0, 0.0, 0.5);
+        for _ in 0..30 {
+            mobs.update(&mut scene, 1.0 / 30.0, player, "steve", 4, ground);
+        }
+        assert_eq!(mobs.mobs().iter().next().unwrap().pos().y, 0.0);
+
+        mobs.update(&mut scene, 0.1, Vector3::new(100.0, 0.0, 0.0), "steve", 8, ground);
+        assert!(mobs.mobs().is_empty());
+        assert!(scene.instances().is_empty());
+    }
+
+    #[test]
+    fn mobs_move_between_chunks_near_the_player() {
+        let mut scene = Scene::new();
+        let mut mobs = Mobs::default();
+        mobs.spawn(&mut scene, "zombie", Vector3::new(14.5, 0.0, 0.5));
+        mobs.spawn(&mut scene, "zombie", Vector3::new(60.5, 0.0, 0.5));
+
+        // Only the mob near the player chases it into the next chunk
+        let player = Vector3::new(24.5, 0.0, 0.5);
+        for _ in 0..60 {
+            mobs.update(&mut scene, 1.0 / 30.0, player, "steve", 1, ground);
+        }
+        assert!(mobs.mobs().in_chunk(Vector2::new(0, 0)).is_empty());
+        assert_eq!(mobs.mobs().in_chunk(Vector2::new(1, 0)).len(), 1);
+        assert_eq!(*mobs.mobs().in_chunk(Vector2::new(3, 0))[0].pos(), Vector3::new(60.5, 0.0, 0.5));
+
+        let saved = mobs.unload_chunk(&mut scene, Vector2::new(3, 0));
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].type_id, MOB_TYPE);
+        assert_eq!(mobs.mobs().len(), 1);
+        mobs.restore(&mut scene, &saved[0]).unwrap();
+        assert_eq!(mobs.saved(Vector2::new(3, 0)), saved);
+        assert_eq!(scene.instances().len(), 2);
+    }
+
+    #[test]
+    fn tamed_wolves_follow_their_owner() {
+        let mut scene = Scene::new();
+        let mut mobs = Mobs { rng: TickRng::new(3), ..Mobs::default() };
+        mobs.spawn(&mut scene, "zombie", Vector3::new(0.5, 0.0, 0.5));
+        mobs.spawn(&mut scene, TAMEABLE_KIND, Vector3::new(0.5, 0.0, 4.5));
+        let zombie = mobs.hit(Vector3::new(0.5, 1.0, -2.0), Vector3::new(0.0, 0.0, 1.0), 5.0).unwrap().0;
+        let wolf = mobs.hit(Vector3::new(0.5, 1.0, 8.0), Vector3::new(0.0, 0.0, -1.0), 5.0).unwrap().0;
+
+        // Only wolves are tamed, which takes a few tries
+        assert_eq!(mobs.tame(zombie, "steve"), None);
+        assert!((0..50).any(|_| mobs.tame(wolf, "steve") == Some(true)));
+        assert_eq!(mobs.get(wolf).unwrap().owner(), Some("steve"));
+        assert_eq!(mobs.tame(wolf, "alex"), None);
+        assert_eq!(mobs.toggle_sitting(&mut scene, wolf, "alex"), None);
+
+        // The wolf teleports next to its owner and follows them
+        let player = Vector3::new(30.5, 0.0, 4.5);
+        mobs.update(&mut scene, 1.0 / 30.0, player, "steve", 4, ground);
+        let pos = *mobs.get(wolf).unwrap().pos();
+        assert!((pos - player).magnitude() < 2.0, "{:?}", pos);
+        assert_eq!(pos.y, 0.0);
+
+        let player = Vector3::new(30.5, 0.0, 12.5);
+        for _ in 0..60 {
+            mobs.update(&mut scene, 1.0 / 30.0, player, "steve", 4, ground);
+        }
+        let pos = *mobs.get(wolf).unwrap().pos();
+        assert!((pos - player).magnitude() <= FOLLOW_DISTANCE + 0.2, "{:?}", pos);
+
+        // Sitting wolves stay, and are saved as they are
+        assert_eq!(mobs.toggle_sitting(&mut scene, wolf, "steve"), Some(true));
+        for _ in 0..30 {
+            mobs.update(&mut scene, 1.0 / 30.0, Vector3::new(30.5, 0.0, 40.5), "steve", 4, ground);
+        }
+        assert_eq!(*mobs.get(wolf).unwrap().pos(), pos);
+        assert_eq!(mobs.get(wolf).unwrap().aabb().max.y, SITTING_HEIGHT);
+
+        let loc = chunk_entities::chunk_of(pos);
+        let saved = mobs.unload_chunk(&mut scene, loc);
+        assert_eq!(saved[0].text("owner"), Ok("steve"));
+        assert!(saved[0].flag("sitting"));
+        mobs.restore(&mut scene, &saved[0]).unwrap();
+        let restored = mobs.get(wolf).unwrap();
+        assert_eq!(restored.owner(), Some("steve"));
+        assert!(restored.is_sitting());
+
+        // Tamed wolves never despawn, unlike the zombie
+        mobs.update(&mut scene, 0.1, Vector3::new(130.5, 0.0, 0.5), "steve", 8, ground);
+        assert!(mobs.get(zombie).is_none());
+        assert!(mobs.get(wolf).is_some());
+    }
+
+    #[test]
+    fn spawners_spawn_near_the_player() {
+        let mut scene = Scene::new();
+        // Most spawn attempts miss the ground, so a fixed seed
+        // keeps the test from failing with unlucky attempts
+        let mut mobs = Mobs { rng: TickRng::new(1), ..Mobs::default() };
+        let spawner = Vector3::new(0, 0, 0);
+        let mut entity = BlockEntity::spawner("zombie");
+        let cooldown = |entity: &BlockEntity| match entity {
+            BlockEntity::Spawner { cooldown, .. } => *cooldown,
+            _ => unreachable!(),
+        };
+
+        // Spawners far away from the player are inactive
+        mobs.tick_spawner(&mut scene, spawner, &mut entity, 1.0, Vector3::new(50.0, 0.0, 0.0), &ground);
+        assert!(mobs.mobs().is_empty());
+        assert_eq!(cooldown(&entity), 0.0);
+
+        let player = Vector3::new(2.0, 0.0, 2.0);
+        mobs.tick_spawner(&mut scene, spawner, &mut entity, 1.0, player, &ground);
+        assert!((MIN_COOLDOWN..=MAX_COOLDOWN).contains(&cooldown(&entity)));
+        assert!(!mobs.mobs().is_empty() && mobs.mobs().len() <= SPAWN_COUNT);
+        for mob in mobs.mobs().iter() {
+            assert_eq!(mob.kind(), "zombie");
+            assert_eq!(mob.pos().y, 0.0);
+        }
+
+        // The cooldown holds back further mobs, and a
+        // crowded spawner doesn't spawn any
+        let count = mobs.mobs().len();
+        mobs.tick_spawner(&mut scene, spawner, &mut entity, 1.0, player, &ground);
+        assert_eq!(mobs.mobs().len(), count);
+        for _ in 0..MAX_NEARBY * 4 {
+            entity = BlockEntity::spawner("zombie");
+            mobs.tick_spawner(&mut scene, spawner, &mut entity, 1.0, player, &ground);
+        }
+        assert_eq!(mobs.mobs().len(), MAX_NEARBY);
+    }
+}
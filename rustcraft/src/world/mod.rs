@@ -1,17 +1,91 @@
-use crate::world::chunk::{Chunk, ChunkRenderer, CHUNK_SIZE};
-use crate::graphics::gl::Gl;
-use crate::resources::Resources;
-use crate::camera::PerspectiveCamera;
-use crate::world::terrain_generator::{TerrainGen, SimpleTerrainGen};
-use cgmath::Vector2;
-use std::thread;
+use crate::graphics::scene::Scene;
+use crate::task::{CancelToken, TaskHandle, TaskPool};
+use crate::world::block::Material;
+use crate::world::block_entity::BlockEntity;
+use crate::world::border::WorldBorder;
+use crate::world::chunk::{Chunk, CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::world::chunk_entities::{EntityUuid, SavedEntity};
+use crate::world::entity_types::{EntityRegistry, EntityState, ScriptedEntities};
+use crate::world::explosion::{Explosion, PrimedTntEntities};
+use crate::world::furnace::Smelting;
+use crate::world::history::{BlockEdit, EditError, EditHistory, EditOperation};
+use crate::world::items::ItemEntities;
+use crate::world::mobs::Mobs;
+use crate::world::pregen::PregenCache;
+use crate::world::save::{RegionData, SavedBlock, REGION_SIZE};
+use crate::world::generators::TerrainGenRegistry;
+use crate::world::terrain_generator::TerrainGen;
+use crate::world::tiers::{ChunkTiers, TierSettings};
+use crate::world::vehicles::{Steering, VehicleKind, Vehicles};
+use crate::world::ticks::TickQueue;
+use crate::world::time::{SkySettings, WorldTime};
+use crate::world::vegetation::Plants;
+use cgmath::{Vector2, Vector3};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+pub mod bed;
+pub mod biome;
 pub mod block;
+pub mod block_entity;
+pub mod border;
+pub mod brush;
 pub mod chunk;
+pub mod chunk_entities;
+pub mod connected;
+pub mod crops;
+pub mod dimension;
+pub mod edit_log;
+pub mod entity_types;
+pub mod explosion;
+pub mod fire;
+pub mod fluids;
+pub mod furnace;
+pub mod generators;
+pub mod history;
+pub mod hopper;
+pub mod items;
+pub mod light;
+pub mod light_overlay;
+pub mod loot;
+pub mod mining;
+pub mod mobs;
+pub mod noise_source;
+pub mod overlay;
+pub mod particles;
+pub mod placement;
+pub mod portal;
+pub mod pregen;
+pub mod preview;
+pub mod raycast;
+pub mod recipes;
+pub mod rules;
+pub mod save;
+pub mod script_api;
+pub mod selection;
+pub mod sim;
+pub mod structure;
+pub mod tags;
 pub mod terrain_generator;
+pub mod texture_pack;
+pub mod tiers;
+pub mod ticks;
+pub mod time;
+pub mod universe;
+pub mod vegetation;
+pub mod vehicles;
+pub mod worldgen_overlay;
 
-const RENDER_DISTANCE: i32 = 6;
+/// The default distance in chunks up to which chunks
+/// are loaded and drawn
+pub const RENDER_DISTANCE: i32 = 6;
+/// The largest amount of chunks queued to be generated. Further
+/// chunks are loaded once the workers caught up.
+const GENERATION_QUEUE_CAPACITY: usize = 64;
+
+/// The edited blocks of a chunk with their states, by
+/// their position within the chunk
+type EditedBlocks = HashMap<Vector3<i16>, (Material, u8)>;
 
 /// World
 ///
@@ -19,61 +93,233 @@ const RENDER_DISTANCE: i32 = 6;
 /// are currently loaded from the file
 /// system.
 ///
-/// At the moment, chunks are just stored
-/// in memory, this will change in upcoming
-/// releases.
+/// Only the blocks which differ from the
+/// generated terrain are saved. They're
+/// kept in `edited` and written to the
+/// region files of `dirty_regions`, while
+/// the terrain itself is generated from
+/// the seed again, or restored from its
+/// pregenerated files. It's the very same
+/// each time, so the saves stay small.
+///
+/// The world is owned by the simulation thread
+/// and doesn't require an `OpenGL` context. The
+/// chunks are handed over to the render thread
+/// each frame.
 pub struct World {
-    /// An `OpenGL` instance
-    gl: Gl,
     /// The chunks of the world which are
     /// currently loaded from the file system
     chunks: Vec<Chunk>,
-    /// The chunk renderer which is used to render
-    /// the given chunks to the screen
-    chunk_renderer: ChunkRenderer,
     /// The terrain generator which is used to generate
     /// loading chunks
     terrain_gen: Arc<Box<dyn TerrainGen + Send + Sync>>,
+    /// The scene containing all non-voxel props
+    scene: Scene,
+    /// The items lying in the world
+    items: ItemEntities,
+    /// The primed TNT in the world
+    tnt: PrimedTntEntities,
+    /// The mobs in the world
+    mobs: Mobs,
+    /// The vehicles in the world
+    vehicles: Vehicles,
+    /// The entities of types scripts registered
+    scripted: ScriptedEntities,
+    /// The spawn functions of the entity types
+    entity_types: EntityRegistry,
+    /// The time of the world
+    time: WorldTime,
+    /// The look of the sky of the world
+    sky: SkySettings,
+    /// The border beyond which no chunks are loaded
+    border: Option<WorldBorder>,
+    /// The plants decorating the generated terrain
+    plants: Plants,
+    /// The blocks which differ from the generated terrain with
+    /// their states, by the location of their chunk
+    edited: HashMap<Vector2<i32>, EditedBlocks>,
+    /// The locations of the regions with unsaved edits
+    dirty_regions: HashSet<Vector2<i32>>,
+    /// The entities of the chunks which aren't loaded, by the
+    /// location of their chunk. They're spawned again once
+    /// their chunk is generated.
+    stored_entities: HashMap<Vector2<i32>, Vec<SavedEntity>>,
+    /// The locations of the regions which had entities in loaded
+    /// chunks when they have been saved the last time, so they're
+    /// saved again once the entities are gone
+    entity_regions: HashSet<Vector2<i32>>,
+    /// The history of block edits
+    history: EditHistory,
+    /// The block changes since they have been taken the last time
+    changes: Vec<BlockEdit>,
+    /// The workers generating the terrain of the loaded chunks
+    generation_workers: TaskPool<Vector2<i32>>,
+    /// The tasks generating the terrain of the loaded chunks,
+    /// which are cancelled when their chunk is unloaded
+    generation_tasks: HashMap<Vector2<i32>, TaskHandle>,
+    /// The ticks scheduled for later updates
+    scheduled: TickQueue,
+    /// Whether it rains in the world, which puts out
+    /// fires under the open sky
+    raining: bool,
+    /// The terrain which has been pregenerated, which chunks
+    /// are restored from instead of generating them
+    pregenerated: Option<PregenCache>,
+    /// The tiers the blocks of idle chunks are moved to
+    tiers: ChunkTiers,
+}
+
+/// TerrainPipeline
+///
+/// The steps generating the terrain of a chunk, which
+/// could be moved to the thread generating it
+#[derive(Clone)]
+pub struct TerrainPipeline {
+    /// The terrain generator of the world
+    terrain_gen: Arc<Box<dyn TerrainGen + Send + Sync>>,
+    /// The plants decorating the terrain
+    plants: Plants,
+}
+
+impl TerrainPipeline {
+    /// Generates the terrain of a chunk and decorates it
+    /// with plants. Returns `false` if the task has been
+    /// cancelled before the terrain is done.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The empty chunk
+    /// * `token` - The token of the task generating the chunk
+    pub fn generate(&self, chunk: &Chunk, token: &CancelToken) -> bool {
+        let height_map = self.terrain_gen.gen_heightmap(chunk.loc());
+        if token.is_cancelled() {
+            return false;
+        }
+        self.terrain_gen.gen_smooth_terrain(chunk, &height_map);
+        if token.is_cancelled() {
+            return false;
+        }
+        self.plants.decorate(chunk, &**self.terrain_gen, &height_map);
+        true
+    }
 }
 
 impl World {
-    /// Creates a new world
+    /// Creates a new world with the rolling hills of the `simple`
+    /// generator, see `TerrainGenRegistry`
+    pub fn new() -> Self {
+        let terrain_gen = TerrainGenRegistry::default().create("simple", &toml::value::Table::new())
+            .expect("The simple generator has no required options.");
+        Self::with_generator(terrain_gen, SkySettings::default())
+    }
+
+    /// Creates a new world, e.g. a dimension of the universe
     ///
     /// # Arguments
     ///
-    /// * `gl` - An `OpenGl` instance
-    /// * `res` - A `Resources` instance
-    pub fn new(gl: &Gl, res: &Resources) -> Self {
+    /// * `terrain_gen` - The generator of the terrain of the world
+    /// * `sky` - The look of the sky of the world
+    pub fn with_generator(terrain_gen: impl TerrainGen + Send + Sync + 'static, sky: SkySettings) -> Self {
         Self {
-            gl: gl.clone(),
             chunks: Vec::new(),
-            chunk_renderer: ChunkRenderer::new(gl, res),
-            terrain_gen: Arc::new(Box::new(SimpleTerrainGen::default()) as Box<dyn TerrainGen + Send + Sync>),
+            terrain_gen: Arc::new(Box::new(terrain_gen) as Box<dyn TerrainGen + Send + Sync>),
+            scene: Scene::new(),
+            items: ItemEntities::default(),
+            tnt: PrimedTntEntities::default(),
+            mobs: Mobs::default(),
+            vehicles: Vehicles::default(),
+            scripted: ScriptedEntities::default(),
+            entity_types: EntityRegistry::default(),
+            time: WorldTime::default(),
+            history: EditHistory::default(),
+            changes: Vec::new(),
+            sky,
+            border: None,
+            plants: Plants::default(),
+            edited: HashMap::new(),
+            dirty_regions: HashSet::new(),
+            stored_entities: HashMap::new(),
+            entity_regions: HashSet::new(),
+            generation_workers: TaskPool::new("generation-worker", chunk::chunk_workers(), GENERATION_QUEUE_CAPACITY),
+            generation_tasks: HashMap::new(),
+            scheduled: TickQueue::default(),
+            raining: false,
+            pregenerated: None,
+            tiers: ChunkTiers::default(),
         }
     }
 
-    /// Loads a chunk from the file system
+    /// Loads a chunk from the file system. Chunks beyond
+    /// the world border aren't loaded, neither are chunks
+    /// while the generation queue is full, they're loaded
+    /// by a later update.
     ///
     /// # Arguments
     ///
     /// * `loc` - The location of the chunk which is load from
     /// the file system
     pub fn load_chunk(&mut self, loc: &Vector2<i32>) {
+        if matches!(self.border, Some(border) if !border.contains_chunk(loc)) {
+            return;
+        }
         if self.chunk(loc).is_none() {
-            let mut chunk = Chunk::new(&self.gl, loc.clone());
-            self.chunks.push(chunk.clone());
+            let chunk = Chunk::new(loc.clone());
+            let task_chunk = chunk.clone();
+
+            // Edited blocks are restored after the terrain is generated
+            let edits: Vec<(Vector3<i16>, (Material, u8))> = self.edited.get(loc)
+                .map(|blocks| blocks.iter().map(|(&pos, &block)| (pos, block)).collect())
+                .unwrap_or_default();
 
             let loc = loc.clone();
-            let terrain_gen = self.terrain_gen.clone();
-            thread::spawn(move || {
-                let height_map = terrain_gen.gen_heightmap(&loc);
-                terrain_gen.gen_smooth_terrain(&chunk, &height_map);
+            let pipeline = self.terrain_pipeline();
+            let pregenerated = self.pregenerated.clone();
+            let handle = self.generation_workers.submit(loc, move |token| {
+                let restored = match pregenerated {
+                    Some(pregenerated) => pregenerated.restore(&task_chunk),
+                    None => false,
+                };
+                if !restored && !pipeline.generate(&task_chunk, &token) {
+                    return;
+                }
+                for (pos, (material, state)) in edits {
+                    task_chunk.set_block(pos, material);
+                    task_chunk.set_state(pos, state);
+                    // Edited blocks never keep the block entity of
+                    // the generated block, e.g. a looted chest
+                    task_chunk.take_block_entity(pos);
+                }
+                task_chunk.mark_generated();
             });
+            if let Some(handle) = handle {
+                self.chunks.push(chunk);
+                self.generation_tasks.insert(loc, handle);
+            }
+        }
+    }
+
+    /// Loads the chunks within a square around the given position,
+    /// e.g. before the player is teleported there
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - The position in world coordinates
+    /// * `radius` - The amount of chunks to load in each direction
+    pub fn load_area(&mut self, center: &Vector3<f32>, radius: i32) {
+        let chunk_x = (center.x / CHUNK_SIZE as f32).floor() as i32;
+        let chunk_y = (center.z / CHUNK_SIZE as f32).floor() as i32;
+
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                self.load_chunk(&Vector2::new(chunk_x + x, chunk_y + y));
+            }
         }
     }
 
-    /// Unloads a chunk. Stores the chunk to the
-    /// file system.
+    /// Unloads a chunk. The entities in the chunk are
+    /// stored until the chunk is loaded again. Edited blocks
+    /// are kept as well and restored once it's generated
+    /// again, they're written to the region files on save.
     ///
     /// # Arguments
     ///
@@ -82,32 +328,146 @@ impl World {
         if let Some(pos) = self.chunks.iter().position(|x| x.loc() == loc) {
             self.chunks.remove(pos);
         }
+        self.generation_tasks.remove(loc);
+        self.store_entities(*loc);
     }
 
-    /// Clears the renderer before a render call
-    pub fn clear_renderer(&self) {
-        self.chunk_renderer.clear();
+    /// Unloads all chunks, e.g. when the player left the world
+    pub fn unload_chunks(&mut self) {
+        self.chunks.clear();
+        self.generation_tasks.clear();
+        self.tnt.clear(&mut self.scene);
+        for loc in self.entity_chunks() {
+            self.store_entities(loc);
+        }
     }
 
-    /// Renders the world with a given camera perspective.
-    /// Internally, a "spiral like" loop will be used to render the chunks
-    /// around the player.
+    /// Removes the entities of a chunk from the world and
+    /// stores them until the chunk is loaded again
     ///
-    /// At the moment, the render distance is set within the `RENDER_DISTANCE`
-    /// constant.
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the chunk
+    fn store_entities(&mut self, loc: Vector2<i32>) {
+        let mut saved = self.items.unload_chunk(&mut self.scene, loc);
+        saved.extend(self.mobs.unload_chunk(&mut self.scene, loc));
+        saved.extend(self.vehicles.unload_chunk(&mut self.scene, loc));
+        saved.extend(self.scripted.unload_chunk(&mut self.scene, loc));
+        if !saved.is_empty() {
+            self.stored_entities.entry(loc).or_default().extend(saved);
+            self.dirty_regions.insert(Self::region_of(loc));
+        }
+    }
+
+    /// Spawns the stored entities of the chunks which have
+    /// been generated again by the spawn functions of their
+    /// types. Entities of unknown types stay stored, so they
+    /// are saved again as they are.
+    fn restore_entities(&mut self) {
+        if self.stored_entities.is_empty() {
+            return;
+        }
+        let generated: Vec<Vector2<i32>> = self.chunks.iter()
+            .filter(|chunk| chunk.is_generated() && self.stored_entities.contains_key(chunk.loc()))
+            .map(|chunk| *chunk.loc())
+            .collect();
+        for loc in generated {
+            let mut unknown = Vec::new();
+            for saved in self.stored_entities.remove(&loc).unwrap_or_default() {
+                match self.entity_types.spawner(&saved.type_id) {
+                    Some(spawn) => if let Err(error) = spawn(self, &saved) {
+                        log::warn!("Failed to spawn the {} entity {}: {}", saved.type_id, saved.uuid, error);
+                    },
+                    None => unknown.push(saved),
+                }
+            }
+            if !unknown.is_empty() {
+                self.stored_entities.insert(loc, unknown);
+            }
+        }
+    }
+
+    /// Returns the locations of the chunks containing entities
+    fn entity_chunks(&self) -> HashSet<Vector2<i32>> {
+        self.items.items().chunks().into_iter()
+            .chain(self.mobs.mobs().chunks())
+            .chain(self.vehicles.vehicles().chunks())
+            .chain(self.scripted.entities().chunks())
+            .collect()
+    }
+
+    /// Spawns a saved entity by the spawn function of its type,
+    /// e.g. an entity a script spawned
     ///
     /// # Arguments
     ///
-    /// * `camera` - A perspective camera
-    #[allow(unused_assignments)]
-    pub fn render(&mut self, camera: &PerspectiveCamera) {
+    /// * `saved` - The saved entity
+    pub fn spawn_entity(&mut self, saved: &SavedEntity) -> Result<(), String> {
+        let spawn = self.entity_types.spawner(&saved.type_id)
+            .ok_or_else(|| format!("unknown entity type {}", saved.type_id))?;
+        spawn(self, saved)
+    }
+
+    /// Spawns saved items, see `EntityRegistry`
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world
+    /// * `saved` - The saved items
+    pub(crate) fn restore_item(world: &mut World, saved: &SavedEntity) -> Result<(), String> {
+        world.items.restore(&mut world.scene, saved)
+    }
+
+    /// Spawns a saved mob, see `EntityRegistry`
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world
+    /// * `saved` - The saved mob
+    pub(crate) fn restore_mob(world: &mut World, saved: &SavedEntity) -> Result<(), String> {
+        world.mobs.restore(&mut world.scene, saved)
+    }
+
+    /// Places a saved vehicle, see `EntityRegistry`
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world
+    /// * `saved` - The saved vehicle
+    pub(crate) fn restore_vehicle(world: &mut World, saved: &SavedEntity) -> Result<(), String> {
+        world.vehicles.restore(&mut world.scene, saved)
+    }
+
+    /// Spawns a saved entity of a type a script registered,
+    /// see `EntityRegistry`
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world
+    /// * `saved` - The saved entity
+    pub(crate) fn restore_scripted(world: &mut World, saved: &SavedEntity) -> Result<(), String> {
+        let definition = world.entity_types.definition(&saved.type_id);
+        world.scripted.spawn(&mut world.scene, saved, definition);
+        Ok(())
+    }
 
-        self.chunk_renderer.prepare();
+    /// Updates the loaded chunks around the given position and returns
+    /// the chunks which should be rendered, ordered from the inside to
+    /// the outside. Internally, a "spiral like" loop will be used to
+    /// walk the chunks around the player.
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - The position the chunks are loaded around
+    /// * `view_distance` - The distance in chunks up to which chunks are loaded
+    #[allow(unused_assignments)]
+    pub fn update(&mut self, center: &Vector3<f32>, view_distance: i32) -> Vec<Chunk> {
+        let mut visible_chunks = Vec::new();
 
-        let chunk_x = (camera.pos().x / CHUNK_SIZE as f32).floor();
-        let chunk_y = (camera.pos().z / CHUNK_SIZE as f32).floor();
+        let chunk_x = (center.x / CHUNK_SIZE as f32).floor();
+        let chunk_y = (center.z / CHUNK_SIZE as f32).floor();
 
-        let distance = (RENDER_DISTANCE * 2) + 3;
+        let distance = (view_distance * 2) + 3;
         let border = (distance / 2) as f32;
 
         let (mut x, mut y) = (0.0, 0.0);
@@ -123,14 +483,12 @@ impl World {
 
                 if x == -border || x == border || y == -border || y == border {
                     self.unload_chunk(&loc);
-                    self.chunk_renderer.remove_chunk(&loc);
                 } else {
                     self.load_chunk(&loc);
-                    self.chunk_renderer.add_chunk(&loc);
                 }
 
                 if let Some(chunk) = self.chunk(&loc) {
-                    self.chunk_renderer.render_chunk(chunk, &camera);
+                    visible_chunks.push(chunk.clone());
                 }
             }
 
@@ -143,6 +501,8 @@ impl World {
             x += dx;
             y += dy;
         }
+
+        visible_chunks
     }
 
     /// Returns the chunk at a given location
@@ -160,9 +520,874 @@ impl World {
         self.chunks.iter().find(|&chunk| chunk.loc() == loc)
     }
 
+    /// Returns the material of the block at the given
+    /// position in world coordinates
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    ///
+    /// # Safety
+    ///
+    /// This function returns `None` if the chunk containing
+    /// the block isn't loaded or the position is out of the
+    /// world's height.
+    pub fn block(&self, pos: Vector3<i32>) -> Option<Material> {
+        let (loc, local) = Self::split_pos(pos);
+        self.chunk(&loc)?.block(local)
+    }
+
+    /// Sets the material of the block at the given position in
+    /// world coordinates and returns the previous material. The
+    /// edit isn't recorded in the edit history.
+    ///
+    /// The chunk containing the block is recalculated, as well as
+    /// the neighbouring chunk if the block is at its border. Fluids
+    /// at or next to a changed block are updated a bit later, see
+    /// `fluids::schedule_updates`, as well as new fire.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    /// * `material` - The new material of the block
+    ///
+    /// # Safety
+    ///
+    /// This function returns `None` and doesn't change anything
+    /// if the chunk containing the block isn't loaded or the
+    /// position is out of the world's height.
+    pub fn set_block(&mut self, pos: Vector3<i32>, material: Material) -> Option<Material> {
+        let (loc, local) = Self::split_pos(pos);
+        let chunk = self.chunk(&loc)?;
+        let old = chunk.block(local)?;
+        chunk.set_block(local, material);
+        if old != material {
+            self.changes.push(BlockEdit { pos, old, new: material });
+            self.edited.entry(loc).or_default().insert(local, (material, 0));
+            self.dirty_regions.insert(Self::region_of(loc));
+            fluids::schedule_updates(self, pos);
+            if material == Material::Fire {
+                fire::schedule_tick(self, pos);
+            }
+            // Light spreads across the borders of chunks
+            for neighbour in light::neighbours_in_reach(loc, local).filter_map(|loc| self.chunk(&loc)) {
+                neighbour.mark_relight();
+            }
+        }
+
+        let last = CHUNK_SIZE as i16 - 1;
+        let mut neighbours = Vec::new();
+        if local.x == 0 { neighbours.push(Vector2::new(loc.x - 1, loc.y)); }
+        if local.x == last { neighbours.push(Vector2::new(loc.x + 1, loc.y)); }
+        if local.z == 0 { neighbours.push(Vector2::new(loc.x, loc.y - 1)); }
+        if local.z == last { neighbours.push(Vector2::new(loc.x, loc.y + 1)); }
+        for neighbour in neighbours.iter().filter_map(|loc| self.chunk(loc)) {
+            neighbour.mark_recalculate();
+        }
+
+        Some(old)
+    }
+
+    /// Returns the state of the block at the given position
+    /// in world coordinates, e.g. the growth stage of a crop
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    ///
+    /// # Safety
+    ///
+    /// This function returns `None` if the chunk containing
+    /// the block isn't loaded or the position is out of the
+    /// world's height.
+    pub fn block_state(&self, pos: Vector3<i32>) -> Option<u8> {
+        let (loc, local) = Self::split_pos(pos);
+        self.chunk(&loc)?.state(local)
+    }
+
+    /// Changes the state of the block at the given position in
+    /// world coordinates and returns the previous state. The
+    /// state is saved with the edited blocks, but isn't recorded
+    /// in the edit history.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    /// * `state` - The new state of the block
+    ///
+    /// # Safety
+    ///
+    /// This function returns `None` and doesn't change anything
+    /// if the chunk containing the block isn't loaded or the
+    /// position is out of the world's height.
+    pub fn set_block_state(&mut self, pos: Vector3<i32>, state: u8) -> Option<u8> {
+        let (loc, local) = Self::split_pos(pos);
+        let chunk = self.chunk(&loc)?;
+        let material = chunk.block(local)?;
+        let old = chunk.state(local)?;
+        if old != state {
+            chunk.set_state(local, state);
+            self.edited.entry(loc).or_default().insert(local, (material, state));
+            self.dirty_regions.insert(Self::region_of(loc));
+        }
+        Some(old)
+    }
+
+    /// Returns whether the block at the given position in world
+    /// coordinates is still the way the terrain generator made it
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    ///
+    /// # Safety
+    ///
+    /// This function returns `false` if the chunk containing
+    /// the block isn't loaded or the position is out of the
+    /// world's height.
+    pub fn is_generated(&self, pos: Vector3<i32>) -> bool {
+        let (loc, local) = Self::split_pos(pos);
+        match self.chunk(&loc) {
+            Some(chunk) if chunk.is_generated() && chunk.block(local).is_some() => {
+                !matches!(self.edited.get(&loc), Some(edited) if edited.contains_key(&local))
+            }
+            _ => false,
+        }
+    }
+
+    /// Restores a block to the material and state it has been
+    /// generated with, so it's no longer saved as an edited
+    /// block. Returns whether the block has been restored.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    /// * `material` - The generated material of the block
+    /// * `state` - The generated state of the block
+    pub fn regenerate_block(&mut self, pos: Vector3<i32>, material: Material, state: u8) -> bool {
+        if self.set_block(pos, material).is_none() {
+            return false;
+        }
+        let (loc, local) = Self::split_pos(pos);
+        if let Some(chunk) = self.chunk(&loc) {
+            chunk.set_state(local, state);
+        }
+        if let Some(edited) = self.edited.get_mut(&loc) {
+            edited.remove(&local);
+        }
+        self.dirty_regions.insert(Self::region_of(loc));
+        true
+    }
+
+    /// Returns the ticks scheduled for later updates
+    pub fn scheduled(&self) -> &TickQueue {
+        &self.scheduled
+    }
+
+    /// Returns the ticks scheduled for later updates mutably,
+    /// e.g. to schedule a tick
+    pub fn scheduled_mut(&mut self) -> &mut TickQueue {
+        &mut self.scheduled
+    }
+
+    /// Returns whether it rains in the world
+    pub fn is_raining(&self) -> bool {
+        self.raining
+    }
+
+    /// Lets it rain in the world or stops the rain
+    ///
+    /// # Arguments
+    ///
+    /// * `raining` - Whether it should rain
+    pub fn set_raining(&mut self, raining: bool) {
+        self.raining = raining;
+    }
+
+    /// Returns the light level of the sky at the given position in
+    /// world coordinates, from `0` to `15`. Blocks which aren't
+    /// lit by the sky because an opaque block is above them have
+    /// the level `0`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    ///
+    /// # Safety
+    ///
+    /// This function returns `None` if the chunk containing
+    /// the block isn't loaded or the position is out of the
+    /// world's height.
+    pub fn sky_light(&self, pos: Vector3<i32>) -> Option<u8> {
+        let (loc, local) = Self::split_pos(pos);
+        let chunk = self.chunk(&loc)?;
+        chunk.block(local)?;
+
+        let covered = (local.y + 1..CHUNK_HEIGHT as i16)
+            .any(|y| matches!(chunk.block(Vector3::new(local.x, y, local.z)), Some(block) if block.data().is_opaque()));
+        if covered {
+            return Some(0);
+        }
+        Some((self.sky.light(&self.time) * 15.0).round() as u8)
+    }
+
+    /// Sets the material of a block like `set_block` and records
+    /// the edit in the edit history. Returns whether the block
+    /// has been changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    /// * `material` - The new material of the block
+    pub fn edit_block(&mut self, pos: Vector3<i32>, material: Material) -> bool {
+        self.edit_blocks(&[(pos, material)]) > 0
+    }
+
+    /// Sets the materials of multiple blocks and records all
+    /// edits as a single operation in the edit history, so they
+    /// are reverted at once. Returns the amount of changed blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `edits` - The positions in world coordinates and the
+    /// new materials of the blocks
+    pub fn edit_blocks(&mut self, edits: &[(Vector3<i32>, Material)]) -> usize {
+        let mut operation = EditOperation::new();
+        for &(pos, new) in edits {
+            match self.set_block(pos, new) {
+                Some(old) if old != new => operation.push(BlockEdit { pos, old, new }),
+                _ => {}
+            }
+        }
+
+        let changed = operation.edits().len();
+        self.history.record(operation);
+        changed
+    }
+
+    /// Reverts the most recent edit operation. The operation is
+    /// only reverted if all of its blocks are loaded and haven't
+    /// been changed since, otherwise it stays in the history.
+    pub fn undo(&mut self) -> Result<(), EditError> {
+        let edits: Vec<BlockEdit> = match self.history.peek_undo() {
+            Some(operation) => operation.edits().iter().rev().map(|edit| BlockEdit {
+                pos: edit.pos,
+                old: edit.new,
+                new: edit.old,
+            }).collect(),
+            None => return Err(EditError::NothingToUndo),
+        };
+
+        self.apply_edits(&edits)?;
+        self.history.commit_undo();
+        Ok(())
+    }
+
+    /// Reapplies the most recently reverted edit operation. The
+    /// operation is only reapplied if all of its blocks are loaded
+    /// and haven't been changed since, otherwise it stays in the
+    /// history.
+    pub fn redo(&mut self) -> Result<(), EditError> {
+        let edits = match self.history.peek_redo() {
+            Some(operation) => operation.edits().to_vec(),
+            None => return Err(EditError::NothingToRedo),
+        };
+
+        self.apply_edits(&edits)?;
+        self.history.commit_redo();
+        Ok(())
+    }
+
+    /// Applies the given edits in order if every block still has
+    /// the material the edit expects. Otherwise, no block is changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `edits` - The edits which should be applied
+    fn apply_edits(&mut self, edits: &[BlockEdit]) -> Result<(), EditError> {
+        // An operation could change the same block multiple times,
+        // so the expected materials are tracked while validating
+        let mut expected = HashMap::new();
+        for edit in edits {
+            let current = match expected.get(&edit.pos) {
+                Some(&material) => material,
+                None => self.block(edit.pos).ok_or(EditError::NotLoaded(edit.pos))?,
+            };
+            if current != edit.old {
+                return Err(EditError::Conflict(edit.pos));
+            }
+            expected.insert(edit.pos, edit.new);
+        }
+
+        for edit in edits {
+            self.set_block(edit.pos, edit.new);
+        }
+        Ok(())
+    }
+
+    /// Returns the block changes since they have been taken
+    /// the last time, e.g. to find the ones of a single edit
+    pub fn changes(&self) -> &[BlockEdit] {
+        &self.changes
+    }
+
+    /// Takes all block changes since the last call, no matter
+    /// whether they are recorded in the edit history, e.g. to
+    /// record them in a replay
+    pub fn take_changes(&mut self) -> Vec<BlockEdit> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Restores the edited blocks and the entities of a saved
+    /// region. Chunks which are already loaded are updated as
+    /// well, their entities are spawned with the next update.
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The saved region
+    pub fn load_region(&mut self, region: &RegionData) {
+        for block in region.blocks.iter() {
+            let material = match Material::from_name(&block.material) {
+                Some(material) => material,
+                None => {
+                    log::warn!("Skipping block of unknown material {} at {:?}", block.material, block.pos);
+                    continue;
+                }
+            };
+
+            let (loc, local) = Self::split_pos(Vector3::from(block.pos));
+            self.edited.entry(loc).or_default().insert(local, (material, block.state));
+            if let Some(chunk) = self.chunk(&loc) {
+                chunk.set_block(local, material);
+                chunk.set_state(local, block.state);
+            }
+        }
+
+        for entity in region.entities.iter() {
+            let loc = chunk_entities::chunk_of(Vector3::from(entity.pos));
+            self.stored_entities.entry(loc).or_default().push(entity.clone());
+        }
+    }
+
+    /// Takes the regions with edits which haven't been saved
+    /// so far. The regions with entities in loaded chunks are
+    /// saved each time, as their entities keep changing.
+    pub fn take_dirty_regions(&mut self) -> Vec<RegionData> {
+        let entity_chunks = self.entity_chunks();
+        let entity_regions: HashSet<Vector2<i32>> = entity_chunks.iter().map(|loc| Self::region_of(*loc)).collect();
+        let mut regions: HashMap<Vector2<i32>, RegionData> = self.dirty_regions.drain()
+            .chain(self.entity_regions.drain())
+            .chain(entity_regions.iter().copied())
+            .map(|loc| (loc, RegionData { loc: [loc.x, loc.y], ..RegionData::default() }))
+            .collect();
+        self.entity_regions = entity_regions;
+
+        let size = CHUNK_SIZE as i32;
+        for (loc, blocks) in self.edited.iter() {
+            if let Some(region) = regions.get_mut(&Self::region_of(*loc)) {
+                region.blocks.extend(blocks.iter().map(|(pos, (material, state))| SavedBlock {
+                    pos: [loc.x * size + pos.x as i32, pos.y as i32, loc.y * size + pos.z as i32],
+                    material: material.name().to_string(),
+                    state: *state,
+                }));
+            }
+        }
+
+        for (loc, saved) in self.stored_entities.iter() {
+            if let Some(region) = regions.get_mut(&Self::region_of(*loc)) {
+                region.entities.extend(saved.iter().cloned());
+            }
+        }
+        for loc in entity_chunks {
+            let region = regions.get_mut(&Self::region_of(loc)).unwrap();
+            region.entities.extend(self.items.saved(loc));
+            region.entities.extend(self.mobs.saved(loc));
+            region.entities.extend(self.vehicles.saved(loc));
+            region.entities.extend(self.scripted.saved(loc));
+        }
+
+        regions.into_values().collect()
+    }
+
+    /// Returns the location of the region containing a chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the chunk
+    pub fn region_of(loc: Vector2<i32>) -> Vector2<i32> {
+        Vector2::new(loc.x.div_euclid(REGION_SIZE), loc.y.div_euclid(REGION_SIZE))
+    }
+
+    /// Splits a position in world coordinates into the location
+    /// of its chunk and the position within the chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    pub(crate) fn split_pos(pos: Vector3<i32>) -> (Vector2<i32>, Vector3<i16>) {
+        let size = CHUNK_SIZE as i32;
+        let loc = Vector2::new(pos.x.div_euclid(size), pos.z.div_euclid(size));
+        let y = pos.y.clamp(-1, i16::MAX as i32) as i16;
+        let local = Vector3::new(pos.x.rem_euclid(size) as i16, y, pos.z.rem_euclid(size) as i16);
+        (loc, local)
+    }
+
     /// Returns all chunks which are currently
     /// loaded from the file system
     pub fn chunks(&self) -> &Vec<Chunk> {
         &self.chunks
     }
-}
\ No newline at end of file
+
+    /// Returns the scene containing all non-voxel props
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    /// Returns the mutable scene containing all non-voxel props
+    pub fn scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+
+    /// Returns the items lying in the world
+    pub fn items(&self) -> &ItemEntities {
+        &self.items
+    }
+
+    /// Drops items at the given position, e.g. the
+    /// drops of a broken block
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - The name of the item
+    /// * `count` - The amount of items
+    /// * `pos` - The position of the bottom center of the items
+    pub fn spawn_item(&mut self, item: &str, count: u64, pos: Vector3<f32>) {
+        self.items.spawn(&mut self.scene, item, count, pos);
+    }
+
+    /// Advances the items lying in the chunks within the activation
+    /// radius around the player and returns the ones the player
+    /// picked up with their amount. The stored entities of chunks
+    /// which have been generated again are spawned first.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    /// * `player` - The position of the player's feet
+    /// * `radius` - The activation radius in chunks
+    /// * `budget` - The largest amount of items ticked in this update
+    pub fn update_items(&mut self, seconds: f32, player: Vector3<f32>, radius: i32, budget: usize) -> Vec<(String, u64)> {
+        self.restore_entities();
+        let chunks = &self.chunks;
+        let block_at = |pos| {
+            let (loc, local) = Self::split_pos(pos);
+            chunks.iter().find(|chunk| *chunk.loc() == loc)?.block(local)
+        };
+        self.items.update(&mut self.scene, seconds, player, radius, budget, block_at)
+    }
+
+    /// Returns the primed TNT in the world
+    pub fn primed_tnt(&self) -> &PrimedTntEntities {
+        &self.tnt
+    }
+
+    /// Spawns primed TNT at the given position, see `explosion::prime`
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the bottom center of the TNT
+    /// * `fuse` - The time in seconds until the TNT explodes
+    pub fn spawn_primed_tnt(&mut self, pos: Vector3<f32>, fuse: f32) {
+        self.tnt.spawn(&mut self.scene, pos, fuse);
+    }
+
+    /// Advances the primed TNT and blows up the blocks around
+    /// the TNT whose fuse burned down. Returns the explosions.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    /// * `spared` - Returns whether a block is protected from explosions
+    pub fn update_tnt<F>(&mut self, seconds: f32, spared: F) -> Vec<Explosion>
+        where F: Fn(Vector3<i32>) -> bool
+    {
+        let chunks = &self.chunks;
+        let block_at = |pos| {
+            let (loc, local) = Self::split_pos(pos);
+            chunks.iter().find(|chunk| *chunk.loc() == loc)?.block(local)
+        };
+        let centers = self.tnt.update(&mut self.scene, seconds, block_at);
+        centers.into_iter()
+            .map(|center| explosion::explode(self, center, explosion::TNT_RADIUS, &spared))
+            .collect()
+    }
+
+    /// Returns the entities of types scripts registered
+    pub fn scripted_entities(&self) -> &ScriptedEntities {
+        &self.scripted
+    }
+
+    /// Fades the red flash of the scripted entities
+    /// which took damage
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    pub fn update_hurt_flashes(&mut self, seconds: f32) {
+        self.scripted.update_flashes(&mut self.scene, seconds);
+    }
+
+    /// Applies the state of a scripted entity its behavior
+    /// changed. Entities don't move into chunks which aren't
+    /// loaded, as they couldn't be saved with them.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The changed state of the entity
+    pub fn apply_entity_state(&mut self, mut state: EntityState) {
+        let loc = chunk_entities::chunk_of(state.pos);
+        if !self.chunks.iter().any(|chunk| *chunk.loc() == loc) {
+            if let Some(current) = self.scripted.state(state.uuid) {
+                state.pos = current.pos;
+            }
+        }
+        self.scripted.apply(&mut self.scene, state);
+    }
+
+    /// Returns the mobs in the world
+    pub fn mobs(&self) -> &Mobs {
+        &self.mobs
+    }
+
+    /// Advances the spawners and the mobs in the chunks within
+    /// the activation radius around the player. Mobs which walked
+    /// into a chunk which isn't loaded are stored with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    /// * `player` - The position of the player's feet
+    /// * `name` - The name of the player, whose tamed mobs follow them
+    /// * `radius` - The activation radius in chunks
+    pub fn update_mobs(&mut self, seconds: f32, player: Vector3<f32>, name: &str, radius: i32) {
+        let chunks = &self.chunks;
+        let mobs = &mut self.mobs;
+        let scene = &mut self.scene;
+        let block_at = |pos| {
+            let (loc, local) = Self::split_pos(pos);
+            chunks.iter().find(|chunk| *chunk.loc() == loc)?.block(local)
+        };
+
+        let center = chunk_entities::chunk_of(player);
+        for chunk in chunks.iter().filter(|chunk| chunk_entities::is_active(*chunk.loc(), center, radius)) {
+            let origin = Vector3::new(chunk.loc().x * CHUNK_SIZE as i32, 0, chunk.loc().y * CHUNK_SIZE as i32);
+            chunk.update_block_entities(|local, entity| {
+                let pos = origin + local.cast::<i32>().unwrap();
+                mobs.tick_spawner(scene, pos, entity, seconds, player, &block_at);
+            });
+        }
+        mobs.update(scene, seconds, player, name, radius, block_at);
+
+        for loc in self.mobs.mobs().chunks() {
+            if self.chunk(&loc).is_none() {
+                self.store_entities(loc);
+            }
+        }
+    }
+
+    /// Tries to tame a mob for a player, see `Mobs::tame`
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The id of the mob
+    /// * `owner` - The name of the player
+    pub fn tame_mob(&mut self, uuid: EntityUuid, owner: &str) -> Option<bool> {
+        self.mobs.tame(uuid, owner)
+    }
+
+    /// Tells a tamed mob to sit down or to stand up again,
+    /// see `Mobs::toggle_sitting`
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The id of the mob
+    /// * `owner` - The name of the player
+    pub fn toggle_mob_sitting(&mut self, uuid: EntityUuid, owner: &str) -> Option<bool> {
+        self.mobs.toggle_sitting(&mut self.scene, uuid, owner)
+    }
+
+    /// Returns the vehicles in the world
+    pub fn vehicles(&self) -> &Vehicles {
+        &self.vehicles
+    }
+
+    /// Places a vehicle at the given position and returns its id
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of the vehicle
+    /// * `pos` - The position of the bottom center of the vehicle
+    /// * `yaw` - The direction the vehicle faces in radians
+    pub fn spawn_vehicle(&mut self, kind: VehicleKind, pos: Vector3<f32>, yaw: f32) -> EntityUuid {
+        self.vehicles.spawn(&mut self.scene, kind, pos, yaw)
+    }
+
+    /// Removes a vehicle from the world and returns its kind
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The id of the vehicle
+    pub fn remove_vehicle(&mut self, uuid: EntityUuid) -> Option<VehicleKind> {
+        self.vehicles.remove(&mut self.scene, uuid)
+    }
+
+    /// Advances the vehicles in the chunks within the activation
+    /// radius around the player. Vehicles which drove into a chunk
+    /// which isn't loaded are stored with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    /// * `player` - The position of the player's feet
+    /// * `radius` - The activation radius in chunks
+    /// * `ridden` - The vehicle the player rides, with their steering
+    pub fn update_vehicles(&mut self, seconds: f32, player: Vector3<f32>, radius: i32, ridden: Option<(EntityUuid, Steering)>) {
+        let chunks = &self.chunks;
+        let block_at = |pos| {
+            let (loc, local) = Self::split_pos(pos);
+            chunks.iter().find(|chunk| *chunk.loc() == loc)?.block(local)
+        };
+        self.vehicles.update(&mut self.scene, seconds, player, radius, ridden, block_at);
+
+        for loc in self.vehicles.vehicles().chunks() {
+            if self.chunk(&loc).is_none() {
+                self.store_entities(loc);
+            }
+        }
+    }
+
+    /// Removes the block entity of the block at the given
+    /// position in world coordinates and returns it, e.g.
+    /// to roll the loot of a broken chest
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    pub fn take_block_entity(&mut self, pos: Vector3<i32>) -> Option<BlockEntity> {
+        let (loc, local) = Self::split_pos(pos);
+        self.chunk(&loc)?.take_block_entity(local)
+    }
+
+    /// Returns the block entity of the block at the given
+    /// position in world coordinates
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    pub fn block_entity(&self, pos: Vector3<i32>) -> Option<BlockEntity> {
+        let (loc, local) = Self::split_pos(pos);
+        self.chunk(&loc)?.block_entity(local)
+    }
+
+    /// Attaches a block entity to the block at the given
+    /// position in world coordinates, if its chunk is loaded
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    /// * `entity` - The block entity
+    pub fn set_block_entity(&mut self, pos: Vector3<i32>, entity: BlockEntity) {
+        let (loc, local) = Self::split_pos(pos);
+        if let Some(chunk) = self.chunk(&loc) {
+            chunk.set_block_entity(local, entity);
+        }
+    }
+
+    /// Advances the furnaces in the chunks within the activation
+    /// radius around the player. Furnaces which are lit or went
+    /// out are swapped with the block showing it, which keeps
+    /// their block entity.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    /// * `player` - The position of the player's feet
+    /// * `radius` - The activation radius in chunks
+    /// * `smelting` - The smelting recipes and fuels
+    pub fn update_furnaces(&mut self, seconds: f32, player: Vector3<f32>, radius: i32, smelting: &Smelting) {
+        let center = chunk_entities::chunk_of(player);
+        let mut swapped = Vec::new();
+        for chunk in self.chunks.iter().filter(|chunk| chunk_entities::is_active(*chunk.loc(), center, radius)) {
+            let origin = Vector3::new(chunk.loc().x * CHUNK_SIZE as i32, 0, chunk.loc().y * CHUNK_SIZE as i32);
+            chunk.update_block_entities(|local, entity| {
+                if let BlockEntity::Furnace(furnace) = entity {
+                    furnace.tick(seconds, smelting);
+                    let material = furnace::furnace_material(furnace.is_lit());
+                    if chunk.block(local) != Some(material) {
+                        swapped.push((origin + local.cast::<i32>().unwrap(), material));
+                    }
+                }
+            });
+        }
+
+        for (pos, material) in swapped {
+            if let Some(entity) = self.take_block_entity(pos) {
+                self.set_block(pos, material);
+                self.set_block_entity(pos, entity);
+            }
+        }
+    }
+
+    /// Lets the hoppers in the chunks within the activation radius
+    /// around the player move items once their cooldown is over.
+    /// A hopper first pushes an item into the container it faces,
+    /// then pulls an item out of the container above.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    /// * `player` - The position of the player's feet
+    /// * `radius` - The activation radius in chunks
+    /// * `smelting` - The smelting recipes and fuels
+    pub fn update_hoppers(&mut self, seconds: f32, player: Vector3<f32>, radius: i32, smelting: &Smelting) {
+        let center = chunk_entities::chunk_of(player);
+        let mut due = Vec::new();
+        for chunk in self.chunks.iter().filter(|chunk| chunk_entities::is_active(*chunk.loc(), center, radius)) {
+            let origin = Vector3::new(chunk.loc().x * CHUNK_SIZE as i32, 0, chunk.loc().y * CHUNK_SIZE as i32);
+            chunk.update_block_entities(|local, entity| {
+                if let BlockEntity::Hopper(hopper) = entity {
+                    if hopper.tick(seconds) {
+                        due.push(origin + local.cast::<i32>().unwrap());
+                    }
+                }
+            });
+        }
+
+        for pos in due {
+            let state = self.block_state(pos).unwrap_or(0);
+            let target = pos + hopper::target_offset(state);
+            self.move_item(pos, target, hopper::facing(state).is_none(), smelting);
+            self.move_item(pos + Vector3::new(0, 1, 0), pos, true, smelting);
+        }
+    }
+
+    /// Moves a single item from one container into another,
+    /// creating the block entity of containers without one
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The position of the container the item is pulled out of
+    /// * `to` - The position of the container the item is pushed into
+    /// * `from_above` - Whether `to` is below `from`
+    /// * `smelting` - The smelting recipes and fuels
+    fn move_item(&mut self, from: Vector3<i32>, to: Vector3<i32>, from_above: bool, smelting: &Smelting) {
+        let container = |pos| self.block_entity(pos).or_else(|| BlockEntity::container(self.block(pos)?));
+        let (mut source, mut target) = match (container(from), container(to)) {
+            (Some(source), Some(target)) => (source, target),
+            _ => return,
+        };
+        if hopper::move_item(&mut source, &mut target, from_above, smelting) {
+            self.set_block_entity(from, source);
+            self.set_block_entity(to, target);
+        }
+    }
+
+    /// Returns the time of the world
+    pub fn time(&self) -> &WorldTime {
+        &self.time
+    }
+
+    /// Returns the mutable time of the world
+    pub fn time_mut(&mut self) -> &mut WorldTime {
+        &mut self.time
+    }
+
+    /// Returns the look of the sky of the world
+    pub fn sky(&self) -> &SkySettings {
+        &self.sky
+    }
+
+    /// Returns the border beyond which no chunks are loaded
+    pub fn border(&self) -> Option<&WorldBorder> {
+        self.border.as_ref()
+    }
+
+    /// Sets the border beyond which no chunks are loaded.
+    /// Loaded chunks beyond the new border are unloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `border` - The border, `None` for no border
+    pub fn set_border(&mut self, border: Option<WorldBorder>) {
+        self.border = border;
+        if let Some(border) = border {
+            let outside: Vec<Vector2<i32>> = self.chunks.iter()
+                .map(|chunk| *chunk.loc())
+                .filter(|loc| !border.contains_chunk(loc))
+                .collect();
+            for loc in outside.iter() {
+                self.unload_chunk(loc);
+            }
+        }
+    }
+
+    /// Sets the plants decorating the terrain of chunks
+    /// which are generated from now on
+    ///
+    /// # Arguments
+    ///
+    /// * `plants` - The plants
+    pub fn set_plants(&mut self, plants: Plants) {
+        self.plants = plants;
+    }
+
+    /// Compresses or evicts the blocks of the chunks which have
+    /// been idle long enough. Returns the amount of chunks which
+    /// have been compressed and evicted.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    /// * `settings` - The idle times of the tiers
+    pub fn update_tiers(&mut self, seconds: f32, settings: TierSettings) -> (usize, usize) {
+        self.tiers.update(&self.chunks, seconds, settings)
+    }
+
+    /// Returns the steps generating the terrain of the world's chunks
+    pub fn terrain_pipeline(&self) -> TerrainPipeline {
+        TerrainPipeline {
+            terrain_gen: self.terrain_gen.clone(),
+            plants: self.plants.clone(),
+        }
+    }
+
+    /// Returns the generator of the world's terrain, e.g. to
+    /// look up the biomes and features it generated
+    pub fn terrain_gen(&self) -> &(dyn TerrainGen + Send + Sync) {
+        &**self.terrain_gen
+    }
+
+    /// Returns the terrain which has been pregenerated
+    pub fn pregenerated(&self) -> Option<&PregenCache> {
+        self.pregenerated.as_ref()
+    }
+
+    /// Sets the terrain which has been pregenerated, which the
+    /// chunks loaded afterwards are restored from
+    ///
+    /// # Arguments
+    ///
+    /// * `pregenerated` - The pregenerated terrain
+    pub fn set_pregenerated(&mut self, pregenerated: Option<PregenCache>) {
+        self.pregenerated = pregenerated;
+    }
+
+    /// Sets the spawn functions saved entities are spawned by
+    ///
+    /// # Arguments
+    ///
+    /// * `entity_types` - The spawn functions of the entity types
+    pub fn set_entity_types(&mut self, entity_types: EntityRegistry) {
+        self.entity_types = entity_types;
+    }
+}
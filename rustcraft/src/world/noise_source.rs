@@ -0,0 +1,510 @@
+//! Types to sample the noise the terrain is generated
+//! from, independent of the algorithm behind it
+
+use crate::scripting::ScriptEngine;
+use mlua::{Table, UserData, UserDataMethods};
+use noise::{NoiseFn, OpenSimplex, Perlin, Seedable, SuperSimplex, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The amount of samples a cached noise keeps before
+/// it forgets all of them
+const CACHE_CAPACITY: usize = 1 << 16;
+
+/// NoiseSource
+///
+/// A trait which can be implemented by everything
+/// producing a smooth value for each point of the
+/// plane, e.g. the height of the terrain
+pub trait NoiseSource: Send + Sync {
+    /// Returns the value between `-1` and `1` at a point
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The x coordinate in blocks
+    /// * `z` - The z coordinate in blocks
+    fn get(&self, x: f64, z: f64) -> f64;
+}
+
+impl<T: NoiseSource + ?Sized> NoiseSource for Box<T> {
+    fn get(&self, x: f64, z: f64) -> f64 {
+        (**self).get(x, z)
+    }
+}
+
+impl<T: NoiseSource + ?Sized> NoiseSource for Arc<T> {
+    fn get(&self, x: f64, z: f64) -> f64 {
+        (**self).get(x, z)
+    }
+}
+
+/// NoiseAlgorithm
+///
+/// The algorithms noise could be generated with:
+/// * `Perlin` - Classic gradient noise, the noise of the default terrain
+/// * `OpenSimplex` - Simplex-like gradient noise with fewer directional artifacts
+/// * `SuperSimplex` - The smoother successor of `OpenSimplex`
+/// * `Value` - Interpolated random values, blockier than gradient noise
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum NoiseAlgorithm {
+    Perlin,
+    OpenSimplex,
+    SuperSimplex,
+    Value,
+}
+
+impl NoiseAlgorithm {
+    /// Returns the algorithm with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the algorithm, e.g. `"perlin"`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "perlin" => Some(NoiseAlgorithm::Perlin),
+            "open_simplex" => Some(NoiseAlgorithm::OpenSimplex),
+            "super_simplex" => Some(NoiseAlgorithm::SuperSimplex),
+            "value" => Some(NoiseAlgorithm::Value),
+            _ => None,
+        }
+    }
+}
+
+/// Noise
+///
+/// Noise of a single algorithm with a seed and a frequency
+pub struct Noise {
+    /// The noise function of the algorithm
+    function: Box<dyn NoiseFn<[f64; 2]> + Send + Sync>,
+    /// The amount of periods of the noise per block
+    frequency: f64,
+}
+
+impl Noise {
+    /// Creates new noise
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - The algorithm generating the noise
+    /// * `seed` - The seed of the noise
+    /// * `frequency` - The amount of periods of the noise per block,
+    ///   e.g. `1 / 16` for hills which are about a chunk wide
+    pub fn new(algorithm: NoiseAlgorithm, seed: u32, frequency: f64) -> Self {
+        let function: Box<dyn NoiseFn<[f64; 2]> + Send + Sync> = match algorithm {
+            NoiseAlgorithm::Perlin => Box::new(Perlin::new().set_seed(seed)),
+            NoiseAlgorithm::OpenSimplex => Box::new(OpenSimplex::new().set_seed(seed)),
+            NoiseAlgorithm::SuperSimplex => Box::new(SuperSimplex::new().set_seed(seed)),
+            NoiseAlgorithm::Value => Box::new(Value::new().set_seed(seed)),
+        };
+
+        Self {
+            function,
+            frequency,
+        }
+    }
+}
+
+impl NoiseSource for Noise {
+    fn get(&self, x: f64, z: f64) -> f64 {
+        self.function.get([x * self.frequency, z * self.frequency]).clamp(-1.0, 1.0)
+    }
+}
+
+/// VolumeNoise
+///
+/// Noise of a single algorithm with a seed and a frequency,
+/// which is sampled in three dimensions rather than on the
+/// plane, e.g. for hollows within the terrain
+pub struct VolumeNoise {
+    /// The noise function of the algorithm
+    function: Box<dyn NoiseFn<[f64; 3]> + Send + Sync>,
+    /// The amount of periods of the noise per block
+    frequency: f64,
+}
+
+impl VolumeNoise {
+    /// Creates new volume noise
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - The algorithm generating the noise
+    /// * `seed` - The seed of the noise
+    /// * `frequency` - The amount of periods of the noise per block
+    pub fn new(algorithm: NoiseAlgorithm, seed: u32, frequency: f64) -> Self {
+        let function: Box<dyn NoiseFn<[f64; 3]> + Send + Sync> = match algorithm {
+            NoiseAlgorithm::Perlin => Box::new(Perlin::new().set_seed(seed)),
+            NoiseAlgorithm::OpenSimplex => Box::new(OpenSimplex::new().set_seed(seed)),
+            NoiseAlgorithm::SuperSimplex => Box::new(SuperSimplex::new().set_seed(seed)),
+            NoiseAlgorithm::Value => Box::new(Value::new().set_seed(seed)),
+        };
+
+        Self {
+            function,
+            frequency,
+        }
+    }
+
+    /// Returns the value between `-1` and `1` at a point
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The x coordinate in blocks
+    /// * `y` - The y coordinate in blocks
+    /// * `z` - The z coordinate in blocks
+    pub fn get(&self, x: f64, y: f64, z: f64) -> f64 {
+        self.function.get([x * self.frequency, y * self.frequency, z * self.frequency]).clamp(-1.0, 1.0)
+    }
+}
+
+/// CachedNoise
+///
+/// Noise which remembers its samples, for noise which
+/// is expensive to compute and sampled at the same
+/// points repeatedly, e.g. by several layers
+pub struct CachedNoise<N: NoiseSource> {
+    /// The noise which is cached
+    noise: N,
+    /// The samples by the bits of their coordinates
+    samples: Mutex<HashMap<(u64, u64), f64>>,
+}
+
+impl<N: NoiseSource> CachedNoise<N> {
+    /// Creates new cached noise
+    ///
+    /// # Arguments
+    ///
+    /// * `noise` - The noise which is cached
+    pub fn new(noise: N) -> Self {
+        Self {
+            noise,
+            samples: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<N: NoiseSource> NoiseSource for CachedNoise<N> {
+    fn get(&self, x: f64, z: f64) -> f64 {
+        let key = (x.to_bits(), z.to_bits());
+        if let Some(value) = self.samples.lock().unwrap().get(&key) {
+            return *value;
+        }
+
+        // The lock isn't held while sampling, so that other
+        // threads could read the cache in the meantime
+        let value = self.noise.get(x, z);
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() >= CACHE_CAPACITY {
+            samples.clear();
+        }
+        samples.insert(key, value);
+        value
+    }
+}
+
+/// TiledNoise
+///
+/// Noise which repeats seamlessly after a period, e.g.
+/// for textures or worlds which wrap around
+pub struct TiledNoise<N: NoiseSource> {
+    /// The noise which is tiled
+    noise: N,
+    /// The width and depth of a tile in blocks
+    period: f64,
+}
+
+impl<N: NoiseSource> TiledNoise<N> {
+    /// Creates new tiled noise
+    ///
+    /// # Arguments
+    ///
+    /// * `noise` - The noise which is tiled
+    /// * `period` - The width and depth of a tile in blocks
+    pub fn new(noise: N, period: f64) -> Self {
+        Self {
+            noise,
+            period: period.max(1.0),
+        }
+    }
+}
+
+impl<N: NoiseSource> NoiseSource for TiledNoise<N> {
+    fn get(&self, x: f64, z: f64) -> f64 {
+        // Blends the noise with its copies a period away, so
+        // that both edges of a tile sample the same values
+        let x = x.rem_euclid(self.period);
+        let z = z.rem_euclid(self.period);
+        let u = x / self.period;
+        let v = z / self.period;
+
+        self.noise.get(x, z) * (1.0 - u) * (1.0 - v)
+            + self.noise.get(x - self.period, z) * u * (1.0 - v)
+            + self.noise.get(x, z - self.period) * (1.0 - u) * v
+            + self.noise.get(x - self.period, z - self.period) * u * v
+    }
+}
+
+/// LayeredNoise
+///
+/// Noise composed of several layers, each weighted by
+/// its amplitude, e.g. a rough layer on top of hills
+#[derive(Default)]
+pub struct LayeredNoise {
+    /// The layers and their amplitudes
+    layers: Vec<(Box<dyn NoiseSource>, f64)>,
+}
+
+impl LayeredNoise {
+    /// Adds a layer
+    ///
+    /// # Arguments
+    ///
+    /// * `noise` - The noise of the layer
+    /// * `amplitude` - The weight of the layer
+    pub fn add(&mut self, noise: Box<dyn NoiseSource>, amplitude: f64) {
+        self.layers.push((noise, amplitude));
+    }
+
+    /// Creates the layered noise of the settings of its layers
+    ///
+    /// # Arguments
+    ///
+    /// * `layers` - The settings of the layers
+    pub fn from_settings(layers: &[NoiseSettings]) -> Self {
+        let mut noise = Self::default();
+        for layer in layers {
+            noise.add(layer.build(), layer.amplitude);
+        }
+        noise
+    }
+}
+
+impl NoiseSource for LayeredNoise {
+    fn get(&self, x: f64, z: f64) -> f64 {
+        // The sum is divided by the total amplitude to stay
+        // between -1 and 1 regardless of the layers
+        let total: f64 = self.layers.iter().map(|(_, amplitude)| amplitude.abs()).sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let sum: f64 = self.layers.iter().map(|(noise, amplitude)| noise.get(x, z) * amplitude).sum();
+        sum / total
+    }
+}
+
+/// NoiseSettings
+///
+/// The parameters noise is created from, e.g. by scripts
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseSettings {
+    /// The algorithm generating the noise
+    pub algorithm: NoiseAlgorithm,
+    /// The seed of the noise
+    pub seed: u32,
+    /// The amount of periods of the noise per block
+    pub frequency: f64,
+    /// The width and depth of a tile in blocks, if the
+    /// noise repeats
+    pub period: Option<f64>,
+    /// Whether the samples of the noise are cached
+    pub cached: bool,
+    /// The weight of the noise if it's a layer
+    pub amplitude: f64,
+}
+
+impl Default for NoiseSettings {
+    fn default() -> Self {
+        Self {
+            algorithm: NoiseAlgorithm::Perlin,
+            seed: 0,
+            frequency: 1.0 / 16.0,
+            period: None,
+            cached: false,
+            amplitude: 1.0,
+        }
+    }
+}
+
+impl NoiseSettings {
+    /// Creates the noise of these settings
+    pub fn build(&self) -> Box<dyn NoiseSource> {
+        let mut noise: Box<dyn NoiseSource> = Box::new(Noise::new(self.algorithm, self.seed, self.frequency));
+        if let Some(period) = self.period {
+            noise = Box::new(TiledNoise::new(noise, period));
+        }
+        if self.cached {
+            noise = Box::new(CachedNoise::new(noise));
+        }
+        noise
+    }
+
+    /// Reads the settings from a Lua table. All fields
+    /// are optional:
+    ///
+    /// ```lua
+    /// {
+    ///     -- "perlin", "open_simplex", "super_simplex" or "value"
+    ///     algorithm = "perlin",
+    ///     seed = 0,
+    ///     frequency = 1 / 16,
+    ///     -- Repeats the noise every 64 blocks
+    ///     period = 64,
+    ///     cached = false,
+    ///     -- The weight of the noise if it's a layer
+    ///     amplitude = 1,
+    /// }
+    /// ```
+    ///
+    /// # Arguments
+    ///
+    /// * `def` - The Lua table
+    pub fn from_table(def: &Table) -> mlua::Result<Self> {
+        let defaults = Self::default();
+        let algorithm = match def.get::<_, Option<String>>("algorithm")? {
+            Some(name) => NoiseAlgorithm::from_name(&name).ok_or_else(|| {
+                mlua::Error::RuntimeError(format!("unknown noise algorithm {}", name))
+            })?,
+            None => defaults.algorithm,
+        };
+
+        Ok(Self {
+            algorithm,
+            seed: def.get::<_, Option<u32>>("seed")?.unwrap_or(defaults.seed),
+            frequency: def.get::<_, Option<f64>>("frequency")?.unwrap_or(defaults.frequency),
+            period: def.get("period")?,
+            cached: def.get::<_, Option<bool>>("cached")?.unwrap_or(defaults.cached),
+            amplitude: def.get::<_, Option<f64>>("amplitude")?.unwrap_or(defaults.amplitude),
+        })
+    }
+
+    /// Reads the settings of layers from a Lua list of tables
+    ///
+    /// # Arguments
+    ///
+    /// * `layers` - The Lua list
+    pub fn layers_from_table(layers: &Table) -> mlua::Result<Vec<Self>> {
+        layers.clone().sequence_values::<Table>()
+            .map(|layer| Self::from_table(&layer?))
+            .collect()
+    }
+}
+
+/// Noise handed to scripts, which is sampled with `get`
+struct ScriptNoise(Arc<dyn NoiseSource>);
+
+impl UserData for ScriptNoise {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("get", |_, noise, (x, z): (f64, f64)| Ok(noise.0.get(x, z)));
+    }
+}
+
+/// Registers the `noise` API in the given script engine,
+/// which lets scripts sample and compose noise, e.g.
+///
+/// ```lua
+/// local hills = noise.new { algorithm = "super_simplex", seed = 7 }
+/// local rough = noise.layered { { frequency = 1 / 32 }, { frequency = 1 / 4, amplitude = 0.25 } }
+/// local height = hills:get(x, z) + rough:get(x, z)
+/// ```
+///
+/// # Arguments
+///
+/// * `engine` - The script engine
+pub fn register_api(engine: &ScriptEngine) -> mlua::Result<()> {
+    let lua = engine.lua();
+    let api = lua.create_table()?;
+    api.set("new", lua.create_function(|_, def: Option<Table>| {
+        let settings = match def {
+            Some(def) => NoiseSettings::from_table(&def)?,
+            None => NoiseSettings::default(),
+        };
+        Ok(ScriptNoise(Arc::from(settings.build())))
+    })?)?;
+    api.set("layered", lua.create_function(|_, layers: Table| {
+        let layers = NoiseSettings::layers_from_table(&layers)?;
+        Ok(ScriptNoise(Arc::new(LayeredNoise::from_settings(&layers))))
+    })?)?;
+    lua.globals().set("noise", api)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn algorithms_differ_and_stay_in_range() {
+        let algorithms = [NoiseAlgorithm::Perlin, NoiseAlgorithm::OpenSimplex, NoiseAlgorithm::SuperSimplex, NoiseAlgorithm::Value];
+        let samples: Vec<Vec<f64>> = algorithms.iter()
+            .map(|algorithm| {
+                let noise = Noise::new(*algorithm, 3, 1.0 / 7.0);
+                (0..64).map(|i| noise.get(i as f64 * 1.3, i as f64 * 0.7)).collect()
+            })
+            .collect();
+
+        for (i, values) in samples.iter().enumerate() {
+            assert!(values.iter().all(|value| (-1.0..=1.0).contains(value)));
+            for other in samples.iter().skip(i + 1) {
+                assert_ne!(values, other);
+            }
+        }
+        assert_eq!(NoiseAlgorithm::from_name("super_simplex"), Some(NoiseAlgorithm::SuperSimplex));
+        assert_eq!(NoiseAlgorithm::from_name("simplex"), None);
+    }
+
+    #[test]
+    fn seeds_change_the_noise() {
+        let a = Noise::new(NoiseAlgorithm::Perlin, 1, 0.1);
+        let b = Noise::new(NoiseAlgorithm::Perlin, 2, 0.1);
+        assert!((0..16).any(|i| a.get(i as f64 + 0.5, 0.5) != b.get(i as f64 + 0.5, 0.5)));
+    }
+
+    #[test]
+    fn volume_noise_changes_with_the_height() {
+        let noise = VolumeNoise::new(NoiseAlgorithm::Perlin, 3, 1.0 / 7.0);
+        let column: Vec<f64> = (0..32).map(|y| noise.get(2.5, y as f64 + 0.5, 4.5)).collect();
+        assert!(column.iter().all(|value| (-1.0..=1.0).contains(value)));
+        assert!(column.windows(2).any(|pair| pair[0] != pair[1]));
+        assert_eq!(noise.get(1.5, 2.5, 3.5), VolumeNoise::new(NoiseAlgorithm::Perlin, 3, 1.0 / 7.0).get(1.5, 2.5, 3.5));
+    }
+
+    #[test]
+    fn tiled_noise_repeats() {
+        let tiled = TiledNoise::new(Noise::new(NoiseAlgorithm::Perlin, 5, 1.0 / 9.0), 32.0);
+        for i in 0..32 {
+            let (x, z) = (i as f64 + 0.25, i as f64 * 0.5);
+            assert!((tiled.get(x, z) - tiled.get(x + 32.0, z - 64.0)).abs() < 1e-9);
+        }
+        assert!((tiled.get(31.999, 3.0) - tiled.get(0.0, 3.0)).abs() < 1e-2);
+    }
+
+    #[test]
+    fn cached_and_layered_noise_match_their_layers() {
+        let noise = Noise::new(NoiseAlgorithm::Value, 4, 0.2);
+        let cached = CachedNoise::new(Noise::new(NoiseAlgorithm::Value, 4, 0.2));
+        for i in 0..8 {
+            assert_eq!(cached.get(i as f64, 1.0), noise.get(i as f64, 1.0));
+            assert_eq!(cached.get(i as f64, 1.0), noise.get(i as f64, 1.0));
+        }
+
+        let mut layered = LayeredNoise::default();
+        assert_eq!(layered.get(1.0, 1.0), 0.0);
+        layered.add(Box::new(Noise::new(NoiseAlgorithm::Value, 4, 0.2)), 3.0);
+        layered.add(Box::new(Noise::new(NoiseAlgorithm::Value, 4, 0.2)), 1.0);
+        assert!((layered.get(2.5, 1.0) - noise.get(2.5, 1.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn scripts_sample_noise() {
+        let engine = ScriptEngine::new();
+        register_api(&engine).unwrap();
+        engine.exec("test", r#"
+            local hills = noise.new { algorithm = "open_simplex", seed = 7, frequency = 0.1, period = 64, cached = true }
+            local layered = noise.layered { { seed = 1 }, { algorithm = "value", amplitude = 0.5 } }
+            value = hills:get(3.5, 4.5) + layered:get(3.5, 4.5)
+        "#).unwrap();
+        let value: f64 = engine.lua().globals().get("value").unwrap();
+        assert!((-2.0..=2.0).contains(&value));
+
+        assert!(engine.exec("test", r#"noise.new { algorithm = "simplex" }"#).is_err());
+    }
+}
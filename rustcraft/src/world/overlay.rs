@@ -0,0 +1,196 @@
+//! Types to layer a second texture over the faces of blocks,
+//! e.g. a tinted grass fringe, moss or cracks
+
+use crate::scripting::{self, ScriptEngine};
+use crate::world::block::Material;
+use cgmath::{Vector2, Vector3, Vector4};
+use mlua::Table;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Overlay
+///
+/// A tile of the texture atlas which is blended over
+/// the texture of a block face
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Overlay {
+    /// The coordinate of the tile within the texture atlas
+    pub tile: Vector2<f32>,
+    /// The color the tile is multiplied with, where the
+    /// alpha component is the opacity of the overlay
+    pub tint: Vector4<f32>,
+}
+
+/// The overlays of the faces of a single block
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+struct FaceOverlays {
+    /// The overlay of the top face
+    top: Option<Overlay>,
+    /// The overlay of the bottom face
+    bottom: Option<Overlay>,
+    /// The overlay of the side faces
+    side: Option<Overlay>,
+}
+
+/// BlockOverlays
+///
+/// The overlays of the faces of all blocks, which are
+/// added to the chunk meshes as a second tile
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BlockOverlays {
+    /// The overlays by the material of the block
+    overlays: HashMap<Material, FaceOverlays>,
+}
+
+impl BlockOverlays {
+    /// Returns the overlay of a block face, if there is one
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    /// * `normal` - The normal of the face
+    pub fn overlay(&self, material: Material, normal: [f32; 3]) -> Option<Overlay> {
+        let faces = self.overlays.get(&material)?;
+        if normal[1] > 0.0 {
+            faces.top
+        } else if normal[1] < 0.0 {
+            faces.bottom
+        } else {
+            faces.side
+        }
+    }
+}
+
+/// Overlays
+///
+/// The block overlays defined by scripts through the
+/// `blocks` API. Each face is optional, a block defined
+/// again replaces its previous overlays, e.g.
+///
+/// ```lua
+/// blocks.overlay {
+///     block = "dirt",
+///     -- The tiles of the texture atlas of the top,
+///     -- bottom and side faces
+///     side = { 0, 12 },
+///     -- The color the tiles are multiplied with
+///     tint = { 0.45, 0.75, 0.3 },
+///     opacity = 1.0,
+/// }
+/// ```
+pub struct Overlays {
+    /// The defined overlays, shared with the Lua API
+    defined: Arc<Mutex<BlockOverlays>>,
+    /// The overlays which have been published the last time
+    published: Arc<BlockOverlays>,
+}
+
+impl Overlays {
+    /// Creates a new overlay registry and registers its
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let defined = Arc::new(Mutex::new(BlockOverlays::default()));
+
+        let lua = engine.lua();
+        let api = scripting::api_table(lua, "blocks")?;
+        let overlays = defined.clone();
+        api.set("overlay", lua.create_function(move |_, def: Table| {
+            let (material, faces) = overlays_from_table(def)?;
+            overlays.lock().unwrap().overlays.insert(material, faces);
+            Ok(())
+        })?)?;
+
+        Ok(Self {
+            defined,
+            published: Arc::new(BlockOverlays::default()),
+        })
+    }
+
+    /// Returns the defined overlays. A new instance is only
+    /// returned if the overlays changed since the last call,
+    /// so the chunks just need to be remeshed if it differs.
+    pub fn snapshot(&mut self) -> Arc<BlockOverlays> {
+        let defined = self.defined.lock().unwrap();
+        if *defined != *self.published {
+            self.published = Arc::new(defined.clone());
+        }
+        self.published.clone()
+    }
+}
+
+/// Reads the overlays of a block from a Lua table
+///
+/// # Arguments
+///
+/// * `def` - The Lua table
+fn overlays_from_table(def: Table) -> mlua::Result<(Material, FaceOverlays)> {
+    let name: String = def.get("block")?;
+    let material = Material::from_name(&name).ok_or_else(|| {
+        mlua::Error::RuntimeError(format!("unknown block {}", name))
+    })?;
+
+    let tint = match def.get::<_, Option<Table>>("tint")? {
+        Some(tint) => scripting::vector_from_table(tint)?,
+        None => Vector3::new(1.0, 1.0, 1.0),
+    };
+    let opacity = def.get::<_, Option<f32>>("opacity")?.unwrap_or(1.0).clamp(0.0, 1.0);
+
+    let overlay = |face: &str| -> mlua::Result<Option<Overlay>> {
+        match def.get::<_, Option<Table>>(face)? {
+            Some(tile) => Ok(Some(Overlay {
+                tile: Vector2::new(tile.get(1)?, tile.get(2)?),
+                tint: tint.extend(opacity),
+            })),
+            None => Ok(None),
+        }
+    };
+
+    Ok((material, FaceOverlays {
+        top: overlay("top")?,
+        bottom: overlay("bottom")?,
+        side: overlay("side")?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_define_overlays() {
+        let engine = ScriptEngine::new();
+        let mut overlays = Overlays::new(&engine).unwrap();
+        let empty = overlays.snapshot();
+
+        engine.exec("test", r#"
+            blocks.overlay { block = "dirt", side = { 0, 12 }, tint = { 0.5, 0.75, 0.25 }, opacity = 2.0 }
+            blocks.overlay { block = "stone", top = { 2, 12 } }
+        "#).unwrap();
+        let defined = overlays.snapshot();
+        assert!(!Arc::ptr_eq(&empty, &defined));
+        assert!(Arc::ptr_eq(&defined, &overlays.snapshot()));
+
+        assert_eq!(defined.overlay(Material::Dirt, [1.0, 0.0, 0.0]), Some(Overlay {
+            tile: Vector2::new(0.0, 12.0),
+            tint: Vector4::new(0.5, 0.75, 0.25, 1.0),
+        }));
+        assert_eq!(defined.overlay(Material::Dirt, [0.0, 1.0, 0.0]), None);
+        assert_eq!(defined.overlay(Material::Stone, [0.0, 1.0, 0.0]).unwrap().tint, Vector4::new(1.0, 1.0, 1.0, 1.0));
+        assert_eq!(defined.overlay(Material::Stone, [0.0, -1.0, 0.0]), None);
+        assert_eq!(defined.overlay(Material::Grass, [0.0, 1.0, 0.0]), None);
+    }
+
+    #[test]
+    fn invalid_overlays_are_rejected() {
+        let engine = ScriptEngine::new();
+        let mut overlays = Overlays::new(&engine).unwrap();
+        assert!(engine.exec("test", r#"blocks.overlay { block = "cheese", side = { 0, 12 } }"#).is_err());
+        assert!(engine.exec("test", r#"blocks.overlay { block = "dirt", side = { 0 } }"#).is_err());
+        assert!(engine.exec("test", r#"blocks.overlay { side = { 0, 12 } }"#).is_err());
+        assert_eq!(*overlays.snapshot(), BlockOverlays::default());
+    }
+}
@@ -0,0 +1,281 @@
+//! Types to show short-lived particles floating in the
+//! world, e.g. the damage numbers of hurt entities or
+//! the flames of fires
+
+use crate::ecs::{Ecs, EntityId};
+use crate::ecs::components::{Gravity, Lifetime, Position, Velocity};
+use crate::ecs::systems;
+use crate::graphics::backend::GlBackend;
+use crate::graphics::font::{self, GLYPH_SIZE};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::notification::{OverlayBuilder, OverlayPainter, OVERLAY_HEIGHT};
+use crate::resources::Resources;
+use cgmath::{InnerSpace, Vector3, Vector4};
+
+/// The time in seconds damage numbers are shown for
+const DAMAGE_NUMBER_LIFETIME: f32 = 0.8;
+/// The speed in blocks per second damage numbers rise with
+const DAMAGE_NUMBER_SPEED: f32 = 1.2;
+/// The color of damage numbers
+const DAMAGE_NUMBER_COLOR: Vector4<f32> = Vector4 { x: 1.0, y: 0.3, z: 0.25, w: 1.0 };
+/// The time in seconds flames are shown for
+const FLAME_LIFETIME: f32 = 0.6;
+/// The speed in blocks per second flames rise with
+const FLAME_SPEED: f32 = 0.8;
+/// The color of flames
+const FLAME_COLOR: Vector4<f32> = Vector4 { x: 1.0, y: 0.6, z: 0.1, w: 1.0 };
+/// The time in seconds the debris of explosions is shown for
+const DEBRIS_LIFETIME: f32 = 0.7;
+/// The speed in blocks per second the debris of explosions
+/// flies apart with
+const DEBRIS_SPEED: f32 = 4.0;
+/// The color of the debris of explosions
+const DEBRIS_COLOR: Vector4<f32> = Vector4 { x: 0.55, y: 0.55, z: 0.55, w: 1.0 };
+/// The gravity pulling the debris of explosions down
+const DEBRIS_GRAVITY: Gravity = Gravity { acceleration: 12.0, terminal_velocity: 8.0 };
+/// The distance in blocks beyond which particles aren't shown
+const PARTICLE_RANGE: f32 = 32.0;
+
+/// Particle
+///
+/// A text floating in the world, which always faces the
+/// screen and fades out towards the end of its lifetime
+#[derive(Clone, Debug, PartialEq)]
+pub struct Particle {
+    /// The text of the particle
+    pub text: String,
+    /// The position of the particle in world coordinates
+    pub pos: Vector3<f32>,
+    /// The velocity in blocks per second
+    pub velocity: Vector3<f32>,
+    /// The color of the text
+    pub color: Vector4<f32>,
+    /// The time in seconds since the particle has been spawned
+    pub age: f32,
+    /// The time in seconds the particle is shown for
+    pub lifetime: f32,
+}
+
+impl Particle {
+    /// Returns the opacity of the particle, which fades
+    /// out during the second half of its lifetime
+    pub fn opacity(&self) -> f32 {
+        (2.0 * (1.0 - self.age / self.lifetime)).clamp(0.0, 1.0)
+    }
+}
+
+/// Label
+///
+/// The component of the text a particle shows
+#[derive(Clone, Debug, PartialEq)]
+struct Label {
+    /// The text of the particle
+    text: String,
+    /// The color of the text
+    color: Vector4<f32>,
+}
+
+/// Particles
+///
+/// The particles in the dimension the player is in. Each
+/// particle is an entity with a label, moved and aged by
+/// the shared systems.
+#[derive(Default)]
+pub struct Particles {
+    /// The particles
+    ecs: Ecs,
+}
+
+impl Particles {
+    /// Returns the particles in the order they've been spawned in,
+    /// unless the slots of despawned ones have been reused
+    pub fn particles(&self) -> Vec<Particle> {
+        self.ecs.iter::<Label>()
+            .filter_map(|(id, label)| {
+                let lifetime = self.ecs.get::<Lifetime>(id)?;
+                Some(Particle {
+                    text: label.text.clone(),
+                    pos: self.ecs.get::<Position>(id)?.0,
+                    velocity: self.ecs.get::<Velocity>(id)?.0,
+                    color: label.color,
+                    age: lifetime.age,
+                    lifetime: lifetime.lifetime,
+                })
+            })
+            .collect()
+    }
+
+    /// Spawns the number of damage an entity took, which
+    /// rises above the entity
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the top of the entity
+    /// * `damage` - The damage the entity took
+    pub fn spawn_damage_number(&mut self, pos: Vector3<f32>, damage: f32) {
+        let text = if damage.fract() == 0.0 { format!("-{}", damage) } else { format!("-{:.1}", damage) };
+        self.spawn(text, DAMAGE_NUMBER_COLOR, pos, Vector3::new(0.0, DAMAGE_NUMBER_SPEED, 0.0), DAMAGE_NUMBER_LIFETIME);
+    }
+
+    /// Spawns a flame rising from a fire
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the flame
+    pub fn spawn_flame(&mut self, pos: Vector3<f32>) {
+        self.spawn("^".to_string(), FLAME_COLOR, pos, Vector3::new(0.0, FLAME_SPEED, 0.0), FLAME_LIFETIME);
+    }
+
+    /// Spawns the debris of an explosion, which flies apart
+    /// in all directions from its center and falls down
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - The center of the explosion
+    pub fn spawn_explosion(&mut self, center: Vector3<f32>) {
+        for x in [-1.0, 1.0].iter() {
+            for y in [-1.0, 1.0].iter() {
+                for z in [-1.0, 1.0].iter() {
+                    let dir = Vector3::new(*x, *y, *z).normalize();
+                    let debris = self.spawn("*".to_string(), DEBRIS_COLOR, center, dir * DEBRIS_SPEED, DEBRIS_LIFETIME);
+                    self.ecs.insert(debris, DEBRIS_GRAVITY);
+                }
+            }
+        }
+    }
+
+    /// Moves the particles and removes the ones
+    /// which reached the end of their lifetime
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    pub fn update(&mut self, seconds: f32) {
+        // Particles have no body, so there are no blocks to collide with
+        systems::physics(&mut self.ecs, seconds, |_| None);
+        systems::age(&mut self.ecs, seconds);
+    }
+
+    /// Removes all particles, e.g. once the player
+    /// switches the dimension
+    pub fn clear(&mut self) {
+        self.ecs.clear();
+    }
+
+    /// Spawns a particle and returns its entity
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text of the particle
+    /// * `color` - The color of the text
+    /// * `pos` - The position of the particle
+    /// * `velocity` - The velocity in blocks per second
+    /// * `lifetime` - The time in seconds the particle is shown for
+    fn spawn(&mut self, text: String, color: Vector4<f32>, pos: Vector3<f32>, velocity: Vector3<f32>, lifetime: f32) -> EntityId {
+        let particle = self.ecs.spawn();
+        self.ecs.insert(particle, Label { text, color });
+        self.ecs.insert(particle, Position(pos));
+        self.ecs.insert(particle, Velocity(velocity));
+        self.ecs.insert(particle, Lifetime::new(lifetime));
+        particle
+    }
+}
+
+/// ParticleFrame
+///
+/// A frame particles could be rendered for
+pub trait ParticleFrame: Frame {
+    /// Returns the particles in the dimension the player is in
+    fn particles(&self) -> &[Particle];
+}
+
+/// ParticleRenderer
+///
+/// The `ParticleRenderer` is the render pass drawing the
+/// particles as text facing the screen
+pub struct ParticleRenderer {
+    /// The painter drawing the particles
+    painter: OverlayPainter,
+}
+
+impl ParticleRenderer {
+    /// Creates a new particle renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        Self {
+            painter: OverlayPainter::new(backend, resources),
+        }
+    }
+}
+
+impl<F: ParticleFrame> RenderPass<F> for ParticleRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Overlay
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let particles = ctx.frame.particles();
+        if particles.is_empty() {
+            return;
+        }
+
+        let camera = ctx.frame.camera();
+        let view_proj = camera.proj_matrix() * camera.view_matrix();
+        let width = OVERLAY_HEIGHT * camera.aspect_ratio();
+        let mut overlay = OverlayBuilder::default();
+        for particle in particles {
+            // Particles behind the camera or far away aren't shown
+            let clip = view_proj * particle.pos.extend(1.0);
+            if clip.w <= 0.0 || (particle.pos - camera.pos()).magnitude() > PARTICLE_RANGE {
+                continue;
+            }
+            let x = (clip.x / clip.w + 1.0) / 2.0 * width;
+            let y = (1.0 - clip.y / clip.w) / 2.0 * OVERLAY_HEIGHT;
+
+            let text_width = font::text_width(&particle.text) as f32;
+            let color = Vector4::new(particle.color.x, particle.color.y, particle.color.z, particle.color.w * particle.opacity());
+            overlay.text([x - text_width / 2.0, y - GLYPH_SIZE as f32], &particle.text, color);
+        }
+        self.painter.draw(ctx, &overlay, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn damage_numbers_rise_and_fade() {
+        let mut particles = Particles::default();
+        particles.spawn_damage_number(Vector3::new(0.0, 10.0, 0.0), 1.0);
+        particles.spawn_damage_number(Vector3::new(0.0, 10.0, 0.0), 2.5);
+        assert_eq!(particles.particles()[0].text, "-1");
+        assert_eq!(particles.particles()[1].text, "-2.5");
+
+        particles.update(0.5);
+        let particle = &particles.particles()[0];
+        assert!(particle.pos.y > 10.0);
+        assert!(particle.opacity() < 1.0);
+
+        particles.update(0.5);
+        assert!(particles.particles().is_empty());
+    }
+
+    #[test]
+    fn debris_falls_apart() {
+        let mut particles = Particles::default();
+        particles.spawn_explosion(Vector3::new(0.0, 10.0, 0.0));
+        assert_eq!(particles.particles().len(), 8);
+
+        particles.update(0.5);
+        for particle in particles.particles() {
+            assert!(particle.velocity.y < 0.0);
+        }
+        particles.update(0.5);
+        assert!(particles.particles().is_empty());
+    }
+}
@@ -0,0 +1,267 @@
+//! Types to preview the placement of blocks
+
+use crate::camera::Camera;
+use crate::entity::Aabb;
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::mesh::{Mesh, Model};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::shader::ShaderProgram;
+use crate::graphics::texture::TextureAtlas;
+use crate::resources::Resources;
+use crate::world::World;
+use crate::world::block::Material;
+use crate::world::chunk::TileVertex;
+use crate::world::raycast;
+use crate::world::texture_pack::TexturePack;
+use cgmath::{Matrix4, Vector2, Vector3};
+
+/// The maximal distance a player can place blocks at
+pub const REACH_DISTANCE: f32 = 5.0;
+/// The sprite of the cracks drawn over a block which is being broken
+const CRACKS_SPRITE: Vector2<f32> = Vector2::new(2.0, 12.0);
+
+/// PlacementPreview
+///
+/// The block which would be placed at the face
+/// the player is currently looking at.
+#[derive(Copy, Clone, Debug)]
+pub struct PlacementPreview {
+    /// The position of the block the player is looking at
+    pub target: Vector3<i32>,
+    /// The position of the block in world coordinates
+    pub pos: Vector3<i32>,
+    /// The material of the block
+    pub material: Material,
+    /// Whether the block could be placed, which isn't
+    /// the case if it's blocked by an entity
+    pub placeable: bool,
+    /// How far the targeted block has been broken in
+    /// the range `[0, 1]`
+    pub progress: f32,
+}
+
+impl PlacementPreview {
+    /// Returns the preview of the block which would be placed
+    /// at the face the camera is looking at, or `None` if there
+    /// is no block within reach
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world the block would be placed in
+    /// * `camera` - The camera of the player
+    /// * `body` - The bounding box of the player's body
+    /// * `material` - The material of the block
+    pub fn new(world: &World, camera: &Camera, body: &Aabb, material: Material) -> Option<Self> {
+        let hit = raycast::raycast(world, *camera.pos(), camera.look(), REACH_DISTANCE)?;
+        let pos = hit.adjacent();
+
+        // At the moment, the player is the only entity
+        // which could block a placement
+        let free = world.block(pos) == Some(Material::Air);
+        let blocked = body.intersects(&Aabb::block(pos));
+
+        Some(Self {
+            target: hit.block,
+            pos,
+            material,
+            placeable: free && !blocked,
+            progress: 0.0,
+        })
+    }
+}
+
+/// PlacementFrame
+///
+/// A frame the placement ghost could be rendered for
+pub trait PlacementFrame: Frame {
+    /// Returns the preview of the block which would be placed
+    fn placement(&self) -> Option<&PlacementPreview>;
+}
+
+/// PlacementGhostRenderer
+///
+/// The `PlacementGhostRenderer` is the render pass
+/// drawing a translucent ghost of the block which would
+/// be placed. The ghost is tinted green if the block is
+/// placeable and red otherwise. Additionally, the block
+/// the player is looking at is outlined, and covered with
+/// cracks while it's being broken.
+pub struct PlacementGhostRenderer {
+    /// The render backend
+    backend: GlBackend,
+    /// A texture atlas
+    tex_atlas: TextureAtlas,
+    /// The generation of the texture pack the atlas belongs to
+    textures: u64,
+    /// A shader program
+    shader_program: ShaderProgram,
+    /// The ghost model and the material it's textured with
+    model: Option<(Material, Model)>,
+    /// The shader program the outline is drawn with
+    outline_program: ShaderProgram,
+    /// The outline of a single block
+    outline: Model,
+    /// The cube the cracks are drawn on
+    cracks: Model,
+}
+
+impl PlacementGhostRenderer {
+    /// Creates a new placement ghost renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    /// * `textures` - The texture pack of the blocks
+    pub fn new(backend: &GlBackend, resources: &Resources, textures: &TexturePack) -> Self {
+        let shader_program = backend.create_pipeline(resources, "ghost").unwrap();
+        shader_program.disable();
+
+        let outline_program = backend.create_pipeline(resources, "prop").unwrap();
+        outline_program.disable();
+
+        Self {
+            backend: backend.clone(),
+            tex_atlas: textures.atlas(backend),
+            textures: textures.generation(),
+            shader_program,
+            model: None,
+            outline_program,
+            outline: Model::from_mesh(backend, &outline_mesh()),
+            cracks: Self::create_model(backend, [CRACKS_SPRITE; 6]),
+        }
+    }
+
+    /// Creates a cube model which is textured with the sprites
+    /// of the given material
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    fn create_block_model(&self, material: Material) -> Model {
+        let tex_coords = material.data().tex_coords();
+        Self::create_model(&self.backend, [
+            tex_coords.side(),
+            tex_coords.side(),
+            tex_coords.side(),
+            tex_coords.side(),
+            tex_coords.top(),
+            tex_coords.bottom(),
+        ])
+    }
+
+    /// Creates a cube model which is textured with the given sprites.
+    /// The faces of `Mesh::cube` are ordered front, back, left, right,
+    /// top and bottom.
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `sprites` - The sprites of the faces
+    fn create_model(backend: &GlBackend, sprites: [Vector2<f32>; 6]) -> Model {
+        let mut model = Model::from_mesh(backend, &Mesh::cube());
+        let tile_coords: Vec<TileVertex> = sprites.iter()
+            .flat_map(|sprite| vec![TileVertex { tile_coord: [sprite.x, sprite.y] }; 4])
+            .collect();
+        model.add_buffer(backend.create_vertex_buffer(&tile_coords));
+
+        model
+    }
+}
+
+impl<F: PlacementFrame> RenderPass<F> for PlacementGhostRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Transparent
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        if self.textures != ctx.textures.generation() {
+            self.tex_atlas = ctx.textures.atlas(&self.backend);
+            self.textures = ctx.textures.generation();
+        }
+
+        let preview = match ctx.frame.placement() {
+            Some(preview) => preview,
+            None => return,
+        };
+
+        if self.model.as_ref().map_or(true, |(material, _)| *material != preview.material) {
+            self.model = Some((preview.material, self.create_block_model(preview.material)));
+        }
+        let (_, model) = self.model.as_ref().unwrap();
+
+        // Inflate the ghost a little bit to avoid z-fighting
+        // with adjacent blocks
+        let pos = Vector3::new(preview.pos.x as f32, preview.pos.y as f32, preview.pos.z as f32);
+        let transform = Matrix4::from_translation(pos - Vector3::new(0.005, 0.005, 0.005))
+            * Matrix4::from_scale(1.01);
+
+        let camera = ctx.frame.camera();
+        let view_proj = camera.proj_matrix() * camera.view_matrix();
+        let mvp = view_proj * transform;
+
+        self.shader_program.enable();
+        self.shader_program.set_uniform_1i("u_Texture", 0);
+        self.shader_program.set_uniform_mat4f("u_MVP", &mvp);
+        if preview.placeable {
+            self.shader_program.set_uniform_4f("u_Tint", 0.2, 1.0, 0.2, 0.5);
+        } else {
+            self.shader_program.set_uniform_4f("u_Tint", 1.0, 0.2, 0.2, 0.5);
+        }
+        self.tex_atlas.bind(None);
+        model.bind();
+
+        // The ghost is translucent, so it must not occlude
+        // anything rendered after it
+        ctx.renderer.set_depth_write(false);
+        ctx.renderer.draw_with(model.va(), model.ib(), &self.shader_program, model.topology());
+        ctx.renderer.set_depth_write(true);
+
+        model.unbind();
+
+        // Cover the targeted block with cracks while it's being broken,
+        // which get more visible the further it's broken
+        let target = Vector3::new(preview.target.x as f32, preview.target.y as f32, preview.target.z as f32);
+        if preview.progress > 0.0 {
+            let transform = Matrix4::from_translation(target - Vector3::new(0.001, 0.001, 0.001))
+                * Matrix4::from_scale(1.002);
+            self.shader_program.set_uniform_mat4f("u_MVP", &(view_proj * transform));
+            self.shader_program.set_uniform_4f("u_Tint", 0.1, 0.1, 0.1, 0.3 + 0.6 * preview.progress);
+            self.cracks.bind();
+            ctx.renderer.set_depth_write(false);
+            ctx.renderer.draw_with(self.cracks.va(), self.cracks.ib(), &self.shader_program, self.cracks.topology());
+            ctx.renderer.set_depth_write(true);
+            self.cracks.unbind();
+        }
+
+        self.tex_atlas.unbind();
+        self.shader_program.disable();
+
+        // Outline the targeted block, slightly inflated as well
+        let transform = Matrix4::from_translation(target - Vector3::new(0.002, 0.002, 0.002))
+            * Matrix4::from_scale(1.004);
+
+        self.outline_program.enable();
+        self.outline_program.set_uniform_mat4f("u_MVP", &(view_proj * transform));
+        self.outline_program.set_uniform_mat4f("u_Model", &transform);
+        self.outline_program.set_uniform_4f("u_Color", 0.0, 0.0, 0.0, 0.6);
+
+        ctx.renderer.draw_with(self.outline.va(), self.outline.ib(), &self.outline_program, self.outline.topology());
+
+        self.outline.unbind();
+        self.outline_program.disable();
+    }
+}
+
+/// Returns the twelve edges of a unit cube as line mesh
+pub(crate) fn outline_mesh() -> Mesh {
+    let corner = |i: u32| Vector3::new((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32);
+
+    // Connect each corner with the corners differing in exactly one axis
+    let edges: Vec<(Vector3<f32>, Vector3<f32>)> = (0..8)
+        .flat_map(|i| [1, 2, 4].iter().filter(move |&&axis| i & axis == 0).map(move |axis| (i, i | axis)))
+        .map(|(a, b)| (corner(a), corner(b)))
+        .collect();
+
+    Mesh::lines(&edges)
+}
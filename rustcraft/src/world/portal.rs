@@ -0,0 +1,467 @@
+//! Types to teleport the player between linked
+//! locations of the universe through portal blocks
+
+use crate::entity::Aabb;
+use crate::graphics::mesh::{Mesh, MeshBuilder, MeshHandle};
+use crate::graphics::scene::{NodeId, SceneNode};
+use crate::scripting::{self, ScriptEngine, ScriptError};
+use crate::world::universe::{Universe, OVERWORLD};
+use cgmath::{Matrix4, Vector3, Vector4};
+use mlua::{Function, RegistryKey, Table, Value};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// The time of the whole transition of a teleport in seconds.
+/// The player is moved at the half of it, when the screen is
+/// faded out completely.
+pub const TRANSITION_DURATION: f32 = 0.8;
+/// The radius of chunks which are loaded around the
+/// destination before the player arrives
+pub const PRELOAD_RADIUS: i32 = 1;
+
+/// Destination
+///
+/// The location a portal teleports the player to
+#[derive(Clone, Debug, PartialEq)]
+pub struct Destination {
+    /// The dimension the player is moved to, or `None`
+    /// to stay in the dimension of the portal
+    pub dimension: Option<String>,
+    /// The position the player's feet are moved to
+    pub pos: Vector3<f32>,
+}
+
+impl Destination {
+    /// Creates a destination within the dimension of the portal
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position the player's feet are moved to
+    pub fn new(pos: Vector3<f32>) -> Self {
+        Self {
+            dimension: None,
+            pos,
+        }
+    }
+
+    /// Creates a destination in another dimension
+    ///
+    /// # Arguments
+    ///
+    /// * `dimension` - The name of the dimension
+    /// * `pos` - The position the player's feet are moved to
+    pub fn in_dimension(dimension: &str, pos: Vector3<f32>) -> Self {
+        Self {
+            dimension: Some(dimension.to_string()),
+            pos,
+        }
+    }
+}
+
+/// Portal
+///
+/// A block which teleports the player to its destination
+/// when they stand in it
+pub struct Portal {
+    /// The dimension the portal is in
+    dimension: String,
+    /// The position of the portal block in world coordinates
+    pos: Vector3<i32>,
+    /// The location the player is teleported to
+    destination: Destination,
+    /// A Lua function which is called before the player is
+    /// teleported, stored in the Lua registry
+    on_enter: Option<RegistryKey>,
+    /// The scene node showing the portal
+    node: Option<NodeId>,
+}
+
+impl Portal {
+    /// Returns the dimension the portal is in
+    pub fn dimension(&self) -> &str {
+        &self.dimension
+    }
+
+    /// Returns the position of the portal block
+    pub fn pos(&self) -> Vector3<i32> {
+        self.pos
+    }
+
+    /// Returns the location the player is teleported to
+    pub fn destination(&self) -> &Destination {
+        &self.destination
+    }
+}
+
+/// The portals of a world and the scene nodes of
+/// removed portals which still need to be removed
+#[derive(Default)]
+struct PortalRegistry {
+    /// The portals by their id
+    portals: BTreeMap<u32, Portal>,
+    /// The id of the next portal
+    next_id: u32,
+    /// The scene nodes of removed portals and their dimensions
+    removed_nodes: Vec<(String, NodeId)>,
+}
+
+impl PortalRegistry {
+    /// Adds a portal and returns its id
+    fn add(&mut self, dimension: &str, pos: Vector3<i32>, destination: Destination, on_enter: Option<RegistryKey>) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.portals.insert(id, Portal {
+            dimension: dimension.to_string(),
+            pos,
+            destination,
+            on_enter,
+            node: None,
+        });
+        id
+    }
+
+    /// Removes a portal, returns whether it existed
+    fn remove(&mut self, id: u32) -> bool {
+        match self.portals.remove(&id) {
+            Some(portal) => {
+                self.removed_nodes.extend(portal.node.map(|node| (portal.dimension, node)));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Portals
+///
+/// The registry of all portals of the universe. Portals are
+/// shown as props of the scene of their dimension. Scripts
+/// manage portals through the `portals` API, e.g.
+///
+/// ```lua
+/// local id = portals.add {
+///     pos = { 4, 10, 4 },
+///     destination = { 100, 20, 100 },
+///     -- Optional, defaults to the overworld
+///     dimension = "overworld",
+///     -- Optional, defaults to the dimension of the portal
+///     destination_dimension = "caves",
+///     -- Optional, returning false cancels the teleport
+///     on_enter = function(id) return true end,
+/// }
+/// portals.remove(id)
+/// ```
+pub struct Portals {
+    /// The registered portals, shared with the Lua API
+    registry: Arc<Mutex<PortalRegistry>>,
+    /// The mesh all portals are shown with
+    mesh: MeshHandle,
+}
+
+impl Portals {
+    /// Creates a new portal registry and registers its
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let registry = Arc::new(Mutex::new(PortalRegistry::default()));
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let portals = registry.clone();
+        api.set("add", lua.create_function(move |lua, def: Table| {
+            let pos = scripting::vector_from_table(def.get("pos")?)?;
+            let pos = Vector3::new(pos.x.floor() as i32, pos.y.floor() as i32, pos.z.floor() as i32);
+            let dimension = def.get::<_, Option<String>>("dimension")?;
+            let destination = Destination {
+                dimension: def.get("destination_dimension")?,
+                pos: scripting::vector_from_table(def.get("destination")?)?,
+            };
+            let on_enter = match def.get::<_, Option<Function>>("on_enter")? {
+                Some(function) => Some(lua.create_registry_value(function)?),
+                None => None,
+            };
+            let dimension = dimension.as_deref().unwrap_or(OVERWORLD);
+            Ok(portals.lock().unwrap().add(dimension, pos, destination, on_enter))
+        })?)?;
+        let portals = registry.clone();
+        api.set("remove", lua.create_function(move |_, id: u32| {
+            Ok(portals.lock().unwrap().remove(id))
+        })?)?;
+        lua.globals().set("portals", api)?;
+
+        Ok(Self {
+            registry,
+            mesh: MeshHandle::new(portal_mesh()),
+        })
+    }
+
+    /// Adds a portal and returns its id
+    ///
+    /// # Arguments
+    ///
+    /// * `dimension` - The dimension the portal is in
+    /// * `pos` - The position of the portal block
+    /// * `destination` - The location the player is teleported to
+    pub fn add(&mut self, dimension: &str, pos: Vector3<i32>, destination: Destination) -> u32 {
+        self.registry.lock().unwrap().add(dimension, pos, destination, None)
+    }
+
+    /// Removes a portal. Returns whether the portal existed.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the portal
+    pub fn remove(&mut self, id: u32) -> bool {
+        self.registry.lock().unwrap().remove(id)
+    }
+
+    /// Returns the id and the destination of a portal the given
+    /// bounding box is standing in
+    ///
+    /// # Arguments
+    ///
+    /// * `dimension` - The dimension the bounding box is in
+    /// * `aabb` - The bounding box, e.g. of the player
+    pub fn portal_at(&self, dimension: &str, aabb: &Aabb) -> Option<(u32, Destination)> {
+        self.registry.lock().unwrap().portals.iter()
+            .filter(|(_, portal)| portal.dimension == dimension)
+            .find(|(_, portal)| Aabb::block(portal.pos).intersects(aabb))
+            .map(|(&id, portal)| (id, portal.destination.clone()))
+    }
+
+    /// Calls the `on_enter` callback of a portal. Returns whether
+    /// the player should be teleported, which is the case if the
+    /// portal has no callback or the callback doesn't return `false`.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine the portal has been added in
+    /// * `id` - The id of the portal
+    pub fn enter(&self, engine: &ScriptEngine, id: u32) -> Result<bool, ScriptError> {
+        let lua = engine.lua();
+
+        // The callback is taken out of the registry first, so
+        // that it could add or remove portals itself
+        let callback = {
+            let registry = self.registry.lock().unwrap();
+            match registry.portals.get(&id).and_then(|portal| portal.on_enter.as_ref()) {
+                Some(key) => lua.registry_value::<Function>(key),
+                None => return Ok(true),
+            }
+        };
+
+        callback
+            .and_then(|callback| callback.call::<_, Value>(id))
+            .map(|result| !matches!(result, Value::Boolean(false)))
+            .map_err(|error| ScriptError::new(&format!("portal {}", id), error))
+    }
+
+    /// Adds the props of new portals to the scenes of their
+    /// dimensions and removes the ones of removed portals.
+    /// Portals in unknown dimensions aren't shown.
+    ///
+    /// # Arguments
+    ///
+    /// * `universe` - The universe containing the dimensions
+    pub fn sync_scene(&mut self, universe: &mut Universe) {
+        let mut registry = self.registry.lock().unwrap();
+        for (dimension, node) in registry.removed_nodes.drain(..) {
+            if let Some(world) = universe.dimension_mut(&dimension) {
+                world.scene_mut().remove(node);
+            }
+        }
+
+        for portal in registry.portals.values_mut().filter(|portal| portal.node.is_none()) {
+            let scene = match universe.dimension_mut(&portal.dimension) {
+                Some(world) => world.scene_mut(),
+                None => continue,
+            };
+            let pos = Vector3::new(portal.pos.x as f32, portal.pos.y as f32, portal.pos.z as f32);
+            portal.node = Some(scene.add(None, SceneNode::with_mesh(
+                "portal",
+                Matrix4::from_translation(pos),
+                self.mesh.clone(),
+                Vector4::new(0.55, 0.25, 0.95, 1.0),
+            )));
+        }
+    }
+}
+
+/// Returns the mesh of a portal, a thin slab in the middle
+/// of the block which is visible from both sides
+fn portal_mesh() -> Mesh {
+    let mut builder = MeshBuilder::new();
+    builder.add(&Mesh::cube(), &(Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.4))
+        * Matrix4::from_nonuniform_scale(1.0, 1.0, 0.2)));
+    builder.build()
+}
+
+/// Teleport
+///
+/// A running teleport of the player. The screen fades out,
+/// the player is moved and the screen fades in again.
+#[derive(Clone, Debug)]
+pub struct Teleport {
+    /// The location the player is moved to
+    destination: Destination,
+    /// The time since the teleport started in seconds
+    elapsed: f32,
+    /// Whether the player has been moved already
+    arrived: bool,
+}
+
+impl Teleport {
+    /// Starts a new teleport
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - The location the player is moved to
+    pub fn new(destination: Destination) -> Self {
+        Self {
+            destination,
+            elapsed: 0.0,
+            arrived: false,
+        }
+    }
+
+    /// Advances the teleport. Returns the destination once,
+    /// when the player should be moved.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The time passed since the last update
+    pub fn advance(&mut self, seconds: f32) -> Option<&Destination> {
+        self.elapsed += seconds;
+        if !self.arrived && self.elapsed >= TRANSITION_DURATION / 2.0 {
+            self.arrived = true;
+            return Some(&self.destination);
+        }
+        None
+    }
+
+    /// Returns whether the teleport is over
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= TRANSITION_DURATION
+    }
+
+    /// Returns how far the screen is faded out, from `0.0`
+    /// to `1.0` at the moment the player is moved
+    pub fn fade(&self) -> f32 {
+        let half = TRANSITION_DURATION / 2.0;
+        (1.0 - (self.elapsed - half).abs() / half).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::World;
+    use crate::world::universe::CAVES;
+
+    fn portals() -> (ScriptEngine, Portals) {
+        let engine = ScriptEngine::new();
+        let portals = Portals::new(&engine).unwrap();
+        (engine, portals)
+    }
+
+    fn player_at(x: f32, y: f32, z: f32) -> Aabb {
+        Aabb::player(Vector3::new(x, y + crate::entity::PLAYER_EYE_HEIGHT, z))
+    }
+
+    #[test]
+    fn player_standing_in_portal_is_detected() {
+        let (_engine, mut portals) = portals();
+        let destination = Destination::in_dimension(CAVES, Vector3::new(50.0, 10.0, 50.0));
+        let id = portals.add(OVERWORLD, Vector3::new(2, 5, 2), destination.clone());
+
+        assert_eq!(portals.portal_at(OVERWORLD, &player_at(2.5, 5.0, 2.5)), Some((id, destination)));
+        assert_eq!(portals.portal_at(CAVES, &player_at(2.5, 5.0, 2.5)), None);
+        assert_eq!(portals.portal_at(OVERWORLD, &player_at(4.5, 5.0, 2.5)), None);
+        assert_eq!(portals.portal_at(OVERWORLD, &player_at(2.5, 6.0, 2.5)), None);
+
+        assert!(portals.remove(id));
+        assert!(!portals.remove(id));
+        assert_eq!(portals.portal_at(OVERWORLD, &player_at(2.5, 5.0, 2.5)), None);
+    }
+
+    #[test]
+    fn scripts_add_and_remove_portals() {
+        let (engine, portals) = portals();
+        engine.exec("test", r#"
+            first = portals.add { pos = { 0, 0, 0 }, destination = { 10, 0, 0 } }
+            second = portals.add { pos = { 1.5, 0, 0 }, destination = { 20, 0, 0 } }
+            third = portals.add { pos = { 0, 0, 0 }, destination = { 30, 0, 0 }, dimension = "caves", destination_dimension = "overworld" }
+            assert(portals.remove(first))
+        "#).unwrap();
+
+        assert_eq!(portals.portal_at(OVERWORLD, &player_at(0.5, 0.0, 0.5)), None);
+        assert_eq!(portals.portal_at(OVERWORLD, &player_at(1.5, 0.0, 0.5)).map(|(_, destination)| destination),
+            Some(Destination::new(Vector3::new(20.0, 0.0, 0.0))));
+        assert_eq!(portals.portal_at(CAVES, &player_at(0.5, 0.0, 0.5)).map(|(_, destination)| destination),
+            Some(Destination::in_dimension(OVERWORLD, Vector3::new(30.0, 0.0, 0.0))));
+    }
+
+    #[test]
+    fn callbacks_could_cancel_teleports() {
+        let (engine, portals) = portals();
+        engine.exec("test", r#"
+            entered = {}
+            open = portals.add { pos = { 0, 0, 0 }, destination = { 0, 0, 0 }, on_enter = function(id) entered[#entered + 1] = id end }
+            closed = portals.add { pos = { 1, 0, 0 }, destination = { 0, 0, 0 }, on_enter = function() return false end }
+            failing = portals.add { pos = { 2, 0, 0 }, destination = { 0, 0, 0 }, on_enter = function() error("oops") end }
+            plain = portals.add { pos = { 3, 0, 0 }, destination = { 0, 0, 0 } }
+        "#).unwrap();
+        let id = |name: &str| engine.lua().globals().get::<_, u32>(name).unwrap();
+
+        assert!(portals.enter(&engine, id("open")).unwrap());
+        assert!(!portals.enter(&engine, id("closed")).unwrap());
+        assert!(portals.enter(&engine, id("failing")).is_err());
+        assert!(portals.enter(&engine, id("plain")).unwrap());
+
+        let entered: Vec<u32> = engine.lua().globals().get("entered").unwrap();
+        assert_eq!(entered, vec![id("open")]);
+    }
+
+    #[test]
+    fn scene_props_follow_portals() {
+        let (_engine, mut portals) = portals();
+        let mut universe = Universe::new(OVERWORLD, World::new());
+        universe.add(CAVES, World::new());
+        let destination = Destination::new(Vector3::new(0.0, 0.0, 0.0));
+
+        let first = portals.add(OVERWORLD, Vector3::new(0, 0, 0), destination.clone());
+        portals.add(OVERWORLD, Vector3::new(5, 0, 0), destination.clone());
+        portals.add(CAVES, Vector3::new(5, 0, 0), destination.clone());
+        portals.add("nether", Vector3::new(5, 0, 0), destination);
+        portals.sync_scene(&mut universe);
+        portals.sync_scene(&mut universe);
+        assert_eq!(universe.dimension(OVERWORLD).unwrap().scene().instances().len(), 2);
+        assert_eq!(universe.dimension(CAVES).unwrap().scene().instances().len(), 1);
+
+        portals.remove(first);
+        portals.sync_scene(&mut universe);
+        assert_eq!(universe.dimension(OVERWORLD).unwrap().scene().instances().len(), 1);
+    }
+
+    #[test]
+    fn teleport_moves_player_when_faded_out() {
+        let destination = Destination::new(Vector3::new(1.0, 2.0, 3.0));
+        let mut teleport = Teleport::new(destination.clone());
+        assert_eq!(teleport.fade(), 0.0);
+
+        assert_eq!(teleport.advance(TRANSITION_DURATION / 4.0), None);
+        assert!((teleport.fade() - 0.5).abs() < 1e-5);
+
+        assert_eq!(teleport.advance(TRANSITION_DURATION / 4.0), Some(&destination));
+        assert!((teleport.fade() - 1.0).abs() < 1e-5);
+
+        assert_eq!(teleport.advance(TRANSITION_DURATION / 4.0), None);
+        assert!(!teleport.is_finished());
+        teleport.advance(TRANSITION_DURATION / 4.0);
+        assert!(teleport.is_finished());
+        assert!(teleport.fade() < 1e-5);
+    }
+}
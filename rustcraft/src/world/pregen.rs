@@ -0,0 +1,599 @@
+//! Types to generate the terrain around a position ahead of
+//! time, e.g. on a server before the players explore it. The
+//! terrain is written straight to the pregenerated files of
+//! its regions, which chunks are restored from as they are
+//! loaded instead of generating them again.
+
+use crate::task::{TaskHandle, TaskPool};
+use crate::world::World;
+use crate::world::block::Material;
+use crate::world::block_entity::BlockEntity;
+use crate::world::border::WorldBorder;
+use crate::world::chunk::{self, Chunk, CHUNK_AREA, CHUNK_SIZE, CHUNK_VOLUME};
+use crate::world::save::{SaveDir, SaveError, REGION_SIZE};
+use cgmath::{Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+/// The command line flag pre-generating chunks once the game started
+pub const PREGEN_FLAG: &str = "--pregen";
+/// The largest amount of chunks pre-generated in each direction
+pub const MAX_RADIUS: i32 = 1024;
+/// The time in seconds between two reports of the progress
+pub const REPORT_INTERVAL: f32 = 5.0;
+/// The amount of regions whose pregenerated terrain is kept
+/// in memory while their chunks are loaded
+const CACHED_REGIONS: usize = 4;
+/// The bits of a run storing its length minus one
+const LENGTH_MASK: u32 = 0xffff;
+/// The largest amount of chunks queued to be pre-generated
+const PREGEN_QUEUE_CAPACITY: usize = 64;
+
+/// Reads the radius of the chunks which should be pre-generated
+/// from the command line arguments, e.g. `--pregen 64`. Returns
+/// `None` if no chunks should be pre-generated.
+///
+/// # Arguments
+///
+/// * `args` - The arguments without the name of the executable
+pub fn radius_from_args(args: &[String]) -> Result<Option<i32>, String> {
+    let start = match args.iter().position(|arg| arg == PREGEN_FLAG) {
+        Some(start) => start,
+        None => return Ok(None),
+    };
+
+    let value = args.get(start + 1).ok_or_else(|| "missing radius".to_string())?;
+    let radius: i32 = value.parse().map_err(|_| format!("invalid radius '{}'", value))?;
+    Ok(Some(radius.clamp(0, MAX_RADIUS)))
+}
+
+/// PregenChunk
+///
+/// The generated terrain of a single chunk. The blocks are
+/// stored in the order of their index as runs of blocks with
+/// the same material and state, so the layers of stone below
+/// the surface hardly take any space.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PregenChunk {
+    /// The location of the chunk in chunks
+    pub loc: [i32; 2],
+    /// The names of the materials the runs refer to
+    palette: Vec<String>,
+    /// The runs of blocks, each packing the index of its material
+    /// in the palette, its state and its length minus one from the
+    /// most to the least significant bits
+    runs: Vec<u32>,
+    /// The block entities of the chunk, e.g. the chests of structures
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    block_entities: Vec<PregenBlockEntity>,
+}
+
+/// PregenBlockEntity
+///
+/// A block entity of a pregenerated chunk
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct PregenBlockEntity {
+    /// The position of the block within the chunk
+    pos: [i16; 3],
+    /// The block entity
+    entity: BlockEntity,
+}
+
+impl PregenChunk {
+    /// Encodes the generated terrain of a chunk. Fails if the
+    /// blocks of the chunk have been evicted and can't be read
+    /// back, instead of encoding them as air.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The chunk whose terrain has been generated
+    pub fn encode(chunk: &Chunk) -> io::Result<Self> {
+        let mut palette = Vec::new();
+        let mut runs = Vec::new();
+        let mut last: Option<(u32, u8)> = None;
+        let mut length = 0;
+        for index in 0..CHUNK_VOLUME {
+            let pos = pos_of(index);
+            let material = chunk.block(pos)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "the blocks of the chunk are unavailable"))?;
+            let state = chunk.state(pos).unwrap_or(0);
+            let material = match palette.iter().position(|used| *used == material) {
+                Some(position) => position as u32,
+                None => {
+                    palette.push(material);
+                    palette.len() as u32 - 1
+                }
+            };
+
+            if last == Some((material, state)) {
+                length += 1;
+                continue;
+            }
+            if let Some((material, state)) = last {
+                runs.push(material << 24 | (state as u32) << 16 | (length - 1));
+            }
+            last = Some((material, state));
+            length = 1;
+        }
+        if let Some((material, state)) = last {
+            runs.push(material << 24 | (state as u32) << 16 | (length - 1));
+        }
+
+        let mut block_entities = Vec::new();
+        chunk.update_block_entities(|pos, entity| block_entities.push(PregenBlockEntity {
+            pos: [pos.x, pos.y, pos.z],
+            entity: entity.clone(),
+        }));
+        block_entities.sort_by_key(|block_entity| block_entity.pos);
+
+        let loc = chunk.loc();
+        Ok(Self {
+            loc: [loc.x, loc.y],
+            palette: palette.iter().map(|material| material.name().to_string()).collect(),
+            runs,
+            block_entities,
+        })
+    }
+
+    /// Restores the terrain of a chunk which hasn't been generated
+    /// yet. Returns `false` and leaves the chunk untouched if the
+    /// terrain is invalid, e.g. because a material was removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The empty chunk
+    pub fn restore(&self, chunk: &Chunk) -> bool {
+        let palette: Option<Vec<Material>> = self.palette.iter()
+            .map(|name| Material::from_name(name))
+            .collect();
+        let palette = match palette {
+            Some(palette) => palette,
+            None => return false,
+        };
+        let volume: usize = self.runs.iter().map(|run| (run & LENGTH_MASK) as usize + 1).sum();
+        if volume != CHUNK_VOLUME || self.runs.iter().any(|run| (run >> 24) as usize >= palette.len()) {
+            return false;
+        }
+
+        let mut index = 0;
+        for run in self.runs.iter() {
+            let material = palette[(run >> 24) as usize];
+            let state = (run >> 16) as u8;
+            for _ in 0..=(run & LENGTH_MASK) {
+                let pos = pos_of(index);
+                if material != Material::Air {
+                    chunk.set_block(pos, material);
+                }
+                if state != 0 {
+                    chunk.set_state(pos, state);
+                }
+                index += 1;
+            }
+        }
+        for block_entity in self.block_entities.iter() {
+            let [x, y, z] = block_entity.pos;
+            chunk.set_block_entity(Vector3::new(x, y, z), block_entity.entity.clone());
+        }
+        true
+    }
+}
+
+/// Returns the position within a chunk of the block with
+/// the given index
+///
+/// # Arguments
+///
+/// * `index` - The index of the block
+fn pos_of(index: usize) -> Vector3<i16> {
+    Vector3::new(
+        (index % CHUNK_SIZE) as i16,
+        (index / CHUNK_AREA) as i16,
+        (index / CHUNK_SIZE % CHUNK_SIZE) as i16,
+    )
+}
+
+/// PregenRegion
+///
+/// The pregenerated chunks of a region, which don't need
+/// to cover the whole region
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PregenRegion {
+    /// The location of the region in regions
+    pub loc: [i32; 2],
+    /// The pregenerated chunks
+    pub chunks: Vec<PregenChunk>,
+}
+
+impl PregenRegion {
+    /// Returns the pregenerated chunk at the given location
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the chunk
+    pub fn chunk(&self, loc: Vector2<i32>) -> Option<&PregenChunk> {
+        self.chunks.iter().find(|chunk| chunk.loc == [loc.x, loc.y])
+    }
+}
+
+/// The pregenerated regions of a cache, which are `None`
+/// if they haven't been pregenerated
+type CachedRegions = VecDeque<([i32; 2], Option<Arc<PregenRegion>>)>;
+
+/// PregenCache
+///
+/// The pregenerated terrain of the regions a world recently
+/// loaded chunks in, shared with the threads generating them
+#[derive(Clone, Debug)]
+pub struct PregenCache {
+    /// The directory of the dimension of the world
+    dir: SaveDir,
+    /// The most recently used regions first
+    regions: Arc<Mutex<CachedRegions>>,
+}
+
+impl PregenCache {
+    /// Creates a new cache of the pregenerated regions
+    /// of a dimension
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory of the dimension
+    pub fn new(dir: SaveDir) -> Self {
+        Self {
+            dir,
+            regions: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Restores the terrain of a chunk if it has been pregenerated.
+    /// Returns `false` if it has to be generated instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The empty chunk
+    pub fn restore(&self, chunk: &Chunk) -> bool {
+        let loc = *chunk.loc();
+        let region = World::region_of(loc);
+        let region = [region.x, region.y];
+        let data = {
+            let mut regions = self.regions.lock().unwrap();
+            match regions.iter().position(|(loc, _)| *loc == region) {
+                Some(position) => {
+                    let entry = regions.remove(position).unwrap();
+                    regions.push_front(entry);
+                }
+                None => {
+                    let data = self.dir.load_pregenerated(region).unwrap_or_else(|error| {
+                        log::warn!("Failed to load pregenerated region {:?}: {:?}", region, error);
+                        None
+                    });
+                    regions.push_front((region, data.map(Arc::new)));
+                    regions.truncate(CACHED_REGIONS);
+                }
+            }
+            regions[0].1.clone()
+        };
+
+        match data.as_ref().and_then(|data| data.chunk(loc)) {
+            Some(pregenerated) => pregenerated.restore(chunk),
+            None => false,
+        }
+    }
+
+    /// Drops a region from the cache, e.g. because more of
+    /// its chunks have been pregenerated
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The location of the region in regions
+    pub fn invalidate(&self, region: [i32; 2]) {
+        self.regions.lock().unwrap().retain(|(loc, _)| *loc != region);
+    }
+}
+
+/// PregenJob
+///
+/// Pre-generates the chunks within a square around a chunk
+/// region by region. Only the chunks of a single region are
+/// generated at a time, which are written to the region's
+/// file once they're all done, so the chunks never need to
+/// be loaded in the world.
+pub struct PregenJob {
+    /// The name of the dimension whose chunks are generated
+    dimension: String,
+    /// The chunk in the center of the square
+    center: Vector2<i32>,
+    /// The amount of chunks in each direction of the center
+    radius: i32,
+    /// The border beyond which no chunks are generated
+    border: Option<WorldBorder>,
+    /// The regions which haven't been started yet, the
+    /// closest ones first
+    regions: VecDeque<Vector2<i32>>,
+    /// The region which is currently generated
+    region: Option<RegionJob>,
+    /// The workers generating the chunks
+    workers: TaskPool<Vector2<i32>>,
+    /// The amount of chunks which are done
+    done: usize,
+    /// The amount of chunks within the square
+    total: usize,
+    /// The time the job has been started at
+    started: Instant,
+}
+
+/// RegionJob
+///
+/// The generation of the chunks of a single region
+struct RegionJob {
+    /// The pregenerated chunks of the region so far
+    data: PregenRegion,
+    /// The chunks which haven't been started yet
+    pending: Vec<Vector2<i32>>,
+    /// The tasks of the chunks which are generated right now
+    running: HashMap<Vector2<i32>, TaskHandle>,
+    /// Sends the chunks the tasks generated
+    sender: Sender<PregenChunk>,
+    /// Receives the chunks the tasks generated
+    receiver: Receiver<PregenChunk>,
+}
+
+impl PregenJob {
+    /// Creates a new job pre-generating the chunks around a chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `dimension` - The name of the dimension
+    /// * `center` - The location of the chunk in the center
+    /// * `radius` - The amount of chunks in each direction of the center
+    /// * `border` - The border of the dimension
+    pub fn new(dimension: &str, center: Vector2<i32>, radius: i32, border: Option<WorldBorder>) -> Self {
+        let min = World::region_of(center - Vector2::new(radius, radius));
+        let max = World::region_of(center + Vector2::new(radius, radius));
+        let center_region = World::region_of(center);
+        let mut regions = Vec::new();
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                regions.push(Vector2::new(x, y));
+            }
+        }
+        regions.sort_by_key(|loc| ((loc.x - center_region.x).abs().max((loc.y - center_region.y).abs()), loc.x, loc.y));
+
+        let mut job = Self {
+            dimension: dimension.to_string(),
+            center,
+            radius,
+            border,
+            regions: VecDeque::new(),
+            region: None,
+            workers: TaskPool::new("pregen-worker", chunk::chunk_workers(), PREGEN_QUEUE_CAPACITY),
+            done: 0,
+            total: 0,
+            started: Instant::now(),
+        };
+        job.total = regions.iter().map(|region| job.chunks_of(*region).len()).sum();
+        job.regions = regions.into();
+        job
+    }
+
+    /// Returns the name of the dimension whose chunks are generated
+    pub fn dimension(&self) -> &str {
+        &self.dimension
+    }
+
+    /// Returns the amount of chunks which are done and the
+    /// amount of chunks in total
+    pub fn progress(&self) -> (usize, usize) {
+        (self.done, self.total)
+    }
+
+    /// Returns the time since the job has been started
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Returns whether all chunks are done
+    pub fn is_finished(&self) -> bool {
+        self.region.is_none() && self.regions.is_empty()
+    }
+
+    /// Collects the chunks generated since the last update and
+    /// starts generating further ones. A region is written to
+    /// its file once all its chunks are done.
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world of the dimension
+    /// * `dir` - The directory of the dimension
+    /// * `tasks` - The amount of chunks which could be generated
+    ///   at the same time, `0` to not start any further ones
+    pub fn update(&mut self, world: &World, dir: &SaveDir, tasks: usize) -> Result<(), SaveError> {
+        if self.region.is_none() {
+            let loc = match self.regions.pop_front() {
+                Some(loc) => loc,
+                None => return Ok(()),
+            };
+            // Chunks which have been pregenerated before are kept,
+            // e.g. if an earlier job has been interrupted
+            let data = dir.load_pregenerated([loc.x, loc.y])?
+                .unwrap_or_else(|| PregenRegion { loc: [loc.x, loc.y], chunks: Vec::new() });
+            let chunks = self.chunks_of(loc);
+            let pending: Vec<Vector2<i32>> = chunks.iter().copied()
+                .filter(|chunk| data.chunk(*chunk).is_none())
+                .collect();
+            self.done += chunks.len() - pending.len();
+
+            let (sender, receiver) = channel();
+            self.region = Some(RegionJob {
+                data,
+                pending,
+                running: HashMap::new(),
+                sender,
+                receiver,
+            });
+        }
+
+        let region = self.region.as_mut().unwrap();
+        while let Ok(chunk) = region.receiver.try_recv() {
+            region.running.remove(&Vector2::new(chunk.loc[0], chunk.loc[1]));
+            region.data.chunks.push(chunk);
+            self.done += 1;
+        }
+
+        let pipeline = world.terrain_pipeline();
+        while region.running.len() < tasks {
+            let loc = match region.pending.pop() {
+                Some(loc) => loc,
+                None => break,
+            };
+            let pipeline = pipeline.clone();
+            let sender = region.sender.clone();
+            let handle = self.workers.submit(loc, move |token| {
+                let chunk = Chunk::new(loc);
+                if pipeline.generate(&chunk, &token) {
+                    match PregenChunk::encode(&chunk) {
+                        Ok(encoded) => {
+                            let _ = sender.send(encoded);
+                        }
+                        Err(error) => log::error!("Failed to encode chunk {:?}: {}", loc, error),
+                    }
+                }
+            });
+            // A full queue takes the chunk on the next update
+            let handle = match handle {
+                Some(handle) => handle,
+                None => {
+                    region.pending.push(loc);
+                    break;
+                }
+            };
+            region.running.insert(loc, handle);
+        }
+
+        if region.running.is_empty() && region.pending.is_empty() {
+            region.data.chunks.sort_by_key(|chunk| chunk.loc);
+            dir.save_pregenerated(&region.data)?;
+            if let Some(cache) = world.pregenerated() {
+                cache.invalidate(region.data.loc);
+            }
+            self.region = None;
+        }
+        Ok(())
+    }
+
+    /// Returns the chunks of a region within the square and
+    /// the border
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The location of the region in regions
+    fn chunks_of(&self, region: Vector2<i32>) -> Vec<Vector2<i32>> {
+        let mut chunks = Vec::new();
+        for x in 0..REGION_SIZE {
+            for y in 0..REGION_SIZE {
+                let loc = region * REGION_SIZE + Vector2::new(x, y);
+                let inside = (loc.x - self.center.x).abs() <= self.radius
+                    && (loc.y - self.center.y).abs() <= self.radius;
+                if inside && !matches!(self.border, Some(border) if !border.contains_chunk(&loc)) {
+                    chunks.push(loc);
+                }
+            }
+        }
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::chunk::CHUNK_HEIGHT;
+    use crate::world::terrain_generator::FlatTerrainGen;
+    use crate::world::time::SkySettings;
+    use std::fs;
+    use std::thread;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn save_dir(name: &str) -> SaveDir {
+        let path = std::env::temp_dir().join(format!("rustcraft-pregen-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        SaveDir::new(path).unwrap()
+    }
+
+    #[test]
+    fn radius_is_read_from_the_arguments() {
+        assert_eq!(radius_from_args(&args("--fullscreen")), Ok(None));
+        assert_eq!(radius_from_args(&args("--pregen 32")), Ok(Some(32)));
+        assert_eq!(radius_from_args(&args("--pregen 100000")), Ok(Some(MAX_RADIUS)));
+        assert!(radius_from_args(&args("--pregen")).is_err());
+        assert!(radius_from_args(&args("--pregen far")).is_err());
+    }
+
+    #[test]
+    fn chunks_are_restored_as_generated() {
+        let chunk = Chunk::new(Vector2::new(3, -2));
+        let world = World::with_generator(FlatTerrainGen::default(), SkySettings::default());
+        assert!(world.terrain_pipeline().generate(&chunk, &Default::default()));
+        chunk.set_state(Vector3::new(1, 2, 3), 4);
+        chunk.set_block(Vector3::new(5, 6, 7), Material::Chest);
+        chunk.set_block_entity(Vector3::new(5, 6, 7), BlockEntity::chest("village"));
+
+        let encoded = PregenChunk::encode(&chunk).unwrap();
+        assert_eq!(encoded.loc, [3, -2]);
+        assert!(encoded.runs.len() < CHUNK_HEIGHT);
+        let encoded: PregenChunk = toml::from_str(&toml::to_string(&encoded).unwrap()).unwrap();
+
+        let restored = Chunk::new(Vector2::new(3, -2));
+        assert!(encoded.restore(&restored));
+        for index in (0..CHUNK_VOLUME).step_by(7) {
+            let pos = pos_of(index);
+            assert_eq!(restored.block(pos), chunk.block(pos));
+        }
+        assert_eq!(restored.state(Vector3::new(1, 2, 3)), Some(4));
+        assert_eq!(restored.block_entity(Vector3::new(5, 6, 7)), chunk.block_entity(Vector3::new(5, 6, 7)));
+
+        let mut invalid = encoded.clone();
+        invalid.runs.pop();
+        assert!(!invalid.restore(&Chunk::new(Vector2::new(3, -2))));
+    }
+
+    #[test]
+    fn jobs_write_the_regions_they_generated() {
+        let save_dir = save_dir("job");
+        let mut world = World::with_generator(FlatTerrainGen::default(), SkySettings::default());
+        world.set_pregenerated(Some(PregenCache::new(save_dir.clone())));
+
+        let mut job = PregenJob::new("overworld", Vector2::new(0, 0), 1, None);
+        assert_eq!(job.progress(), (0, 9));
+        let start = Instant::now();
+        while !job.is_finished() {
+            assert!(start.elapsed() < Duration::from_secs(10), "the chunks haven't been generated");
+            job.update(&world, &save_dir, 2).unwrap();
+            thread::yield_now();
+        }
+        assert_eq!(job.progress(), (9, 9));
+
+        let region = save_dir.load_pregenerated([-1, -1]).unwrap().unwrap();
+        assert_eq!(region.chunks.len(), 1);
+        assert!(region.chunk(Vector2::new(-1, -1)).is_some());
+        assert!(save_dir.load_pregenerated([5, 5]).unwrap().is_none());
+
+        let chunk = Chunk::new(Vector2::new(0, 1));
+        assert!(world.pregenerated().unwrap().restore(&chunk));
+        assert!(!world.pregenerated().unwrap().restore(&Chunk::new(Vector2::new(2, 0))));
+
+        // Pregenerated chunks aren't generated again
+        let mut job = PregenJob::new("overworld", Vector2::new(0, 0), 1, None);
+        job.update(&world, &save_dir, 0).unwrap();
+        assert_eq!(job.progress().0, 4);
+        let _ = fs::remove_dir_all(save_dir.root_path());
+    }
+}
@@ -0,0 +1,276 @@
+//! Types to preview the terrain of a generator as a map seen
+//! from above, so that changes of the generators could be
+//! judged without walking the terrain
+
+use crate::world::biome::BiomeRegistry;
+use crate::world::block::Material;
+use crate::world::chunk::{Chunk, CHUNK_AREA, CHUNK_SIZE};
+use crate::world::dimension::GeneratorPreset;
+use crate::world::terrain_generator::TerrainGen;
+use cgmath::{Vector2, Vector3};
+use std::path::PathBuf;
+
+/// The command line flag starting the preview
+pub const PREVIEW_FLAG: &str = "--preview-worldgen";
+/// The amount of chunks previewed in each direction by default
+const DEFAULT_RADIUS: i32 = 16;
+/// The largest amount of chunks previewed in each direction
+const MAX_RADIUS: i32 = 256;
+
+/// PreviewOptions
+///
+/// The options of a preview read from the command line, e.g.
+///
+/// ```text
+/// rustcraft --preview-worldgen caves --seed 42 --radius 32 --output caves.png
+/// ```
+///
+/// All options are optional. The preset defaults to the
+/// overworld and the seed to the seed of the game's worlds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreviewOptions {
+    /// The preset whose terrain is previewed
+    pub preset: GeneratorPreset,
+    /// The seed of the noise of the generator
+    pub seed: u32,
+    /// The amount of chunks previewed in each direction
+    /// of the origin
+    pub radius: i32,
+    /// The file the map is exported to
+    pub output: PathBuf,
+}
+
+impl PreviewOptions {
+    /// Reads the options from the command line arguments.
+    /// Returns `None` if no preview is requested.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments without the name of the executable
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        let start = match args.iter().position(|arg| arg == PREVIEW_FLAG) {
+            Some(start) => start,
+            None => return Ok(None),
+        };
+
+        let mut args = args[start + 1..].iter().peekable();
+        let preset_name = match args.peek() {
+            Some(name) if !name.starts_with("--") => args.next().unwrap().as_str(),
+            _ => "overworld",
+        };
+        let preset = GeneratorPreset::from_name(preset_name)
+            .ok_or_else(|| format!("unknown preset '{}'", preset_name))?;
+
+        let mut seed = 0;
+        let mut radius = DEFAULT_RADIUS;
+        let mut output = None;
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| format!("missing value of {}", flag))?;
+            match flag.as_str() {
+                "--seed" => seed = value.parse().map_err(|_| format!("invalid seed '{}'", value))?,
+                "--radius" => {
+                    radius = value.parse().map_err(|_| format!("invalid radius '{}'", value))?;
+                    radius = radius.clamp(0, MAX_RADIUS);
+                }
+                "--output" => output = Some(PathBuf::from(value)),
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+
+        Ok(Some(Self {
+            preset,
+            seed,
+            radius,
+            output: output.unwrap_or_else(|| PathBuf::from(format!("worldgen-{}-{}.png", preset_name, seed))),
+        }))
+    }
+}
+
+/// TerrainMap
+///
+/// The surface of the terrain around the origin seen from
+/// above, with a pixel per block
+pub struct TerrainMap {
+    /// The width and height of the map in blocks
+    size: u32,
+    /// The material and height of the surface block of each
+    /// column, row by row starting at the northern edge
+    surface: Vec<Option<(Material, i32)>>,
+}
+
+impl TerrainMap {
+    /// Generates the chunks around the origin and records
+    /// the surface of each column
+    ///
+    /// # Arguments
+    ///
+    /// * `generator` - The terrain generator
+    /// * `radius` - The amount of chunks in each direction of the origin
+    pub fn generate(generator: &dyn TerrainGen, radius: i32) -> Self {
+        let chunks = (2 * radius + 1) as usize;
+        let size = chunks * CHUNK_SIZE;
+        let mut surface = vec![None; size * size];
+
+        for chunk_z in -radius..=radius {
+            for chunk_x in -radius..=radius {
+                let loc = Vector2::new(chunk_x, chunk_z);
+                let chunk = Chunk::new(loc);
+                let height_map: [i32; CHUNK_AREA] = generator.gen_heightmap(&loc);
+                generator.gen_smooth_terrain(&chunk, &height_map);
+
+                // The surface is the block the height map ends at,
+                // e.g. the floor of the caves rather than their ceiling
+                for z in 0..CHUNK_SIZE {
+                    for x in 0..CHUNK_SIZE {
+                        let height = height_map[z * CHUNK_SIZE + x];
+                        let block = if height >= 0 {
+                            chunk.block(Vector3::new(x as i16, height as i16, z as i16))
+                        } else {
+                            None
+                        };
+                        let row = (chunk_z + radius) as usize * CHUNK_SIZE + z;
+                        let column = (chunk_x + radius) as usize * CHUNK_SIZE + x;
+                        surface[row * size + column] = block
+                            .filter(|material| *material != Material::Air)
+                            .map(|material| (material, height));
+                    }
+                }
+            }
+        }
+
+        Self {
+            size: size as u32,
+            surface,
+        }
+    }
+
+    /// Returns the width and height of the map in blocks
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the `RGBA` pixels of the map, row by row starting
+    /// at the northern edge. Columns are colored by their surface
+    /// block and are brighter the higher they are. Columns without
+    /// any block are transparent.
+    pub fn pixels(&self) -> Vec<u8> {
+        let heights = || self.surface.iter().filter_map(|column| column.map(|(_, height)| height));
+        let min = heights().min().unwrap_or(0);
+        let max = heights().max().unwrap_or(0);
+        let range = (max - min).max(1) as f32;
+
+        let mut pixels = Vec::with_capacity(self.surface.len() * 4);
+        for column in self.surface.iter() {
+            match column {
+                Some((material, height)) => {
+                    let brightness = 0.6 + 0.4 * (height - min) as f32 / range;
+                    let [r, g, b] = material_color(*material);
+                    let shade = |channel: u8| (channel as f32 * brightness).round() as u8;
+                    pixels.extend_from_slice(&[shade(r), shade(g), shade(b), 255]);
+                }
+                None => pixels.extend_from_slice(&[0, 0, 0, 0]),
+            }
+        }
+        pixels
+    }
+}
+
+/// Generates the map of a preview and exports it as `PNG`
+///
+/// # Arguments
+///
+/// * `options` - The options of the preview
+pub fn export(options: &PreviewOptions) -> image::ImageResult<()> {
+    let generator = options.preset.generator(options.seed, &BiomeRegistry::default());
+    let map = TerrainMap::generate(&generator, options.radius);
+    image::save_buffer(&options.output, &map.pixels(), map.size(), map.size(), image::ColorType::Rgba8)
+}
+
+/// Returns the color a material is shown with on the map
+///
+/// # Arguments
+///
+/// * `material` - The material of the surface block
+fn material_color(material: Material) -> [u8; 3] {
+    match material {
+        Material::Air => [0, 0, 0],
+        Material::Grass => [95, 159, 53],
+        Material::Dirt => [134, 96, 67],
+        Material::Stone => [125, 125, 125],
+        Material::Glass => [200, 220, 230],
+        Material::Lava => [207, 92, 15],
+        Material::Glowstone => [248, 214, 119],
+        Material::Farmland => [92, 62, 40],
+        Material::Wheat => [220, 200, 90],
+        Material::Water => [50, 90, 200],
+        Material::Chest => [160, 110, 50],
+        Material::Spawner => [40, 45, 60],
+        Material::Sand => [219, 204, 150],
+        Material::TallGrass => [80, 150, 45],
+        Material::Flower => [220, 50, 60],
+        Material::Mushroom => [170, 60, 45],
+        Material::Ice => [160, 200, 240],
+        Material::PolishedStone => [150, 150, 155],
+        Material::Furnace => [110, 110, 110],
+        Material::LitFurnace => [140, 110, 80],
+        Material::Hopper => [75, 75, 80],
+        Material::Bed => [170, 35, 35],
+        Material::Obsidian => [25, 15, 40],
+        Material::Fire => [240, 130, 20],
+        Material::Tnt => [200, 40, 30],
+        Material::Ladder => [150, 110, 60],
+        Material::Vine => [50, 120, 30],
+        Material::Log => [105, 78, 46],
+        Material::Leaves => [55, 115, 40],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn options_are_read_from_the_arguments() {
+        assert_eq!(PreviewOptions::from_args(&args("--fullscreen")), Ok(None));
+
+        let options = PreviewOptions::from_args(&args("--preview-worldgen")).unwrap().unwrap();
+        assert_eq!(options.preset, GeneratorPreset::Overworld);
+        assert_eq!(options.radius, DEFAULT_RADIUS);
+        assert_eq!(options.output, PathBuf::from("worldgen-overworld-0.png"));
+
+        let options = PreviewOptions::from_args(&args("--preview-worldgen caves --seed 42 --radius 1000 --output map.png"))
+            .unwrap().unwrap();
+        assert_eq!(options.preset, GeneratorPreset::Caves);
+        assert_eq!(options.seed, 42);
+        assert_eq!(options.radius, MAX_RADIUS);
+        assert_eq!(options.output, PathBuf::from("map.png"));
+
+        assert!(PreviewOptions::from_args(&args("--preview-worldgen nether")).is_err());
+        assert!(PreviewOptions::from_args(&args("--preview-worldgen --seed")).is_err());
+        assert!(PreviewOptions::from_args(&args("--preview-worldgen --seed -1")).is_err());
+    }
+
+    #[test]
+    fn map_shows_the_surface() {
+        let map = TerrainMap::generate(&GeneratorPreset::Flat.generator(0, &BiomeRegistry::default()), 1);
+        assert_eq!(map.size(), 3 * CHUNK_SIZE as u32);
+        let pixels = map.pixels();
+        assert_eq!(pixels.len(), (map.size() * map.size() * 4) as usize);
+        let [r, g, b] = material_color(Material::Grass);
+        assert_eq!(&pixels[..4], &[(r as f32 * 0.6).round() as u8, (g as f32 * 0.6).round() as u8, (b as f32 * 0.6).round() as u8, 255]);
+
+        let void = TerrainMap::generate(&GeneratorPreset::Void.generator(0, &BiomeRegistry::default()), 0);
+        assert!(void.pixels().iter().all(|channel| *channel == 0));
+    }
+
+    #[test]
+    fn seeds_change_the_terrain() {
+        let default = TerrainMap::generate(&GeneratorPreset::Overworld.generator(0, &BiomeRegistry::default()), 0).pixels();
+        assert_eq!(default, TerrainMap::generate(&GeneratorPreset::Overworld.generator(0, &BiomeRegistry::default()), 0).pixels());
+        assert_ne!(default, TerrainMap::generate(&GeneratorPreset::Overworld.generator(7, &BiomeRegistry::default()), 0).pixels());
+    }
+}
@@ -0,0 +1,309 @@
+//! Types and functions to cast rays through the
+//! blocks of a world
+
+use crate::world::block::Material;
+use crate::world::chunk_entities::EntityUuid;
+use crate::world::World;
+use cgmath::{InnerSpace, Vector3, Zero};
+
+/// RaycastHit
+///
+/// The first solid block a ray hits
+#[derive(Copy, Clone, Debug)]
+pub struct RaycastHit {
+    /// The position of the block in world coordinates
+    pub block: Vector3<i32>,
+    /// The normal of the face which was hit. It's zero if
+    /// the ray started within the block.
+    pub normal: Vector3<i32>,
+    /// The distance from the origin of the ray to the hit
+    pub distance: f32,
+    /// The material of the block
+    pub material: Material,
+}
+
+impl RaycastHit {
+    /// Returns the position adjacent to the hit face,
+    /// where a new block would be placed
+    pub fn adjacent(&self) -> Vector3<i32> {
+        self.block + self.normal
+    }
+}
+
+/// RaycastFilter
+///
+/// What a ray passes through besides air, and whether it
+/// hits entities as well
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct RaycastFilter {
+    /// Whether the ray passes through fluids like water
+    pub ignore_fluids: bool,
+    /// Whether the ray hits scripted entities
+    pub include_entities: bool,
+}
+
+/// RaycastTarget
+///
+/// The first block or entity a ray hits
+#[derive(Copy, Clone, Debug)]
+pub enum RaycastTarget {
+    Block(RaycastHit),
+    Entity {
+        /// The id of the entity
+        uuid: EntityUuid,
+        /// The distance from the origin of the ray to the hit
+        distance: f32,
+    },
+}
+
+impl RaycastTarget {
+    /// Returns the distance from the origin of the ray to the hit
+    pub fn distance(&self) -> f32 {
+        match self {
+            RaycastTarget::Block(hit) => hit.distance,
+            RaycastTarget::Entity { distance, .. } => *distance,
+        }
+    }
+}
+
+/// Casts a ray through the blocks of the world and returns
+/// the first solid block within the given distance.
+///
+/// The blocks are traversed with a DDA (digital differential
+/// analyzer), visiting each block the ray passes exactly once.
+/// Unloaded blocks are treated as air.
+///
+/// # Arguments
+///
+/// * `world` - The world whose blocks are traversed
+/// * `origin` - The origin of the ray in world coordinates
+/// * `dir` - The direction of the ray, doesn't need to be normalized
+/// * `max_distance` - The maximal distance of the ray
+pub fn raycast(world: &World, origin: Vector3<f32>, dir: Vector3<f32>, max_distance: f32) -> Option<RaycastHit> {
+    raycast_with(|pos| world.block(pos), origin, dir, max_distance)
+}
+
+/// Casts a ray through the world and returns the first block
+/// or entity it hits within the given distance, as the filter
+/// says. A block is hit rather than an entity at the same
+/// distance.
+///
+/// # Arguments
+///
+/// * `world` - The world whose blocks and entities are hit
+/// * `origin` - The origin of the ray in world coordinates
+/// * `dir` - The direction of the ray, doesn't need to be normalized
+/// * `max_distance` - The maximal distance of the ray
+/// * `filter` - What the ray passes through or hits
+pub fn raycast_filtered(world: &World, origin: Vector3<f32>, dir: Vector3<f32>, max_distance: f32, filter: RaycastFilter) -> Option<RaycastTarget> {
+    if dir.is_zero() {
+        return None;
+    }
+    let block = raycast_with(
+        |pos| world.block(pos).filter(|material| !(filter.ignore_fluids && material.is_fluid())),
+        origin,
+        dir,
+        max_distance,
+    );
+    let entity = if filter.include_entities {
+        world.scripted_entities().hit(origin, dir, max_distance)
+    } else {
+        None
+    };
+    match (block, entity) {
+        (Some(hit), Some((_, distance))) if hit.distance <= distance => Some(RaycastTarget::Block(hit)),
+        (_, Some((uuid, distance))) => Some(RaycastTarget::Entity { uuid, distance }),
+        (hit, None) => hit.map(RaycastTarget::Block),
+    }
+}
+
+/// Casts a ray through the blocks returned by the given lookup
+/// and returns the first solid block within the given distance.
+/// Blocks for which the lookup returns `None` are treated as air.
+///
+/// # Arguments
+///
+/// * `block_at` - Returns the material of the block at a position
+/// * `origin` - The origin of the ray in world coordinates
+/// * `dir` - The direction of the ray, doesn't need to be normalized
+/// * `max_distance` - The maximal distance of the ray
+pub fn raycast_with<F>(block_at: F, origin: Vector3<f32>, dir: Vector3<f32>, max_distance: f32) -> Option<RaycastHit>
+    where F: Fn(Vector3<i32>) -> Option<Material>
+{
+    if dir.is_zero() {
+        return None;
+    }
+    let dir = dir.normalize();
+
+    let mut block = Vector3::new(origin.x.floor() as i32, origin.y.floor() as i32, origin.z.floor() as i32);
+    let step = Vector3::new(dir.x.signum() as i32, dir.y.signum() as i32, dir.z.signum() as i32);
+
+    // The distance along the ray to cross a whole block on each axis
+    let delta = Vector3::new(
+        (1.0 / dir.x).abs(),
+        (1.0 / dir.y).abs(),
+        (1.0 / dir.z).abs(),
+    );
+
+    // The distance along the ray to the next block boundary on each axis
+    let boundary = |pos: f32, block: i32, dir: f32| {
+        if dir > 0.0 {
+            (block as f32 + 1.0 - pos) / dir
+        } else if dir < 0.0 {
+            (pos - block as f32) / -dir
+        } else {
+            f32::INFINITY
+        }
+    };
+    let mut next = Vector3::new(
+        boundary(origin.x, block.x, dir.x),
+        boundary(origin.y, block.y, dir.y),
+        boundary(origin.z, block.z, dir.z),
+    );
+
+    let mut normal = Vector3::zero();
+    let mut distance = 0.0;
+
+    while distance <= max_distance {
+        if let Some(material) = block_at(block).filter(|&material| material != Material::Air) {
+            return Some(RaycastHit {
+                block,
+                normal,
+                distance,
+                material,
+            });
+        }
+
+        if next.x < next.y && next.x < next.z {
+            block.x += step.x;
+            distance = next.x;
+            next.x += delta.x;
+            normal = Vector3::new(-step.x, 0, 0);
+        } else if next.y < next.z {
+            block.y += step.y;
+            distance = next.y;
+            next.y += delta.y;
+            normal = Vector3::new(0, -step.y, 0);
+        } else {
+            block.z += step.z;
+            distance = next.z;
+            next.z += delta.z;
+            normal = Vector3::new(0, 0, -step.z);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::terrain_generator::FlatTerrainGen;
+    use crate::world::time::SkySettings;
+    use cgmath::Vector2;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// A lookup containing a single stone block
+    fn single_block(solid: Vector3<i32>) -> impl Fn(Vector3<i32>) -> Option<Material> {
+        move |pos| if pos == solid { Some(Material::Stone) } else { Some(Material::Air) }
+    }
+
+    #[test]
+    fn hits_block_along_each_axis() {
+        let cases = [
+            (Vector3::new(1.0, 0.0, 0.0), Vector3::new(3, 0, 0), Vector3::new(-1, 0, 0)),
+            (Vector3::new(-1.0, 0.0, 0.0), Vector3::new(-3, 0, 0), Vector3::new(1, 0, 0)),
+            (Vector3::new(0.0, 1.0, 0.0), Vector3::new(0, 3, 0), Vector3::new(0, -1, 0)),
+            (Vector3::new(0.0, -1.0, 0.0), Vector3::new(0, -3, 0), Vector3::new(0, 1, 0)),
+            (Vector3::new(0.0, 0.0, 1.0), Vector3::new(0, 0, 3), Vector3::new(0, 0, -1)),
+            (Vector3::new(0.0, 0.0, -1.0), Vector3::new(0, 0, -3), Vector3::new(0, 0, 1)),
+        ];
+        let origin = Vector3::new(0.5, 0.5, 0.5);
+
+        for &(dir, block, normal) in cases.iter() {
+            let hit = raycast_with(single_block(block), origin, dir, 10.0).unwrap();
+            assert_eq!(hit.block, block);
+            assert_eq!(hit.normal, normal);
+            assert_eq!(hit.adjacent(), block + normal);
+            assert!((hit.distance - 2.5).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn traverses_diagonal_rays() {
+        let block = Vector3::new(2, -2, 1);
+        let origin = Vector3::new(0.5, 0.5, 0.5);
+        let target = Vector3::new(2.5, -1.5, 1.5);
+
+        let hit = raycast_with(single_block(block), origin, target - origin, 10.0).unwrap();
+        assert_eq!(hit.block, block);
+        assert_eq!(hit.material, Material::Stone);
+    }
+
+    #[test]
+    fn respects_max_distance() {
+        let block = Vector3::new(5, 0, 0);
+        let origin = Vector3::new(0.5, 0.5, 0.5);
+        let dir = Vector3::new(1.0, 0.0, 0.0);
+
+        assert!(raycast_with(single_block(block), origin, dir, 4.0).is_none());
+        assert!(raycast_with(single_block(block), origin, dir, 4.5).is_some());
+    }
+
+    #[test]
+    fn starting_inside_a_block_has_no_normal() {
+        let block = Vector3::new(0, 0, 0);
+        let hit = raycast_with(single_block(block), Vector3::new(0.5, 0.5, 0.5), Vector3::new(0.0, 1.0, 0.0), 10.0).unwrap();
+
+        assert_eq!(hit.block, block);
+        assert_eq!(hit.normal, Vector3::zero());
+        assert_eq!(hit.distance, 0.0);
+    }
+
+    #[test]
+    fn ignores_zero_direction_and_unloaded_blocks() {
+        let origin = Vector3::new(0.5, 0.5, 0.5);
+
+        assert!(raycast_with(single_block(Vector3::zero()), origin, Vector3::zero(), 10.0).is_none());
+        assert!(raycast_with(|_| None, origin, Vector3::new(1.0, 1.0, 1.0), 10.0).is_none());
+    }
+
+    #[test]
+    fn crosses_chunk_boundaries() {
+        // The blocks of the chunk next to the origin, left and right of it
+        let origin = Vector3::new(15.5, 60.5, -0.5);
+        for &(dir, block) in [(1.0, Vector3::new(17, 60, -1)), (-1.0, Vector3::new(-2, 60, -1))].iter() {
+            let hit = raycast_with(single_block(block), origin, Vector3::new(dir, 0.0, 0.0), 20.0).unwrap();
+            assert_eq!(hit.block, block);
+            assert_eq!(hit.adjacent(), block - Vector3::new(dir as i32, 0, 0));
+        }
+    }
+
+    #[test]
+    fn finds_blocks_of_adjacent_chunks_in_the_world() {
+        let mut world = World::with_generator(FlatTerrainGen {}, SkySettings::default());
+        let chunks = [Vector2::new(-1, 0), Vector2::new(0, 0)];
+        for loc in chunks.iter() {
+            world.load_chunk(loc);
+        }
+        let start = Instant::now();
+        while !chunks.iter().all(|loc| world.chunk(loc).unwrap().is_generated()) {
+            assert!(start.elapsed() < Duration::from_secs(10), "the chunks haven't been generated");
+            thread::yield_now();
+        }
+
+        // Each block lies just across the border from the origin
+        let cases = [
+            (Vector3::new(-1.5, 10.5, 0.5), 1.0, Vector3::new(1, 10, 0)),
+            (Vector3::new(0.5, 10.5, 0.5), -1.0, Vector3::new(-3, 10, 0)),
+        ];
+        for &(origin, dir, block) in cases.iter() {
+            assert_eq!(world.set_block(block, Material::Stone), Some(Material::Air));
+            let hit = raycast(&world, origin, Vector3::new(dir, 0.0, 0.0), 10.0).unwrap();
+            assert_eq!(hit.block, block);
+            assert_eq!(hit.normal, Vector3::new(-dir as i32, 0, 0));
+            assert!((hit.distance - 2.5).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Types to define recipes, which turn items the player
+//! collected into other items
+
+use crate::scripting::ScriptEngine;
+use crate::world::tags::{Tags, TAG_PREFIX};
+use mlua::Table;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Recipe
+///
+/// The items a recipe takes and the items it results in
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recipe {
+    /// The name of the resulting item
+    pub result: String,
+    /// The amount of resulting items
+    pub count: u64,
+    /// The amounts of the ingredients by the name of their
+    /// item or a tag, which takes any of its items
+    pub ingredients: BTreeMap<String, u64>,
+}
+
+impl Recipe {
+    /// Returns whether the recipe matches a search query, which
+    /// it does if the name of its result or of an ingredient
+    /// contains the query, ignoring its case
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        std::iter::once(&self.result)
+            .chain(self.ingredients.keys())
+            .any(|name| name.to_lowercase().contains(&query))
+    }
+
+    /// Returns the ingredients as text, e.g. `4 cobblestone`
+    pub fn ingredients_text(&self) -> String {
+        self.ingredients.iter()
+            .map(|(name, count)| format!("{} {}", count, name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Recipes
+///
+/// The recipes scripts define through the `recipes` API, e.g.
+///
+/// ```lua
+/// recipes.define {
+///     result = "polished_stone",
+///     count = 4,
+///     ingredients = { cobblestone = 4 },
+/// }
+/// recipes.define { result = "chest", ingredients = { ["#stone"] = 8 } }
+/// ```
+///
+/// The count defaults to a single item. Ingredients given by a
+/// tag take any of the items of the tag, whichever the player
+/// collected the most of first. Recipes are listed in the order
+/// they've been defined, and a recipe defined again for the
+/// same result replaces its previous definition.
+#[derive(Clone)]
+pub struct Recipes {
+    /// The recipes, shared with the Lua API
+    recipes: Arc<Mutex<Vec<Recipe>>>,
+    /// The tags ingredients could refer to
+    tags: Tags,
+}
+
+impl Recipes {
+    /// Creates a new recipe registry and registers its
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `tags` - The tags ingredients could refer to
+    pub fn new(engine: &ScriptEngine, tags: &Tags) -> mlua::Result<Self> {
+        let registry = Self {
+            recipes: Arc::new(Mutex::new(Vec::new())),
+            tags: tags.clone(),
+        };
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let recipes = registry.clone();
+        api.set("define", lua.create_function(move |_, def: Table| {
+            let recipe = recipes.recipe_from_table(def)?;
+            let mut defined = recipes.recipes.lock().unwrap();
+            match defined.iter_mut().find(|existing| existing.result == recipe.result) {
+                Some(existing) => *existing = recipe,
+                None => defined.push(recipe),
+            }
+            Ok(())
+        })?)?;
+        let recipes = registry.clone();
+        api.set("list", lua.create_function(move |_, ()| {
+            Ok(recipes.all().into_iter().map(|recipe| recipe.result).collect::<Vec<_>>())
+        })?)?;
+        lua.globals().set("recipes", api)?;
+
+        Ok(registry)
+    }
+
+    /// Reads a recipe from the table passed to `recipes.define`
+    ///
+    /// # Arguments
+    ///
+    /// * `def` - The Lua table
+    fn recipe_from_table(&self, def: Table) -> mlua::Result<Recipe> {
+        let result: String = def.get("result")?;
+        let count = def.get::<_, Option<u64>>("count")?.unwrap_or(1);
+        if count == 0 {
+            return Err(mlua::Error::RuntimeError(format!("the recipe of {} results in no items", result)));
+        }
+
+        let ingredients = def.get::<_, Table>("ingredients")?
+            .pairs::<String, u64>()
+            .collect::<mlua::Result<BTreeMap<_, _>>>()?;
+        if ingredients.is_empty() || ingredients.values().any(|&count| count == 0) {
+            return Err(mlua::Error::RuntimeError(format!("the recipe of {} needs ingredients", result)));
+        }
+        if let Some(tag) = ingredients.keys().find(|name| name.starts_with(TAG_PREFIX) && self.tags.names(name).is_none()) {
+            return Err(mlua::Error::RuntimeError(format!("unknown tag {}", tag)));
+        }
+        Ok(Recipe { result, count, ingredients })
+    }
+
+    /// Returns all recipes in the order they've been defined
+    pub fn all(&self) -> Vec<Recipe> {
+        self.recipes.lock().unwrap().clone()
+    }
+
+    /// Returns the recipes matching a search query, or
+    /// all of them for an empty query
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query
+    pub fn search(&self, query: &str) -> Vec<Recipe> {
+        self.recipes.lock().unwrap().iter()
+            .filter(|recipe| recipe.matches(query))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the items a recipe would take out of the
+    /// collected items, or `None` if some are missing
+    ///
+    /// # Arguments
+    ///
+    /// * `recipe` - The recipe
+    /// * `items` - The amount of each collected item
+    pub fn consumed(&self, recipe: &Recipe, items: &BTreeMap<String, u64>) -> Option<BTreeMap<String, u64>> {
+        let mut consumed: BTreeMap<String, u64> = BTreeMap::new();
+        for (ingredient, &needed) in &recipe.ingredients {
+            let mut candidates: Vec<String> = if ingredient.starts_with(TAG_PREFIX) {
+                self.tags.names(ingredient)?.into_iter().collect()
+            } else {
+                vec![ingredient.clone()]
+            };
+            let left = |consumed: &BTreeMap<String, u64>, name: &String| {
+                items.get(name).copied().unwrap_or(0) - consumed.get(name).copied().unwrap_or(0)
+            };
+            candidates.sort_by_key(|name| std::cmp::Reverse(left(&consumed, name)));
+
+            let mut needed = needed;
+            for name in candidates {
+                let taken = left(&consumed, &name).min(needed);
+                if taken > 0 {
+                    *consumed.entry(name).or_insert(0) += taken;
+                    needed -= taken;
+                }
+            }
+            if needed > 0 {
+                return None;
+            }
+        }
+        Some(consumed)
+    }
+
+    /// Returns whether the collected items suffice for a recipe
+    ///
+    /// # Arguments
+    ///
+    /// * `recipe` - The recipe
+    /// * `items` - The amount of each collected item
+    pub fn can_craft(&self, recipe: &Recipe, items: &BTreeMap<String, u64>) -> bool {
+        self.consumed(recipe, items).is_some()
+    }
+
+    /// Crafts a recipe by taking its ingredients out of the
+    /// collected items and adding its result. Returns whether
+    /// the items sufficed, otherwise they aren't changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `recipe` - The recipe
+    /// * `items` - The amount of each collected item
+    pub fn craft(&self, recipe: &Recipe, items: &mut BTreeMap<String, u64>) -> bool {
+        let consumed = match self.consumed(recipe, items) {
+            Some(consumed) => consumed,
+            None => return false,
+        };
+        for (name, count) in consumed {
+            let left = items.get(&name).copied().unwrap_or(0) - count;
+            if left == 0 {
+                items.remove(&name);
+            } else {
+                items.insert(name, left);
+            }
+        }
+        *items.entry(recipe.result.clone()).or_insert(0) += recipe.count;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recipes_take_items_of_tags() {
+        let engine = ScriptEngine::new();
+        let tags = Tags::new(&engine).unwrap();
+        let recipes = Recipes::new(&engine, &tags).unwrap();
+        engine.exec("test", r##"
+            tags.add("#soil", { "dirt", "grass" })
+            recipes.define { result = "farmland", count = 2, ingredients = { ["#soil"] = 3 } }
+            recipes.define { result = "polished_stone", ingredients = { cobblestone = 4 } }
+            recipes.define { result = "polished_stone", count = 4, ingredients = { cobblestone = 4 } }
+            assert(#recipes.list() == 2)
+        "##).unwrap();
+        assert!(engine.exec("test", r##"recipes.define { result = "glass", ingredients = { ["#sand"] = 1 } }"##).is_err());
+        assert!(engine.exec("test", r#"recipes.define { result = "glass", ingredients = {} }"#).is_err());
+
+        assert_eq!(recipes.search("STONE").len(), 1);
+        assert_eq!(recipes.search("dirt").len(), 0);
+        assert_eq!(recipes.search("").len(), 2);
+
+        let mut items: BTreeMap<String, u64> = vec![("dirt".to_string(), 1), ("grass".to_string(), 2), ("cobblestone".to_string(), 3)]
+            .into_iter()
+            .collect();
+        let farmland = &recipes.search("farmland")[0];
+        let polished = &recipes.search("polished")[0];
+        assert!(!recipes.craft(polished, &mut items));
+        assert!(recipes.craft(farmland, &mut items));
+        assert_eq!(items, vec![("cobblestone".to_string(), 3), ("farmland".to_string(), 2)].into_iter().collect());
+        assert!(!recipes.can_craft(farmland, &items));
+    }
+}
@@ -0,0 +1,168 @@
+//! Types to change the rules of the game from scripts,
+//! e.g. how fast blocks are ticked randomly
+
+use crate::scripting::ScriptEngine;
+use std::sync::{Arc, Mutex};
+
+/// The largest amount of random ticks per section and update
+const MAX_RANDOM_TICK_SPEED: u32 = 4096;
+/// The longest delay in seconds after which broken terrain
+/// regenerates, which is a day
+const MAX_REGENERATION_DELAY: u32 = 24 * 60 * 60;
+
+/// GameRules
+///
+/// The rules of the game, which scripts could change
+/// through the `gamerules` API
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GameRules {
+    /// The amount of blocks ticked randomly per section
+    /// of a chunk and update, `0` disables random ticks
+    pub random_tick_speed: u32,
+    /// The seconds after which broken blocks of the generated
+    /// terrain regenerate, `0` disables the regeneration
+    pub regeneration_delay: u32,
+    /// Whether the player keeps their items when they die,
+    /// instead of dropping them where they died
+    pub keep_inventory: bool,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            random_tick_speed: 3,
+            regeneration_delay: 0,
+            keep_inventory: false,
+        }
+    }
+}
+
+impl GameRules {
+    /// Returns the value of the rule with the given name as
+    /// used by scripts, or `None` if there is no such rule
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the rule
+    pub fn get(&self, name: &str) -> Option<u32> {
+        match name {
+            "randomTickSpeed" => Some(self.random_tick_speed),
+            "terrainRegenerationDelay" => Some(self.regeneration_delay),
+            "keepInventory" => Some(self.keep_inventory as u32),
+            _ => None,
+        }
+    }
+
+    /// Changes the value of the rule with the given name.
+    /// Returns `false` if there is no such rule.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the rule
+    /// * `value` - The new value of the rule
+    pub fn set(&mut self, name: &str, value: u32) -> bool {
+        match name {
+            "randomTickSpeed" => self.random_tick_speed = value.min(MAX_RANDOM_TICK_SPEED),
+            "terrainRegenerationDelay" => self.regeneration_delay = value.min(MAX_REGENERATION_DELAY),
+            "keepInventory" => self.keep_inventory = value != 0,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Rules
+///
+/// The game rules shared with the `gamerules` API, e.g.
+///
+/// ```lua
+/// gamerules.set("randomTickSpeed", 10)
+/// local speed = gamerules.get("randomTickSpeed")
+///
+/// -- Broken terrain regenerates after five minutes,
+/// -- e.g. on a hub server
+/// gamerules.set("terrainRegenerationDelay", 300)
+///
+/// -- Boolean rules are set to 0 or 1
+/// gamerules.set("keepInventory", 1)
+/// ```
+pub struct Rules {
+    /// The current rules, shared with the Lua API
+    rules: Arc<Mutex<GameRules>>,
+}
+
+impl Rules {
+    /// Creates the default rules and registers their
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let rules = Arc::new(Mutex::new(GameRules::default()));
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let current = rules.clone();
+        api.set("get", lua.create_function(move |_, name: String| {
+            current.lock().unwrap().get(&name).ok_or_else(|| {
+                mlua::Error::RuntimeError(format!("unknown game rule '{}'", name))
+            })
+        })?)?;
+        let current = rules.clone();
+        api.set("set", lua.create_function(move |_, (name, value): (String, u32)| {
+            if current.lock().unwrap().set(&name, value) {
+                Ok(())
+            } else {
+                Err(mlua::Error::RuntimeError(format!("unknown game rule '{}'", name)))
+            }
+        })?)?;
+        lua.globals().set("gamerules", api)?;
+
+        Ok(Self {
+            rules,
+        })
+    }
+
+    /// Returns the current rules
+    pub fn current(&self) -> GameRules {
+        *self.rules.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_change_game_rules() {
+        let engine = ScriptEngine::new();
+        let rules = Rules::new(&engine).unwrap();
+        assert_eq!(rules.current(), GameRules::default());
+
+        engine.exec("test", r#"
+            gamerules.set("randomTickSpeed", gamerules.get("randomTickSpeed") * 2)
+        "#).unwrap();
+        assert_eq!(rules.current().random_tick_speed, 6);
+
+        engine.exec("test", r#"gamerules.set("randomTickSpeed", 100000)"#).unwrap();
+        assert_eq!(rules.current().random_tick_speed, MAX_RANDOM_TICK_SPEED);
+
+        engine.exec("test", r#"gamerules.set("terrainRegenerationDelay", 1000000)"#).unwrap();
+        assert_eq!(rules.current().regeneration_delay, MAX_REGENERATION_DELAY);
+
+        engine.exec("test", r#"gamerules.set("keepInventory", 2)"#).unwrap();
+        assert!(rules.current().keep_inventory);
+        engine.exec("test", r#"assert(gamerules.get("keepInventory") == 1)"#).unwrap();
+    }
+
+    #[test]
+    fn unknown_game_rules_are_rejected() {
+        let engine = ScriptEngine::new();
+        let rules = Rules::new(&engine).unwrap();
+        assert!(engine.exec("test", r#"gamerules.set("doDaylightCycle", 1)"#).is_err());
+        assert!(engine.exec("test", r#"gamerules.get("doDaylightCycle")"#).is_err());
+        assert!(engine.exec("test", r#"gamerules.set("randomTickSpeed", -1)"#).is_err());
+        assert_eq!(rules.current(), GameRules::default());
+    }
+}
@@ -0,0 +1,521 @@
+//! Types to persist a world and its players on
+//! the file system
+
+use crate::death::DeathLocation;
+use crate::keybindings::KeyOverrides;
+use crate::player::Skin;
+use crate::scoreboard::ScoreboardData;
+use crate::stats::Statistics;
+use crate::world::chunk_entities::SavedEntity;
+use crate::world::edit_log::LoggedEdit;
+use crate::world::mining::HeldTool;
+use crate::world::pregen::PregenRegion;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The file name of the player data
+const PLAYER_DATA_FILE: &str = "player.toml";
+/// The file name of the world metadata
+const META_FILE: &str = "world.toml";
+/// The file name of the scoreboard
+const SCOREBOARD_FILE: &str = "scoreboard.toml";
+/// The directory containing the skins of the players
+const SKINS_DIR: &str = "skins";
+/// The name of the player if it hasn't been changed
+pub const DEFAULT_PLAYER_NAME: &str = "Player";
+/// The directory containing a directory per dimension
+const DIMENSIONS_DIR: &str = "dimensions";
+/// The directory containing the resource packs shipped with the world
+const RESOURCE_PACKS_DIR: &str = "resourcepacks";
+/// The width and depth of a region in chunks
+pub const REGION_SIZE: i32 = 8;
+/// The prefix of the file names of the edit logs of regions
+pub const EDIT_LOG_PREFIX: &str = "log.";
+/// The prefix of the file names of the pregenerated terrain of regions
+const PREGEN_PREFIX: &str = "p.";
+
+#[derive(Debug)]
+pub enum SaveError {
+    FailedToGetExePath,
+    Io(io::Error),
+    Deserialize(toml::de::Error),
+    Serialize(toml::ser::Error),
+    Image(image::ImageError),
+    InvalidSkinSize(u32, u32),
+}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for SaveError {
+    fn from(error: toml::de::Error) -> Self {
+        SaveError::Deserialize(error)
+    }
+}
+
+impl From<image::ImageError> for SaveError {
+    fn from(error: image::ImageError) -> Self {
+        SaveError::Image(error)
+    }
+}
+
+impl From<toml::ser::Error> for SaveError {
+    fn from(error: toml::ser::Error) -> Self {
+        SaveError::Serialize(error)
+    }
+}
+
+/// SaveDir
+///
+/// The `SaveDir` is the directory a single world is
+/// saved in.
+#[derive(Clone, Debug)]
+pub struct SaveDir {
+    /// The root path of the save directory
+    root_path: PathBuf,
+}
+
+impl SaveDir {
+    /// Creates a new `SaveDir` from a given path relative
+    /// to the executable. The directory is created if it
+    /// doesn't exist yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `rel_path` - A path to the save directory relative to
+    /// the executable
+    pub fn from_relative_exe_path(rel_path: &Path) -> Result<Self, SaveError> {
+        let exe_file_name = ::std::env::current_exe()
+            .map_err(|_| SaveError::FailedToGetExePath)?;
+
+        let exe_path = exe_file_name.parent()
+            .ok_or(SaveError::FailedToGetExePath)?;
+
+        Self::new(exe_path.join(rel_path))
+    }
+
+    /// Creates a new `SaveDir` at the given path. The
+    /// directory is created if it doesn't exist yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `root_path` - The path of the save directory
+    pub fn new(root_path: PathBuf) -> Result<Self, SaveError> {
+        fs::create_dir_all(&root_path)?;
+        Ok(Self {
+            root_path,
+        })
+    }
+
+    /// Returns the root path of the save directory
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
+    /// Loads the player data. If the player data hasn't
+    /// been saved so far, the default player data is returned.
+    pub fn load_player_data(&self) -> Result<PlayerData, SaveError> {
+        let path = self.root_path.join(PLAYER_DATA_FILE);
+        if !path.exists() {
+            return Ok(PlayerData::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Loads the world metadata. If the metadata hasn't
+    /// been saved so far, the default metadata is returned.
+    pub fn load_meta(&self) -> Result<WorldMeta, SaveError> {
+        let path = self.root_path.join(META_FILE);
+        if !path.exists() {
+            return Ok(WorldMeta::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Saves the world metadata
+    ///
+    /// # Arguments
+    ///
+    /// * `meta` - The world metadata
+    pub fn save_meta(&self, meta: &WorldMeta) -> Result<(), SaveError> {
+        let content = toml::to_string(meta)?;
+        write_atomic(&self.root_path.join(META_FILE), content.as_bytes())?;
+        Ok(())
+    }
+
+    /// Loads the scoreboard. If the scoreboard hasn't
+    /// been saved so far, an empty scoreboard is returned.
+    pub fn load_scoreboard(&self) -> Result<ScoreboardData, SaveError> {
+        let path = self.root_path.join(SCOREBOARD_FILE);
+        if !path.exists() {
+            return Ok(ScoreboardData::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Saves the scoreboard
+    ///
+    /// # Arguments
+    ///
+    /// * `scoreboard` - The scoreboard
+    pub fn save_scoreboard(&self, scoreboard: &ScoreboardData) -> Result<(), SaveError> {
+        let content = toml::to_string(scoreboard)?;
+        write_atomic(&self.root_path.join(SCOREBOARD_FILE), content.as_bytes())?;
+        Ok(())
+    }
+
+    /// Loads the skin of a player from the `PNG` image named
+    /// after them. If the player doesn't have a skin, `None`
+    /// is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the player
+    pub fn load_skin(&self, name: &str) -> Result<Option<Skin>, SaveError> {
+        let path = self.root_path.join(SKINS_DIR).join(format!("{}.png", name));
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        // Flip the image vertically for `OpenGL` use
+        let image = image::open(path)?.flipv().into_rgba8();
+        let (width, height) = image.dimensions();
+        Skin::new(width, height, image.into_raw())
+            .map(Some)
+            .ok_or(SaveError::InvalidSkinSize(width, height))
+    }
+
+    /// Returns the directory the regions of a dimension
+    /// are saved in. The directory is created if it
+    /// doesn't exist yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the dimension
+    pub fn dimension(&self, name: &str) -> Result<SaveDir, SaveError> {
+        Self::new(self.root_path.join(DIMENSIONS_DIR).join(name))
+    }
+
+    /// Returns the directory or ZIP archive of a resource
+    /// pack shipped with the world, if it exists. Archives
+    /// could be named with or without their extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the resource pack
+    pub fn resource_pack(&self, name: &str) -> Option<PathBuf> {
+        let dir = self.root_path.join(RESOURCE_PACKS_DIR);
+        [dir.join(name), dir.join(format!("{}.zip", name))].iter()
+            .find(|path| path.exists())
+            .cloned()
+    }
+
+    /// Returns whether any chunks are saved in this directory,
+    /// either edited or pregenerated ones
+    pub fn has_chunks(&self) -> bool {
+        fs::read_dir(&self.root_path).is_ok_and(|entries| {
+            entries.filter_map(|entry| entry.ok()).any(|entry| {
+                let name = entry.file_name();
+                let name = name.to_str().unwrap_or_default();
+                name.ends_with(".toml") && (name.starts_with("r.") || name.starts_with(PREGEN_PREFIX))
+            })
+        })
+    }
+
+    /// Loads all regions saved in this directory
+    pub fn load_regions(&self) -> Result<Vec<RegionData>, SaveError> {
+        let mut regions = Vec::new();
+        for entry in fs::read_dir(&self.root_path)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            if name.starts_with("r.") && name.ends_with(".toml") {
+                let content = fs::read_to_string(path)?;
+                regions.push(toml::from_str(&content)?);
+            }
+        }
+        Ok(regions)
+    }
+
+    /// Saves a region. A region without any blocks and
+    /// entities is removed from the directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The region
+    pub fn save_region(&self, region: &RegionData) -> Result<(), SaveError> {
+        let path = self.root_path.join(format!("r.{}.{}.toml", region.loc[0], region.loc[1]));
+        if region.blocks.is_empty() && region.entities.is_empty() {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+
+        let content = toml::to_string(region)?;
+        write_atomic(&path, content.as_bytes())?;
+        Ok(())
+    }
+
+    /// Appends edits to the edit log of a region. The log
+    /// is created if it doesn't exist yet, but it's never
+    /// rewritten.
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The location of the region in regions
+    /// * `edits` - The edits of blocks of the region
+    pub fn append_edit_log(&self, region: [i32; 2], edits: &[LoggedEdit]) -> Result<(), SaveError> {
+        // The edits are an array of tables, which are
+        // continued by the tables appended to the log
+        let content = toml::to_string(&EditLogData { edits: edits.to_vec() })?;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.edit_log_path(region))?;
+        file.write_all(content.as_bytes())?;
+        Ok(())
+    }
+
+    /// Loads the edit log of a region. If nothing has been
+    /// logged for the region so far, no edits are returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The location of the region in regions
+    pub fn load_edit_log(&self, region: [i32; 2]) -> Result<Vec<LoggedEdit>, SaveError> {
+        let path = self.edit_log_path(region);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let log: EditLogData = toml::from_str(&fs::read_to_string(path)?)?;
+        Ok(log.edits)
+    }
+
+    /// Loads the edit logs of all regions saved in this directory
+    pub fn load_edit_logs(&self) -> Result<Vec<LoggedEdit>, SaveError> {
+        let mut edits = Vec::new();
+        for entry in fs::read_dir(&self.root_path)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            if name.starts_with(EDIT_LOG_PREFIX) && name.ends_with(".toml") {
+                let log: EditLogData = toml::from_str(&fs::read_to_string(path)?)?;
+                edits.extend(log.edits);
+            }
+        }
+        Ok(edits)
+    }
+
+    /// Returns the path of the edit log of a region
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The location of the region in regions
+    fn edit_log_path(&self, region: [i32; 2]) -> PathBuf {
+        self.root_path.join(format!("{}{}.{}.toml", EDIT_LOG_PREFIX, region[0], region[1]))
+    }
+
+    /// Loads the pregenerated terrain of a region. Returns
+    /// `None` if none of its chunks have been pregenerated.
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The location of the region in regions
+    pub fn load_pregenerated(&self, region: [i32; 2]) -> Result<Option<PregenRegion>, SaveError> {
+        let path = self.pregen_path(region);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(toml::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    /// Saves the pregenerated terrain of a region, replacing
+    /// the terrain pregenerated before
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The pregenerated terrain
+    pub fn save_pregenerated(&self, region: &PregenRegion) -> Result<(), SaveError> {
+        let content = toml::to_string(region)?;
+        write_atomic(&self.pregen_path(region.loc), content.as_bytes())?;
+        Ok(())
+    }
+
+    /// Returns the path of the pregenerated terrain of a region
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The location of the region in regions
+    fn pregen_path(&self, region: [i32; 2]) -> PathBuf {
+        self.root_path.join(format!("{}{}.{}.toml", PREGEN_PREFIX, region[0], region[1]))
+    }
+
+    /// Saves the player data
+    ///
+    /// # Arguments
+    ///
+    /// * `player_data` - The player data
+    pub fn save_player_data(&self, player_data: &PlayerData) -> Result<(), SaveError> {
+        let content = toml::to_string(player_data)?;
+        write_atomic(&self.root_path.join(PLAYER_DATA_FILE), content.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// PlayerData
+///
+/// Everything which is persisted about the player
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerData {
+    /// The name of the player, which is shown above their
+    /// head and names their skin
+    pub name: String,
+    /// The statistics of the player
+    pub statistics: Statistics,
+    /// The amount of each item the player collected by the
+    /// name of the item, e.g. food harvested from crops
+    pub items: BTreeMap<String, u64>,
+    /// The tool the player holds, `None` for the bare hand
+    pub tool: Option<HeldTool>,
+    /// Whether the player is an operator, who could edit
+    /// blocks within the spawn protection
+    pub operator: bool,
+    /// The keys the player bound the actions of scripts
+    /// to, by the name of the action
+    pub key_bindings: KeyOverrides,
+    /// The position of the foot of the bed the player
+    /// respawns at, `None` to respawn at the world spawn
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bed: Option<[i32; 3]>,
+    /// The waypoint in the overworld the compass points
+    /// towards, `None` to point towards the spawn
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub waypoint: Option<[f32; 3]>,
+    /// Where the player died the last time, if they ever died
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_death: Option<DeathLocation>,
+}
+
+impl Default for PlayerData {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_PLAYER_NAME.to_string(),
+            statistics: Statistics::default(),
+            items: BTreeMap::new(),
+            tool: None,
+            operator: false,
+            key_bindings: KeyOverrides::new(),
+            bed: None,
+            waypoint: None,
+            last_death: None,
+        }
+    }
+}
+
+/// WorldMeta
+///
+/// The settings of a world which are shared by all of its
+/// dimensions. They aren't changed by the game, but could
+/// be edited in the saved file.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldMeta {
+    /// The distance from the origin to each side of the
+    /// world border in blocks, `0` for no border
+    pub border_radius: u32,
+    /// The distance from the spawn to each side of the area
+    /// only operators could edit in blocks, `0` for no protection
+    pub spawn_protection: u32,
+    /// The name of the resource pack in the `resourcepacks`
+    /// directory of the world, either a directory or a ZIP
+    /// archive, whose files replace the resources of the game
+    /// while the world is played. Empty for none.
+    pub resource_pack: String,
+    /// The shader options the world is played with, by the
+    /// section of the game settings, e.g. `cave_fog`. They
+    /// replace the options of the game settings key by key.
+    pub shader_options: toml::value::Table,
+    /// The name of the terrain generator of the overworld, see
+    /// `TerrainGenRegistry`. Empty for the default biomes.
+    pub generator: String,
+    /// The parameters of the generator, e.g. its `seed`
+    pub generator_options: toml::value::Table,
+}
+
+/// RegionData
+///
+/// The blocks of a region of `REGION_SIZE` by `REGION_SIZE`
+/// chunks which differ from the generated terrain, and the
+/// entities in its chunks. Chunks are generated again when
+/// they are loaded, so only the edited blocks need to be saved.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegionData {
+    /// The location of the region in regions
+    pub loc: [i32; 2],
+    /// The edited blocks
+    pub blocks: Vec<SavedBlock>,
+    /// The entities, e.g. dropped items and mobs
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub entities: Vec<SavedEntity>,
+}
+
+/// EditLogData
+///
+/// The edit log of a region
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct EditLogData {
+    /// The edits in the order they have been logged
+    edits: Vec<LoggedEdit>,
+}
+
+/// SavedBlock
+///
+/// A single block of a region
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedBlock {
+    /// The position of the block in world coordinates
+    pub pos: [i32; 3],
+    /// The name of the material of the block
+    pub material: String,
+    /// The state of the block, e.g. the growth stage of a crop
+    #[serde(default, skip_serializing_if = "is_default_state")]
+    pub state: u8,
+}
+
+/// Returns whether a block has the default state, which
+/// isn't saved
+///
+/// # Arguments
+///
+/// * `state` - The state of the block
+fn is_default_state(state: &u8) -> bool {
+    *state == 0
+}
+
+/// Writes a file by writing a temporary file first and
+/// renaming it afterwards, so the file is never left
+/// half written.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file
+/// * `content` - The content of the file
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(tmp_path, path)
+}
@@ -0,0 +1,156 @@
+//! The `world` table passed to script callbacks, which lets
+//! them read and edit the world they are called for
+
+use crate::scripting;
+use crate::world::World;
+use crate::world::block::Material;
+use crate::world::raycast::{self, RaycastFilter, RaycastTarget};
+use cgmath::{InnerSpace, Vector3};
+use mlua::{Lua, Scope, Table};
+use std::cell::RefCell;
+
+/// Creates the `world` table of a world, which is only valid
+/// within the given scope. It offers these functions:
+/// * `block(pos)` - Returns the name of a block
+/// * `set_block(pos, name)` - Changes a block
+/// * `state(pos)` - Returns the state of a block
+/// * `set_state(pos, state)` - Changes the state of a block
+/// * `light(pos)` - Returns the light level of the sky at a block
+/// * `raycast(origin, dir, maxDist, filter)` - Returns what a
+///   ray hits first, e.g.
+///
+/// ```lua
+/// local hit = world.raycast(origin, look, 32, { ignoreFluids = true, includeEntities = true })
+/// if hit and hit.entity then
+///     -- `hit.entity` is the id of the entity
+/// elseif hit then
+///     world.set_block(hit.blockPos, "glass")
+/// end
+/// ```
+///
+/// A hit has the position where the ray hit as `pos` and the
+/// `distance` to it. Blocks are hit with the `face` the ray
+/// entered through as its normal, the name of the `block` and
+/// its `blockPos`, while entities are hit with their id as
+/// `entity`. Rays pass through air and fluids if `ignoreFluids`
+/// is set, and only hit scripted entities if `includeEntities`
+/// is set.
+///
+/// # Arguments
+///
+/// * `lua` - The Lua state
+/// * `scope` - The scope the functions are valid in
+/// * `world` - The world
+pub fn world_api<'lua, 'scope>(lua: &'lua Lua, scope: &Scope<'lua, 'scope>, world: &'scope RefCell<&mut World>) -> mlua::Result<Table<'lua>> {
+    let api = lua.create_table()?;
+    api.set("block", scope.create_function(move |_, pos: Table| {
+        let pos = block_pos(pos)?;
+        Ok(world.borrow().block(pos).map(|material| material.name()))
+    })?)?;
+    api.set("set_block", scope.create_function(move |_, (pos, name): (Table, String)| {
+        let pos = block_pos(pos)?;
+        let material = Material::from_name(&name).ok_or_else(|| {
+            mlua::Error::RuntimeError(format!("unknown block {}", name))
+        })?;
+        Ok(world.borrow_mut().set_block(pos, material).is_some())
+    })?)?;
+    api.set("state", scope.create_function(move |_, pos: Table| {
+        Ok(world.borrow().block_state(block_pos(pos)?))
+    })?)?;
+    api.set("set_state", scope.create_function(move |_, (pos, state): (Table, u8)| {
+        Ok(world.borrow_mut().set_block_state(block_pos(pos)?, state).is_some())
+    })?)?;
+    api.set("light", scope.create_function(move |_, pos: Table| {
+        Ok(world.borrow().sky_light(block_pos(pos)?))
+    })?)?;
+    api.set("raycast", scope.create_function(move |lua, (origin, dir, max_distance, filter): (Table, Table, f32, Option<Table>)| {
+        let origin = scripting::vector_from_table(origin)?;
+        let dir = scripting::vector_from_table(dir)?;
+        let filter = match filter {
+            Some(filter) => RaycastFilter {
+                ignore_fluids: filter.get::<_, Option<bool>>("ignoreFluids")?.unwrap_or(false),
+                include_entities: filter.get::<_, Option<bool>>("includeEntities")?.unwrap_or(false),
+            },
+            None => RaycastFilter::default(),
+        };
+        match raycast::raycast_filtered(&world.borrow(), origin, dir, max_distance, filter) {
+            Some(target) => Ok(Some(hit_table(lua, origin, dir, target)?)),
+            None => Ok(None),
+        }
+    })?)?;
+    Ok(api)
+}
+
+/// Creates the Lua table of what a ray hit
+///
+/// # Arguments
+///
+/// * `lua` - The Lua state
+/// * `origin` - The origin of the ray
+/// * `dir` - The direction of the ray
+/// * `target` - What the ray hit
+fn hit_table<'lua>(lua: &'lua Lua, origin: Vector3<f32>, dir: Vector3<f32>, target: RaycastTarget) -> mlua::Result<Table<'lua>> {
+    let pos = origin + dir.normalize_to(target.distance());
+    let hit = lua.create_table()?;
+    hit.set("pos", lua.create_sequence_from(vec![pos.x, pos.y, pos.z])?)?;
+    hit.set("distance", target.distance())?;
+    match target {
+        RaycastTarget::Block(block) => {
+            hit.set("block", block.material.name())?;
+            hit.set("blockPos", lua.create_sequence_from(vec![block.block.x, block.block.y, block.block.z])?)?;
+            hit.set("face", lua.create_sequence_from(vec![block.normal.x, block.normal.y, block.normal.z])?)?;
+        }
+        RaycastTarget::Entity { uuid, .. } => hit.set("entity", uuid.to_string())?,
+    }
+    Ok(hit)
+}
+
+/// Reads the position of a block from a Lua table
+/// of three numbers
+///
+/// # Arguments
+///
+/// * `table` - The Lua table
+fn block_pos(table: Table) -> mlua::Result<Vector3<i32>> {
+    let pos = scripting::vector_from_table(table)?;
+    Ok(Vector3::new(pos.x.floor() as i32, pos.y.floor() as i32, pos.z.floor() as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::terrain_generator::FlatTerrainGen;
+    use crate::world::time::SkySettings;
+    use cgmath::Vector2;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn rays_pass_through_fluids_if_ignored() {
+        let mut world = World::with_generator(FlatTerrainGen {}, SkySettings::default());
+        world.load_chunk(&Vector2::new(0, 0));
+        let start = Instant::now();
+        while !world.chunks()[0].is_generated() {
+            assert!(start.elapsed() < Duration::from_secs(10), "the chunk hasn't been generated");
+            thread::yield_now();
+        }
+        world.set_block(Vector3::new(3, 5, 3), Material::Water);
+
+        let lua = Lua::new();
+        let world = RefCell::new(&mut world);
+        lua.scope(|scope| {
+            lua.globals().set("world", world_api(&lua, scope, &world)?)?;
+            lua.load(r#"
+                local hit = world.raycast({ 3.5, 10.5, 3.5 }, { 0, -1, 0 }, 20)
+                assert(hit.block == "water" and hit.blockPos[2] == 5)
+                assert(hit.face[2] == 1 and hit.distance == 4.5 and hit.pos[2] == 6)
+
+                hit = world.raycast({ 3.5, 10.5, 3.5 }, { 0, -1, 0 }, 20, { ignoreFluids = true })
+                assert(hit.block == "grass" and hit.blockPos[2] == 4 and hit.entity == nil)
+
+                assert(world.raycast({ 3.5, 10.5, 3.5 }, { 0, 1, 0 }, 20, { includeEntities = true }) == nil)
+                assert(world.raycast({ 3.5, 10.5, 3.5 }, { 0, -1, 0 }, 2) == nil)
+            "#).exec()
+        }).unwrap();
+    }
+}
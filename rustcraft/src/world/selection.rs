@@ -0,0 +1,428 @@
+//! Types to select a region of blocks with two corners and
+//! to edit it at once, e.g. to fill it or to copy it
+
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::mesh::Model;
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::shader::ShaderProgram;
+use crate::resources::Resources;
+use crate::scripting::{self, ScriptEngine};
+use crate::world::World;
+use crate::world::block::Material;
+use crate::world::brush::Brush;
+use crate::world::history::DEFAULT_HISTORY_CAPACITY;
+use crate::world::placement;
+use cgmath::{Matrix4, Vector3};
+use mlua::{Table, Value, Variadic};
+use std::sync::{Arc, Mutex};
+
+/// The name of the tool selecting the corners, the first
+/// one by breaking a block and the second one by placing one
+pub const WAND: &str = "wand";
+/// The maximal amount of blocks of a selection which could
+/// be edited at once, so that every edit could be undone
+pub const MAX_VOLUME: usize = DEFAULT_HISTORY_CAPACITY;
+
+/// Selection
+///
+/// The two corners of the selected region, which includes
+/// the blocks of both corners
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Selection {
+    /// The first corner
+    pub first: Option<Vector3<i32>>,
+    /// The second corner
+    pub second: Option<Vector3<i32>>,
+}
+
+impl Selection {
+    /// Returns the lowest and the highest block of the region,
+    /// or `None` unless both corners are selected
+    pub fn bounds(&self) -> Option<(Vector3<i32>, Vector3<i32>)> {
+        let (first, second) = (self.first?, self.second?);
+        Some((
+            Vector3::new(first.x.min(second.x), first.y.min(second.y), first.z.min(second.z)),
+            Vector3::new(first.x.max(second.x), first.y.max(second.y), first.z.max(second.z)),
+        ))
+    }
+
+    /// Returns the region which is outlined, which is the
+    /// block of a single corner until both are selected
+    pub fn outline(&self) -> Option<(Vector3<i32>, Vector3<i32>)> {
+        self.bounds().or_else(|| {
+            let corner = self.first.or(self.second)?;
+            Some((corner, corner))
+        })
+    }
+
+    /// Returns the amount of selected blocks, `0` unless
+    /// both corners are selected
+    pub fn volume(&self) -> usize {
+        match self.bounds() {
+            Some((min, max)) => {
+                let size = max - min + Vector3::new(1, 1, 1);
+                size.x as usize * size.y as usize * size.z as usize
+            }
+            None => 0,
+        }
+    }
+
+    /// Returns the positions of all selected blocks
+    pub fn positions(&self) -> Vec<Vector3<i32>> {
+        let (min, max) = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return Vec::new(),
+        };
+        let mut positions = Vec::with_capacity(self.volume());
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    positions.push(Vector3::new(x, y, z));
+                }
+            }
+        }
+        positions
+    }
+
+    /// Removes both corners
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Clipboard
+///
+/// The blocks of a copied region, which could be pasted
+/// elsewhere. They're stored relative to its lowest block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clipboard {
+    /// The blocks with their offset to the lowest block
+    blocks: Vec<(Vector3<i32>, Material)>,
+}
+
+impl Clipboard {
+    /// Copies the selected blocks of a world, or returns the
+    /// first block which isn't loaded
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world
+    /// * `selection` - The selected region
+    pub fn copy(world: &World, selection: &Selection) -> Result<Self, Vector3<i32>> {
+        let min = match selection.bounds() {
+            Some((min, _)) => min,
+            None => return Ok(Self { blocks: Vec::new() }),
+        };
+        let blocks = selection.positions().into_iter()
+            .map(|pos| world.block(pos).map(|material| (pos - min, material)).ok_or(pos))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { blocks })
+    }
+
+    /// Returns the amount of copied blocks
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns whether no blocks have been copied
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Returns the edits pasting the blocks
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The position of the lowest block
+    pub fn paste(&self, origin: Vector3<i32>) -> Vec<(Vector3<i32>, Material)> {
+        self.blocks.iter().map(|&(offset, material)| (origin + offset, material)).collect()
+    }
+}
+
+/// EditCommand
+///
+/// A command scripts issued through the `worldedit` API,
+/// which is applied with the next update
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EditCommand {
+    /// The first corner should be selected, or the
+    /// block the player is looking at if none is given
+    Pos1(Option<Vector3<i32>>),
+    /// The second corner should be selected, or the
+    /// block the player is looking at if none is given
+    Pos2(Option<Vector3<i32>>),
+    /// Both corners should be removed
+    Clear,
+    /// The selected blocks should be replaced
+    Fill(Material),
+    /// The selected blocks should be copied
+    Copy,
+    /// The copied blocks should be pasted with their lowest
+    /// block at the given position, or in front of the block
+    /// the player is looking at if none is given
+    Paste(Option<Vector3<i32>>),
+    /// The most recent edit should be reverted
+    Undo,
+    /// The brush tool should apply the given brush,
+    /// or none if it's removed
+    Brush(Option<Brush>),
+}
+
+/// WorldEdit
+///
+/// The commands scripts issue through the `worldedit` API
+/// to edit a selected region at once, e.g.
+///
+/// ```lua
+/// -- Corners at the block the player is looking at
+/// -- or at a given position
+/// worldedit.pos1()
+/// worldedit.pos2({ 10, 70, -4 })
+///
+/// worldedit.fill("stone")
+/// worldedit.copy()
+/// worldedit.paste()
+/// worldedit.undo()
+/// worldedit.clear()
+///
+/// -- Sculpting with the `brush` tool around the block
+/// -- the player is looking at
+/// worldedit.brush("sphere", "stone", 5)
+/// worldedit.brush("cylinder", "dirt", 3, 2)
+/// worldedit.brush("smooth", 4)
+/// worldedit.brush()
+/// ```
+///
+/// The corners could be selected with the `wand` tool as well.
+/// Pastes are placed in front of the block the player is looking
+/// at, unless a position is given. At most `MAX_VOLUME` blocks
+/// are filled or copied at once, and an edit is reverted at once.
+pub struct WorldEdit {
+    /// The commands issued since the last update
+    commands: Arc<Mutex<Vec<EditCommand>>>,
+}
+
+impl WorldEdit {
+    /// Creates a new world edit API and registers it
+    /// in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let commands = Arc::new(Mutex::new(Vec::new()));
+
+        let lua = engine.lua();
+        let api = scripting::api_table(lua, "worldedit")?;
+        let issued = commands.clone();
+        api.set("pos1", lua.create_function(move |_, pos: Option<Table>| {
+            issued.lock().unwrap().push(EditCommand::Pos1(pos.map(block_pos).transpose()?));
+            Ok(())
+        })?)?;
+        let issued = commands.clone();
+        api.set("pos2", lua.create_function(move |_, pos: Option<Table>| {
+            issued.lock().unwrap().push(EditCommand::Pos2(pos.map(block_pos).transpose()?));
+            Ok(())
+        })?)?;
+        let issued = commands.clone();
+        api.set("clear", lua.create_function(move |_, ()| {
+            issued.lock().unwrap().push(EditCommand::Clear);
+            Ok(())
+        })?)?;
+        let issued = commands.clone();
+        api.set("fill", lua.create_function(move |_, name: String| {
+            let material = Material::from_name(&name).ok_or_else(|| {
+                mlua::Error::RuntimeError(format!("unknown block {}", name))
+            })?;
+            issued.lock().unwrap().push(EditCommand::Fill(material));
+            Ok(())
+        })?)?;
+        let issued = commands.clone();
+        api.set("copy", lua.create_function(move |_, ()| {
+            issued.lock().unwrap().push(EditCommand::Copy);
+            Ok(())
+        })?)?;
+        let issued = commands.clone();
+        api.set("paste", lua.create_function(move |_, pos: Option<Table>| {
+            issued.lock().unwrap().push(EditCommand::Paste(pos.map(block_pos).transpose()?));
+            Ok(())
+        })?)?;
+        let issued = commands.clone();
+        api.set("undo", lua.create_function(move |_, ()| {
+            issued.lock().unwrap().push(EditCommand::Undo);
+            Ok(())
+        })?)?;
+        let issued = commands.clone();
+        api.set("brush", lua.create_function(move |lua, (shape, args): (Option<String>, Variadic<Value>)| {
+            let brush = shape.map(|shape| Brush::from_args(lua, &shape, args)).transpose()?;
+            issued.lock().unwrap().push(EditCommand::Brush(brush));
+            Ok(())
+        })?)?;
+
+        Ok(Self { commands })
+    }
+
+    /// Takes the commands scripts issued since the last call
+    pub fn take_commands(&self) -> Vec<EditCommand> {
+        std::mem::take(&mut *self.commands.lock().unwrap())
+    }
+}
+
+/// Reads the position of a block from a Lua table
+/// of three numbers
+///
+/// # Arguments
+///
+/// * `table` - The Lua table
+fn block_pos(table: Table) -> mlua::Result<Vector3<i32>> {
+    let pos = scripting::vector_from_table(table)?;
+    Ok(Vector3::new(pos.x.floor() as i32, pos.y.floor() as i32, pos.z.floor() as i32))
+}
+
+/// SelectionFrame
+///
+/// A frame the selection could be rendered for
+pub trait SelectionFrame: Frame {
+    /// Returns the lowest and the highest block of the
+    /// outlined region, if any
+    fn selection(&self) -> Option<(Vector3<i32>, Vector3<i32>)>;
+}
+
+/// SelectionRenderer
+///
+/// The `SelectionRenderer` is the render pass outlining
+/// the selected region with the lines the targeted block
+/// is outlined with
+pub struct SelectionRenderer {
+    /// The shader program the outline is drawn with
+    shader_program: ShaderProgram,
+    /// The outline of a single block
+    outline: Model,
+}
+
+impl SelectionRenderer {
+    /// Creates a new selection renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        let shader_program = backend.create_pipeline(resources, "prop").unwrap();
+        shader_program.disable();
+
+        Self {
+            shader_program,
+            outline: Model::from_mesh(backend, &placement::outline_mesh()),
+        }
+    }
+}
+
+impl<F: SelectionFrame> RenderPass<F> for SelectionRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Transparent
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let (min, max) = match ctx.frame.selection() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        // Inflate the outline a little more than the one of
+        // the targeted block, so both are visible
+        let size = (max - min + Vector3::new(1, 1, 1)).cast::<f32>().unwrap();
+        let transform = Matrix4::from_translation(min.cast::<f32>().unwrap() - Vector3::new(0.01, 0.01, 0.01))
+            * Matrix4::from_nonuniform_scale(size.x + 0.02, size.y + 0.02, size.z + 0.02);
+        let camera = ctx.frame.camera();
+
+        self.shader_program.enable();
+        self.shader_program.set_uniform_mat4f("u_MVP", &(camera.proj_matrix() * camera.view_matrix() * transform));
+        self.shader_program.set_uniform_mat4f("u_Model", &transform);
+        self.shader_program.set_uniform_4f("u_Color", 1.0, 0.6, 0.1, 0.9);
+        self.outline.bind();
+        ctx.renderer.draw_with(self.outline.va(), self.outline.ib(), &self.shader_program, self.outline.topology());
+        self.outline.unbind();
+        self.shader_program.disable();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::terrain_generator::FlatTerrainGen;
+    use crate::world::time::SkySettings;
+    use cgmath::Vector2;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn corners_span_the_region_in_any_order() {
+        let mut selection = Selection::default();
+        assert_eq!(selection.outline(), None);
+        assert_eq!(selection.volume(), 0);
+
+        selection.second = Some(Vector3::new(2, 5, -1));
+        assert_eq!(selection.bounds(), None);
+        assert_eq!(selection.outline(), Some((Vector3::new(2, 5, -1), Vector3::new(2, 5, -1))));
+
+        selection.first = Some(Vector3::new(0, 7, -3));
+        assert_eq!(selection.bounds(), Some((Vector3::new(0, 5, -3), Vector3::new(2, 7, -1))));
+        assert_eq!(selection.volume(), 27);
+        assert_eq!(selection.positions().len(), 27);
+
+        selection.clear();
+        assert_eq!(selection.outline(), None);
+    }
+
+    #[test]
+    fn copied_blocks_are_pasted_relative_to_the_origin() {
+        let mut world = World::with_generator(FlatTerrainGen {}, SkySettings::default());
+        world.load_chunk(&Vector2::new(0, 0));
+        let start = Instant::now();
+        while !world.chunks()[0].is_generated() {
+            assert!(start.elapsed() < Duration::from_secs(10), "the chunk hasn't been generated");
+            thread::yield_now();
+        }
+
+        let selection = Selection { first: Some(Vector3::new(1, 4, 1)), second: Some(Vector3::new(2, 3, 1)) };
+        let clipboard = Clipboard::copy(&world, &selection).unwrap();
+        assert_eq!(clipboard.len(), 4);
+        let mut pasted = clipboard.paste(Vector3::new(5, 10, 5));
+        pasted.sort_by_key(|(pos, _)| (pos.y, pos.x));
+        assert_eq!(pasted, vec![
+            (Vector3::new(5, 10, 5), Material::Dirt),
+            (Vector3::new(6, 10, 5), Material::Dirt),
+            (Vector3::new(5, 11, 5), Material::Grass),
+            (Vector3::new(6, 11, 5), Material::Grass),
+        ]);
+
+        let unloaded = Selection { first: Some(Vector3::new(0, 0, 0)), second: Some(Vector3::new(20, 0, 0)) };
+        assert_eq!(Clipboard::copy(&world, &unloaded), Err(Vector3::new(16, 0, 0)));
+    }
+
+    #[test]
+    fn commands_are_queued_in_order() {
+        let engine = ScriptEngine::new();
+        let edit = WorldEdit::new(&engine).unwrap();
+        engine.exec("test", r#"
+            worldedit.pos1()
+            worldedit.pos2({ 1.5, -2, 3 })
+            worldedit.fill("stone")
+            worldedit.paste()
+            worldedit.brush("smooth", 3)
+            worldedit.brush()
+        "#).unwrap();
+        assert!(engine.exec("test", r#"worldedit.fill("cheese")"#).is_err());
+
+        assert_eq!(edit.take_commands(), vec![
+            EditCommand::Pos1(None),
+            EditCommand::Pos2(Some(Vector3::new(1, -2, 3))),
+            EditCommand::Fill(Material::Stone),
+            EditCommand::Paste(None),
+            EditCommand::Brush(Some(Brush::Smooth { radius: 3 })),
+            EditCommand::Brush(None),
+        ]);
+        assert!(edit.take_commands().is_empty());
+    }
+}
@@ -0,0 +1,338 @@
+//! A world simulated without a window or an `OpenGL`
+//! context, e.g. to test terrain, ticks, fluids and
+//! saving from integration tests
+
+use crate::entity::{Controls, Player, PLAYER_EYE_HEIGHT};
+use crate::timestep::TimeStep;
+use crate::world::World;
+use crate::world::block::Material;
+use crate::world::chunk::{Chunk, CHUNK_SIZE};
+use crate::world::light::{LightFormat, LightMap};
+use crate::world::save::{SaveDir, SaveError};
+use crate::world::terrain_generator::{SimpleTerrainGen, TerrainGen};
+use crate::world::ticks::{self, BlockBehaviors};
+use crate::world::time::SkySettings;
+use crate::world::universe::{Universe, OVERWORLD};
+use cgmath::{Vector2, Vector3};
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The time a tick advances the world by, the same
+/// as an update of the simulation thread
+pub const TICK_SECONDS: f32 = 1.0 / 60.0;
+
+/// The time chunks are waited for to be generated
+const GENERATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// WorldSim
+///
+/// A world which is advanced tick by tick instead of in real
+/// time. It's observed from the feet of a player, who moves
+/// with the controls they're given: entities are active around
+/// them and they pick up the items lying next to them.
+///
+/// ```ignore
+/// let mut sim = WorldSim::new(42);
+/// assert!(sim.generate(Vector3::new(0.0, 0.0, 0.0), 1));
+/// sim.place_block(Vector3::new(0, 60, 0), Material::Water);
+/// sim.tick(20);
+/// let items = sim.entity_count();
+/// ```
+pub struct WorldSim {
+    /// The dimensions of the world
+    universe: Universe,
+    /// The behaviors of the blocks the scheduled ticks depend on
+    behaviors: BlockBehaviors,
+    /// The position the world is observed from, which
+    /// are the feet of the player
+    observer: Vector3<f32>,
+    /// The body of the player
+    player: Player,
+    /// How the player moves each tick
+    controls: Controls,
+    /// The distance in chunks around the observer
+    /// within which entities are active
+    radius: i32,
+    /// The amount of ticks the world has been advanced by
+    ticks: u64,
+    /// The items picked up at the observer with their amount
+    picked_up: Vec<(String, u64)>,
+    /// The light maps of the chunks computed since the
+    /// blocks changed the last time
+    light_maps: HashMap<Vector2<i32>, LightMap>,
+}
+
+impl WorldSim {
+    /// Creates a world of the default terrain from a seed
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed of the terrain
+    pub fn new(seed: u32) -> Self {
+        Self::with_generator(SimpleTerrainGen::new(seed))
+    }
+
+    /// Creates a world whose terrain is made by a generator,
+    /// e.g. a flat one
+    ///
+    /// # Arguments
+    ///
+    /// * `terrain_gen` - The generator of the terrain
+    pub fn with_generator(terrain_gen: impl TerrainGen + Send + Sync + 'static) -> Self {
+        Self {
+            universe: Universe::new(OVERWORLD, World::with_generator(terrain_gen, SkySettings::default())),
+            behaviors: BlockBehaviors::default(),
+            observer: Vector3::new(0.0, 0.0, 0.0),
+            player: Player::at_eye(Vector3::new(0.0, PLAYER_EYE_HEIGHT, 0.0)),
+            controls: Controls::default(),
+            radius: 0,
+            ticks: 0,
+            picked_up: Vec::new(),
+            light_maps: HashMap::new(),
+        }
+    }
+
+    /// Sets the behaviors of the blocks, which the scripts
+    /// define in the game, e.g. the interactions of fluids
+    ///
+    /// # Arguments
+    ///
+    /// * `behaviors` - The behaviors of the blocks
+    pub fn with_behaviors(mut self, behaviors: BlockBehaviors) -> Self {
+        self.behaviors = behaviors;
+        self
+    }
+
+    /// Moves the player's feet and generates the chunks around
+    /// them. Returns whether all of them have been generated in
+    /// time.
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - The position of the player's feet
+    /// * `radius` - The amount of chunks to generate in each direction
+    pub fn generate(&mut self, center: Vector3<f32>, radius: i32) -> bool {
+        self.observer = center;
+        self.player.teleport(center + Vector3::new(0.0, PLAYER_EYE_HEIGHT, 0.0));
+        self.radius = radius;
+        self.universe.load_area(&center, radius);
+
+        let chunk_x = (center.x / CHUNK_SIZE as f32).floor() as i32;
+        let chunk_z = (center.z / CHUNK_SIZE as f32).floor() as i32;
+        let start = Instant::now();
+        for x in -radius..=radius {
+            for z in -radius..=radius {
+                let loc = Vector2::new(chunk_x + x, chunk_z + z);
+                while matches!(self.universe.chunk(&loc), Some(chunk) if !chunk.is_generated()) {
+                    if start.elapsed() > GENERATION_TIMEOUT {
+                        return false;
+                    }
+                    thread::yield_now();
+                }
+            }
+        }
+        self.light_maps.clear();
+        true
+    }
+
+    /// Sets how the player moves in the following ticks
+    ///
+    /// # Arguments
+    ///
+    /// * `controls` - How the player moves
+    pub fn set_controls(&mut self, controls: Controls) {
+        self.controls = controls;
+    }
+
+    /// Advances the world tick by tick: the time of day, the
+    /// scheduled ticks, e.g. of fluids and fire, the player and
+    /// the entities around them
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The amount of ticks
+    pub fn tick(&mut self, count: u32) {
+        for _ in 0..count {
+            ticks::run_scheduled(&mut self.universe, &self.behaviors);
+            self.universe.time_mut().advance(TimeStep(TICK_SECONDS));
+
+            let universe = &self.universe;
+            self.player.update(TICK_SECONDS, self.controls, |pos| universe.block(pos));
+            self.observer = *self.player.pos();
+
+            let (observer, radius) = (self.observer, self.radius);
+            let picked_up = self.universe.update_items(TICK_SECONDS, observer, radius, usize::MAX);
+            self.picked_up.extend(picked_up);
+            self.universe.update_tnt(TICK_SECONDS, |_| false);
+            self.universe.update_mobs(TICK_SECONDS, observer, "", radius);
+            self.universe.update_vehicles(TICK_SECONDS, observer, radius, None);
+
+            if !self.universe.take_changes().is_empty() {
+                self.light_maps.clear();
+            }
+            self.ticks += 1;
+        }
+    }
+
+    /// Returns the amount of ticks the world has been advanced by
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Places a block like the player does, so the edit could
+    /// be undone. Returns whether the block has been changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    /// * `material` - The material of the block
+    pub fn place_block(&mut self, pos: Vector3<i32>, material: Material) -> bool {
+        let changed = self.universe.edit_block(pos, material);
+        self.universe.take_changes();
+        self.light_maps.clear();
+        changed
+    }
+
+    /// Breaks a block like the player does and returns its
+    /// material, or `None` if there is no block to break
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    pub fn break_block(&mut self, pos: Vector3<i32>) -> Option<Material> {
+        let material = self.universe.block(pos).filter(|&material| material != Material::Air)?;
+        if self.place_block(pos, Material::Air) {
+            Some(material)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the material of the block at a position,
+    /// or `None` if its chunk isn't loaded
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    pub fn block(&self, pos: Vector3<i32>) -> Option<Material> {
+        self.universe.block(pos)
+    }
+
+    /// Returns the state of the block at a position,
+    /// or `None` if its chunk isn't loaded
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    pub fn block_state(&self, pos: Vector3<i32>) -> Option<u8> {
+        self.universe.block_state(pos)
+    }
+
+    /// Returns the level of the light of glowing blocks at a
+    /// position, or `None` if its chunk isn't loaded
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    pub fn block_light(&mut self, pos: Vector3<i32>) -> Option<u8> {
+        let (loc, local) = World::split_pos(pos);
+        self.light_map(loc).map(|light_map| light_map.level(local))
+    }
+
+    /// Returns the level of the light of the sky at a position,
+    /// regardless of the time of day, or `None` if its chunk
+    /// isn't loaded
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block in world coordinates
+    pub fn sky_light(&mut self, pos: Vector3<i32>) -> Option<u8> {
+        let (loc, local) = World::split_pos(pos);
+        self.light_map(loc).map(|light_map| light_map.sky_level(local))
+    }
+
+    /// Returns the light map of a chunk, which is computed
+    /// the way the chunk renderer computes it
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the chunk
+    fn light_map(&mut self, loc: Vector2<i32>) -> Option<&LightMap> {
+        if !self.light_maps.contains_key(&loc) {
+            let chunk = self.universe.chunk(&loc)?;
+            let neighbours: Vec<Chunk> = self.universe.chunks().iter()
+                .filter(|neighbour| neighbour.is_generated() && neighbour.loc() != &loc)
+                .cloned()
+                .collect();
+            let light_map = LightMap::compute(chunk, &neighbours, LightFormat::Rgb);
+            self.light_maps.insert(loc, light_map);
+        }
+        self.light_maps.get(&loc)
+    }
+
+    /// Returns the items picked up by the player with their amount
+    pub fn picked_up(&self) -> &[(String, u64)] {
+        &self.picked_up
+    }
+
+    /// Returns the position of the player's feet
+    pub fn player_pos(&self) -> Vector3<f32> {
+        *self.player.pos()
+    }
+
+    /// Returns the velocity of the player in blocks per second
+    pub fn player_velocity(&self) -> Vector3<f32> {
+        *self.player.velocity()
+    }
+
+    /// Returns the amount of entities in the world, see
+    /// `entity_positions`
+    pub fn entity_count(&self) -> usize {
+        self.entity_positions().len()
+    }
+
+    /// Returns the positions of the entities in the world, which
+    /// are the items, mobs, vehicles, primed TNT and the entities
+    /// of scripts, in that order
+    pub fn entity_positions(&self) -> Vec<Vector3<f32>> {
+        let world: &World = &self.universe;
+        world.items().items().iter().map(|item| *item.pos())
+            .chain(world.mobs().mobs().iter().map(|mob| *mob.pos()))
+            .chain(world.vehicles().vehicles().iter().map(|vehicle| *vehicle.pos()))
+            .chain(world.primed_tnt().primed().iter().map(|tnt| *tnt.pos()))
+            .chain(world.scripted_entities().entities().iter().map(|entity| *entity.pos()))
+            .collect()
+    }
+
+    /// Returns the world, e.g. to look at its entities
+    pub fn world(&self) -> &World {
+        &self.universe
+    }
+
+    /// Returns the world mutably, e.g. to spawn entities
+    pub fn world_mut(&mut self) -> &mut World {
+        self.light_maps.clear();
+        &mut self.universe
+    }
+
+    /// Saves the regions which have been edited since the
+    /// world has been saved the last time
+    ///
+    /// # Arguments
+    ///
+    /// * `save_dir` - The directory the world is saved in
+    pub fn save(&mut self, save_dir: &SaveDir) -> Result<(), SaveError> {
+        self.universe.save(save_dir)
+    }
+
+    /// Loads the saved regions, which are restored once
+    /// their chunks are generated, see `generate`
+    ///
+    /// # Arguments
+    ///
+    /// * `save_dir` - The directory the world is saved in
+    pub fn load(&mut self, save_dir: &SaveDir) -> Result<(), SaveError> {
+        self.universe.load(save_dir)
+    }
+}
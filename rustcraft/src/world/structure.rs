@@ -0,0 +1,793 @@
+//! Types to generate structures spanning several chunks,
+//! e.g. villages, which are assembled from pieces
+//! declared by the resources
+
+use crate::resources::Resources;
+use crate::world::biome::Biome;
+use crate::world::block::Material;
+use crate::world::block_entity::{BlockEntity, DEFAULT_MOB};
+use crate::world::chunk::{Chunk, CHUNK_AREA, CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::world::terrain_generator::{GenFeature, TerrainGen};
+use crate::world::ticks::TickRng;
+use cgmath::{Vector2, Vector3};
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+/// The resource directory declaring the structures
+const STRUCTURE_DIR: &str = "structures";
+/// The distance in chunks pieces could be placed from the
+/// start of their structure. It bounds the cells which are
+/// checked for structures reaching into a chunk.
+const MAX_REACH: i32 = 4;
+/// The largest amount of pieces of a single structure
+const MAX_PIECES: usize = 48;
+/// The symbol of the layers of a piece which leaves
+/// the terrain as it is
+const KEEP_SYMBOL: char = ' ';
+
+/// StructureError
+///
+/// An error which occurred while a structure was
+/// read from its declaration
+#[derive(Debug)]
+pub enum StructureError {
+    Deserialize(toml::de::Error),
+    /// The spacing isn't larger than the separation
+    InvalidSpacing,
+    /// A piece which isn't declared is referred to
+    UnknownPiece(String),
+    /// The palette maps a symbol to an unknown block
+    UnknownBlock(String),
+    /// A key of the palette isn't a single character
+    InvalidSymbol(String),
+    /// A layer uses a symbol the palette doesn't map
+    UnknownSymbol(char),
+}
+
+impl From<toml::de::Error> for StructureError {
+    fn from(error: toml::de::Error) -> Self {
+        StructureError::Deserialize(error)
+    }
+}
+
+/// Facing
+///
+/// The horizontal direction a connector of a piece
+/// faces, `North` being the negative z axis
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Facing {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Facing {
+    /// Returns the offset to the adjacent block
+    /// in this direction
+    pub fn offset(&self) -> Vector3<i32> {
+        match self {
+            Facing::North => Vector3::new(0, 0, -1),
+            Facing::East => Vector3::new(1, 0, 0),
+            Facing::South => Vector3::new(0, 0, 1),
+            Facing::West => Vector3::new(-1, 0, 0),
+        }
+    }
+
+    /// Returns the direction of an offset to an adjacent
+    /// block, or `None` if it isn't horizontal
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset to the adjacent block
+    pub fn from_offset(offset: Vector3<i32>) -> Option<Self> {
+        [Facing::North, Facing::East, Facing::South, Facing::West].iter()
+            .copied()
+            .find(|facing| facing.offset() == offset)
+    }
+
+    /// Returns the opposite direction
+    pub fn opposite(&self) -> Self {
+        match self {
+            Facing::North => Facing::South,
+            Facing::East => Facing::West,
+            Facing::South => Facing::North,
+            Facing::West => Facing::East,
+        }
+    }
+}
+
+/// The declaration of a connector of a piece
+#[derive(Deserialize)]
+struct ConnectorDef {
+    /// The position of the connector within the piece
+    pos: [i32; 3],
+    /// The direction further pieces are attached in
+    facing: Facing,
+    /// The names of the pieces which could be attached
+    pool: Vec<String>,
+}
+
+/// The declaration of a piece
+#[derive(Deserialize)]
+struct PieceDef {
+    /// The name of the piece
+    name: String,
+    /// The names of the blocks by their symbol
+    #[serde(default)]
+    palette: HashMap<String, String>,
+    /// The layers from the bottom up, each with its
+    /// rows from north to south
+    layers: Vec<Vec<String>>,
+    /// The connectors further pieces are attached to
+    #[serde(default)]
+    connectors: Vec<ConnectorDef>,
+}
+
+/// The declaration of a structure
+#[derive(Deserialize)]
+struct StructureFileDef {
+    /// The name of the structure
+    name: String,
+    /// The size of the cells of the placement grid in chunks
+    spacing: i32,
+    /// The chunks at the edges of each cell which are
+    /// never the start of a structure
+    separation: i32,
+    /// A number distinguishing the placements of structures
+    /// with the same spacing
+    #[serde(default)]
+    salt: u64,
+    /// The name of the piece the structure starts with
+    start: String,
+    /// The amount of pieces between the start and the outmost pieces
+    #[serde(default = "default_depth")]
+    depth: u32,
+    /// The height of the start piece relative to the surface,
+    /// negative for structures buried underground
+    #[serde(default)]
+    elevation: i32,
+    /// The loot table of the chests of the structure,
+    /// which defaults to the name of the structure
+    loot_table: Option<String>,
+    /// The kind of the mobs spawners of the structure spawn
+    mob: Option<String>,
+    /// The pieces of the structure
+    pieces: Vec<PieceDef>,
+}
+
+/// Returns the depth of structures which don't declare it
+fn default_depth() -> u32 {
+    4
+}
+
+/// A connector of a piece
+struct Connector {
+    /// The position of the connector within the piece
+    pos: Vector3<i32>,
+    /// The direction further pieces are attached in
+    facing: Facing,
+    /// The indices of the pieces which could be attached
+    pool: Vec<usize>,
+}
+
+/// A piece of a structure
+struct Piece {
+    /// The width, height and depth of the piece
+    size: Vector3<i32>,
+    /// The blocks of the piece by their position within it
+    blocks: Vec<(Vector3<i32>, Material)>,
+    /// The connectors further pieces are attached to
+    connectors: Vec<Connector>,
+}
+
+/// PlacedPiece
+///
+/// A piece of an assembled structure
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PlacedPiece {
+    /// The index of the piece within its structure
+    pub piece: usize,
+    /// The north-western bottom corner of the piece
+    /// in world coordinates
+    pub origin: Vector3<i32>,
+}
+
+/// StructureDef
+///
+/// A structure declared by the resources, e.g.
+///
+/// ```toml
+/// name = "village"
+/// spacing = 10
+/// separation = 3
+/// start = "well"
+///
+/// [[pieces]]
+/// name = "well"
+/// palette = { "#" = "stone", "~" = "water" }
+/// layers = [["###", "#~#", "###"]]
+///
+/// [[pieces.connectors]]
+/// pos = [1, 0, 2]
+/// facing = "south"
+/// pool = ["street"]
+/// ```
+///
+/// At most one structure starts in each cell of a grid of
+/// `spacing` chunks. The structure is assembled from its
+/// start piece by attaching pieces of the pool of each
+/// connector, whose own connector faces the opposite way,
+/// until `depth` pieces are chained. Pieces aren't rotated,
+/// so pools need pieces facing each way they are used.
+pub struct StructureDef {
+    /// The name of the structure
+    name: String,
+    /// The size of the cells of the placement grid in chunks
+    spacing: i32,
+    /// The chunks at the edges of each cell which are
+    /// never the start of a structure
+    separation: i32,
+    /// A number distinguishing the placements of structures
+    salt: u64,
+    /// The index of the piece the structure starts with
+    start: usize,
+    /// The amount of pieces between the start and the outmost pieces
+    depth: u32,
+    /// The height of the start piece relative to the surface
+    elevation: i32,
+    /// The loot table of the chests of the structure
+    loot_table: String,
+    /// The kind of the mobs spawners of the structure spawn
+    mob: String,
+    /// The pieces of the structure
+    pieces: Vec<Piece>,
+}
+
+impl StructureDef {
+    /// Loads all structures declared by the resources.
+    /// Structures which can't be loaded are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - A resource instance
+    pub fn load_all(resources: &Resources) -> Vec<Self> {
+        let names = match resources.list_files(STRUCTURE_DIR, "toml") {
+            Ok(names) => names,
+            Err(error) => {
+                log::warn!("Failed to list {}: {:?}", STRUCTURE_DIR, error);
+                return Vec::new();
+            }
+        };
+
+        names.iter()
+            .filter_map(|name| {
+                let structure = resources.load_string(name)
+                    .map_err(|error| format!("{:?}", error))
+                    .and_then(|source| Self::from_toml(&source).map_err(|error| format!("{:?}", error)));
+                match structure {
+                    Ok(structure) => Some(structure),
+                    Err(error) => {
+                        log::warn!("Skipping structure {}: {}", name, error);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Parses a structure from its `TOML` declaration
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The declaration of the structure
+    pub fn from_toml(source: &str) -> Result<Self, StructureError> {
+        let def: StructureFileDef = toml::from_str(source)?;
+        if def.separation < 0 || def.spacing <= def.separation {
+            return Err(StructureError::InvalidSpacing);
+        }
+
+        let index_of = |name: &str| {
+            def.pieces.iter().position(|piece| piece.name == name)
+                .ok_or_else(|| StructureError::UnknownPiece(name.to_string()))
+        };
+
+        let mut pieces = Vec::with_capacity(def.pieces.len());
+        for piece in def.pieces.iter() {
+            let mut palette = HashMap::new();
+            for (symbol, block) in piece.palette.iter() {
+                let mut chars = symbol.chars();
+                let symbol = match (chars.next(), chars.next()) {
+                    (Some(symbol), None) => symbol,
+                    _ => return Err(StructureError::InvalidSymbol(symbol.clone())),
+                };
+                let material = Material::from_name(block)
+                    .ok_or_else(|| StructureError::UnknownBlock(block.clone()))?;
+                palette.insert(symbol, material);
+            }
+
+            let mut size = Vector3::new(0, piece.layers.len() as i32, 0);
+            let mut blocks = Vec::new();
+            for (y, layer) in piece.layers.iter().enumerate() {
+                size.z = size.z.max(layer.len() as i32);
+                for (z, row) in layer.iter().enumerate() {
+                    size.x = size.x.max(row.chars().count() as i32);
+                    for (x, symbol) in row.chars().enumerate() {
+                        if symbol == KEEP_SYMBOL {
+                            continue;
+                        }
+                        let material = *palette.get(&symbol).ok_or(StructureError::UnknownSymbol(symbol))?;
+                        blocks.push((Vector3::new(x as i32, y as i32, z as i32), material));
+                    }
+                }
+            }
+
+            let mut connectors = Vec::with_capacity(piece.connectors.len());
+            for connector in piece.connectors.iter() {
+                connectors.push(Connector {
+                    pos: Vector3::from(connector.pos),
+                    facing: connector.facing,
+                    pool: connector.pool.iter().map(|name| index_of(name)).collect::<Result<_, _>>()?,
+                });
+            }
+
+            pieces.push(Piece {
+                size,
+                blocks,
+                connectors,
+            });
+        }
+
+        Ok(Self {
+            name: def.name.clone(),
+            spacing: def.spacing,
+            separation: def.separation,
+            salt: def.salt,
+            start: index_of(&def.start)?,
+            depth: def.depth,
+            elevation: def.elevation,
+            loot_table: def.loot_table.clone().unwrap_or_else(|| def.name.clone()),
+            mob: def.mob.clone().unwrap_or_else(|| DEFAULT_MOB.to_string()),
+            pieces,
+        })
+    }
+
+    /// Returns the name of the structure
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the block entity a block of the structure
+    /// is placed with, e.g. the loot table of a chest
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The material of the block
+    pub fn block_entity(&self, material: Material) -> Option<BlockEntity> {
+        match material {
+            Material::Chest => Some(BlockEntity::chest(&self.loot_table)),
+            Material::Spawner => Some(BlockEntity::spawner(&self.mob)),
+            _ => None,
+        }
+    }
+
+    /// Returns the chunk the structure of a cell of the
+    /// placement grid starts in
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed of the world
+    /// * `cell` - The cell of the placement grid
+    pub fn start_chunk(&self, seed: u32, cell: Vector2<i32>) -> Vector2<i32> {
+        let mut rng = self.rng(seed, cell);
+        let range = (self.spacing - self.separation) as usize;
+        Vector2::new(
+            cell.x * self.spacing + rng.below(range) as i32,
+            cell.y * self.spacing + rng.below(range) as i32,
+        )
+    }
+
+    /// Assembles the structure of a cell of the placement grid
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed of the world
+    /// * `cell` - The cell of the placement grid
+    /// * `start` - The position of the bottom center of the start piece
+    pub fn assemble(&self, seed: u32, cell: Vector2<i32>, start: Vector3<i32>) -> Vec<PlacedPiece> {
+        let mut rng = self.rng(seed, cell);
+        let reach = MAX_REACH * CHUNK_SIZE as i32;
+
+        let start_piece = &self.pieces[self.start];
+        let mut placed = vec![PlacedPiece {
+            piece: self.start,
+            origin: start - Vector3::new(start_piece.size.x / 2, 0, start_piece.size.z / 2),
+        }];
+
+        // The open connectors by the index of their placed
+        // piece and their depth, attached breadth first
+        let mut open: VecDeque<(usize, usize, u32)> = (0..start_piece.connectors.len())
+            .map(|connector| (0, connector, 1))
+            .collect();
+
+        while let Some((parent, connector, depth)) = open.pop_front() {
+            if depth > self.depth || placed.len() >= MAX_PIECES {
+                continue;
+            }
+            let parent = placed[parent];
+            let connector = &self.pieces[parent.piece].connectors[connector];
+            let target = parent.origin + connector.pos + connector.facing.offset();
+
+            // All connectors of the pool which face back, tried
+            // in a random order until one of them fits
+            let mut candidates: Vec<(usize, usize)> = connector.pool.iter()
+                .flat_map(|&piece| {
+                    self.pieces[piece].connectors.iter().enumerate()
+                        .filter(|(_, other)| other.facing == connector.facing.opposite())
+                        .map(move |(index, _)| (piece, index))
+                })
+                .collect();
+            while !candidates.is_empty() {
+                let (piece, index) = candidates.swap_remove(rng.below(candidates.len()));
+                let origin = target - self.pieces[piece].connectors[index].pos;
+                let size = self.pieces[piece].size;
+
+                let within_reach = (origin.x - start.x).abs().max((origin.x + size.x - start.x).abs()) <= reach
+                    && (origin.z - start.z).abs().max((origin.z + size.z - start.z).abs()) <= reach;
+                let overlaps = placed.iter()
+                    .any(|other| overlap(origin, size, other.origin, self.pieces[other.piece].size));
+                if !within_reach || overlaps {
+                    continue;
+                }
+
+                placed.push(PlacedPiece {
+                    piece,
+                    origin,
+                });
+                let placed_index = placed.len() - 1;
+                for other in 0..self.pieces[piece].connectors.len() {
+                    if other != index {
+                        open.push_back((placed_index, other, depth + 1));
+                    }
+                }
+                break;
+            }
+        }
+
+        placed
+    }
+
+    /// Returns the lowest and the highest block spanned
+    /// by the pieces of an assembled structure
+    ///
+    /// # Arguments
+    ///
+    /// * `pieces` - The pieces of the structure
+    pub fn bounds(&self, pieces: &[PlacedPiece]) -> (Vector3<i32>, Vector3<i32>) {
+        let mut min = Vector3::new(i32::MAX, i32::MAX, i32::MAX);
+        let mut max = Vector3::new(i32::MIN, i32::MIN, i32::MIN);
+        for placed in pieces {
+            let upper = placed.origin + self.pieces[placed.piece].size - Vector3::new(1, 1, 1);
+            min = Vector3::new(min.x.min(placed.origin.x), min.y.min(placed.origin.y), min.z.min(placed.origin.z));
+            max = Vector3::new(max.x.max(upper.x), max.y.max(upper.y), max.z.max(upper.z));
+        }
+        (min, max)
+    }
+
+    /// Returns the generator of the random choices of the
+    /// structure of a cell of the placement grid
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed of the world
+    /// * `cell` - The cell of the placement grid
+    fn rng(&self, seed: u32, cell: Vector2<i32>) -> TickRng {
+        let hash = (cell.x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ (cell.y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+            ^ self.salt.wrapping_mul(0x1656_67B1_9E37_79F9)
+            ^ ((seed as u64) << 32);
+        let mut rng = TickRng::new(hash);
+        // The first numbers of seeds which are alike are alike too
+        for _ in 0..4 {
+            rng.below(2);
+        }
+        rng
+    }
+}
+
+/// Returns whether two boxes overlap
+///
+/// # Arguments
+///
+/// * `a` - The lower corner of the first box
+/// * `a_size` - The size of the first box
+/// * `b` - The lower corner of the second box
+/// * `b_size` - The size of the second box
+fn overlap(a: Vector3<i32>, a_size: Vector3<i32>, b: Vector3<i32>, b_size: Vector3<i32>) -> bool {
+    a.x < b.x + b_size.x && b.x < a.x + a_size.x
+        && a.y < b.y + b_size.y && b.y < a.y + a_size.y
+        && a.z < b.z + b_size.z && b.z < a.z + a_size.z
+}
+
+/// StructureGen
+///
+/// A terrain generator placing structures on top of
+/// the terrain of another generator. Each chunk places
+/// the blocks of all structures reaching into it, so the
+/// chunks of a structure could be generated in any order.
+pub struct StructureGen<T: TerrainGen> {
+    /// The generator of the terrain below the structures
+    terrain: T,
+    /// The structures which are placed
+    structures: Arc<Vec<StructureDef>>,
+    /// The seed of the placements of the structures
+    seed: u32,
+}
+
+impl<T: TerrainGen> StructureGen<T> {
+    /// Creates a new generator placing structures
+    ///
+    /// # Arguments
+    ///
+    /// * `terrain` - The generator of the terrain below the structures
+    /// * `structures` - The structures which are placed
+    /// * `seed` - The seed of the placements of the structures
+    pub fn new(terrain: T, structures: Arc<Vec<StructureDef>>, seed: u32) -> Self {
+        Self {
+            terrain,
+            structures,
+            seed,
+        }
+    }
+
+    /// Returns the structures of the cells of a structure's
+    /// grid which could reach into a chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `structure` - The structure
+    /// * `loc` - The location of the chunk
+    fn structures_near(&self, structure: &StructureDef, loc: &Vector2<i32>) -> Vec<Vec<PlacedPiece>> {
+        let cell_of = |chunk: i32| chunk.div_euclid(structure.spacing);
+        let mut structures = Vec::new();
+
+        for cell_z in cell_of(loc.y - MAX_REACH)..=cell_of(loc.y + MAX_REACH) {
+            for cell_x in cell_of(loc.x - MAX_REACH)..=cell_of(loc.x + MAX_REACH) {
+                let cell = Vector2::new(cell_x, cell_z);
+                let start_chunk = structure.start_chunk(self.seed, cell);
+                if (start_chunk.x - loc.x).abs() > MAX_REACH || (start_chunk.y - loc.y).abs() > MAX_REACH {
+                    continue;
+                }
+                structures.extend(self.place(structure, cell, start_chunk));
+            }
+        }
+
+        structures
+    }
+
+    /// Assembles the structure of a cell of a structure's grid
+    /// at the surface of its start chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `structure` - The structure
+    /// * `cell` - The cell of the placement grid
+    /// * `start_chunk` - The chunk the structure starts in
+    fn place(&self, structure: &StructureDef, cell: Vector2<i32>, start_chunk: Vector2<i32>) -> Option<Vec<PlacedPiece>> {
+        // Structures stand on the surface at the center of
+        // their start chunk, or are buried below it, and
+        // not on empty terrain
+        let center = CHUNK_SIZE / 2;
+        let surface = self.terrain.gen_heightmap(&start_chunk)[center * CHUNK_SIZE + center];
+        let height = surface + structure.elevation;
+        if surface < 0 || height < 1 {
+            return None;
+        }
+        let start = Vector3::new(
+            start_chunk.x * CHUNK_SIZE as i32 + center as i32,
+            height,
+            start_chunk.y * CHUNK_SIZE as i32 + center as i32,
+        );
+        Some(structure.assemble(self.seed, cell, start))
+    }
+}
+
+impl<T: TerrainGen> TerrainGen for StructureGen<T> {
+    fn gen_heightmap(&self, loc: &Vector2<i32>) -> [i32; CHUNK_AREA] {
+        self.terrain.gen_heightmap(loc)
+    }
+
+    fn biome(&self, x: i32, z: i32) -> Option<Biome> {
+        self.terrain.biome(x, z)
+    }
+
+    fn features(&self, loc: &Vector2<i32>) -> Vec<GenFeature> {
+        let mut features = self.terrain.features(loc);
+        for structure in self.structures.iter() {
+            let cell = Vector2::new(loc.x.div_euclid(structure.spacing), loc.y.div_euclid(structure.spacing));
+            if structure.start_chunk(self.seed, cell) != *loc {
+                continue;
+            }
+            if let Some(pieces) = self.place(structure, cell, *loc) {
+                let (min, max) = structure.bounds(&pieces);
+                features.push(GenFeature::Structure { name: structure.name.clone(), min, max });
+            }
+        }
+        features
+    }
+
+    fn gen_smooth_terrain(&self, chunk: &Chunk, height_map: &[i32; CHUNK_AREA]) {
+        self.terrain.gen_smooth_terrain(chunk, height_map);
+
+        let loc = chunk.loc();
+        let chunk_origin = Vector3::new(loc.x * CHUNK_SIZE as i32, 0, loc.y * CHUNK_SIZE as i32);
+        for structure in self.structures.iter() {
+            for pieces in self.structures_near(structure, loc) {
+                for placed in pieces {
+                    for (pos, material) in structure.pieces[placed.piece].blocks.iter() {
+                        let pos = placed.origin + pos - chunk_origin;
+                        let inside = (0..CHUNK_SIZE as i32).contains(&pos.x)
+                            && (0..CHUNK_HEIGHT as i32).contains(&pos.y)
+                            && (0..CHUNK_SIZE as i32).contains(&pos.z);
+                        if inside {
+                            let pos = Vector3::new(pos.x as i16, pos.y as i16, pos.z as i16);
+                            chunk.set_block(pos, *material);
+                            if let Some(entity) = structure.block_entity(*material) {
+                                chunk.set_block_entity(pos, entity);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::terrain_generator::FlatTerrainGen;
+
+    const VILLAGE: &str = r#"
+        name = "village"
+        spacing = 4
+        separation = 1
+        start = "well"
+        depth = 2
+
+        [[pieces]]
+        name = "well"
+        palette = { "s" = "stone", "~" = "water" }
+        layers = [["sss", "s~s", "sss"]]
+        [[pieces.connectors]]
+        pos = [1, 0, 2]
+        facing = "south"
+        pool = ["street"]
+        [[pieces.connectors]]
+        pos = [1, 0, 0]
+        facing = "north"
+        pool = ["street"]
+
+        [[pieces]]
+        name = "street"
+        palette = { "d" = "dirt" }
+        layers = [["d", "d", "d", "d"]]
+        [[pieces.connectors]]
+        pos = [0, 0, 0]
+        facing = "north"
+        pool = ["street"]
+        [[pieces.connectors]]
+        pos = [0, 0, 3]
+        facing = "south"
+        pool = ["street"]
+    "#;
+
+    #[test]
+    fn structures_are_read_from_toml() {
+        let village = StructureDef::from_toml(VILLAGE).unwrap();
+        assert_eq!(village.name(), "village");
+        assert_eq!(village.pieces.len(), 2);
+        assert_eq!(village.pieces[0].size, Vector3::new(3, 1, 3));
+        assert_eq!(village.pieces[0].blocks.len(), 9);
+        assert_eq!(village.pieces[1].connectors[1].pool, vec![1]);
+
+        assert!(matches!(StructureDef::from_toml(&VILLAGE.replace("separation = 1", "separation = 4")),
+            Err(StructureError::InvalidSpacing)));
+        assert!(matches!(StructureDef::from_toml(&VILLAGE.replace("start = \"well\"", "start = \"church\"")),
+            Err(StructureError::UnknownPiece(_))));
+        assert!(matches!(StructureDef::from_toml(&VILLAGE.replace("\"dirt\"", "\"cobblestone\"")),
+            Err(StructureError::UnknownBlock(_))));
+        assert!(matches!(StructureDef::from_toml(&VILLAGE.replace("\"d\", \"d\"", "\"x\", \"d\"")),
+            Err(StructureError::UnknownSymbol('x'))));
+    }
+
+    #[test]
+    fn resources_declare_valid_structures() {
+        let village = StructureDef::from_toml(include_str!("../../res/structures/village.toml")).unwrap();
+        let ruins = StructureDef::from_toml(include_str!("../../res/structures/ruins.toml")).unwrap();
+        let dungeon = StructureDef::from_toml(include_str!("../../res/structures/dungeon.toml")).unwrap();
+        assert!(village.assemble(0, Vector2::new(0, 0), Vector3::new(0, 8, 0)).len() > 5);
+        assert_eq!(ruins.assemble(0, Vector2::new(0, 0), Vector3::new(0, 8, 0)).len(), 3);
+        assert_eq!(dungeon.assemble(0, Vector2::new(0, 0), Vector3::new(0, 8, 0)).len(), 2);
+        assert_eq!(dungeon.block_entity(Material::Chest), Some(BlockEntity::chest("dungeon")));
+    }
+
+    #[test]
+    fn pieces_are_attached_at_their_connectors() {
+        let village = StructureDef::from_toml(VILLAGE).unwrap();
+        let pieces = village.assemble(0, Vector2::new(0, 0), Vector3::new(8, 4, 8));
+
+        // The well with two streets in each direction
+        assert_eq!(pieces.len(), 5);
+        assert_eq!(pieces[0], PlacedPiece { piece: 0, origin: Vector3::new(7, 4, 7) });
+        assert!(pieces.contains(&PlacedPiece { piece: 1, origin: Vector3::new(8, 4, 10) }));
+        assert!(pieces.contains(&PlacedPiece { piece: 1, origin: Vector3::new(8, 4, 14) }));
+        assert!(pieces.contains(&PlacedPiece { piece: 1, origin: Vector3::new(8, 4, 3) }));
+        for (i, a) in pieces.iter().enumerate() {
+            for b in pieces.iter().skip(i + 1) {
+                assert!(!overlap(a.origin, village.pieces[a.piece].size, b.origin, village.pieces[b.piece].size));
+            }
+        }
+    }
+
+    #[test]
+    fn buried_structures_are_placed_with_block_entities() {
+        let dungeon = VILLAGE.replace("depth = 2", "depth = 2\nelevation = -2\nmob = \"skeleton\"")
+            .replace("\"~\" = \"water\"", "\"~\" = \"spawner\"");
+        let dungeon = StructureDef::from_toml(&dungeon).unwrap();
+        let start = dungeon.start_chunk(7, Vector2::new(0, 0));
+
+        let generator = StructureGen::new(FlatTerrainGen::default(), Arc::new(vec![dungeon]), 7);
+        let chunk = Chunk::new(start);
+        generator.gen_smooth_terrain(&chunk, &generator.gen_heightmap(&start));
+        let center = CHUNK_SIZE as i16 / 2;
+        let spawner = Vector3::new(center, 2, center);
+        assert_eq!(chunk.block(spawner), Some(Material::Spawner));
+        assert_eq!(chunk.block_entity(spawner), Some(BlockEntity::spawner("skeleton")));
+
+        // Replaced blocks lose their block entity
+        chunk.set_block(spawner, Material::Air);
+        assert_eq!(chunk.block_entity(spawner), None);
+    }
+
+    #[test]
+    fn structures_report_their_bounds_in_their_start_chunk() {
+        let village = StructureDef::from_toml(VILLAGE).unwrap();
+        let start = village.start_chunk(7, Vector2::new(0, 0));
+        let generator = StructureGen::new(FlatTerrainGen::default(), Arc::new(vec![village]), 7);
+
+        let center = Vector3::new(start.x * CHUNK_SIZE as i32 + 8, 4, start.y * CHUNK_SIZE as i32 + 8);
+        assert_eq!(generator.features(&start), vec![GenFeature::Structure {
+            name: "village".to_string(),
+            min: center + Vector3::new(-1, 0, -9),
+            max: center + Vector3::new(1, 0, 9),
+        }]);
+        assert!(generator.features(&(start + Vector2::new(0, 1))).is_empty());
+    }
+
+    #[test]
+    fn structures_are_placed_across_chunks() {
+        let village = StructureDef::from_toml(VILLAGE).unwrap();
+        let start = village.start_chunk(7, Vector2::new(0, 0));
+        assert!((0..3).contains(&start.x) && (0..3).contains(&start.y));
+        assert_eq!(start, village.start_chunk(7, Vector2::new(0, 0)));
+
+        let generator = StructureGen::new(FlatTerrainGen::default(), Arc::new(vec![village]), 7);
+        let count_blocks = |loc: Vector2<i32>, material: Material| {
+            let chunk = Chunk::new(loc);
+            generator.gen_smooth_terrain(&chunk, &generator.gen_heightmap(&loc));
+            (0..CHUNK_SIZE as i16)
+                .flat_map(|x| (0..CHUNK_SIZE as i16).map(move |z| Vector3::new(x, 4, z)))
+                .filter(|pos| chunk.block(*pos) == Some(material))
+                .count()
+        };
+
+        // The well is placed at the center of the start chunk,
+        // while the streets reach into the chunks around it
+        assert_eq!(count_blocks(start, Material::Water), 1);
+        let streets: usize = (-1..=1).map(|z| count_blocks(start + Vector2::new(0, z), Material::Dirt)).sum();
+        assert_eq!(streets, 16);
+    }
+}
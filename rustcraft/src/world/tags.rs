@@ -0,0 +1,182 @@
+//! Types to group blocks and items under a tag, e.g. `#stone`,
+//! so that definitions could refer to all of them at once
+
+use crate::scripting::ScriptEngine;
+use crate::world::block::Material;
+use mlua::Table;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// The prefix telling tags apart from the names
+/// of blocks and items
+pub const TAG_PREFIX: char = '#';
+
+/// Tags
+///
+/// The names of the blocks and items by their tag, which
+/// scripts add through the `tags` API, e.g.
+///
+/// ```lua
+/// tags.add("#soil", { "dirt", "grass", "farmland" })
+/// tags.add("#natural", { "#soil", "stone", "sand" })
+///
+/// tags.has("#natural", "grass") -- true
+/// tags.list("#soil")            -- { "dirt", "farmland", "grass" }
+/// ```
+///
+/// Tags could contain other tags, whose names they include.
+/// Tool speeds, drop tables and plants accept a tag wherever
+/// they accept a block. They are resolved as they're defined,
+/// so the tags need to be added by a script loaded before,
+/// since scripts are loaded in the order of their names.
+#[derive(Clone, Default)]
+pub struct Tags {
+    /// The names by their tag without the prefix, which
+    /// are shared with the Lua API
+    tags: Arc<Mutex<HashMap<String, BTreeSet<String>>>>,
+}
+
+impl Tags {
+    /// Creates a new tag registry and registers its
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let registry = Self::default();
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let tags = registry.tags.clone();
+        api.set("add", lua.create_function(move |_, (tag, names): (String, Table)| {
+            let names = names.sequence_values::<String>().collect::<mlua::Result<Vec<_>>>()?;
+            tags.lock().unwrap().entry(strip_prefix(&tag).to_string()).or_default().extend(names);
+            Ok(())
+        })?)?;
+        let tags = registry.clone();
+        api.set("has", lua.create_function(move |_, (tag, name): (String, String)| {
+            Ok(tags.contains(&tag, &name))
+        })?)?;
+        let tags = registry.clone();
+        api.set("list", lua.create_function(move |_, tag: String| {
+            Ok(tags.names(&tag).map(|names| names.into_iter().collect::<Vec<_>>()))
+        })?)?;
+        lua.globals().set("tags", api)?;
+
+        Ok(registry)
+    }
+
+    /// Returns the names of the blocks and items of a tag
+    /// including the ones of the tags it contains, or `None`
+    /// if the tag hasn't been added
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - The tag, with or without the prefix
+    pub fn names(&self, tag: &str) -> Option<BTreeSet<String>> {
+        let tags = self.tags.lock().unwrap();
+        let mut names = BTreeSet::new();
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![strip_prefix(tag)];
+        tags.get(strip_prefix(tag))?;
+        while let Some(tag) = pending.pop() {
+            if !visited.insert(tag) {
+                continue;
+            }
+            for name in tags.get(tag).into_iter().flatten() {
+                if name.starts_with(TAG_PREFIX) {
+                    pending.push(strip_prefix(name));
+                } else {
+                    names.insert(name.clone());
+                }
+            }
+        }
+        Some(names)
+    }
+
+    /// Returns whether a block or item has a tag
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - The tag, with or without the prefix
+    /// * `name` - The name of the block or item
+    pub fn contains(&self, tag: &str, name: &str) -> bool {
+        self.names(tag).map_or(false, |names| names.contains(name))
+    }
+
+    /// Returns the names of the items a name of a definition
+    /// refers to, which is either the name of an item or a tag.
+    /// Scripts get an error for unknown tags.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the item or the tag
+    pub fn items(&self, name: &str) -> mlua::Result<Vec<String>> {
+        if !name.starts_with(TAG_PREFIX) {
+            return Ok(vec![name.to_string()]);
+        }
+        self.names(name).map(|names| names.into_iter().collect()).ok_or_else(|| {
+            mlua::Error::RuntimeError(format!("unknown tag {}", name))
+        })
+    }
+
+    /// Returns the materials a name of a definition refers to,
+    /// which is either the name of a block or a tag. Scripts get
+    /// an error for unknown blocks and tags without blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the block or the tag
+    pub fn materials(&self, name: &str) -> mlua::Result<Vec<Material>> {
+        if !name.starts_with(TAG_PREFIX) {
+            return Material::from_name(name).map(|material| vec![material]).ok_or_else(|| {
+                mlua::Error::RuntimeError(format!("unknown block {}", name))
+            });
+        }
+
+        let names = self.names(name).ok_or_else(|| {
+            mlua::Error::RuntimeError(format!("unknown tag {}", name))
+        })?;
+        let materials: Vec<Material> = names.iter().filter_map(|name| Material::from_name(name)).collect();
+        if materials.is_empty() {
+            return Err(mlua::Error::RuntimeError(format!("the tag {} contains no blocks", name)));
+        }
+        Ok(materials)
+    }
+}
+
+/// Returns a tag without its prefix
+///
+/// # Arguments
+///
+/// * `tag` - The tag, with or without the prefix
+fn strip_prefix(tag: &str) -> &str {
+    tag.strip_prefix(TAG_PREFIX).unwrap_or(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_include_the_names_of_nested_tags() {
+        let engine = ScriptEngine::new();
+        let tags = Tags::new(&engine).unwrap();
+        engine.exec("test", r##"
+            tags.add("#soil", { "dirt", "grass" })
+            tags.add("soil", { "farmland" })
+            tags.add("#natural", { "#soil", "stone", "cobblestone", "#natural" })
+            assert(tags.has("#natural", "farmland") and not tags.has("#soil", "stone"))
+            assert(#tags.list("#soil") == 3 and tags.list("#unknown") == nil)
+        "##).unwrap();
+
+        assert!(tags.contains("natural", "cobblestone"));
+        assert_eq!(tags.materials("#natural").unwrap(), vec![
+            Material::Dirt, Material::Farmland, Material::Grass, Material::Stone,
+        ]);
+        assert_eq!(tags.materials("sand").unwrap(), vec![Material::Sand]);
+        assert!(tags.materials("#unknown").is_err());
+        assert!(tags.materials("cheese").is_err());
+    }
+}
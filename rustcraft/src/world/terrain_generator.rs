@@ -1,8 +1,13 @@
 use crate::world::chunk::{CHUNK_AREA, Chunk, CHUNK_SIZE, CHUNK_HEIGHT};
 use cgmath::{Vector2, Vector3};
+use crate::world::biome::Biome;
 use crate::world::block::Material;
-use noise::{Perlin, NoiseFn};
+use crate::world::noise_source::{Noise, NoiseAlgorithm, NoiseSource, VolumeNoise};
 use cgmath::num_traits::FromPrimitive;
+use image::GrayImage;
+use mlua::{Function, Lua};
+use std::path::Path;
+use std::sync::Mutex;
 
 /// TerrainGen
 ///
@@ -26,10 +31,105 @@ pub trait TerrainGen {
     /// * `height_map` - The height map which should be applied
     /// to the generator
     fn gen_smooth_terrain(&self, chunk: &Chunk, height_map: &[i32; CHUNK_AREA]);
+
+    /// Returns the biome of the column at the given position,
+    /// or `None` if the terrain isn't made up of biomes
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The x coordinate of the column
+    /// * `z` - The z coordinate of the column
+    fn biome(&self, _x: i32, _z: i32) -> Option<Biome> {
+        None
+    }
+
+    /// Returns the features the generator places into a chunk,
+    /// which the worldgen overlay outlines to debug the generator
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the chunk
+    fn features(&self, _loc: &Vector2<i32>) -> Vec<GenFeature> {
+        Vec::new()
+    }
 }
 
-#[derive(Default)]
-pub struct SimpleTerrainGen {}
+impl<T: TerrainGen + ?Sized> TerrainGen for Box<T> {
+    fn gen_heightmap(&self, loc: &Vector2<i32>) -> [i32; CHUNK_AREA] {
+        (**self).gen_heightmap(loc)
+    }
+
+    fn gen_smooth_terrain(&self, chunk: &Chunk, height_map: &[i32; CHUNK_AREA]) {
+        (**self).gen_smooth_terrain(chunk, height_map)
+    }
+
+    fn biome(&self, x: i32, z: i32) -> Option<Biome> {
+        (**self).biome(x, z)
+    }
+
+    fn features(&self, loc: &Vector2<i32>) -> Vec<GenFeature> {
+        (**self).features(loc)
+    }
+}
+
+/// GenFeature
+///
+/// A feature a terrain generator placed into a chunk
+#[derive(Clone, Debug, PartialEq)]
+pub enum GenFeature {
+    /// A structure starting in the chunk
+    Structure {
+        /// The name of the structure
+        name: String,
+        /// The lowest block the structure spans
+        min: Vector3<i32>,
+        /// The highest block the structure spans
+        max: Vector3<i32>,
+    },
+    /// The path along which a carver hollowed out the
+    /// terrain, starting in the chunk
+    CarverPath(Vec<Vector3<f32>>),
+}
+
+/// The frequency of the hills of the default terrain
+const HILL_FREQUENCY: f64 = 1.0 / 16.0;
+
+/// SimpleTerrainGen
+///
+/// A terrain generator for rolling hills of dirt, whose
+/// height follows the noise it's created with
+pub struct SimpleTerrainGen {
+    /// The noise the height of the terrain follows
+    noise: Box<dyn NoiseSource>,
+}
+
+impl Default for SimpleTerrainGen {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl SimpleTerrainGen {
+    /// Creates a new generator of the default hills
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed of the noise, `0` for the default terrain
+    pub fn new(seed: u32) -> Self {
+        Self::with_noise(Box::new(Noise::new(NoiseAlgorithm::Perlin, seed, HILL_FREQUENCY)))
+    }
+
+    /// Creates a new generator whose hills follow the given noise
+    ///
+    /// # Arguments
+    ///
+    /// * `noise` - The noise the height of the terrain follows
+    pub fn with_noise(noise: Box<dyn NoiseSource>) -> Self {
+        Self {
+            noise,
+        }
+    }
+}
 
 impl TerrainGen for SimpleTerrainGen {
     fn gen_heightmap(&self, loc: &Vector2<i32>) -> [i32; CHUNK_AREA] {
@@ -44,7 +144,7 @@ impl TerrainGen for SimpleTerrainGen {
                 let block_x = x as f64 + cx as f64 * CHUNK_SIZE as f64;
                 let block_y = y as f64 + cy as f64 * CHUNK_SIZE as f64;
                 // Get noise value
-                let mut value = Perlin::new().get([block_x / 16.0, block_y / 16.0]);
+                let mut value = self.noise.get(block_x, block_y);
 
                 // Make it between 0.0 and 1.0
                 value = (value + 1.0) / 2.0;
@@ -72,4 +172,439 @@ impl TerrainGen for SimpleTerrainGen {
             }
         }
     }
-}
\ No newline at end of file
+}
+/// The height of the ceiling of the caves
+const CAVE_CEILING: i32 = 40;
+
+/// The height of the floor up to which it's covered with lava
+const CAVE_LAVA_DEPTH: i32 = 3;
+/// The height of the floor from which on glowstone
+/// grows at the ceiling above
+const CAVE_GLOWSTONE_HEIGHT: i32 = 13;
+/// The frequency of the hollows of the floor
+const CAVE_FREQUENCY: f64 = 1.0 / 9.0;
+/// The distance in blocks the noise of the caves is sampled
+/// apart from the overworld, so that they don't mirror its hills
+const CAVE_NOISE_OFFSET: f64 = 512.5 / CAVE_FREQUENCY;
+
+/// CaveTerrainGen
+///
+/// A terrain generator for underground dimensions. It
+/// generates a rough stone floor and a hanging stone
+/// ceiling, with an open cave in between. The deepest
+/// hollows of the floor are filled with lava, while
+/// glowstone grows where the ceiling hangs lowest.
+pub struct CaveTerrainGen {
+    /// The noise the height of the floor follows
+    noise: Noise,
+}
+
+impl Default for CaveTerrainGen {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl CaveTerrainGen {
+    /// Creates a new generator of caves
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed of the noise, `0` for the default terrain
+    pub fn new(seed: u32) -> Self {
+        Self {
+            noise: Noise::new(NoiseAlgorithm::Perlin, seed, CAVE_FREQUENCY),
+        }
+    }
+}
+
+impl TerrainGen for CaveTerrainGen {
+    fn gen_heightmap(&self, loc: &Vector2<i32>) -> [i32; CHUNK_AREA] {
+        let mut height_map = [0i32; CHUNK_AREA];
+
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let block_x = x as f64 + loc.x as f64 * CHUNK_SIZE as f64;
+                let block_y = y as f64 + loc.y as f64 * CHUNK_SIZE as f64;
+
+                let value = self.noise.get(block_x + CAVE_NOISE_OFFSET, block_y + CAVE_NOISE_OFFSET);
+                let value = (value + 1.0) / 2.0 * 12.0 + 2.0;
+
+                height_map[y * CHUNK_SIZE + x] = i32::from_f64(value).unwrap();
+            }
+        }
+
+        height_map
+    }
+
+    fn gen_smooth_terrain(&self, chunk: &Chunk, height_map: &[i32; CHUNK_AREA]) {
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let height = height_map[z * CHUNK_SIZE + x];
+
+                // The ceiling hangs lower above higher floors
+                let ceiling = CAVE_CEILING - height / 2;
+                for y in 0..=CAVE_CEILING {
+                    let material = if y == height && height <= CAVE_LAVA_DEPTH {
+                        Material::Lava
+                    } else if y == ceiling && height >= CAVE_GLOWSTONE_HEIGHT {
+                        Material::Glowstone
+                    } else if y <= height || y >= ceiling {
+                        Material::Stone
+                    } else {
+                        continue;
+                    };
+                    chunk.set_block(Vector3::new(x as i16, y as i16, z as i16), material);
+                }
+            }
+        }
+    }
+}
+
+/// The frequency of the tunnels along the ground
+const TUNNEL_FREQUENCY: f64 = 1.0 / 40.0;
+/// How much the tunnels are squashed, so that they rather
+/// wind along the ground than up and down
+const TUNNEL_SQUASH: f64 = 2.0;
+/// How close to zero both noises of the tunnels have to
+/// be for a block to be carved, the wider the tunnels
+const TUNNEL_WIDTH: f64 = 0.08;
+/// The depth of the solid ground below the surface which
+/// the tunnels stay beneath
+const TUNNEL_CRUST: i32 = 4;
+/// The height up to which the tunnels are flooded with lava
+const TUNNEL_LAVA_LEVEL: i32 = 2;
+
+/// CaveCarver
+///
+/// A pass carving winding tunnels into the terrain after it
+/// has been filled up to its height map. The tunnels run where
+/// two 3D noises are both close to zero, i.e. along the lines
+/// where their zero surfaces cross. They stay beneath a crust
+/// of solid ground, so that neither the surface nor the seas
+/// fall into them, and never reach the bottom of the world.
+/// The deepest tunnels are flooded with lava.
+pub struct CaveCarver {
+    /// The first noise the tunnels follow
+    first: VolumeNoise,
+    /// The second noise the tunnels follow, which is seeded
+    /// apart from the first one so that their zero surfaces
+    /// cross along lines instead of overlapping
+    second: VolumeNoise,
+}
+
+impl Default for CaveCarver {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl CaveCarver {
+    /// Creates a new carver of tunnels
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed of the noise, `0` for the default tunnels
+    pub fn new(seed: u32) -> Self {
+        Self {
+            first: VolumeNoise::new(NoiseAlgorithm::Perlin, seed, TUNNEL_FREQUENCY),
+            second: VolumeNoise::new(NoiseAlgorithm::Perlin, seed.wrapping_add(1), TUNNEL_FREQUENCY),
+        }
+    }
+
+    /// Returns whether the block at a position lies within a
+    /// tunnel, regardless of the terrain around it
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the block
+    pub fn is_tunnel(&self, pos: Vector3<i32>) -> bool {
+        let (x, y, z) = (pos.x as f64 + 0.5, (pos.y as f64 + 0.5) * TUNNEL_SQUASH, pos.z as f64 + 0.5);
+        self.first.get(x, y, z).abs() < TUNNEL_WIDTH
+            && self.second.get(x, y, z).abs() < TUNNEL_WIDTH
+    }
+
+    /// Carves the tunnels into a chunk whose columns are
+    /// filled up to the given height map. Fluids are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The chunk
+    /// * `height_map` - The height map the chunk is filled up to
+    pub fn carve(&self, chunk: &Chunk, height_map: &[i32; CHUNK_AREA]) {
+        let loc = chunk.loc();
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let height = height_map[z * CHUNK_SIZE + x].min(CHUNK_HEIGHT as i32 - 1);
+                for y in 1..=height - TUNNEL_CRUST {
+                    let pos = Vector3::new(loc.x * CHUNK_SIZE as i32 + x as i32, y, loc.y * CHUNK_SIZE as i32 + z as i32);
+                    let local = Vector3::new(x as i16, y as i16, z as i16);
+                    let solid = matches!(chunk.block(local), Some(block) if block != Material::Air && !block.is_fluid());
+                    if !solid || !self.is_tunnel(pos) {
+                        continue;
+                    }
+                    let material = if y <= TUNNEL_LAVA_LEVEL { Material::Lava } else { Material::Air };
+                    chunk.set_block(local, material);
+                }
+            }
+        }
+    }
+}
+
+/// The height of the surface of the flat terrain
+const FLAT_HEIGHT: usize = 4;
+
+/// FlatTerrainGen
+///
+/// A terrain generator for a flat world of dirt
+/// covered with grass
+#[derive(Default)]
+pub struct FlatTerrainGen {}
+
+impl TerrainGen for FlatTerrainGen {
+    fn gen_heightmap(&self, _loc: &Vector2<i32>) -> [i32; CHUNK_AREA] {
+        [FLAT_HEIGHT as i32; CHUNK_AREA]
+    }
+
+    fn gen_smooth_terrain(&self, chunk: &Chunk, height_map: &[i32; CHUNK_AREA]) {
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let height = height_map[z * CHUNK_SIZE + x];
+                for y in 0..=height {
+                    let material = if y == height { Material::Grass } else { Material::Dirt };
+                    chunk.set_block(Vector3::new(x as i16, y as i16, z as i16), material);
+                }
+            }
+        }
+    }
+}
+
+/// VoidTerrainGen
+///
+/// A terrain generator which leaves all chunks empty,
+/// e.g. for creative building dimensions
+#[derive(Default)]
+pub struct VoidTerrainGen {}
+
+impl TerrainGen for VoidTerrainGen {
+    fn gen_heightmap(&self, _loc: &Vector2<i32>) -> [i32; CHUNK_AREA] {
+        [-1; CHUNK_AREA]
+    }
+
+    fn gen_smooth_terrain(&self, _chunk: &Chunk, _height_map: &[i32; CHUNK_AREA]) {}
+}
+
+/// Fills the columns of a chunk up to their height, topped
+/// with the surface block
+///
+/// # Arguments
+///
+/// * `chunk` - The chunk
+/// * `height_map` - The height of each column, `-1` for none
+/// * `surface` - The block at the top of each column
+/// * `filler` - The blocks below the surface
+fn fill_columns(chunk: &Chunk, height_map: &[i32; CHUNK_AREA], surface: Material, filler: Material) {
+    for z in 0..CHUNK_SIZE {
+        for x in 0..CHUNK_SIZE {
+            let height = height_map[z * CHUNK_SIZE + x].min(CHUNK_HEIGHT as i32 - 1);
+            for y in 0..=height {
+                let material = if y == height { surface } else { filler };
+                chunk.set_block(Vector3::new(x as i16, y as i16, z as i16), material);
+            }
+        }
+    }
+}
+
+/// HeightmapTerrainGen
+///
+/// A terrain generator following the brightness of a
+/// grayscale image, whose top left pixel lies at the
+/// origin. Black pixels are at the lowest height and
+/// white ones at the highest, while the terrain beyond
+/// the image stays at the lowest height.
+pub struct HeightmapTerrainGen {
+    /// The height of each pixel, row by row
+    heights: Vec<i32>,
+    /// The width of the image in pixels
+    width: u32,
+    /// The height of the image in pixels
+    depth: u32,
+    /// The height of black pixels and beyond the image
+    min_height: i32,
+    /// The block at the top of each column
+    surface: Material,
+    /// The blocks below the surface
+    filler: Material,
+}
+
+impl HeightmapTerrainGen {
+    /// Creates a new generator following an image
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - The grayscale image
+    /// * `min_height` - The height of black pixels
+    /// * `max_height` - The height of white pixels
+    /// * `surface` - The block at the top of each column
+    /// * `filler` - The blocks below the surface
+    pub fn new(image: &GrayImage, min_height: i32, max_height: i32, surface: Material, filler: Material) -> Self {
+        let range = max_height - min_height;
+        Self {
+            heights: image.pixels()
+                .map(|pixel| min_height + (pixel.0[0] as i32 * range + 127) / 255)
+                .collect(),
+            width: image.width(),
+            depth: image.height(),
+            min_height,
+            surface,
+            filler,
+        }
+    }
+
+    /// Creates a new generator following the image at a path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the image
+    /// * `min_height` - The height of black pixels
+    /// * `max_height` - The height of white pixels
+    /// * `surface` - The block at the top of each column
+    /// * `filler` - The blocks below the surface
+    pub fn open(path: &Path, min_height: i32, max_height: i32, surface: Material, filler: Material) -> image::ImageResult<Self> {
+        let image = image::open(path)?.into_luma8();
+        Ok(Self::new(&image, min_height, max_height, surface, filler))
+    }
+}
+
+impl TerrainGen for HeightmapTerrainGen {
+    fn gen_heightmap(&self, loc: &Vector2<i32>) -> [i32; CHUNK_AREA] {
+        let mut height_map = [self.min_height; CHUNK_AREA];
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let pixel_x = loc.x * CHUNK_SIZE as i32 + x as i32;
+                let pixel_y = loc.y * CHUNK_SIZE as i32 + z as i32;
+                if pixel_x >= 0 && pixel_y >= 0 && (pixel_x as u32) < self.width && (pixel_y as u32) < self.depth {
+                    height_map[z * CHUNK_SIZE + x] = self.heights[pixel_y as usize * self.width as usize + pixel_x as usize];
+                }
+            }
+        }
+        height_map
+    }
+
+    fn gen_smooth_terrain(&self, chunk: &Chunk, height_map: &[i32; CHUNK_AREA]) {
+        fill_columns(chunk, height_map, self.surface, self.filler);
+    }
+}
+
+/// ScriptedTerrainGen
+///
+/// A terrain generator whose heights are returned by a
+/// Lua script, which defines a global `height` function
+/// taking the `x` and `z` coordinates of a column, e.g.
+///
+/// ```lua
+/// function height(x, z)
+///     return 16 + math.floor(8 * math.sin(x / 16) * math.cos(z / 16))
+/// end
+/// ```
+///
+/// The script runs in a Lua state of its own, as chunks
+/// are generated on other threads than the game's scripts
+/// run on. Chunks are generated one after another by it.
+pub struct ScriptedTerrainGen {
+    /// The Lua state the script has been executed in
+    lua: Mutex<Lua>,
+    /// The block at the top of each column
+    surface: Material,
+    /// The blocks below the surface
+    filler: Material,
+}
+
+impl ScriptedTerrainGen {
+    /// Creates a new generator from the source of its script
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The Lua source code defining `height`
+    /// * `surface` - The block at the top of each column
+    /// * `filler` - The blocks below the surface
+    pub fn new(source: &str, surface: Material, filler: Material) -> mlua::Result<Self> {
+        let lua = Lua::new();
+        lua.load(source).set_name("terrain").exec()?;
+        lua.globals().get::<_, Function>("height")?;
+        Ok(Self {
+            lua: Mutex::new(lua),
+            surface,
+            filler,
+        })
+    }
+}
+
+impl TerrainGen for ScriptedTerrainGen {
+    fn gen_heightmap(&self, loc: &Vector2<i32>) -> [i32; CHUNK_AREA] {
+        let lua = self.lua.lock().unwrap();
+        let heights = lua.globals().get::<_, Function>("height").and_then(|height| {
+            let mut height_map = [0; CHUNK_AREA];
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let block_x = loc.x * CHUNK_SIZE as i32 + x as i32;
+                    let block_z = loc.y * CHUNK_SIZE as i32 + z as i32;
+                    let value: f64 = height.call((block_x, block_z))?;
+                    height_map[z * CHUNK_SIZE + x] = value.floor() as i32;
+                }
+            }
+            Ok(height_map)
+        });
+
+        // Chunks whose heights failed are left empty
+        heights.unwrap_or_else(|error| {
+            log::warn!("Failed to generate the heights of chunk {:?}: {}", loc, error);
+            [-1; CHUNK_AREA]
+        })
+    }
+
+    fn gen_smooth_terrain(&self, chunk: &Chunk, height_map: &[i32; CHUNK_AREA]) {
+        fill_columns(chunk, height_map, self.surface, self.filler);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tunnels_are_carved_beneath_the_crust() {
+        let carver = CaveCarver::default();
+        let height = 60;
+        let height_map = [height; CHUNK_AREA];
+        let mut carved = 0;
+        for chunk_x in 0..4 {
+            let chunk = Chunk::new(Vector2::new(chunk_x, 0));
+            fill_columns(&chunk, &height_map, Material::Grass, Material::Stone);
+            carver.carve(&chunk, &height_map);
+
+            for z in 0..CHUNK_SIZE as i16 {
+                for x in 0..CHUNK_SIZE as i16 {
+                    assert_eq!(chunk.block(Vector3::new(x, 0, z)), Some(Material::Stone));
+                    for y in (height - TUNNEL_CRUST + 1)..=height {
+                        assert_ne!(chunk.block(Vector3::new(x, y as i16, z)), Some(Material::Air));
+                    }
+                    for y in 1..=(height - TUNNEL_CRUST) {
+                        let pos = Vector3::new(chunk_x * CHUNK_SIZE as i32 + x as i32, y, z as i32);
+                        let block = chunk.block(Vector3::new(x, y as i16, z));
+                        assert_eq!(block != Some(Material::Stone), carver.is_tunnel(pos));
+                        if block == Some(Material::Lava) {
+                            assert!(y <= TUNNEL_LAVA_LEVEL);
+                        }
+                        carved += (block == Some(Material::Air)) as usize;
+                    }
+                }
+            }
+        }
+
+        // The tunnels wind through the ground rather than hollowing it out
+        let volume = 4 * CHUNK_AREA * (height - TUNNEL_CRUST) as usize;
+        assert!(carved > 0, "no tunnels");
+        assert!(carved < volume / 8, "{} of {} blocks carved", carved, volume);
+    }
+}
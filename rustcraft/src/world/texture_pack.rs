@@ -0,0 +1,247 @@
+//! Types to reload the textures of the blocks while the game
+//! is running, e.g. to iterate on a texture pack without
+//! restarting the game
+
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::texture::TextureAtlas;
+use crate::resources::Resources;
+use crate::world::connected::{ConnectedTextures, CONNECTED_TEXTURES_FILE};
+use cgmath::Vector2;
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// The resource of the texture atlas of the blocks
+pub const ATLAS_FILE: &str = "textures/textures.png";
+/// The size of a sprite of the texture atlas in pixels
+pub const SPRITE_SIZE: f32 = 16.0;
+/// The time between two checks whether the files of the
+/// texture pack changed
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The `RGBA` pixels of the texture atlas, row by row
+/// starting at the bottom
+#[derive(Debug, PartialEq)]
+struct AtlasImage {
+    /// The width of the atlas in pixels
+    width: u32,
+    /// The height of the atlas in pixels
+    height: u32,
+    /// The pixels
+    pixels: Vec<u8>,
+}
+
+/// TexturePack
+///
+/// The texture atlas of the blocks and the tile sets of their
+/// connected textures. The pack is reloaded whenever its files
+/// change or a reload is requested, which increases its
+/// generation. The renderers compare the generation with the
+/// one they uploaded, so they replace their atlas with the next
+/// frame. Chunks are only remeshed if the tiles of the blocks
+/// changed, since the meshes refer to the tiles.
+pub struct TexturePack {
+    /// The generation, which is increased with each reload
+    generation: u64,
+    /// The texture atlas
+    atlas: Arc<AtlasImage>,
+    /// The tile sets of the blocks with connected textures
+    connected: Arc<ConnectedTextures>,
+    /// The times the files of the pack have been modified at
+    modified: Vec<Option<SystemTime>>,
+    /// The time of the last check whether the files changed
+    last_poll: Instant,
+}
+
+impl TexturePack {
+    /// Loads the texture pack from the resources. Without its
+    /// tile sets, blocks are drawn without connected textures.
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - A resource instance
+    pub fn load(resources: &Resources) -> Result<Self, String> {
+        let connected = ConnectedTextures::load(resources).unwrap_or_else(|error| {
+            log::warn!("{}", error);
+            ConnectedTextures::default()
+        });
+        Ok(Self {
+            generation: 0,
+            atlas: Arc::new(load_atlas(resources)?),
+            connected: Arc::new(connected),
+            modified: modified_times(resources),
+            last_poll: Instant::now(),
+        })
+    }
+
+    /// Returns the generation of the pack, which is increased
+    /// with each reload
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the tile sets of the blocks with connected textures
+    pub fn connected(&self) -> &Arc<ConnectedTextures> {
+        &self.connected
+    }
+
+    /// Uploads the texture atlas
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    pub fn atlas(&self, backend: &GlBackend) -> TextureAtlas {
+        let atlas = &self.atlas;
+        let texture = backend.create_texture_from_pixels(atlas.width, atlas.height, atlas.pixels.clone())
+            .expect("The atlas has been loaded with a matching size.");
+        let tex_atlas = TextureAtlas::from_texture(texture, Vector2::new(SPRITE_SIZE, SPRITE_SIZE));
+        tex_atlas.unbind();
+        tex_atlas
+    }
+
+    /// Reloads the texture pack. If it can't be loaded, e.g.
+    /// because a file is saved halfway, the current pack is
+    /// kept. Returns whether the pack has been reloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - A resource instance
+    pub fn reload(&mut self, resources: &Resources) -> bool {
+        self.modified = modified_times(resources);
+        let reloaded = load_atlas(resources).and_then(|atlas| Ok((atlas, ConnectedTextures::load(resources)?)));
+        match reloaded {
+            Ok((atlas, connected)) => {
+                // Unchanged tile sets are kept, so the chunks
+                // aren't remeshed in vain
+                if connected != *self.connected {
+                    self.connected = Arc::new(connected);
+                }
+                self.atlas = Arc::new(atlas);
+                self.generation += 1;
+                log::info!("Reloaded the texture pack");
+                true
+            }
+            Err(error) => {
+                log::warn!("Keeping the current texture pack. {}", error);
+                false
+            }
+        }
+    }
+
+    /// Reloads the texture pack if one of its files changed since
+    /// it has been loaded. The files are checked once per
+    /// `POLL_INTERVAL`. Returns whether the pack has been reloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - A resource instance
+    pub fn poll(&mut self, resources: &Resources) -> bool {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+        if modified_times(resources) == self.modified {
+            return false;
+        }
+        self.reload(resources)
+    }
+}
+
+/// Loads the texture atlas, flipped vertically for `OpenGL`
+///
+/// # Arguments
+///
+/// * `resources` - A resource instance
+fn load_atlas(resources: &Resources) -> Result<AtlasImage, String> {
+    let image = resources.load_image(ATLAS_FILE)
+        .map_err(|error| format!("Failed to load {}: {:?}", ATLAS_FILE, error))?
+        .flipv()
+        .into_rgba8();
+    Ok(AtlasImage {
+        width: image.width(),
+        height: image.height(),
+        pixels: image.into_raw(),
+    })
+}
+
+/// Returns the times the files of the texture pack have been
+/// modified at, `None` for files which can't be read
+///
+/// # Arguments
+///
+/// * `resources` - A resource instance
+fn modified_times(resources: &Resources) -> Vec<Option<SystemTime>> {
+    [ATLAS_FILE, CONNECTED_TEXTURES_FILE].iter()
+        .map(|file| fs::metadata(resources.path(file)).and_then(|metadata| metadata.modified()).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn resources(name: &str) -> Resources {
+        let root = std::env::temp_dir().join(format!("rustcraft-texture-pack-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("textures")).unwrap();
+        Resources::from_path(root)
+    }
+
+    fn write_atlas(resources: &Resources, color: [u8; 4]) {
+        RgbaImage::from_pixel(32, 16, Rgba(color)).save(resources.path(ATLAS_FILE)).unwrap();
+    }
+
+    fn write_connected(resources: &Resources, row: u32) {
+        let source = format!("[[tile_sets]]\nblock = \"glass\"\nrow = {}\n", row);
+        fs::write(resources.path(CONNECTED_TEXTURES_FILE), source).unwrap();
+    }
+
+    #[test]
+    fn tile_sets_are_only_replaced_if_they_changed() {
+        let resources = resources("reload");
+        write_atlas(&resources, [255, 0, 0, 255]);
+        write_connected(&resources, 13);
+        let mut pack = TexturePack::load(&resources).unwrap();
+        assert_eq!((pack.atlas.width, pack.atlas.height), (32, 16));
+        let connected = pack.connected().clone();
+
+        // A new atlas keeps the tile sets
+        write_atlas(&resources, [0, 255, 0, 255]);
+        assert!(pack.reload(&resources));
+        assert_eq!(pack.generation(), 1);
+        assert_eq!(&pack.atlas.pixels[..4], &[0, 255, 0, 255]);
+        assert!(Arc::ptr_eq(&connected, pack.connected()));
+
+        // Moved tile sets are replaced
+        write_connected(&resources, 14);
+        assert!(pack.reload(&resources));
+        assert!(!Arc::ptr_eq(&connected, pack.connected()));
+
+        // A broken file keeps the current pack
+        fs::write(resources.path(CONNECTED_TEXTURES_FILE), "tile_sets = 1").unwrap();
+        assert!(!pack.reload(&resources));
+        assert_eq!(pack.generation(), 2);
+        let _ = fs::remove_dir_all(resources.path(""));
+    }
+
+    #[test]
+    fn changed_files_are_reloaded_when_polled() {
+        let resources = resources("poll");
+        write_atlas(&resources, [255, 0, 0, 255]);
+        write_connected(&resources, 13);
+        let mut pack = TexturePack::load(&resources).unwrap();
+
+        pack.last_poll -= POLL_INTERVAL;
+        assert!(!pack.poll(&resources));
+        assert_eq!(pack.generation(), 0);
+
+        // The file system might not tell modifications of
+        // the same second apart
+        pack.modified[1] = None;
+        pack.last_poll -= POLL_INTERVAL;
+        assert!(pack.poll(&resources));
+        assert_eq!(pack.generation(), 1);
+        let _ = fs::remove_dir_all(resources.path(""));
+    }
+}
@@ -0,0 +1,564 @@
+//! Types to tick random blocks of the loaded chunks, which
+//! lets scripts define behaviors like spreading grass, and
+//! to schedule changes of blocks for later updates
+
+use crate::scripting::{self, ScriptEngine, ScriptError};
+use crate::world::script_api;
+use crate::world::World;
+use crate::world::block::Material;
+use crate::world::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::world::fire::{self, FlammableBlocks};
+use crate::world::fluids::{self, FluidInteractions};
+use crate::world::rules::GameRules;
+use cgmath::Vector3;
+use mlua::{Function, RegistryKey};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The height of a section of a chunk. Each section
+/// is ticked separately, so tall chunks aren't ticked
+/// less often than flat ones.
+pub const SECTION_HEIGHT: usize = 16;
+/// The updates after which a scheduled tick of a block
+/// which isn't loaded is tried again
+const RETRY_DELAY: u64 = 60;
+
+/// A small xorshift generator picking the ticked blocks,
+/// which is good enough for gameplay randomness
+pub(crate) struct TickRng {
+    /// The state of the generator, which is never `0`
+    state: u64,
+}
+
+impl TickRng {
+    /// Creates a new generator with the given seed
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed
+    pub(crate) fn new(seed: u64) -> Self {
+        Self {
+            state: seed | 1,
+        }
+    }
+
+    /// Creates a new generator seeded with the current time
+    pub(crate) fn from_time() -> Self {
+        Self::new(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64))
+    }
+
+    /// Advances the generator and returns its new state
+    pub(crate) fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Returns a random number below `bound`
+    ///
+    /// # Arguments
+    ///
+    /// * `bound` - The exclusive upper bound
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    /// Returns a random number in the range `[0, 1)`
+    pub(crate) fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// RandomTicks
+///
+/// The callbacks scripts registered for random ticks. Each
+/// update, a few random blocks of every section of the loaded
+/// chunks are picked, as many as the `randomTickSpeed` game
+/// rule says. If a callback is registered for the material of
+/// a picked block, it's called with the position of the block
+/// and an API to edit the world, e.g.
+///
+/// ```lua
+/// blocks.onRandomTick("dirt", function(pos, world)
+///     local above = { pos[1], pos[2] + 1, pos[3] }
+///     if world.block(above) == "air" then
+///         world.set_block(pos, "grass")
+///     end
+/// end)
+/// ```
+///
+/// Besides `block` and `set_block`, the API offers `state` and
+/// `set_state` to read and change the state of a block, `light`
+/// returning the light level of the sky at a block and `raycast`,
+/// see `script_api::world_api`.
+///
+/// A block type registered again replaces its callback.
+pub struct RandomTicks {
+    /// The callbacks by the material of the block, stored
+    /// in the Lua registry
+    callbacks: Arc<Mutex<HashMap<Material, RegistryKey>>>,
+    /// The generator picking the ticked blocks
+    rng: TickRng,
+}
+
+impl RandomTicks {
+    /// Creates a new random tick registry and registers its
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    pub fn new(engine: &ScriptEngine) -> mlua::Result<Self> {
+        let callbacks = Arc::new(Mutex::new(HashMap::new()));
+
+        let lua = engine.lua();
+        let api = scripting::api_table(lua, "blocks")?;
+        let registered = callbacks.clone();
+        api.set("onRandomTick", lua.create_function(move |lua, (name, callback): (String, Function)| {
+            let material = Material::from_name(&name).ok_or_else(|| {
+                mlua::Error::RuntimeError(format!("unknown block {}", name))
+            })?;
+            let key = lua.create_registry_value(callback)?;
+            registered.lock().unwrap().insert(material, key);
+            Ok(())
+        })?)?;
+
+        Ok(Self {
+            callbacks,
+            rng: TickRng::from_time(),
+        })
+    }
+
+    /// Ticks random blocks of the generated chunks of a world
+    /// and returns the errors of failing callbacks
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine the callbacks are registered in
+    /// * `world` - The world whose blocks are ticked
+    /// * `rules` - The game rules
+    pub fn tick(&mut self, engine: &ScriptEngine, world: &mut World, rules: &GameRules) -> Vec<ScriptError> {
+        let lua = engine.lua();
+
+        // The callbacks are taken out of the registry first, so
+        // that they could register callbacks themselves
+        let callbacks: HashMap<Material, Function> = {
+            let registered = self.callbacks.lock().unwrap();
+            if registered.is_empty() || rules.random_tick_speed == 0 {
+                return Vec::new();
+            }
+            registered.iter()
+                .filter_map(|(material, key)| Some((*material, lua.registry_value(key).ok()?)))
+                .collect()
+        };
+
+        let mut ticked = Vec::new();
+        for chunk in world.chunks().iter().filter(|chunk| chunk.is_generated()) {
+            let origin = Vector3::new(chunk.loc().x * CHUNK_SIZE as i32, 0, chunk.loc().y * CHUNK_SIZE as i32);
+            for section in 0..CHUNK_HEIGHT / SECTION_HEIGHT {
+                for _ in 0..rules.random_tick_speed {
+                    let local = Vector3::new(
+                        self.rng.below(CHUNK_SIZE) as i16,
+                        (section * SECTION_HEIGHT + self.rng.below(SECTION_HEIGHT)) as i16,
+                        self.rng.below(CHUNK_SIZE) as i16,
+                    );
+                    match chunk.peek_block(local) {
+                        Some(material) if callbacks.contains_key(&material) => {
+                            ticked.push((origin + local.cast::<i32>().unwrap(), material));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        if ticked.is_empty() {
+            return Vec::new();
+        }
+
+        let mut errors = Vec::new();
+        let world = RefCell::new(world);
+        let result = lua.scope(|scope| {
+            let api = script_api::world_api(lua, scope, &world)?;
+
+            for (pos, material) in ticked {
+                // A previous callback could have changed the block
+                if world.borrow().block(pos) != Some(material) {
+                    continue;
+                }
+                let result = lua.create_sequence_from(vec![pos.x, pos.y, pos.z])
+                    .and_then(|pos| callbacks[&material].call::<_, ()>((pos, api.clone())));
+                if let Err(error) = result {
+                    errors.push(ScriptError::new(&format!("random tick of {}", material.name()), error));
+                }
+            }
+            Ok(())
+        });
+        if let Err(error) = result {
+            errors.push(ScriptError::new("random ticks", error));
+        }
+        errors
+    }
+}
+
+/// TickAction
+///
+/// The change a scheduled tick makes to its block
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TickAction {
+    /// The block is restored to the material and state
+    /// it has been generated with, unless it has been
+    /// replaced after it had been broken
+    Regenerate {
+        /// The generated material of the block
+        material: Material,
+        /// The generated state of the block
+        state: u8,
+    },
+    /// The fluid block reacts with the blocks it touches
+    /// and flows on, see `fluids::update`
+    FluidUpdate,
+    /// The fire spreads or burns out, see `fire::tick`
+    FireTick,
+}
+
+/// ScheduledTick
+///
+/// A change of a block which is due at a later update
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ScheduledTick {
+    /// The position of the block in world coordinates
+    pub pos: Vector3<i32>,
+    /// The change of the block
+    pub action: TickAction,
+}
+
+/// TickQueue
+///
+/// The ticks scheduled for the blocks of a world, ordered
+/// by the update they are due at. Ticks due at the same
+/// update are run in the order they have been scheduled.
+/// The queue isn't saved, so pending ticks are dropped
+/// when the game is closed.
+#[derive(Debug, Default)]
+pub struct TickQueue {
+    /// The amount of updates so far
+    tick: u64,
+    /// The pending ticks by the update they are due at
+    pending: BTreeMap<u64, Vec<ScheduledTick>>,
+}
+
+impl TickQueue {
+    /// Schedules a tick for a later update
+    ///
+    /// # Arguments
+    ///
+    /// * `delay` - The updates until the tick is due, at least one
+    /// * `tick` - The scheduled tick
+    pub fn schedule(&mut self, delay: u64, tick: ScheduledTick) {
+        self.pending.entry(self.tick + delay.max(1)).or_default().push(tick);
+    }
+
+    /// Returns the amount of pending ticks
+    pub fn len(&self) -> usize {
+        self.pending.values().map(Vec::len).sum()
+    }
+
+    /// Returns whether no tick is pending
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Advances the queue by a single update and takes
+    /// the ticks which are due
+    pub fn advance(&mut self) -> Vec<ScheduledTick> {
+        self.tick += 1;
+        let later = self.pending.split_off(&(self.tick + 1));
+        std::mem::replace(&mut self.pending, later).into_values().flatten().collect()
+    }
+}
+
+/// BlockBehaviors
+///
+/// The behaviors of blocks defined by the scripts,
+/// which scheduled ticks depend on
+#[derive(Clone, Debug, Default)]
+pub struct BlockBehaviors {
+    /// The interactions of fluids
+    pub interactions: FluidInteractions,
+    /// The blocks which catch fire
+    pub flammable: FlammableBlocks,
+}
+
+/// ScheduledReport
+///
+/// What the scheduled ticks of an update did
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScheduledReport {
+    /// The amount of regenerated blocks
+    pub regenerated: usize,
+    /// The positions of the fires which are still burning
+    pub burning: Vec<Vector3<i32>>,
+}
+
+/// Runs the scheduled ticks of a world which are due with
+/// the current update. Ticks of blocks which aren't loaded
+/// are tried again later.
+///
+/// # Arguments
+///
+/// * `world` - The world
+/// * `behaviors` - The behaviors of the blocks
+pub fn run_scheduled(world: &mut World, behaviors: &BlockBehaviors) -> ScheduledReport {
+    let mut report = ScheduledReport::default();
+    let mut rng = TickRng::from_time();
+    for tick in world.scheduled_mut().advance() {
+        match tick.action {
+            TickAction::Regenerate { material, state } => match world.block(tick.pos) {
+                Some(Material::Air) => {
+                    if world.regenerate_block(tick.pos, material, state) {
+                        report.regenerated += 1;
+                    }
+                }
+                Some(_) => {}
+                None => world.scheduled_mut().schedule(RETRY_DELAY, tick),
+            },
+            TickAction::FluidUpdate => {
+                fluids::update(world, tick.pos, &behaviors.interactions);
+                // Lava sets fire to flammable blocks it touches
+                fire::ignite_near_lava(world, tick.pos, &behaviors.flammable, &mut rng);
+            }
+            TickAction::FireTick => {
+                if fire::tick(world, tick.pos, &behaviors.flammable, &mut rng) {
+                    report.burning.push(tick.pos);
+                }
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::terrain_generator::FlatTerrainGen;
+    use crate::world::time::SkySettings;
+    use cgmath::Vector2;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// Creates a flat world with a single generated chunk
+    fn flat_world() -> World {
+        let mut world = World::with_generator(FlatTerrainGen {}, SkySettings::default());
+        world.load_chunk(&Vector2::new(0, 0));
+        let start = Instant::now();
+        while !world.chunks()[0].is_generated() {
+            assert!(start.elapsed() < Duration::from_secs(10), "the chunk hasn't been generated");
+            thread::yield_now();
+        }
+        world
+    }
+
+    /// Counts the blocks of a material in the lowest section
+    fn count(world: &World, material: Material) -> usize {
+        let mut count = 0;
+        for x in 0..CHUNK_SIZE as i32 {
+            for y in 0..SECTION_HEIGHT as i32 {
+                for z in 0..CHUNK_SIZE as i32 {
+                    if world.block(Vector3::new(x, y, z)) == Some(material) {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn callbacks_edit_ticked_blocks() {
+        let engine = ScriptEngine::new();
+        let mut ticks = RandomTicks::new(&engine).unwrap();
+        engine.exec("test", r#"
+            blocks.onRandomTick("grass", function(pos, world)
+                assert(world.block(pos) == "grass")
+                world.set_block(pos, "stone")
+            end)
+        "#).unwrap();
+
+        let mut world = flat_world();
+        let grass = count(&world, Material::Grass);
+        assert_eq!(grass, CHUNK_SIZE * CHUNK_SIZE);
+
+        let rules = GameRules { random_tick_speed: 0, ..GameRules::default() };
+        assert!(ticks.tick(&engine, &mut world, &rules).is_empty());
+        assert_eq!(count(&world, Material::Grass), grass);
+
+        let rules = GameRules { random_tick_speed: 4096, ..GameRules::default() };
+        assert!(ticks.tick(&engine, &mut world, &rules).is_empty());
+        assert!(count(&world, Material::Stone) > 0);
+        assert_eq!(count(&world, Material::Stone) + count(&world, Material::Grass), grass);
+    }
+
+    #[test]
+    fn failing_callbacks_are_reported() {
+        let engine = ScriptEngine::new();
+        let mut ticks = RandomTicks::new(&engine).unwrap();
+        assert!(engine.exec("test", r#"blocks.onRandomTick("cheese", function() end)"#).is_err());
+        engine.exec("test", r#"blocks.onRandomTick("dirt", function(pos, world) world.set_block(pos, "cheese") end)"#).unwrap();
+
+        let mut world = flat_world();
+        let errors = ticks.tick(&engine, &mut world, &GameRules { random_tick_speed: 64, ..GameRules::default() });
+        assert!(!errors.is_empty());
+        assert_eq!(errors[0].source(), "random tick of dirt");
+        assert_eq!(count(&world, Material::Dirt), CHUNK_SIZE * CHUNK_SIZE * 4);
+    }
+
+    #[test]
+    fn broken_terrain_regenerates_when_due() {
+        let mut world = flat_world();
+        let (broken, replaced, unloaded) = (Vector3::new(1, 1, 1), Vector3::new(2, 1, 2), Vector3::new(100, 1, 100));
+        assert!(world.is_generated(broken));
+        for pos in [broken, replaced, unloaded].iter() {
+            world.set_block(*pos, Material::Air);
+            world.scheduled_mut().schedule(2, ScheduledTick {
+                pos: *pos,
+                action: TickAction::Regenerate { material: Material::Dirt, state: 0 },
+            });
+        }
+        world.set_block(replaced, Material::Stone);
+        assert!(!world.is_generated(broken));
+
+        assert_eq!(run_scheduled(&mut world, &BlockBehaviors::default()).regenerated, 0);
+        assert_eq!(run_scheduled(&mut world, &BlockBehaviors::default()).regenerated, 1);
+        assert_eq!(world.block(broken), Some(Material::Dirt));
+        assert!(world.is_generated(broken));
+        assert_eq!(world.block(replaced), Some(Material::Stone));
+
+        // The block which isn't loaded is tried again later
+        assert_eq!(world.scheduled().len(), 1);
+    }
+
+    #[test]
+    fn fluids_react_when_due() {
+        let mut world = flat_world();
+        let mut behaviors = BlockBehaviors::default();
+        behaviors.interactions.add(fluids::FluidInteraction {
+            fluid: Material::Lava,
+            touching: Material::Water,
+            source: Material::Obsidian,
+            flowing: Material::Stone,
+        });
+        let (still, flowing) = (Vector3::new(2, 10, 2), Vector3::new(6, 10, 6));
+        world.set_block(still, Material::Lava);
+        world.set_block(flowing, Material::Lava);
+        world.set_block_state(flowing, 3);
+        world.set_block(still + Vector3::new(1, 0, 0), Material::Water);
+        world.set_block(flowing + Vector3::new(0, 1, 0), Material::Water);
+
+        let delay = fluids::flow(Material::Lava).delay;
+        for _ in 0..delay - 1 {
+            run_scheduled(&mut world, &behaviors);
+        }
+        assert_eq!(world.block(still), Some(Material::Lava));
+        run_scheduled(&mut world, &behaviors);
+        assert_eq!(world.block(still), Some(Material::Obsidian));
+        assert_eq!(world.block(flowing), Some(Material::Stone));
+        assert_eq!(world.block(still + Vector3::new(1, 0, 0)), Some(Material::Water));
+    }
+
+    #[test]
+    fn fluids_spread_from_their_sources() {
+        let mut world = flat_world();
+        let behaviors = BlockBehaviors::default();
+        let source = Vector3::new(8, 5, 8);
+        world.set_block(source, Material::Water);
+        world.set_block(Vector3::new(8, 8, 2), Material::Lava);
+
+        let water = fluids::flow(Material::Water);
+        for _ in 0..water.delay * 2 {
+            run_scheduled(&mut world, &behaviors);
+        }
+        assert_eq!(world.block(source + Vector3::new(2, 0, 0)), Some(Material::Water));
+        assert_eq!(world.block_state(source + Vector3::new(2, 0, 0)), Some(2));
+        // Lava flows slower than water
+        assert_eq!(world.block(Vector3::new(8, 7, 2)), Some(Material::Air));
+
+        for _ in 0..water.delay * 8 {
+            run_scheduled(&mut world, &behaviors);
+        }
+        let range = water.range as i32;
+        assert_eq!(world.block(source + Vector3::new(-range, 0, 0)), Some(Material::Water));
+        assert_eq!(world.block(source + Vector3::new(-range - 1, 0, 0)), Some(Material::Air));
+        assert_eq!(world.block(source + Vector3::new(3, 0, 4)), Some(Material::Water));
+        assert_eq!(world.block(source + Vector3::new(4, 0, 4)), Some(Material::Air));
+        assert_eq!(world.block(Vector3::new(8, 7, 2)), Some(Material::Lava));
+
+        // The flowing water dries up without its source
+        world.set_block(source, Material::Air);
+        for _ in 0..water.delay * 20 {
+            run_scheduled(&mut world, &behaviors);
+        }
+        assert_eq!(world.block(source + Vector3::new(1, 0, 0)), Some(Material::Air));
+        assert_eq!(count(&world, Material::Water), 0);
+    }
+
+    #[test]
+    fn fire_burns_flammable_blocks_until_it_rains() {
+        let mut world = flat_world();
+        let mut behaviors = BlockBehaviors::default();
+        behaviors.flammable.set(Material::TallGrass, fire::Flammability { spread: 1.0, burn: 1.0 });
+        let (grass, lit, above) = (Vector3::new(5, 5, 5), Vector3::new(6, 5, 5), Vector3::new(7, 6, 5));
+        world.set_block(grass, Material::TallGrass);
+        world.set_block(above, Material::TallGrass);
+        assert!(fire::ignite(&mut world, lit));
+        assert!(!fire::ignite(&mut world, Vector3::new(6, 4, 5)), "only air catches fire");
+
+        let mut burning = Vec::new();
+        for _ in 0..fire::FIRE_TICK_DELAY {
+            burning.extend(run_scheduled(&mut world, &behaviors).burning);
+        }
+        assert_eq!(burning, vec![lit]);
+        assert_eq!(world.block(grass), Some(Material::Fire));
+        // The fire spread to the air below the other grass
+        assert_eq!(world.block(above - Vector3::new(0, 1, 0)), Some(Material::Fire));
+
+        world.set_raining(true);
+        for _ in 0..fire::FIRE_TICK_DELAY {
+            run_scheduled(&mut world, &behaviors);
+        }
+        assert_eq!(count(&world, Material::Fire), 0);
+        assert_eq!(world.block(above), Some(Material::TallGrass));
+    }
+
+    #[test]
+    fn scheduled_ticks_are_due_in_order() {
+        let tick = |x| ScheduledTick { pos: Vector3::new(x, 0, 0), action: TickAction::Regenerate { material: Material::Dirt, state: 0 } };
+        let mut queue = TickQueue::default();
+        queue.schedule(2, tick(1));
+        queue.schedule(1, tick(2));
+        queue.schedule(2, tick(3));
+        queue.schedule(0, tick(4));
+        assert_eq!(queue.len(), 4);
+
+        assert_eq!(queue.advance(), vec![tick(2), tick(4)]);
+        assert_eq!(queue.advance(), vec![tick(1), tick(3)]);
+        assert!(queue.advance().is_empty());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn ticked_blocks_are_spread_over_the_section() {
+        let mut rng = TickRng::new(0);
+        let mut seen = [false; CHUNK_SIZE];
+        for _ in 0..1000 {
+            seen[rng.below(CHUNK_SIZE)] = true;
+        }
+        assert!(seen.iter().all(|seen| *seen));
+
+        let units: Vec<f64> = (0..1000).map(|_| rng.unit()).collect();
+        assert!(units.iter().all(|unit| (0.0..1.0).contains(unit)));
+        assert!(units.iter().any(|unit| *unit < 0.1) && units.iter().any(|unit| *unit > 0.9));
+    }
+}
@@ -0,0 +1,198 @@
+//! Types to keep the blocks of idle chunks compressed or
+//! evicted to disk, so that far more chunks fit into memory
+//! while they're loaded
+
+use crate::world::chunk::{BlockTier, Chunk};
+use cgmath::Vector2;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// The time in seconds between two updates of the tiers
+pub const SWEEP_INTERVAL: f32 = 1.0;
+
+/// TierSettings
+///
+/// The time in seconds a chunk has to be idle before its
+/// blocks are compressed or evicted to disk, `0` to never
+/// do so
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TierSettings {
+    /// The time until the blocks are compressed
+    pub compress_after: f32,
+    /// The time until the compressed blocks are evicted
+    pub evict_after: f32,
+}
+
+/// ChunkTiers
+///
+/// Tracks how long the chunks of a world have been idle and
+/// moves their blocks to the next tier once they've been idle
+/// long enough. Chunks are moved back as soon as their blocks
+/// are accessed again.
+pub struct ChunkTiers {
+    /// The time in seconds each loaded chunk has been idle
+    idle: HashMap<Vector2<i32>, f32>,
+    /// The time since the tiers have been updated the last time
+    since_sweep: f32,
+    /// The directory the evicted blocks are written to
+    swap_dir: PathBuf,
+}
+
+impl Default for ChunkTiers {
+    fn default() -> Self {
+        Self {
+            idle: HashMap::new(),
+            since_sweep: 0.0,
+            swap_dir: std::env::temp_dir().join(format!("rustcraft-swap-{}", std::process::id())),
+        }
+    }
+}
+
+impl ChunkTiers {
+    /// Updates the idle time of the chunks and moves the blocks
+    /// of idle chunks to the next tier. Returns the amount of
+    /// chunks which have been compressed and evicted.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunks` - The loaded chunks of the world
+    /// * `seconds` - The time passed since the last update
+    /// * `settings` - The idle times of the tiers
+    pub fn update(&mut self, chunks: &[Chunk], seconds: f32, settings: TierSettings) -> (usize, usize) {
+        self.since_sweep += seconds;
+        if self.since_sweep < SWEEP_INTERVAL {
+            return (0, 0);
+        }
+        let elapsed = self.since_sweep;
+        self.since_sweep = 0.0;
+
+        let mut idle = HashMap::with_capacity(chunks.len());
+        let (mut compressed, mut evicted) = (0, 0);
+        for chunk in chunks.iter() {
+            let accessed = chunk.take_accessed();
+            let seconds = match self.idle.get(chunk.loc()) {
+                Some(seconds) if !accessed => seconds + elapsed,
+                _ => 0.0,
+            };
+            idle.insert(*chunk.loc(), seconds);
+
+            let tier = chunk.tier();
+            if tier == BlockTier::Decoded && settings.compress_after > 0.0 && seconds >= settings.compress_after {
+                if chunk.compress() {
+                    compressed += 1;
+                }
+            } else if tier == BlockTier::Compressed && settings.evict_after > 0.0 && seconds >= settings.evict_after {
+                match chunk.evict(&self.swap_dir) {
+                    Ok(true) => evicted += 1,
+                    Ok(false) => {}
+                    Err(error) => log::warn!("Failed to evict chunk {:?}: {}", chunk.loc(), error),
+                }
+            }
+        }
+        self.idle = idle;
+        (compressed, evicted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::Material;
+    use crate::world::chunk::CHUNK_VOLUME;
+    use cgmath::Vector3;
+    use std::fs;
+    use std::slice;
+
+    fn generated_chunk() -> Chunk {
+        let chunk = Chunk::new(Vector2::new(1, 2));
+        for x in 0..16 {
+            for z in 0..16 {
+                for y in 0..40 {
+                    chunk.set_block(Vector3::new(x, y, z), Material::Stone);
+                }
+            }
+        }
+        chunk.set_block(Vector3::new(3, 40, 5), Material::Chest);
+        chunk.mark_generated();
+        chunk
+    }
+
+    #[test]
+    fn idle_chunks_are_compressed_and_evicted() {
+        let chunk = generated_chunk();
+        let mut tiers = ChunkTiers::default();
+        let settings = TierSettings { compress_after: 2.0, evict_after: 4.0 };
+
+        // The chunk has been accessed while it was generated
+        assert_eq!(tiers.update(slice::from_ref(&chunk), 1.0, settings), (0, 0));
+        assert_eq!(tiers.update(slice::from_ref(&chunk), 1.0, settings), (0, 0));
+        assert_eq!(tiers.update(slice::from_ref(&chunk), 1.0, settings), (1, 0));
+        assert_eq!(chunk.tier(), BlockTier::Compressed);
+
+        // Peeking at the blocks doesn't count as an access
+        assert_eq!(chunk.peek_block(Vector3::new(3, 40, 5)), Some(Material::Chest));
+        assert_eq!(chunk.peek_block(Vector3::new(3, 39, 5)), Some(Material::Stone));
+        assert_eq!(tiers.update(slice::from_ref(&chunk), 1.0, settings), (0, 0));
+        assert_eq!(tiers.update(slice::from_ref(&chunk), 1.0, settings), (0, 1));
+        assert_eq!(chunk.tier(), BlockTier::Evicted);
+        assert_eq!(chunk.peek_block(Vector3::new(3, 40, 5)), None);
+
+        // Reading blocks brings them back, but keeps them compressed
+        assert_eq!(chunk.block(Vector3::new(3, 40, 5)), Some(Material::Chest));
+        assert_eq!(chunk.block(Vector3::new(3, 41, 5)), Some(Material::Air));
+        assert_eq!(chunk.tier(), BlockTier::Compressed);
+
+        // Changing blocks decodes them
+        chunk.set_block(Vector3::new(0, 0, 0), Material::Dirt);
+        assert_eq!(chunk.tier(), BlockTier::Decoded);
+        assert_eq!(chunk.block(Vector3::new(0, 0, 0)), Some(Material::Dirt));
+        assert_eq!(chunk.block(Vector3::new(1, 0, 0)), Some(Material::Stone));
+        assert_eq!(chunk.block(Vector3::new(3, 40, 5)), Some(Material::Chest));
+        let _ = fs::remove_dir_all(&tiers.swap_dir);
+    }
+
+    #[test]
+    fn unreadable_swap_files_keep_the_blocks_evicted() {
+        let chunk = generated_chunk();
+        let mut tiers = ChunkTiers { swap_dir: ChunkTiers::default().swap_dir.join("unreadable"), ..Default::default() };
+        let settings = TierSettings { compress_after: 1.0, evict_after: 1.0 };
+        while chunk.tier() != BlockTier::Evicted {
+            tiers.update(slice::from_ref(&chunk), 1.0, settings);
+        }
+
+        let swap_file = fs::read_dir(&tiers.swap_dir).unwrap().next().unwrap().unwrap().path();
+        let content = fs::read(&swap_file).unwrap();
+        fs::write(&swap_file, &content[..content.len() / 2]).unwrap();
+        assert_eq!(chunk.block(Vector3::new(3, 40, 5)), None);
+        chunk.set_block(Vector3::new(0, 0, 0), Material::Dirt);
+        assert_eq!(chunk.tier(), BlockTier::Evicted);
+        assert!(swap_file.exists());
+
+        // Runs which don't end after the one before are refused as well
+        let mut unordered = Vec::new();
+        for &(end, material) in [(100u32, Material::Stone), (50, Material::Air), (CHUNK_VOLUME as u32, Material::Air)].iter() {
+            unordered.extend_from_slice(&end.to_le_bytes());
+            unordered.push(material as u8);
+        }
+        fs::write(&swap_file, zstd::bulk::compress(&unordered, 0).unwrap()).unwrap();
+        chunk.set_block(Vector3::new(0, 0, 0), Material::Dirt);
+        assert_eq!(chunk.tier(), BlockTier::Evicted);
+
+        // The blocks come back once the file can be read again
+        fs::write(&swap_file, &content).unwrap();
+        assert_eq!(chunk.block(Vector3::new(3, 40, 5)), Some(Material::Chest));
+        assert_eq!(chunk.block(Vector3::new(0, 0, 0)), Some(Material::Stone));
+        let _ = fs::remove_dir_all(&tiers.swap_dir);
+    }
+
+    #[test]
+    fn chunks_are_kept_without_idle_times() {
+        let chunk = generated_chunk();
+        let mut tiers = ChunkTiers::default();
+        let settings = TierSettings { compress_after: 0.0, evict_after: 0.0 };
+        for _ in 0..5 {
+            assert_eq!(tiers.update(slice::from_ref(&chunk), 10.0, settings), (0, 0));
+        }
+        assert_eq!(chunk.tier(), BlockTier::Decoded);
+    }
+}
@@ -0,0 +1,313 @@
+//! Types representing the time and the sky of a world
+
+use crate::timestep::TimeStep;
+use cgmath::{Vector3, VectorSpace};
+use std::f32::consts::PI;
+
+/// The default length of a full day in seconds
+pub const DEFAULT_DAY_LENGTH: f32 = 1200.0;
+/// The color of the sky at day
+const DAY_SKY_COLOR: [f32; 3] = [0.23, 0.38, 0.47];
+/// The color of the sky at night
+const NIGHT_SKY_COLOR: [f32; 3] = [0.01, 0.02, 0.05];
+/// The color the sky is tinted with at sunrise and sunset
+const TWILIGHT_SKY_COLOR: [f32; 3] = [0.75, 0.42, 0.25];
+/// The color of the sky of underground dimensions
+const UNDERGROUND_SKY_COLOR: [f32; 3] = [0.02, 0.02, 0.03];
+
+/// WorldTime
+///
+/// The `WorldTime` keeps track of the time of day.
+/// The time of day is in the range `[0, 1)`, where
+/// `0` is sunrise, `0.25` noon, `0.5` sunset and
+/// `0.75` midnight.
+///
+/// The sun and the moon travel around the world on
+/// opposite sides, rising in the east (positive x) and
+/// setting in the west (negative x).
+#[derive(Copy, Clone, Debug)]
+pub struct WorldTime {
+    /// The time of day in the range `[0, 1)`
+    time_of_day: f32,
+    /// The length of a full day in seconds
+    day_length: f32,
+    /// The amount of full days passed
+    day: u64,
+}
+
+impl Default for WorldTime {
+    fn default() -> Self {
+        Self::new(0.1, DEFAULT_DAY_LENGTH)
+    }
+}
+
+impl WorldTime {
+    /// Creates a new world time
+    ///
+    /// # Arguments
+    ///
+    /// * `time_of_day` - The time of day in the range `[0, 1)`
+    /// * `day_length` - The length of a full day in seconds
+    pub fn new(time_of_day: f32, day_length: f32) -> Self {
+        Self {
+            time_of_day: time_of_day.rem_euclid(1.0),
+            day_length: day_length.max(1.0),
+            day: 0,
+        }
+    }
+
+    /// Advances the time by the given time step
+    ///
+    /// # Arguments
+    ///
+    /// * `time_step` - The time passed since the last update
+    pub fn advance(&mut self, time_step: TimeStep) {
+        self.time_of_day += time_step.seconds() / self.day_length;
+        while self.time_of_day >= 1.0 {
+            self.time_of_day -= 1.0;
+            self.day += 1;
+        }
+    }
+
+    /// Returns the time of day in the range `[0, 1)`
+    pub fn time_of_day(&self) -> f32 {
+        self.time_of_day
+    }
+
+    /// Sets the time of day
+    ///
+    /// # Arguments
+    ///
+    /// * `time_of_day` - The time of day in the range `[0, 1)`
+    pub fn set_time_of_day(&mut self, time_of_day: f32) {
+        self.time_of_day = time_of_day.rem_euclid(1.0);
+    }
+
+    /// Fast-forwards to the next time the given time of day
+    /// is reached, which starts a new day if it's earlier than
+    /// the current time of day, e.g. when sleeping through a night
+    ///
+    /// # Arguments
+    ///
+    /// * `time_of_day` - The time of day in the range `[0, 1)`
+    pub fn skip_to(&mut self, time_of_day: f32) {
+        let time_of_day = time_of_day.rem_euclid(1.0);
+        if time_of_day < self.time_of_day {
+            self.day += 1;
+        }
+        self.time_of_day = time_of_day;
+    }
+
+    /// Returns the length of a full day in seconds
+    pub fn day_length(&self) -> f32 {
+        self.day_length
+    }
+
+    /// Sets the length of a full day, which keeps
+    /// the time of day
+    ///
+    /// # Arguments
+    ///
+    /// * `day_length` - The length of a full day in seconds
+    pub fn set_day_length(&mut self, day_length: f32) {
+        self.day_length = day_length.max(1.0);
+    }
+
+    /// Returns the amount of full days passed
+    pub fn day(&self) -> u64 {
+        self.day
+    }
+
+    /// Returns the angle of the sun above the eastern
+    /// horizon in radians
+    pub fn sun_angle(&self) -> f32 {
+        self.time_of_day * 2.0 * PI
+    }
+
+    /// Returns the normalized direction towards the sun
+    pub fn sun_direction(&self) -> Vector3<f32> {
+        let angle = self.sun_angle();
+        Vector3::new(angle.cos(), angle.sin(), 0.0)
+    }
+
+    /// Returns the normalized direction towards the moon,
+    /// which is always opposite of the sun
+    pub fn moon_direction(&self) -> Vector3<f32> {
+        -self.sun_direction()
+    }
+
+    /// Returns the amount of daylight in the range `[0, 1]`.
+    /// The daylight fades in and out while the sun is close
+    /// to the horizon.
+    pub fn daylight(&self) -> f32 {
+        const TWILIGHT: f32 = 0.2;
+        (self.sun_direction().y / TWILIGHT + 0.5).clamp(0.0, 1.0)
+    }
+
+    /// Returns the darkness in the range `[0, 1]`, the
+    /// inverse of the daylight
+    pub fn darkness(&self) -> f32 {
+        1.0 - self.daylight()
+    }
+
+}
+
+/// SkySettings
+///
+/// The `SkySettings` describe the sky of a dimension,
+/// e.g. an underground dimension has a dark sky without
+/// any sun, moon or stars.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SkySettings {
+    /// The color of the sky at day
+    pub day_color: Vector3<f32>,
+    /// The color of the sky at night
+    pub night_color: Vector3<f32>,
+    /// The color the sky is tinted with at sunrise and sunset
+    pub twilight_color: Vector3<f32>,
+    /// Whether the sun, the moon and the stars are shown
+    pub celestial_bodies: bool,
+    /// The minimal brightness of the blocks in the range `[0, 1]`
+    pub ambient_light: f32,
+    /// Whether the day and night cycle applies. Otherwise,
+    /// the sky keeps its day color and the blocks are lit
+    /// by the ambient light only.
+    pub daylight: bool,
+    /// Whether weather applies to the dimension
+    pub weather: bool,
+}
+
+impl Default for SkySettings {
+    fn default() -> Self {
+        Self {
+            day_color: Vector3::from(DAY_SKY_COLOR),
+            night_color: Vector3::from(NIGHT_SKY_COLOR),
+            twilight_color: Vector3::from(TWILIGHT_SKY_COLOR),
+            celestial_bodies: true,
+            ambient_light: 1.0,
+            daylight: true,
+            weather: true,
+        }
+    }
+}
+
+impl SkySettings {
+    /// Returns the settings of a dark sky without any
+    /// celestial bodies, e.g. for caves
+    pub fn underground() -> Self {
+        Self {
+            day_color: Vector3::from(UNDERGROUND_SKY_COLOR),
+            night_color: Vector3::from(UNDERGROUND_SKY_COLOR),
+            twilight_color: Vector3::from(UNDERGROUND_SKY_COLOR),
+            celestial_bodies: false,
+            ambient_light: 0.7,
+            daylight: false,
+            weather: false,
+        }
+    }
+
+    /// Returns the color of the sky. It fades from the day
+    /// to the night color with the daylight and is tinted
+    /// while the sun is close to the horizon.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The time of the world
+    pub fn color(&self, time: &WorldTime) -> Vector3<f32> {
+        if !self.daylight {
+            return self.day_color;
+        }
+        let color = self.night_color.lerp(self.day_color, time.daylight());
+        let twilight = (1.0 - time.sun_direction().y.abs() / 0.25).max(0.0);
+        color.lerp(self.twilight_color, twilight * 0.5)
+    }
+
+    /// Returns how dark the sky is, from `0.0` at day
+    /// to `1.0` at night
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The time of the world
+    pub fn darkness(&self, time: &WorldTime) -> f32 {
+        if self.daylight { time.darkness() } else { 0.0 }
+    }
+
+    /// Returns the brightness of the blocks in the range
+    /// `[0, 1]`, which is never below the ambient light
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The time of the world
+    pub fn light(&self, time: &WorldTime) -> f32 {
+        let daylight = if self.daylight { time.daylight() } else { 0.0 };
+        daylight.max(self.ambient_light).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::InnerSpace;
+
+    fn assert_color_eq(actual: Vector3<f32>, expected: [f32; 3]) {
+        let expected = Vector3::from(expected);
+        assert!((actual - expected).magnitude() < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn advance_wraps_into_next_day() {
+        let mut time = WorldTime::new(0.9, 100.0);
+        time.advance(TimeStep(20.0));
+        assert!((time.time_of_day() - 0.1).abs() < 1e-5);
+        assert_eq!(time.day(), 1);
+    }
+
+    #[test]
+    fn skipping_past_midnight_starts_next_day() {
+        let mut time = WorldTime::new(0.8, 100.0);
+        time.skip_to(0.02);
+        assert_eq!(time.time_of_day(), 0.02);
+        assert_eq!(time.day(), 1);
+
+        time.skip_to(0.3);
+        assert_eq!(time.day(), 1);
+    }
+
+    #[test]
+    fn daylight_at_noon_and_midnight() {
+        assert_eq!(WorldTime::new(0.25, 100.0).daylight(), 1.0);
+        assert_eq!(WorldTime::new(0.75, 100.0).daylight(), 0.0);
+        assert_eq!(WorldTime::new(0.75, 100.0).darkness(), 1.0);
+    }
+
+    #[test]
+    fn sky_color_follows_time_of_day() {
+        let sky = SkySettings::default();
+        assert_color_eq(sky.color(&WorldTime::new(0.25, 100.0)), DAY_SKY_COLOR);
+        assert_color_eq(sky.color(&WorldTime::new(0.75, 100.0)), NIGHT_SKY_COLOR);
+
+        // The sky is tinted at sunrise
+        let sunrise = sky.color(&WorldTime::new(0.0, 100.0));
+        assert!(sunrise.x > sunrise.z);
+    }
+
+    #[test]
+    fn light_never_falls_below_ambient_light() {
+        let sky = SkySettings { ambient_light: 0.3, ..SkySettings::default() };
+        assert_eq!(sky.light(&WorldTime::new(0.25, 100.0)), 1.0);
+        assert_eq!(sky.light(&WorldTime::new(0.75, 100.0)), 0.3);
+
+        let sky = SkySettings { daylight: false, ..sky };
+        assert_eq!(sky.light(&WorldTime::new(0.25, 100.0)), 0.3);
+        assert_eq!(sky.darkness(&WorldTime::new(0.75, 100.0)), 0.0);
+        assert_color_eq(sky.color(&WorldTime::new(0.75, 100.0)), DAY_SKY_COLOR);
+    }
+
+    #[test]
+    fn underground_sky_stays_dark() {
+        let sky = SkySettings::underground();
+        for &time_of_day in &[0.0, 0.25, 0.5, 0.75] {
+            assert_color_eq(sky.color(&WorldTime::new(time_of_day, 100.0)), UNDERGROUND_SKY_COLOR);
+        }
+    }
+}
@@ -0,0 +1,474 @@
+//! Types to manage multiple dimensions, each
+//! being a world of its own
+
+use crate::world::World;
+use crate::world::biome::BiomeRegistry;
+use crate::world::border::WorldBorder;
+use crate::world::entity_types::EntityRegistry;
+use crate::world::pregen::PregenCache;
+use crate::world::save::{SaveDir, SaveError};
+use crate::world::vegetation::Plants;
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+
+/// The name of the dimension the player spawns in
+pub const OVERWORLD: &str = "overworld";
+/// The name of the underground dimension
+pub const CAVES: &str = "caves";
+
+/// Universe
+///
+/// The `Universe` holds all dimensions by their name.
+/// Each dimension is a `World` with its own terrain
+/// generator, chunks and sky. The player is in exactly
+/// one dimension at a time, the active one, which the
+/// universe dereferences to.
+pub struct Universe {
+    /// The dimensions by their name
+    dimensions: BTreeMap<String, World>,
+    /// The name of the active dimension
+    active: String,
+    /// The border of all dimensions
+    border: Option<WorldBorder>,
+    /// The length of a full day in seconds in all dimensions,
+    /// unless each keeps its own
+    day_length: Option<f32>,
+    /// The plants decorating the terrain of all dimensions
+    plants: Plants,
+    /// The spawn functions of the entity types of all dimensions
+    entity_types: EntityRegistry,
+    /// The definitions of the biomes of the dimensions'
+    /// generators, which scripts define
+    biomes: BiomeRegistry,
+}
+
+impl Universe {
+    /// Creates a new universe with a single dimension,
+    /// which is the active one
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the dimension
+    /// * `world` - The world of the dimension
+    pub fn new(name: &str, world: World) -> Self {
+        let mut dimensions = BTreeMap::new();
+        dimensions.insert(name.to_string(), world);
+        Self {
+            dimensions,
+            active: name.to_string(),
+            border: None,
+            day_length: None,
+            plants: Plants::default(),
+            entity_types: EntityRegistry::default(),
+            biomes: BiomeRegistry::default(),
+        }
+    }
+
+    /// Adds a dimension. Returns `false` and keeps the existing
+    /// dimension if there is one with the same name already.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the dimension
+    /// * `world` - The world of the dimension
+    pub fn add(&mut self, name: &str, mut world: World) -> bool {
+        if self.dimensions.contains_key(name) {
+            return false;
+        }
+        world.set_border(self.border);
+        if let Some(day_length) = self.day_length {
+            world.time_mut().set_day_length(day_length);
+        }
+        world.set_plants(self.plants.clone());
+        world.set_entity_types(self.entity_types.clone());
+        self.dimensions.insert(name.to_string(), world);
+        true
+    }
+
+    /// Sets the border of all dimensions, including the
+    /// ones which are added later on
+    ///
+    /// # Arguments
+    ///
+    /// * `border` - The border, `None` for no border
+    pub fn set_border(&mut self, border: Option<WorldBorder>) {
+        self.border = border;
+        for world in self.dimensions.values_mut() {
+            world.set_border(border);
+        }
+    }
+
+    /// Sets the length of a full day in all dimensions,
+    /// including the ones which are added later on
+    ///
+    /// # Arguments
+    ///
+    /// * `day_length` - The length of a full day in seconds
+    pub fn set_day_length(&mut self, day_length: f32) {
+        self.day_length = Some(day_length);
+        for world in self.dimensions.values_mut() {
+            world.time_mut().set_day_length(day_length);
+        }
+    }
+
+    /// Sets the plants decorating the terrain of all
+    /// dimensions, including the ones which are added
+    /// later on
+    ///
+    /// # Arguments
+    ///
+    /// * `plants` - The plants
+    pub fn set_plants(&mut self, plants: Plants) {
+        for world in self.dimensions.values_mut() {
+            world.set_plants(plants.clone());
+        }
+        self.plants = plants;
+    }
+
+    /// Sets the spawn functions saved entities are spawned by
+    /// in all dimensions, including the ones which are added
+    /// later on
+    ///
+    /// # Arguments
+    ///
+    /// * `entity_types` - The spawn functions of the entity types
+    pub fn set_entity_types(&mut self, entity_types: EntityRegistry) {
+        for world in self.dimensions.values_mut() {
+            world.set_entity_types(entity_types.clone());
+        }
+        self.entity_types = entity_types;
+    }
+
+    /// Sets the definitions of the biomes, which should be the
+    /// ones the generators of the dimensions share
+    ///
+    /// # Arguments
+    ///
+    /// * `biomes` - The definitions of the biomes
+    pub fn set_biomes(&mut self, biomes: BiomeRegistry) {
+        self.biomes = biomes;
+    }
+
+    /// Returns the definitions of the biomes
+    pub fn biomes(&self) -> &BiomeRegistry {
+        &self.biomes
+    }
+
+    /// Returns whether there is a dimension with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the dimension
+    pub fn contains(&self, name: &str) -> bool {
+        self.dimensions.contains_key(name)
+    }
+
+    /// Returns the dimension with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the dimension
+    pub fn dimension(&self, name: &str) -> Option<&World> {
+        self.dimensions.get(name)
+    }
+
+    /// Returns the mutable dimension with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the dimension
+    pub fn dimension_mut(&mut self, name: &str) -> Option<&mut World> {
+        self.dimensions.get_mut(name)
+    }
+
+    /// Returns all dimensions with their names
+    pub fn dimensions_mut(&mut self) -> impl Iterator<Item = (&str, &mut World)> {
+        self.dimensions.iter_mut().map(|(name, world)| (name.as_str(), world))
+    }
+
+    /// Returns the name of the active dimension
+    pub fn active_name(&self) -> &str {
+        &self.active
+    }
+
+    /// Makes the dimension with the given name the active one.
+    /// The chunks of the previously active dimension are unloaded,
+    /// its edited blocks are kept. Returns `false` and keeps the
+    /// active dimension if there is no dimension with the name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the dimension
+    pub fn switch_to(&mut self, name: &str) -> bool {
+        if !self.dimensions.contains_key(name) {
+            return false;
+        }
+        if self.active != name {
+            self.deref_mut().unload_chunks();
+            self.active = name.to_string();
+        }
+        true
+    }
+
+    /// Restores the edited blocks of all dimensions from
+    /// their directories within the save directory. Their
+    /// chunks are restored from the pregenerated terrain
+    /// from now on.
+    ///
+    /// # Arguments
+    ///
+    /// * `save_dir` - The directory the world is saved in
+    pub fn load(&mut self, save_dir: &SaveDir) -> Result<(), SaveError> {
+        for (name, world) in self.dimensions.iter_mut() {
+            let dir = save_dir.dimension(name)?;
+            for region in dir.load_regions()? {
+                world.load_region(&region);
+            }
+            world.set_pregenerated(Some(PregenCache::new(dir)));
+        }
+        Ok(())
+    }
+
+    /// Saves the regions of all dimensions which have been
+    /// edited since they have been saved the last time
+    ///
+    /// # Arguments
+    ///
+    /// * `save_dir` - The directory the world is saved in
+    pub fn save(&mut self, save_dir: &SaveDir) -> Result<(), SaveError> {
+        for (name, world) in self.dimensions.iter_mut() {
+            let regions = world.take_dirty_regions();
+            if regions.is_empty() {
+                continue;
+            }
+
+            let dir = save_dir.dimension(name)?;
+            for region in regions {
+                dir.save_region(&region)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Deref for Universe {
+    type Target = World;
+
+    fn deref(&self) -> &Self::Target {
+        &self.dimensions[&self.active]
+    }
+}
+
+impl DerefMut for Universe {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.dimensions.get_mut(&self.active).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::Material;
+    use crate::world::chunk_entities::{ChunkEntity, SavedEntity, ITEM_TYPE};
+    use crate::world::save::{RegionData, SavedBlock};
+    use crate::world::terrain_generator::CaveTerrainGen;
+    use crate::world::time::SkySettings;
+    use cgmath::{Vector2, Vector3};
+    use std::fs;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn dimensions() -> Universe {
+        let mut universe = Universe::new(OVERWORLD, World::new());
+        assert!(universe.add(CAVES, World::with_generator(CaveTerrainGen::default(), SkySettings::underground())));
+        universe
+    }
+
+    fn save_dir(name: &str) -> SaveDir {
+        let path = std::env::temp_dir().join(format!("rustcraft-universe-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        SaveDir::new(path).unwrap()
+    }
+
+    fn block(pos: [i32; 3], material: Material) -> SavedBlock {
+        SavedBlock { pos, material: material.name().to_string(), state: 0 }
+    }
+
+    #[test]
+    fn dimensions_are_switched_by_name() {
+        let mut universe = dimensions();
+        assert!(!universe.add(CAVES, World::new()));
+        assert_eq!(universe.active_name(), OVERWORLD);
+        assert!(universe.sky().celestial_bodies);
+
+        assert!(!universe.switch_to("nether"));
+        assert_eq!(universe.active_name(), OVERWORLD);
+
+        assert!(universe.switch_to(CAVES));
+        assert_eq!(universe.active_name(), CAVES);
+        assert!(!universe.sky().celestial_bodies);
+    }
+
+    #[test]
+    fn border_applies_to_all_dimensions() {
+        let mut universe = dimensions();
+        universe.load_area(&Vector3::new(0.0, 0.0, 0.0), 2);
+        assert_eq!(universe.chunks().len(), 25);
+
+        universe.set_border(Some(WorldBorder::new(16)));
+        assert_eq!(universe.chunks().len(), 4);
+        universe.load_chunk(&Vector2::new(2, 0));
+        assert!(universe.chunk(&Vector2::new(2, 0)).is_none());
+
+        assert!(universe.add("nether", World::new()));
+        assert!(universe.dimension("nether").unwrap().border().is_some());
+        assert!(universe.dimension(CAVES).unwrap().border().is_some());
+    }
+
+    #[test]
+    fn day_length_applies_to_all_dimensions() {
+        let mut universe = dimensions();
+        universe.set_day_length(600.0);
+        assert!(universe.add("nether", World::new()));
+        for (_, world) in universe.dimensions_mut() {
+            assert_eq!(world.time().day_length(), 600.0);
+        }
+    }
+
+    /// Loads the chunk at the origin of the active dimension and
+    /// waits until the block at the given position has the material
+    fn wait_for_block(universe: &mut Universe, pos: Vector3<i32>, material: Material) {
+        universe.load_chunk(&Vector2::new(0, 0));
+        let start = Instant::now();
+        while universe.block(pos) != Some(material) {
+            assert!(start.elapsed() < Duration::from_secs(10), "{:?} hasn't been generated", pos);
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn loaded_regions_are_not_saved_again() {
+        let save_dir = save_dir("loaded");
+        let mut universe = dimensions();
+        universe.dimension_mut(CAVES).unwrap().load_region(&RegionData {
+            loc: [0, 0],
+            blocks: vec![block([1, 2, 3], Material::Grass)],
+            ..RegionData::default()
+        });
+
+        universe.save(&save_dir).unwrap();
+        assert!(save_dir.dimension(CAVES).unwrap().load_regions().unwrap().is_empty());
+        let _ = fs::remove_dir_all(save_dir.root_path());
+    }
+
+    #[test]
+    fn edits_are_saved_per_dimension() {
+        let save_dir = save_dir("edits");
+        let mut universe = dimensions();
+        wait_for_block(&mut universe, Vector3::new(0, 0, 0), Material::Dirt);
+        universe.set_block(Vector3::new(0, 200, 0), Material::Grass);
+        universe.save(&save_dir).unwrap();
+
+        let regions = save_dir.dimension(OVERWORLD).unwrap().load_regions().unwrap();
+        assert_eq!(regions, vec![RegionData {
+            loc: [0, 0],
+            blocks: vec![block([0, 200, 0], Material::Grass)],
+            ..RegionData::default()
+        }]);
+        assert!(save_dir.dimension(CAVES).unwrap().load_regions().unwrap().is_empty());
+
+        // The edit is restored once the chunk is generated again
+        let mut loaded = dimensions();
+        loaded.load(&save_dir).unwrap();
+        wait_for_block(&mut loaded, Vector3::new(0, 200, 0), Material::Grass);
+
+        // But not in the other dimension
+        loaded.switch_to(CAVES);
+        wait_for_block(&mut loaded, Vector3::new(0, 0, 0), Material::Stone);
+        assert_eq!(loaded.block(Vector3::new(0, 200, 0)), Some(Material::Air));
+        let _ = fs::remove_dir_all(save_dir.root_path());
+    }
+
+    #[test]
+    fn edits_survive_unloading_and_restarts() {
+        let save_dir = save_dir("unloaded");
+        let mut universe = dimensions();
+        wait_for_block(&mut universe, Vector3::new(0, 0, 0), Material::Dirt);
+        let pos = Vector3::new(3, 200, 3);
+        universe.set_block(pos, Material::Obsidian);
+
+        // The edit is kept while the chunk is unloaded
+        universe.unload_chunk(&Vector2::new(0, 0));
+        assert_eq!(universe.block(pos), None);
+        wait_for_block(&mut universe, pos, Material::Obsidian);
+
+        // And written to its region once saved
+        universe.unload_chunks();
+        universe.save(&save_dir).unwrap();
+        let mut loaded = dimensions();
+        loaded.load(&save_dir).unwrap();
+        wait_for_block(&mut loaded, pos, Material::Obsidian);
+        let _ = fs::remove_dir_all(save_dir.root_path());
+    }
+
+    #[test]
+    fn block_states_are_saved() {
+        let save_dir = save_dir("states");
+        let mut universe = dimensions();
+        wait_for_block(&mut universe, Vector3::new(0, 0, 0), Material::Dirt);
+        let pos = Vector3::new(0, 200, 0);
+        universe.set_block(pos, Material::Wheat);
+        assert_eq!(universe.set_block_state(pos, 5), Some(0));
+        universe.save(&save_dir).unwrap();
+
+        let mut loaded = dimensions();
+        loaded.load(&save_dir).unwrap();
+        wait_for_block(&mut loaded, pos, Material::Wheat);
+        let start = Instant::now();
+        while !loaded.chunk(&Vector2::new(0, 0)).unwrap().is_generated() {
+            assert!(start.elapsed() < Duration::from_secs(10), "the chunk hasn't been generated");
+            thread::yield_now();
+        }
+        assert_eq!(loaded.block_state(pos), Some(5));
+
+        // A different block resets the state
+        loaded.set_block(pos, Material::Dirt);
+        assert_eq!(loaded.block_state(pos), Some(0));
+        let _ = fs::remove_dir_all(save_dir.root_path());
+    }
+
+    #[test]
+    fn entities_are_saved_with_their_chunk() {
+        let save_dir = save_dir("entities");
+        let mut universe = dimensions();
+        universe.spawn_item("cobblestone", 2, Vector3::new(4.5, 200.0, 4.5));
+
+        // The items of the chunks left behind are stored
+        universe.switch_to(CAVES);
+        assert!(universe.dimension(OVERWORLD).unwrap().items().items().is_empty());
+        universe.save(&save_dir).unwrap();
+        let regions = save_dir.dimension(OVERWORLD).unwrap().load_regions().unwrap();
+        assert_eq!(regions.len(), 1);
+        assert!(regions[0].blocks.is_empty());
+        let uuid = regions[0].entities[0].uuid;
+        assert_eq!(regions[0].entities, vec![SavedEntity::new(ITEM_TYPE, uuid, Vector3::new(4.5, 200.0, 4.5))
+            .with("item", "cobblestone")
+            .with("count", 2)
+            .with("age", 0.0)]);
+
+        // And dropped again once their chunk is generated
+        let mut loaded = dimensions();
+        loaded.load(&save_dir).unwrap();
+        wait_for_block(&mut loaded, Vector3::new(0, 0, 0), Material::Dirt);
+        let start = Instant::now();
+        while loaded.items().items().is_empty() {
+            assert!(start.elapsed() < Duration::from_secs(10), "the items haven't been restored");
+            loaded.update_items(0.0, Vector3::new(100.0, 0.0, 100.0), 1, 16);
+            thread::yield_now();
+        }
+        let item = loaded.items().items().iter().next().unwrap();
+        assert_eq!(loaded.items().stack(item).unwrap().count, 2);
+        assert_eq!(ChunkEntity::uuid(item), uuid);
+        let _ = fs::remove_dir_all(save_dir.root_path());
+    }
+}
@@ -0,0 +1,322 @@
+//! Types to decorate the generated terrain with plants,
+//! e.g. tall grass, flowers and cave mushrooms
+
+use crate::scripting::ScriptEngine;
+use crate::world::biome::Biome;
+use crate::world::block::Material;
+use crate::world::chunk::{Chunk, CHUNK_AREA, CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::world::tags::Tags;
+use crate::world::terrain_generator::TerrainGen;
+use crate::world::ticks::TickRng;
+use cgmath::Vector3;
+use mlua::Table;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// PlantDef
+///
+/// A plant which is placed on the generated terrain
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlantDef {
+    /// The block of the plant
+    pub block: Material,
+    /// The blocks the plant grows on
+    pub on: Vec<Material>,
+    /// The chance of a column to grow the plant in biomes
+    /// which aren't listed, and in worlds without biomes
+    pub density: f64,
+    /// The chance of a column to grow the plant by biome
+    pub biomes: HashMap<Biome, f64>,
+    /// Whether the plant grows in the dark below a cover,
+    /// e.g. a cave ceiling, rather than under the open sky
+    pub dark: bool,
+}
+
+impl PlantDef {
+    /// Returns the chance of a column to grow the plant
+    ///
+    /// # Arguments
+    ///
+    /// * `biome` - The biome of the column, if there is one
+    pub fn density(&self, biome: Option<&Biome>) -> f64 {
+        biome.and_then(|biome| self.biomes.get(biome).copied()).unwrap_or(self.density)
+    }
+}
+
+/// Plants
+///
+/// The plants decorating the terrain, shared by the
+/// worlds with the registry the scripts define them in
+#[derive(Clone, Default)]
+pub struct Plants {
+    /// The defined plants in the order of their definition
+    defined: Arc<Mutex<Vec<PlantDef>>>,
+}
+
+impl Plants {
+    /// Places plants on a generated chunk. Each column grows
+    /// at most one plant, the first one of the defined plants
+    /// its roll succeeds for. The rolls only depend on the
+    /// position of the column, so a chunk is decorated the
+    /// same way each time it's generated.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The generated chunk
+    /// * `terrain_gen` - The generator of the terrain of the chunk
+    /// * `height_map` - The height map the chunk has been generated with
+    pub fn decorate(&self, chunk: &Chunk, terrain_gen: &dyn TerrainGen, height_map: &[i32; CHUNK_AREA]) {
+        let plants = self.defined.lock().unwrap().clone();
+        if plants.is_empty() {
+            return;
+        }
+
+        let loc = chunk.loc();
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let height = height_map[z * CHUNK_SIZE + x];
+                if height < 0 || height + 1 >= CHUNK_HEIGHT as i32 {
+                    continue;
+                }
+                let ground = Vector3::new(x as i16, height as i16, z as i16);
+                let above = ground + Vector3::new(0, 1, 0);
+                if chunk.block(above) != Some(Material::Air) {
+                    continue;
+                }
+
+                let block_x = loc.x * CHUNK_SIZE as i32 + x as i32;
+                let block_z = loc.y * CHUNK_SIZE as i32 + z as i32;
+                let biome = terrain_gen.biome(block_x, block_z);
+                let mut rng = column_rng(block_x, block_z, 0);
+                let ground_block = chunk.block(ground);
+
+                let plant = plants.iter().find(|plant| {
+                    rng.unit() < plant.density(biome.as_ref()) && ground_block.map_or(false, |ground| plant.on.contains(&ground)) && is_covered(chunk, above) == plant.dark
+                });
+                if let Some(plant) = plant {
+                    chunk.set_block(above, plant.block);
+                }
+            }
+        }
+    }
+}
+
+/// Returns whether there is an opaque block above the
+/// given position, which keeps the sky from lighting it
+///
+/// # Arguments
+///
+/// * `chunk` - The chunk
+/// * `pos` - The position in the chunk
+fn is_covered(chunk: &Chunk, pos: Vector3<i16>) -> bool {
+    (pos.y + 1..CHUNK_HEIGHT as i16)
+        .any(|y| matches!(chunk.block(Vector3::new(pos.x, y, pos.z)), Some(block) if block.data().is_opaque()))
+}
+
+/// Returns the generator rolling the decorations of a column,
+/// e.g. its plants
+///
+/// # Arguments
+///
+/// * `x` - The x coordinate of the column
+/// * `z` - The z coordinate of the column
+/// * `salt` - A number distinguishing the kinds of decorations
+pub(crate) fn column_rng(x: i32, z: i32, salt: u64) -> TickRng {
+    let hash = (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (z as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ salt.wrapping_mul(0x1656_67B1_9E37_79F9);
+    let mut rng = TickRng::new(hash);
+    // The first numbers of seeds which are alike are alike too
+    for _ in 0..4 {
+        rng.below(2);
+    }
+    rng
+}
+
+/// Vegetation
+///
+/// The plants defined by scripts through the
+/// `vegetation` API, e.g.
+///
+/// ```lua
+/// vegetation.define {
+///     block = "tall_grass",
+///     -- The block the plant grows on, or a tag of blocks
+///     on = "grass",
+///     -- The chance of a column to grow the plant
+///     density = 0.05,
+///     -- The chances in single biomes
+///     biomes = { plains = 0.3, mountains = 0.01 },
+/// }
+///
+/// -- Plants in the dark grow below a cover like a cave
+/// -- ceiling rather than under the open sky
+/// vegetation.define { block = "mushroom", on = "stone", density = 0.04, dark = true }
+/// ```
+///
+/// Plants are placed on chunks generated after their
+/// definition. A plant defined again replaces its
+/// previous definition.
+pub struct Vegetation {
+    /// The defined plants, shared with the Lua API
+    plants: Plants,
+}
+
+impl Vegetation {
+    /// Creates a new plant registry and registers its
+    /// API in the given script engine
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The script engine
+    /// * `tags` - The tags definitions could refer to
+    pub fn new(engine: &ScriptEngine, tags: &Tags) -> mlua::Result<Self> {
+        let plants = Plants::default();
+
+        let lua = engine.lua();
+        let api = lua.create_table()?;
+        let defined = plants.defined.clone();
+        let tags = tags.clone();
+        api.set("define", lua.create_function(move |_, def: Table| {
+            let plant = plant_from_table(def, &tags)?;
+            let mut defined = defined.lock().unwrap();
+            match defined.iter_mut().find(|other| other.block == plant.block) {
+                Some(other) => *other = plant,
+                None => defined.push(plant),
+            }
+            Ok(())
+        })?)?;
+        lua.globals().set("vegetation", api)?;
+
+        Ok(Self {
+            plants,
+        })
+    }
+
+    /// Returns the plants, which are shared with the registry
+    pub fn plants(&self) -> Plants {
+        self.plants.clone()
+    }
+}
+
+/// Reads the definition of a plant from a Lua table
+///
+/// # Arguments
+///
+/// * `def` - The Lua table
+/// * `tags` - The tags the ground could refer to
+fn plant_from_table(def: Table, tags: &Tags) -> mlua::Result<PlantDef> {
+    let material = |key: &str| -> mlua::Result<Material> {
+        let name: String = def.get(key)?;
+        Material::from_name(&name).ok_or_else(|| {
+            mlua::Error::RuntimeError(format!("unknown block {}", name))
+        })
+    };
+
+    let mut biomes = HashMap::new();
+    if let Some(table) = def.get::<_, Option<Table>>("biomes")? {
+        for pair in table.pairs::<String, f64>() {
+            let (name, density) = pair?;
+            biomes.insert(Biome::new(&name), density.clamp(0.0, 1.0));
+        }
+    }
+
+    Ok(PlantDef {
+        block: material("block")?,
+        on: tags.materials(&def.get::<_, String>("on")?)?,
+        density: def.get::<_, Option<f64>>("density")?.unwrap_or(0.0).clamp(0.0, 1.0),
+        biomes,
+        dark: def.get::<_, Option<bool>>("dark")?.unwrap_or(false),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::biome::BiomeTerrainGen;
+    use crate::world::terrain_generator::{CaveTerrainGen, FlatTerrainGen};
+    use cgmath::Vector2;
+
+    fn vegetation() -> (ScriptEngine, Vegetation) {
+        let engine = ScriptEngine::new();
+        let tags = Tags::new(&engine).unwrap();
+        let vegetation = Vegetation::new(&engine, &tags).unwrap();
+        engine.exec("test", r#"
+            vegetation.define { block = "tall_grass", on = "grass", density = 0.5, biomes = { ocean = 0 } }
+            vegetation.define { block = "flower", on = "grass", density = 1 }
+            vegetation.define { block = "mushroom", on = "stone", density = 0.2, dark = true }
+        "#).unwrap();
+        (engine, vegetation)
+    }
+
+    /// Generates a chunk and counts its blocks of the given material
+    fn count_blocks(generator: &dyn TerrainGen, plants: &Plants, loc: Vector2<i32>, material: Material) -> usize {
+        let chunk = Chunk::new(loc);
+        let height_map = generator.gen_heightmap(&loc);
+        generator.gen_smooth_terrain(&chunk, &height_map);
+        plants.decorate(&chunk, generator, &height_map);
+
+        let mut count = 0;
+        for y in 0..CHUNK_HEIGHT as i16 {
+            for z in 0..CHUNK_SIZE as i16 {
+                for x in 0..CHUNK_SIZE as i16 {
+                    if chunk.block(Vector3::new(x, y, z)) == Some(material) {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn plants_are_placed_by_density() {
+        let (_engine, vegetation) = vegetation();
+        let plants = vegetation.plants();
+        let generator = FlatTerrainGen::default();
+        let loc = Vector2::new(3, -2);
+
+        // Every column the tall grass is rolled out of grows a flower
+        let grass = count_blocks(&generator, &plants, loc, Material::TallGrass);
+        let flowers = count_blocks(&generator, &plants, loc, Material::Flower);
+        assert!(grass > CHUNK_AREA / 4 && grass < CHUNK_AREA * 3 / 4, "{} tall grass", grass);
+        assert_eq!(grass + flowers, CHUNK_AREA);
+        assert_eq!(grass, count_blocks(&generator, &plants, loc, Material::TallGrass));
+
+        // Mushrooms don't grow under the open sky
+        assert_eq!(count_blocks(&generator, &plants, loc, Material::Mushroom), 0);
+        assert!(count_blocks(&CaveTerrainGen::default(), &plants, loc, Material::Mushroom) > 0);
+    }
+
+    #[test]
+    fn densities_depend_on_the_biome() {
+        let (_engine, vegetation) = vegetation();
+        let plant = vegetation.plants().defined.lock().unwrap()[0].clone();
+        assert_eq!(plant.density(None), 0.5);
+        assert_eq!(plant.density(Some(&Biome::new("plains"))), 0.5);
+        assert_eq!(plant.density(Some(&Biome::new("ocean"))), 0.0);
+
+        // The surface of biomes is decorated too
+        let generator = BiomeTerrainGen::default();
+        let plants = vegetation.plants();
+        let decorated: usize = (0..4)
+            .map(|x| count_blocks(&generator, &plants, Vector2::new(x, 0), Material::Flower))
+            .sum();
+        assert!(decorated > 0);
+    }
+
+    #[test]
+    fn invalid_plants_are_rejected() {
+        let (engine, vegetation) = vegetation();
+        assert!(engine.exec("test", r#"vegetation.define { block = "cactus", on = "sand" }"#).is_err());
+        assert!(engine.exec("test", r#"vegetation.define { block = "flower" }"#).is_err());
+        assert!(engine.exec("test", r##"vegetation.define { block = "flower", on = "#meadow" }"##).is_err());
+
+        // Plants defined again replace their definition. They could
+        // grow in the biomes scripts add, whichever script runs first.
+        engine.exec("test", r#"vegetation.define { block = "flower", on = "sand", density = 0.1, biomes = { tundra = 1 } }"#).unwrap();
+        let plants = vegetation.plants().defined.lock().unwrap().clone();
+        assert_eq!(plants.len(), 3);
+        assert_eq!(plants[1].on, vec![Material::Sand]);
+        assert_eq!(plants[1].density(Some(&Biome::new("tundra"))), 1.0);
+    }
+}
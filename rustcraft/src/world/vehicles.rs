@@ -0,0 +1,563 @@
+//! Types representing vehicles the player could ride,
+//! e.g. boats floating on water
+
+use crate::entity::Aabb;
+use crate::graphics::mesh::{Mesh, MeshHandle};
+use crate::graphics::scene::{NodeId, Scene, SceneNode};
+use crate::world::block::Material;
+use crate::world::chunk_entities::{self, ChunkEntities, ChunkEntity, EntityUuid, SavedEntity, VEHICLE_TYPE};
+use cgmath::{Matrix4, Rad, Vector2, Vector3, Vector4};
+
+/// The width and depth of vehicles in blocks
+const VEHICLE_WIDTH: f32 = 1.0;
+/// The height of vehicles in blocks
+const VEHICLE_HEIGHT: f32 = 0.5;
+/// The height of the seat above the bottom of a vehicle,
+/// which the feet of the rider are at
+pub const SEAT_HEIGHT: f32 = 0.3;
+/// The acceleration of falling vehicles in blocks per second squared
+const GRAVITY: f32 = 20.0;
+/// The acceleration of a boat fully under water in blocks per
+/// second squared, so boats float half submerged
+const BUOYANCY: f32 = 2.0 * GRAVITY;
+/// The share of their vertical speed boats lose per second in water
+const WATER_DRAG: f32 = 3.0;
+/// The share of their horizontal speed vehicles lose per second
+/// without being steered
+const FRICTION: f32 = 1.5;
+/// The acceleration of steered vehicles in blocks per second squared
+const ACCELERATION: f32 = 8.0;
+/// The speed vehicles turn with in radians per second
+const TURN_SPEED: f32 = 2.0;
+/// The fastest falling speed of vehicles in blocks per second
+const MAX_FALL_SPEED: f32 = 20.0;
+
+/// VehicleKind
+///
+/// The kind of a vehicle:
+/// * `Boat` - Floats on water and crawls on land
+/// * `Minecart` - Rolls over the ground and sinks in water
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum VehicleKind {
+    Boat,
+    Minecart,
+}
+
+impl VehicleKind {
+    /// All kinds of vehicles
+    pub const ALL: [VehicleKind; 2] = [VehicleKind::Boat, VehicleKind::Minecart];
+
+    /// Returns the name of the kind, which is the name
+    /// of the item placing it as well
+    pub fn name(&self) -> &'static str {
+        match self {
+            VehicleKind::Boat => "boat",
+            VehicleKind::Minecart => "minecart",
+        }
+    }
+
+    /// Returns the kind with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the kind
+    pub fn from_name(name: &str) -> Option<VehicleKind> {
+        Self::ALL.iter().copied().find(|kind| kind.name() == name)
+    }
+
+    /// Returns the top speed in blocks per second on land
+    /// and in water
+    fn top_speed(&self, in_water: bool) -> f32 {
+        match (self, in_water) {
+            (VehicleKind::Boat, true) => 6.0,
+            (VehicleKind::Boat, false) => 1.0,
+            (VehicleKind::Minecart, false) => 5.0,
+            (VehicleKind::Minecart, true) => 0.5,
+        }
+    }
+
+    /// Returns the color the vehicle is shown with
+    fn color(&self) -> Vector4<f32> {
+        match self {
+            VehicleKind::Boat => Vector4::new(0.55, 0.38, 0.2, 1.0),
+            VehicleKind::Minecart => Vector4::new(0.45, 0.45, 0.5, 1.0),
+        }
+    }
+}
+
+/// Steering
+///
+/// How the rider steers a vehicle, where each
+/// value is in the range `[-1, 1]`
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Steering {
+    /// Forwards if positive, backwards if negative
+    pub forward: f32,
+    /// Clockwise if positive, as seen from above
+    pub turn: f32,
+}
+
+/// Vehicle
+///
+/// A vehicle standing or driving in the world
+pub struct Vehicle {
+    /// The id of the entity
+    uuid: EntityUuid,
+    /// The kind of the vehicle
+    kind: VehicleKind,
+    /// The position of the bottom center of the vehicle
+    pos: Vector3<f32>,
+    /// The velocity in blocks per second
+    velocity: Vector3<f32>,
+    /// The direction the vehicle faces in radians
+    yaw: f32,
+    /// The node the vehicle is shown with
+    node: NodeId,
+}
+
+impl Vehicle {
+    /// Returns the kind of the vehicle
+    pub fn kind(&self) -> VehicleKind {
+        self.kind
+    }
+
+    /// Returns the position of the bottom center of the vehicle
+    pub fn pos(&self) -> &Vector3<f32> {
+        &self.pos
+    }
+
+    /// Returns the position of the feet of the rider
+    pub fn seat(&self) -> Vector3<f32> {
+        self.pos + Vector3::new(0.0, SEAT_HEIGHT, 0.0)
+    }
+
+    /// Returns the box the vehicle occupies
+    pub fn aabb(&self) -> Aabb {
+        let half_width = VEHICLE_WIDTH / 2.0;
+        Aabb::new(
+            self.pos - Vector3::new(half_width, 0.0, half_width),
+            self.pos + Vector3::new(half_width, VEHICLE_HEIGHT, half_width),
+        )
+    }
+
+    /// Returns the vehicle as it's saved with its chunk
+    fn saved(&self) -> SavedEntity {
+        SavedEntity::new(VEHICLE_TYPE, self.uuid, self.pos)
+            .with("kind", self.kind.name())
+            .with("yaw", self.yaw as f64)
+    }
+}
+
+impl ChunkEntity for Vehicle {
+    fn uuid(&self) -> EntityUuid {
+        self.uuid
+    }
+
+    fn pos(&self) -> Vector3<f32> {
+        self.pos
+    }
+}
+
+/// Returns whether vehicles collide with a block, which they
+/// don't with plants and fluids
+///
+/// # Arguments
+///
+/// * `material` - The material of the block
+fn blocks(material: Material) -> bool {
+    material.is_solid() && !material.is_fluid()
+}
+
+/// Returns the share of a boat's height below the surface
+/// of the water it's in
+///
+/// # Arguments
+///
+/// * `pos` - The position of the bottom center of the boat
+/// * `block_at` - Returns the material of the block at a position
+fn submerged<F>(pos: Vector3<f32>, block_at: &F) -> f32
+    where F: Fn(Vector3<i32>) -> Option<Material>
+{
+    let block = Vector3::new(pos.x.floor() as i32, pos.y.floor() as i32, pos.z.floor() as i32);
+    if block_at(block) != Some(Material::Water) {
+        return 0.0;
+    }
+    if block_at(block + Vector3::new(0, 1, 0)) == Some(Material::Water) {
+        return 1.0;
+    }
+    ((block.y as f32 + 1.0 - pos.y) / VEHICLE_HEIGHT).clamp(0.0, 1.0)
+}
+
+/// Vehicles
+///
+/// The vehicles in a world. The player steers the vehicle they
+/// ride, the others coast to a halt. Boats float on water by
+/// their buoyancy, everything else falls until it lands on a
+/// block. Vehicles are shown as flat boxes colored by their kind
+/// in the scene of the world.
+///
+/// Vehicles are stored in the chunk they are in, and only the
+/// ones within the activation radius around the player move.
+pub struct Vehicles {
+    /// The vehicles in the world by their chunk
+    vehicles: ChunkEntities<Vehicle>,
+    /// The mesh all vehicles are shown with
+    mesh: MeshHandle,
+}
+
+impl Default for Vehicles {
+    fn default() -> Self {
+        Self {
+            vehicles: ChunkEntities::default(),
+            mesh: MeshHandle::new(Mesh::cube()),
+        }
+    }
+}
+
+impl Vehicles {
+    /// Returns the vehicles in the world
+    pub fn vehicles(&self) -> &ChunkEntities<Vehicle> {
+        &self.vehicles
+    }
+
+    /// Returns the vehicle with the given id, if it's in the world
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The id of the vehicle
+    pub fn get(&self, uuid: EntityUuid) -> Option<&Vehicle> {
+        self.vehicles.iter().find(|vehicle| vehicle.uuid == uuid)
+    }
+
+    /// Places a vehicle at the given position and returns its id
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the vehicle is shown in
+    /// * `kind` - The kind of the vehicle
+    /// * `pos` - The position of the bottom center of the vehicle
+    /// * `yaw` - The direction the vehicle faces in radians
+    pub fn spawn(&mut self, scene: &mut Scene, kind: VehicleKind, pos: Vector3<f32>, yaw: f32) -> EntityUuid {
+        let uuid = EntityUuid::generate();
+        self.spawn_with_id(scene, uuid, kind, pos, yaw);
+        uuid
+    }
+
+    /// Places a vehicle with the given id at the given position
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the vehicle is shown in
+    /// * `uuid` - The id of the entity
+    /// * `kind` - The kind of the vehicle
+    /// * `pos` - The position of the bottom center of the vehicle
+    /// * `yaw` - The direction the vehicle faces in radians
+    fn spawn_with_id(&mut self, scene: &mut Scene, uuid: EntityUuid, kind: VehicleKind, pos: Vector3<f32>, yaw: f32) {
+        let node = scene.add(None, SceneNode::with_mesh(
+            "vehicle",
+            vehicle_transform(pos, yaw),
+            self.mesh.clone(),
+            kind.color(),
+        ));
+        self.vehicles.insert(Vehicle {
+            uuid,
+            kind,
+            pos,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            yaw,
+            node,
+        });
+    }
+
+    /// Removes a vehicle from the world, e.g. once the player
+    /// broke it. Returns its kind, if it has been in the world.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the vehicle is shown in
+    /// * `uuid` - The id of the vehicle
+    pub fn remove(&mut self, scene: &mut Scene, uuid: EntityUuid) -> Option<VehicleKind> {
+        let key = self.vehicles.find_key(uuid)?;
+        let vehicle = self.vehicles.remove_and_relocate(vec![key]).pop()?;
+        scene.remove(vehicle.node);
+        Some(vehicle.kind)
+    }
+
+    /// Returns the closest vehicle a ray hits within the
+    /// given distance, with the distance to it
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The origin of the ray in world coordinates
+    /// * `dir` - The direction of the ray, doesn't need to be normalized
+    /// * `max_distance` - The maximal distance of the ray
+    pub fn hit(&self, origin: Vector3<f32>, dir: Vector3<f32>, max_distance: f32) -> Option<(EntityUuid, f32)> {
+        self.vehicles.iter()
+            .filter_map(|vehicle| Some((vehicle.uuid, vehicle.aabb().ray_distance(origin, dir)?)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+    }
+
+    /// Advances the vehicles within the activation radius around
+    /// the player
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the vehicles are shown in
+    /// * `seconds` - The time passed since the last update
+    /// * `player` - The position of the player's feet
+    /// * `radius` - The activation radius in chunks
+    /// * `ridden` - The vehicle the player rides, with their steering
+    /// * `block_at` - Returns the material of the block at a position
+    pub fn update<F>(&mut self, scene: &mut Scene, seconds: f32, player: Vector3<f32>, radius: i32,
+                     ridden: Option<(EntityUuid, Steering)>, block_at: F)
+        where F: Fn(Vector3<i32>) -> Option<Material>
+    {
+        let block_pos = |pos: Vector3<f32>| Vector3::new(pos.x.floor() as i32, pos.y.floor() as i32, pos.z.floor() as i32);
+        let passable = |pos: Vector3<f32>| matches!(block_at(block_pos(pos)), Some(material) if !blocks(material));
+
+        for key in self.vehicles.active(chunk_entities::chunk_of(player), radius) {
+            let vehicle = self.vehicles.get_mut(key);
+            let steering = match ridden {
+                Some((uuid, steering)) if uuid == vehicle.uuid => steering,
+                _ => Steering::default(),
+            };
+            let submerged = if vehicle.kind == VehicleKind::Boat { submerged(vehicle.pos, &block_at) } else { 0.0 };
+            let in_water = block_at(block_pos(vehicle.pos + Vector3::new(0.0, 0.01, 0.0))) == Some(Material::Water);
+
+            // Steering turns the vehicle and speeds it up in the
+            // direction it faces, up to the speed of its kind
+            vehicle.yaw += steering.turn.clamp(-1.0, 1.0) * TURN_SPEED * seconds;
+            let heading = Vector3::new(vehicle.yaw.cos(), 0.0, vehicle.yaw.sin());
+            let mut horizontal = Vector3::new(vehicle.velocity.x, 0.0, vehicle.velocity.z);
+            horizontal += heading * steering.forward.clamp(-1.0, 1.0) * ACCELERATION * seconds;
+            horizontal *= (1.0 - FRICTION * seconds).max(0.0);
+            let top_speed = vehicle.kind.top_speed(in_water);
+            let speed = (horizontal.x * horizontal.x + horizontal.z * horizontal.z).sqrt();
+            if speed > top_speed {
+                horizontal *= top_speed / speed;
+            }
+
+            // Boats are pushed up by the water they displace
+            let mut vertical = vehicle.velocity.y + (BUOYANCY * submerged - GRAVITY) * seconds;
+            if submerged > 0.0 {
+                vertical *= (1.0 - WATER_DRAG * seconds).max(0.0);
+            }
+            vertical = vertical.max(-MAX_FALL_SPEED);
+
+            // Vehicles don't drive into solid blocks, which stop them
+            let target = vehicle.pos + horizontal * seconds;
+            let lifted = target + Vector3::new(0.0, 0.01, 0.0);
+            if passable(lifted) && passable(lifted + Vector3::new(0.0, VEHICLE_HEIGHT, 0.0)) {
+                vehicle.pos = target;
+            } else {
+                horizontal = Vector3::new(0.0, 0.0, 0.0);
+            }
+
+            // Vehicles land on solid blocks of loaded chunks
+            let below = block_pos(vehicle.pos - Vector3::new(0.0, 0.01, 0.0));
+            let floor = match block_at(below) {
+                Some(material) if blocks(material) => Some(below.y as f32 + 1.0),
+                Some(_) => None,
+                None => Some(vehicle.pos.y),
+            };
+            vehicle.pos.y += vertical * seconds;
+            if let Some(floor) = floor.filter(|&floor| vehicle.pos.y <= floor) {
+                vehicle.pos.y = floor;
+                vertical = vertical.max(0.0);
+            }
+            let above = vehicle.pos + Vector3::new(0.0, VEHICLE_HEIGHT, 0.0);
+            if !passable(above) && vertical > 0.0 {
+                vehicle.pos.y = above.y.floor() - VEHICLE_HEIGHT;
+                vertical = 0.0;
+            }
+            vehicle.velocity = Vector3::new(horizontal.x, vertical, horizontal.z);
+
+            if let Some(node) = scene.node_mut(vehicle.node) {
+                node.set_transform(vehicle_transform(vehicle.pos, vehicle.yaw));
+            }
+        }
+        // Vehicles which drove into another chunk move to its list
+        self.vehicles.remove_and_relocate(Vec::new());
+    }
+
+    /// Removes the vehicles of a chunk from the world and
+    /// returns them, so they can be saved with the chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the vehicles are shown in
+    /// * `loc` - The location of the chunk
+    pub fn unload_chunk(&mut self, scene: &mut Scene, loc: Vector2<i32>) -> Vec<SavedEntity> {
+        self.vehicles.take_chunk(loc).into_iter()
+            .map(|vehicle| {
+                scene.remove(vehicle.node);
+                vehicle.saved()
+            })
+            .collect()
+    }
+
+    /// Returns the vehicles of a chunk as they're saved
+    ///
+    /// # Arguments
+    ///
+    /// * `loc` - The location of the chunk
+    pub fn saved(&self, loc: Vector2<i32>) -> Vec<SavedEntity> {
+        self.vehicles.in_chunk(loc).iter().map(Vehicle::saved).collect()
+    }
+
+    /// Places a saved vehicle again with its id
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the vehicles are shown in
+    /// * `saved` - The saved vehicle
+    pub fn restore(&mut self, scene: &mut Scene, saved: &SavedEntity) -> Result<(), String> {
+        let name = saved.text("kind")?;
+        let kind = VehicleKind::from_name(name).ok_or_else(|| format!("unknown vehicle {}", name))?;
+        let yaw = saved.number("yaw").unwrap_or(0.0) as f32;
+        self.spawn_with_id(scene, saved.uuid, kind, saved.pos.into(), yaw);
+        Ok(())
+    }
+
+    /// Removes all vehicles from the world
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene the vehicles are shown in
+    pub fn clear(&mut self, scene: &mut Scene) {
+        for vehicle in self.vehicles.take_all() {
+            scene.remove(vehicle.node);
+        }
+    }
+}
+
+/// Returns the transform of the node a vehicle is shown with
+///
+/// # Arguments
+///
+/// * `pos` - The position of the bottom center of the vehicle
+/// * `yaw` - The direction the vehicle faces in radians
+fn vehicle_transform(pos: Vector3<f32>, yaw: f32) -> Matrix4<f32> {
+    Matrix4::from_translation(pos)
+        * Matrix4::from_angle_y(Rad(-yaw))
+        * Matrix4::from_nonuniform_scale(VEHICLE_WIDTH * 1.4, VEHICLE_HEIGHT, VEHICLE_WIDTH)
+        * Matrix4::from_translation(Vector3::new(-0.5, 0.0, -0.5))
+}
+
+/// Returns where the rider of a vehicle is placed once they
+/// get off, which is the position of their feet. They step
+/// onto the solid ground next to the vehicle where they fit,
+/// or onto the vehicle if there's none.
+///
+/// # Arguments
+///
+/// * `pos` - The position of the bottom center of the vehicle
+/// * `block_at` - Returns the material of the block at a position
+pub fn dismount_pos<F>(pos: Vector3<f32>, block_at: F) -> Vector3<f32>
+    where F: Fn(Vector3<i32>) -> Option<Material>
+{
+    let free = |pos: Vector3<i32>| matches!(block_at(pos), Some(material) if !material.is_solid());
+    let ground = |pos: Vector3<i32>| matches!(block_at(pos), Some(material) if blocks(material));
+    let block = Vector3::new(pos.x.floor() as i32, pos.y.floor() as i32, pos.z.floor() as i32);
+
+    // The sides are tried first, so the rider doesn't stand in the vehicle
+    let offsets = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (-1, -1), (1, -1), (-1, 1), (0, 0)];
+    for &(x, z) in offsets.iter() {
+        for y in [0, 1, -1].iter() {
+            let feet = block + Vector3::new(x, *y, z);
+            if free(feet) && free(feet + Vector3::new(0, 1, 0)) && ground(feet - Vector3::new(0, 1, 0)) {
+                return feet.cast::<f32>().unwrap() + Vector3::new(0.5, 0.0, 0.5);
+            }
+        }
+    }
+    pos + Vector3::new(0.0, VEHICLE_HEIGHT, 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A world of stone below `y = -3` with water above up to
+    /// `y = 0`, except for the land at `x >= 4`
+    fn lake(pos: Vector3<i32>) -> Option<Material> {
+        Some(if pos.y < -3 || (pos.x >= 4 && pos.y < 0) {
+            Material::Stone
+        } else if pos.y < 0 {
+            Material::Water
+        } else {
+            Material::Air
+        })
+    }
+
+    #[test]
+    fn boats_float_and_minecarts_sink() {
+        let mut scene = Scene::new();
+        let mut vehicles = Vehicles::default();
+        let boat = vehicles.spawn(&mut scene, VehicleKind::Boat, Vector3::new(0.5, 2.0, 0.5), 0.0);
+        let minecart = vehicles.spawn(&mut scene, VehicleKind::Minecart, Vector3::new(-4.5, 2.0, 0.5), 0.0);
+        assert_eq!(scene.instances().len(), 2);
+
+        for _ in 0..300 {
+            vehicles.update(&mut scene, 1.0 / 60.0, Vector3::new(0.0, 0.0, 0.0), 4, None, lake);
+        }
+        // Half of the boat is below the surface
+        let pos = *vehicles.get(boat).unwrap().pos();
+        assert!((pos.y + VEHICLE_HEIGHT / 2.0).abs() < 0.05, "{:?}", pos);
+        assert_eq!(vehicles.get(minecart).unwrap().pos().y, -3.0);
+    }
+
+    #[test]
+    fn riders_steer_their_vehicle_until_it_runs_aground() {
+        let mut scene = Scene::new();
+        let mut vehicles = Vehicles::default();
+        let boat = vehicles.spawn(&mut scene, VehicleKind::Boat, Vector3::new(0.5, -0.25, 0.5), 0.0);
+        let other = vehicles.spawn(&mut scene, VehicleKind::Boat, Vector3::new(0.5, -0.25, 3.5), 0.0);
+
+        let steering = Steering { forward: 1.0, turn: 0.0 };
+        for _ in 0..60 {
+            vehicles.update(&mut scene, 1.0 / 60.0, Vector3::new(0.0, 0.0, 0.0), 4, Some((boat, steering)), lake);
+        }
+        assert!(vehicles.get(boat).unwrap().pos().x > 1.5);
+        assert_eq!(vehicles.get(other).unwrap().pos().x, 0.5);
+
+        // The shore stops the boat
+        for _ in 0..240 {
+            vehicles.update(&mut scene, 1.0 / 60.0, Vector3::new(0.0, 0.0, 0.0), 4, Some((boat, steering)), lake);
+        }
+        assert!(vehicles.get(boat).unwrap().pos().x < 4.0);
+
+        let steering = Steering { forward: 0.0, turn: 1.0 };
+        vehicles.update(&mut scene, 0.5, Vector3::new(0.0, 0.0, 0.0), 4, Some((boat, steering)), lake);
+        assert_eq!(vehicles.get(boat).unwrap().yaw, TURN_SPEED * 0.5);
+    }
+
+    #[test]
+    fn riders_get_off_onto_the_shore() {
+        // The boat floats next to the shore, where the rider steps onto
+        let pos = dismount_pos(Vector3::new(3.5, -0.25, 0.5), lake);
+        assert_eq!(pos, Vector3::new(4.5, 0.0, 0.5));
+
+        // Far from the shore, the rider stays on the boat
+        let pos = dismount_pos(Vector3::new(-10.5, -0.25, 0.5), lake);
+        assert_eq!(pos, Vector3::new(-10.5, 0.25, 0.5));
+    }
+
+    #[test]
+    fn vehicles_are_saved_with_their_chunk() {
+        let mut scene = Scene::new();
+        let mut vehicles = Vehicles::default();
+        let uuid = vehicles.spawn(&mut scene, VehicleKind::Minecart, Vector3::new(20.5, 0.0, 0.5), 1.5);
+
+        let saved = vehicles.unload_chunk(&mut scene, Vector2::new(1, 0));
+        assert_eq!(saved, vec![SavedEntity::new(VEHICLE_TYPE, uuid, Vector3::new(20.5, 0.0, 0.5))
+            .with("kind", "minecart")
+            .with("yaw", 1.5)]);
+        assert!(vehicles.vehicles().is_empty());
+
+        vehicles.restore(&mut scene, &saved[0]).unwrap();
+        assert_eq!(vehicles.get(uuid).unwrap().kind(), VehicleKind::Minecart);
+        assert_eq!(vehicles.remove(&mut scene, uuid), Some(VehicleKind::Minecart));
+        assert!(scene.instances().is_empty());
+    }
+}
@@ -0,0 +1,435 @@
+//! A debug overlay outlining what the terrain generator
+//! produced around the player: the bounding boxes of the
+//! structures, the chunks colored by their biome and the
+//! paths of the carvers
+
+use crate::graphics::backend::{GlBackend, RenderBackend};
+use crate::graphics::mesh::{Mesh, Model};
+use crate::graphics::pass::{Frame, RenderContext, RenderPass, RenderStage};
+use crate::graphics::shader::ShaderProgram;
+use crate::resources::Resources;
+use crate::world::World;
+use crate::world::biome::Biome;
+use crate::world::chunk::CHUNK_SIZE;
+use crate::world::terrain_generator::GenFeature;
+use cgmath::{Matrix4, SquareMatrix, Vector2, Vector3};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// The distance in chunks around the player up to
+/// which the generated features are outlined
+pub const OVERLAY_RADIUS: i32 = 4;
+/// The time in seconds between two refreshes of the overlay
+const REFRESH_INTERVAL: f32 = 0.5;
+/// The distance in blocks the outline of a chunk is
+/// moved inwards, so the outlines of neighbours don't overlap
+const CHUNK_INSET: f32 = 0.1;
+
+/// WorldgenLayer
+///
+/// The kinds of generated features the overlay could
+/// outline, each of which is shown or hidden on its own
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum WorldgenLayer {
+    /// The bounding boxes of the structures
+    Structures,
+    /// The outlines of the chunks, colored by their biome
+    Biomes,
+    /// The paths of the carvers
+    Carvers,
+}
+
+impl WorldgenLayer {
+    /// Returns the layer with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the layer, e.g. `"biomes"`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "structures" => Some(WorldgenLayer::Structures),
+            "biomes" => Some(WorldgenLayer::Biomes),
+            "carvers" => Some(WorldgenLayer::Carvers),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the layer
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorldgenLayer::Structures => "structures",
+            WorldgenLayer::Biomes => "biomes",
+            WorldgenLayer::Carvers => "carvers",
+        }
+    }
+}
+
+/// LineKind
+///
+/// What a line of the overlay outlines, which
+/// determines its color
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum LineKind {
+    /// The bounding box of a structure
+    Structure,
+    /// The outline of a chunk of a biome, or of a
+    /// chunk whose terrain isn't made up of biomes
+    Biome(Option<Biome>),
+    /// The path of a carver
+    Carver,
+}
+
+/// WorldgenLine
+///
+/// A line of the overlay in world coordinates
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorldgenLine {
+    /// What the line outlines
+    pub kind: LineKind,
+    /// The start of the line
+    pub from: Vector3<f32>,
+    /// The end of the line
+    pub to: Vector3<f32>,
+}
+
+/// GeneratedChunk
+///
+/// What the terrain generator produced for a chunk
+struct GeneratedChunk {
+    /// The biome at the center of the chunk
+    biome: Option<Biome>,
+    /// The height of the surface at the center of the chunk
+    surface: i32,
+    /// The features placed into the chunk
+    features: Vec<GenFeature>,
+}
+
+/// WorldgenOverlay
+///
+/// The outlines of the generated features of the loaded
+/// chunks around the player, which are refreshed periodically
+/// while a layer is shown. As generators are deterministic,
+/// the features of a chunk are kept until the player leaves
+/// the dimension.
+#[derive(Default)]
+pub struct WorldgenOverlay {
+    /// The layers which are shown
+    layers: HashSet<WorldgenLayer>,
+    /// The features of the chunks around the player
+    chunks: HashMap<Vector2<i32>, GeneratedChunk>,
+    /// The lines of the last refresh
+    lines: Arc<Vec<WorldgenLine>>,
+    /// The time in seconds since the last refresh
+    since_refresh: f32,
+}
+
+impl WorldgenOverlay {
+    /// Shows a layer if it's hidden or hides it otherwise.
+    /// Returns whether the layer is shown.
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - The layer
+    pub fn toggle(&mut self, layer: WorldgenLayer) -> bool {
+        if !self.layers.remove(&layer) {
+            self.layers.insert(layer);
+        }
+        // Refresh with the next update, so the layer
+        // appears or disappears immediately
+        self.since_refresh = REFRESH_INTERVAL;
+        self.layers.contains(&layer)
+    }
+
+    /// Returns whether any layer is shown
+    pub fn is_shown(&self) -> bool {
+        !self.layers.is_empty()
+    }
+
+    /// Discards the features of the chunks, e.g. after
+    /// the player entered another dimension
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.lines = Arc::new(Vec::new());
+        self.since_refresh = REFRESH_INTERVAL;
+    }
+
+    /// Refreshes the lines around a chunk once the refresh
+    /// interval has passed and a layer is shown
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world
+    /// * `center` - The location of the chunk the player is in
+    /// * `dt` - The time passed since the last update
+    pub fn update(&mut self, world: &World, center: Vector2<i32>, dt: f32) {
+        self.since_refresh += dt;
+        if !self.is_shown() || self.since_refresh < REFRESH_INTERVAL {
+            return;
+        }
+        self.since_refresh = 0.0;
+
+        let in_reach = |loc: &Vector2<i32>| (loc.x - center.x).abs() <= OVERLAY_RADIUS && (loc.y - center.y).abs() <= OVERLAY_RADIUS;
+        self.chunks.retain(|loc, _| in_reach(loc));
+        for x in -OVERLAY_RADIUS..=OVERLAY_RADIUS {
+            for z in -OVERLAY_RADIUS..=OVERLAY_RADIUS {
+                let loc = center + Vector2::new(x, z);
+                if self.chunks.contains_key(&loc) || world.chunk(&loc).is_none() {
+                    continue;
+                }
+                let terrain_gen = world.terrain_gen();
+                let middle = CHUNK_SIZE as i32 / 2;
+                self.chunks.insert(loc, GeneratedChunk {
+                    biome: terrain_gen.biome(loc.x * CHUNK_SIZE as i32 + middle, loc.y * CHUNK_SIZE as i32 + middle),
+                    surface: terrain_gen.gen_heightmap(&loc)[middle as usize * CHUNK_SIZE + middle as usize],
+                    features: terrain_gen.features(&loc),
+                });
+            }
+        }
+
+        let mut lines = Vec::new();
+        for (loc, chunk) in &self.chunks {
+            if self.layers.contains(&WorldgenLayer::Biomes) {
+                chunk_outline(&mut lines, *loc, chunk);
+            }
+            for feature in &chunk.features {
+                match feature {
+                    GenFeature::Structure { min, max, .. } if self.layers.contains(&WorldgenLayer::Structures) => {
+                        box_outline(&mut lines, *min, *max);
+                    }
+                    GenFeature::CarverPath(points) if self.layers.contains(&WorldgenLayer::Carvers) => {
+                        lines.extend(points.windows(2).map(|segment| WorldgenLine {
+                            kind: LineKind::Carver,
+                            from: segment[0],
+                            to: segment[1],
+                        }));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.lines = Arc::new(lines);
+    }
+
+    /// Returns the lines of the last refresh,
+    /// or `None` if no layer is shown
+    pub fn lines(&self) -> Option<&Arc<Vec<WorldgenLine>>> {
+        if self.is_shown() {
+            Some(&self.lines)
+        } else {
+            None
+        }
+    }
+}
+
+/// Outlines a chunk just above the surface at its center
+///
+/// # Arguments
+///
+/// * `lines` - The lines the outline is appended to
+/// * `loc` - The location of the chunk
+/// * `chunk` - What has been generated for the chunk
+fn chunk_outline(lines: &mut Vec<WorldgenLine>, loc: Vector2<i32>, chunk: &GeneratedChunk) {
+    let y = chunk.surface as f32 + 1.05;
+    let min = Vector2::new(loc.x as f32, loc.y as f32) * CHUNK_SIZE as f32 + Vector2::new(CHUNK_INSET, CHUNK_INSET);
+    let max = min + Vector2::new(1.0, 1.0) * (CHUNK_SIZE as f32 - 2.0 * CHUNK_INSET);
+    let corners = [
+        Vector3::new(min.x, y, min.y),
+        Vector3::new(max.x, y, min.y),
+        Vector3::new(max.x, y, max.y),
+        Vector3::new(min.x, y, max.y),
+    ];
+    for i in 0..corners.len() {
+        lines.push(WorldgenLine {
+            kind: LineKind::Biome(chunk.biome.clone()),
+            from: corners[i],
+            to: corners[(i + 1) % corners.len()],
+        });
+    }
+}
+
+/// Outlines the blocks between two corners
+///
+/// # Arguments
+///
+/// * `lines` - The lines the outline is appended to
+/// * `min` - The lowest block
+/// * `max` - The highest block
+fn box_outline(lines: &mut Vec<WorldgenLine>, min: Vector3<i32>, max: Vector3<i32>) {
+    let low = min.cast::<f32>().unwrap();
+    let high = (max + Vector3::new(1, 1, 1)).cast::<f32>().unwrap();
+    let corner = |i: u32| Vector3::new(
+        if i & 1 == 0 { low.x } else { high.x },
+        if i & 2 == 0 { low.y } else { high.y },
+        if i & 4 == 0 { low.z } else { high.z },
+    );
+
+    // Connect each corner with the corners differing in exactly one axis
+    for i in 0..8 {
+        for axis in [1, 2, 4] {
+            if i & axis == 0 {
+                lines.push(WorldgenLine { kind: LineKind::Structure, from: corner(i), to: corner(i | axis) });
+            }
+        }
+    }
+}
+
+/// Returns the color lines of a kind are drawn with
+///
+/// # Arguments
+///
+/// * `kind` - The kind of the lines
+fn color(kind: &LineKind) -> [f32; 4] {
+    match kind {
+        LineKind::Structure => [1.0, 0.3, 0.8, 0.9],
+        LineKind::Carver => [0.2, 0.9, 1.0, 0.9],
+        LineKind::Biome(Some(biome)) => match biome.name() {
+            "ocean" => [0.1, 0.3, 1.0, 0.8],
+            "plains" => [0.4, 0.9, 0.2, 0.8],
+            "desert" => [1.0, 0.95, 0.5, 0.8],
+            "hills" => [0.9, 0.7, 0.2, 0.8],
+            "mountains" => [0.9, 0.9, 0.9, 0.8],
+            // The biomes of scripts get a color of their
+            // name, which stays the same across sessions
+            name => {
+                let hash = name.bytes().fold(0u32, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as u32));
+                let channel = |shift: u32| 0.3 + 0.7 * ((hash >> shift) & 0xFF) as f32 / 255.0;
+                [channel(0), channel(8), channel(16), 0.8]
+            }
+        },
+        LineKind::Biome(None) => [0.5, 0.5, 0.5, 0.8],
+    }
+}
+
+/// WorldgenOverlayFrame
+///
+/// A frame the worldgen overlay could be rendered for
+pub trait WorldgenOverlayFrame: Frame {
+    /// Returns the lines which should be drawn,
+    /// or `None` if the overlay is hidden
+    fn worldgen_lines(&self) -> Option<&Arc<Vec<WorldgenLine>>>;
+}
+
+/// WorldgenOverlayRenderer
+///
+/// The `WorldgenOverlayRenderer` is the debug render pass
+/// drawing the lines of the worldgen overlay. The lines of
+/// each kind are batched into a single model, which is
+/// rebuilt whenever the overlay has been refreshed.
+pub struct WorldgenOverlayRenderer {
+    /// The render backend
+    backend: GlBackend,
+    /// The shader program the lines are drawn with
+    shader_program: ShaderProgram,
+    /// The lines the models have been built from
+    lines: Option<Arc<Vec<WorldgenLine>>>,
+    /// The models of the lines by their kind
+    models: Vec<(LineKind, Model)>,
+}
+
+impl WorldgenOverlayRenderer {
+    /// Creates a new worldgen overlay renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The render backend
+    /// * `resources` - A resource instance
+    pub fn new(backend: &GlBackend, resources: &Resources) -> Self {
+        let shader_program = backend.create_pipeline(resources, "prop").unwrap();
+        shader_program.disable();
+
+        Self {
+            backend: backend.clone(),
+            shader_program,
+            lines: None,
+            models: Vec::new(),
+        }
+    }
+
+    /// Rebuilds the models of the lines
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - The lines
+    fn rebuild(&mut self, lines: &[WorldgenLine]) {
+        let mut by_kind = HashMap::new();
+        for line in lines {
+            by_kind.entry(line.kind.clone()).or_insert_with(Vec::new).push((line.from, line.to));
+        }
+        self.models = by_kind.into_iter()
+            .map(|(kind, lines)| (kind, Model::from_mesh(&self.backend, &Mesh::lines(&lines))))
+            .collect();
+    }
+}
+
+impl<F: WorldgenOverlayFrame> RenderPass<F> for WorldgenOverlayRenderer {
+    fn stage(&self) -> RenderStage {
+        RenderStage::Transparent
+    }
+
+    fn render(&mut self, ctx: &RenderContext<F>) {
+        let lines = match ctx.frame.worldgen_lines() {
+            Some(lines) => lines,
+            None => {
+                self.lines = None;
+                self.models.clear();
+                return;
+            }
+        };
+        if !matches!(&self.lines, Some(built) if Arc::ptr_eq(built, lines)) {
+            self.rebuild(lines);
+            self.lines = Some(lines.clone());
+        }
+
+        let camera = ctx.frame.camera();
+        self.shader_program.enable();
+        self.shader_program.set_uniform_mat4f("u_MVP", &(camera.proj_matrix() * camera.view_matrix()));
+        self.shader_program.set_uniform_mat4f("u_Model", &Matrix4::identity());
+        for (kind, model) in &self.models {
+            let [r, g, b, a] = color(kind);
+            self.shader_program.set_uniform_4f("u_Color", r, g, b, a);
+            model.bind();
+            ctx.renderer.draw_with(model.va(), model.ib(), &self.shader_program, model.topology());
+            model.unbind();
+        }
+        self.shader_program.disable();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::structure::{StructureDef, StructureGen};
+    use crate::world::terrain_generator::FlatTerrainGen;
+    use crate::world::time::SkySettings;
+
+    #[test]
+    fn shown_layers_outline_the_generated_features() {
+        let ruins = StructureDef::from_toml(include_str!("../../res/structures/ruins.toml")).unwrap();
+        let start = ruins.start_chunk(0, Vector2::new(0, 0));
+        let generator = StructureGen::new(FlatTerrainGen::default(), Arc::new(vec![ruins]), 0);
+        let mut world = World::with_generator(generator, SkySettings::default());
+        for x in -1..=1 {
+            for z in -1..=1 {
+                world.load_chunk(&(start + Vector2::new(x, z)));
+            }
+        }
+
+        let mut overlay = WorldgenOverlay::default();
+        assert!(overlay.lines().is_none());
+        assert!(overlay.toggle(WorldgenLayer::Biomes));
+        overlay.update(&world, start, 0.0);
+        let lines = overlay.lines().unwrap().clone();
+        assert_eq!(lines.len(), 9 * 4);
+        assert!(lines.iter().all(|line| line.kind == LineKind::Biome(None) && line.from.y == 5.05));
+
+        assert!(overlay.toggle(WorldgenLayer::Structures));
+        overlay.update(&world, start, 0.0);
+        let boxes = overlay.lines().unwrap().iter().filter(|line| line.kind == LineKind::Structure).count();
+        assert_eq!(boxes, 12, "the ruins start in a single chunk");
+
+        assert!(!overlay.toggle(WorldgenLayer::Biomes));
+        assert!(!overlay.toggle(WorldgenLayer::Structures));
+        overlay.update(&world, start, 0.0);
+        assert!(overlay.lines().is_none());
+    }
+}
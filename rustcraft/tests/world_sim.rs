@@ -0,0 +1,124 @@
+use cgmath::{Vector2, Vector3};
+use rustcraft::entity::Controls;
+use rustcraft::world::block::Material;
+use rustcraft::world::save::SaveDir;
+use rustcraft::world::sim::WorldSim;
+use rustcraft::world::terrain_generator::FlatTerrainGen;
+use std::fs;
+
+/// The height of the grass of the flat terrain
+const SURFACE: i32 = 4;
+
+/// Creates a flat world whose chunks around the origin are generated
+fn flat_world() -> WorldSim {
+    let mut sim = WorldSim::with_generator(FlatTerrainGen::default());
+    assert!(sim.generate(Vector3::new(8.0, SURFACE as f32 + 1.0, 8.0), 1), "the chunks haven't been generated");
+    sim
+}
+
+/// Creates an empty save directory in the temporary directory
+fn save_dir(name: &str) -> SaveDir {
+    let path = std::env::temp_dir().join(format!("rustcraft-sim-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    SaveDir::new(path).unwrap()
+}
+
+#[test]
+fn seeded_worlds_generate_the_same_terrain() {
+    let surface = |seed: u32| {
+        let mut sim = WorldSim::new(seed);
+        assert!(sim.generate(Vector3::new(0.0, 0.0, 0.0), 1));
+        (-16..16).flat_map(|x| (0..128).map(move |y| Vector3::new(x, y, 3)))
+            .map(|pos| sim.block(pos).unwrap())
+            .collect::<Vec<Material>>()
+    };
+    assert_eq!(surface(7), surface(7));
+    assert_ne!(surface(7), surface(8));
+}
+
+#[test]
+fn glowing_blocks_light_their_surroundings() {
+    let mut sim = flat_world();
+    let lamp = Vector3::new(8, SURFACE + 1, 8);
+    assert_eq!(sim.block_light(lamp + Vector3::new(0, 1, 0)), Some(0));
+    assert_eq!(sim.sky_light(lamp + Vector3::new(0, 1, 0)), Some(15));
+    assert_eq!(sim.sky_light(Vector3::new(8, SURFACE - 1, 8)), Some(0));
+
+    assert!(sim.place_block(lamp, Material::Glowstone));
+    assert_eq!(sim.block_light(lamp + Vector3::new(0, 1, 0)), Some(14));
+    assert_eq!(sim.block_light(lamp + Vector3::new(3, 0, 0)), Some(12));
+    // Light spreads into the neighbouring chunk
+    assert_eq!(sim.block_light(Vector3::new(16, SURFACE + 1, 8)), Some(7));
+
+    assert_eq!(sim.break_block(lamp), Some(Material::Glowstone));
+    assert_eq!(sim.break_block(lamp), None);
+    assert_eq!(sim.block_light(lamp + Vector3::new(0, 1, 0)), Some(0));
+}
+
+#[test]
+fn water_flows_into_a_hole() {
+    let mut sim = flat_world();
+    let hole = Vector3::new(8, SURFACE, 8);
+    assert_eq!(sim.break_block(hole), Some(Material::Grass));
+    assert!(sim.place_block(hole + Vector3::new(1, 1, 0), Material::Water));
+
+    sim.tick(120);
+    assert_eq!(sim.ticks(), 120);
+    assert_eq!(sim.block(hole), Some(Material::Water));
+    assert_eq!(sim.block(hole - Vector3::new(0, 1, 0)), Some(Material::Dirt));
+}
+
+#[test]
+fn items_are_picked_up_at_the_observer() {
+    let mut sim = flat_world();
+    sim.world_mut().spawn_item("dirt", 3, Vector3::new(8.5, SURFACE as f32 + 3.0, 8.5));
+    sim.world_mut().spawn_item("stone", 1, Vector3::new(40.0, SURFACE as f32 + 1.0, 8.5));
+
+    sim.tick(60);
+    assert_eq!(sim.picked_up(), &[("dirt".to_string(), 3)]);
+    assert_eq!(sim.world().items().items().len(), 1);
+}
+
+#[test]
+fn the_player_and_entities_are_queried() {
+    let mut sim = flat_world();
+    let ground = SURFACE as f32 + 1.0;
+    assert_eq!(sim.player_pos(), Vector3::new(8.0, ground, 8.0));
+    assert_eq!(sim.entity_count(), 0);
+
+    sim.world_mut().spawn_item("dirt", 1, Vector3::new(2.5, ground + 3.0, 14.5));
+    sim.set_controls(Controls { walk: Vector2::new(1.0, 0.0), ..Controls::default() });
+    sim.tick(30);
+
+    // The player walks along the ground while the item falls onto it
+    assert!(sim.player_pos().x > 9.0, "{:?}", sim.player_pos());
+    assert_eq!(sim.player_pos().y, ground);
+    assert!(sim.player_velocity().x > 1.0, "{:?}", sim.player_velocity());
+    assert_eq!(sim.player_velocity().z, 0.0);
+    assert_eq!(sim.entity_count(), 1);
+    let item = sim.entity_positions()[0];
+    assert_eq!((item.x, item.z), (2.5, 14.5));
+    assert!(item.y < ground + 3.0, "{:?}", item);
+
+    sim.set_controls(Controls::default());
+    sim.tick(60);
+    assert!(sim.player_velocity().x.abs() < 0.01, "{:?}", sim.player_velocity());
+}
+
+#[test]
+fn edits_are_saved_and_restored() {
+    let save_dir = save_dir("edits");
+    let mut sim = flat_world();
+    assert!(sim.place_block(Vector3::new(3, SURFACE + 1, -5), Material::Stone));
+    assert_eq!(sim.break_block(Vector3::new(20, SURFACE, 2)), Some(Material::Grass));
+    sim.save(&save_dir).unwrap();
+
+    let mut restored = WorldSim::with_generator(FlatTerrainGen::default());
+    restored.load(&save_dir).unwrap();
+    assert!(restored.generate(Vector3::new(8.0, 0.0, 8.0), 1));
+    assert_eq!(restored.block(Vector3::new(3, SURFACE + 1, -5)), Some(Material::Stone));
+    assert_eq!(restored.block(Vector3::new(20, SURFACE, 2)), Some(Material::Air));
+    assert_eq!(restored.block(Vector3::new(21, SURFACE, 2)), Some(Material::Grass));
+
+    let _ = fs::remove_dir_all(save_dir.root_path());
+}